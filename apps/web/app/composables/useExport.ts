@@ -426,6 +426,9 @@ export function useExport() {
       encodeJpeg: async (pixels, width, height, q) => {
         return decodeService.encodeJpeg(pixels, width, height, q)
       },
+      rotateJpegLossless: async (bytes, quarterTurns) => {
+        return decodeService.rotateJpegLossless(bytes, quarterTurns)
+      },
       getEditState,
       loadImageBytes,
     }
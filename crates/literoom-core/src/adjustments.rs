@@ -3,19 +3,240 @@
 //! Applies the 10 basic adjustments to RGB pixel data.
 //!
 //! ## Adjustment Order
+//!
+//! The adjustments run in stages ([`AdjustmentStage`]), by default in the
+//! order of [`DEFAULT_STAGE_ORDER`]:
 //! 1. Exposure
 //! 2. Contrast
-//! 3. Temperature
-//! 4. Tint
-//! 5. Highlights
-//! 6. Shadows
-//! 7. Whites
-//! 8. Blacks
-//! 9. Saturation
-//! 10. Vibrance
-
-use crate::luminance::calculate_luminance;
-use crate::BasicAdjustments;
+//! 3. White balance: temperature, then tint
+//! 4. Highlights and shadows
+//! 5. Whites and blacks
+//! 6. Saturation, then vibrance (see Skin Tone Protection)
+//!
+//! `apply_adjustments_with_order` runs the stages in any other order, to
+//! match renders from tools that, say, balance white before exposure. The
+//! order matters: contrast pivots around middle gray, so with positive
+//! exposure and contrast, running exposure first lifts every pixel by
+//! `(2^exposure - 1) * (contrast/100) / 2` more than running it second.
+//!
+//! ## Skin Tone Protection
+//!
+//! With `protect_skin_tones` set, the saturation and vibrance amounts are
+//! scaled down per pixel by how much the pixel looks like skin (orange hue,
+//! moderate saturation), so strong saturation leaves faces alone.
+//!
+//! ## Color Model
+//!
+//! Saturation and vibrance normally scale each channel's distance from the
+//! pixel's luminance, which pushes blues toward purple and skin toward
+//! orange. With `color_model` set to [`ColorModel::Oklab`] they scale Oklch
+//! chroma instead, keeping lightness and hue (see [`crate::color`]). The
+//! default, `Legacy`, renders exactly as before.
+//!
+//! ## Split White Balance
+//!
+//! An optional `SplitWhiteBalance` runs at the end of the white balance
+//! stage, right after the global tint. Its shadow and highlight pairs are
+//! blended by luminance, so mixed lighting can be corrected separately in
+//! dark and bright areas.
+//!
+//! ## Kelvin White Balance
+//!
+//! A `WhiteBalance::Absolute` value sets the temperature in Kelvin. Channel
+//! multipliers come from a Planckian-locus approximation and replace the
+//! relative temperature in the white balance stage; tint is applied as usual.
+//!
+//! ## Auto Levels
+//!
+//! `auto_levels` is a standalone one-shot operation, not part of the slider
+//! pipeline: it stretches the tonal range so that chosen percentiles land on
+//! black and white, and reports the gains and offsets it used.
+//!
+//! ## Channel Levels
+//!
+//! `ChannelLevels` is a calibration step for scans and scientific sources:
+//! each channel is remapped linearly from its own black and white level, so
+//! channel-dependent offsets such as a scanner's dark current can be removed
+//! before the rest of the pipeline. `estimate_channel_levels` measures the
+//! levels from per-channel percentiles.
+//!
+//! ## Auto White Balance
+//!
+//! `estimate_white_balance` is a one-shot analysis for one-click auto white
+//! balance: it estimates the scene illuminant and returns the temperature and
+//! tint slider values that neutralize it. Nothing is applied to the image.
+
+use crate::color::{scale_chroma, srgb_to_oklab, ColorModel};
+use crate::decode::{subsample, subsample_map, DecodeError, DecodedImage};
+use crate::histogram::compute_histogram;
+use crate::luminance::{calculate_luminance, calculate_luminance_u8};
+use crate::perf;
+use crate::ranges::{self, AdjustmentError};
+use crate::validate::{validate_rgb_buffer, ImageError};
+use crate::{BasicAdjustments, SplitWhiteBalance, WhiteBalance};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Luminance below which only the shadow white balance applies.
+const SPLIT_WB_SHADOW_EDGE: f32 = 0.2;
+
+/// Luminance above which only the highlight white balance applies.
+const SPLIT_WB_HIGHLIGHT_EDGE: f32 = 0.8;
+
+/// Color temperature that maps to neutral multipliers (close to D65).
+pub const NEUTRAL_KELVIN: f32 = 6500.0;
+
+/// Lowest color temperature covered by the Planckian approximation.
+pub const MIN_KELVIN: f32 = 1667.0;
+
+/// Highest color temperature covered by the Planckian approximation.
+pub const MAX_KELVIN: f32 = 25000.0;
+
+/// Largest gain `auto_levels` applies to a channel.
+///
+/// Keeps a nearly empty channel (e.g. green in a pure red image) from being
+/// stretched into noise.
+pub const MAX_AUTO_LEVELS_GAIN: f32 = 4.0;
+
+/// Fraction of unclipped pixels, by luminance, averaged by
+/// `WhiteBalanceMethod::BrightestPixels`.
+const BRIGHTEST_PIXELS_FRACTION: f32 = 0.02;
+
+/// Hue edges of the skin tone band in degrees: the weight rises between the
+/// first two and falls between the last two.
+pub(crate) const SKIN_TONE_HUE_EDGES: [f32; 4] = [10.0, 20.0, 50.0, 60.0];
+
+/// HSV saturation edges of the skin tone band, as for the hue.
+pub(crate) const SKIN_TONE_SATURATION_EDGES: [f32; 4] = [0.05, 0.15, 0.7, 0.9];
+
+/// Green multiplier change per unit of tint in Kelvin mode.
+const KELVIN_TINT_SCALE: f32 = 0.002;
+
+/// CIE XYZ to linear sRGB (D65).
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.240_454_2, -1.537_138_5, -0.498_531_4],
+    [-0.969_266, 1.876_010_8, 0.041_556],
+    [0.055_643_4, -0.204_025_9, 1.057_225_2],
+];
+
+/// A group of per-pixel adjustments that always run together.
+///
+/// Serializes as its [`name`](Self::name), e.g. `"white_balance"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentStage {
+    /// Exposure
+    Exposure,
+    /// Contrast
+    Contrast,
+    /// Temperature (or Kelvin gains), tint and the split white balance
+    WhiteBalance,
+    /// Highlights and shadows, both weighted by the luminance entering the
+    /// stage
+    HighlightsShadows,
+    /// Whites and blacks
+    WhitesBlacks,
+    /// Saturation, then vibrance
+    SaturationVibrance,
+}
+
+/// Stage order used by [`apply_all_adjustments`] and the rest of the pipeline.
+pub const DEFAULT_STAGE_ORDER: [AdjustmentStage; 6] = [
+    AdjustmentStage::Exposure,
+    AdjustmentStage::Contrast,
+    AdjustmentStage::WhiteBalance,
+    AdjustmentStage::HighlightsShadows,
+    AdjustmentStage::WhitesBlacks,
+    AdjustmentStage::SaturationVibrance,
+];
+
+impl AdjustmentStage {
+    /// Stable snake_case name, as used across the WASM boundary.
+    pub fn name(self) -> &'static str {
+        match self {
+            AdjustmentStage::Exposure => "exposure",
+            AdjustmentStage::Contrast => "contrast",
+            AdjustmentStage::WhiteBalance => "white_balance",
+            AdjustmentStage::HighlightsShadows => "highlights_shadows",
+            AdjustmentStage::WhitesBlacks => "whites_blacks",
+            AdjustmentStage::SaturationVibrance => "saturation_vibrance",
+        }
+    }
+
+    /// Look up a stage by its [`name`](Self::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        DEFAULT_STAGE_ORDER
+            .into_iter()
+            .find(|stage| stage.name() == name)
+    }
+}
+
+/// Invalid adjustment stage order.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum StageOrderError {
+    /// A name does not match any stage.
+    #[error("Unknown adjustment stage: {name}")]
+    Unknown { name: String },
+
+    /// A stage is listed more than once.
+    #[error("Adjustment stage listed more than once: {}", .stage.name())]
+    Duplicate { stage: AdjustmentStage },
+
+    /// A stage is not listed.
+    #[error("Adjustment stage missing from order: {}", .stage.name())]
+    Missing { stage: AdjustmentStage },
+}
+
+impl StageOrderError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            StageOrderError::Unknown { .. } => "UNKNOWN_STAGE",
+            StageOrderError::Duplicate { .. } => "DUPLICATE_STAGE",
+            StageOrderError::Missing { .. } => "MISSING_STAGE",
+        }
+    }
+}
+
+/// Check that `order` lists every stage exactly once.
+///
+/// # Errors
+/// Returns the first duplicate found, or else the first missing stage in
+/// [`DEFAULT_STAGE_ORDER`].
+pub fn validate_stage_order(order: &[AdjustmentStage]) -> Result<(), StageOrderError> {
+    for (i, &stage) in order.iter().enumerate() {
+        if order[..i].contains(&stage) {
+            return Err(StageOrderError::Duplicate { stage });
+        }
+    }
+    match DEFAULT_STAGE_ORDER
+        .into_iter()
+        .find(|stage| !order.contains(stage))
+    {
+        Some(stage) => Err(StageOrderError::Missing { stage }),
+        None => Ok(()),
+    }
+}
+
+/// Parse and validate a stage order given by name.
+///
+/// # Errors
+/// Returns [`StageOrderError::Unknown`] for the first unrecognized name,
+/// otherwise the same errors as [`validate_stage_order`].
+pub fn parse_stage_order<S: AsRef<str>>(
+    names: &[S],
+) -> Result<Vec<AdjustmentStage>, StageOrderError> {
+    let order = names
+        .iter()
+        .map(|name| {
+            AdjustmentStage::from_name(name.as_ref()).ok_or_else(|| StageOrderError::Unknown {
+                name: name.as_ref().to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    validate_stage_order(&order)?;
+    Ok(order)
+}
 
 /// Apply all adjustments to an image's pixel data in place.
 ///
@@ -23,6 +244,9 @@ use crate::BasicAdjustments;
 /// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
 /// * `adjustments` - The adjustment values to apply
 ///
+/// Only complete RGB triples are processed, so an empty or truncated buffer
+/// never panics (see [`crate::validate`]).
+///
 /// # Example
 /// ```
 /// use literoom_core::{BasicAdjustments, adjustments::apply_all_adjustments};
@@ -35,16 +259,172 @@ use crate::BasicAdjustments;
 /// // Pixel is now brighter (clamped at 255)
 /// ```
 pub fn apply_all_adjustments(pixels: &mut [u8], adjustments: &BasicAdjustments) {
+    let _perf = perf::scope("apply_adjustments");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    if adjustments.is_default() {
+        return;
+    }
+
+    for chunk in pixels.chunks_exact_mut(3) {
+        adjust_rgb8(chunk, adjustments);
+    }
+}
+
+/// Apply all adjustments to a subsampled preview of `image`.
+///
+/// Reads every `2^shift`-th pixel of every `2^shift`-th row, as
+/// [`subsample`] does, and adjusts each one as it is written, so dragging a
+/// slider never builds a resized copy first. The result is exactly
+/// `subsample` followed by [`apply_all_adjustments`], and a shift of 0 is
+/// the full-size render.
+///
+/// # Errors
+/// Returns `DecodeError::InvalidImage` if the image is empty or its buffer
+/// does not match its dimensions.
+pub fn apply_adjustments_subsampled(
+    image: &DecodedImage,
+    adjustments: &BasicAdjustments,
+    shift: u8,
+) -> Result<DecodedImage, DecodeError> {
+    if adjustments.is_default() {
+        return subsample(image, shift);
+    }
+    subsample_map(image, shift, |px| adjust_rgb8(px, adjustments))
+}
+
+/// Adjust one 8-bit RGB pixel in place with the default stage order.
+#[inline]
+fn adjust_rgb8(chunk: &mut [u8], adjustments: &BasicAdjustments) {
+    let r = chunk[0] as f32 / 255.0;
+    let g = chunk[1] as f32 / 255.0;
+    let b = chunk[2] as f32 / 255.0;
+
+    let (r, g, b) = apply_adjustments_to_pixel(r, g, b, adjustments);
+
+    chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+    chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+    chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+}
+
+/// Apply all adjustments with the stages in a custom order.
+///
+/// With [`DEFAULT_STAGE_ORDER`] the result is identical to
+/// [`apply_all_adjustments`].
+///
+/// # Errors
+/// Returns `StageOrderError` if `order` does not list every stage exactly
+/// once; the pixels are unchanged.
+///
+/// # Example
+/// ```
+/// use literoom_core::adjustments::{apply_adjustments_with_order, AdjustmentStage};
+/// use literoom_core::BasicAdjustments;
+///
+/// let mut pixels = vec![180, 180, 180];
+/// let adj = BasicAdjustments {
+///     exposure: 0.5,
+///     contrast: 50.0,
+///     ..Default::default()
+/// };
+/// let order = [
+///     AdjustmentStage::Contrast,
+///     AdjustmentStage::Exposure,
+///     AdjustmentStage::WhiteBalance,
+///     AdjustmentStage::HighlightsShadows,
+///     AdjustmentStage::WhitesBlacks,
+///     AdjustmentStage::SaturationVibrance,
+/// ];
+/// apply_adjustments_with_order(&mut pixels, &adj, &order).unwrap();
+/// ```
+pub fn apply_adjustments_with_order(
+    pixels: &mut [u8],
+    adjustments: &BasicAdjustments,
+    order: &[AdjustmentStage],
+) -> Result<(), StageOrderError> {
+    validate_stage_order(order)?;
+
+    let _perf = perf::scope("apply_adjustments");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    if adjustments.is_default() {
+        return Ok(());
+    }
+
+    for chunk in pixels.chunks_exact_mut(3) {
+        let r = chunk[0] as f32 / 255.0;
+        let g = chunk[1] as f32 / 255.0;
+        let b = chunk[2] as f32 / 255.0;
+
+        let (r, g, b) = adjust_pixel(r, g, b, adjustments, None, None, order);
+
+        chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+    }
+    Ok(())
+}
+
+/// Apply all adjustments to float RGB pixel data in place.
+///
+/// Same pipeline as [`apply_all_adjustments`], on values where 1.0 is the
+/// 8-bit white, but nothing is clamped or quantized: +1 EV takes 0.8 to 1.6.
+/// Values are brought into range when converting back to 8 bits (see
+/// [`crate::decode::DecodedImageF32::to_u8`]).
+///
+/// Only complete RGB triples are processed, as in the 8-bit version.
+pub fn apply_all_adjustments_f32(pixels: &mut [f32], adjustments: &BasicAdjustments) {
+    let _perf = perf::scope("apply_adjustments_f32");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
     if adjustments.is_default() {
         return;
     }
 
+    for chunk in pixels.chunks_exact_mut(3) {
+        let (r, g, b) = apply_adjustments_to_pixel(chunk[0], chunk[1], chunk[2], adjustments);
+        chunk[0] = r;
+        chunk[1] = g;
+        chunk[2] = b;
+    }
+}
+
+/// Apply all adjustments plus a split white balance to an image in place.
+///
+/// Equivalent to [`apply_all_adjustments`] with the split white balance
+/// applied right after the global tint.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
+/// * `adjustments` - The adjustment values to apply
+/// * `split` - Shadow and highlight white balance
+pub fn apply_all_adjustments_with_split(
+    pixels: &mut [u8],
+    adjustments: &BasicAdjustments,
+    split: &SplitWhiteBalance,
+) {
+    if split.is_default() {
+        apply_all_adjustments(pixels, adjustments);
+        return;
+    }
+
+    let _perf = perf::scope("apply_adjustments");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
     for chunk in pixels.chunks_exact_mut(3) {
         let r = chunk[0] as f32 / 255.0;
         let g = chunk[1] as f32 / 255.0;
         let b = chunk[2] as f32 / 255.0;
 
-        let (r, g, b) = apply_adjustments_to_pixel(r, g, b, adjustments);
+        let (r, g, b) = adjust_pixel(
+            r,
+            g,
+            b,
+            adjustments,
+            None,
+            Some(split),
+            &DEFAULT_STAGE_ORDER,
+        );
 
         chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
         chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
@@ -52,6 +432,77 @@ pub fn apply_all_adjustments(pixels: &mut [u8], adjustments: &BasicAdjustments)
     }
 }
 
+/// Apply a split white balance to an image's pixel data in place.
+///
+/// The shadow pair is weighted by `1 - w` and the highlight pair by `w`, where
+/// `w` rises smoothly from 0 at 20% luminance to 1 at 80% luminance.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
+/// * `split` - Shadow and highlight white balance
+///
+/// # Example
+/// ```
+/// use literoom_core::{SplitWhiteBalance, adjustments::apply_split_white_balance};
+///
+/// let mut pixels = vec![40, 40, 40, 220, 220, 220];
+/// let split = SplitWhiteBalance {
+///     shadow_temperature: 40.0, // Cool the shadows
+///     ..Default::default()
+/// };
+///
+/// apply_split_white_balance(&mut pixels, &split);
+/// assert!(pixels[2] > pixels[0]); // Dark pixel turned blue
+/// assert_eq!(&pixels[3..6], &[220, 220, 220]); // Bright pixel untouched
+/// ```
+pub fn apply_split_white_balance(pixels: &mut [u8], split: &SplitWhiteBalance) {
+    let _perf = perf::scope("apply_split_white_balance");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    if split.is_default() {
+        return;
+    }
+
+    for chunk in pixels.chunks_exact_mut(3) {
+        let r = chunk[0] as f32 / 255.0;
+        let g = chunk[1] as f32 / 255.0;
+        let b = chunk[2] as f32 / 255.0;
+
+        let (r, g, b) = apply_split_white_balance_to_pixel(r, g, b, split);
+
+        chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+    }
+}
+
+/// Apply a split white balance to a single pixel.
+///
+/// # Arguments
+/// * `r`, `g`, `b` - Input RGB values (0.0 to 1.0)
+/// * `split` - Shadow and highlight white balance
+///
+/// # Returns
+/// Tuple of (r, g, b) with the blended white balance applied (not clamped)
+#[inline]
+pub fn apply_split_white_balance_to_pixel(
+    r: f32,
+    g: f32,
+    b: f32,
+    split: &SplitWhiteBalance,
+) -> (f32, f32, f32) {
+    let luminance = calculate_luminance(r, g, b);
+    let highlight_weight = smoothstep(SPLIT_WB_SHADOW_EDGE, SPLIT_WB_HIGHLIGHT_EDGE, luminance);
+    let shadow_weight = 1.0 - highlight_weight;
+
+    let temperature =
+        split.shadow_temperature * shadow_weight + split.highlight_temperature * highlight_weight;
+    let tint = split.shadow_tint * shadow_weight + split.highlight_tint * highlight_weight;
+
+    let (r, g, b) = apply_temperature(r, g, b, temperature);
+    apply_tint(r, g, b, tint)
+}
+
 /// Apply all adjustments to a single pixel.
 ///
 /// This is used by the mask module for per-pixel blending of adjusted colors.
@@ -69,21 +520,72 @@ pub fn apply_adjustments_to_pixel(
     b: f32,
     adjustments: &BasicAdjustments,
 ) -> (f32, f32, f32) {
-    let (mut r, mut g, mut b) = (r, g, b);
+    adjust_pixel(r, g, b, adjustments, None, None, &DEFAULT_STAGE_ORDER)
+}
 
-    // Apply adjustments in order
-    (r, g, b) = apply_exposure(r, g, b, adjustments.exposure);
-    (r, g, b) = apply_contrast(r, g, b, adjustments.contrast);
-    (r, g, b) = apply_temperature(r, g, b, adjustments.temperature);
-    (r, g, b) = apply_tint(r, g, b, adjustments.tint);
+/// Shared per-pixel pipeline with optional Kelvin gains and split white balance.
+///
+/// When `gains` is set it replaces the relative temperature in the white
+/// balance stage. `order` must be valid (see [`validate_stage_order`]).
+#[inline]
+fn adjust_pixel(
+    r: f32,
+    g: f32,
+    b: f32,
+    adjustments: &BasicAdjustments,
+    gains: Option<[f32; 3]>,
+    split: Option<&SplitWhiteBalance>,
+    order: &[AdjustmentStage],
+) -> (f32, f32, f32) {
+    let (mut r, mut g, mut b) = (r, g, b);
 
-    let luminance = calculate_luminance(r, g, b);
-    (r, g, b) = apply_highlights(r, g, b, luminance, adjustments.highlights);
-    (r, g, b) = apply_shadows(r, g, b, luminance, adjustments.shadows);
-    (r, g, b) = apply_whites(r, g, b, adjustments.whites);
-    (r, g, b) = apply_blacks(r, g, b, adjustments.blacks);
-    (r, g, b) = apply_saturation(r, g, b, adjustments.saturation);
-    (r, g, b) = apply_vibrance(r, g, b, adjustments.vibrance);
+    for stage in order {
+        match stage {
+            AdjustmentStage::Exposure => {
+                (r, g, b) = apply_exposure(r, g, b, adjustments.exposure);
+            }
+            AdjustmentStage::Contrast => {
+                (r, g, b) = apply_contrast(r, g, b, adjustments.contrast);
+            }
+            AdjustmentStage::WhiteBalance => {
+                (r, g, b) = match gains {
+                    Some([gr, gg, gb]) => (r * gr, g * gg, b * gb),
+                    None => apply_temperature(r, g, b, adjustments.temperature),
+                };
+                (r, g, b) = apply_tint(r, g, b, adjustments.tint);
+                if let Some(split) = split {
+                    (r, g, b) = apply_split_white_balance_to_pixel(r, g, b, split);
+                }
+            }
+            AdjustmentStage::HighlightsShadows => {
+                let luminance = calculate_luminance(r, g, b);
+                (r, g, b) = apply_highlights(r, g, b, luminance, adjustments.highlights);
+                (r, g, b) = apply_shadows(r, g, b, luminance, adjustments.shadows);
+            }
+            AdjustmentStage::WhitesBlacks => {
+                (r, g, b) = apply_whites(r, g, b, adjustments.whites);
+                (r, g, b) = apply_blacks(r, g, b, adjustments.blacks);
+            }
+            AdjustmentStage::SaturationVibrance => {
+                let keep = if adjustments.protect_skin_tones {
+                    1.0 - skin_tone_weight(r, g, b)
+                } else {
+                    1.0
+                };
+                let (saturation, vibrance) =
+                    (adjustments.saturation * keep, adjustments.vibrance * keep);
+                (r, g, b) = match adjustments.color_model {
+                    ColorModel::Legacy => {
+                        let (r, g, b) = apply_saturation(r, g, b, saturation);
+                        apply_vibrance(r, g, b, vibrance)
+                    }
+                    ColorModel::Oklab => {
+                        apply_saturation_vibrance_oklab(r, g, b, saturation, vibrance)
+                    }
+                };
+            }
+        }
+    }
 
     (r, g, b)
 }
@@ -123,6 +625,137 @@ fn apply_contrast(r: f32, g: f32, b: f32, contrast: f32) -> (f32, f32, f32) {
     )
 }
 
+/// Apply all adjustments with white balance given in either mode.
+///
+/// The temperature and tint of `adjustments` are ignored in favor of
+/// `white_balance`. In `Absolute` mode the image is assumed to be balanced
+/// for `reference_kelvin` (the as-shot value, or [`NEUTRAL_KELVIN`] when
+/// unknown); choosing a higher Kelvin value warms it, a lower one cools it.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
+/// * `adjustments` - The adjustment values to apply
+/// * `white_balance` - Relative or Kelvin white balance
+/// * `reference_kelvin` - Color temperature the image is currently balanced for
+pub fn apply_all_adjustments_with_white_balance(
+    pixels: &mut [u8],
+    adjustments: &BasicAdjustments,
+    white_balance: &WhiteBalance,
+    reference_kelvin: f32,
+) {
+    let mut adjustments = adjustments.clone();
+    match *white_balance {
+        WhiteBalance::Relative { temperature, tint } => {
+            adjustments.temperature = temperature;
+            adjustments.tint = tint;
+            apply_all_adjustments(pixels, &adjustments);
+        }
+        WhiteBalance::Absolute { kelvin, tint } => {
+            adjustments.temperature = 0.0;
+            adjustments.tint = tint;
+
+            let _perf = perf::scope("apply_adjustments");
+            perf::record_pixels((pixels.len() / 3) as u64);
+
+            let gains = kelvin_gains(kelvin, reference_kelvin);
+            for chunk in pixels.chunks_exact_mut(3) {
+                let r = chunk[0] as f32 / 255.0;
+                let g = chunk[1] as f32 / 255.0;
+                let b = chunk[2] as f32 / 255.0;
+
+                let (r, g, b) = adjust_pixel(
+                    r,
+                    g,
+                    b,
+                    &adjustments,
+                    Some(gains),
+                    None,
+                    &DEFAULT_STAGE_ORDER,
+                );
+
+                chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+                chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+                chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+            }
+        }
+    }
+}
+
+/// Channel multipliers describing the color of a Planckian illuminant.
+///
+/// Returns `[r, g, b]` relative to a 6500K illuminant and normalized so that
+/// green is 1.0 before tint: 6500K is neutral, lower temperatures give
+/// `r > b` (warm light) and higher ones `b > r`. Positive tint lowers the
+/// green multiplier (magenta light), negative tint raises it.
+///
+/// # Arguments
+/// * `kelvin` - Color temperature, clamped to [`MIN_KELVIN`]..[`MAX_KELVIN`]
+/// * `tint` - Green-magenta shift (-100 to 100)
+pub fn white_balance_kelvin(kelvin: f32, tint: f32) -> [f32; 3] {
+    let [r, g, b] = planckian_rgb(kelvin);
+    let [nr, ng, nb] = planckian_rgb(NEUTRAL_KELVIN);
+    let (r, g, b) = (r / nr, g / ng, b / nb);
+    [r / g, 1.0 - tint * KELVIN_TINT_SCALE, b / g]
+}
+
+/// Estimate the color temperature that produced a set of multipliers.
+///
+/// Inverse of [`white_balance_kelvin`], using the red/blue ratio (tint does
+/// not affect the estimate). The result is clamped to the supported range.
+pub fn estimate_kelvin(multipliers: [f32; 3]) -> f32 {
+    let target = multipliers[0] / multipliers[2].max(f32::EPSILON);
+    let ratio = |kelvin: f32| {
+        let [r, _, b] = white_balance_kelvin(kelvin, 0.0);
+        r / b
+    };
+
+    // The red/blue ratio falls monotonically as temperature rises
+    let (mut lo, mut hi) = (MIN_KELVIN, MAX_KELVIN);
+    for _ in 0..40 {
+        let mid = (lo + hi) / 2.0;
+        if ratio(mid) > target {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// Gains that move an image balanced for `reference_kelvin` to `kelvin`.
+fn kelvin_gains(kelvin: f32, reference_kelvin: f32) -> [f32; 3] {
+    let target = white_balance_kelvin(kelvin, 0.0);
+    let reference = white_balance_kelvin(reference_kelvin, 0.0);
+    [
+        reference[0] / target[0],
+        reference[1] / target[1],
+        reference[2] / target[2],
+    ]
+}
+
+/// Linear sRGB color of a Planckian illuminant (Kim et al. approximation).
+fn planckian_rgb(kelvin: f32) -> [f32; 3] {
+    let t = kelvin.clamp(MIN_KELVIN, MAX_KELVIN) as f64;
+    let (t2, t3) = (t * t, t * t * t);
+
+    let x = if t <= 4000.0 {
+        -0.266_123_9e9 / t3 - 0.234_358_9e6 / t2 + 0.877_695_6e3 / t + 0.179_910
+    } else {
+        -3.025_846_9e9 / t3 + 2.107_037_9e6 / t2 + 0.222_634_7e3 / t + 0.240_390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.106_381_4 * x3 - 1.348_110_20 * x2 + 2.185_558_32 * x - 0.202_196_83
+    } else if t <= 4000.0 {
+        -0.954_947_6 * x3 - 1.374_185_93 * x2 + 2.091_370_15 * x - 0.167_488_67
+    } else {
+        3.081_758_0 * x3 - 5.873_386_70 * x2 + 3.751_129_97 * x - 0.370_014_83
+    };
+
+    let xyz = [(x / y) as f32, 1.0, ((1.0 - x - y) / y) as f32];
+    XYZ_TO_SRGB.map(|row| (row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2]).max(1e-4))
+}
+
 /// Apply temperature (white balance) adjustment.
 ///
 /// Temperature ranges from -100 to +100.
@@ -168,7 +801,7 @@ fn apply_tint(r: f32, g: f32, b: f32, tint: f32) -> (f32, f32, f32) {
 /// Returns 0 for x <= edge0, 1 for x >= edge1,
 /// and smoothly interpolates between.
 #[inline]
-fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+pub(crate) fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
     let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
     t * t * (3.0 - 2.0 * t)
 }
@@ -277,6 +910,37 @@ fn apply_saturation(r: f32, g: f32, b: f32, saturation: f32) -> (f32, f32, f32)
     )
 }
 
+/// How much a pixel looks like skin, from 0.0 to 1.0.
+///
+/// Skin of every complexion sits in a narrow band of orange hues (about 20°
+/// to 50°) at moderate saturation. The weight is 1.0 inside that band and
+/// falls off smoothly over 10° of hue and 0.1-0.2 of saturation (HSV) on
+/// either side, so protected and unprotected pixels blend without edges.
+#[inline]
+fn skin_tone_weight(r: f32, g: f32, b: f32) -> f32 {
+    let max_c = r.max(g).max(b);
+    let min_c = r.min(g).min(b);
+    let chroma = max_c - min_c;
+    if max_c <= 0.0 || chroma <= 0.0 {
+        return 0.0;
+    }
+
+    let hue = if max_c == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max_c == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    let saturation = chroma / max_c;
+
+    let [h0, h1, h2, h3] = SKIN_TONE_HUE_EDGES;
+    let [s0, s1, s2, s3] = SKIN_TONE_SATURATION_EDGES;
+    let hue_weight = smoothstep(h0, h1, hue) * (1.0 - smoothstep(h2, h3, hue));
+    let saturation_weight = smoothstep(s0, s1, saturation) * (1.0 - smoothstep(s2, s3, saturation));
+    hue_weight * saturation_weight
+}
+
 /// Apply vibrance adjustment.
 ///
 /// Vibrance ranges from -100 to +100.
@@ -311,9 +975,484 @@ fn apply_vibrance(r: f32, g: f32, b: f32, vibrance: f32) -> (f32, f32, f32) {
     apply_saturation(r, g, b, effective_vibrance)
 }
 
+/// Oklch chroma at which Oklab vibrance stops having an effect, about the
+/// chroma of the most saturated sRGB reds and blues.
+pub(crate) const VIBRANCE_FULL_CHROMA: f32 = 0.3;
+
+/// Apply saturation, then vibrance, by scaling Oklch chroma.
+///
+/// Both use the same -100 to +100 scale as the legacy stages: saturation
+/// multiplies chroma by `1 + saturation/100`, and vibrance does the same
+/// scaled down by how close the pixel already is to
+/// [`VIBRANCE_FULL_CHROMA`]. Lightness and hue are kept, and colors are
+/// pulled back inside sRGB along constant hue rather than clipped.
+#[inline]
+fn apply_saturation_vibrance_oklab(
+    r: f32,
+    g: f32,
+    b: f32,
+    saturation: f32,
+    vibrance: f32,
+) -> (f32, f32, f32) {
+    if saturation == 0.0 && vibrance == 0.0 {
+        return (r, g, b);
+    }
+    let saturation_factor = (1.0 + saturation / 100.0).max(0.0);
+    let factor = if vibrance == 0.0 {
+        saturation_factor
+    } else {
+        let chroma = srgb_to_oklab([r, g, b]).to_oklch().c * saturation_factor;
+        let protection = 1.0 - (chroma / VIBRANCE_FULL_CHROMA).min(1.0);
+        saturation_factor * (1.0 + vibrance / 100.0 * protection).max(0.0)
+    };
+    let [r, g, b] = scale_chroma([r, g, b], factor);
+    (r, g, b)
+}
+
+/// Which histogram `auto_levels` stretches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoLevelsMode {
+    /// Stretch each RGB channel independently (also neutralizes color casts).
+    PerChannel,
+    /// Stretch luminance and apply the same levels to all channels (keeps hue).
+    #[default]
+    Luminance,
+}
+
+/// Levels computed and applied by [`auto_levels`].
+///
+/// Each channel is mapped as `output = input * gain + offset`, in 0-255 units.
+/// In `Luminance` mode all three channels share the same values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AutoLevels {
+    /// Mode the levels were computed in
+    pub mode: AutoLevelsMode,
+    /// Input value mapped to 0, per channel (R, G, B)
+    pub black_point: [u8; 3],
+    /// Input value mapped to 255 (before the gain cap), per channel
+    pub white_point: [u8; 3],
+    /// Multiplier per channel, at most [`MAX_AUTO_LEVELS_GAIN`]
+    pub gains: [f32; 3],
+    /// Offset per channel, added after the gain
+    pub offsets: [f32; 3],
+}
+
+impl AutoLevels {
+    /// Levels that leave the image unchanged.
+    pub fn identity(mode: AutoLevelsMode) -> Self {
+        Self {
+            mode,
+            black_point: [0; 3],
+            white_point: [255; 3],
+            gains: [1.0; 3],
+            offsets: [0.0; 3],
+        }
+    }
+}
+
+/// Stretch the tonal range so chosen percentiles map to black and white.
+///
+/// The value at `low_percentile` becomes 0 and the value at `high_percentile`
+/// becomes 255, per channel or on luminance depending on `mode`. Gains are
+/// capped at [`MAX_AUTO_LEVELS_GAIN`]; a flat channel is left as is.
+///
+/// Percentiles are in percent (e.g. 0.1 and 99.9). If they are not finite or
+/// `low_percentile >= high_percentile`, the image is left unchanged and
+/// identity levels are returned.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `mode` - Per-channel or luminance stretch
+/// * `low_percentile` - Percentile mapped to 0
+/// * `high_percentile` - Percentile mapped to 255
+///
+/// # Returns
+/// The levels that were applied, so the UI can show what changed.
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+pub fn auto_levels(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    mode: AutoLevelsMode,
+    low_percentile: f32,
+    high_percentile: f32,
+) -> Result<AutoLevels, ImageError> {
+    let hist = compute_histogram(pixels, width, height)?;
+
+    let valid = low_percentile.is_finite()
+        && high_percentile.is_finite()
+        && low_percentile < high_percentile;
+    if !valid {
+        return Ok(AutoLevels::identity(mode));
+    }
+
+    let _perf = perf::scope("auto_levels");
+    perf::record_pixels(width as u64 * height as u64);
+
+    let low = low_percentile.clamp(0.0, 100.0) / 100.0;
+    let high = high_percentile.clamp(0.0, 100.0) / 100.0;
+    let points = |bins: &[u32; 256]| (percentile_value(bins, low), percentile_value(bins, high));
+
+    let mut levels = AutoLevels::identity(mode);
+    let channel_points = match mode {
+        AutoLevelsMode::PerChannel => [points(&hist.red), points(&hist.green), points(&hist.blue)],
+        AutoLevelsMode::Luminance => [points(&hist.luminance); 3],
+    };
+    for (c, (black, white)) in channel_points.into_iter().enumerate() {
+        let (gain, offset) = levels_gain_offset(black, white);
+        levels.black_point[c] = black;
+        levels.white_point[c] = white;
+        levels.gains[c] = gain;
+        levels.offsets[c] = offset;
+    }
+
+    let luts: [[u8; 256]; 3] = std::array::from_fn(|c| {
+        std::array::from_fn(|v| {
+            (v as f32 * levels.gains[c] + levels.offsets[c])
+                .round()
+                .clamp(0.0, 255.0) as u8
+        })
+    });
+    apply_channel_luts(pixels, &luts);
+
+    Ok(levels)
+}
+
+/// Smallest value whose cumulative count reaches `fraction` of all pixels.
+fn percentile_value(bins: &[u32; 256], fraction: f32) -> u8 {
+    let total: u64 = bins.iter().map(|&n| n as u64).sum();
+    let rank = ((fraction as f64 * total as f64).ceil() as u64).max(1);
+
+    let mut seen = 0u64;
+    for (value, &count) in bins.iter().enumerate() {
+        seen += count as u64;
+        if seen >= rank {
+            return value as u8;
+        }
+    }
+    255
+}
+
+/// Gain and offset mapping `black` to 0 and `white` to 255, gain capped.
+fn levels_gain_offset(black: u8, white: u8) -> (f32, f32) {
+    if white <= black {
+        return (1.0, 0.0);
+    }
+    let gain = (255.0 / (white - black) as f32).min(MAX_AUTO_LEVELS_GAIN);
+    (gain, -(black as f32) * gain)
+}
+
+/// Per-channel black and white levels, in 0.0-1.0 units (R, G, B).
+///
+/// Each channel is mapped as `(v - black) / (white - black)`, clamped to
+/// 0.0-1.0. The default maps every channel onto itself.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChannelLevels {
+    /// Input level mapped to 0, per channel
+    pub black: [f32; 3],
+    /// Input level mapped to 1, per channel
+    pub white: [f32; 3],
+}
+
+impl Default for ChannelLevels {
+    fn default() -> Self {
+        Self {
+            black: [0.0; 3],
+            white: [1.0; 3],
+        }
+    }
+}
+
+impl ChannelLevels {
+    /// Check if the levels leave pixels unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check that every level is finite and each black is below its white.
+    ///
+    /// # Errors
+    /// Returns `AdjustmentError::NonFinite` naming `"black"` and/or
+    /// `"white"`, or `AdjustmentError::InvertedLevels` naming the channels
+    /// whose black is at or above their white.
+    pub fn validate(&self) -> Result<(), AdjustmentError> {
+        let non_finite: Vec<&'static str> = [("black", &self.black), ("white", &self.white)]
+            .into_iter()
+            .filter(|(_, values)| values.iter().any(|v| !v.is_finite()))
+            .map(|(name, _)| name)
+            .collect();
+        if !non_finite.is_empty() {
+            return Err(AdjustmentError::NonFinite { fields: non_finite });
+        }
+
+        let channels: Vec<&'static str> = ["red", "green", "blue"]
+            .into_iter()
+            .zip(self.black.iter().zip(&self.white))
+            .filter(|(_, (black, white))| black >= white)
+            .map(|(name, _)| name)
+            .collect();
+        if !channels.is_empty() {
+            return Err(AdjustmentError::InvertedLevels { channels });
+        }
+        Ok(())
+    }
+
+    /// Lookup table per channel mapping 8-bit input to output.
+    pub(crate) fn luts(&self) -> [[u8; 256]; 3] {
+        std::array::from_fn(|c| {
+            let (black, range) = (self.black[c], self.white[c] - self.black[c]);
+            std::array::from_fn(|v| {
+                let normalized = ((v as f32 / 255.0 - black) / range).clamp(0.0, 1.0);
+                (normalized * 255.0).round() as u8
+            })
+        })
+    }
+}
+
+/// Remap each channel from its black and white level.
+///
+/// Meant to run before the rest of the pipeline, to calibrate scanned film
+/// or scientific sources whose channels have different offsets.
+///
+/// # Errors
+/// Returns `AdjustmentError` if the levels are invalid (see
+/// [`ChannelLevels::validate`]); the pixels are left untouched.
+pub fn apply_channel_levels(
+    pixels: &mut [u8],
+    levels: &ChannelLevels,
+) -> Result<(), AdjustmentError> {
+    levels.validate()?;
+    if levels.is_identity() {
+        return Ok(());
+    }
+
+    let _perf = perf::scope("apply_channel_levels");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    apply_channel_luts(pixels, &levels.luts());
+    Ok(())
+}
+
+/// Map each channel through its own lookup table.
+pub(crate) fn apply_channel_luts(pixels: &mut [u8], luts: &[[u8; 256]; 3]) {
+    for chunk in pixels.chunks_exact_mut(3) {
+        chunk[0] = luts[0][chunk[0] as usize];
+        chunk[1] = luts[1][chunk[1] as usize];
+        chunk[2] = luts[2][chunk[2] as usize];
+    }
+}
+
+/// Measure per-channel black and white levels from percentiles.
+///
+/// The value at `low_percentile` of each channel becomes its black level
+/// and the value at `high_percentile` its white level, so applying the
+/// result with [`apply_channel_levels`] lines the channels up. A channel
+/// whose two percentiles coincide keeps identity levels.
+///
+/// Percentiles are in percent (e.g. 0.5 and 99.5). If they are not finite
+/// or `low_percentile >= high_percentile`, identity levels are returned.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `low_percentile` - Percentile taken as black
+/// * `high_percentile` - Percentile taken as white
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+pub fn estimate_channel_levels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    low_percentile: f32,
+    high_percentile: f32,
+) -> Result<ChannelLevels, ImageError> {
+    let hist = compute_histogram(pixels, width, height)?;
+
+    let mut levels = ChannelLevels::default();
+    let valid = low_percentile.is_finite()
+        && high_percentile.is_finite()
+        && low_percentile < high_percentile;
+    if !valid {
+        return Ok(levels);
+    }
+
+    let low = low_percentile.clamp(0.0, 100.0) / 100.0;
+    let high = high_percentile.clamp(0.0, 100.0) / 100.0;
+    for (c, bins) in [&hist.red, &hist.green, &hist.blue].into_iter().enumerate() {
+        let (black, white) = (percentile_value(bins, low), percentile_value(bins, high));
+        if black < white {
+            levels.black[c] = black as f32 / 255.0;
+            levels.white[c] = white as f32 / 255.0;
+        }
+    }
+    Ok(levels)
+}
+
+/// How `estimate_white_balance` estimates the scene illuminant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhiteBalanceMethod {
+    /// Mean of each channel: assumes the scene averages to gray.
+    #[default]
+    GrayWorld,
+    /// Mean color weighted by local contrast. Large flat areas, such as a
+    /// dominant colored object, carry little weight, so textured detail
+    /// decides the estimate.
+    RobustGrayEdge,
+    /// Mean of the brightest pixels: assumes highlights are neutral.
+    BrightestPixels,
+}
+
+/// Estimate the temperature and tint that neutralize an image's color cast.
+///
+/// The illuminant is estimated with `method`, then mapped back to the
+/// slider space of [`BasicAdjustments::temperature`] and
+/// [`BasicAdjustments::tint`] by inverting the temperature and tint steps,
+/// so applying the returned values turns the estimated illuminant gray.
+/// Pixels with a clipped channel (255) carry no reliable color and are
+/// ignored; if nothing is left, `(0.0, 0.0)` is returned.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `method` - Illuminant estimate to use
+///
+/// # Returns
+/// `(temperature, tint)`, clamped to their slider ranges.
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+pub fn estimate_white_balance(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    method: WhiteBalanceMethod,
+) -> Result<(f32, f32), ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    let _perf = perf::scope("estimate_white_balance");
+    perf::record_pixels(width as u64 * height as u64);
+
+    let illuminant = match method {
+        WhiteBalanceMethod::GrayWorld => gray_world_illuminant(pixels),
+        WhiteBalanceMethod::RobustGrayEdge => gray_edge_illuminant(pixels, width as usize),
+        WhiteBalanceMethod::BrightestPixels => brightest_pixels_illuminant(pixels),
+    };
+    Ok(illuminant.map_or((0.0, 0.0), neutralizing_temperature_tint))
+}
+
+/// Whether any channel of an RGB pixel is clipped.
+#[inline]
+fn is_clipped(p: &[u8]) -> bool {
+    p[0] == 255 || p[1] == 255 || p[2] == 255
+}
+
+/// Weighted mean color, or `None` if the total weight is zero.
+fn weighted_mean(samples: impl Iterator<Item = ([u8; 3], f64)>) -> Option<[f64; 3]> {
+    let mut sum = [0.0f64; 3];
+    let mut total = 0.0f64;
+    for (p, weight) in samples {
+        for c in 0..3 {
+            sum[c] += p[c] as f64 * weight;
+        }
+        total += weight;
+    }
+    (total > 0.0).then(|| sum.map(|s| s / total))
+}
+
+/// Mean of all unclipped pixels.
+fn gray_world_illuminant(pixels: &[u8]) -> Option<[f64; 3]> {
+    weighted_mean(
+        pixels
+            .chunks_exact(3)
+            .filter(|p| !is_clipped(p))
+            .map(|p| ([p[0], p[1], p[2]], 1.0)),
+    )
+}
+
+/// Mean of the unclipped pixels, each weighted by its luminance gradient.
+fn gray_edge_illuminant(pixels: &[u8], width: usize) -> Option<[f64; 3]> {
+    let stride = width * 3;
+    let luminance = |i: usize| {
+        calculate_luminance(pixels[i] as f32, pixels[i + 1] as f32, pixels[i + 2] as f32)
+    };
+    let samples = (0..pixels.len()).step_by(3).filter_map(|i| {
+        let p = &pixels[i..i + 3];
+        if is_clipped(p) {
+            return None;
+        }
+        let x = (i % stride) / 3;
+        let here = luminance(i);
+        let dx = if x + 1 < width {
+            luminance(i + 3) - here
+        } else {
+            0.0
+        };
+        let dy = if i + stride < pixels.len() {
+            luminance(i + stride) - here
+        } else {
+            0.0
+        };
+        Some(([p[0], p[1], p[2]], (dx * dx + dy * dy).sqrt() as f64))
+    });
+    weighted_mean(samples)
+}
+
+/// Mean of the brightest [`BRIGHTEST_PIXELS_FRACTION`] of unclipped pixels.
+fn brightest_pixels_illuminant(pixels: &[u8]) -> Option<[f64; 3]> {
+    let unclipped = || pixels.chunks_exact(3).filter(|p| !is_clipped(p));
+    let mut bins = [0u32; 256];
+    for p in unclipped() {
+        bins[calculate_luminance_u8(p[0], p[1], p[2]) as usize] += 1;
+    }
+    let threshold = percentile_value(&bins, 1.0 - BRIGHTEST_PIXELS_FRACTION);
+    weighted_mean(
+        unclipped()
+            .filter(|p| calculate_luminance_u8(p[0], p[1], p[2]) >= threshold)
+            .map(|p| ([p[0], p[1], p[2]], 1.0)),
+    )
+}
+
+/// Temperature and tint that map `illuminant` to gray.
+///
+/// Inverts [`apply_temperature`] on the red/blue ratio, then [`apply_tint`]
+/// on green against the balanced red and blue.
+fn neutralizing_temperature_tint(illuminant: [f64; 3]) -> (f32, f32) {
+    let [r, g, b] = illuminant.map(|v| v.max(f64::EPSILON));
+
+    // Both branches of the temperature step solve to the same signed shift
+    let temp_shift = (r - b) / (r + b);
+    let temperature = temp_shift / 0.3 * 100.0;
+    let balanced = r * (1.0 - temp_shift);
+
+    let tint = if g > balanced {
+        // Magenta: balanced * (1 + s) == g * (1 - s)
+        (g - balanced) / (g + balanced) / 0.2 * 100.0
+    } else {
+        // Green: g * (1 + s) == balanced
+        -(balanced / g - 1.0) / 0.2 * 100.0
+    };
+
+    (
+        ranges::TEMPERATURE.clamp(temperature as f32),
+        ranges::TINT.clamp(tint as f32),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{color_bars, noise};
 
     /// Helper to create a pixel from RGB values (0-255)
     fn pixel(r: u8, g: u8, b: u8) -> Vec<u8> {
@@ -636,23 +1775,16 @@ mod tests {
 
     #[test]
     fn test_multiple_pixels() {
-        let mut pixels = vec![
-            255, 0, 0, // Red
-            0, 255, 0, // Green
-            0, 0, 255, // Blue
-            128, 128, 128, // Gray
-        ];
+        let mut image = color_bars(8, 1);
         let mut adj = BasicAdjustments::default();
         adj.saturation = -100.0;
-        apply_all_adjustments(&mut pixels, &adj);
+        apply_all_adjustments(&mut image.pixels, &adj);
 
-        // All should be grayscale now
-        // Red pixel
-        assert!((pixels[0] as i32 - pixels[1] as i32).abs() < 10);
-        // Green pixel
-        assert!((pixels[3] as i32 - pixels[4] as i32).abs() < 10);
-        // Blue pixel
-        assert!((pixels[6] as i32 - pixels[7] as i32).abs() < 10);
+        // Every bar should be grayscale now
+        for p in image.pixels.chunks(3) {
+            let spread = *p.iter().max().unwrap() as i32 - *p.iter().min().unwrap() as i32;
+            assert!(spread < 10, "{:?} not gray", p);
+        }
     }
 
     // ===== Edge Case Tests =====
@@ -676,4 +1808,770 @@ mod tests {
         assert_eq!(pixels[0], 255); // Brightened and clamped
         assert_eq!(pixels[3], 64); // Remainder unchanged
     }
+
+    #[test]
+    fn test_apply_all_adjustments_reports_pixel_count() {
+        let mut pixels = noise(40, 30, 3).pixels;
+        let mut adj = BasicAdjustments::default();
+        adj.exposure = 0.5;
+
+        apply_all_adjustments(&mut pixels, &adj);
+
+        let stats = crate::perf::last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "apply_adjustments");
+        assert_eq!(stats.pixels_processed, 40 * 30);
+        assert_eq!(stats.bytes_allocated, 0); // In place
+    }
+
+    /// 2x1 image: one dark gray pixel, one light gray pixel
+    fn dark_and_light() -> Vec<u8> {
+        vec![50, 50, 50, 210, 210, 210]
+    }
+
+    #[test]
+    fn test_split_white_balance_zero_is_noop() {
+        let mut pixels = dark_and_light();
+        apply_split_white_balance(&mut pixels, &SplitWhiteBalance::default());
+        assert_eq!(pixels, dark_and_light());
+
+        // Non-default adjustments with a zero split match the plain pipeline
+        let mut adj = BasicAdjustments::default();
+        adj.contrast = 30.0;
+        let mut plain = dark_and_light();
+        let mut split = dark_and_light();
+        apply_all_adjustments(&mut plain, &adj);
+        apply_all_adjustments_with_split(&mut split, &adj, &SplitWhiteBalance::default());
+        assert_eq!(plain, split);
+    }
+
+    #[test]
+    fn test_split_white_balance_warm_shadows_only() {
+        let mut pixels = dark_and_light();
+        let split = SplitWhiteBalance {
+            shadow_temperature: -60.0, // Warm
+            ..Default::default()
+        };
+
+        apply_split_white_balance(&mut pixels, &split);
+
+        // Dark half: red up, blue down
+        let ratio = pixels[0] as f32 / pixels[2] as f32;
+        assert!(ratio > 1.2, "dark R/B ratio {}", ratio);
+        // Bright half unchanged within ±1
+        for (&out, &orig) in pixels[3..6].iter().zip(&dark_and_light()[3..6]) {
+            assert!((out as i16 - orig as i16).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_split_white_balance_highlights_only() {
+        let mut pixels = dark_and_light();
+        let split = SplitWhiteBalance {
+            highlight_temperature: 50.0, // Cool
+            highlight_tint: 20.0,
+            ..Default::default()
+        };
+
+        apply_split_white_balance(&mut pixels, &split);
+
+        assert_eq!(&pixels[0..3], &[50, 50, 50]);
+        assert!(pixels[5] > pixels[3]);
+    }
+
+    #[test]
+    fn test_split_white_balance_smooth_crossover() {
+        let split = SplitWhiteBalance {
+            shadow_temperature: -100.0,
+            ..Default::default()
+        };
+
+        // The warm shift fades monotonically as luminance rises
+        let mut last_ratio = f32::MAX;
+        for v in (0..=10).map(|i| 0.05 + i as f32 * 0.09) {
+            let (r, _, b) = apply_split_white_balance_to_pixel(v, v, v, &split);
+            let ratio = r / b;
+            assert!(ratio <= last_ratio + 1e-6);
+            last_ratio = ratio;
+        }
+        assert!((last_ratio - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_split_white_balance_in_pipeline() {
+        let mut pixels = dark_and_light();
+        let adj = BasicAdjustments::default();
+        let split = SplitWhiteBalance {
+            shadow_temperature: -60.0,
+            ..Default::default()
+        };
+
+        apply_all_adjustments_with_split(&mut pixels, &adj, &split);
+
+        let mut standalone = dark_and_light();
+        apply_split_white_balance(&mut standalone, &split);
+        assert_eq!(pixels, standalone);
+    }
+
+    #[test]
+    fn test_kelvin_6500_is_neutral() {
+        let [r, g, b] = white_balance_kelvin(6500.0, 0.0);
+        assert!((r - 1.0).abs() < 0.01, "r = {}", r);
+        assert_eq!(g, 1.0);
+        assert!((b - 1.0).abs() < 0.01, "b = {}", b);
+    }
+
+    #[test]
+    fn test_kelvin_3000_is_warm() {
+        let [r, _, b] = white_balance_kelvin(3000.0, 0.0);
+        assert!(r > 1.0 && b < 1.0, "r = {}, b = {}", r, b);
+        assert!(r > b);
+
+        let [r, _, b] = white_balance_kelvin(9000.0, 0.0);
+        assert!(b > r, "r = {}, b = {}", r, b);
+    }
+
+    #[test]
+    fn test_kelvin_tint_moves_green() {
+        assert!(white_balance_kelvin(5000.0, 50.0)[1] < 1.0);
+        assert!(white_balance_kelvin(5000.0, -50.0)[1] > 1.0);
+    }
+
+    #[test]
+    fn test_kelvin_round_trip() {
+        for kelvin in (3000..=8000).step_by(250) {
+            let kelvin = kelvin as f32;
+            let estimated = estimate_kelvin(white_balance_kelvin(kelvin, 20.0));
+            assert!(
+                (estimated - kelvin).abs() < 200.0,
+                "{}K estimated as {}K",
+                kelvin,
+                estimated
+            );
+        }
+    }
+
+    #[test]
+    fn test_absolute_white_balance_at_reference_is_noop() {
+        let mut pixels = vec![100, 150, 200, 30, 60, 90];
+        let original = pixels.clone();
+        let wb = WhiteBalance::Absolute {
+            kelvin: 5200.0,
+            tint: 0.0,
+        };
+        apply_all_adjustments_with_white_balance(
+            &mut pixels,
+            &BasicAdjustments::default(),
+            &wb,
+            5200.0,
+        );
+        for (a, b) in pixels.iter().zip(&original) {
+            assert!((*a as i32 - *b as i32).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_absolute_white_balance_direction() {
+        let adj = BasicAdjustments::default();
+        let warm = WhiteBalance::Absolute {
+            kelvin: 9000.0,
+            tint: 0.0,
+        };
+        let cool = WhiteBalance::Absolute {
+            kelvin: 3500.0,
+            tint: 0.0,
+        };
+
+        let mut warmed = vec![128, 128, 128];
+        apply_all_adjustments_with_white_balance(&mut warmed, &adj, &warm, NEUTRAL_KELVIN);
+        assert!(warmed[0] > warmed[2], "{:?}", warmed);
+
+        let mut cooled = vec![128, 128, 128];
+        apply_all_adjustments_with_white_balance(&mut cooled, &adj, &cool, NEUTRAL_KELVIN);
+        assert!(cooled[2] > cooled[0], "{:?}", cooled);
+    }
+
+    #[test]
+    fn test_relative_white_balance_overrides_adjustments() {
+        let adj = BasicAdjustments {
+            temperature: 80.0,
+            exposure: 0.5,
+            ..Default::default()
+        };
+        let wb = WhiteBalance::Relative {
+            temperature: -40.0,
+            tint: 10.0,
+        };
+
+        let mut pixels = vec![100, 120, 140];
+        apply_all_adjustments_with_white_balance(&mut pixels, &adj, &wb, NEUTRAL_KELVIN);
+
+        let expected_adj = BasicAdjustments {
+            temperature: -40.0,
+            tint: 10.0,
+            exposure: 0.5,
+            ..Default::default()
+        };
+        let mut expected = vec![100, 120, 140];
+        apply_all_adjustments(&mut expected, &expected_adj);
+        assert_eq!(pixels, expected);
+    }
+
+    // ===== Auto Levels Tests =====
+
+    /// Horizontal gray gradient from `lo` to `hi`, `width` x 4 pixels.
+    fn gray_gradient(width: u32, lo: u32, hi: u32) -> Vec<u8> {
+        let mut pixels = Vec::new();
+        for _ in 0..4 {
+            for x in 0..width {
+                let v = (lo + (hi - lo) * x / (width - 1)) as u8;
+                pixels.extend_from_slice(&[v, v, v]);
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn test_auto_levels_expands_low_contrast_gradient() {
+        let mut pixels = gray_gradient(256, 60, 200);
+        let levels =
+            auto_levels(&mut pixels, 256, 4, AutoLevelsMode::Luminance, 0.1, 99.9).unwrap();
+
+        assert_eq!(levels.black_point, [60; 3]);
+        assert_eq!(levels.white_point, [200; 3]);
+        assert!(*pixels.iter().min().unwrap() <= 2);
+        assert!(*pixels.iter().max().unwrap() >= 253);
+    }
+
+    #[test]
+    fn test_auto_levels_pure_red_caps_gain() {
+        let mut pixels = [255u8, 0, 0].repeat(16);
+        let levels = auto_levels(&mut pixels, 4, 4, AutoLevelsMode::PerChannel, 0.1, 99.9).unwrap();
+
+        for c in 0..3 {
+            assert!(levels.gains[c].is_finite());
+            assert!(levels.gains[c] <= MAX_AUTO_LEVELS_GAIN);
+        }
+        assert!(pixels.chunks(3).all(|p| p == [255, 0, 0]));
+    }
+
+    #[test]
+    fn test_auto_levels_caps_nearly_empty_channel() {
+        // Blue only spans 0..=8: a full stretch would be a 32x gain
+        let mut pixels = Vec::new();
+        for i in 0..64u32 {
+            pixels.extend_from_slice(&[(i * 4) as u8, 128, (i % 9) as u8]);
+        }
+        let levels =
+            auto_levels(&mut pixels, 8, 8, AutoLevelsMode::PerChannel, 0.0, 100.0).unwrap();
+
+        assert_eq!(levels.gains[2], MAX_AUTO_LEVELS_GAIN);
+        assert!(pixels.chunks(3).all(|p| p[2] <= 32));
+    }
+
+    #[test]
+    fn test_auto_levels_nearly_idempotent() {
+        let max_diff = |a: &[u8], b: &[u8]| {
+            a.iter()
+                .zip(b)
+                .map(|(&x, &y)| (x as i32 - y as i32).abs())
+                .max()
+                .unwrap()
+        };
+
+        // Colorful image, per channel
+        let mut colorful = Vec::new();
+        for i in 0..400u32 {
+            colorful.extend_from_slice(&[
+                (40 + i * 7 % 150) as u8,
+                (70 + i * 13 % 120) as u8,
+                (20 + i * 3 % 170) as u8,
+            ]);
+        }
+        // Slightly warm gradient, on luminance. Strongly colored pixels clip
+        // per channel, which shifts luminance, so use a low-saturation image.
+        let warm: Vec<u8> = gray_gradient(100, 50, 180)
+            .chunks(3)
+            .flat_map(|p| [p[0].saturating_add(6), p[1], p[2].saturating_sub(6)])
+            .collect();
+
+        for (mode, pixels, width, height) in [
+            (AutoLevelsMode::PerChannel, colorful, 20, 20),
+            (AutoLevelsMode::Luminance, warm, 100, 4),
+        ] {
+            let mut once = pixels;
+            auto_levels(&mut once, width, height, mode, 0.5, 99.5).unwrap();
+            let mut twice = once.clone();
+            auto_levels(&mut twice, width, height, mode, 0.5, 99.5).unwrap();
+
+            let diff = max_diff(&once, &twice);
+            assert!(diff <= 2, "{:?}: second pass moved by {}", mode, diff);
+        }
+    }
+
+    #[test]
+    fn test_auto_levels_invalid_percentiles_is_identity() {
+        let original = gray_gradient(16, 60, 200);
+        for (low, high) in [(50.0, 50.0), (90.0, 10.0), (f32::NAN, 99.0)] {
+            let mut pixels = original.clone();
+            let levels =
+                auto_levels(&mut pixels, 16, 4, AutoLevelsMode::Luminance, low, high).unwrap();
+            assert_eq!(levels, AutoLevels::identity(AutoLevelsMode::Luminance));
+            assert_eq!(pixels, original);
+        }
+    }
+
+    #[test]
+    fn test_auto_levels_buffer_mismatch() {
+        let mut pixels = vec![0u8; 10];
+        let result = auto_levels(&mut pixels, 2, 2, AutoLevelsMode::Luminance, 0.1, 99.9);
+        assert!(matches!(result, Err(ImageError::BufferSizeMismatch { .. })));
+    }
+
+    // ===== Channel Levels Tests =====
+
+    #[test]
+    fn test_channel_levels_neutralize_scanner_offsets() {
+        // Gray scene as a scanner with per-channel dark current sees it
+        let offsets = [10u8, 4, 16];
+        let mut pixels: Vec<u8> = noise(64, 64, 3)
+            .pixels
+            .chunks(3)
+            .flat_map(|p| {
+                let v = (p[0] as u32 * 230 / 255) as u8;
+                [v + offsets[0], v + offsets[1], v + offsets[2]]
+            })
+            .collect();
+
+        let levels = estimate_channel_levels(&pixels, 64, 64, 0.5, 99.5).unwrap();
+        for (c, &offset) in offsets.iter().enumerate() {
+            assert!(levels.black[c] * 255.0 >= offset as f32, "{:?}", levels);
+        }
+        apply_channel_levels(&mut pixels, &levels).unwrap();
+
+        for p in pixels.chunks(3) {
+            let (min, max) = (*p.iter().min().unwrap(), *p.iter().max().unwrap());
+            assert!(max - min <= 2, "pixel {:?} is not neutral", p);
+        }
+    }
+
+    #[test]
+    fn test_channel_levels_identity_is_noop() {
+        let mut pixels = noise(16, 16, 9).pixels;
+        let original = pixels.clone();
+        apply_channel_levels(&mut pixels, &ChannelLevels::default()).unwrap();
+        assert_eq!(pixels, original);
+
+        // The full-range LUT maps every value onto itself too
+        let luts = ChannelLevels::default().luts();
+        assert!(luts
+            .iter()
+            .all(|lut| lut.iter().enumerate().all(|(v, &o)| v == o as usize)));
+    }
+
+    #[test]
+    fn test_channel_levels_rejects_inverted_or_equal() {
+        let levels = ChannelLevels {
+            black: [0.5, 0.2, 0.1],
+            white: [0.4, 0.2, 0.9],
+        };
+        let err = levels.validate().unwrap_err();
+        assert_eq!(
+            err,
+            AdjustmentError::InvertedLevels {
+                channels: vec!["red", "green"]
+            }
+        );
+        assert_eq!(err.code(), "INVERTED_CHANNEL_LEVELS");
+
+        let mut pixels = vec![100u8; 12];
+        assert!(apply_channel_levels(&mut pixels, &levels).is_err());
+        assert_eq!(pixels, vec![100u8; 12], "failed call must not write");
+
+        let levels = ChannelLevels {
+            white: [1.0, f32::NAN, 1.0],
+            ..Default::default()
+        };
+        assert_eq!(
+            levels.validate(),
+            Err(AdjustmentError::NonFinite {
+                fields: vec!["white"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_estimate_channel_levels_flat_or_invalid_is_identity() {
+        let pixels = [40u8, 90, 200].repeat(16);
+        let flat = estimate_channel_levels(&pixels, 4, 4, 0.5, 99.5).unwrap();
+        assert!(flat.is_identity());
+
+        let pixels = noise(8, 8, 1).pixels;
+        for (low, high) in [(50.0, 50.0), (90.0, 10.0), (f32::NAN, 99.0)] {
+            let levels = estimate_channel_levels(&pixels, 8, 8, low, high).unwrap();
+            assert!(levels.is_identity());
+        }
+        assert!(estimate_channel_levels(&pixels, 8, 9, 0.5, 99.5).is_err());
+    }
+
+    /// Textured gray scene (values 40-200) with a cool cast: temperature +40.
+    /// `red_box` paints a flat red object over that fraction of each axis.
+    fn cast_scene(width: u32, height: u32, red_box: f32) -> Vec<u8> {
+        let mut pixels: Vec<u8> = noise(width, height, 7)
+            .pixels
+            .chunks(3)
+            .flat_map(|p| [(40 + p[0] as u32 * 160 / 255) as u8; 3])
+            .collect();
+        let (bw, bh) = (width as f32 * red_box, height as f32 * red_box);
+        let (x0, y0) = ((width as f32 - bw) / 2.0, (height as f32 - bh) / 2.0);
+        for (i, p) in pixels.chunks_exact_mut(3).enumerate() {
+            let (x, y) = ((i as u32 % width) as f32, (i as u32 / width) as f32);
+            if x >= x0 && x < x0 + bw && y >= y0 && y < y0 + bh {
+                p.copy_from_slice(&[200, 40, 40]);
+            }
+        }
+        let cast = BasicAdjustments {
+            temperature: 40.0,
+            ..Default::default()
+        };
+        apply_all_adjustments(&mut pixels, &cast);
+        pixels
+    }
+
+    /// Distance of an estimate from the correction for [`cast_scene`].
+    fn wb_error((temperature, tint): (f32, f32)) -> f32 {
+        (temperature + 40.0).abs() + tint.abs()
+    }
+
+    #[test]
+    fn test_estimate_white_balance_gray_world_recovers_cast() {
+        let pixels = cast_scene(64, 48, 0.0);
+        let (temperature, tint) =
+            estimate_white_balance(&pixels, 64, 48, WhiteBalanceMethod::GrayWorld).unwrap();
+        assert!(
+            (temperature + 40.0).abs() <= 8.0,
+            "temperature {}",
+            temperature
+        );
+        assert!(tint.abs() <= 8.0, "tint {}", tint);
+
+        // Applying the estimate neutralizes the scene
+        let mut corrected = pixels;
+        let correction = BasicAdjustments {
+            temperature,
+            tint,
+            ..Default::default()
+        };
+        apply_all_adjustments(&mut corrected, &correction);
+        let neutral = estimate_white_balance(&corrected, 64, 48, WhiteBalanceMethod::GrayWorld);
+        let (t, g) = neutral.unwrap();
+        assert!(t.abs() <= 2.0 && g.abs() <= 2.0, "residual ({}, {})", t, g);
+    }
+
+    #[test]
+    fn test_estimate_white_balance_gray_edge_resists_dominant_color() {
+        let pixels = cast_scene(64, 48, 0.8);
+        let estimate = |method| estimate_white_balance(&pixels, 64, 48, method).unwrap();
+        let gray_world = wb_error(estimate(WhiteBalanceMethod::GrayWorld));
+        let gray_edge = wb_error(estimate(WhiteBalanceMethod::RobustGrayEdge));
+        assert!(
+            gray_edge < gray_world,
+            "gray edge error {} vs gray world {}",
+            gray_edge,
+            gray_world
+        );
+    }
+
+    #[test]
+    fn test_estimate_white_balance_brightest_pixels() {
+        let pixels = cast_scene(64, 48, 0.0);
+        let estimate = estimate_white_balance(&pixels, 64, 48, WhiteBalanceMethod::BrightestPixels);
+        assert!(wb_error(estimate.unwrap()) <= 8.0);
+    }
+
+    #[test]
+    fn test_estimate_white_balance_fully_clipped_is_neutral() {
+        let pixels = vec![255u8; 4 * 4 * 3];
+        for method in [
+            WhiteBalanceMethod::GrayWorld,
+            WhiteBalanceMethod::RobustGrayEdge,
+            WhiteBalanceMethod::BrightestPixels,
+        ] {
+            assert_eq!(
+                estimate_white_balance(&pixels, 4, 4, method),
+                Ok((0.0, 0.0))
+            );
+        }
+        let result = estimate_white_balance(&pixels, 5, 4, WhiteBalanceMethod::GrayWorld);
+        assert!(matches!(result, Err(ImageError::BufferSizeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_f32_exposure_is_not_clamped() {
+        let mut pixels = vec![0.8f32, 0.4, 2.0];
+        let adj = BasicAdjustments {
+            exposure: 1.0,
+            ..Default::default()
+        };
+        apply_all_adjustments_f32(&mut pixels, &adj);
+        assert_eq!(pixels, vec![1.6, 0.8, 4.0]);
+
+        // Darkening again recovers the highlight the 8-bit path would clip
+        let adj = BasicAdjustments {
+            exposure: -1.0,
+            ..Default::default()
+        };
+        apply_all_adjustments_f32(&mut pixels, &adj);
+        assert_eq!(pixels, vec![0.8, 0.4, 2.0]);
+    }
+
+    fn busy_adjustments() -> BasicAdjustments {
+        BasicAdjustments {
+            temperature: 20.0,
+            tint: -10.0,
+            exposure: 0.7,
+            contrast: 25.0,
+            highlights: -40.0,
+            shadows: 30.0,
+            whites: 10.0,
+            blacks: -15.0,
+            vibrance: 20.0,
+            saturation: -10.0,
+            protect_skin_tones: false,
+            color_model: ColorModel::Legacy,
+        }
+    }
+
+    #[test]
+    fn test_default_order_matches_fixed_pipeline() {
+        let adj = busy_adjustments();
+        for image in [noise(32, 24, 5), color_bars(64, 8)] {
+            // The stage sequence as it was written out before orders existed
+            let mut expected = image.pixels.clone();
+            for chunk in expected.chunks_exact_mut(3) {
+                let (mut r, mut g, mut b) = (
+                    chunk[0] as f32 / 255.0,
+                    chunk[1] as f32 / 255.0,
+                    chunk[2] as f32 / 255.0,
+                );
+                (r, g, b) = apply_exposure(r, g, b, adj.exposure);
+                (r, g, b) = apply_contrast(r, g, b, adj.contrast);
+                (r, g, b) = apply_temperature(r, g, b, adj.temperature);
+                (r, g, b) = apply_tint(r, g, b, adj.tint);
+                let luminance = calculate_luminance(r, g, b);
+                (r, g, b) = apply_highlights(r, g, b, luminance, adj.highlights);
+                (r, g, b) = apply_shadows(r, g, b, luminance, adj.shadows);
+                (r, g, b) = apply_whites(r, g, b, adj.whites);
+                (r, g, b) = apply_blacks(r, g, b, adj.blacks);
+                (r, g, b) = apply_saturation(r, g, b, adj.saturation);
+                (r, g, b) = apply_vibrance(r, g, b, adj.vibrance);
+                chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+                chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+                chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+            }
+
+            let mut ordered = image.pixels.clone();
+            apply_adjustments_with_order(&mut ordered, &adj, &DEFAULT_STAGE_ORDER).unwrap();
+            assert_eq!(ordered, expected);
+            assert_eq!(apply(&image.pixels, &adj), expected);
+        }
+    }
+
+    #[test]
+    fn test_exposure_before_contrast_is_brighter() {
+        let adj = BasicAdjustments {
+            exposure: 0.5,
+            contrast: 50.0,
+            ..Default::default()
+        };
+        let mut swapped = DEFAULT_STAGE_ORDER;
+        swapped.swap(0, 1);
+
+        let mut exposure_first = pixel(100, 100, 100);
+        let mut contrast_first = exposure_first.clone();
+        apply_adjustments_with_order(&mut exposure_first, &adj, &DEFAULT_STAGE_ORDER).unwrap();
+        apply_adjustments_with_order(&mut contrast_first, &adj, &swapped).unwrap();
+
+        // 100/255 lifted by (sqrt(2) - 1) * 0.5 / 2 ≈ 0.104, about 26 levels
+        assert_eq!(exposure_first, pixel(148, 148, 148));
+        assert_eq!(contrast_first, pixel(121, 121, 121));
+    }
+
+    #[test]
+    fn test_invalid_stage_orders_are_rejected() {
+        use AdjustmentStage::*;
+
+        let mut pixels = pixel(100, 150, 200);
+        let adj = busy_adjustments();
+
+        let duplicate = [
+            Exposure,
+            Exposure,
+            WhiteBalance,
+            HighlightsShadows,
+            WhitesBlacks,
+        ];
+        assert_eq!(
+            apply_adjustments_with_order(&mut pixels, &adj, &duplicate),
+            Err(StageOrderError::Duplicate { stage: Exposure })
+        );
+        let missing = [
+            Exposure,
+            Contrast,
+            WhiteBalance,
+            HighlightsShadows,
+            WhitesBlacks,
+        ];
+        assert_eq!(
+            apply_adjustments_with_order(&mut pixels, &adj, &missing),
+            Err(StageOrderError::Missing {
+                stage: SaturationVibrance
+            })
+        );
+        assert_eq!(pixels, pixel(100, 150, 200));
+
+        let err = parse_stage_order(&["exposure", "levels"]).unwrap_err();
+        assert_eq!(err.code(), "UNKNOWN_STAGE");
+        let names: Vec<_> = DEFAULT_STAGE_ORDER.iter().map(|s| s.name()).collect();
+        assert_eq!(parse_stage_order(&names), Ok(DEFAULT_STAGE_ORDER.to_vec()));
+    }
+
+    /// Distance of a pixel from its own luminance gray, as in `apply_saturation`.
+    fn chroma(pixel: &[u8]) -> f32 {
+        let (r, g, b) = (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+        let gray = calculate_luminance(r, g, b);
+        ((r - gray).powi(2) + (g - gray).powi(2) + (b - gray).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_protect_skin_tones_holds_back_saturation_on_skin() {
+        let skin = pixel(224, 172, 140);
+        let sky = pixel(110, 160, 220);
+        let off = BasicAdjustments {
+            saturation: 80.0,
+            ..Default::default()
+        };
+        let on = BasicAdjustments {
+            protect_skin_tones: true,
+            ..off.clone()
+        };
+
+        let skin_change_off = chroma(&apply(&skin, &off)) - chroma(&skin);
+        let skin_change_on = chroma(&apply(&skin, &on)) - chroma(&skin);
+        assert!(skin_change_off > 20.0, "{}", skin_change_off);
+        assert!(
+            skin_change_on.abs() < skin_change_off / 2.0,
+            "{} vs {}",
+            skin_change_on,
+            skin_change_off
+        );
+
+        assert_ne!(apply(&sky, &off), sky);
+        assert_eq!(apply(&sky, &on), apply(&sky, &off));
+    }
+
+    #[test]
+    fn test_skin_tone_weight_band() {
+        assert_eq!(skin_tone_weight(0.88, 0.67, 0.55), 1.0);
+        // Gray, saturated red, green and blue are not skin
+        assert_eq!(skin_tone_weight(0.5, 0.5, 0.5), 0.0);
+        assert_eq!(skin_tone_weight(1.0, 0.0, 0.0), 0.0);
+        assert_eq!(skin_tone_weight(0.2, 0.8, 0.3), 0.0);
+        assert_eq!(skin_tone_weight(0.43, 0.63, 0.86), 0.0);
+    }
+
+    #[test]
+    fn test_skin_protection_off_is_unchanged() {
+        let adj = BasicAdjustments {
+            saturation: 60.0,
+            vibrance: 40.0,
+            ..Default::default()
+        };
+        let image = noise(32, 24, 9);
+        let mut expected = image.pixels.clone();
+        for chunk in expected.chunks_exact_mut(3) {
+            let (r, g, b) = (
+                chunk[0] as f32 / 255.0,
+                chunk[1] as f32 / 255.0,
+                chunk[2] as f32 / 255.0,
+            );
+            let (r, g, b) = apply_saturation(r, g, b, adj.saturation);
+            let (r, g, b) = apply_vibrance(r, g, b, adj.vibrance);
+            chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+            chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+            chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+        }
+        assert_eq!(apply(&image.pixels, &adj), expected);
+    }
+
+    fn oklch(pixel: &[u8]) -> crate::color::Oklch {
+        srgb_to_oklab([pixel[0], pixel[1], pixel[2]].map(|v| v as f32 / 255.0)).to_oklch()
+    }
+
+    #[test]
+    fn test_oklab_saturation_keeps_blue_hue() {
+        let blue = pixel(40, 70, 170);
+        let adj = BasicAdjustments {
+            saturation: 60.0,
+            color_model: ColorModel::Oklab,
+            ..Default::default()
+        };
+        let (before, after) = (oklch(&blue), oklch(&apply(&blue, &adj)));
+        assert!(after.c > before.c, "{:?} -> {:?}", before, after);
+        assert!(
+            (after.h - before.h).abs() < 1.0,
+            "{:?} -> {:?}",
+            before,
+            after
+        );
+    }
+
+    #[test]
+    fn test_oklab_vibrance_favors_muted_colors() {
+        let adj = BasicAdjustments {
+            vibrance: 50.0,
+            color_model: ColorModel::Oklab,
+            ..Default::default()
+        };
+        let gain = |p: Vec<u8>| oklch(&apply(&p, &adj)).c / oklch(&p).c;
+        assert!(gain(pixel(140, 120, 110)) > gain(pixel(200, 40, 30)));
+    }
+
+    #[test]
+    fn test_oklab_gray_is_unchanged() {
+        let adj = BasicAdjustments {
+            saturation: 100.0,
+            vibrance: 100.0,
+            color_model: ColorModel::Oklab,
+            ..Default::default()
+        };
+        for v in [0, 77, 128, 255] {
+            assert_eq!(apply(&pixel(v, v, v), &adj), pixel(v, v, v));
+        }
+    }
+
+    #[test]
+    fn test_subsampled_preview_matches_subsample_then_adjust() {
+        let adj = BasicAdjustments {
+            exposure: 0.7,
+            contrast: 25.0,
+            shadows: 30.0,
+            vibrance: 20.0,
+            ..Default::default()
+        };
+        let image = noise(37, 23, 4);
+        for shift in 0..4 {
+            let mut expected = subsample(&image, shift).unwrap();
+            apply_all_adjustments(&mut expected.pixels, &adj);
+
+            let preview = apply_adjustments_subsampled(&image, &adj, shift).unwrap();
+            assert_eq!(
+                (preview.width, preview.height),
+                (expected.width, expected.height)
+            );
+            assert_eq!(preview.pixels, expected.pixels);
+        }
+
+        let full = apply_adjustments_subsampled(&image, &adj, 0).unwrap();
+        assert_eq!(full.pixels, apply(&image.pixels, &adj));
+    }
 }
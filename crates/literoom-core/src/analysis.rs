@@ -0,0 +1,292 @@
+//! Image quality analysis for culling.
+//!
+//! [`assess_image_quality`] scores how sharp and how well exposed a photo
+//! is, so an import of hundreds of frames can flag the obviously bad ones
+//! for review. It is meant for the small previews made during import: the
+//! luma is averaged down to at most [`QUALITY_MAX_EDGE`] pixels on the long
+//! edge before measuring sharpness, which keeps a 640px preview well under
+//! 10 ms.
+//!
+//! Both scores run from 0.0 (bad) to 1.0 (good):
+//!
+//! - **Sharpness** is the variance of the Laplacian of the downsized luma,
+//!   mapped to `variance / (variance + SHARPNESS_REFERENCE_VARIANCE)`. Soft
+//!   focus and motion blur leave few strong second derivatives. Frames below
+//!   [`LIKELY_BLURRY_BELOW`] are likely blurred; very smooth subjects such
+//!   as a clear sky also score low, so treat the flag as a hint.
+//! - **Exposure** starts at 1.0 for a mean luma between 20% and 80% and
+//!   falls linearly to 0.0 at black and white; it is then reduced by the
+//!   share of clipped pixels above 2%, reaching 0.0 at 30%. Frames below
+//!   [`LIKELY_BADLY_EXPOSED_BELOW`] are likely badly exposed.
+
+use serde::Serialize;
+
+use crate::luminance::calculate_luminance;
+use crate::perf;
+use crate::validate::{validate_rgb_buffer, ImageError};
+
+/// Longest edge of the luma the sharpness is measured on.
+pub const QUALITY_MAX_EDGE: u32 = 320;
+
+/// Laplacian variance, in 8-bit luma units, that scores a sharpness of 0.5.
+pub const SHARPNESS_REFERENCE_VARIANCE: f32 = 100.0;
+
+/// Sharpness scores below this are likely blurred.
+pub const LIKELY_BLURRY_BELOW: f32 = 0.25;
+
+/// Exposure scores below this are likely badly exposed.
+pub const LIKELY_BADLY_EXPOSED_BELOW: f32 = 0.4;
+
+/// Luma at or above this counts as a clipped highlight.
+const HIGHLIGHT_CLIP: f32 = 250.0;
+
+/// Luma at or below this counts as a clipped shadow.
+const SHADOW_CLIP: f32 = 5.0;
+
+/// Mean luma (0.0 to 1.0) inside this range gets the full exposure score.
+const GOOD_MEAN_LOW: f32 = 0.2;
+const GOOD_MEAN_HIGH: f32 = 0.8;
+
+/// Share of clipped pixels tolerated before the exposure score drops, and
+/// the share at which it reaches zero.
+const CLIP_TOLERANCE: f32 = 0.02;
+const CLIP_LIMIT: f32 = 0.3;
+
+/// Sharpness and exposure measurements from [`assess_image_quality`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct QualityReport {
+    /// 0.0 (blurred) to 1.0 (sharp)
+    pub sharpness_score: f32,
+    /// 0.0 (badly exposed) to 1.0 (well exposed)
+    pub exposure_score: f32,
+    /// Share of pixels with luma at or above 250
+    pub clipped_highlight_fraction: f32,
+    /// Share of pixels with luma at or below 5
+    pub clipped_shadow_fraction: f32,
+    /// Mean luma, 0.0 to 1.0
+    pub mean_luma: f32,
+}
+
+impl QualityReport {
+    /// Whether the frame is likely blurred or badly exposed, by
+    /// [`LIKELY_BLURRY_BELOW`] and [`LIKELY_BADLY_EXPOSED_BELOW`].
+    pub fn is_likely_bad(&self) -> bool {
+        self.sharpness_score < LIKELY_BLURRY_BELOW
+            || self.exposure_score < LIKELY_BADLY_EXPOSED_BELOW
+    }
+}
+
+/// Score the sharpness and exposure of an RGB image.
+///
+/// See the [module docs](self) for how the scores are computed. The result
+/// depends only on the pixels, so the same image always scores the same.
+///
+/// # Errors
+///
+/// Returns an error if the image is empty or the buffer does not match its
+/// dimensions.
+///
+/// # Example
+///
+/// ```ignore
+/// use literoom_core::analysis::assess_image_quality;
+///
+/// let report = assess_image_quality(&preview.pixels, preview.width, preview.height)?;
+/// if report.is_likely_bad() {
+///     flag_for_review(id);
+/// }
+/// ```
+pub fn assess_image_quality(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<QualityReport, ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    let _perf = perf::scope("assess_image_quality");
+    perf::record_dimensions(width, height);
+    perf::record_pixels(width as u64 * height as u64);
+
+    let factor = width.max(height).div_ceil(QUALITY_MAX_EDGE) as usize;
+    let small_width = (width as usize).div_ceil(factor);
+    let small_height = (height as usize).div_ceil(factor);
+    let mut sums = vec![0.0f32; small_width * small_height];
+    let mut counts = vec![0u32; small_width * small_height];
+
+    let mut total = 0.0f64;
+    let mut highlights = 0usize;
+    let mut shadows = 0usize;
+    for (y, row) in pixels.chunks_exact(width as usize * 3).enumerate() {
+        let small_row = (y / factor) * small_width;
+        for (x, px) in row.chunks_exact(3).enumerate() {
+            let luma = calculate_luminance(px[0] as f32, px[1] as f32, px[2] as f32);
+            total += luma as f64;
+            if luma >= HIGHLIGHT_CLIP {
+                highlights += 1;
+            } else if luma <= SHADOW_CLIP {
+                shadows += 1;
+            }
+            let i = small_row + x / factor;
+            sums[i] += luma;
+            counts[i] += 1;
+        }
+    }
+    for (sum, count) in sums.iter_mut().zip(&counts) {
+        *sum /= *count as f32;
+    }
+
+    let count = (width as u64 * height as u64) as f32;
+    let mean_luma = (total / count as f64 / 255.0) as f32;
+    let clipped_highlight_fraction = highlights as f32 / count;
+    let clipped_shadow_fraction = shadows as f32 / count;
+
+    let variance = laplacian_variance(&sums, small_width, small_height);
+    let sharpness_score = variance / (variance + SHARPNESS_REFERENCE_VARIANCE);
+
+    let mean_score = if mean_luma < GOOD_MEAN_LOW {
+        mean_luma / GOOD_MEAN_LOW
+    } else if mean_luma > GOOD_MEAN_HIGH {
+        (1.0 - mean_luma) / (1.0 - GOOD_MEAN_HIGH)
+    } else {
+        1.0
+    };
+    let clipped = clipped_highlight_fraction + clipped_shadow_fraction;
+    let clip_penalty = ((clipped - CLIP_TOLERANCE) / (CLIP_LIMIT - CLIP_TOLERANCE)).clamp(0.0, 1.0);
+    let exposure_score = (mean_score * (1.0 - clip_penalty)).clamp(0.0, 1.0);
+
+    Ok(QualityReport {
+        sharpness_score: sharpness_score.clamp(0.0, 1.0),
+        exposure_score,
+        clipped_highlight_fraction,
+        clipped_shadow_fraction,
+        mean_luma: mean_luma.clamp(0.0, 1.0),
+    })
+}
+
+/// Variance of the 4-neighbour Laplacian over the interior of `luma`.
+///
+/// Images too small to have an interior have a variance of zero.
+fn laplacian_variance(luma: &[f32], width: usize, height: usize) -> f32 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0f64;
+    let mut sum_sq = 0.0f64;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let i = y * width + x;
+            let laplacian =
+                4.0 * luma[i] - luma[i - 1] - luma[i + 1] - luma[i - width] - luma[i + width];
+            sum += laplacian as f64;
+            sum_sq += (laplacian * laplacian) as f64;
+        }
+    }
+    let n = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / n;
+    (sum_sq / n - mean * mean).max(0.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::DecodedImage;
+    use crate::testing::{checkerboard, noise};
+
+    fn assess(image: &DecodedImage) -> QualityReport {
+        assess_image_quality(&image.pixels, image.width, image.height).unwrap()
+    }
+
+    /// Box blur with the given radius, clamped at the edges.
+    fn box_blur(image: &DecodedImage, radius: i64) -> DecodedImage {
+        let (width, height) = (image.width as i64, image.height as i64);
+        let mut pixels = Vec::with_capacity(image.pixels.len());
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 3];
+                let mut count = 0;
+                for sy in (y - radius).max(0)..=(y + radius).min(height - 1) {
+                    for sx in (x - radius).max(0)..=(x + radius).min(width - 1) {
+                        let i = ((sy * width + sx) * 3) as usize;
+                        for (c, total) in sum.iter_mut().enumerate() {
+                            *total += image.pixels[i + c] as u32;
+                        }
+                        count += 1;
+                    }
+                }
+                pixels.extend(sum.map(|total| (total / count) as u8));
+            }
+        }
+        DecodedImage::new(image.width, image.height, pixels)
+    }
+
+    fn in_unit_range(report: &QualityReport) -> bool {
+        [
+            report.sharpness_score,
+            report.exposure_score,
+            report.clipped_highlight_fraction,
+            report.clipped_shadow_fraction,
+            report.mean_luma,
+        ]
+        .iter()
+        .all(|v| (0.0..=1.0).contains(v))
+    }
+
+    #[test]
+    fn test_blur_lowers_sharpness() {
+        let sharp = checkerboard(96, 64, 6);
+        let blurred = box_blur(&sharp, 4);
+
+        let sharp = assess(&sharp);
+        let blurred = assess(&blurred);
+        assert!(sharp.sharpness_score > 0.9, "{sharp:?}");
+        assert!(blurred.sharpness_score < sharp.sharpness_score);
+    }
+
+    #[test]
+    fn test_pushed_exposure_scores_lower() {
+        let source = noise(80, 60, 9);
+        let mut pushed = source.clone();
+        for v in &mut pushed.pixels {
+            *v = (*v as u32 * 8).min(255) as u8;
+        }
+
+        let normal = assess(&source);
+        let pushed = assess(&pushed);
+        assert!(normal.exposure_score > 0.8, "{normal:?}");
+        assert!(pushed.exposure_score < LIKELY_BADLY_EXPOSED_BELOW);
+        assert!(pushed.clipped_highlight_fraction > 0.5);
+        assert!(pushed.is_likely_bad());
+    }
+
+    #[test]
+    fn test_flat_frames_are_flagged() {
+        let black = DecodedImage::new(40, 30, vec![0u8; 40 * 30 * 3]);
+        let report = assess(&black);
+        assert_eq!(report.sharpness_score, 0.0);
+        assert_eq!(report.exposure_score, 0.0);
+        assert_eq!(report.clipped_shadow_fraction, 1.0);
+        assert!(report.is_likely_bad());
+    }
+
+    #[test]
+    fn test_large_images_are_downsized() {
+        let large = checkerboard(1300, 700, 20);
+        let report = assess(&large);
+        assert!(report.sharpness_score > 0.5, "{report:?}");
+        assert!((report.mean_luma - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_scores_are_deterministic_and_in_range() {
+        for image in [noise(33, 17, 1), checkerboard(2, 2, 1), noise(1, 1, 2)] {
+            let first = assess(&image);
+            assert_eq!(first, assess(&image));
+            assert!(in_unit_range(&first), "{first:?}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_buffer_is_rejected() {
+        assert!(assess_image_quality(&[0u8; 10], 4, 4).is_err());
+        assert!(assess_image_quality(&[], 0, 0).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! Camera-matched base tone curves.
+//!
+//! Embedded RAW previews have the camera's rendering baked in, while a
+//! linear RAW decode looks flat next to them. A base curve is applied before
+//! the user's adjustments to bring the decode close to the preview, so the
+//! swap from preview to full decode is not jarring.
+//!
+//! The edit state records the base curve explicitly (see
+//! [`crate::edit::EditState::base_curve`]), so it can be shown in the UI and
+//! switched off. [`get_base_curve_for_camera`] picks the default for a
+//! camera from its EXIF make and model.
+//!
+//! When a camera has no good match, [`estimate_baked_curve`] measures the
+//! look baked into its preview against a flat decode of the same shot.
+//! Rendering the flat decode through the estimated curve before the user's
+//! adjustments reproduces the preview the edits were judged on, and
+//! [`invert_baked_curve`] takes preview pixels back to the flat space.
+
+use crate::decode::{resize, DecodeError, DecodedImage, FilterType};
+use crate::luminance::calculate_luminance_u8;
+use crate::{CurvePoint, ToneCurve};
+use serde::{Deserialize, Serialize};
+
+/// Longest edge the two renderings are compared at.
+const BAKED_CURVE_MAX_EDGE: u32 = 512;
+
+/// Control points in a curve from [`estimate_baked_curve`], evenly spaced.
+const BAKED_CURVE_POINTS: usize = 17;
+
+/// A named base tone curve.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BaseCurve {
+    /// Gentle contrast for cameras without a specific match
+    #[default]
+    Neutral,
+    /// Approximation of the "Standard" picture profile of common bodies
+    CameraStandardApprox,
+    /// No curve; the linear decode as is
+    Flat,
+}
+
+/// Cameras with a specific base curve: lowercase make and model substrings.
+///
+/// An empty model matches every model of the make. The first match wins.
+const CAMERA_CURVES: &[(&str, &str, BaseCurve)] = &[
+    ("sony", "ilce", BaseCurve::CameraStandardApprox),
+    ("sony", "dsc-rx", BaseCurve::CameraStandardApprox),
+    ("canon", "", BaseCurve::CameraStandardApprox),
+    ("nikon", "", BaseCurve::CameraStandardApprox),
+    ("fujifilm", "", BaseCurve::CameraStandardApprox),
+];
+
+impl BaseCurve {
+    /// Control points of the curve.
+    pub fn curve(self) -> ToneCurve {
+        let points: &[(f32, f32)] = match self {
+            BaseCurve::Neutral => &[(0.0, 0.0), (0.25, 0.23), (0.75, 0.78), (1.0, 1.0)],
+            BaseCurve::CameraStandardApprox => &[
+                (0.0, 0.0),
+                (0.1, 0.07),
+                (0.25, 0.22),
+                (0.5, 0.55),
+                (0.75, 0.83),
+                (1.0, 1.0),
+            ],
+            BaseCurve::Flat => &[(0.0, 0.0), (1.0, 1.0)],
+        };
+        ToneCurve {
+            points: points.iter().map(|&(x, y)| CurvePoint::new(x, y)).collect(),
+        }
+    }
+}
+
+/// Base curve for a camera, from its EXIF make and model.
+///
+/// Matching ignores case and accepts the make and model anywhere in the
+/// strings, so "SONY" with "ILCE-6600" and "Sony Corporation" with
+/// "ilce-7m3" both find the Sony curve. Unknown cameras get
+/// [`BaseCurve::Neutral`].
+pub fn get_base_curve_for_camera(make: &str, model: &str) -> BaseCurve {
+    let make = make.to_lowercase();
+    let model = model.to_lowercase();
+    CAMERA_CURVES
+        .iter()
+        .find(|(m, prefix, _)| make.contains(m) && model.contains(prefix))
+        .map_or(BaseCurve::Neutral, |&(_, _, curve)| curve)
+}
+
+/// Estimate the tone curve baked into an embedded preview.
+///
+/// `preview` and `flat` are two renderings of the same shot: the camera's
+/// embedded preview and a flat decode of the RAW data. Both are resized to
+/// a common size, then the luminance histogram of `flat` is matched to that
+/// of `preview`. The value at each quantile of `flat` maps to the value at
+/// the same quantile of `preview`, so the curve does not depend on the two
+/// lining up pixel for pixel.
+///
+/// # Returns
+///
+/// A monotonic curve with evenly spaced control points, mapping flat
+/// luminance to preview luminance. Flat values outside the range the flat
+/// rendering covers follow the nearest covered value.
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidImage` if either image is empty or its
+/// buffer does not match its dimensions.
+pub fn estimate_baked_curve(
+    preview: &DecodedImage,
+    flat: &DecodedImage,
+) -> Result<ToneCurve, DecodeError> {
+    preview.validate()?;
+    flat.validate()?;
+
+    // Compare at the smaller of the two sizes, capped for speed
+    let (mut width, mut height) = (
+        preview.width.min(flat.width),
+        preview.height.min(flat.height),
+    );
+    let longest = width.max(height);
+    if longest > BAKED_CURVE_MAX_EDGE {
+        let scale = BAKED_CURVE_MAX_EDGE as f64 / longest as f64;
+        width = ((width as f64 * scale).round() as u32).max(1);
+        height = ((height as f64 * scale).round() as u32).max(1);
+    }
+    let preview_cdf = LumaCdf::new(&fit(preview, width, height)?);
+    let flat_cdf = LumaCdf::new(&fit(flat, width, height)?);
+
+    let mut previous = 0.0f32;
+    let points = (0..BAKED_CURVE_POINTS)
+        .map(|i| {
+            let x = i as f32 / (BAKED_CURVE_POINTS - 1) as f32;
+            let value = preview_cdf.quantile(flat_cdf.rank(x * 255.0)) / 255.0;
+            previous = previous.max(value.clamp(0.0, 1.0));
+            CurvePoint::new(x, previous)
+        })
+        .collect();
+    Ok(ToneCurve { points })
+}
+
+/// Invert a monotonic curve, such as one from [`estimate_baked_curve`].
+///
+/// The inverse maps preview values back to flat values, so adjustments
+/// can be applied in the flat space and the baked curve applied after
+/// them. A flat stretch of `curve` has no single inverse, so its output
+/// value maps to the middle of the stretch. A curve that is flat
+/// everywhere gives the identity.
+pub fn invert_baked_curve(curve: &ToneCurve) -> ToneCurve {
+    // (output value, first input, last input) for each run of equal outputs
+    let mut runs: Vec<(f32, f32, f32)> = Vec::with_capacity(curve.points.len());
+    for point in &curve.points {
+        match runs.last_mut() {
+            Some((y, _, last)) if point.y <= *y => *last = point.x,
+            _ => runs.push((point.y, point.x, point.x)),
+        }
+    }
+    if runs.len() < 2 {
+        return ToneCurve::default();
+    }
+    ToneCurve {
+        points: runs
+            .into_iter()
+            .map(|(y, first, last)| CurvePoint::new(y, (first + last) / 2.0))
+            .collect(),
+    }
+}
+
+/// Resize `image` to `width x height` unless it already is that size.
+fn fit(image: &DecodedImage, width: u32, height: u32) -> Result<DecodedImage, DecodeError> {
+    if image.width == width && image.height == height {
+        return Ok(image.clone());
+    }
+    resize(image, width, height, FilterType::Bilinear)
+}
+
+/// Cumulative luminance distribution of an image, treating each 8-bit
+/// level as spread evenly over `level - 0.5..level + 0.5`.
+struct LumaCdf {
+    /// Pixels at or below each level
+    cumulative: [u64; 256],
+}
+
+impl LumaCdf {
+    fn new(image: &DecodedImage) -> Self {
+        let mut counts = [0u64; 256];
+        for p in image.pixels.chunks_exact(3) {
+            counts[calculate_luminance_u8(p[0], p[1], p[2]) as usize] += 1;
+        }
+        let mut cumulative = [0u64; 256];
+        let mut total = 0;
+        for (level, count) in counts.iter().enumerate() {
+            total += count;
+            cumulative[level] = total;
+        }
+        Self { cumulative }
+    }
+
+    fn total(&self) -> f64 {
+        self.cumulative[255] as f64
+    }
+
+    fn count(&self, level: usize) -> f64 {
+        let below = if level == 0 {
+            0
+        } else {
+            self.cumulative[level - 1]
+        };
+        (self.cumulative[level] - below) as f64
+    }
+
+    /// Fraction of pixels below `value` (0.0 to 255.0).
+    fn rank(&self, value: f32) -> f64 {
+        let level = (value.round() as usize).min(255);
+        let below = if level == 0 {
+            0
+        } else {
+            self.cumulative[level - 1]
+        };
+        let within = (value as f64 - level as f64 + 0.5).clamp(0.0, 1.0);
+        (below as f64 + within * self.count(level)) / self.total()
+    }
+
+    /// Value below which `fraction` of the pixels lie.
+    fn quantile(&self, fraction: f64) -> f32 {
+        // A zero target still lands on the first level with any pixels
+        let target = (fraction.clamp(0.0, 1.0) * self.total()).max(f64::MIN_POSITIVE);
+        let level = self
+            .cumulative
+            .partition_point(|&c| (c as f64) < target)
+            .min(255);
+        let below = if level == 0 {
+            0
+        } else {
+            self.cumulative[level - 1]
+        };
+        let within = (target - below as f64) / self.count(level).max(1.0);
+        (level as f64 - 0.5 + within.clamp(0.0, 1.0)) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{apply_tone_curve, evaluate_curve, ToneCurveLut};
+    use crate::testing::{gradient, GradientDirection};
+
+    #[test]
+    fn test_lookup_ignores_case_and_surrounding_text() {
+        for (make, model) in [
+            ("SONY", "ILCE-6600"),
+            ("sony", "ilce-6600"),
+            ("Sony Corporation", "ILCE-7M3"),
+            ("Canon", "Canon EOS R5"),
+        ] {
+            assert_eq!(
+                get_base_curve_for_camera(make, model),
+                BaseCurve::CameraStandardApprox,
+                "{} {}",
+                make,
+                model
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_camera_is_neutral() {
+        assert_eq!(
+            get_base_curve_for_camera("Hasselblad", "X2D"),
+            BaseCurve::Neutral
+        );
+        assert_eq!(get_base_curve_for_camera("", ""), BaseCurve::Neutral);
+        // A known make with an unmatched model falls back too
+        assert_eq!(
+            get_base_curve_for_camera("SONY", "XPERIA 1 V"),
+            BaseCurve::Neutral
+        );
+    }
+
+    /// A flat rendering, and a preview of it with `curve` baked in.
+    fn renderings(curve: &ToneCurve) -> (DecodedImage, DecodedImage) {
+        let flat = gradient(256, 96, GradientDirection::Horizontal);
+        let mut preview = flat.clone();
+        apply_tone_curve(&mut preview.pixels, &ToneCurveLut::from_curve(curve));
+        (preview, flat)
+    }
+
+    fn assert_curve_close(estimated: &ToneCurve, known: &ToneCurve, tolerance: f32) {
+        assert_eq!(estimated.points.len(), BAKED_CURVE_POINTS);
+        for point in &estimated.points {
+            let expected = evaluate_curve(known, point.x);
+            assert!(
+                (point.y - expected).abs() <= tolerance,
+                "at {}: estimated {} vs {}",
+                point.x,
+                point.y,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimate_recovers_baked_s_curve() {
+        let known = BaseCurve::CameraStandardApprox.curve();
+        let (preview, flat) = renderings(&known);
+        let estimated = estimate_baked_curve(&preview, &flat).unwrap();
+
+        assert_curve_close(&estimated, &known, 0.02);
+        assert!(estimated
+            .points
+            .windows(2)
+            .all(|w| w[0].x < w[1].x && w[0].y <= w[1].y));
+    }
+
+    #[test]
+    fn test_estimate_of_same_rendering_is_identity() {
+        let image = gradient(120, 90, GradientDirection::Diagonal);
+        let estimated = estimate_baked_curve(&image, &image).unwrap();
+        assert_curve_close(&estimated, &ToneCurve::default(), 0.01);
+    }
+
+    #[test]
+    fn test_estimate_resizes_mismatched_renderings() {
+        let known = BaseCurve::Neutral.curve();
+        let (preview, flat) = renderings(&known);
+        // Embedded previews are usually smaller than the RAW decode
+        let preview = resize(&preview, 128, 48, FilterType::Bilinear).unwrap();
+
+        let estimated = estimate_baked_curve(&preview, &flat).unwrap();
+        assert_curve_close(&estimated, &known, 0.03);
+
+        let empty = DecodedImage::new(0, 0, vec![]);
+        assert!(matches!(
+            estimate_baked_curve(&empty, &flat),
+            Err(DecodeError::InvalidImage(_))
+        ));
+    }
+
+    #[test]
+    fn test_inverted_curve_round_trips() {
+        let curve = BaseCurve::CameraStandardApprox.curve();
+        let inverse = invert_baked_curve(&curve);
+        for i in 0..=20 {
+            let x = i as f32 / 20.0;
+            let back = evaluate_curve(&inverse, evaluate_curve(&curve, x));
+            assert!((back - x).abs() < 0.02, "{} -> {}", x, back);
+        }
+
+        // Flat stretches map to their middle
+        let clipped = ToneCurve {
+            points: vec![
+                CurvePoint::new(0.0, 0.0),
+                CurvePoint::new(0.8, 1.0),
+                CurvePoint::new(1.0, 1.0),
+            ],
+        };
+        let inverse = invert_baked_curve(&clipped);
+        assert_eq!(inverse.points.last(), Some(&CurvePoint::new(1.0, 0.9)));
+        assert!(invert_baked_curve(&ToneCurve {
+            points: vec![CurvePoint::new(0.0, 0.5), CurvePoint::new(1.0, 0.5)],
+        })
+        .is_linear());
+    }
+
+    #[test]
+    fn test_curves_are_monotonic_and_flat_is_identity() {
+        assert!(BaseCurve::Flat.curve().is_linear());
+        for base in [BaseCurve::Neutral, BaseCurve::CameraStandardApprox] {
+            let curve = base.curve();
+            assert!(!curve.is_linear());
+            assert!(curve
+                .points
+                .windows(2)
+                .all(|w| w[0].x < w[1].x && w[0].y <= w[1].y));
+        }
+    }
+}
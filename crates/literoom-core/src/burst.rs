@@ -0,0 +1,84 @@
+//! Grouping photos into bursts by capture time.
+//!
+//! Culling a burst means comparing shots taken fractions of a second
+//! apart. [`group_by_capture_time`] clusters capture timestamps (see
+//! [`ImageMetadata::capture_timestamp_ms`](crate::decode::ImageMetadata))
+//! so the grid can stack each burst.
+
+/// Cluster photos whose capture times are at most `max_gap_ms` apart.
+///
+/// Timestamps are sorted first, so the input can be in any order; a photo
+/// joins the current group when it follows the previous one by no more than
+/// `max_gap_ms`. Each group lists indexes into `timestamps` in capture
+/// order, and groups are ordered by their first capture. Every index
+/// appears in exactly one group, so photos without a neighbour form
+/// single-photo groups. Equal timestamps keep their input order.
+///
+/// # Example
+///
+/// ```
+/// use literoom_core::burst::group_by_capture_time;
+///
+/// let timestamps = [10_000, 10_120, 60_000, 10_250];
+/// assert_eq!(
+///     group_by_capture_time(&timestamps, 200),
+///     vec![vec![0, 1, 3], vec![2]]
+/// );
+/// ```
+pub fn group_by_capture_time(timestamps: &[i64], max_gap_ms: u64) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..timestamps.len()).collect();
+    order.sort_by_key(|&i| timestamps[i]);
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut previous = None;
+    for i in order {
+        let time = timestamps[i];
+        match (groups.last_mut(), previous) {
+            (Some(group), Some(prev)) if time.abs_diff(prev) <= max_gap_ms => group.push(i),
+            _ => groups.push(vec![i]),
+        }
+        previous = Some(time);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_bursts_and_singletons() {
+        // Two bursts at 8 fps, a lone shot, and a third burst
+        let timestamps = [0, 125, 250, 5_000, 9_000, 9_100, 375];
+        assert_eq!(
+            group_by_capture_time(&timestamps, 200),
+            vec![vec![0, 1, 2, 6], vec![3], vec![4, 5]]
+        );
+    }
+
+    #[test]
+    fn test_gap_boundary_is_inclusive() {
+        assert_eq!(
+            group_by_capture_time(&[0, 200, 401], 200),
+            vec![vec![0, 1], vec![2]]
+        );
+        assert_eq!(group_by_capture_time(&[0, 200], 0), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn test_equal_timestamps_keep_input_order() {
+        assert_eq!(
+            group_by_capture_time(&[7, 3, 7, 3], 0),
+            vec![vec![1, 3], vec![0, 2]]
+        );
+    }
+
+    #[test]
+    fn test_empty_and_extreme_values() {
+        assert!(group_by_capture_time(&[], 1000).is_empty());
+        assert_eq!(
+            group_by_capture_time(&[i64::MAX, i64::MIN], u64::MAX),
+            vec![vec![1, 0]]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Color space conversions.
+//!
+//! The sRGB transfer functions, and [Oklab], a perceptual color space where
+//! equal steps look like equal changes and hue stays put when chroma
+//! changes. Scaling chroma in Oklch (Oklab in polar form) saturates blues
+//! without drifting them toward purple, which scaling the distance from
+//! gray in RGB does not.
+//!
+//! The matrices are the published ones for linear sRGB with a D65 white,
+//! so white maps to `L = 1, a = b = 0`.
+//!
+//! [Oklab]: https://bottosson.github.io/posts/oklab/
+
+use serde::{Deserialize, Serialize};
+
+/// Color model the saturation and vibrance adjustments work in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorModel {
+    /// Scale each channel's distance from the pixel's luminance, as earlier
+    /// versions did. Fast, but hues drift as saturation rises.
+    #[default]
+    Legacy,
+    /// Scale Oklch chroma at constant lightness and hue, reducing it only as
+    /// far as needed to stay inside sRGB.
+    Oklab,
+}
+
+/// A color in Oklab: lightness `l` (0.0 black to 1.0 white) and the
+/// green-red `a` and blue-yellow `b` axes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+/// A color in Oklch: Oklab lightness, chroma (distance from gray) and hue
+/// angle in degrees (0.0 to 360.0).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oklch {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+}
+
+impl Oklab {
+    /// Polar form of this color. Grays get a hue of 0.0.
+    pub fn to_oklch(self) -> Oklch {
+        Oklch {
+            l: self.l,
+            c: self.a.hypot(self.b),
+            h: self.b.atan2(self.a).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
+
+impl Oklch {
+    /// Cartesian form of this color.
+    pub fn to_oklab(self) -> Oklab {
+        let (sin, cos) = self.h.to_radians().sin_cos();
+        Oklab {
+            l: self.l,
+            a: self.c * cos,
+            b: self.c * sin,
+        }
+    }
+}
+
+/// sRGB transfer function: encoded to linear.
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGB transfer function: linear to encoded.
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Convert linear sRGB to Oklab.
+pub fn linear_srgb_to_oklab([r, g, b]: [f32; 3]) -> Oklab {
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    Oklab {
+        l: 0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        a: 1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        b: 0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    }
+}
+
+/// Convert Oklab to linear sRGB. Colors outside sRGB give values outside
+/// 0.0-1.0.
+pub fn oklab_to_linear_srgb(lab: Oklab) -> [f32; 3] {
+    let l = (lab.l + 0.396_337_78 * lab.a + 0.215_803_76 * lab.b).powi(3);
+    let m = (lab.l - 0.105_561_346 * lab.a - 0.063_854_17 * lab.b).powi(3);
+    let s = (lab.l - 0.089_484_18 * lab.a - 1.291_485_5 * lab.b).powi(3);
+    [
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+}
+
+/// Convert gamma-encoded sRGB (0.0 to 1.0) to Oklab.
+pub fn srgb_to_oklab([r, g, b]: [f32; 3]) -> Oklab {
+    linear_srgb_to_oklab([srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b)])
+}
+
+/// Convert Oklab to gamma-encoded sRGB. Colors outside sRGB give values
+/// outside 0.0-1.0.
+pub fn oklab_to_srgb(lab: Oklab) -> [f32; 3] {
+    oklab_to_linear_srgb(lab).map(linear_to_srgb)
+}
+
+/// Scale the Oklch chroma of a gamma-encoded sRGB color by `factor`.
+///
+/// Lightness and hue are kept. If the scaled color falls outside sRGB, the
+/// chroma is reduced until it fits, so clipping never changes the hue; a
+/// color that was already outside sRGB is scaled without this limit. Grays
+/// are returned unchanged.
+pub fn scale_chroma(rgb: [f32; 3], factor: f32) -> [f32; 3] {
+    let lch = srgb_to_oklab(rgb).to_oklch();
+    if lch.c < ACHROMATIC_CHROMA || factor == 1.0 {
+        return rgb;
+    }
+    let target = Oklch {
+        c: lch.c * factor.max(0.0),
+        ..lch
+    };
+    let scaled = oklab_to_srgb(target.to_oklab());
+    if target.c <= lch.c || in_gamut(scaled) || !in_gamut(rgb) {
+        return scaled;
+    }
+    fit_chroma(target, lch.c)
+}
+
+/// Convert Oklch to gamma-encoded sRGB, reducing chroma at constant
+/// lightness and hue as far as needed to stay inside sRGB.
+///
+/// Lightness is clamped to 0.0-1.0 first, so there is always a gray to fall
+/// back to.
+pub fn oklch_to_srgb_in_gamut(lch: Oklch) -> [f32; 3] {
+    let lch = Oklch {
+        l: lch.l.clamp(0.0, 1.0),
+        c: lch.c.max(0.0),
+        ..lch
+    };
+    let rgb = oklab_to_srgb(lch.to_oklab());
+    if in_gamut(rgb) {
+        return rgb;
+    }
+    fit_chroma(lch, 0.0)
+}
+
+/// Largest chroma between `inside` (known to fit) and `lch.c` that still
+/// fits in sRGB, converted to sRGB.
+fn fit_chroma(lch: Oklch, inside: f32) -> [f32; 3] {
+    let with_chroma = |c: f32| oklab_to_srgb(Oklch { c, ..lch }.to_oklab());
+    let (mut inside, mut outside) = (inside, lch.c);
+    for _ in 0..GAMUT_SEARCH_STEPS {
+        let mid = 0.5 * (inside + outside);
+        if in_gamut(with_chroma(mid)) {
+            inside = mid;
+        } else {
+            outside = mid;
+        }
+    }
+    with_chroma(inside)
+}
+
+/// Chroma below which a color is treated as gray. Float error gives exact
+/// grays a chroma around 1e-7; the least saturated 8-bit colors are near 1e-3.
+const ACHROMATIC_CHROMA: f32 = 1e-5;
+
+/// Bisection steps when pulling a color back into sRGB; 16 steps resolve
+/// chroma far below one 8-bit code value.
+const GAMUT_SEARCH_STEPS: u32 = 16;
+
+/// Tolerance for float error at the edges of the sRGB cube.
+const GAMUT_EPSILON: f32 = 1e-4;
+
+fn in_gamut(rgb: [f32; 3]) -> bool {
+    rgb.iter()
+        .all(|v| (-GAMUT_EPSILON..=1.0 + GAMUT_EPSILON).contains(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_u8(v: f32) -> i32 {
+        (v.clamp(0.0, 1.0) * 255.0).round() as i32
+    }
+
+    #[test]
+    fn test_round_trip_within_one_code_value() {
+        let samples = (0..=255).step_by(5).chain([1, 254, 255]);
+        let values: Vec<i32> = samples.collect();
+        for &r in &values {
+            for &g in &values {
+                for &b in &values {
+                    let rgb = [r, g, b].map(|v| v as f32 / 255.0);
+                    let back = oklab_to_srgb(srgb_to_oklab(rgb));
+                    for (original, restored) in [r, g, b].iter().zip(back) {
+                        assert!(
+                            (original - to_u8(restored)).abs() <= 1,
+                            "{:?} came back as {:?}",
+                            (r, g, b),
+                            back
+                        );
+                    }
+                }
+            }
+        }
+        for v in 0..=255 {
+            for rgb in [[v, 0, 0], [0, v, 0], [0, 0, v], [v, v, v]] {
+                let back = oklab_to_srgb(srgb_to_oklab(rgb.map(|c| c as f32 / 255.0)));
+                for (original, restored) in rgb.iter().zip(back) {
+                    assert!((original - to_u8(restored)).abs() <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_white_and_gray_have_no_chroma() {
+        let white = srgb_to_oklab([1.0; 3]);
+        assert!((white.l - 1.0).abs() < 1e-3);
+        assert!(white.a.abs() < 1e-3 && white.b.abs() < 1e-3);
+
+        let gray = srgb_to_oklab([0.5; 3]).to_oklch();
+        assert!(gray.c < 1e-3);
+    }
+
+    #[test]
+    fn test_oklch_round_trip() {
+        let lab = srgb_to_oklab([0.2, 0.4, 0.8]);
+        let lch = lab.to_oklch();
+        assert!((0.0..360.0).contains(&lch.h));
+        let back = lch.to_oklab();
+        assert!((back.a - lab.a).abs() < 1e-6 && (back.b - lab.b).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_scale_chroma_keeps_hue_inside_gamut() {
+        let blue = [0.15, 0.3, 0.75];
+        let before = srgb_to_oklab(blue).to_oklch();
+        let boosted = scale_chroma(blue, 3.0);
+        assert!(in_gamut(boosted));
+
+        let after = srgb_to_oklab(boosted).to_oklch();
+        assert!(after.c > before.c);
+        assert!((after.h - before.h).abs() < 0.5);
+        assert!((after.l - before.l).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_oklch_in_gamut_keeps_hue() {
+        let lch = srgb_to_oklab([0.1, 0.8, 0.2]).to_oklch();
+        let rgb = oklch_to_srgb_in_gamut(Oklch { c: 1.0, ..lch });
+        assert!(in_gamut(rgb));
+        let fitted = srgb_to_oklab(rgb).to_oklch();
+        assert!((fitted.h - lch.h).abs() < 0.5);
+        assert!(fitted.c >= lch.c - 1e-3);
+    }
+
+    #[test]
+    fn test_scale_chroma_to_zero_is_gray() {
+        let gray = scale_chroma([0.8, 0.4, 0.1], 0.0);
+        assert!((gray[0] - gray[1]).abs() < 1e-4 && (gray[1] - gray[2]).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,391 @@
+//! Reference-image color matching.
+//!
+//! Matching transfers the tonal and color distribution of a reference photo
+//! to a target, so a series shot under changing light can share one look.
+//! Both images are converted to YCbCr (BT.709 weights, full range) and each
+//! component of the target is histogram-matched to the reference: a value
+//! at some cumulative fraction of the target maps to the value at the same
+//! fraction of the reference. Working on luma and the two color differences
+//! instead of R, G and B keeps the channels from drifting apart, which would
+//! show up as hue shifts.
+//!
+//! The derived lookup tables ([`ColorMatchLuts`]) are serializable, so a
+//! match can be stored with an edit and reapplied without the reference.
+
+use crate::decode::DecodedImage;
+use crate::luminance::{LUMINANCE_B, LUMINANCE_G, LUMINANCE_R};
+use crate::perf;
+use crate::validate::ImageError;
+use serde::{Deserialize, Serialize};
+
+/// Scale from `B - Y` to Cb: `2 * (1 - LUMINANCE_B)`.
+const CB_SCALE: f32 = 2.0 * (1.0 - LUMINANCE_B);
+
+/// Scale from `R - Y` to Cr: `2 * (1 - LUMINANCE_R)`.
+const CR_SCALE: f32 = 2.0 * (1.0 - LUMINANCE_R);
+
+/// Offset of the color difference components, in 0-255 units.
+const CHROMA_OFFSET: f32 = 128.0;
+
+/// Which components [`match_colors`] matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMatchMode {
+    /// Match brightness and contrast only; colors keep their chroma.
+    LumaOnly,
+    /// Match luma and both color differences, transferring color casts too.
+    #[default]
+    LumaAndColor,
+}
+
+/// Histogram-matching lookup tables derived from a target and a reference.
+///
+/// Each table has 256 entries mapping a target component value (in 0-255
+/// units) to its matched value. In [`ColorMatchMode::LumaOnly`] the color
+/// tables are the identity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorMatchLuts {
+    /// Mode the tables were derived in
+    pub mode: ColorMatchMode,
+    /// Luma (Y) mapping
+    pub luma: Vec<u8>,
+    /// Blue difference (Cb) mapping
+    pub cb: Vec<u8>,
+    /// Red difference (Cr) mapping
+    pub cr: Vec<u8>,
+}
+
+impl ColorMatchLuts {
+    /// Derive the tables that match `target` to `reference`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageError` if either image is empty or its buffer does not
+    /// match its dimensions.
+    pub fn from_images(
+        target: &DecodedImage,
+        reference: &DecodedImage,
+        mode: ColorMatchMode,
+    ) -> Result<Self, ImageError> {
+        target.validate()?;
+        reference.validate()?;
+
+        let _perf = perf::scope("color_match_luts");
+        perf::record_pixels(target.pixel_count() as u64 + reference.pixel_count() as u64);
+
+        let target_hist = component_histograms(&target.pixels);
+        let reference_hist = component_histograms(&reference.pixels);
+        let matched = |c: usize| match_histograms(&target_hist[c], &reference_hist[c]);
+        let (cb, cr) = match mode {
+            ColorMatchMode::LumaOnly => (identity_lut(), identity_lut()),
+            ColorMatchMode::LumaAndColor => (matched(1), matched(2)),
+        };
+        Ok(Self {
+            mode,
+            luma: matched(0),
+            cb,
+            cr,
+        })
+    }
+
+    /// Check if the tables map every value onto itself.
+    pub fn is_identity(&self) -> bool {
+        let identity = identity_lut();
+        self.luma == identity && self.cb == identity && self.cr == identity
+    }
+
+    /// Apply the tables to RGB pixels in place.
+    ///
+    /// Each component moves by `strength` times its mapped change, so 0.0
+    /// leaves the pixels alone and 1.0 applies the full match. `strength`
+    /// is clamped to 0.0-1.0; NaN counts as 0.0. Missing table entries (from
+    /// a truncated saved table) leave their values unchanged.
+    pub fn apply(&self, pixels: &mut [u8], strength: f32) {
+        let strength = if strength.is_nan() {
+            0.0
+        } else {
+            strength.clamp(0.0, 1.0)
+        };
+        if strength == 0.0 {
+            return;
+        }
+
+        let _perf = perf::scope("apply_color_match");
+        perf::record_pixels((pixels.len() / 3) as u64);
+
+        // Change of each component per table entry, scaled by strength
+        let shift = |lut: &[u8]| -> [f32; 256] {
+            std::array::from_fn(|v| {
+                let mapped = lut.get(v).map_or(v as f32, |&m| m as f32);
+                (mapped - v as f32) * strength
+            })
+        };
+        let shifts = [shift(&self.luma), shift(&self.cb), shift(&self.cr)];
+
+        for pixel in pixels.chunks_exact_mut(3) {
+            let ycc = to_ycbcr(pixel);
+            let shifted: [f32; 3] =
+                std::array::from_fn(|c| ycc[c] + shifts[c][component_bin(ycc[c])]);
+            let rgb = from_ycbcr(shifted);
+            for (out, value) in pixel.iter_mut().zip(rgb) {
+                *out = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+/// Match the tones and colors of `target` to `reference`.
+///
+/// # Arguments
+///
+/// * `target` - Image to change
+/// * `reference` - Image whose look is transferred
+/// * `strength` - Blend between the original (0.0) and the full match (1.0)
+/// * `mode` - Match luma only, or luma and color
+///
+/// # Returns
+///
+/// The matched copy of `target` and the tables used, which can be saved
+/// and reapplied with [`ColorMatchLuts::apply`].
+///
+/// # Errors
+///
+/// Returns `ImageError` if either image is empty or its buffer does not
+/// match its dimensions.
+pub fn match_colors(
+    target: &DecodedImage,
+    reference: &DecodedImage,
+    strength: f32,
+    mode: ColorMatchMode,
+) -> Result<(DecodedImage, ColorMatchLuts), ImageError> {
+    let luts = ColorMatchLuts::from_images(target, reference, mode)?;
+    let mut output = target.clone();
+    luts.apply(&mut output.pixels, strength);
+    Ok((output, luts))
+}
+
+/// Table mapping every value onto itself.
+fn identity_lut() -> Vec<u8> {
+    (0..=255).collect()
+}
+
+/// Y, Cb and Cr of an RGB pixel, in 0-255 units.
+fn to_ycbcr(pixel: &[u8]) -> [f32; 3] {
+    let (r, g, b) = (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+    let y = LUMINANCE_R * r + LUMINANCE_G * g + LUMINANCE_B * b;
+    [
+        y,
+        (b - y) / CB_SCALE + CHROMA_OFFSET,
+        (r - y) / CR_SCALE + CHROMA_OFFSET,
+    ]
+}
+
+/// RGB from Y, Cb and Cr, in 0-255 units (not clamped).
+fn from_ycbcr([y, cb, cr]: [f32; 3]) -> [f32; 3] {
+    let r = y + (cr - CHROMA_OFFSET) * CR_SCALE;
+    let b = y + (cb - CHROMA_OFFSET) * CB_SCALE;
+    let g = (y - LUMINANCE_R * r - LUMINANCE_B * b) / LUMINANCE_G;
+    [r, g, b]
+}
+
+/// Histogram bin of a component value.
+fn component_bin(value: f32) -> usize {
+    value.round().clamp(0.0, 255.0) as usize
+}
+
+/// 256-bin histograms of Y, Cb and Cr.
+fn component_histograms(pixels: &[u8]) -> [[u32; 256]; 3] {
+    let mut hist = [[0u32; 256]; 3];
+    for pixel in pixels.chunks_exact(3) {
+        for (bins, value) in hist.iter_mut().zip(to_ycbcr(pixel)) {
+            bins[component_bin(value)] += 1;
+        }
+    }
+    hist
+}
+
+/// Map each source value to the smallest reference value whose cumulative
+/// fraction reaches the source value's.
+fn match_histograms(source: &[u32; 256], reference: &[u32; 256]) -> Vec<u8> {
+    let cdf = |bins: &[u32; 256]| -> [f64; 256] {
+        let total = bins.iter().map(|&n| n as u64).sum::<u64>().max(1) as f64;
+        let mut seen = 0u64;
+        std::array::from_fn(|v| {
+            seen += bins[v] as u64;
+            seen as f64 / total
+        })
+    };
+    let (source_cdf, reference_cdf) = (cdf(source), cdf(reference));
+
+    let mut lut = Vec::with_capacity(256);
+    let mut r = 0;
+    for &fraction in &source_cdf {
+        // The source CDF only grows, so the search resumes where it stopped
+        while r < 255 && reference_cdf[r] < fraction - 1e-9 {
+            r += 1;
+        }
+        lut.push(r as u8);
+    }
+    lut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::compute_histogram;
+    use crate::testing::{color_bars, noise};
+
+    fn median_luminance(image: &DecodedImage) -> u8 {
+        let hist = compute_histogram(&image.pixels, image.width, image.height).unwrap();
+        let total: u32 = hist.luminance.iter().sum();
+        let mut seen = 0;
+        for (v, &count) in hist.luminance.iter().enumerate() {
+            seen += count;
+            if seen * 2 >= total {
+                return v as u8;
+            }
+        }
+        255
+    }
+
+    /// `image` with every channel scaled by `factor`.
+    fn scaled(image: &DecodedImage, factor: f32) -> DecodedImage {
+        let pixels = image
+            .pixels
+            .iter()
+            .map(|&v| (v as f32 * factor).round().min(255.0) as u8)
+            .collect();
+        DecodedImage::new(image.width, image.height, pixels)
+    }
+
+    #[test]
+    fn test_ycbcr_round_trip() {
+        for pixel in noise(32, 32, 5).pixels.chunks(3) {
+            let rgb = from_ycbcr(to_ycbcr(pixel));
+            for (&v, out) in pixel.iter().zip(rgb) {
+                assert_eq!(out.round() as u8, v, "{:?}", pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn test_matching_to_itself_is_noop() {
+        for image in [noise(40, 30, 2), color_bars(48, 16)] {
+            for mode in [ColorMatchMode::LumaOnly, ColorMatchMode::LumaAndColor] {
+                for strength in [0.0, 0.5, 1.0] {
+                    let (matched, luts) = match_colors(&image, &image, strength, mode).unwrap();
+                    assert_eq!(matched.pixels, image.pixels, "{:?} at {}", mode, strength);
+                    assert_eq!(luts.mode, mode);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dark_image_moves_toward_bright_reference() {
+        let reference = noise(64, 48, 8);
+        let dark = scaled(&reference, 0.4);
+        let (matched, _) =
+            match_colors(&dark, &reference, 1.0, ColorMatchMode::LumaAndColor).unwrap();
+
+        let (before, after, goal) = (
+            median_luminance(&dark),
+            median_luminance(&matched),
+            median_luminance(&reference),
+        );
+        assert!(after > before, "{} -> {}", before, after);
+        assert!(after.abs_diff(goal) <= 4, "{} vs reference {}", after, goal);
+    }
+
+    #[test]
+    fn test_half_strength_lies_between_endpoints() {
+        let reference = noise(64, 48, 8);
+        let dark = scaled(&noise(64, 48, 9), 0.5);
+        for mode in [ColorMatchMode::LumaOnly, ColorMatchMode::LumaAndColor] {
+            let luts = ColorMatchLuts::from_images(&dark, &reference, mode).unwrap();
+            let at = |strength: f32| {
+                let mut pixels = dark.pixels.clone();
+                luts.apply(&mut pixels, strength);
+                pixels
+            };
+            let (none, half, full) = (at(0.0), at(0.5), at(1.0));
+            assert_eq!(none, dark.pixels);
+
+            for i in 0..none.len() {
+                let (lo, hi) = (none[i].min(full[i]), none[i].max(full[i]));
+                // One level of slack for rounding the intermediate color
+                assert!(
+                    half[i] + 1 >= lo && half[i] <= hi.saturating_add(1),
+                    "{:?}: {} not between {} and {}",
+                    mode,
+                    half[i],
+                    none[i],
+                    full[i]
+                );
+            }
+            let (a, b, c) = (
+                median_luminance(&DecodedImage::new(64, 48, none)),
+                median_luminance(&DecodedImage::new(64, 48, half)),
+                median_luminance(&DecodedImage::new(64, 48, full)),
+            );
+            assert!(a <= b && b <= c, "{:?}: medians {} {} {}", mode, a, b, c);
+        }
+    }
+
+    #[test]
+    fn test_luma_only_keeps_grays_neutral() {
+        let gray: Vec<u8> = (0..64u8).flat_map(|v| [v * 2; 3]).collect();
+        let target = DecodedImage::new(8, 8, gray);
+        // Warm, bright reference
+        let warm: Vec<u8> = (0..64u8).flat_map(|v| [150 + v, 120 + v, 90 + v]).collect();
+        let reference = DecodedImage::new(8, 8, warm);
+
+        let (matched, luts) =
+            match_colors(&target, &reference, 1.0, ColorMatchMode::LumaOnly).unwrap();
+        assert_eq!(luts.cb, identity_lut());
+        assert_eq!(luts.cr, identity_lut());
+        for p in matched.pixels.chunks(3) {
+            assert!(
+                p[0].abs_diff(p[1]) <= 1 && p[0].abs_diff(p[2]) <= 1,
+                "{:?}",
+                p
+            );
+        }
+
+        let (matched, _) =
+            match_colors(&target, &reference, 1.0, ColorMatchMode::LumaAndColor).unwrap();
+        let p = &matched.pixels[matched.pixels.len() / 2..][..3];
+        assert!(
+            p[0] > p[2],
+            "color mode should carry the warm cast: {:?}",
+            p
+        );
+    }
+
+    #[test]
+    fn test_truncated_tables_leave_values_alone() {
+        let luts = ColorMatchLuts {
+            mode: ColorMatchMode::LumaAndColor,
+            luma: vec![],
+            cb: vec![],
+            cr: vec![],
+        };
+        let mut pixels = noise(8, 8, 3).pixels;
+        let original = pixels.clone();
+        luts.apply(&mut pixels, 1.0);
+        assert_eq!(pixels, original);
+    }
+
+    #[test]
+    fn test_invalid_images_rejected() {
+        let good = noise(4, 4, 1);
+        let bad = DecodedImage {
+            width: 4,
+            height: 4,
+            pixels: vec![0; 10],
+        };
+        let mode = ColorMatchMode::LumaAndColor;
+        assert!(match_colors(&bad, &good, 1.0, mode).is_err());
+        assert!(match_colors(&good, &bad, 1.0, mode).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Texture atlas packing for strips of small images.
+
+use super::canvas::{blit, solid_canvas};
+use super::ComposeError;
+use crate::decode::DecodedImage;
+use crate::perf;
+use serde::{Deserialize, Serialize};
+
+/// Location of one source image inside an atlas, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasCell {
+    /// Left edge of the cell
+    pub x: u32,
+    /// Top edge of the cell
+    pub y: u32,
+    /// Width of the source image
+    pub width: u32,
+    /// Height of the source image
+    pub height: u32,
+}
+
+/// Several images packed into one, with the position of each.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    /// The packed image
+    pub image: DecodedImage,
+    /// One cell per source image, in input order
+    pub cells: Vec<AtlasCell>,
+}
+
+/// Pack `images` into a single atlas image.
+///
+/// Images are placed on a grid of `columns` slots, left to right, top to
+/// bottom. Every slot is as large as the largest image, and each image sits
+/// in the top-left corner of its slot with no padding. Unused space is black.
+///
+/// Uploading one atlas as a texture is much cheaper than uploading many
+/// small images, which is what the preset strip does.
+///
+/// # Errors
+///
+/// - `ComposeError::NoImages` if `images` is empty
+/// - `ComposeError::InvalidLayout` if `columns` is zero
+/// - `ComposeError::EmptyImage` if any image has zero width or height
+/// - `ComposeError::DimensionsTooLarge` if the atlas exceeds the image size limits
+pub fn pack_atlas(images: &[DecodedImage], columns: u32) -> Result<Atlas, ComposeError> {
+    if images.is_empty() {
+        return Err(ComposeError::NoImages);
+    }
+    if columns == 0 {
+        return Err(ComposeError::InvalidLayout(
+            "columns must be positive".to_string(),
+        ));
+    }
+    if images.iter().any(DecodedImage::is_empty) {
+        return Err(ComposeError::EmptyImage);
+    }
+
+    let _perf = perf::scope("pack_atlas");
+
+    let slot_w = images.iter().map(|img| img.width).max().unwrap_or(0);
+    let slot_h = images.iter().map(|img| img.height).max().unwrap_or(0);
+    let columns = columns.min(images.len() as u32);
+    let rows = (images.len() as u32).div_ceil(columns);
+
+    let width = columns as u64 * slot_w as u64;
+    let height = rows as u64 * slot_h as u64;
+    if width > u32::MAX as u64 || height > u32::MAX as u64 {
+        return Err(ComposeError::DimensionsTooLarge { width, height });
+    }
+
+    let mut atlas = solid_canvas(width as u32, height as u32, (0, 0, 0))?;
+    perf::record_allocation(atlas.pixels.len() as u64);
+
+    let cells = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let x = (i as u32 % columns) * slot_w;
+            let y = (i as u32 / columns) * slot_h;
+            blit(&mut atlas, image, x, y);
+            AtlasCell {
+                x,
+                y,
+                width: image.width,
+                height: image.height,
+            }
+        })
+        .collect();
+
+    Ok(Atlas {
+        image: atlas,
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, value: u8) -> DecodedImage {
+        solid_canvas(width, height, (value, value, value)).unwrap()
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let images: Vec<_> = (0..5).map(|i| solid(4, 3, i * 40 + 10)).collect();
+        let atlas = pack_atlas(&images, 2).unwrap();
+
+        assert_eq!((atlas.image.width, atlas.image.height), (8, 9));
+        assert_eq!(
+            atlas.cells[3],
+            AtlasCell {
+                x: 4,
+                y: 3,
+                width: 4,
+                height: 3
+            }
+        );
+        assert_eq!((atlas.cells[4].x, atlas.cells[4].y), (0, 6));
+    }
+
+    #[test]
+    fn test_columns_capped_at_image_count() {
+        let images = vec![solid(4, 4, 1), solid(4, 4, 2)];
+        let atlas = pack_atlas(&images, 10).unwrap();
+        assert_eq!((atlas.image.width, atlas.image.height), (8, 4));
+    }
+
+    #[test]
+    fn test_mixed_sizes_use_largest_slot() {
+        let images = vec![solid(4, 2, 50), solid(2, 4, 100)];
+        let atlas = pack_atlas(&images, 2).unwrap();
+
+        assert_eq!((atlas.image.width, atlas.image.height), (8, 4));
+        // Below the landscape image the slot is unused
+        let idx = ((3 * atlas.image.width) * 3) as usize;
+        assert_eq!(atlas.image.pixels[idx], 0);
+        assert_eq!(atlas.cells[1].width, 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(pack_atlas(&[], 2), Err(ComposeError::NoImages)));
+        assert!(matches!(
+            pack_atlas(&[solid(2, 2, 0)], 0),
+            Err(ComposeError::InvalidLayout(_))
+        ));
+        let empty = DecodedImage {
+            width: 0,
+            height: 0,
+            pixels: vec![],
+        };
+        assert!(matches!(
+            pack_atlas(&[empty], 1),
+            Err(ComposeError::EmptyImage)
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+//! Low-level canvas helpers shared by the composition operations.
+
+use super::ComposeError;
+use crate::decode::DecodedImage;
+use crate::validate::{checked_pixel_count, ImageError};
+
+/// Allocate an RGB canvas filled with a single color.
+///
+/// # Errors
+///
+/// Returns `ComposeError::EmptyImage` for a zero dimension, or
+/// `ComposeError::DimensionsTooLarge` if the canvas exceeds the image size
+/// limits.
+pub(crate) fn solid_canvas(
+    width: u32,
+    height: u32,
+    color: (u8, u8, u8),
+) -> Result<DecodedImage, ComposeError> {
+    let pixel_count = check_canvas_size(width, height)?;
+    let mut pixels = Vec::with_capacity(pixel_count * 3);
+    for _ in 0..pixel_count {
+        pixels.extend_from_slice(&[color.0, color.1, color.2]);
+    }
+    Ok(DecodedImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Check that a canvas of `width x height` can be allocated, returning its
+/// pixel count.
+///
+/// # Errors
+///
+/// Same as [`solid_canvas`].
+pub(crate) fn check_canvas_size(width: u32, height: u32) -> Result<usize, ComposeError> {
+    checked_pixel_count(width, height).map_err(|e| match e {
+        ImageError::EmptyImage { .. } => ComposeError::EmptyImage,
+        _ => ComposeError::DimensionsTooLarge {
+            width: width as u64,
+            height: height as u64,
+        },
+    })
+}
+
+/// Copy `src` into `canvas` with its top-left corner at (`x`, `y`).
+///
+/// Rows and columns falling outside the canvas are clipped.
+pub(crate) fn blit(canvas: &mut DecodedImage, src: &DecodedImage, x: u32, y: u32) {
+    if x >= canvas.width || y >= canvas.height {
+        return;
+    }
+
+    let copy_w = src.width.min(canvas.width - x) as usize;
+    let dst_start = x as usize * 3;
+    let row_bytes = copy_w * 3;
+
+    for (dst, src) in canvas.rows_mut().skip(y as usize).zip(src.rows()) {
+        dst[dst_start..dst_start + row_bytes].copy_from_slice(&src[..row_bytes]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solid_canvas() {
+        let canvas = solid_canvas(3, 2, (10, 20, 30)).unwrap();
+        assert_eq!(canvas.width, 3);
+        assert_eq!(canvas.height, 2);
+        assert_eq!(canvas.pixels.len(), 3 * 2 * 3);
+        for chunk in canvas.pixels.chunks_exact(3) {
+            assert_eq!(chunk, &[10, 20, 30]);
+        }
+    }
+
+    #[test]
+    fn test_blit_clips_to_canvas() {
+        let mut canvas = solid_canvas(4, 4, (0, 0, 0)).unwrap();
+        let src = solid_canvas(3, 3, (255, 255, 255)).unwrap();
+
+        blit(&mut canvas, &src, 2, 2);
+
+        // Only the 2x2 bottom-right corner is covered
+        let white: usize = canvas
+            .pixels
+            .chunks_exact(3)
+            .filter(|c| c[0] == 255)
+            .count();
+        assert_eq!(white, 4);
+        assert_eq!(&canvas.pixels[0..3], &[0, 0, 0]);
+    }
+}
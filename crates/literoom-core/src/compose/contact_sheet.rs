@@ -0,0 +1,302 @@
+//! Contact sheet (thumbnail grid) composition.
+
+use super::canvas::{blit, solid_canvas};
+use super::font::{draw_text, text_width, GLYPH_ADVANCE, GLYPH_HEIGHT};
+use super::{ComposeError, Gravity};
+use crate::decode::{generate_thumbnail, DecodedImage};
+use crate::perf;
+
+/// Vertical space above and below a label inside its strip.
+const LABEL_MARGIN: u32 = 2;
+
+/// Height of the label strip added below each cell when labels are drawn.
+pub const LABEL_STRIP_HEIGHT: u32 = GLYPH_HEIGHT + 2 * LABEL_MARGIN;
+
+/// Lay out thumbnails of `images` in a grid on a solid background.
+///
+/// Each source is scaled with [`generate_thumbnail`] to fit a
+/// `cell_size x cell_size` cell and centered in it, so portrait and landscape
+/// images are letterboxed with the background color. Cells are filled left to
+/// right, top to bottom, with `padding` pixels between cells and around the
+/// outer edge.
+///
+/// # Arguments
+///
+/// * `images` - Source images, in display order
+/// * `columns` - Number of cells per row
+/// * `cell_size` - Edge length of each square cell in pixels
+/// * `padding` - Gap between cells and around the sheet in pixels
+/// * `background` - RGB fill color for the sheet
+///
+/// # Returns
+///
+/// A sheet of `columns * cell_size + (columns + 1) * padding` by
+/// `rows * cell_size + (rows + 1) * padding` pixels, where
+/// `rows = ceil(images.len() / columns)`.
+///
+/// # Errors
+///
+/// - `ComposeError::NoImages` if `images` is empty
+/// - `ComposeError::InvalidLayout` if `columns` or `cell_size` is zero
+/// - `ComposeError::EmptyImage` if any source has zero width or height
+/// - `ComposeError::DimensionsTooLarge` if the sheet exceeds the image size limits
+pub fn compose_contact_sheet(
+    images: &[DecodedImage],
+    columns: u32,
+    cell_size: u32,
+    padding: u32,
+    background: (u8, u8, u8),
+) -> Result<DecodedImage, ComposeError> {
+    render_sheet(images, None, columns, cell_size, padding, background)
+}
+
+/// Like [`compose_contact_sheet`], with a text label under each thumbnail.
+///
+/// Every cell grows by [`LABEL_STRIP_HEIGHT`] pixels to hold its label, which
+/// is drawn centered in black or white, whichever contrasts with the
+/// background. Labels wider than the cell are truncated. Images without a
+/// matching entry in `labels` get an empty label.
+///
+/// # Arguments
+///
+/// * `images` - Source images, in display order
+/// * `labels` - One label per image (typically a filename or index)
+/// * `columns`, `cell_size`, `padding`, `background` - As for [`compose_contact_sheet`]
+///
+/// # Errors
+///
+/// Same as [`compose_contact_sheet`].
+pub fn compose_labeled_contact_sheet(
+    images: &[DecodedImage],
+    labels: &[String],
+    columns: u32,
+    cell_size: u32,
+    padding: u32,
+    background: (u8, u8, u8),
+) -> Result<DecodedImage, ComposeError> {
+    render_sheet(
+        images,
+        Some(labels),
+        columns,
+        cell_size,
+        padding,
+        background,
+    )
+}
+
+fn render_sheet(
+    images: &[DecodedImage],
+    labels: Option<&[String]>,
+    columns: u32,
+    cell_size: u32,
+    padding: u32,
+    background: (u8, u8, u8),
+) -> Result<DecodedImage, ComposeError> {
+    if images.is_empty() {
+        return Err(ComposeError::NoImages);
+    }
+    if columns == 0 || cell_size == 0 {
+        return Err(ComposeError::InvalidLayout(format!(
+            "columns ({}) and cell size ({}) must be positive",
+            columns, cell_size
+        )));
+    }
+    if images.iter().any(DecodedImage::is_empty) {
+        return Err(ComposeError::EmptyImage);
+    }
+
+    let _perf = perf::scope("compose_contact_sheet");
+
+    let cell_height = match labels {
+        Some(_) => cell_size as u64 + LABEL_STRIP_HEIGHT as u64,
+        None => cell_size as u64,
+    };
+    let rows = (images.len() as u64).div_ceil(columns as u64);
+    let width = columns as u64 * cell_size as u64 + (columns as u64 + 1) * padding as u64;
+    let height = rows * cell_height + (rows + 1) * padding as u64;
+    if width > u32::MAX as u64 || height > u32::MAX as u64 {
+        return Err(ComposeError::DimensionsTooLarge { width, height });
+    }
+
+    let mut sheet = solid_canvas(width as u32, height as u32, background)?;
+    perf::record_allocation(sheet.pixels.len() as u64);
+    let label_color = contrasting_color(background);
+
+    for (i, image) in images.iter().enumerate() {
+        let col = i as u32 % columns;
+        let row = i as u32 / columns;
+        let cell_x = padding + col * (cell_size + padding);
+        let cell_y = padding + row * (cell_height as u32 + padding);
+
+        let thumb = generate_thumbnail(image, cell_size)
+            .map_err(|e| ComposeError::Thumbnail(e.to_string()))?;
+        let (dx, dy) = Gravity::Center.offset((cell_size, cell_size), (thumb.width, thumb.height));
+        blit(&mut sheet, &thumb, cell_x + dx, cell_y + dy);
+
+        if let Some(label) = labels.and_then(|labels| labels.get(i)) {
+            let label = fit_label(label, cell_size);
+            let label_x = cell_x + (cell_size - text_width(&label)) / 2;
+            let label_y = cell_y + cell_size + LABEL_MARGIN;
+            draw_text(&mut sheet, &label, label_x, label_y, label_color);
+        }
+    }
+
+    Ok(sheet)
+}
+
+/// Truncate `label` to the number of glyphs that fit in `width` pixels.
+fn fit_label(label: &str, width: u32) -> String {
+    let max_chars = ((width + 1) / GLYPH_ADVANCE) as usize;
+    label.chars().take(max_chars).collect()
+}
+
+/// Pick black or white, whichever stands out against `background`.
+fn contrasting_color(background: (u8, u8, u8)) -> (u8, u8, u8) {
+    let luma =
+        0.2126 * background.0 as f32 + 0.7152 * background.1 as f32 + 0.0722 * background.2 as f32;
+    if luma >= 128.0 {
+        (0, 0, 0)
+    } else {
+        (255, 255, 255)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_image(width: u32, height: u32, color: (u8, u8, u8)) -> DecodedImage {
+        solid_canvas(width, height, color).unwrap()
+    }
+
+    fn get_pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+        let idx = ((y * image.width + x) * 3) as usize;
+        [
+            image.pixels[idx],
+            image.pixels[idx + 1],
+            image.pixels[idx + 2],
+        ]
+    }
+
+    #[test]
+    fn test_sheet_dimensions() {
+        let images: Vec<_> = (0..7).map(|_| solid_image(40, 30, (200, 0, 0))).collect();
+        let sheet = compose_contact_sheet(&images, 3, 20, 4, (255, 255, 255)).unwrap();
+
+        // 3 columns, ceil(7 / 3) = 3 rows
+        assert_eq!(sheet.width, 3 * 20 + 4 * 4);
+        assert_eq!(sheet.height, 3 * 20 + 4 * 4);
+        assert_eq!(
+            sheet.pixels.len(),
+            (sheet.width * sheet.height * 3) as usize
+        );
+    }
+
+    #[test]
+    fn test_sheet_keeps_full_width_for_partial_row() {
+        let images: Vec<_> = (0..2).map(|_| solid_image(10, 10, (0, 0, 0))).collect();
+        let sheet = compose_contact_sheet(&images, 5, 10, 2, (255, 255, 255)).unwrap();
+
+        // Fewer images than columns: the grid keeps its configured width
+        assert_eq!(sheet.width, 5 * 10 + 6 * 2);
+        assert_eq!(sheet.height, 10 + 2 * 2);
+    }
+
+    #[test]
+    fn test_portrait_cell_is_letterboxed() {
+        let background = (255, 255, 255);
+        let images = vec![solid_image(50, 100, (200, 0, 0))];
+        let sheet = compose_contact_sheet(&images, 1, 40, 0, background).unwrap();
+
+        // 50x100 fits a 40 cell as 20x40, centered with 10px bars left and right
+        assert_eq!((sheet.width, sheet.height), (40, 40));
+        for y in 0..40 {
+            assert_eq!(get_pixel(&sheet, 0, y), [255, 255, 255]);
+            assert_eq!(get_pixel(&sheet, 9, y), [255, 255, 255]);
+            assert_eq!(get_pixel(&sheet, 30, y), [255, 255, 255]);
+            assert_eq!(get_pixel(&sheet, 39, y), [255, 255, 255]);
+        }
+        assert_eq!(get_pixel(&sheet, 10, 0), [200, 0, 0]);
+        assert_eq!(get_pixel(&sheet, 29, 39), [200, 0, 0]);
+    }
+
+    #[test]
+    fn test_cells_placed_in_reading_order() {
+        let images = vec![
+            solid_image(10, 10, (255, 0, 0)),
+            solid_image(10, 10, (0, 255, 0)),
+            solid_image(10, 10, (0, 0, 255)),
+        ];
+        let sheet = compose_contact_sheet(&images, 2, 10, 2, (0, 0, 0)).unwrap();
+
+        assert_eq!(get_pixel(&sheet, 2, 2), [255, 0, 0]);
+        assert_eq!(get_pixel(&sheet, 14, 2), [0, 255, 0]);
+        assert_eq!(get_pixel(&sheet, 2, 14), [0, 0, 255]);
+        // Empty trailing cell and padding stay background
+        assert_eq!(get_pixel(&sheet, 14, 14), [0, 0, 0]);
+        assert_eq!(get_pixel(&sheet, 0, 0), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_empty_input_errors() {
+        assert!(matches!(
+            compose_contact_sheet(&[], 3, 100, 4, (0, 0, 0)),
+            Err(ComposeError::NoImages)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_layout_errors() {
+        let images = vec![solid_image(10, 10, (0, 0, 0))];
+        assert!(matches!(
+            compose_contact_sheet(&images, 0, 100, 4, (0, 0, 0)),
+            Err(ComposeError::InvalidLayout(_))
+        ));
+        assert!(matches!(
+            compose_contact_sheet(&images, 3, 0, 4, (0, 0, 0)),
+            Err(ComposeError::InvalidLayout(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_source_image_errors() {
+        let images = vec![DecodedImage::new(0, 0, vec![])];
+        assert!(matches!(
+            compose_contact_sheet(&images, 1, 10, 0, (0, 0, 0)),
+            Err(ComposeError::EmptyImage)
+        ));
+    }
+
+    #[test]
+    fn test_labeled_sheet_adds_label_strip() {
+        let images: Vec<_> = (0..4)
+            .map(|_| solid_image(30, 30, (128, 128, 128)))
+            .collect();
+        let labels: Vec<String> = (1..=4).map(|i| i.to_string()).collect();
+        let sheet =
+            compose_labeled_contact_sheet(&images, &labels, 2, 30, 3, (255, 255, 255)).unwrap();
+
+        assert_eq!(sheet.width, 2 * 30 + 3 * 3);
+        assert_eq!(sheet.height, 2 * (30 + LABEL_STRIP_HEIGHT) + 3 * 3);
+
+        // The first label strip contains black text on the white background
+        let strip_top = 3 + 30;
+        let dark = (strip_top..strip_top + LABEL_STRIP_HEIGHT)
+            .flat_map(|y| (3..33).map(move |x| (x, y)))
+            .filter(|&(x, y)| get_pixel(&sheet, x, y) == [0, 0, 0])
+            .count();
+        assert!(dark > 0);
+    }
+
+    #[test]
+    fn test_long_label_is_truncated() {
+        assert_eq!(fit_label("IMG_0001.CR3", 23), "IMG_");
+        assert_eq!(fit_label("ABC", 100), "ABC");
+    }
+
+    #[test]
+    fn test_contrasting_color() {
+        assert_eq!(contrasting_color((255, 255, 255)), (0, 0, 0));
+        assert_eq!(contrasting_color((20, 20, 20)), (255, 255, 255));
+    }
+}
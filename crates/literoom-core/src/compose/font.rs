@@ -0,0 +1,258 @@
+//! Minimal 5x7 bitmap font for burning short labels into images.
+//!
+//! Covers digits, uppercase letters, the punctuation common in file names,
+//! and `©` for watermarks. Lowercase letters render as uppercase; anything
+//! else renders as `?`.
+
+use crate::decode::DecodedImage;
+
+/// Width of a glyph in pixels.
+pub const GLYPH_WIDTH: u32 = 5;
+
+/// Height of a glyph in pixels.
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// Horizontal distance between the starts of consecutive glyphs.
+pub const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// Look up the rows of a glyph. Bit 4 of each row is the leftmost pixel.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        ' ' => [0x00; 7],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '©' => [0x0E, 0x11, 0x17, 0x19, 0x17, 0x11, 0x0E],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // '?'
+    }
+}
+
+/// Look up the rows of a glyph, keeping lowercase letters and covering all
+/// printable ASCII and `©`. Anything else renders as `?`.
+pub(crate) fn ascii_glyph(c: char) -> [u8; 7] {
+    match c {
+        'a' => [0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F],
+        'b' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E],
+        'c' => [0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E],
+        'd' => [0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F],
+        'e' => [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E],
+        'f' => [0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08],
+        'g' => [0x00, 0x0F, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'h' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'i' => [0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E],
+        'j' => [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0C],
+        'k' => [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12],
+        'l' => [0x0C, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'm' => [0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11],
+        'n' => [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'o' => [0x00, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E],
+        'p' => [0x00, 0x00, 0x1E, 0x11, 0x1E, 0x10, 0x10],
+        'q' => [0x00, 0x00, 0x0D, 0x13, 0x0F, 0x01, 0x01],
+        'r' => [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10],
+        's' => [0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E],
+        't' => [0x08, 0x08, 0x1C, 0x08, 0x08, 0x09, 0x06],
+        'u' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0D],
+        'v' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'w' => [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0A],
+        'x' => [0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11],
+        'y' => [0x00, 0x00, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'z' => [0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '"' => [0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '$' => [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '*' => [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00],
+        ';' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08],
+        '<' => [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '>' => [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],
+        '@' => [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E],
+        '[' => [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E],
+        '\\' => [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00],
+        ']' => [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E],
+        '^' => [0x04, 0x0A, 0x11, 0x00, 0x00, 0x00, 0x00],
+        '`' => [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00],
+        '{' => [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02],
+        '|' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        '}' => [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08],
+        '~' => [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00],
+        _ => glyph(c),
+    }
+}
+
+/// Width in pixels of `text` when drawn with [`draw_text`].
+pub fn text_width(text: &str) -> u32 {
+    let chars = text.chars().count() as u32;
+    if chars == 0 {
+        0
+    } else {
+        chars * GLYPH_ADVANCE - 1
+    }
+}
+
+/// Draw a single line of text with its top-left corner at (`x`, `y`).
+///
+/// Only set pixels are written, so the existing background shows through.
+/// Pixels falling outside the image are clipped.
+///
+/// # Arguments
+///
+/// * `image` - Image to draw into
+/// * `text` - Text to draw (see the module docs for the supported characters)
+/// * `x`, `y` - Top-left position of the first glyph
+/// * `color` - RGB text color
+pub fn draw_text(image: &mut DecodedImage, text: &str, x: u32, y: u32, color: (u8, u8, u8)) {
+    draw_glyphs(image, text, x, y, 1, color, glyph);
+}
+
+/// Draw a line of text looked up with `glyph`, each font pixel becoming a
+/// `scale` x `scale` block.
+///
+/// Only set pixels are written, and pixels falling outside the image are
+/// clipped. A `scale` of 0 is treated as 1.
+pub(crate) fn draw_glyphs(
+    image: &mut DecodedImage,
+    text: &str,
+    x: u32,
+    y: u32,
+    scale: u32,
+    color: (u8, u8, u8),
+    glyph: fn(char) -> [u8; 7],
+) {
+    let block = scale.max(1) as u64;
+    for (i, c) in text.chars().enumerate() {
+        let gx = x as u64 + i as u64 * GLYPH_ADVANCE as u64 * block;
+        if gx >= image.width as u64 {
+            break;
+        }
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let py = y as u64 + row as u64 * block;
+            if py >= image.height as u64 {
+                break;
+            }
+            for col in 0..GLYPH_WIDTH as u64 {
+                if bits & (0x10 >> col) != 0 {
+                    fill_rect(image, gx + col * block, py, block, block, color);
+                }
+            }
+        }
+    }
+}
+
+/// Fill a rectangle with a solid color, clipped to the image.
+pub(crate) fn fill_rect(
+    image: &mut DecodedImage,
+    x: u64,
+    y: u64,
+    width: u64,
+    height: u64,
+    rgb: (u8, u8, u8),
+) {
+    let x_end = x.saturating_add(width).min(image.width as u64);
+    let y_end = y.saturating_add(height).min(image.height as u64);
+    if x >= x_end || y >= y_end {
+        return;
+    }
+    let stride = image.width as usize * 3;
+    for row in y as usize..y_end as usize {
+        let start = row * stride + x as usize * 3;
+        let end = row * stride + x_end as usize * 3;
+        for px in image.pixels[start..end].chunks_exact_mut(3) {
+            px.copy_from_slice(&[rgb.0, rgb.1, rgb.2]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::canvas::solid_canvas;
+
+    fn lit_pixels(image: &DecodedImage) -> usize {
+        image.pixels.chunks_exact(3).filter(|c| c[0] == 255).count()
+    }
+
+    #[test]
+    fn test_text_width() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("A"), 5);
+        assert_eq!(text_width("12"), 11);
+    }
+
+    #[test]
+    fn test_draw_text_sets_glyph_pixels() {
+        let mut image = solid_canvas(5, 7, (0, 0, 0)).unwrap();
+        draw_text(&mut image, "-", 0, 0, (255, 255, 255));
+
+        // A dash is a single full-width row in the middle
+        assert_eq!(lit_pixels(&image), 5);
+        let idx = (3 * 5) * 3;
+        assert_eq!(&image.pixels[idx..idx + 3], &[255, 255, 255]);
+    }
+
+    #[test]
+    fn test_draw_text_lowercase_matches_uppercase() {
+        let mut lower = solid_canvas(20, 7, (0, 0, 0)).unwrap();
+        let mut upper = solid_canvas(20, 7, (0, 0, 0)).unwrap();
+        draw_text(&mut lower, "img", 0, 0, (255, 255, 255));
+        draw_text(&mut upper, "IMG", 0, 0, (255, 255, 255));
+        assert_eq!(lower.pixels, upper.pixels);
+    }
+
+    #[test]
+    fn test_draw_text_clips_to_image() {
+        let mut image = solid_canvas(8, 4, (0, 0, 0)).unwrap();
+        draw_text(&mut image, "WWWW", 3, 2, (255, 255, 255));
+        assert!(lit_pixels(&image) > 0);
+
+        // Fully outside: nothing drawn, no panic
+        let mut image = solid_canvas(8, 4, (0, 0, 0)).unwrap();
+        draw_text(&mut image, "W", 100, 100, (255, 255, 255));
+        assert_eq!(lit_pixels(&image), 0);
+    }
+}
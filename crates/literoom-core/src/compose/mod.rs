@@ -0,0 +1,42 @@
+//! Image composition operations for export.
+//!
+//! This module provides functionality for:
+//! - Padding an image onto a solid-color canvas of a fixed aspect ratio
+//! - Adding a uniform solid-color border around an image
+//! - Laying out thumbnails in a labeled contact sheet grid
+//! - Packing small images into a texture atlas
+//! - Blending image and text watermarks into exports
+//!
+//! # Architecture
+//!
+//! Composition always produces a new `DecodedImage`; source pixels are copied
+//! bit-for-bit into the output canvas without resampling. Resizing (if any)
+//! should happen before composition so that borders stay crisp. Watermarks
+//! are the exception: they are blended into the image in place, and image
+//! watermarks are resampled to their requested size.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use literoom_core::compose::{add_border, pad_to_aspect, Gravity};
+//!
+//! // Place a 3:2 photo on a white 4:5 canvas, then add a 20px gray frame
+//! let padded = pad_to_aspect(&image, 4.0 / 5.0, (255, 255, 255), Gravity::Center)?;
+//! let framed = add_border(&padded, 20, (128, 128, 128))?;
+//! ```
+
+mod atlas;
+mod canvas;
+mod contact_sheet;
+mod font;
+mod pad;
+mod types;
+mod watermark;
+
+pub use atlas::{pack_atlas, Atlas, AtlasCell};
+pub use contact_sheet::{compose_contact_sheet, compose_labeled_contact_sheet, LABEL_STRIP_HEIGHT};
+pub(crate) use font::{ascii_glyph, draw_glyphs, fill_rect};
+pub use font::{draw_text, text_width, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+pub use pad::{add_border, composed_dimensions, pad_to_aspect};
+pub use types::{ComposeError, Gravity};
+pub use watermark::{apply_watermark_image, apply_watermark_text, fit_watermark, WatermarkAnchor};
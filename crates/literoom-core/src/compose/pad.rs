@@ -0,0 +1,399 @@
+//! Aspect-ratio padding and solid borders.
+
+use super::canvas::{blit, check_canvas_size, solid_canvas};
+use super::{ComposeError, Gravity};
+use crate::decode::DecodedImage;
+
+/// Place an image on the smallest solid-color canvas with the target aspect ratio.
+///
+/// The canvas keeps one dimension of the source and extends the other so that
+/// `canvas_width / canvas_height ≈ target_aspect`. The source is copied
+/// unchanged into the canvas at the position given by `gravity`.
+///
+/// # Arguments
+///
+/// * `image` - Source image
+/// * `target_aspect` - Desired width / height ratio (e.g. `4.0 / 5.0`)
+/// * `background` - RGB fill color for the padded area
+/// * `gravity` - Where to place the source within the canvas
+///
+/// # Returns
+///
+/// A new `DecodedImage` containing the padded canvas. If the image already
+/// has the target aspect ratio, the result is a copy of the source.
+///
+/// # Errors
+///
+/// - `ComposeError::EmptyImage` if the source has zero width or height
+/// - `ComposeError::InvalidAspect` if `target_aspect` is not positive and finite
+/// - `ComposeError::DimensionsTooLarge` if the canvas exceeds the image size
+///   limits
+///
+/// # Example
+///
+/// ```
+/// use literoom_core::compose::{pad_to_aspect, Gravity};
+/// use literoom_core::decode::DecodedImage;
+///
+/// // 3:2 landscape image padded to a 4:5 portrait canvas
+/// let image = DecodedImage::new(300, 200, vec![128u8; 300 * 200 * 3]);
+/// let padded = pad_to_aspect(&image, 4.0 / 5.0, (255, 255, 255), Gravity::Center).unwrap();
+/// assert_eq!((padded.width, padded.height), (300, 375));
+/// ```
+pub fn pad_to_aspect(
+    image: &DecodedImage,
+    target_aspect: f32,
+    background: (u8, u8, u8),
+    gravity: Gravity,
+) -> Result<DecodedImage, ComposeError> {
+    if !target_aspect.is_finite() || target_aspect <= 0.0 {
+        return Err(ComposeError::InvalidAspect(target_aspect));
+    }
+    if image.is_empty() {
+        return Err(ComposeError::EmptyImage);
+    }
+
+    let (canvas_w, canvas_h) = padded_dimensions(image.width, image.height, target_aspect)?;
+
+    // Fast path: already at the target aspect ratio
+    if canvas_w == image.width && canvas_h == image.height {
+        return Ok(image.clone());
+    }
+
+    let (x, y) = gravity.offset((canvas_w, canvas_h), (image.width, image.height));
+    let mut canvas = solid_canvas(canvas_w, canvas_h, background)?;
+    blit(&mut canvas, image, x, y);
+
+    Ok(canvas)
+}
+
+/// Surround an image with a solid-color border of uniform width.
+///
+/// # Arguments
+///
+/// * `image` - Source image
+/// * `border_px` - Border width in pixels on each side
+/// * `color` - RGB border color
+///
+/// # Returns
+///
+/// A new `DecodedImage` that is `2 * border_px` larger in each dimension.
+///
+/// # Errors
+///
+/// - `ComposeError::EmptyImage` if the source has zero width or height
+/// - `ComposeError::DimensionsTooLarge` if the result exceeds the image size
+///   limits
+pub fn add_border(
+    image: &DecodedImage,
+    border_px: u32,
+    color: (u8, u8, u8),
+) -> Result<DecodedImage, ComposeError> {
+    if image.is_empty() {
+        return Err(ComposeError::EmptyImage);
+    }
+    if border_px == 0 {
+        return Ok(image.clone());
+    }
+
+    let (width, height) = bordered_dimensions(image.width, image.height, border_px)?;
+    let mut canvas = solid_canvas(width, height, color)?;
+    blit(&mut canvas, image, border_px, border_px);
+
+    Ok(canvas)
+}
+
+/// Size of the canvas [`pad_to_aspect`] and then [`add_border`] produce for
+/// a `width x height` image, without allocating it.
+///
+/// A `pad_aspect` of `None` or a `border_px` of `None` or 0 skips that
+/// step.
+///
+/// # Errors
+///
+/// The errors [`pad_to_aspect`] and [`add_border`] would return.
+pub fn composed_dimensions(
+    width: u32,
+    height: u32,
+    pad_aspect: Option<f32>,
+    border_px: Option<u32>,
+) -> Result<(u32, u32), ComposeError> {
+    if width == 0 || height == 0 {
+        return Err(ComposeError::EmptyImage);
+    }
+    let mut size = (width, height);
+    if let Some(aspect) = pad_aspect {
+        if !aspect.is_finite() || aspect <= 0.0 {
+            return Err(ComposeError::InvalidAspect(aspect));
+        }
+        let padded = padded_dimensions(size.0, size.1, aspect)?;
+        if padded != size {
+            check_canvas_size(padded.0, padded.1)?;
+            size = padded;
+        }
+    }
+    if let Some(border_px) = border_px.filter(|&b| b > 0) {
+        size = bordered_dimensions(size.0, size.1, border_px)?;
+        check_canvas_size(size.0, size.1)?;
+    }
+    Ok(size)
+}
+
+/// Size of `width x height` with `border_px` added on each side.
+fn bordered_dimensions(
+    width: u32,
+    height: u32,
+    border_px: u32,
+) -> Result<(u32, u32), ComposeError> {
+    let total = 2 * border_px as u64;
+    let width = width as u64 + total;
+    let height = height as u64 + total;
+    if width > u32::MAX as u64 || height > u32::MAX as u64 {
+        return Err(ComposeError::DimensionsTooLarge { width, height });
+    }
+    Ok((width as u32, height as u32))
+}
+
+/// Calculate the minimal canvas with the target aspect that contains `width x height`.
+fn padded_dimensions(
+    width: u32,
+    height: u32,
+    target_aspect: f32,
+) -> Result<(u32, u32), ComposeError> {
+    let aspect = target_aspect as f64;
+    let current = width as f64 / height as f64;
+
+    let (canvas_w, canvas_h) = if current > aspect {
+        // Too wide: keep width, grow height
+        let h = (width as f64 / aspect).round().max(height as f64);
+        (width as f64, h)
+    } else {
+        // Too tall (or exact): keep height, grow width
+        let w = (height as f64 * aspect).round().max(width as f64);
+        (w, height as f64)
+    };
+
+    if canvas_w > u32::MAX as f64 || canvas_h > u32::MAX as f64 {
+        return Err(ComposeError::DimensionsTooLarge {
+            width: canvas_w as u64,
+            height: canvas_h as u64,
+        });
+    }
+
+    Ok((canvas_w as u32, canvas_h as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a test image where each pixel has a unique-ish value based on position.
+    fn create_test_image(width: u32, height: u32) -> DecodedImage {
+        let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push((x % 256) as u8); // R
+                pixels.push((y % 256) as u8); // G
+                pixels.push(((x + y) % 256) as u8); // B
+            }
+        }
+        DecodedImage::new(width, height, pixels)
+    }
+
+    fn get_pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+        let idx = ((y * image.width + x) * 3) as usize;
+        [
+            image.pixels[idx],
+            image.pixels[idx + 1],
+            image.pixels[idx + 2],
+        ]
+    }
+
+    /// Check that `inner` appears bit-identical in `outer` at (x, y) and that
+    /// every other pixel equals `background`.
+    fn assert_placed(
+        outer: &DecodedImage,
+        inner: &DecodedImage,
+        x: u32,
+        y: u32,
+        background: (u8, u8, u8),
+    ) {
+        let bg = [background.0, background.1, background.2];
+        for oy in 0..outer.height {
+            for ox in 0..outer.width {
+                let inside = ox >= x && ox < x + inner.width && oy >= y && oy < y + inner.height;
+                let expected = if inside {
+                    get_pixel(inner, ox - x, oy - y)
+                } else {
+                    bg
+                };
+                assert_eq!(
+                    get_pixel(outer, ox, oy),
+                    expected,
+                    "Mismatch at ({}, {})",
+                    ox,
+                    oy
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pad_3x2_to_4x5_dimensions() {
+        let img = create_test_image(300, 200);
+        let padded = pad_to_aspect(&img, 4.0 / 5.0, (255, 255, 255), Gravity::Center).unwrap();
+
+        // Width is kept, height grows to 300 / 0.8 = 375
+        assert_eq!(padded.width, 300);
+        assert_eq!(padded.height, 375);
+        assert_eq!(padded.pixels.len(), 300 * 375 * 3);
+    }
+
+    #[test]
+    fn test_pad_3x2_to_4x5_pixels() {
+        let img = create_test_image(30, 20);
+        let background = (255, 255, 255);
+        let padded = pad_to_aspect(&img, 4.0 / 5.0, background, Gravity::Center).unwrap();
+
+        // 30 / 0.8 = 37.5; the f32 ratio lands just below .5 and rounds down
+        assert_eq!((padded.width, padded.height), (30, 37));
+        // (37 - 20) / 2 = 8 rows above
+        assert_placed(&padded, &img, 0, 8, background);
+    }
+
+    #[test]
+    fn test_pad_portrait_to_square() {
+        let img = create_test_image(20, 40);
+        let background = (10, 20, 30);
+        let padded = pad_to_aspect(&img, 1.0, background, Gravity::Center).unwrap();
+
+        assert_eq!((padded.width, padded.height), (40, 40));
+        assert_placed(&padded, &img, 10, 0, background);
+    }
+
+    #[test]
+    fn test_pad_gravity_placement() {
+        let img = create_test_image(30, 20);
+        let background = (0, 0, 0);
+
+        let top = pad_to_aspect(&img, 1.0, background, Gravity::Top).unwrap();
+        assert_placed(&top, &img, 0, 0, background);
+
+        let bottom = pad_to_aspect(&img, 1.0, background, Gravity::Bottom).unwrap();
+        assert_placed(&bottom, &img, 0, 10, background);
+
+        let img = create_test_image(20, 30);
+        let left = pad_to_aspect(&img, 1.0, background, Gravity::Left).unwrap();
+        assert_placed(&left, &img, 0, 0, background);
+
+        let right = pad_to_aspect(&img, 1.0, background, Gravity::Right).unwrap();
+        assert_placed(&right, &img, 10, 0, background);
+    }
+
+    #[test]
+    fn test_pad_already_at_aspect() {
+        let img = create_test_image(40, 50);
+        let padded = pad_to_aspect(&img, 4.0 / 5.0, (255, 255, 255), Gravity::Center).unwrap();
+
+        assert_eq!((padded.width, padded.height), (40, 50));
+        assert_eq!(padded.pixels, img.pixels);
+    }
+
+    #[test]
+    fn test_pad_invalid_aspect() {
+        let img = create_test_image(10, 10);
+        for aspect in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(
+                pad_to_aspect(&img, aspect, (0, 0, 0), Gravity::Center),
+                Err(ComposeError::InvalidAspect(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pad_empty_image() {
+        let img = DecodedImage::new(0, 0, vec![]);
+        assert!(matches!(
+            pad_to_aspect(&img, 1.0, (0, 0, 0), Gravity::Center),
+            Err(ComposeError::EmptyImage)
+        ));
+    }
+
+    #[test]
+    fn test_add_border() {
+        let img = create_test_image(10, 6);
+        let color = (200, 100, 50);
+        let bordered = add_border(&img, 4, color).unwrap();
+
+        assert_eq!((bordered.width, bordered.height), (18, 14));
+        assert_placed(&bordered, &img, 4, 4, color);
+    }
+
+    #[test]
+    fn test_add_border_zero_width() {
+        let img = create_test_image(10, 6);
+        let bordered = add_border(&img, 0, (0, 0, 0)).unwrap();
+
+        assert_eq!((bordered.width, bordered.height), (10, 6));
+        assert_eq!(bordered.pixels, img.pixels);
+    }
+
+    #[test]
+    fn test_add_border_empty_image() {
+        let img = DecodedImage::new(0, 0, vec![]);
+        assert!(matches!(
+            add_border(&img, 5, (0, 0, 0)),
+            Err(ComposeError::EmptyImage)
+        ));
+    }
+
+    #[test]
+    fn test_add_border_too_large() {
+        let img = create_test_image(1, 1);
+        assert!(matches!(
+            add_border(&img, u32::MAX / 2 + 1, (0, 0, 0)),
+            Err(ComposeError::DimensionsTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pad_then_border_chain() {
+        let img = create_test_image(40, 20);
+        let padded = pad_to_aspect(&img, 4.0 / 5.0, (255, 255, 255), Gravity::Center).unwrap();
+        let framed = add_border(&padded, 2, (0, 0, 0)).unwrap();
+
+        assert_eq!((framed.width, framed.height), (44, 54));
+        assert_placed(&framed, &padded, 2, 2, (0, 0, 0));
+    }
+
+    #[test]
+    fn test_composed_dimensions_match_pad_and_border() {
+        let img = create_test_image(40, 20);
+        for aspect in [None, Some(0.8), Some(2.0), Some(3.0)] {
+            for border in [None, Some(0), Some(3)] {
+                let mut out = img.clone();
+                if let Some(aspect) = aspect {
+                    out = pad_to_aspect(&out, aspect, (255, 255, 255), Gravity::Center).unwrap();
+                }
+                if let Some(border) = border {
+                    out = add_border(&out, border, (0, 0, 0)).unwrap();
+                }
+                assert_eq!(
+                    composed_dimensions(40, 20, aspect, border).unwrap(),
+                    (out.width, out.height),
+                    "aspect {:?} border {:?}",
+                    aspect,
+                    border
+                );
+            }
+        }
+        assert!(matches!(
+            composed_dimensions(40, 20, Some(f32::NAN), None),
+            Err(ComposeError::InvalidAspect(_))
+        ));
+        assert!(matches!(
+            composed_dimensions(1, 1, None, Some(u32::MAX / 2 + 1)),
+            Err(ComposeError::DimensionsTooLarge { .. })
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+//! Core types for image composition.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors that can occur during image composition.
+#[derive(Debug, Error)]
+pub enum ComposeError {
+    /// The source image has zero width or height.
+    #[error("Cannot compose an empty image")]
+    EmptyImage,
+
+    /// The target aspect ratio is not a positive, finite number.
+    #[error("Invalid aspect ratio: {0} (must be positive and finite)")]
+    InvalidAspect(f32),
+
+    /// The output canvas would exceed the maximum dimension or pixel count.
+    #[error("Output dimensions too large: {width}x{height}")]
+    DimensionsTooLarge { width: u64, height: u64 },
+
+    /// A contact sheet was requested with no source images.
+    #[error("Contact sheet requires at least one image")]
+    NoImages,
+
+    /// The grid layout parameters are unusable.
+    #[error("Invalid layout: {0}")]
+    InvalidLayout(String),
+
+    /// A source image could not be thumbnailed.
+    #[error("Failed to generate thumbnail: {0}")]
+    Thumbnail(String),
+
+    /// The watermark or its parameters are unusable.
+    #[error("Invalid watermark: {0}")]
+    InvalidWatermark(String),
+}
+
+/// Placement of an image inside a larger canvas.
+///
+/// Gravity only affects the axis that has spare room; the other axis is
+/// always centered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Gravity {
+    /// Center on both axes.
+    #[default]
+    Center,
+    /// Align to the top edge (horizontally centered).
+    Top,
+    /// Align to the bottom edge (horizontally centered).
+    Bottom,
+    /// Align to the left edge (vertically centered).
+    Left,
+    /// Align to the right edge (vertically centered).
+    Right,
+}
+
+impl Gravity {
+    /// Compute the top-left offset for an `inner` rect placed inside `outer`.
+    ///
+    /// Both rects are given as (width, height). `inner` must not exceed `outer`.
+    pub fn offset(self, outer: (u32, u32), inner: (u32, u32)) -> (u32, u32) {
+        let spare_x = outer.0.saturating_sub(inner.0);
+        let spare_y = outer.1.saturating_sub(inner.1);
+        match self {
+            Gravity::Center => (spare_x / 2, spare_y / 2),
+            Gravity::Top => (spare_x / 2, 0),
+            Gravity::Bottom => (spare_x / 2, spare_y),
+            Gravity::Left => (0, spare_y / 2),
+            Gravity::Right => (spare_x, spare_y / 2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gravity_offsets() {
+        let outer = (100, 80);
+        let inner = (60, 40);
+        assert_eq!(Gravity::Center.offset(outer, inner), (20, 20));
+        assert_eq!(Gravity::Top.offset(outer, inner), (20, 0));
+        assert_eq!(Gravity::Bottom.offset(outer, inner), (20, 40));
+        assert_eq!(Gravity::Left.offset(outer, inner), (0, 20));
+        assert_eq!(Gravity::Right.offset(outer, inner), (40, 20));
+    }
+
+    #[test]
+    fn test_gravity_offset_same_size() {
+        for gravity in [
+            Gravity::Center,
+            Gravity::Top,
+            Gravity::Bottom,
+            Gravity::Left,
+            Gravity::Right,
+        ] {
+            assert_eq!(gravity.offset((50, 50), (50, 50)), (0, 0));
+        }
+    }
+
+    #[test]
+    fn test_compose_error_display() {
+        let err = ComposeError::InvalidAspect(-1.0);
+        assert_eq!(
+            err.to_string(),
+            "Invalid aspect ratio: -1 (must be positive and finite)"
+        );
+    }
+}
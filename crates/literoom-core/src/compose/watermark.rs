@@ -0,0 +1,428 @@
+//! Image and text watermarks for exports.
+//!
+//! Watermarks are blended into the image in place at a given opacity. An
+//! optional alpha plane (one byte per watermark pixel) keys out the
+//! watermark's background, e.g. the transparent area around a logo.
+
+use super::canvas::solid_canvas;
+use super::font::{draw_text, text_width, GLYPH_HEIGHT};
+use super::types::ComposeError;
+use crate::decode::{resize, DecodedImage, FilterType};
+use serde::{Deserialize, Serialize};
+
+/// Where a watermark sits in the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WatermarkAnchor {
+    /// Top-left corner.
+    TopLeft,
+    /// Top-right corner.
+    TopRight,
+    /// Bottom-left corner.
+    BottomLeft,
+    /// Bottom-right corner.
+    #[default]
+    BottomRight,
+    /// Centered on both axes; the margin is ignored.
+    Center,
+}
+
+impl WatermarkAnchor {
+    /// Compute the top-left position of a `mark` placed inside `canvas`,
+    /// `margin` pixels away from the anchored edges.
+    ///
+    /// Both sizes are given as (width, height). When the margin does not fit
+    /// next to the mark it shrinks, so a mark that fits the canvas always
+    /// stays fully inside it.
+    pub fn origin(self, canvas: (u32, u32), mark: (u32, u32), margin: u32) -> (u32, u32) {
+        let spare_x = canvas.0.saturating_sub(mark.0);
+        let spare_y = canvas.1.saturating_sub(mark.1);
+        let near = |spare: u32| margin.min(spare);
+        let far = |spare: u32| spare.saturating_sub(margin);
+        match self {
+            WatermarkAnchor::TopLeft => (near(spare_x), near(spare_y)),
+            WatermarkAnchor::TopRight => (far(spare_x), near(spare_y)),
+            WatermarkAnchor::BottomLeft => (near(spare_x), far(spare_y)),
+            WatermarkAnchor::BottomRight => (far(spare_x), far(spare_y)),
+            WatermarkAnchor::Center => (spare_x / 2, spare_y / 2),
+        }
+    }
+}
+
+/// Size of a `mark` scaled by `scale`, shrunk if needed to fit inside
+/// `canvas` less `margin` on each side, with its aspect ratio preserved.
+///
+/// If the margins leave no room, the whole canvas is used instead.
+pub fn fit_watermark(canvas: (u32, u32), mark: (u32, u32), margin: u32, scale: f32) -> (u32, u32) {
+    let inset = |size: u32| match size.saturating_sub(margin.saturating_mul(2)) {
+        0 => size,
+        room => room,
+    };
+    let room = (inset(canvas.0) as f64, inset(canvas.1) as f64);
+    let wanted = (mark.0 as f64 * scale as f64, mark.1 as f64 * scale as f64);
+
+    let shrink = (room.0 / wanted.0).min(room.1 / wanted.1).min(1.0);
+    let size = |wanted: f64, room: f64| ((wanted * shrink).round().min(room) as u32).max(1);
+    (size(wanted.0, room.0), size(wanted.1, room.1))
+}
+
+/// Blend an image watermark into `image`.
+///
+/// The watermark is resized by `scale` (1.0 keeps its size), shrunk further
+/// if it would not fit, and placed at `anchor` with `margin_px` of space.
+///
+/// # Arguments
+///
+/// * `image` - Image to watermark, modified in place
+/// * `watermark` - Watermark pixels
+/// * `alpha` - Optional coverage per watermark pixel (0 = transparent, 255
+///   = opaque); without it the whole rectangle is blended
+/// * `anchor` - Corner or center to place the watermark at
+/// * `margin_px` - Distance from the anchored edges
+/// * `opacity` - Overall strength, 0.0 to 1.0 (clamped)
+/// * `scale` - Size relative to the watermark's own dimensions
+///
+/// # Errors
+///
+/// Returns `ComposeError::EmptyImage` if either image is empty, and
+/// `ComposeError::InvalidWatermark` if `alpha` has the wrong length or
+/// `opacity` or `scale` are unusable.
+pub fn apply_watermark_image(
+    image: &mut DecodedImage,
+    watermark: &DecodedImage,
+    alpha: Option<&[u8]>,
+    anchor: WatermarkAnchor,
+    margin_px: u32,
+    opacity: f32,
+    scale: f32,
+) -> Result<(), ComposeError> {
+    if image.is_empty() || watermark.is_empty() {
+        return Err(ComposeError::EmptyImage);
+    }
+    let opacity = check_opacity(opacity)?;
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(ComposeError::InvalidWatermark(format!(
+            "scale must be positive and finite, got {}",
+            scale
+        )));
+    }
+    if let Some(alpha) = alpha {
+        let expected = watermark.pixel_count() as usize;
+        if alpha.len() != expected {
+            return Err(ComposeError::InvalidWatermark(format!(
+                "alpha plane has {} values, expected {}",
+                alpha.len(),
+                expected
+            )));
+        }
+    }
+
+    let canvas = (image.width, image.height);
+    let (width, height) = fit_watermark(
+        canvas,
+        (watermark.width, watermark.height),
+        margin_px,
+        scale,
+    );
+    let resample = |source: &DecodedImage| -> Result<DecodedImage, ComposeError> {
+        if (source.width, source.height) == (width, height) {
+            return Ok(source.clone());
+        }
+        resize(source, width, height, FilterType::Lanczos3)
+            .map_err(|e| ComposeError::InvalidWatermark(e.to_string()))
+    };
+
+    let mark = resample(watermark)?;
+    let alpha: Option<Vec<u8>> = match alpha {
+        Some(alpha) => {
+            // Resample the alpha plane as a gray image so it stays aligned
+            let gray = DecodedImage {
+                width: watermark.width,
+                height: watermark.height,
+                pixels: alpha.iter().flat_map(|&a| [a, a, a]).collect(),
+            };
+            Some(resample(&gray)?.pixels.iter().step_by(3).copied().collect())
+        }
+        None => None,
+    };
+
+    let origin = anchor.origin(canvas, (width, height), margin_px);
+    blend(image, &mark, alpha.as_deref(), origin, opacity);
+    Ok(())
+}
+
+/// Blend a line of text into `image` as a watermark, e.g. `"© 2024 Jane Doe"`.
+///
+/// The text is drawn in white with the built-in bitmap font, scaled by a
+/// whole factor so that glyphs are about `size_px` tall. Text that would be
+/// wider than the image is drawn smaller, down to the font's native size,
+/// and clipped beyond that. Empty text leaves the image unchanged.
+///
+/// # Errors
+///
+/// Returns `ComposeError::EmptyImage` if the image is empty, and
+/// `ComposeError::InvalidWatermark` if `opacity` is not finite.
+pub fn apply_watermark_text(
+    image: &mut DecodedImage,
+    text: &str,
+    anchor: WatermarkAnchor,
+    margin_px: u32,
+    opacity: f32,
+    size_px: u32,
+) -> Result<(), ComposeError> {
+    if image.is_empty() {
+        return Err(ComposeError::EmptyImage);
+    }
+    let opacity = check_opacity(opacity)?;
+    let native_width = text_width(text);
+    if native_width == 0 {
+        return Ok(());
+    }
+
+    let room = image
+        .width
+        .saturating_sub(margin_px.saturating_mul(2))
+        .max(1);
+    let factor = ((size_px as f32 / GLYPH_HEIGHT as f32).round() as u32)
+        .clamp(1, (room / native_width).max(1));
+
+    let mut glyphs = solid_canvas(native_width, GLYPH_HEIGHT, (0, 0, 0))?;
+    draw_text(&mut glyphs, text, 0, 0, (255, 255, 255));
+
+    // Nearest-neighbor upscale keeps the pixel font crisp
+    let (width, height) = (native_width * factor, GLYPH_HEIGHT * factor);
+    let coverage: Vec<u8> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x / factor, y / factor)))
+        .map(|(x, y)| glyphs.pixel(x, y).map_or(0, |(r, _, _)| r))
+        .collect();
+    let mark = solid_canvas(width, height, (255, 255, 255))?;
+
+    let origin = anchor.origin((image.width, image.height), (width, height), margin_px);
+    blend(image, &mark, Some(&coverage), origin, opacity);
+    Ok(())
+}
+
+fn check_opacity(opacity: f32) -> Result<f32, ComposeError> {
+    if opacity.is_finite() {
+        Ok(opacity.clamp(0.0, 1.0))
+    } else {
+        Err(ComposeError::InvalidWatermark(format!(
+            "opacity must be finite, got {}",
+            opacity
+        )))
+    }
+}
+
+/// Blend `mark` over `image` with its top-left corner at `origin`.
+///
+/// Each pixel moves toward the mark by `opacity` times its coverage.
+/// Pixels falling outside the image are clipped.
+fn blend(
+    image: &mut DecodedImage,
+    mark: &DecodedImage,
+    alpha: Option<&[u8]>,
+    origin: (u32, u32),
+    opacity: f32,
+) {
+    if opacity == 0.0 {
+        return;
+    }
+    for (y, row) in mark.rows().enumerate() {
+        let py = origin.1 + y as u32;
+        if py >= image.height {
+            break;
+        }
+        for (x, src) in row.chunks_exact(3).enumerate() {
+            let px = origin.0 + x as u32;
+            if px >= image.width {
+                break;
+            }
+            let coverage = alpha.map_or(1.0, |a| a[y * mark.width as usize + x] as f32 / 255.0);
+            let weight = opacity * coverage;
+            let Some((r, g, b)) = image.pixel(px, py) else {
+                continue;
+            };
+            let mix = |base: u8, over: u8| {
+                (base as f32 + (over as f32 - base as f32) * weight).round() as u8
+            };
+            image.set_pixel(px, py, (mix(r, src[0]), mix(g, src[1]), mix(b, src[2])));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANCHORS: [WatermarkAnchor; 5] = [
+        WatermarkAnchor::TopLeft,
+        WatermarkAnchor::TopRight,
+        WatermarkAnchor::BottomLeft,
+        WatermarkAnchor::BottomRight,
+        WatermarkAnchor::Center,
+    ];
+
+    #[test]
+    fn test_anchor_origins_landscape_and_portrait() {
+        let landscape: Vec<_> = ANCHORS
+            .iter()
+            .map(|a| a.origin((300, 200), (60, 20), 10))
+            .collect();
+        assert_eq!(
+            landscape,
+            vec![(10, 10), (230, 10), (10, 170), (230, 170), (120, 90)]
+        );
+
+        let portrait: Vec<_> = ANCHORS
+            .iter()
+            .map(|a| a.origin((200, 300), (60, 20), 10))
+            .collect();
+        assert_eq!(
+            portrait,
+            vec![(10, 10), (130, 10), (10, 270), (130, 270), (70, 140)]
+        );
+
+        // A margin wider than the spare room shrinks instead of pushing the
+        // mark off the canvas
+        assert_eq!(
+            WatermarkAnchor::BottomRight.origin((70, 30), (60, 20), 10),
+            (0, 0)
+        );
+        assert_eq!(
+            WatermarkAnchor::TopLeft.origin((70, 30), (60, 20), 10),
+            (10, 10)
+        );
+    }
+
+    #[test]
+    fn test_half_opacity_blends_to_midpoint() {
+        let mut image = solid_canvas(8, 6, (100, 0, 250)).unwrap();
+        let mark = solid_canvas(4, 2, (200, 255, 50)).unwrap();
+        apply_watermark_image(
+            &mut image,
+            &mark,
+            None,
+            WatermarkAnchor::TopLeft,
+            0,
+            0.5,
+            1.0,
+        )
+        .unwrap();
+
+        assert_eq!(image.pixel(0, 0), Some((150, 128, 150)));
+        assert_eq!(image.pixel(3, 1), Some((150, 128, 150)));
+        assert_eq!(image.pixel(4, 0), Some((100, 0, 250)));
+        assert_eq!(image.pixel(0, 2), Some((100, 0, 250)));
+    }
+
+    #[test]
+    fn test_alpha_plane_keys_out_background() {
+        let mut image = solid_canvas(4, 1, (0, 0, 0)).unwrap();
+        let mark = solid_canvas(4, 1, (255, 255, 255)).unwrap();
+        let alpha = [255, 0, 255, 0];
+        apply_watermark_image(
+            &mut image,
+            &mark,
+            Some(&alpha),
+            WatermarkAnchor::TopLeft,
+            0,
+            1.0,
+            1.0,
+        )
+        .unwrap();
+        assert_eq!(
+            image.pixels,
+            vec![255, 255, 255, 0, 0, 0, 255, 255, 255, 0, 0, 0]
+        );
+
+        let err = apply_watermark_image(
+            &mut image,
+            &mark,
+            Some(&[0; 3]),
+            WatermarkAnchor::TopLeft,
+            0,
+            1.0,
+            1.0,
+        );
+        assert!(matches!(err, Err(ComposeError::InvalidWatermark(_))));
+    }
+
+    #[test]
+    fn test_oversized_watermark_is_scaled_to_fit() {
+        // 400x100 (4:1) on a 100x80 image with 10px margins: 80 px of room
+        assert_eq!(fit_watermark((100, 80), (400, 100), 10, 1.0), (80, 20));
+        // Tall mark limited by height instead
+        assert_eq!(fit_watermark((100, 80), (50, 200), 10, 1.0), (15, 60));
+        // Scale applies first; a mark that fits is left alone
+        assert_eq!(fit_watermark((100, 80), (40, 20), 10, 0.5), (20, 10));
+
+        let mut image = solid_canvas(100, 80, (0, 0, 0)).unwrap();
+        let mark = solid_canvas(400, 100, (255, 255, 255)).unwrap();
+        apply_watermark_image(
+            &mut image,
+            &mark,
+            None,
+            WatermarkAnchor::BottomRight,
+            10,
+            1.0,
+            1.0,
+        )
+        .unwrap();
+        let white = image.pixels.chunks_exact(3).filter(|c| c[0] == 255).count();
+        assert_eq!(white, 80 * 20);
+        assert_eq!(image.pixel(89, 69), Some((255, 255, 255)));
+        assert_eq!(image.pixel(90, 70), Some((0, 0, 0)));
+    }
+
+    #[test]
+    fn test_text_watermark_scales_and_anchors() {
+        let mut image = solid_canvas(200, 100, (0, 0, 0)).unwrap();
+        apply_watermark_text(
+            &mut image,
+            "© 2024",
+            WatermarkAnchor::BottomRight,
+            8,
+            1.0,
+            14,
+        )
+        .unwrap();
+
+        // Twice the 7px glyph height, ending 8px from the bottom-right corner
+        let lit: Vec<(u32, u32)> = image
+            .enumerate_pixels()
+            .filter(|(_, _, rgb)| rgb.0 == 255)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let max_x = lit.iter().map(|p| p.0).max().unwrap();
+        let min_y = lit.iter().map(|p| p.1).min().unwrap();
+        let max_y = lit.iter().map(|p| p.1).max().unwrap();
+        assert_eq!(max_x, 200 - 8 - 1);
+        assert_eq!(max_y, 100 - 8 - 1);
+        assert_eq!(max_y - min_y + 1, 14);
+
+        let mut unchanged = solid_canvas(10, 10, (5, 5, 5)).unwrap();
+        apply_watermark_text(&mut unchanged, "", WatermarkAnchor::Center, 0, 1.0, 14).unwrap();
+        assert_eq!(unchanged.pixels, vec![5; 10 * 10 * 3]);
+    }
+
+    #[test]
+    fn test_invalid_parameters_are_rejected() {
+        let mut image = solid_canvas(10, 10, (0, 0, 0)).unwrap();
+        let mark = solid_canvas(2, 2, (255, 255, 255)).unwrap();
+        let anchor = WatermarkAnchor::Center;
+        for (opacity, scale) in [(f32::NAN, 1.0), (0.5, 0.0), (0.5, f32::INFINITY)] {
+            assert!(matches!(
+                apply_watermark_image(&mut image, &mark, None, anchor, 0, opacity, scale),
+                Err(ComposeError::InvalidWatermark(_))
+            ));
+        }
+        let empty = DecodedImage {
+            width: 0,
+            height: 0,
+            pixels: vec![],
+        };
+        assert!(matches!(
+            apply_watermark_image(&mut image, &empty, None, anchor, 0, 0.5, 1.0),
+            Err(ComposeError::EmptyImage)
+        ));
+    }
+}
@@ -2,8 +2,184 @@
 //!
 //! This module implements the Fritsch-Carlson algorithm for monotonic spline interpolation,
 //! which guarantees that the curve never crosses (no solarization artifacts).
+//!
+//! [`HueCurve`]s use the same spline over a periodic hue axis for hue-vs-hue,
+//! hue-vs-saturation and hue-vs-luminance adjustments (see
+//! [`apply_hue_curves`]).
 
+use crate::color::{oklch_to_srgb_in_gamut, srgb_to_oklab, Oklch};
+use crate::decode::{subsample, subsample_map, DecodeError, DecodedImage};
+use crate::perf;
 use crate::{CurvePoint, ToneCurve};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Smallest gap kept between the input black and white points.
+///
+/// One 8-bit step, so a degenerate `white <= black` becomes a hard threshold
+/// instead of a division by zero.
+pub const MIN_INPUT_SPAN: f32 = 1.0 / 255.0;
+
+/// Largest step between adjacent LUT entries when smoothing is requested
+/// without an explicit bound (see [`ToneCurveLut::limit_steps`]).
+///
+/// Eight levels keeps gradients through a steep segment visibly smooth
+/// while a threshold curve still reads as a hard transition.
+pub const DEFAULT_MAX_LUT_STEP: u8 = 8;
+
+/// Most control points a tone curve may have.
+///
+/// Far more than anyone places by hand; the bound stops a runaway caller
+/// (such as one appending a point per mouse move) from making every LUT
+/// build slow.
+pub const MAX_CURVE_POINTS: usize = 64;
+
+// ============================================================================
+// Curve Validation
+// ============================================================================
+
+/// Control points that cannot form a tone curve.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CurveError {
+    /// More than [`MAX_CURVE_POINTS`] control points.
+    #[error("Tone curve has {count} control points; at most {max} are allowed")]
+    TooManyPoints { count: usize, max: usize },
+
+    /// A control point has a NaN or infinite coordinate.
+    #[error("Tone curve point {index} has a non-finite coordinate")]
+    NonFinitePoint { index: usize },
+}
+
+impl CurveError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CurveError::TooManyPoints { .. } => "TOO_MANY_CURVE_POINTS",
+            CurveError::NonFinitePoint { .. } => "NON_FINITE_CURVE_POINT",
+        }
+    }
+}
+
+impl ToneCurve {
+    /// Check that the curve has at most [`MAX_CURVE_POINTS`] points, all of
+    /// them finite.
+    ///
+    /// # Errors
+    /// Returns the first problem found; a non-finite point is reported by
+    /// its index in `points`.
+    pub fn validate(&self) -> Result<(), CurveError> {
+        if self.points.len() > MAX_CURVE_POINTS {
+            return Err(CurveError::TooManyPoints {
+                count: self.points.len(),
+                max: MAX_CURVE_POINTS,
+            });
+        }
+        match self
+            .points
+            .iter()
+            .position(|p| !p.x.is_finite() || !p.y.is_finite())
+        {
+            Some(index) => Err(CurveError::NonFinitePoint { index }),
+            None => Ok(()),
+        }
+    }
+
+    /// Validate the curve and put its points into the form the spline
+    /// expects.
+    ///
+    /// Coordinates are clamped to 0.0-1.0 and points sorted by x. Points
+    /// less than `f32::EPSILON` apart in x are a zero-width segment, which
+    /// the spline already evaluates as its last point, so only that one is
+    /// kept. A valid, sorted curve is left unchanged.
+    ///
+    /// Returns whether any coordinate was clamped.
+    ///
+    /// # Errors
+    /// Returns the error from [`Self::validate`], leaving the curve as is.
+    pub fn sanitize(&mut self) -> Result<bool, CurveError> {
+        self.validate()?;
+
+        let mut clamped = false;
+        for p in &mut self.points {
+            let (x, y) = (p.x.clamp(0.0, 1.0), p.y.clamp(0.0, 1.0));
+            clamped |= x != p.x || y != p.y;
+            *p = CurvePoint::new(x, y);
+        }
+
+        self.points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let mut deduped: Vec<CurvePoint> = Vec::with_capacity(self.points.len());
+        for &p in &self.points {
+            match deduped.last_mut() {
+                Some(last) if (p.x - last.x).abs() < f32::EPSILON => *last = p,
+                _ => deduped.push(p),
+            }
+        }
+        self.points = deduped;
+        Ok(clamped)
+    }
+}
+
+// ============================================================================
+// Levels and Curve
+// ============================================================================
+
+/// A tone curve preceded by levels-style input black and white points.
+///
+/// These are the triangular sliders under a curve panel: inputs are first
+/// remapped as `(x - input_black) / (input_white - input_black)`, clamped
+/// to 0.0-1.0, and the curve is evaluated on the result. The defaults of 0
+/// and 1 leave the curve as is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelsAndCurve {
+    /// Curve applied after the remap
+    pub curve: ToneCurve,
+    /// Input value mapped to 0 (0.0 to 1.0)
+    pub input_black: f32,
+    /// Input value mapped to 1 (0.0 to 1.0)
+    pub input_white: f32,
+}
+
+impl Default for LevelsAndCurve {
+    fn default() -> Self {
+        Self::new(ToneCurve::default())
+    }
+}
+
+impl LevelsAndCurve {
+    /// Wrap `curve` with the default input range.
+    pub fn new(curve: ToneCurve) -> Self {
+        Self {
+            curve,
+            input_black: 0.0,
+            input_white: 1.0,
+        }
+    }
+
+    /// Input black and white points as applied.
+    ///
+    /// Both are clamped to 0.0-1.0 and kept at least [`MIN_INPUT_SPAN`]
+    /// apart, moving the white point up (or the black point down at the
+    /// top of the range). Non-finite values fall back to the defaults.
+    pub fn input_range(&self) -> (f32, f32) {
+        let black = if self.input_black.is_finite() {
+            self.input_black.clamp(0.0, 1.0 - MIN_INPUT_SPAN)
+        } else {
+            0.0
+        };
+        let white = if self.input_white.is_finite() {
+            self.input_white.clamp(black + MIN_INPUT_SPAN, 1.0)
+        } else {
+            1.0
+        };
+        (black, white)
+    }
+
+    /// Check if the input range is the default full range.
+    pub fn is_full_range(&self) -> bool {
+        self.input_range() == (0.0, 1.0)
+    }
+}
 
 // ============================================================================
 // LUT Type
@@ -19,21 +195,108 @@ pub struct ToneCurveLut {
 impl ToneCurveLut {
     /// Generate LUT from a tone curve.
     pub fn from_curve(curve: &ToneCurve) -> Self {
+        let mut lut = Self::identity();
+        lut.rebuild_from_curve(curve);
+        lut
+    }
+
+    /// Regenerate this LUT in place from a tone curve.
+    ///
+    /// Produces the same table as [`Self::from_curve`] without allocating a
+    /// new LUT, so one table can follow a curve being edited.
+    pub fn rebuild_from_curve(&mut self, curve: &ToneCurve) {
         // Fast path for linear curve
         if curve.is_linear() {
-            return Self::identity();
+            *self = Self::identity();
+            return;
         }
 
         let tangents = compute_monotonic_tangents(&curve.points);
-        let lut = core::array::from_fn(|i| {
+        for (i, out) in self.lut.iter_mut().enumerate() {
             let x = i as f32 / 255.0;
             let y = evaluate_with_tangents(&curve.points, &tangents, x);
+            *out = (y * 255.0).clamp(0.0, 255.0).round() as u8;
+        }
+    }
+
+    /// Generate LUT from a curve with input black and white points.
+    ///
+    /// With the full input range this is the same as [`Self::from_curve`].
+    pub fn from_levels_and_curve(levels: &LevelsAndCurve) -> Self {
+        if levels.is_full_range() {
+            return Self::from_curve(&levels.curve);
+        }
+
+        let (black, white) = levels.input_range();
+        let curve = &levels.curve;
+        let tangents = compute_monotonic_tangents(&curve.points);
+        let lut = core::array::from_fn(|i| {
+            let x = ((i as f32 / 255.0 - black) / (white - black)).clamp(0.0, 1.0);
+            let y = if curve.is_linear() {
+                x
+            } else {
+                evaluate_with_tangents(&curve.points, &tangents, x)
+            };
             (y * 255.0).clamp(0.0, 255.0).round() as u8
         });
 
         Self { lut }
     }
 
+    /// Generate LUT from a tone curve, then limit its steps with
+    /// [`Self::limit_steps`].
+    pub fn from_curve_smoothed(curve: &ToneCurve, max_delta: u8) -> Self {
+        let mut lut = Self::from_curve(curve);
+        lut.limit_steps(max_delta);
+        lut
+    }
+
+    /// Spread steep jumps so adjacent entries differ by at most `max_delta`.
+    ///
+    /// Nearly vertical curve segments (a hard threshold look) turn into
+    /// jumps of dozens of levels between neighbouring entries, which
+    /// posterize gradients and amplify JPEG block noise. Each jump is
+    /// relaxed into a ramp of `max_delta` steps, grown symmetrically around
+    /// the jump where there is room. The first and last entries are kept,
+    /// and a monotonic table stays monotonic. A `max_delta` of 0 is treated
+    /// as 1.
+    ///
+    /// Returns `true` if any entry changed; tables that already respect the
+    /// bound are left as they are.
+    pub fn limit_steps(&mut self, max_delta: u8) -> bool {
+        let max_delta = max_delta.max(1) as i32;
+        let clamp_to =
+            |value: i32, neighbour: i32| value.clamp(neighbour - max_delta, neighbour + max_delta);
+        let original = self.lut.map(i32::from);
+
+        // Cap the table sweeping forward then backward, and the other way
+        // round: each pass alone pushes the whole ramp to one side of a jump
+        let mut forward = original;
+        for i in 1..255 {
+            forward[i] = clamp_to(forward[i], forward[i - 1]);
+        }
+        for i in (1..255).rev() {
+            forward[i] = clamp_to(forward[i], forward[i + 1]);
+        }
+        let mut backward = original;
+        for i in (1..255).rev() {
+            backward[i] = clamp_to(backward[i], backward[i + 1]);
+        }
+        for i in 1..255 {
+            backward[i] = clamp_to(backward[i], backward[i - 1]);
+        }
+
+        let mut changed = false;
+        for (i, out) in self.lut.iter_mut().enumerate() {
+            // Averaging two tables within the bound stays within the bound,
+            // and rounding half up the same way keeps it so
+            let value = ((forward[i] + backward[i] + 1) / 2) as u8;
+            changed |= value != *out;
+            *out = value;
+        }
+        changed
+    }
+
     /// Create identity LUT (no change).
     pub fn identity() -> Self {
         Self {
@@ -45,6 +308,20 @@ impl ToneCurveLut {
     pub fn is_identity(&self) -> bool {
         self.lut.iter().enumerate().all(|(i, &v)| v == i as u8)
     }
+
+    /// Look up a normalized value (0.0 to 1.0), interpolating between entries.
+    ///
+    /// Used where pixels are already in float form (e.g. mask blending), so the
+    /// curve doesn't add an extra 8-bit quantization step.
+    #[inline]
+    pub fn sample(&self, value: f32) -> f32 {
+        let pos = value.clamp(0.0, 1.0) * 255.0;
+        let i = (pos as usize).min(254);
+        let t = pos - i as f32;
+        let lo = self.lut[i] as f32;
+        let hi = self.lut[i + 1] as f32;
+        (lo + (hi - lo) * t) / 255.0
+    }
 }
 
 impl Default for ToneCurveLut {
@@ -62,7 +339,13 @@ impl Default for ToneCurveLut {
 /// # Arguments
 /// * `pixels` - RGB pixel data (3 bytes per pixel)
 /// * `lut` - Pre-computed lookup table
+///
+/// Only complete RGB triples are processed, so an empty or truncated buffer
+/// never panics (see [`crate::validate`]).
 pub fn apply_tone_curve(pixels: &mut [u8], lut: &ToneCurveLut) {
+    let _perf = perf::scope("apply_tone_curve");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
     // Early exit for identity
     if lut.is_identity() {
         return;
@@ -75,6 +358,61 @@ pub fn apply_tone_curve(pixels: &mut [u8], lut: &ToneCurveLut) {
     }
 }
 
+/// Apply a tone curve LUT to a subsampled preview of `image`.
+///
+/// The curve counterpart of
+/// [`apply_adjustments_subsampled`](crate::adjustments::apply_adjustments_subsampled):
+/// exactly [`subsample`] followed by [`apply_tone_curve`], in one pass.
+///
+/// # Errors
+/// Returns `DecodeError::InvalidImage` if the image is empty or its buffer
+/// does not match its dimensions.
+pub fn apply_tone_curve_subsampled(
+    image: &DecodedImage,
+    lut: &ToneCurveLut,
+    shift: u8,
+) -> Result<DecodedImage, DecodeError> {
+    if lut.is_identity() {
+        return subsample(image, shift);
+    }
+    subsample_map(image, shift, |px| {
+        for v in px {
+            *v = lut.lut[*v as usize];
+        }
+    })
+}
+
+/// Apply a tone curve to float RGB pixels in place.
+///
+/// The spline is evaluated directly instead of through an 8-bit LUT. Values
+/// outside 0.0-1.0 continue from the curve's end points with slope 1, so HDR
+/// headroom is kept and an identity curve changes nothing.
+///
+/// Only complete RGB triples are processed, as in [`apply_tone_curve`].
+pub fn apply_tone_curve_f32(pixels: &mut [f32], curve: &ToneCurve) {
+    let _perf = perf::scope("apply_tone_curve_f32");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    if curve.is_linear() {
+        return;
+    }
+
+    let tangents = compute_monotonic_tangents(&curve.points);
+    let eval = |x: f32| evaluate_with_tangents(&curve.points, &tangents, x);
+    let (low, high) = (eval(0.0), eval(1.0));
+    for chunk in pixels.chunks_exact_mut(3) {
+        for v in chunk {
+            *v = if *v > 1.0 {
+                high + (*v - 1.0)
+            } else if *v < 0.0 {
+                low + *v
+            } else {
+                eval(*v)
+            };
+        }
+    }
+}
+
 // ============================================================================
 // Monotonic Cubic Hermite Spline (Fritsch-Carlson)
 // ============================================================================
@@ -143,6 +481,11 @@ fn clamp_tangent(tangent: f32, delta: f32) -> f32 {
 
 /// Evaluate curve at x with pre-computed tangents.
 fn evaluate_with_tangents(points: &[CurvePoint], tangents: &[f32], x: f32) -> f32 {
+    hermite(points, tangents, x).clamp(0.0, 1.0)
+}
+
+/// Evaluate the spline through `points` at x, without clamping the output.
+fn hermite(points: &[CurvePoint], tangents: &[f32], x: f32) -> f32 {
     let n = points.len();
 
     if n == 0 {
@@ -176,9 +519,7 @@ fn evaluate_with_tangents(points: &[CurvePoint], tangents: &[f32], x: f32) -> f3
     let h01 = -2.0 * t3 + 3.0 * t2;
     let h11 = t3 - t2;
 
-    let y = h00 * p0.y + h10 * h * tangents[i] + h01 * p1.y + h11 * h * tangents[i + 1];
-
-    y.clamp(0.0, 1.0)
+    h00 * p0.y + h10 * h * tangents[i] + h01 * p1.y + h11 * h * tangents[i + 1]
 }
 
 /// Find the interval containing x using binary search.
@@ -203,6 +544,197 @@ pub fn evaluate_curve(curve: &ToneCurve, x: f32) -> f32 {
     evaluate_with_tangents(&curve.points, &tangents, x)
 }
 
+// ============================================================================
+// Hue Curves
+// ============================================================================
+
+/// Entries in a hue curve LUT, one per degree.
+pub const HUE_LUT_SIZE: usize = 360;
+
+/// Spacing in degrees of the neutral anchors a hue curve is pinned to.
+pub const HUE_CURVE_ANCHOR_SPACING: f32 = 60.0;
+
+/// Hue rotation in degrees for a hue-vs-hue value of 1.0.
+pub const MAX_HUE_SHIFT_DEGREES: f32 = 180.0;
+
+/// Oklab lightness change for a hue-vs-luma value of 1.0.
+pub const MAX_LUMA_SHIFT: f32 = 0.25;
+
+/// Oklch chroma at which hue curves take full effect.
+///
+/// The hue of a nearly gray pixel is mostly noise, so the effect fades out
+/// below this chroma and grays are never touched.
+const HUE_CURVE_FULL_CHROMA: f32 = 0.04;
+
+/// A curve over hue, for Capture One style hue-vs-X adjustments.
+///
+/// Point `x` is an Oklch hue in degrees (0.0 to 360.0; red is near 30,
+/// yellow 110, green 140, cyan 195, blue 265 and magenta 330) and `y` the
+/// shift at that hue, from -1.0 to 1.0 with 0.0 for no change.
+///
+/// The curve is periodic: it wraps around at 360 degrees, so a point at
+/// 350 degrees shapes the curve at 5 degrees as well. Like the fixed ends of
+/// a tone curve, neutral anchors every [`HUE_CURVE_ANCHOR_SPACING`] degrees
+/// keep a point's effect local; an anchor is dropped when a point lies
+/// within half the spacing of it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HueCurve {
+    /// Control points; x outside 0.0-360.0 wraps around
+    pub points: Vec<CurvePoint>,
+}
+
+impl HueCurve {
+    /// Create a hue curve from control points.
+    pub fn new(points: Vec<CurvePoint>) -> Self {
+        Self { points }
+    }
+
+    /// Check if the curve leaves every hue unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.points.iter().all(|p| p.y == 0.0 || !p.y.is_finite())
+    }
+
+    /// Evaluate the curve at `hue` degrees.
+    ///
+    /// Used for drawing the curve in the UI; rendering samples the LUT.
+    pub fn evaluate(&self, hue: f32) -> f32 {
+        let points = self.periodic_points();
+        let tangents = compute_monotonic_tangents(&points);
+        hermite(&points, &tangents, hue.rem_euclid(360.0)).clamp(-1.0, 1.0)
+    }
+
+    /// Sample the curve at every whole degree.
+    pub fn lut(&self) -> [f32; HUE_LUT_SIZE] {
+        let points = self.periodic_points();
+        let tangents = compute_monotonic_tangents(&points);
+        core::array::from_fn(|i| hermite(&points, &tangents, i as f32).clamp(-1.0, 1.0))
+    }
+
+    /// The control points and anchors sorted by hue, with the last two
+    /// repeated one turn earlier and the first two one turn later, so the
+    /// spline and its tangents are continuous across 0/360.
+    fn periodic_points(&self) -> Vec<CurvePoint> {
+        let mut points: Vec<CurvePoint> = self
+            .points
+            .iter()
+            .filter(|p| p.x.is_finite() && p.y.is_finite())
+            .map(|p| CurvePoint::new(p.x.rem_euclid(360.0), p.y.clamp(-1.0, 1.0)))
+            .collect();
+        let anchors = (0..(360.0 / HUE_CURVE_ANCHOR_SPACING) as usize)
+            .map(|i| i as f32 * HUE_CURVE_ANCHOR_SPACING)
+            .filter(|&anchor| {
+                points
+                    .iter()
+                    .all(|p| hue_distance(p.x, anchor) >= HUE_CURVE_ANCHOR_SPACING / 2.0)
+            })
+            .map(|anchor| CurvePoint::new(anchor, 0.0))
+            .collect::<Vec<_>>();
+        points.extend(anchors);
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+        let n = points.len();
+        let before =
+            (n.saturating_sub(2)..n).map(|i| CurvePoint::new(points[i].x - 360.0, points[i].y));
+        let after = (0..n.min(2)).map(|i| CurvePoint::new(points[i].x + 360.0, points[i].y));
+        before.chain(points.iter().copied()).chain(after).collect()
+    }
+}
+
+/// Distance between two hues in degrees, going the short way round.
+fn hue_distance(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(360.0);
+    d.min(360.0 - d)
+}
+
+/// Hue-indexed curves shifting hue, saturation and luminance.
+///
+/// Every curve is looked up at the pixel's original hue. Missing curves
+/// leave their property unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HueCurveSet {
+    /// Hue rotation, up to [`MAX_HUE_SHIFT_DEGREES`] either way
+    pub hue_vs_hue: Option<HueCurve>,
+    /// Chroma scale: -1.0 removes all color, 1.0 doubles it
+    pub hue_vs_sat: Option<HueCurve>,
+    /// Lightness shift, up to [`MAX_LUMA_SHIFT`] either way
+    pub hue_vs_luma: Option<HueCurve>,
+}
+
+impl HueCurveSet {
+    /// Check if no curve changes anything.
+    pub fn is_identity(&self) -> bool {
+        [&self.hue_vs_hue, &self.hue_vs_sat, &self.hue_vs_luma]
+            .iter()
+            .all(|curve| curve.as_ref().is_none_or(HueCurve::is_identity))
+    }
+}
+
+/// Look up a hue LUT at `hue` degrees, interpolating between entries and
+/// wrapping from 359 back to 0.
+#[inline]
+fn sample_hue_lut(lut: &[f32; HUE_LUT_SIZE], hue: f32) -> f32 {
+    let pos = hue.rem_euclid(360.0);
+    let i = (pos as usize).min(HUE_LUT_SIZE - 1);
+    let t = pos - i as f32;
+    let (lo, hi) = (lut[i], lut[(i + 1) % HUE_LUT_SIZE]);
+    lo + (hi - lo) * t
+}
+
+/// Apply hue-vs-hue, hue-vs-saturation and hue-vs-luminance curves to RGB
+/// pixels in place.
+///
+/// Pixels are converted to Oklch, the curves are looked up in 360-entry
+/// LUTs at the pixel's hue, and the result is brought back inside sRGB by
+/// reducing chroma at constant hue. Pixels whose hue the curves do not
+/// touch, and grays, are left exactly as they are.
+///
+/// Only complete RGB triples are processed, as in [`apply_tone_curve`].
+pub fn apply_hue_curves(pixels: &mut [u8], curves: &HueCurveSet) {
+    let _perf = perf::scope("apply_hue_curves");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    if curves.is_identity() {
+        return;
+    }
+    let lut = |curve: &Option<HueCurve>| {
+        curve
+            .as_ref()
+            .filter(|curve| !curve.is_identity())
+            .map(HueCurve::lut)
+    };
+    let (hue_lut, sat_lut, luma_lut) = (
+        lut(&curves.hue_vs_hue),
+        lut(&curves.hue_vs_sat),
+        lut(&curves.hue_vs_luma),
+    );
+    let sample = |lut: &Option<[f32; HUE_LUT_SIZE]>, hue: f32| {
+        lut.as_ref().map_or(0.0, |lut| sample_hue_lut(lut, hue))
+    };
+
+    for chunk in pixels.chunks_exact_mut(3) {
+        let rgb = [chunk[0], chunk[1], chunk[2]].map(|v| v as f32 / 255.0);
+        let lch = srgb_to_oklab(rgb).to_oklch();
+        let weight = (lch.c / HUE_CURVE_FULL_CHROMA).min(1.0);
+        let hue_shift = sample(&hue_lut, lch.h) * weight;
+        let sat_shift = sample(&sat_lut, lch.h) * weight;
+        let luma_shift = sample(&luma_lut, lch.h) * weight;
+        if hue_shift == 0.0 && sat_shift == 0.0 && luma_shift == 0.0 {
+            continue;
+        }
+
+        let shifted = Oklch {
+            l: lch.l + luma_shift * MAX_LUMA_SHIFT,
+            c: lch.c * (1.0 + sat_shift),
+            h: lch.h + hue_shift * MAX_HUE_SHIFT_DEGREES,
+        };
+        let rgb = oklch_to_srgb_in_gamut(shifted);
+        for (out, v) in chunk.iter_mut().zip(rgb) {
+            *out = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -210,6 +742,7 @@ pub fn evaluate_curve(curve: &ToneCurve, x: f32) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{gradient, noise, GradientDirection};
 
     fn linear_curve() -> ToneCurve {
         ToneCurve::default()
@@ -226,6 +759,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_input_black_maps_shadows_to_curve_start() {
+        let levels = LevelsAndCurve {
+            input_black: 0.1,
+            ..LevelsAndCurve::new(s_curve())
+        };
+        let lut = ToneCurveLut::from_levels_and_curve(&levels);
+        let start = (evaluate_curve(&s_curve(), 0.0) * 255.0).round() as u8;
+        for i in 0..=(0.1f32 * 255.0) as usize {
+            assert_eq!(lut.lut[i], start, "input {}", i);
+        }
+        assert!(lut.lut[30] > start);
+        assert_eq!(lut.lut[255], 255);
+    }
+
+    #[test]
+    fn test_linear_curve_with_input_range_is_levels() {
+        let (black, white) = (0.2f32, 0.8f32);
+        let levels = LevelsAndCurve {
+            input_black: black,
+            input_white: white,
+            ..LevelsAndCurve::default()
+        };
+        let lut = ToneCurveLut::from_levels_and_curve(&levels);
+
+        let gain = 1.0 / (white - black);
+        let offset = -black * 255.0 * gain;
+        for (v, &out) in lut.lut.iter().enumerate() {
+            let expected = (v as f32 * gain + offset).clamp(0.0, 255.0).round() as u8;
+            assert!(
+                out.abs_diff(expected) <= 1,
+                "input {}: {} vs {}",
+                v,
+                out,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_input_range_matches_from_curve() {
+        for curve in [linear_curve(), s_curve()] {
+            let levels = LevelsAndCurve::new(curve.clone());
+            assert_eq!(
+                ToneCurveLut::from_levels_and_curve(&levels).lut,
+                ToneCurveLut::from_curve(&curve).lut
+            );
+        }
+    }
+
+    #[test]
+    fn test_degenerate_input_range_keeps_min_span() {
+        let levels = LevelsAndCurve {
+            input_black: 0.6,
+            input_white: 0.4,
+            ..LevelsAndCurve::default()
+        };
+        let (black, white) = levels.input_range();
+        assert_eq!(black, 0.6);
+        assert!((white - black - MIN_INPUT_SPAN).abs() < 1e-6);
+
+        // A hard threshold at the black point, with no NaN entries
+        let lut = ToneCurveLut::from_levels_and_curve(&levels);
+        assert_eq!(lut.lut[150], 0);
+        assert_eq!(lut.lut[160], 255);
+
+        let top = LevelsAndCurve {
+            input_black: 1.0,
+            input_white: f32::NAN,
+            ..LevelsAndCurve::default()
+        };
+        assert_eq!(top.input_range(), (1.0 - MIN_INPUT_SPAN, 1.0));
+    }
+
     #[test]
     fn test_identity_lut() {
         let lut = ToneCurveLut::identity();
@@ -235,6 +842,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sample_matches_lut_entries() {
+        let curve = ToneCurve {
+            points: vec![CurvePoint::new(0.0, 1.0), CurvePoint::new(1.0, 0.0)],
+        };
+        let lut = ToneCurveLut::from_curve(&curve);
+
+        for i in [0usize, 1, 64, 128, 254, 255] {
+            let expected = lut.lut[i] as f32 / 255.0;
+            assert!((lut.sample(i as f32 / 255.0) - expected).abs() < 1e-4);
+        }
+
+        // Out-of-range input is clamped
+        assert_eq!(lut.sample(-1.0), lut.lut[0] as f32 / 255.0);
+        assert_eq!(lut.sample(2.0), lut.lut[255] as f32 / 255.0);
+    }
+
+    #[test]
+    fn test_sample_interpolates() {
+        let lut = ToneCurveLut::identity();
+        let mid = (10.5f32) / 255.0;
+        assert!((lut.sample(mid) - mid).abs() < 1e-6);
+    }
+
     #[test]
     fn test_linear_curve_produces_identity_lut() {
         let curve = linear_curve();
@@ -250,6 +881,152 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rebuild_from_curve_matches_fresh_lut() {
+        let mut lut = ToneCurveLut::identity();
+        lut.rebuild_from_curve(&s_curve());
+        assert_eq!(lut.lut, ToneCurveLut::from_curve(&s_curve()).lut);
+        assert!(!lut.is_identity());
+
+        lut.rebuild_from_curve(&linear_curve());
+        assert!(lut.is_identity());
+    }
+
+    #[test]
+    fn test_validate_rejects_too_many_and_non_finite_points() {
+        assert_eq!(s_curve().validate(), Ok(()));
+        assert_eq!(ToneCurve { points: vec![] }.validate(), Ok(()));
+
+        let many = ToneCurve {
+            points: (0..=MAX_CURVE_POINTS)
+                .map(|i| {
+                    let x = i as f32 / MAX_CURVE_POINTS as f32;
+                    CurvePoint::new(x, x)
+                })
+                .collect(),
+        };
+        let err = many.validate().unwrap_err();
+        assert_eq!(err, CurveError::TooManyPoints { count: 65, max: 64 });
+        assert_eq!(err.code(), "TOO_MANY_CURVE_POINTS");
+        assert!(err.to_string().contains("65 control points"));
+
+        for bad in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let mut curve = s_curve();
+            curve.points[2].y = bad;
+            let err = curve.validate().unwrap_err();
+            assert_eq!(err, CurveError::NonFinitePoint { index: 2 });
+            assert_eq!(err.code(), "NON_FINITE_CURVE_POINT");
+        }
+    }
+
+    #[test]
+    fn test_sanitize_clamps_sorts_and_dedups() {
+        let mut curve = ToneCurve {
+            points: vec![
+                CurvePoint::new(0.75, 0.85),
+                CurvePoint::new(-0.2, 0.0),
+                CurvePoint::new(0.25, 0.1),
+                CurvePoint::new(0.25, 0.15),
+                CurvePoint::new(1.0, 1.4),
+            ],
+        };
+        assert_eq!(curve.sanitize(), Ok(true));
+        // Clamped, sorted, and the last of the points at 0.25 kept
+        assert_eq!(curve, s_curve());
+
+        let mut nan = s_curve();
+        nan.points[1].x = f32::NAN;
+        assert!(nan.sanitize().is_err());
+        assert!(nan.points[1].x.is_nan());
+    }
+
+    #[test]
+    fn test_sanitize_leaves_valid_curve_unchanged() {
+        let mut curve = s_curve();
+        assert_eq!(curve.sanitize(), Ok(false));
+        assert_eq!(curve, s_curve());
+        assert_eq!(
+            ToneCurveLut::from_curve(&curve).lut,
+            ToneCurveLut::from_curve(&s_curve()).lut
+        );
+    }
+
+    /// Largest difference between adjacent LUT entries.
+    fn max_step(lut: &ToneCurveLut) -> u8 {
+        lut.lut
+            .windows(2)
+            .map(|w| w[0].abs_diff(w[1]))
+            .max()
+            .unwrap()
+    }
+
+    /// A hard threshold at mid-gray.
+    fn step_curve() -> ToneCurve {
+        ToneCurve {
+            points: vec![
+                CurvePoint::new(0.0, 0.0),
+                CurvePoint::new(0.49, 0.0),
+                CurvePoint::new(0.51, 1.0),
+                CurvePoint::new(1.0, 1.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_limit_steps_bounds_step_curve() {
+        let curve = step_curve();
+        let exact = ToneCurveLut::from_curve(&curve);
+        assert!(max_step(&exact) > 32);
+
+        for max_delta in [1, 4, 8, 32] {
+            let mut lut = exact.clone();
+            assert!(lut.limit_steps(max_delta));
+            assert!(max_step(&lut) <= max_delta, "max_delta {}", max_delta);
+            assert_eq!((lut.lut[0], lut.lut[255]), (exact.lut[0], exact.lut[255]));
+            assert!(lut.lut.windows(2).all(|w| w[0] <= w[1]));
+            assert_eq!(
+                lut.lut,
+                ToneCurveLut::from_curve_smoothed(&curve, max_delta).lut
+            );
+        }
+
+        // The ramp is centered on the jump
+        let lut = ToneCurveLut::from_curve_smoothed(&curve, 8);
+        let first = lut.lut.iter().position(|&v| v > 0).unwrap();
+        let last = lut.lut.iter().position(|&v| v == 255).unwrap();
+        let jump = exact.lut.iter().position(|&v| v > 127).unwrap();
+        assert!(
+            first < jump && jump < last,
+            "{}..{} around {}",
+            first,
+            last,
+            jump
+        );
+        assert!((first + last).abs_diff(2 * jump) <= 2);
+    }
+
+    #[test]
+    fn test_limit_steps_inverted_step_stays_decreasing() {
+        let mut curve = step_curve();
+        for point in &mut curve.points {
+            point.y = 1.0 - point.y;
+        }
+        let lut = ToneCurveLut::from_curve_smoothed(&curve, 6);
+        assert_eq!((lut.lut[0], lut.lut[255]), (255, 0));
+        assert!(max_step(&lut) <= 6);
+        assert!(lut.lut.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn test_limit_steps_leaves_gentle_curves_alone() {
+        for curve in [linear_curve(), s_curve()] {
+            let exact = ToneCurveLut::from_curve(&curve);
+            let mut lut = exact.clone();
+            assert!(!lut.limit_steps(8));
+            assert_eq!(lut.lut, exact.lut);
+        }
+    }
+
     #[test]
     fn test_s_curve_increases_contrast() {
         let curve = s_curve();
@@ -293,7 +1070,7 @@ mod tests {
 
     #[test]
     fn test_apply_tone_curve_identity() {
-        let original = vec![0, 64, 128, 192, 255, 100];
+        let original = noise(16, 16, 1).pixels;
         let mut pixels = original.clone();
         let lut = ToneCurveLut::identity();
 
@@ -315,6 +1092,23 @@ mod tests {
         assert!(pixels[3] > 192, "Bright pixel not brightened");
     }
 
+    #[test]
+    fn test_apply_tone_curve_f32_matches_lut_and_keeps_headroom() {
+        let curve = s_curve();
+        let lut = ToneCurveLut::from_curve(&curve);
+        let mut pixels = vec![0.25, 0.5, 0.75, 1.5, -0.25, 1.0];
+
+        apply_tone_curve_f32(&mut pixels, &curve);
+
+        for (&v, x) in pixels[..3].iter().zip([0.25f32, 0.5, 0.75]) {
+            let expected = lut.lut[(x * 255.0).round() as usize] as f32 / 255.0;
+            assert!((v - expected).abs() <= 1.0 / 255.0, "{} vs {}", v, expected);
+        }
+        // Outside 0-1 the curve continues with slope 1 from its end points
+        assert!((pixels[3] - (pixels[5] + 0.5)).abs() < 1e-6);
+        assert!((pixels[4] - (evaluate_curve(&curve, 0.0) - 0.25)).abs() < 1e-6);
+    }
+
     #[test]
     fn test_steep_curve_no_overshoot() {
         let curve = ToneCurve {
@@ -385,6 +1179,148 @@ mod tests {
         let y = evaluate_curve(&curve, 0.5);
         assert!((y - 0.5).abs() < 0.01);
     }
+
+    #[test]
+    fn test_apply_tone_curve_reports_pixel_count() {
+        let lut = ToneCurveLut::from_curve(&s_curve());
+        let mut pixels = vec![100u8; 17 * 9 * 3];
+
+        apply_tone_curve(&mut pixels, &lut);
+
+        let stats = crate::perf::last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "apply_tone_curve");
+        assert_eq!(stats.pixels_processed, 17 * 9);
+    }
+
+    #[test]
+    fn test_s_curve_keeps_gradient_ordered() {
+        let mut image = gradient(256, 1, GradientDirection::Horizontal);
+        apply_tone_curve(&mut image.pixels, &ToneCurveLut::from_curve(&s_curve()));
+
+        assert_eq!(image.pixels[0], 0);
+        assert_eq!(image.pixels[255 * 3], 255);
+        assert!(image.pixels.windows(4).step_by(3).all(|w| w[0] <= w[3]));
+    }
+
+    #[test]
+    fn test_subsampled_curve_matches_subsample_then_curve() {
+        let image = noise(29, 19, 6);
+        let lut = ToneCurveLut::from_curve(&s_curve());
+        for shift in 0..3 {
+            let mut expected = subsample(&image, shift).unwrap();
+            apply_tone_curve(&mut expected.pixels, &lut);
+            let preview = apply_tone_curve_subsampled(&image, &lut, shift).unwrap();
+            assert_eq!(
+                (preview.width, preview.height),
+                (expected.width, expected.height)
+            );
+            assert_eq!(preview.pixels, expected.pixels);
+        }
+    }
+
+    // ===== Hue Curves =====
+
+    fn hue_of(pixel: &[u8]) -> f32 {
+        srgb_to_oklab([pixel[0], pixel[1], pixel[2]].map(|v| v as f32 / 255.0))
+            .to_oklch()
+            .h
+    }
+
+    #[test]
+    fn test_hue_point_at_120_shifts_greens_not_reds() {
+        let curves = HueCurveSet {
+            hue_vs_hue: Some(HueCurve::new(vec![CurvePoint::new(120.0, 0.1)])),
+            ..Default::default()
+        };
+        let (green, red) = ([40u8, 200, 60], [200u8, 40, 40]);
+        let mut pixels = [green, red].concat();
+        apply_hue_curves(&mut pixels, &curves);
+
+        assert!(hue_of(&pixels[0..3]) > hue_of(&green) + 1.0, "{:?}", pixels);
+        assert_eq!(&pixels[3..6], &red);
+    }
+
+    #[test]
+    fn test_hue_curve_wraps_around_360() {
+        let curve = HueCurve::new(vec![CurvePoint::new(350.0, 0.5)]);
+        assert!((curve.evaluate(350.0) - 0.5).abs() < 1e-6);
+        // The point bends the curve past 0 degrees
+        assert!(curve.evaluate(5.0) > 0.1);
+        assert!((curve.evaluate(360.0) - curve.evaluate(0.0)).abs() < 1e-6);
+        assert!((curve.evaluate(-10.0) - curve.evaluate(350.0)).abs() < 1e-6);
+
+        // No jump anywhere near the seam
+        let mut previous = curve.evaluate(340.0);
+        for step in 1..=400 {
+            let value = curve.evaluate(340.0 + step as f32 * 0.1);
+            assert!((value - previous).abs() < 0.01, "jump at step {}", step);
+            previous = value;
+        }
+
+        let lut = curve.lut();
+        assert!((lut[359] - lut[0]).abs() < 0.05);
+        assert!((sample_hue_lut(&lut, 359.5) - (lut[359] + lut[0]) / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_hue_curve_anchors_keep_points_local() {
+        let curve = HueCurve::new(vec![CurvePoint::new(120.0, 1.0)]);
+        assert_eq!(curve.evaluate(30.0), 0.0);
+        assert_eq!(curve.evaluate(240.0), 0.0);
+        assert!(curve.evaluate(90.0) > 0.0 && curve.evaluate(150.0) > 0.0);
+        // The spline does not overshoot the point
+        assert!((0..360).all(|h| curve.evaluate(h as f32) <= 1.0));
+    }
+
+    #[test]
+    fn test_identity_hue_curves_are_noop() {
+        let flat = HueCurve::new(vec![
+            CurvePoint::new(30.0, 0.0),
+            CurvePoint::new(200.0, 0.0),
+        ]);
+        let sets = [
+            HueCurveSet::default(),
+            HueCurveSet {
+                hue_vs_hue: Some(HueCurve::default()),
+                hue_vs_sat: Some(flat.clone()),
+                hue_vs_luma: Some(flat),
+            },
+        ];
+        let image = noise(32, 24, 3);
+        for curves in sets {
+            assert!(curves.is_identity());
+            let mut pixels = image.pixels.clone();
+            apply_hue_curves(&mut pixels, &curves);
+            assert_eq!(pixels, image.pixels);
+        }
+    }
+
+    #[test]
+    fn test_hue_vs_sat_and_luma_leave_grays_alone() {
+        let everywhere: Vec<_> = (0..6)
+            .map(|i| CurvePoint::new(i as f32 * 60.0, -1.0))
+            .collect();
+        let curves = HueCurveSet {
+            hue_vs_sat: Some(HueCurve::new(everywhere.clone())),
+            hue_vs_luma: Some(HueCurve::new(everywhere)),
+            ..Default::default()
+        };
+        let mut grays = gradient(256, 1, GradientDirection::Horizontal);
+        let original = grays.pixels.clone();
+        apply_hue_curves(&mut grays.pixels, &curves);
+        assert_eq!(grays.pixels, original);
+
+        // A saturated blue loses its color and gets darker
+        let blue = [40u8, 70, 200];
+        let mut pixels = blue;
+        apply_hue_curves(&mut pixels, &curves);
+        let spread = pixels.iter().max().unwrap() - pixels.iter().min().unwrap();
+        assert!(spread <= 2, "{:?}", pixels);
+        assert!(
+            srgb_to_oklab(pixels.map(|v| v as f32 / 255.0)).l
+                < srgb_to_oklab(blue.map(|v| v as f32 / 255.0)).l
+        );
+    }
 }
 
 // ============================================================================
@@ -396,6 +1332,14 @@ mod proptests {
     use super::*;
     use proptest::prelude::*;
 
+    fn max_step_of(lut: &ToneCurveLut) -> u8 {
+        lut.lut
+            .windows(2)
+            .map(|w| w[0].abs_diff(w[1]))
+            .max()
+            .unwrap()
+    }
+
     /// Strategy to generate valid curve points sorted by x value.
     fn sorted_curve_points_strategy(
         min_points: usize,
@@ -462,6 +1406,30 @@ mod proptests {
     }
 
     proptest! {
+        /// Property: Limiting steps bounds every step, keeps both endpoints
+        /// and never breaks monotonicity.
+        #[test]
+        fn prop_limit_steps_bounds_and_keeps_shape(
+            values in prop::collection::vec(any::<u8>(), 256),
+            max_delta in 0u8..=40,
+            monotone in any::<bool>(),
+        ) {
+            let mut lut = ToneCurveLut::identity();
+            lut.lut.copy_from_slice(&values);
+            if monotone {
+                lut.lut.sort_unstable();
+            }
+            let original = lut.clone();
+            lut.limit_steps(max_delta);
+
+            prop_assert!(max_step_of(&lut) <= max_delta.max(1));
+            prop_assert_eq!(lut.lut[0], original.lut[0]);
+            prop_assert_eq!(lut.lut[255], original.lut[255]);
+            if monotone {
+                prop_assert!(lut.lut.windows(2).all(|w| w[0] <= w[1]));
+            }
+        }
+
         /// Property: Output is always within [0, 1] range.
         #[test]
         fn prop_output_in_range(
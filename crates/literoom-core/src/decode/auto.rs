@@ -0,0 +1,436 @@
+//! Decoding any supported file with one call, reporting how it was read.
+//!
+//! [`decode_auto`] sniffs the format from the leading bytes and runs the
+//! decoders that apply to it in a fixed order:
+//!
+//! 1. **JPEG** (starts with SOI): decoded directly, as [`decode_jpeg`]
+//!    does. Both preferences give the same image.
+//! 2. **PNG**: decoded directly to 8-bit RGB; alpha is dropped.
+//! 3. **TIFF-based RAW**: the embedded JPEG preview, searched for as
+//!    [`extract_raw_thumbnail_with_location`] does (SubIFDs, then the IFD
+//!    chain, then a marker scan). Full RAW decoding is not available, so
+//!    preferring full quality falls back to the preview with a warning.
+//! 4. **HEIC/HEIF**: the embedded JPEG preview, with the same fallback.
+//!
+//! Every step that did not go the preferred way adds a [`DecodeWarning`]
+//! to the result, so the UI can tell a full-size decode from a small
+//! thumbnail dug out of a damaged RAW. When no decoder succeeds, the error
+//! lists what was tried.
+//!
+//! [`decode_jpeg`]: super::decode_jpeg
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::heif::extract_heif_preview;
+use super::jpeg::{decode_jpeg_with_options, JpegDecodeOptions};
+use super::raw_thumbnail::{extract_raw_thumbnail_with_location, PreviewLocation};
+use super::resize::resize_to_fit;
+use super::types::{DecodeError, DecodedImage, FilterType};
+use super::{sniff_image_format, ImageFormat};
+use crate::perf;
+
+/// Which image [`decode_auto`] should aim for when a file holds several.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodePreference {
+    /// The embedded preview of RAW and HEIF files, which is fast to decode
+    #[default]
+    EmbeddedPreview,
+    /// The full image data, falling back to the preview where it cannot be
+    /// decoded
+    FullQuality,
+}
+
+/// Options for [`decode_auto`].
+///
+/// Missing fields deserialize to their defaults, so `{}` decodes the
+/// embedded preview at its own size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoDecodeOptions {
+    /// Which image to aim for
+    pub prefer: DecodePreference,
+    /// Resize the result to fit this longest edge (Lanczos3); images that
+    /// already fit are returned unchanged
+    pub max_edge: Option<u32>,
+}
+
+/// The decoder that produced the image of [`decode_auto`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeSource {
+    /// A JPEG file
+    Jpeg,
+    /// A PNG file
+    Png,
+    /// The JPEG preview embedded in a RAW file
+    RawEmbedded,
+    /// The JPEG preview embedded in a HEIC/HEIF file
+    HeifEmbedded,
+}
+
+impl DecodeSource {
+    /// Stable snake_case name, as used across the WASM boundary.
+    pub fn name(self) -> &'static str {
+        match self {
+            DecodeSource::Jpeg => "jpeg",
+            DecodeSource::Png => "png",
+            DecodeSource::RawEmbedded => "raw_embedded",
+            DecodeSource::HeifEmbedded => "heif_embedded",
+        }
+    }
+}
+
+/// A step of [`decode_auto`] that did not go the preferred way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DecodeWarning {
+    /// Full quality was preferred, but the format's image data cannot be
+    /// decoded, so its embedded preview was used
+    FullQualityUnavailable,
+    /// The RAW file has no SubIFD preview; the JPEG referenced from this
+    /// IFD of the main chain (0 = IFD0) was used, which is often smaller
+    PreviewFromIfd { ifd: usize },
+    /// No IFD references a preview; the JPEG was found by scanning the file
+    /// for markers
+    PreviewFromMarkerScan,
+    /// The EXIF orientation was outside 1-8 and was ignored
+    InvalidOrientation { value: u32 },
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::FullQualityUnavailable => {
+                f.write_str("full-quality decoding is not available; used the embedded preview")
+            }
+            DecodeWarning::PreviewFromIfd { ifd } => {
+                write!(f, "no SubIFD preview; used the preview in IFD{ifd}")
+            }
+            DecodeWarning::PreviewFromMarkerScan => f.write_str(
+                "no preview referenced from an IFD; found one by scanning for JPEG markers",
+            ),
+            DecodeWarning::InvalidOrientation { value } => {
+                write!(f, "ignored invalid EXIF orientation {value}")
+            }
+        }
+    }
+}
+
+/// Image produced by [`decode_auto`], with how it was obtained.
+#[derive(Debug, Clone)]
+pub struct DecodedResult {
+    /// Decoded image, upright and in sRGB, resized to fit `max_edge`
+    pub image: DecodedImage,
+    /// Decoder that produced the image
+    pub source: DecodeSource,
+    /// Fallbacks taken on the way, in the order they happened
+    pub warnings: Vec<DecodeWarning>,
+}
+
+/// Detect a file's format and decode it with the first decoder that
+/// succeeds, in the order described in the [module docs](self).
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidFormat` if the format is not recognized,
+/// `DecodeError::NoDecoderSucceeded` listing each attempt and fallback when
+/// a RAW or HEIF file has no usable preview, the errors of
+/// [`decode_jpeg`](super::decode_jpeg) for a JPEG that cannot be decoded,
+/// `DecodeError::CorruptedFile` for a broken PNG, and those of
+/// [`resize_to_fit`] for a `max_edge` of zero.
+pub fn decode_auto(
+    bytes: &[u8],
+    options: &AutoDecodeOptions,
+) -> Result<DecodedResult, DecodeError> {
+    let _perf = perf::scope("decode_auto");
+    let mut warnings = Vec::new();
+
+    let format = sniff_image_format(bytes).ok_or(DecodeError::InvalidFormat)?;
+    let (image, source) = match format {
+        ImageFormat::Jpeg => {
+            let image = decode_jpeg_noting(bytes, &mut warnings)?;
+            (image, DecodeSource::Jpeg)
+        }
+        ImageFormat::Png => (decode_png(bytes)?, DecodeSource::Png),
+        ImageFormat::Tiff => {
+            let image = decode_embedded(options.prefer, &mut warnings, |warnings| {
+                let (jpeg, location) = extract_raw_thumbnail_with_location(bytes)?;
+                match location {
+                    PreviewLocation::SubIfd { .. } => {}
+                    PreviewLocation::Ifd(ifd) => {
+                        warnings.push(DecodeWarning::PreviewFromIfd { ifd })
+                    }
+                    PreviewLocation::MarkerScan { .. } => {
+                        warnings.push(DecodeWarning::PreviewFromMarkerScan)
+                    }
+                }
+                Ok(jpeg)
+            })?;
+            (image, DecodeSource::RawEmbedded)
+        }
+        ImageFormat::Heif => {
+            let image = decode_embedded(options.prefer, &mut warnings, |_| {
+                extract_heif_preview(bytes)
+            })?;
+            (image, DecodeSource::HeifEmbedded)
+        }
+    };
+
+    let image = match options.max_edge {
+        Some(max_edge) => resize_to_fit(&image, max_edge, FilterType::Lanczos3)?,
+        None => image,
+    };
+    Ok(DecodedResult {
+        image,
+        source,
+        warnings,
+    })
+}
+
+/// Decode a JPEG as `decode_jpeg` does, noting an ignored orientation.
+fn decode_jpeg_noting(
+    bytes: &[u8],
+    warnings: &mut Vec<DecodeWarning>,
+) -> Result<DecodedImage, DecodeError> {
+    let (image, metadata) = decode_jpeg_with_options(bytes, &JpegDecodeOptions::default())?;
+    if let Some(value) = metadata.invalid_orientation {
+        warnings.push(DecodeWarning::InvalidOrientation { value });
+    }
+    Ok(image)
+}
+
+/// Decode a PNG to 8-bit RGB.
+pub(super) fn decode_png(bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
+    let decoded = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+        .map_err(|e| DecodeError::CorruptedFile(format!("Failed to decode PNG: {}", e)))?
+        .to_rgb8();
+    let (width, height) = decoded.dimensions();
+    Ok(DecodedImage::new(width, height, decoded.into_raw()))
+}
+
+/// Decode the embedded JPEG preview returned by `extract`.
+///
+/// A failure is reported as `DecodeError::NoDecoderSucceeded`, listing the
+/// warnings gathered so far and then the failure itself.
+fn decode_embedded(
+    prefer: DecodePreference,
+    warnings: &mut Vec<DecodeWarning>,
+    extract: impl FnOnce(&mut Vec<DecodeWarning>) -> Result<Vec<u8>, DecodeError>,
+) -> Result<DecodedImage, DecodeError> {
+    if prefer == DecodePreference::FullQuality {
+        warnings.push(DecodeWarning::FullQualityUnavailable);
+    }
+    let result = extract(warnings)
+        .map_err(|e| ("embedded preview", e))
+        .and_then(|jpeg| decode_jpeg_noting(&jpeg, warnings).map_err(|e| ("preview JPEG", e)));
+    result.map_err(|(step, e)| {
+        let mut attempts: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+        attempts.push(format!("{step}: {e}"));
+        DecodeError::NoDecoderSucceeded { attempts }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_heif_preview, decode_jpeg, decode_raw_thumbnail};
+    use crate::encode::encode_jpeg;
+    use crate::testing::noise;
+    use image::codecs::png::PngEncoder;
+    use image::ImageEncoder;
+
+    fn test_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = noise(width, height, 11);
+        encode_jpeg(&image.pixels, width, height, 95).unwrap()
+    }
+
+    /// A little-endian TIFF whose IFD0 has `entries` and no next IFD.
+    fn tiff_with_ifd0(entries: &[(u16, u16, u32, u32)]) -> Vec<u8> {
+        let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for &(tag, field_type, count, value) in entries {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&field_type.to_le_bytes());
+            tiff.extend_from_slice(&count.to_le_bytes());
+            tiff.extend_from_slice(&value.to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff
+    }
+
+    /// A TIFF whose IFD0 references `jpeg` directly.
+    fn raw_with_ifd0_preview(jpeg: &[u8]) -> Vec<u8> {
+        let offset = 8 + 2 + 2 * 12 + 4;
+        let mut tiff = tiff_with_ifd0(&[
+            (0x0201, 4, 1, offset),            // JPEGInterchangeFormat
+            (0x0202, 4, 1, jpeg.len() as u32), // JPEGInterchangeFormatLength
+        ]);
+        tiff.extend_from_slice(jpeg);
+        tiff
+    }
+
+    fn bmff_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn full_box(box_type: &[u8; 4], version: u8, body: &[u8]) -> Vec<u8> {
+        let mut full = vec![version, 0, 0, 0];
+        full.extend_from_slice(body);
+        bmff_box(box_type, &full)
+    }
+
+    /// A minimal HEIF whose only, primary item is `jpeg`, stored in `mdat`.
+    fn heif_with_preview(jpeg: &[u8]) -> Vec<u8> {
+        let ftyp = bmff_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        let build_meta = |data_start: u32| {
+            let mut infe = 1u16.to_be_bytes().to_vec();
+            infe.extend_from_slice(b"\0\0jpeg\0");
+            let mut iinf = 1u16.to_be_bytes().to_vec();
+            iinf.extend(full_box(b"infe", 2, &infe));
+
+            let mut iloc = vec![0x44, 0x00, 0, 1, 0, 1, 0, 0, 0, 1];
+            iloc.extend_from_slice(&data_start.to_be_bytes());
+            iloc.extend_from_slice(&(jpeg.len() as u32).to_be_bytes());
+
+            let mut meta = full_box(b"hdlr", 0, b"\0\0\0\0pict\0\0\0\0\0\0\0\0\0\0\0\0\0");
+            meta.extend(full_box(b"pitm", 0, &1u16.to_be_bytes()));
+            meta.extend(full_box(b"iinf", 0, &iinf));
+            meta.extend(full_box(b"iloc", 0, &iloc));
+            full_box(b"meta", 0, &meta)
+        };
+
+        let meta_len = build_meta(0).len();
+        let mut file = ftyp;
+        let data_start = file.len() + meta_len + 8;
+        file.extend(build_meta(data_start as u32));
+        file.extend(bmff_box(b"mdat", jpeg));
+        file
+    }
+
+    #[test]
+    fn test_jpeg_full_quality_matches_decode_jpeg() {
+        let jpeg = test_jpeg(64, 48);
+        for prefer in [
+            DecodePreference::EmbeddedPreview,
+            DecodePreference::FullQuality,
+        ] {
+            let options = AutoDecodeOptions {
+                prefer,
+                max_edge: None,
+            };
+            let decoded = decode_auto(&jpeg, &options).unwrap();
+            assert_eq!(decoded.source, DecodeSource::Jpeg);
+            assert!(decoded.warnings.is_empty());
+            assert_eq!(decoded.image.pixels, decode_jpeg(&jpeg).unwrap().pixels);
+        }
+    }
+
+    #[test]
+    fn test_png_is_decoded_directly() {
+        let image = noise(20, 10, 4);
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png)
+            .write_image(&image.pixels, 20, 10, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        let decoded = decode_auto(&png, &AutoDecodeOptions::default()).unwrap();
+        assert_eq!(decoded.source, DecodeSource::Png);
+        assert_eq!(decoded.image.pixels, image.pixels);
+
+        png.truncate(40);
+        assert!(matches!(
+            decode_auto(&png, &AutoDecodeOptions::default()),
+            Err(DecodeError::CorruptedFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_raw_routes_to_embedded_preview() {
+        let raw = raw_with_ifd0_preview(&test_jpeg(48, 32));
+        let decoded = decode_auto(&raw, &AutoDecodeOptions::default()).unwrap();
+        assert_eq!(decoded.source, DecodeSource::RawEmbedded);
+        assert_eq!(decoded.warnings, [DecodeWarning::PreviewFromIfd { ifd: 0 }]);
+        assert_eq!(
+            decoded.image.pixels,
+            decode_raw_thumbnail(&raw).unwrap().pixels
+        );
+
+        let options = AutoDecodeOptions {
+            prefer: DecodePreference::FullQuality,
+            max_edge: None,
+        };
+        let decoded = decode_auto(&raw, &options).unwrap();
+        assert_eq!(
+            decoded.warnings,
+            [
+                DecodeWarning::FullQualityUnavailable,
+                DecodeWarning::PreviewFromIfd { ifd: 0 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_raw_preview_found_by_marker_scan() {
+        let mut raw = tiff_with_ifd0(&[]);
+        raw.resize(8192, 0);
+        raw.extend_from_slice(&test_jpeg(320, 240));
+        let decoded = decode_auto(&raw, &AutoDecodeOptions::default()).unwrap();
+        assert_eq!(decoded.warnings, [DecodeWarning::PreviewFromMarkerScan]);
+        assert_eq!((decoded.image.width, decoded.image.height), (320, 240));
+    }
+
+    #[test]
+    fn test_tiff_without_preview_lists_attempts() {
+        let options = AutoDecodeOptions {
+            prefer: DecodePreference::FullQuality,
+            max_edge: None,
+        };
+        let err = decode_auto(&tiff_with_ifd0(&[]), &options).unwrap_err();
+        let DecodeError::NoDecoderSucceeded { attempts } = &err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(
+            attempts[0],
+            DecodeWarning::FullQualityUnavailable.to_string()
+        );
+        assert!(
+            attempts[1].contains("No embedded thumbnail"),
+            "{}",
+            attempts[1]
+        );
+        assert_eq!(err.code(), "NO_DECODER_SUCCEEDED");
+        assert!(err.to_string().contains("embedded preview"), "{err}");
+    }
+
+    #[test]
+    fn test_heif_routes_to_embedded_preview() {
+        let heif = heif_with_preview(&test_jpeg(40, 24));
+        let expected = decode_heif_preview(&heif).unwrap();
+        let decoded = decode_auto(&heif, &AutoDecodeOptions::default()).unwrap();
+        assert_eq!(decoded.source, DecodeSource::HeifEmbedded);
+        assert!(decoded.warnings.is_empty());
+        assert_eq!(decoded.image.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_max_edge_resizes_and_unknown_is_rejected() {
+        let jpeg = test_jpeg(64, 48);
+        let options = AutoDecodeOptions {
+            prefer: DecodePreference::EmbeddedPreview,
+            max_edge: Some(32),
+        };
+        let decoded = decode_auto(&jpeg, &options).unwrap();
+        assert_eq!((decoded.image.width, decoded.image.height), (32, 24));
+
+        assert!(matches!(
+            decode_auto(b"GIF89a", &AutoDecodeOptions::default()),
+            Err(DecodeError::InvalidFormat)
+        ));
+    }
+}
@@ -0,0 +1,53 @@
+//! Bounds-checked integer reads for the container parsers.
+//!
+//! TIFF headers store integers in either byte order, so each read takes the
+//! file's `le` flag. Reads past the end return `None` instead of panicking.
+
+pub(crate) fn read_u16(data: &[u8], offset: usize, le: bool) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+    Some(if le {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize, le: bool) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+    Some(if le {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize, le: bool) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(8)?)?.try_into().ok()?;
+    Some(if le {
+        u64::from_le_bytes(bytes)
+    } else {
+        u64::from_be_bytes(bytes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_both_byte_orders() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(read_u16(&data, 1, false), Some(0x0203));
+        assert_eq!(read_u32(&data, 0, true), Some(0x0403_0201));
+        assert_eq!(read_u64(&data, 0, false), Some(0x0102_0304_0506_0708));
+    }
+
+    #[test]
+    fn test_out_of_range_offsets_return_none() {
+        let data = [0u8; 8];
+        assert_eq!(read_u64(&data, 1, false), None);
+        assert_eq!(read_u16(&data, usize::MAX, true), None);
+        assert_eq!(read_u32(&data, usize::MAX - 1, false), None);
+        assert_eq!(read_u64(&data, usize::MAX - 3, false), None);
+    }
+}
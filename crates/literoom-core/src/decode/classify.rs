@@ -0,0 +1,337 @@
+//! Classifying files by their leading bytes before any decoder runs.
+//!
+//! Users drop all sorts of files into the importer: PDFs, videos, archives,
+//! empty files. Handed to the decoders, each fails with a different error,
+//! and a RAW thumbnail search over a multi-gigabyte video can keep a worker
+//! busy for a long time. [`classify_file`] needs only the first
+//! [`CLASSIFY_BYTES`] of a file and its length, so the importer can turn
+//! such files away up front; [`FileClassification::check`] turns the result
+//! into the error to show.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::types::DecodeError;
+use super::{sniff_image_format, ImageFormat};
+
+/// Number of leading bytes [`classify_file`] looks at.
+pub const CLASSIFY_BYTES: usize = 64;
+
+/// Largest file the decode entry points accept unless configured otherwise.
+///
+/// Well above the largest RAW files (medium format runs to a few hundred
+/// megabytes), but low enough that a stray video is refused before it is
+/// scanned.
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// `ftyp` brands of MPEG-4 and 3GPP video.
+const VIDEO_BRANDS: [&[u8; 4]; 14] = [
+    b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"M4V ", b"M4VH",
+    b"3gp4", b"3gp5", b"3g2a", b"dash",
+];
+
+/// Top-level atoms that start QuickTime files written without an `ftyp`.
+const QUICKTIME_ATOMS: [&[u8; 4]; 4] = [b"moov", b"mdat", b"wide", b"pnot"];
+
+/// File type recognized from the leading bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileType {
+    /// JPEG (starts with SOI)
+    Jpeg,
+    /// TIFF container, which covers most RAW formats
+    Tiff,
+    /// HEIC/HEIF
+    Heif,
+    /// PNG
+    Png,
+    /// GIF
+    Gif,
+    /// WebP
+    Webp,
+    /// PDF document
+    Pdf,
+    /// MPEG-4 or 3GPP video
+    Mp4,
+    /// QuickTime movie
+    QuickTime,
+    /// ZIP archive, including formats built on it
+    Zip,
+    /// Zero-byte file
+    Empty,
+    /// No known signature
+    Unknown,
+}
+
+impl FileType {
+    /// Stable snake_case name, as used across the WASM boundary.
+    pub fn name(self) -> &'static str {
+        match self {
+            FileType::Jpeg => "jpeg",
+            FileType::Tiff => "tiff",
+            FileType::Heif => "heif",
+            FileType::Png => "png",
+            FileType::Gif => "gif",
+            FileType::Webp => "webp",
+            FileType::Pdf => "pdf",
+            FileType::Mp4 => "mp4",
+            FileType::QuickTime => "quick_time",
+            FileType::Zip => "zip",
+            FileType::Empty => "empty",
+            FileType::Unknown => "unknown",
+        }
+    }
+
+    /// Whether a decoder reads this type.
+    pub fn is_supported(self) -> bool {
+        matches!(
+            self,
+            FileType::Jpeg | FileType::Tiff | FileType::Heif | FileType::Png
+        )
+    }
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FileType::Jpeg => "JPEG image",
+            FileType::Tiff => "TIFF or RAW image",
+            FileType::Heif => "HEIF image",
+            FileType::Png => "PNG image",
+            FileType::Gif => "GIF image",
+            FileType::Webp => "WebP image",
+            FileType::Pdf => "PDF document",
+            FileType::Mp4 => "MP4 video",
+            FileType::QuickTime => "QuickTime movie",
+            FileType::Zip => "ZIP archive",
+            FileType::Empty => "empty file",
+            FileType::Unknown => "unrecognized file",
+        })
+    }
+}
+
+/// What [`classify_file`] found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileClassification {
+    /// Type recognized from the leading bytes.
+    pub file_type: FileType,
+    /// Length of the whole file in bytes.
+    pub size: u64,
+}
+
+impl FileClassification {
+    /// Check that the file is worth handing to a decoder.
+    ///
+    /// Files without a known signature pass, so the decoders can report
+    /// what is wrong with them as before.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DecodeError::UnsupportedFileType` for empty files and types
+    /// no decoder reads, or `DecodeError::FileTooLarge` if the file is over
+    /// `max_bytes`.
+    pub fn check(&self, max_bytes: u64) -> Result<(), DecodeError> {
+        let unsupported = !self.file_type.is_supported() && self.file_type != FileType::Unknown;
+        if unsupported {
+            return Err(DecodeError::UnsupportedFileType {
+                file_type: self.file_type,
+            });
+        }
+        if self.size > max_bytes {
+            return Err(DecodeError::FileTooLarge {
+                size: self.size,
+                max_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Recognize a file's type from its first bytes.
+///
+/// Only the first [`CLASSIFY_BYTES`] of `bytes_prefix` are read, so the
+/// work is the same for a 2 GB video as for a 20 KB JPEG; the prefix may
+/// also be the whole file. `total_len` is the length of the whole file,
+/// which lets a caller classify a file from a slice without reading the
+/// rest. A `total_len` shorter than the prefix is taken as the prefix
+/// length.
+///
+/// # Example
+///
+/// ```
+/// use literoom_core::decode::{classify_file, FileType};
+///
+/// let classification = classify_file(b"%PDF-1.7\n", 120_000);
+/// assert_eq!(classification.file_type, FileType::Pdf);
+/// assert!(!classification.file_type.is_supported());
+/// ```
+pub fn classify_file(bytes_prefix: &[u8], total_len: u64) -> FileClassification {
+    let head = &bytes_prefix[..bytes_prefix.len().min(CLASSIFY_BYTES)];
+    let size = total_len.max(bytes_prefix.len() as u64);
+    FileClassification {
+        file_type: if size == 0 {
+            FileType::Empty
+        } else {
+            detect_type(head)
+        },
+        size,
+    }
+}
+
+fn detect_type(head: &[u8]) -> FileType {
+    if let Some(format) = sniff_image_format(head) {
+        match format {
+            ImageFormat::Jpeg => FileType::Jpeg,
+            ImageFormat::Png => FileType::Png,
+            ImageFormat::Tiff => FileType::Tiff,
+            ImageFormat::Heif => FileType::Heif,
+        }
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        FileType::Gif
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        FileType::Webp
+    } else if head.windows(5).any(|w| w == b"%PDF-") {
+        // Some writers put junk before the header
+        FileType::Pdf
+    } else if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+        FileType::Zip
+    } else {
+        detect_iso_media(head)
+    }
+}
+
+/// MP4 and QuickTime share the ISO base media box structure with HEIF.
+fn detect_iso_media(head: &[u8]) -> FileType {
+    let Some(box_type) = head.get(4..8) else {
+        return FileType::Unknown;
+    };
+    if box_type == b"ftyp" {
+        match head.get(8..12) {
+            Some(b"qt  ") => FileType::QuickTime,
+            Some(brand) if VIDEO_BRANDS.iter().any(|b| &b[..] == brand) => FileType::Mp4,
+            // Other brands include camera RAW formats such as CR3
+            _ => FileType::Unknown,
+        }
+    } else if QUICKTIME_ATOMS.iter().any(|a| &a[..] == box_type) {
+        FileType::QuickTime
+    } else {
+        FileType::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::sniff::PNG_SIGNATURE;
+    use crate::encode::encode_jpeg;
+    use crate::testing::noise;
+    use std::time::{Duration, Instant};
+
+    /// An ISO media file header: an `ftyp` box with `brands`.
+    fn ftyp(brands: &[&[u8; 4]]) -> Vec<u8> {
+        let len = 16 + 4 * (brands.len() - 1);
+        let mut bytes = (len as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"ftyp");
+        bytes.extend_from_slice(brands[0]);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        for brand in &brands[1..] {
+            bytes.extend_from_slice(&brand[..]);
+        }
+        bytes.extend_from_slice(&[0; 8]);
+        bytes
+    }
+
+    #[test]
+    fn test_magic_bytes_classify() {
+        let image = noise(8, 8, 1);
+        let cases: Vec<(Vec<u8>, FileType)> = vec![
+            (
+                encode_jpeg(&image.pixels, 8, 8, 90).unwrap(),
+                FileType::Jpeg,
+            ),
+            (b"II*\0\x08\0\0\0".to_vec(), FileType::Tiff),
+            (b"MM\0*\0\0\0\x08".to_vec(), FileType::Tiff),
+            (ftyp(&[b"heic", b"mif1"]), FileType::Heif),
+            (PNG_SIGNATURE.to_vec(), FileType::Png),
+            (b"GIF89a\x01\0\x01\0".to_vec(), FileType::Gif),
+            (b"RIFF\x24\0\0\0WEBPVP8 ".to_vec(), FileType::Webp),
+            (b"%PDF-1.7\n%\xe2\xe3".to_vec(), FileType::Pdf),
+            (b"\r\n%PDF-1.4\n".to_vec(), FileType::Pdf),
+            (ftyp(&[b"isom", b"avc1"]), FileType::Mp4),
+            (ftyp(&[b"mp42"]), FileType::Mp4),
+            (ftyp(&[b"qt  "]), FileType::QuickTime),
+            (b"\0\0\0\x08wide\0\0\0\0mdat".to_vec(), FileType::QuickTime),
+            (b"PK\x03\x04\x14\0\0\0".to_vec(), FileType::Zip),
+            (ftyp(&[b"crx "]), FileType::Unknown),
+            (b"hello, world".to_vec(), FileType::Unknown),
+        ];
+        for (bytes, expected) in cases {
+            let classification = classify_file(&bytes, bytes.len() as u64);
+            assert_eq!(classification.file_type, expected, "{:?}", &bytes[..8]);
+            assert_eq!(classification.size, bytes.len() as u64);
+        }
+        assert_eq!(classify_file(&[], 0).file_type, FileType::Empty);
+    }
+
+    #[test]
+    fn test_check_rejects_unsupported_and_oversized() {
+        let pdf = classify_file(b"%PDF-1.7", 2048);
+        let err = pdf.check(DEFAULT_MAX_FILE_BYTES).unwrap_err();
+        assert_eq!(err.code(), "UNSUPPORTED_FILE_TYPE");
+        assert_eq!(err.to_string(), "Unsupported file type: PDF document");
+
+        let err = classify_file(&[], 0)
+            .check(DEFAULT_MAX_FILE_BYTES)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DecodeError::UnsupportedFileType {
+                file_type: FileType::Empty
+            }
+        ));
+
+        // The length alone decides, so a huge file is refused from its header
+        let huge_tiff = classify_file(b"II*\0\x08\0\0\0", 5 << 30);
+        assert!(matches!(
+            huge_tiff.check(DEFAULT_MAX_FILE_BYTES),
+            Err(DecodeError::FileTooLarge { size, .. }) if size == 5 << 30
+        ));
+        assert!(huge_tiff.check(u64::MAX).is_ok());
+        assert!(classify_file(b"unknown", 7).check(7).is_ok());
+    }
+
+    #[test]
+    fn test_giant_video_is_refused_without_scanning() {
+        let mut video = vec![0u8; 256 * 1024 * 1024];
+        let header = ftyp(&[b"isom", b"iso2", b"avc1", b"mp41"]);
+        video[..header.len()].copy_from_slice(&header);
+
+        let start = Instant::now();
+        let classification = classify_file(&video, video.len() as u64);
+        let result = classification.check(DEFAULT_MAX_FILE_BYTES);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert_eq!(classification.file_type, FileType::Mp4);
+        assert!(matches!(
+            result,
+            Err(DecodeError::UnsupportedFileType {
+                file_type: FileType::Mp4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_supported_types_pass_to_decoders() {
+        let image = noise(16, 8, 2);
+        let jpeg = encode_jpeg(&image.pixels, 16, 8, 90).unwrap();
+        let classification = classify_file(&jpeg, jpeg.len() as u64);
+        assert!(classification.check(DEFAULT_MAX_FILE_BYTES).is_ok());
+        let decoded = crate::decode::decode_jpeg(&jpeg).unwrap();
+        assert_eq!((decoded.width, decoded.height), (16, 8));
+
+        for file_type in [FileType::Tiff, FileType::Heif, FileType::Png] {
+            let classification = FileClassification { file_type, size: 1 };
+            assert!(classification.check(DEFAULT_MAX_FILE_BYTES).is_ok());
+        }
+    }
+}
@@ -0,0 +1,661 @@
+//! Ultra HDR gain-map JPEGs.
+//!
+//! An Ultra HDR file is an ordinary SDR JPEG followed by a second, usually
+//! quarter-resolution and single-channel JPEG: the gain map. Viewers that do
+//! not understand it show the SDR image; HDR-aware viewers multiply the gain
+//! map into the SDR image in linear light to recover the HDR rendition.
+//!
+//! # Structure
+//!
+//! - Primary image `APP1` XMP - `hdrgm:Version` marks the file as Ultra HDR
+//! - Primary image `APP2` `MPF` - Multi-Picture Format index listing each
+//!   image's size and offset (relative to the MPF TIFF header)
+//! - Gain map image `APP1` XMP - `hdrgm:GainMapMin`, `GainMapMax`, `Gamma`,
+//!   `OffsetSDR`, `OffsetHDR`, `HDRCapacityMin` and `HDRCapacityMax`
+//!
+//! Our pipeline is 8-bit sRGB, so applying a gain map brightens the image
+//! and clips highlights that would exceed SDR white.
+
+use super::jpeg::{app_segments, decode_jpeg};
+use super::{DecodeError, DecodedImage};
+use crate::color::{linear_to_srgb, srgb_to_linear};
+
+/// XMP packet signature at the start of an `APP1` payload.
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Multi-Picture Format signature at the start of an `APP2` payload.
+const MPF_SIGNATURE: &[u8] = b"MPF\0";
+
+/// ISO 21496-1 gain map metadata signature at the start of an `APP2` payload.
+const ISO_GAIN_MAP_SIGNATURE: &[u8] = b"urn:iso:std:iso:ts:21496:-1\0";
+
+/// Namespace prefix of the Ultra HDR XMP properties.
+const HDRGM: &str = "hdrgm:";
+
+/// MPF tag holding the array of 16-byte MP entries.
+const MP_ENTRY_TAG: u16 = 0xB002;
+
+/// Size of one MP entry.
+const MP_ENTRY_SIZE: usize = 16;
+
+/// Parameters describing how to apply a gain map.
+///
+/// Gain and capacity values are stored as log2 of the linear boost, as in
+/// the XMP. Missing properties take the Ultra HDR defaults.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainMapMetadata {
+    /// Log2 boost encoded by a gain map value of 0
+    pub gain_map_min: f32,
+    /// Log2 boost encoded by a gain map value of 1
+    pub gain_map_max: f32,
+    /// Gamma the gain map values were encoded with
+    pub gamma: f32,
+    /// Offset added to SDR linear values before applying the gain
+    pub offset_sdr: f32,
+    /// Offset subtracted from HDR linear values after applying the gain
+    pub offset_hdr: f32,
+    /// Log2 display boost below which the gain map is not applied
+    pub hdr_capacity_min: f32,
+    /// Log2 display boost at which the gain map is fully applied
+    pub hdr_capacity_max: f32,
+}
+
+impl Default for GainMapMetadata {
+    fn default() -> Self {
+        Self {
+            gain_map_min: 0.0,
+            gain_map_max: 1.0,
+            gamma: 1.0,
+            offset_sdr: 1.0 / 64.0,
+            offset_hdr: 1.0 / 64.0,
+            hdr_capacity_min: 0.0,
+            hdr_capacity_max: 1.0,
+        }
+    }
+}
+
+impl GainMapMetadata {
+    /// Parse the `hdrgm:` properties of an XMP packet.
+    ///
+    /// Accepts both the attribute form (`hdrgm:GainMapMax="2.0"`) and the
+    /// element form; for per-channel sequences the first value is used.
+    pub fn from_xmp(xmp: &str) -> Self {
+        let default = Self::default();
+        let value = |name: &str, fallback: f32| xmp_property(xmp, name).unwrap_or(fallback);
+        Self {
+            gain_map_min: value("GainMapMin", default.gain_map_min),
+            gain_map_max: value("GainMapMax", default.gain_map_max),
+            gamma: value("Gamma", default.gamma),
+            offset_sdr: value("OffsetSDR", default.offset_sdr),
+            offset_hdr: value("OffsetHDR", default.offset_hdr),
+            hdr_capacity_min: value("HDRCapacityMin", default.hdr_capacity_min),
+            hdr_capacity_max: value("HDRCapacityMax", default.hdr_capacity_max),
+        }
+    }
+
+    /// Weight to apply the gain map with on a display offering `display_boost`
+    /// times SDR white.
+    ///
+    /// Returns 0 at or below the HDR capacity minimum and 1 at or above the
+    /// maximum, interpolating in log2 space between them.
+    pub fn weight_for_display_boost(&self, display_boost: f32) -> f32 {
+        let log_boost = display_boost.max(1.0).log2();
+        let range = self.hdr_capacity_max - self.hdr_capacity_min;
+        if range <= f32::EPSILON {
+            return if log_boost >= self.hdr_capacity_max {
+                1.0
+            } else {
+                0.0
+            };
+        }
+        ((log_boost - self.hdr_capacity_min) / range).clamp(0.0, 1.0)
+    }
+}
+
+/// A gain map extracted from an Ultra HDR JPEG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GainMap {
+    /// The encoded gain map JPEG
+    pub jpeg: Vec<u8>,
+    /// Application parameters read from the gain map's XMP
+    pub metadata: GainMapMetadata,
+}
+
+/// Check whether a JPEG carries an Ultra HDR gain map.
+///
+/// Only reads the marker segments; the image data is not decoded.
+pub fn has_gain_map(bytes: &[u8]) -> bool {
+    extract_gain_map(bytes).is_ok()
+}
+
+/// Extract the gain map JPEG and its metadata from an Ultra HDR file.
+///
+/// The gain map is the second image of the Multi-Picture Format index. It
+/// is accepted when either the primary image's XMP declares `hdrgm:Version`
+/// or the secondary image itself carries gain map metadata.
+///
+/// # Errors
+///
+/// Returns `DecodeError::NoGainMap` if the file is not an Ultra HDR JPEG,
+/// and `DecodeError::CorruptedFile` if the MPF index points outside the
+/// file.
+pub fn extract_gain_map(bytes: &[u8]) -> Result<GainMap, DecodeError> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return Err(DecodeError::NoGainMap);
+    }
+
+    let segments = app_segments(bytes);
+    let primary_declares = segments
+        .iter()
+        .filter_map(|&(marker, payload)| xmp_packet(marker, payload))
+        .any(|xmp| xmp.contains("hdrgm:Version"));
+    let Some(range) = segments
+        .iter()
+        .find_map(|&(marker, payload)| second_mpf_image(marker, payload, bytes))
+    else {
+        return Err(DecodeError::NoGainMap);
+    };
+    let range = range?;
+    let jpeg = &bytes[range];
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return Err(DecodeError::CorruptedFile(
+            "MPF entry does not point at a JPEG".to_string(),
+        ));
+    }
+
+    let gain_map_segments = app_segments(jpeg);
+    let gain_map_xmp = gain_map_segments
+        .iter()
+        .filter_map(|&(marker, payload)| xmp_packet(marker, payload))
+        .find(|xmp| xmp.contains(HDRGM));
+    let iso_metadata = gain_map_segments
+        .iter()
+        .any(|&(marker, payload)| marker == 0xE2 && payload.starts_with(ISO_GAIN_MAP_SIGNATURE));
+    if !primary_declares && gain_map_xmp.is_none() && !iso_metadata {
+        return Err(DecodeError::NoGainMap);
+    }
+
+    Ok(GainMap {
+        jpeg: jpeg.to_vec(),
+        metadata: gain_map_xmp
+            .map(GainMapMetadata::from_xmp)
+            .unwrap_or_default(),
+    })
+}
+
+/// Apply a gain map to `base` with default metadata.
+///
+/// See [`apply_gain_map_with_metadata`].
+pub fn apply_gain_map(
+    base: &mut DecodedImage,
+    gain_map: &DecodedImage,
+    weight: f32,
+) -> Result<(), DecodeError> {
+    apply_gain_map_with_metadata(base, gain_map, &GainMapMetadata::default(), weight)
+}
+
+/// Multiply a gain map into `base` in linear light.
+///
+/// The gain map is bilinearly upsampled to the base size; a single-channel
+/// map decodes to equal RGB channels and so applies the same boost to all
+/// three. Each pixel becomes `(sdr + offset_sdr) * 2^(log_boost * weight) -
+/// offset_hdr`, where `log_boost` interpolates between `gain_map_min` and
+/// `gain_map_max`. A weight of 0 leaves the base unchanged.
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidImage` if either image is empty or its
+/// buffer does not match its dimensions.
+pub fn apply_gain_map_with_metadata(
+    base: &mut DecodedImage,
+    gain_map: &DecodedImage,
+    metadata: &GainMapMetadata,
+    weight: f32,
+) -> Result<(), DecodeError> {
+    base.validate()?;
+    gain_map.validate()?;
+    if weight <= 0.0 {
+        return Ok(());
+    }
+
+    let decode: Vec<f32> = (0..=255u8)
+        .map(|v| srgb_to_linear(v as f32 / 255.0))
+        .collect();
+    let inverse_gamma = if metadata.gamma > 0.0 {
+        1.0 / metadata.gamma
+    } else {
+        1.0
+    };
+    let boost: Vec<f32> = (0..=255u8)
+        .map(|v| {
+            let recovery = (v as f32 / 255.0).powf(inverse_gamma);
+            let log_boost =
+                metadata.gain_map_min * (1.0 - recovery) + metadata.gain_map_max * recovery;
+            (log_boost * weight).exp2()
+        })
+        .collect();
+
+    let (width, height) = (base.width as usize, base.height as usize);
+    let (map_width, map_height) = (gain_map.width as usize, gain_map.height as usize);
+    let scale_x = map_width as f32 / width as f32;
+    let scale_y = map_height as f32 / height as f32;
+
+    for y in 0..height {
+        let (y0, y1, fy) = sample_position(y, scale_y, map_height);
+        for x in 0..width {
+            let (x0, x1, fx) = sample_position(x, scale_x, map_width);
+            let base_index = (y * width + x) * 3;
+            for c in 0..3 {
+                let at = |mx: usize, my: usize| {
+                    boost[gain_map.pixels[(my * map_width + mx) * 3 + c] as usize]
+                };
+                let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+                let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+                let factor = top * (1.0 - fy) + bottom * fy;
+
+                let sdr = decode[base.pixels[base_index + c] as usize];
+                let hdr = (sdr + metadata.offset_sdr) * factor - metadata.offset_hdr;
+                base.pixels[base_index + c] =
+                    (linear_to_srgb(hdr.clamp(0.0, 1.0)) * 255.0).round() as u8;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode a JPEG and apply its Ultra HDR gain map with `weight`.
+///
+/// Files without a gain map, or whose gain map cannot be read, decode
+/// exactly like [`decode_jpeg`]. A weight of 0 skips the gain map.
+pub fn decode_jpeg_with_gain_map(bytes: &[u8], weight: f32) -> Result<DecodedImage, DecodeError> {
+    let mut image = decode_jpeg(bytes)?;
+    if weight <= 0.0 {
+        return Ok(image);
+    }
+    if let Ok(gain_map) = extract_gain_map(bytes) {
+        if let Ok(map) = decode_jpeg(&gain_map.jpeg) {
+            apply_gain_map_with_metadata(&mut image, &map, &gain_map.metadata, weight)?;
+        }
+    }
+    Ok(image)
+}
+
+/// Source pixels and blend fraction for bilinear sampling along one axis.
+fn sample_position(dest: usize, scale: f32, len: usize) -> (usize, usize, f32) {
+    let pos = ((dest as f32 + 0.5) * scale - 0.5).clamp(0.0, (len - 1) as f32);
+    let lower = pos.floor() as usize;
+    let upper = (lower + 1).min(len - 1);
+    (lower, upper, pos - lower as f32)
+}
+
+/// The XMP packet of an `APP1` segment, if it is one.
+fn xmp_packet(marker: u8, payload: &[u8]) -> Option<&str> {
+    if marker != 0xE1 || !payload.starts_with(XMP_SIGNATURE) {
+        return None;
+    }
+    std::str::from_utf8(&payload[XMP_SIGNATURE.len()..]).ok()
+}
+
+/// Read a numeric `hdrgm:` property in attribute or element form.
+fn xmp_property(xmp: &str, name: &str) -> Option<f32> {
+    let key = format!("{}{}", HDRGM, name);
+    let mut search = 0;
+    while let Some(found) = xmp[search..].find(&key) {
+        let after = search + found + key.len();
+        let rest = &xmp[after..];
+        search = after;
+
+        if let Some(quoted) = rest.trim_start().strip_prefix('=') {
+            let quoted = quoted.trim_start();
+            let quote = quoted.chars().next()?;
+            let body = &quoted[1..];
+            let end = body.find(quote)?;
+            return body[..end].trim().parse().ok();
+        }
+        if let Some(content) = rest.strip_prefix('>') {
+            let end = content.find('<')?;
+            let text = content[..end].trim();
+            if !text.is_empty() {
+                return text.parse().ok();
+            }
+            // Per-channel values: take the first list item
+            let item = content.find("<rdf:li>")? + "<rdf:li>".len();
+            let end = content[item..].find('<')?;
+            return content[item..item + end].trim().parse().ok();
+        }
+    }
+    None
+}
+
+/// Byte range of the second image listed in an `APP2` MPF segment.
+///
+/// Returns `None` if the segment is not MPF or lists fewer than two images,
+/// and an error if the listed range falls outside `file`.
+fn second_mpf_image(
+    marker: u8,
+    payload: &[u8],
+    file: &[u8],
+) -> Option<Result<std::ops::Range<usize>, DecodeError>> {
+    if marker != 0xE2 || !payload.starts_with(MPF_SIGNATURE) {
+        return None;
+    }
+    let tiff = &payload[MPF_SIGNATURE.len()..];
+    let little_endian = match tiff.get(..4)? {
+        [0x49, 0x49, 0x2A, 0x00] => true,
+        [0x4D, 0x4D, 0x00, 0x2A] => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| {
+        let b = tiff.get(offset..offset + 2)?;
+        Some(if little_endian {
+            u16::from_le_bytes([b[0], b[1]])
+        } else {
+            u16::from_be_bytes([b[0], b[1]])
+        })
+    };
+    let u32_at = |offset: usize| {
+        let b = tiff.get(offset..offset + 4)?;
+        Some(if little_endian {
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        } else {
+            u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+        })
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    let entries = (0..count).find_map(|i| {
+        let entry = ifd + 2 + i * 12;
+        (u16_at(entry)? == MP_ENTRY_TAG).then(|| (u32_at(entry + 4), u32_at(entry + 8)))
+    })?;
+    let (length, offset) = (entries.0? as usize, entries.1? as usize);
+    if length < 2 * MP_ENTRY_SIZE {
+        return None;
+    }
+    let second = offset + MP_ENTRY_SIZE;
+    let size = u32_at(second + 4)? as usize;
+    let data_offset = u32_at(second + 8)? as usize;
+
+    // Offsets are relative to the MPF TIFF header
+    let tiff_start = tiff.as_ptr() as usize - file.as_ptr() as usize;
+    let start = tiff_start.checked_add(data_offset);
+    let range = start.and_then(|start| Some(start..start.checked_add(size)?));
+    Some(match range {
+        Some(range) if size > 0 && range.end <= file.len() => Ok(range),
+        _ => Err(DecodeError::CorruptedFile(
+            "MPF entry points outside the file".to_string(),
+        )),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_jpeg(width: u32, height: u32, value: u8) -> Vec<u8> {
+        let pixels = vec![value; (width * height * 3) as usize];
+        crate::encode::encode_jpeg(&pixels, width, height, 95).unwrap()
+    }
+
+    /// Insert an `APPn` segment directly after SOI.
+    fn with_segment(jpeg: &[u8], marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, marker]);
+        out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        out.extend_from_slice(payload);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn xmp_segment(body: &str) -> Vec<u8> {
+        let mut payload = XMP_SIGNATURE.to_vec();
+        payload.extend_from_slice(body.as_bytes());
+        payload
+    }
+
+    /// Big-endian MPF payload listing the primary image and one secondary.
+    fn mpf_segment(primary_size: u32, secondary_size: u32, secondary_offset: u32) -> Vec<u8> {
+        let mut tiff = b"MM\x00\x2A".to_vec();
+        tiff.extend_from_slice(&8u32.to_be_bytes());
+        tiff.extend_from_slice(&2u16.to_be_bytes());
+        // Version: UNDEFINED[4] "0100"
+        tiff.extend_from_slice(&0xB000u16.to_be_bytes());
+        tiff.extend_from_slice(&7u16.to_be_bytes());
+        tiff.extend_from_slice(&4u32.to_be_bytes());
+        tiff.extend_from_slice(b"0100");
+        // MP entries: UNDEFINED[32] after the IFD
+        let entries_offset = 8 + 2 + 2 * 12 + 4;
+        tiff.extend_from_slice(&MP_ENTRY_TAG.to_be_bytes());
+        tiff.extend_from_slice(&7u16.to_be_bytes());
+        tiff.extend_from_slice(&32u32.to_be_bytes());
+        tiff.extend_from_slice(&(entries_offset as u32).to_be_bytes());
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+        for (attributes, size, offset) in [
+            (0x0003_0000u32, primary_size, 0u32),
+            (0, secondary_size, secondary_offset),
+        ] {
+            tiff.extend_from_slice(&attributes.to_be_bytes());
+            tiff.extend_from_slice(&size.to_be_bytes());
+            tiff.extend_from_slice(&offset.to_be_bytes());
+            tiff.extend_from_slice(&[0; 4]);
+        }
+
+        let mut payload = MPF_SIGNATURE.to_vec();
+        payload.extend(tiff);
+        payload
+    }
+
+    /// Assemble an Ultra HDR file from an SDR JPEG and a gain map JPEG.
+    fn ultra_hdr(primary: &[u8], gain_map: &[u8], gain_map_xmp: &str) -> Vec<u8> {
+        let gain_map = with_segment(gain_map, 0xE1, &xmp_segment(gain_map_xmp));
+        let primary_xmp = xmp_segment(r#"<rdf:Description hdrgm:Version="1.0"/>"#);
+
+        // The MPF payload has a fixed size, so lay out the file with
+        // placeholder offsets first and patch in the real ones.
+        let placeholder = mpf_segment(0, 0, 0);
+        let primary = with_segment(primary, 0xE2, &placeholder);
+        let primary = with_segment(&primary, 0xE1, &primary_xmp);
+        let tiff_start = 2 + (4 + primary_xmp.len()) + 4 + MPF_SIGNATURE.len();
+        let secondary_offset = primary.len() - tiff_start;
+
+        let mpf = mpf_segment(
+            primary.len() as u32,
+            gain_map.len() as u32,
+            secondary_offset as u32,
+        );
+        let mut file = primary;
+        let mpf_start = tiff_start - MPF_SIGNATURE.len();
+        file[mpf_start..mpf_start + mpf.len()].copy_from_slice(&mpf);
+        file.extend(gain_map);
+        file
+    }
+
+    fn expected_pixel(sdr: u8, gain: u8, metadata: &GainMapMetadata, weight: f32) -> u8 {
+        let recovery = (gain as f32 / 255.0).powf(1.0 / metadata.gamma);
+        let log_boost = metadata.gain_map_min * (1.0 - recovery) + metadata.gain_map_max * recovery;
+        let linear = srgb_to_linear(sdr as f32 / 255.0);
+        let hdr =
+            (linear + metadata.offset_sdr) * (log_boost * weight).exp2() - metadata.offset_hdr;
+        (linear_to_srgb(hdr.clamp(0.0, 1.0)) * 255.0).round() as u8
+    }
+
+    #[test]
+    fn test_flat_gain_map_multiplies_base() {
+        let gain_map = DecodedImage::new(2, 2, vec![255; 12]);
+        let mut base = DecodedImage::new(8, 8, vec![100; 192]);
+        apply_gain_map(&mut base, &gain_map, 1.0).unwrap();
+
+        // Full gain at the default GainMapMax of 1 doubles linear light
+        let expected = expected_pixel(100, 255, &GainMapMetadata::default(), 1.0);
+        assert!(expected > 120);
+        assert!(base.pixels.iter().all(|&v| v == expected));
+    }
+
+    #[test]
+    fn test_weight_zero_leaves_base_unchanged() {
+        let gain_map = DecodedImage::new(2, 2, vec![200; 12]);
+        let mut base = DecodedImage::new(8, 8, (0..192).map(|i| i as u8).collect());
+        let original = base.clone();
+        apply_gain_map(&mut base, &gain_map, 0.0).unwrap();
+        assert_eq!(base.pixels, original.pixels);
+    }
+
+    #[test]
+    fn test_metadata_and_partial_weight() {
+        let metadata = GainMapMetadata {
+            gain_map_min: -1.0,
+            gain_map_max: 2.0,
+            gamma: 2.0,
+            ..Default::default()
+        };
+        let gain_map = DecodedImage::new(1, 1, vec![64; 3]);
+        let mut base = DecodedImage::new(4, 4, vec![80; 48]);
+        apply_gain_map_with_metadata(&mut base, &gain_map, &metadata, 0.5).unwrap();
+
+        let expected = expected_pixel(80, 64, &metadata, 0.5);
+        assert!(base.pixels.iter().all(|&v| v == expected));
+    }
+
+    #[test]
+    fn test_gain_map_is_upsampled_smoothly() {
+        // Left half no boost, right half full boost
+        let gain_map = DecodedImage::new(2, 1, vec![0, 0, 0, 255, 255, 255]);
+        let mut base = DecodedImage::new(8, 2, vec![100; 48]);
+        apply_gain_map(&mut base, &gain_map, 1.0).unwrap();
+
+        let row: Vec<u8> = base.pixels[..24].iter().step_by(3).copied().collect();
+        assert!(row.windows(2).all(|w| w[0] <= w[1]), "row: {:?}", row);
+        let metadata = GainMapMetadata::default();
+        assert_eq!(row[0], expected_pixel(100, 0, &metadata, 1.0));
+        assert_eq!(row[7], expected_pixel(100, 255, &metadata, 1.0));
+        assert!(row[3] > row[0] && row[4] < row[7]);
+    }
+
+    #[test]
+    fn test_invalid_gain_map_is_rejected() {
+        let mut base = DecodedImage::new(2, 2, vec![0; 12]);
+        let gain_map = DecodedImage {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 5],
+        };
+        assert!(matches!(
+            apply_gain_map(&mut base, &gain_map, 1.0),
+            Err(DecodeError::InvalidImage(_))
+        ));
+    }
+
+    #[test]
+    fn test_plain_jpeg_has_no_gain_map() {
+        let jpeg = test_jpeg(16, 16, 90);
+        assert!(!has_gain_map(&jpeg));
+        assert!(matches!(
+            extract_gain_map(&jpeg),
+            Err(DecodeError::NoGainMap)
+        ));
+        assert!(!has_gain_map(b"not a jpeg"));
+
+        let plain = decode_jpeg(&jpeg).unwrap();
+        assert_eq!(
+            decode_jpeg_with_gain_map(&jpeg, 1.0).unwrap().pixels,
+            plain.pixels
+        );
+    }
+
+    #[test]
+    fn test_mpf_without_gain_map_metadata_is_not_ultra_hdr() {
+        // Cameras use MPF for large previews too
+        let preview = test_jpeg(8, 8, 50);
+        let primary = test_jpeg(16, 16, 90);
+        let placeholder = mpf_segment(0, 0, 0);
+        let mut file = with_segment(&primary, 0xE2, &placeholder);
+        let tiff_start = 2 + 4 + MPF_SIGNATURE.len();
+        let mpf = mpf_segment(
+            file.len() as u32,
+            preview.len() as u32,
+            (file.len() - tiff_start) as u32,
+        );
+        file[tiff_start - MPF_SIGNATURE.len()..tiff_start - MPF_SIGNATURE.len() + mpf.len()]
+            .copy_from_slice(&mpf);
+        file.extend(preview);
+        assert!(!has_gain_map(&file));
+    }
+
+    #[test]
+    fn test_extract_gain_map_from_ultra_hdr() {
+        let gain_map = test_jpeg(4, 4, 255);
+        let file = ultra_hdr(
+            &test_jpeg(16, 16, 100),
+            &gain_map,
+            r#"<rdf:Description hdrgm:Version="1.0" hdrgm:GainMapMax="2.0" hdrgm:Gamma="1.0"/>"#,
+        );
+        assert!(has_gain_map(&file));
+
+        let extracted = extract_gain_map(&file).unwrap();
+        assert!(extracted.jpeg.starts_with(&[0xFF, 0xD8]));
+        assert!(extracted.jpeg.ends_with(&[0xFF, 0xD9]));
+        assert_eq!(extracted.metadata.gain_map_max, 2.0);
+        assert_eq!(extracted.metadata.gain_map_min, 0.0);
+        let map = decode_jpeg(&extracted.jpeg).unwrap();
+        assert_eq!((map.width, map.height), (4, 4));
+    }
+
+    #[test]
+    fn test_decode_with_gain_map_applies_weight() {
+        let file = ultra_hdr(
+            &test_jpeg(16, 16, 100),
+            &test_jpeg(4, 4, 255),
+            r#"<rdf:Description hdrgm:GainMapMax="1.0"/>"#,
+        );
+        let plain = decode_jpeg(&file).unwrap();
+        assert_eq!(
+            decode_jpeg_with_gain_map(&file, 0.0).unwrap().pixels,
+            plain.pixels
+        );
+
+        let boosted = decode_jpeg_with_gain_map(&file, 1.0).unwrap();
+        let mean = |image: &DecodedImage| {
+            image.pixels.iter().map(|&v| v as f32).sum::<f32>() / image.pixels.len() as f32
+        };
+        assert!(mean(&boosted) > mean(&plain) + 20.0);
+    }
+
+    #[test]
+    fn test_truncated_mpf_target_is_corrupted() {
+        let mut file = ultra_hdr(
+            &test_jpeg(16, 16, 100),
+            &test_jpeg(4, 4, 255),
+            r#"<rdf:Description hdrgm:GainMapMax="1.0"/>"#,
+        );
+        file.truncate(file.len() - 10);
+        assert!(matches!(
+            extract_gain_map(&file),
+            Err(DecodeError::CorruptedFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_xmp_property_forms() {
+        let xmp = r#"<rdf:Description hdrgm:GainMapMin = '-0.5'>
+            <hdrgm:GainMapMax>3.25</hdrgm:GainMapMax>
+            <hdrgm:Gamma><rdf:Seq><rdf:li>2.2</rdf:li><rdf:li>1.0</rdf:li></rdf:Seq></hdrgm:Gamma>
+        </rdf:Description>"#;
+        let metadata = GainMapMetadata::from_xmp(xmp);
+        assert_eq!(metadata.gain_map_min, -0.5);
+        assert_eq!(metadata.gain_map_max, 3.25);
+        assert_eq!(metadata.gamma, 2.2);
+        assert_eq!(metadata.offset_sdr, 1.0 / 64.0);
+    }
+
+    #[test]
+    fn test_weight_for_display_boost() {
+        let metadata = GainMapMetadata {
+            hdr_capacity_min: 0.0,
+            hdr_capacity_max: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(metadata.weight_for_display_boost(1.0), 0.0);
+        assert!((metadata.weight_for_display_boost(2.0) - 0.5).abs() < 1e-6);
+        assert_eq!(metadata.weight_for_display_boost(8.0), 1.0);
+    }
+}
@@ -0,0 +1,674 @@
+//! HEIC/HEIF container parsing for embedded preview extraction.
+//!
+//! HEIC files store their main image as HEVC, which we do not decode.
+//! The container usually also carries an EXIF block with a small JPEG
+//! thumbnail, and some writers add a JPEG-coded preview item. This module
+//! walks the ISO-BMFF box structure to find those items so the app can show
+//! something for iPhone photos.
+//!
+//! # Structure
+//!
+//! - `ftyp` - brand identifying the file as HEIF
+//! - `meta` - item metadata:
+//!   - `pitm` - primary item ID
+//!   - `iinf`/`infe` - item IDs and types (`hvc1`, `jpeg`, `Exif`, ...)
+//!   - `iloc` - where each item's bytes live (file offsets or `idat`)
+//!   - `iref` - relations between items (e.g. `thmb` thumbnails)
+
+use std::ops::Range;
+
+use exif::{In, Reader, Tag};
+
+use super::{read_u16, read_u32, read_u64, DecodeError, DecodedImage};
+use crate::decode::jpeg::decode_jpeg;
+
+/// Brands that identify a HEIF image file.
+const HEIF_BRANDS: [&[u8; 4]; 8] = [
+    b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1",
+];
+
+/// Item types that carry HEVC-coded image data.
+const HEVC_ITEM_TYPES: [&str; 2] = ["hvc1", "hev1"];
+
+/// Maximum box nesting depth followed when parsing.
+const MAX_BOX_DEPTH: usize = 8;
+
+/// An item declared in a HEIF container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeifItem {
+    /// Item ID.
+    pub id: u32,
+    /// Four-character item type (e.g. "hvc1", "jpeg", "Exif", "grid").
+    pub item_type: String,
+    /// Absolute byte ranges of the item's data, in order.
+    ///
+    /// Empty if the item's location could not be resolved.
+    pub extents: Vec<Range<usize>>,
+    /// ID of the item this is a thumbnail of, from a `thmb` reference.
+    pub thumbnail_for: Option<u32>,
+}
+
+impl HeifItem {
+    /// Total size of the item's data in bytes.
+    pub fn len(&self) -> usize {
+        self.extents.iter().map(|r| r.len()).sum()
+    }
+
+    /// Whether the item has no resolvable data.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy the item's data out of the file bytes.
+    pub fn data(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut data = Vec::with_capacity(self.len());
+        for range in &self.extents {
+            data.extend_from_slice(bytes.get(range.clone())?);
+        }
+        Some(data)
+    }
+}
+
+/// Parsed summary of a HEIF container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeifContainer {
+    /// Major brand from the `ftyp` box (e.g. "heic").
+    pub major_brand: String,
+    /// Primary item ID from the `pitm` box.
+    pub primary_item: Option<u32>,
+    /// All items declared in `iinf`, in declaration order.
+    pub items: Vec<HeifItem>,
+}
+
+impl HeifContainer {
+    /// Items of the given type.
+    pub fn items_of_type<'a>(&'a self, item_type: &'a str) -> impl Iterator<Item = &'a HeifItem> {
+        self.items
+            .iter()
+            .filter(move |item| item.item_type == item_type)
+    }
+}
+
+/// Check if a file appears to be HEIC/HEIF based on its `ftyp` box.
+///
+/// # Arguments
+///
+/// * `bytes` - First few bytes of the file (at least 32 recommended)
+pub fn is_heif_file(bytes: &[u8]) -> bool {
+    if bytes.len() < 12 || &bytes[4..8] != b"ftyp" {
+        return false;
+    }
+    let size = (read_u32(bytes, 0, false).unwrap_or(0) as usize).clamp(12, bytes.len());
+    // Major brand, then compatible brands after the minor version
+    std::iter::once(&bytes[8..12])
+        .chain(bytes[16.min(size)..size].chunks_exact(4))
+        .any(|brand| HEIF_BRANDS.iter().any(|b| &b[..] == brand))
+}
+
+/// Walk the ISO-BMFF box structure of a HEIF file and list its items.
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidFormat` if the file is not HEIF, or
+/// `DecodeError::CorruptedFile` if the `meta` box is missing or malformed.
+pub fn parse_heif_container(bytes: &[u8]) -> Result<HeifContainer, DecodeError> {
+    if !is_heif_file(bytes) {
+        return Err(DecodeError::InvalidFormat);
+    }
+
+    let top = parse_boxes(bytes, 0..bytes.len(), 0)?;
+    let ftyp = find_box(&top, b"ftyp").ok_or(DecodeError::InvalidFormat)?;
+    let major_brand = fourcc(&bytes[ftyp.body.start..ftyp.body.start + 4]);
+
+    let meta = find_box(&top, b"meta")
+        .ok_or_else(|| DecodeError::CorruptedFile("HEIF file has no meta box".to_string()))?;
+    // meta is a full box: skip version and flags
+    let meta_children = parse_boxes(bytes, meta.body.start + 4..meta.body.end, 1)?;
+
+    let primary_item = match find_box(&meta_children, b"pitm") {
+        Some(pitm) => Some(parse_pitm(&bytes[pitm.body.clone()])?),
+        None => None,
+    };
+
+    let mut items = match find_box(&meta_children, b"iinf") {
+        Some(iinf) => parse_iinf(bytes, iinf.body.clone())?,
+        None => Vec::new(),
+    };
+
+    let idat_start = find_box(&meta_children, b"idat").map(|b| b.body.start);
+    if let Some(iloc) = find_box(&meta_children, b"iloc") {
+        for (id, extents) in parse_iloc(&bytes[iloc.body.clone()], idat_start, bytes.len())? {
+            if let Some(item) = items.iter_mut().find(|item| item.id == id) {
+                item.extents = extents;
+            }
+        }
+    }
+
+    if let Some(iref) = find_box(&meta_children, b"iref") {
+        for (from, to) in parse_thumbnail_refs(bytes, iref.body.clone())? {
+            if let Some(item) = items.iter_mut().find(|item| item.id == from) {
+                item.thumbnail_for = Some(to);
+            }
+        }
+    }
+
+    Ok(HeifContainer {
+        major_brand,
+        primary_item,
+        items,
+    })
+}
+
+/// Extract the best embedded JPEG preview from a HEIF file.
+///
+/// Preference order:
+/// 1. The primary item, if it is JPEG-coded
+/// 2. The largest JPEG-coded item (previews and thumbnails)
+/// 3. The JPEG thumbnail inside the `Exif` item
+///
+/// # Errors
+///
+/// Returns `DecodeError::UnsupportedCodec` if the image data is only
+/// available as HEVC, and `DecodeError::NoThumbnail` if there is no image
+/// data we recognize at all.
+pub fn extract_heif_preview(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let container = parse_heif_container(bytes)?;
+
+    let primary_jpeg = container
+        .items_of_type("jpeg")
+        .find(|item| Some(item.id) == container.primary_item);
+    let largest_jpeg = container
+        .items_of_type("jpeg")
+        .filter(|item| !item.is_empty())
+        .max_by_key(|item| item.len());
+    if let Some(jpeg) = primary_jpeg
+        .or(largest_jpeg)
+        .and_then(|item| item.data(bytes))
+    {
+        return Ok(jpeg);
+    }
+
+    if let Some(thumbnail) = container
+        .items_of_type("Exif")
+        .find_map(|item| exif_thumbnail(&item.data(bytes)?))
+    {
+        return Ok(thumbnail);
+    }
+
+    if container
+        .items
+        .iter()
+        .any(|item| HEVC_ITEM_TYPES.contains(&item.item_type.as_str()))
+    {
+        return Err(DecodeError::UnsupportedCodec("HEVC".to_string()));
+    }
+    Err(DecodeError::NoThumbnail)
+}
+
+/// Extract and decode the best embedded JPEG preview from a HEIF file.
+///
+/// # Errors
+///
+/// Same as [`extract_heif_preview`], plus JPEG decode errors.
+pub fn decode_heif_preview(bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
+    let jpeg_bytes = extract_heif_preview(bytes)?;
+    decode_jpeg(&jpeg_bytes)
+}
+
+/// An item ID together with its absolute data extents.
+type ItemLocation = (u32, Vec<Range<usize>>);
+
+/// A box header: four-character type and body range within the file.
+struct BmffBox {
+    box_type: [u8; 4],
+    body: Range<usize>,
+}
+
+/// Parse the sequence of boxes inside `range`.
+fn parse_boxes(
+    bytes: &[u8],
+    range: Range<usize>,
+    depth: usize,
+) -> Result<Vec<BmffBox>, DecodeError> {
+    if depth > MAX_BOX_DEPTH {
+        return Err(corrupted("boxes nested too deeply"));
+    }
+
+    let mut boxes = Vec::new();
+    let mut pos = range.start;
+    while pos + 8 <= range.end {
+        let size =
+            read_u32(bytes, pos, false).ok_or_else(|| corrupted("truncated box header"))? as usize;
+        let mut box_type = [0u8; 4];
+        box_type.copy_from_slice(&bytes[pos + 4..pos + 8]);
+
+        let (header_len, box_len) = match size {
+            0 => (8, range.end - pos),
+            1 => {
+                let large = read_u64(bytes, pos + 8, false)
+                    .ok_or_else(|| corrupted("truncated box header"))?;
+                (
+                    16,
+                    usize::try_from(large).map_err(|_| corrupted("box too large"))?,
+                )
+            }
+            _ => (8, size),
+        };
+        let end = pos
+            .checked_add(box_len)
+            .filter(|&end| box_len >= header_len && end <= range.end)
+            .ok_or_else(|| corrupted("box extends past its parent"))?;
+
+        boxes.push(BmffBox {
+            box_type,
+            body: pos + header_len..end,
+        });
+        pos = end;
+    }
+    Ok(boxes)
+}
+
+fn find_box<'a>(boxes: &'a [BmffBox], box_type: &[u8; 4]) -> Option<&'a BmffBox> {
+    boxes.iter().find(|b| &b.box_type == box_type)
+}
+
+/// Parse a `pitm` body into the primary item ID.
+fn parse_pitm(body: &[u8]) -> Result<u32, DecodeError> {
+    let version = *body.first().ok_or_else(|| corrupted("truncated pitm"))?;
+    let id = if version == 0 {
+        read_u16(body, 4, false).map(u32::from)
+    } else {
+        read_u32(body, 4, false)
+    };
+    id.ok_or_else(|| corrupted("truncated pitm"))
+}
+
+/// Parse an `iinf` body into items (without locations).
+fn parse_iinf(bytes: &[u8], body: Range<usize>) -> Result<Vec<HeifItem>, DecodeError> {
+    let version = *bytes
+        .get(body.start)
+        .ok_or_else(|| corrupted("truncated iinf"))?;
+    let entries_start = body.start + if version == 0 { 6 } else { 8 };
+    if entries_start > body.end {
+        return Err(corrupted("truncated iinf"));
+    }
+
+    let mut items = Vec::new();
+    for infe in parse_boxes(bytes, entries_start..body.end, 2)? {
+        if &infe.box_type != b"infe" {
+            continue;
+        }
+        let infe_body = &bytes[infe.body.clone()];
+        let version = *infe_body
+            .first()
+            .ok_or_else(|| corrupted("truncated infe"))?;
+        // Versions 0 and 1 predate item types and cannot describe images
+        if version < 2 {
+            continue;
+        }
+        let (id, type_offset) = if version == 2 {
+            (read_u16(infe_body, 4, false).map(u32::from), 8)
+        } else {
+            (read_u32(infe_body, 4, false), 10)
+        };
+        let id = id.ok_or_else(|| corrupted("truncated infe"))?;
+        let item_type = infe_body
+            .get(type_offset..type_offset + 4)
+            .ok_or_else(|| corrupted("truncated infe"))?;
+
+        items.push(HeifItem {
+            id,
+            item_type: fourcc(item_type),
+            extents: Vec::new(),
+            thumbnail_for: None,
+        });
+    }
+    Ok(items)
+}
+
+/// Parse an `iloc` body into absolute extents per item ID.
+///
+/// Items stored by reference to other items (construction method 2) are
+/// skipped, as are extents that fall outside the file.
+fn parse_iloc(
+    body: &[u8],
+    idat_start: Option<usize>,
+    file_len: usize,
+) -> Result<Vec<ItemLocation>, DecodeError> {
+    let mut reader = FieldReader { data: body, pos: 0 };
+    let version = reader.read(1)?;
+    reader.read(3)?; // flags
+
+    let sizes = reader.read(1)?;
+    let (offset_size, length_size) = ((sizes >> 4) as usize, (sizes & 0x0F) as usize);
+    let sizes = reader.read(1)?;
+    let base_offset_size = (sizes >> 4) as usize;
+    let index_size = if version == 1 || version == 2 {
+        (sizes & 0x0F) as usize
+    } else {
+        0
+    };
+    let item_count = reader.read(if version < 2 { 2 } else { 4 })?;
+
+    let mut locations = Vec::new();
+    for _ in 0..item_count {
+        let id = reader.read(if version < 2 { 2 } else { 4 })? as u32;
+        let construction_method = if version == 1 || version == 2 {
+            reader.read(2)? & 0x0F
+        } else {
+            0
+        };
+        reader.read(2)?; // data_reference_index
+        let base_offset = reader.read(base_offset_size)?;
+        let extent_count = reader.read(2)?;
+
+        let base = match construction_method {
+            0 => Some(0),
+            1 => idat_start.map(|start| start as u64),
+            _ => None,
+        };
+
+        let mut extents = Vec::new();
+        for _ in 0..extent_count {
+            reader.read(index_size)?;
+            let offset = reader.read(offset_size)?;
+            let length = reader.read(length_size)?;
+
+            let start = base.and_then(|b| b.checked_add(base_offset)?.checked_add(offset));
+            // A zero length means "to the end of the file"
+            let range = start.and_then(|start| {
+                let start = usize::try_from(start).ok()?;
+                let end = if length == 0 {
+                    file_len
+                } else {
+                    start.checked_add(usize::try_from(length).ok()?)?
+                };
+                (start < end && end <= file_len).then_some(start..end)
+            });
+            if let Some(range) = range {
+                extents.push(range);
+            }
+        }
+        if base.is_some() && extents.len() == extent_count as usize {
+            locations.push((id, extents));
+        }
+    }
+    Ok(locations)
+}
+
+/// Parse an `iref` body into `(thumbnail, master)` pairs from `thmb` references.
+fn parse_thumbnail_refs(bytes: &[u8], body: Range<usize>) -> Result<Vec<(u32, u32)>, DecodeError> {
+    let version = *bytes
+        .get(body.start)
+        .ok_or_else(|| corrupted("truncated iref"))?;
+    let id_size = if version == 0 { 2 } else { 4 };
+
+    let mut refs = Vec::new();
+    for reference in parse_boxes(bytes, body.start + 4..body.end, 2)? {
+        if &reference.box_type != b"thmb" {
+            continue;
+        }
+        let mut reader = FieldReader {
+            data: &bytes[reference.body.clone()],
+            pos: 0,
+        };
+        let from = reader.read(id_size)? as u32;
+        let count = reader.read(2)?;
+        for _ in 0..count {
+            refs.push((from, reader.read(id_size)? as u32));
+        }
+    }
+    Ok(refs)
+}
+
+/// Extract the JPEG thumbnail from an `Exif` item's data.
+///
+/// The item starts with a 32-bit offset to the TIFF header, which is
+/// usually preceded by an `Exif\0\0` marker.
+fn exif_thumbnail(item: &[u8]) -> Option<Vec<u8>> {
+    let tiff_offset = (read_u32(item, 0, false)? as usize).checked_add(4)?;
+    let tiff = item.get(tiff_offset..)?;
+    let exif = Reader::new().read_raw(tiff.to_vec()).ok()?;
+
+    let offset = exif
+        .get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+    let length = exif
+        .get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+    let jpeg = tiff.get(offset..offset.checked_add(length)?)?;
+    jpeg.starts_with(&[0xFF, 0xD8]).then(|| jpeg.to_vec())
+}
+
+/// Sequential reader for the variable-width integer fields in `iloc`.
+struct FieldReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl FieldReader<'_> {
+    /// Read a big-endian unsigned integer of `size` bytes (0 reads as 0).
+    fn read(&mut self, size: usize) -> Result<u64, DecodeError> {
+        let field = self
+            .data
+            .get(self.pos..self.pos + size)
+            .ok_or_else(|| corrupted("truncated iloc"))?;
+        self.pos += size;
+        Ok(field.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+}
+
+fn fourcc(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn corrupted(message: &str) -> DecodeError {
+    DecodeError::CorruptedFile(format!("Invalid HEIF container: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bmff_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn full_box(box_type: &[u8; 4], version: u8, body: &[u8]) -> Vec<u8> {
+        let mut full = vec![version, 0, 0, 0];
+        full.extend_from_slice(body);
+        bmff_box(box_type, &full)
+    }
+
+    /// Build a minimal HEIF file with one item per `(type, data)` entry.
+    ///
+    /// Item IDs start at 1; item 1 is primary. Data is stored in `mdat`.
+    fn build_heif(items: &[(&[u8; 4], &[u8])], thumbnail_refs: &[(u16, u16)]) -> Vec<u8> {
+        let ftyp = bmff_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+
+        let build_meta = |mdat_data_start: u32| {
+            let mut infes = (items.len() as u16).to_be_bytes().to_vec();
+            for (i, (item_type, _)) in items.iter().enumerate() {
+                let mut body = (i as u16 + 1).to_be_bytes().to_vec();
+                body.extend_from_slice(&[0, 0]);
+                body.extend_from_slice(*item_type);
+                body.push(0);
+                infes.extend(full_box(b"infe", 2, &body));
+            }
+
+            let mut iloc = vec![0x44, 0x00];
+            iloc.extend_from_slice(&(items.len() as u16).to_be_bytes());
+            let mut offset = mdat_data_start;
+            for (i, (_, data)) in items.iter().enumerate() {
+                iloc.extend_from_slice(&(i as u16 + 1).to_be_bytes());
+                iloc.extend_from_slice(&[0, 0, 0, 1]);
+                iloc.extend_from_slice(&offset.to_be_bytes());
+                iloc.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                offset += data.len() as u32;
+            }
+
+            let mut iref = Vec::new();
+            for (from, to) in thumbnail_refs {
+                let mut body = from.to_be_bytes().to_vec();
+                body.extend_from_slice(&1u16.to_be_bytes());
+                body.extend_from_slice(&to.to_be_bytes());
+                iref.extend(bmff_box(b"thmb", &body));
+            }
+
+            let mut meta = full_box(b"hdlr", 0, b"\0\0\0\0pict\0\0\0\0\0\0\0\0\0\0\0\0\0");
+            meta.extend(full_box(b"pitm", 0, &1u16.to_be_bytes()));
+            meta.extend(full_box(b"iinf", 0, &infes));
+            meta.extend(full_box(b"iloc", 0, &iloc));
+            if !iref.is_empty() {
+                meta.extend(full_box(b"iref", 0, &iref));
+            }
+            full_box(b"meta", 0, &meta)
+        };
+
+        let meta_len = build_meta(0).len();
+        let meta = build_meta((ftyp.len() + meta_len + 8) as u32);
+        let mdat: Vec<u8> = items
+            .iter()
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect();
+
+        let mut file = ftyp;
+        file.extend(meta);
+        file.extend(bmff_box(b"mdat", &mdat));
+        file
+    }
+
+    fn test_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let pixels = vec![128u8; (width * height * 3) as usize];
+        crate::encode::encode_jpeg(&pixels, width, height, 90).unwrap()
+    }
+
+    /// Build an `Exif` item whose IFD1 points at an embedded JPEG thumbnail.
+    fn exif_item_with_thumbnail(jpeg: &[u8]) -> Vec<u8> {
+        // Little-endian TIFF: empty IFD0 linking to IFD1 with two entries
+        let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        tiff.extend_from_slice(&[0, 0]); // IFD0: no entries
+        tiff.extend_from_slice(&14u32.to_le_bytes()); // next IFD at 14
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        let thumb_offset = 14 + 2 + 2 * 12 + 4;
+        for (tag, value) in [
+            (0x0201u16, thumb_offset as u32),
+            (0x0202, jpeg.len() as u32),
+        ] {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&4u16.to_le_bytes()); // LONG
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            tiff.extend_from_slice(&value.to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(jpeg);
+
+        let mut item = 6u32.to_be_bytes().to_vec();
+        item.extend_from_slice(b"Exif\0\0");
+        item.extend(tiff);
+        item
+    }
+
+    #[test]
+    fn test_is_heif_file() {
+        let heif = build_heif(&[(b"hvc1", b"hevc")], &[]);
+        assert!(is_heif_file(&heif));
+        assert!(!is_heif_file(&test_jpeg(2, 2)));
+        assert!(!is_heif_file(&[0x49, 0x49, 0x2A, 0x00]));
+        assert!(!is_heif_file(&bmff_box(b"ftyp", b"isom\0\0\0\0mp41")));
+    }
+
+    #[test]
+    fn test_parse_container_items() {
+        let heif = build_heif(&[(b"hvc1", b"hevcdata"), (b"jpeg", b"jpg")], &[(2, 1)]);
+        let container = parse_heif_container(&heif).unwrap();
+
+        assert_eq!(container.major_brand, "heic");
+        assert_eq!(container.primary_item, Some(1));
+        assert_eq!(container.items.len(), 2);
+
+        let hevc = &container.items[0];
+        assert_eq!(hevc.item_type, "hvc1");
+        assert_eq!(hevc.data(&heif).unwrap(), b"hevcdata");
+
+        let jpeg = &container.items[1];
+        assert_eq!(jpeg.item_type, "jpeg");
+        assert_eq!(jpeg.data(&heif).unwrap(), b"jpg");
+        assert_eq!(jpeg.thumbnail_for, Some(1));
+    }
+
+    #[test]
+    fn test_extract_embedded_jpeg_item() {
+        let jpeg = test_jpeg(8, 6);
+        let heif = build_heif(&[(b"hvc1", b"hevcdata"), (b"jpeg", &jpeg)], &[(2, 1)]);
+
+        assert_eq!(extract_heif_preview(&heif).unwrap(), jpeg);
+
+        let image = decode_heif_preview(&heif).unwrap();
+        assert_eq!((image.width, image.height), (8, 6));
+    }
+
+    #[test]
+    fn test_extract_prefers_largest_jpeg() {
+        let small = test_jpeg(4, 4);
+        let large = test_jpeg(32, 32);
+        let heif = build_heif(
+            &[(b"hvc1", b"hevc"), (b"jpeg", &small), (b"jpeg", &large)],
+            &[],
+        );
+        assert_eq!(extract_heif_preview(&heif).unwrap(), large);
+    }
+
+    #[test]
+    fn test_extract_exif_thumbnail() {
+        let jpeg = test_jpeg(16, 12);
+        let exif = exif_item_with_thumbnail(&jpeg);
+        let heif = build_heif(&[(b"hvc1", b"hevcdata"), (b"Exif", &exif)], &[]);
+
+        assert_eq!(extract_heif_preview(&heif).unwrap(), jpeg);
+    }
+
+    #[test]
+    fn test_hevc_only_is_unsupported_codec() {
+        let heif = build_heif(&[(b"hvc1", b"tile1"), (b"hvc1", b"tile2")], &[]);
+        match extract_heif_preview(&heif) {
+            Err(DecodeError::UnsupportedCodec(codec)) => assert_eq!(codec, "HEVC"),
+            other => panic!("Expected UnsupportedCodec, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_not_heif_is_invalid_format() {
+        assert!(matches!(
+            extract_heif_preview(&test_jpeg(2, 2)),
+            Err(DecodeError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_container_is_corrupted() {
+        let heif = build_heif(&[(b"jpeg", b"data")], &[]);
+        let truncated = &heif[..heif.len() - 20];
+        assert!(matches!(
+            parse_heif_container(truncated),
+            Err(DecodeError::CorruptedFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_box_size_overflow_is_rejected() {
+        let mut heif = build_heif(&[(b"jpeg", b"data")], &[]);
+        // Give the meta box an impossible size
+        let meta_pos = 24;
+        heif[meta_pos..meta_pos + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(parse_heif_container(&heif).is_err());
+    }
+}
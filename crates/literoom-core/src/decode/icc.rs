@@ -0,0 +1,773 @@
+//! ICC profile extraction, writing, and conversion to and from sRGB.
+//!
+//! JPEGs carry their ICC profile in one or more APP2 segments. We reassemble
+//! the profile, recognize the common RGB working spaces (sRGB, Adobe RGB and
+//! Display P3) by description or colorant primaries, and convert pixels to
+//! sRGB with a linear-light 3x3 matrix. This covers the cases that matter for
+//! display without pulling in a full color management engine.
+//!
+//! The same matrix math runs the other way on output: [`convert_for_display`]
+//! re-encodes finished sRGB pixels for a Display P3 canvas, so wide-gamut
+//! screens show the values the pipeline computed instead of stretching them,
+//! and [`convert_from_srgb`] re-encodes exports for the working space whose
+//! profile [`ColorProfile::icc_profile`] writes into the file.
+
+use super::jpeg::app_segments;
+use super::read_u32;
+use crate::color::{linear_to_srgb, srgb_to_linear};
+
+/// Signature that starts every ICC APP2 segment payload.
+const ICC_SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+
+/// Largest profile chunk that fits in one APP2 segment after the signature
+/// and sequence bytes.
+const MAX_ICC_CHUNK: usize = 65535 - 2 - 12 - 2;
+
+/// Size of the ICC profile header preceding the tag table.
+const ICC_HEADER_SIZE: usize = 128;
+
+/// Tolerance when matching D50-adapted colorant XYZ values.
+const PRIMARY_TOLERANCE: f32 = 0.02;
+
+/// Pure power gamma used by Adobe RGB (1998).
+const ADOBE_RGB_GAMMA: f32 = 563.0 / 256.0;
+
+/// Number of entries in the linear-to-sRGB encoding table.
+const ENCODE_LUT_SIZE: usize = 4096;
+
+/// Number of entries in the tone curve of a written sRGB or Display P3
+/// profile.
+const TRC_TABLE_SIZE: usize = 1024;
+
+/// D50 white point of the ICC profile connection space.
+const D50_WHITE: [f32; 3] = [0.9642, 1.0, 0.8249];
+
+/// RGB color spaces recognized from an embedded ICC profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorProfile {
+    /// sRGB IEC61966-2.1 (no conversion needed).
+    Srgb,
+    /// Adobe RGB (1998).
+    AdobeRgb,
+    /// Display P3.
+    DisplayP3,
+}
+
+impl ColorProfile {
+    /// Human-readable profile name.
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorProfile::Srgb => "sRGB",
+            ColorProfile::AdobeRgb => "Adobe RGB (1998)",
+            ColorProfile::DisplayP3 => "Display P3",
+        }
+    }
+
+    /// Linear-light matrix from this space to linear sRGB (both D65).
+    fn to_srgb_matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorProfile::Srgb => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            ColorProfile::AdobeRgb => [
+                [1.39836, -0.39836, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, -0.04293, 1.04293],
+            ],
+            ColorProfile::DisplayP3 => [
+                [1.22494, -0.22494, 0.0],
+                [-0.04206, 1.04206, 0.0],
+                [-0.01964, -0.07864, 1.09828],
+            ],
+        }
+    }
+
+    /// Linear-light matrix from linear sRGB to this space (both D65).
+    fn srgb_to_profile_matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorProfile::Srgb => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            ColorProfile::AdobeRgb => [
+                [0.71512, 0.28488, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.04116, 0.95884],
+            ],
+            ColorProfile::DisplayP3 => SRGB_TO_DISPLAY_P3,
+        }
+    }
+
+    /// Decode an encoded channel value (0.0 to 1.0) to linear light.
+    fn to_linear(self, v: f32) -> f32 {
+        match self {
+            ColorProfile::AdobeRgb => v.powf(ADOBE_RGB_GAMMA),
+            // Display P3 shares the sRGB transfer function
+            ColorProfile::Srgb | ColorProfile::DisplayP3 => srgb_to_linear(v),
+        }
+    }
+
+    /// Encode a linear-light channel value (0.0 to 1.0).
+    fn encode_linear(self, v: f32) -> f32 {
+        match self {
+            ColorProfile::AdobeRgb => v.powf(1.0 / ADOBE_RGB_GAMMA),
+            ColorProfile::Srgb | ColorProfile::DisplayP3 => linear_to_srgb(v),
+        }
+    }
+
+    /// D50-adapted red and green colorants as stored in `rXYZ`/`gXYZ` tags.
+    fn colorants(self) -> [[f32; 3]; 2] {
+        match self {
+            ColorProfile::Srgb => [[0.4360, 0.2225, 0.0139], [0.3851, 0.7169, 0.0971]],
+            ColorProfile::AdobeRgb => [[0.6097, 0.3111, 0.0195], [0.2052, 0.6257, 0.0609]],
+            ColorProfile::DisplayP3 => [[0.5151, 0.2412, -0.0011], [0.2919, 0.6922, 0.0419]],
+        }
+    }
+
+    /// Build an ICC v2 display profile describing this space.
+    ///
+    /// The profile carries the description from [`ColorProfile::name`], the
+    /// D50 white point, the three colorants and the tone curve, which is
+    /// enough for browsers and image viewers to color-manage an export and
+    /// for [`detect_color_profile`] to recognize it again.
+    pub fn icc_profile(self) -> Vec<u8> {
+        let [red, green] = self.colorants();
+        let blue: [f32; 3] = std::array::from_fn(|i| D50_WHITE[i] - red[i] - green[i]);
+
+        let mut desc = b"desc\0\0\0\0".to_vec();
+        desc.extend_from_slice(&(self.name().len() as u32 + 1).to_be_bytes());
+        desc.extend_from_slice(self.name().as_bytes());
+        desc.push(0);
+        // Empty Unicode and ScriptCode descriptions
+        desc.extend_from_slice(&[0; 4 + 4 + 2 + 1 + 67]);
+
+        let xyz = |value: [f32; 3]| {
+            let mut data = b"XYZ \0\0\0\0".to_vec();
+            for v in value {
+                data.extend_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+            }
+            data
+        };
+
+        let mut trc = b"curv\0\0\0\0".to_vec();
+        match self {
+            ColorProfile::AdobeRgb => {
+                // A single entry is a u8Fixed8 gamma
+                trc.extend_from_slice(&1u32.to_be_bytes());
+                trc.extend_from_slice(&((ADOBE_RGB_GAMMA * 256.0).round() as u16).to_be_bytes());
+            }
+            ColorProfile::Srgb | ColorProfile::DisplayP3 => {
+                trc.extend_from_slice(&(TRC_TABLE_SIZE as u32).to_be_bytes());
+                for i in 0..TRC_TABLE_SIZE {
+                    let v = self.to_linear(i as f32 / (TRC_TABLE_SIZE - 1) as f32);
+                    trc.extend_from_slice(&((v * 65535.0).round() as u16).to_be_bytes());
+                }
+            }
+        }
+
+        let tags: [([u8; 4], Vec<u8>); 8] = [
+            (*b"desc", desc),
+            (*b"wtpt", xyz(D50_WHITE)),
+            (*b"rXYZ", xyz(red)),
+            (*b"gXYZ", xyz(green)),
+            (*b"bXYZ", xyz(blue)),
+            (*b"rTRC", trc.clone()),
+            (*b"gTRC", trc.clone()),
+            (*b"bTRC", trc),
+        ];
+
+        let mut icc = vec![0u8; ICC_HEADER_SIZE];
+        icc[8..12].copy_from_slice(&[2, 0x10, 0, 0]);
+        icc[12..16].copy_from_slice(b"mntr");
+        icc[16..20].copy_from_slice(b"RGB ");
+        icc[20..24].copy_from_slice(b"XYZ ");
+        icc[36..40].copy_from_slice(b"acsp");
+        for (i, v) in D50_WHITE.into_iter().enumerate() {
+            let offset = 68 + i * 4;
+            icc[offset..offset + 4].copy_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+        }
+
+        icc.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+        let mut offset = ICC_HEADER_SIZE + 4 + tags.len() * 12;
+        let mut body = Vec::new();
+        for (sig, data) in &tags {
+            icc.extend_from_slice(sig);
+            icc.extend_from_slice(&(offset as u32).to_be_bytes());
+            icc.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            body.extend_from_slice(data);
+            // Tag data starts on a 4-byte boundary
+            let padded = data.len().next_multiple_of(4);
+            body.resize(body.len() + padded - data.len(), 0);
+            offset += padded;
+        }
+        icc.extend_from_slice(&body);
+        let size = icc.len() as u32;
+        icc[0..4].copy_from_slice(&size.to_be_bytes());
+        icc
+    }
+}
+
+/// Color space of the canvas that rendered pixels are drawn to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayColorSpace {
+    /// sRGB, the default canvas color space. Pixels are left as they are.
+    #[default]
+    Srgb,
+    /// Display P3 (`{ colorSpace: 'display-p3' }` canvases).
+    DisplayP3,
+}
+
+/// Linear-light matrix from sRGB to Display P3 (both D65).
+const SRGB_TO_DISPLAY_P3: [[f32; 3]; 3] = [
+    [0.82246, 0.17754, 0.0],
+    [0.03319, 0.96681, 0.0],
+    [0.01708, 0.07240, 0.91052],
+];
+
+/// Extract the ICC profile embedded in a JPEG's APP2 segments.
+///
+/// Profiles larger than one segment are split into numbered chunks, which
+/// are reassembled in sequence order. Returns `None` if the file has no
+/// profile or if any chunk is missing.
+pub fn extract_icc_profile(bytes: &[u8]) -> Option<Vec<u8>> {
+    if bytes.len() < 4 || bytes[0] != 0xFF || bytes[1] != 0xD8 {
+        return None;
+    }
+
+    let mut chunks: Vec<(u8, &[u8])> = Vec::new();
+    let mut expected_count = 0u8;
+    for (marker, payload) in app_segments(bytes) {
+        if marker == 0xE2
+            && payload.len() > ICC_SIGNATURE.len() + 2
+            && payload.starts_with(ICC_SIGNATURE)
+        {
+            let seq = payload[ICC_SIGNATURE.len()];
+            let count = payload[ICC_SIGNATURE.len() + 1];
+            if expected_count != 0 && count != expected_count {
+                return None;
+            }
+            expected_count = count;
+            chunks.push((seq, &payload[ICC_SIGNATURE.len() + 2..]));
+        }
+    }
+
+    if chunks.is_empty() || chunks.len() != expected_count as usize {
+        return None;
+    }
+    chunks.sort_by_key(|(seq, _)| *seq);
+    if chunks
+        .iter()
+        .enumerate()
+        .any(|(i, (seq, _))| *seq as usize != i + 1)
+    {
+        return None;
+    }
+
+    Some(
+        chunks
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .copied()
+            .collect(),
+    )
+}
+
+/// Read the profile description (`desc` tag) from an ICC profile.
+///
+/// Supports both the ICC v2 `desc` type and the v4 `mluc` type.
+pub fn icc_description(icc: &[u8]) -> Option<String> {
+    let tag = find_tag(icc, b"desc")?;
+    if tag.len() < 12 {
+        return None;
+    }
+
+    match &tag[0..4] {
+        b"desc" => {
+            let count = read_u32(tag, 8, false)? as usize;
+            let text = tag.get(12..12 + count)?;
+            let text = text.split(|&b| b == 0).next().unwrap_or(&[]);
+            let s = String::from_utf8_lossy(text).trim().to_string();
+            (!s.is_empty()).then_some(s)
+        }
+        b"mluc" => {
+            let records = read_u32(tag, 8, false)?;
+            if records == 0 {
+                return None;
+            }
+            // Use the first record regardless of language
+            let length = read_u32(tag, 20, false)? as usize;
+            let offset = read_u32(tag, 24, false)? as usize;
+            let text = tag.get(offset..offset + length)?;
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            let s = String::from_utf16_lossy(&units)
+                .trim_end_matches('\0')
+                .trim()
+                .to_string();
+            (!s.is_empty()).then_some(s)
+        }
+        _ => None,
+    }
+}
+
+/// Identify which common RGB space an ICC profile describes.
+///
+/// The description is checked first; if it is unrecognized, the red and
+/// green colorant tags are compared against the known primaries. Returns
+/// `None` for profiles outside the supported set.
+pub fn detect_color_profile(icc: &[u8]) -> Option<ColorProfile> {
+    if let Some(desc) = icc_description(icc) {
+        let desc = desc.to_ascii_lowercase();
+        if desc.contains("adobe rgb") || desc.contains("adobergb") {
+            return Some(ColorProfile::AdobeRgb);
+        }
+        if desc.contains("display p3") {
+            return Some(ColorProfile::DisplayP3);
+        }
+        if desc.contains("srgb") {
+            return Some(ColorProfile::Srgb);
+        }
+    }
+
+    let red = read_xyz_tag(icc, b"rXYZ")?;
+    let green = read_xyz_tag(icc, b"gXYZ")?;
+    [
+        ColorProfile::Srgb,
+        ColorProfile::AdobeRgb,
+        ColorProfile::DisplayP3,
+    ]
+    .into_iter()
+    .find(|profile| {
+        let [r, g] = profile.colorants();
+        r.iter()
+            .zip(&red)
+            .chain(g.iter().zip(&green))
+            .all(|(a, b)| (a - b).abs() < PRIMARY_TOLERANCE)
+    })
+}
+
+/// Convert RGB pixel data from `profile` to sRGB in place.
+///
+/// Out-of-gamut colors are clipped. Converting from sRGB is a no-op.
+pub fn convert_to_srgb(pixels: &mut [u8], profile: ColorProfile) {
+    if profile == ColorProfile::Srgb {
+        return;
+    }
+    convert_linear(pixels, |v| profile.to_linear(v), &profile.to_srgb_matrix());
+}
+
+/// Convert sRGB pixel data to `profile` in place.
+///
+/// This is the inverse of [`convert_to_srgb`], used when exporting to a
+/// wider working space. Every sRGB color is inside Adobe RGB and Display
+/// P3, so nothing is clipped. Values are encoded exactly rather than
+/// through a table, since a pure gamma like Adobe RGB's needs far finer
+/// steps in the shadows than a linear-light table provides. Converting to
+/// sRGB is a no-op.
+pub fn convert_from_srgb(pixels: &mut [u8], profile: ColorProfile) {
+    if profile == ColorProfile::Srgb {
+        return;
+    }
+    let wide = convert_from_srgb_16(pixels, profile);
+    for (out, v) in pixels.iter_mut().zip(wide) {
+        *out = ((u32::from(v) * 255 + 32767) / 65535) as u8;
+    }
+}
+
+/// Convert sRGB pixel data to `profile` with 16 bits per channel.
+///
+/// The 16-bit counterpart of [`convert_from_srgb`], so a 16-bit export
+/// keeps the precision the conversion gains.
+pub fn convert_from_srgb_16(pixels: &[u8], profile: ColorProfile) -> Vec<u16> {
+    if profile == ColorProfile::Srgb {
+        return pixels.iter().map(|&v| u16::from(v) * 257).collect();
+    }
+    let decode: Vec<f32> = (0..=255u8)
+        .map(|v| srgb_to_linear(v as f32 / 255.0))
+        .collect();
+    let m = profile.srgb_to_profile_matrix();
+    let mut out = Vec::with_capacity(pixels.len());
+    for pixel in pixels.chunks_exact(3) {
+        let r = decode[pixel[0] as usize];
+        let g = decode[pixel[1] as usize];
+        let b = decode[pixel[2] as usize];
+        for row in &m {
+            let linear = (row[0] * r + row[1] * g + row[2] * b).clamp(0.0, 1.0);
+            out.push((profile.encode_linear(linear) * 65535.0).round() as u16);
+        }
+    }
+    out
+}
+
+/// Convert finished sRGB pixels in place for display on a `target` canvas.
+///
+/// This is the last step before pixels are drawn: sRGB primaries are
+/// re-expressed in the target space so saturated colors land where the
+/// canvas expects them. Every sRGB color is inside Display P3, so nothing
+/// is clipped. Converting to sRGB is a no-op.
+pub fn convert_for_display(pixels: &mut [u8], target: DisplayColorSpace) {
+    match target {
+        DisplayColorSpace::Srgb => {}
+        // Display P3 shares the sRGB transfer function
+        DisplayColorSpace::DisplayP3 => convert_linear(pixels, srgb_to_linear, &SRGB_TO_DISPLAY_P3),
+    }
+}
+
+/// Decode with `to_linear`, apply `m` in linear light, and encode with the
+/// sRGB transfer function, clipping to the encoded range.
+fn convert_linear(pixels: &mut [u8], to_linear: impl Fn(f32) -> f32, m: &[[f32; 3]; 3]) {
+    let decode: Vec<f32> = (0..=255u8).map(|v| to_linear(v as f32 / 255.0)).collect();
+    let encode: Vec<u8> = (0..ENCODE_LUT_SIZE)
+        .map(|i| {
+            let linear = i as f32 / (ENCODE_LUT_SIZE - 1) as f32;
+            (linear_to_srgb(linear) * 255.0).round() as u8
+        })
+        .collect();
+    let scale = (ENCODE_LUT_SIZE - 1) as f32;
+
+    for pixel in pixels.chunks_exact_mut(3) {
+        let r = decode[pixel[0] as usize];
+        let g = decode[pixel[1] as usize];
+        let b = decode[pixel[2] as usize];
+        for (out, row) in pixel.iter_mut().zip(m) {
+            let linear = (row[0] * r + row[1] * g + row[2] * b).clamp(0.0, 1.0);
+            *out = encode[(linear * scale).round() as usize];
+        }
+    }
+}
+
+/// Embed an ICC profile in a JPEG as APP2 segments.
+///
+/// The segments go right after the JFIF header (or after SOI if there is
+/// none), split into numbered chunks when the profile is larger than one
+/// segment holds.
+pub(crate) fn embed_icc_profile(jpeg: &[u8], icc: &[u8]) -> Vec<u8> {
+    let mut insert_at = 2;
+    if jpeg.get(2..4) == Some(&[0xFF, 0xE0]) {
+        if let Some(length) = jpeg.get(4..6) {
+            insert_at = (4 + u16::from_be_bytes([length[0], length[1]]) as usize).min(jpeg.len());
+        }
+    }
+
+    let chunks: Vec<&[u8]> = icc.chunks(MAX_ICC_CHUNK).collect();
+    let mut out = Vec::with_capacity(jpeg.len() + icc.len() + chunks.len() * 18);
+    out.extend_from_slice(&jpeg[..insert_at]);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let length = 2 + ICC_SIGNATURE.len() + 2 + chunk.len();
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&(length as u16).to_be_bytes());
+        out.extend_from_slice(ICC_SIGNATURE);
+        out.push(i as u8 + 1);
+        out.push(chunks.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&jpeg[insert_at..]);
+    out
+}
+
+/// Locate a tag's data in the ICC tag table.
+fn find_tag<'a>(icc: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = read_u32(icc, ICC_HEADER_SIZE, false)? as usize;
+    (0..count).find_map(|i| {
+        let entry = ICC_HEADER_SIZE + 4 + i * 12;
+        if icc.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let offset = read_u32(icc, entry + 4, false)? as usize;
+        let size = read_u32(icc, entry + 8, false)? as usize;
+        icc.get(offset..offset.checked_add(size)?)
+    })
+}
+
+/// Read an `XYZ ` tag as three floats.
+fn read_xyz_tag(icc: &[u8], signature: &[u8; 4]) -> Option<[f32; 3]> {
+    let tag = find_tag(icc, signature)?;
+    if tag.get(0..4)? != b"XYZ " {
+        return None;
+    }
+    let s15 = |offset| read_u32(tag, offset, false).map(|v| v as i32 as f32 / 65536.0);
+    Some([s15(8)?, s15(12)?, s15(16)?])
+}
+
+/// Build a minimal ICC profile with a v2 `desc` tag and optional colorants.
+#[cfg(test)]
+pub(super) fn build_test_profile(
+    description: Option<&str>,
+    colorants: Option<[[f32; 3]; 2]>,
+) -> Vec<u8> {
+    let mut tags: Vec<([u8; 4], Vec<u8>)> = Vec::new();
+    if let Some(desc) = description {
+        let mut data = b"desc\0\0\0\0".to_vec();
+        data.extend_from_slice(&(desc.len() as u32 + 1).to_be_bytes());
+        data.extend_from_slice(desc.as_bytes());
+        data.push(0);
+        tags.push((*b"desc", data));
+    }
+    if let Some([red, green]) = colorants {
+        for (sig, xyz) in [(*b"rXYZ", red), (*b"gXYZ", green)] {
+            let mut data = b"XYZ \0\0\0\0".to_vec();
+            for v in xyz {
+                data.extend_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+            }
+            tags.push((sig, data));
+        }
+    }
+
+    let mut icc = vec![0u8; ICC_HEADER_SIZE];
+    icc.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+    let mut offset = ICC_HEADER_SIZE + 4 + tags.len() * 12;
+    let mut body = Vec::new();
+    for (sig, data) in &tags {
+        icc.extend_from_slice(sig);
+        icc.extend_from_slice(&(offset as u32).to_be_bytes());
+        icc.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(data);
+        offset += data.len();
+    }
+    icc.extend_from_slice(&body);
+    let size = icc.len() as u32;
+    icc[0..4].copy_from_slice(&size.to_be_bytes());
+    icc
+}
+
+/// Insert an ICC profile into a JPEG as APP2 chunks of at most `chunk_size` bytes.
+#[cfg(test)]
+pub(super) fn embed_test_profile(jpeg: &[u8], icc: &[u8], chunk_size: usize) -> Vec<u8> {
+    let chunks: Vec<&[u8]> = icc.chunks(chunk_size).collect();
+    let mut out = jpeg[..2].to_vec();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let length = 2 + ICC_SIGNATURE.len() + 2 + chunk.len();
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&(length as u16).to_be_bytes());
+        out.extend_from_slice(ICC_SIGNATURE);
+        out.push(i as u8 + 1);
+        out.push(chunks.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&jpeg[2..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JPEG_STUB: &[u8] = &[0xFF, 0xD8, 0xFF, 0xD9];
+
+    #[test]
+    fn test_extract_single_segment_profile() {
+        let icc = build_test_profile(Some("Adobe RGB (1998)"), None);
+        let jpeg = embed_test_profile(JPEG_STUB, &icc, 60000);
+        assert_eq!(extract_icc_profile(&jpeg), Some(icc));
+    }
+
+    #[test]
+    fn test_extract_multi_segment_profile() {
+        let icc = build_test_profile(
+            Some("Display P3"),
+            Some(ColorProfile::DisplayP3.colorants()),
+        );
+        let jpeg = embed_test_profile(JPEG_STUB, &icc, 40);
+        assert_eq!(extract_icc_profile(&jpeg), Some(icc));
+    }
+
+    #[test]
+    fn test_extract_missing_chunk_returns_none() {
+        let icc = build_test_profile(Some("Display P3"), None);
+        let jpeg = embed_test_profile(JPEG_STUB, &icc, 40);
+        // Drop the first APP2 segment
+        let first_len = u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+        let mut broken = jpeg[..2].to_vec();
+        broken.extend_from_slice(&jpeg[4 + first_len..]);
+        assert_eq!(extract_icc_profile(&broken), None);
+    }
+
+    #[test]
+    fn test_extract_untagged_returns_none() {
+        assert_eq!(extract_icc_profile(JPEG_STUB), None);
+        assert_eq!(extract_icc_profile(&[0, 1, 2]), None);
+    }
+
+    #[test]
+    fn test_description_v2() {
+        let icc = build_test_profile(Some("sRGB IEC61966-2.1"), None);
+        assert_eq!(icc_description(&icc).as_deref(), Some("sRGB IEC61966-2.1"));
+    }
+
+    #[test]
+    fn test_detect_by_description() {
+        let cases = [
+            ("sRGB IEC61966-2.1", ColorProfile::Srgb),
+            ("Adobe RGB (1998)", ColorProfile::AdobeRgb),
+            ("Display P3", ColorProfile::DisplayP3),
+        ];
+        for (desc, expected) in cases {
+            let icc = build_test_profile(Some(desc), None);
+            assert_eq!(detect_color_profile(&icc), Some(expected), "{}", desc);
+        }
+    }
+
+    #[test]
+    fn test_detect_by_primaries() {
+        for profile in [
+            ColorProfile::Srgb,
+            ColorProfile::AdobeRgb,
+            ColorProfile::DisplayP3,
+        ] {
+            let icc = build_test_profile(Some("Custom"), Some(profile.colorants()));
+            assert_eq!(detect_color_profile(&icc), Some(profile));
+        }
+    }
+
+    #[test]
+    fn test_detect_unknown_profile() {
+        let icc = build_test_profile(
+            Some("ProPhoto RGB"),
+            Some([[0.7977, 0.2880, 0.0], [0.1352, 0.7119, 0.0]]),
+        );
+        assert_eq!(detect_color_profile(&icc), None);
+    }
+
+    #[test]
+    fn test_convert_preserves_neutrals() {
+        for profile in [ColorProfile::AdobeRgb, ColorProfile::DisplayP3] {
+            let mut pixels = vec![0, 0, 0, 128, 128, 128, 255, 255, 255];
+            convert_to_srgb(&mut pixels, profile);
+            assert_eq!(pixels[0..3], [0, 0, 0]);
+            assert_eq!(pixels[6..9], [255, 255, 255]);
+            for &v in &pixels[3..6] {
+                assert!((v as i32 - pixels[3] as i32).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert_adobe_green_is_more_saturated() {
+        let mut pixels = vec![60, 200, 60];
+        convert_to_srgb(&mut pixels, ColorProfile::AdobeRgb);
+        // Adobe RGB green maps outside the encoded sRGB value, pulling red down
+        assert!(pixels[0] < 40, "red {}", pixels[0]);
+        assert!(pixels[1] >= 195, "green {}", pixels[1]);
+    }
+
+    #[test]
+    fn test_written_profile_is_recognized() {
+        for profile in [
+            ColorProfile::Srgb,
+            ColorProfile::AdobeRgb,
+            ColorProfile::DisplayP3,
+        ] {
+            let icc = profile.icc_profile();
+            assert_eq!(read_u32(&icc, 0, false), Some(icc.len() as u32));
+            assert_eq!(&icc[36..40], b"acsp");
+            assert_eq!(icc_description(&icc).as_deref(), Some(profile.name()));
+            assert_eq!(detect_color_profile(&icc), Some(profile));
+
+            // The colorants are written as given, and sum to the white point
+            let [red, green] = profile.colorants();
+            let written = read_xyz_tag(&icc, b"rXYZ").unwrap();
+            assert!(written.iter().zip(&red).all(|(a, b)| (a - b).abs() < 1e-4));
+            let blue = read_xyz_tag(&icc, b"bXYZ").unwrap();
+            for i in 0..3 {
+                let white = red[i] + green[i] + blue[i];
+                assert!((white - D50_WHITE[i]).abs() < 1e-4, "{:?}", profile);
+            }
+
+            // Embedded after the JFIF header, it reads back unchanged
+            let jfif = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, b'J', b'F', 0xFF, 0xD9];
+            let jpeg = embed_icc_profile(&jfif, &icc);
+            assert_eq!(&jpeg[2..8], &jfif[2..8]);
+            assert_eq!(&jpeg[8..10], &[0xFF, 0xE2]);
+            assert_eq!(extract_icc_profile(&jpeg), Some(icc));
+        }
+    }
+
+    #[test]
+    fn test_convert_from_srgb_round_trip() {
+        let srgb: Vec<u8> = (0..=255u8)
+            .step_by(17)
+            .flat_map(|r| (0..=255u8).step_by(51).map(move |g| [r, g, 255 - r]))
+            .flatten()
+            .collect();
+        for profile in [ColorProfile::AdobeRgb, ColorProfile::DisplayP3] {
+            let mut converted = srgb.clone();
+            convert_from_srgb(&mut converted, profile);
+            assert_ne!(converted, srgb);
+
+            // The 16-bit conversion agrees with the 8-bit one
+            let wide = convert_from_srgb_16(&srgb, profile);
+            for (&v, &w) in converted.iter().zip(&wide) {
+                assert!((v as f32 - w as f32 / 257.0).abs() <= 0.5);
+            }
+
+            // 8-bit steps of a pure gamma are coarse next to the linear toe of
+            // sRGB, so values near black can move a few steps
+            convert_to_srgb(&mut converted, profile);
+            let mut total = 0;
+            for (&a, &b) in srgb.iter().zip(&converted) {
+                let diff = (a as i32 - b as i32).abs();
+                assert!(diff <= 6, "{:?}: {} became {}", profile, a, b);
+                total += diff;
+            }
+            assert!(total as f32 / (srgb.len() as f32) < 1.0, "{:?}", profile);
+        }
+
+        let mut unchanged = srgb.clone();
+        convert_from_srgb(&mut unchanged, ColorProfile::Srgb);
+        assert_eq!(unchanged, srgb);
+    }
+
+    #[test]
+    fn test_display_p3_red() {
+        let mut pixels = vec![255, 0, 0];
+        convert_for_display(&mut pixels, DisplayColorSpace::DisplayP3);
+        // Linear (0.8225, 0.0332, 0.0171) encodes to (233.96, 51.07, 35.33)
+        for (&v, expected) in pixels.iter().zip([234, 51, 35]) {
+            assert!((v as i32 - expected).abs() <= 1, "{:?}", pixels);
+        }
+    }
+
+    #[test]
+    fn test_display_p3_preserves_neutrals() {
+        let mut pixels: Vec<u8> = (0..=255u8).flat_map(|v| [v, v, v]).collect();
+        let original = pixels.clone();
+        convert_for_display(&mut pixels, DisplayColorSpace::DisplayP3);
+        for (&v, &o) in pixels.iter().zip(&original) {
+            assert!((v as i32 - o as i32).abs() <= 1, "{} became {}", o, v);
+        }
+    }
+
+    #[test]
+    fn test_display_p3_round_trip() {
+        // P3 colors inside the sRGB gamut, taken from a grid of sRGB colors
+        let mut p3: Vec<u8> = (0..=255u8)
+            .step_by(15)
+            .flat_map(|r| (0..=255u8).step_by(51).map(move |g| (r, g)))
+            .flat_map(|(r, g)| [[r, g, 0], [r, g, 128], [r, g, 255]])
+            .flatten()
+            .collect();
+        convert_for_display(&mut p3, DisplayColorSpace::DisplayP3);
+
+        let mut round_trip = p3.clone();
+        convert_to_srgb(&mut round_trip, ColorProfile::DisplayP3);
+        convert_for_display(&mut round_trip, DisplayColorSpace::DisplayP3);
+        for (a, b) in p3.chunks_exact(3).zip(round_trip.chunks_exact(3)) {
+            for (&x, &y) in a.iter().zip(b) {
+                assert!((x as i32 - y as i32).abs() <= 1, "{:?} became {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_display_srgb_is_noop() {
+        let mut pixels = vec![255, 0, 0, 10, 200, 90];
+        let original = pixels.clone();
+        convert_for_display(&mut pixels, DisplayColorSpace::Srgb);
+        assert_eq!(pixels, original);
+    }
+
+    #[test]
+    fn test_convert_srgb_is_noop() {
+        let mut pixels = vec![10, 20, 30, 200, 100, 50];
+        let original = pixels.clone();
+        convert_to_srgb(&mut pixels, ColorProfile::Srgb);
+        assert_eq!(pixels, original);
+    }
+}
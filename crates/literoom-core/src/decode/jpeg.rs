@@ -1,15 +1,50 @@
-//! JPEG image decoding with EXIF orientation handling.
+//! JPEG image decoding with EXIF orientation and ICC profile handling.
 
 use std::io::Cursor;
+use std::ops::Range;
 
 use exif::{In, Reader, Tag};
 use image::DynamicImage;
-use image::ImageReader;
+use image::{ImageReader, Limits};
+use serde::{Deserialize, Serialize};
 
-use super::{DecodeError, DecodedImage, Orientation};
+use super::icc::{convert_to_srgb, detect_color_profile, extract_icc_profile, icc_description};
+use super::probe::probe_image;
+use super::variant::{decode_uninverted_cmyk, JpegVariant};
+use super::{DecodeError, DecodedImage, ImageMetadata, Orientation};
+use crate::encode::{crop_jpeg_rows, EncodeError};
+use crate::mask::PixelRect;
+use crate::perf;
+use crate::validate::{validate_dimensions, MAX_IMAGE_DIMENSION, MAX_PIXELS};
+
+/// Signature at the start of an EXIF `APP1` segment.
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+/// Options controlling JPEG decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegDecodeOptions {
+    /// Apply the EXIF orientation to the decoded pixels.
+    pub apply_orientation: bool,
+    /// Convert pixels from a recognized embedded ICC profile to sRGB.
+    ///
+    /// Disable this to get the raw channel values as stored in the file.
+    pub convert_to_srgb: bool,
+}
+
+impl Default for JpegDecodeOptions {
+    fn default() -> Self {
+        Self {
+            apply_orientation: true,
+            convert_to_srgb: true,
+        }
+    }
+}
 
 /// Decode a JPEG image from bytes, applying EXIF orientation correction.
 ///
+/// Pixels tagged with an Adobe RGB or Display P3 ICC profile are converted
+/// to sRGB.
+///
 /// # Arguments
 ///
 /// * `bytes` - Raw JPEG file bytes
@@ -22,11 +57,11 @@ use super::{DecodeError, DecodedImage, Orientation};
 ///
 /// Returns `DecodeError::InvalidFormat` if the bytes are not a valid JPEG.
 /// Returns `DecodeError::CorruptedFile` if the JPEG is corrupted.
+/// Returns `DecodeError::UnsupportedVariant` for 12-bit, arithmetic-coded,
+/// lossless or hierarchical JPEGs. CMYK and YCCK JPEGs are converted to RGB
+/// without a color profile.
 pub fn decode_jpeg(bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
-    let orientation = extract_orientation(bytes);
-    let img = decode_bytes_to_dynamic_image(bytes)?;
-    let oriented_img = apply_orientation(img, orientation);
-    Ok(DecodedImage::from_rgb_image(oriented_img.into_rgb8()))
+    decode_jpeg_with_options(bytes, &JpegDecodeOptions::default()).map(|(image, _)| image)
 }
 
 /// Decode a JPEG image from bytes without applying EXIF orientation.
@@ -42,40 +77,526 @@ pub fn decode_jpeg(bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
 ///
 /// A `DecodedImage` with RGB pixel data (orientation not applied).
 pub fn decode_jpeg_no_orientation(bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
+    let options = JpegDecodeOptions {
+        apply_orientation: false,
+        ..Default::default()
+    };
+    decode_jpeg_with_options(bytes, &options).map(|(image, _)| image)
+}
+
+/// Decode a JPEG image from bytes with explicit options.
+///
+/// # Arguments
+///
+/// * `bytes` - Raw JPEG file bytes
+/// * `options` - Orientation and color conversion options
+///
+/// # Returns
+///
+/// The decoded image and its metadata. Only `width`, `height`,
+/// `orientation` and `color_profile` are filled in; `color_profile` holds
+/// the original ICC profile name, or `None` for untagged files.
+///
+/// # Errors
+///
+/// Returns `DecodeError::CorruptedFile` if the bytes are not a valid JPEG,
+/// or `DecodeError::InvalidImage` if the header declares dimensions beyond
+/// the image size limits; nothing is allocated for the pixels in that case.
+pub fn decode_jpeg_with_options(
+    bytes: &[u8],
+    options: &JpegDecodeOptions,
+) -> Result<(DecodedImage, ImageMetadata), DecodeError> {
+    let _perf = perf::scope("decode_jpeg");
+    let (orientation, invalid_orientation) = extract_orientation(bytes);
     let img = decode_bytes_to_dynamic_image(bytes)?;
-    Ok(DecodedImage::from_rgb_image(img.into_rgb8()))
+
+    let mut metadata = ImageMetadata {
+        width: img.width(),
+        height: img.height(),
+        orientation,
+        invalid_orientation,
+        ..Default::default()
+    };
+
+    let img = if options.apply_orientation {
+        apply_orientation(img, orientation)
+    } else {
+        img
+    };
+    let mut image = DecodedImage::from_rgb_image(img.into_rgb8());
+
+    if let Some(icc) = extract_icc_profile(bytes) {
+        let profile = detect_color_profile(&icc);
+        if let (true, Some(profile)) = (options.convert_to_srgb, profile) {
+            convert_to_srgb(&mut image.pixels, profile);
+        }
+        metadata.color_profile = icc_description(&icc).or(profile.map(|p| p.name().to_string()));
+    }
+
+    perf::record_dimensions(image.width, image.height);
+    perf::record_pixels(image.pixel_count() as u64);
+    perf::record_allocation(image.byte_size() as u64);
+    Ok((image, metadata))
+}
+
+/// Decode only the rows `row_start..row_start + row_count` of a JPEG.
+///
+/// Rows are counted in the image as stored: EXIF orientation is not
+/// applied, so the result matches the same rows of
+/// [`decode_jpeg_no_orientation`]. ICC profiles are converted as in
+/// [`decode_jpeg`].
+///
+/// For sequential JPEGs only the MCU rows covering the range (and one MCU row
+/// either side, which the chroma upsampling reads) are decoded; the rows
+/// above are entropy-decoded to find where the range starts but skip the
+/// IDCT and color conversion, and the rows below are not read at all.
+/// Progressive files cannot be cut this way and are decoded whole.
+///
+/// # Errors
+///
+/// Returns `DecodeError::RegionOutOfBounds` if `row_count` is zero or the
+/// rows reach past the bottom of the image, and the errors of
+/// [`decode_jpeg`] otherwise.
+pub fn decode_jpeg_rows(
+    bytes: &[u8],
+    row_start: u32,
+    row_count: u32,
+) -> Result<DecodedImage, DecodeError> {
+    let _perf = perf::scope("decode_jpeg_rows");
+    let (width, height) = stored_size(bytes)?;
+    let end = row_start.saturating_add(row_count);
+    if row_count == 0 || end > height {
+        return Err(DecodeError::RegionOutOfBounds { width, height });
+    }
+    perf::record_dimensions(width, height);
+    decode_stored_rows(bytes, row_start..end)
+}
+
+/// Decode only the pixels inside `rect` of a JPEG.
+///
+/// `rect` is in stored pixels, like the rows of [`decode_jpeg_rows`], which
+/// decodes the full-width stripe the columns are then cut from.
+///
+/// # Errors
+///
+/// Returns `DecodeError::RegionOutOfBounds` if `rect` is empty or reaches
+/// outside the image, and the errors of [`decode_jpeg`] otherwise.
+pub fn decode_jpeg_region(bytes: &[u8], rect: &PixelRect) -> Result<DecodedImage, DecodeError> {
+    let _perf = perf::scope("decode_jpeg_region");
+    let (width, height) = stored_size(bytes)?;
+    let fits = |start: u32, len: u32, limit: u32| len > 0 && start.saturating_add(len) <= limit;
+    if !fits(rect.x, rect.width, width) || !fits(rect.y, rect.height, height) {
+        return Err(DecodeError::RegionOutOfBounds { width, height });
+    }
+    perf::record_dimensions(width, height);
+
+    let stripe = decode_stored_rows(bytes, rect.y..rect.y + rect.height)?;
+    let columns = rect.x as usize * 3..(rect.x + rect.width) as usize * 3;
+    let pixels = stripe
+        .pixels
+        .chunks_exact(stripe.width as usize * 3)
+        .flat_map(|row| &row[columns.clone()])
+        .copied()
+        .collect();
+    Ok(DecodedImage::new(rect.width, rect.height, pixels))
+}
+
+/// Width and height of the image as stored, from the frame header.
+fn stored_size(bytes: &[u8]) -> Result<(u32, u32), DecodeError> {
+    let probe = probe_image(bytes);
+    probe
+        .width
+        .zip(probe.height)
+        .ok_or_else(|| DecodeError::CorruptedFile("missing frame header".to_string()))
+}
+
+/// Decode the full-width stored rows `rows`, which must be inside the image.
+fn decode_stored_rows(bytes: &[u8], rows: Range<u32>) -> Result<DecodedImage, DecodeError> {
+    let options = JpegDecodeOptions {
+        apply_orientation: false,
+        ..Default::default()
+    };
+    let (stripe, top) = match crop_jpeg_rows(bytes, rows.clone()) {
+        Ok((stripe, top)) => (decode_jpeg_with_options(&stripe, &options)?.0, top),
+        // Progressive and multi-scan files cannot be cut; the decoder also
+        // rejects the other unsupported variants with a specific error
+        Err(EncodeError::UnsupportedJpeg(_)) => (decode_jpeg_with_options(bytes, &options)?.0, 0),
+        Err(e) => return Err(DecodeError::CorruptedFile(e.to_string())),
+    };
+
+    let row_len = stripe.width as usize * 3;
+    let start = (rows.start - top) as usize * row_len;
+    let end = (rows.end - top) as usize * row_len;
+    let pixels = stripe
+        .pixels
+        .get(start..end)
+        .ok_or_else(|| DecodeError::CorruptedFile("fewer rows than the frame header".to_string()))?
+        .to_vec();
+    Ok(DecodedImage::new(
+        stripe.width,
+        rows.end - rows.start,
+        pixels,
+    ))
+}
+
+/// Channel value of the pixels a truncated JPEG has no data for.
+///
+/// The decoder leaves missing blocks at zero, which is mid-gray once the
+/// level shift and YCbCr conversion are applied.
+const TRUNCATION_FILL: u8 = 128;
+
+/// What a tolerant decode had to recover from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeWarnings {
+    /// Rows decoded from the file, counted from the top of the image as
+    /// stored (before orientation correction)
+    pub rows_decoded: u32,
+    /// Rows in the image as stored
+    pub total_rows: u32,
+    /// The file ended before the end-of-image marker
+    pub truncated: bool,
+    /// EXIF orientation value outside 1-8 that was ignored; the image is
+    /// decoded upright
+    pub invalid_orientation: Option<u32>,
+}
+
+/// Decode a JPEG that may be truncated, e.g. from a corrupted card.
+///
+/// When the file ends inside the compressed image data, the rows that were
+/// decoded are kept and the rest of the image is filled with mid-gray, so
+/// the photo still gets a mostly usable thumbnail. The warnings say whether
+/// this happened and how many rows are real. Orientation and color profile
+/// handling are the same as [`decode_jpeg`], which stays strict.
+///
+/// Rows are counted in whole MCU rows (8 or 16 pixels): the block row the
+/// data ended in is only partly decoded, so it is filled too.
+///
+/// # Errors
+///
+/// Returns `DecodeError::CorruptedFile` if the bytes are not a JPEG or end
+/// before the image data starts, since nothing can be recovered then.
+pub fn decode_jpeg_tolerant(bytes: &[u8]) -> Result<(DecodedImage, DecodeWarnings), DecodeError> {
+    let _perf = perf::scope("decode_jpeg_tolerant");
+    let options = JpegDecodeOptions {
+        apply_orientation: false,
+        ..Default::default()
+    };
+    let (mut image, metadata) = decode_jpeg_with_options(bytes, &options)?;
+
+    let layout = JpegLayout::scan(bytes);
+    let mut warnings = DecodeWarnings {
+        rows_decoded: image.height,
+        total_rows: image.height,
+        truncated: !layout.complete,
+        invalid_orientation: metadata.invalid_orientation,
+    };
+    if warnings.truncated {
+        warnings.rows_decoded = decoded_rows(&image, layout.mcu_height);
+        let start = warnings.rows_decoded as usize * image.width as usize * 3;
+        image.pixels[start..].fill(TRUNCATION_FILL);
+    }
+
+    if metadata.orientation != Orientation::Normal {
+        // The buffer comes straight from the decoder, so it matches the size
+        if let Some(rgb) = image.to_rgb_image() {
+            let oriented = apply_orientation(DynamicImage::ImageRgb8(rgb), metadata.orientation);
+            image = DecodedImage::from_rgb_image(oriented.into_rgb8());
+        }
+    }
+    Ok((image, warnings))
+}
+
+/// Structure of a JPEG file, as far as [`decode_jpeg_tolerant`] needs it.
+struct JpegLayout {
+    /// Height of one MCU row in pixels
+    mcu_height: u32,
+    /// Whether the end-of-image marker was found
+    complete: bool,
+}
+
+impl JpegLayout {
+    /// Walk the markers of `bytes`, skipping over compressed scan data.
+    fn scan(bytes: &[u8]) -> Self {
+        let mut walk = JpegSegments::new(bytes);
+        walk.by_ref().for_each(drop);
+        Self {
+            mcu_height: JpegVariant::read(bytes).map_or(8, |variant| variant.mcu_height),
+            complete: walk.end_of_image().is_some(),
+        }
+    }
+}
+
+/// Number of intact rows above the fill a truncated decode ends with.
+///
+/// When the data runs out, the decoder finishes the MCU row it was in from
+/// zero padding, so the last MCU row before the fill is unreliable and not
+/// counted. With vertically subsampled chroma the upsampler also blends that
+/// row into the one above, so it is dropped as well.
+fn decoded_rows(image: &DecodedImage, mcu_height: u32) -> u32 {
+    let row_len = image.width as usize * 3;
+    let filled = image
+        .pixels
+        .chunks_exact(row_len)
+        .rev()
+        .take_while(|row| row.iter().all(|&v| v == TRUNCATION_FILL))
+        .count() as u32;
+    let Some(last) = (image.height - filled).checked_sub(1) else {
+        return 0;
+    };
+    let complete = last / mcu_height * mcu_height;
+    if mcu_height > 8 {
+        complete.saturating_sub(mcu_height)
+    } else {
+        complete
+    }
 }
 
 /// Decode raw bytes into a DynamicImage.
+///
+/// The dimensions in the header are checked before the decoder allocates
+/// anything, and the decoder's own limits back that up for headers the
+/// probe cannot read. Unsupported coding variants are rejected from the
+/// frame header, and CMYK without Adobe's inversion takes its own path.
 fn decode_bytes_to_dynamic_image(bytes: &[u8]) -> Result<DynamicImage, DecodeError> {
+    let probe = probe_image(bytes);
+    // A zero height is filled in later by a DNL marker; leave that to the decoder
+    if let (Some(width @ 1..), Some(height @ 1..)) = (probe.width, probe.height) {
+        validate_dimensions(width, height)?;
+    }
+    if let Some(variant) = JpegVariant::read(bytes) {
+        variant.check_supported()?;
+        if variant.is_uninverted_cmyk() {
+            return decode_uninverted_cmyk(bytes);
+        }
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    // RGBA output at the pixel limit
+    limits.max_alloc = Some(MAX_PIXELS * 4);
+
     let cursor = Cursor::new(bytes);
-    let reader = ImageReader::new(cursor)
+    let mut reader = ImageReader::new(cursor)
         .with_guessed_format()
         .map_err(|e| DecodeError::CorruptedFile(e.to_string()))?;
+    reader.limits(limits);
     reader
         .decode()
         .map_err(|e| DecodeError::CorruptedFile(e.to_string()))
 }
 
-/// Extract EXIF orientation from JPEG bytes.
+/// A marker segment of a JPEG stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct JpegSegment<'a> {
+    /// Marker code, the byte after 0xFF
+    pub(crate) marker: u8,
+    /// Offset of the segment's 0xFF marker byte
+    pub(crate) start: usize,
+    /// Offset just past the segment
+    pub(crate) end: usize,
+    /// Segment body after the length field
+    pub(crate) payload: &'a [u8],
+}
+
+/// SOF0..SOF15, excluding DHT (C4), JPG (C8) and DAC (CC).
+pub(crate) fn is_start_of_frame(marker: u8) -> bool {
+    (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC)
+}
+
+/// Walk over the marker segments of a JPEG stream, in file order.
+///
+/// Fill bytes and the markers without a length (TEM and RST0..RST7) are
+/// skipped, and so is the entropy-coded data after each SOS, so every scan
+/// header of a progressive file is returned. The walk ends at EOI, at the
+/// end of the data, and at the first malformed segment: a second SOI, a
+/// byte where a marker should be, or a length below 2. A segment cut off by
+/// the end of the data is not returned but kept as [`truncated`].
 ///
-/// Returns `Orientation::Normal` if no EXIF data is found or orientation
-/// cannot be determined.
-fn extract_orientation(bytes: &[u8]) -> Orientation {
-    let exif_reader = Reader::new();
-    let mut cursor = Cursor::new(bytes);
-
-    match exif_reader.read_from_container(&mut cursor) {
-        Ok(exif) => {
-            if let Some(field) = exif.get_field(Tag::Orientation, In::PRIMARY) {
-                if let Some(value) = field.value.get_uint(0) {
-                    return Orientation::from(value);
+/// Yields nothing unless `bytes` starts with SOI.
+///
+/// [`truncated`]: Self::truncated
+pub(crate) struct JpegSegments<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Whether `pos` is in the entropy-coded data after an SOS
+    in_scan: bool,
+    done: bool,
+    end_of_image: Option<usize>,
+    truncated: Option<JpegSegment<'a>>,
+}
+
+impl<'a> JpegSegments<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 2,
+            in_scan: false,
+            done: !bytes.starts_with(&[0xFF, 0xD8]),
+            end_of_image: None,
+            truncated: None,
+        }
+    }
+
+    /// Offset just past the EOI marker, once the walk has reached it.
+    pub(crate) fn end_of_image(&self) -> Option<usize> {
+        self.end_of_image
+    }
+
+    /// The segment the walk stopped at because the data ends inside it,
+    /// with `end` and `payload` cut at the end of the data.
+    pub(crate) fn truncated(&self) -> Option<JpegSegment<'a>> {
+        self.truncated
+    }
+
+    /// Offset the walk has reached.
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+impl<'a> Iterator for JpegSegments<'a> {
+    type Item = JpegSegment<'a>;
+
+    fn next(&mut self) -> Option<JpegSegment<'a>> {
+        let bytes = self.bytes;
+        while !self.done {
+            if self.in_scan {
+                // Scan data runs until a marker other than a stuffed zero
+                // or a restart marker
+                let Some(offset) = bytes[self.pos..].iter().position(|&b| b == 0xFF) else {
+                    self.pos = bytes.len();
+                    self.done = true;
+                    break;
+                };
+                self.pos += offset;
+                match bytes.get(self.pos + 1) {
+                    Some(0x00 | 0xD0..=0xD7) => self.pos += 2,
+                    Some(_) => self.in_scan = false,
+                    None => self.done = true,
                 }
+                continue;
+            }
+
+            let start = self.pos;
+            let Some(&[0xFF, marker]) = bytes.get(start..start + 2) else {
+                self.done = true;
+                break;
+            };
+            match marker {
+                // Fill byte before a marker
+                0xFF => {
+                    self.pos += 1;
+                    continue;
+                }
+                0x01 | 0xD0..=0xD7 => {
+                    self.pos += 2;
+                    continue;
+                }
+                0xD9 => {
+                    self.pos += 2;
+                    self.end_of_image = Some(self.pos);
+                    self.done = true;
+                    break;
+                }
+                // A second SOI, or a stuffed zero outside scan data
+                0x00 | 0xD8 => {
+                    self.done = true;
+                    break;
+                }
+                _ => {}
+            }
+
+            let Some(&[high, low]) = bytes.get(start + 2..start + 4) else {
+                self.done = true;
+                break;
+            };
+            let length = u16::from_be_bytes([high, low]) as usize;
+            if length < 2 {
+                self.done = true;
+                break;
+            }
+            let end = start + 2 + length;
+            if end > bytes.len() {
+                self.truncated = Some(JpegSegment {
+                    marker,
+                    start,
+                    end: bytes.len(),
+                    payload: &bytes[start + 4..],
+                });
+                self.done = true;
+                break;
+            }
+            self.pos = end;
+            self.in_scan = marker == 0xDA;
+            return Some(JpegSegment {
+                marker,
+                start,
+                end,
+                payload: &bytes[start + 4..end],
+            });
+        }
+        None
+    }
+}
+
+/// Marker and payload of each segment between SOI and the first SOS.
+///
+/// Stops quietly at the first malformed segment.
+pub(super) fn app_segments(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    JpegSegments::new(bytes)
+        .take_while(|segment| segment.marker != 0xDA)
+        .map(|segment| (segment.marker, segment.payload))
+        .collect()
+}
+
+/// Like [`app_segments`], for when `bytes` may be only the start of a
+/// file: a last segment cut off by the end of `bytes` is returned as far as
+/// it goes.
+pub(super) fn app_segments_partial(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut walk = JpegSegments::new(bytes);
+    let mut segments: Vec<_> = walk
+        .by_ref()
+        .take_while(|segment| segment.marker != 0xDA)
+        .map(|segment| (segment.marker, segment.payload))
+        .collect();
+    if let Some(segment) = walk.truncated() {
+        segments.push((segment.marker, segment.payload));
+    }
+    segments
+}
+
+/// Extract EXIF orientation from JPEG bytes.
+///
+/// Every EXIF `APP1` segment is tried in file order and the first with a
+/// valid orientation (1-8) is used, since some phones write a second,
+/// conflicting segment. Returns `Orientation::Normal` if no EXIF data is
+/// found or orientation cannot be determined, together with the raw value
+/// if the only orientation found was outside 1-8.
+fn extract_orientation(bytes: &[u8]) -> (Orientation, Option<u32>) {
+    let mut invalid = None;
+    for (marker, payload) in app_segments(bytes) {
+        let tiff = match payload.strip_prefix(EXIF_HEADER) {
+            Some(tiff) if marker == 0xE1 => tiff,
+            _ => continue,
+        };
+        let Ok(exif) = Reader::new().read_raw(tiff.to_vec()) else {
+            continue;
+        };
+        let Some(value) = exif
+            .get_field(Tag::Orientation, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+        else {
+            continue;
+        };
+        match Orientation::from_exif(value) {
+            Some(orientation) => return (orientation, None),
+            None => {
+                invalid.get_or_insert(value);
             }
-            Orientation::Normal
         }
-        Err(_) => Orientation::Normal,
     }
+    (Orientation::Normal, invalid)
 }
 
 /// Apply EXIF orientation transformation to an image.
@@ -93,8 +614,10 @@ fn apply_orientation(img: DynamicImage, orientation: Orientation) -> DynamicImag
 }
 
 /// Extract EXIF orientation value from JPEG bytes (for external use).
+///
+/// Values outside 1-8 give `Orientation::Normal`.
 pub fn get_orientation(bytes: &[u8]) -> Orientation {
-    extract_orientation(bytes)
+    extract_orientation(bytes).0
 }
 
 #[cfg(test)]
@@ -129,6 +652,28 @@ mod tests {
         0xDB, 0x20, 0xA8, 0xF1, 0x7E, 0xFF, 0xD9,
     ];
 
+    /// MINIMAL_JPEG with the frame header declaring `width x height`.
+    fn with_declared_size(width: u16, height: u16) -> Vec<u8> {
+        let mut bytes = MINIMAL_JPEG.to_vec();
+        let sof = bytes.windows(2).position(|m| m == [0xFF, 0xC0]).unwrap();
+        bytes[sof + 5..sof + 7].copy_from_slice(&height.to_be_bytes());
+        bytes[sof + 7..sof + 9].copy_from_slice(&width.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_oversized_header_rejected_before_decode() {
+        use crate::validate::ImageError;
+
+        for (width, height) in [(65_535, 65_535), (65_535, 8_192)] {
+            let err = decode_jpeg(&with_declared_size(width, height)).unwrap_err();
+            assert!(matches!(
+                err,
+                DecodeError::InvalidImage(ImageError::DimensionOverflow { .. })
+            ));
+        }
+    }
+
     #[test]
     fn test_decode_valid_jpeg() {
         let result = decode_jpeg(MINIMAL_JPEG);
@@ -140,6 +685,16 @@ mod tests {
         assert_eq!(img.pixels.len(), 3); // 1x1 RGB = 3 bytes
     }
 
+    #[test]
+    fn test_decode_jpeg_reports_counters() {
+        decode_jpeg(MINIMAL_JPEG).unwrap();
+
+        let stats = crate::perf::last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "decode_jpeg");
+        assert_eq!(stats.pixels_processed, 1);
+        assert_eq!(stats.bytes_allocated, 3);
+    }
+
     #[test]
     fn test_decode_jpeg_no_orientation() {
         let result = decode_jpeg_no_orientation(MINIMAL_JPEG);
@@ -164,6 +719,74 @@ mod tests {
         }
     }
 
+    /// Encode a solid-color JPEG, optionally tagged with an ICC profile.
+    fn solid_jpeg(color: [u8; 3], description: Option<&str>) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(8, 8, image::Rgb(color));
+        let mut bytes = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, 100)
+            .encode_image(&img)
+            .unwrap();
+        match description {
+            Some(desc) => {
+                let icc = super::super::icc::build_test_profile(Some(desc), None);
+                super::super::icc::embed_test_profile(&bytes, &icc, 64)
+            }
+            None => bytes,
+        }
+    }
+
+    #[test]
+    fn test_adobe_rgb_jpeg_converted_to_srgb() {
+        let jpeg = solid_jpeg([60, 200, 60], Some("Adobe RGB (1998)"));
+        let raw_options = JpegDecodeOptions {
+            convert_to_srgb: false,
+            ..Default::default()
+        };
+
+        let (converted, metadata) =
+            decode_jpeg_with_options(&jpeg, &JpegDecodeOptions::default()).unwrap();
+        let (raw, raw_metadata) = decode_jpeg_with_options(&jpeg, &raw_options).unwrap();
+
+        assert_eq!(metadata.color_profile.as_deref(), Some("Adobe RGB (1998)"));
+        assert_eq!(raw_metadata.color_profile, metadata.color_profile);
+        // The raw path keeps the stored values; the converted green is purer
+        assert!((raw.pixels[0] as i32 - 60).abs() <= 3);
+        assert!(
+            (raw.pixels[0] as i32 - converted.pixels[0] as i32) > 30,
+            "raw {:?} converted {:?}",
+            &raw.pixels[0..3],
+            &converted.pixels[0..3]
+        );
+        assert!(converted.pixels[1] >= raw.pixels[1] - 3);
+    }
+
+    #[test]
+    fn test_untagged_jpeg_unchanged() {
+        let jpeg = solid_jpeg([60, 200, 60], None);
+        let raw_options = JpegDecodeOptions {
+            convert_to_srgb: false,
+            ..Default::default()
+        };
+
+        let (converted, metadata) =
+            decode_jpeg_with_options(&jpeg, &JpegDecodeOptions::default()).unwrap();
+        let (raw, _) = decode_jpeg_with_options(&jpeg, &raw_options).unwrap();
+
+        assert_eq!(metadata.color_profile, None);
+        assert_eq!(converted.pixels, raw.pixels);
+    }
+
+    #[test]
+    fn test_srgb_tagged_jpeg_unchanged() {
+        let tagged = solid_jpeg([60, 200, 60], Some("sRGB IEC61966-2.1"));
+        let untagged = solid_jpeg([60, 200, 60], None);
+
+        let (image, metadata) =
+            decode_jpeg_with_options(&tagged, &JpegDecodeOptions::default()).unwrap();
+        assert_eq!(metadata.color_profile.as_deref(), Some("sRGB IEC61966-2.1"));
+        assert_eq!(image.pixels, decode_jpeg(&untagged).unwrap().pixels);
+    }
+
     #[test]
     fn test_decode_empty_bytes() {
         let result = decode_jpeg(&[]);
@@ -178,6 +801,270 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// Baseline JPEG of a noise image.
+    fn noise_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let source = crate::testing::noise(width, height, 7);
+        let img = source.to_rgb_image().unwrap();
+        let mut bytes = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, 90)
+            .encode_image(&img)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_tolerant_decode_recovers_truncated_file() {
+        let bytes = noise_jpeg(96, 80);
+        let full = decode_jpeg(&bytes).unwrap();
+        let truncated = &bytes[..bytes.len() * 6 / 10];
+
+        let (image, warnings) = decode_jpeg_tolerant(truncated).unwrap();
+        assert!(warnings.truncated);
+        assert_eq!((image.width, image.height), (96, 80));
+        assert_eq!(warnings.total_rows, 80);
+        assert!(
+            (32..=48).contains(&warnings.rows_decoded),
+            "rows_decoded {}",
+            warnings.rows_decoded
+        );
+
+        // Decoded rows match the intact file; the rest is mid-gray
+        let split = (warnings.rows_decoded * 96 * 3) as usize;
+        assert_eq!(image.pixels[..split], full.pixels[..split]);
+        assert!(image.pixels[split..].iter().all(|&v| v == TRUNCATION_FILL));
+    }
+
+    #[test]
+    fn test_tolerant_decode_rows_never_include_garbage() {
+        let bytes = noise_jpeg(64, 64);
+        let full = decode_jpeg(&bytes).unwrap();
+        let mut previous = 0;
+        for percent in (20..100).step_by(10) {
+            let truncated = &bytes[..bytes.len() * percent / 100];
+            let (image, warnings) = decode_jpeg_tolerant(truncated).unwrap();
+            let split = (warnings.rows_decoded * 64 * 3) as usize;
+            assert_eq!(image.pixels[..split], full.pixels[..split], "{}%", percent);
+            assert!(warnings.rows_decoded >= previous);
+            previous = warnings.rows_decoded;
+        }
+        assert!(previous > 0);
+    }
+
+    #[test]
+    fn test_tolerant_decode_of_intact_file_matches_strict() {
+        let bytes = noise_jpeg(40, 24);
+        let (image, warnings) = decode_jpeg_tolerant(&bytes).unwrap();
+        assert_eq!(
+            warnings,
+            DecodeWarnings {
+                rows_decoded: 24,
+                total_rows: 24,
+                truncated: false,
+                invalid_orientation: None,
+            }
+        );
+        assert_eq!(image.pixels, decode_jpeg(&bytes).unwrap().pixels);
+
+        // Nothing is recoverable without the headers
+        assert!(decode_jpeg_tolerant(&bytes[..20]).is_err());
+    }
+
+    /// `jpeg` with one EXIF APP1 segment per orientation value, in order.
+    fn with_orientations(jpeg: &[u8], values: &[u16]) -> Vec<u8> {
+        let mut out = jpeg[..2].to_vec();
+        for &value in values {
+            let mut app1 = EXIF_HEADER.to_vec();
+            app1.extend_from_slice(b"II\x2A\x00\x08\x00\x00\x00\x01\x00");
+            // Orientation, SHORT, count 1
+            app1.extend_from_slice(&[0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00]);
+            app1.extend_from_slice(&value.to_le_bytes());
+            app1.extend_from_slice(&[0; 6]);
+            out.extend_from_slice(&[0xFF, 0xE1]);
+            out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+            out.extend(app1);
+        }
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    #[test]
+    fn test_invalid_orientation_decodes_upright_with_warning() {
+        let plain = noise_jpeg(24, 16);
+        let upright = decode_jpeg(&plain).unwrap();
+        for value in [0, 9, 65_535] {
+            let bytes = with_orientations(&plain, &[value]);
+            let (image, metadata) =
+                decode_jpeg_with_options(&bytes, &JpegDecodeOptions::default()).unwrap();
+            assert_eq!((image.width, image.height), (24, 16));
+            assert_eq!(image.pixels, upright.pixels);
+            assert_eq!(metadata.orientation, Orientation::Normal);
+            assert_eq!(metadata.invalid_orientation, Some(value as u32));
+
+            let (image, warnings) = decode_jpeg_tolerant(&bytes).unwrap();
+            assert_eq!(image.pixels, upright.pixels);
+            assert_eq!(warnings.invalid_orientation, Some(value as u32));
+        }
+    }
+
+    #[test]
+    fn test_first_valid_exif_segment_wins() {
+        let plain = noise_jpeg(24, 16);
+        for values in [[6, 3], [0, 6]] {
+            let bytes = with_orientations(&plain, &values);
+            let (image, metadata) =
+                decode_jpeg_with_options(&bytes, &JpegDecodeOptions::default()).unwrap();
+            assert_eq!(metadata.orientation, Orientation::Rotate90CW);
+            assert_eq!(metadata.invalid_orientation, None);
+            assert_eq!((image.width, image.height), (16, 24));
+        }
+    }
+
+    #[test]
+    fn test_valid_orientation_has_no_warning() {
+        let plain = noise_jpeg(24, 16);
+        for bytes in [
+            plain.clone(),
+            with_orientations(&plain, &[1]),
+            with_orientations(&plain, &[8]),
+        ] {
+            let (_, metadata) =
+                decode_jpeg_with_options(&bytes, &JpegDecodeOptions::default()).unwrap();
+            assert_eq!(metadata.invalid_orientation, None);
+            assert_eq!(
+                decode_jpeg_tolerant(&bytes).unwrap().1.invalid_orientation,
+                None
+            );
+        }
+    }
+
+    /// Rows `rows` of `image`, cut from columns `columns`.
+    fn crop(image: &DecodedImage, columns: Range<u32>, rows: Range<u32>) -> Vec<u8> {
+        let row_len = image.width as usize * 3;
+        image
+            .pixels
+            .chunks_exact(row_len)
+            .skip(rows.start as usize)
+            .take(rows.len())
+            .flat_map(|row| &row[columns.start as usize * 3..columns.end as usize * 3])
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_rows_match_full_decode() {
+        // 4:2:0, so MCU rows are 16 pixels and chroma is upsampled across them
+        let bytes = noise_jpeg(72, 100);
+        let full = decode_jpeg_no_orientation(&bytes).unwrap();
+        for (start, count) in [
+            (0, 1),
+            (3, 7),
+            (15, 2),
+            (16, 16),
+            (37, 40),
+            (90, 10),
+            (0, 100),
+        ] {
+            let rows = decode_jpeg_rows(&bytes, start, count).unwrap();
+            assert_eq!((rows.width, rows.height), (72, count));
+            assert_eq!(
+                rows.pixels,
+                crop(&full, 0..72, start..start + count),
+                "rows {start}..{}",
+                start + count
+            );
+        }
+    }
+
+    #[test]
+    fn test_region_matches_crop_of_full_decode() {
+        let bytes = noise_jpeg(90, 70);
+        let full = decode_jpeg_no_orientation(&bytes).unwrap();
+        for (x, y, width, height) in [
+            (0, 0, 90, 70),
+            (5, 9, 20, 13),
+            (33, 48, 57, 22),
+            (89, 69, 1, 1),
+        ] {
+            let rect = PixelRect {
+                x,
+                y,
+                width,
+                height,
+            };
+            let region = decode_jpeg_region(&bytes, &rect).unwrap();
+            assert_eq!((region.width, region.height), (width, height));
+            assert_eq!(region.pixels, crop(&full, x..x + width, y..y + height));
+        }
+    }
+
+    #[test]
+    fn test_region_is_in_stored_pixels() {
+        let bytes = with_orientations(&noise_jpeg(40, 24), &[6]);
+        let stored = decode_jpeg_no_orientation(&bytes).unwrap();
+        let rows = decode_jpeg_rows(&bytes, 10, 4).unwrap();
+        assert_eq!(rows.width, 40);
+        assert_eq!(rows.pixels, crop(&stored, 0..40, 10..14));
+    }
+
+    #[test]
+    fn test_out_of_range_rows_error() {
+        let bytes = noise_jpeg(32, 20);
+        let out_of_bounds = DecodeError::RegionOutOfBounds {
+            width: 32,
+            height: 20,
+        };
+        for (start, count) in [(15, 6), (20, 1), (0, 0), (1, u32::MAX)] {
+            assert_eq!(
+                decode_jpeg_rows(&bytes, start, count)
+                    .unwrap_err()
+                    .to_string(),
+                out_of_bounds.to_string()
+            );
+        }
+        let rect = PixelRect {
+            x: 30,
+            y: 0,
+            width: 3,
+            height: 1,
+        };
+        assert!(matches!(
+            decode_jpeg_region(&bytes, &rect),
+            Err(DecodeError::RegionOutOfBounds { .. })
+        ));
+        assert!(decode_jpeg_rows(b"not a jpeg", 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_segments_skip_scan_data_to_eoi() {
+        let bytes = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xFF, 0xE0, 0x00, 0x03, 0x01, // APP0 after a fill byte
+            0xFF, 0xDA, 0x00, 0x02, // SOS
+            0x12, 0xFF, 0x00, 0xFF, 0xD0, 0x34, // stuffed zero and RST0
+            0xFF, 0xC4, 0x00, 0x02, // DHT between scans
+            0xFF, 0xD9, 0xAA, // EOI and trailing data
+        ];
+        let mut walk = JpegSegments::new(&bytes);
+        let markers: Vec<_> = walk.by_ref().map(|s| (s.marker, s.start, s.end)).collect();
+        assert_eq!(markers, [(0xE0, 3, 8), (0xDA, 8, 12), (0xC4, 18, 22)]);
+        assert_eq!(walk.end_of_image(), Some(24));
+        assert_eq!(walk.truncated(), None);
+    }
+
+    #[test]
+    fn test_segments_stop_at_malformed_or_cut_off_segment() {
+        let cut_off = [0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x10, b'E', b'x'];
+        let mut walk = JpegSegments::new(&cut_off);
+        assert_eq!(walk.next(), None);
+        let truncated = walk.truncated().unwrap();
+        assert_eq!((truncated.marker, truncated.payload), (0xE1, &b"Ex"[..]));
+
+        // A second SOI ends the walk
+        let nested = [0xFF, 0xD8, 0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x02];
+        assert_eq!(JpegSegments::new(&nested).count(), 0);
+        assert_eq!(JpegSegments::new(b"not a jpeg").count(), 0);
+    }
+
     #[test]
     fn test_orientation_extraction_no_exif() {
         // The minimal JPEG has no EXIF data
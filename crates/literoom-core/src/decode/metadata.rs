@@ -0,0 +1,286 @@
+//! Camera, exposure and capture-time details from EXIF.
+
+use std::io::Cursor;
+
+use exif::{In, Reader, Tag, Value};
+
+use super::types::ImageMetadata;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// Fill the camera, exposure and capture-time fields of `metadata` from the
+/// file's EXIF.
+///
+/// Leaves the fields untouched when the file has no readable EXIF.
+pub(super) fn read_exif_metadata(bytes: &[u8], metadata: &mut ImageMetadata) {
+    let Ok(exif) = Reader::new().read_from_container(&mut Cursor::new(bytes)) else {
+        return;
+    };
+    let field = |tag| exif.get_field(tag, In::PRIMARY).map(|f| &f.value);
+    let text = |tag| match field(tag)? {
+        Value::Ascii(parts) => {
+            let text = String::from_utf8_lossy(parts.first()?);
+            let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+            (!text.is_empty()).then(|| text.to_string())
+        }
+        _ => None,
+    };
+    let rational = |tag| match field(tag)? {
+        Value::Rational(values) => values.first().filter(|r| r.denom != 0).copied(),
+        _ => None,
+    };
+
+    metadata.camera_make = text(Tag::Make);
+    metadata.camera_model = text(Tag::Model);
+
+    // Each timestamp has its own subsecond tag; never mix the two
+    let capture = text(Tag::DateTimeOriginal)
+        .map(|date| (date, text(Tag::SubSecTimeOriginal)))
+        .or_else(|| text(Tag::DateTime).map(|date| (date, text(Tag::SubSecTime))));
+    metadata.date_taken = capture.as_ref().map(|(date, _)| exif_date_to_iso(date));
+    metadata.capture_timestamp_ms = capture
+        .as_ref()
+        .and_then(|(date, subsec)| capture_timestamp_ms(date, subsec.as_deref()));
+
+    metadata.iso = field(Tag::PhotographicSensitivity).and_then(|v| v.get_uint(0));
+    metadata.shutter_speed = rational(Tag::ExposureTime).map(|t| {
+        if t.num == 0 || t.num >= t.denom {
+            format!("{}", (t.to_f64() * 10.0).round() / 10.0)
+        } else {
+            format!("1/{}", (t.denom as f64 / t.num as f64).round())
+        }
+    });
+    metadata.aperture = rational(Tag::FNumber).map(|r| r.to_f64() as f32);
+    metadata.focal_length = rational(Tag::FocalLength).map(|r| r.to_f64() as f32);
+}
+
+/// Convert an EXIF `YYYY:MM:DD HH:MM:SS` timestamp to ISO 8601.
+///
+/// Anything else is returned unchanged.
+pub(super) fn exif_date_to_iso(date: &str) -> String {
+    let bytes = date.as_bytes();
+    if bytes.len() == 19 && bytes[4] == b':' && bytes[7] == b':' && bytes[10] == b' ' {
+        format!(
+            "{}-{}-{}T{}",
+            &date[..4],
+            &date[5..7],
+            &date[8..10],
+            &date[11..]
+        )
+    } else {
+        date.to_string()
+    }
+}
+
+/// Milliseconds since the Unix epoch for an EXIF timestamp.
+///
+/// `date` is `YYYY:MM:DD HH:MM:SS` and `subsec` the matching `SubSecTime*`
+/// digits, a decimal fraction of the second (`"5"` is 500ms, `"05"` 50ms);
+/// without it the milliseconds are zero. EXIF times are the camera's wall
+/// clock with no time zone, so they are converted as if they were UTC:
+/// shots from one camera compare correctly, but the value is not the true
+/// instant unless the camera clock was set to UTC.
+///
+/// Returns `None` for malformed or unset (`0000:00:00 ...`) dates.
+fn capture_timestamp_ms(date: &str, subsec: Option<&str>) -> Option<i64> {
+    let bytes = date.as_bytes();
+    if bytes.len() < 19 || bytes[4] != b':' || bytes[7] != b':' || bytes[10] != b' ' {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| date.get(range)?.parse::<u32>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    let valid = (1..=12).contains(&month)
+        && (1..=days_in_month(year, month)).contains(&day)
+        && hour < 24
+        && minute < 60
+        && second <= 60;
+    if !valid {
+        return None;
+    }
+
+    let millis = subsec.map_or(0, |digits| {
+        digits
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .chain(std::iter::repeat(b'0'))
+            .take(3)
+            .fold(0, |ms, digit| ms * 10 + (digit - b'0') as i64)
+    });
+    let seconds = (hour * 3600 + minute * 60 + second) as i64;
+    Some(days_from_civil(year as i64, month, day) * MS_PER_DAY + seconds * 1000 + millis)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to a proleptic Gregorian date (Howard Hinnant's
+/// `days_from_civil`).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG_DATE_TIME: u16 = 0x0132;
+    const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+    const TAG_SUB_SEC_TIME: u16 = 0x9290;
+    const TAG_SUB_SEC_TIME_ORIGINAL: u16 = 0x9291;
+
+    /// Wrap ASCII entries in a minimal little-endian TIFF inside a JPEG APP1.
+    ///
+    /// `ifd0` entries go in IFD0, `exif` entries in the Exif sub-IFD.
+    fn jpeg_with_ascii(ifd0: &[(u16, &str)], exif: &[(u16, &str)]) -> Vec<u8> {
+        let ifd_size = |entries: usize| 2 + entries * 12 + 4;
+        let exif_offset = 8 + ifd_size(ifd0.len() + 1);
+        let mut data_offset = exif_offset + ifd_size(exif.len());
+        let mut data = Vec::new();
+
+        let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        let mut write_ifd = |tiff: &mut Vec<u8>, entries: &[(u16, &str)], pointer: Option<u32>| {
+            let count = entries.len() + pointer.is_some() as usize;
+            tiff.extend_from_slice(&(count as u16).to_le_bytes());
+            for &(tag, value) in entries {
+                let mut bytes = value.as_bytes().to_vec();
+                bytes.push(0);
+                tiff.extend_from_slice(&tag.to_le_bytes());
+                tiff.extend_from_slice(&2u16.to_le_bytes());
+                tiff.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                if bytes.len() <= 4 {
+                    bytes.resize(4, 0);
+                    tiff.extend_from_slice(&bytes);
+                } else {
+                    tiff.extend_from_slice(&(data_offset as u32).to_le_bytes());
+                    data_offset += bytes.len();
+                    data.extend(bytes);
+                }
+            }
+            if let Some(pointer) = pointer {
+                tiff.extend_from_slice(&0x8769u16.to_le_bytes());
+                tiff.extend_from_slice(&4u16.to_le_bytes());
+                tiff.extend_from_slice(&1u32.to_le_bytes());
+                tiff.extend_from_slice(&pointer.to_le_bytes());
+            }
+            tiff.extend_from_slice(&0u32.to_le_bytes());
+        };
+        write_ifd(&mut tiff, ifd0, Some(exif_offset as u32));
+        write_ifd(&mut tiff, exif, None);
+        tiff.extend(data);
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(app1);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        jpeg
+    }
+
+    fn read(bytes: &[u8]) -> ImageMetadata {
+        let mut metadata = ImageMetadata::default();
+        read_exif_metadata(bytes, &mut metadata);
+        metadata
+    }
+
+    /// 2024-06-01T14:30:05 as if UTC.
+    const JUNE_FIRST_MS: i64 = 1_717_252_205_000;
+
+    #[test]
+    fn test_capture_timestamp_with_subseconds() {
+        let jpeg = jpeg_with_ascii(
+            &[],
+            &[
+                (TAG_DATE_TIME_ORIGINAL, "2024:06:01 14:30:05"),
+                (TAG_SUB_SEC_TIME_ORIGINAL, "37"),
+            ],
+        );
+        let metadata = read(&jpeg);
+        assert_eq!(metadata.date_taken.as_deref(), Some("2024-06-01T14:30:05"));
+        assert_eq!(metadata.capture_timestamp_ms, Some(JUNE_FIRST_MS + 370));
+    }
+
+    #[test]
+    fn test_capture_timestamp_without_subseconds() {
+        let jpeg = jpeg_with_ascii(&[], &[(TAG_DATE_TIME_ORIGINAL, "2024:06:01 14:30:05")]);
+        assert_eq!(read(&jpeg).capture_timestamp_ms, Some(JUNE_FIRST_MS));
+    }
+
+    #[test]
+    fn test_capture_timestamp_falls_back_to_date_time_pair() {
+        // SubSecTimeOriginal belongs to DateTimeOriginal, so it is ignored
+        let jpeg = jpeg_with_ascii(
+            &[(TAG_DATE_TIME, "2024:06:01 14:30:05")],
+            &[
+                (TAG_SUB_SEC_TIME, "250"),
+                (TAG_SUB_SEC_TIME_ORIGINAL, "999"),
+            ],
+        );
+        assert_eq!(read(&jpeg).capture_timestamp_ms, Some(JUNE_FIRST_MS + 250));
+    }
+
+    #[test]
+    fn test_capture_timestamp_is_naive() {
+        // The same wall-clock time always gives the same value
+        let a = capture_timestamp_ms("1970:01:01 00:00:00", None);
+        let b = capture_timestamp_ms("1970:01:01 00:00:01", Some("5"));
+        assert_eq!(a, Some(0));
+        assert_eq!(b, Some(1500));
+        assert_eq!(
+            capture_timestamp_ms("2000:02:29 23:59:59", Some("0123")),
+            Some(951_868_799_012)
+        );
+        assert_eq!(
+            capture_timestamp_ms("1969:12:31 23:59:59", None),
+            Some(-1000)
+        );
+    }
+
+    #[test]
+    fn test_capture_timestamp_rejects_unset_and_malformed_dates() {
+        for date in [
+            "0000:00:00 00:00:00",
+            "2023:02:29 12:00:00",
+            "2024:13:01 12:00:00",
+            "2024-06-01 14:30:05",
+            "2024:06:01",
+            "    :  :     :  :  ",
+        ] {
+            assert_eq!(capture_timestamp_ms(date, None), None, "{}", date);
+        }
+        let jpeg = jpeg_with_ascii(&[], &[(TAG_DATE_TIME_ORIGINAL, "0000:00:00 00:00:00")]);
+        let metadata = read(&jpeg);
+        assert_eq!(metadata.capture_timestamp_ms, None);
+        assert!(metadata.date_taken.is_some());
+    }
+
+    #[test]
+    fn test_no_exif_leaves_metadata_alone() {
+        let metadata = read(&[0xFF, 0xD8, 0xFF, 0xD9]);
+        assert_eq!(metadata.capture_timestamp_ms, None);
+        assert_eq!(metadata.date_taken, None);
+    }
+
+    #[test]
+    fn test_exif_date_to_iso() {
+        assert_eq!(
+            exif_date_to_iso("2024:06:01 14:30:05"),
+            "2024-06-01T14:30:05"
+        );
+        assert_eq!(exif_date_to_iso("June 2024"), "June 2024");
+    }
+}
@@ -1,10 +1,22 @@
 //! Image decoding pipeline for Literoom.
 //!
 //! This module provides functionality for:
-//! - Decoding JPEG images
+//! - Decoding JPEG images, converting embedded ICC color profiles to sRGB
+//! - Decoding only the rows or region of a JPEG a zoomed-in view needs
+//! - Detecting and applying Ultra HDR gain maps
 //! - Extracting embedded thumbnails from RAW files (fast path)
+//! - Extracting embedded JPEG previews from HEIC/HEIF containers
 //! - Full RAW decoding with demosaicing (quality path)
-//! - Image resizing for thumbnails and previews
+//! - Image resizing for thumbnails and previews, with a canonical downscale both
+//!   can share so they match
+//! - Float (`f32`) images for HDR data, with 8-bit conversion and tone mapping
+//! - Single-channel images for masks and luminance maps
+//! - Header-only probing of type, dimensions and orientation
+//! - Classifying dropped files by their leading bytes, so PDFs, videos and
+//!   other non-images are refused before any decoder runs
+//! - Opening a file in one call (decode, preview, thumbnail, histogram, metadata)
+//! - Decoding any supported format through a fallback chain that reports
+//!   which decoder succeeded
 //!
 //! # Architecture
 //!
@@ -27,14 +39,66 @@
 //! println!("Decoded {}x{} image", image.width, image.height);
 //! ```
 
+mod auto;
+mod bytes;
+mod classify;
+mod gain_map;
+mod heif;
+mod icc;
 mod jpeg;
+mod metadata;
+mod open;
+mod probe;
 mod raw_thumbnail;
 mod resize;
+mod sniff;
 mod types;
+mod variant;
+mod white_balance;
 
-pub use jpeg::{decode_jpeg, decode_jpeg_no_orientation, get_orientation};
+pub use auto::{
+    decode_auto, AutoDecodeOptions, DecodePreference, DecodeSource, DecodeWarning, DecodedResult,
+};
+pub(crate) use bytes::{read_u16, read_u32, read_u64};
+pub use classify::{
+    classify_file, FileClassification, FileType, CLASSIFY_BYTES, DEFAULT_MAX_FILE_BYTES,
+};
+pub use gain_map::{
+    apply_gain_map, apply_gain_map_with_metadata, decode_jpeg_with_gain_map, extract_gain_map,
+    has_gain_map, GainMap, GainMapMetadata,
+};
+pub use heif::{
+    decode_heif_preview, extract_heif_preview, is_heif_file, parse_heif_container, HeifContainer,
+    HeifItem,
+};
+pub(crate) use icc::embed_icc_profile;
+pub use icc::{
+    convert_for_display, convert_from_srgb, convert_from_srgb_16, convert_to_srgb,
+    detect_color_profile, extract_icc_profile, icc_description, ColorProfile, DisplayColorSpace,
+};
+pub use jpeg::{
+    decode_jpeg, decode_jpeg_no_orientation, decode_jpeg_region, decode_jpeg_rows,
+    decode_jpeg_tolerant, decode_jpeg_with_options, get_orientation, DecodeWarnings,
+    JpegDecodeOptions,
+};
+pub(crate) use jpeg::{is_start_of_frame, JpegSegment, JpegSegments};
+pub use open::{open_file, FileFormat, OpenFileError, OpenFileOptions, OpenStage, OpenedFile};
+pub use probe::{probe_image, ImageProbe, ProbeFormat, PROBE_BYTES};
 pub use raw_thumbnail::{
-    decode_raw_thumbnail, extract_raw_thumbnail, get_raw_camera_info, is_raw_file,
+    decode_raw_thumbnail, extract_raw_thumbnail, extract_raw_thumbnail_with_location,
+    get_raw_camera_info, is_raw_file, PreviewLocation,
+};
+#[cfg(any(test, feature = "test-util"))]
+pub(crate) use resize::canonical_resize;
+pub use resize::{
+    canonical_preview, extract_region_scaled, generate_thumbnail, generate_thumbnail_detail,
+    resize, resize_banded, resize_f32, resize_to_fit, resize_to_fit_banded,
+    resize_to_fit_dimensions, subsample, subsample_map, subsampled_dimensions,
+    DEFAULT_MAX_SCRATCH_BYTES,
+};
+pub(crate) use sniff::{sniff_image_format, ImageFormat};
+pub use types::{
+    quantize_to_u8, CanvasColorSpace, DecodeError, DecodedImage, DecodedImageF32, FilterType,
+    GrayImage, ImageMetadata, Orientation, PixelBuffer, QuantizeMethod, ToneMap, UnitSample,
 };
-pub use resize::{generate_thumbnail, resize, resize_to_fit};
-pub use types::{DecodeError, DecodedImage, FilterType, ImageMetadata, Orientation};
+pub use white_balance::{get_as_shot_white_balance, AsShotWhiteBalance, WhiteBalanceSource};
@@ -0,0 +1,446 @@
+//! Opening a file in one call: sniff, decode, preview, thumbnail, histogram
+//! and metadata.
+//!
+//! Both the import flow and the editor need the same sequence of steps to
+//! show a photo. [`open_file`] runs them in order on one decode, computing
+//! only the pieces that were asked for, and reports which step failed.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::auto::decode_png;
+use super::heif::extract_heif_preview;
+use super::jpeg::{decode_jpeg_with_options, JpegDecodeOptions};
+use super::metadata::read_exif_metadata;
+use super::raw_thumbnail::extract_raw_thumbnail;
+use super::resize::{canonical_preview, generate_thumbnail, resize_to_fit};
+use super::types::{DecodeError, DecodedImage, FilterType, ImageMetadata};
+use super::{sniff_image_format, ImageFormat};
+use crate::histogram::compute_histogram;
+use crate::{perf, Histogram};
+
+/// File type detected by [`open_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    /// JPEG, decoded directly
+    Jpeg,
+    /// PNG, decoded directly
+    Png,
+    /// TIFF-based RAW, opened through its embedded JPEG preview
+    Raw,
+    /// HEIC/HEIF, opened through its embedded JPEG preview
+    Heif,
+}
+
+impl FileFormat {
+    /// Stable snake_case name, as used across the WASM boundary.
+    pub fn name(self) -> &'static str {
+        match self {
+            FileFormat::Jpeg => "jpeg",
+            FileFormat::Png => "png",
+            FileFormat::Raw => "raw",
+            FileFormat::Heif => "heif",
+        }
+    }
+}
+
+/// A step of [`open_file`], reported when it fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenStage {
+    /// Recognizing the file type
+    Detect,
+    /// Extracting the embedded preview (RAW, HEIF) and decoding the JPEG
+    Decode,
+    /// Resizing the preview
+    Preview,
+    /// Generating the thumbnail
+    Thumbnail,
+    /// Computing the histogram
+    Histogram,
+}
+
+impl OpenStage {
+    /// Stable snake_case name, as used across the WASM boundary.
+    pub fn name(self) -> &'static str {
+        match self {
+            OpenStage::Detect => "detect",
+            OpenStage::Decode => "decode",
+            OpenStage::Preview => "preview",
+            OpenStage::Thumbnail => "thumbnail",
+            OpenStage::Histogram => "histogram",
+        }
+    }
+}
+
+impl fmt::Display for OpenStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error from [`open_file`], naming the step that failed.
+#[derive(Debug, Error)]
+#[error("Opening file failed at {stage}: {source}")]
+pub struct OpenFileError {
+    /// Step that failed.
+    pub stage: OpenStage,
+    /// What went wrong in that step.
+    #[source]
+    pub source: DecodeError,
+}
+
+impl OpenFileError {
+    fn at(stage: OpenStage) -> impl FnOnce(DecodeError) -> Self {
+        move |source| Self { stage, source }
+    }
+}
+
+/// Which pieces [`open_file`] should produce.
+///
+/// Missing fields deserialize to their defaults, so `{}` only detects and
+/// decodes the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenFileOptions {
+    /// Longest edge of the preview; no preview when `None`.
+    pub preview_max_edge: Option<u32>,
+    /// Bounding square of the thumbnail; no thumbnail when `None`.
+    pub thumbnail_size: Option<u32>,
+    /// Compute the histogram.
+    pub want_histogram: bool,
+    /// Read the file's metadata.
+    pub want_metadata: bool,
+    /// Make the preview and thumbnail with [`canonical_preview`], so the
+    /// thumbnail matches the preview downscaled to its size.
+    pub consistent_resize: bool,
+}
+
+/// What [`open_file`] produced. Each optional piece is present exactly when
+/// it was requested.
+#[derive(Debug, Clone)]
+pub struct OpenedFile {
+    /// Detected file type.
+    pub format: FileFormat,
+    /// Decoded image resized to fit `preview_max_edge` (Lanczos3, or the
+    /// canonical downscale with `consistent_resize`).
+    pub preview: Option<DecodedImage>,
+    /// Thumbnail generated from the full decoded image.
+    pub thumbnail: Option<DecodedImage>,
+    /// Histogram of the preview, or of the full decoded image when no
+    /// preview was requested.
+    pub histogram: Option<Histogram>,
+    /// Dimensions, orientation and color profile of the decoded JPEG (only
+    /// dimensions for a PNG), plus camera and exposure details from the
+    /// file's EXIF.
+    pub metadata: Option<ImageMetadata>,
+}
+
+/// Detect, decode and prepare a file for display in one pass.
+///
+/// JPEGs and PNGs are decoded directly; RAW and HEIF files through their embedded
+/// JPEG preview, as [`decode_raw_thumbnail`](super::decode_raw_thumbnail)
+/// and [`decode_heif_preview`](super::decode_heif_preview) do. Every piece
+/// equals what the individual function would return for the same input:
+/// [`resize_to_fit`] with Lanczos3 for the preview, [`generate_thumbnail`]
+/// for the thumbnail, and [`compute_histogram`] for the histogram. With
+/// `consistent_resize` set, both the preview and the thumbnail come from
+/// [`canonical_preview`] instead.
+///
+/// Metadata never fails the open: fields the file does not carry are
+/// `None`.
+///
+/// # Errors
+///
+/// Returns an [`OpenFileError`] naming the step that failed:
+/// `Detect` with `DecodeError::InvalidFormat` for unrecognized files,
+/// `Decode` for missing previews or corrupted data, and `Preview` or
+/// `Thumbnail` for a zero size.
+pub fn open_file(bytes: &[u8], options: &OpenFileOptions) -> Result<OpenedFile, OpenFileError> {
+    let _perf = perf::scope("open_file");
+    let format = detect_format(bytes).ok_or(OpenFileError {
+        stage: OpenStage::Detect,
+        source: DecodeError::InvalidFormat,
+    })?;
+
+    let jpeg_options = JpegDecodeOptions::default();
+    let (image, mut metadata) = match format {
+        FileFormat::Jpeg => decode_jpeg_with_options(bytes, &jpeg_options),
+        FileFormat::Png => decode_png(bytes).map(|image| {
+            let metadata = ImageMetadata {
+                width: image.width,
+                height: image.height,
+                ..Default::default()
+            };
+            (image, metadata)
+        }),
+        FileFormat::Raw => extract_raw_thumbnail(bytes)
+            .and_then(|jpeg| decode_jpeg_with_options(&jpeg, &jpeg_options)),
+        FileFormat::Heif => extract_heif_preview(bytes)
+            .and_then(|jpeg| decode_jpeg_with_options(&jpeg, &jpeg_options)),
+    }
+    .map_err(OpenFileError::at(OpenStage::Decode))?;
+
+    let preview = options
+        .preview_max_edge
+        .map(|max_edge| {
+            if options.consistent_resize {
+                canonical_preview(&image, max_edge)
+            } else {
+                resize_to_fit(&image, max_edge, FilterType::Lanczos3)
+            }
+        })
+        .transpose()
+        .map_err(OpenFileError::at(OpenStage::Preview))?;
+    let thumbnail = options
+        .thumbnail_size
+        .map(|size| {
+            if options.consistent_resize {
+                canonical_preview(&image, size)
+            } else {
+                generate_thumbnail(&image, size)
+            }
+        })
+        .transpose()
+        .map_err(OpenFileError::at(OpenStage::Thumbnail))?;
+
+    let histogram = if options.want_histogram {
+        let source = preview.as_ref().unwrap_or(&image);
+        let histogram = compute_histogram(&source.pixels, source.width, source.height)
+            .map_err(DecodeError::from)
+            .map_err(OpenFileError::at(OpenStage::Histogram))?;
+        Some(histogram)
+    } else {
+        None
+    };
+
+    let metadata = options.want_metadata.then(|| {
+        read_exif_metadata(bytes, &mut metadata);
+        metadata
+    });
+
+    Ok(OpenedFile {
+        format,
+        preview,
+        thumbnail,
+        histogram,
+        metadata,
+    })
+}
+
+/// Recognize the file type from its leading bytes.
+fn detect_format(bytes: &[u8]) -> Option<FileFormat> {
+    Some(match sniff_image_format(bytes)? {
+        ImageFormat::Jpeg => FileFormat::Jpeg,
+        ImageFormat::Png => FileFormat::Png,
+        ImageFormat::Tiff => FileFormat::Raw,
+        ImageFormat::Heif => FileFormat::Heif,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_jpeg, decode_raw_thumbnail};
+    use crate::encode::encode_jpeg;
+    use crate::testing::noise;
+    use image::codecs::png::PngEncoder;
+    use image::ImageEncoder;
+
+    fn test_jpeg() -> Vec<u8> {
+        let image = noise(96, 64, 7);
+        encode_jpeg(&image.pixels, image.width, image.height, 90).unwrap()
+    }
+
+    /// Append little-endian IFD entries `(tag, type, count, value)` to `tiff`.
+    fn write_entries(tiff: &mut Vec<u8>, entries: &[(u16, u16, u32, u32)]) {
+        tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for &(tag, field_type, count, value) in entries {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&field_type.to_le_bytes());
+            tiff.extend_from_slice(&count.to_le_bytes());
+            tiff.extend_from_slice(&value.to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+    }
+
+    /// A little-endian TIFF whose IFD0 carries `Make` and an embedded JPEG,
+    /// and whose Exif IFD carries ISO, exposure time and f-number.
+    fn test_raw(jpeg: &[u8]) -> Vec<u8> {
+        const IFD0_ENTRIES: usize = 4;
+        const EXIF_ENTRIES: usize = 3;
+        let exif_ifd = 8 + 2 + IFD0_ENTRIES * 12 + 4;
+        let data = exif_ifd + 2 + EXIF_ENTRIES * 12 + 4;
+        let (make, exposure, f_number, image) = (data, data + 8, data + 16, data + 24);
+
+        let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        write_entries(
+            &mut tiff,
+            &[
+                (0x010F, 2, 5, make as u32),       // Make (ASCII)
+                (0x0201, 4, 1, image as u32),      // JPEGInterchangeFormat
+                (0x0202, 4, 1, jpeg.len() as u32), // JPEGInterchangeFormatLength
+                (0x8769, 4, 1, exif_ifd as u32),   // Exif IFD pointer
+            ],
+        );
+        write_entries(
+            &mut tiff,
+            &[
+                (0x829A, 5, 1, exposure as u32), // ExposureTime (RATIONAL)
+                (0x829D, 5, 1, f_number as u32), // FNumber (RATIONAL)
+                (0x8827, 3, 1, 400),             // PhotographicSensitivity
+            ],
+        );
+        tiff.extend_from_slice(b"Sony\0\0\0\0");
+        for (num, den) in [(1u32, 250u32), (28, 10)] {
+            tiff.extend_from_slice(&num.to_le_bytes());
+            tiff.extend_from_slice(&den.to_le_bytes());
+        }
+        assert_eq!(tiff.len(), image);
+        tiff.extend_from_slice(jpeg);
+        tiff
+    }
+
+    fn all_pieces() -> OpenFileOptions {
+        OpenFileOptions {
+            preview_max_edge: Some(48),
+            thumbnail_size: Some(16),
+            want_histogram: true,
+            want_metadata: true,
+            consistent_resize: false,
+        }
+    }
+
+    #[test]
+    fn test_pieces_present_iff_requested() {
+        let jpeg = test_jpeg();
+        for mask in 0..16u8 {
+            let options = OpenFileOptions {
+                preview_max_edge: (mask & 1 != 0).then_some(48),
+                thumbnail_size: (mask & 2 != 0).then_some(16),
+                want_histogram: mask & 4 != 0,
+                want_metadata: mask & 8 != 0,
+                consistent_resize: false,
+            };
+            let opened = open_file(&jpeg, &options).unwrap();
+            assert_eq!(opened.format, FileFormat::Jpeg);
+            assert_eq!(opened.preview.is_some(), mask & 1 != 0, "mask {mask}");
+            assert_eq!(opened.thumbnail.is_some(), mask & 2 != 0, "mask {mask}");
+            assert_eq!(opened.histogram.is_some(), mask & 4 != 0, "mask {mask}");
+            assert_eq!(opened.metadata.is_some(), mask & 8 != 0, "mask {mask}");
+        }
+    }
+
+    #[test]
+    fn test_consistent_resize_uses_canonical_preview() {
+        let jpeg = test_jpeg();
+        let options = OpenFileOptions {
+            consistent_resize: true,
+            ..all_pieces()
+        };
+        let opened = open_file(&jpeg, &options).unwrap();
+
+        let image = decode_jpeg(&jpeg).unwrap();
+        let preview = canonical_preview(&image, 48).unwrap();
+        assert_eq!(opened.preview.unwrap().pixels, preview.pixels);
+        let thumbnail = canonical_preview(&image, 16).unwrap();
+        assert_eq!(opened.thumbnail.unwrap().pixels, thumbnail.pixels);
+    }
+
+    #[test]
+    fn test_jpeg_matches_individual_functions() {
+        let jpeg = test_jpeg();
+        let opened = open_file(&jpeg, &all_pieces()).unwrap();
+
+        let image = decode_jpeg(&jpeg).unwrap();
+        let preview = resize_to_fit(&image, 48, FilterType::Lanczos3).unwrap();
+        let thumbnail = generate_thumbnail(&image, 16).unwrap();
+        let histogram = compute_histogram(&preview.pixels, preview.width, preview.height).unwrap();
+
+        let opened_preview = opened.preview.unwrap();
+        assert_eq!((opened_preview.width, opened_preview.height), (48, 32));
+        assert_eq!(opened_preview.pixels, preview.pixels);
+        assert_eq!(opened.thumbnail.unwrap().pixels, thumbnail.pixels);
+        let opened_histogram = opened.histogram.unwrap();
+        assert_eq!(opened_histogram.red, histogram.red);
+        assert_eq!(opened_histogram.luminance, histogram.luminance);
+
+        let metadata = opened.metadata.unwrap();
+        assert_eq!((metadata.width, metadata.height), (96, 64));
+        assert_eq!(metadata.camera_make, None);
+    }
+
+    #[test]
+    fn test_histogram_without_preview_covers_full_image() {
+        let jpeg = test_jpeg();
+        let options = OpenFileOptions {
+            want_histogram: true,
+            ..Default::default()
+        };
+        let histogram = open_file(&jpeg, &options).unwrap().histogram.unwrap();
+        let image = decode_jpeg(&jpeg).unwrap();
+        let expected = compute_histogram(&image.pixels, image.width, image.height).unwrap();
+        assert_eq!(histogram.green, expected.green);
+    }
+
+    #[test]
+    fn test_raw_opens_embedded_preview_with_metadata() {
+        let raw = test_raw(&test_jpeg());
+        let opened = open_file(&raw, &all_pieces()).unwrap();
+        assert_eq!(opened.format, FileFormat::Raw);
+
+        let image = decode_raw_thumbnail(&raw).unwrap();
+        let thumbnail = generate_thumbnail(&image, 16).unwrap();
+        assert_eq!(opened.thumbnail.unwrap().pixels, thumbnail.pixels);
+
+        let metadata = opened.metadata.unwrap();
+        assert_eq!(metadata.camera_make.as_deref(), Some("Sony"));
+        assert_eq!(metadata.iso, Some(400));
+        assert_eq!(metadata.shutter_speed.as_deref(), Some("1/250"));
+        assert_eq!(metadata.aperture, Some(2.8));
+        assert_eq!(metadata.focal_length, None);
+    }
+
+    #[test]
+    fn test_png_is_decoded_directly() {
+        let image = noise(40, 20, 3);
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png)
+            .write_image(&image.pixels, 40, 20, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        let opened = open_file(&png, &all_pieces()).unwrap();
+
+        assert_eq!(opened.format, FileFormat::Png);
+        let preview = resize_to_fit(&image, 48, FilterType::Lanczos3).unwrap();
+        assert_eq!(opened.preview.unwrap().pixels, preview.pixels);
+        let metadata = opened.metadata.unwrap();
+        assert_eq!((metadata.width, metadata.height), (40, 20));
+        assert_eq!(metadata.camera_make, None);
+    }
+
+    #[test]
+    fn test_failures_report_stage() {
+        let err = open_file(b"not an image", &all_pieces()).unwrap_err();
+        assert_eq!(err.stage, OpenStage::Detect);
+        assert!(matches!(err.source, DecodeError::InvalidFormat));
+
+        let mut corrupt = test_jpeg();
+        corrupt.truncate(200);
+        let err = open_file(&corrupt, &all_pieces()).unwrap_err();
+        assert_eq!(err.stage, OpenStage::Decode);
+        assert!(err.to_string().contains("decode"), "{}", err);
+
+        let err = open_file(b"II\x2A\x00\x08\x00\x00\x00", &all_pieces()).unwrap_err();
+        assert_eq!(err.stage, OpenStage::Decode);
+
+        let options = OpenFileOptions {
+            thumbnail_size: Some(0),
+            ..Default::default()
+        };
+        let err = open_file(&test_jpeg(), &options).unwrap_err();
+        assert_eq!(err.stage, OpenStage::Thumbnail);
+    }
+}
@@ -0,0 +1,380 @@
+//! Header-only probing of image type, dimensions and orientation.
+//!
+//! Import needs every file's aspect ratio before anything is decoded, so the
+//! grid can show correctly shaped placeholders. [`probe_image`] reads just
+//! the headers:
+//!
+//! - **JPEG**: walks marker segments up to the first SOF (no entropy
+//!   decoding), reading orientation from an EXIF APP1 segment on the way
+//! - **TIFF-based RAW** (ARW, NEF, DNG, ...): IFD0 `ImageWidth`,
+//!   `ImageLength` and `Orientation`
+//! - **PNG**: the IHDR chunk
+//!
+//! The first [`PROBE_BYTES`] of a file are enough for typical files, and any
+//! prefix is accepted: whatever the prefix does not contain is reported as
+//! unknown.
+
+use serde::{Deserialize, Serialize};
+
+use super::jpeg::app_segments_partial;
+use super::{read_u16, read_u32, sniff_image_format, ImageFormat, Orientation};
+
+/// Number of leading bytes that is enough to probe typical files.
+pub const PROBE_BYTES: usize = 128 * 1024;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+const TAG_IMAGE_WIDTH: u16 = 0x0100;
+const TAG_IMAGE_LENGTH: u16 = 0x0101;
+const TAG_ORIENTATION: u16 = 0x0112;
+
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+
+/// File type detected from the leading bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeFormat {
+    /// JPEG (starts with SOI)
+    Jpeg,
+    /// TIFF container, which covers most RAW formats
+    TiffRaw,
+    /// PNG
+    Png,
+    /// Not a recognized image
+    Unknown,
+}
+
+/// What a header scan could determine about a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageProbe {
+    /// Detected file type.
+    pub format: ProbeFormat,
+    /// Stored width in pixels (before orientation), if found.
+    pub width: Option<u32>,
+    /// Stored height in pixels (before orientation), if found.
+    pub height: Option<u32>,
+    /// EXIF orientation; `Normal` when the file has none.
+    pub orientation: Orientation,
+}
+
+impl ImageProbe {
+    fn new(format: ProbeFormat) -> Self {
+        Self {
+            format,
+            width: None,
+            height: None,
+            orientation: Orientation::Normal,
+        }
+    }
+
+    /// Get the displayed dimensions after orientation, if both are known.
+    pub fn oriented_dimensions(&self) -> Option<(u32, u32)> {
+        let (width, height) = (self.width?, self.height?);
+        if self.orientation.swaps_dimensions() {
+            Some((height, width))
+        } else {
+            Some((width, height))
+        }
+    }
+}
+
+/// Read a file's type, dimensions and orientation from its headers.
+///
+/// Never fails: unrecognized bytes give [`ProbeFormat::Unknown`], and a
+/// truncated header leaves the fields it did not reach as `None` (or
+/// `Normal` for orientation).
+///
+/// For TIFF-based RAW files the dimensions are those of IFD0. Depending on
+/// the camera this is the sensor image or an embedded preview; both have
+/// the photo's aspect ratio.
+pub fn probe_image(bytes: &[u8]) -> ImageProbe {
+    match sniff_image_format(bytes) {
+        Some(ImageFormat::Jpeg) => probe_jpeg(bytes),
+        Some(ImageFormat::Png) => probe_png(bytes),
+        Some(ImageFormat::Tiff) => {
+            let mut probe = ImageProbe::new(ProbeFormat::TiffRaw);
+            read_tiff_ifd0(bytes, &mut probe);
+            probe
+        }
+        Some(ImageFormat::Heif) | None => ImageProbe::new(ProbeFormat::Unknown),
+    }
+}
+
+/// Walk JPEG segments up to the first SOF header.
+fn probe_jpeg(bytes: &[u8]) -> ImageProbe {
+    let mut probe = ImageProbe::new(ProbeFormat::Jpeg);
+    let mut has_orientation = false;
+
+    // A segment cut off by truncation is read as far as it goes
+    for (marker, payload) in app_segments_partial(bytes) {
+        // SOF0..SOF15, excluding DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            if payload.len() >= 5 {
+                let height = u16::from_be_bytes([payload[1], payload[2]]) as u32;
+                let width = u16::from_be_bytes([payload[3], payload[4]]) as u32;
+                probe.width = (width > 0).then_some(width);
+                probe.height = (height > 0).then_some(height);
+            }
+            break;
+        }
+        // Like the decoder, take the first segment with a valid orientation
+        if marker == 0xE1 && !has_orientation {
+            if let Some(tiff) = payload.strip_prefix(EXIF_HEADER) {
+                let mut exif = ImageProbe::new(ProbeFormat::Jpeg);
+                let value = read_tiff_ifd0(tiff, &mut exif);
+                probe.orientation = exif.orientation;
+                has_orientation = value.and_then(Orientation::from_exif).is_some();
+            }
+        }
+    }
+
+    probe
+}
+
+/// Read the IHDR chunk, which PNG requires to come first.
+fn probe_png(bytes: &[u8]) -> ImageProbe {
+    let mut probe = ImageProbe::new(ProbeFormat::Png);
+    if bytes.get(12..16) == Some(b"IHDR") {
+        probe.width = read_u32(bytes, 16, false).filter(|&w| w > 0);
+        probe.height = read_u32(bytes, 20, false).filter(|&h| h > 0);
+    }
+    probe
+}
+
+/// Byte order of a TIFF header: `Some(true)` for little-endian.
+fn tiff_byte_order(bytes: &[u8]) -> Option<bool> {
+    match bytes.get(..4)? {
+        [b'I', b'I', 0x2A, 0x00] => Some(true),
+        [b'M', b'M', 0x00, 0x2A] => Some(false),
+        _ => None,
+    }
+}
+
+/// Fill dimensions and orientation from the entries of IFD0, and return
+/// the raw orientation value if there is one.
+///
+/// Entries past the end of `tiff` are skipped.
+fn read_tiff_ifd0(tiff: &[u8], probe: &mut ImageProbe) -> Option<u32> {
+    let le = tiff_byte_order(tiff)?;
+    let ifd = read_u32(tiff, 4, le)? as usize;
+    let count = read_u16(tiff, ifd, le)?;
+
+    let mut orientation = None;
+
+    for i in 0..count as usize {
+        let entry = ifd + 2 + i * 12;
+        let (Some(tag), Some(field_type)) =
+            (read_u16(tiff, entry, le), read_u16(tiff, entry + 2, le))
+        else {
+            break;
+        };
+        // Values of these tags fit in the entry's 4-byte value field
+        let value = match field_type {
+            TYPE_SHORT => read_u16(tiff, entry + 8, le).map(u32::from),
+            TYPE_LONG => read_u32(tiff, entry + 8, le),
+            _ => None,
+        };
+        let Some(value) = value else {
+            continue;
+        };
+
+        match tag {
+            TAG_IMAGE_WIDTH if value > 0 => probe.width = Some(value),
+            TAG_IMAGE_LENGTH if value > 0 => probe.height = Some(value),
+            TAG_ORIENTATION => {
+                probe.orientation = Orientation::from(value);
+                orientation = Some(value);
+            }
+            _ => {}
+        }
+    }
+    orientation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::noise;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::codecs::png::PngEncoder;
+    use image::{ExtendedColorType, ImageEncoder};
+
+    fn encode_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, 80)
+            .write_image(
+                &noise(width, height, 1).pixels,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )
+            .unwrap();
+        out
+    }
+
+    fn encode_png(width: u32, height: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        PngEncoder::new(&mut out)
+            .write_image(
+                &noise(width, height, 2).pixels,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )
+            .unwrap();
+        out
+    }
+
+    /// Minimal TIFF with one IFD holding the given (tag, type, value) entries.
+    fn tiff(little_endian: bool, entries: &[(u16, u16, u32)]) -> Vec<u8> {
+        let u16b = |v: u16| {
+            if little_endian {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            }
+        };
+        let u32b = |v: u32| {
+            if little_endian {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            }
+        };
+
+        let mut out = if little_endian {
+            b"II\x2A\x00".to_vec()
+        } else {
+            b"MM\x00\x2A".to_vec()
+        };
+        out.extend_from_slice(&u32b(8));
+        out.extend_from_slice(&u16b(entries.len() as u16));
+        for &(tag, field_type, value) in entries {
+            out.extend_from_slice(&u16b(tag));
+            out.extend_from_slice(&u16b(field_type));
+            out.extend_from_slice(&u32b(1));
+            if field_type == TYPE_SHORT {
+                out.extend_from_slice(&u16b(value as u16));
+                out.extend_from_slice(&[0, 0]);
+            } else {
+                out.extend_from_slice(&u32b(value));
+            }
+        }
+        out.extend_from_slice(&u32b(0));
+        out
+    }
+
+    /// Insert an EXIF APP1 segment right after SOI.
+    fn with_exif(jpeg: &[u8], tiff: &[u8]) -> Vec<u8> {
+        let mut app1 = EXIF_HEADER.to_vec();
+        app1.extend_from_slice(tiff);
+        let mut out = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        out.extend(app1);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    /// Offset just past the SOF segment's dimension fields.
+    fn end_of_sof(jpeg: &[u8]) -> usize {
+        let sof = jpeg.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        sof + 9
+    }
+
+    #[test]
+    fn test_jpeg_dimensions() {
+        let probe = probe_image(&encode_jpeg(37, 21));
+        assert_eq!(probe.format, ProbeFormat::Jpeg);
+        assert_eq!((probe.width, probe.height), (Some(37), Some(21)));
+        assert_eq!(probe.orientation, Orientation::Normal);
+    }
+
+    #[test]
+    fn test_jpeg_orientation_from_exif() {
+        let exif = tiff(true, &[(TAG_ORIENTATION, TYPE_SHORT, 6)]);
+        let jpeg = with_exif(&encode_jpeg(40, 30), &exif);
+        let probe = probe_image(&jpeg);
+
+        assert_eq!(probe.orientation, Orientation::Rotate90CW);
+        assert_eq!(probe.oriented_dimensions(), Some((30, 40)));
+    }
+
+    #[test]
+    fn test_jpeg_orientation_from_first_valid_exif() {
+        let first = tiff(true, &[(TAG_ORIENTATION, TYPE_SHORT, 6)]);
+        let second = tiff(true, &[(TAG_ORIENTATION, TYPE_SHORT, 3)]);
+        let jpeg = with_exif(&with_exif(&encode_jpeg(40, 30), &second), &first);
+        assert_eq!(probe_image(&jpeg).orientation, Orientation::Rotate90CW);
+
+        let invalid = tiff(true, &[(TAG_ORIENTATION, TYPE_SHORT, 0)]);
+        let jpeg = with_exif(&with_exif(&encode_jpeg(40, 30), &first), &invalid);
+        assert_eq!(probe_image(&jpeg).orientation, Orientation::Rotate90CW);
+    }
+
+    #[test]
+    fn test_jpeg_truncated_after_sof() {
+        let jpeg = encode_jpeg(64, 48);
+        let probe = probe_image(&jpeg[..end_of_sof(&jpeg)]);
+        assert_eq!((probe.width, probe.height), (Some(64), Some(48)));
+    }
+
+    #[test]
+    fn test_jpeg_truncated_before_sof() {
+        let jpeg = encode_jpeg(64, 48);
+        let probe = probe_image(&jpeg[..end_of_sof(&jpeg) - 4]);
+        assert_eq!(probe.format, ProbeFormat::Jpeg);
+        assert_eq!((probe.width, probe.height), (None, None));
+    }
+
+    #[test]
+    fn test_png_dimensions() {
+        let png = encode_png(50, 17);
+        let probe = probe_image(&png);
+        assert_eq!(probe.format, ProbeFormat::Png);
+        assert_eq!((probe.width, probe.height), (Some(50), Some(17)));
+
+        // IHDR ends at byte 24
+        assert_eq!(probe_image(&png[..24]), probe);
+        assert_eq!(probe_image(&png[..20]).height, None);
+    }
+
+    #[test]
+    fn test_tiff_ifd0_both_byte_orders() {
+        for le in [true, false] {
+            let bytes = tiff(
+                le,
+                &[
+                    (TAG_IMAGE_WIDTH, TYPE_LONG, 6000),
+                    (TAG_IMAGE_LENGTH, TYPE_SHORT, 4000),
+                    (TAG_ORIENTATION, TYPE_SHORT, 8),
+                ],
+            );
+            let probe = probe_image(&bytes);
+            assert_eq!(probe.format, ProbeFormat::TiffRaw);
+            assert_eq!((probe.width, probe.height), (Some(6000), Some(4000)));
+            assert_eq!(probe.orientation, Orientation::Rotate270CW);
+        }
+    }
+
+    #[test]
+    fn test_tiff_truncated_mid_ifd() {
+        let bytes = tiff(
+            true,
+            &[
+                (TAG_IMAGE_WIDTH, TYPE_LONG, 6000),
+                (TAG_IMAGE_LENGTH, TYPE_LONG, 4000),
+            ],
+        );
+        // Cut inside the second entry
+        let probe = probe_image(&bytes[..10 + 12 + 6]);
+        assert_eq!((probe.width, probe.height), (Some(6000), None));
+    }
+
+    #[test]
+    fn test_garbage_is_unknown() {
+        for bytes in [&b""[..], b"not an image", &[0xFF], &[0x89, b'P', b'N']] {
+            assert_eq!(probe_image(bytes), ImageProbe::new(ProbeFormat::Unknown));
+        }
+    }
+}
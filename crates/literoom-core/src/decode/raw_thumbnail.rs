@@ -21,7 +21,7 @@
 use std::io::{Cursor, Read, Seek, SeekFrom};
 
 use super::{DecodeError, DecodedImage};
-use crate::decode::jpeg::decode_jpeg;
+use crate::decode::jpeg::{decode_jpeg, is_start_of_frame, JpegSegments};
 
 // TIFF constants
 const TIFF_MAGIC_LE: [u8; 4] = [0x49, 0x49, 0x2A, 0x00]; // II + 42
@@ -39,6 +39,10 @@ const TAG_COMPRESSION: u16 = 0x0103;
 const COMPRESSION_JPEG: u16 = 6;
 const COMPRESSION_JPEG_OLD: u16 = 7;
 
+// TIFF field types used by the strip tags
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+
 // JPEG magic bytes
 const JPEG_START: [u8; 2] = [0xFF, 0xD8];
 const JPEG_END: [u8; 2] = [0xFF, 0xD9];
@@ -70,6 +74,32 @@ fn extract_jpeg_data(file_bytes: &[u8], offset: u32, length: u32) -> Option<Vec<
     }
 }
 
+/// Upper bound on the number of IFDs visited while searching for a preview.
+///
+/// Real files have a handful; anything beyond this is treated as corrupt.
+const MAX_IFDS: usize = 64;
+
+/// Where in a RAW file the embedded preview was found.
+///
+/// Returned by [`extract_raw_thumbnail_with_location`] for diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewLocation {
+    /// A SubIFD referenced from the main IFD chain.
+    SubIfd {
+        /// Position of the parent IFD in the chain (0 = IFD0)
+        parent: usize,
+        /// Position of the offset within the parent's SubIFD array
+        index: usize,
+    },
+    /// An IFD in the main chain (0 = IFD0, 1 = IFD1, ...).
+    Ifd(usize),
+    /// Found by scanning the file for JPEG markers.
+    ///
+    /// The dimensions come from the JPEG's SOF header, so callers can size
+    /// the preview without decoding it.
+    MarkerScan { width: u32, height: u32 },
+}
+
 /// Extract the embedded JPEG thumbnail from a RAW file.
 ///
 /// This extracts the raw JPEG bytes from the RAW file without decoding.
@@ -99,6 +129,26 @@ fn extract_jpeg_data(file_bytes: &[u8], offset: u32, length: u32) -> Option<Vec<
 /// let image = decode_jpeg(&jpeg_bytes)?;
 /// ```
 pub fn extract_raw_thumbnail(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    extract_raw_thumbnail_with_location(bytes).map(|(jpeg, _)| jpeg)
+}
+
+/// Extract the embedded JPEG thumbnail and report where it was found.
+///
+/// Locations are tried in this order:
+/// 1. Every SubIFD referenced from the IFD chain (preferred for ARW/NEF - larger preview)
+/// 2. IFD1, IFD2, ... following the next-IFD chain
+/// 3. JPEG data referenced directly from IFD0
+/// 4. A marker scan over the whole file
+///
+/// The IFD walk is capped at a fixed number of IFDs and never visits the
+/// same offset twice, so circular chains in corrupt files terminate.
+///
+/// # Errors
+///
+/// Same as [`extract_raw_thumbnail`].
+pub fn extract_raw_thumbnail_with_location(
+    bytes: &[u8],
+) -> Result<(Vec<u8>, PreviewLocation), DecodeError> {
     let mut cursor = Cursor::new(bytes);
 
     // Detect byte order and validate TIFF header
@@ -121,55 +171,65 @@ pub fn extract_raw_thumbnail(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
         .seek(SeekFrom::Start(ifd0_offset as u64))
         .map_err(|e| DecodeError::CorruptedFile(format!("Failed to seek to IFD0: {}", e)))?;
 
-    // Try to find preview in various locations:
-    // 1. SubIFD (preferred for ARW - larger preview)
-    // 2. IFD1 (standard EXIF thumbnail)
-    // 3. JPEG data in IFD0
-
-    // First, parse IFD0 to find SubIFD pointer
-    let mut subifd_offset: Option<u32> = None;
-    let mut ifd1_offset: Option<u32> = None;
-
-    // Parse IFD0
-    let (entries, next_ifd) = parse_ifd(&mut cursor, little_endian, bytes.len())?;
-
-    for entry in &entries {
-        if entry.tag == TAG_SUBIFD && entry.count > 0 {
-            // SubIFD offset - this often contains the main preview
-            subifd_offset = Some(entry.value_offset);
+    // Parse IFD0 (errors here mean the file is unusable)
+    let (ifd0_entries, mut next_ifd) = parse_ifd(&mut cursor, little_endian, bytes.len())?;
+
+    // Walk the next-IFD chain, guarding against loops
+    let mut visited = vec![ifd0_offset];
+    let mut chain = vec![ifd0_entries];
+    while next_ifd != 0 && visited.len() < MAX_IFDS && !visited.contains(&next_ifd) {
+        visited.push(next_ifd);
+        match read_ifd_at(&mut cursor, next_ifd, little_endian, bytes.len()) {
+            Ok((entries, next)) => {
+                chain.push(entries);
+                next_ifd = next;
+            }
+            Err(_) => break,
         }
     }
 
-    // Check for IFD1 (next IFD after IFD0)
-    if next_ifd != 0 {
-        ifd1_offset = Some(next_ifd);
-    }
-
-    // Try SubIFD first (usually has larger preview for Sony cameras)
-    if let Some(offset) = subifd_offset {
-        if let Ok(jpeg) = extract_jpeg_from_ifd(&mut cursor, offset, little_endian, bytes) {
-            if jpeg.len() > 10000 {
-                // Likely a real preview, not a tiny thumbnail
-                return Ok(jpeg);
+    // Try SubIFDs first (usually have the larger preview for Sony/Nikon cameras)
+    for (parent, entries) in chain.iter().enumerate() {
+        for entry in entries
+            .iter()
+            .filter(|e| e.tag == TAG_SUBIFD && e.count > 0)
+        {
+            let offsets =
+                read_subifd_offsets(&mut cursor, entry, little_endian).unwrap_or_default();
+            for (index, offset) in offsets.into_iter().enumerate() {
+                if visited.len() >= MAX_IFDS || visited.contains(&offset) {
+                    continue;
+                }
+                visited.push(offset);
+                if let Ok(jpeg) = extract_jpeg_from_ifd(&mut cursor, offset, little_endian, bytes) {
+                    if jpeg.len() > 10000 {
+                        // Likely a real preview, not a tiny thumbnail
+                        return Ok((jpeg, PreviewLocation::SubIfd { parent, index }));
+                    }
+                }
             }
         }
     }
 
-    // Try IFD1 (standard thumbnail location)
-    if let Some(offset) = ifd1_offset {
-        if let Ok(jpeg) = extract_jpeg_from_ifd(&mut cursor, offset, little_endian, bytes) {
-            return Ok(jpeg);
+    // Try IFD1, IFD2, ... (standard thumbnail location and later previews)
+    for (index, entries) in chain.iter().enumerate().skip(1) {
+        if let Ok(jpeg) = extract_jpeg_from_entries(entries, bytes, little_endian) {
+            return Ok((jpeg, PreviewLocation::Ifd(index)));
         }
     }
 
     // Try extracting from IFD0 entries directly
-    if let Ok(jpeg) = extract_jpeg_from_entries(&entries, bytes) {
-        return Ok(jpeg);
+    if let Ok(jpeg) = extract_jpeg_from_entries(&chain[0], bytes, little_endian) {
+        return Ok((jpeg, PreviewLocation::Ifd(0)));
     }
 
     // Last resort: scan for JPEG markers in the file
     if let Some(jpeg) = scan_for_jpeg(bytes) {
-        return Ok(jpeg);
+        let location = PreviewLocation::MarkerScan {
+            width: jpeg.width,
+            height: jpeg.height,
+        };
+        return Ok((jpeg.data, location));
     }
 
     Err(DecodeError::NoThumbnail)
@@ -252,7 +312,6 @@ pub fn get_raw_camera_info(bytes: &[u8]) -> Result<(String, String), DecodeError
 // IFD entry structure
 struct IfdEntry {
     tag: u16,
-    #[allow(dead_code)]
     typ: u16,
     count: u32,
     value_offset: u32,
@@ -321,37 +380,76 @@ fn parse_ifd<R: Read + Seek>(
     Ok((entries, next_ifd))
 }
 
-fn extract_jpeg_from_ifd<R: Read + Seek>(
+fn read_ifd_at<R: Read + Seek>(
     reader: &mut R,
     ifd_offset: u32,
     little_endian: bool,
-    file_bytes: &[u8],
-) -> Result<Vec<u8>, DecodeError> {
+    file_size: usize,
+) -> Result<(Vec<IfdEntry>, u32), DecodeError> {
     reader
         .seek(SeekFrom::Start(ifd_offset as u64))
         .map_err(|e| DecodeError::CorruptedFile(format!("Failed to seek to IFD: {}", e)))?;
 
-    let (entries, _) = parse_ifd(reader, little_endian, file_bytes.len())?;
+    parse_ifd(reader, little_endian, file_size)
+}
 
-    extract_jpeg_from_entries(&entries, file_bytes)
+/// Resolve the IFD offsets referenced by a SubIFDs entry.
+///
+/// A single offset is stored inline in `value_offset`; for `count > 1` the
+/// field points to an array of `count` u32 offsets instead.
+fn read_subifd_offsets<R: Read + Seek>(
+    reader: &mut R,
+    entry: &IfdEntry,
+    little_endian: bool,
+) -> Result<Vec<u32>, DecodeError> {
+    if entry.count == 1 {
+        return Ok(vec![entry.value_offset]);
+    }
+
+    reader
+        .seek(SeekFrom::Start(entry.value_offset as u64))
+        .map_err(|e| DecodeError::CorruptedFile(format!("Failed to seek to SubIFDs: {}", e)))?;
+
+    let count = (entry.count as usize).min(MAX_IFDS);
+    let mut offsets = Vec::with_capacity(count);
+    for _ in 0..count {
+        match read_u32(reader, little_endian) {
+            Ok(offset) => offsets.push(offset),
+            Err(_) => break, // Truncated array: keep what we have
+        }
+    }
+
+    Ok(offsets)
+}
+
+fn extract_jpeg_from_ifd<R: Read + Seek>(
+    reader: &mut R,
+    ifd_offset: u32,
+    little_endian: bool,
+    file_bytes: &[u8],
+) -> Result<Vec<u8>, DecodeError> {
+    let (entries, _) = read_ifd_at(reader, ifd_offset, little_endian, file_bytes.len())?;
+
+    extract_jpeg_from_entries(&entries, file_bytes, little_endian)
 }
 
 fn extract_jpeg_from_entries(
     entries: &[IfdEntry],
     file_bytes: &[u8],
+    little_endian: bool,
 ) -> Result<Vec<u8>, DecodeError> {
     let mut jpeg_offset: Option<u32> = None;
     let mut jpeg_length: Option<u32> = None;
-    let mut strip_offsets: Option<u32> = None;
-    let mut strip_byte_counts: Option<u32> = None;
+    let mut strip_offsets: Option<&IfdEntry> = None;
+    let mut strip_byte_counts: Option<&IfdEntry> = None;
     let mut compression: Option<u16> = None;
 
     for entry in entries {
         match entry.tag {
             TAG_JPEG_OFFSET => jpeg_offset = Some(entry.value_offset),
             TAG_JPEG_LENGTH => jpeg_length = Some(entry.value_offset),
-            TAG_STRIP_OFFSETS => strip_offsets = Some(entry.value_offset),
-            TAG_STRIP_BYTE_COUNTS => strip_byte_counts = Some(entry.value_offset),
+            TAG_STRIP_OFFSETS => strip_offsets = Some(entry),
+            TAG_STRIP_BYTE_COUNTS => strip_byte_counts = Some(entry),
             TAG_COMPRESSION => compression = Some(entry.value_offset as u16),
             _ => {}
         }
@@ -365,13 +463,18 @@ fn extract_jpeg_from_entries(
     }
 
     // Try strip-based JPEG (used by some cameras)
-    if let (Some(offset), Some(length)) = (strip_offsets, strip_byte_counts) {
+    if let (Some(offsets), Some(lengths)) = (strip_offsets, strip_byte_counts) {
         let is_jpeg = compression
             .map(|c| c == COMPRESSION_JPEG || c == COMPRESSION_JPEG_OLD)
             .unwrap_or(false);
 
         if is_jpeg {
-            if let Some(data) = extract_jpeg_data(file_bytes, offset, length) {
+            if offsets.count > 1 || lengths.count > 1 {
+                return assemble_jpeg_strips(offsets, lengths, file_bytes, little_endian);
+            }
+            if let Some(data) =
+                extract_jpeg_data(file_bytes, offsets.value_offset, lengths.value_offset)
+            {
                 return Ok(data);
             }
         }
@@ -380,29 +483,197 @@ fn extract_jpeg_from_entries(
     Err(DecodeError::NoThumbnail)
 }
 
+/// Read the SHORT or LONG values of an IFD entry.
+///
+/// Values that fit in four bytes are stored inline in `value_offset`;
+/// larger arrays live at the file offset it holds.
+fn read_entry_values(
+    entry: &IfdEntry,
+    file_bytes: &[u8],
+    little_endian: bool,
+) -> Result<Vec<u32>, DecodeError> {
+    let size = match entry.typ {
+        TYPE_SHORT => 2,
+        TYPE_LONG => 4,
+        typ => {
+            return Err(DecodeError::CorruptedFile(format!(
+                "Unsupported type {} for tag {:#06x}",
+                typ, entry.tag
+            )))
+        }
+    };
+    let total = (entry.count as usize)
+        .checked_mul(size)
+        .ok_or_else(|| DecodeError::CorruptedFile("IFD value array too large".to_string()))?;
+
+    let inline = if little_endian {
+        entry.value_offset.to_le_bytes()
+    } else {
+        entry.value_offset.to_be_bytes()
+    };
+    let data = if total <= inline.len() {
+        &inline[..total]
+    } else {
+        let start = entry.value_offset as usize;
+        start
+            .checked_add(total)
+            .and_then(|end| file_bytes.get(start..end))
+            .ok_or_else(|| {
+                DecodeError::CorruptedFile(format!(
+                    "Values of tag {:#06x} extend past end of file",
+                    entry.tag
+                ))
+            })?
+    };
+
+    Ok(data
+        .chunks_exact(size)
+        .map(|c| match (size, little_endian) {
+            (2, true) => u16::from_le_bytes([c[0], c[1]]) as u32,
+            (2, false) => u16::from_be_bytes([c[0], c[1]]) as u32,
+            (_, true) => u32::from_le_bytes([c[0], c[1], c[2], c[3]]),
+            (_, false) => u32::from_be_bytes([c[0], c[1], c[2], c[3]]),
+        })
+        .collect())
+}
+
+/// Concatenate a JPEG preview that is split across several strips.
+///
+/// Some Sony bodies write the large preview this way. Strips that point
+/// outside the file are corruption; an assembled buffer that is not a
+/// complete JPEG stream is reported as no thumbnail.
+fn assemble_jpeg_strips(
+    offsets: &IfdEntry,
+    lengths: &IfdEntry,
+    file_bytes: &[u8],
+    little_endian: bool,
+) -> Result<Vec<u8>, DecodeError> {
+    let offsets = read_entry_values(offsets, file_bytes, little_endian)?;
+    let lengths = read_entry_values(lengths, file_bytes, little_endian)?;
+    if offsets.len() != lengths.len() {
+        return Err(DecodeError::CorruptedFile(format!(
+            "{} strip offsets but {} strip byte counts",
+            offsets.len(),
+            lengths.len()
+        )));
+    }
+
+    let mut jpeg = Vec::new();
+    for (&offset, &length) in offsets.iter().zip(&lengths) {
+        let strip = extract_slice(file_bytes, offset as usize, length as usize)
+            .filter(|_| jpeg.len() + length as usize <= file_bytes.len())
+            .ok_or_else(|| {
+                DecodeError::CorruptedFile(format!(
+                    "Strip at {} with {} bytes exceeds file length",
+                    offset, length
+                ))
+            })?;
+        jpeg.extend_from_slice(strip);
+    }
+
+    if is_jpeg_data(&jpeg) && jpeg.ends_with(&JPEG_END) {
+        Ok(jpeg)
+    } else {
+        Err(DecodeError::NoThumbnail)
+    }
+}
+
+/// Maximum number of SOI candidates validated by [`scan_for_jpeg`].
+///
+/// Each validation is linear in the remaining file size, so this bounds the
+/// scan to a small multiple of the file length even on adversarial input.
+const MAX_SCAN_CANDIDATES: usize = 64;
+
+/// A JPEG located by [`scan_for_jpeg`], with dimensions read from its SOF header.
+#[derive(Debug)]
+struct ScannedJpeg {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// Structural summary of a validated JPEG stream.
+struct JpegSpan {
+    /// Offset one past the EOI marker
+    end: usize,
+    width: u32,
+    height: u32,
+}
+
 /// Scan for embedded JPEG by looking for JPEG markers.
 /// This is a fallback method when IFD parsing doesn't find the preview.
-fn scan_for_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+///
+/// Every SOI candidate is validated by walking its segment markers to EOI,
+/// and the candidate with the largest pixel area wins.
+fn scan_for_jpeg(bytes: &[u8]) -> Option<ScannedJpeg> {
+    scan_for_jpeg_counted(bytes, &mut 0)
+}
+
+/// Implementation of [`scan_for_jpeg`] that reports the number of bytes examined.
+fn scan_for_jpeg_counted(bytes: &[u8], steps: &mut usize) -> Option<ScannedJpeg> {
     // Skip the first few KB to avoid the main TIFF structure
     let start_offset = 8192.min(bytes.len());
     const MIN_PREVIEW_SIZE: usize = 50_000;
 
-    for i in start_offset..bytes.len().saturating_sub(2) {
-        if bytes[i] == JPEG_START[0] && bytes[i + 1] == JPEG_START[1] {
-            // Found potential JPEG start, now find the end
-            for j in (i + 2)..bytes.len().saturating_sub(1) {
-                if bytes[j] == JPEG_END[0] && bytes[j + 1] == JPEG_END[1] {
-                    let jpeg_data = &bytes[i..j + 2];
-                    // Sanity check: preview JPEGs are typically > 50KB
-                    if jpeg_data.len() > MIN_PREVIEW_SIZE {
-                        return Some(jpeg_data.to_vec());
+    let mut best: Option<(usize, JpegSpan)> = None;
+    let mut candidates = 0;
+    let mut i = start_offset;
+
+    // A real SOI is always followed by another marker, so require FF D8 FF
+    while i + 2 < bytes.len() && candidates < MAX_SCAN_CANDIDATES {
+        *steps += 1;
+        if bytes[i] != JPEG_START[0] || bytes[i + 1] != JPEG_START[1] || bytes[i + 2] != 0xFF {
+            i += 1;
+            continue;
+        }
+
+        candidates += 1;
+        match walk_jpeg_markers(bytes, i, steps) {
+            Some(span) => {
+                let next = span.end;
+                // Sanity check: preview JPEGs are typically > 50KB
+                if span.end - i > MIN_PREVIEW_SIZE {
+                    let area = span.width as u64 * span.height as u64;
+                    let is_larger = best
+                        .as_ref()
+                        .is_none_or(|(_, b)| area > b.width as u64 * b.height as u64);
+                    if is_larger {
+                        best = Some((i, span));
                     }
                 }
+                // Skip past this JPEG so embedded thumbnails aren't rescanned
+                i = next;
             }
+            None => i += 2,
         }
     }
 
-    None
+    best.map(|(start, span)| ScannedJpeg {
+        data: bytes[start..span.end].to_vec(),
+        width: span.width,
+        height: span.height,
+    })
+}
+
+/// Walk JPEG segments from the SOI at `start` until EOI.
+///
+/// Returns `None` if the stream is truncated, malformed, or has no SOF header.
+fn walk_jpeg_markers(bytes: &[u8], start: usize, steps: &mut usize) -> Option<JpegSpan> {
+    let mut walk = JpegSegments::new(&bytes[start..]);
+    let sof = walk
+        .by_ref()
+        .find(|segment| is_start_of_frame(segment.marker));
+    walk.by_ref().for_each(drop);
+    *steps += walk.position();
+
+    let end = start + walk.end_of_image()?;
+    let frame = sof?.payload;
+    let height = u16::from_be_bytes([*frame.get(1)?, *frame.get(2)?]) as u32;
+    let width = u16::from_be_bytes([*frame.get(3)?, *frame.get(4)?]) as u32;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some(JpegSpan { end, width, height })
 }
 
 #[cfg(test)]
@@ -580,74 +851,192 @@ mod tests {
         );
     }
 
+    /// Build a structurally valid baseline JPEG stream.
+    ///
+    /// The entropy-coded segment is `payload_len` bytes of filler with a
+    /// stuffed 0xFF 0x00 pair, so the marker walker has to handle it.
+    fn make_test_jpeg(width: u16, height: u16, payload_len: usize) -> Vec<u8> {
+        // SOI
+        let mut jpeg = vec![0xFF, 0xD8];
+        // APP0 segment (contents irrelevant)
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x06, b'J', b'F', b'I', b'F']);
+        // SOF0: length 11, precision 8, height, width, 1 component
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x0B, 0x08]);
+        jpeg.extend_from_slice(&height.to_be_bytes());
+        jpeg.extend_from_slice(&width.to_be_bytes());
+        jpeg.extend_from_slice(&[0x01, 0x01, 0x11, 0x00]);
+        // SOS: length 8, 1 component, spectral selection 0..63
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3F, 0x00]);
+        // Entropy-coded data with a stuffed byte
+        jpeg.extend(std::iter::repeat_n(0x55, payload_len));
+        jpeg.extend_from_slice(&[0xFF, 0x00]);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]); // EOI
+        jpeg
+    }
+
     #[test]
     fn test_scan_for_jpeg_finds_large_jpeg() {
-        // Create a 70KB buffer with JPEG markers after offset 8192
+        // Place a >50KB JPEG at offset 10000 (after 8192) in a 70KB buffer
+        let jpeg = make_test_jpeg(1616, 1080, 55_000);
         let mut bytes = vec![0u8; 70_000];
-        // Place JPEG start marker at offset 10000 (after 8192)
-        bytes[10_000] = 0xFF;
-        bytes[10_001] = 0xD8;
-        // Place JPEG end marker at offset 65000 (gives >50KB JPEG)
-        bytes[65_000] = 0xFF;
-        bytes[65_001] = 0xD9;
+        bytes[10_000..10_000 + jpeg.len()].copy_from_slice(&jpeg);
 
-        let result = scan_for_jpeg(&bytes);
-        assert!(result.is_some());
-        let jpeg = result.unwrap();
-        // Should extract from 10000 to 65002 (inclusive of end marker)
-        assert_eq!(jpeg.len(), 65_002 - 10_000);
-        assert_eq!(jpeg[0], 0xFF);
-        assert_eq!(jpeg[1], 0xD8);
-        assert_eq!(jpeg[jpeg.len() - 2], 0xFF);
-        assert_eq!(jpeg[jpeg.len() - 1], 0xD9);
+        let result = scan_for_jpeg(&bytes).unwrap();
+        assert_eq!(result.data, jpeg);
+        assert_eq!((result.width, result.height), (1616, 1080));
     }
 
     #[test]
     fn test_scan_for_jpeg_ignores_small_jpeg() {
-        // Create buffer with a small JPEG (< 50KB)
+        // A valid JPEG under the 50KB threshold
+        let jpeg = make_test_jpeg(160, 120, 30_000);
         let mut bytes = vec![0u8; 70_000];
-        // Place JPEG start marker at offset 10000
-        bytes[10_000] = 0xFF;
-        bytes[10_001] = 0xD8;
-        // Place JPEG end marker at offset 40000 (gives ~30KB JPEG, under 50KB threshold)
-        bytes[40_000] = 0xFF;
-        bytes[40_001] = 0xD9;
+        bytes[10_000..10_000 + jpeg.len()].copy_from_slice(&jpeg);
 
         let result = scan_for_jpeg(&bytes);
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_scan_for_jpeg_prefers_largest_dimensions() {
+        // A smaller-dimension JPEG followed by a larger one
+        let small = make_test_jpeg(640, 480, 60_000);
+        let large = make_test_jpeg(1616, 1080, 52_000);
+        let mut bytes = vec![0u8; 10_000];
+        bytes.extend_from_slice(&small);
+        bytes.extend_from_slice(&[0u8; 100]);
+        bytes.extend_from_slice(&large);
+        bytes.extend_from_slice(&[0u8; 100]);
+
+        let result = scan_for_jpeg(&bytes).unwrap();
+        assert_eq!((result.width, result.height), (1616, 1080));
+        assert_eq!(result.data, large);
+    }
+
+    #[test]
+    fn test_scan_for_jpeg_skips_truncated_candidate() {
+        // A truncated JPEG (no EOI, cut mid-scan) followed by a complete one
+        let mut truncated = make_test_jpeg(4000, 3000, 60_000);
+        truncated.truncate(truncated.len() - 1_000);
+        let valid = make_test_jpeg(1024, 768, 55_000);
+
+        let mut bytes = vec![0u8; 10_000];
+        bytes.extend_from_slice(&truncated);
+        bytes.extend_from_slice(&valid);
+
+        let result = scan_for_jpeg(&bytes).unwrap();
+        assert_eq!((result.width, result.height), (1024, 768));
+        assert_eq!(result.data, valid);
+    }
+
+    #[test]
+    fn test_scan_for_jpeg_skips_segment_past_eof() {
+        // Segment length pointing past the end of the buffer
+        let mut bytes = vec![0u8; 70_000];
+        bytes[10_000..10_006].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xE1, 0xFF, 0xFF]);
+        bytes[69_998] = JPEG_END[0];
+        bytes[69_999] = JPEG_END[1];
+        assert!(scan_for_jpeg(&bytes).is_none());
+    }
+
+    #[test]
+    fn test_scan_for_jpeg_nested_thumbnail_not_split() {
+        // A preview containing an EXIF-style APP1 with its own SOI..EOI.
+        // The outer JPEG must be returned whole, not cut at the inner EOI.
+        let inner = make_test_jpeg(160, 120, 100);
+        let outer_tail = make_test_jpeg(1616, 1080, 55_000);
+
+        let mut outer = vec![0xFF, 0xD8];
+        let app1_len = (inner.len() + 2) as u16;
+        outer.extend_from_slice(&[0xFF, 0xE1]);
+        outer.extend_from_slice(&app1_len.to_be_bytes());
+        outer.extend_from_slice(&inner);
+        outer.extend_from_slice(&outer_tail[2..]); // Skip the tail's SOI
+
+        let mut bytes = vec![0u8; 10_000];
+        bytes.extend_from_slice(&outer);
+
+        let result = scan_for_jpeg(&bytes).unwrap();
+        assert_eq!(result.data, outer);
+        assert_eq!((result.width, result.height), (1616, 1080));
+    }
+
+    #[test]
+    fn test_scan_for_jpeg_worst_case_is_bounded() {
+        // A buffer full of 0xFF used to degrade the nested scan to O(n^2)
+        let bytes = vec![0xFFu8; 2_000_000];
+        let mut steps = 0;
+        assert!(scan_for_jpeg_counted(&bytes, &mut steps).is_none());
+        assert!(steps <= bytes.len(), "Too many steps: {}", steps);
+
+        // Repeated SOI + SOS headers whose scans run into the next candidate
+        let pattern = [0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02, 0x11, 0x22];
+        let bytes: Vec<u8> = pattern.iter().copied().cycle().take(2_000_000).collect();
+        let mut steps = 0;
+        assert!(scan_for_jpeg_counted(&bytes, &mut steps).is_none());
+        assert!(steps <= bytes.len() * 2, "Too many steps: {}", steps);
+
+        // Open-ended scans are capped by the candidate limit
+        let mut bytes = vec![0x11u8; 2_000_000];
+        for chunk in bytes[8192..].chunks_mut(20_000) {
+            chunk[..6].copy_from_slice(&[0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02]);
+        }
+        let mut steps = 0;
+        assert!(scan_for_jpeg_counted(&bytes, &mut steps).is_none());
+        assert!(
+            steps <= bytes.len() * (MAX_SCAN_CANDIDATES + 1),
+            "Too many steps: {}",
+            steps
+        );
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_marker_scan_reports_dimensions() {
+        // Valid TIFF with an empty IFD0; the preview is only found by scanning
+        let jpeg = make_test_jpeg(1616, 1080, 55_000);
+        let mut file = make_tiff_header_le(8);
+        file.extend_from_slice(&0u16.to_le_bytes()); // 0 entries
+        file.extend_from_slice(&0u32.to_le_bytes()); // no next IFD
+        file.resize(10_000, 0);
+        file.extend_from_slice(&jpeg);
+
+        let (data, location) = extract_raw_thumbnail_with_location(&file).unwrap();
+        assert_eq!(data, jpeg);
+        assert_eq!(
+            location,
+            PreviewLocation::MarkerScan {
+                width: 1616,
+                height: 1080
+            }
+        );
+    }
+
     #[test]
     fn test_scan_for_jpeg_not_found_cases() {
+        let jpeg = make_test_jpeg(1616, 1080, 55_000);
+
         // No start marker
         let mut bytes = vec![0u8; 70_000];
-        bytes[65_000] = JPEG_END[0];
-        bytes[65_001] = JPEG_END[1];
+        bytes[10_000..10_000 + jpeg.len() - 2].copy_from_slice(&jpeg[2..]);
         assert!(scan_for_jpeg(&bytes).is_none(), "Should not find without start marker");
 
         // No end marker
         let mut bytes = vec![0u8; 70_000];
-        bytes[10_000] = JPEG_START[0];
-        bytes[10_001] = JPEG_START[1];
+        bytes[10_000..10_000 + jpeg.len() - 2].copy_from_slice(&jpeg[..jpeg.len() - 2]);
         assert!(scan_for_jpeg(&bytes).is_none(), "Should not find without end marker");
 
         // Start marker before 8192 offset (ignored)
         let mut bytes = vec![0u8; 70_000];
-        bytes[1_000] = JPEG_START[0];
-        bytes[1_001] = JPEG_START[1];
-        bytes[60_000] = JPEG_END[0];
-        bytes[60_001] = JPEG_END[1];
+        bytes[1_000..1_000 + jpeg.len()].copy_from_slice(&jpeg);
         assert!(scan_for_jpeg(&bytes).is_none(), "Should ignore markers before offset 8192");
 
         // Empty input
         assert!(scan_for_jpeg(&[]).is_none(), "Empty input should return None");
 
         // Input smaller than 8192 bytes
+        let small = make_test_jpeg(64, 64, 1_000);
         let mut bytes = vec![0u8; 5_000];
-        bytes[1_000] = JPEG_START[0];
-        bytes[1_001] = JPEG_START[1];
-        bytes[4_000] = JPEG_END[0];
-        bytes[4_001] = JPEG_END[1];
+        bytes[1_000..1_000 + small.len()].copy_from_slice(&small);
         assert!(scan_for_jpeg(&bytes).is_none(), "Small input should return None");
     }
 
@@ -780,7 +1169,7 @@ mod tests {
         let file_bytes = make_file_with_jpeg_at(100, 12, 200);
         let entries = make_jpeg_interchange_entries(100, 12);
 
-        let result = extract_jpeg_from_entries(&entries, &file_bytes);
+        let result = extract_jpeg_from_entries(&entries, &file_bytes, true);
         assert!(result.is_ok());
         let jpeg_data = result.unwrap();
         assert_eq!(jpeg_data.len(), 12);
@@ -831,7 +1220,7 @@ mod tests {
             let file_bytes = make_file_with_jpeg_at(50, 20, 150);
             let entries = make_strip_entries(50, 20, compression);
 
-            let result = extract_jpeg_from_entries(&entries, &file_bytes);
+            let result = extract_jpeg_from_entries(&entries, &file_bytes, true);
             assert!(result.is_ok(), "Failed for compression type {}", compression);
             let jpeg_data = result.unwrap();
             assert_eq!(jpeg_data.len(), 20);
@@ -847,7 +1236,7 @@ mod tests {
         file_bytes[100] = 0x00; // Invalid magic
         let entries = make_jpeg_interchange_entries(100, 50);
         assert!(matches!(
-            extract_jpeg_from_entries(&entries, &file_bytes),
+            extract_jpeg_from_entries(&entries, &file_bytes, true),
             Err(DecodeError::NoThumbnail)
         ));
 
@@ -855,7 +1244,7 @@ mod tests {
         let file_bytes = vec![0u8; 50];
         let entries = make_jpeg_interchange_entries(40, 20);
         assert!(matches!(
-            extract_jpeg_from_entries(&entries, &file_bytes),
+            extract_jpeg_from_entries(&entries, &file_bytes, true),
             Err(DecodeError::NoThumbnail)
         ));
 
@@ -863,7 +1252,7 @@ mod tests {
         let file_bytes = make_file_with_jpeg_at(100, 10, 200);
         let entries = make_jpeg_interchange_entries(100, 0);
         assert!(matches!(
-            extract_jpeg_from_entries(&entries, &file_bytes),
+            extract_jpeg_from_entries(&entries, &file_bytes, true),
             Err(DecodeError::NoThumbnail)
         ));
 
@@ -874,7 +1263,7 @@ mod tests {
             IfdEntry { tag: 0x0101, typ: 3, count: 1, value_offset: 1080 },
         ];
         assert!(matches!(
-            extract_jpeg_from_entries(&entries, &file_bytes),
+            extract_jpeg_from_entries(&entries, &file_bytes, true),
             Err(DecodeError::NoThumbnail)
         ));
     }
@@ -1039,4 +1428,285 @@ mod tests {
         assert_eq!(entries[0].value_offset, 100);
         assert_eq!(next_ifd, 0);
     }
+
+    /// Write an IFD with LONG entries at `offset` in a little-endian file.
+    fn write_ifd_le(file: &mut [u8], offset: usize, entries: &[(u16, u32, u32)], next: u32) {
+        file[offset..offset + 2].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut pos = offset + 2;
+        for &(tag, count, value) in entries {
+            file[pos..pos + 2].copy_from_slice(&tag.to_le_bytes());
+            file[pos + 2..pos + 4].copy_from_slice(&4u16.to_le_bytes()); // type LONG
+            file[pos + 4..pos + 8].copy_from_slice(&count.to_le_bytes());
+            file[pos + 8..pos + 12].copy_from_slice(&value.to_le_bytes());
+            pos += 12;
+        }
+        file[pos..pos + 4].copy_from_slice(&next.to_le_bytes());
+    }
+
+    /// Create a little-endian TIFF of `total_size` bytes with a JPEG at `jpeg_offset`.
+    fn make_tiff_with_jpeg(jpeg_offset: usize, jpeg_length: usize, total_size: usize) -> Vec<u8> {
+        let mut file = make_file_with_jpeg_at(jpeg_offset, jpeg_length, total_size);
+        file[..8].copy_from_slice(&make_tiff_header_le(8));
+        file
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_subifd_array() {
+        // IFD0 has a SubIFDs entry with two offsets; only the second has the JPEG
+        let (jpeg_offset, jpeg_length) = (1000, 12_000);
+        let mut file = make_tiff_with_jpeg(jpeg_offset, jpeg_length, 14_000);
+
+        write_ifd_le(&mut file, 8, &[(TAG_SUBIFD, 2, 100)], 0);
+        // SubIFD offset array at 100
+        file[100..104].copy_from_slice(&200u32.to_le_bytes());
+        file[104..108].copy_from_slice(&300u32.to_le_bytes());
+        // First SubIFD: no preview
+        write_ifd_le(&mut file, 200, &[(0x0100, 1, 1920)], 0);
+        // Second SubIFD: the preview
+        write_ifd_le(
+            &mut file,
+            300,
+            &[
+                (TAG_JPEG_OFFSET, 1, jpeg_offset as u32),
+                (TAG_JPEG_LENGTH, 1, jpeg_length as u32),
+            ],
+            0,
+        );
+
+        let (jpeg, location) = extract_raw_thumbnail_with_location(&file).unwrap();
+        assert_eq!(jpeg.len(), jpeg_length);
+        assert_eq!(
+            location,
+            PreviewLocation::SubIfd {
+                parent: 0,
+                index: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_single_subifd() {
+        let (jpeg_offset, jpeg_length) = (1000, 12_000);
+        let mut file = make_tiff_with_jpeg(jpeg_offset, jpeg_length, 14_000);
+
+        write_ifd_le(&mut file, 8, &[(TAG_SUBIFD, 1, 200)], 0);
+        write_ifd_le(
+            &mut file,
+            200,
+            &[
+                (TAG_JPEG_OFFSET, 1, jpeg_offset as u32),
+                (TAG_JPEG_LENGTH, 1, jpeg_length as u32),
+            ],
+            0,
+        );
+
+        let (_, location) = extract_raw_thumbnail_with_location(&file).unwrap();
+        assert_eq!(
+            location,
+            PreviewLocation::SubIfd {
+                parent: 0,
+                index: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_preview_in_ifd2() {
+        // IFD0 -> IFD1 (no preview) -> IFD2 (preview)
+        let (jpeg_offset, jpeg_length) = (400, 64);
+        let mut file = make_tiff_with_jpeg(jpeg_offset, jpeg_length, 500);
+
+        write_ifd_le(&mut file, 8, &[(0x0100, 1, 1920)], 100);
+        write_ifd_le(&mut file, 100, &[(0x0100, 1, 160)], 200);
+        write_ifd_le(
+            &mut file,
+            200,
+            &[
+                (TAG_JPEG_OFFSET, 1, jpeg_offset as u32),
+                (TAG_JPEG_LENGTH, 1, jpeg_length as u32),
+            ],
+            0,
+        );
+
+        let (jpeg, location) = extract_raw_thumbnail_with_location(&file).unwrap();
+        assert_eq!(jpeg.len(), jpeg_length);
+        assert_eq!(location, PreviewLocation::Ifd(2));
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_circular_ifd_chain() {
+        // IFD0 -> IFD1 -> IFD0 -> ... must terminate
+        let mut file = make_tiff_header_le(8);
+        file.resize(200, 0);
+        write_ifd_le(&mut file, 8, &[(0x0100, 1, 1920)], 100);
+        write_ifd_le(&mut file, 100, &[(0x0100, 1, 160)], 8);
+
+        let result = extract_raw_thumbnail(&file);
+        assert!(
+            matches!(result, Err(DecodeError::NoThumbnail)),
+            "Expected NoThumbnail for circular chain, got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_self_referencing_subifd() {
+        // A SubIFD that points back at IFD0 and a chain that points at itself
+        let mut file = make_tiff_header_le(8);
+        file.resize(200, 0);
+        write_ifd_le(&mut file, 8, &[(TAG_SUBIFD, 1, 8)], 8);
+
+        let result = extract_raw_thumbnail(&file);
+        assert!(matches!(result, Err(DecodeError::NoThumbnail)));
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_location_ifd0() {
+        let (jpeg_offset, jpeg_length) = (100, 32);
+        let mut file = make_tiff_with_jpeg(jpeg_offset, jpeg_length, 200);
+        write_ifd_le(
+            &mut file,
+            8,
+            &[
+                (TAG_JPEG_OFFSET, 1, jpeg_offset as u32),
+                (TAG_JPEG_LENGTH, 1, jpeg_length as u32),
+            ],
+            0,
+        );
+
+        let (_, location) = extract_raw_thumbnail_with_location(&file).unwrap();
+        assert_eq!(location, PreviewLocation::Ifd(0));
+    }
+
+    #[test]
+    fn test_read_subifd_offsets_truncated_array() {
+        // Array claims 4 offsets but only 1.5 fit in the data
+        let mut data = [0u8; 16];
+        data[10..14].copy_from_slice(&0x1234u32.to_le_bytes());
+        let entry = IfdEntry {
+            tag: TAG_SUBIFD,
+            typ: 4,
+            count: 4,
+            value_offset: 10,
+        };
+
+        let mut cursor = Cursor::new(&data[..]);
+        let offsets = read_subifd_offsets(&mut cursor, &entry, true).unwrap();
+        assert_eq!(offsets, vec![0x1234]);
+    }
+
+    /// Entries for a JPEG split into strips whose arrays are out of line.
+    fn make_multi_strip_entries(
+        offsets_at: u32,
+        counts_at: u32,
+        typ: u16,
+        n: u32,
+    ) -> Vec<IfdEntry> {
+        vec![
+            IfdEntry {
+                tag: TAG_COMPRESSION,
+                typ: TYPE_SHORT,
+                count: 1,
+                value_offset: COMPRESSION_JPEG as u32,
+            },
+            IfdEntry {
+                tag: TAG_STRIP_OFFSETS,
+                typ: TYPE_LONG,
+                count: n,
+                value_offset: offsets_at,
+            },
+            IfdEntry {
+                tag: TAG_STRIP_BYTE_COUNTS,
+                typ,
+                count: n,
+                value_offset: counts_at,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_extract_raw_thumbnail_reassembles_strips() {
+        let jpeg = make_test_jpeg(64, 48, 300);
+        let (a, b) = (jpeg.len() / 3, 2 * jpeg.len() / 3);
+        let strips = [&jpeg[..a], &jpeg[a..b], &jpeg[b..]];
+        // Strips stored out of order in the file to check they are joined by index
+        let strip_at = [600u32, 200, 1000];
+
+        let mut file = make_tiff_header_le(8);
+        file.resize(1500, 0);
+        for (strip, &at) in strips.iter().zip(&strip_at) {
+            file[at as usize..at as usize + strip.len()].copy_from_slice(strip);
+        }
+        for (i, (strip, &at)) in strips.iter().zip(&strip_at).enumerate() {
+            file[100 + 4 * i..104 + 4 * i].copy_from_slice(&at.to_le_bytes());
+            file[150 + 4 * i..154 + 4 * i].copy_from_slice(&(strip.len() as u32).to_le_bytes());
+        }
+        write_ifd_le(
+            &mut file,
+            8,
+            &[
+                (TAG_COMPRESSION, 1, COMPRESSION_JPEG as u32),
+                (TAG_STRIP_OFFSETS, 3, 100),
+                (TAG_STRIP_BYTE_COUNTS, 3, 150),
+            ],
+            0,
+        );
+
+        let (extracted, location) = extract_raw_thumbnail_with_location(&file).unwrap();
+        assert_eq!(extracted, jpeg);
+        assert_eq!(location, PreviewLocation::Ifd(0));
+    }
+
+    #[test]
+    fn test_extract_jpeg_from_entries_short_strip_counts() {
+        // Big-endian file with SHORT byte counts: two fit inline, three do not
+        let jpeg = make_test_jpeg(16, 16, 40);
+        let half = jpeg.len() / 2;
+        let mut file = vec![0u8; 400];
+        file[200..200 + half].copy_from_slice(&jpeg[..half]);
+        file[300..300 + jpeg.len() - half].copy_from_slice(&jpeg[half..]);
+        file[100..104].copy_from_slice(&200u32.to_be_bytes());
+        file[104..108].copy_from_slice(&300u32.to_be_bytes());
+        let counts = ((half as u32) << 16) | (jpeg.len() - half) as u32;
+
+        let entries = make_multi_strip_entries(100, counts, TYPE_SHORT, 2);
+        let extracted = extract_jpeg_from_entries(&entries, &file, false).unwrap();
+        assert_eq!(extracted, jpeg);
+
+        let third = jpeg.len() / 3;
+        file[108..112].copy_from_slice(&(200 + 2 * third as u32).to_be_bytes());
+        file[104..108].copy_from_slice(&(200 + third as u32).to_be_bytes());
+        file[200..200 + jpeg.len()].copy_from_slice(&jpeg);
+        let lengths = [third, third, jpeg.len() - 2 * third];
+        for (i, len) in lengths.into_iter().enumerate() {
+            file[150 + 2 * i..152 + 2 * i].copy_from_slice(&(len as u16).to_be_bytes());
+        }
+        let entries = make_multi_strip_entries(100, 150, TYPE_SHORT, 3);
+        let extracted = extract_jpeg_from_entries(&entries, &file, false).unwrap();
+        assert_eq!(extracted, jpeg);
+    }
+
+    #[test]
+    fn test_extract_jpeg_from_entries_rejects_strips_past_eof() {
+        let jpeg = make_test_jpeg(16, 16, 40);
+        let mut file = vec![0u8; 300];
+        file[200..200 + jpeg.len()].copy_from_slice(&jpeg);
+        file[100..104].copy_from_slice(&200u32.to_le_bytes());
+        file[104..108].copy_from_slice(&210u32.to_le_bytes());
+        file[150..154].copy_from_slice(&10u32.to_le_bytes());
+        file[154..158].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let entries = make_multi_strip_entries(100, 150, TYPE_LONG, 2);
+        assert!(matches!(
+            extract_jpeg_from_entries(&entries, &file, true),
+            Err(DecodeError::CorruptedFile(_))
+        ));
+
+        // Byte count array itself pointing past the end
+        let entries = make_multi_strip_entries(100, 296, TYPE_LONG, 2);
+        assert!(matches!(
+            extract_jpeg_from_entries(&entries, &file, true),
+            Err(DecodeError::CorruptedFile(_))
+        ));
+    }
 }
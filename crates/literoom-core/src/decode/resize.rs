@@ -1,9 +1,22 @@
 //! Image resizing functions for thumbnail and preview generation.
 //!
 //! Provides various resize operations using the `image` crate's algorithms.
-//! All functions return new `DecodedImage` instances without modifying the input.
+//! All functions return new images without modifying the input; [`resize`]
+//! and [`resize_to_fit`] take gray images as well as RGB ones.
+//!
+//! Resizing works in horizontal bands of output rows, so peak memory is the
+//! source, the output and a bounded amount of scratch (see
+//! [`resize_banded`]). This keeps stitched panoramas (e.g. 30000×6000)
+//! within the WASM heap.
+
+use super::{DecodeError, DecodedImage, DecodedImageF32, FilterType, PixelBuffer};
+use crate::edit::CropRect;
+use crate::perf;
+use crate::validate::{checked_buffer_len, validate_buffer};
 
-use super::{DecodeError, DecodedImage, FilterType};
+/// Scratch memory [`resize`] and [`resize_to_fit`] may use on top of the
+/// source and output buffers.
+pub const DEFAULT_MAX_SCRATCH_BYTES: usize = 16 * 1024 * 1024;
 
 /// Resize an image to exact dimensions.
 ///
@@ -16,33 +29,180 @@ use super::{DecodeError, DecodedImage, FilterType};
 ///
 /// # Returns
 ///
-/// A new `DecodedImage` with the specified dimensions.
+/// A new image of the same kind with the specified dimensions. Each channel
+/// is resampled on its own, so a gray image resizes exactly like the same
+/// values repeated in an RGB one.
 ///
 /// # Errors
 ///
-/// Returns `DecodeError::InvalidFormat` if the source image cannot be converted.
-pub fn resize(
-    image: &DecodedImage,
+/// Returns `DecodeError::InvalidFormat` if the target size is zero, or
+/// `DecodeError::InvalidImage` if the source is empty or its buffer does not
+/// match its dimensions, or if the target exceeds the image size limits.
+pub fn resize<I: PixelBuffer>(
+    image: &I,
     width: u32,
     height: u32,
     filter: FilterType,
-) -> Result<DecodedImage, DecodeError> {
+) -> Result<I, DecodeError> {
+    resize_banded(image, width, height, filter, DEFAULT_MAX_SCRATCH_BYTES)
+}
+
+/// Resize an image to exact dimensions using at most `max_scratch_bytes` of
+/// scratch memory.
+///
+/// Output rows are produced in bands: each band is sampled vertically into a
+/// float buffer as wide as the source, then horizontally into the output.
+/// The band height is whatever fits in `max_scratch_bytes` (after the filter
+/// weights), but never less than one row, which needs `source width * 4`
+/// bytes per channel. The result is the same as the `image` crate's resampler for any
+/// band height.
+///
+/// # Errors
+///
+/// Same as [`resize`].
+pub fn resize_banded<I: PixelBuffer>(
+    image: &I,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+    max_scratch_bytes: usize,
+) -> Result<I, DecodeError> {
     if width == 0 || height == 0 {
         return Err(DecodeError::InvalidFormat);
     }
+    let channels = I::CHANNELS;
+    let (src_width, src_height) = image.dimensions();
+    let samples = image.samples();
+    validate_buffer(samples.len(), src_width, src_height, channels)?;
+    let out_len = checked_buffer_len(width, height, channels)?;
+
+    let _perf = perf::scope("resize");
+    perf::record_dimensions(src_width, src_height);
+    let output_pixels = width as u64 * height as u64;
+    perf::record_pixels(output_pixels);
+    perf::record_allocation(output_pixels * channels as u64);
 
     // Fast path: if dimensions match, just clone
+    if src_width == width && src_height == height {
+        return Ok(image.clone());
+    }
+
+    let columns = ResampleTaps::new(src_width, width, filter);
+    let rows = ResampleTaps::new(src_height, height, filter);
+
+    let row_len = src_width as usize * channels;
+    let out_row_len = width as usize * channels;
+    let taps_bytes = columns.heap_bytes() + rows.heap_bytes();
+    let row_bytes = row_len * std::mem::size_of::<f32>();
+    let band_rows =
+        (max_scratch_bytes.saturating_sub(taps_bytes) / row_bytes).clamp(1, height as usize);
+    let mut band = vec![0f32; band_rows * row_len];
+
+    let mut pixels = vec![0u8; out_len];
+    for (band_index, out_band) in pixels.chunks_mut(band_rows * out_row_len).enumerate() {
+        let first_row = band_index * band_rows;
+
+        // Vertical pass: source rows -> one float row per output row
+        for (r, acc) in band
+            .chunks_exact_mut(row_len)
+            .take(out_band.len() / out_row_len)
+            .enumerate()
+        {
+            let (start, weights) = rows.get(first_row + r);
+            acc.fill(0.0);
+            for (i, &w) in weights.iter().enumerate() {
+                let src = &samples[(start + i) * row_len..][..row_len];
+                for (a, &p) in acc.iter_mut().zip(src) {
+                    *a += p as f32 * w;
+                }
+            }
+        }
+
+        // Horizontal pass: float rows -> output rows
+        for (src_row, dst_row) in band
+            .chunks_exact(row_len)
+            .zip(out_band.chunks_exact_mut(out_row_len))
+        {
+            for (x, dst) in dst_row.chunks_exact_mut(channels).enumerate() {
+                let (start, weights) = columns.get(x);
+                let mut t = [0f32; 3];
+                for (i, &w) in weights.iter().enumerate() {
+                    let px = &src_row[(start + i) * channels..][..channels];
+                    for (t, &p) in t.iter_mut().zip(px) {
+                        *t += p * w;
+                    }
+                }
+                for (d, v) in dst.iter_mut().zip(t) {
+                    *d = v.clamp(0.0, 255.0).round() as u8;
+                }
+            }
+        }
+    }
+
+    Ok(I::from_samples(width, height, pixels))
+}
+
+/// Resize a float image to exact dimensions.
+///
+/// Uses the same filters as [`resize`], but values are neither clamped nor
+/// rounded, so HDR highlights above 1.0 survive the resample. Lanczos3 can
+/// ring slightly below 0.0 or above the source range at hard edges.
+///
+/// # Errors
+///
+/// Same as [`resize`].
+pub fn resize_f32(
+    image: &DecodedImageF32,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+) -> Result<DecodedImageF32, DecodeError> {
+    if width == 0 || height == 0 {
+        return Err(DecodeError::InvalidFormat);
+    }
+    image.validate()?;
+    let out_len = checked_buffer_len(width, height, 3)?;
+
+    let _perf = perf::scope("resize_f32");
+    perf::record_dimensions(image.width, image.height);
+    let output_pixels = width as u64 * height as u64;
+    perf::record_pixels(output_pixels);
+    perf::record_allocation(output_pixels * 12);
+
     if image.width == width && image.height == height {
         return Ok(image.clone());
     }
 
-    let rgb_image = image
-        .to_rgb_image()
-        .ok_or_else(|| DecodeError::CorruptedFile("Failed to create RgbImage".to_string()))?;
+    let columns = ResampleTaps::new(image.width, width, filter);
+    let rows = ResampleTaps::new(image.height, height, filter);
+    let row_len = image.width as usize * 3;
+    let out_row_len = width as usize * 3;
 
-    let resized = image::imageops::resize(&rgb_image, width, height, filter.to_image_filter());
+    let mut acc = vec![0f32; row_len];
+    let mut pixels = vec![0f32; out_len];
+    for (y, dst_row) in pixels.chunks_exact_mut(out_row_len).enumerate() {
+        // Vertical pass into one source-wide row, then horizontal into the output
+        let (start, weights) = rows.get(y);
+        acc.fill(0.0);
+        for (i, &w) in weights.iter().enumerate() {
+            let src = &image.pixels[(start + i) * row_len..][..row_len];
+            for (a, &p) in acc.iter_mut().zip(src) {
+                *a += p * w;
+            }
+        }
 
-    Ok(DecodedImage::from_rgb_image(resized))
+        for (x, dst) in dst_row.chunks_exact_mut(3).enumerate() {
+            let (start, weights) = columns.get(x);
+            for (i, &w) in weights.iter().enumerate() {
+                let px = &acc[(start + i) * 3..][..3];
+                dst[0] += px[0] * w;
+                dst[1] += px[1] * w;
+                dst[2] += px[2] * w;
+            }
+        }
+    }
+
+    Ok(DecodedImageF32::new(width, height, pixels))
 }
 
 /// Resize an image to fit within a maximum edge length while preserving aspect ratio.
@@ -59,31 +219,52 @@ pub fn resize(
 ///
 /// # Returns
 ///
-/// A new `DecodedImage` that fits within the specified dimensions.
+/// A new image of the same kind that fits within the specified dimensions.
 ///
 /// # Errors
 ///
-/// Returns `DecodeError::InvalidFormat` if the source image cannot be converted.
-pub fn resize_to_fit(
-    image: &DecodedImage,
+/// Returns `DecodeError::InvalidFormat` if `max_edge` is zero, or
+/// `DecodeError::InvalidImage` if the source is empty or its buffer does not
+/// match its dimensions.
+pub fn resize_to_fit<I: PixelBuffer>(
+    image: &I,
     max_edge: u32,
     filter: FilterType,
-) -> Result<DecodedImage, DecodeError> {
+) -> Result<I, DecodeError> {
+    resize_to_fit_banded(image, max_edge, filter, DEFAULT_MAX_SCRATCH_BYTES)
+}
+
+/// [`resize_to_fit`] with an explicit scratch budget; see [`resize_banded`].
+pub fn resize_to_fit_banded<I: PixelBuffer>(
+    image: &I,
+    max_edge: u32,
+    filter: FilterType,
+    max_scratch_bytes: usize,
+) -> Result<I, DecodeError> {
     if max_edge == 0 {
         return Err(DecodeError::InvalidFormat);
     }
+    let (src_width, src_height) = image.dimensions();
+    validate_buffer(image.samples().len(), src_width, src_height, I::CHANNELS)?;
 
-    let (src_width, src_height) = (image.width, image.height);
-
+    let (new_width, new_height) = resize_to_fit_dimensions(src_width, src_height, max_edge);
     // If already fits, just clone
-    if src_width <= max_edge && src_height <= max_edge {
+    if (new_width, new_height) == (src_width, src_height) {
         return Ok(image.clone());
     }
 
-    // Calculate new dimensions preserving aspect ratio
-    let (new_width, new_height) = calculate_fit_dimensions(src_width, src_height, max_edge);
+    resize_banded(image, new_width, new_height, filter, max_scratch_bytes)
+}
 
-    resize(image, new_width, new_height, filter)
+/// Size [`resize_to_fit`] produces for a `width x height` image.
+///
+/// Images that already fit keep their size.
+pub fn resize_to_fit_dimensions(width: u32, height: u32, max_edge: u32) -> (u32, u32) {
+    if width <= max_edge && height <= max_edge {
+        (width, height)
+    } else {
+        calculate_fit_dimensions(width, height, max_edge)
+    }
 }
 
 /// Generate a thumbnail optimized for grid display.
@@ -108,6 +289,579 @@ pub fn generate_thumbnail(image: &DecodedImage, size: u32) -> Result<DecodedImag
     resize_to_fit(image, size, FilterType::Bilinear)
 }
 
+/// How much of a thumbnail pixel's difference from its neighbours
+/// [`generate_thumbnail_detail`] adds back.
+const DETAIL_GAIN: f32 = 1.5;
+
+/// Generate a thumbnail that keeps text and fine lines legible.
+///
+/// Screenshots and scanned documents turn to mush with a plain resample, as
+/// 1px strokes are averaged into the background. This variant starts from
+/// an area average (each thumbnail pixel is the mean of the source pixels it
+/// covers), then adds back local contrast: each pixel moves away from the
+/// mean of its 3x3 neighbourhood. To avoid halos the result never leaves the
+/// range of the source pixels the thumbnail pixel covers, so flat areas and
+/// smooth gradients are unchanged and edges cannot ring.
+///
+/// Sizing is the same as [`generate_thumbnail`].
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidFormat` if `size` is zero, or
+/// `DecodeError::InvalidImage` if the source is empty or its buffer does not
+/// match its dimensions.
+pub fn generate_thumbnail_detail(
+    image: &DecodedImage,
+    size: u32,
+) -> Result<DecodedImage, DecodeError> {
+    if size == 0 {
+        return Err(DecodeError::InvalidFormat);
+    }
+    image.validate()?;
+    if image.width <= size && image.height <= size {
+        return Ok(image.clone());
+    }
+
+    let _perf = perf::scope("generate_thumbnail_detail");
+    perf::record_dimensions(image.width, image.height);
+    let (width, height) = calculate_fit_dimensions(image.width, image.height, size);
+    let output_pixels = width as u64 * height as u64;
+    perf::record_pixels(output_pixels);
+    perf::record_allocation(output_pixels * 3);
+
+    let area = AreaSamples::new(image, width, height);
+    let (w, h) = (width as usize, height as usize);
+    let mut pixels = vec![0u8; checked_buffer_len(width, height, 3)?];
+    for y in 0..h {
+        for x in 0..w {
+            for c in 0..3 {
+                let i = (y * w + x) * 3 + c;
+                let mut sum = 0.0;
+                let mut count = 0.0;
+                for ny in y.saturating_sub(1)..(y + 2).min(h) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(w) {
+                        sum += area.mean[(ny * w + nx) * 3 + c];
+                        count += 1.0;
+                    }
+                }
+                let mean = area.mean[i];
+                let detail = mean + DETAIL_GAIN * (mean - sum / count);
+                let limited = detail.clamp(area.min[i] as f32, area.max[i] as f32);
+                pixels[i] = limited.round() as u8;
+            }
+        }
+    }
+
+    Ok(DecodedImage::new(width, height, pixels))
+}
+
+/// Per-channel mean, minimum and maximum of the source pixels each output
+/// pixel covers, for [`generate_thumbnail_detail`].
+struct AreaSamples {
+    mean: Vec<f32>,
+    min: Vec<u8>,
+    max: Vec<u8>,
+}
+
+impl AreaSamples {
+    fn new(image: &DecodedImage, width: u32, height: u32) -> Self {
+        // Source range [start, end) covered by output pixel `i` along an axis
+        let span = |i: u32, out_len: u32, src_len: u32| {
+            let start = (i as u64 * src_len as u64 / out_len as u64) as usize;
+            let end = ((i as u64 + 1) * src_len as u64 / out_len as u64) as usize;
+            (start, end.max(start + 1))
+        };
+
+        let len = width as usize * height as usize * 3;
+        let mut samples = Self {
+            mean: vec![0.0; len],
+            min: vec![u8::MAX; len],
+            max: vec![0; len],
+        };
+        let src_width = image.width as usize;
+        for y in 0..height {
+            let (y0, y1) = span(y, height, image.height);
+            for x in 0..width {
+                let (x0, x1) = span(x, width, image.width);
+                let i = (y as usize * width as usize + x as usize) * 3;
+                let mut sum = [0u32; 3];
+                for row in y0..y1 {
+                    let src = &image.pixels[(row * src_width + x0) * 3..(row * src_width + x1) * 3];
+                    for px in src.chunks_exact(3) {
+                        for c in 0..3 {
+                            sum[c] += px[c] as u32;
+                            samples.min[i + c] = samples.min[i + c].min(px[c]);
+                            samples.max[i + c] = samples.max[i + c].max(px[c]);
+                        }
+                    }
+                }
+                let count = ((y1 - y0) * (x1 - x0)) as f32;
+                for (mean, sum) in samples.mean[i..i + 3].iter_mut().zip(sum) {
+                    *mean = sum as f32 / count;
+                }
+            }
+        }
+        samples
+    }
+}
+
+/// Filter for the final stage of [`canonical_preview`].
+const CANONICAL_FILTER: FilterType = FilterType::Bilinear;
+
+/// Downscale an image for display the same way at every size.
+///
+/// The grid thumbnail and the editor preview of a photo are made from the
+/// same source at very different sizes. With a resize chain of their own
+/// each, they come out with slightly different contrast and color, so a
+/// photo looks different in the grid than in the editor. Routing both
+/// through this function keeps them consistent: a 2560px preview
+/// downscaled to 256px matches the 256px thumbnail to within a level or
+/// two.
+///
+/// The downscale runs in two stages:
+///
+/// 1. For reductions of 16x or more, blocks of `k x k` source pixels are
+///    averaged, with `k` the largest whole factor that leaves at least
+///    eight times the target size. This does the bulk of the work cheaply
+///    and bounds the filter taps of the second stage.
+/// 2. A triangle (bilinear) resample takes the result to the target size.
+///    Its kernel widens with the remaining ratio, so it antialiases without
+///    the sharpening of Lanczos3, which would compound each time a preview
+///    is downscaled again. With at least 8x left to go, the kernel is wide
+///    enough that the block averaging before it barely shows, and the
+///    result is close to one triangle resample from the source.
+///
+/// Images that already fit within `target_edge` are returned unchanged, as
+/// by [`resize_to_fit`].
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidFormat` if `target_edge` is zero, or
+/// `DecodeError::InvalidImage` if the source is empty or its buffer does not
+/// match its dimensions.
+pub fn canonical_preview(
+    image: &DecodedImage,
+    target_edge: u32,
+) -> Result<DecodedImage, DecodeError> {
+    if target_edge == 0 {
+        return Err(DecodeError::InvalidFormat);
+    }
+    image.validate()?;
+    let (width, height) = resize_to_fit_dimensions(image.width, image.height, target_edge);
+    if (width, height) == (image.width, image.height) {
+        return Ok(image.clone());
+    }
+    canonical_resize(image, width, height)
+}
+
+/// The downscale of [`canonical_preview`] to exact dimensions.
+pub(crate) fn canonical_resize(
+    image: &DecodedImage,
+    width: u32,
+    height: u32,
+) -> Result<DecodedImage, DecodeError> {
+    if width == 0 || height == 0 {
+        return Err(DecodeError::InvalidFormat);
+    }
+    image.validate()?;
+    let _perf = perf::scope("canonical_preview");
+    perf::record_dimensions(image.width, image.height);
+
+    let factor =
+        (image.width / width.saturating_mul(8)).min(image.height / height.saturating_mul(8));
+    if factor < 2 {
+        return resize(image, width, height, CANONICAL_FILTER);
+    }
+    let reduced = box_reduce(image, factor);
+    // The last block in each row and column may be partial; resample only
+    // the part of the reduced image the source covers
+    let covered = CropRect {
+        left: 0.0,
+        top: 0.0,
+        width: image.width as f32 / (reduced.width * factor) as f32,
+        height: image.height as f32 / (reduced.height * factor) as f32,
+    };
+    extract_region_scaled(&reduced, &covered, width, height, CANONICAL_FILTER)
+}
+
+/// Average each `factor x factor` block of pixels into one.
+///
+/// Blocks along the right and bottom edges may be partial; they average the
+/// pixels they have.
+fn box_reduce(image: &DecodedImage, factor: u32) -> DecodedImage {
+    let k = factor as usize;
+    let (w, h) = (image.width as usize, image.height as usize);
+    let (out_w, out_h) = (w.div_ceil(k), h.div_ceil(k));
+    let mut pixels = Vec::with_capacity(out_w * out_h * 3);
+    let mut sums = vec![0u64; out_w * 3];
+    for block_y in 0..out_h {
+        sums.fill(0);
+        let rows = block_y * k..((block_y + 1) * k).min(h);
+        let block_h = rows.len();
+        for y in rows {
+            let row = &image.pixels[y * w * 3..][..w * 3];
+            for (x, px) in row.chunks_exact(3).enumerate() {
+                let sum = &mut sums[x / k * 3..][..3];
+                for (s, &p) in sum.iter_mut().zip(px) {
+                    *s += p as u64;
+                }
+            }
+        }
+        for (block_x, sum) in sums.chunks_exact(3).enumerate() {
+            let block_w = ((block_x + 1) * k).min(w) - block_x * k;
+            let count = (block_w * block_h) as u64;
+            pixels.extend(sum.iter().map(|&s| ((s + count / 2) / count) as u8));
+        }
+    }
+    DecodedImage::new(out_w as u32, out_h as u32, pixels)
+}
+
+/// Extract a region of an image scaled to exact output dimensions.
+///
+/// Equivalent to cropping `src_rect` and resizing the crop to
+/// `out_width x out_height`, but done in one pass: output pixels sample the
+/// source directly, and only the source rows under the region are read. The
+/// zoom view calls this on every pan tick to fill the viewport.
+///
+/// Unlike [`crate::transform::apply_crop`], `src_rect` is not snapped to
+/// whole pixels, so panning by a fraction of a pixel moves the output
+/// smoothly. Filters near the region's edges read the neighboring source
+/// pixels; at the image borders (and for any part of the rect outside the
+/// image) the edge pixels are repeated.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `src_rect` - Region to extract, normalized to the image dimensions
+/// * `out_width` - Output width in pixels
+/// * `out_height` - Output height in pixels
+/// * `filter` - Interpolation filter to use; matches [`resize`]
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidFormat` if the output size is zero or the
+/// rect has a non-positive or non-finite size, or `DecodeError::InvalidImage`
+/// if the source is empty or its buffer does not match its dimensions, or if
+/// the output exceeds the image size limits.
+pub fn extract_region_scaled(
+    image: &DecodedImage,
+    src_rect: &CropRect,
+    out_width: u32,
+    out_height: u32,
+    filter: FilterType,
+) -> Result<DecodedImage, DecodeError> {
+    let valid_size = |v: f32| v.is_finite() && v > 0.0;
+    if out_width == 0
+        || out_height == 0
+        || !valid_size(src_rect.width)
+        || !valid_size(src_rect.height)
+        || !src_rect.left.is_finite()
+        || !src_rect.top.is_finite()
+    {
+        return Err(DecodeError::InvalidFormat);
+    }
+    image.validate()?;
+    let out_len = checked_buffer_len(out_width, out_height, 3)?;
+
+    let _perf = perf::scope("extract_region_scaled");
+    perf::record_dimensions(image.width, image.height);
+    let output_pixels = out_width as u64 * out_height as u64;
+    perf::record_pixels(output_pixels);
+    perf::record_allocation(output_pixels * 3);
+
+    let (src_w, src_h) = (image.width as f64, image.height as f64);
+    let columns = axis_taps(
+        image.width,
+        src_rect.left as f64 * src_w,
+        src_rect.width as f64 * src_w,
+        out_width,
+        filter,
+    );
+    let rows = axis_taps(
+        image.height,
+        src_rect.top as f64 * src_h,
+        src_rect.height as f64 * src_h,
+        out_height,
+        filter,
+    );
+
+    // Horizontal pass over just the source rows the vertical taps touch
+    let first_row = rows.iter().map(|t| t.start).min().unwrap_or(0);
+    let last_row = rows
+        .iter()
+        .map(|t| t.start + t.weights.len())
+        .max()
+        .unwrap_or(0);
+    let out_w = out_width as usize;
+    let band_rows = (last_row - first_row) as u32;
+    let mut horizontal = vec![0f32; checked_buffer_len(out_width, band_rows, 3)?];
+    for (row, y) in (first_row..last_row).enumerate() {
+        let src_row = &image.pixels[y * image.width as usize * 3..][..image.width as usize * 3];
+        let dst_row = &mut horizontal[row * out_w * 3..][..out_w * 3];
+        for (dst, taps) in dst_row.chunks_exact_mut(3).zip(&columns) {
+            let mut acc = [0f32; 3];
+            for (i, &w) in taps.weights.iter().enumerate() {
+                let px = &src_row[(taps.start + i) * 3..][..3];
+                acc[0] += px[0] as f32 * w;
+                acc[1] += px[1] as f32 * w;
+                acc[2] += px[2] as f32 * w;
+            }
+            dst.copy_from_slice(&acc);
+        }
+    }
+
+    // Vertical pass into the output
+    let mut pixels = vec![0u8; out_len];
+    for (dst_row, taps) in pixels.chunks_exact_mut(out_w * 3).zip(&rows) {
+        for (x, dst) in dst_row.chunks_exact_mut(3).enumerate() {
+            let mut acc = [0f32; 3];
+            for (i, &w) in taps.weights.iter().enumerate() {
+                let px = &horizontal[((taps.start - first_row + i) * out_w + x) * 3..][..3];
+                acc[0] += px[0] * w;
+                acc[1] += px[1] * w;
+                acc[2] += px[2] * w;
+            }
+            for (d, v) in dst.iter_mut().zip(acc) {
+                *d = v.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    Ok(DecodedImage::new(out_width, out_height, pixels))
+}
+
+/// Size of an image subsampled by [`subsample`] with the given shift.
+///
+/// Each side is divided by `2^shift`, rounding down, but never below one
+/// pixel.
+pub fn subsampled_dimensions(width: u32, height: u32, shift: u8) -> (u32, u32) {
+    let side = |n: u32| n.checked_shr(shift as u32).unwrap_or(0).max(1);
+    (side(width), side(height))
+}
+
+/// Nearest-neighbour subsample: keep every `2^shift`-th pixel of every
+/// `2^shift`-th row.
+///
+/// Output pixel `(x, y)` is source pixel `(x << shift, y << shift)`, and the
+/// output size is [`subsampled_dimensions`]. No filtering is done, so this
+/// is much cheaper than [`resize`] and meant for previews while a slider is
+/// dragged; aliasing on fine detail is expected. A shift of 0 copies the
+/// image.
+///
+/// # Errors
+///
+/// Returns `DecodeError::InvalidImage` if the source is empty or its buffer
+/// does not match its dimensions.
+pub fn subsample(image: &DecodedImage, shift: u8) -> Result<DecodedImage, DecodeError> {
+    subsample_map(image, shift, |_| {})
+}
+
+/// [`subsample`] that also calls `f` on each output pixel (an RGB triple)
+/// as it is written.
+///
+/// Per-pixel operations run this way read only the sampled source pixels
+/// and make one pass over the small output, with no intermediate image.
+///
+/// # Errors
+///
+/// Same as [`subsample`].
+pub fn subsample_map(
+    image: &DecodedImage,
+    shift: u8,
+    mut f: impl FnMut(&mut [u8]),
+) -> Result<DecodedImage, DecodeError> {
+    image.validate()?;
+    let _perf = perf::scope("subsample");
+    perf::record_dimensions(image.width, image.height);
+    let (width, height) = subsampled_dimensions(image.width, image.height, shift);
+    perf::record_pixels(width as u64 * height as u64);
+
+    // Only x = 0 and y = 0 are read when the shift exceeds the image
+    let step = 1usize.checked_shl(shift as u32).unwrap_or(usize::MAX);
+    let row_len = image.width as usize * 3;
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+    for y in 0..height as usize {
+        let row = &image.pixels[y * step * row_len..][..row_len];
+        for x in 0..width as usize {
+            let start = pixels.len();
+            pixels.extend_from_slice(&row[x * step * 3..][..3]);
+            f(&mut pixels[start..]);
+        }
+    }
+    Ok(DecodedImage::new(width, height, pixels))
+}
+
+/// Source pixels and normalized weights for one output pixel along an axis.
+struct Taps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Compute filter taps for sampling `extent` source pixels from `offset`
+/// into `out_len` output pixels.
+///
+/// Follows the `image` crate's resampler so results match [`resize`]: the
+/// kernel widens with the downscale ratio, and taps outside the source are
+/// dropped with the remaining weights renormalized.
+fn axis_taps(
+    src_len: u32,
+    offset: f64,
+    extent: f64,
+    out_len: u32,
+    filter: FilterType,
+) -> Vec<Taps> {
+    let (support, kernel): (f64, fn(f64) -> f64) = match filter {
+        FilterType::Nearest => (0.0, box_kernel),
+        FilterType::Bilinear => (1.0, triangle_kernel),
+        FilterType::Lanczos3 => (3.0, lanczos3_kernel),
+    };
+    let ratio = extent / out_len as f64;
+    let sratio = ratio.max(1.0);
+    let src_support = support * sratio;
+    let last = src_len as i64 - 1;
+
+    (0..out_len)
+        .map(|out| {
+            let center = offset + (out as f64 + 0.5) * ratio;
+            let left = ((center - src_support).floor() as i64).clamp(0, last);
+            let right = ((center + src_support).ceil() as i64).clamp(left + 1, last + 1);
+
+            let mut weights: Vec<f64> = (left..right)
+                .map(|i| kernel((i as f64 - (center - 0.5)) / sratio))
+                .collect();
+            let sum: f64 = weights.iter().sum();
+            if sum <= 0.0 {
+                // Entirely outside the image: repeat the nearest edge pixel
+                let nearest = ((center - 0.5).round() as i64).clamp(0, last);
+                return Taps {
+                    start: nearest as usize,
+                    weights: vec![1.0],
+                };
+            }
+            weights.iter_mut().for_each(|w| *w /= sum);
+
+            Taps {
+                start: left as usize,
+                weights: weights.into_iter().map(|w| w as f32).collect(),
+            }
+        })
+        .collect()
+}
+
+fn box_kernel(x: f64) -> f64 {
+    if x.abs() <= 0.5 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn triangle_kernel(x: f64) -> f64 {
+    (1.0 - x.abs()).max(0.0)
+}
+
+fn lanczos3_kernel(x: f64) -> f64 {
+    let sinc = |x: f64| {
+        if x == 0.0 {
+            1.0
+        } else {
+            let a = x * std::f64::consts::PI;
+            a.sin() / a
+        }
+    };
+    if x.abs() < 3.0 {
+        sinc(x) * sinc(x / 3.0)
+    } else {
+        0.0
+    }
+}
+
+/// Filter taps for every output pixel along one axis of [`resize_banded`].
+///
+/// Computed exactly as the `image` crate's resampler does (in `f32`, with
+/// the same clamping and normalization) so the output matches it bit for
+/// bit. Weights are stored flat to keep per-pixel allocations out of the
+/// scratch budget.
+struct ResampleTaps {
+    /// First source index for each output pixel
+    starts: Vec<u32>,
+    /// Output `i` uses `weights[offsets[i]..offsets[i + 1]]`
+    offsets: Vec<u32>,
+    weights: Vec<f32>,
+}
+
+impl ResampleTaps {
+    fn new(src_len: u32, out_len: u32, filter: FilterType) -> Self {
+        let (support, kernel): (f32, fn(f32) -> f32) = match filter {
+            FilterType::Nearest => (0.0, |_| 1.0),
+            FilterType::Bilinear => (1.0, |x| (1.0 - x.abs()).max(0.0)),
+            FilterType::Lanczos3 => (3.0, lanczos3_kernel_f32),
+        };
+        let ratio = src_len as f32 / out_len as f32;
+        let sratio = if ratio < 1.0 { 1.0 } else { ratio };
+        let src_support = support * sratio;
+        let last = src_len as i64 - 1;
+
+        let mut starts = Vec::with_capacity(out_len as usize);
+        let mut offsets = Vec::with_capacity(out_len as usize + 1);
+        let mut weights = Vec::new();
+        offsets.push(0);
+        for out in 0..out_len {
+            let input = (out as f32 + 0.5) * ratio;
+            let left = ((input - src_support).floor() as i64).clamp(0, last);
+            let right = ((input + src_support).ceil() as i64).clamp(left + 1, last + 1);
+            // The kernel treats the centre of a pixel as 0
+            let input = input - 0.5;
+
+            let first = weights.len();
+            let mut sum = 0.0;
+            for i in left..right {
+                let w = kernel((i as f32 - input) / sratio);
+                weights.push(w);
+                sum += w;
+            }
+            for w in &mut weights[first..] {
+                *w /= sum;
+            }
+            starts.push(left as u32);
+            offsets.push(weights.len() as u32);
+        }
+
+        Self {
+            starts,
+            offsets,
+            weights,
+        }
+    }
+
+    /// First source index and weights for output pixel `i`.
+    fn get(&self, i: usize) -> (usize, &[f32]) {
+        let range = self.offsets[i] as usize..self.offsets[i + 1] as usize;
+        (self.starts[i] as usize, &self.weights[range])
+    }
+
+    fn heap_bytes(&self) -> usize {
+        (self.starts.capacity() + self.offsets.capacity()) * std::mem::size_of::<u32>()
+            + self.weights.capacity() * std::mem::size_of::<f32>()
+    }
+}
+
+fn lanczos3_kernel_f32(x: f32) -> f32 {
+    let sinc = |x: f32| {
+        if x == 0.0 {
+            1.0
+        } else {
+            let a = x * std::f32::consts::PI;
+            a.sin() / a
+        }
+    };
+    if x.abs() < 3.0 {
+        sinc(x) * sinc(x / 3.0)
+    } else {
+        0.0
+    }
+}
+
 /// Calculate dimensions to fit within max_edge while preserving aspect ratio.
 fn calculate_fit_dimensions(width: u32, height: u32, max_edge: u32) -> (u32, u32) {
     if width == 0 || height == 0 {
@@ -125,23 +879,15 @@ fn calculate_fit_dimensions(width: u32, height: u32, max_edge: u32) -> (u32, u32
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    fn create_test_image(width: u32, height: u32) -> DecodedImage {
-        // Create a simple gradient image for testing
-        let mut pixels = Vec::with_capacity((width * height * 3) as usize);
-        for y in 0..height {
-            for x in 0..width {
-                pixels.push(((x * 255) / width.max(1)) as u8); // R
-                pixels.push(((y * 255) / height.max(1)) as u8); // G
-                pixels.push(128); // B
-            }
-        }
-        DecodedImage::new(width, height, pixels)
-    }
+    use crate::decode::GrayImage;
+    use crate::testing::{
+        checkerboard, color_bars, gradient, noise, slanted_edge, verify_consistency,
+        GradientDirection,
+    };
 
     #[test]
     fn test_resize_basic() {
-        let img = create_test_image(100, 50);
+        let img = gradient(100, 50, GradientDirection::Diagonal);
         let resized = resize(&img, 50, 25, FilterType::Bilinear).unwrap();
 
         assert_eq!(resized.width, 50);
@@ -151,7 +897,7 @@ mod tests {
 
     #[test]
     fn test_resize_same_dimensions() {
-        let img = create_test_image(100, 50);
+        let img = gradient(100, 50, GradientDirection::Diagonal);
         let resized = resize(&img, 100, 50, FilterType::Bilinear).unwrap();
 
         assert_eq!(resized.width, 100);
@@ -160,7 +906,7 @@ mod tests {
 
     #[test]
     fn test_resize_upscale() {
-        let img = create_test_image(50, 25);
+        let img = gradient(50, 25, GradientDirection::Diagonal);
         let resized = resize(&img, 100, 50, FilterType::Lanczos3).unwrap();
 
         assert_eq!(resized.width, 100);
@@ -169,7 +915,7 @@ mod tests {
 
     #[test]
     fn test_resize_zero_dimensions_error() {
-        let img = create_test_image(100, 50);
+        let img = gradient(100, 50, GradientDirection::Diagonal);
 
         assert!(resize(&img, 0, 50, FilterType::Bilinear).is_err());
         assert!(resize(&img, 50, 0, FilterType::Bilinear).is_err());
@@ -177,7 +923,7 @@ mod tests {
 
     #[test]
     fn test_resize_to_fit_landscape() {
-        let img = create_test_image(6000, 4000);
+        let img = gradient(6000, 4000, GradientDirection::Diagonal);
         let resized = resize_to_fit(&img, 2560, FilterType::Lanczos3).unwrap();
 
         // Width should be 2560, height scaled proportionally
@@ -187,7 +933,7 @@ mod tests {
 
     #[test]
     fn test_resize_to_fit_portrait() {
-        let img = create_test_image(4000, 6000);
+        let img = gradient(4000, 6000, GradientDirection::Diagonal);
         let resized = resize_to_fit(&img, 2560, FilterType::Lanczos3).unwrap();
 
         // Height should be 2560, width scaled proportionally
@@ -197,7 +943,7 @@ mod tests {
 
     #[test]
     fn test_resize_to_fit_square() {
-        let img = create_test_image(4000, 4000);
+        let img = gradient(4000, 4000, GradientDirection::Diagonal);
         let resized = resize_to_fit(&img, 256, FilterType::Bilinear).unwrap();
 
         assert_eq!(resized.width, 256);
@@ -206,7 +952,7 @@ mod tests {
 
     #[test]
     fn test_resize_to_fit_already_smaller() {
-        let img = create_test_image(100, 50);
+        let img = gradient(100, 50, GradientDirection::Diagonal);
         let resized = resize_to_fit(&img, 256, FilterType::Bilinear).unwrap();
 
         // Should return same dimensions when already smaller
@@ -216,13 +962,93 @@ mod tests {
 
     #[test]
     fn test_resize_to_fit_zero_max_edge_error() {
-        let img = create_test_image(100, 50);
+        let img = gradient(100, 50, GradientDirection::Diagonal);
         assert!(resize_to_fit(&img, 0, FilterType::Bilinear).is_err());
     }
 
+    /// The `image` crate's resampler, which `resize_banded` reproduces.
+    fn reference_resize(
+        img: &DecodedImage,
+        width: u32,
+        height: u32,
+        filter: FilterType,
+    ) -> Vec<u8> {
+        let rgb = img.to_rgb_image().unwrap();
+        image::imageops::resize(&rgb, width, height, filter.to_image_filter()).into_raw()
+    }
+
+    #[test]
+    fn test_resize_matches_image_crate() {
+        let img = noise(37, 23, 7);
+        for filter in [
+            FilterType::Nearest,
+            FilterType::Bilinear,
+            FilterType::Lanczos3,
+        ] {
+            for (w, h) in [(10, 6), (37, 9), (80, 51), (5, 40), (1, 1)] {
+                let resized = resize(&img, w, h, filter).unwrap();
+                assert_eq!(
+                    resized.pixels,
+                    reference_resize(&img, w, h, filter),
+                    "{:?} {}x{}",
+                    filter,
+                    w,
+                    h
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_resize_banded_output_independent_of_band_size() {
+        // Wide strip so that small budgets split the output into many bands
+        let img = noise(600, 40, 3);
+        let expected = reference_resize(&img, 150, 17, FilterType::Lanczos3);
+        for scratch in [0, 600 * 12, 600 * 12 * 5, usize::MAX] {
+            let resized = resize_banded(&img, 150, 17, FilterType::Lanczos3, scratch).unwrap();
+            assert_eq!(resized.pixels, expected, "scratch {}", scratch);
+        }
+    }
+
+    #[test]
+    fn test_gray_resize_matches_rgb_channel() {
+        let gray = noise(120, 45, 4).to_gray(crate::luminance::LUMA_WEIGHTS);
+        let rgb = gray.to_rgb();
+        for filter in [
+            FilterType::Nearest,
+            FilterType::Bilinear,
+            FilterType::Lanczos3,
+        ] {
+            for scratch in [0, DEFAULT_MAX_SCRATCH_BYTES] {
+                let small = resize_banded(&gray, 37, 20, filter, scratch).unwrap();
+                let expected = resize_banded(&rgb, 37, 20, filter, scratch).unwrap();
+                let red: Vec<u8> = expected.pixels.iter().step_by(3).copied().collect();
+                assert_eq!(small.pixels, red, "{:?}, scratch {}", filter, scratch);
+            }
+        }
+
+        let fit = resize_to_fit(&gray, 60, FilterType::Bilinear).unwrap();
+        assert_eq!((fit.width, fit.height, fit.pixels.len()), (60, 23, 60 * 23));
+        let short = GrayImage {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 3],
+        };
+        assert!(resize(&short, 1, 1, FilterType::Nearest).is_err());
+    }
+
+    #[test]
+    fn test_resize_to_fit_banded_matches_resize_to_fit() {
+        let img = checkerboard(300, 60, 7);
+        let banded = resize_to_fit_banded(&img, 64, FilterType::Bilinear, 0).unwrap();
+        let plain = resize_to_fit(&img, 64, FilterType::Bilinear).unwrap();
+        assert_eq!((banded.width, banded.height), (64, 13));
+        assert_eq!(banded.pixels, plain.pixels);
+    }
+
     #[test]
     fn test_generate_thumbnail() {
-        let img = create_test_image(6000, 4000);
+        let img = gradient(6000, 4000, GradientDirection::Diagonal);
         let thumb = generate_thumbnail(&img, 256).unwrap();
 
         // Should fit within 256x256
@@ -232,9 +1058,135 @@ mod tests {
         assert!(thumb.width == 256 || thumb.height == 256);
     }
 
+    /// White page with a grid of 1px black lines every `period` pixels.
+    fn line_grid(size: u32, period: u32) -> DecodedImage {
+        let mut pixels = vec![255u8; (size * size * 3) as usize];
+        for y in 0..size {
+            for x in 0..size {
+                if x % period == 0 || y % period == 0 {
+                    let i = ((y * size + x) * 3) as usize;
+                    pixels[i..i + 3].fill(0);
+                }
+            }
+        }
+        DecodedImage::new(size, size, pixels)
+    }
+
+    /// Dark local minima along the middle row that stand out from both
+    /// neighbours by at least `threshold`.
+    fn distinguishable_lines(image: &DecodedImage, threshold: u8) -> usize {
+        let y = image.height / 2 + 1;
+        let row: Vec<u8> = (0..image.width)
+            .map(|x| image.pixels[((y * image.width + x) * 3) as usize])
+            .collect();
+        row.windows(3)
+            .filter(|w| w[0].min(w[2]).saturating_sub(w[1]) >= threshold)
+            .count()
+    }
+
+    /// Largest amount any output pixel overshoots the range of the source
+    /// pixels around it.
+    fn max_overshoot(source: &DecodedImage, output: &DecodedImage) -> u8 {
+        let (sx, sy) = (
+            source.width as f32 / output.width as f32,
+            source.height as f32 / output.height as f32,
+        );
+        let mut worst = 0;
+        for y in 0..output.height {
+            for x in 0..output.width {
+                let x0 = ((x as f32 - 1.0) * sx).max(0.0) as u32;
+                let x1 = (((x + 2) as f32 * sx) as u32).min(source.width);
+                let y0 = ((y as f32 - 1.0) * sy).max(0.0) as u32;
+                let y1 = (((y + 2) as f32 * sy) as u32).min(source.height);
+                for c in 0..3 {
+                    let (mut lo, mut hi) = (u8::MAX, 0);
+                    for v in y0..y1 {
+                        for u in x0..x1 {
+                            let p = source.pixels[((v * source.width + u) * 3 + c) as usize];
+                            lo = lo.min(p);
+                            hi = hi.max(p);
+                        }
+                    }
+                    let p = output.pixels[((y * output.width + x) * 3 + c) as usize];
+                    worst = worst.max(p.saturating_sub(hi)).max(lo.saturating_sub(p));
+                }
+            }
+        }
+        worst
+    }
+
+    #[test]
+    fn test_detail_thumbnail_keeps_fine_lines() {
+        let source = line_grid(1024, 12);
+        let detail = generate_thumbnail_detail(&source, 256).unwrap();
+        let standard = generate_thumbnail(&source, 256).unwrap();
+        let lanczos = resize_to_fit(&source, 256, FilterType::Lanczos3).unwrap();
+        assert_eq!((detail.width, detail.height), (256, 256));
+
+        let lines = |img: &DecodedImage| distinguishable_lines(img, 64);
+        assert!(lines(&detail) > lines(&standard));
+        assert!(lines(&detail) > lines(&lanczos));
+    }
+
+    #[test]
+    fn test_detail_thumbnail_does_not_ring() {
+        for source in [
+            color_bars(800, 400),
+            noise(600, 600, 9),
+            slanted_edge(640, 480, 5.0),
+        ] {
+            let detail = generate_thumbnail_detail(&source, 128).unwrap();
+            let lanczos = resize_to_fit(&source, 128, FilterType::Lanczos3).unwrap();
+            assert!(max_overshoot(&source, &detail) <= max_overshoot(&source, &lanczos));
+            assert!(max_overshoot(&source, &detail) <= 2);
+        }
+
+        // Flat areas are untouched
+        let flat = DecodedImage::new(400, 300, vec![90; 400 * 300 * 3]);
+        let thumb = generate_thumbnail_detail(&flat, 100).unwrap();
+        assert!(thumb.pixels.iter().all(|&v| v == 90));
+    }
+
+    #[test]
+    fn test_canonical_preview_sizes() {
+        let source = gradient(300, 200, GradientDirection::Diagonal);
+        let preview = canonical_preview(&source, 120).unwrap();
+        assert_eq!((preview.width, preview.height), (120, 80));
+        assert_eq!(
+            canonical_preview(&source, 300).unwrap().pixels,
+            source.pixels
+        );
+        assert!(canonical_preview(&source, 0).is_err());
+
+        // Flat images stay flat through both stages
+        let flat = DecodedImage::new(2000, 1000, vec![77; 2000 * 1000 * 3]);
+        let thumb = canonical_preview(&flat, 50).unwrap();
+        assert_eq!((thumb.width, thumb.height), (50, 25));
+        assert!(thumb.pixels.iter().all(|&v| v == 77));
+    }
+
+    #[test]
+    fn test_downscaled_preview_matches_thumbnail() {
+        // A tenth of the preview's size, as 256px thumbnails are of 2560px
+        // previews; the thumbnail takes the two-stage path
+        for source in [
+            gradient(2400, 1600, GradientDirection::Diagonal),
+            color_bars(2400, 1600),
+            slanted_edge(2400, 1600, 5.0),
+            checkerboard(2400, 1600, 45),
+            noise(2401, 1597, 4),
+        ] {
+            let preview = canonical_preview(&source, 1280).unwrap();
+            let thumbnail = canonical_preview(&source, 128).unwrap();
+            let report = verify_consistency(&preview, &thumbnail);
+            assert!(report.mean_error < 1.0, "{:?}", report);
+            assert!(report.max_error < 4, "{:?}", report);
+        }
+    }
+
     #[test]
     fn test_generate_thumbnail_small_image() {
-        let img = create_test_image(100, 50);
+        let img = gradient(100, 50, GradientDirection::Diagonal);
         let thumb = generate_thumbnail(&img, 256).unwrap();
 
         // Small images should not be upscaled
@@ -272,7 +1224,7 @@ mod tests {
 
     #[test]
     fn test_all_filter_types() {
-        let img = create_test_image(100, 50);
+        let img = gradient(100, 50, GradientDirection::Diagonal);
 
         for filter in [
             FilterType::Nearest,
@@ -284,4 +1236,226 @@ mod tests {
             assert_eq!(resized.height, 25);
         }
     }
+
+    #[test]
+    fn test_downscale_preserves_mean_brightness() {
+        let board = checkerboard(64, 64, 8);
+        let mean = |img: &DecodedImage| {
+            img.pixels.iter().map(|&v| v as f64).sum::<f64>() / img.pixels.len() as f64
+        };
+
+        for filter in [FilterType::Bilinear, FilterType::Lanczos3] {
+            let resized = resize(&board, 32, 32, filter).unwrap();
+            assert!((mean(&resized) - mean(&board)).abs() < 2.0, "{:?}", filter);
+        }
+    }
+
+    /// Normalized rect for a rectangle given in `image` pixels.
+    fn pixel_rect(image: &DecodedImage, x: f32, y: f32, w: f32, h: f32) -> CropRect {
+        CropRect {
+            left: x / image.width as f32,
+            top: y / image.height as f32,
+            width: w / image.width as f32,
+            height: h / image.height as f32,
+        }
+    }
+
+    fn max_diff(a: &[u8], b: &[u8]) -> u8 {
+        a.iter().zip(b).map(|(x, y)| x.abs_diff(*y)).max().unwrap()
+    }
+
+    #[test]
+    fn test_region_matches_crop_then_resize() {
+        let img = gradient(300, 200, GradientDirection::Diagonal);
+        // (x, y, w, h, out_w, out_h): downscale, upscale, and 1:1
+        let cases = [
+            (40, 30, 120, 80, 60, 40),
+            (100, 50, 30, 20, 90, 60),
+            (0, 0, 300, 200, 150, 100),
+            (10, 20, 64, 64, 64, 64),
+        ];
+        for filter in [
+            FilterType::Nearest,
+            FilterType::Bilinear,
+            FilterType::Lanczos3,
+        ] {
+            for (x, y, w, h, out_w, out_h) in cases {
+                let crop = crate::transform::apply_crop(
+                    &img,
+                    x as f64 / 300.0,
+                    y as f64 / 200.0,
+                    w as f64 / 300.0,
+                    h as f64 / 200.0,
+                )
+                .unwrap();
+                let expected = resize(&crop, out_w, out_h, filter).unwrap();
+
+                let rect = pixel_rect(&img, x as f32, y as f32, w as f32, h as f32);
+                let region = extract_region_scaled(&img, &rect, out_w, out_h, filter).unwrap();
+
+                assert_eq!((region.width, region.height), (out_w, out_h));
+                let diff = max_diff(&region.pixels, &expected.pixels);
+                assert!(diff <= 1, "{:?} {:?}: diff {}", filter, (x, y, w, h), diff);
+            }
+        }
+    }
+
+    #[test]
+    fn test_region_honors_subpixel_offset() {
+        let img = noise(64, 64, 4);
+        let aligned = pixel_rect(&img, 10.0, 10.0, 32.0, 32.0);
+        let shifted = pixel_rect(&img, 10.5, 10.0, 32.0, 32.0);
+
+        let a = extract_region_scaled(&img, &aligned, 32, 32, FilterType::Bilinear).unwrap();
+        let b = extract_region_scaled(&img, &shifted, 32, 32, FilterType::Bilinear).unwrap();
+        assert_ne!(a.pixels, b.pixels);
+
+        // At 1:1 the aligned rect is an exact copy of the source
+        for row in 0..32 {
+            let src = offset(&img, 10, 10 + row);
+            let dst = row as usize * 96;
+            assert_eq!(a.pixels[dst..dst + 96], img.pixels[src..src + 96]);
+        }
+        // Half a pixel to the right averages horizontal neighbors
+        let left = img.pixels[offset(&img, 10, 10)] as f32;
+        let right = img.pixels[offset(&img, 11, 10)] as f32;
+        assert!(b.pixels[0].abs_diff(((left + right) / 2.0).round() as u8) <= 1);
+    }
+
+    /// Byte offset of pixel (x, y).
+    fn offset(image: &DecodedImage, x: u32, y: u32) -> usize {
+        (y * image.width + x) as usize * 3
+    }
+
+    #[test]
+    fn test_region_at_edges_stays_in_bounds() {
+        let img = noise(50, 40, 5);
+        let rects = [
+            pixel_rect(&img, 0.0, 0.0, 10.0, 10.0),
+            pixel_rect(&img, 40.5, 30.5, 9.5, 9.5),
+            pixel_rect(&img, 45.0, 35.0, 20.0, 20.0),
+            pixel_rect(&img, -5.0, -5.0, 60.0, 50.0),
+            pixel_rect(&img, 100.0, 100.0, 5.0, 5.0),
+        ];
+        for rect in &rects {
+            for filter in [
+                FilterType::Nearest,
+                FilterType::Bilinear,
+                FilterType::Lanczos3,
+            ] {
+                for (w, h) in [(7, 5), (40, 40)] {
+                    let region = extract_region_scaled(&img, rect, w, h, filter).unwrap();
+                    assert_eq!(region.pixels.len(), (w * h * 3) as usize);
+                }
+            }
+        }
+
+        // Fully outside to the bottom right repeats the corner pixel
+        let corner = &img.pixels[offset(&img, 49, 39)..][..3];
+        let region = extract_region_scaled(&img, &rects[4], 3, 3, FilterType::Bilinear).unwrap();
+        assert!(region.pixels.chunks(3).all(|p| p == corner));
+    }
+
+    #[test]
+    fn test_region_invalid_arguments() {
+        let img = noise(10, 10, 6);
+        let rect = pixel_rect(&img, 0.0, 0.0, 5.0, 5.0);
+        let bad_size = CropRect { width: 0.0, ..rect };
+        let bad_pos = CropRect {
+            left: f32::NAN,
+            ..rect
+        };
+
+        assert!(extract_region_scaled(&img, &rect, 0, 5, FilterType::Bilinear).is_err());
+        assert!(extract_region_scaled(&img, &bad_size, 5, 5, FilterType::Bilinear).is_err());
+        assert!(extract_region_scaled(&img, &bad_pos, 5, 5, FilterType::Bilinear).is_err());
+
+        let empty = DecodedImage::new(0, 0, vec![]);
+        assert!(matches!(
+            extract_region_scaled(&empty, &rect, 5, 5, FilterType::Bilinear),
+            Err(DecodeError::InvalidImage(_))
+        ));
+    }
+
+    #[test]
+    fn test_resize_reports_output_pixels() {
+        let img = gradient(100, 50, GradientDirection::Diagonal);
+        resize(&img, 40, 20, FilterType::Bilinear).unwrap();
+
+        let stats = perf::last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "resize");
+        assert_eq!(stats.pixels_processed, 40 * 20);
+        assert_eq!(stats.bytes_allocated, 40 * 20 * 3);
+    }
+
+    #[test]
+    fn test_nested_operations_aggregate_once() {
+        let img = gradient(100, 50, GradientDirection::Diagonal);
+
+        // resize_to_fit calls resize internally; both must count once
+        resize_to_fit(&img, 50, FilterType::Bilinear).unwrap();
+        let stats = perf::last_operation_stats().unwrap();
+        assert_eq!(stats.pixels_processed, 50 * 25);
+
+        // A pipeline scope wrapping several operations reports their sum
+        {
+            let _scope = perf::scope("pipeline");
+            let mut resized = resize(&img, 50, 25, FilterType::Bilinear).unwrap();
+            let mut adj = crate::BasicAdjustments::default();
+            adj.exposure = 1.0;
+            crate::adjustments::apply_all_adjustments(&mut resized.pixels, &adj);
+        }
+        let stats = perf::last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "pipeline");
+        assert_eq!(stats.pixels_processed, 50 * 25 * 2);
+        assert_eq!(stats.bytes_allocated, 50 * 25 * 3);
+    }
+
+    #[test]
+    fn test_resize_f32_matches_u8_and_keeps_headroom() {
+        use crate::decode::ToneMap;
+
+        let img = gradient(64, 48, GradientDirection::Diagonal);
+        let resized = resize_f32(&img.to_f32(), 20, 15, FilterType::Bilinear).unwrap();
+        let expected = resize(&img, 20, 15, FilterType::Bilinear).unwrap();
+        assert_eq!(resized.to_u8(ToneMap::Clamp).pixels, expected.pixels);
+
+        // Values above 1.0 are averaged, not clipped
+        let bright = DecodedImageF32::new(4, 4, vec![3.0; 4 * 4 * 3]);
+        let resized = resize_f32(&bright, 2, 2, FilterType::Lanczos3).unwrap();
+        assert!(resized.pixels.iter().all(|&v| (v - 3.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_subsample_picks_every_nth_pixel() {
+        let img = noise(13, 7, 2);
+        let small = subsample(&img, 1).unwrap();
+        assert_eq!((small.width, small.height), (6, 3));
+        for (x, y, px) in small.enumerate_pixels() {
+            assert_eq!(Some(px), img.pixel(x * 2, y * 2));
+        }
+
+        assert_eq!(subsample(&img, 0).unwrap().pixels, img.pixels);
+    }
+
+    #[test]
+    fn test_subsampled_dimensions_never_reach_zero() {
+        assert_eq!(subsampled_dimensions(1001, 667, 2), (250, 166));
+        assert_eq!(subsampled_dimensions(5, 3, 2), (1, 1));
+        assert_eq!(subsampled_dimensions(5, 3, 200), (1, 1));
+
+        let img = noise(5, 3, 1);
+        let small = subsample(&img, 40).unwrap();
+        assert_eq!(small.pixels, img.pixels[..3]);
+    }
+
+    #[test]
+    fn test_subsample_rejects_invalid_buffer() {
+        let broken = DecodedImage {
+            width: 4,
+            height: 4,
+            pixels: vec![0u8; 5],
+        };
+        assert!(subsample(&broken, 1).is_err());
+    }
 }
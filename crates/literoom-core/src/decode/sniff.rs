@@ -0,0 +1,70 @@
+//! Recognizing the image formats decode reads from a file's leading bytes.
+//!
+//! Probing, classification, automatic decoding and opening all dispatch on
+//! the same check, so a file one of them accepts is accepted by the others.
+
+use super::heif::is_heif_file;
+use super::raw_thumbnail::is_raw_file;
+
+pub(crate) const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Image format recognized by [`sniff_image_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImageFormat {
+    /// JPEG (starts with SOI)
+    Jpeg,
+    /// PNG
+    Png,
+    /// TIFF container, which covers most RAW formats
+    Tiff,
+    /// HEIC/HEIF
+    Heif,
+}
+
+/// Recognize an image format from the leading bytes of a file.
+///
+/// Returns `None` for anything decode cannot read.
+pub(crate) fn sniff_image_format(bytes: &[u8]) -> Option<ImageFormat> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        Some(ImageFormat::Jpeg)
+    } else if bytes.starts_with(&PNG_SIGNATURE) {
+        Some(ImageFormat::Png)
+    } else if is_raw_file(bytes) {
+        Some(ImageFormat::Tiff)
+    } else if is_heif_file(bytes) {
+        Some(ImageFormat::Heif)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_image_format() {
+        assert_eq!(
+            sniff_image_format(&[0xFF, 0xD8, 0xFF]),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(sniff_image_format(&PNG_SIGNATURE), Some(ImageFormat::Png));
+        assert_eq!(
+            sniff_image_format(b"II*\0\x08\0\0\0"),
+            Some(ImageFormat::Tiff)
+        );
+        assert_eq!(
+            sniff_image_format(b"MM\0*\0\0\0\x08"),
+            Some(ImageFormat::Tiff)
+        );
+
+        let mut heif = 16u32.to_be_bytes().to_vec();
+        heif.extend_from_slice(b"ftypheic\0\0\0\0");
+        assert_eq!(sniff_image_format(&heif), Some(ImageFormat::Heif));
+
+        // TIFF magic needs the full four bytes in either byte order
+        assert_eq!(sniff_image_format(b"II\0*"), None);
+        assert_eq!(sniff_image_format(b"GIF89a"), None);
+        assert_eq!(sniff_image_format(&[]), None);
+    }
+}
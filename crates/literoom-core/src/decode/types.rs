@@ -1,5 +1,8 @@
 //! Core types for image decoding.
 
+use super::classify::FileType;
+use super::icc::{convert_to_srgb, ColorProfile};
+use crate::luminance::calculate_luminance;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -33,6 +36,87 @@ pub enum DecodeError {
     /// No embedded thumbnail found in RAW file.
     #[error("No embedded thumbnail found")]
     NoThumbnail,
+
+    /// No HDR gain map found in the JPEG.
+    #[error("No gain map found")]
+    NoGainMap,
+
+    /// The image data uses a compression codec we cannot decode (e.g. HEVC).
+    #[error("Unsupported codec: {0}")]
+    UnsupportedCodec(String),
+
+    /// The JPEG uses a coding variant the decoder cannot handle (12-bit
+    /// samples, arithmetic coding, lossless or hierarchical coding).
+    #[error("Unsupported JPEG variant: {detail}")]
+    UnsupportedVariant {
+        /// What about the file is unsupported
+        detail: String,
+    },
+
+    /// The requested rows or region reach outside the stored image.
+    #[error("Region outside the {width}x{height} image")]
+    RegionOutOfBounds {
+        /// Stored width of the image
+        width: u32,
+        /// Stored height of the image
+        height: u32,
+    },
+
+    /// No decoder could read the file; each entry is a fallback taken or
+    /// the failure that ended the attempt, in order.
+    #[error("No decoder could read the file: {}", .attempts.join("; "))]
+    NoDecoderSucceeded {
+        /// What was tried and why it failed
+        attempts: Vec<String>,
+    },
+
+    /// The source image is empty or its buffer does not match its dimensions.
+    #[error(transparent)]
+    InvalidImage(#[from] crate::validate::ImageError),
+
+    /// The file is empty or of a type no decoder reads, such as a PDF or a
+    /// video.
+    #[error("Unsupported file type: {file_type}")]
+    UnsupportedFileType {
+        /// Type recognized from the file's leading bytes
+        file_type: FileType,
+    },
+
+    /// The file is larger than the accepted maximum.
+    #[error("File of {size} bytes exceeds the {max_bytes} byte limit")]
+    FileTooLarge {
+        /// Length of the file
+        size: u64,
+        /// Largest accepted length
+        max_bytes: u64,
+    },
+}
+
+impl DecodeError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    ///
+    /// Image errors keep their own codes (see [`ImageError::code`]).
+    ///
+    /// [`ImageError::code`]: crate::validate::ImageError::code
+    pub fn code(&self) -> &'static str {
+        match self {
+            DecodeError::InvalidFormat => "INVALID_FORMAT",
+            DecodeError::UnsupportedCamera(_) => "UNSUPPORTED_CAMERA",
+            DecodeError::CorruptedFile(_) => "CORRUPTED_FILE",
+            DecodeError::OutOfMemory => "OUT_OF_MEMORY",
+            DecodeError::IoError(_) => "IO_ERROR",
+            DecodeError::ExifError(_) => "EXIF_ERROR",
+            DecodeError::NoThumbnail => "NO_THUMBNAIL",
+            DecodeError::NoGainMap => "NO_GAIN_MAP",
+            DecodeError::UnsupportedCodec(_) => "UNSUPPORTED_CODEC",
+            DecodeError::UnsupportedVariant { .. } => "UNSUPPORTED_VARIANT",
+            DecodeError::RegionOutOfBounds { .. } => "REGION_OUT_OF_BOUNDS",
+            DecodeError::NoDecoderSucceeded { .. } => "NO_DECODER_SUCCEEDED",
+            DecodeError::InvalidImage(err) => err.code(),
+            DecodeError::UnsupportedFileType { .. } => "UNSUPPORTED_FILE_TYPE",
+            DecodeError::FileTooLarge { .. } => "FILE_TOO_LARGE",
+        }
+    }
 }
 
 /// Filter type for image resizing operations.
@@ -97,24 +181,57 @@ impl Orientation {
                 | Orientation::Rotate270CW
         )
     }
+
+    /// The orientation that displays like this one followed by
+    /// `quarter_turns` clockwise quarter turns.
+    pub fn rotated_cw(self, quarter_turns: u8) -> Orientation {
+        // Each orientation as an optional horizontal flip followed by
+        // clockwise quarter turns; rotating afterwards just adds turns
+        const FLIP_THEN_TURNS: [(bool, u8); 8] = [
+            (false, 0),
+            (true, 0),
+            (false, 2),
+            (true, 2),
+            (true, 3),
+            (false, 1),
+            (true, 1),
+            (false, 3),
+        ];
+        let (flip, turns) = FLIP_THEN_TURNS[self as usize - 1];
+        let turns = (turns + quarter_turns % 4) % 4;
+        let value = FLIP_THEN_TURNS
+            .iter()
+            .position(|&entry| entry == (flip, turns))
+            .unwrap_or(0);
+        Orientation::from(value as u32 + 1)
+    }
 }
 
-impl From<u32> for Orientation {
-    fn from(value: u32) -> Self {
+impl Orientation {
+    /// Parse an EXIF orientation value, or `None` if it is outside 1-8.
+    pub fn from_exif(value: u32) -> Option<Orientation> {
         match value {
-            1 => Orientation::Normal,
-            2 => Orientation::FlipHorizontal,
-            3 => Orientation::Rotate180,
-            4 => Orientation::FlipVertical,
-            5 => Orientation::Transpose,
-            6 => Orientation::Rotate90CW,
-            7 => Orientation::Transverse,
-            8 => Orientation::Rotate270CW,
-            _ => Orientation::Normal,
+            1 => Some(Orientation::Normal),
+            2 => Some(Orientation::FlipHorizontal),
+            3 => Some(Orientation::Rotate180),
+            4 => Some(Orientation::FlipVertical),
+            5 => Some(Orientation::Transpose),
+            6 => Some(Orientation::Rotate90CW),
+            7 => Some(Orientation::Transverse),
+            8 => Some(Orientation::Rotate270CW),
+            _ => None,
         }
     }
 }
 
+/// Values outside 1-8 map to `Normal`; use [`Orientation::from_exif`] to
+/// tell them apart.
+impl From<u32> for Orientation {
+    fn from(value: u32) -> Self {
+        Orientation::from_exif(value).unwrap_or_default()
+    }
+}
+
 /// Metadata extracted from an image file.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ImageMetadata {
@@ -124,12 +241,22 @@ pub struct ImageMetadata {
     pub height: u32,
     /// EXIF orientation.
     pub orientation: Orientation,
+    /// EXIF orientation value outside 1-8 that was ignored, leaving the
+    /// image upright. Some broken phone firmware writes these.
+    pub invalid_orientation: Option<u32>,
     /// Camera make (e.g., "Sony").
     pub camera_make: Option<String>,
     /// Camera model (e.g., "ILCE-6600").
     pub camera_model: Option<String>,
     /// Date/time the photo was taken (ISO 8601 format).
     pub date_taken: Option<String>,
+    /// Capture time in milliseconds since the Unix epoch, including
+    /// subseconds when the file records them.
+    ///
+    /// EXIF times have no time zone: the camera's wall clock is read as if
+    /// it were UTC, so shots from one camera order and compare correctly
+    /// (e.g. for burst grouping) but the value is not a true instant.
+    pub capture_timestamp_ms: Option<i64>,
     /// ISO sensitivity.
     pub iso: Option<u32>,
     /// Shutter speed as a string (e.g., "1/250").
@@ -138,6 +265,8 @@ pub struct ImageMetadata {
     pub aperture: Option<f32>,
     /// Focal length in mm.
     pub focal_length: Option<f32>,
+    /// Name of the embedded ICC color profile (e.g., "Adobe RGB (1998)").
+    pub color_profile: Option<String>,
 }
 
 impl ImageMetadata {
@@ -159,7 +288,8 @@ pub struct DecodedImage {
     /// Image height in pixels.
     pub height: u32,
     /// RGB pixel data in row-major order (3 bytes per pixel).
-    /// Length should be width * height * 3.
+    /// Length should be width * height * 3, and pixel (x, y) starts at
+    /// byte (y * width + x) * 3.
     pub pixels: Vec<u8>,
 }
 
@@ -167,8 +297,8 @@ impl DecodedImage {
     /// Create a new DecodedImage with the given dimensions and pixel data.
     pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
         debug_assert_eq!(
-            pixels.len(),
-            (width * height * 3) as usize,
+            pixels.len() as u64,
+            width as u64 * height as u64 * 3,
             "Pixel buffer size mismatch"
         );
         Self {
@@ -195,8 +325,11 @@ impl DecodedImage {
     }
 
     /// Get the total number of pixels.
+    ///
+    /// Saturates at `u32::MAX`; within [`MAX_PIXELS`](crate::validate::MAX_PIXELS)
+    /// it is exact.
     pub fn pixel_count(&self) -> u32 {
-        self.width * self.height
+        self.width.saturating_mul(self.height)
     }
 
     /// Get the size of the pixel buffer in bytes.
@@ -208,6 +341,552 @@ impl DecodedImage {
     pub fn is_empty(&self) -> bool {
         self.width == 0 || self.height == 0 || self.pixels.is_empty()
     }
+
+    /// Check that the image is non-empty and its buffer matches its dimensions.
+    pub fn validate(&self) -> Result<(), crate::validate::ImageError> {
+        crate::validate::validate_rgb_buffer(&self.pixels, self.width, self.height)
+    }
+
+    /// RGB value of the pixel at (`x`, `y`), or `None` outside the image.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<(u8, u8, u8)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = self.pixel_offset(x, y);
+        let p = self.pixels.get(i..i + 3)?;
+        Some((p[0], p[1], p[2]))
+    }
+
+    /// Set the pixel at (`x`, `y`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if (`x`, `y`) is outside the image, in release builds too:
+    /// an `x` past the row end would otherwise write into the next row.
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgb: (u8, u8, u8)) {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({}, {}) outside {}x{} image",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        let i = self.pixel_offset(x, y);
+        self.pixels[i..i + 3].copy_from_slice(&[rgb.0, rgb.1, rgb.2]);
+    }
+
+    /// Rows of pixels, top to bottom, each `width * 3` bytes.
+    pub fn rows(&self) -> std::slice::ChunksExact<'_, u8> {
+        self.debug_assert_buffer();
+        self.pixels.chunks_exact(self.row_len())
+    }
+
+    /// Mutable rows of pixels, top to bottom, each `width * 3` bytes.
+    pub fn rows_mut(&mut self) -> std::slice::ChunksExactMut<'_, u8> {
+        self.debug_assert_buffer();
+        let row_len = self.row_len();
+        self.pixels.chunks_exact_mut(row_len)
+    }
+
+    /// Disjoint bands of up to `rows_per_band` rows with the `y` of their
+    /// first row.
+    ///
+    /// Bands borrow separate parts of the buffer, so each can be handed to
+    /// its own thread. The last band holds the remaining rows. A
+    /// `rows_per_band` of zero is treated as one.
+    pub fn bands_mut(&mut self, rows_per_band: u32) -> impl Iterator<Item = (u32, &mut [u8])> {
+        self.debug_assert_buffer();
+        let rows_per_band = rows_per_band.max(1);
+        let band_len = self.row_len() * rows_per_band as usize;
+        self.pixels
+            .chunks_mut(band_len)
+            .enumerate()
+            .map(move |(i, band)| (i as u32 * rows_per_band, band))
+    }
+
+    /// Every pixel with its coordinates, in row-major order.
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (u32, u32, (u8, u8, u8))> + '_ {
+        self.rows().enumerate().flat_map(|(y, row)| {
+            row.chunks_exact(3)
+                .enumerate()
+                .map(move |(x, p)| (x as u32, y as u32, (p[0], p[1], p[2])))
+        })
+    }
+
+    /// Replace every pixel with `f(x, y, rgb)`, in row-major order.
+    pub fn map_pixels(&mut self, mut f: impl FnMut(u32, u32, (u8, u8, u8)) -> (u8, u8, u8)) {
+        for (y, row) in self.rows_mut().enumerate() {
+            for (x, p) in row.chunks_exact_mut(3).enumerate() {
+                let (r, g, b) = f(x as u32, y as u32, (p[0], p[1], p[2]));
+                p.copy_from_slice(&[r, g, b]);
+            }
+        }
+    }
+
+    /// Bytes in one row; never zero so it can size chunks.
+    fn row_len(&self) -> usize {
+        (self.width as usize * 3).max(1)
+    }
+
+    fn pixel_offset(&self, x: u32, y: u32) -> usize {
+        (y as usize * self.width as usize + x as usize) * 3
+    }
+
+    fn debug_assert_buffer(&self) {
+        debug_assert_eq!(
+            self.pixels.len(),
+            self.width as usize * self.height as usize * 3,
+            "Pixel buffer size mismatch"
+        );
+    }
+
+    /// Convert to premultiplied RGBA with a uniform alpha.
+    ///
+    /// Produces `width * height * 4` bytes, the layout WebGL expects with
+    /// `UNPACK_PREMULTIPLY_ALPHA_WEBGL` off. Each color channel becomes
+    /// `round(c * alpha / 255)`; with `alpha = 255` the colors are unchanged.
+    pub fn to_rgba_premultiplied(&self, alpha: u8) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() / 3 * 4);
+        for chunk in self.pixels.chunks_exact(3) {
+            rgba.extend_from_slice(&[
+                premultiply(chunk[0], alpha),
+                premultiply(chunk[1], alpha),
+                premultiply(chunk[2], alpha),
+                alpha,
+            ]);
+        }
+        rgba
+    }
+
+    /// Create an RGB image from premultiplied RGBA (e.g. a WebGL readback).
+    ///
+    /// Each pixel is un-premultiplied with its own alpha, then alpha is
+    /// dropped. Fully transparent pixels become black.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageError` if either dimension is zero or `rgba` is not
+    /// `width * height * 4` bytes.
+    pub fn from_rgba_premultiplied(
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+    ) -> Result<Self, crate::validate::ImageError> {
+        crate::validate::validate_rgba_buffer(rgba, width, height)?;
+
+        let mut pixels = Vec::with_capacity(rgba.len() / 4 * 3);
+        for chunk in rgba.chunks_exact(4) {
+            let alpha = chunk[3];
+            pixels.extend_from_slice(&[
+                unpremultiply(chunk[0], alpha),
+                unpremultiply(chunk[1], alpha),
+                unpremultiply(chunk[2], alpha),
+            ]);
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Create an RGB image from straight (not premultiplied) RGBA canvas data.
+    ///
+    /// Canvas `getImageData` returns pixels in the canvas color space, which
+    /// is Display P3 for wide-gamut canvases. Those are converted to sRGB with
+    /// the same math as ICC-tagged JPEGs, clipping colors outside the sRGB
+    /// gamut. Alpha is dropped. [`CanvasColorSpace::Unknown`] data is taken
+    /// as is; check [`CanvasColorSpace::is_managed`] to flag it to the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageError` if either dimension is zero or `rgba` is not
+    /// `width * height * 4` bytes.
+    pub fn from_rgba_with_space(
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+        space: CanvasColorSpace,
+    ) -> Result<Self, crate::validate::ImageError> {
+        crate::validate::validate_rgba_buffer(rgba, width, height)?;
+
+        let mut pixels = Vec::with_capacity(rgba.len() / 4 * 3);
+        for chunk in rgba.chunks_exact(4) {
+            pixels.extend_from_slice(&chunk[..3]);
+        }
+        if space == CanvasColorSpace::DisplayP3 {
+            convert_to_srgb(&mut pixels, ColorProfile::DisplayP3);
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Convert to float pixels, mapping 0-255 to 0.0-1.0.
+    ///
+    /// [`DecodedImageF32::to_u8`] with [`ToneMap::Clamp`] converts back
+    /// without loss.
+    pub fn to_f32(&self) -> DecodedImageF32 {
+        DecodedImageF32 {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|&v| v as f32 / 255.0).collect(),
+        }
+    }
+
+    /// Convert to one channel, `weights[0] * r + weights[1] * g + weights[2] * b`
+    /// rounded per pixel.
+    ///
+    /// With [`LUMA_WEIGHTS`](crate::luminance::LUMA_WEIGHTS) this is the
+    /// luminance every other part of the pipeline uses.
+    pub fn to_gray(&self, weights: [f32; 3]) -> GrayImage {
+        let pixels = self
+            .pixels
+            .chunks_exact(3)
+            .map(|px| {
+                let v = weights[0] * px[0] as f32
+                    + weights[1] * px[1] as f32
+                    + weights[2] * px[2] as f32;
+                v.clamp(0.0, 255.0).round() as u8
+            })
+            .collect();
+        GrayImage {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+/// A decoded image with a single 8-bit channel.
+///
+/// For masks, luminance and other per-pixel maps, which take a third of the
+/// memory of the same values repeated in a [`DecodedImage`]. Convert with
+/// [`DecodedImage::to_gray`] and [`GrayImage::to_rgb`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrayImage {
+    /// Image width in pixels.
+    pub width: u32,
+    /// Image height in pixels.
+    pub height: u32,
+    /// One value per pixel in row-major order.
+    /// Length should be width * height.
+    pub pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    /// Create a new GrayImage with the given dimensions and pixel data.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        debug_assert_eq!(
+            pixels.len() as u64,
+            width as u64 * height as u64,
+            "Pixel buffer size mismatch"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Check that the image is non-empty and its buffer matches its dimensions.
+    pub fn validate(&self) -> Result<(), crate::validate::ImageError> {
+        crate::validate::validate_gray_buffer(&self.pixels, self.width, self.height)
+    }
+
+    /// Convert to RGB with the value in all three channels.
+    pub fn to_rgb(&self) -> DecodedImage {
+        DecodedImage {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().flat_map(|&v| [v, v, v]).collect(),
+        }
+    }
+}
+
+/// An 8-bit image with interleaved channels.
+///
+/// Lets operations such as [`resize`](super::resize) take RGB and gray
+/// images alike.
+pub trait PixelBuffer: Clone {
+    /// Values per pixel.
+    const CHANNELS: usize;
+
+    /// Width and height in pixels.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// The pixel values, row-major, `CHANNELS` per pixel.
+    fn samples(&self) -> &[u8];
+
+    /// Build an image from values laid out as [`Self::samples`] returns them.
+    fn from_samples(width: u32, height: u32, samples: Vec<u8>) -> Self;
+}
+
+impl PixelBuffer for DecodedImage {
+    const CHANNELS: usize = 3;
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn samples(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn from_samples(width: u32, height: u32, samples: Vec<u8>) -> Self {
+        DecodedImage::new(width, height, samples)
+    }
+}
+
+impl PixelBuffer for GrayImage {
+    const CHANNELS: usize = 1;
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn samples(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn from_samples(width: u32, height: u32, samples: Vec<u8>) -> Self {
+        GrayImage::new(width, height, samples)
+    }
+}
+
+/// Color space of pixels read back from a canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanvasColorSpace {
+    /// sRGB, the default canvas color space.
+    #[default]
+    Srgb,
+    /// Display P3, used by wide-gamut canvases (e.g. clipboard images in Safari).
+    DisplayP3,
+    /// Not reported by the browser; pixels are imported without conversion.
+    Unknown,
+}
+
+impl CanvasColorSpace {
+    /// Whether pixels in this space are converted to sRGB on import.
+    pub fn is_managed(self) -> bool {
+        self != CanvasColorSpace::Unknown
+    }
+}
+
+/// How [`DecodedImageF32::to_u8`] brings values above 1.0 into range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMap {
+    /// Clip to 0.0-1.0. Lossless for values that came from 8-bit pixels.
+    #[default]
+    Clamp,
+    /// Compress each channel with `v / (1 + v)`, so highlights of any
+    /// brightness keep detail instead of clipping.
+    Reinhard,
+}
+
+impl ToneMap {
+    /// Map one channel value to the 0.0-1.0 display range.
+    #[inline]
+    pub fn apply(self, value: f32) -> f32 {
+        match self {
+            ToneMap::Clamp => value.clamp(0.0, 1.0),
+            ToneMap::Reinhard => {
+                let v = value.max(0.0);
+                v / (1.0 + v)
+            }
+        }
+    }
+}
+
+/// A decoded image with float RGB pixel data, for HDR and scientific data.
+///
+/// The nominal range is 0.0-1.0 (1.0 is the 8-bit white), but values are
+/// allowed outside it: float operations never clip, so brightening and then
+/// darkening an image recovers its highlights. Convert with
+/// [`DecodedImage::to_f32`] and [`DecodedImageF32::to_u8`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedImageF32 {
+    /// Image width in pixels.
+    pub width: u32,
+    /// Image height in pixels.
+    pub height: u32,
+    /// RGB values in row-major order (3 per pixel).
+    /// Length should be width * height * 3.
+    pub pixels: Vec<f32>,
+}
+
+impl DecodedImageF32 {
+    /// Create a new DecodedImageF32 with the given dimensions and pixel data.
+    pub fn new(width: u32, height: u32, pixels: Vec<f32>) -> Self {
+        debug_assert_eq!(
+            pixels.len() as u64,
+            width as u64 * height as u64 * 3,
+            "Pixel buffer size mismatch"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Check that the image is non-empty and its buffer matches its dimensions.
+    pub fn validate(&self) -> Result<(), crate::validate::ImageError> {
+        crate::validate::validate_rgb_f32_buffer(&self.pixels, self.width, self.height)
+    }
+
+    /// Convert to 8-bit pixels, bringing values into range with `tone_map`.
+    ///
+    /// NaN becomes 0.
+    pub fn to_u8(&self, tone_map: ToneMap) -> DecodedImage {
+        self.to_u8_with(tone_map, QuantizeMethod::Truncate)
+    }
+
+    /// Convert to 8-bit pixels with `tone_map`, quantizing with `method`.
+    ///
+    /// [`QuantizeMethod::ErrorDiffusion`] hides the banding that 8 bits
+    /// leave on large soft gradients, for previews of float results.
+    pub fn to_u8_with(&self, tone_map: ToneMap, method: QuantizeMethod) -> DecodedImage {
+        let values = self.pixels.iter().map(|&v| tone_map.apply(v));
+        DecodedImage {
+            width: self.width,
+            height: self.height,
+            pixels: quantize_values(values, self.width as usize, method),
+        }
+    }
+}
+
+/// How [`quantize_to_u8`] brings higher-precision values down to 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantizeMethod {
+    /// Drop the extra precision: round every value to the nearest level on
+    /// its own. Smooth gradients that span few levels show bands.
+    #[default]
+    Truncate,
+    /// Floyd–Steinberg error diffusion of the luminance error only: each
+    /// pixel's rounding error in luminance is spread over its unvisited
+    /// neighbors, so bands become a fine pattern whose local mean tracks
+    /// the true value. One error value per pixel keeps it fast.
+    ErrorDiffusion,
+}
+
+/// A higher-precision sample that [`quantize_to_u8`] accepts.
+pub trait UnitSample: Copy {
+    /// The sample in the nominal 0.0-1.0 range (values outside it are
+    /// clipped when quantized).
+    fn to_unit(self) -> f32;
+}
+
+impl UnitSample for f32 {
+    #[inline]
+    fn to_unit(self) -> f32 {
+        self
+    }
+}
+
+impl UnitSample for u16 {
+    #[inline]
+    fn to_unit(self) -> f32 {
+        self as f32 / 65535.0
+    }
+}
+
+/// Quantize a 16-bit or float RGB buffer to 8 bits.
+///
+/// Values are clipped to the nominal range and NaN becomes 0. With
+/// [`QuantizeMethod::Truncate`] each value becomes `round(v * 255)`, exactly
+/// as [`DecodedImageF32::to_u8`] has always converted.
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+pub fn quantize_to_u8<S: UnitSample>(
+    src: &[S],
+    width: u32,
+    height: u32,
+    method: QuantizeMethod,
+) -> Result<Vec<u8>, crate::validate::ImageError> {
+    crate::validate::validate_buffer(
+        std::mem::size_of_val(src),
+        width,
+        height,
+        3 * std::mem::size_of::<S>(),
+    )?;
+    let values = src.iter().map(|&s| s.to_unit());
+    Ok(quantize_values(values, width as usize, method))
+}
+
+/// Quantize RGB values in the nominal 0.0-1.0 range, `width` pixels a row.
+fn quantize_values(
+    values: impl Iterator<Item = f32>,
+    width: usize,
+    method: QuantizeMethod,
+) -> Vec<u8> {
+    // NaN clamps to NaN, which casts to 0
+    let levels = values.map(|v| v.clamp(0.0, 1.0) * 255.0);
+    match method {
+        QuantizeMethod::Truncate => levels.map(|v| v.round() as u8).collect(),
+        QuantizeMethod::ErrorDiffusion => error_diffuse(levels, width),
+    }
+}
+
+/// Floyd–Steinberg dither of RGB levels (0.0-255.0, NaN allowed) on the
+/// luminance error.
+fn error_diffuse(mut levels: impl Iterator<Item = f32>, width: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(levels.size_hint().0);
+    // Luminance error owed to this row and the next, with a pixel of padding
+    // at either end so the kernel never needs bounds checks
+    let mut current = vec![0.0f32; width + 2];
+    let mut next = vec![0.0f32; width + 2];
+    let mut x = 0;
+    while let (Some(r), Some(g), Some(b)) = (levels.next(), levels.next(), levels.next()) {
+        let error = current[x + 1];
+        // Error past the ends of the range cannot be shown, so it is dropped
+        let target = [r, g, b].map(|v| (v + error).clamp(0.0, 255.0));
+        let pixel = target.map(|v| v.round() as u8);
+        let [tr, tg, tb] = target;
+        let [pr, pg, pb] = pixel.map(f32::from);
+        let residual = calculate_luminance(tr, tg, tb) - calculate_luminance(pr, pg, pb);
+
+        current[x + 2] += residual * 7.0 / 16.0;
+        next[x] += residual * 3.0 / 16.0;
+        next[x + 1] += residual * 5.0 / 16.0;
+        next[x + 2] += residual / 16.0;
+        out.extend_from_slice(&pixel);
+
+        x += 1;
+        if x == width {
+            x = 0;
+            std::mem::swap(&mut current, &mut next);
+            next.fill(0.0);
+        }
+    }
+    out
+}
+
+/// Premultiply one channel: `round(value * alpha / 255)`.
+#[inline]
+fn premultiply(value: u8, alpha: u8) -> u8 {
+    ((value as u32 * alpha as u32 + 127) / 255) as u8
+}
+
+/// Inverse of [`premultiply`]: `round(value * 255 / alpha)`, clamped.
+///
+/// Rounding to nearest (rather than truncating) keeps a round trip within 1
+/// of the original, instead of drifting down at low alpha.
+#[inline]
+fn unpremultiply(value: u8, alpha: u8) -> u8 {
+    if alpha == 0 {
+        return 0;
+    }
+    let a = alpha as u32;
+    ((value as u32 * 255 + a / 2) / a).min(255) as u8
 }
 
 #[cfg(test)]
@@ -235,6 +914,10 @@ mod tests {
         assert_eq!(Orientation::from(1), Orientation::Normal);
         assert_eq!(Orientation::from(6), Orientation::Rotate90CW);
         assert_eq!(Orientation::from(99), Orientation::Normal); // Invalid defaults to Normal
+        assert_eq!(Orientation::from_exif(6), Some(Orientation::Rotate90CW));
+        for value in [0, 9, 65_535] {
+            assert_eq!(Orientation::from_exif(value), None);
+        }
     }
 
     #[test]
@@ -252,6 +935,30 @@ mod tests {
         assert!(Orientation::Rotate270CW.swaps_dimensions());
     }
 
+    #[test]
+    fn test_orientation_rotated_cw() {
+        assert_eq!(Orientation::Normal.rotated_cw(1), Orientation::Rotate90CW);
+        assert_eq!(
+            Orientation::Rotate90CW.rotated_cw(1),
+            Orientation::Rotate180
+        );
+        assert_eq!(Orientation::Rotate270CW.rotated_cw(1), Orientation::Normal);
+        assert_eq!(Orientation::Rotate180.rotated_cw(6), Orientation::Normal);
+        assert_eq!(
+            Orientation::FlipHorizontal.rotated_cw(1),
+            Orientation::Transverse
+        );
+        assert_eq!(
+            Orientation::FlipVertical.rotated_cw(1),
+            Orientation::Transpose
+        );
+        for value in 1..=8 {
+            let orientation = Orientation::from(value);
+            assert_eq!(orientation.rotated_cw(0), orientation);
+            assert_eq!(orientation.rotated_cw(4), orientation);
+        }
+    }
+
     #[test]
     fn test_oriented_dimensions() {
         let mut meta = ImageMetadata {
@@ -281,6 +988,62 @@ mod tests {
         assert!(!img.is_empty());
     }
 
+    #[test]
+    fn test_pixel_accessors_round_trip() {
+        let mut img = DecodedImage::new(4, 3, vec![0; 4 * 3 * 3]);
+        img.set_pixel(3, 1, (10, 20, 30));
+        assert_eq!(img.pixel(3, 1), Some((10, 20, 30)));
+        // Row-major: (3, 1) is the 8th pixel
+        assert_eq!(&img.pixels[7 * 3..8 * 3], &[10, 20, 30]);
+
+        img.map_pixels(|x, y, (r, _, _)| (r, x as u8, y as u8));
+        assert_eq!(img.pixel(3, 1), Some((10, 3, 1)));
+        assert_eq!(img.pixel(0, 2), Some((0, 0, 2)));
+    }
+
+    #[test]
+    fn test_pixel_iterators_on_non_square_image() {
+        let (width, height) = (5, 2);
+        let pixels = (0..width * height)
+            .flat_map(|i| [(i % width) as u8, (i / width) as u8, 0])
+            .collect();
+        let mut img = DecodedImage::new(width, height, pixels);
+
+        let coords: Vec<_> = img.enumerate_pixels().collect();
+        assert_eq!(coords.len(), 10);
+        for (x, y, rgb) in coords {
+            assert_eq!(rgb, (x as u8, y as u8, 0));
+        }
+
+        assert_eq!(img.rows().count(), 2);
+        assert!(img.rows().all(|row| row.len() == 15));
+        assert_eq!(img.rows().nth(1).unwrap()[0..3], [0, 1, 0]);
+
+        let bands: Vec<_> = img.bands_mut(3).map(|(y, band)| (y, band.len())).collect();
+        assert_eq!(bands, vec![(0, 30)]);
+        let bands: Vec<_> = img.bands_mut(1).map(|(y, band)| (y, band.len())).collect();
+        assert_eq!(bands, vec![(0, 15), (1, 15)]);
+        for row in img.rows_mut() {
+            row[0] = 99;
+        }
+        assert_eq!(img.pixel(0, 1), Some((99, 1, 0)));
+    }
+
+    #[test]
+    fn test_pixel_out_of_bounds_is_none() {
+        let img = DecodedImage::new(5, 2, vec![0; 5 * 2 * 3]);
+        assert_eq!(img.pixel(5, 0), None);
+        assert_eq!(img.pixel(0, 2), None);
+        assert_eq!(img.pixel(u32::MAX, u32::MAX), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside 5x2 image")]
+    fn test_set_pixel_out_of_bounds_panics() {
+        let mut img = DecodedImage::new(5, 2, vec![0; 5 * 2 * 3]);
+        img.set_pixel(5, 0, (1, 2, 3));
+    }
+
     #[test]
     fn test_decoded_image_empty() {
         let img = DecodedImage::new(0, 0, vec![]);
@@ -295,4 +1058,259 @@ mod tests {
         let err = DecodeError::InvalidFormat;
         assert_eq!(err.to_string(), "Invalid or unsupported image format");
     }
+
+    fn every_value_image() -> DecodedImage {
+        // 16x16 pixels covering every channel value 0-255
+        let pixels = (0..256 * 3).map(|i| (i * 7 % 256) as u8).collect();
+        DecodedImage::new(16, 16, pixels)
+    }
+
+    #[test]
+    fn test_rgba_premultiplied_layout() {
+        let img = DecodedImage::new(2, 1, vec![255, 128, 0, 10, 20, 30]);
+        let rgba = img.to_rgba_premultiplied(128);
+        assert_eq!(rgba.len(), 2 * 4);
+        assert_eq!(rgba, vec![128, 64, 0, 128, 5, 10, 15, 128]);
+    }
+
+    #[test]
+    fn test_rgba_premultiplied_opaque_round_trip_is_lossless() {
+        let img = every_value_image();
+        let rgba = img.to_rgba_premultiplied(255);
+        let back = DecodedImage::from_rgba_premultiplied(16, 16, &rgba).unwrap();
+        assert_eq!(back.pixels, img.pixels);
+    }
+
+    #[test]
+    fn test_rgba_premultiplied_half_alpha_round_trip() {
+        let img = every_value_image();
+        let rgba = img.to_rgba_premultiplied(128);
+        let back = DecodedImage::from_rgba_premultiplied(16, 16, &rgba).unwrap();
+        for (a, b) in img.pixels.iter().zip(&back.pixels) {
+            assert!(a.abs_diff(*b) <= 1, "{} -> {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_rgba_premultiplied_transparent_is_black() {
+        let back = DecodedImage::from_rgba_premultiplied(1, 1, &[0, 0, 0, 0]).unwrap();
+        assert_eq!(back.pixels, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_from_rgba_premultiplied_validates_buffer() {
+        use crate::validate::ImageError;
+
+        assert!(matches!(
+            DecodedImage::from_rgba_premultiplied(2, 2, &[0; 12]),
+            Err(ImageError::BufferSizeMismatch { expected: 16, .. })
+        ));
+        assert!(matches!(
+            DecodedImage::from_rgba_premultiplied(0, 2, &[]),
+            Err(ImageError::EmptyImage { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_rgba_with_space_converts_display_p3() {
+        // Fully saturated P3 red is outside sRGB and clips to sRGB red;
+        // a mid-saturation P3 red lands inside the gamut
+        let rgba = [255, 0, 0, 255, 200, 60, 60, 128];
+        let image =
+            DecodedImage::from_rgba_with_space(2, 1, &rgba, CanvasColorSpace::DisplayP3).unwrap();
+        let expected = [255, 0, 0, 218, 41, 51];
+        for (actual, expected) in image.pixels.iter().zip(expected) {
+            assert!((*actual as i32 - expected).abs() <= 2, "{:?}", image.pixels);
+        }
+
+        // sRGB and unknown data only lose alpha
+        for space in [CanvasColorSpace::Srgb, CanvasColorSpace::Unknown] {
+            let image = DecodedImage::from_rgba_with_space(2, 1, &rgba, space).unwrap();
+            assert_eq!(image.pixels, vec![255, 0, 0, 200, 60, 60]);
+        }
+        assert!(!CanvasColorSpace::Unknown.is_managed());
+    }
+
+    #[test]
+    fn test_from_rgba_with_space_keeps_grays() {
+        let rgba: Vec<u8> = (0..=255u8).flat_map(|v| [v, v, v, 255]).collect();
+        let image =
+            DecodedImage::from_rgba_with_space(256, 1, &rgba, CanvasColorSpace::DisplayP3).unwrap();
+        for (i, pixel) in image.pixels.chunks_exact(3).enumerate() {
+            assert_eq!(pixel, [i as u8; 3]);
+        }
+    }
+
+    #[test]
+    fn test_from_rgba_with_space_validates_buffer() {
+        assert!(matches!(
+            DecodedImage::from_rgba_with_space(2, 2, &[0; 15], CanvasColorSpace::DisplayP3),
+            Err(crate::validate::ImageError::BufferSizeMismatch { .. })
+        ));
+        assert!(DecodedImage::from_rgba_with_space(0, 2, &[], CanvasColorSpace::Srgb).is_err());
+    }
+
+    #[test]
+    fn test_f32_round_trip_with_clamp_is_lossless() {
+        let pixels: Vec<u8> = (0..=255).flat_map(|v| [v, 255 - v, v / 2]).collect();
+        let image = DecodedImage::new(256, 1, pixels);
+        let float = image.to_f32();
+        assert!(float.validate().is_ok());
+        assert_eq!(float.to_u8(ToneMap::Clamp).pixels, image.pixels);
+    }
+
+    #[test]
+    fn test_reinhard_compresses_highlights_monotonically() {
+        let values = [0.0, 0.25, 0.8, 1.0, 1.6, 4.0, 100.0];
+        let mapped: Vec<f32> = values.iter().map(|&v| ToneMap::Reinhard.apply(v)).collect();
+        assert!(mapped.iter().all(|&v| (0.0..1.0).contains(&v)));
+        assert!(mapped.windows(2).all(|w| w[0] < w[1]));
+
+        let image = DecodedImageF32::new(1, 1, vec![1.6, 0.8, -0.5]);
+        assert_eq!(image.to_u8(ToneMap::Clamp).pixels, vec![255, 204, 0]);
+        let mapped = image.to_u8(ToneMap::Reinhard).pixels;
+        assert!(mapped[0] < 255 && mapped[0] > mapped[1]);
+    }
+
+    /// Mean 8-bit level of each run of `block` columns of a gray image.
+    fn column_block_means(pixels: &[u8], width: usize, block: usize) -> Vec<f32> {
+        let rows = pixels.len() / 3 / width;
+        (0..width / block)
+            .map(|i| {
+                let sum: u32 = (0..rows)
+                    .flat_map(|y| (i * block..(i + 1) * block).map(move |x| (y * width + x) * 3))
+                    .map(|idx| pixels[idx] as u32)
+                    .sum();
+                sum as f32 / (rows * block) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_error_diffusion_tracks_a_16_bit_ramp() {
+        // A gray ramp from level 100 to level 102 in 16-bit steps
+        let (width, height) = (256, 32);
+        let level = |x: usize| 100.0 + 2.0 * x as f32 / (width - 1) as f32;
+        let src: Vec<u16> = (0..height)
+            .flat_map(|_| (0..width).map(|x| (level(x) / 255.0 * 65535.0).round() as u16))
+            .flat_map(|v| [v; 3])
+            .collect();
+
+        let quantize = |method| quantize_to_u8(&src, width as u32, height as u32, method).unwrap();
+
+        let dithered = quantize(QuantizeMethod::ErrorDiffusion);
+        assert_eq!(quantize(QuantizeMethod::ErrorDiffusion), dithered);
+        assert!(dithered.iter().all(|v| (100..=102).contains(v)));
+
+        let block = 16;
+        let means = column_block_means(&dithered, width, block);
+        for (i, mean) in means.iter().enumerate() {
+            let expected = (i * block..(i + 1) * block).map(level).sum::<f32>() / block as f32;
+            assert!(
+                (mean - expected).abs() < 0.5,
+                "block {i}: mean {mean}, expected {expected}"
+            );
+        }
+
+        // Rounding alone gives bands: whole blocks at a single level
+        let truncated = quantize(QuantizeMethod::Truncate);
+        assert_ne!(truncated, dithered);
+        assert!(column_block_means(&truncated, width, block)
+            .iter()
+            .all(|mean| mean.fract() == 0.0));
+    }
+
+    #[test]
+    fn test_truncate_matches_simple_rounding() {
+        let values = vec![0.0, 0.5, 1.0, 0.2, 1.7, -0.3, f32::NAN, 0.998, 0.001];
+        let expected: Vec<u8> = values
+            .iter()
+            .map(|&v: &f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect();
+        let quantized = quantize_to_u8(&values, 3, 1, QuantizeMethod::Truncate).unwrap();
+        assert_eq!(quantized, expected);
+
+        let image = DecodedImageF32::new(3, 1, values);
+        assert_eq!(image.to_u8(ToneMap::Clamp).pixels, quantized);
+        assert_eq!(
+            image
+                .to_u8_with(ToneMap::Clamp, QuantizeMethod::Truncate)
+                .pixels,
+            quantized
+        );
+
+        assert_eq!(
+            quantize_to_u8(&[0u16, 32768, 65535], 1, 1, QuantizeMethod::Truncate).unwrap(),
+            vec![0, 128, 255]
+        );
+        assert!(matches!(
+            quantize_to_u8(&[0u16; 5], 1, 2, QuantizeMethod::ErrorDiffusion),
+            Err(crate::validate::ImageError::BufferSizeMismatch {
+                expected: 12,
+                actual: 10,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_to_gray_uses_luma_weights_and_round_trips() {
+        use crate::luminance::{calculate_luminance_u8, LUMA_WEIGHTS};
+
+        let image = crate::testing::noise(7, 5, 2);
+        let gray = image.to_gray(LUMA_WEIGHTS);
+        assert_eq!((gray.width, gray.height), (7, 5));
+        for (px, &v) in image.pixels.chunks_exact(3).zip(&gray.pixels) {
+            assert_eq!(v, calculate_luminance_u8(px[0], px[1], px[2]));
+        }
+        assert_eq!(image.to_gray([0.0, 1.0, 0.0]).pixels[0], image.pixels[1]);
+
+        // Gray pixels are their own luminance
+        let rgb = gray.to_rgb();
+        assert_eq!(rgb.pixels.len(), 7 * 5 * 3);
+        let v = gray.pixels[2 * 7 + 3];
+        assert_eq!(rgb.pixel(3, 2), Some((v, v, v)));
+        assert_eq!(rgb.to_gray(LUMA_WEIGHTS), gray);
+    }
+
+    #[test]
+    fn test_gray_validate_checks_length() {
+        use crate::validate::ImageError;
+
+        assert_eq!(GrayImage::new(3, 2, vec![0; 6]).validate(), Ok(()));
+        let short = GrayImage {
+            width: 3,
+            height: 2,
+            pixels: vec![0; 5],
+        };
+        assert_eq!(
+            short.validate(),
+            Err(ImageError::BufferSizeMismatch {
+                width: 3,
+                height: 2,
+                expected: 6,
+                actual: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn test_f32_validate_reports_bytes() {
+        use crate::validate::ImageError;
+
+        let image = DecodedImageF32 {
+            width: 2,
+            height: 2,
+            pixels: vec![0.0; 6],
+        };
+        assert_eq!(
+            image.validate(),
+            Err(ImageError::BufferSizeMismatch {
+                width: 2,
+                height: 2,
+                expected: 48,
+                actual: 24,
+            })
+        );
+    }
 }
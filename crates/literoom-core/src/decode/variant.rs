@@ -0,0 +1,342 @@
+//! JPEG coding variants: precision, coding process and color model.
+//!
+//! The decoder handles 8-bit sequential and progressive Huffman-coded
+//! JPEGs. Anything else (12-bit scans from some scanners, arithmetic
+//! coding, the lossless and hierarchical processes) is recognized from the
+//! frame header and reported as [`DecodeError::UnsupportedVariant`] instead
+//! of a generic decoder failure, so the app can say why the file does not
+//! open.
+//!
+//! Four-component JPEGs are CMYK, or YCCK when an Adobe APP14 segment says
+//! so. Photoshop, which writes nearly all of them, stores the inks inverted
+//! (0 is full ink) and always adds the APP14 segment; files without one
+//! store ink amounts directly. Both are converted to RGB with the naive
+//! `(1 - C)(1 - K)` formula, which is a fair approximation without the
+//! press profile.
+
+use image::{DynamicImage, RgbImage};
+use zune_core::bytestream::ZCursor;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+use zune_jpeg::JpegDecoder;
+
+use super::jpeg::{app_segments, is_start_of_frame};
+use super::DecodeError;
+use crate::validate::MAX_IMAGE_DIMENSION;
+
+/// Frame details needed to decide how a JPEG decodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct JpegVariant {
+    /// The SOF marker (0xC0 to 0xCF), which encodes the coding process
+    pub(super) frame_marker: u8,
+    /// Sample precision in bits
+    pub(super) precision: u8,
+    /// Number of components in the frame
+    pub(super) components: u8,
+    /// Color transform from an Adobe APP14 segment (0 = none/CMYK,
+    /// 1 = YCbCr, 2 = YCCK)
+    pub(super) adobe_transform: Option<u8>,
+    /// Height of one MCU row in pixels
+    pub(super) mcu_height: u32,
+}
+
+impl JpegVariant {
+    /// Read the frame header and APP14 segment, or `None` if `bytes` is not
+    /// a JPEG or ends before the frame header.
+    pub(super) fn read(bytes: &[u8]) -> Option<Self> {
+        if !bytes.starts_with(&[0xFF, 0xD8]) {
+            return None;
+        }
+        let mut adobe_transform = None;
+        for (marker, payload) in app_segments(bytes) {
+            match marker {
+                0xEE if payload.starts_with(b"Adobe") => {
+                    adobe_transform = payload.get(11).copied();
+                }
+                marker if is_start_of_frame(marker) => {
+                    return Some(Self {
+                        frame_marker: marker,
+                        precision: *payload.first()?,
+                        components: *payload.get(5)?,
+                        adobe_transform,
+                        mcu_height: frame_mcu_height(payload),
+                    });
+                }
+                _ => {}
+            }
+        }
+        // Scan data, the end of the image or a malformed segment came
+        // before any frame
+        None
+    }
+
+    /// Check that the decoder can handle this variant.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DecodeError::UnsupportedVariant` for precisions other than
+    /// 8 bits and for arithmetic-coded, lossless and hierarchical JPEGs.
+    pub(super) fn check_supported(&self) -> Result<(), DecodeError> {
+        let detail = match self.frame_marker {
+            _ if self.precision != 8 => format!("{}-bit samples", self.precision),
+            0xC0..=0xC2 => return Ok(()),
+            0xC3 | 0xC7 | 0xCB | 0xCF => "lossless coding".to_string(),
+            0xC5..=0xC7 | 0xCD..=0xCF => "hierarchical coding".to_string(),
+            _ => "arithmetic coding".to_string(),
+        };
+        Err(DecodeError::UnsupportedVariant { detail })
+    }
+
+    /// Whether the four channels hold ink amounts directly rather than
+    /// Adobe's inverted values.
+    pub(super) fn is_uninverted_cmyk(&self) -> bool {
+        self.components == 4 && self.adobe_transform.is_none()
+    }
+}
+
+/// MCU height of a frame, from the body of its SOF segment.
+fn frame_mcu_height(frame: &[u8]) -> u32 {
+    let components = frame.get(5).copied().unwrap_or(0) as usize;
+    if components <= 1 {
+        // Non-interleaved: one 8x8 block per MCU whatever the sampling
+        return 8;
+    }
+    let max_vertical = (0..components)
+        .filter_map(|i| frame.get(6 + i * 3 + 1))
+        .map(|&sampling| (sampling & 0x0F) as u32)
+        .max()
+        .unwrap_or(1);
+    8 * max_vertical.clamp(1, 4)
+}
+
+/// Decode a CMYK JPEG that stores ink amounts directly.
+///
+/// The `image` decoder assumes Adobe's inverted channels for every CMYK
+/// file, which turns these into near-negatives, so the raw samples are read
+/// and converted here instead.
+pub(super) fn decode_uninverted_cmyk(bytes: &[u8]) -> Result<DynamicImage, DecodeError> {
+    let options = DecoderOptions::default()
+        .set_strict_mode(false)
+        .set_max_width(MAX_IMAGE_DIMENSION as usize)
+        .set_max_height(MAX_IMAGE_DIMENSION as usize)
+        .jpeg_set_out_colorspace(ColorSpace::CMYK);
+    let mut decoder = JpegDecoder::new_with_options(ZCursor::new(bytes), options);
+    let cmyk = decoder
+        .decode()
+        .map_err(|e| DecodeError::CorruptedFile(e.to_string()))?;
+    let (width, height) = decoder
+        .dimensions()
+        .ok_or_else(|| DecodeError::CorruptedFile("missing frame header".to_string()))?;
+
+    let ink = |value: u8, k: u8| {
+        let (paper, light) = (255 - value as u32, 255 - k as u32);
+        ((paper * light + 127) / 255) as u8
+    };
+    let rgb = cmyk
+        .chunks_exact(4)
+        .flat_map(|p| [ink(p[0], p[3]), ink(p[1], p[3]), ink(p[2], p[3])])
+        .collect();
+    RgbImage::from_raw(width as u32, height as u32, rgb)
+        .map(DynamicImage::ImageRgb8)
+        .ok_or_else(|| DecodeError::CorruptedFile("truncated CMYK samples".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_jpeg;
+
+    /// Collects bits MSB first, with JPEG byte stuffing.
+    #[derive(Default)]
+    struct Bits {
+        out: Vec<u8>,
+        acc: u32,
+        count: u32,
+    }
+
+    impl Bits {
+        fn put(&mut self, value: u32, len: u32) {
+            for i in (0..len).rev() {
+                self.acc = (self.acc << 1) | ((value >> i) & 1);
+                self.count += 1;
+                if self.count == 8 {
+                    self.out.push(self.acc as u8);
+                    if self.acc == 0xFF {
+                        self.out.push(0);
+                    }
+                    self.acc = 0;
+                    self.count = 0;
+                }
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            while self.count != 0 {
+                self.put(1, 1);
+            }
+            self.out
+        }
+    }
+
+    fn segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+        out.extend_from_slice(&[0xFF, marker]);
+        out.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(payload);
+    }
+
+    /// An 8-pixel-high JPEG of flat 8x8 blocks, one per entry of `blocks`
+    /// from left to right, with 4 components (stored sample values).
+    ///
+    /// Every block is coded as its DC coefficient only, with a quantizer of
+    /// 1 and minimal Huffman tables, so the samples decode exactly.
+    fn four_component_jpeg(blocks: &[[u8; 4]], adobe_transform: Option<u8>) -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8];
+        if let Some(transform) = adobe_transform {
+            let mut app14 = b"Adobe\x00\x64\x00\x00\x00\x00".to_vec();
+            app14.push(transform);
+            segment(&mut out, 0xEE, &app14);
+        }
+        let mut dqt = vec![0u8];
+        dqt.extend([1u8; 64]);
+        segment(&mut out, 0xDB, &dqt);
+        let width = blocks.len() as u16 * 8;
+        let mut sof = vec![8, 0, 8];
+        sof.extend_from_slice(&width.to_be_bytes());
+        sof.push(4);
+        for id in 1..=4 {
+            sof.extend_from_slice(&[id, 0x11, 0]);
+        }
+        segment(&mut out, 0xC0, &sof);
+        // DC: sizes 0-11 as the 4-bit codes 0-11; AC: end-of-block only
+        let mut dc = vec![0x00, 0, 0, 0, 12];
+        dc.extend([0; 12]);
+        dc.extend(0..12);
+        segment(&mut out, 0xC4, &dc);
+        let mut ac = vec![0x10, 1];
+        ac.extend([0; 15]);
+        ac.push(0);
+        segment(&mut out, 0xC4, &ac);
+        segment(
+            &mut out,
+            0xDA,
+            &[4, 1, 0x00, 2, 0x00, 3, 0x00, 4, 0x00, 0, 63, 0],
+        );
+
+        let mut bits = Bits::default();
+        let mut prev = [0i32; 4];
+        for block in blocks {
+            for (c, &sample) in block.iter().enumerate() {
+                let dc = (sample as i32 - 128) * 8;
+                let diff = dc - prev[c];
+                prev[c] = dc;
+                let size = 32 - diff.unsigned_abs().leading_zeros();
+                bits.put(size, 4);
+                let value = if diff < 0 { diff - 1 } else { diff };
+                bits.put(value as u32 & ((1 << size) - 1), size);
+                // End of block
+                bits.put(0, 1);
+            }
+        }
+        out.extend(bits.finish());
+        out.extend_from_slice(&[0xFF, 0xD9]);
+        out
+    }
+
+    /// RGB of a CMYK ink mix by the reference formula, `(1 - C)(1 - K)`.
+    fn reference_rgb([c, m, y, k]: [u8; 4]) -> [f32; 3] {
+        let light = |v: u8| (255.0 - v as f32) * (255.0 - k as f32) / 255.0;
+        [light(c), light(m), light(y)]
+    }
+
+    fn assert_close(pixel: &[u8], expected: [f32; 3]) {
+        for (&v, e) in pixel.iter().zip(expected) {
+            assert!((v as f32 - e).abs() <= 2.0, "{:?} vs {:?}", pixel, expected);
+        }
+    }
+
+    /// A light skin tone and a deep blue, as ink amounts.
+    const SKIN: [u8; 4] = [20, 45, 60, 5];
+    const BLUE: [u8; 4] = [230, 160, 10, 40];
+
+    #[test]
+    fn test_adobe_cmyk_decodes_to_rgb() {
+        let inverted = [SKIN, BLUE].map(|inks| inks.map(|v| 255 - v));
+        let image = decode_jpeg(&four_component_jpeg(&inverted, Some(0))).unwrap();
+        assert_eq!((image.width, image.height), (16, 8));
+        assert_close(&image.pixels[..3], reference_rgb(SKIN));
+        assert_close(&image.pixels[8 * 3..][..3], reference_rgb(BLUE));
+        // Skin stays warm and light rather than turning into its negative
+        assert!(image.pixels[0] > 200 && image.pixels[0] > image.pixels[2]);
+    }
+
+    #[test]
+    fn test_plain_cmyk_is_not_inverted() {
+        let bytes = four_component_jpeg(&[SKIN, BLUE], None);
+        let variant = JpegVariant::read(&bytes).unwrap();
+        assert!(variant.is_uninverted_cmyk());
+        assert_eq!(variant.mcu_height, 8);
+
+        let image = decode_jpeg(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (16, 8));
+        assert_close(&image.pixels[..3], reference_rgb(SKIN));
+        assert_close(&image.pixels[8 * 3..][..3], reference_rgb(BLUE));
+    }
+
+    /// A baseline JPEG from the encoder with its frame header patched.
+    fn patched_frame(marker: u8, precision: u8) -> Vec<u8> {
+        let mut bytes = crate::encode::encode_jpeg(&[90; 16 * 16 * 3], 16, 16, 90).unwrap();
+        let sof = bytes.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        bytes[sof + 1] = marker;
+        bytes[sof + 4] = precision;
+        bytes
+    }
+
+    #[test]
+    fn test_12_bit_is_unsupported_variant() {
+        let bytes = patched_frame(0xC1, 12);
+        let variant = JpegVariant::read(&bytes).unwrap();
+        assert_eq!(variant.precision, 12);
+        assert_eq!(variant.components, 3);
+        // The encoder does not subsample chroma, so MCUs are 8 rows
+        assert_eq!(variant.mcu_height, 8);
+
+        let err = decode_jpeg(&bytes).unwrap_err();
+        assert_eq!(err.code(), "UNSUPPORTED_VARIANT");
+        match err {
+            DecodeError::UnsupportedVariant { detail } => assert!(detail.contains("12-bit")),
+            other => panic!("expected UnsupportedVariant, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_other_processes_are_unsupported_variants() {
+        for (marker, detail) in [
+            (0xC3, "lossless"),
+            (0xC5, "hierarchical"),
+            (0xC9, "arithmetic"),
+        ] {
+            match decode_jpeg(&patched_frame(marker, 8)) {
+                Err(DecodeError::UnsupportedVariant { detail: d }) => {
+                    assert!(d.contains(detail), "{:02X}: {}", marker, d)
+                }
+                other => panic!("{:02X}: got {:?}", marker, other),
+            }
+        }
+        for marker in [0xC0, 0xC1, 0xC2] {
+            assert_eq!(
+                JpegVariant::read(&patched_frame(marker, 8))
+                    .unwrap()
+                    .check_supported()
+                    .ok(),
+                Some(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_stops_at_non_jpeg_and_missing_frame() {
+        assert_eq!(JpegVariant::read(b"not a jpeg"), None);
+        assert_eq!(JpegVariant::read(&[0xFF, 0xD8, 0xFF, 0xD9]), None);
+        assert_eq!(JpegVariant::read(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]), None);
+    }
+}
@@ -0,0 +1,199 @@
+//! As-shot white balance from standard metadata tags.
+//!
+//! Camera white balance lives mostly in vendor MakerNotes, but two standard
+//! tags carry enough to seed the Kelvin slider:
+//!
+//! - `AsShotWhiteXY` (DNG) - the white point chromaticity, converted to a
+//!   correlated color temperature with McCamy's approximation
+//! - `LightSource` (EXIF) - the illuminant preset chosen in camera, mapped to
+//!   its nominal temperature
+
+use std::io::Cursor;
+
+use exif::{Context, In, Reader, Tag, Value};
+use serde::{Deserialize, Serialize};
+
+/// DNG `AsShotWhiteXY` tag (not predefined by the EXIF reader).
+const TAG_AS_SHOT_WHITE_XY: Tag = Tag(Context::Tiff, 0xC629);
+
+/// Where the as-shot white balance was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhiteBalanceSource {
+    /// DNG `AsShotWhiteXY` chromaticity.
+    AsShotWhiteXy,
+    /// EXIF `LightSource` preset.
+    LightSource,
+}
+
+/// White balance recorded by the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AsShotWhiteBalance {
+    /// Correlated color temperature in Kelvin.
+    pub kelvin: f32,
+    /// Tint on the -100 to 100 scale (0 when the source has no tint).
+    pub tint: f32,
+    /// Tag the value was derived from.
+    pub source: WhiteBalanceSource,
+}
+
+/// Read the as-shot white balance from a file's metadata.
+///
+/// Returns `None` if no EXIF data is found, or if the camera recorded auto
+/// white balance without a specific illuminant.
+pub fn get_as_shot_white_balance(bytes: &[u8]) -> Option<AsShotWhiteBalance> {
+    let mut cursor = Cursor::new(bytes);
+    let exif = Reader::new().read_from_container(&mut cursor).ok()?;
+
+    if let Some(field) = exif.get_field(TAG_AS_SHOT_WHITE_XY, In::PRIMARY) {
+        if let Value::Rational(ref xy) = field.value {
+            if let [x, y, ..] = xy[..] {
+                if let Some(kelvin) = cct_from_xy(x.to_f64(), y.to_f64()) {
+                    return Some(AsShotWhiteBalance {
+                        kelvin,
+                        tint: 0.0,
+                        source: WhiteBalanceSource::AsShotWhiteXy,
+                    });
+                }
+            }
+        }
+    }
+
+    let light_source = exif
+        .get_field(Tag::LightSource, In::PRIMARY)?
+        .value
+        .get_uint(0)?;
+    light_source_kelvin(light_source).map(|kelvin| AsShotWhiteBalance {
+        kelvin,
+        tint: 0.0,
+        source: WhiteBalanceSource::LightSource,
+    })
+}
+
+/// McCamy's approximation of correlated color temperature from CIE xy.
+fn cct_from_xy(x: f64, y: f64) -> Option<f32> {
+    if !(x.is_finite() && y.is_finite()) || (0.1858 - y).abs() < 1e-6 {
+        return None;
+    }
+    let n = (x - 0.3320) / (0.1858 - y);
+    let cct = 449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33;
+    (1000.0..=25000.0).contains(&cct).then_some(cct as f32)
+}
+
+/// Nominal temperature of an EXIF `LightSource` value.
+fn light_source_kelvin(value: u32) -> Option<f32> {
+    let kelvin = match value {
+        1 | 4 | 9 => 5500.0, // Daylight, flash, fine weather
+        2 | 15 => 3525.0,    // Fluorescent, white fluorescent
+        3 => 2850.0,         // Tungsten
+        10 => 6500.0,        // Cloudy
+        11 => 7500.0,        // Shade
+        12 => 6400.0,        // Daylight fluorescent
+        13 => 5000.0,        // Day white fluorescent
+        14 => 4150.0,        // Cool white fluorescent
+        16 => 3000.0,        // Warm white fluorescent
+        17 => 2856.0,        // Standard light A
+        18 => 4874.0,        // Standard light B
+        19 => 6774.0,        // Standard light C
+        20 => 5503.0,        // D55
+        21 => 6504.0,        // D65
+        22 => 7504.0,        // D75
+        23 => 5003.0,        // D50
+        24 => 3200.0,        // ISO studio tungsten
+        _ => return None,    // Unknown (0) or other (255)
+    };
+    Some(kelvin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wrap a single IFD entry in a minimal little-endian TIFF inside a JPEG APP1.
+    ///
+    /// With `exif_ifd` the entry goes in the Exif sub-IFD, otherwise in IFD0.
+    fn jpeg_with_entry(
+        exif_ifd: bool,
+        tag: u16,
+        field_type: u16,
+        count: u32,
+        value: &[u8],
+    ) -> Vec<u8> {
+        let entry = |tiff: &mut Vec<u8>, tag: u16, field_type: u16, count: u32, data: [u8; 4]| {
+            tiff.extend_from_slice(&1u16.to_le_bytes());
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&field_type.to_le_bytes());
+            tiff.extend_from_slice(&count.to_le_bytes());
+            tiff.extend_from_slice(&data);
+            tiff.extend_from_slice(&0u32.to_le_bytes());
+        };
+
+        let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        if exif_ifd {
+            // IFD0 holds only the Exif IFD pointer; the sub-IFD follows at 26
+            entry(&mut tiff, 0x8769, 4, 1, 26u32.to_le_bytes());
+        }
+        let value_offset = tiff.len() as u32 + 18;
+        let data = if value.len() <= 4 {
+            let mut inline = [0u8; 4];
+            inline[..value.len()].copy_from_slice(value);
+            inline
+        } else {
+            value_offset.to_le_bytes()
+        };
+        entry(&mut tiff, tag, field_type, count, data);
+        if value.len() > 4 {
+            tiff.extend_from_slice(value);
+        }
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(app1);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_light_source_tungsten() {
+        // LightSource is SHORT (3)
+        let jpeg = jpeg_with_entry(true, 0x9208, 3, 1, &3u16.to_le_bytes());
+        let wb = get_as_shot_white_balance(&jpeg).unwrap();
+        assert_eq!(wb.kelvin, 2850.0);
+        assert_eq!(wb.source, WhiteBalanceSource::LightSource);
+    }
+
+    #[test]
+    fn test_light_source_unknown_is_none() {
+        let jpeg = jpeg_with_entry(true, 0x9208, 3, 1, &0u16.to_le_bytes());
+        assert_eq!(get_as_shot_white_balance(&jpeg), None);
+    }
+
+    #[test]
+    fn test_as_shot_white_xy() {
+        // D65 white point as two RATIONALs (5)
+        let mut value = Vec::new();
+        for (num, den) in [(31271u32, 100000u32), (32902, 100000)] {
+            value.extend_from_slice(&num.to_le_bytes());
+            value.extend_from_slice(&den.to_le_bytes());
+        }
+        let jpeg = jpeg_with_entry(false, 0xC629, 5, 2, &value);
+        let wb = get_as_shot_white_balance(&jpeg).unwrap();
+        assert_eq!(wb.source, WhiteBalanceSource::AsShotWhiteXy);
+        assert!((wb.kelvin - 6504.0).abs() < 50.0, "kelvin = {}", wb.kelvin);
+    }
+
+    #[test]
+    fn test_no_exif_is_none() {
+        assert_eq!(get_as_shot_white_balance(&[0xFF, 0xD8, 0xFF, 0xD9]), None);
+        assert_eq!(get_as_shot_white_balance(&[]), None);
+    }
+
+    #[test]
+    fn test_mccamy_tungsten() {
+        // CIE illuminant A
+        let cct = cct_from_xy(0.44757, 0.40745).unwrap();
+        assert!((cct - 2856.0).abs() < 30.0, "cct = {}", cct);
+    }
+}
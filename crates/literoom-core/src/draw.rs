@@ -0,0 +1,342 @@
+//! Text drawing for burning labels, such as capture settings, into exports.
+//!
+//! Uses the same 5x7 bitmap font as composition watermarks, but keeps
+//! lowercase letters and covers all printable ASCII. Glyphs are scaled by
+//! whole pixels so they stay crisp, and everything is clipped at the image
+//! borders.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use literoom_core::compose::WatermarkAnchor;
+//! use literoom_core::draw::{draw_capture_info, CaptureInfo};
+//!
+//! // "1/250s f/2.8 ISO400 85mm" in the bottom-right corner, at 3x size
+//! let info = CaptureInfo::from_metadata(&metadata);
+//! draw_capture_info(&mut image, &info, WatermarkAnchor::BottomRight, 3);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::compose::{
+    ascii_glyph, draw_glyphs, fill_rect, WatermarkAnchor, GLYPH_ADVANCE, GLYPH_HEIGHT,
+};
+use crate::decode::{DecodedImage, ImageMetadata};
+
+/// Text color of burned-in capture info.
+pub const CAPTURE_INFO_TEXT: (u8, u8, u8) = (255, 255, 255);
+
+/// Color of the box behind burned-in capture info.
+pub const CAPTURE_INFO_BACKGROUND: (u8, u8, u8) = (24, 24, 24);
+
+/// Size in pixels, as (width, height), of `text` drawn at `scale`.
+///
+/// Covers the glyphs only: there is no spacing after the last character.
+/// Empty text measures (0, 0). A `scale` of 0 is treated as 1.
+pub fn measure_text(text: &str, scale: u32) -> (u32, u32) {
+    let scale = scale.max(1);
+    let chars = text.chars().count().min(u32::MAX as usize) as u32;
+    if chars == 0 {
+        return (0, 0);
+    }
+    let width = (chars.saturating_mul(GLYPH_ADVANCE) - 1).saturating_mul(scale);
+    (width, GLYPH_HEIGHT.saturating_mul(scale))
+}
+
+/// Draw a single line of text with its top-left corner at (`x`, `y`).
+///
+/// The scaled, full-ASCII counterpart of
+/// [`compose::draw_text`](crate::compose::draw_text). Each font pixel
+/// becomes a `scale` x `scale` block. Pixels falling outside the image are
+/// clipped.
+///
+/// # Arguments
+///
+/// * `image` - Image to draw into
+/// * `text` - Text to draw; characters outside printable ASCII render as `?`
+/// * `x`, `y` - Top-left position of the first glyph
+/// * `scale` - Whole-pixel magnification; 0 is treated as 1
+/// * `color` - RGB text color
+/// * `background` - Color of a box filling exactly [`measure_text`]'s
+///   extent behind the text; with `None` only the glyphs are written
+pub fn draw_text(
+    image: &mut DecodedImage,
+    text: &str,
+    x: u32,
+    y: u32,
+    scale: u32,
+    color: (u8, u8, u8),
+    background: Option<(u8, u8, u8)>,
+) {
+    let scale = scale.max(1);
+    if let Some(background) = background {
+        let (width, height) = measure_text(text, scale);
+        fill_rect(
+            image,
+            x as u64,
+            y as u64,
+            width as u64,
+            height as u64,
+            background,
+        );
+    }
+
+    draw_glyphs(image, text, x, y, scale, color, ascii_glyph);
+}
+
+/// Capture settings shown by [`draw_capture_info`].
+///
+/// Field names match [`ImageMetadata`], so a serialized metadata object
+/// deserializes directly; missing fields are left out of the label.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureInfo {
+    /// Shutter speed as a string (e.g., "1/250").
+    pub shutter_speed: Option<String>,
+    /// Aperture as f-number (e.g., 2.8).
+    pub aperture: Option<f32>,
+    /// ISO sensitivity.
+    pub iso: Option<u32>,
+    /// Focal length in mm.
+    pub focal_length: Option<f32>,
+}
+
+impl CaptureInfo {
+    /// Take the capture settings from image metadata.
+    pub fn from_metadata(metadata: &ImageMetadata) -> Self {
+        Self {
+            shutter_speed: metadata.shutter_speed.clone(),
+            aperture: metadata.aperture,
+            iso: metadata.iso,
+            focal_length: metadata.focal_length,
+        }
+    }
+
+    /// One-line label such as "1/250s f/2.8 ISO400 85mm".
+    ///
+    /// Missing, empty or non-positive values are skipped; with none left
+    /// the label is empty.
+    pub fn label(&self) -> String {
+        let mut parts = Vec::with_capacity(4);
+        if let Some(shutter) = self.shutter_speed.as_deref().map(str::trim) {
+            if !shutter.is_empty() {
+                let unit = if shutter.ends_with('s') { "" } else { "s" };
+                parts.push(format!("{}{}", shutter, unit));
+            }
+        }
+        if let Some(aperture) = self.aperture.filter(|a| a.is_finite() && *a > 0.0) {
+            parts.push(format!("f/{}", (aperture * 10.0).round() / 10.0));
+        }
+        if let Some(iso) = self.iso.filter(|iso| *iso > 0) {
+            parts.push(format!("ISO{}", iso));
+        }
+        if let Some(focal) = self.focal_length.filter(|f| f.is_finite() && *f > 0.0) {
+            parts.push(format!("{}mm", focal.round()));
+        }
+        parts.join(" ")
+    }
+}
+
+/// Burn the capture settings into a corner of `image`.
+///
+/// The label is drawn with [`CAPTURE_INFO_TEXT`] on a
+/// [`CAPTURE_INFO_BACKGROUND`] box padded by two font pixels, one line
+/// height away from the anchored edges. Nothing is drawn when the label is
+/// empty.
+///
+/// # Arguments
+///
+/// * `image` - Image to draw into
+/// * `info` - Capture settings to show
+/// * `corner` - Where to place the label
+/// * `scale` - Whole-pixel magnification; 0 is treated as 1
+pub fn draw_capture_info(
+    image: &mut DecodedImage,
+    info: &CaptureInfo,
+    corner: WatermarkAnchor,
+    scale: u32,
+) {
+    let label = info.label();
+    if label.is_empty() {
+        return;
+    }
+    let scale = scale.max(1);
+    let padding = 2u32.saturating_mul(scale);
+    let (text_width, text_height) = measure_text(&label, scale);
+    let boxed = (
+        text_width.saturating_add(padding.saturating_mul(2)),
+        text_height.saturating_add(padding.saturating_mul(2)),
+    );
+    let margin = GLYPH_HEIGHT.saturating_mul(scale);
+    let (bx, by) = corner.origin((image.width, image.height), boxed, margin);
+
+    fill_rect(
+        image,
+        bx as u64,
+        by as u64,
+        boxed.0 as u64,
+        boxed.1 as u64,
+        CAPTURE_INFO_BACKGROUND,
+    );
+    draw_text(
+        image,
+        &label,
+        bx.saturating_add(padding),
+        by.saturating_add(padding),
+        scale,
+        CAPTURE_INFO_TEXT,
+        None,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: (u8, u8, u8) = (255, 255, 255);
+    const GRAY: (u8, u8, u8) = (90, 90, 90);
+
+    fn canvas(width: u32, height: u32) -> DecodedImage {
+        DecodedImage::new(width, height, vec![GRAY.0; (width * height * 3) as usize])
+    }
+
+    /// Bounding box (x, y, width, height) of pixels that are no longer gray.
+    fn touched_bounds(image: &DecodedImage) -> Option<(u32, u32, u32, u32)> {
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+        for y in 0..image.height {
+            for x in 0..image.width {
+                if image.pixel(x, y) == Some(GRAY) {
+                    continue;
+                }
+                let (x0, y0, x1, y1) = bounds.unwrap_or((x, y, x, y));
+                bounds = Some((x0.min(x), y0.min(y), x1.max(x), y1.max(y)));
+            }
+        }
+        bounds.map(|(x0, y0, x1, y1)| (x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+    }
+
+    #[test]
+    fn test_glyphs_match_font_table() {
+        for scale in [1, 3] {
+            for c in ['a', 'R', '7', '%', 'g'] {
+                let mut image = canvas(5 * scale, 7 * scale);
+                draw_text(&mut image, &c.to_string(), 0, 0, scale, WHITE, None);
+
+                let rows = ascii_glyph(c);
+                for y in 0..image.height {
+                    for x in 0..image.width {
+                        let set = rows[(y / scale) as usize] & (0x10 >> (x / scale)) != 0;
+                        let expected = if set { WHITE } else { GRAY };
+                        assert_eq!(image.pixel(x, y), Some(expected), "{:?} at {},{}", c, x, y);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lowercase_differs_from_uppercase() {
+        let mut lower = canvas(20, 7);
+        let mut upper = canvas(20, 7);
+        draw_text(&mut lower, "iso", 0, 0, 1, WHITE, None);
+        draw_text(&mut upper, "ISO", 0, 0, 1, WHITE, None);
+        assert_ne!(lower.pixels, upper.pixels);
+    }
+
+    #[test]
+    fn test_draw_text_clips_at_borders() {
+        let mut image = canvas(20, 12);
+        draw_text(&mut image, "Hello", 10, 5, 4, WHITE, Some((0, 0, 0)));
+        let (x, y, w, h) = touched_bounds(&image).unwrap();
+        assert_eq!((x, y), (10, 5));
+        assert_eq!((x + w, y + h), (20, 12));
+
+        // Fully outside: nothing drawn, no panic
+        let mut image = canvas(20, 12);
+        draw_text(
+            &mut image,
+            "Hello",
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            WHITE,
+            Some((0, 0, 0)),
+        );
+        assert_eq!(touched_bounds(&image), None);
+    }
+
+    #[test]
+    fn test_measure_text_matches_touched_pixels() {
+        // 'M' reaches the left and top edges of its cell, '_' the right and bottom
+        for scale in [1, 2, 5] {
+            let mut image = canvas(200, 50);
+            draw_text(&mut image, "M2.8_", 3, 4, scale, WHITE, None);
+            let (w, h) = measure_text("M2.8_", scale);
+            assert_eq!(touched_bounds(&image), Some((3, 4, w, h)));
+        }
+        assert_eq!(measure_text("", 3), (0, 0));
+        assert_eq!(measure_text("ab", 0), measure_text("ab", 1));
+    }
+
+    #[test]
+    fn test_background_covers_measured_extent() {
+        let background = (0, 0, 255);
+        let mut image = canvas(60, 30);
+        draw_text(&mut image, "f/2.8 iso", 3, 4, 1, WHITE, Some(background));
+
+        let (w, h) = measure_text("f/2.8 iso", 1);
+        assert_eq!(touched_bounds(&image), Some((3, 4, w, h)));
+        for y in 4..4 + h {
+            for x in 3..3 + w {
+                let px = image.pixel(x, y).unwrap();
+                assert!(px == WHITE || px == background, "{:?} at {},{}", px, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_capture_info_label() {
+        let metadata = ImageMetadata {
+            shutter_speed: Some("1/250".to_string()),
+            aperture: Some(2.8),
+            iso: Some(400),
+            focal_length: Some(85.0),
+            ..Default::default()
+        };
+        let info = CaptureInfo::from_metadata(&metadata);
+        assert_eq!(info.label(), "1/250s f/2.8 ISO400 85mm");
+
+        let partial = CaptureInfo {
+            aperture: Some(8.0),
+            focal_length: Some(0.0),
+            ..Default::default()
+        };
+        assert_eq!(partial.label(), "f/8");
+        assert_eq!(CaptureInfo::default().label(), "");
+    }
+
+    #[test]
+    fn test_draw_capture_info_corner() {
+        let info = CaptureInfo {
+            iso: Some(100),
+            ..Default::default()
+        };
+        let mut image = canvas(200, 100);
+        draw_capture_info(&mut image, &info, WatermarkAnchor::BottomRight, 2);
+
+        let (text_w, text_h) = measure_text("ISO100", 2);
+        let (x, y, w, h) = touched_bounds(&image).unwrap();
+        assert_eq!((w, h), (text_w + 8, text_h + 8));
+        assert_eq!((x + w, y + h), (200 - 14, 100 - 14));
+        assert_eq!(image.pixel(x, y), Some(CAPTURE_INFO_BACKGROUND));
+
+        let mut untouched = canvas(200, 100);
+        draw_capture_info(
+            &mut untouched,
+            &CaptureInfo::default(),
+            WatermarkAnchor::TopLeft,
+            2,
+        );
+        assert_eq!(touched_bounds(&untouched), None);
+    }
+}
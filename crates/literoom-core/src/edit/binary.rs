@@ -0,0 +1,995 @@
+//! Compact binary encoding and delta encoding of `EditState`.
+
+use super::types::{CropRect, EditState, LinearMaskEdit, LutReference, RadialMaskEdit};
+use crate::adjustments::ChannelLevels;
+use crate::base_curve::BaseCurve;
+use crate::color::ColorModel;
+use crate::mask::{LinearGradientMask, RadialGradientMask};
+use crate::monochrome::BwMix;
+use crate::noise_reduction::NoiseReductionParams;
+use crate::{BasicAdjustments, CurvePoint, ToneCurve};
+use thiserror::Error;
+
+/// Current binary format version. Bumped on incompatible layout changes.
+pub const FORMAT_VERSION: u8 = 1;
+
+const MAGIC: [u8; 2] = *b"LR";
+const KIND_FULL: u8 = 0;
+const KIND_DELTA: u8 = 1;
+
+// Field tags. Tags 0x01..=0x0A are the basic adjustments in declaration order.
+const TAG_ADJUSTMENT_FIRST: u8 = 0x01;
+const TAG_PROTECT_SKIN_TONES: u8 = 0x0B;
+const TAG_COLOR_MODEL: u8 = 0x0C;
+const TAG_TONE_CURVE: u8 = 0x10;
+const TAG_BASE_CURVE: u8 = 0x11;
+const TAG_MONOCHROME: u8 = 0x12;
+const TAG_BW_MIX: u8 = 0x13;
+const TAG_CHANNEL_LEVELS: u8 = 0x14;
+const TAG_NOISE_REDUCTION: u8 = 0x15;
+const TAG_AUTO_NR: u8 = 0x16;
+const TAG_LUT: u8 = 0x17;
+const TAG_CROP: u8 = 0x20;
+const TAG_ROTATION_ANGLE: u8 = 0x21;
+const TAG_STRAIGHTEN: u8 = 0x22;
+const TAG_LINEAR_MASKS: u8 = 0x30;
+const TAG_RADIAL_MASKS: u8 = 0x31;
+
+/// Number of scalar fields in `BasicAdjustments`.
+const ADJUSTMENT_COUNT: usize = 10;
+
+/// Bit in a mask's adjustment bitmask for `protect_skin_tones`, which has
+/// no value of its own.
+const PROTECT_SKIN_TONES_BIT: u16 = 1 << ADJUSTMENT_COUNT;
+
+/// Bit in a mask's adjustment bitmask set for the Oklab color model.
+const OKLAB_BIT: u16 = 1 << (ADJUSTMENT_COUNT + 1);
+
+/// Errors that can occur while decoding an edit state.
+#[derive(Debug, Error)]
+pub enum EditError {
+    /// The data does not start with the edit state magic bytes.
+    #[error("Not an edit state encoding")]
+    InvalidMagic,
+
+    /// The data was written by an incompatible format version.
+    #[error("Unsupported edit state format version: {0}")]
+    UnsupportedVersion(u8),
+
+    /// A full encoding was passed where a delta was expected, or vice versa.
+    #[error("Expected a {expected} edit state encoding")]
+    WrongKind { expected: &'static str },
+
+    /// The data ended in the middle of a field.
+    #[error("Edit state data is truncated")]
+    Truncated,
+
+    /// The data contains a field tag this version does not know.
+    #[error("Unknown edit state field tag: {0:#04x}")]
+    UnknownTag(u8),
+
+    /// A mask id is not valid UTF-8.
+    #[error("Invalid UTF-8 in mask id")]
+    InvalidString,
+
+    /// The base curve field holds a value this version does not know.
+    #[error("Unknown base curve: {0}")]
+    UnknownBaseCurve(u8),
+
+    /// The color model field holds a value this version does not know.
+    #[error("Unknown color model: {0}")]
+    UnknownColorModel(u8),
+
+    /// The delta was built against a different base state.
+    #[error(
+        "Delta was built against a different base state \
+         (expected checksum {expected:#010x}, got {actual:#010x})"
+    )]
+    BaseMismatch { expected: u32, actual: u32 },
+}
+
+impl EditState {
+    /// Encode the state in the compact binary format.
+    ///
+    /// Only fields that differ from `EditState::default()` are stored, so an
+    /// unedited photo encodes to the 4-byte header alone.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = header(KIND_FULL);
+        write_changes(&mut out, &EditState::default(), self);
+        out
+    }
+
+    /// Decode a state produced by [`EditState::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an `EditError` if the data is not a full edit state encoding
+    /// of a supported version, or if it is truncated or malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EditError> {
+        let mut reader = Reader::new(bytes);
+        read_header(&mut reader, KIND_FULL)?;
+
+        let mut state = EditState::default();
+        read_changes(&mut reader, &mut state)?;
+        Ok(state)
+    }
+}
+
+/// Encode only the fields that changed from `prev` to `next`.
+///
+/// The delta records a checksum of `prev`, so applying it to any other base
+/// fails instead of silently producing a mixed state. A delta between equal
+/// states contains only the 8-byte header and checksum.
+pub fn delta_encode(prev: &EditState, next: &EditState) -> Vec<u8> {
+    let mut out = header(KIND_DELTA);
+    out.extend_from_slice(&checksum(prev).to_le_bytes());
+    write_changes(&mut out, prev, next);
+    out
+}
+
+/// Apply a delta produced by [`delta_encode`] to its base state.
+///
+/// # Errors
+///
+/// Returns `EditError::BaseMismatch` if `prev` is not the state the delta
+/// was built against, or another `EditError` if the delta is malformed.
+pub fn delta_apply(prev: &EditState, delta: &[u8]) -> Result<EditState, EditError> {
+    let mut reader = Reader::new(delta);
+    read_header(&mut reader, KIND_DELTA)?;
+
+    let expected = reader.u32()?;
+    let actual = checksum(prev);
+    if expected != actual {
+        return Err(EditError::BaseMismatch { expected, actual });
+    }
+
+    let mut state = prev.clone();
+    read_changes(&mut reader, &mut state)?;
+    Ok(state)
+}
+
+fn header(kind: u8) -> Vec<u8> {
+    vec![MAGIC[0], MAGIC[1], FORMAT_VERSION, kind]
+}
+
+fn read_header(reader: &mut Reader, expected_kind: u8) -> Result<(), EditError> {
+    let header = reader.take(4)?;
+    if header[0..2] != MAGIC {
+        return Err(EditError::InvalidMagic);
+    }
+    if header[2] != FORMAT_VERSION {
+        return Err(EditError::UnsupportedVersion(header[2]));
+    }
+    if header[3] != expected_kind {
+        let expected = if expected_kind == KIND_DELTA {
+            "delta"
+        } else {
+            "full"
+        };
+        return Err(EditError::WrongKind { expected });
+    }
+    Ok(())
+}
+
+/// FNV-1a hash of the full encoding, used to identify a delta's base.
+fn checksum(state: &EditState) -> u32 {
+    state.to_bytes().iter().fold(0x811c_9dc5u32, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn adjustment_values(adj: &BasicAdjustments) -> [f32; ADJUSTMENT_COUNT] {
+    [
+        adj.temperature,
+        adj.tint,
+        adj.exposure,
+        adj.contrast,
+        adj.highlights,
+        adj.shadows,
+        adj.whites,
+        adj.blacks,
+        adj.vibrance,
+        adj.saturation,
+    ]
+}
+
+fn adjustment_slot(adj: &mut BasicAdjustments, index: usize) -> &mut f32 {
+    match index {
+        0 => &mut adj.temperature,
+        1 => &mut adj.tint,
+        2 => &mut adj.exposure,
+        3 => &mut adj.contrast,
+        4 => &mut adj.highlights,
+        5 => &mut adj.shadows,
+        6 => &mut adj.whites,
+        7 => &mut adj.blacks,
+        8 => &mut adj.vibrance,
+        _ => &mut adj.saturation,
+    }
+}
+
+// ===== Writing =====
+
+fn write_changes(out: &mut Vec<u8>, prev: &EditState, next: &EditState) {
+    let old = adjustment_values(&prev.adjustments);
+    let new = adjustment_values(&next.adjustments);
+    for (i, (a, b)) in old.iter().zip(new.iter()).enumerate() {
+        if a != b {
+            out.push(TAG_ADJUSTMENT_FIRST + i as u8);
+            write_f32(out, *b);
+        }
+    }
+    if prev.adjustments.protect_skin_tones != next.adjustments.protect_skin_tones {
+        out.push(TAG_PROTECT_SKIN_TONES);
+        out.push(next.adjustments.protect_skin_tones as u8);
+    }
+    if prev.adjustments.color_model != next.adjustments.color_model {
+        out.push(TAG_COLOR_MODEL);
+        out.push(match next.adjustments.color_model {
+            ColorModel::Legacy => 0,
+            ColorModel::Oklab => 1,
+        });
+    }
+
+    if prev.tone_curve != next.tone_curve {
+        out.push(TAG_TONE_CURVE);
+        write_curve(out, &next.tone_curve);
+    }
+
+    if prev.base_curve != next.base_curve {
+        out.push(TAG_BASE_CURVE);
+        out.push(match next.base_curve {
+            None => 0,
+            Some(BaseCurve::Neutral) => 1,
+            Some(BaseCurve::CameraStandardApprox) => 2,
+            Some(BaseCurve::Flat) => 3,
+        });
+    }
+
+    if prev.monochrome != next.monochrome {
+        out.push(TAG_MONOCHROME);
+        out.push(next.monochrome as u8);
+    }
+    if prev.bw_mix != next.bw_mix {
+        out.push(TAG_BW_MIX);
+        match &next.bw_mix {
+            Some(mix) => {
+                out.push(1);
+                for value in [mix.red, mix.green, mix.blue] {
+                    write_f32(out, value);
+                }
+            }
+            None => out.push(0),
+        }
+    }
+
+    if prev.channel_levels != next.channel_levels {
+        out.push(TAG_CHANNEL_LEVELS);
+        let levels = &next.channel_levels;
+        for value in levels.black.iter().chain(&levels.white) {
+            write_f32(out, *value);
+        }
+    }
+
+    if prev.noise_reduction != next.noise_reduction {
+        out.push(TAG_NOISE_REDUCTION);
+        match &next.noise_reduction {
+            Some(nr) => {
+                out.push(1);
+                write_f32(out, nr.luminance);
+                write_f32(out, nr.color);
+            }
+            None => out.push(0),
+        }
+    }
+    if prev.auto_nr != next.auto_nr {
+        out.push(TAG_AUTO_NR);
+        out.push(next.auto_nr as u8);
+    }
+
+    if prev.lut != next.lut {
+        out.push(TAG_LUT);
+        match &next.lut {
+            Some(lut) => {
+                out.push(1);
+                write_string(out, &lut.id);
+                write_f32(out, lut.strength);
+            }
+            None => out.push(0),
+        }
+    }
+
+    if prev.crop != next.crop {
+        out.push(TAG_CROP);
+        match &next.crop {
+            Some(crop) => {
+                out.push(1);
+                for value in [crop.left, crop.top, crop.width, crop.height] {
+                    write_f32(out, value);
+                }
+            }
+            None => out.push(0),
+        }
+    }
+
+    if prev.rotation.angle != next.rotation.angle {
+        out.push(TAG_ROTATION_ANGLE);
+        write_f32(out, next.rotation.angle);
+    }
+    if prev.rotation.straighten != next.rotation.straighten {
+        out.push(TAG_STRAIGHTEN);
+        write_f32(out, next.rotation.straighten);
+    }
+
+    if prev.linear_masks != next.linear_masks {
+        out.push(TAG_LINEAR_MASKS);
+        write_varint(out, next.linear_masks.len() as u64);
+        for mask in &next.linear_masks {
+            write_linear_mask(out, mask);
+        }
+    }
+    if prev.radial_masks != next.radial_masks {
+        out.push(TAG_RADIAL_MASKS);
+        write_varint(out, next.radial_masks.len() as u64);
+        for mask in &next.radial_masks {
+            write_radial_mask(out, mask);
+        }
+    }
+}
+
+fn write_f32(out: &mut Vec<u8>, value: f32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Write an unsigned LEB128 varint.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    write_varint(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_curve(out: &mut Vec<u8>, curve: &ToneCurve) {
+    write_varint(out, curve.points.len() as u64);
+    for point in &curve.points {
+        write_f32(out, point.x);
+        write_f32(out, point.y);
+    }
+}
+
+fn write_optional_curve(out: &mut Vec<u8>, curve: Option<&ToneCurve>) {
+    match curve {
+        Some(curve) => {
+            out.push(1);
+            write_curve(out, curve);
+        }
+        None => out.push(0),
+    }
+}
+
+/// Write adjustments as a bitmask of non-default fields followed by their values.
+fn write_adjustments(out: &mut Vec<u8>, adj: &BasicAdjustments) {
+    let values = adjustment_values(adj);
+    let mask = values
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| **v != 0.0)
+        .fold(0u16, |mask, (i, _)| mask | (1 << i));
+    let mask = if adj.protect_skin_tones {
+        mask | PROTECT_SKIN_TONES_BIT
+    } else {
+        mask
+    };
+    let mask = match adj.color_model {
+        ColorModel::Legacy => mask,
+        ColorModel::Oklab => mask | OKLAB_BIT,
+    };
+    out.extend_from_slice(&mask.to_le_bytes());
+    for value in values.iter().filter(|v| **v != 0.0) {
+        write_f32(out, *value);
+    }
+}
+
+fn write_linear_mask(out: &mut Vec<u8>, edit: &LinearMaskEdit) {
+    write_string(out, &edit.id);
+    let m = &edit.mask;
+    for value in [m.start_x, m.start_y, m.end_x, m.end_y, m.feather] {
+        write_f32(out, value);
+    }
+    // Older encodings hold only the enabled flag in this byte
+    out.push(edit.enabled as u8 | (m.invert as u8) << 1);
+    write_adjustments(out, &edit.adjustments);
+    write_optional_curve(out, edit.tone_curve.as_ref());
+}
+
+fn write_radial_mask(out: &mut Vec<u8>, edit: &RadialMaskEdit) {
+    write_string(out, &edit.id);
+    let m = &edit.mask;
+    for value in [
+        m.center_x, m.center_y, m.radius_x, m.radius_y, m.rotation, m.feather,
+    ] {
+        write_f32(out, value);
+    }
+    out.push(m.invert as u8);
+    out.push(edit.enabled as u8);
+    write_adjustments(out, &edit.adjustments);
+    write_optional_curve(out, edit.tone_curve.as_ref());
+}
+
+// ===== Reading =====
+
+fn read_changes(reader: &mut Reader, state: &mut EditState) -> Result<(), EditError> {
+    let adjustment_tags = TAG_ADJUSTMENT_FIRST..TAG_ADJUSTMENT_FIRST + ADJUSTMENT_COUNT as u8;
+
+    while !reader.is_empty() {
+        let tag = reader.u8()?;
+        match tag {
+            t if adjustment_tags.contains(&t) => {
+                let index = (t - TAG_ADJUSTMENT_FIRST) as usize;
+                *adjustment_slot(&mut state.adjustments, index) = reader.f32()?;
+            }
+            TAG_PROTECT_SKIN_TONES => state.adjustments.protect_skin_tones = reader.bool()?,
+            TAG_COLOR_MODEL => {
+                state.adjustments.color_model = match reader.u8()? {
+                    0 => ColorModel::Legacy,
+                    1 => ColorModel::Oklab,
+                    other => return Err(EditError::UnknownColorModel(other)),
+                };
+            }
+            TAG_TONE_CURVE => state.tone_curve = read_curve(reader)?,
+            TAG_BASE_CURVE => {
+                state.base_curve = match reader.u8()? {
+                    0 => None,
+                    1 => Some(BaseCurve::Neutral),
+                    2 => Some(BaseCurve::CameraStandardApprox),
+                    3 => Some(BaseCurve::Flat),
+                    other => return Err(EditError::UnknownBaseCurve(other)),
+                };
+            }
+            TAG_MONOCHROME => state.monochrome = reader.bool()?,
+            TAG_BW_MIX => {
+                state.bw_mix = if reader.bool()? {
+                    Some(BwMix {
+                        red: reader.f32()?,
+                        green: reader.f32()?,
+                        blue: reader.f32()?,
+                    })
+                } else {
+                    None
+                };
+            }
+            TAG_CHANNEL_LEVELS => {
+                state.channel_levels = ChannelLevels {
+                    black: [reader.f32()?, reader.f32()?, reader.f32()?],
+                    white: [reader.f32()?, reader.f32()?, reader.f32()?],
+                };
+            }
+            TAG_NOISE_REDUCTION => {
+                state.noise_reduction = if reader.bool()? {
+                    Some(NoiseReductionParams {
+                        luminance: reader.f32()?,
+                        color: reader.f32()?,
+                    })
+                } else {
+                    None
+                };
+            }
+            TAG_AUTO_NR => state.auto_nr = reader.bool()?,
+            TAG_LUT => {
+                state.lut = if reader.bool()? {
+                    Some(LutReference {
+                        id: reader.string()?,
+                        strength: reader.f32()?,
+                    })
+                } else {
+                    None
+                };
+            }
+            TAG_CROP => {
+                state.crop = if reader.bool()? {
+                    Some(CropRect {
+                        left: reader.f32()?,
+                        top: reader.f32()?,
+                        width: reader.f32()?,
+                        height: reader.f32()?,
+                    })
+                } else {
+                    None
+                };
+            }
+            TAG_ROTATION_ANGLE => state.rotation.angle = reader.f32()?,
+            TAG_STRAIGHTEN => state.rotation.straighten = reader.f32()?,
+            TAG_LINEAR_MASKS => {
+                let count = reader.count()?;
+                state.linear_masks = (0..count)
+                    .map(|_| read_linear_mask(reader))
+                    .collect::<Result<_, _>>()?;
+            }
+            TAG_RADIAL_MASKS => {
+                let count = reader.count()?;
+                state.radial_masks = (0..count)
+                    .map(|_| read_radial_mask(reader))
+                    .collect::<Result<_, _>>()?;
+            }
+            _ => return Err(EditError::UnknownTag(tag)),
+        }
+    }
+
+    Ok(())
+}
+
+fn read_curve(reader: &mut Reader) -> Result<ToneCurve, EditError> {
+    let count = reader.count()?;
+    let points = (0..count)
+        .map(|_| Ok(CurvePoint::new(reader.f32()?, reader.f32()?)))
+        .collect::<Result<_, EditError>>()?;
+    Ok(ToneCurve { points })
+}
+
+fn read_optional_curve(reader: &mut Reader) -> Result<Option<ToneCurve>, EditError> {
+    if reader.bool()? {
+        Ok(Some(read_curve(reader)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_adjustments(reader: &mut Reader) -> Result<BasicAdjustments, EditError> {
+    let bytes = reader.take(2)?;
+    let mask = u16::from_le_bytes([bytes[0], bytes[1]]);
+
+    let mut adj = BasicAdjustments {
+        protect_skin_tones: mask & PROTECT_SKIN_TONES_BIT != 0,
+        color_model: if mask & OKLAB_BIT != 0 {
+            ColorModel::Oklab
+        } else {
+            ColorModel::Legacy
+        },
+        ..Default::default()
+    };
+    for i in 0..ADJUSTMENT_COUNT {
+        if mask & (1 << i) != 0 {
+            *adjustment_slot(&mut adj, i) = reader.f32()?;
+        }
+    }
+    Ok(adj)
+}
+
+fn read_linear_mask(reader: &mut Reader) -> Result<LinearMaskEdit, EditError> {
+    let id = reader.string()?;
+    let mask = LinearGradientMask::new(
+        reader.f32()?,
+        reader.f32()?,
+        reader.f32()?,
+        reader.f32()?,
+        reader.f32()?,
+    );
+    let flags = reader.u8()?;
+    Ok(LinearMaskEdit {
+        id,
+        mask: mask.with_invert(flags & 2 != 0),
+        enabled: flags & 1 != 0,
+        adjustments: read_adjustments(reader)?,
+        tone_curve: read_optional_curve(reader)?,
+    })
+}
+
+fn read_radial_mask(reader: &mut Reader) -> Result<RadialMaskEdit, EditError> {
+    let id = reader.string()?;
+    let mask = RadialGradientMask::new(
+        reader.f32()?,
+        reader.f32()?,
+        reader.f32()?,
+        reader.f32()?,
+        reader.f32()?,
+        reader.f32()?,
+        reader.bool()?,
+    );
+    Ok(RadialMaskEdit {
+        id,
+        mask,
+        enabled: reader.bool()?,
+        adjustments: read_adjustments(reader)?,
+        tone_curve: read_optional_curve(reader)?,
+    })
+}
+
+/// Bounds-checked cursor over encoded bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], EditError> {
+        if len > self.remaining() {
+            return Err(EditError::Truncated);
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, EditError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, EditError> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u32(&mut self) -> Result<u32, EditError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, EditError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    fn varint(&mut self) -> Result<u64, EditError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(EditError::Truncated)
+    }
+
+    /// Read an element count. Every element takes at least one byte, so a
+    /// count larger than the remaining data means the input is corrupt.
+    fn count(&mut self) -> Result<usize, EditError> {
+        let count = self.varint()?;
+        if count > self.remaining() as u64 {
+            return Err(EditError::Truncated);
+        }
+        Ok(count as usize)
+    }
+
+    fn string(&mut self) -> Result<String, EditError> {
+        let len = self.count()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| EditError::InvalidString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::Rotation;
+
+    /// A state with every field set to a non-default value.
+    fn full_state() -> EditState {
+        EditState {
+            channel_levels: ChannelLevels {
+                black: [0.04, 0.015, 0.06],
+                white: [0.95, 1.0, 0.9],
+            },
+            base_curve: Some(BaseCurve::CameraStandardApprox),
+            adjustments: BasicAdjustments {
+                temperature: 12.0,
+                tint: -4.0,
+                exposure: 0.7,
+                contrast: 15.0,
+                highlights: -30.0,
+                shadows: 25.0,
+                whites: 5.0,
+                blacks: -8.0,
+                vibrance: 20.0,
+                saturation: -10.0,
+                protect_skin_tones: true,
+                color_model: ColorModel::Legacy,
+            },
+            monochrome: true,
+            bw_mix: Some(BwMix {
+                red: 0.5,
+                green: 0.3,
+                blue: 0.2,
+            }),
+            noise_reduction: Some(NoiseReductionParams {
+                luminance: 15.0,
+                color: 35.0,
+            }),
+            auto_nr: true,
+            tone_curve: ToneCurve {
+                points: (0..16)
+                    .map(|i| {
+                        let x = i as f32 / 15.0;
+                        CurvePoint::new(x, x * x)
+                    })
+                    .collect(),
+            },
+            lut: Some(LutReference {
+                id: "kodak-portra-400".to_string(),
+                strength: 0.8,
+            }),
+            crop: Some(CropRect {
+                left: 0.1,
+                top: 0.05,
+                width: 0.8,
+                height: 0.9,
+            }),
+            rotation: Rotation {
+                angle: 90.0,
+                straighten: -2.5,
+            },
+            linear_masks: vec![LinearMaskEdit {
+                id: "5f0c1e2a-linear".to_string(),
+                mask: LinearGradientMask::new(0.0, 0.2, 0.0, 0.6, 0.5).with_invert(true),
+                enabled: true,
+                adjustments: BasicAdjustments {
+                    exposure: -1.0,
+                    highlights: -40.0,
+                    ..Default::default()
+                },
+                tone_curve: Some(ToneCurve {
+                    points: vec![CurvePoint::new(0.0, 0.1), CurvePoint::new(1.0, 0.9)],
+                }),
+            }],
+            radial_masks: vec![
+                RadialMaskEdit {
+                    id: "radial-1".to_string(),
+                    mask: RadialGradientMask::new(0.5, 0.4, 0.2, 0.3, 0.25, 0.8, true),
+                    enabled: false,
+                    adjustments: BasicAdjustments {
+                        saturation: -100.0,
+                        protect_skin_tones: true,
+                        ..Default::default()
+                    },
+                    tone_curve: None,
+                },
+                RadialMaskEdit {
+                    id: "radial-2 ✓".to_string(),
+                    mask: RadialGradientMask::new(0.1, 0.9, 0.05, 0.05, 0.0, 0.0, false),
+                    enabled: true,
+                    adjustments: BasicAdjustments::default(),
+                    tone_curve: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_round_trip_every_field() {
+        let state = full_state();
+        let bytes = state.to_bytes();
+        assert_eq!(EditState::from_bytes(&bytes).unwrap(), state);
+    }
+
+    #[test]
+    fn test_default_state_is_header_only() {
+        let bytes = EditState::default().to_bytes();
+        assert_eq!(bytes, vec![b'L', b'R', FORMAT_VERSION, KIND_FULL]);
+        assert!(EditState::from_bytes(&bytes).unwrap().is_default());
+    }
+
+    #[test]
+    fn test_encoding_is_much_smaller_than_json_fields() {
+        // 16-point curve + 3 masks + every slider set + channel levels + LUT
+        let bytes = full_state().to_bytes();
+        assert!(bytes.len() < 450, "encoded {} bytes", bytes.len());
+    }
+
+    #[test]
+    fn test_delta_of_identical_states_is_header_only() {
+        let state = full_state();
+        let delta = delta_encode(&state, &state);
+        assert_eq!(delta.len(), 8); // header + base checksum
+        assert_eq!(delta_apply(&state, &delta).unwrap(), state);
+    }
+
+    #[test]
+    fn test_single_slider_delta_is_small() {
+        let prev = full_state();
+        let mut next = prev.clone();
+        next.adjustments.exposure = 1.25;
+
+        let delta = delta_encode(&prev, &next);
+        assert!(delta.len() < 32, "delta is {} bytes", delta.len());
+        assert_eq!(delta_apply(&prev, &delta).unwrap(), next);
+    }
+
+    #[test]
+    fn test_delta_round_trips_structural_changes() {
+        let prev = full_state();
+        let mut next = prev.clone();
+        next.crop = None;
+        next.tone_curve = ToneCurve::default();
+        next.rotation.straighten = 0.0;
+        next.linear_masks.clear();
+        next.radial_masks[1].mask.feather = 0.3;
+
+        let delta = delta_encode(&prev, &next);
+        assert_eq!(delta_apply(&prev, &delta).unwrap(), next);
+
+        // And back again
+        let delta = delta_encode(&next, &prev);
+        assert_eq!(delta_apply(&next, &delta).unwrap(), prev);
+    }
+
+    #[test]
+    fn test_delta_against_different_base_errors() {
+        let prev = full_state();
+        let mut next = prev.clone();
+        next.adjustments.contrast = 50.0;
+        let delta = delta_encode(&prev, &next);
+
+        let mut other = prev.clone();
+        other.adjustments.tint = 3.0;
+        let err = delta_apply(&other, &delta).unwrap_err();
+        assert!(matches!(err, EditError::BaseMismatch { .. }));
+        assert!(err.to_string().contains("different base state"));
+    }
+
+    #[test]
+    fn test_full_and_delta_encodings_are_not_interchangeable() {
+        let state = full_state();
+        assert!(matches!(
+            EditState::from_bytes(&delta_encode(&state, &state)),
+            Err(EditError::WrongKind { expected: "full" })
+        ));
+        assert!(matches!(
+            delta_apply(&state, &state.to_bytes()),
+            Err(EditError::WrongKind { expected: "delta" })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_header_errors() {
+        assert!(matches!(
+            EditState::from_bytes(b"{}"),
+            Err(EditError::Truncated)
+        ));
+        assert!(matches!(
+            EditState::from_bytes(b"JSON"),
+            Err(EditError::InvalidMagic)
+        ));
+        assert!(matches!(
+            EditState::from_bytes(&[b'L', b'R', 99, KIND_FULL]),
+            Err(EditError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_body_errors() {
+        let bytes = full_state().to_bytes();
+        for len in [5, 10, bytes.len() / 2, bytes.len() - 1] {
+            assert!(
+                EditState::from_bytes(&bytes[..len]).is_err(),
+                "prefix of {} bytes decoded",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_tag_errors() {
+        let mut bytes = EditState::default().to_bytes();
+        bytes.push(0x7F);
+        assert!(matches!(
+            EditState::from_bytes(&bytes),
+            Err(EditError::UnknownTag(0x7F))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_base_curve_errors() {
+        let mut bytes = EditState::default().to_bytes();
+        bytes.extend_from_slice(&[TAG_BASE_CURVE, 9]);
+        assert!(matches!(
+            EditState::from_bytes(&bytes),
+            Err(EditError::UnknownBaseCurve(9))
+        ));
+    }
+
+    #[test]
+    fn test_color_model_round_trips() {
+        let prev = full_state();
+        let mut next = prev.clone();
+        next.adjustments.color_model = ColorModel::Oklab;
+        next.linear_masks[0].adjustments.color_model = ColorModel::Oklab;
+
+        assert_eq!(EditState::from_bytes(&next.to_bytes()).unwrap(), next);
+        let delta = delta_encode(&prev, &next);
+        assert_eq!(delta_apply(&prev, &delta).unwrap(), next);
+    }
+
+    #[test]
+    fn test_unknown_color_model_errors() {
+        let mut bytes = EditState::default().to_bytes();
+        bytes.extend_from_slice(&[TAG_COLOR_MODEL, 7]);
+        assert!(matches!(
+            EditState::from_bytes(&bytes),
+            Err(EditError::UnknownColorModel(7))
+        ));
+    }
+
+    #[test]
+    fn test_huge_count_does_not_allocate() {
+        let mut bytes = EditState::default().to_bytes();
+        bytes.push(TAG_LINEAR_MASKS);
+        write_varint(&mut bytes, u32::MAX as u64);
+        assert!(matches!(
+            EditState::from_bytes(&bytes),
+            Err(EditError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        for value in [0u64, 1, 127, 128, 300, 16_384, u32::MAX as u64] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            assert_eq!(Reader::new(&out).varint().unwrap(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use crate::edit::Rotation;
+    use proptest::prelude::*;
+
+    fn adjustments_strategy() -> impl Strategy<Value = BasicAdjustments> {
+        prop::array::uniform10(-100.0f32..100.0).prop_map(|v| BasicAdjustments {
+            temperature: v[0],
+            tint: v[1],
+            exposure: v[2] / 20.0,
+            contrast: v[3],
+            highlights: v[4],
+            shadows: v[5],
+            whites: v[6],
+            blacks: v[7],
+            vibrance: v[8],
+            saturation: v[9],
+            protect_skin_tones: v[0] > 0.0,
+            color_model: if v[1] > 0.0 {
+                ColorModel::Oklab
+            } else {
+                ColorModel::Legacy
+            },
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_delta_round_trip(
+            prev_adj in adjustments_strategy(),
+            next_adj in adjustments_strategy(),
+            angle in -180.0f32..180.0,
+        ) {
+            let prev = EditState { adjustments: prev_adj, ..Default::default() };
+            let next = EditState {
+                adjustments: next_adj,
+                rotation: Rotation { angle, straighten: 0.0 },
+                ..Default::default()
+            };
+
+            prop_assert_eq!(EditState::from_bytes(&next.to_bytes()).unwrap(), next.clone());
+            let delta = delta_encode(&prev, &next);
+            prop_assert_eq!(delta_apply(&prev, &delta).unwrap(), next);
+        }
+    }
+}
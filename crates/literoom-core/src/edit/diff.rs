@@ -0,0 +1,221 @@
+//! Which render stages an edit touches.
+
+use crate::edit::EditState;
+use crate::render::RenderStage;
+use crate::{BasicAdjustments, LinearGradientMask, RadialGradientMask, ToneCurve};
+use serde::{Deserialize, Serialize};
+
+/// A mask layer as it renders: geometry, adjustments and curve.
+type Layer<'a, M> = (&'a M, &'a BasicAdjustments, &'a Option<ToneCurve>);
+
+/// For each [`RenderStage`], whether its settings differ between two edit
+/// states.
+///
+/// Only settings that change the render count: disabled masks, the B&W mix
+/// while monochrome is off, mask ids, and the noise reduction and LUT
+/// fields (which [`render_edit`](crate::render::render_edit) does not
+/// apply) are ignored. A changed transform sets every flag, since every
+/// later stage then works on a different frame and masks move with it.
+///
+/// A cached intermediate is only valid up to the first dirty stage; resume
+/// from [`first_dirty`](Self::first_dirty), which re-runs every later stage
+/// whatever its flag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageDiff {
+    /// Rotation or crop changed
+    pub transform: bool,
+    /// Channel levels, base curve or basic adjustments changed
+    pub adjustments: bool,
+    /// The B&W conversion changed
+    pub effects: bool,
+    /// The tone curve changed
+    pub curve: bool,
+    /// An enabled mask, or the color model masks share with the global
+    /// adjustments, changed
+    pub masks: bool,
+}
+
+impl StageDiff {
+    /// Whether the settings of `stage` changed.
+    pub fn is_dirty(&self, stage: RenderStage) -> bool {
+        match stage {
+            RenderStage::Transform => self.transform,
+            RenderStage::Adjustments => self.adjustments,
+            RenderStage::Effects => self.effects,
+            RenderStage::Curve => self.curve,
+            RenderStage::Masks => self.masks,
+        }
+    }
+
+    /// The dirty stages, in pipeline order.
+    pub fn dirty_stages(&self) -> Vec<RenderStage> {
+        RenderStage::ALL
+            .into_iter()
+            .filter(|stage| self.is_dirty(*stage))
+            .collect()
+    }
+
+    /// The stage to resume rendering from, or `None` when nothing changed.
+    pub fn first_dirty(&self) -> Option<RenderStage> {
+        RenderStage::ALL
+            .into_iter()
+            .find(|stage| self.is_dirty(*stage))
+    }
+
+    /// Check if no stage changed.
+    pub fn is_clean(&self) -> bool {
+        self.first_dirty().is_none()
+    }
+}
+
+/// Report which render stages differ between `a` and `b`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use literoom_core::edit::compare_edit_states_stages;
+/// use literoom_core::render::{render_from_stage, render_to_stage};
+///
+/// if let Some(stage) = compare_edit_states_stages(&prev, &next).first_dirty() {
+///     // `cached` was rendered with render_to_stage(&source, stage, &prev, filter)
+///     let output = render_from_stage(&cached, stage, &next, filter)?;
+/// }
+/// ```
+pub fn compare_edit_states_stages(a: &EditState, b: &EditState) -> StageDiff {
+    let transform = a.crop != b.crop || a.rotation != b.rotation;
+    let color_model = a.adjustments.color_model != b.adjustments.color_model;
+    let mix = |state: &EditState| state.monochrome.then(|| state.bw_mix.unwrap_or_default());
+    StageDiff {
+        transform,
+        adjustments: transform
+            || a.channel_levels != b.channel_levels
+            || a.base_curve != b.base_curve
+            || a.adjustments != b.adjustments,
+        effects: transform || mix(a) != mix(b),
+        curve: transform || a.tone_curve != b.tone_curve,
+        masks: transform
+            || color_model
+            || !enabled_linear(a).eq(enabled_linear(b))
+            || !enabled_radial(a).eq(enabled_radial(b)),
+    }
+}
+
+/// What the enabled linear masks render with, ids left out.
+fn enabled_linear(state: &EditState) -> impl Iterator<Item = Layer<'_, LinearGradientMask>> {
+    state
+        .linear_masks
+        .iter()
+        .filter(|m| m.enabled)
+        .map(|m| (&m.mask, &m.adjustments, &m.tone_curve))
+}
+
+/// What the enabled radial masks render with, ids left out.
+fn enabled_radial(state: &EditState) -> impl Iterator<Item = Layer<'_, RadialGradientMask>> {
+    state
+        .radial_masks
+        .iter()
+        .filter(|m| m.enabled)
+        .map(|m| (&m.mask, &m.adjustments, &m.tone_curve))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorModel;
+    use crate::edit::{CropRect, LinearMaskEdit};
+    use crate::monochrome::BwMix;
+    use crate::CurvePoint;
+
+    fn linear_mask(enabled: bool) -> LinearMaskEdit {
+        LinearMaskEdit {
+            id: "sky".to_string(),
+            mask: LinearGradientMask::new(0.5, 0.0, 0.5, 0.6, 0.5),
+            enabled,
+            adjustments: BasicAdjustments {
+                exposure: -0.5,
+                ..Default::default()
+            },
+            tone_curve: None,
+        }
+    }
+
+    #[test]
+    fn test_identical_states_are_clean() {
+        let state = EditState {
+            linear_masks: vec![linear_mask(true)],
+            ..Default::default()
+        };
+        let diff = compare_edit_states_stages(&state, &state.clone());
+        assert!(diff.is_clean());
+        assert_eq!(diff.first_dirty(), None);
+    }
+
+    #[test]
+    fn test_curve_change_dirties_only_curve() {
+        let a = EditState::default();
+        let mut b = a.clone();
+        b.tone_curve.points.insert(1, CurvePoint::new(0.5, 0.6));
+
+        let diff = compare_edit_states_stages(&a, &b);
+        assert_eq!(diff.dirty_stages(), vec![RenderStage::Curve]);
+        assert_eq!(diff.first_dirty(), Some(RenderStage::Curve));
+    }
+
+    #[test]
+    fn test_crop_change_dirties_everything_downstream() {
+        let a = EditState::default();
+        let b = EditState {
+            crop: Some(CropRect {
+                left: 0.1,
+                top: 0.1,
+                width: 0.5,
+                height: 0.5,
+            }),
+            ..Default::default()
+        };
+        let diff = compare_edit_states_stages(&a, &b);
+        assert_eq!(diff.dirty_stages(), RenderStage::ALL.to_vec());
+        assert_eq!(diff.first_dirty(), Some(RenderStage::Transform));
+    }
+
+    #[test]
+    fn test_color_model_dirties_adjustments_and_masks() {
+        let a = EditState::default();
+        let mut b = a.clone();
+        b.adjustments.color_model = ColorModel::Oklab;
+
+        let diff = compare_edit_states_stages(&a, &b);
+        assert_eq!(
+            diff.dirty_stages(),
+            vec![RenderStage::Adjustments, RenderStage::Masks]
+        );
+    }
+
+    #[test]
+    fn test_settings_that_do_not_render_are_ignored() {
+        let a = EditState {
+            linear_masks: vec![linear_mask(false)],
+            ..Default::default()
+        };
+        let mut b = a.clone();
+        b.linear_masks[0].adjustments.exposure = 1.0;
+        b.linear_masks[0].id = "renamed".to_string();
+        b.bw_mix = Some(BwMix {
+            red: 0.8,
+            green: 0.1,
+            blue: 0.1,
+        });
+        b.auto_nr = true;
+        assert!(compare_edit_states_stages(&a, &b).is_clean());
+
+        // The same edits render once the mask and monochrome are on
+        let mut c = b.clone();
+        c.linear_masks[0].enabled = true;
+        c.monochrome = true;
+        let diff = compare_edit_states_stages(&b, &c);
+        assert_eq!(
+            diff.dirty_stages(),
+            vec![RenderStage::Effects, RenderStage::Masks]
+        );
+    }
+}
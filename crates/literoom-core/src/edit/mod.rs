@@ -0,0 +1,40 @@
+//! Edit state model and compact serialization.
+//!
+//! This module provides:
+//! - `EditState`, the complete set of non-destructive edits for one photo
+//! - A compact, versioned binary encoding for autosave
+//! - Delta encoding that stores only the fields changed between two states
+//! - Per-stage comparison of two states, so a render can resume from the
+//!   first stage they differ in
+//!
+//! # Binary Format
+//!
+//! All values are little-endian. Every encoding starts with a 4-byte header:
+//! the magic bytes `LR`, a format version, and a kind byte (full or delta).
+//! A delta additionally stores a 32-bit checksum of the base state it was
+//! built against.
+//!
+//! The body is a sequence of tagged fields. Only fields that differ from the
+//! base are written: the default state for a full encoding, the previous
+//! state for a delta. A default state therefore encodes to just the header,
+//! and a single slider change to a few bytes.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use literoom_core::edit::{delta_apply, delta_encode, EditState};
+//!
+//! let mut next = prev.clone();
+//! next.adjustments.exposure = 0.7;
+//!
+//! let delta = delta_encode(&prev, &next);
+//! assert_eq!(delta_apply(&prev, &delta)?, next);
+//! ```
+
+mod binary;
+mod diff;
+mod types;
+
+pub use binary::{delta_apply, delta_encode, EditError, FORMAT_VERSION};
+pub use diff::{compare_edit_states_stages, StageDiff};
+pub use types::{CropRect, EditState, LinearMaskEdit, LutReference, RadialMaskEdit, Rotation};
@@ -0,0 +1,295 @@
+//! Edit state types.
+
+use crate::adjustments::ChannelLevels;
+use crate::base_curve::BaseCurve;
+use crate::decode::ImageMetadata;
+use crate::mask::{LinearGradientMask, RadialGradientMask};
+use crate::monochrome::BwMix;
+use crate::noise_reduction::{compute_default_noise_reduction, NoiseReductionParams};
+use crate::{BasicAdjustments, ToneCurve};
+use serde::{Deserialize, Serialize};
+
+/// Normalized crop rectangle (all values 0.0 to 1.0).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CropRect {
+    /// Left edge position
+    pub left: f32,
+    /// Top edge position
+    pub top: f32,
+    /// Width of the crop region
+    pub width: f32,
+    /// Height of the crop region
+    pub height: f32,
+}
+
+/// Rotation parameters in degrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rotation {
+    /// Main rotation angle (-180 to 180)
+    pub angle: f32,
+    /// Additional straighten angle (typically -45 to 45)
+    pub straighten: f32,
+}
+
+/// A 3D LUT look applied to the photo.
+///
+/// Only the id is stored; the app resolves it to the `.cube` data and
+/// applies it with [`apply_lut3d`](crate::lut3d::apply_lut3d).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LutReference {
+    /// Identifier the app resolves to a LUT file
+    pub id: String,
+    /// Blend between the original (0.0) and the full look (1.0)
+    #[serde(default = "full_strength")]
+    pub strength: f32,
+}
+
+fn full_strength() -> f32 {
+    1.0
+}
+
+/// A linear gradient mask together with its local edits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinearMaskEdit {
+    /// Stable identifier assigned by the app
+    pub id: String,
+    /// Mask geometry
+    pub mask: LinearGradientMask,
+    /// Whether the mask is applied
+    pub enabled: bool,
+    /// Adjustments applied inside the mask
+    pub adjustments: BasicAdjustments,
+    /// Optional tone curve applied inside the mask
+    #[serde(default)]
+    pub tone_curve: Option<ToneCurve>,
+}
+
+/// A radial gradient mask together with its local edits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RadialMaskEdit {
+    /// Stable identifier assigned by the app
+    pub id: String,
+    /// Mask geometry
+    pub mask: RadialGradientMask,
+    /// Whether the mask is applied
+    pub enabled: bool,
+    /// Adjustments applied inside the mask
+    pub adjustments: BasicAdjustments,
+    /// Optional tone curve applied inside the mask
+    #[serde(default)]
+    pub tone_curve: Option<ToneCurve>,
+}
+
+/// Complete non-destructive edit state for one photo.
+///
+/// Mirrors the app's `EditState`. Missing fields deserialize to their
+/// defaults, so partial objects are accepted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EditState {
+    /// Per-channel black and white levels, applied first; identity by
+    /// default
+    #[serde(default)]
+    pub channel_levels: ChannelLevels,
+    /// Camera base curve applied before the adjustments, or `None` when
+    /// switched off
+    #[serde(default)]
+    pub base_curve: Option<BaseCurve>,
+    /// Global basic adjustments
+    #[serde(default)]
+    pub adjustments: BasicAdjustments,
+    /// Render in black and white; the color adjustments are kept
+    #[serde(default)]
+    pub monochrome: bool,
+    /// Channel mix for black and white, or `None` for the default mix
+    #[serde(default)]
+    pub bw_mix: Option<BwMix>,
+    /// Explicit noise reduction, or `None` to leave it to `auto_nr`
+    #[serde(default)]
+    pub noise_reduction: Option<NoiseReductionParams>,
+    /// Pick noise reduction from the photo's ISO when none is set
+    #[serde(default)]
+    pub auto_nr: bool,
+    /// Global tone curve
+    #[serde(default)]
+    pub tone_curve: ToneCurve,
+    /// 3D LUT look, or `None` for none
+    #[serde(default)]
+    pub lut: Option<LutReference>,
+    /// Crop region, or `None` for the full image
+    #[serde(default)]
+    pub crop: Option<CropRect>,
+    /// Rotation and straighten angles
+    #[serde(default)]
+    pub rotation: Rotation,
+    /// Linear gradient masks, applied in order
+    #[serde(default)]
+    pub linear_masks: Vec<LinearMaskEdit>,
+    /// Radial gradient masks, applied in order
+    #[serde(default)]
+    pub radial_masks: Vec<RadialMaskEdit>,
+}
+
+impl EditState {
+    /// Create a new edit state with no edits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if the state contains no edits.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The crop, if it is the only edit.
+    ///
+    /// Disabled masks are ignored since they do not change the render.
+    /// Returns `None` when there is no crop or when anything else would
+    /// change the pixels; such a state can then be exported with
+    /// [`crop_jpeg_lossless`](crate::encode::crop_jpeg_lossless).
+    pub fn crop_only(&self) -> Option<CropRect> {
+        let rest = Self {
+            crop: None,
+            linear_masks: self
+                .linear_masks
+                .iter()
+                .filter(|m| m.enabled)
+                .cloned()
+                .collect(),
+            radial_masks: self
+                .radial_masks
+                .iter()
+                .filter(|m| m.enabled)
+                .cloned()
+                .collect(),
+            ..self.clone()
+        };
+        if rest.is_default() {
+            self.crop
+        } else {
+            None
+        }
+    }
+
+    /// Nudge the global adjustments by `delta`, clamping each field.
+    ///
+    /// The tone curve, crop, rotation and masks (including their local
+    /// adjustments) are left untouched.
+    pub fn apply_relative(&mut self, delta: &BasicAdjustments) {
+        self.adjustments.apply_relative(delta);
+    }
+
+    /// Noise reduction to apply to the photo described by `metadata`.
+    ///
+    /// Explicit values win. Otherwise, with `auto_nr` on, the defaults
+    /// for the photo's ISO and camera are used; with it off, none.
+    pub fn effective_noise_reduction(&self, metadata: &ImageMetadata) -> NoiseReductionParams {
+        match self.noise_reduction {
+            Some(params) => params,
+            None if self.auto_nr => compute_default_noise_reduction(metadata),
+            None => NoiseReductionParams::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CurvePoint;
+
+    #[test]
+    fn test_apply_relative_preserves_curve_and_masks() {
+        let mut state = EditState::new();
+        state.adjustments.exposure = 1.0;
+        state.tone_curve.points.insert(1, CurvePoint::new(0.5, 0.6));
+        state.linear_masks.push(LinearMaskEdit {
+            id: "mask-1".to_string(),
+            mask: LinearGradientMask::new(0.0, 0.0, 1.0, 1.0, 0.5),
+            enabled: true,
+            adjustments: BasicAdjustments {
+                exposure: 2.0,
+                ..Default::default()
+            },
+            tone_curve: None,
+        });
+        let before = state.clone();
+
+        state.apply_relative(&BasicAdjustments {
+            exposure: 0.5,
+            ..Default::default()
+        });
+
+        assert_eq!(state.adjustments.exposure, 1.5);
+        assert_eq!(state.tone_curve, before.tone_curve);
+        assert_eq!(state.linear_masks, before.linear_masks);
+    }
+
+    #[test]
+    fn test_crop_only() {
+        let crop = CropRect {
+            left: 0.1,
+            top: 0.2,
+            width: 0.5,
+            height: 0.5,
+        };
+        let mut state = EditState::new();
+        assert_eq!(state.crop_only(), None);
+
+        state.crop = Some(crop);
+        assert_eq!(state.crop_only(), Some(crop));
+
+        state.linear_masks.push(LinearMaskEdit {
+            id: "mask-1".to_string(),
+            mask: LinearGradientMask::new(0.0, 0.0, 1.0, 1.0, 0.5),
+            enabled: false,
+            adjustments: BasicAdjustments {
+                exposure: 2.0,
+                ..Default::default()
+            },
+            tone_curve: None,
+        });
+        assert_eq!(state.crop_only(), Some(crop));
+
+        state.linear_masks[0].enabled = true;
+        assert_eq!(state.crop_only(), None);
+
+        state.linear_masks.clear();
+        state.rotation.straighten = 1.5;
+        assert_eq!(state.crop_only(), None);
+    }
+
+    #[test]
+    fn test_effective_noise_reduction() {
+        let metadata = ImageMetadata {
+            iso: Some(6400),
+            ..Default::default()
+        };
+        let mut state = EditState::new();
+        assert!(state.effective_noise_reduction(&metadata).is_zero());
+
+        state.auto_nr = true;
+        assert_eq!(
+            state.effective_noise_reduction(&metadata),
+            compute_default_noise_reduction(&metadata)
+        );
+        assert!(!state.effective_noise_reduction(&metadata).is_zero());
+
+        // Explicit values win, even zero
+        state.noise_reduction = Some(NoiseReductionParams::default());
+        assert!(state.effective_noise_reduction(&metadata).is_zero());
+    }
+
+    #[test]
+    fn test_lut_strength_defaults_to_full() {
+        let state: EditState = serde_json::from_str(r#"{"lut": {"id": "portra-400"}}"#).unwrap();
+        let lut = state.lut.unwrap();
+        assert_eq!(lut.id, "portra-400");
+        assert_eq!(lut.strength, 1.0);
+    }
+
+    #[test]
+    fn test_noise_reduction_fields_default_when_missing() {
+        let state: EditState = serde_json::from_str(r#"{"auto_nr": true}"#).unwrap();
+        assert!(state.auto_nr);
+        assert_eq!(state.noise_reduction, None);
+    }
+}
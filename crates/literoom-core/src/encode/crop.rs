@@ -0,0 +1,1001 @@
+//! Lossless cropping of JPEG files.
+//!
+//! Cropping by decoding and re-encoding costs quality. [`crop_jpeg_lossless`]
+//! instead keeps the quantized DCT coefficients of every block inside the
+//! crop: the entropy-coded data is decoded to coefficients and coded again
+//! with only the kept blocks, so they decode to exactly the same pixels.
+//!
+//! Blocks cannot be split, so the crop's left and top edges snap outward to
+//! the nearest MCU boundary (8 or 16 pixels, depending on chroma
+//! subsampling). The right and bottom edges stay where they were asked for:
+//! a partial MCU there is padding that decoders discard.
+
+use super::lossless::header_segments;
+use super::stream::{BitWriter, CHROMA_AC_LUT, CHROMA_DC_LUT, LUMA_AC_LUT, LUMA_DC_LUT, UNZIGZAG};
+use super::EncodeError;
+use crate::decode::{is_start_of_frame, probe_image, JpegSegment, Orientation};
+use crate::edit::CropRect;
+use crate::mask::PixelRect;
+use crate::perf;
+use crate::transform::crop_pixel_rect;
+
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const DHT: u8 = 0xC4;
+const DRI: u8 = 0xDD;
+const SOS: u8 = 0xDA;
+
+/// Codes up to this many bits are decoded with a single table lookup.
+const LOOKUP_BITS: u32 = 9;
+
+/// A JPEG cropped by [`crop_jpeg_lossless`].
+#[derive(Debug, Clone)]
+pub struct LosslessCropResult {
+    /// The cropped JPEG file
+    pub bytes: Vec<u8>,
+    /// Crop actually applied, in pixels of the displayed (EXIF-oriented)
+    /// image
+    pub rect: PixelRect,
+    /// The same crop normalized to the displayed image, as in the edit state
+    pub crop: CropRect,
+}
+
+/// Crop a JPEG without re-encoding its pixels.
+///
+/// `crop` is normalized to the image as displayed, after its EXIF
+/// orientation, and rounded to whole pixels like
+/// [`apply_crop`](crate::transform::apply_crop). Its left and top edges
+/// (in the stored, unrotated pixels) are then moved out to the nearest MCU
+/// boundary; [`LosslessCropResult::rect`] is the crop actually used.
+///
+/// Every segment before the scan is kept, including EXIF, ICC profiles and
+/// the quantization tables, except that the frame header gets the new size,
+/// the scan is re-coded with the standard Huffman tables, and restart
+/// intervals are dropped. EXIF dimension tags are not updated.
+///
+/// # Errors
+///
+/// Returns `EncodeError::UnsupportedJpeg` for progressive, arithmetic-coded,
+/// lossless, hierarchical, 12-bit or multi-scan JPEGs, which are rejected
+/// before any output is produced, and `EncodeError::InvalidJpeg` if the
+/// headers or the entropy-coded data are malformed.
+///
+/// # Example
+///
+/// ```ignore
+/// use literoom_core::edit::CropRect;
+/// use literoom_core::encode::crop_jpeg_lossless;
+///
+/// let crop = CropRect { left: 0.1, top: 0.1, width: 0.5, height: 0.5 };
+/// let result = crop_jpeg_lossless(&jpeg_bytes, crop)?;
+/// // Show the user the crop that was actually applied
+/// overlay.set(result.crop);
+/// ```
+pub fn crop_jpeg_lossless(bytes: &[u8], crop: CropRect) -> Result<LosslessCropResult, EncodeError> {
+    let _perf = perf::scope("crop_jpeg_lossless");
+    let segments = header_segments(bytes)?;
+    let frame = Frame::parse(&segments)?;
+    let scan = Scan::parse(&segments, &frame)?;
+
+    let orientation = probe_image(bytes).orientation;
+    let (display_width, display_height) = if orientation.swaps_dimensions() {
+        (frame.height, frame.width)
+    } else {
+        (frame.width, frame.height)
+    };
+    let (left, top, width, height) = crop_pixel_rect(
+        display_width,
+        display_height,
+        crop.left as f64,
+        crop.top as f64,
+        crop.width as f64,
+        crop.height as f64,
+    );
+    let requested = PixelRect {
+        x: left,
+        y: top,
+        width,
+        height,
+    };
+
+    let stored = map_rect(requested, |x, y| {
+        stored_point(orientation, frame.width, frame.height, x, y)
+    });
+    let (mcu_width, mcu_height) = frame.mcu_size();
+    let x0 = stored.x / mcu_width * mcu_width;
+    let y0 = stored.y / mcu_height * mcu_height;
+    let stored = PixelRect {
+        x: x0,
+        y: y0,
+        width: stored.x + stored.width - x0,
+        height: stored.y + stored.height - y0,
+    };
+
+    let bytes = transcode(bytes, &segments, &frame, &scan, stored)?;
+    let rect = map_rect(stored, |x, y| {
+        displayed_point(orientation, frame.width, frame.height, x, y)
+    });
+    let crop = CropRect {
+        left: rect.x as f32 / display_width as f32,
+        top: rect.y as f32 / display_height as f32,
+        width: rect.width as f32 / display_width as f32,
+        height: rect.height as f32 / display_height as f32,
+    };
+    Ok(LosslessCropResult { bytes, rect, crop })
+}
+
+/// Cut a JPEG down to the full-width stripe of MCU rows covering the stored
+/// rows `rows`, plus one MCU row of context above and below where the image
+/// has one.
+///
+/// The context rows let the decoder upsample subsampled chroma at the
+/// stripe's edges exactly as it does in the whole image, so the requested
+/// rows decode to the same pixels. Returns the stripe and the stored row it
+/// starts at.
+///
+/// # Errors
+///
+/// Same as [`crop_jpeg_lossless`].
+pub(crate) fn crop_jpeg_rows(
+    bytes: &[u8],
+    rows: std::ops::Range<u32>,
+) -> Result<(Vec<u8>, u32), EncodeError> {
+    let segments = header_segments(bytes)?;
+    let frame = Frame::parse(&segments)?;
+    let scan = Scan::parse(&segments, &frame)?;
+
+    let (_, mcu_height) = frame.mcu_size();
+    let top = (rows.start / mcu_height).saturating_sub(1) * mcu_height;
+    let bottom = (rows.end.div_ceil(mcu_height) + 1) * mcu_height;
+    let stripe = PixelRect {
+        x: 0,
+        y: top,
+        width: frame.width,
+        height: bottom.min(frame.height) - top,
+    };
+    Ok((transcode(bytes, &segments, &frame, &scan, stripe)?, top))
+}
+
+/// Write the file with only the blocks of `rect` (stored pixels, MCU
+/// aligned at the top left).
+fn transcode(
+    bytes: &[u8],
+    segments: &[JpegSegment<'_>],
+    frame: &Frame,
+    scan: &Scan,
+    rect: PixelRect,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut writer = BitWriter::default();
+    writer.write_marker(SOI);
+    for segment in segments {
+        match segment.marker {
+            marker if is_start_of_frame(marker) => {
+                let mut header = segment.payload.to_vec();
+                header[1..3].copy_from_slice(&(rect.height as u16).to_be_bytes());
+                header[3..5].copy_from_slice(&(rect.width as u16).to_be_bytes());
+                writer.write_segment(marker, &header);
+            }
+            // Replaced by the standard tables and a scan without restarts
+            DHT | DRI | SOS => {}
+            _ => writer
+                .out
+                .extend_from_slice(&bytes[segment.start..segment.end]),
+        }
+    }
+    writer.write_standard_huffman_tables();
+    let mut header = vec![scan.components.len() as u8];
+    for (i, component) in scan.components.iter().enumerate() {
+        let table = (i > 0) as u8;
+        header.extend_from_slice(&[component.id, (table << 4) | table]);
+    }
+    header.extend_from_slice(&[0, 63, 0]);
+    writer.write_segment(SOS, &header);
+
+    let (mcu_width, mcu_height) = frame.mcu_size();
+    let mcus_x = frame.width.div_ceil(mcu_width);
+    let keep_x = rect.x / mcu_width..(rect.x + rect.width).div_ceil(mcu_width);
+    let keep_y = rect.y / mcu_height..(rect.y + rect.height).div_ceil(mcu_height);
+    let single = scan.components.len() == 1;
+
+    let data = &bytes[segments.last().map_or(0, |sos| sos.end)..];
+    let mut reader = BitReader::new(data);
+    let mut dc_in = vec![0i32; scan.components.len()];
+    let mut dc_out = vec![0i32; scan.components.len()];
+    let mut block = [0i32; 64];
+    let mut mcu = 0u32;
+    // Rows below the crop are never needed
+    for my in 0..keep_y.end {
+        for mx in 0..mcus_x {
+            if mcu > 0 && mcu.is_multiple_of(scan.restart_interval) {
+                reader.restart()?;
+                dc_in.fill(0);
+            }
+            mcu += 1;
+            let keep = keep_x.contains(&mx) && keep_y.contains(&my);
+            for (i, component) in scan.components.iter().enumerate() {
+                let blocks = if single {
+                    1
+                } else {
+                    component.h as usize * component.v as usize
+                };
+                let (dc_lut, ac_lut) = if i == 0 {
+                    (&LUMA_DC_LUT, &LUMA_AC_LUT)
+                } else {
+                    (&CHROMA_DC_LUT, &CHROMA_AC_LUT)
+                };
+                for _ in 0..blocks {
+                    dc_in[i] = reader.read_block(&mut block, dc_in[i], &component.tables)?;
+                    if keep {
+                        dc_out[i] = writer.write_block(&block, dc_out[i], dc_lut, ac_lut);
+                    }
+                }
+            }
+        }
+    }
+    writer.pad_byte();
+    writer.write_marker(EOI);
+    Ok(writer.out)
+}
+
+fn invalid(message: &str) -> EncodeError {
+    EncodeError::InvalidJpeg(message.to_string())
+}
+
+fn unsupported(message: &str) -> EncodeError {
+    EncodeError::UnsupportedJpeg(message.to_string())
+}
+
+/// Size and components from the frame header.
+struct Frame {
+    width: u32,
+    height: u32,
+    /// (id, horizontal sampling, vertical sampling) per component
+    components: Vec<(u8, u8, u8)>,
+}
+
+impl Frame {
+    fn parse(segments: &[JpegSegment<'_>]) -> Result<Self, EncodeError> {
+        let sof = segments
+            .iter()
+            .find(|segment| is_start_of_frame(segment.marker))
+            .ok_or_else(|| invalid("missing frame header"))?;
+        match sof.marker {
+            // Baseline and extended sequential, Huffman-coded
+            0xC0 | 0xC1 => {}
+            0xC2 | 0xC6 | 0xCA | 0xCE => {
+                return Err(unsupported(
+                    "progressive JPEGs cannot be cropped losslessly",
+                ))
+            }
+            0xC3 | 0xC7 | 0xCB | 0xCF => {
+                return Err(unsupported("lossless JPEGs cannot be cropped losslessly"))
+            }
+            0xC5 => {
+                return Err(unsupported(
+                    "hierarchical JPEGs cannot be cropped losslessly",
+                ))
+            }
+            _ => {
+                return Err(unsupported(
+                    "arithmetic-coded JPEGs cannot be cropped losslessly",
+                ))
+            }
+        }
+
+        let header = sof.payload;
+        let count = *header
+            .get(5)
+            .ok_or_else(|| invalid("truncated frame header"))? as usize;
+        if header.len() < 6 + count * 3 {
+            return Err(invalid("truncated frame header"));
+        }
+        if header[0] != 8 {
+            return Err(unsupported(&format!(
+                "{}-bit JPEGs cannot be cropped losslessly",
+                header[0]
+            )));
+        }
+        let height = u16::from_be_bytes([header[1], header[2]]) as u32;
+        let width = u16::from_be_bytes([header[3], header[4]]) as u32;
+        if width == 0 {
+            return Err(invalid("zero image width"));
+        }
+        if height == 0 {
+            return Err(unsupported("height defined after the scan (DNL)"));
+        }
+
+        let components: Vec<_> = header[6..6 + count * 3]
+            .chunks(3)
+            .map(|c| (c[0], c[1] >> 4, c[1] & 0x0F))
+            .collect();
+        let sampling_ok = |&(_, h, v): &(u8, u8, u8)| (1..=4).contains(&h) && (1..=4).contains(&v);
+        if components.is_empty() || components.len() > 4 || !components.iter().all(sampling_ok) {
+            return Err(invalid("invalid frame components"));
+        }
+        Ok(Self {
+            width,
+            height,
+            components,
+        })
+    }
+
+    /// Width and height of an MCU in pixels.
+    fn mcu_size(&self) -> (u32, u32) {
+        if self.components.len() == 1 {
+            // A single-component scan is coded block by block
+            return (8, 8);
+        }
+        let h = self.components.iter().map(|c| c.1).max().unwrap_or(1);
+        let v = self.components.iter().map(|c| c.2).max().unwrap_or(1);
+        (8 * h as u32, 8 * v as u32)
+    }
+}
+
+/// A component of the scan with its Huffman tables.
+struct ScanComponent {
+    id: u8,
+    h: u8,
+    v: u8,
+    tables: [HuffmanDecoder; 2],
+}
+
+/// The first scan's components, in coding order, and restart interval.
+struct Scan {
+    components: Vec<ScanComponent>,
+    restart_interval: u32,
+}
+
+impl Scan {
+    fn parse(segments: &[JpegSegment<'_>], frame: &Frame) -> Result<Self, EncodeError> {
+        // Tables in force at the first scan, by class (DC, AC) and id
+        let mut tables: [[Option<HuffmanDecoder>; 4]; 2] = Default::default();
+        let mut restart_interval = 0;
+        for segment in segments {
+            let payload = segment.payload;
+            match segment.marker {
+                DHT => {
+                    let mut rest = payload;
+                    while let [class_and_id, tail @ ..] = rest {
+                        let (class, id) =
+                            ((class_and_id >> 4) as usize, (class_and_id & 0x0F) as usize);
+                        if class > 1 || id > 3 || tail.len() < 16 {
+                            return Err(invalid("invalid Huffman table"));
+                        }
+                        let counts: &[u8; 16] = tail[..16].try_into().unwrap_or(&[0; 16]);
+                        let total: usize = counts.iter().map(|&n| n as usize).sum();
+                        let values = tail
+                            .get(16..16 + total)
+                            .ok_or_else(|| invalid("truncated Huffman table"))?;
+                        tables[class][id] = Some(HuffmanDecoder::new(counts, values)?);
+                        rest = &tail[16 + total..];
+                    }
+                }
+                DRI => {
+                    let interval = payload
+                        .get(..2)
+                        .ok_or_else(|| invalid("truncated restart interval"))?;
+                    restart_interval = u16::from_be_bytes([interval[0], interval[1]]) as u32;
+                }
+                _ => {}
+            }
+        }
+
+        let sos = segments
+            .last()
+            .filter(|segment| segment.marker == SOS)
+            .ok_or_else(|| invalid("missing scan header"))?;
+        let header = sos.payload;
+        let count = *header
+            .first()
+            .ok_or_else(|| invalid("truncated scan header"))? as usize;
+        let spectral = header
+            .get(1 + count * 2..4 + count * 2)
+            .ok_or_else(|| invalid("truncated scan header"))?;
+        if count != frame.components.len() {
+            return Err(unsupported(
+                "JPEGs with more than one scan cannot be cropped losslessly",
+            ));
+        }
+        if spectral != [0, 63, 0] {
+            return Err(invalid("baseline scan must cover all coefficients"));
+        }
+
+        let mut components = Vec::with_capacity(count);
+        for selector in header[1..1 + count * 2].chunks(2) {
+            let (id, dc, ac) = (
+                selector[0],
+                (selector[1] >> 4) as usize,
+                (selector[1] & 0x0F) as usize,
+            );
+            let &(_, h, v) = frame
+                .components
+                .iter()
+                .find(|c| c.0 == id)
+                .ok_or_else(|| invalid("scan component not in frame"))?;
+            let table = |class: usize, index: usize| {
+                tables[class]
+                    .get(index)
+                    .cloned()
+                    .flatten()
+                    .ok_or_else(|| invalid("missing Huffman table"))
+            };
+            components.push(ScanComponent {
+                id,
+                h,
+                v,
+                tables: [table(0, dc)?, table(1, ac)?],
+            });
+        }
+        Ok(Self {
+            components,
+            restart_interval,
+        })
+    }
+}
+
+/// Decoder for one Huffman table (JPEG Annex C and F.2.2.3).
+#[derive(Clone)]
+struct HuffmanDecoder {
+    /// (code length, symbol) for every `LOOKUP_BITS`-bit prefix; length 0
+    /// if the code is longer
+    lookup: Vec<(u8, u8)>,
+    /// Largest code of each length, or -1 if there is none
+    max_code: [i32; 17],
+    /// Added to a code of each length to get its index in `values`
+    offset: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl HuffmanDecoder {
+    fn new(counts: &[u8; 16], values: &[u8]) -> Result<Self, EncodeError> {
+        let mut decoder = Self {
+            lookup: vec![(0, 0); 1 << LOOKUP_BITS],
+            max_code: [-1; 17],
+            offset: [0; 17],
+            values: values.to_vec(),
+        };
+        let mut code = 0i32;
+        let mut k = 0usize;
+        for len in 1..=16 {
+            let count = counts[len - 1] as usize;
+            decoder.offset[len] = k as i32 - code;
+            for &value in &values[k..k + count] {
+                if len as u32 <= LOOKUP_BITS {
+                    let shift = LOOKUP_BITS - len as u32;
+                    let first = (code as usize) << shift;
+                    decoder.lookup[first..first + (1 << shift)].fill((len as u8, value));
+                }
+                code += 1;
+            }
+            if code > 1 << len {
+                return Err(invalid("invalid Huffman table"));
+            }
+            if count > 0 {
+                decoder.max_code[len] = code - 1;
+            }
+            k += count;
+            code <<= 1;
+        }
+        Ok(decoder)
+    }
+}
+
+/// Reads entropy-coded data, removing byte stuffing.
+///
+/// At a marker or the end of the data it supplies zero bits, and fails if
+/// any of them are consumed.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Buffered bits, most significant first
+    bits: u64,
+    count: u32,
+    /// Trailing zero bits in `bits` that are past the data
+    padding: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bits: 0,
+            count: 0,
+            padding: 0,
+        }
+    }
+
+    fn refill(&mut self) {
+        while self.count <= 56 {
+            let byte = match (self.data.get(self.pos), self.data.get(self.pos + 1)) {
+                (Some(0xFF), Some(0x00)) => {
+                    self.pos += 2;
+                    0xFF
+                }
+                (Some(0xFF), _) | (None, _) => {
+                    self.padding += 8;
+                    0
+                }
+                (Some(&byte), _) => {
+                    self.pos += 1;
+                    byte
+                }
+            };
+            self.bits |= (byte as u64) << (56 - self.count);
+            self.count += 8;
+        }
+    }
+
+    /// The next 16 bits, without consuming them.
+    fn peek(&mut self) -> u32 {
+        self.refill();
+        (self.bits >> 48) as u32
+    }
+
+    fn consume(&mut self, n: u32) -> Result<(), EncodeError> {
+        if n > self.count - self.padding {
+            return Err(invalid("truncated scan data"));
+        }
+        self.bits <<= n;
+        self.count -= n;
+        Ok(())
+    }
+
+    fn decode(&mut self, table: &HuffmanDecoder) -> Result<u8, EncodeError> {
+        let peek = self.peek();
+        let (len, value) = table.lookup[(peek >> (16 - LOOKUP_BITS)) as usize];
+        if len > 0 {
+            self.consume(len as u32)?;
+            return Ok(value);
+        }
+        for len in LOOKUP_BITS as usize + 1..=16 {
+            let code = (peek >> (16 - len)) as i32;
+            if code <= table.max_code[len] {
+                self.consume(len as u32)?;
+                return Ok(table.values[(code + table.offset[len]) as usize]);
+            }
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+
+    /// Read `size` extra bits as a signed coefficient (JPEG F.2.2.1).
+    fn receive(&mut self, size: u8) -> Result<i32, EncodeError> {
+        if size == 0 {
+            return Ok(0);
+        }
+        let value = (self.peek() >> (16 - size)) as i32;
+        self.consume(size as u32)?;
+        Ok(if value < 1 << (size - 1) {
+            value - (1 << size) + 1
+        } else {
+            value
+        })
+    }
+
+    /// Decode one block into `block` (natural order) and return its DC
+    /// value for the next block's prediction.
+    fn read_block(
+        &mut self,
+        block: &mut [i32; 64],
+        prev_dc: i32,
+        [dc_table, ac_table]: &[HuffmanDecoder; 2],
+    ) -> Result<i32, EncodeError> {
+        *block = [0; 64];
+        let size = self.decode(dc_table)?;
+        if size > 11 {
+            return Err(invalid("DC coefficient out of range"));
+        }
+        let dc = prev_dc + self.receive(size)?;
+        // Keeps every re-coded DC difference within 11 bits
+        if !(-1024..1024).contains(&dc) {
+            return Err(invalid("DC coefficient out of range"));
+        }
+        block[0] = dc;
+
+        let mut k = 1;
+        while k < 64 {
+            let symbol = self.decode(ac_table)?;
+            let (run, size) = ((symbol >> 4) as usize, symbol & 0x0F);
+            if size == 0 {
+                if run == 15 {
+                    k += 16;
+                    continue;
+                }
+                break;
+            }
+            k += run;
+            if k > 63 || size > 10 {
+                return Err(invalid("AC coefficient out of range"));
+            }
+            block[UNZIGZAG[k] as usize] = self.receive(size)?;
+            k += 1;
+        }
+        Ok(dc)
+    }
+
+    /// Skip to just past the next restart marker.
+    fn restart(&mut self) -> Result<(), EncodeError> {
+        // Only the padding of the interval's last byte may be left
+        if self.count - self.padding >= 8 {
+            return Err(invalid("expected a restart marker"));
+        }
+        self.bits = 0;
+        self.count = 0;
+        self.padding = 0;
+        while self.data.get(self.pos..self.pos + 2) == Some(&[0xFF, 0xFF]) {
+            self.pos += 1;
+        }
+        match self.data.get(self.pos..self.pos + 2) {
+            Some(&[0xFF, marker]) if (0xD0..=0xD7).contains(&marker) => {
+                self.pos += 2;
+                Ok(())
+            }
+            _ => Err(invalid("expected a restart marker")),
+        }
+    }
+}
+
+/// The rect spanned by the images of two opposite corners of `rect`.
+fn map_rect(rect: PixelRect, map: impl Fn(u32, u32) -> (u32, u32)) -> PixelRect {
+    let (ax, ay) = map(rect.x, rect.y);
+    let (bx, by) = map(rect.x + rect.width, rect.y + rect.height);
+    PixelRect {
+        x: ax.min(bx),
+        y: ay.min(by),
+        width: ax.abs_diff(bx),
+        height: ay.abs_diff(by),
+    }
+}
+
+/// Stored position of a point on the displayed image, for stored pixels of
+/// `width` x `height`. Points are pixel corners, from 0 to the size.
+fn stored_point(orientation: Orientation, width: u32, height: u32, u: u32, v: u32) -> (u32, u32) {
+    match orientation {
+        Orientation::Normal => (u, v),
+        Orientation::FlipHorizontal => (width - u, v),
+        Orientation::Rotate180 => (width - u, height - v),
+        Orientation::FlipVertical => (u, height - v),
+        Orientation::Transpose => (v, u),
+        Orientation::Rotate90CW => (v, height - u),
+        Orientation::Transverse => (width - v, height - u),
+        Orientation::Rotate270CW => (width - v, u),
+    }
+}
+
+/// Displayed position of a stored point; the inverse of [`stored_point`].
+fn displayed_point(
+    orientation: Orientation,
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+) -> (u32, u32) {
+    match orientation {
+        Orientation::Normal => (x, y),
+        Orientation::FlipHorizontal => (width - x, y),
+        Orientation::Rotate180 => (width - x, height - y),
+        Orientation::FlipVertical => (x, height - y),
+        Orientation::Transpose => (y, x),
+        Orientation::Rotate90CW => (height - y, x),
+        Orientation::Transverse => (height - y, width - x),
+        Orientation::Rotate270CW => (y, width - x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_jpeg, DecodedImage};
+    use crate::encode::{encode_jpeg, rotate_jpeg_lossless};
+    use crate::testing::noise;
+    use crate::transform::apply_crop;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        encode_jpeg(&noise(width, height, 3).pixels, width, height, 90).unwrap()
+    }
+
+    fn normalized(rect: PixelRect, width: u32, height: u32) -> CropRect {
+        CropRect {
+            left: rect.x as f32 / width as f32,
+            top: rect.y as f32 / height as f32,
+            width: rect.width as f32 / width as f32,
+            height: rect.height as f32 / height as f32,
+        }
+    }
+
+    /// Pixels of `rect` in `image`.
+    fn region(image: &DecodedImage, rect: PixelRect) -> DecodedImage {
+        let (w, h) = (image.width as f64, image.height as f64);
+        apply_crop(
+            image,
+            rect.x as f64 / w,
+            rect.y as f64 / h,
+            rect.width as f64 / w,
+            rect.height as f64 / h,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_decodes_to_source_region() {
+        let source = jpeg(100, 75);
+        let full = decode_jpeg(&source).unwrap();
+        let requested = PixelRect {
+            x: 16,
+            y: 24,
+            width: 50,
+            height: 37,
+        };
+
+        let result = crop_jpeg_lossless(&source, normalized(requested, 100, 75)).unwrap();
+
+        // 4:4:4, so the MCU is 8x8 and the origin is already aligned
+        assert_eq!(result.rect, requested);
+        let cropped = decode_jpeg(&result.bytes).unwrap();
+        assert_eq!((cropped.width, cropped.height), (50, 37));
+        assert_eq!(cropped.pixels, region(&full, requested).pixels);
+    }
+
+    #[test]
+    fn test_unaligned_origin_snaps_out() {
+        let source = jpeg(64, 48);
+        let full = decode_jpeg(&source).unwrap();
+        let requested = PixelRect {
+            x: 13,
+            y: 5,
+            width: 30,
+            height: 30,
+        };
+
+        let result = crop_jpeg_lossless(&source, normalized(requested, 64, 48)).unwrap();
+
+        let snapped = PixelRect {
+            x: 8,
+            y: 0,
+            width: 35,
+            height: 35,
+        };
+        assert_eq!(result.rect, snapped);
+        assert_eq!(result.crop, normalized(snapped, 64, 48));
+        assert_eq!(
+            decode_jpeg(&result.bytes).unwrap().pixels,
+            region(&full, snapped).pixels
+        );
+    }
+
+    #[test]
+    fn test_full_frame_keeps_pixels() {
+        let source = jpeg(37, 21);
+        let full_crop = CropRect {
+            left: 0.0,
+            top: 0.0,
+            width: 1.0,
+            height: 1.0,
+        };
+        let result = crop_jpeg_lossless(&source, full_crop).unwrap();
+        assert_eq!(result.rect, PixelRect::full(37, 21));
+        assert_eq!(
+            decode_jpeg(&result.bytes).unwrap().pixels,
+            decode_jpeg(&source).unwrap().pixels
+        );
+    }
+
+    #[test]
+    fn test_crop_follows_exif_orientation() {
+        // Stored 64x40, displayed 40x64 after a clockwise turn
+        let source = rotate_jpeg_lossless(&jpeg(64, 40), 1).unwrap();
+        let displayed = decode_jpeg(&source).unwrap();
+        assert_eq!((displayed.width, displayed.height), (40, 64));
+        let requested = PixelRect {
+            x: 3,
+            y: 10,
+            width: 30,
+            height: 40,
+        };
+
+        let result = crop_jpeg_lossless(&source, normalized(requested, 40, 64)).unwrap();
+
+        // Stored x = displayed y snaps from 10 to 8; stored y = 40 - right
+        // edge, from 40 - 33 = 7 to 0, which moves the displayed right edge
+        let snapped = PixelRect {
+            x: 3,
+            y: 8,
+            width: 37,
+            height: 42,
+        };
+        assert_eq!(result.rect, snapped);
+        let cropped = decode_jpeg(&result.bytes).unwrap();
+        assert_eq!(cropped.pixels, region(&displayed, snapped).pixels);
+    }
+
+    #[test]
+    fn test_grayscale() {
+        let image = noise(45, 30, 8);
+        let luma: Vec<u8> = image.pixels.iter().step_by(3).copied().collect();
+        let mut source = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut source, 85)
+            .encode(&luma, 45, 30, image::ExtendedColorType::L8)
+            .unwrap();
+        let requested = PixelRect {
+            x: 9,
+            y: 9,
+            width: 20,
+            height: 12,
+        };
+
+        let result = crop_jpeg_lossless(&source, normalized(requested, 45, 30)).unwrap();
+
+        let snapped = PixelRect {
+            x: 8,
+            y: 8,
+            width: 21,
+            height: 13,
+        };
+        assert_eq!(result.rect, snapped);
+        let full = decode_jpeg(&source).unwrap();
+        assert_eq!(
+            decode_jpeg(&result.bytes).unwrap().pixels,
+            region(&full, snapped).pixels
+        );
+    }
+
+    #[test]
+    fn test_subsampled_snaps_to_16() {
+        // The image crate encodes color with 2x2 chroma subsampling
+        let image = noise(64, 48, 5);
+        let mut source = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut source, 90)
+            .encode(&image.pixels, 64, 48, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        let requested = PixelRect {
+            x: 20,
+            y: 17,
+            width: 30,
+            height: 20,
+        };
+
+        let result = crop_jpeg_lossless(&source, normalized(requested, 64, 48)).unwrap();
+
+        let snapped = PixelRect {
+            x: 16,
+            y: 16,
+            width: 34,
+            height: 21,
+        };
+        assert_eq!(result.rect, snapped);
+        let cropped = decode_jpeg(&result.bytes).unwrap();
+        let expected = region(&decode_jpeg(&source).unwrap(), snapped);
+        assert_eq!((cropped.width, cropped.height), (34, 21));
+        assert_eq!(cropped.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_keeps_other_segments() {
+        let source = rotate_jpeg_lossless(&jpeg(32, 32), 2).unwrap();
+        let crop = CropRect {
+            left: 0.5,
+            top: 0.5,
+            width: 0.5,
+            height: 0.5,
+        };
+        let result = crop_jpeg_lossless(&source, crop).unwrap();
+        assert_eq!(
+            probe_image(&result.bytes).orientation,
+            Orientation::Rotate180
+        );
+        assert_eq!(probe_image(&result.bytes).width, Some(16));
+    }
+
+    #[test]
+    fn test_row_stripe_adds_context_rows() {
+        let source = jpeg(64, 48);
+        let full = decode_jpeg(&source).unwrap();
+
+        let (stripe, top) = crop_jpeg_rows(&source, 21..30).unwrap();
+
+        // MCU rows 2 and 3 cover the rows, with rows 1 and 4 as context
+        assert_eq!(top, 8);
+        let stripe = decode_jpeg(&stripe).unwrap();
+        assert_eq!((stripe.width, stripe.height), (64, 32));
+        assert_eq!(stripe.pixels, full.pixels[8 * 64 * 3..40 * 64 * 3]);
+
+        // No context row below the last MCU row
+        let (stripe, top) = crop_jpeg_rows(&source, 44..48).unwrap();
+        assert_eq!(top, 32);
+        assert_eq!(probe_image(&stripe).height, Some(16));
+    }
+
+    #[test]
+    fn test_progressive_is_rejected() {
+        let mut source = jpeg(32, 32);
+        let sof = source.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        source[sof + 1] = 0xC2;
+
+        let err = crop_jpeg_lossless(
+            &source,
+            CropRect {
+                left: 0.25,
+                top: 0.25,
+                width: 0.5,
+                height: 0.5,
+            },
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, EncodeError::UnsupportedJpeg(_)), "{}", err);
+        assert!(err.to_string().contains("progressive"));
+    }
+
+    #[test]
+    fn test_corrupt_scan_is_rejected() {
+        let source = jpeg(64, 64);
+        let crop = CropRect {
+            left: 0.0,
+            top: 0.5,
+            width: 1.0,
+            height: 0.5,
+        };
+        let truncated = &source[..source.len() / 2];
+        assert!(matches!(
+            crop_jpeg_lossless(truncated, crop),
+            Err(EncodeError::InvalidJpeg(_))
+        ));
+        assert!(matches!(
+            crop_jpeg_lossless(b"not a jpeg", crop),
+            Err(EncodeError::InvalidJpeg(_))
+        ));
+    }
+
+    #[test]
+    fn test_huffman_decoder_matches_writer() {
+        let mut writer = BitWriter::default();
+        let mut block = [0i32; 64];
+        block[0] = -300;
+        block[1] = 5;
+        block[UNZIGZAG[40] as usize] = -1023;
+        block[63] = 2;
+        writer.write_block(&block, 0, &LUMA_DC_LUT, &LUMA_AC_LUT);
+        writer.write_block(&[0; 64], -300, &LUMA_DC_LUT, &LUMA_AC_LUT);
+        writer.pad_byte();
+
+        let mut source = vec![0xFF, 0xD8];
+        let mut header = BitWriter::default();
+        header.write_standard_huffman_tables();
+        source.extend(header.out);
+        source.extend_from_slice(&[0xFF, 0xDA, 0, 2]);
+        let segments = header_segments(&source).unwrap();
+        assert_eq!(segments.len(), 5);
+
+        let mut tables: [[Option<HuffmanDecoder>; 4]; 2] = Default::default();
+        for segment in &segments[..4] {
+            let payload = segment.payload;
+            let (class, id) = ((payload[0] >> 4) as usize, (payload[0] & 0x0F) as usize);
+            let counts: &[u8; 16] = payload[1..17].try_into().unwrap();
+            tables[class][id] = Some(HuffmanDecoder::new(counts, &payload[17..]).unwrap());
+        }
+        let luma = [tables[0][0].clone().unwrap(), tables[1][0].clone().unwrap()];
+
+        let mut reader = BitReader::new(&writer.out);
+        let mut decoded = [0i32; 64];
+        assert_eq!(reader.read_block(&mut decoded, 0, &luma).unwrap(), -300);
+        assert_eq!(decoded, block);
+        assert_eq!(reader.read_block(&mut decoded, -300, &luma).unwrap(), 0);
+        assert_eq!(decoded, [0; 64]);
+    }
+
+    #[test]
+    fn test_orientation_maps_round_trip() {
+        let rect = PixelRect {
+            x: 3,
+            y: 5,
+            width: 7,
+            height: 11,
+        };
+        for value in 1..=8u32 {
+            let orientation = Orientation::from(value);
+            let stored = map_rect(rect, |x, y| stored_point(orientation, 30, 20, x, y));
+            let back = map_rect(stored, |x, y| displayed_point(orientation, 30, 20, x, y));
+            assert_eq!(back, rect, "orientation {}", value);
+        }
+    }
+}
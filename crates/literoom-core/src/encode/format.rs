@@ -0,0 +1,453 @@
+//! Export format: container, color space and bit depth.
+//!
+//! An [`ExportFormat`] picks the file type and what goes into it. Rendered
+//! pixels are always sRGB; [`encode_image`] re-encodes them for the chosen
+//! color space and embeds the matching ICC profile, so a viewer shows the
+//! same colors whatever space the file is in. Combinations the encoders
+//! cannot write, such as a 16-bit JPEG, are rejected by
+//! [`ExportFormat::validate`] before any work is done.
+//!
+//! Missing fields take their defaults when a format is deserialized:
+//!
+//! | Field                | Default                                     |
+//! |----------------------|---------------------------------------------|
+//! | `container`          | `jpeg`                                      |
+//! | `color_space`        | `srgb`                                      |
+//! | `bit_depth`          | 8                                           |
+//! | `quality`            | none: [`DEFAULT_JPEG_QUALITY`] for JPEG     |
+//! | `chroma_subsampling` | none: 4:4:4 for JPEG                        |
+
+use std::io::Cursor;
+
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{encode_jpeg, EncodeError};
+use crate::decode::{
+    convert_from_srgb, convert_from_srgb_16, embed_icc_profile, ColorProfile, DecodedImage,
+};
+
+/// JPEG quality used when an export format does not set one.
+pub const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// File type of an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportContainer {
+    /// Baseline JPEG, 8 bits per channel.
+    #[default]
+    Jpeg,
+    /// PNG, 8 or 16 bits per channel.
+    Png,
+}
+
+impl ExportContainer {
+    /// Name of the container for messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportContainer::Jpeg => "JPEG",
+            ExportContainer::Png => "PNG",
+        }
+    }
+}
+
+/// Color space an export is encoded in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportColorSpace {
+    /// sRGB, for the web and most screens.
+    #[default]
+    Srgb,
+    /// Display P3, for wide-gamut screens.
+    DisplayP3,
+    /// Adobe RGB (1998), for print workflows.
+    AdobeRgb,
+}
+
+impl ExportColorSpace {
+    /// The profile that describes this space.
+    pub fn profile(self) -> ColorProfile {
+        match self {
+            ExportColorSpace::Srgb => ColorProfile::Srgb,
+            ExportColorSpace::DisplayP3 => ColorProfile::DisplayP3,
+            ExportColorSpace::AdobeRgb => ColorProfile::AdobeRgb,
+        }
+    }
+}
+
+/// JPEG chroma subsampling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChromaSubsampling {
+    /// Full-resolution chroma.
+    Yuv444,
+    /// Chroma at half resolution in both directions.
+    Yuv420,
+}
+
+/// Errors for export formats that cannot be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum ExportFormatError {
+    /// JPEG only stores 8 bits per channel
+    #[error("JPEG export supports only 8-bit output, not {bit_depth}-bit")]
+    JpegBitDepth { bit_depth: u8 },
+
+    /// PNG export writes 8 or 16 bits per channel
+    #[error("PNG export supports 8-bit or 16-bit output, not {bit_depth}-bit")]
+    PngBitDepth { bit_depth: u8 },
+
+    /// Quality only applies to lossy containers
+    #[error("{container} export is lossless and takes no quality")]
+    QualityNotApplicable { container: &'static str },
+
+    /// Quality is outside 1-100
+    #[error("Invalid quality {quality}: must be between 1 and 100")]
+    QualityOutOfRange { quality: u8 },
+
+    /// Chroma subsampling only applies to JPEG
+    #[error("{container} export has no chroma subsampling")]
+    SubsamplingNotApplicable { container: &'static str },
+
+    /// The JPEG encoder only writes full-resolution chroma
+    #[error("JPEG export does not support {subsampling:?} chroma subsampling")]
+    UnsupportedSubsampling { subsampling: ChromaSubsampling },
+}
+
+impl ExportFormatError {
+    /// Stable error code for this error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ExportFormatError::JpegBitDepth { .. } | ExportFormatError::PngBitDepth { .. } => {
+                "UNSUPPORTED_BIT_DEPTH"
+            }
+            ExportFormatError::QualityNotApplicable { .. } => "QUALITY_NOT_APPLICABLE",
+            ExportFormatError::QualityOutOfRange { .. } => "QUALITY_OUT_OF_RANGE",
+            ExportFormatError::SubsamplingNotApplicable { .. } => "SUBSAMPLING_NOT_APPLICABLE",
+            ExportFormatError::UnsupportedSubsampling { .. } => "UNSUPPORTED_SUBSAMPLING",
+        }
+    }
+}
+
+/// Container, color space and bit depth of an export.
+///
+/// See the [module documentation](self) for the defaults of missing
+/// fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportFormat {
+    /// File type
+    pub container: ExportContainer,
+    /// Color space the pixels are encoded in; its ICC profile is embedded
+    pub color_space: ExportColorSpace,
+    /// Bits per channel: 8 for JPEG, 8 or 16 for PNG
+    pub bit_depth: u8,
+    /// JPEG quality (1-100); `None` uses [`DEFAULT_JPEG_QUALITY`]
+    pub quality: Option<u8>,
+    /// JPEG chroma subsampling; `None` uses 4:4:4
+    pub chroma_subsampling: Option<ChromaSubsampling>,
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        Self {
+            container: ExportContainer::Jpeg,
+            color_space: ExportColorSpace::Srgb,
+            bit_depth: 8,
+            quality: None,
+            chroma_subsampling: None,
+        }
+    }
+}
+
+impl ExportFormat {
+    /// An 8-bit sRGB JPEG at `quality`.
+    pub fn jpeg(quality: u8) -> Self {
+        Self {
+            quality: Some(quality),
+            ..Self::default()
+        }
+    }
+
+    /// An 8-bit sRGB PNG.
+    pub fn png() -> Self {
+        Self {
+            container: ExportContainer::Png,
+            ..Self::default()
+        }
+    }
+
+    /// Check that the container can hold this bit depth, quality and
+    /// subsampling.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found: the bit depth, then the quality,
+    /// then the subsampling.
+    pub fn validate(&self) -> Result<(), ExportFormatError> {
+        let container = self.container.name();
+        match self.container {
+            ExportContainer::Jpeg => {
+                if self.bit_depth != 8 {
+                    return Err(ExportFormatError::JpegBitDepth {
+                        bit_depth: self.bit_depth,
+                    });
+                }
+                if let Some(quality) = self.quality.filter(|q| !(1..=100).contains(q)) {
+                    return Err(ExportFormatError::QualityOutOfRange { quality });
+                }
+                match self.chroma_subsampling {
+                    None | Some(ChromaSubsampling::Yuv444) => Ok(()),
+                    Some(subsampling) => {
+                        Err(ExportFormatError::UnsupportedSubsampling { subsampling })
+                    }
+                }
+            }
+            ExportContainer::Png => {
+                if self.bit_depth != 8 && self.bit_depth != 16 {
+                    return Err(ExportFormatError::PngBitDepth {
+                        bit_depth: self.bit_depth,
+                    });
+                }
+                if self.quality.is_some() {
+                    return Err(ExportFormatError::QualityNotApplicable { container });
+                }
+                if self.chroma_subsampling.is_some() {
+                    return Err(ExportFormatError::SubsamplingNotApplicable { container });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// JPEG quality to encode with.
+    pub fn jpeg_quality(&self) -> u8 {
+        self.quality.unwrap_or(DEFAULT_JPEG_QUALITY)
+    }
+}
+
+/// Encode rendered sRGB pixels in `format`.
+///
+/// The pixels are converted to the format's color space and its ICC
+/// profile is embedded, in APP2 segments for JPEG and an `iCCP` chunk for
+/// PNG. A 16-bit PNG gets the full precision of the conversion.
+///
+/// # Errors
+///
+/// Returns `EncodeError::InvalidFormat` if the format does not validate,
+/// and otherwise the errors of [`encode_jpeg`].
+pub fn encode_image(image: &DecodedImage, format: &ExportFormat) -> Result<Vec<u8>, EncodeError> {
+    format.validate()?;
+    let profile = format.color_space.profile();
+    let icc = profile.icc_profile();
+
+    match format.container {
+        ExportContainer::Jpeg => {
+            let jpeg = if profile == ColorProfile::Srgb {
+                encode_jpeg(
+                    &image.pixels,
+                    image.width,
+                    image.height,
+                    format.jpeg_quality(),
+                )?
+            } else {
+                let mut pixels = image.pixels.clone();
+                convert_from_srgb(&mut pixels, profile);
+                encode_jpeg(&pixels, image.width, image.height, format.jpeg_quality())?
+            };
+            Ok(embed_icc_profile(&jpeg, &icc))
+        }
+        ExportContainer::Png => {
+            image
+                .validate()
+                .map_err(|e| EncodeError::EncodingFailed(e.to_string()))?;
+            let (bytes, color_type) = if format.bit_depth == 16 {
+                let samples = convert_from_srgb_16(&image.pixels, profile);
+                let bytes = samples.iter().flat_map(|v| v.to_ne_bytes()).collect();
+                (bytes, ExtendedColorType::Rgb16)
+            } else {
+                let mut pixels = image.pixels.clone();
+                convert_from_srgb(&mut pixels, profile);
+                (pixels, ExtendedColorType::Rgb8)
+            };
+
+            let mut png = Vec::new();
+            let mut encoder = PngEncoder::new(Cursor::new(&mut png));
+            encoder
+                .set_icc_profile(icc)
+                .map_err(|e| EncodeError::EncodingFailed(e.to_string()))?;
+            encoder
+                .write_image(&bytes, image.width, image.height, color_type)
+                .map_err(|e| EncodeError::EncodingFailed(e.to_string()))?;
+            Ok(png)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_jpeg, detect_color_profile, extract_icc_profile};
+    use crate::testing::{color_bars, noise};
+    use image::codecs::png::PngDecoder;
+    use image::{ColorType, ImageDecoder};
+
+    fn png_profile_and_color(bytes: &[u8]) -> (ColorProfile, ColorType) {
+        let mut decoder = PngDecoder::new(Cursor::new(bytes)).unwrap();
+        let icc = decoder.icc_profile().unwrap().unwrap();
+        (detect_color_profile(&icc).unwrap(), decoder.color_type())
+    }
+
+    #[test]
+    fn test_invalid_combinations_give_specific_errors() {
+        let cases = [
+            (
+                ExportFormat {
+                    bit_depth: 16,
+                    ..ExportFormat::default()
+                },
+                ExportFormatError::JpegBitDepth { bit_depth: 16 },
+            ),
+            (
+                ExportFormat {
+                    bit_depth: 12,
+                    ..ExportFormat::png()
+                },
+                ExportFormatError::PngBitDepth { bit_depth: 12 },
+            ),
+            (
+                ExportFormat {
+                    quality: Some(90),
+                    ..ExportFormat::png()
+                },
+                ExportFormatError::QualityNotApplicable { container: "PNG" },
+            ),
+            (
+                ExportFormat::jpeg(0),
+                ExportFormatError::QualityOutOfRange { quality: 0 },
+            ),
+            (
+                ExportFormat {
+                    chroma_subsampling: Some(ChromaSubsampling::Yuv444),
+                    ..ExportFormat::png()
+                },
+                ExportFormatError::SubsamplingNotApplicable { container: "PNG" },
+            ),
+            (
+                ExportFormat {
+                    chroma_subsampling: Some(ChromaSubsampling::Yuv420),
+                    ..ExportFormat::default()
+                },
+                ExportFormatError::UnsupportedSubsampling {
+                    subsampling: ChromaSubsampling::Yuv420,
+                },
+            ),
+        ];
+        for (format, expected) in cases {
+            assert_eq!(format.validate(), Err(expected), "{:?}", format);
+            assert!(matches!(
+                encode_image(&noise(4, 4, 1), &format),
+                Err(EncodeError::InvalidFormat(e)) if e == expected
+            ));
+        }
+    }
+
+    #[test]
+    fn test_jpeg_embeds_requested_profile() {
+        let image = color_bars(64, 16);
+        let mut srgb_error = 0.0;
+        for color_space in [
+            ExportColorSpace::Srgb,
+            ExportColorSpace::DisplayP3,
+            ExportColorSpace::AdobeRgb,
+        ] {
+            let format = ExportFormat {
+                color_space,
+                ..ExportFormat::jpeg(95)
+            };
+            let bytes = encode_image(&image, &format).unwrap();
+            let icc = extract_icc_profile(&bytes).unwrap();
+            assert_eq!(detect_color_profile(&icc), Some(color_space.profile()));
+
+            // Decoding converts back to sRGB, so the error is about the
+            // compression loss of the sRGB file
+            let decoded = decode_jpeg(&bytes).unwrap();
+            let error: f64 = image
+                .pixels
+                .iter()
+                .zip(&decoded.pixels)
+                .map(|(&a, &b)| (a as f64 - b as f64).abs())
+                .sum::<f64>()
+                / image.pixels.len() as f64;
+            if color_space == ExportColorSpace::Srgb {
+                srgb_error = error;
+            }
+            assert!(
+                error < srgb_error + 1.0,
+                "{:?}: mean error {}",
+                color_space,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_png_embeds_profile_at_requested_depth() {
+        let image = noise(20, 10, 3);
+        for (color_space, bit_depth, color_type) in [
+            (ExportColorSpace::Srgb, 8, ColorType::Rgb8),
+            (ExportColorSpace::AdobeRgb, 8, ColorType::Rgb8),
+            (ExportColorSpace::DisplayP3, 16, ColorType::Rgb16),
+            (ExportColorSpace::Srgb, 16, ColorType::Rgb16),
+        ] {
+            let format = ExportFormat {
+                color_space,
+                bit_depth,
+                ..ExportFormat::png()
+            };
+            let bytes = encode_image(&image, &format).unwrap();
+            assert_eq!(
+                png_profile_and_color(&bytes),
+                (color_space.profile(), color_type)
+            );
+        }
+
+        // 16-bit sRGB widens the 8-bit values exactly
+        let format = ExportFormat {
+            bit_depth: 16,
+            ..ExportFormat::png()
+        };
+        let decoded = image::load_from_memory(&encode_image(&image, &format).unwrap()).unwrap();
+        let samples = decoded.into_rgb16().into_raw();
+        assert!(samples
+            .iter()
+            .zip(&image.pixels)
+            .all(|(&s, &p)| s == u16::from(p) * 257));
+    }
+
+    #[test]
+    fn test_missing_fields_take_defaults() {
+        let format: ExportFormat = serde_json::from_str("{}").unwrap();
+        assert_eq!(format, ExportFormat::default());
+        assert_eq!(format.container, ExportContainer::Jpeg);
+        assert_eq!(format.color_space, ExportColorSpace::Srgb);
+        assert_eq!(format.bit_depth, 8);
+        assert_eq!(format.jpeg_quality(), DEFAULT_JPEG_QUALITY);
+        assert_eq!(format.validate(), Ok(()));
+
+        let png: ExportFormat =
+            serde_json::from_str(r#"{ "container": "png", "color_space": "display_p3" }"#).unwrap();
+        assert_eq!(png.bit_depth, 8);
+        assert_eq!(png.quality, None);
+        assert_eq!(png.validate(), Ok(()));
+
+        // The default format writes the same file as encode_jpeg at the
+        // default quality, plus the sRGB profile
+        let image = noise(16, 16, 4);
+        let bytes = encode_image(&image, &ExportFormat::default()).unwrap();
+        let plain = encode_jpeg(&image.pixels, 16, 16, DEFAULT_JPEG_QUALITY).unwrap();
+        let icc = extract_icc_profile(&bytes).unwrap();
+        assert_eq!(bytes.len(), plain.len() + icc.len() + 18);
+    }
+}
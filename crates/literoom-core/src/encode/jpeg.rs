@@ -1,15 +1,15 @@
 //! JPEG encoding for export.
 //!
-//! This module provides JPEG encoding using the `image` crate's JPEG encoder.
-//! The encoder supports configurable quality settings for balancing file size
-//! and image quality during export.
-
-use image::codecs::jpeg::JpegEncoder;
-use image::ExtendedColorType;
-use image::ImageEncoder;
-use std::io::Cursor;
+//! This module provides one-shot JPEG encoding on top of the row-streaming
+//! [`JpegStreamEncoder`]. The encoder supports configurable quality settings
+//! for balancing file size and image quality during export.
+
 use thiserror::Error;
 
+use super::{ExportFormatError, JpegStreamEncoder};
+use crate::perf;
+use crate::validate::checked_buffer_len;
+
 /// Errors that can occur during JPEG encoding.
 #[derive(Debug, Error)]
 pub enum EncodeError {
@@ -21,9 +21,43 @@ pub enum EncodeError {
     #[error("Invalid dimensions: width ({width}) and height ({height}) must be non-zero")]
     InvalidDimensions { width: u32, height: u32 },
 
+    /// Tiled export was asked for tiles of size zero
+    #[error("Invalid tile size: must be non-zero")]
+    InvalidTileSize,
+
     /// JPEG encoding failed
     #[error("JPEG encoding failed: {0}")]
     EncodingFailed(String),
+
+    /// The input is not a well-formed JPEG, or its EXIF data cannot be edited
+    #[error("Invalid JPEG: {0}")]
+    InvalidJpeg(String),
+
+    /// The JPEG uses a coding process that cannot be transformed without
+    /// re-encoding (progressive, arithmetic-coded, lossless or 12-bit)
+    #[error("Unsupported JPEG: {0}")]
+    UnsupportedJpeg(String),
+
+    /// The pixels were already rotated but the EXIF orientation still asks
+    /// for the rotation, so the intended display orientation is ambiguous
+    #[error("Image is already rotated but its EXIF orientation is still {orientation}")]
+    ConflictingOrientation { orientation: u8 },
+
+    /// A streamed band does not end on a row boundary
+    #[error("Partial row: expected a multiple of {row_bytes} bytes per band, got {actual}")]
+    PartialRow { row_bytes: usize, actual: usize },
+
+    /// More rows were streamed than the image height, or fewer before finishing
+    #[error("Wrong number of rows: image has {expected}, got {actual}")]
+    RowCount { expected: u32, actual: usize },
+
+    /// The stream encoder was used after it finished
+    #[error("JPEG stream is already finished")]
+    Finished,
+
+    /// The export format asks for something its container cannot hold
+    #[error("Invalid export format: {0}")]
+    InvalidFormat(#[from] ExportFormatError),
 }
 
 /// Encode RGB pixel data to JPEG bytes.
@@ -69,7 +103,8 @@ pub fn encode_jpeg(
     }
 
     // Validate pixel data length
-    let expected_len = (width as usize) * (height as usize) * 3;
+    let expected_len = checked_buffer_len(width, height, 3)
+        .map_err(|e| EncodeError::EncodingFailed(e.to_string()))?;
     if pixels.len() != expected_len {
         return Err(EncodeError::InvalidPixelData {
             expected: expected_len,
@@ -77,21 +112,19 @@ pub fn encode_jpeg(
         });
     }
 
-    // Clamp quality to valid range (1-100)
-    let quality = quality.clamp(1, 100);
-
-    // Create output buffer
-    let mut buffer = Cursor::new(Vec::new());
-
-    // Create JPEG encoder with specified quality
-    let encoder = JpegEncoder::new_with_quality(&mut buffer, quality);
+    let _perf = perf::scope("encode_jpeg");
+    perf::record_dimensions(width, height);
+    perf::record_pixels(width as u64 * height as u64);
 
-    // Encode the image
-    encoder
-        .write_image(pixels, width, height, ExtendedColorType::Rgb8)
-        .map_err(|e| EncodeError::EncodingFailed(e.to_string()))?;
+    let mut buffer = Vec::new();
+    let mut encoder = JpegStreamEncoder::new(width, height, quality, |chunk: &[u8]| {
+        buffer.extend_from_slice(chunk)
+    })?;
+    encoder.write_rows(pixels)?;
+    encoder.finish()?;
+    drop(encoder);
 
-    Ok(buffer.into_inner())
+    Ok(perf::track_vec(buffer))
 }
 
 #[cfg(test)]
@@ -225,6 +258,17 @@ mod tests {
         assert!(jpeg_bytes.len() > 500); // Not too small
         assert!(jpeg_bytes.len() < 50000); // Not too large for 100x100
     }
+
+    #[test]
+    fn test_encode_jpeg_reports_counters() {
+        let pixels = vec![128u8; 64 * 48 * 3];
+        let jpeg_bytes = encode_jpeg(&pixels, 64, 48, 90).unwrap();
+
+        let stats = crate::perf::last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "encode_jpeg");
+        assert_eq!(stats.pixels_processed, 64 * 48);
+        assert!(stats.bytes_allocated >= jpeg_bytes.len() as u64);
+    }
 }
 
 // ============================================================================
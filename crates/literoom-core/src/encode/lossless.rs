@@ -0,0 +1,509 @@
+//! Lossless quarter-turn rotation of JPEG files.
+//!
+//! Rotating by re-encoding costs quality every time. [`rotate_jpeg_lossless`]
+//! instead rewrites the EXIF orientation tag, which viewers (and
+//! [`decode_jpeg`](crate::decode::decode_jpeg)) apply when displaying the
+//! image. Every segment is copied as is except the EXIF APP1 segment, and
+//! the entropy-coded scan data is never touched.
+
+use super::EncodeError;
+use crate::decode::{
+    is_start_of_frame, read_u16, read_u32, JpegSegment, JpegSegments, Orientation,
+};
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_PIXEL_X_DIMENSION: u16 = 0xA002;
+const TAG_PIXEL_Y_DIMENSION: u16 = 0xA003;
+
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+
+/// Size of one IFD entry in bytes.
+const ENTRY_SIZE: usize = 12;
+
+/// Largest value of a segment's length field, which counts itself.
+const MAX_SEGMENT_LENGTH: usize = 0xFFFF;
+
+/// Rotate a JPEG by quarter turns without re-encoding it.
+///
+/// The EXIF orientation is combined with the rotation, so a file that is
+/// already tagged (say, shot in portrait) turns relative to how it is
+/// displayed. A file without EXIF data gets a minimal APP1 segment holding
+/// just the orientation, placed right after SOI or after a JFIF APP0
+/// segment. If the result displays like the input, the input is returned
+/// unchanged.
+///
+/// # Arguments
+///
+/// * `bytes` - JPEG file bytes
+/// * `quarter_turns` - Clockwise quarter turns. Note that this is the
+///   opposite direction to the edit state's rotation angle, where positive
+///   is counter-clockwise.
+///
+/// # Errors
+///
+/// Returns `EncodeError::InvalidJpeg` if the header segments are malformed
+/// or the EXIF data is truncated or would no longer fit in a segment, and
+/// `EncodeError::ConflictingOrientation` if the orientation asks for a
+/// quarter turn while the EXIF pixel dimensions show the stored pixels were
+/// already turned: such a file displays wrong in some viewers and right in
+/// others, so there is no correct tag to write.
+///
+/// # Example
+///
+/// ```ignore
+/// use literoom_core::encode::rotate_jpeg_lossless;
+///
+/// // Rotate 90 degrees clockwise, keeping every compressed byte
+/// let rotated = rotate_jpeg_lossless(&jpeg_bytes, 1)?;
+/// ```
+pub fn rotate_jpeg_lossless(bytes: &[u8], quarter_turns: u8) -> Result<Vec<u8>, EncodeError> {
+    let segments = header_segments(bytes)?;
+    let frame_size = segments
+        .iter()
+        .find(|segment| is_start_of_frame(segment.marker))
+        .and_then(|segment| {
+            let payload = segment.payload;
+            let height = u16::from_be_bytes([*payload.get(1)?, *payload.get(2)?]) as u32;
+            let width = u16::from_be_bytes([*payload.get(3)?, *payload.get(4)?]) as u32;
+            Some((width, height))
+        });
+    let exif_segment = segments
+        .iter()
+        .find(|segment| segment.marker == 0xE1 && segment.payload.starts_with(EXIF_HEADER));
+
+    let Some(segment) = exif_segment else {
+        let target = Orientation::Normal.rotated_cw(quarter_turns);
+        if target == Orientation::Normal {
+            return Ok(bytes.to_vec());
+        }
+        // EXIF belongs right after SOI, but JFIF requires its APP0 first
+        let at = segments
+            .iter()
+            .take_while(|segment| segment.marker == 0xE0)
+            .last()
+            .map_or(2, |segment| segment.end);
+        return splice(bytes, at, at, &minimal_tiff(target));
+    };
+
+    let tiff = &segment.payload[EXIF_HEADER.len()..];
+    let exif = ExifIfd0::parse(tiff)?;
+    if exif.orientation.swaps_dimensions() {
+        if let (Some((width, height)), Some(stored)) = (frame_size, exif.pixel_dimensions(tiff)) {
+            if width != height && stored == (height, width) {
+                return Err(EncodeError::ConflictingOrientation {
+                    orientation: exif.orientation as u8,
+                });
+            }
+        }
+    }
+
+    let target = exif.orientation.rotated_cw(quarter_turns);
+    if target == exif.orientation {
+        return Ok(bytes.to_vec());
+    }
+    splice(
+        bytes,
+        segment.start,
+        segment.end,
+        &exif.with_orientation(tiff, target),
+    )
+}
+
+/// Walk the segments from SOI through the first SOS, whose segment is the
+/// last one returned and is followed by the entropy-coded data.
+pub(super) fn header_segments(bytes: &[u8]) -> Result<Vec<JpegSegment<'_>>, EncodeError> {
+    let invalid = |message: &str| EncodeError::InvalidJpeg(message.to_string());
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return Err(invalid("missing start of image marker"));
+    }
+
+    let mut walk = JpegSegments::new(bytes);
+    let mut segments = Vec::new();
+    for segment in walk.by_ref() {
+        segments.push(segment);
+        if segment.marker == 0xDA {
+            return Ok(segments);
+        }
+    }
+    Err(if walk.truncated().is_some() {
+        invalid("truncated segment")
+    } else if walk.end_of_image().is_none() && walk.position() < bytes.len() {
+        invalid("expected a marker")
+    } else {
+        invalid("no image data")
+    })
+}
+
+/// Replace `bytes[start..end]` with an EXIF APP1 segment holding `tiff`.
+fn splice(bytes: &[u8], start: usize, end: usize, tiff: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    let length = 2 + EXIF_HEADER.len() + tiff.len();
+    if length > MAX_SEGMENT_LENGTH {
+        return Err(EncodeError::InvalidJpeg(
+            "EXIF data too large for an APP1 segment".to_string(),
+        ));
+    }
+
+    let mut out = Vec::with_capacity(bytes.len() - (end - start) + 2 + length);
+    out.extend_from_slice(&bytes[..start]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&(length as u16).to_be_bytes());
+    out.extend_from_slice(EXIF_HEADER);
+    out.extend_from_slice(tiff);
+    out.extend_from_slice(&bytes[end..]);
+    Ok(out)
+}
+
+/// Big-endian TIFF with a single IFD holding the orientation.
+fn minimal_tiff(orientation: Orientation) -> Vec<u8> {
+    let mut tiff = b"MM\x00\x2A".to_vec();
+    tiff.extend_from_slice(&8u32.to_be_bytes());
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    tiff.extend(orientation_entry(orientation, false));
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+    tiff
+}
+
+/// IFD entry for the orientation tag.
+fn orientation_entry(orientation: Orientation, le: bool) -> [u8; ENTRY_SIZE] {
+    let mut entry = [0u8; ENTRY_SIZE];
+    write_u16(&mut entry, 0, TAG_ORIENTATION, le);
+    write_u16(&mut entry, 2, TYPE_SHORT, le);
+    write_u32(&mut entry, 4, 1, le);
+    write_u16(&mut entry, 8, orientation as u16, le);
+    entry
+}
+
+/// Location of IFD0 within EXIF TIFF data.
+struct ExifIfd0 {
+    le: bool,
+    offset: usize,
+    count: usize,
+    /// Offset of the orientation entry, if IFD0 has one
+    orientation_entry: Option<usize>,
+    orientation: Orientation,
+}
+
+impl ExifIfd0 {
+    fn parse(tiff: &[u8]) -> Result<Self, EncodeError> {
+        let truncated = || EncodeError::InvalidJpeg("truncated EXIF data".to_string());
+        let le = match tiff.get(..4) {
+            Some([b'I', b'I', 0x2A, 0x00]) => true,
+            Some([b'M', b'M', 0x00, 0x2A]) => false,
+            _ => return Err(EncodeError::InvalidJpeg("invalid EXIF header".to_string())),
+        };
+        let offset = read_u32(tiff, 4, le).ok_or_else(truncated)? as usize;
+        let count = read_u16(tiff, offset, le).ok_or_else(truncated)? as usize;
+        // Entries and the next-IFD pointer must all be present
+        if offset + 2 + count * ENTRY_SIZE + 4 > tiff.len() {
+            return Err(truncated());
+        }
+
+        let orientation_entry = (0..count)
+            .map(|i| offset + 2 + i * ENTRY_SIZE)
+            .find(|&entry| read_u16(tiff, entry, le) == Some(TAG_ORIENTATION));
+        let orientation = orientation_entry
+            .and_then(|entry| read_u16(tiff, entry + 8, le))
+            .map_or(Orientation::Normal, |value| Orientation::from(value as u32));
+
+        Ok(Self {
+            le,
+            offset,
+            count,
+            orientation_entry,
+            orientation,
+        })
+    }
+
+    /// `PixelXDimension` and `PixelYDimension` from the EXIF sub-IFD.
+    fn pixel_dimensions(&self, tiff: &[u8]) -> Option<(u32, u32)> {
+        let exif_ifd = self.entries().find_map(|entry| {
+            (read_u16(tiff, entry, self.le)? == TAG_EXIF_IFD)
+                .then(|| read_u32(tiff, entry + 8, self.le))
+                .flatten()
+        })? as usize;
+
+        let count = read_u16(tiff, exif_ifd, self.le)? as usize;
+        let (mut width, mut height) = (None, None);
+        for entry in (0..count).map(|i| exif_ifd + 2 + i * ENTRY_SIZE) {
+            let value = match read_u16(tiff, entry + 2, self.le)? {
+                TYPE_SHORT => read_u16(tiff, entry + 8, self.le).map(u32::from),
+                TYPE_LONG => read_u32(tiff, entry + 8, self.le),
+                _ => None,
+            };
+            match read_u16(tiff, entry, self.le)? {
+                TAG_PIXEL_X_DIMENSION => width = value,
+                TAG_PIXEL_Y_DIMENSION => height = value,
+                _ => {}
+            }
+        }
+        Some((width?, height?))
+    }
+
+    fn entries(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.count).map(|i| self.offset + 2 + i * ENTRY_SIZE)
+    }
+
+    /// Copy of `tiff` with the orientation set to `orientation`.
+    ///
+    /// An existing entry is overwritten in place. Otherwise IFD0 is copied
+    /// to the end with the new entry added in tag order, so no other data
+    /// moves and every offset in the file stays valid.
+    fn with_orientation(&self, tiff: &[u8], orientation: Orientation) -> Vec<u8> {
+        let mut out = tiff.to_vec();
+        let new_entry = orientation_entry(orientation, self.le);
+        if let Some(entry) = self.orientation_entry {
+            out[entry..entry + ENTRY_SIZE].copy_from_slice(&new_entry);
+            return out;
+        }
+
+        // IFDs start on a word boundary
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+        let new_offset = out.len();
+        let insert_at = self
+            .entries()
+            .position(|entry| {
+                read_u16(tiff, entry, self.le).is_some_and(|tag| tag > TAG_ORIENTATION)
+            })
+            .unwrap_or(self.count);
+
+        let mut count = [0u8; 2];
+        write_u16(&mut count, 0, (self.count + 1) as u16, self.le);
+        out.extend_from_slice(&count);
+        let entries_start = self.offset + 2;
+        let split = entries_start + insert_at * ENTRY_SIZE;
+        let entries_end = entries_start + self.count * ENTRY_SIZE;
+        out.extend_from_slice(&tiff[entries_start..split]);
+        out.extend_from_slice(&new_entry);
+        out.extend_from_slice(&tiff[split..entries_end]);
+        // Pointer to the next IFD (the thumbnail's IFD1, if any)
+        out.extend_from_slice(&tiff[entries_end..entries_end + 4]);
+
+        write_u32(&mut out, 4, new_offset as u32, self.le);
+        out
+    }
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16, le: bool) {
+    let bytes = if le {
+        value.to_le_bytes()
+    } else {
+        value.to_be_bytes()
+    };
+    data[offset..offset + 2].copy_from_slice(&bytes);
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32, le: bool) {
+    let bytes = if le {
+        value.to_le_bytes()
+    } else {
+        value.to_be_bytes()
+    };
+    data[offset..offset + 4].copy_from_slice(&bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_jpeg, decode_jpeg_no_orientation, probe_image};
+    use crate::encode::encode_jpeg;
+    use crate::testing::noise;
+    use exif::{In, Reader, Tag};
+    use std::io::Cursor;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        encode_jpeg(&noise(width, height, 5).pixels, width, height, 85).unwrap()
+    }
+
+    /// Bytes from the first SOS marker to the end of the file.
+    fn scan_data(bytes: &[u8]) -> &[u8] {
+        let sos = bytes.windows(2).position(|w| w == [0xFF, 0xDA]).unwrap();
+        &bytes[sos..]
+    }
+
+    /// Big-endian TIFF with IFD0 holding `entries` (tag, type, value) and,
+    /// if `pixel_dimensions` is set, an EXIF sub-IFD with them.
+    fn tiff(entries: &[(u16, u16, u32)], pixel_dimensions: Option<(u32, u32)>) -> Vec<u8> {
+        let mut entries = entries.to_vec();
+        let sub_ifd_offset = 8 + 2 + (entries.len() + 1) as u32 * 12 + 4;
+        if pixel_dimensions.is_some() {
+            entries.push((TAG_EXIF_IFD, TYPE_LONG, sub_ifd_offset));
+        }
+
+        let write_ifd = |out: &mut Vec<u8>, entries: &[(u16, u16, u32)]| {
+            out.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+            for &(tag, field_type, value) in entries {
+                out.extend_from_slice(&tag.to_be_bytes());
+                out.extend_from_slice(&field_type.to_be_bytes());
+                out.extend_from_slice(&1u32.to_be_bytes());
+                if field_type == TYPE_SHORT {
+                    out.extend_from_slice(&(value as u16).to_be_bytes());
+                    out.extend_from_slice(&[0, 0]);
+                } else {
+                    out.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            out.extend_from_slice(&0u32.to_be_bytes());
+        };
+
+        let mut out = b"MM\x00\x2A".to_vec();
+        out.extend_from_slice(&8u32.to_be_bytes());
+        write_ifd(&mut out, &entries);
+        if let Some((width, height)) = pixel_dimensions {
+            assert_eq!(out.len() as u32, sub_ifd_offset);
+            write_ifd(
+                &mut out,
+                &[
+                    (TAG_PIXEL_X_DIMENSION, TYPE_LONG, width),
+                    (TAG_PIXEL_Y_DIMENSION, TYPE_LONG, height),
+                ],
+            );
+        }
+        out
+    }
+
+    /// Insert an EXIF APP1 segment holding `tiff` right after SOI.
+    fn with_exif(jpeg: &[u8], tiff: &[u8]) -> Vec<u8> {
+        splice(jpeg, 2, 2, tiff).unwrap()
+    }
+
+    fn read_exif(bytes: &[u8]) -> exif::Exif {
+        Reader::new()
+            .read_from_container(&mut Cursor::new(bytes))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_scan_data_is_unchanged() {
+        let source = jpeg(40, 24);
+        for turns in 1..4 {
+            let rotated = rotate_jpeg_lossless(&source, turns).unwrap();
+            assert_eq!(scan_data(&rotated), scan_data(&source));
+        }
+    }
+
+    #[test]
+    fn test_inserts_exif_after_jfif() {
+        let source = jpeg(40, 24);
+        let rotated = rotate_jpeg_lossless(&source, 1).unwrap();
+
+        assert_eq!(probe_image(&rotated).orientation, Orientation::Rotate90CW);
+        let field = read_exif(&rotated)
+            .get_field(Tag::Orientation, In::PRIMARY)
+            .unwrap()
+            .value
+            .get_uint(0);
+        assert_eq!(field, Some(6));
+
+        // Everything before the new segment is kept in place
+        let segments = header_segments(&rotated).unwrap();
+        let app1 = segments.iter().position(|s| s.marker == 0xE1).unwrap();
+        assert!(segments[..app1].iter().all(|s| s.marker == 0xE0));
+        let at = segments[app1].start;
+        assert_eq!(&rotated[..at], &source[..at]);
+        assert_eq!(&rotated[segments[app1].end..], &source[at..]);
+    }
+
+    #[test]
+    fn test_decodes_rotated() {
+        let source = jpeg(40, 24);
+        let stored = decode_jpeg_no_orientation(&source).unwrap();
+
+        let displayed = decode_jpeg(&rotate_jpeg_lossless(&source, 1).unwrap()).unwrap();
+
+        // A clockwise turn: the bottom-left of the stored image is top-left
+        assert_eq!((displayed.width, displayed.height), (24, 40));
+        for y in 0..40 {
+            for x in 0..24 {
+                let out = ((y * 24 + x) * 3) as usize;
+                let src = (((23 - x) * 40 + y) * 3) as usize;
+                assert_eq!(
+                    displayed.pixels[out..out + 3],
+                    stored.pixels[src..src + 3],
+                    "pixel ({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_existing_orientation_is_combined() {
+        let source = with_exif(
+            &jpeg(40, 24),
+            &tiff(&[(TAG_ORIENTATION, TYPE_SHORT, 6)], None),
+        );
+
+        let rotated = rotate_jpeg_lossless(&source, 1).unwrap();
+
+        assert_eq!(rotated.len(), source.len());
+        assert_eq!(probe_image(&rotated).orientation, Orientation::Rotate180);
+        let back = rotate_jpeg_lossless(&rotated, 3).unwrap();
+        assert_eq!(back, source);
+    }
+
+    #[test]
+    fn test_orientation_added_to_existing_exif() {
+        // IFD0 has tags on both sides of the orientation tag
+        let source = with_exif(
+            &jpeg(40, 24),
+            &tiff(
+                &[(0x0100, TYPE_LONG, 40), (0x0128, TYPE_SHORT, 2)],
+                Some((40, 24)),
+            ),
+        );
+
+        let rotated = rotate_jpeg_lossless(&source, 2).unwrap();
+
+        assert_eq!(scan_data(&rotated), scan_data(&source));
+        let exif = read_exif(&rotated);
+        let uint = |tag: Tag, ifd: In| exif.get_field(tag, ifd).unwrap().value.get_uint(0);
+        assert_eq!(uint(Tag::Orientation, In::PRIMARY), Some(3));
+        assert_eq!(uint(Tag::ImageWidth, In::PRIMARY), Some(40));
+        assert_eq!(uint(Tag::PixelXDimension, In::PRIMARY), Some(40));
+    }
+
+    #[test]
+    fn test_no_turn_returns_input() {
+        let source = jpeg(16, 16);
+        assert_eq!(rotate_jpeg_lossless(&source, 0).unwrap(), source);
+        assert_eq!(rotate_jpeg_lossless(&source, 4).unwrap(), source);
+    }
+
+    #[test]
+    fn test_rotated_pixels_with_stale_tag_are_rejected() {
+        // Stored 24x40, but tagged to turn and recorded as shot 40x24
+        let source = with_exif(
+            &jpeg(24, 40),
+            &tiff(&[(TAG_ORIENTATION, TYPE_SHORT, 6)], Some((40, 24))),
+        );
+
+        let err = rotate_jpeg_lossless(&source, 1).unwrap_err();
+
+        assert!(matches!(
+            err,
+            EncodeError::ConflictingOrientation { orientation: 6 }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_input_is_rejected() {
+        let source = jpeg(16, 16);
+        for bytes in [
+            &b"not a jpeg"[..],
+            &source[..source.len() / 20],
+            &[0xFF, 0xD8],
+        ] {
+            assert!(matches!(
+                rotate_jpeg_lossless(bytes, 1),
+                Err(EncodeError::InvalidJpeg(_))
+            ));
+        }
+    }
+}
@@ -2,6 +2,12 @@
 //!
 //! This module provides functionality for:
 //! - Encoding images to JPEG format with configurable quality
+//! - Exporting to JPEG or 8/16-bit PNG in sRGB, Display P3 or Adobe RGB,
+//!   with the matching ICC profile embedded
+//! - Streaming JPEG encoding, fed in bands of rows with output delivered in chunks
+//! - Rotating JPEG files by quarter turns without re-encoding them
+//! - Cropping JPEG files to MCU boundaries without re-encoding them
+//! - Exporting very large images as a grid of JPEG tiles
 //!
 //! # Architecture
 //!
@@ -18,6 +24,20 @@
 //! println!("Encoded {} bytes", jpeg_bytes.len());
 //! ```
 
+mod crop;
+mod format;
 mod jpeg;
+mod lossless;
+mod stream;
+mod tiled;
 
+pub(crate) use crop::crop_jpeg_rows;
+pub use crop::{crop_jpeg_lossless, LosslessCropResult};
+pub use format::{
+    encode_image, ChromaSubsampling, ExportColorSpace, ExportContainer, ExportFormat,
+    ExportFormatError, DEFAULT_JPEG_QUALITY,
+};
 pub use jpeg::{encode_jpeg, EncodeError};
+pub use lossless::rotate_jpeg_lossless;
+pub use stream::JpegStreamEncoder;
+pub use tiled::{export_tiled, TileDescriptor, TiledExport};
@@ -0,0 +1,795 @@
+//! Row-streaming JPEG encoding.
+//!
+//! [`JpegStreamEncoder`] accepts RGB rows in bands of any height and hands
+//! the compressed bytes to a sink as soon as each strip of 8 rows has been
+//! encoded. Large exports can therefore be written out while they are still
+//! being rendered, without holding the whole image or the whole file in
+//! memory.
+//!
+//! The output is a baseline JPEG with 4:4:4 sampling and the standard
+//! Huffman tables. It is byte-for-byte what the `image` crate's encoder
+//! produces for the same pixels and quality, so switching between the
+//! one-shot and streaming paths never changes an exported file.
+//!
+//! The forward DCT is a translation of `jfdctint.c` and is based in part on
+//! the work of the Independent JPEG Group.
+
+use super::EncodeError;
+
+/// Rows per strip of 8x8 blocks.
+const STRIP_ROWS: usize = 8;
+
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const APP0: u8 = 0xE0;
+const SOF0: u8 = 0xC0;
+const DQT: u8 = 0xDB;
+const DHT: u8 = 0xC4;
+const SOS: u8 = 0xDA;
+
+const STD_LUMA_QTABLE: [u8; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+const STD_CHROMA_QTABLE: [u8; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+const STD_LUMA_DC_CODE_LENGTHS: [u8; 16] = [
+    0x00, 0x01, 0x05, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const STD_LUMA_DC_VALUES: [u8; 12] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B,
+];
+const STD_LUMA_AC_CODE_LENGTHS: [u8; 16] = [
+    0x00, 0x02, 0x01, 0x03, 0x03, 0x02, 0x04, 0x03, 0x05, 0x05, 0x04, 0x04, 0x00, 0x00, 0x01, 0x7D,
+];
+const STD_LUMA_AC_VALUES: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xA1, 0x08, 0x23, 0x42, 0xB1, 0xC1, 0x15, 0x52, 0xD1, 0xF0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0A, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2A, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7,
+    0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3, 0xC4, 0xC5,
+    0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA, 0xE1, 0xE2,
+    0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+const STD_CHROMA_DC_CODE_LENGTHS: [u8; 16] = [
+    0x00, 0x03, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const STD_CHROMA_DC_VALUES: [u8; 12] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B,
+];
+const STD_CHROMA_AC_CODE_LENGTHS: [u8; 16] = [
+    0x00, 0x02, 0x01, 0x02, 0x04, 0x04, 0x03, 0x04, 0x07, 0x05, 0x04, 0x04, 0x00, 0x01, 0x02, 0x77,
+];
+const STD_CHROMA_AC_VALUES: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xA1, 0xB1, 0xC1, 0x09, 0x23, 0x33, 0x52, 0xF0,
+    0x15, 0x62, 0x72, 0xD1, 0x0A, 0x16, 0x24, 0x34, 0xE1, 0x25, 0xF1, 0x17, 0x18, 0x19, 0x1A, 0x26,
+    0x27, 0x28, 0x29, 0x2A, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5,
+    0xA6, 0xA7, 0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3,
+    0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA,
+    0xE2, 0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+pub(super) const UNZIGZAG: [u8; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+pub(super) const LUMA_DC_LUT: [(u8, u16); 256] =
+    huffman_lut(&STD_LUMA_DC_CODE_LENGTHS, &STD_LUMA_DC_VALUES);
+pub(super) const LUMA_AC_LUT: [(u8, u16); 256] =
+    huffman_lut(&STD_LUMA_AC_CODE_LENGTHS, &STD_LUMA_AC_VALUES);
+pub(super) const CHROMA_DC_LUT: [(u8, u16); 256] =
+    huffman_lut(&STD_CHROMA_DC_CODE_LENGTHS, &STD_CHROMA_DC_VALUES);
+pub(super) const CHROMA_AC_LUT: [(u8, u16); 256] =
+    huffman_lut(&STD_CHROMA_AC_CODE_LENGTHS, &STD_CHROMA_AC_VALUES);
+
+/// A JPEG encoder that is fed pixel rows incrementally.
+///
+/// Rows are RGB, 3 bytes per pixel, top to bottom. Call
+/// [`write_rows`](Self::write_rows) with as many whole rows at a time as is
+/// convenient, then [`finish`](Self::finish) once every row has been
+/// written. The sink receives the file in order; concatenating every chunk
+/// gives the complete JPEG.
+///
+/// # Example
+///
+/// ```
+/// use literoom_core::encode::JpegStreamEncoder;
+///
+/// let mut jpeg = Vec::new();
+/// let mut encoder = JpegStreamEncoder::new(64, 48, 90, |chunk: &[u8]| {
+///     jpeg.extend_from_slice(chunk)
+/// })
+/// .unwrap();
+///
+/// let band = vec![128u8; 64 * 16 * 3];
+/// for _ in 0..3 {
+///     encoder.write_rows(&band).unwrap();
+/// }
+/// encoder.finish().unwrap();
+/// drop(encoder);
+///
+/// assert_eq!(&jpeg[0..2], &[0xFF, 0xD8]);
+/// ```
+pub struct JpegStreamEncoder<S: FnMut(&[u8])> {
+    width: u32,
+    height: u32,
+    rows_written: u32,
+    /// Rows of the strip currently being collected
+    strip: Vec<u8>,
+    tables: [[u8; 64]; 2],
+    dc_pred: [i32; 3],
+    writer: BitWriter,
+    sink: S,
+    finished: bool,
+}
+
+impl<S: FnMut(&[u8])> JpegStreamEncoder<S> {
+    /// Start encoding a `width` x `height` image.
+    ///
+    /// The file header is passed to `sink` straight away. `quality` is
+    /// clamped to 1-100 like [`encode_jpeg`](super::encode_jpeg).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::InvalidDimensions`] if either dimension is
+    /// zero, or [`EncodeError::EncodingFailed`] if either is larger than
+    /// JPEG allows (65535).
+    pub fn new(width: u32, height: u32, quality: u8, sink: S) -> Result<Self, EncodeError> {
+        if width == 0 || height == 0 {
+            return Err(EncodeError::InvalidDimensions { width, height });
+        }
+        let (Ok(frame_width), Ok(frame_height)) = (u16::try_from(width), u16::try_from(height))
+        else {
+            return Err(EncodeError::EncodingFailed(format!(
+                "Invalid image size ({} x {}) to encode",
+                width, height
+            )));
+        };
+
+        let mut encoder = Self {
+            width,
+            height,
+            rows_written: 0,
+            strip: Vec::with_capacity(width as usize * 3 * STRIP_ROWS),
+            tables: scaled_tables(quality),
+            dc_pred: [0; 3],
+            writer: BitWriter::default(),
+            sink,
+            finished: false,
+        };
+        encoder.write_header(frame_width, frame_height);
+        encoder.flush();
+        Ok(encoder)
+    }
+
+    /// Encode the next band of rows.
+    ///
+    /// `rows` must hold a whole number of rows. Every complete strip of 8
+    /// rows is compressed and passed to the sink before this returns; any
+    /// remainder waits for the next call.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::PartialRow`] if `rows` does not end on a row
+    /// boundary, [`EncodeError::RowCount`] if it would take the image past
+    /// its height, or [`EncodeError::Finished`] after [`finish`](Self::finish).
+    pub fn write_rows(&mut self, rows: &[u8]) -> Result<(), EncodeError> {
+        if self.finished {
+            return Err(EncodeError::Finished);
+        }
+        let row_bytes = self.width as usize * 3;
+        if !rows.len().is_multiple_of(row_bytes) {
+            return Err(EncodeError::PartialRow {
+                row_bytes,
+                actual: rows.len(),
+            });
+        }
+        let count = rows.len() / row_bytes;
+        let total = self.rows_written as usize + count;
+        if total > self.height as usize {
+            return Err(EncodeError::RowCount {
+                expected: self.height,
+                actual: total,
+            });
+        }
+
+        for row in rows.chunks_exact(row_bytes) {
+            self.strip.extend_from_slice(row);
+            if self.strip.len() == row_bytes * STRIP_ROWS {
+                self.encode_strip();
+            }
+        }
+        self.rows_written = total as u32;
+        self.flush();
+        Ok(())
+    }
+
+    /// Encode the last partial strip and close the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::RowCount`] if fewer rows than the image height
+    /// were written (more rows can still be written after this error), or
+    /// [`EncodeError::Finished`] if the encoder was already finished.
+    pub fn finish(&mut self) -> Result<(), EncodeError> {
+        if self.finished {
+            return Err(EncodeError::Finished);
+        }
+        if self.rows_written != self.height {
+            return Err(EncodeError::RowCount {
+                expected: self.height,
+                actual: self.rows_written as usize,
+            });
+        }
+
+        if !self.strip.is_empty() {
+            self.encode_strip();
+        }
+        self.writer.pad_byte();
+        self.writer.write_marker(EOI);
+        self.flush();
+        self.finished = true;
+        Ok(())
+    }
+
+    fn write_header(&mut self, width: u16, height: u16) {
+        let w = &mut self.writer;
+        w.write_marker(SOI);
+
+        // JFIF 1.2 with a 1:1 pixel aspect ratio
+        w.write_segment(
+            APP0,
+            &[b'J', b'F', b'I', b'F', 0, 1, 2, 0, 0, 1, 0, 1, 0, 0],
+        );
+
+        // Baseline frame, 8-bit precision, 3 components with 1x1 sampling
+        let mut frame = vec![8];
+        frame.extend_from_slice(&height.to_be_bytes());
+        frame.extend_from_slice(&width.to_be_bytes());
+        frame.extend_from_slice(&[3, 1, 0x11, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        w.write_segment(SOF0, &frame);
+
+        for (id, table) in self.tables.iter().enumerate() {
+            let mut segment = vec![id as u8];
+            segment.extend(UNZIGZAG.iter().map(|&i| table[i as usize]));
+            w.write_segment(DQT, &segment);
+        }
+
+        w.write_standard_huffman_tables();
+
+        // One interleaved scan: luma uses tables 0, both chroma planes table 1
+        w.write_segment(SOS, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+    }
+
+    /// Compress the collected strip, repeating its last row and column to
+    /// fill partial blocks.
+    fn encode_strip(&mut self) {
+        let width = self.width as usize;
+        let rows = self.strip.len() / (width * 3);
+
+        let mut blocks = [[0u8; 64]; 3];
+        let mut coeffs = [0i32; 64];
+        for x0 in (0..width).step_by(8) {
+            for y in 0..8 {
+                let row = y.min(rows - 1) * width;
+                for x in 0..8 {
+                    let i = (row + (x0 + x).min(width - 1)) * 3;
+                    let (luma, cb, cr) = rgb_to_ycbcr(&self.strip[i..i + 3]);
+                    blocks[0][y * 8 + x] = luma;
+                    blocks[1][y * 8 + x] = cb;
+                    blocks[2][y * 8 + x] = cr;
+                }
+            }
+
+            for (component, block) in blocks.iter().enumerate() {
+                // Coefficients come out of the DCT scaled by 8
+                fdct(block, &mut coeffs);
+                let table = &self.tables[component.min(1)];
+                for (coeff, &q) in coeffs.iter_mut().zip(table) {
+                    *coeff = ((*coeff / 8) as f32 / f32::from(q)).round() as i32;
+                }
+
+                let (dc, ac) = if component == 0 {
+                    (&LUMA_DC_LUT, &LUMA_AC_LUT)
+                } else {
+                    (&CHROMA_DC_LUT, &CHROMA_AC_LUT)
+                };
+                self.dc_pred[component] =
+                    self.writer
+                        .write_block(&coeffs, self.dc_pred[component], dc, ac);
+            }
+        }
+        self.strip.clear();
+    }
+
+    fn flush(&mut self) {
+        if !self.writer.out.is_empty() {
+            (self.sink)(&self.writer.out);
+            self.writer.out.clear();
+        }
+    }
+}
+
+/// Scale the standard quantization tables for `quality` the way libjpeg
+/// does.
+fn scaled_tables(quality: u8) -> [[u8; 64]; 2] {
+    let quality = u32::from(quality.clamp(1, 100));
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+
+    let mut tables = [STD_LUMA_QTABLE, STD_CHROMA_QTABLE];
+    for v in tables.iter_mut().flatten() {
+        *v = ((u32::from(*v) * scale + 50) / 100).clamp(1, 255) as u8;
+    }
+    tables
+}
+
+/// Build a symbol -> (code length, code) lookup from a Huffman table
+/// specification (JPEG Annex C).
+const fn huffman_lut(lengths: &[u8; 16], values: &[u8]) -> [(u8, u16); 256] {
+    let mut lut = [(0u8, 0u16); 256];
+    let mut code = 0u16;
+    let mut k = 0;
+    let mut len = 0;
+    while len < 16 {
+        let mut i = 0;
+        while i < lengths[len] {
+            lut[values[k] as usize] = (len as u8 + 1, code);
+            code += 1;
+            k += 1;
+            i += 1;
+        }
+        code <<= 1;
+        len += 1;
+    }
+    lut
+}
+
+/// Entropy-coded output with 0xFF byte stuffing.
+#[derive(Default)]
+pub(super) struct BitWriter {
+    pub(super) out: Vec<u8>,
+    accumulator: u32,
+    nbits: u8,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, bits: u16, size: u8) {
+        if size == 0 {
+            return;
+        }
+
+        self.nbits += size;
+        self.accumulator |= u32::from(bits) << (32 - self.nbits);
+
+        while self.nbits >= 8 {
+            let byte = (self.accumulator >> 24) as u8;
+            self.out.push(byte);
+            if byte == 0xFF {
+                self.out.push(0x00);
+            }
+
+            self.nbits -= 8;
+            self.accumulator <<= 8;
+        }
+    }
+
+    /// Fill the last byte with 1 bits.
+    pub(super) fn pad_byte(&mut self) {
+        self.write_bits(0x7F, 7);
+    }
+
+    fn huffman_encode(&mut self, symbol: u8, lut: &[(u8, u16); 256]) {
+        let (size, code) = lut[symbol as usize];
+        self.write_bits(code, size);
+    }
+
+    /// Write one quantized block and return its DC value for the next
+    /// block's prediction.
+    pub(super) fn write_block(
+        &mut self,
+        block: &[i32; 64],
+        prev_dc: i32,
+        dc_lut: &[(u8, u16); 256],
+        ac_lut: &[(u8, u16); 256],
+    ) -> i32 {
+        let dc = block[0];
+        let (size, value) = encode_coefficient(dc - prev_dc);
+        self.huffman_encode(size, dc_lut);
+        self.write_bits(value, size);
+
+        let mut zero_run = 0u8;
+        for &k in &UNZIGZAG[1..] {
+            if block[k as usize] == 0 {
+                zero_run += 1;
+                continue;
+            }
+            while zero_run > 15 {
+                self.huffman_encode(0xF0, ac_lut);
+                zero_run -= 16;
+            }
+            let (size, value) = encode_coefficient(block[k as usize]);
+            self.huffman_encode((zero_run << 4) | size, ac_lut);
+            self.write_bits(value, size);
+            zero_run = 0;
+        }
+        if block[UNZIGZAG[63] as usize] == 0 {
+            self.huffman_encode(0x00, ac_lut);
+        }
+
+        dc
+    }
+
+    pub(super) fn write_marker(&mut self, marker: u8) {
+        self.out.extend_from_slice(&[0xFF, marker]);
+    }
+
+    pub(super) fn write_segment(&mut self, marker: u8, data: &[u8]) {
+        self.write_marker(marker);
+        self.out
+            .extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+        self.out.extend_from_slice(data);
+    }
+
+    /// Define the standard tables: luma as table 0, chroma as table 1.
+    pub(super) fn write_standard_huffman_tables(&mut self) {
+        let huffman: [(u8, &[u8; 16], &[u8]); 4] = [
+            (0x00, &STD_LUMA_DC_CODE_LENGTHS, &STD_LUMA_DC_VALUES),
+            (0x10, &STD_LUMA_AC_CODE_LENGTHS, &STD_LUMA_AC_VALUES),
+            (0x01, &STD_CHROMA_DC_CODE_LENGTHS, &STD_CHROMA_DC_VALUES),
+            (0x11, &STD_CHROMA_AC_CODE_LENGTHS, &STD_CHROMA_AC_VALUES),
+        ];
+        for (class_and_id, lengths, values) in huffman {
+            let mut segment = vec![class_and_id];
+            segment.extend_from_slice(lengths);
+            segment.extend_from_slice(values);
+            self.write_segment(DHT, &segment);
+        }
+    }
+}
+
+/// Split a coefficient into its magnitude category and the extra bits that
+/// follow the Huffman code.
+fn encode_coefficient(coefficient: i32) -> (u8, u16) {
+    let magnitude = coefficient.unsigned_abs() as u16;
+    let num_bits = (16 - magnitude.leading_zeros()) as u8;
+    let mask = ((1u32 << num_bits) - 1) as u16;
+
+    let value = if coefficient < 0 {
+        (coefficient - 1) as u16 & mask
+    } else {
+        coefficient as u16 & mask
+    };
+    (num_bits, value)
+}
+
+/// Full-range BT.601 RGB -> YCbCr in 16.16 fixed point.
+fn rgb_to_ycbcr(rgb: &[u8]) -> (u8, u8, u8) {
+    const C_YR: i32 = 19595;
+    const C_YG: i32 = 38469;
+    const C_YB: i32 = 7471;
+    const Y_ROUNDING: i32 = (1 << 15) - 1;
+    const C_UR: i32 = 11059;
+    const C_UG: i32 = 21709;
+    const C_UB: i32 = 32768;
+    const UV_BIAS_ROUNDING: i32 = (128 << 16) + ((1 << 15) - 1);
+    const C_VR: i32 = C_UB;
+    const C_VG: i32 = 27439;
+    const C_VB: i32 = 5329;
+
+    let r = i32::from(rgb[0]);
+    let g = i32::from(rgb[1]);
+    let b = i32::from(rgb[2]);
+
+    let y = (C_YR * r + C_YG * g + C_YB * b + Y_ROUNDING) >> 16;
+    let cb = (-C_UR * r - C_UG * g + C_UB * b + UV_BIAS_ROUNDING) >> 16;
+    let cr = (C_VR * r - C_VG * g - C_VB * b + UV_BIAS_ROUNDING) >> 16;
+    (y as u8, cb as u8, cr as u8)
+}
+
+const CONST_BITS: i32 = 13;
+const PASS1_BITS: i32 = 2;
+
+const FIX_0_298631336: i32 = 2446;
+const FIX_0_390180644: i32 = 3196;
+const FIX_0_541196100: i32 = 4433;
+const FIX_0_765366865: i32 = 6270;
+const FIX_0_899976223: i32 = 7373;
+const FIX_1_175875602: i32 = 9633;
+const FIX_1_501321110: i32 = 12_299;
+const FIX_1_847759065: i32 = 15_137;
+const FIX_1_961570560: i32 = 16_069;
+const FIX_2_053119869: i32 = 16_819;
+const FIX_2_562915447: i32 = 20_995;
+const FIX_3_072711026: i32 = 25_172;
+
+/// Integer forward DCT of a level-shifted 8x8 block, from the Independent
+/// JPEG Group's `jfdctint.c` (libjpeg 9a). The results are scaled by 8.
+///
+/// The odd part of the column pass rounds with the row pass's fudge factor,
+/// as the `image` crate's translation does; it is kept so the output
+/// matches that encoder exactly.
+fn fdct(samples: &[u8; 64], coeffs: &mut [i32; 64]) {
+    // Pass 1: rows, scaled up by sqrt(8) * 2^PASS1_BITS
+    for y in 0..8 {
+        let y0 = y * 8;
+        let s = |i: usize| i32::from(samples[y0 + i]);
+
+        // Even part
+        let t0 = s(0) + s(7);
+        let t1 = s(1) + s(6);
+        let t2 = s(2) + s(5);
+        let t3 = s(3) + s(4);
+
+        let t10 = t0 + t3;
+        let t12 = t0 - t3;
+        let t11 = t1 + t2;
+        let t13 = t1 - t2;
+
+        let t0 = s(0) - s(7);
+        let t1 = s(1) - s(6);
+        let t2 = s(2) - s(5);
+        let t3 = s(3) - s(4);
+
+        // Level shift the DC term from unsigned to signed
+        coeffs[y0] = (t10 + t11 - 8 * 128) << PASS1_BITS;
+        coeffs[y0 + 4] = (t10 - t11) << PASS1_BITS;
+
+        let z1 = (t12 + t13) * FIX_0_541196100 + (1 << (CONST_BITS - PASS1_BITS - 1));
+        coeffs[y0 + 2] = (z1 + t12 * FIX_0_765366865) >> (CONST_BITS - PASS1_BITS);
+        coeffs[y0 + 6] = (z1 - t13 * FIX_1_847759065) >> (CONST_BITS - PASS1_BITS);
+
+        // Odd part
+        let t12 = t0 + t2;
+        let t13 = t1 + t3;
+
+        let z1 = (t12 + t13) * FIX_1_175875602 + (1 << (CONST_BITS - PASS1_BITS - 1));
+        let t12 = t12 * -FIX_0_390180644 + z1;
+        let t13 = t13 * -FIX_1_961570560 + z1;
+
+        let z1 = (t0 + t3) * -FIX_0_899976223;
+        let t0 = t0 * FIX_1_501321110 + z1 + t12;
+        let t3 = t3 * FIX_0_298631336 + z1 + t13;
+
+        let z1 = (t1 + t2) * -FIX_2_562915447;
+        let t1 = t1 * FIX_3_072711026 + z1 + t13;
+        let t2 = t2 * FIX_2_053119869 + z1 + t12;
+
+        coeffs[y0 + 1] = t0 >> (CONST_BITS - PASS1_BITS);
+        coeffs[y0 + 3] = t1 >> (CONST_BITS - PASS1_BITS);
+        coeffs[y0 + 5] = t2 >> (CONST_BITS - PASS1_BITS);
+        coeffs[y0 + 7] = t3 >> (CONST_BITS - PASS1_BITS);
+    }
+
+    // Pass 2: columns, removing PASS1_BITS but leaving the factor of 8
+    for x in 0..8 {
+        let column: [i32; 8] = std::array::from_fn(|row| coeffs[x + 8 * row]);
+        let c = |row: usize| column[row];
+
+        // Even part
+        let t0 = c(0) + c(7);
+        let t1 = c(1) + c(6);
+        let t2 = c(2) + c(5);
+        let t3 = c(3) + c(4);
+
+        let t10 = t0 + t3 + (1 << (PASS1_BITS - 1));
+        let t12 = t0 - t3;
+        let t11 = t1 + t2;
+        let t13 = t1 - t2;
+
+        let t0 = c(0) - c(7);
+        let t1 = c(1) - c(6);
+        let t2 = c(2) - c(5);
+        let t3 = c(3) - c(4);
+
+        coeffs[x] = (t10 + t11) >> PASS1_BITS;
+        coeffs[x + 8 * 4] = (t10 - t11) >> PASS1_BITS;
+
+        let z1 = (t12 + t13) * FIX_0_541196100 + (1 << (CONST_BITS + PASS1_BITS - 1));
+        coeffs[x + 8 * 2] = (z1 + t12 * FIX_0_765366865) >> (CONST_BITS + PASS1_BITS);
+        coeffs[x + 8 * 6] = (z1 - t13 * FIX_1_847759065) >> (CONST_BITS + PASS1_BITS);
+
+        // Odd part
+        let t12 = t0 + t2;
+        let t13 = t1 + t3;
+
+        let z1 = (t12 + t13) * FIX_1_175875602 + (1 << (CONST_BITS - PASS1_BITS - 1));
+        let t12 = t12 * -FIX_0_390180644 + z1;
+        let t13 = t13 * -FIX_1_961570560 + z1;
+
+        let z1 = (t0 + t3) * -FIX_0_899976223;
+        let t0 = t0 * FIX_1_501321110 + z1 + t12;
+        let t3 = t3 * FIX_0_298631336 + z1 + t13;
+
+        let z1 = (t1 + t2) * -FIX_2_562915447;
+        let t1 = t1 * FIX_3_072711026 + z1 + t13;
+        let t2 = t2 * FIX_2_053119869 + z1 + t12;
+
+        coeffs[x + 8] = t0 >> (CONST_BITS + PASS1_BITS);
+        coeffs[x + 8 * 3] = t1 >> (CONST_BITS + PASS1_BITS);
+        coeffs[x + 8 * 5] = t2 >> (CONST_BITS + PASS1_BITS);
+        coeffs[x + 8 * 7] = t3 >> (CONST_BITS + PASS1_BITS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::encode_jpeg;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ExtendedColorType, ImageEncoder};
+
+    /// A noisy gradient whose size is not a multiple of 8 in either direction
+    fn test_pixels(width: u32, height: u32) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let noise = (x * 7919 + y * 104_729) % 37;
+                pixels.push((x * 255 / width) as u8);
+                pixels.push((y * 255 / height + noise) as u8);
+                pixels.push(((x + y) * 3 % 256) as u8);
+            }
+        }
+        pixels
+    }
+
+    fn encode_in_bands(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        quality: u8,
+        band: usize,
+    ) -> Vec<u8> {
+        let mut chunks = Vec::new();
+        let mut encoder = JpegStreamEncoder::new(width, height, quality, |chunk: &[u8]| {
+            chunks.push(chunk.to_vec())
+        })
+        .unwrap();
+        for rows in pixels.chunks(width as usize * 3 * band) {
+            encoder.write_rows(rows).unwrap();
+        }
+        encoder.finish().unwrap();
+        drop(encoder);
+        chunks.concat()
+    }
+
+    fn collecting_encoder(width: u32, height: u32) -> JpegStreamEncoder<impl FnMut(&[u8])> {
+        JpegStreamEncoder::new(width, height, 90, |_: &[u8]| {}).unwrap()
+    }
+
+    #[test]
+    fn test_matches_image_crate_encoder() {
+        let (width, height) = (37, 29);
+        let pixels = test_pixels(width, height);
+
+        for quality in [1, 30, 75, 90, 100] {
+            let mut expected = Vec::new();
+            JpegEncoder::new_with_quality(&mut expected, quality)
+                .write_image(&pixels, width, height, ExtendedColorType::Rgb8)
+                .unwrap();
+
+            let streamed = encode_in_bands(&pixels, width, height, quality, height as usize);
+            assert_eq!(streamed, expected, "quality {}", quality);
+        }
+    }
+
+    #[test]
+    fn test_streamed_chunks_match_one_shot_encode() {
+        let (width, height) = (64, 45);
+        let pixels = test_pixels(width, height);
+
+        let one_shot = encode_jpeg(&pixels, width, height, 90).unwrap();
+        assert_eq!(encode_in_bands(&pixels, width, height, 90, 16), one_shot);
+    }
+
+    #[test]
+    fn test_band_size_does_not_change_output() {
+        let (width, height) = (23, 41);
+        let pixels = test_pixels(width, height);
+
+        let reference = encode_in_bands(&pixels, width, height, 85, height as usize);
+        for band in [1, 3, 7, 8, 9, 16, 40] {
+            assert_eq!(
+                encode_in_bands(&pixels, width, height, 85, band),
+                reference,
+                "band of {} rows",
+                band
+            );
+        }
+    }
+
+    #[test]
+    fn test_chunks_arrive_before_finish() {
+        let pixels = test_pixels(16, 32);
+        let mut chunk_count = 0;
+        let mut encoder = JpegStreamEncoder::new(16, 32, 90, |_: &[u8]| chunk_count += 1).unwrap();
+        encoder.write_rows(&pixels[..16 * 8 * 3]).unwrap();
+        encoder.finish().unwrap_err();
+        encoder.write_rows(&pixels[16 * 8 * 3..]).unwrap();
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        // Header, first strip, remaining strips, trailer
+        assert_eq!(chunk_count, 4);
+    }
+
+    #[test]
+    fn test_finish_twice_errors() {
+        let mut encoder = collecting_encoder(8, 8);
+        encoder.write_rows(&[0u8; 8 * 8 * 3]).unwrap();
+        encoder.finish().unwrap();
+
+        assert!(matches!(encoder.finish(), Err(EncodeError::Finished)));
+        assert!(matches!(
+            encoder.write_rows(&[0u8; 8 * 3]),
+            Err(EncodeError::Finished)
+        ));
+    }
+
+    #[test]
+    fn test_too_few_rows_errors_on_finish() {
+        let mut encoder = collecting_encoder(8, 10);
+        encoder.write_rows(&[0u8; 8 * 9 * 3]).unwrap();
+
+        assert!(matches!(
+            encoder.finish(),
+            Err(EncodeError::RowCount {
+                expected: 10,
+                actual: 9
+            })
+        ));
+    }
+
+    #[test]
+    fn test_too_many_rows_errors() {
+        let mut encoder = collecting_encoder(8, 10);
+        encoder.write_rows(&[0u8; 8 * 6 * 3]).unwrap();
+
+        assert!(matches!(
+            encoder.write_rows(&[0u8; 8 * 5 * 3]),
+            Err(EncodeError::RowCount {
+                expected: 10,
+                actual: 11
+            })
+        ));
+    }
+
+    #[test]
+    fn test_partial_row_errors() {
+        let mut encoder = collecting_encoder(8, 10);
+
+        assert!(matches!(
+            encoder.write_rows(&[0u8; 8 * 3 + 1]),
+            Err(EncodeError::PartialRow {
+                row_bytes: 24,
+                actual: 25
+            })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_oversized_dimensions() {
+        let result = JpegStreamEncoder::new(70_000, 10, 90, |_: &[u8]| {});
+        assert!(matches!(result, Err(EncodeError::EncodingFailed(_))));
+
+        let result = JpegStreamEncoder::new(0, 10, 90, |_: &[u8]| {});
+        assert!(matches!(result, Err(EncodeError::InvalidDimensions { .. })));
+    }
+}
@@ -0,0 +1,262 @@
+//! Tiled JPEG export for very large images.
+//!
+//! A stitched panorama can be far larger than a browser can comfortably hold
+//! as one encoded JPEG, and JPEG itself stops at 65,535 pixels per side.
+//! [`export_tiled`] instead cuts the image into a grid of square tiles, each
+//! encoded as its own JPEG, with a [`TileDescriptor`] telling a viewer where
+//! each tile goes. This is the full-resolution level of a Deep Zoom pyramid.
+
+use serde::Serialize;
+
+use super::{encode_jpeg, EncodeError};
+use crate::decode::DecodedImage;
+use crate::mask::PixelRect;
+use crate::perf;
+use crate::validate::checked_buffer_len;
+
+/// An image exported by [`export_tiled`].
+#[derive(Debug, Clone)]
+pub struct TiledExport {
+    /// `(x, y, jpeg)` for every tile, row by row from the top left; `x` and
+    /// `y` are the tile's top-left corner in pixels
+    pub tiles: Vec<(u32, u32, Vec<u8>)>,
+    /// Side of a full tile; tiles on the right and bottom edges are smaller
+    pub tile_size: u32,
+    /// Width of the whole image
+    pub width: u32,
+    /// Height of the whole image
+    pub height: u32,
+}
+
+/// Layout of a [`TiledExport`], for a viewer to stitch the tiles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TileDescriptor {
+    /// Width of the whole image
+    pub width: u32,
+    /// Height of the whole image
+    pub height: u32,
+    /// Side of a full tile
+    pub tile_size: u32,
+    /// Number of tile columns
+    pub columns: u32,
+    /// Number of tile rows
+    pub rows: u32,
+    /// Position and true size of every tile, in the order of
+    /// [`TiledExport::tiles`]
+    pub tiles: Vec<PixelRect>,
+}
+
+impl TiledExport {
+    /// Layout of the tiles, without their bytes.
+    pub fn descriptor(&self) -> TileDescriptor {
+        TileDescriptor {
+            width: self.width,
+            height: self.height,
+            tile_size: self.tile_size,
+            columns: self.width.div_ceil(self.tile_size),
+            rows: self.height.div_ceil(self.tile_size),
+            tiles: tile_rects(self.width, self.height, self.tile_size),
+        }
+    }
+}
+
+/// Export an image as a grid of JPEG tiles.
+///
+/// Tiles are `tile_size` pixels square, except on the right and bottom edges
+/// where they are cut to the image and encoded at their true size, so the
+/// tiles cover the image exactly once. A `tile_size` at least as large as
+/// the image gives a single tile.
+///
+/// # Errors
+///
+/// Returns `EncodeError::InvalidTileSize` if `tile_size` is 0, and the
+/// same errors as [`encode_jpeg`] for an empty image or a pixel buffer that
+/// does not match its dimensions.
+///
+/// # Example
+///
+/// ```ignore
+/// use literoom_core::encode::export_tiled;
+///
+/// let export = export_tiled(&panorama, 1024, 90)?;
+/// for (x, y, jpeg) in &export.tiles {
+///     write_tile(*x, *y, jpeg);
+/// }
+/// ```
+pub fn export_tiled(
+    image: &DecodedImage,
+    tile_size: u32,
+    quality: u8,
+) -> Result<TiledExport, EncodeError> {
+    if tile_size == 0 {
+        return Err(EncodeError::InvalidTileSize);
+    }
+    let (width, height) = (image.width, image.height);
+    if width == 0 || height == 0 {
+        return Err(EncodeError::InvalidDimensions { width, height });
+    }
+    let expected = checked_buffer_len(width, height, 3)
+        .map_err(|e| EncodeError::EncodingFailed(e.to_string()))?;
+    if image.pixels.len() != expected {
+        return Err(EncodeError::InvalidPixelData {
+            expected,
+            actual: image.pixels.len(),
+        });
+    }
+
+    let _perf = perf::scope("export_tiled");
+    perf::record_dimensions(width, height);
+
+    let mut tiles = Vec::new();
+    let mut buffer = Vec::new();
+    for rect in tile_rects(width, height, tile_size) {
+        buffer.clear();
+        let row_bytes = rect.width as usize * 3;
+        for y in rect.y..rect.y + rect.height {
+            let start = (y as usize * width as usize + rect.x as usize) * 3;
+            buffer.extend_from_slice(&image.pixels[start..start + row_bytes]);
+        }
+        let jpeg = encode_jpeg(&buffer, rect.width, rect.height, quality)?;
+        tiles.push((rect.x, rect.y, jpeg));
+    }
+
+    Ok(TiledExport {
+        tiles,
+        tile_size,
+        width,
+        height,
+    })
+}
+
+/// Tiles of a `width x height` image, row by row from the top left.
+fn tile_rects(width: u32, height: u32, tile_size: u32) -> Vec<PixelRect> {
+    let mut rects = Vec::new();
+    for y in (0..height).step_by(tile_size as usize) {
+        for x in (0..width).step_by(tile_size as usize) {
+            rects.push(PixelRect {
+                x,
+                y,
+                width: tile_size.min(width - x),
+                height: tile_size.min(height - y),
+            });
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_jpeg;
+    use crate::testing::noise;
+
+    /// Decode every tile and paste it into one image.
+    fn reassemble(export: &TiledExport) -> DecodedImage {
+        let width = export.width as usize;
+        let mut pixels = vec![0u8; width * export.height as usize * 3];
+        for (x, y, jpeg) in &export.tiles {
+            let tile = decode_jpeg(jpeg).unwrap();
+            for (row, line) in tile.rows().enumerate() {
+                let start = ((*y as usize + row) * width + *x as usize) * 3;
+                pixels[start..start + line.len()].copy_from_slice(line);
+            }
+        }
+        DecodedImage::new(export.width, export.height, pixels)
+    }
+
+    #[test]
+    fn test_tiles_reassemble_to_the_source() {
+        // Flat 8x8 blocks survive JPEG exactly, so any misplaced tile shows
+        let mut source = DecodedImage::new(40, 24, vec![0u8; 40 * 24 * 3]);
+        for y in 0..24usize {
+            for x in 0..40usize {
+                let value = if (x / 8 + y / 8) % 2 == 0 { 32 } else { 224 };
+                let i = (y * 40 + x) * 3;
+                source.pixels[i..i + 3].copy_from_slice(&[value; 3]);
+            }
+        }
+
+        let export = export_tiled(&source, 16, 100).unwrap();
+        assert_eq!(reassemble(&export).pixels, source.pixels);
+    }
+
+    #[test]
+    fn test_tiles_match_the_source_closely() {
+        let source = noise(50, 37, 4);
+        let export = export_tiled(&source, 16, 100).unwrap();
+        let restored = reassemble(&export);
+        let worst = source
+            .pixels
+            .iter()
+            .zip(&restored.pixels)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        assert!(worst <= 24, "worst difference {worst}");
+    }
+
+    #[test]
+    fn test_edge_tiles_have_true_dimensions() {
+        let export = export_tiled(&noise(50, 37, 4), 16, 90).unwrap();
+        let descriptor = export.descriptor();
+        assert_eq!((descriptor.columns, descriptor.rows), (4, 3));
+        assert_eq!(export.tiles.len(), 12);
+
+        for ((x, y, jpeg), rect) in export.tiles.iter().zip(&descriptor.tiles) {
+            assert_eq!((*x, *y), (rect.x, rect.y));
+            let tile = decode_jpeg(jpeg).unwrap();
+            assert_eq!((tile.width, tile.height), (rect.width, rect.height));
+        }
+        assert_eq!(descriptor.tiles[3].width, 2);
+        assert_eq!(descriptor.tiles[11].height, 5);
+    }
+
+    #[test]
+    fn test_tile_grid_covers_every_pixel_once() {
+        for (width, height, tile_size) in [(50, 37, 16), (64, 64, 16), (1, 9, 4), (17, 3, 5)] {
+            let mut covered = vec![0u8; (width * height) as usize];
+            for rect in tile_rects(width, height, tile_size) {
+                assert!(rect.width > 0 && rect.height > 0);
+                for y in rect.y..rect.y + rect.height {
+                    for x in rect.x..rect.x + rect.width {
+                        covered[(y * width + x) as usize] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|&count| count == 1));
+        }
+    }
+
+    #[test]
+    fn test_large_tile_size_gives_one_tile() {
+        let source = noise(20, 12, 1);
+        let export = export_tiled(&source, 4096, 90).unwrap();
+        assert_eq!(export.tiles.len(), 1);
+        assert_eq!((export.tiles[0].0, export.tiles[0].1), (0, 0));
+        let tile = decode_jpeg(&export.tiles[0].2).unwrap();
+        assert_eq!((tile.width, tile.height), (20, 12));
+    }
+
+    #[test]
+    fn test_invalid_input_is_rejected() {
+        let source = noise(20, 12, 1);
+        assert!(matches!(
+            export_tiled(&source, 0, 90),
+            Err(EncodeError::InvalidTileSize)
+        ));
+        let short = DecodedImage {
+            width: 20,
+            height: 12,
+            pixels: vec![0u8; 10],
+        };
+        assert!(matches!(
+            export_tiled(&short, 8, 90),
+            Err(EncodeError::InvalidPixelData { .. })
+        ));
+        let empty = DecodedImage::new(0, 12, Vec::new());
+        assert!(matches!(
+            export_tiled(&empty, 8, 90),
+            Err(EncodeError::InvalidDimensions { .. })
+        ));
+    }
+}
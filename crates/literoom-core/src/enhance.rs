@@ -0,0 +1,267 @@
+//! One-click auto enhance.
+//!
+//! [`auto_enhance`] chains the individual analyzers into a starting edit:
+//!
+//! 1. Straighten: [`detect_straighten_angle`] sets the straighten angle, and
+//!    [`compute_rotation_auto_crop`] crops away the empty corners
+//! 2. White balance: [`estimate_white_balance`] on the straightened frame
+//!    sets temperature and tint
+//! 3. Tone: the luminance black and white points of the balanced frame
+//!    become a two-point tone curve that stretches contrast
+//!
+//! Each step measures the image as the previous steps leave it, so the
+//! white balance ignores the cropped-away corners and the levels see the
+//! balanced colors. Weak or uncertain findings are skipped rather than
+//! applied: a photo that is already level, neutral and full-range comes
+//! back with a default edit state. Analysis runs on a copy downsized to
+//! [`ANALYSIS_MAX_EDGE`], so the cost barely depends on the source size.
+
+use crate::adjustments::{auto_levels, estimate_white_balance, AutoLevelsMode, WhiteBalanceMethod};
+use crate::decode::DecodedImage;
+use crate::edit::{CropRect, EditState};
+use crate::perf;
+use crate::render::render_edit;
+use crate::transform::{
+    compute_rotation_auto_crop, detect_straighten_angle, InterpolationFilter, TransformError,
+};
+use crate::{CurvePoint, ToneCurve};
+
+/// Longest edge of the copy the analyzers run on.
+pub const ANALYSIS_MAX_EDGE: u32 = 512;
+
+/// Straighten estimates below this confidence are ignored.
+pub const MIN_STRAIGHTEN_CONFIDENCE: f32 = 0.25;
+
+/// Tilts smaller than this, in degrees, are left alone.
+const MIN_STRAIGHTEN_DEGREES: f32 = 0.2;
+
+/// Temperature and tint corrections smaller than this are left alone.
+const MIN_WHITE_BALANCE: f32 = 2.0;
+
+/// Percentiles of luminance taken as the black and white points.
+const LEVELS_LOW_PERCENTILE: f32 = 0.5;
+const LEVELS_HIGH_PERCENTILE: f32 = 99.5;
+
+/// Levels are only stretched if the black and white points together leave
+/// at least this many of the 256 values unused.
+const MIN_LEVELS_SLACK: u8 = 12;
+
+/// Suggest an edit that straightens, crops, white balances and tones `image`.
+///
+/// See the [module docs](self) for the steps and when each one is skipped.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `render` - Also render the full-size image with the suggested edit
+///
+/// # Returns
+///
+/// The suggested edit state, and the image rendered with it (bilinear
+/// rotation) when `render` is set.
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+pub fn auto_enhance(
+    image: &DecodedImage,
+    render: bool,
+) -> Result<(EditState, Option<DecodedImage>), TransformError> {
+    image.validate()?;
+    let _perf = perf::scope("auto_enhance");
+    perf::record_dimensions(image.width, image.height);
+    perf::record_pixels(image.width as u64 * image.height as u64);
+
+    let analysis = analysis_copy(image);
+    let mut state = EditState::default();
+
+    // 1. Straighten, cropping to the source aspect ratio
+    if let Some(estimate) = detect_straighten_angle(&analysis)? {
+        if estimate.confidence >= MIN_STRAIGHTEN_CONFIDENCE
+            && estimate.angle.abs() >= MIN_STRAIGHTEN_DEGREES
+        {
+            state.rotation.straighten = estimate.angle;
+            let (left, top, width, height) =
+                compute_rotation_auto_crop(image.width, image.height, estimate.angle as f64);
+            state.crop = Some(CropRect {
+                left: left as f32,
+                top: top as f32,
+                width: width as f32,
+                height: height as f32,
+            });
+        }
+    }
+
+    // 2. White balance on the straightened frame
+    let framed = render_edit(&analysis, &state, InterpolationFilter::Bilinear)?;
+    let (temperature, tint) = estimate_white_balance(
+        &framed.pixels,
+        framed.width,
+        framed.height,
+        WhiteBalanceMethod::GrayWorld,
+    )?;
+    if temperature.abs() >= MIN_WHITE_BALANCE {
+        state.adjustments.temperature = temperature;
+    }
+    if tint.abs() >= MIN_WHITE_BALANCE {
+        state.adjustments.tint = tint;
+    }
+
+    // 3. Levels of the balanced frame
+    let mut balanced = render_edit(&analysis, &state, InterpolationFilter::Bilinear)?;
+    let levels = auto_levels(
+        &mut balanced.pixels,
+        balanced.width,
+        balanced.height,
+        AutoLevelsMode::Luminance,
+        LEVELS_LOW_PERCENTILE,
+        LEVELS_HIGH_PERCENTILE,
+    )?;
+    let (black, white) = (levels.black_point[0], levels.white_point[0]);
+    if white > black && black.saturating_add(255 - white) >= MIN_LEVELS_SLACK {
+        state.tone_curve = ToneCurve {
+            points: vec![
+                CurvePoint::new(black as f32 / 255.0, 0.0),
+                CurvePoint::new(white as f32 / 255.0, 1.0),
+            ],
+        };
+    }
+
+    let rendered = if render {
+        Some(render_edit(image, &state, InterpolationFilter::Bilinear)?)
+    } else {
+        None
+    };
+    Ok((state, rendered))
+}
+
+/// Box-downsample `image` by a whole factor so it fits [`ANALYSIS_MAX_EDGE`].
+///
+/// Averaging whole blocks keeps edges straight and colors unbiased, which
+/// is all the analyzers need.
+fn analysis_copy(image: &DecodedImage) -> DecodedImage {
+    let factor = image.width.max(image.height).div_ceil(ANALYSIS_MAX_EDGE);
+    if factor <= 1 {
+        return image.clone();
+    }
+
+    let (width, height) = (image.width / factor, image.height / factor);
+    let (width, height) = (width.max(1), height.max(1));
+    let src_width = image.width as usize;
+    let block = factor as usize;
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let mut sum = [0u32; 3];
+            let mut count = 0u32;
+            for sy in (y * block..(y + 1) * block).take_while(|&sy| sy < image.height as usize) {
+                for sx in (x * block..(x + 1) * block).take_while(|&sx| sx < src_width) {
+                    let idx = (sy * src_width + sx) * 3;
+                    for (c, total) in sum.iter_mut().enumerate() {
+                        *total += image.pixels[idx + c] as u32;
+                    }
+                    count += 1;
+                }
+            }
+            pixels.extend(sum.map(|total| ((total + count / 2) / count) as u8));
+        }
+    }
+    DecodedImage::new(width, height, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{checkerboard, noise};
+    use crate::transform::{apply_crop, apply_rotation};
+
+    /// A dull checkerboard tilted by `tilt` degrees, with a warm cast.
+    fn tilted_warm_flat(tilt: f64) -> DecodedImage {
+        let mut board = checkerboard(420, 320, 40);
+        for value in &mut board.pixels {
+            *value = if *value == 0 { 70 } else { 180 };
+        }
+        let rotated = apply_rotation(&board, tilt, InterpolationFilter::Bilinear).unwrap();
+        let mut image = apply_crop(&rotated, 0.12, 0.12, 0.76, 0.76).unwrap();
+        for pixel in image.pixels.chunks_exact_mut(3) {
+            pixel[0] = pixel[0].saturating_add(25);
+            pixel[2] = pixel[2].saturating_sub(25);
+        }
+        image
+    }
+
+    #[test]
+    fn test_corrects_tilt_cast_and_contrast() {
+        let image = tilted_warm_flat(3.0);
+        let (state, rendered) = auto_enhance(&image, false).unwrap();
+
+        assert!(rendered.is_none());
+        assert!(
+            (state.rotation.straighten + 3.0).abs() < 0.3,
+            "straighten {}",
+            state.rotation.straighten
+        );
+        let crop = state.crop.expect("straightening crops the corners");
+        assert!(crop.width < 1.0 && crop.height < 1.0);
+
+        // The cast is warm, so the correction cools: in this crate positive
+        // temperature is cooler
+        assert!(
+            state.adjustments.temperature > MIN_WHITE_BALANCE,
+            "temperature {}",
+            state.adjustments.temperature
+        );
+
+        let points = &state.tone_curve.points;
+        assert_eq!(points.len(), 2);
+        assert!(points[0].x > 0.1 && points[1].x < 0.9, "{:?}", points);
+        assert_eq!((points[0].y, points[1].y), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_good_image_is_left_near_identity() {
+        // Level, neutral and full-range, with some neutral grain
+        let mut image = checkerboard(300, 200, 25);
+        let grain = noise(300, 200, 9);
+        for (pixel, grain) in image
+            .pixels
+            .chunks_exact_mut(3)
+            .zip(grain.pixels.chunks_exact(3))
+        {
+            let offset = grain[0] / 16;
+            for value in pixel {
+                *value = if *value == 0 { offset } else { 255 - offset };
+            }
+        }
+        let (state, _) = auto_enhance(&image, false).unwrap();
+
+        assert_eq!(state.rotation.straighten, 0.0);
+        assert_eq!(state.crop, None);
+        assert!(state.adjustments.temperature.abs() < MIN_WHITE_BALANCE);
+        assert!(state.adjustments.tint.abs() < MIN_WHITE_BALANCE);
+        assert!(state.tone_curve.is_linear());
+    }
+
+    #[test]
+    fn test_render_matches_state() {
+        let image = tilted_warm_flat(-2.0);
+        let (state, rendered) = auto_enhance(&image, true).unwrap();
+
+        let expected = render_edit(&image, &state, InterpolationFilter::Bilinear).unwrap();
+        let rendered = rendered.unwrap();
+        assert_eq!(
+            (rendered.width, rendered.height),
+            (expected.width, expected.height)
+        );
+        assert_eq!(rendered.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_analysis_copy_fits_max_edge() {
+        let image = noise(1100, 700, 1);
+        let copy = analysis_copy(&image);
+        assert!(copy.width <= ANALYSIS_MAX_EDGE && copy.height <= ANALYSIS_MAX_EDGE);
+        assert_eq!((copy.width, copy.height), (366, 233));
+    }
+}
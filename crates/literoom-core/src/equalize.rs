@@ -0,0 +1,398 @@
+//! Histogram equalization and CLAHE.
+//!
+//! Equalization spreads the tones of a flat image (fog, haze, scientific
+//! captures) over the whole range by mapping each luma value through the
+//! image's cumulative histogram. Contrast-limited adaptive histogram
+//! equalization (CLAHE) does the same per tile, so each region gets its own
+//! stretch, and clips every tile's histogram first so that flat areas are
+//! not blown up into noise.
+//!
+//! Both work on BT.709 luma and move R, G and B by the same amount, which
+//! keeps the color differences of each pixel.
+
+use crate::luminance::{LUMINANCE_B, LUMINANCE_G, LUMINANCE_R};
+use crate::perf;
+use crate::validate::{validate_rgb_buffer, ImageError};
+
+/// Equalize the luma histogram of the whole image.
+///
+/// `strength` blends between the original (0.0) and the fully equalized
+/// image (1.0); it is clamped to 0.0-1.0 and NaN counts as 0.0.
+pub fn equalize_histogram(pixels: &mut [u8], strength: f32) {
+    let strength = clamp_strength(strength);
+    if strength == 0.0 {
+        return;
+    }
+
+    let _perf = perf::scope("equalize_histogram");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    let mut hist = [0.0f32; 256];
+    for pixel in pixels.chunks_exact(3) {
+        hist[luma_bin(pixel)] += 1.0;
+    }
+    let shift = shifts(&equalization_lut(&hist), strength);
+    for pixel in pixels.chunks_exact_mut(3) {
+        apply_shift(pixel, shift[luma_bin(pixel)]);
+    }
+}
+
+/// Apply contrast-limited adaptive histogram equalization.
+///
+/// The image is split into a `tiles_x` x `tiles_y` grid and each tile's
+/// luma is equalized on its own. Each pixel's mapping is interpolated
+/// bilinearly between the four nearest tile centers, so there are no seams
+/// at tile boundaries.
+///
+/// # Arguments
+///
+/// * `pixels` - RGB pixel data, modified in place
+/// * `width`, `height` - Image dimensions
+/// * `tiles_x`, `tiles_y` - Grid size; clamped to at least 1 and at most
+///   the image size. 8 x 8 is a common choice
+/// * `clip_limit` - Highest histogram bin, as a multiple of the mean bin
+///   height; the excess is spread over all bins. 0.0 flattens every
+///   histogram, so nothing changes, and larger values approach plain
+///   equalization. 2.0 to 4.0 is typical; NaN and negative values count as
+///   0.0
+/// * `strength` - Blend between the original (0.0) and the full effect
+///   (1.0); clamped, NaN counts as 0.0
+///
+/// # Errors
+///
+/// Returns `ImageError` if the image is empty or the buffer does not match
+/// the dimensions.
+///
+/// # Example
+///
+/// ```ignore
+/// use literoom_core::equalize::apply_clahe;
+///
+/// // Bring out detail in a foggy landscape
+/// apply_clahe(&mut image.pixels, image.width, image.height, 8, 8, 3.0, 0.7)?;
+/// ```
+pub fn apply_clahe(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    tiles_x: u32,
+    tiles_y: u32,
+    clip_limit: f32,
+    strength: f32,
+) -> Result<(), ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    let strength = clamp_strength(strength);
+    if strength == 0.0 {
+        return Ok(());
+    }
+
+    let _perf = perf::scope("apply_clahe");
+    perf::record_pixels(width as u64 * height as u64);
+
+    let tiles_x = tiles_x.clamp(1, width);
+    let tiles_y = tiles_y.clamp(1, height);
+    let tile_w = width as f32 / tiles_x as f32;
+    let tile_h = height as f32 / tiles_y as f32;
+    let tile_of = |p: u32, size: u32, tiles: u32| (p as u64 * tiles as u64 / size as u64) as usize;
+
+    let mut hists = vec![[0.0f32; 256]; (tiles_x * tiles_y) as usize];
+    for (y, row) in pixels.chunks_exact(width as usize * 3).enumerate() {
+        let ty = tile_of(y as u32, height, tiles_y);
+        for (x, pixel) in row.chunks_exact(3).enumerate() {
+            let tx = tile_of(x as u32, width, tiles_x);
+            hists[ty * tiles_x as usize + tx][luma_bin(pixel)] += 1.0;
+        }
+    }
+    let clip_limit = if clip_limit.is_nan() {
+        0.0
+    } else {
+        clip_limit.max(0.0)
+    };
+    let tile_shifts: Vec<[f32; 256]> = hists
+        .iter_mut()
+        .map(|hist| {
+            clip_histogram(hist, clip_limit);
+            shifts(&equalization_lut(hist), strength)
+        })
+        .collect();
+
+    // Tile centers lie at (i + 0.5) * tile size; outside the outer centers
+    // the nearest tile's mapping is used alone
+    let neighbours = |p: u32, size: f32, tiles: u32| {
+        let t = ((p as f32 + 0.5) / size - 0.5).clamp(0.0, (tiles - 1) as f32);
+        let first = t.floor() as usize;
+        (first, (first + 1).min(tiles as usize - 1), t - first as f32)
+    };
+    for (y, row) in pixels.chunks_exact_mut(width as usize * 3).enumerate() {
+        let (ty0, ty1, wy) = neighbours(y as u32, tile_h, tiles_y);
+        let (row0, row1) = (ty0 * tiles_x as usize, ty1 * tiles_x as usize);
+        for (x, pixel) in row.chunks_exact_mut(3).enumerate() {
+            let (tx0, tx1, wx) = neighbours(x as u32, tile_w, tiles_x);
+            let bin = luma_bin(pixel);
+            let top = lerp(
+                tile_shifts[row0 + tx0][bin],
+                tile_shifts[row0 + tx1][bin],
+                wx,
+            );
+            let bottom = lerp(
+                tile_shifts[row1 + tx0][bin],
+                tile_shifts[row1 + tx1][bin],
+                wx,
+            );
+            apply_shift(pixel, lerp(top, bottom, wy));
+        }
+    }
+    Ok(())
+}
+
+fn clamp_strength(strength: f32) -> f32 {
+    if strength.is_nan() {
+        0.0
+    } else {
+        strength.clamp(0.0, 1.0)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Luma of a pixel, rounded to a histogram bin.
+fn luma_bin(pixel: &[u8]) -> usize {
+    let luma = LUMINANCE_R * pixel[0] as f32
+        + LUMINANCE_G * pixel[1] as f32
+        + LUMINANCE_B * pixel[2] as f32;
+    luma.round().clamp(0.0, 255.0) as usize
+}
+
+/// Cap every bin at `clip_limit` times the mean and spread the excess
+/// evenly over all bins.
+fn clip_histogram(hist: &mut [f32; 256], clip_limit: f32) {
+    let total: f32 = hist.iter().sum();
+    let limit = clip_limit * total / 256.0;
+    let mut excess = 0.0;
+    for bin in hist.iter_mut() {
+        if *bin > limit {
+            excess += *bin - limit;
+            *bin = limit;
+        }
+    }
+    let share = excess / 256.0;
+    for bin in hist.iter_mut() {
+        *bin += share;
+    }
+}
+
+/// Map each value to its position in the cumulative histogram, 0.0-255.0.
+///
+/// Values are placed at the middle of their bin's share, rescaled so that
+/// the middles of the first and last bins land on 0 and 255. A flat
+/// histogram thus maps every value onto itself. An empty histogram gives
+/// the identity too.
+fn equalization_lut(hist: &[f32; 256]) -> [f32; 256] {
+    let total: f32 = hist.iter().sum();
+    let low = hist[0] / 2.0;
+    let span = total - low - hist[255] / 2.0;
+    if span <= 0.0 {
+        return std::array::from_fn(|v| v as f32);
+    }
+    let mut below = 0.0;
+    std::array::from_fn(|v| {
+        let middle = below + hist[v] / 2.0;
+        below += hist[v];
+        ((middle - low) / span * 255.0).clamp(0.0, 255.0)
+    })
+}
+
+/// Change of luma for each bin, scaled by `strength`.
+fn shifts(lut: &[f32; 256], strength: f32) -> [f32; 256] {
+    std::array::from_fn(|v| (lut[v] - v as f32) * strength)
+}
+
+/// Move all three channels by `shift`, which moves the luma by as much.
+fn apply_shift(pixel: &mut [u8], shift: f32) {
+    for value in pixel.iter_mut() {
+        *value = (*value as f32 + shift).round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::DecodedImage;
+    use crate::testing::noise;
+
+    const SIZE: u32 = 128;
+
+    /// Low-contrast diagonal ramps repeating every 20 pixels, brighter
+    /// towards the right. The repeats fall between the 32-pixel tiles of a
+    /// 4 x 4 grid.
+    fn tiled_gradient() -> DecodedImage {
+        let pixels = (0..SIZE * SIZE)
+            .flat_map(|i| {
+                let (x, y) = (i % SIZE, i / SIZE);
+                let v = (100 + (x % 20 + y % 20) / 2 + (x + y) / 10) as u8;
+                [v, v, v.saturating_sub(10)]
+            })
+            .collect();
+        DecodedImage::new(SIZE, SIZE, pixels)
+    }
+
+    fn luma(image: &DecodedImage, x: u32, y: u32) -> f32 {
+        luma_bin(&image.pixels[((y * image.width + x) * 3) as usize..]) as f32
+    }
+
+    /// Mean luma change from column `x - 1` to `x`.
+    fn column_step(image: &DecodedImage, x: u32) -> f32 {
+        (0..image.height)
+            .map(|y| (luma(image, x, y) - luma(image, x - 1, y)).abs())
+            .sum::<f32>()
+            / image.height as f32
+    }
+
+    /// Mean luma change from row `y - 1` to `y`.
+    fn row_step(image: &DecodedImage, y: u32) -> f32 {
+        (0..image.width)
+            .map(|x| (luma(image, x, y) - luma(image, x, y - 1)).abs())
+            .sum::<f32>()
+            / image.width as f32
+    }
+
+    fn clahe(image: &DecodedImage, tiles: u32, clip_limit: f32, strength: f32) -> DecodedImage {
+        let mut out = image.clone();
+        apply_clahe(
+            &mut out.pixels,
+            out.width,
+            out.height,
+            tiles,
+            tiles,
+            clip_limit,
+            strength,
+        )
+        .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_clahe_raises_local_contrast_without_seams() {
+        let image = tiled_gradient();
+        let out = clahe(&image, 4, 3.0, 1.0);
+
+        // Inside a 20-pixel ramp, away from tile boundaries
+        let before = column_step(&image, 45) + row_step(&image, 45);
+        let after = column_step(&out, 45) + row_step(&out, 45);
+        assert!(after > before * 1.5, "{} -> {}", before, after);
+
+        for boundary in [32, 64, 96] {
+            for step in [column_step, row_step] {
+                let across = step(&out, boundary);
+                let within = (step(&out, boundary - 2) + step(&out, boundary + 2)) / 2.0;
+                assert!(
+                    across <= within * 1.5 + 0.5,
+                    "seam at {}: {} vs {}",
+                    boundary,
+                    across,
+                    within
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_strength_is_noop() {
+        let image = tiled_gradient();
+        assert_eq!(clahe(&image, 4, 3.0, 0.0).pixels, image.pixels);
+        assert_eq!(clahe(&image, 4, 3.0, f32::NAN).pixels, image.pixels);
+
+        let mut pixels = image.pixels.clone();
+        equalize_histogram(&mut pixels, 0.0);
+        assert_eq!(pixels, image.pixels);
+    }
+
+    #[test]
+    fn test_zero_clip_limit_is_noop() {
+        for image in [tiled_gradient(), noise(50, 30, 4)] {
+            for tiles in [1, 3, 8] {
+                assert_eq!(clahe(&image, tiles, 0.0, 1.0).pixels, image.pixels);
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_clip_limit_approaches_equalization() {
+        let image = tiled_gradient();
+        let mut equalized = image.clone();
+        equalize_histogram(&mut equalized.pixels, 1.0);
+
+        // A single tile with no clipping is plain equalization
+        assert_eq!(
+            clahe(&image, 1, f32::INFINITY, 1.0).pixels,
+            equalized.pixels
+        );
+
+        let distance = |clip_limit: f32| {
+            let out = clahe(&image, 1, clip_limit, 1.0);
+            out.pixels
+                .iter()
+                .zip(&equalized.pixels)
+                .map(|(&a, &b)| a.abs_diff(b) as u64)
+                .sum::<u64>()
+        };
+        let distances: Vec<_> = [1.5, 3.0, 10.0, 1000.0].map(distance).to_vec();
+        assert!(
+            distances.windows(2).all(|w| w[0] >= w[1]),
+            "{:?}",
+            distances
+        );
+        assert_eq!(distances[3], 0);
+    }
+
+    #[test]
+    fn test_equalize_spreads_tones() {
+        let image = tiled_gradient();
+        let mut pixels = image.pixels.clone();
+        equalize_histogram(&mut pixels, 1.0);
+        let out = DecodedImage::new(SIZE, SIZE, pixels);
+
+        let range = |image: &DecodedImage| {
+            let lumas: Vec<_> = image.pixels.chunks(3).map(luma_bin).collect();
+            lumas.iter().max().unwrap() - lumas.iter().min().unwrap()
+        };
+        assert!(range(&image) < 60);
+        assert!(range(&out) > 240, "{}", range(&out));
+
+        // Half strength lies between
+        let mut half = image.pixels.clone();
+        equalize_histogram(&mut half, 0.5);
+        for ((&a, &h), &b) in image.pixels.iter().zip(&half).zip(&out.pixels) {
+            assert!(
+                a.min(b).saturating_sub(1) <= h && h <= a.max(b).saturating_add(1),
+                "{} {} {}",
+                a,
+                h,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_equalization_lut_identity_cases() {
+        let flat = [7.0f32; 256];
+        for (v, &mapped) in equalization_lut(&flat).iter().enumerate() {
+            assert!((mapped - v as f32).abs() < 1e-3, "{} -> {}", v, mapped);
+        }
+        let empty = [0.0f32; 256];
+        assert_eq!(equalization_lut(&empty)[100], 100.0);
+    }
+
+    #[test]
+    fn test_clahe_handles_small_images_and_rejects_invalid() {
+        // More tiles than pixels
+        let image = noise(3, 2, 9);
+        clahe(&image, 16, 2.0, 1.0);
+
+        let mut short = vec![0u8; 10];
+        assert!(apply_clahe(&mut short, 4, 4, 2, 2, 2.0, 1.0).is_err());
+        assert!(apply_clahe(&mut [], 0, 0, 2, 2, 2.0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,738 @@
+//! Export of an edited image in an [`ExportFormat`], with taps on the final
+//! pixels.
+//!
+//! The app records the exported image's histogram in the export manifest
+//! and shows a small preview of each exported file. Both are computed by
+//! [`process_and_encode`] from the rendered pixel buffer that is about to be
+//! encoded, so nothing has to decode the file again. The histogram is that
+//! of the sRGB pixels before conversion to the export's color space and
+//! before JPEG compression; a decode of the file differs by the compression
+//! loss.
+//!
+//! ```ignore
+//! use literoom_core::encode::ExportFormat;
+//! use literoom_core::export::{process_and_encode, ExportTaps};
+//!
+//! let taps = ExportTaps { want_histogram: true, thumbnail_size: Some(256) };
+//! let format = ExportFormat::jpeg(90);
+//! let export = process_and_encode(&image, &prepared, InterpolationFilter::Lanczos3, &format, &taps)?;
+//! manifest.add(name, export.histogram.unwrap());
+//! ```
+//!
+//! Before an export starts, [`preflight_export`] reports the output size
+//! and an estimate of the file size from the same size math, without
+//! rendering the full image.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::compose::{composed_dimensions, ComposeError};
+use crate::decode::{generate_thumbnail, resize_to_fit_dimensions, DecodeError, DecodedImage};
+use crate::encode::{encode_image, EncodeError, ExportContainer, ExportFormat};
+use crate::histogram::compute_histogram;
+use crate::render::PreparedEdit;
+use crate::transform::{InterpolationFilter, TransformError};
+use crate::validate::validate_dimensions;
+use crate::Histogram;
+
+/// Errors that can occur during an export.
+#[derive(Debug, Error)]
+pub enum ExportError {
+    /// The edit could not be rendered.
+    #[error("Render failed: {0}")]
+    Render(#[from] TransformError),
+
+    /// The rendered image could not be encoded.
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+
+    /// The thumbnail tap could not be generated.
+    #[error("Thumbnail failed: {0}")]
+    Thumbnail(DecodeError),
+
+    /// The export resize is invalid, such as a `max_edge` of zero.
+    #[error(transparent)]
+    Resize(DecodeError),
+
+    /// The padding or border is invalid or too large.
+    #[error(transparent)]
+    Compose(#[from] ComposeError),
+}
+
+/// What to compute from the final pixels of an export.
+///
+/// The default computes nothing, and the export is the same as encoding the
+/// rendered image directly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportTaps {
+    /// Compute the histogram of the exported pixels
+    pub want_histogram: bool,
+    /// Also produce a thumbnail of the exported pixels with this long edge,
+    /// as [`generate_thumbnail`] would
+    pub thumbnail_size: Option<u32>,
+}
+
+/// An encoded export and the results of its taps.
+#[derive(Debug, Clone)]
+pub struct TappedExport {
+    /// The encoded file
+    pub bytes: Vec<u8>,
+    /// Histogram of the pixels that were encoded, if requested
+    pub histogram: Option<Histogram>,
+    /// Thumbnail of the pixels that were encoded, if requested
+    pub thumbnail: Option<DecodedImage>,
+}
+
+/// Render `image` with a prepared edit and encode it in `format`, computing
+/// the requested taps from the rendered pixels on the way.
+///
+/// # Errors
+///
+/// Returns `ExportError::Render` if the image is empty or its buffer does
+/// not match its dimensions, `ExportError::Thumbnail` for a thumbnail size
+/// of zero, and `ExportError::Encode` if the format is invalid or encoding
+/// fails. An invalid format is reported before anything is rendered.
+pub fn process_and_encode(
+    image: &DecodedImage,
+    prepared: &PreparedEdit,
+    filter: InterpolationFilter,
+    format: &ExportFormat,
+    taps: &ExportTaps,
+) -> Result<TappedExport, ExportError> {
+    format.validate().map_err(EncodeError::from)?;
+    let rendered = prepared.render(image, filter)?;
+    encode_with_taps(&rendered, format, taps)
+}
+
+/// Encode an already rendered image in `format`, computing the requested
+/// taps from its pixels.
+///
+/// # Errors
+///
+/// Returns `ExportError::Render` if the image is empty or its buffer does
+/// not match its dimensions, and otherwise the errors of
+/// [`process_and_encode`].
+pub fn encode_with_taps(
+    image: &DecodedImage,
+    format: &ExportFormat,
+    taps: &ExportTaps,
+) -> Result<TappedExport, ExportError> {
+    format.validate().map_err(EncodeError::from)?;
+    image.validate().map_err(TransformError::from)?;
+    let histogram = if taps.want_histogram {
+        let histogram = compute_histogram(&image.pixels, image.width, image.height)
+            .map_err(TransformError::from)?;
+        Some(histogram)
+    } else {
+        None
+    };
+    let thumbnail = taps
+        .thumbnail_size
+        .map(|size| generate_thumbnail(image, size))
+        .transpose()
+        .map_err(ExportError::Thumbnail)?;
+
+    let bytes = encode_image(image, format)?;
+    Ok(TappedExport {
+        bytes,
+        histogram,
+        thumbnail,
+    })
+}
+
+/// JPEG bytes per output pixel by quality, for typical photos.
+///
+/// Interpolated linearly between qualities.
+const JPEG_BYTES_PER_PIXEL: &[(u8, f64)] = &[
+    (1, 0.20),
+    (30, 0.26),
+    (50, 0.32),
+    (75, 0.38),
+    (85, 0.44),
+    (90, 0.48),
+    (95, 0.56),
+    (100, 0.70),
+];
+
+/// 8-bit PNG bytes per output pixel for typical photos.
+const PNG8_BYTES_PER_PIXEL: f64 = 1.8;
+
+/// 16-bit PNG bytes per output pixel for typical photos.
+const PNG16_BYTES_PER_PIXEL: f64 = 3.6;
+
+/// Export steps that change the output file, besides the edit and format.
+///
+/// The size steps are those of the app's export resize (resize, then pad,
+/// then border); a watermark does not change the size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportPreflightOptions {
+    /// Maximum length of the longest edge (no resize when omitted)
+    pub max_edge: Option<u32>,
+    /// Target width / height ratio to pad to (no padding when omitted)
+    pub pad_aspect: Option<f32>,
+    /// Border width in pixels (no border when omitted or 0)
+    pub border_px: Option<u32>,
+    /// A crop-only JPEG export will be cropped losslessly instead of
+    /// re-encoded
+    pub lossless_when_possible: bool,
+}
+
+/// What an export will produce, from [`preflight_export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportPreflight {
+    /// Width of the exported image in pixels
+    pub output_width: u32,
+    /// Height of the exported image in pixels
+    pub output_height: u32,
+    /// Estimated file size in bytes
+    pub estimated_bytes: u64,
+    /// The rendered image is scaled down to `max_edge`
+    pub will_resize: bool,
+    /// The pixels are encoded again rather than losslessly cropped
+    pub will_reencode: bool,
+}
+
+/// Report the size of an export before running it.
+///
+/// The output dimensions come from the same math as the export: the
+/// rendered size after rotation and crop, then [`resize_to_fit`], then
+/// [`pad_to_aspect`] and [`add_border`], so they match the exported file
+/// exactly. With `lossless_when_possible`, a JPEG whose only edit is a crop
+/// is not re-encoded; its left and top edges may then snap out by up to 15
+/// pixels, which the reported size does not include.
+///
+/// The file size is estimated from `proxy`, a small decode of the source
+/// (such as its preview), when given: the proxy is rendered and encoded,
+/// and its bytes per pixel are scaled to the output, which is within a
+/// factor of 2.5 of the real size on the test fixtures. Padding and borders
+/// are not counted, since solid areas compress to almost nothing. Without
+/// a proxy a bytes-per-pixel figure for typical photos is used, by JPEG
+/// quality or PNG bit depth. On the test fixtures it is within a factor of
+/// 7 of the real JPEG size; PNG of continuous-tone images is within a
+/// factor of 2.5, but flat graphics compress far below the estimate.
+///
+/// [`resize_to_fit`]: crate::decode::resize_to_fit
+/// [`pad_to_aspect`]: crate::compose::pad_to_aspect
+/// [`add_border`]: crate::compose::add_border
+///
+/// # Errors
+///
+/// The errors the export would return for the same input:
+/// `ExportError::Encode` for an invalid format, `ExportError::Render` for
+/// empty or oversized source dimensions or an invalid proxy,
+/// `ExportError::Resize` for a `max_edge` of zero, and
+/// `ExportError::Compose` for an invalid pad aspect or an output that is
+/// too large.
+pub fn preflight_export(
+    source_width: u32,
+    source_height: u32,
+    prepared: &PreparedEdit,
+    options: &ExportPreflightOptions,
+    format: &ExportFormat,
+    proxy: Option<&DecodedImage>,
+) -> Result<ExportPreflight, ExportError> {
+    format.validate().map_err(EncodeError::from)?;
+    validate_dimensions(source_width, source_height).map_err(TransformError::from)?;
+
+    let (rendered_width, rendered_height) = prepared.rendered_size(source_width, source_height);
+    let (width, height) = match options.max_edge {
+        Some(0) => return Err(ExportError::Resize(DecodeError::InvalidFormat)),
+        Some(max_edge) => resize_to_fit_dimensions(rendered_width, rendered_height, max_edge),
+        None => (rendered_width, rendered_height),
+    };
+    let will_resize = (width, height) != (rendered_width, rendered_height);
+    let (output_width, output_height) =
+        composed_dimensions(width, height, options.pad_aspect, options.border_px)?;
+
+    let content_pixels = width as f64 * height as f64;
+    let estimated_bytes = match proxy {
+        Some(proxy) => {
+            let (overhead, bytes_per_pixel) = proxy_encode_rate(proxy, prepared, format)?;
+            overhead + content_pixels * bytes_per_pixel
+        }
+        None => content_pixels * typical_bytes_per_pixel(format),
+    };
+
+    let unchanged_size = (output_width, output_height) == (rendered_width, rendered_height);
+    let lossless = options.lossless_when_possible
+        && format.container == ExportContainer::Jpeg
+        && prepared.toggles().transform
+        && prepared.state().crop_only().is_some()
+        && unchanged_size;
+
+    Ok(ExportPreflight {
+        output_width,
+        output_height,
+        estimated_bytes: estimated_bytes.round() as u64,
+        will_resize,
+        will_reencode: !lossless,
+    })
+}
+
+/// Fixed overhead and bytes per pixel of `proxy` rendered and encoded in
+/// `format`.
+///
+/// The overhead (headers and the ICC profile) is that of a 1x1 image, so
+/// it is not spread over the proxy's few pixels.
+fn proxy_encode_rate(
+    proxy: &DecodedImage,
+    prepared: &PreparedEdit,
+    format: &ExportFormat,
+) -> Result<(f64, f64), ExportError> {
+    let rendered = prepared.render(proxy, InterpolationFilter::Bilinear)?;
+    let bytes = encode_image(&rendered, format)?.len() as f64;
+    let overhead = encode_image(&DecodedImage::new(1, 1, vec![0; 3]), format)?.len() as f64;
+    let pixels = rendered.width as f64 * rendered.height as f64;
+    Ok((overhead, (bytes - overhead).max(0.0) / pixels))
+}
+
+/// Bytes per pixel of a typical photo encoded in `format`.
+fn typical_bytes_per_pixel(format: &ExportFormat) -> f64 {
+    match format.container {
+        ExportContainer::Png if format.bit_depth == 16 => PNG16_BYTES_PER_PIXEL,
+        ExportContainer::Png => PNG8_BYTES_PER_PIXEL,
+        ExportContainer::Jpeg => {
+            let quality = format.jpeg_quality();
+            let upper = JPEG_BYTES_PER_PIXEL
+                .iter()
+                .position(|&(q, _)| q >= quality)
+                .unwrap_or(JPEG_BYTES_PER_PIXEL.len() - 1);
+            let (q1, b1) = JPEG_BYTES_PER_PIXEL[upper];
+            if upper == 0 || q1 == quality {
+                return b1;
+            }
+            let (q0, b0) = JPEG_BYTES_PER_PIXEL[upper - 1];
+            let t = (quality - q0) as f64 / (q1 - q0) as f64;
+            b0 + (b1 - b0) * t
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::{add_border, pad_to_aspect, Gravity};
+    use crate::decode::{decode_jpeg, resize_to_fit, FilterType};
+    use crate::edit::{CropRect, EditState, Rotation};
+    use crate::render::rendered_size;
+    use crate::testing::{
+        checkerboard, color_bars, gradient, noise, slanted_edge, GradientDirection,
+    };
+
+    fn prepared() -> PreparedEdit {
+        let mut state = EditState::default();
+        state.adjustments.exposure = 0.4;
+        state.adjustments.contrast = 15.0;
+        PreparedEdit::new(&state).unwrap()
+    }
+
+    fn all_taps() -> ExportTaps {
+        ExportTaps {
+            want_histogram: true,
+            thumbnail_size: Some(24),
+        }
+    }
+
+    #[test]
+    fn test_histogram_tap_matches_encoded_pixels() {
+        let image = noise(64, 48, 5);
+        let prepared = prepared();
+        let export = process_and_encode(
+            &image,
+            &prepared,
+            InterpolationFilter::Bilinear,
+            &ExportFormat::jpeg(90),
+            &all_taps(),
+        )
+        .unwrap();
+
+        let rendered = prepared
+            .render(&image, InterpolationFilter::Bilinear)
+            .unwrap();
+        let expected = compute_histogram(&rendered.pixels, 64, 48).unwrap();
+        let histogram = export.histogram.unwrap();
+        assert_eq!(histogram.red, expected.red);
+        assert_eq!(histogram.luminance, expected.luminance);
+
+        // A decode of the file only differs by the compression loss
+        let decoded = decode_jpeg(&export.bytes).unwrap();
+        let reread = compute_histogram(&decoded.pixels, 64, 48).unwrap();
+        let mean = |bins: &[u32; 256]| {
+            let total: u64 = bins.iter().map(|&n| n as u64).sum();
+            let sum: u64 = bins
+                .iter()
+                .enumerate()
+                .map(|(v, &n)| v as u64 * n as u64)
+                .sum();
+            sum as f64 / total as f64
+        };
+        for (tapped, reread) in [
+            (&histogram.red, &reread.red),
+            (&histogram.green, &reread.green),
+            (&histogram.blue, &reread.blue),
+        ] {
+            assert!((mean(tapped) - mean(reread)).abs() < 2.0);
+        }
+    }
+
+    #[test]
+    fn test_thumbnail_tap_matches_generate_thumbnail() {
+        let image = noise(64, 48, 6);
+        let prepared = prepared();
+        let export = process_and_encode(
+            &image,
+            &prepared,
+            InterpolationFilter::Bilinear,
+            &ExportFormat::jpeg(90),
+            &all_taps(),
+        )
+        .unwrap();
+
+        let rendered = prepared
+            .render(&image, InterpolationFilter::Bilinear)
+            .unwrap();
+        let expected = generate_thumbnail(&rendered, 24).unwrap();
+        let thumbnail = export.thumbnail.unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (24, 18));
+        assert_eq!(thumbnail.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_taps_do_not_change_encoded_bytes() {
+        let image = noise(40, 30, 7);
+        let prepared = prepared();
+        let filter = InterpolationFilter::Bilinear;
+        let format = ExportFormat::jpeg(85);
+        let plain =
+            process_and_encode(&image, &prepared, filter, &format, &ExportTaps::default()).unwrap();
+        let tapped = process_and_encode(&image, &prepared, filter, &format, &all_taps()).unwrap();
+
+        assert!(plain.histogram.is_none() && plain.thumbnail.is_none());
+        assert_eq!(plain.bytes, tapped.bytes);
+        let rendered = prepared.render(&image, filter).unwrap();
+        let direct = encode_image(&rendered, &format).unwrap();
+        assert_eq!(plain.bytes, direct);
+    }
+
+    #[test]
+    fn test_invalid_format_errors() {
+        let format = ExportFormat {
+            bit_depth: 16,
+            ..ExportFormat::jpeg(90)
+        };
+        let result = process_and_encode(
+            &noise(8, 8, 1),
+            &prepared(),
+            InterpolationFilter::Bilinear,
+            &format,
+            &all_taps(),
+        );
+        assert!(matches!(
+            result,
+            Err(ExportError::Encode(EncodeError::InvalidFormat(_)))
+        ));
+    }
+
+    #[test]
+    fn test_zero_thumbnail_size_errors() {
+        let taps = ExportTaps {
+            want_histogram: false,
+            thumbnail_size: Some(0),
+        };
+        assert!(matches!(
+            encode_with_taps(&noise(8, 8, 1), &ExportFormat::jpeg(90), &taps),
+            Err(ExportError::Thumbnail(_))
+        ));
+    }
+
+    /// Export `image` as the app does: render, then resize, pad and border.
+    fn export_dimensions(
+        image: &DecodedImage,
+        prepared: &PreparedEdit,
+        options: &ExportPreflightOptions,
+    ) -> (u32, u32) {
+        let mut out = prepared
+            .render(image, InterpolationFilter::Lanczos3)
+            .unwrap();
+        if let Some(max_edge) = options.max_edge {
+            out = resize_to_fit(&out, max_edge, FilterType::Bilinear).unwrap();
+        }
+        if let Some(aspect) = options.pad_aspect {
+            out = pad_to_aspect(&out, aspect, (255, 255, 255), Gravity::Center).unwrap();
+        }
+        if let Some(border_px) = options.border_px {
+            out = add_border(&out, border_px, (0, 0, 0)).unwrap();
+        }
+        let bytes = encode_image(&out, &ExportFormat::jpeg(90)).unwrap();
+        let decoded = decode_jpeg(&bytes).unwrap();
+        (decoded.width, decoded.height)
+    }
+
+    fn preflight(
+        image: &DecodedImage,
+        state: &EditState,
+        options: &ExportPreflightOptions,
+        format: &ExportFormat,
+    ) -> Result<ExportPreflight, ExportError> {
+        let prepared = PreparedEdit::new(state).unwrap();
+        preflight_export(image.width, image.height, &prepared, options, format, None)
+    }
+
+    #[test]
+    fn test_preflight_dimensions_match_export() {
+        let image = noise(97, 61, 3);
+        let crops = [
+            None,
+            Some(CropRect {
+                left: 0.1,
+                top: 0.2,
+                width: 0.7,
+                height: 0.5,
+            }),
+            // Extends past the right and bottom edges; the export clamps it
+            Some(CropRect {
+                left: 0.8,
+                top: 0.6,
+                width: 0.6,
+                height: 0.9,
+            }),
+            Some(CropRect {
+                left: 0.5,
+                top: 0.5,
+                width: 0.001,
+                height: 0.001,
+            }),
+        ];
+        let rotations = [(0.0, 0.0), (90.0, 0.0), (0.0, -7.0), (180.0, 3.5)];
+        let options = [
+            ExportPreflightOptions::default(),
+            ExportPreflightOptions {
+                max_edge: Some(40),
+                ..Default::default()
+            },
+            ExportPreflightOptions {
+                max_edge: Some(1000),
+                ..Default::default()
+            },
+            ExportPreflightOptions {
+                max_edge: Some(50),
+                pad_aspect: Some(0.8),
+                border_px: Some(3),
+                ..Default::default()
+            },
+        ];
+        for crop in crops {
+            for (angle, straighten) in rotations {
+                for options in &options {
+                    let state = EditState {
+                        crop,
+                        rotation: Rotation { angle, straighten },
+                        ..Default::default()
+                    };
+                    let prepared = PreparedEdit::new(&state).unwrap();
+                    let expected = export_dimensions(&image, &prepared, options);
+                    let result =
+                        preflight(&image, &state, options, &ExportFormat::jpeg(90)).unwrap();
+                    assert_eq!(
+                        (result.output_width, result.output_height),
+                        expected,
+                        "crop {:?} rotation {} {} options {:?}",
+                        crop,
+                        angle,
+                        straighten,
+                        options
+                    );
+                    let (rendered_width, rendered_height) = rendered_size(97, 61, &state);
+                    assert_eq!(
+                        result.will_resize,
+                        options
+                            .max_edge
+                            .is_some_and(|e| rendered_width.max(rendered_height) > e)
+                    );
+                }
+            }
+        }
+    }
+
+    /// The heuristic is for typical photos; the fixtures bracket them, from
+    /// smooth gradients to pure noise.
+    #[test]
+    fn test_heuristic_estimate_within_documented_factor() {
+        let (w, h) = (384, 256);
+        let continuous = [gradient(w, h, GradientDirection::Diagonal), noise(w, h, 9)];
+        let graphics = [
+            color_bars(w, h),
+            checkerboard(w, h, 16),
+            slanted_edge(w, h, 5.0),
+        ];
+        let within = |image: &DecodedImage, format: &ExportFormat, factor: f64| {
+            let actual = encode_image(image, format).unwrap().len() as f64;
+            let state = EditState::default();
+            let options = ExportPreflightOptions::default();
+            let estimate = preflight(image, &state, &options, format)
+                .unwrap()
+                .estimated_bytes;
+            let ratio = estimate as f64 / actual;
+            assert!(
+                ratio < factor && ratio > 1.0 / factor,
+                "{:?}: estimate {} actual {}",
+                format,
+                estimate,
+                actual
+            );
+        };
+        for quality in [30, 50, 75, 90, 95, 100] {
+            for image in continuous.iter().chain(&graphics) {
+                within(image, &ExportFormat::jpeg(quality), 7.0);
+            }
+        }
+        let png16 = ExportFormat {
+            bit_depth: 16,
+            ..ExportFormat::png()
+        };
+        for image in &continuous {
+            within(image, &ExportFormat::png(), 2.5);
+            within(image, &png16, 2.5);
+        }
+    }
+
+    #[test]
+    fn test_proxy_estimate_tracks_real_encode() {
+        let (w, h) = (384, 256);
+        let state = EditState {
+            crop: Some(CropRect {
+                left: 0.1,
+                top: 0.1,
+                width: 0.8,
+                height: 0.8,
+            }),
+            ..Default::default()
+        };
+        let prepared = PreparedEdit::new(&state).unwrap();
+        let options = ExportPreflightOptions::default();
+        for image in [
+            gradient(w, h, GradientDirection::Diagonal),
+            color_bars(w, h),
+            noise(w, h, 4),
+        ] {
+            let proxy = resize_to_fit(&image, 128, FilterType::Bilinear).unwrap();
+            for format in [ExportFormat::jpeg(90), ExportFormat::png()] {
+                let actual = process_and_encode(
+                    &image,
+                    &prepared,
+                    InterpolationFilter::Lanczos3,
+                    &format,
+                    &ExportTaps::default(),
+                )
+                .unwrap()
+                .bytes
+                .len() as f64;
+                let estimate = preflight_export(w, h, &prepared, &options, &format, Some(&proxy))
+                    .unwrap()
+                    .estimated_bytes as f64;
+                let ratio = estimate / actual;
+                assert!(
+                    ratio < 2.5 && ratio > 1.0 / 2.5,
+                    "{:?}: estimate {} actual {}",
+                    format.container,
+                    estimate,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_preflight_errors_match_export() {
+        let image = noise(16, 12, 2);
+        let state = EditState::default();
+        let options = ExportPreflightOptions::default();
+        let bad_format = ExportFormat {
+            bit_depth: 16,
+            ..ExportFormat::jpeg(90)
+        };
+        assert!(matches!(
+            preflight(&image, &state, &options, &bad_format),
+            Err(ExportError::Encode(EncodeError::InvalidFormat(_)))
+        ));
+
+        let prepared = PreparedEdit::new(&state).unwrap();
+        let format = ExportFormat::jpeg(90);
+        assert!(matches!(
+            preflight_export(0, 12, &prepared, &options, &format, None),
+            Err(ExportError::Render(TransformError::InvalidImage(_)))
+        ));
+
+        let zero_edge = ExportPreflightOptions {
+            max_edge: Some(0),
+            ..Default::default()
+        };
+        let err = preflight(&image, &state, &zero_edge, &format).unwrap_err();
+        let real = resize_to_fit(&image, 0, FilterType::Bilinear).unwrap_err();
+        assert_eq!(err.to_string(), real.to_string());
+
+        let bad_aspect = ExportPreflightOptions {
+            pad_aspect: Some(-1.0),
+            ..Default::default()
+        };
+        let err = preflight(&image, &state, &bad_aspect, &format).unwrap_err();
+        let real = pad_to_aspect(&image, -1.0, (0, 0, 0), Gravity::Center).unwrap_err();
+        assert!(matches!(
+            err,
+            ExportError::Compose(ComposeError::InvalidAspect(_))
+        ));
+        assert_eq!(err.to_string(), real.to_string());
+    }
+
+    #[test]
+    fn test_lossless_crop_is_not_reencoded() {
+        let image = noise(16, 12, 2);
+        let mut state = EditState {
+            crop: Some(CropRect {
+                left: 0.25,
+                top: 0.25,
+                width: 0.5,
+                height: 0.5,
+            }),
+            ..Default::default()
+        };
+        let lossless = ExportPreflightOptions {
+            lossless_when_possible: true,
+            ..Default::default()
+        };
+        let jpeg = ExportFormat::jpeg(90);
+        assert!(
+            !preflight(&image, &state, &lossless, &jpeg)
+                .unwrap()
+                .will_reencode
+        );
+        assert!(
+            preflight(&image, &state, &Default::default(), &jpeg)
+                .unwrap()
+                .will_reencode
+        );
+        assert!(
+            preflight(&image, &state, &lossless, &ExportFormat::png())
+                .unwrap()
+                .will_reencode
+        );
+        let resized = ExportPreflightOptions {
+            max_edge: Some(4),
+            ..lossless
+        };
+        assert!(
+            preflight(&image, &state, &resized, &jpeg)
+                .unwrap()
+                .will_reencode
+        );
+
+        state.adjustments.exposure = 0.5;
+        assert!(
+            preflight(&image, &state, &lossless, &jpeg)
+                .unwrap()
+                .will_reencode
+        );
+    }
+}
@@ -0,0 +1,412 @@
+//! Output file naming for batch export.
+//!
+//! When a selection is exported, every file gets a name from a template such
+//! as `{basename}-{seq:3}-{width}px.jpg`. Expansion and collision handling
+//! live here so every caller names files the same way.
+//!
+//! # Template Syntax
+//!
+//! | Token        | Expands to                                   |
+//! |--------------|----------------------------------------------|
+//! | `{basename}` | Source file name without its extension       |
+//! | `{seq}`      | Position in the export, starting at 1        |
+//! | `{date}`     | Capture date as passed in (e.g. `2024-05-01`) |
+//! | `{width}`    | Output width in pixels                       |
+//! | `{height}`   | Output height in pixels                      |
+//! | `{quality}`  | JPEG quality                                 |
+//!
+//! Numeric tokens take a minimum width, zero-padded: `{seq:3}` gives `007`.
+//! `{{` and `}}` stand for literal braces. Positions in errors count
+//! characters, not bytes, so they point at the right place in templates with
+//! non-ASCII text.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// Errors from [`ExportNameTemplate::parse`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TemplateError {
+    /// A `{...}` token names no known field.
+    #[error("Unknown token '{{{token}}}' at position {position}")]
+    UnknownToken { token: String, position: usize },
+
+    /// A padding width was given to a non-numeric token, or is not a number.
+    #[error("Invalid width in '{{{token}}}' at position {position}")]
+    InvalidWidth { token: String, position: usize },
+
+    /// A `{` has no matching `}`.
+    #[error("Unclosed '{{' at position {position}")]
+    UnclosedBrace { position: usize },
+
+    /// A `}` has no matching `{` (write `}}` for a literal brace).
+    #[error("Unmatched '}}' at position {position}")]
+    UnmatchedBrace { position: usize },
+}
+
+impl TemplateError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TemplateError::UnknownToken { .. } => "UNKNOWN_TOKEN",
+            TemplateError::InvalidWidth { .. } => "INVALID_WIDTH",
+            TemplateError::UnclosedBrace { .. } => "UNCLOSED_BRACE",
+            TemplateError::UnmatchedBrace { .. } => "UNMATCHED_BRACE",
+        }
+    }
+
+    /// Character position in the template where the problem starts.
+    pub fn position(&self) -> usize {
+        match self {
+            TemplateError::UnknownToken { position, .. }
+            | TemplateError::InvalidWidth { position, .. }
+            | TemplateError::UnclosedBrace { position }
+            | TemplateError::UnmatchedBrace { position } => *position,
+        }
+    }
+}
+
+/// Values a template is expanded with, for one exported file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportNameContext {
+    /// Source file name without its extension
+    pub basename: String,
+    /// Position in the export; [`build_export_manifest`] numbers items from
+    /// 1 when this is `None`
+    pub seq: Option<u32>,
+    /// Capture date, inserted as given
+    pub date: String,
+    /// Output width in pixels
+    pub width: u32,
+    /// Output height in pixels
+    pub height: u32,
+    /// JPEG quality
+    pub quality: u8,
+}
+
+/// A field a template token refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Basename,
+    Seq,
+    Date,
+    Width,
+    Height,
+    Quality,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "basename" => Field::Basename,
+            "seq" => Field::Seq,
+            "date" => Field::Date,
+            "width" => Field::Width,
+            "height" => Field::Height,
+            "quality" => Field::Quality,
+            _ => return None,
+        })
+    }
+
+    fn is_numeric(self) -> bool {
+        !matches!(self, Field::Basename | Field::Date)
+    }
+}
+
+/// One piece of a parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Token { field: Field, width: usize },
+}
+
+/// A parsed file name template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportNameTemplate {
+    parts: Vec<Part>,
+}
+
+impl ExportNameTemplate {
+    /// Parse a template such as `{basename}-{seq:3}.jpg`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TemplateError`] with the character position of the first
+    /// unknown token, bad width or unbalanced brace.
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().enumerate().peekable();
+
+        while let Some((position, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|&(_, c)| c) == Some('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek().map(|&(_, c)| c) == Some('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(TemplateError::UnmatchedBrace { position }),
+                '{' => {
+                    let mut token = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, '{')) | None => {
+                                return Err(TemplateError::UnclosedBrace { position })
+                            }
+                            Some((_, c)) => token.push(c),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_token(&token, position)?);
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Expand the template for one file.
+    ///
+    /// Path separators and control characters in substituted values are
+    /// replaced with `_`, so a basename cannot point outside the export
+    /// folder. A missing `seq` expands as 1.
+    pub fn expand(&self, context: &ExportNameContext) -> String {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => name.push_str(text),
+                Part::Token { field, width } => {
+                    let value = match field {
+                        Field::Basename => sanitize(&context.basename),
+                        Field::Date => sanitize(&context.date),
+                        Field::Seq => context.seq.unwrap_or(1).to_string(),
+                        Field::Width => context.width.to_string(),
+                        Field::Height => context.height.to_string(),
+                        Field::Quality => context.quality.to_string(),
+                    };
+                    name.push_str(&format!("{:0>width$}", value, width = width));
+                }
+            }
+        }
+        name
+    }
+
+    /// Expand the template and make the name unique among `used`.
+    ///
+    /// A name that is taken gets `-1`, `-2`, ... before its extension
+    /// (`beach.jpg` becomes `beach-1.jpg`). Names are compared ignoring
+    /// case, since the export folder may be on a case-insensitive file
+    /// system. The returned name is added to `used`.
+    pub fn expand_unique(&self, context: &ExportNameContext, used: &mut HashSet<String>) -> String {
+        let name = self.expand(context);
+        let (stem, extension) = split_extension(&name);
+        let mut candidate = name.clone();
+        let mut suffix = 0;
+        while used.contains(&candidate.to_lowercase()) {
+            suffix += 1;
+            candidate = format!("{}-{}{}", stem, suffix, extension);
+        }
+        used.insert(candidate.to_lowercase());
+        candidate
+    }
+}
+
+/// Expand `template` for every item, in order, with unique names.
+///
+/// Items without a `seq` are numbered by their position, starting at 1.
+/// `existing` lists names already in the export folder; they are avoided
+/// too.
+///
+/// # Errors
+///
+/// Returns a [`TemplateError`] if the template is invalid.
+pub fn build_export_manifest(
+    template: &str,
+    items: &[ExportNameContext],
+    existing: &[String],
+) -> Result<Vec<String>, TemplateError> {
+    let template = ExportNameTemplate::parse(template)?;
+    let mut used: HashSet<String> = existing.iter().map(|n| n.to_lowercase()).collect();
+    Ok(items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let context = ExportNameContext {
+                seq: Some(item.seq.unwrap_or(i as u32 + 1)),
+                ..item.clone()
+            };
+            template.expand_unique(&context, &mut used)
+        })
+        .collect())
+}
+
+/// Parse the inside of a `{...}` token that starts at `position`.
+fn parse_token(token: &str, position: usize) -> Result<Part, TemplateError> {
+    let (name, width) = match token.split_once(':') {
+        Some((name, width)) => (name, Some(width)),
+        None => (token, None),
+    };
+    let field = Field::from_name(name).ok_or_else(|| TemplateError::UnknownToken {
+        token: token.to_string(),
+        position,
+    })?;
+    let width = match width {
+        None => 0,
+        Some(width) => match width.parse::<usize>() {
+            Ok(width) if field.is_numeric() && width <= 10 => width,
+            _ => {
+                return Err(TemplateError::InvalidWidth {
+                    token: token.to_string(),
+                    position,
+                })
+            }
+        },
+    };
+    Ok(Part::Token { field, width })
+}
+
+/// Replace characters that cannot appear in a single file name.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c == '/' || c == '\\' || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Split `name` into stem and extension (with its dot, or empty).
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(basename: &str) -> ExportNameContext {
+        ExportNameContext {
+            basename: basename.to_string(),
+            width: 2048,
+            height: 1365,
+            quality: 90,
+            date: "2024-05-01".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_expand_all_tokens() {
+        let template =
+            ExportNameTemplate::parse("{date}_{basename}-{seq}-{width}x{height}-q{quality}.jpg")
+                .unwrap();
+        let context = ExportNameContext {
+            seq: Some(4),
+            ..item("DSC01234")
+        };
+        assert_eq!(
+            template.expand(&context),
+            "2024-05-01_DSC01234-4-2048x1365-q90.jpg"
+        );
+    }
+
+    #[test]
+    fn test_zero_padded_seq() {
+        let names =
+            build_export_manifest("{basename}-{seq:3}.jpg", &[item("a"), item("b")], &[]).unwrap();
+        assert_eq!(names, vec!["a-001.jpg", "b-002.jpg"]);
+
+        // Values longer than the width are not cut
+        let template = ExportNameTemplate::parse("{seq:2}").unwrap();
+        let context = ExportNameContext {
+            seq: Some(1234),
+            ..Default::default()
+        };
+        assert_eq!(template.expand(&context), "1234");
+    }
+
+    #[test]
+    fn test_collisions_get_numbered_suffixes() {
+        let items = [item("beach"), item("Beach"), item("beach"), item("sunset")];
+        let existing = ["sunset.jpg".to_string()];
+        let names = build_export_manifest("{basename}.jpg", &items, &existing).unwrap();
+        assert_eq!(
+            names,
+            vec!["beach.jpg", "Beach-1.jpg", "beach-2.jpg", "sunset-1.jpg"]
+        );
+
+        // Without an extension the suffix goes at the end
+        let names = build_export_manifest("{basename}", &[item("x"), item("x")], &[]).unwrap();
+        assert_eq!(names, vec!["x", "x-1"]);
+    }
+
+    #[test]
+    fn test_unicode_basenames() {
+        let items = [item("Café"), item("café"), item("東京タワー"), item("a/b")];
+        let names = build_export_manifest("{basename}-{width}px.jpg", &items, &[]).unwrap();
+        assert_eq!(
+            names,
+            vec![
+                "Café-2048px.jpg",
+                "café-2048px-1.jpg",
+                "東京タワー-2048px.jpg",
+                "a_b-2048px.jpg",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literal_braces() {
+        let template = ExportNameTemplate::parse("{{{basename}}}.jpg").unwrap();
+        assert_eq!(template.expand(&item("x")), "{x}.jpg");
+    }
+
+    #[test]
+    fn test_template_errors_report_position() {
+        let err = |t: &str| ExportNameTemplate::parse(t).unwrap_err();
+
+        assert_eq!(
+            err("{basename}-{size}.jpg"),
+            TemplateError::UnknownToken {
+                token: "size".to_string(),
+                position: 11,
+            }
+        );
+        assert_eq!(
+            err("{basename:3}"),
+            TemplateError::InvalidWidth {
+                token: "basename:3".to_string(),
+                position: 0,
+            }
+        );
+        assert_eq!(err("{seq:x}").code(), "INVALID_WIDTH");
+        assert_eq!(
+            err("ß-{seq.jpg"),
+            TemplateError::UnclosedBrace { position: 2 }
+        );
+        assert_eq!(err("{seq}}.jpg").position(), 5);
+        assert_eq!(err("{seq}}.jpg").code(), "UNMATCHED_BRACE");
+        assert_eq!(err("{seq").to_string(), "Unclosed '{' at position 0");
+        assert_eq!(
+            err("{nope}").to_string(),
+            "Unknown token '{nope}' at position 0"
+        );
+    }
+}
@@ -3,8 +3,12 @@
 //! This module provides functions for computing RGB and luminance histograms
 //! from pixel data, used for the edit view histogram display.
 
-use crate::luminance::calculate_luminance_u8;
+use crate::curve::ToneCurveLut;
+use crate::luminance::{calculate_luminance, calculate_luminance_u8};
+use crate::mask::{MaskEntry, MaskError};
+use crate::validate::{validate_rgb_buffer, validate_rgb_f32_buffer, ImageError};
 use crate::Histogram;
+use serde::{Deserialize, Serialize};
 
 /// Compute RGB and luminance histograms from pixel data.
 ///
@@ -16,12 +20,16 @@ use crate::Histogram;
 /// # Returns
 /// A `Histogram` with all four channels (red, green, blue, luminance) populated.
 ///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+///
 /// # Example
 /// ```
 /// use literoom_core::histogram::compute_histogram;
 ///
 /// let pixels = vec![255, 0, 0, 0, 255, 0]; // Red, Green pixels
-/// let hist = compute_histogram(&pixels, 2, 1);
+/// let hist = compute_histogram(&pixels, 2, 1).unwrap();
 /// assert_eq!(hist.red[255], 1);
 /// assert_eq!(hist.green[255], 1);
 /// ```
@@ -29,57 +37,453 @@ use crate::Histogram;
 /// # Performance
 /// This function uses a single-pass algorithm with O(n) time complexity
 /// where n is the number of pixels. Memory usage is constant (4KB for bins).
-pub fn compute_histogram(pixels: &[u8], width: u32, height: u32) -> Histogram {
-    let mut hist = Histogram::new();
-
-    let expected_len = (width as usize) * (height as usize) * 3;
+pub fn compute_histogram(pixels: &[u8], width: u32, height: u32) -> Result<Histogram, ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    Ok(bin_pixels(pixels, |v| v))
+}
 
-    // Early return for empty or invalid data
-    if pixels.is_empty() || expected_len == 0 {
-        return hist;
-    }
+/// Compute the histograms of pixel data as it will look after a tone curve.
+///
+/// Each value is mapped through `lut` while binning, so the result equals
+/// [`compute_histogram`] of the pixels after
+/// [`apply_tone_curve`](crate::curve::apply_tone_curve), without writing the
+/// curved image. Use it to show the post-curve histogram while the curve is
+/// still applied later in the pipeline.
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+pub fn compute_histogram_with_lut(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    lut: &ToneCurveLut,
+) -> Result<Histogram, ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    Ok(bin_pixels(pixels, |v| lut.lut[v as usize]))
+}
 
-    debug_assert!(
-        pixels.len() == expected_len,
-        "Pixel data size mismatch. Expected {}, got {}",
-        expected_len,
-        pixels.len()
-    );
+/// Bin RGB pixels, passing each channel value through `map` first.
+fn bin_pixels(pixels: &[u8], map: impl Fn(u8) -> u8) -> Histogram {
+    let mut hist = Histogram::new();
 
     // Process pixels in chunks of 3 (RGB)
     for chunk in pixels.chunks_exact(3) {
-        let r = chunk[0] as usize;
-        let g = chunk[1] as usize;
-        let b = chunk[2] as usize;
+        let (r, g, b) = (map(chunk[0]), map(chunk[1]), map(chunk[2]));
 
         // Bin RGB channels
-        hist.red[r] += 1;
-        hist.green[g] += 1;
-        hist.blue[b] += 1;
+        hist.red[r as usize] += 1;
+        hist.green[g as usize] += 1;
+        hist.blue[b as usize] += 1;
 
         // Compute and bin luminance
-        let lum = calculate_luminance_u8(chunk[0], chunk[1], chunk[2]);
+        let lum = calculate_luminance_u8(r, g, b);
         hist.luminance[lum as usize] += 1;
     }
 
     hist
 }
 
+/// Compute RGB and luminance histograms from float pixel data.
+///
+/// The 256 bins span `min..=max`; values outside the range land in the first
+/// or last bin. With the range 0.0-1.0, an image converted with
+/// [`crate::decode::DecodedImage::to_f32`] gives the same histogram as
+/// [`compute_histogram`] on the original. Widen `max` to see HDR highlights.
+/// A range that is empty or not finite falls back to 0.0-1.0. NaN values
+/// count toward the first bin.
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+pub fn compute_histogram_f32(
+    pixels: &[f32],
+    width: u32,
+    height: u32,
+    min: f32,
+    max: f32,
+) -> Result<Histogram, ImageError> {
+    validate_rgb_f32_buffer(pixels, width, height)?;
+
+    let (min, max) = if min.is_finite() && max.is_finite() && max > min {
+        (min, max)
+    } else {
+        (0.0, 1.0)
+    };
+    let scale = 255.0 / (max - min);
+    let bin = |v: f32| ((v - min) * scale).round().clamp(0.0, 255.0) as usize;
+
+    let mut hist = Histogram::new();
+    for chunk in pixels.chunks_exact(3) {
+        hist.red[bin(chunk[0])] += 1;
+        hist.green[bin(chunk[1])] += 1;
+        hist.blue[bin(chunk[2])] += 1;
+        hist.luminance[bin(calculate_luminance(chunk[0], chunk[1], chunk[2]))] += 1;
+    }
+
+    Ok(hist)
+}
+
+/// Which mask of a stack [`compute_masked_histogram`] weights by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskTarget<'a> {
+    /// The mask at this position in the stack
+    Index(usize),
+    /// The mask with this id
+    Id(&'a str),
+}
+
+/// Compute the histograms of the pixels one mask of a stack affects.
+///
+/// Weighting is proportional: each pixel counts toward its bins with the
+/// mask's strength at its center, so a pixel at 30% strength adds 0.3. The
+/// weighted bins are rounded to whole counts at the end. A hard-edged mask
+/// therefore gives exactly the histogram of the pixels inside it, and a
+/// uniform mask scales every bin by its strength, leaving the shape of the
+/// full histogram unchanged. The mask is evaluated whether or not it is
+/// enabled, so its region can be inspected while it is switched off.
+///
+/// # Errors
+/// Returns `MaskError::IndexOutOfRange` or `MaskError::UnknownId` if
+/// `target` names no mask in the stack, or `MaskError::InvalidImage` if
+/// either dimension is zero or the buffer length is not
+/// `width * height * 3`.
+pub fn compute_masked_histogram(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    masks: &[MaskEntry],
+    target: MaskTarget,
+) -> Result<Histogram, MaskError> {
+    let mask = find_mask(masks, target)?;
+    validate_rgb_buffer(pixels, width, height)?;
+
+    // f64 sums stay exact for whole weights well past any image size
+    let mut weighted = [[0.0f64; 256]; 4];
+    let (w_f, h_f) = (width as f32, height as f32);
+    for (py, row) in pixels.chunks_exact(width as usize * 3).enumerate() {
+        let y = (py as f32 + 0.5) / h_f;
+        for (px, chunk) in row.chunks_exact(3).enumerate() {
+            let weight = mask.evaluate((px as f32 + 0.5) / w_f, y) as f64;
+            if weight <= 0.0 {
+                continue;
+            }
+            let (r, g, b) = (chunk[0], chunk[1], chunk[2]);
+            weighted[0][r as usize] += weight;
+            weighted[1][g as usize] += weight;
+            weighted[2][b as usize] += weight;
+            weighted[3][calculate_luminance_u8(r, g, b) as usize] += weight;
+        }
+    }
+
+    let mut hist = Histogram::new();
+    let channels = [
+        &mut hist.red,
+        &mut hist.green,
+        &mut hist.blue,
+        &mut hist.luminance,
+    ];
+    for (bins, sums) in channels.into_iter().zip(&weighted) {
+        for (bin, sum) in bins.iter_mut().zip(sums) {
+            *bin = sum.round() as u32;
+        }
+    }
+    Ok(hist)
+}
+
+/// The mask `target` names.
+fn find_mask<'a>(masks: &'a [MaskEntry], target: MaskTarget) -> Result<&'a MaskEntry, MaskError> {
+    let found = match target {
+        MaskTarget::Index(index) => masks.get(index),
+        MaskTarget::Id(id) => masks.iter().find(|m| m.id() == Some(id)),
+    };
+    found.ok_or_else(|| match target {
+        MaskTarget::Index(index) => MaskError::IndexOutOfRange {
+            index,
+            len: masks.len(),
+        },
+        MaskTarget::Id(id) => MaskError::UnknownId {
+            id: id.to_string(),
+            known: masks
+                .iter()
+                .filter_map(MaskEntry::id)
+                .map(str::to_string)
+                .collect(),
+        },
+    })
+}
+
+/// Luminance comparison of two histograms, for the before/after overlay.
+///
+/// Bins are compared as fractions of each histogram's pixel count, so images
+/// of different sizes (an edit that crops, for instance) still line up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramDelta {
+    /// `after - before` for each luminance bin, as fractions (256 bins)
+    pub luminance: Vec<f32>,
+    /// Median luminance bin of the first histogram
+    pub median_before: u8,
+    /// Median luminance bin of the second histogram
+    pub median_after: u8,
+    /// `median_after - median_before`, in bins
+    pub median_shift: i32,
+    /// Mean luminance of the first histogram, in bins
+    pub mean_before: f32,
+    /// Mean luminance of the second histogram, in bins
+    pub mean_after: f32,
+    /// `mean_after - mean_before`, in bins
+    pub mean_shift: f32,
+}
+
+/// Each bin as a fraction of the channel's total count.
+///
+/// The fractions sum to 1, or are all zero for an empty channel.
+pub fn bin_fractions(bins: &[u32; 256]) -> Vec<f32> {
+    let total: u64 = bins.iter().map(|&n| n as u64).sum();
+    if total == 0 {
+        return vec![0.0; 256];
+    }
+    bins.iter()
+        .map(|&n| (n as f64 / total as f64) as f32)
+        .collect()
+}
+
+/// Compare the luminance of `after` against `before`.
+///
+/// The histograms may come from images of different sizes; see
+/// [`HistogramDelta`].
+pub fn compute_histogram_delta(before: &Histogram, after: &Histogram) -> HistogramDelta {
+    let luminance = bin_fractions(&before.luminance)
+        .iter()
+        .zip(bin_fractions(&after.luminance))
+        .map(|(b, a)| a - b)
+        .collect();
+    let median_before = median_bin(&before.luminance);
+    let median_after = median_bin(&after.luminance);
+    let mean_before = mean_bin(&before.luminance);
+    let mean_after = mean_bin(&after.luminance);
+
+    HistogramDelta {
+        luminance,
+        median_before,
+        median_after,
+        median_shift: median_after as i32 - median_before as i32,
+        mean_before,
+        mean_after,
+        mean_shift: mean_after - mean_before,
+    }
+}
+
+/// Smallest bin whose cumulative count reaches half of all pixels, or 0 if
+/// the histogram is empty.
+fn median_bin(bins: &[u32; 256]) -> u8 {
+    let total: u64 = bins.iter().map(|&n| n as u64).sum();
+    let rank = total.div_ceil(2).max(1);
+
+    let mut seen = 0u64;
+    for (value, &count) in bins.iter().enumerate() {
+        seen += count as u64;
+        if seen >= rank {
+            return value as u8;
+        }
+    }
+    0
+}
+
+/// Count-weighted mean bin, or 0.0 if the histogram is empty.
+fn mean_bin(bins: &[u32; 256]) -> f32 {
+    let total: u64 = bins.iter().map(|&n| n as u64).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let sum: u64 = bins
+        .iter()
+        .enumerate()
+        .map(|(value, &n)| value as u64 * n as u64)
+        .sum();
+    (sum as f64 / total as f64) as f32
+}
+
+/// Statistics of one channel over a neighborhood, in 0-255 units.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStats {
+    /// Mean value
+    pub mean: f32,
+    /// Smallest value
+    pub min: u8,
+    /// Largest value
+    pub max: u8,
+    /// Population standard deviation
+    pub stddev: f32,
+}
+
+/// A pixel's value and the statistics of its neighborhood, for the
+/// eyedropper and info panel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PixelStats {
+    /// Column of the center pixel
+    pub x: u32,
+    /// Row of the center pixel
+    pub y: u32,
+    /// RGB value of the center pixel
+    pub center: [u8; 3],
+    /// Luminance of the center pixel
+    pub center_luminance: u8,
+    /// Number of pixels in the neighborhood, after clipping to the image
+    pub sample_count: u32,
+    /// Red channel over the neighborhood
+    pub red: ChannelStats,
+    /// Green channel over the neighborhood
+    pub green: ChannelStats,
+    /// Blue channel over the neighborhood
+    pub blue: ChannelStats,
+    /// Luminance over the neighborhood
+    pub luminance: ChannelStats,
+}
+
+/// Running sums for [`ChannelStats`].
+#[derive(Clone, Copy)]
+struct ChannelAccumulator {
+    sum: u64,
+    sum_sq: u64,
+    min: u8,
+    max: u8,
+}
+
+impl ChannelAccumulator {
+    fn new() -> Self {
+        Self {
+            sum: 0,
+            sum_sq: 0,
+            min: u8::MAX,
+            max: u8::MIN,
+        }
+    }
+
+    fn add(&mut self, value: u8) {
+        self.sum += value as u64;
+        self.sum_sq += value as u64 * value as u64;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn finish(self, count: u64) -> ChannelStats {
+        let n = count as f64;
+        let mean = self.sum as f64 / n;
+        let variance = (self.sum_sq as f64 / n - mean * mean).max(0.0);
+        ChannelStats {
+            mean: mean as f32,
+            min: self.min,
+            max: self.max,
+            stddev: variance.sqrt() as f32,
+        }
+    }
+}
+
+/// Sample the pixel under a point and the statistics of the disc around it.
+///
+/// The point is in normalized image coordinates (0.0 to 1.0 across the
+/// width and height), so the caller does not need to know the zoom level;
+/// it selects the pixel it falls in, and points outside the image select
+/// the nearest edge pixel. The disc holds every pixel whose center is
+/// within `radius` pixels of the selected pixel's center. Pixels outside
+/// the image are left out rather than padded, so near the border the
+/// statistics cover fewer pixels (see `sample_count`). A radius of zero
+/// samples the center pixel alone.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data (3 bytes per pixel, row-major order)
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `x`, `y` - Point in normalized image coordinates
+/// * `radius` - Radius of the neighborhood in pixels
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+pub fn sample_pixel_stats(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    x: f32,
+    y: f32,
+    radius: u32,
+) -> Result<PixelStats, ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    // Float-to-int casts saturate, and NaN becomes 0
+    let cx = ((x * width as f32).floor() as i64).clamp(0, width as i64 - 1);
+    let cy = ((y * height as f32).floor() as i64).clamp(0, height as i64 - 1);
+    // Any radius past the image size covers all of it
+    let r = radius.min(width + height) as i64;
+
+    let mut channels = [ChannelAccumulator::new(); 4];
+    let mut count = 0u64;
+    for py in (cy - r).max(0)..=(cy + r).min(height as i64 - 1) {
+        let dy = py - cy;
+        // Widest column offset still inside the disc on this row
+        let reach = ((r * r - dy * dy) as f64).sqrt() as i64;
+        for px in (cx - reach).max(0)..=(cx + reach).min(width as i64 - 1) {
+            let i = (py as usize * width as usize + px as usize) * 3;
+            let [red, green, blue] = [pixels[i], pixels[i + 1], pixels[i + 2]];
+            let luminance = calculate_luminance_u8(red, green, blue);
+            for (channel, value) in channels.iter_mut().zip([red, green, blue, luminance]) {
+                channel.add(value);
+            }
+            count += 1;
+        }
+    }
+
+    let i = (cy as usize * width as usize + cx as usize) * 3;
+    let center = [pixels[i], pixels[i + 1], pixels[i + 2]];
+    let [red, green, blue, luminance] = channels.map(|channel| channel.finish(count));
+    Ok(PixelStats {
+        x: cx as u32,
+        y: cy as u32,
+        center,
+        center_luminance: calculate_luminance_u8(center[0], center[1], center[2]),
+        sample_count: count as u32,
+        red,
+        green,
+        blue,
+        luminance,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::curve::apply_tone_curve;
+    use crate::mask::{BrushMask, StackMask, StripMask};
+    use crate::testing::{
+        checkerboard, color_bars, gradient, noise, GradientDirection, COLOR_BARS,
+    };
+    use crate::{CurvePoint, ToneCurve};
 
     #[test]
-    fn test_empty_histogram() {
+    fn test_empty_image_is_error() {
         let pixels: Vec<u8> = vec![];
-        let hist = compute_histogram(&pixels, 0, 0);
-        assert_eq!(hist.max_value(), 0);
+        assert!(matches!(
+            compute_histogram(&pixels, 0, 0),
+            Err(ImageError::EmptyImage {
+                width: 0,
+                height: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_buffer_mismatch_is_error() {
+        let pixels = vec![0u8; 9];
+        assert!(matches!(
+            compute_histogram(&pixels, 2, 2),
+            Err(ImageError::BufferSizeMismatch { .. })
+        ));
     }
 
     #[test]
     fn test_single_red_pixel() {
         let pixels = vec![255, 0, 0];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
         assert_eq!(hist.red[255], 1);
         assert_eq!(hist.green[0], 1);
         assert_eq!(hist.blue[0], 1);
@@ -90,7 +494,7 @@ mod tests {
     #[test]
     fn test_single_green_pixel() {
         let pixels = vec![0, 255, 0];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
         assert_eq!(hist.red[0], 1);
         assert_eq!(hist.green[255], 1);
         assert_eq!(hist.blue[0], 1);
@@ -101,7 +505,7 @@ mod tests {
     #[test]
     fn test_single_blue_pixel() {
         let pixels = vec![0, 0, 255];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
         assert_eq!(hist.red[0], 1);
         assert_eq!(hist.green[0], 1);
         assert_eq!(hist.blue[255], 1);
@@ -116,7 +520,7 @@ mod tests {
             0, 255, 0, // Green
             0, 0, 255, // Blue
         ];
-        let hist = compute_histogram(&pixels, 3, 1);
+        let hist = compute_histogram(&pixels, 3, 1).unwrap();
         assert_eq!(hist.red[255], 1);
         assert_eq!(hist.red[0], 2);
         assert_eq!(hist.green[255], 1);
@@ -128,7 +532,7 @@ mod tests {
     #[test]
     fn test_grayscale_midtone() {
         let pixels = vec![128, 128, 128];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
         assert_eq!(hist.red[128], 1);
         assert_eq!(hist.green[128], 1);
         assert_eq!(hist.blue[128], 1);
@@ -140,7 +544,7 @@ mod tests {
     #[test]
     fn test_white_pixel() {
         let pixels = vec![255, 255, 255];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
         assert_eq!(hist.red[255], 1);
         assert_eq!(hist.green[255], 1);
         assert_eq!(hist.blue[255], 1);
@@ -152,7 +556,7 @@ mod tests {
     #[test]
     fn test_black_pixel() {
         let pixels = vec![0, 0, 0];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
         assert_eq!(hist.red[0], 1);
         assert_eq!(hist.green[0], 1);
         assert_eq!(hist.blue[0], 1);
@@ -167,7 +571,7 @@ mod tests {
             100, 100, 100, 100, 100, 100, 100, 100, 100, 200, 200,
             200, // Only one bright pixel
         ];
-        let hist = compute_histogram(&pixels, 4, 1);
+        let hist = compute_histogram(&pixels, 4, 1).unwrap();
         assert_eq!(hist.red[100], 3);
         assert_eq!(hist.red[200], 1);
         assert_eq!(hist.max_value(), 3);
@@ -177,7 +581,7 @@ mod tests {
     fn test_large_image() {
         // 100x100 image = 10,000 pixels
         let pixels = vec![128u8; 100 * 100 * 3];
-        let hist = compute_histogram(&pixels, 100, 100);
+        let hist = compute_histogram(&pixels, 100, 100).unwrap();
         assert_eq!(hist.red[128], 10_000);
         assert_eq!(hist.green[128], 10_000);
         assert_eq!(hist.blue[128], 10_000);
@@ -194,7 +598,7 @@ mod tests {
             0, 0, 255, // Blue
             128, 128, 128, // Gray
         ];
-        let hist = compute_histogram(&pixels, 2, 2);
+        let hist = compute_histogram(&pixels, 2, 2).unwrap();
         assert_eq!(hist.red[255], 1);
         assert_eq!(hist.green[255], 1);
         assert_eq!(hist.blue[255], 1);
@@ -204,21 +608,15 @@ mod tests {
     #[test]
     fn test_no_clipping_midtone_image() {
         let pixels = vec![50, 60, 70, 100, 110, 120, 150, 160, 170, 200, 210, 220];
-        let hist = compute_histogram(&pixels, 4, 1);
+        let hist = compute_histogram(&pixels, 4, 1).unwrap();
         assert!(!hist.has_highlight_clipping());
         assert!(!hist.has_shadow_clipping());
     }
 
     #[test]
     fn test_gradient_image() {
-        // Create a simple gradient
-        let mut pixels = Vec::new();
-        for i in 0..=255 {
-            pixels.push(i as u8);
-            pixels.push(i as u8);
-            pixels.push(i as u8);
-        }
-        let hist = compute_histogram(&pixels, 256, 1);
+        let image = gradient(256, 1, GradientDirection::Horizontal);
+        let hist = compute_histogram(&image.pixels, 256, 1).unwrap();
 
         // Each bin should have exactly 1 pixel
         for i in 0..256 {
@@ -228,6 +626,309 @@ mod tests {
         }
         assert_eq!(hist.max_value(), 1);
     }
+
+    #[test]
+    fn test_color_bars() {
+        let image = color_bars(80, 10);
+        let hist = compute_histogram(&image.pixels, 80, 10).unwrap();
+
+        // Four bars have full red and four have none, 100 pixels each
+        assert_eq!(hist.red[255], 400);
+        assert_eq!(hist.red[0], 400);
+        assert_eq!(hist.green[255], 400);
+        assert_eq!(hist.blue[255], 400);
+        // Each bar has its own luminance
+        let luminance_bins = hist.luminance.iter().filter(|&&n| n > 0).count();
+        assert_eq!(luminance_bins, COLOR_BARS.len());
+    }
+
+    #[test]
+    fn test_checkerboard_is_bimodal() {
+        let image = checkerboard(16, 16, 4);
+        let hist = compute_histogram(&image.pixels, 16, 16).unwrap();
+
+        assert_eq!(hist.luminance[0], 128);
+        assert_eq!(hist.luminance[255], 128);
+        assert!(hist.has_shadow_clipping());
+        assert!(hist.has_highlight_clipping());
+    }
+
+    #[test]
+    fn test_f32_histogram_matches_u8() {
+        let image = gradient(64, 32, GradientDirection::Diagonal);
+        let float = image.to_f32();
+        let expected = compute_histogram(&image.pixels, 64, 32).unwrap();
+        let hist = compute_histogram_f32(&float.pixels, 64, 32, 0.0, 1.0).unwrap();
+        assert_eq!(hist.red, expected.red);
+        assert_eq!(hist.green, expected.green);
+        assert_eq!(hist.blue, expected.blue);
+        assert_eq!(hist.luminance, expected.luminance);
+
+        // An invalid range falls back to 0.0-1.0
+        let fallback = compute_histogram_f32(&float.pixels, 64, 32, 1.0, 1.0).unwrap();
+        assert_eq!(fallback.red, expected.red);
+    }
+
+    #[test]
+    fn test_f32_histogram_range() {
+        // 0.5, 2.0 and 4.0 with a 0-4 range
+        let pixels = vec![0.5, 0.5, 0.5, 2.0, 2.0, 2.0, 4.0, 4.0, 4.0];
+        let hist = compute_histogram_f32(&pixels, 3, 1, 0.0, 4.0).unwrap();
+        assert_eq!(hist.red[32], 1);
+        assert_eq!(hist.red[128], 1);
+        assert_eq!(hist.red[255], 1);
+
+        let hist = compute_histogram_f32(&pixels, 3, 1, 0.0, 1.0).unwrap();
+        assert_eq!(hist.red[255], 2);
+    }
+
+    fn gray_ramp(max: u8) -> Vec<u8> {
+        (0..=max).flat_map(|v| [v, v, v]).collect()
+    }
+
+    #[test]
+    fn test_delta_of_identical_histograms_is_zero() {
+        let image = color_bars(64, 16);
+        let hist = compute_histogram(&image.pixels, 64, 16).unwrap();
+        let delta = compute_histogram_delta(&hist, &hist);
+        assert_eq!(delta.luminance.len(), 256);
+        assert!(delta.luminance.iter().all(|&d| d == 0.0));
+        assert_eq!(delta.median_shift, 0);
+        assert_eq!(delta.mean_shift, 0.0);
+    }
+
+    #[test]
+    fn test_delta_of_one_stop_brighter_edit() {
+        use crate::adjustments::apply_all_adjustments;
+        use crate::BasicAdjustments;
+
+        let original = gray_ramp(127);
+        let mut edited = original.clone();
+        apply_all_adjustments(
+            &mut edited,
+            &BasicAdjustments {
+                exposure: 1.0,
+                ..Default::default()
+            },
+        );
+        let before = compute_histogram(&original, 128, 1).unwrap();
+        let after = compute_histogram(&edited, 128, 1).unwrap();
+        let delta = compute_histogram_delta(&before, &after);
+
+        // One stop doubles every value, and with it the median and mean.
+        assert_eq!(delta.median_before, 63);
+        assert!(delta.median_after.abs_diff(126) <= 1, "{:?}", delta);
+        assert!((delta.median_shift - 63).abs() <= 1);
+        assert!((delta.mean_shift - delta.mean_before).abs() < 1.0);
+        assert!(delta.luminance[..64].iter().sum::<f32>() < 0.0);
+    }
+
+    #[test]
+    fn test_delta_normalizes_different_sizes() {
+        let small = compute_histogram(&gray_ramp(99), 100, 1).unwrap();
+        let image = checkerboard(40, 30, 5);
+        let large = compute_histogram(&image.pixels, 40, 30).unwrap();
+
+        for bins in [&large.red, &large.green, &large.blue, &large.luminance] {
+            let sum: f32 = bin_fractions(bins).iter().sum();
+            assert!((sum - 1.0).abs() < 1e-4, "{}", sum);
+        }
+        // Differences of two distributions that each sum to 1 cancel out.
+        let delta = compute_histogram_delta(&small, &large);
+        assert!(delta.luminance.iter().sum::<f32>().abs() < 1e-4);
+        assert!(delta.luminance.iter().all(|d| d.abs() <= 1.0));
+        assert_eq!(bin_fractions(&[0; 256]), vec![0.0; 256]);
+    }
+
+    fn channels(hist: &Histogram) -> [[u32; 256]; 4] {
+        [hist.red, hist.green, hist.blue, hist.luminance]
+    }
+
+    #[test]
+    fn test_lut_histogram_matches_curved_image() {
+        let image = color_bars(64, 16);
+        let curves = [
+            ToneCurve::default(),
+            ToneCurve {
+                points: vec![
+                    CurvePoint::new(0.0, 0.0),
+                    CurvePoint::new(0.25, 0.4),
+                    CurvePoint::new(0.75, 0.8),
+                    CurvePoint::new(1.0, 1.0),
+                ],
+            },
+            ToneCurve {
+                points: vec![CurvePoint::new(0.2, 0.0), CurvePoint::new(0.8, 1.0)],
+            },
+            // Inverting
+            ToneCurve {
+                points: vec![CurvePoint::new(0.0, 1.0), CurvePoint::new(1.0, 0.0)],
+            },
+        ];
+
+        for curve in curves {
+            let lut = ToneCurveLut::from_curve(&curve);
+            let mut curved = image.pixels.clone();
+            apply_tone_curve(&mut curved, &lut);
+
+            let expected = compute_histogram(&curved, 64, 16).unwrap();
+            let hist = compute_histogram_with_lut(&image.pixels, 64, 16, &lut).unwrap();
+            assert_eq!(channels(&hist), channels(&expected), "{:?}", curve);
+        }
+    }
+
+    #[test]
+    fn test_identity_lut_histogram_matches_plain() {
+        let image = gradient(256, 4, GradientDirection::Horizontal);
+        let plain = compute_histogram(&image.pixels, 256, 4).unwrap();
+        let hist =
+            compute_histogram_with_lut(&image.pixels, 256, 4, &ToneCurveLut::identity()).unwrap();
+        assert_eq!(channels(&hist), channels(&plain));
+
+        assert!(matches!(
+            compute_histogram_with_lut(&[0; 5], 2, 1, &ToneCurveLut::identity()),
+            Err(ImageError::BufferSizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pixel_stats_on_gradient_match_expectation() {
+        // Each column holds its own x value
+        let image = gradient(256, 64, GradientDirection::Horizontal);
+        let stats = sample_pixel_stats(&image.pixels, 256, 64, 100.5 / 256.0, 0.5, 10).unwrap();
+
+        assert_eq!((stats.x, stats.y), (100, 32));
+        assert_eq!(stats.center, [100, 100, 100]);
+        for channel in [stats.red, stats.green, stats.blue, stats.luminance] {
+            // The disc is symmetric about the center column
+            assert_eq!(channel.mean, 100.0);
+            assert_eq!((channel.min, channel.max), (90, 110));
+            // x over a disc of radius R has a standard deviation of R/2
+            assert!((channel.stddev - 5.0).abs() < 0.1, "{}", channel.stddev);
+        }
+        // Close to the area of the disc
+        assert!((stats.sample_count as f32 - std::f32::consts::PI * 100.0).abs() < 10.0);
+    }
+
+    #[test]
+    fn test_pixel_stats_clipped_by_border_use_in_bounds_pixels() {
+        let image = gradient(256, 64, GradientDirection::Horizontal);
+        let stats = sample_pixel_stats(&image.pixels, 256, 64, 0.0, 0.5, 2).unwrap();
+
+        // Right half of a radius-2 disc: 5 pixels at x=0, 3 at x=1, 1 at x=2
+        assert_eq!(stats.x, 0);
+        assert_eq!(stats.sample_count, 9);
+        assert!((stats.red.mean - 5.0 / 9.0).abs() < 1e-6);
+        assert_eq!((stats.red.min, stats.red.max), (0, 2));
+
+        // Points past the edge select the edge pixel
+        let corner = sample_pixel_stats(&image.pixels, 256, 64, 1.5, -0.2, 1).unwrap();
+        assert_eq!((corner.x, corner.y), (255, 0));
+        assert_eq!(corner.sample_count, 3);
+        assert!((corner.red.mean - (255.0 + 255.0 + 254.0) / 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_pixel_stats_radius_zero_is_center_pixel() {
+        let image = crate::testing::noise(32, 24, 9);
+        let stats = sample_pixel_stats(&image.pixels, 32, 24, 0.4, 0.7, 0).unwrap();
+
+        let i = (stats.y as usize * 32 + stats.x as usize) * 3;
+        assert_eq!((stats.x, stats.y), (12, 16));
+        assert_eq!(stats.center, image.pixels[i..i + 3]);
+        assert_eq!(stats.sample_count, 1);
+        let [r, g, b] = stats.center;
+        for (channel, value) in [stats.red, stats.green, stats.blue, stats.luminance]
+            .into_iter()
+            .zip([r, g, b, stats.center_luminance])
+        {
+            assert_eq!(channel.mean, value as f32);
+            assert_eq!((channel.min, channel.max), (value, value));
+            assert_eq!(channel.stddev, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_pixel_stats_rejects_invalid_buffer() {
+        assert!(matches!(
+            sample_pixel_stats(&[0; 5], 2, 1, 0.5, 0.5, 1),
+            Err(ImageError::BufferSizeMismatch { .. })
+        ));
+        // A huge radius covers the whole image
+        let image = checkerboard(8, 8, 1);
+        let stats = sample_pixel_stats(&image.pixels, 8, 8, 0.5, 0.5, u32::MAX).unwrap();
+        assert_eq!(stats.sample_count, 64);
+        assert_eq!(stats.red.mean, 127.5);
+    }
+
+    fn masked<M>(id: &str, mask: M) -> MaskEntry
+    where
+        StackMask<M>: Into<MaskEntry>,
+    {
+        StackMask {
+            id: Some(id.to_string()),
+            enabled: true,
+            mask,
+            adjustments: Default::default(),
+            curve: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_masked_histogram_of_hard_edged_half() {
+        let image = noise(8, 6, 7);
+        // Hard-edged band over the left four columns
+        let left = masked("left", StripMask::new(0.25, 0.0, 0.25, 1.0, 0.25, 0.0));
+        let hist =
+            compute_masked_histogram(&image.pixels, 8, 6, &[left], MaskTarget::Id("left")).unwrap();
+
+        let half: Vec<u8> = image
+            .pixels
+            .chunks_exact(8 * 3)
+            .flat_map(|row| &row[..4 * 3])
+            .copied()
+            .collect();
+        let expected = compute_histogram(&half, 4, 6).unwrap();
+        assert_eq!(hist.red, expected.red);
+        assert_eq!(hist.green, expected.green);
+        assert_eq!(hist.blue, expected.blue);
+        assert_eq!(hist.luminance, expected.luminance);
+    }
+
+    #[test]
+    fn test_masked_histogram_scales_with_uniform_strength() {
+        let image = color_bars(64, 16);
+        let half = masked("half", BrushMask::from_alpha(2, 2, vec![128; 4]).unwrap());
+        let hist =
+            compute_masked_histogram(&image.pixels, 64, 16, &[half], MaskTarget::Index(0)).unwrap();
+        let full = compute_histogram(&image.pixels, 64, 16).unwrap();
+
+        let strength = 128.0 / 255.0;
+        for (masked, full) in [(hist.red, full.red), (hist.luminance, full.luminance)] {
+            for (&m, &f) in masked.iter().zip(&full) {
+                assert!((m as f32 - f as f32 * strength).abs() <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_masked_histogram_rejects_unknown_mask() {
+        let pixels = [0u8; 12];
+        let stack = [masked("a", StripMask::new(0.0, 0.5, 1.0, 0.5, 0.1, 0.0))];
+        assert!(matches!(
+            compute_masked_histogram(&pixels, 2, 2, &stack, MaskTarget::Index(1)),
+            Err(MaskError::IndexOutOfRange { index: 1, len: 1 })
+        ));
+        assert!(matches!(
+            compute_masked_histogram(&pixels, 2, 2, &stack, MaskTarget::Id("b")),
+            Err(MaskError::UnknownId { .. })
+        ));
+        assert!(matches!(
+            compute_masked_histogram(&pixels, 2, 1, &stack, MaskTarget::Index(0)),
+            Err(MaskError::InvalidImage(_))
+        ));
+    }
 }
 
 // ============================================================================
@@ -262,7 +963,7 @@ mod proptests {
         /// Property: Total count in each channel equals pixel count.
         #[test]
         fn prop_histogram_count_equals_pixel_count((pixels, width, height) in small_image_strategy()) {
-            let hist = compute_histogram(&pixels, width, height);
+            let hist = compute_histogram(&pixels, width, height).unwrap();
             let pixel_count = (width * height) as u64;
 
             let red_total: u64 = hist.red.iter().map(|&c| c as u64).sum();
@@ -279,7 +980,7 @@ mod proptests {
         /// Property: Histogram bins are always non-negative (trivially true for u32, but tests no overflow).
         #[test]
         fn prop_bins_are_non_negative((pixels, width, height) in small_image_strategy()) {
-            let hist = compute_histogram(&pixels, width, height);
+            let hist = compute_histogram(&pixels, width, height).unwrap();
 
             for i in 0..256 {
                 prop_assert!(hist.red[i] >= 0, "Red bin {} is negative", i);
@@ -294,7 +995,7 @@ mod proptests {
         /// since luminance is typically displayed separately in histograms.
         #[test]
         fn prop_max_value_is_correct((pixels, width, height) in small_image_strategy()) {
-            let hist = compute_histogram(&pixels, width, height);
+            let hist = compute_histogram(&pixels, width, height).unwrap();
 
             // max_value() only considers RGB channels (not luminance)
             let expected_max = hist.red.iter()
@@ -317,32 +1018,30 @@ mod proptests {
                 pixels.push(v);
             }
 
-            let hist = compute_histogram(&pixels, count as u32, 1);
+            let hist = compute_histogram(&pixels, count as u32, 1).unwrap();
 
             prop_assert_eq!(hist.red[v as usize], count as u32);
             prop_assert_eq!(hist.green[v as usize], count as u32);
             prop_assert_eq!(hist.blue[v as usize], count as u32);
         }
 
-        /// Property: Empty pixel array produces zero histogram.
+        /// Property: Zero width or height is always an error, never a panic.
         #[test]
-        fn prop_empty_produces_zero_histogram(_dummy in 0..1i32) {
-            let pixels: Vec<u8> = vec![];
-            let hist = compute_histogram(&pixels, 0, 0);
+        fn prop_zero_dimension_is_error(len in 0usize..30, other in 0u32..10, zero_width: bool) {
+            let pixels = vec![0u8; len];
+            let (width, height) = if zero_width { (0, other) } else { (other, 0) };
 
-            prop_assert_eq!(hist.max_value(), 0);
-            for i in 0..256 {
-                prop_assert_eq!(hist.red[i], 0);
-                prop_assert_eq!(hist.green[i], 0);
-                prop_assert_eq!(hist.blue[i], 0);
-                prop_assert_eq!(hist.luminance[i], 0);
-            }
+            let is_empty_error = matches!(
+                compute_histogram(&pixels, width, height),
+                Err(ImageError::EmptyImage { .. })
+            );
+            prop_assert!(is_empty_error);
         }
 
         /// Property: Clipping detection is consistent with bin values.
         #[test]
         fn prop_clipping_detection_consistent((pixels, width, height) in small_image_strategy()) {
-            let hist = compute_histogram(&pixels, width, height);
+            let hist = compute_histogram(&pixels, width, height).unwrap();
 
             // Highlight clipping should be true iff any channel has non-zero count at index 255
             let has_highlight = hist.red[255] > 0 || hist.green[255] > 0 || hist.blue[255] > 0;
@@ -364,8 +1063,8 @@ mod proptests {
         /// Property: Histogram computation is deterministic.
         #[test]
         fn prop_deterministic((pixels, width, height) in small_image_strategy()) {
-            let hist1 = compute_histogram(&pixels, width, height);
-            let hist2 = compute_histogram(&pixels, width, height);
+            let hist1 = compute_histogram(&pixels, width, height).unwrap();
+            let hist2 = compute_histogram(&pixels, width, height).unwrap();
 
             for i in 0..256 {
                 prop_assert_eq!(hist1.red[i], hist2.red[i]);
@@ -379,7 +1078,7 @@ mod proptests {
         #[test]
         fn prop_single_pixel(r in 0u8..=255, g in 0u8..=255, b in 0u8..=255) {
             let pixels = vec![r, g, b];
-            let hist = compute_histogram(&pixels, 1, 1);
+            let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
             // Each channel should have exactly 1 entry
             let red_total: u32 = hist.red.iter().sum();
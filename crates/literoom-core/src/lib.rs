@@ -4,20 +4,54 @@
 //! including RAW decoding, edit pipeline, histogram computation, and more.
 
 pub mod adjustments;
+pub mod analysis;
+pub mod base_curve;
+pub mod burst;
+pub mod color;
+pub mod color_match;
+pub mod compose;
 pub mod curve;
 pub mod decode;
+pub mod draw;
+pub mod edit;
 pub mod encode;
+pub mod enhance;
+pub mod equalize;
+pub mod export;
+pub mod export_manifest;
 pub mod histogram;
 pub mod luminance;
+pub mod lut3d;
 pub mod mask;
+pub mod monochrome;
+pub mod noise_reduction;
+pub mod perf;
+pub mod phash;
+pub mod pipeline;
+pub mod ranges;
+pub mod recipe;
+pub mod render;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
 pub mod transform;
+pub mod validate;
+pub mod variants;
 
-pub use curve::{apply_tone_curve, evaluate_curve, ToneCurveLut};
+pub use curve::{
+    apply_tone_curve, apply_tone_curve_f32, evaluate_curve, LevelsAndCurve, ToneCurveLut,
+};
 pub use mask::{apply_masked_adjustments, LinearGradientMask, RadialGradientMask};
+pub use ranges::AdjustmentError;
 pub use transform::{apply_crop, apply_rotation, compute_rotated_bounds, InterpolationFilter};
+pub use validate::ImageError;
 
 /// Basic adjustments for image editing
+///
+/// Valid ranges for each field are defined in the [`ranges`] module. Missing
+/// fields deserialize to zero, so partial objects (such as relative deltas)
+/// are accepted.
 #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct BasicAdjustments {
     /// White balance temperature (-100 to 100)
     pub temperature: f32,
@@ -39,6 +73,12 @@ pub struct BasicAdjustments {
     pub vibrance: f32,
     /// Saturation (-100 to 100)
     pub saturation: f32,
+    /// Hold back saturation and vibrance on skin tones, so pushing them does
+    /// not turn faces orange
+    pub protect_skin_tones: bool,
+    /// Color model saturation and vibrance work in. When rendering an edit
+    /// state, masks follow the global adjustments' model.
+    pub color_model: color::ColorModel,
 }
 
 impl BasicAdjustments {
@@ -53,8 +93,61 @@ impl BasicAdjustments {
     }
 }
 
+/// Split white balance: separate temperature and tint for shadows and highlights.
+///
+/// Kept apart from `BasicAdjustments` because most edits never use it. Values
+/// use the same -100 to 100 scale as the global temperature and tint.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SplitWhiteBalance {
+    /// Temperature applied to dark areas (-100 to 100)
+    pub shadow_temperature: f32,
+    /// Tint applied to dark areas (-100 to 100)
+    pub shadow_tint: f32,
+    /// Temperature applied to bright areas (-100 to 100)
+    pub highlight_temperature: f32,
+    /// Tint applied to bright areas (-100 to 100)
+    pub highlight_tint: f32,
+}
+
+impl SplitWhiteBalance {
+    /// Create a new SplitWhiteBalance with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if all values are at their defaults
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// White balance expressed either as relative sliders or in Kelvin.
+///
+/// `Relative` uses the unitless -100 to 100 temperature scale of
+/// `BasicAdjustments`. `Absolute` sets a color temperature in Kelvin
+/// (2500 to 10000 in the UI), which RAW workflows expect alongside the
+/// as-shot value from metadata. Serializes as
+/// `{ "mode": "relative" | "absolute", ... }`.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum WhiteBalance {
+    /// Unitless temperature and tint (-100 to 100)
+    Relative { temperature: f32, tint: f32 },
+    /// Color temperature in Kelvin and tint (-100 to 100)
+    Absolute { kelvin: f32, tint: f32 },
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        WhiteBalance::Relative {
+            temperature: 0.0,
+            tint: 0.0,
+        }
+    }
+}
+
 /// Tone curve control point
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct CurvePoint {
     /// Input value (0.0 to 1.0)
     pub x: f32,
@@ -69,7 +162,7 @@ impl CurvePoint {
 }
 
 /// Tone curve with control points
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ToneCurve {
     /// Control points for the curve (sorted by x)
     pub points: Vec<CurvePoint>,
@@ -12,6 +12,10 @@ pub const LUMINANCE_G: f32 = 0.7152;
 /// ITU-R BT.709 coefficient for blue channel in luminance calculation.
 pub const LUMINANCE_B: f32 = 0.0722;
 
+/// The BT.709 coefficients as `[red, green, blue]` weights, for
+/// [`DecodedImage::to_gray`](crate::decode::DecodedImage::to_gray).
+pub const LUMA_WEIGHTS: [f32; 3] = [LUMINANCE_R, LUMINANCE_G, LUMINANCE_B];
+
 /// Calculate luminance from normalized RGB values (0.0 to 1.0).
 ///
 /// Uses ITU-R BT.709 coefficients for accurate perceptual luminance.
@@ -0,0 +1,465 @@
+//! 3D color lookup tables from `.cube` files.
+//!
+//! Film-emulation packs and grading tools ship their looks as `.cube` files:
+//! a lattice of `size³` output colors over the RGB cube, with the red index
+//! changing fastest. [`parse_cube_lut`] reads the text format and
+//! [`apply_lut3d`] maps pixels through the lattice with trilinear
+//! interpolation, blended with the original by a strength.
+//!
+//! The edit state refers to a LUT by an id the app resolves to its file
+//! (see [`crate::edit::LutReference`]); the lattice itself is not stored
+//! with the edit.
+
+use crate::perf;
+use thiserror::Error;
+
+/// Smallest lattice size: one cell spanning the whole cube.
+pub const MIN_LUT_SIZE: usize = 2;
+
+/// Largest lattice size accepted.
+///
+/// Files are typically 17, 33 or 65 points per side; a 128-point lattice
+/// already holds two million entries.
+pub const MAX_LUT_SIZE: usize = 128;
+
+/// Errors from [`parse_cube_lut`].
+///
+/// Problems with a particular line carry its 1-based number.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum LutError {
+    /// A line could not be read: a malformed keyword, a non-numeric value or
+    /// a data line in the wrong place.
+    #[error("Line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    /// `LUT_3D_SIZE` is outside [`MIN_LUT_SIZE`] to [`MAX_LUT_SIZE`].
+    #[error("Line {line}: LUT_3D_SIZE {size} is out of range ({MIN_LUT_SIZE} to {MAX_LUT_SIZE})")]
+    InvalidSize { line: usize, size: usize },
+
+    /// The file has no `LUT_3D_SIZE` line.
+    #[error("Missing LUT_3D_SIZE")]
+    MissingSize,
+
+    /// The file ends before the lattice is complete.
+    #[error("Expected {expected} lattice entries, found {found}")]
+    MissingEntries { expected: usize, found: usize },
+}
+
+impl LutError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LutError::Syntax { .. } => "LUT_SYNTAX",
+            LutError::InvalidSize { .. } => "LUT_INVALID_SIZE",
+            LutError::MissingSize => "LUT_MISSING_SIZE",
+            LutError::MissingEntries { .. } => "LUT_MISSING_ENTRIES",
+        }
+    }
+
+    /// 1-based line of the file where the problem is, if it is on one line.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            LutError::Syntax { line, .. } | LutError::InvalidSize { line, .. } => Some(*line),
+            LutError::MissingSize | LutError::MissingEntries { .. } => None,
+        }
+    }
+}
+
+/// A 3D lookup table: `size³` RGB outputs over the input domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    /// Title from the file, if any
+    pub title: Option<String>,
+    size: usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    /// Outputs with the red index changing fastest, then green, then blue
+    table: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    /// Create a LUT from its lattice over the 0.0-1.0 domain.
+    ///
+    /// `table` holds `size³` outputs with the red index changing fastest, as
+    /// in a `.cube` file. Returns `None` if the size is out of range or the
+    /// table has the wrong length.
+    pub fn new(size: usize, table: Vec<[f32; 3]>) -> Option<Self> {
+        if !(MIN_LUT_SIZE..=MAX_LUT_SIZE).contains(&size) || table.len() != size.pow(3) {
+            return None;
+        }
+        Some(Self {
+            title: None,
+            size,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            table,
+        })
+    }
+
+    /// The identity LUT of a given size, which maps every color to itself.
+    ///
+    /// Returns `None` if the size is out of range.
+    pub fn identity(size: usize) -> Option<Self> {
+        if !(MIN_LUT_SIZE..=MAX_LUT_SIZE).contains(&size) {
+            return None;
+        }
+        let step = 1.0 / (size - 1) as f32;
+        let table = (0..size.pow(3))
+            .map(|i| {
+                let (r, g, b) = (i % size, i / size % size, i / (size * size));
+                [r as f32 * step, g as f32 * step, b as f32 * step]
+            })
+            .collect();
+        Self::new(size, table)
+    }
+
+    /// Points per side of the lattice.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Output for a normalized input color, interpolated trilinearly between
+    /// the eight surrounding lattice points.
+    ///
+    /// Inputs outside the domain are clamped to it.
+    pub fn sample(&self, r: f32, g: f32, b: f32) -> [f32; 3] {
+        let (r0, tr) = self.cell(0, r);
+        let (g0, tg) = self.cell(1, g);
+        let (b0, tb) = self.cell(2, b);
+        self.interpolate([r0, g0, b0], [tr, tg, tb])
+    }
+
+    /// Lattice cell (its lower index) and position within it along one
+    /// channel.
+    fn cell(&self, channel: usize, value: f32) -> (usize, f32) {
+        let (min, max) = (self.domain_min[channel], self.domain_max[channel]);
+        let t = ((value - min) / (max - min)).clamp(0.0, 1.0);
+        // NaN lands on the first lattice point
+        let t = if t.is_nan() { 0.0 } else { t };
+        let x = t * (self.size - 1) as f32;
+        let i = (x as usize).min(self.size - 2);
+        (i, x - i as f32)
+    }
+
+    fn interpolate(&self, [r, g, b]: [usize; 3], [tr, tg, tb]: [f32; 3]) -> [f32; 3] {
+        let n = self.size;
+        let at = |dr: usize, dg: usize, db: usize| {
+            self.table[(r + dr) + (g + dg) * n + (b + db) * n * n]
+        };
+        let lerp = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] {
+            std::array::from_fn(|c| a[c] + (b[c] - a[c]) * t)
+        };
+        let c00 = lerp(at(0, 0, 0), at(1, 0, 0), tr);
+        let c10 = lerp(at(0, 1, 0), at(1, 1, 0), tr);
+        let c01 = lerp(at(0, 0, 1), at(1, 0, 1), tr);
+        let c11 = lerp(at(0, 1, 1), at(1, 1, 1), tr);
+        lerp(lerp(c00, c10, tg), lerp(c01, c11, tg), tb)
+    }
+}
+
+/// Parse the text of a `.cube` file.
+///
+/// Reads `TITLE`, `LUT_3D_SIZE`, `DOMAIN_MIN` and `DOMAIN_MAX` (and the
+/// older `LUT_3D_INPUT_RANGE`), then `size³` lines of three output values.
+/// Blank lines and `#` comments are skipped anywhere. Other keywords, such as
+/// those some tools add for their own use, are ignored; a 1D LUT is
+/// rejected.
+///
+/// # Errors
+///
+/// Returns `LutError::Syntax` with the line number for a malformed line,
+/// `LutError::InvalidSize` for an absurd lattice size,
+/// `LutError::MissingSize` if no size is given, and
+/// `LutError::MissingEntries` if the file ends early.
+///
+/// # Example
+///
+/// ```
+/// use literoom_core::lut3d::parse_cube_lut;
+///
+/// let text = "LUT_3D_SIZE 2\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+/// let lut = parse_cube_lut(text).unwrap();
+/// assert_eq!(lut.size(), 2);
+/// ```
+pub fn parse_cube_lut(text: &str) -> Result<Lut3d, LutError> {
+    let mut title = None;
+    let mut size: Option<usize> = None;
+    let mut domain_min = [0.0; 3];
+    let mut domain_max = [1.0; 3];
+    // Line of the last domain keyword, to report an empty domain on
+    let mut domain_line = 0;
+    let mut table = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let content = raw.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let syntax = |message: String| LutError::Syntax { line, message };
+        let (keyword, rest) = content
+            .split_once(char::is_whitespace)
+            .map_or((content, ""), |(k, r)| (k, r.trim()));
+        let is_data = keyword.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c));
+        if !is_data && !table.is_empty() {
+            return Err(syntax(format!("{} after the lattice entries", keyword)));
+        }
+
+        match keyword {
+            _ if is_data => {
+                let Some(size) = size else {
+                    return Err(syntax("lattice entry before LUT_3D_SIZE".to_string()));
+                };
+                if table.len() == size * size * size {
+                    return Err(syntax(format!("more than {} lattice entries", size.pow(3))));
+                }
+                table.push(parse_triple(content).map_err(syntax)?);
+            }
+            "TITLE" => title = Some(rest.trim_matches('"').to_string()),
+            "LUT_3D_SIZE" => {
+                let value = rest
+                    .parse::<usize>()
+                    .map_err(|_| syntax(format!("invalid LUT_3D_SIZE '{}'", rest)))?;
+                if !(MIN_LUT_SIZE..=MAX_LUT_SIZE).contains(&value) {
+                    return Err(LutError::InvalidSize { line, size: value });
+                }
+                size = Some(value);
+            }
+            "DOMAIN_MIN" => {
+                domain_min = parse_triple(rest).map_err(syntax)?;
+                domain_line = line;
+            }
+            "DOMAIN_MAX" => {
+                domain_max = parse_triple(rest).map_err(syntax)?;
+                domain_line = line;
+            }
+            "LUT_3D_INPUT_RANGE" => {
+                let [min, max] = parse_values::<2>(rest).map_err(syntax)?;
+                domain_min = [min; 3];
+                domain_max = [max; 3];
+                domain_line = line;
+            }
+            "LUT_1D_SIZE" => return Err(syntax("1D LUTs are not supported".to_string())),
+            _ => {}
+        }
+    }
+
+    if domain_min
+        .iter()
+        .zip(&domain_max)
+        .any(|(min, max)| min >= max)
+    {
+        return Err(LutError::Syntax {
+            line: domain_line,
+            message: "DOMAIN_MIN must be below DOMAIN_MAX".to_string(),
+        });
+    }
+    let size = size.ok_or(LutError::MissingSize)?;
+    if table.len() != size.pow(3) {
+        return Err(LutError::MissingEntries {
+            expected: size.pow(3),
+            found: table.len(),
+        });
+    }
+    Ok(Lut3d {
+        title,
+        size,
+        domain_min,
+        domain_max,
+        table,
+    })
+}
+
+fn parse_triple(text: &str) -> Result<[f32; 3], String> {
+    parse_values::<3>(text)
+}
+
+/// Exactly `N` finite numbers separated by whitespace.
+fn parse_values<const N: usize>(text: &str) -> Result<[f32; N], String> {
+    let values = text
+        .split_whitespace()
+        .map(|v| {
+            v.parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("invalid number '{}'", v))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    values
+        .try_into()
+        .map_err(|values: Vec<f32>| format!("expected {} values, found {}", N, values.len()))
+}
+
+/// Map RGB pixels through a 3D LUT in place.
+///
+/// Each pixel moves by `strength` times the change the LUT makes to it, so
+/// 0.0 leaves the pixels alone and 1.0 applies the full look. `strength` is
+/// clamped to 0.0-1.0; NaN counts as 0.0. Outputs are clamped to the 0-255
+/// range.
+pub fn apply_lut3d(pixels: &mut [u8], lut: &Lut3d, strength: f32) {
+    let strength = if strength.is_nan() {
+        0.0
+    } else {
+        strength.clamp(0.0, 1.0)
+    };
+    if strength == 0.0 {
+        return;
+    }
+
+    let _perf = perf::scope("apply_lut3d");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    // Lattice cell and offset of every 8-bit value, per channel
+    let cells: [Vec<(usize, f32)>; 3] =
+        std::array::from_fn(|c| (0..=255).map(|v| lut.cell(c, v as f32 / 255.0)).collect());
+
+    for pixel in pixels.chunks_exact_mut(3) {
+        let [(r, tr), (g, tg), (b, tb)] = [0, 1, 2].map(|c| cells[c][pixel[c] as usize]);
+        let mapped = lut.interpolate([r, g, b], [tr, tg, tb]);
+        for (out, value) in pixel.iter_mut().zip(mapped) {
+            let original = *out as f32;
+            let target = value * 255.0;
+            *out = (original + (target - original) * strength)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::noise;
+
+    /// `.cube` text for a lattice whose outputs are `f` of the lattice
+    /// coordinates.
+    fn cube_text(size: usize, f: impl Fn([f32; 3]) -> [f32; 3]) -> String {
+        let mut text = format!("# generated\nTITLE \"test\"\nLUT_3D_SIZE {}\n\n", size);
+        let step = 1.0 / (size - 1) as f32;
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let [x, y, z] = f([r as f32 * step, g as f32 * step, b as f32 * step]);
+                    text.push_str(&format!("{:.6} {:.6} {:.6}\n", x, y, z));
+                }
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn test_identity_is_a_no_op_at_any_strength() {
+        let image = noise(32, 32, 5);
+        for size in [2, 17, 33] {
+            let lut = parse_cube_lut(&cube_text(size, |rgb| rgb)).unwrap();
+            assert_eq!(lut.title.as_deref(), Some("test"));
+            for strength in [0.0, 0.3, 1.0] {
+                let mut pixels = image.pixels.clone();
+                apply_lut3d(&mut pixels, &lut, strength);
+                assert_eq!(pixels, image.pixels, "size {} strength {}", size, strength);
+            }
+        }
+    }
+
+    #[test]
+    fn test_channel_swap_maps_exactly() {
+        let lut = parse_cube_lut(&cube_text(17, |[r, g, b]| [b, r, g])).unwrap();
+        let mut pixels = vec![255, 0, 0, 10, 128, 250, 17, 34, 51];
+        apply_lut3d(&mut pixels, &lut, 1.0);
+        assert_eq!(pixels, [0, 255, 0, 250, 10, 128, 51, 17, 34]);
+    }
+
+    #[test]
+    fn test_strength_blends_with_original() {
+        let lut = parse_cube_lut(&cube_text(2, |[r, g, b]| [1.0 - r, 1.0 - g, 1.0 - b])).unwrap();
+        let mut pixels = vec![0, 255, 0];
+        apply_lut3d(&mut pixels, &lut, 0.5);
+        assert_eq!(pixels, [128, 128, 128]);
+
+        let mut pixels = vec![0, 100, 255];
+        apply_lut3d(&mut pixels, &lut, f32::NAN);
+        assert_eq!(pixels, [0, 100, 255]);
+    }
+
+    #[test]
+    fn test_trilinear_between_lattice_points() {
+        // Red output is 1 only at the white corner, so it equals r * g * b;
+        // green is 1 only at the red corner, so it equals r * (1 - g) * (1 - b)
+        let lut = parse_cube_lut(&cube_text(2, |[r, g, b]| {
+            let white = r * g * b;
+            let red = r * (1.0 - g) * (1.0 - b);
+            [white.round(), red.round(), 0.5]
+        }))
+        .unwrap();
+
+        let [r, g, b] = lut.sample(0.2, 0.4, 0.8);
+        assert!((r - 0.064).abs() < 1e-6, "{}", r);
+        assert!((g - 0.024).abs() < 1e-6, "{}", g);
+        assert!((b - 0.5).abs() < 1e-6, "{}", b);
+
+        let [r, g, _] = lut.sample(0.5, 0.5, 0.5);
+        assert!((r - 0.125).abs() < 1e-6, "{}", r);
+        assert!((g - 0.125).abs() < 1e-6, "{}", g);
+    }
+
+    #[test]
+    fn test_domain_rescales_input() {
+        let mut text = cube_text(2, |rgb| rgb);
+        text = text.replace(
+            "LUT_3D_SIZE 2",
+            "LUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2",
+        );
+        let lut = parse_cube_lut(&text).unwrap();
+        let [r, g, b] = lut.sample(1.0, 0.5, 4.0);
+        assert!((r - 0.5).abs() < 1e-6);
+        assert!((g - 0.25).abs() < 1e-6);
+        assert!((b - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_malformed_files_report_the_line() {
+        let text = "# comment\nLUT_3D_SIZE 2\n0 0 0\n1 0 x\n";
+        let err = parse_cube_lut(text).unwrap_err();
+        assert_eq!(err.line(), Some(4));
+        assert_eq!(err.code(), "LUT_SYNTAX");
+        assert!(err.to_string().starts_with("Line 4:"), "{}", err);
+
+        let err = parse_cube_lut("LUT_3D_SIZE 2\n0 0\n").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+
+        let err = parse_cube_lut("TITLE \"x\"\n0 0 0\n").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+
+        let extra = cube_text(2, |rgb| rgb) + "1 1 1\n";
+        assert_eq!(parse_cube_lut(&extra).unwrap_err().line(), Some(13));
+
+        let err =
+            parse_cube_lut("LUT_3D_SIZE 2\nDOMAIN_MIN 1 0 0\nDOMAIN_MAX 1 1 1\n").unwrap_err();
+        assert_eq!(err.line(), Some(3));
+
+        let err = parse_cube_lut("LUT_1D_SIZE 1024\n").unwrap_err();
+        assert_eq!(err.line(), Some(1));
+    }
+
+    #[test]
+    fn test_rejects_absurd_sizes() {
+        for size in ["0", "1", "129", "100000"] {
+            let err = parse_cube_lut(&format!("\nLUT_3D_SIZE {}\n", size)).unwrap_err();
+            assert!(
+                matches!(err, LutError::InvalidSize { line: 2, .. }),
+                "{:?}",
+                err
+            );
+        }
+        assert_eq!(parse_cube_lut("# empty\n"), Err(LutError::MissingSize));
+        assert_eq!(
+            parse_cube_lut("LUT_3D_SIZE 2\n0 0 0\n"),
+            Err(LutError::MissingEntries {
+                expected: 8,
+                found: 1
+            })
+        );
+        assert!(Lut3d::identity(MAX_LUT_SIZE + 1).is_none());
+        assert!(Lut3d::new(2, vec![[0.0; 3]; 7]).is_none());
+    }
+}
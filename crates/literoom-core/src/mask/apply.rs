@@ -6,26 +6,55 @@
 
 use super::{LinearGradientMask, RadialGradientMask};
 use crate::adjustments::apply_adjustments_to_pixel;
+use crate::curve::ToneCurveLut;
+use crate::perf;
+use crate::validate::{validate_rgb_buffer, ImageError};
 use crate::BasicAdjustments;
 
+/// Mask strength below which a mask leaves a pixel untouched.
+pub(super) const MASK_EPSILON: f32 = 0.001;
+
+/// A mask paired with the adjustments and optional curve it applies.
+pub(super) type MaskLayer<'a, M> = (&'a M, &'a BasicAdjustments, Option<&'a ToneCurveLut>);
+
 /// Apply mask-based blending to a single pixel.
 ///
-/// Blends the original pixel with its adjusted version based on mask strength.
-/// Modifies RGB values in place if mask has effect and adjustments are non-default.
+/// Blends the original pixel with its adjusted (and optionally curved) version
+/// based on mask strength. Modifies RGB values in place if the mask has effect
+/// and there is something to apply.
 #[inline]
-fn apply_masked_blend(r: &mut f32, g: &mut f32, b: &mut f32, mask_val: f32, adj: &BasicAdjustments) {
+pub(super) fn apply_masked_blend(
+    r: &mut f32,
+    g: &mut f32,
+    b: &mut f32,
+    mask_val: f32,
+    adj: &BasicAdjustments,
+    curve: Option<&ToneCurveLut>,
+) {
     // Skip if mask has no effect at this pixel
-    if mask_val < 0.001 {
+    if mask_val < MASK_EPSILON {
         return;
     }
 
-    // Skip if adjustments are all default
-    if adj.is_default() {
+    // Skip if adjustments are all default and there is no curve
+    let adj_default = adj.is_default();
+    if adj_default && curve.is_none() {
         return;
     }
 
     // Apply adjustments to get target color
-    let (ar, ag, ab) = apply_adjustments_to_pixel(*r, *g, *b, adj);
+    let (mut ar, mut ag, mut ab) = if adj_default {
+        (*r, *g, *b)
+    } else {
+        apply_adjustments_to_pixel(*r, *g, *b, adj)
+    };
+
+    // Apply the mask's curve on top of its adjustments
+    if let Some(lut) = curve {
+        ar = lut.sample(ar);
+        ag = lut.sample(ag);
+        ab = lut.sample(ab);
+    }
 
     // Blend based on mask value: output = original * (1 - mask) + adjusted * mask
     *r = *r * (1.0 - mask_val) + ar * mask_val;
@@ -57,6 +86,10 @@ fn apply_masked_blend(r: &mut f32, g: &mut f32, b: &mut f32, mask_val: f32, adj:
 /// - Per-pixel early exit if mask value is near zero
 /// - O(pixels × masks) complexity
 ///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`. The pixels are left untouched in that case.
+///
 /// # Example
 /// ```
 /// use literoom_core::mask::{LinearGradientMask, apply_masked_adjustments};
@@ -73,7 +106,7 @@ fn apply_masked_blend(r: &mut f32, g: &mut f32, b: &mut f32, mask_val: f32, adj:
 ///     100, 100,
 ///     &[(mask, adj)],
 ///     &[],
-/// );
+/// ).unwrap();
 /// // Left side is brighter, right side unchanged
 /// ```
 pub fn apply_masked_adjustments(
@@ -82,43 +115,161 @@ pub fn apply_masked_adjustments(
     height: u32,
     linear_masks: &[(LinearGradientMask, BasicAdjustments)],
     radial_masks: &[(RadialGradientMask, BasicAdjustments)],
-) {
+) -> Result<(), ImageError> {
+    let linear: Vec<_> = linear_masks.iter().map(|(m, a)| (m, a, None)).collect();
+    let radial: Vec<_> = radial_masks.iter().map(|(m, a)| (m, a, None)).collect();
+    let (w_f, h_f) = (width as f32, height as f32);
+    apply_mask_layers(pixels, width, height, &linear, &radial, |px, py| {
+        ((px as f32 + 0.5) / w_f, (py as f32 + 0.5) / h_f)
+    })
+}
+
+/// Apply masked adjustments with an optional tone curve per mask.
+///
+/// Identical to [`apply_masked_adjustments`], except each mask may also carry
+/// a `ToneCurveLut` that is applied after its adjustments:
+/// `output = original * (1 - mask) + curve(adjusted) * mask`.
+///
+/// Identity curves are treated the same as `None`, so they add no per-pixel cost.
+/// Returns the same errors as [`apply_masked_adjustments`].
+///
+/// # Example
+/// ```
+/// use literoom_core::curve::ToneCurveLut;
+/// use literoom_core::mask::{apply_masked_adjustments_with_curves, RadialGradientMask};
+/// use literoom_core::{BasicAdjustments, CurvePoint, ToneCurve};
+///
+/// let mut pixels = vec![128u8; 100 * 100 * 3];
+///
+/// // S-curve on the subject only
+/// let curve = ToneCurve {
+///     points: vec![
+///         CurvePoint::new(0.0, 0.0),
+///         CurvePoint::new(0.25, 0.15),
+///         CurvePoint::new(0.75, 0.85),
+///         CurvePoint::new(1.0, 1.0),
+///     ],
+/// };
+/// let mask = RadialGradientMask::circle(0.5, 0.5, 0.3, 0.5);
+/// let lut = ToneCurveLut::from_curve(&curve);
+///
+/// apply_masked_adjustments_with_curves(
+///     &mut pixels,
+///     100, 100,
+///     &[],
+///     &[(mask, BasicAdjustments::default(), Some(lut))],
+/// ).unwrap();
+/// ```
+pub fn apply_masked_adjustments_with_curves(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    linear_masks: &[(LinearGradientMask, BasicAdjustments, Option<ToneCurveLut>)],
+    radial_masks: &[(RadialGradientMask, BasicAdjustments, Option<ToneCurveLut>)],
+) -> Result<(), ImageError> {
+    let (w_f, h_f) = (width as f32, height as f32);
+    apply_masked_adjustments_mapped(
+        pixels,
+        width,
+        height,
+        linear_masks,
+        radial_masks,
+        |px, py| ((px as f32 + 0.5) / w_f, (py as f32 + 0.5) / h_f),
+    )
+}
+
+/// Apply masked adjustments to a buffer that is part of a larger frame.
+///
+/// Like [`apply_masked_adjustments_with_curves`], except `to_normalized`
+/// maps each buffer pixel to the mask coordinates it samples. The region
+/// renderer uses this to evaluate masks against the full image while only
+/// holding one tile.
+pub(crate) fn apply_masked_adjustments_mapped(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    linear_masks: &[(LinearGradientMask, BasicAdjustments, Option<ToneCurveLut>)],
+    radial_masks: &[(RadialGradientMask, BasicAdjustments, Option<ToneCurveLut>)],
+    to_normalized: impl Fn(u32, u32) -> (f32, f32),
+) -> Result<(), ImageError> {
+    let linear = resolve_curves(linear_masks);
+    let radial = resolve_curves(radial_masks);
+    apply_mask_layers(pixels, width, height, &linear, &radial, to_normalized)
+}
+
+/// Borrow mask layers, resolving identity curves to `None` once rather than
+/// per pixel.
+pub(super) fn resolve_curves<M>(
+    masks: &[(M, BasicAdjustments, Option<ToneCurveLut>)],
+) -> Vec<MaskLayer<'_, M>> {
+    masks
+        .iter()
+        .map(|(m, a, c)| (m, a, c.as_ref().filter(|lut| !lut.is_identity())))
+        .collect()
+}
+
+/// Shared per-pixel loop for the public mask entry points.
+///
+/// `to_normalized` gives the mask coordinates of each pixel.
+fn apply_mask_layers(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    linear_masks: &[MaskLayer<LinearGradientMask>],
+    radial_masks: &[MaskLayer<RadialGradientMask>],
+    to_normalized: impl Fn(u32, u32) -> (f32, f32),
+) -> Result<(), ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+
+    let _perf = perf::scope("apply_masked_adjustments");
+    perf::record_dimensions(width, height);
+    perf::record_pixels((pixels.len() / 3) as u64);
+
     // Early exit if no masks
     if linear_masks.is_empty() && radial_masks.is_empty() {
-        return;
+        return Ok(());
     }
 
-    let w_f = width as f32;
-    let h_f = height as f32;
-
     for (idx, chunk) in pixels.chunks_exact_mut(3).enumerate() {
         let px = (idx as u32) % width;
         let py = (idx as u32) / width;
 
         // Normalized coordinates (0-1), centered on pixel
-        let x = (px as f32 + 0.5) / w_f;
-        let y = (py as f32 + 0.5) / h_f;
-
-        // Current pixel values as floats (0-1)
-        let mut r = chunk[0] as f32 / 255.0;
-        let mut g = chunk[1] as f32 / 255.0;
-        let mut b = chunk[2] as f32 / 255.0;
+        let (x, y) = to_normalized(px, py);
+        blend_pixel(chunk, x, y, linear_masks, radial_masks);
+    }
 
-        // Apply each linear mask
-        for (mask, adj) in linear_masks {
-            apply_masked_blend(&mut r, &mut g, &mut b, mask.evaluate(x, y), adj);
-        }
+    Ok(())
+}
 
-        // Apply each radial mask
-        for (mask, adj) in radial_masks {
-            apply_masked_blend(&mut r, &mut g, &mut b, mask.evaluate(x, y), adj);
-        }
+/// Apply every mask layer to one pixel at mask coordinates `(x, y)`.
+#[inline]
+pub(super) fn blend_pixel(
+    chunk: &mut [u8],
+    x: f32,
+    y: f32,
+    linear_masks: &[MaskLayer<LinearGradientMask>],
+    radial_masks: &[MaskLayer<RadialGradientMask>],
+) {
+    // Current pixel values as floats (0-1)
+    let mut r = chunk[0] as f32 / 255.0;
+    let mut g = chunk[1] as f32 / 255.0;
+    let mut b = chunk[2] as f32 / 255.0;
+
+    // Apply each linear mask
+    for &(mask, adj, curve) in linear_masks {
+        apply_masked_blend(&mut r, &mut g, &mut b, mask.evaluate(x, y), adj, curve);
+    }
 
-        // Write back (clamp to valid range)
-        chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
-        chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
-        chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+    // Apply each radial mask
+    for &(mask, adj, curve) in radial_masks {
+        apply_masked_blend(&mut r, &mut g, &mut b, mask.evaluate(x, y), adj, curve);
     }
+
+    // Write back (clamp to valid range)
+    chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+    chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+    chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
 }
 
 #[cfg(test)]
@@ -141,7 +292,7 @@ mod tests {
         let mut pixels = gray_image(100, 100, 128);
         let original = pixels.clone();
 
-        apply_masked_adjustments(&mut pixels, 100, 100, &[], &[]);
+        apply_masked_adjustments(&mut pixels, 100, 100, &[], &[]).unwrap();
 
         assert_eq!(pixels, original, "No masks should leave image unchanged");
     }
@@ -154,7 +305,7 @@ mod tests {
         let mask = LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 1.0);
         let adj = BasicAdjustments::default();
 
-        apply_masked_adjustments(&mut pixels, 100, 100, &[(mask, adj)], &[]);
+        apply_masked_adjustments(&mut pixels, 100, 100, &[(mask, adj)], &[]).unwrap();
 
         assert_eq!(
             pixels, original,
@@ -170,7 +321,7 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.exposure = 1.0; // +1 stop (doubles brightness)
 
-        apply_masked_adjustments(&mut pixels, 10, 10, &[(mask, adj)], &[]);
+        apply_masked_adjustments(&mut pixels, 10, 10, &[(mask, adj)], &[]).unwrap();
 
         // Left side (mask = 1.0) should be brighter
         let left_pixel = get_pixel(&pixels, 10, 0, 5);
@@ -194,7 +345,7 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.exposure = 1.0;
 
-        apply_masked_adjustments(&mut pixels, 20, 20, &[], &[(mask, adj)]);
+        apply_masked_adjustments(&mut pixels, 20, 20, &[], &[(mask, adj)]).unwrap();
 
         // Center (mask = 1.0) should be brighter
         let center = get_pixel(&pixels, 20, 10, 10);
@@ -205,6 +356,28 @@ mod tests {
         assert_eq!(corner, (100, 100, 100), "Corner should be unchanged");
     }
 
+    #[test]
+    fn test_mask_protects_skin_tones() {
+        let skin: Vec<u8> = [224u8, 172, 140].repeat(16);
+        let mask = RadialGradientMask::circle(0.5, 0.5, 2.0, 0.0); // Covers everything
+        let adj = BasicAdjustments {
+            saturation: 80.0,
+            ..Default::default()
+        };
+        let protected = BasicAdjustments {
+            protect_skin_tones: true,
+            ..adj.clone()
+        };
+
+        let mut pushed = skin.clone();
+        apply_masked_adjustments(&mut pushed, 4, 4, &[], &[(mask.clone(), adj)]).unwrap();
+        let mut spared = skin.clone();
+        apply_masked_adjustments(&mut spared, 4, 4, &[], &[(mask, protected)]).unwrap();
+
+        assert_ne!(pushed, skin);
+        assert_eq!(spared, skin);
+    }
+
     #[test]
     fn test_radial_mask_inverted() {
         let mut pixels = gray_image(20, 20, 100);
@@ -214,7 +387,7 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.exposure = 1.0;
 
-        apply_masked_adjustments(&mut pixels, 20, 20, &[], &[(mask, adj)]);
+        apply_masked_adjustments(&mut pixels, 20, 20, &[], &[(mask, adj)]).unwrap();
 
         // Center (inverted mask = 0.0) should be unchanged
         let center = get_pixel(&pixels, 20, 10, 10);
@@ -246,7 +419,7 @@ mod tests {
         let mut adj2 = BasicAdjustments::default();
         adj2.contrast = 50.0;
 
-        apply_masked_adjustments(&mut pixels, 20, 20, &[(mask1, adj1)], &[(mask2, adj2)]);
+        apply_masked_adjustments(&mut pixels, 20, 20, &[(mask1, adj1)], &[(mask2, adj2)]).unwrap();
 
         // Just verify it doesn't crash and produces some change
         let center = get_pixel(&pixels, 20, 10, 10);
@@ -266,7 +439,7 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.temperature = -100.0; // Warm (boost red, reduce blue)
 
-        apply_masked_adjustments(&mut pixels, 10, 10, &[(mask, adj)], &[]);
+        apply_masked_adjustments(&mut pixels, 10, 10, &[(mask, adj)], &[]).unwrap();
 
         let left = get_pixel(&pixels, 10, 0, 5);
         assert!(left.0 > left.2, "Warm should have more red than blue");
@@ -281,7 +454,7 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.saturation = -100.0; // Full desaturation
 
-        apply_masked_adjustments(&mut pixels, 1, 1, &[(mask, adj)], &[]);
+        apply_masked_adjustments(&mut pixels, 1, 1, &[(mask, adj)], &[]).unwrap();
 
         // Should be nearly grayscale
         let diff = (pixels[0] as i32 - pixels[2] as i32).abs();
@@ -300,7 +473,7 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.exposure = 1.0;
 
-        apply_masked_adjustments(&mut pixels, 100, 1, &[(mask, adj)], &[]);
+        apply_masked_adjustments(&mut pixels, 100, 1, &[(mask, adj)], &[]).unwrap();
 
         // Check for smooth transition
         let left = pixels[0];
@@ -333,12 +506,25 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.exposure = 1.0;
 
-        // Should not panic
-        apply_masked_adjustments(&mut pixels, 0, 0, &[(mask, adj)], &[]);
+        let result = apply_masked_adjustments(&mut pixels, 0, 0, &[(mask, adj)], &[]);
 
+        assert!(matches!(result, Err(ImageError::EmptyImage { .. })));
         assert!(pixels.is_empty());
     }
 
+    #[test]
+    fn test_buffer_mismatch_leaves_pixels_untouched() {
+        let mut pixels = gray_image(4, 4, 100);
+        let mask = LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 0.5);
+        let mut adj = BasicAdjustments::default();
+        adj.exposure = 1.0;
+
+        let result = apply_masked_adjustments(&mut pixels, 5, 4, &[(mask, adj)], &[]);
+
+        assert!(matches!(result, Err(ImageError::BufferSizeMismatch { .. })));
+        assert!(pixels.iter().all(|&v| v == 100));
+    }
+
     #[test]
     fn test_extreme_adjustments_clamp() {
         let mut pixels = gray_image(1, 1, 200);
@@ -347,7 +533,7 @@ mod tests {
         let mut adj = BasicAdjustments::default();
         adj.exposure = 5.0; // Maximum exposure
 
-        apply_masked_adjustments(&mut pixels, 1, 1, &[(mask, adj)], &[]);
+        apply_masked_adjustments(&mut pixels, 1, 1, &[(mask, adj)], &[]).unwrap();
 
         // Should clamp at 255
         assert_eq!(pixels[0], 255, "Should clamp at white");
@@ -366,7 +552,7 @@ mod tests {
         adj.shadows = 50.0; // Lift shadows
 
         let original = pixels.clone();
-        apply_masked_adjustments(&mut pixels, 2, 1, &[(mask, adj)], &[]);
+        apply_masked_adjustments(&mut pixels, 2, 1, &[(mask, adj)], &[]).unwrap();
 
         // Dark pixel should be lifted
         assert!(pixels[0] > original[0], "Shadow should be lifted");
@@ -374,6 +560,138 @@ mod tests {
         // Bright pixel should be reduced
         assert!(pixels[3] < original[3], "Highlight should be reduced");
     }
+
+    /// Helper to create an inverting tone curve LUT
+    fn inverting_lut() -> ToneCurveLut {
+        let curve = crate::ToneCurve {
+            points: vec![
+                crate::CurvePoint::new(0.0, 1.0),
+                crate::CurvePoint::new(1.0, 0.0),
+            ],
+        };
+        ToneCurveLut::from_curve(&curve)
+    }
+
+    /// Helper to create an image with a different value in every channel
+    fn varied_image(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height * 3)
+            .map(|i| (i * 7 % 256) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_curve_full_coverage_inverts() {
+        let mut pixels = varied_image(10, 10);
+        let original = pixels.clone();
+
+        // Circle large enough to cover the whole image
+        let mask = RadialGradientMask::circle(0.5, 0.5, 2.0, 0.0);
+        let layer = (mask, BasicAdjustments::default(), Some(inverting_lut()));
+
+        apply_masked_adjustments_with_curves(&mut pixels, 10, 10, &[], &[layer]).unwrap();
+
+        for (i, (&out, &orig)) in pixels.iter().zip(original.iter()).enumerate() {
+            let expected = 255 - orig as i32;
+            assert!(
+                (out as i32 - expected).abs() <= 1,
+                "Byte {} should be inverted: {} -> {} (expected {})",
+                i,
+                orig,
+                out,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_curve_half_coverage_inverts_half() {
+        let mut pixels = varied_image(10, 10);
+        let original = pixels.clone();
+
+        // Hard-edged linear mask: left half fully affected, right half untouched
+        let mask = LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 0.0);
+        let layer = (mask, BasicAdjustments::default(), Some(inverting_lut()));
+
+        apply_masked_adjustments_with_curves(&mut pixels, 10, 10, &[layer], &[]).unwrap();
+
+        for y in 0..10 {
+            for x in 0..10 {
+                let out = get_pixel(&pixels, 10, x, y);
+                let orig = get_pixel(&original, 10, x, y);
+                if x < 5 {
+                    assert!(
+                        (out.0 as i32 - (255 - orig.0 as i32)).abs() <= 1,
+                        "Left pixel ({}, {}) should be inverted",
+                        x,
+                        y
+                    );
+                } else {
+                    assert_eq!(out, orig, "Right pixel ({}, {}) should be unchanged", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_identity_curve_matches_plain_adjustments() {
+        let mask = LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 0.5);
+        let mut adj = BasicAdjustments::default();
+        adj.exposure = 0.7;
+        adj.contrast = 20.0;
+
+        let mut plain = varied_image(16, 16);
+        let mut curved = plain.clone();
+
+        apply_masked_adjustments(&mut plain, 16, 16, &[(mask.clone(), adj.clone())], &[]).unwrap();
+        apply_masked_adjustments_with_curves(
+            &mut curved,
+            16,
+            16,
+            &[(mask, adj, Some(ToneCurveLut::identity()))],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(plain, curved, "Identity curve must not change the result");
+    }
+
+    #[test]
+    fn test_masked_adjustments_report_pixel_count() {
+        let mut pixels = gray_image(12, 8, 100);
+        let mask = LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 0.5);
+        let mut adj = BasicAdjustments::default();
+        adj.exposure = 1.0;
+
+        apply_masked_adjustments(&mut pixels, 12, 8, &[(mask, adj)], &[]).unwrap();
+
+        let stats = crate::perf::last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "apply_masked_adjustments");
+        assert_eq!(stats.pixels_processed, 12 * 8);
+    }
+
+    #[test]
+    fn test_curve_applied_after_adjustments() {
+        // Exposure pushes gray to white; the inverting curve then maps it to black
+        let mut pixels = gray_image(4, 4, 128);
+        let mask = RadialGradientMask::circle(0.5, 0.5, 2.0, 0.0);
+        let mut adj = BasicAdjustments::default();
+        adj.exposure = 5.0;
+
+        apply_masked_adjustments_with_curves(
+            &mut pixels,
+            4,
+            4,
+            &[],
+            &[(mask, adj, Some(inverting_lut()))],
+        )
+        .unwrap();
+
+        assert!(
+            pixels.iter().all(|&v| v <= 1),
+            "Expected black, got {:?}",
+            &pixels[..3]
+        );
+    }
 }
 
 // ============================================================================
@@ -412,6 +730,8 @@ mod proptests {
                         tint,
                         vibrance,
                         saturation,
+                        protect_skin_tones: false,
+                        color_model: Default::default(),
                     }
                 },
             )
@@ -464,7 +784,7 @@ mod proptests {
             mask in linear_mask_strategy(),
             adj in adjustments_strategy(),
         ) {
-            apply_masked_adjustments(&mut pixels, width, height, &[(mask, adj)], &[]);
+            apply_masked_adjustments(&mut pixels, width, height, &[(mask, adj)], &[]).unwrap();
 
             for (i, &pixel) in pixels.iter().enumerate() {
                 prop_assert!(
@@ -481,7 +801,7 @@ mod proptests {
         fn prop_no_masks_no_change((mut pixels, width, height) in small_image_strategy()) {
             let original = pixels.clone();
 
-            apply_masked_adjustments(&mut pixels, width, height, &[], &[]);
+            apply_masked_adjustments(&mut pixels, width, height, &[], &[]).unwrap();
 
             prop_assert_eq!(pixels, original, "No masks should leave image unchanged");
         }
@@ -495,7 +815,7 @@ mod proptests {
             let original = pixels.clone();
             let adj = BasicAdjustments::default();
 
-            apply_masked_adjustments(&mut pixels, width, height, &[(mask, adj)], &[]);
+            apply_masked_adjustments(&mut pixels, width, height, &[(mask, adj)], &[]).unwrap();
 
             prop_assert_eq!(pixels, original, "Default adjustments should not modify image");
         }
@@ -510,8 +830,8 @@ mod proptests {
             let mut pixels1 = pixels.clone();
             let mut pixels2 = pixels.clone();
 
-            apply_masked_adjustments(&mut pixels1, width, height, &[(mask.clone(), adj.clone())], &[]);
-            apply_masked_adjustments(&mut pixels2, width, height, &[(mask, adj)], &[]);
+            apply_masked_adjustments(&mut pixels1, width, height, &[(mask.clone(), adj.clone())], &[]).unwrap();
+            apply_masked_adjustments(&mut pixels2, width, height, &[(mask, adj)], &[]).unwrap();
 
             prop_assert_eq!(pixels1, pixels2, "Same inputs should produce same outputs");
         }
@@ -532,7 +852,7 @@ mod proptests {
                 height,
                 &[(mask1, adj.clone())],
                 &[(mask2, adj)],
-            );
+            ).unwrap();
 
             prop_assert_eq!(pixels, original, "Default adjustments should not modify image");
         }
@@ -551,12 +871,12 @@ mod proptests {
             // Normal mask - affects inside
             let normal_mask = RadialGradientMask::new(cx, cy, r, r, 0.0, 0.0, false);
             let mut normal_pixels = pixels.clone();
-            apply_masked_adjustments(&mut normal_pixels, width, height, &[], &[(normal_mask, adj.clone())]);
+            apply_masked_adjustments(&mut normal_pixels, width, height, &[], &[(normal_mask, adj.clone())]).unwrap();
 
             // Inverted mask - affects outside
             let inverted_mask = RadialGradientMask::new(cx, cy, r, r, 0.0, 0.0, true);
             let mut inverted_pixels = pixels.clone();
-            apply_masked_adjustments(&mut inverted_pixels, width, height, &[], &[(inverted_mask, adj)]);
+            apply_masked_adjustments(&mut inverted_pixels, width, height, &[], &[(inverted_mask, adj)]).unwrap();
 
             // Center pixel index (approximately)
             let center_x = (width / 2) as usize;
@@ -0,0 +1,482 @@
+//! Brush masks: editable alpha planes in the mask stack
+//!
+//! A linear or radial mask can only be reshaped through its handles. To
+//! erase part of one by hand, the editor converts it with
+//! [`convert_mask_to_brush`] into a [`BrushMask`], which stores the strength
+//! as an 8-bit alpha plane that [`BrushMask::erase`] strokes paint into. The
+//! mask keeps its id, adjustments and curve, so it takes the place of the
+//! original in the stack.
+//!
+//! # Resolution
+//!
+//! The alpha plane has its own size and is sampled bilinearly at normalized
+//! coordinates, so one brush mask applies to the full image and to any
+//! preview. At the size of the image it is applied to, every pixel reads its
+//! own texel and the result matches the analytic mask within 1 per channel,
+//! as for a [`RasterizedMask`](super::RasterizedMask). A plane of half the
+//! image size takes a quarter of the memory; interpolating between its
+//! texels adds an error that grows as the mask's feathered band narrows.
+//! With adjustments of up to +1 stop, the output stays within 2 per channel
+//! of the analytic mask while the band is at least 20 image pixels wide,
+//! and within 1 from about 30 pixels. A hard edge becomes a ramp two image
+//! pixels wide, where the output can be off by the full adjustment.
+
+use super::apply::{apply_masked_blend, MaskLayer};
+use super::raster::{MaskGeometry, RasterizedMask};
+use super::selection::{MaskError, StackMask};
+use super::smootherstep;
+use crate::perf;
+use crate::validate::{validate_dimensions, ImageError};
+use serde::{Deserialize, Serialize};
+
+/// A mask whose strength is stored as an alpha plane.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrushMask {
+    width: u32,
+    height: u32,
+    /// Strength per texel, row-major, scaled to 0-255
+    alpha: Vec<u8>,
+}
+
+/// A point of a brush stroke, in normalized coordinates (0.0 to 1.0).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StrokePoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A stroke of the eraser brush.
+///
+/// The brush is round in image pixels. Along the path, within
+/// `radius * (1 - feather)` of it, the stroke removes `flow` of the alpha;
+/// the feathered rim fades out to the full radius.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrushStroke {
+    /// Path of the brush center; a single point dabs once
+    pub points: Vec<StrokePoint>,
+    /// Brush radius as a fraction of the image width
+    pub radius: f32,
+    /// Soft rim as a fraction of the radius (0.0 = hard edge)
+    #[serde(default)]
+    pub feather: f32,
+    /// Fraction of the alpha removed at the brush center (0.0 to 1.0)
+    #[serde(default = "full_flow")]
+    pub flow: f32,
+}
+
+fn full_flow() -> f32 {
+    1.0
+}
+
+impl BrushStroke {
+    /// Check that the stroke has points and its parameters are in range.
+    fn validate(&self) -> Result<(), MaskError> {
+        if self.points.is_empty() {
+            return Err(MaskError::InvalidStroke("stroke has no points"));
+        }
+        if self
+            .points
+            .iter()
+            .any(|p| !p.x.is_finite() || !p.y.is_finite())
+        {
+            return Err(MaskError::InvalidStroke("stroke points must be finite"));
+        }
+        if !(self.radius.is_finite() && self.radius > 0.0) {
+            return Err(MaskError::InvalidStroke("radius must be positive"));
+        }
+        if !(0.0..=1.0).contains(&self.feather) {
+            return Err(MaskError::InvalidStroke("feather must be 0.0 to 1.0"));
+        }
+        if !(0.0..=1.0).contains(&self.flow) {
+            return Err(MaskError::InvalidStroke("flow must be 0.0 to 1.0"));
+        }
+        Ok(())
+    }
+}
+
+impl BrushMask {
+    /// Rasterize a mask's geometry into a `width x height` alpha plane,
+    /// sampling each texel at its center.
+    ///
+    /// Callers taking sizes from outside should check them with
+    /// [`validate_dimensions`] first.
+    pub fn rasterize(geometry: &MaskGeometry, width: u32, height: u32) -> Self {
+        let raster = RasterizedMask::new(geometry.clone(), width, height);
+        Self {
+            width,
+            height,
+            alpha: raster.alpha().to_vec(),
+        }
+    }
+
+    /// A brush mask from a stored alpha plane.
+    ///
+    /// # Errors
+    /// Returns `MaskError::InvalidImage` if the dimensions are invalid or
+    /// `alpha` does not hold one value per texel.
+    pub fn from_alpha(width: u32, height: u32, alpha: Vec<u8>) -> Result<Self, MaskError> {
+        validate_dimensions(width, height)?;
+        let expected = width as usize * height as usize;
+        if alpha.len() != expected {
+            return Err(ImageError::BufferSizeMismatch {
+                width,
+                height,
+                expected,
+                actual: alpha.len(),
+            }
+            .into());
+        }
+        Ok(Self {
+            width,
+            height,
+            alpha,
+        })
+    }
+
+    /// Alpha plane width in texels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Alpha plane height in texels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Strength per texel, row-major, scaled to 0-255.
+    pub fn alpha(&self) -> &[u8] {
+        &self.alpha
+    }
+
+    /// Mask strength at normalized coordinates (0.0 to 1.0), interpolated
+    /// bilinearly between texel centers.
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        let fx = (x * self.width as f32 - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let fy = (y * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (fx as usize, fy as usize);
+        let x1 = (x0 + 1).min(self.width as usize - 1);
+        let y1 = (y0 + 1).min(self.height as usize - 1);
+        let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+
+        let row = self.width as usize;
+        let at = |x: usize, y: usize| self.alpha[y * row + x] as f32;
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+        (top + (bottom - top) * ty) / 255.0
+    }
+
+    /// Paint an eraser stroke into the alpha plane.
+    ///
+    /// Each texel within the brush's reach keeps `1 - flow * strength` of
+    /// its alpha, where the strength is 1.0 inside the hard core of the
+    /// brush and falls to 0.0 across the feathered rim.
+    ///
+    /// # Errors
+    /// Returns `MaskError::InvalidStroke` if the stroke has no points, or a
+    /// non-finite point or out-of-range parameter. The mask is left
+    /// untouched in that case.
+    pub fn erase(&mut self, stroke: &BrushStroke) -> Result<(), MaskError> {
+        stroke.validate()?;
+
+        let (w_f, h_f) = (self.width as f32, self.height as f32);
+        // Work in texels so the brush stays round on non-square planes
+        let path: Vec<(f32, f32)> = stroke
+            .points
+            .iter()
+            .map(|p| (p.x * w_f, p.y * h_f))
+            .collect();
+        let radius = stroke.radius * w_f;
+        let core = radius * (1.0 - stroke.feather);
+
+        // Only texels within the radius of the path can change
+        let (mut left, mut top, mut right, mut bottom) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for &(x, y) in &path {
+            left = left.min(x - radius);
+            top = top.min(y - radius);
+            right = right.max(x + radius);
+            bottom = bottom.max(y + radius);
+        }
+        let x_start = left.floor().clamp(0.0, w_f) as u32;
+        let x_end = right.ceil().clamp(0.0, w_f) as u32;
+        let y_start = top.floor().clamp(0.0, h_f) as u32;
+        let y_end = bottom.ceil().clamp(0.0, h_f) as u32;
+
+        for ty in y_start..y_end {
+            for tx in x_start..x_end {
+                let center = (tx as f32 + 0.5, ty as f32 + 0.5);
+                let distance = distance_to_path(center, &path);
+                if distance >= radius {
+                    continue;
+                }
+                let strength = if distance <= core {
+                    1.0
+                } else {
+                    1.0 - smootherstep((distance - core) / (radius - core))
+                };
+                let idx = ty as usize * self.width as usize + tx as usize;
+                let kept = self.alpha[idx] as f32 * (1.0 - stroke.flow * strength);
+                self.alpha[idx] = kept.round() as u8;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Distance from `point` to the polyline through `path`.
+fn distance_to_path(point: (f32, f32), path: &[(f32, f32)]) -> f32 {
+    let (px, py) = point;
+    if path.len() == 1 {
+        return (px - path[0].0).hypot(py - path[0].1);
+    }
+    path.windows(2)
+        .map(|segment| {
+            let ((ax, ay), (bx, by)) = (segment[0], segment[1]);
+            let (dx, dy) = (bx - ax, by - ay);
+            let len_sq = dx * dx + dy * dy;
+            let t = if len_sq > 0.0 {
+                (((px - ax) * dx + (py - ay) * dy) / len_sq).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            (px - (ax + t * dx)).hypot(py - (ay + t * dy))
+        })
+        .fold(f32::MAX, f32::min)
+}
+
+/// Convert a linear or radial mask into a brush mask with a
+/// `width x height` alpha plane, keeping its id, adjustments and curve.
+///
+/// See the [module docs](self) for choosing the size. Callers taking sizes
+/// from outside should check them with [`validate_dimensions`] first.
+pub fn convert_mask_to_brush<M: Clone + Into<MaskGeometry>>(
+    mask: &StackMask<M>,
+    width: u32,
+    height: u32,
+) -> StackMask<BrushMask> {
+    StackMask {
+        id: mask.id.clone(),
+        enabled: mask.enabled,
+        mask: BrushMask::rasterize(&mask.mask.clone().into(), width, height),
+        adjustments: mask.adjustments.clone(),
+        curve: mask.curve.clone(),
+    }
+}
+
+/// Apply brush mask layers to a valid `width x height` RGB buffer, in order.
+pub(super) fn apply_brush_layers(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    layers: &[MaskLayer<BrushMask>],
+) {
+    if layers.is_empty() {
+        return;
+    }
+    let _perf = perf::scope("apply_brush_masks");
+    perf::record_dimensions(width, height);
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    let (w_f, h_f) = (width as f32, height as f32);
+    for (idx, chunk) in pixels.chunks_exact_mut(3).enumerate() {
+        let x = ((idx as u32 % width) as f32 + 0.5) / w_f;
+        let y = ((idx as u32 / width) as f32 + 0.5) / h_f;
+
+        let mut r = chunk[0] as f32 / 255.0;
+        let mut g = chunk[1] as f32 / 255.0;
+        let mut b = chunk[2] as f32 / 255.0;
+        for &(mask, adj, curve) in layers {
+            apply_masked_blend(&mut r, &mut g, &mut b, mask.evaluate(x, y), adj, curve);
+        }
+        chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask::{
+        apply_selected_mask_stack, apply_selected_masks, MaskSelection, RadialGradientMask,
+    };
+    use crate::testing::{gradient, GradientDirection};
+    use crate::BasicAdjustments;
+
+    fn radial_stack_mask(feather: f32) -> StackMask<RadialGradientMask> {
+        StackMask {
+            id: Some("subject".to_string()),
+            enabled: true,
+            mask: RadialGradientMask::circle(0.5, 0.5, 0.3, feather),
+            adjustments: BasicAdjustments {
+                exposure: 1.0,
+                ..Default::default()
+            },
+            curve: None,
+        }
+    }
+
+    /// Render a gradient with only `radial` or only `brush`.
+    fn render_at(
+        (width, height): (u32, u32),
+        radial: &[StackMask<RadialGradientMask>],
+        brush: &[StackMask<BrushMask>],
+    ) -> Vec<u8> {
+        let mut pixels = gradient(width, height, GradientDirection::Diagonal).pixels;
+        apply_selected_mask_stack(
+            &mut pixels,
+            width,
+            height,
+            &[],
+            radial,
+            brush,
+            &MaskSelection::default(),
+        )
+        .unwrap();
+        pixels
+    }
+
+    fn render(radial: &[StackMask<RadialGradientMask>], brush: &[StackMask<BrushMask>]) -> Vec<u8> {
+        render_at((64, 48), radial, brush)
+    }
+
+    fn max_diff(a: &[u8], b: &[u8]) -> u8 {
+        a.iter().zip(b).map(|(x, y)| x.abs_diff(*y)).max().unwrap()
+    }
+
+    #[test]
+    fn test_full_size_brush_matches_analytic_mask() {
+        for feather in [0.0, 0.5, 1.0] {
+            let radial = radial_stack_mask(feather);
+            let brush = convert_mask_to_brush(&radial, 64, 48);
+            assert_eq!(brush.id.as_deref(), Some("subject"));
+            assert_eq!(brush.adjustments, radial.adjustments);
+
+            let analytic = render(&[radial], &[]);
+            let rasterized = render(&[], &[brush]);
+            assert!(max_diff(&analytic, &rasterized) <= 1);
+        }
+    }
+
+    #[test]
+    fn test_half_size_brush_error_is_bounded() {
+        // The radius is 0.3 of the width, so the feathered band is
+        // 0.3 * feather * width image pixels wide
+        let cases = [
+            ((128, 96), 0.5, 2),  // 19 pixels
+            ((256, 192), 0.5, 1), // 38 pixels
+            ((128, 96), 1.0, 1),  // 38 pixels
+        ];
+        for (size, feather, tolerance) in cases {
+            let radial = radial_stack_mask(feather);
+            let half = convert_mask_to_brush(&radial, size.0 / 2, size.1 / 2);
+            let analytic = render_at(size, &[radial], &[]);
+            let rasterized = render_at(size, &[], &[half]);
+            assert!(max_diff(&analytic, &rasterized) <= tolerance);
+        }
+
+        // A hard edge is only matched at full size
+        let hard = radial_stack_mask(0.0);
+        let full = render(&[], &[convert_mask_to_brush(&hard, 64, 48)]);
+        let half = render(&[], &[convert_mask_to_brush(&hard, 32, 24)]);
+        let analytic = render(&[hard], &[]);
+        assert_eq!(full, analytic);
+        assert!(max_diff(&analytic, &half) > 2);
+    }
+
+    #[test]
+    fn test_erase_stroke_zeroes_its_path() {
+        let mut brush = BrushMask::rasterize(
+            &MaskGeometry::Radial(RadialGradientMask::circle(0.5, 0.5, 0.4, 0.0)),
+            100,
+            100,
+        );
+        let before = brush.clone();
+        let stroke = BrushStroke {
+            points: vec![
+                StrokePoint { x: 0.3, y: 0.5 },
+                StrokePoint { x: 0.7, y: 0.5 },
+            ],
+            radius: 0.05,
+            feather: 0.5,
+            flow: 1.0,
+        };
+        brush.erase(&stroke).unwrap();
+
+        // Along the path the alpha is gone
+        for x in [0.3, 0.4, 0.5, 0.6, 0.7] {
+            assert_eq!(brush.evaluate(x, 0.5), 0.0, "x = {x}");
+        }
+        // Inside the mask but away from the stroke it is untouched
+        for (x, y) in [(0.5, 0.3), (0.5, 0.7), (0.2, 0.5)] {
+            assert_eq!(brush.evaluate(x, y), before.evaluate(x, y));
+        }
+        // Only rows within the radius change
+        let changed_rows: Vec<usize> = (0..100)
+            .filter(|&y| {
+                brush.alpha[y * 100..(y + 1) * 100] != before.alpha[y * 100..(y + 1) * 100]
+            })
+            .collect();
+        assert!(changed_rows.iter().all(|&y| (45..55).contains(&y)));
+    }
+
+    #[test]
+    fn test_partial_flow_scales_alpha() {
+        let mut brush = BrushMask::from_alpha(10, 10, vec![200; 100]).unwrap();
+        let stroke = BrushStroke {
+            points: vec![StrokePoint { x: 0.5, y: 0.5 }],
+            radius: 0.2,
+            feather: 0.0,
+            flow: 0.5,
+        };
+        brush.erase(&stroke).unwrap();
+        assert_eq!(brush.alpha()[5 * 10 + 5], 100);
+        assert_eq!(brush.alpha()[0], 200);
+    }
+
+    #[test]
+    fn test_invalid_stroke_leaves_mask_untouched() {
+        let mut brush = BrushMask::from_alpha(4, 4, vec![255; 16]).unwrap();
+        let empty = BrushStroke {
+            points: vec![],
+            radius: 0.1,
+            feather: 0.0,
+            flow: 1.0,
+        };
+        let err = brush.erase(&empty).unwrap_err();
+        assert_eq!(err.code(), "INVALID_BRUSH_STROKE");
+        let negative = BrushStroke {
+            points: vec![StrokePoint { x: 0.5, y: 0.5 }],
+            radius: -1.0,
+            ..empty
+        };
+        assert!(brush.erase(&negative).is_err());
+        assert_eq!(brush.alpha(), &[255; 16]);
+    }
+
+    #[test]
+    fn test_alpha_length_is_checked() {
+        let err = BrushMask::from_alpha(4, 4, vec![0; 15]).unwrap_err();
+        assert_eq!(err.code(), "BUFFER_SIZE_MISMATCH");
+        assert!(BrushMask::from_alpha(0, 4, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_brush_ids_can_be_soloed() {
+        let radial = [radial_stack_mask(0.5)];
+        let mut brush = convert_mask_to_brush(&radial[0], 64, 48);
+        brush.id = Some("brush".to_string());
+        let brush = [brush];
+        let solo = MaskSelection {
+            solo: Some("brush".to_string()),
+            ..Default::default()
+        };
+
+        let mut pixels = gradient(64, 48, GradientDirection::Diagonal).pixels;
+        apply_selected_mask_stack(&mut pixels, 64, 48, &[], &radial, &brush, &solo).unwrap();
+        assert_eq!(pixels, render(&[], &brush));
+
+        // Without brush masks in the stack, the id is unknown
+        let mut pixels = gradient(64, 48, GradientDirection::Diagonal).pixels;
+        assert!(apply_selected_masks(&mut pixels, 64, 48, &[], &radial, &solo).is_err());
+    }
+}
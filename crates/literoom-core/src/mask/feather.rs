@@ -0,0 +1,345 @@
+//! Automatic feather suggestion for gradient masks
+//!
+//! A hard mask edge on a noisy, high-ISO image shimmers: the boundary cuts
+//! through the grain and every speck on it is either fully adjusted or not
+//! at all. Where the boundary follows a strong edge in the photo the cut is
+//! hidden by the edge itself, so a hard mask looks right there.
+//!
+//! [`suggest_mask_feather`] and [`suggest_linear_mask_feather`] sample a
+//! band across the mask's boundary, measure the noise in it (median
+//! absolute deviation of high-pass filtered luma) and the contrast across
+//! it, and recommend a feather: large on noisy or featureless boundaries,
+//! small where the boundary sits on a strong edge.
+
+use super::{LinearGradientMask, RadialGradientMask};
+use crate::luminance::{LUMINANCE_B, LUMINANCE_G, LUMINANCE_R};
+use crate::validate::{validate_rgb_buffer, ImageError};
+
+/// Largest feather ever suggested.
+pub const MAX_SUGGESTED_FEATHER: f32 = 0.8;
+
+/// Pixels sampled on each side of the boundary for the noise estimate.
+const BAND_HALF_WIDTH: i32 = 6;
+
+/// Distance in pixels from the boundary at which contrast is measured.
+const EDGE_OFFSET: f32 = 2.0;
+
+/// Upper bound on the number of boundary positions sampled.
+const MAX_SAMPLES: usize = 512;
+
+/// Luma noise (standard deviation, 0-255 levels) treated as fully noisy.
+const NOISY_SIGMA: f32 = 12.0;
+
+/// Suggest a feather for a radial mask from the image under its boundary.
+///
+/// The boundary is the mask's ellipse, where a hard mask cuts off. Parts of
+/// it outside the frame are ignored.
+///
+/// # Returns
+///
+/// A feather between 0.0 and [`MAX_SUGGESTED_FEATHER`], or the mask's own
+/// feather when none of its boundary lies inside the frame. The image is
+/// not modified.
+///
+/// # Errors
+///
+/// Returns `ImageError` if the buffer is empty or does not match the
+/// dimensions.
+pub fn suggest_mask_feather(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    mask: &RadialGradientMask,
+) -> Result<f32, ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    let (w_f, h_f) = (width as f32, height as f32);
+    let (rx, ry) = (mask.radius_x.max(0.001), mask.radius_y.max(0.001));
+    let (cos_r, sin_r) = (mask.rotation.cos(), mask.rotation.sin());
+
+    // Roughly one sample per pixel of circumference
+    let (a, b) = (rx * w_f, ry * h_f);
+    let circumference = std::f32::consts::TAU * ((a * a + b * b) / 2.0).sqrt();
+    let count = (circumference as usize).clamp(16, MAX_SAMPLES);
+
+    let samples = (0..count).map(|i| {
+        let theta = std::f32::consts::TAU * i as f32 / count as f32;
+        // Ellipse point in the mask's local frame, rotated back to the image
+        let (local_x, local_y) = (rx * theta.cos(), ry * theta.sin());
+        let dx = local_x * cos_r - local_y * sin_r;
+        let dy = local_x * sin_r + local_y * cos_r;
+        let point = (
+            (mask.center_x + dx) * w_f - 0.5,
+            (mask.center_y + dy) * h_f - 0.5,
+        );
+        // Gradient of the normalized distance, in pixel units
+        let (gx, gy) = (local_x / (rx * rx), local_y / (ry * ry));
+        let normal = (
+            (gx * cos_r - gy * sin_r) / w_f,
+            (gx * sin_r + gy * cos_r) / h_f,
+        );
+        (point, normal)
+    });
+
+    Ok(suggest(pixels, width, height, samples).unwrap_or(mask.feather))
+}
+
+/// Suggest a feather for a linear mask from the image under its boundary.
+///
+/// The boundary is the line through the midpoint of the gradient, where a
+/// hard mask cuts off. Only the part inside the frame is sampled.
+///
+/// # Returns
+///
+/// A feather between 0.0 and [`MAX_SUGGESTED_FEATHER`], or the mask's own
+/// feather when the boundary misses the frame or the start and end points
+/// coincide. The image is not modified.
+///
+/// # Errors
+///
+/// Returns `ImageError` if the buffer is empty or does not match the
+/// dimensions.
+pub fn suggest_linear_mask_feather(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    mask: &LinearGradientMask,
+) -> Result<f32, ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    let (w_f, h_f) = (width as f32, height as f32);
+    let (dx, dy) = (mask.end_x - mask.start_x, mask.end_y - mask.start_y);
+    if dx * dx + dy * dy < f32::EPSILON {
+        return Ok(mask.feather);
+    }
+
+    let mid = (
+        (mask.start_x + dx / 2.0) * w_f - 0.5,
+        (mask.start_y + dy / 2.0) * h_f - 0.5,
+    );
+    let normal = (dx / w_f, dy / h_f);
+    let (ux, uy) = unit((-dy * w_f, dx * h_f));
+
+    // Walk the whole line across the frame; samples outside are skipped
+    let reach = w_f.hypot(h_f) + mid.0.abs() + mid.1.abs();
+    let step = (2.0 * reach / MAX_SAMPLES as f32).max(1.0);
+    let count = (2.0 * reach / step) as usize;
+    let samples = (0..=count).map(|i| {
+        let s = i as f32 * step - reach;
+        ((mid.0 + s * ux, mid.1 + s * uy), normal)
+    });
+
+    Ok(suggest(pixels, width, height, samples).unwrap_or(mask.feather))
+}
+
+/// Measure noise and edge contrast at the boundary samples and turn them
+/// into a feather, or `None` if no sample is usable.
+///
+/// Each sample is a boundary point in pixel coordinates and the boundary
+/// normal there (any length).
+fn suggest(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    samples: impl Iterator<Item = ((f32, f32), (f32, f32))>,
+) -> Option<f32> {
+    let (width, height) = (width as usize, height as usize);
+    if width < 3 || height < 3 {
+        return None;
+    }
+    let luma: Vec<f32> = pixels
+        .chunks_exact(3)
+        .map(|p| LUMINANCE_R * p[0] as f32 + LUMINANCE_G * p[1] as f32 + LUMINANCE_B * p[2] as f32)
+        .collect();
+    // Nearest pixel whose 3x3 neighbourhood is inside the image
+    let interior = |x: f32, y: f32| {
+        let (x, y) = (x.round(), y.round());
+        (x >= 1.0 && y >= 1.0 && x <= (width - 2) as f32 && y <= (height - 2) as f32)
+            .then_some((x as usize, y as usize))
+    };
+    let box_mean = |(x, y): (usize, usize)| {
+        let sum: f32 = (y - 1..=y + 1)
+            .flat_map(|row| &luma[row * width + x - 1..=row * width + x + 1])
+            .sum();
+        sum / 9.0
+    };
+
+    let mut high_pass = Vec::new();
+    let mut contrast = Vec::new();
+    for ((px, py), normal) in samples {
+        let (nx, ny) = unit(normal);
+        if !nx.is_finite() || interior(px, py).is_none() {
+            continue;
+        }
+        for k in -BAND_HALF_WIDTH..=BAND_HALF_WIDTH {
+            let k = k as f32;
+            if let Some((x, y)) = interior(px + k * nx, py + k * ny) {
+                high_pass.push(luma[y * width + x] - box_mean((x, y)));
+            }
+        }
+        let inside = interior(px - EDGE_OFFSET * nx, py - EDGE_OFFSET * ny);
+        let outside = interior(px + EDGE_OFFSET * nx, py + EDGE_OFFSET * ny);
+        if let (Some(inside), Some(outside)) = (inside, outside) {
+            contrast.push((box_mean(outside) - box_mean(inside)).abs() / (2.0 * EDGE_OFFSET));
+        }
+    }
+    if high_pass.is_empty() {
+        return None;
+    }
+
+    // Robust noise sigma from the median absolute deviation
+    let center = median(&mut high_pass);
+    let mut deviations: Vec<f32> = high_pass.iter().map(|v| (v - center).abs()).collect();
+    let noise = 1.4826 * median(&mut deviations);
+    let edge = if contrast.is_empty() {
+        0.0
+    } else {
+        median(&mut contrast)
+    };
+
+    // An edge well above the noise hides the cut; noise makes it shimmer
+    let flatness = 1.0 - edge / (edge + 3.0 * noise + 2.0);
+    let noisiness = (noise / NOISY_SIGMA).min(1.0);
+    Some(MAX_SUGGESTED_FEATHER * flatness * (0.5 + 0.5 * noisiness))
+}
+
+/// Scale a vector to unit length. Zero vectors give NaN components.
+fn unit((x, y): (f32, f32)) -> (f32, f32) {
+    let len = x.hypot(y);
+    (x / len, y / len)
+}
+
+/// Median of a non-empty slice, reordering it.
+fn median(values: &mut [f32]) -> f32 {
+    let mid = values.len() / 2;
+    *values.select_nth_unstable_by(mid, f32::total_cmp).1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::DecodedImage;
+    use crate::testing::noise;
+
+    /// Bright disk of radius 0.25 (of the width) on a dark background.
+    fn sharp_disk(size: u32) -> DecodedImage {
+        let mut pixels = Vec::with_capacity((size * size * 3) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let u = (x as f32 + 0.5) / size as f32 - 0.5;
+                let v = (y as f32 + 0.5) / size as f32 - 0.5;
+                let value = if u.hypot(v) <= 0.25 { 220 } else { 30 };
+                pixels.extend_from_slice(&[value; 3]);
+            }
+        }
+        DecodedImage::new(size, size, pixels)
+    }
+
+    /// Mid gray with heavy grain.
+    fn noisy_flat(size: u32) -> DecodedImage {
+        let mut image = noise(size, size, 11);
+        for v in image.pixels.iter_mut() {
+            *v = 128 + *v / 3 - 42;
+        }
+        image
+    }
+
+    #[test]
+    fn test_sharp_edge_gets_small_feather() {
+        let image = sharp_disk(128);
+        let mask = RadialGradientMask::circle(0.5, 0.5, 0.25, 0.0);
+        let feather = suggest_mask_feather(&image.pixels, 128, 128, &mask).unwrap();
+        assert!(feather < 0.2, "got {}", feather);
+    }
+
+    #[test]
+    fn test_noisy_flat_gets_large_feather() {
+        let image = noisy_flat(128);
+        let mask = RadialGradientMask::circle(0.5, 0.5, 0.25, 0.0);
+        let noisy = suggest_mask_feather(&image.pixels, 128, 128, &mask).unwrap();
+        assert!(noisy > 0.6, "got {}", noisy);
+
+        let sharp = sharp_disk(128);
+        let clean = suggest_mask_feather(&sharp.pixels, 128, 128, &mask).unwrap();
+        assert!(noisy > clean + 0.4, "noisy {} vs clean {}", noisy, clean);
+    }
+
+    #[test]
+    fn test_flat_clean_is_between() {
+        let image = DecodedImage::new(64, 64, vec![128; 64 * 64 * 3]);
+        let mask = RadialGradientMask::circle(0.5, 0.5, 0.25, 0.0);
+        let feather = suggest_mask_feather(&image.pixels, 64, 64, &mask).unwrap();
+        assert!(
+            (feather - MAX_SUGGESTED_FEATHER / 2.0).abs() < 0.01,
+            "got {}",
+            feather
+        );
+    }
+
+    #[test]
+    fn test_linear_mask() {
+        // Hard vertical edge at x = 0.5, matching the mask boundary
+        let mut pixels = Vec::new();
+        for _ in 0..64 {
+            for x in 0..96 {
+                pixels.extend_from_slice(&[if x < 48 { 20 } else { 230 }; 3]);
+            }
+        }
+        let mask = LinearGradientMask::new(0.4, 0.5, 0.6, 0.5, 0.0);
+        let sharp = suggest_linear_mask_feather(&pixels, 96, 64, &mask).unwrap();
+        assert!(sharp < 0.2, "got {}", sharp);
+
+        let image = noisy_flat(96);
+        let noisy = suggest_linear_mask_feather(&image.pixels, 96, 96, &mask).unwrap();
+        assert!(noisy > 0.6, "got {}", noisy);
+    }
+
+    #[test]
+    fn test_does_not_mutate_image() {
+        let image = noisy_flat(64);
+        let before = image.pixels.clone();
+        let mask = RadialGradientMask::circle(0.3, 0.6, 0.2, 0.1);
+        suggest_mask_feather(&image.pixels, 64, 64, &mask).unwrap();
+        let mask = LinearGradientMask::new(0.0, 0.0, 1.0, 1.0, 0.1);
+        suggest_linear_mask_feather(&image.pixels, 64, 64, &mask).unwrap();
+        assert_eq!(image.pixels, before);
+    }
+
+    #[test]
+    fn test_boundary_partly_outside_frame() {
+        // Disk centered on the right edge: half the boundary is off-frame
+        let image = sharp_disk(128);
+        let mask = RadialGradientMask::circle(1.0, 0.5, 0.3, 0.0);
+        let feather = suggest_mask_feather(&image.pixels, 128, 128, &mask).unwrap();
+        assert!((0.0..=MAX_SUGGESTED_FEATHER).contains(&feather));
+
+        let image = noisy_flat(64);
+        let mask = RadialGradientMask::circle(0.0, 0.0, 0.5, 0.0);
+        let feather = suggest_mask_feather(&image.pixels, 64, 64, &mask).unwrap();
+        assert!(feather > 0.6, "got {}", feather);
+
+        // Linear boundary clipping a corner
+        let mask = LinearGradientMask::new(0.7, 0.7, 1.1, 1.1, 0.0);
+        let feather = suggest_linear_mask_feather(&image.pixels, 64, 64, &mask).unwrap();
+        assert!(feather > 0.6, "got {}", feather);
+    }
+
+    #[test]
+    fn test_boundary_outside_frame_keeps_feather() {
+        let image = noisy_flat(32);
+        let mask = RadialGradientMask::circle(5.0, 5.0, 0.2, 0.35);
+        assert_eq!(
+            suggest_mask_feather(&image.pixels, 32, 32, &mask).unwrap(),
+            0.35
+        );
+        let mask = LinearGradientMask::new(3.0, 3.0, 3.0, 3.0, 0.25);
+        assert_eq!(
+            suggest_linear_mask_feather(&image.pixels, 32, 32, &mask).unwrap(),
+            0.25
+        );
+    }
+
+    #[test]
+    fn test_invalid_buffer() {
+        let mask = RadialGradientMask::circle(0.5, 0.5, 0.25, 0.0);
+        assert!(suggest_mask_feather(&[0; 10], 4, 4, &mask).is_err());
+    }
+}
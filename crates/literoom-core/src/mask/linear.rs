@@ -3,6 +3,7 @@
 //! A linear gradient mask is defined by a start point and end point.
 //! The mask transitions from full effect (1.0) at the start side to no effect (0.0)
 //! at the end side, with the transition zone controlled by the feather amount.
+//! An inverted mask runs the other way without moving its points.
 
 use super::smootherstep;
 use serde::{Deserialize, Serialize};
@@ -27,6 +28,7 @@ use serde::{Deserialize, Serialize};
 ///     end_x: 1.0,
 ///     end_y: 0.5,
 ///     feather: 0.5,
+///     invert: false,
 /// };
 ///
 /// // Left edge: full effect
@@ -46,6 +48,9 @@ pub struct LinearGradientMask {
     pub end_y: f32,
     /// Feather amount (0.0 = hard edge, 1.0 = full gradient)
     pub feather: f32,
+    /// Whether to invert the mask (full effect on the end side)
+    #[serde(default)]
+    pub invert: bool,
 }
 
 impl LinearGradientMask {
@@ -62,9 +67,16 @@ impl LinearGradientMask {
             end_x,
             end_y,
             feather: feather.clamp(0.0, 1.0),
+            invert: false,
         }
     }
 
+    /// Set whether the mask is inverted.
+    pub fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
     /// Compute the direction vector from start to end and its squared length.
     ///
     /// Returns (dx, dy, len_sq) where len_sq = dx² + dy².
@@ -90,6 +102,7 @@ impl LinearGradientMask {
     /// 3. Normalize to get position along the gradient (0 = start, 1 = end)
     /// 4. Apply feathering centered at the midpoint
     /// 5. Use smootherstep for natural transition
+    /// 6. Optionally invert the result
     pub fn evaluate(&self, x: f32, y: f32) -> f32 {
         let (dx, dy, len_sq) = self.direction_and_len_sq();
 
@@ -107,7 +120,7 @@ impl LinearGradientMask {
         let feather_zone = 0.5 * self.feather.clamp(0.0, 1.0);
         let center = 0.5;
 
-        if t <= center - feather_zone {
+        let mask = if t <= center - feather_zone {
             // Before the transition zone: full effect
             1.0
         } else if t >= center + feather_zone {
@@ -117,6 +130,13 @@ impl LinearGradientMask {
             // In the transition zone: interpolate with smootherstep
             let local_t = (t - (center - feather_zone)) / (2.0 * feather_zone).max(0.001);
             1.0 - smootherstep(local_t)
+        };
+
+        // Optionally invert: the end side gets the effect
+        if self.invert {
+            1.0 - mask
+        } else {
+            mask
         }
     }
 
@@ -227,6 +247,46 @@ mod tests {
         assert!(val_left < 0.01);
     }
 
+    #[test]
+    fn test_inverted_gradient_complements() {
+        for feather in [0.0, 0.4, 1.0] {
+            let mask = LinearGradientMask::new(0.2, 0.1, 0.7, 0.9, feather);
+            let inverted = mask.clone().with_invert(true);
+            for i in 0..=10 {
+                for j in 0..=10 {
+                    let (x, y) = (i as f32 / 10.0, j as f32 / 10.0);
+                    let expected = 1.0 - mask.evaluate(x, y);
+                    assert!(
+                        (inverted.evaluate(x, y) - expected).abs() < 1e-6,
+                        "({}, {}) with feather {}",
+                        x,
+                        y,
+                        feather
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserializes_without_invert() {
+        use serde::de::value::{Error, MapDeserializer};
+
+        // Masks saved before `invert` existed
+        let fields = [
+            ("start_x", 0.0f32),
+            ("start_y", 0.5),
+            ("end_x", 1.0),
+            ("end_y", 0.5),
+            ("feather", 0.5),
+        ];
+        let mask =
+            LinearGradientMask::deserialize(MapDeserializer::<_, Error>::new(fields.into_iter()))
+                .unwrap();
+        assert_eq!(mask, LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 0.5));
+        assert!(!mask.invert);
+    }
+
     #[test]
     fn test_perpendicular_to_line() {
         let mask = LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 1.0);
@@ -1,12 +1,22 @@
 //! Local adjustment masks for region-based editing
 //!
-//! This module provides linear and radial gradient masks for applying
-//! localized adjustments to specific regions of an image.
+//! This module provides linear and radial gradient masks and strip masks for
+//! applying localized adjustments to specific regions of an image.
 //!
 //! ## Mask Types
 //!
 //! - **Linear Gradient**: A gradient defined by start and end points with feathering
 //! - **Radial Gradient**: An elliptical gradient with center, radii, rotation, and feathering
+//! - **Strip**: A band of full effect along a center line, feathered on both sides
+//!
+//! Masks in a stack can be soloed or bypassed by id (see [`selection`]).
+//! While a mask is dragged, only the pixels it changes need re-rendering
+//! (see [`region`]). Masks whose geometry is unchanged can be applied from a
+//! cached alpha plane (see [`raster`]). A feather suited to the noise and
+//! edges under a mask's boundary can be suggested (see [`feather`]).
+//! A linear or radial mask can be converted into a brush mask, whose alpha
+//! plane can be erased by hand (see [`brush`]). Masks of every kind can be
+//! applied in one user-defined order (see [`stack`]).
 //!
 //! ## Algorithm
 //!
@@ -14,12 +24,31 @@
 //! The feathering uses the smootherstep function for natural transitions.
 
 pub mod apply;
+pub mod brush;
+pub mod feather;
 pub mod linear;
 pub mod radial;
+pub mod raster;
+pub mod region;
+pub mod selection;
+pub mod stack;
+pub mod strip;
 
-pub use apply::apply_masked_adjustments;
+pub use apply::{apply_masked_adjustments, apply_masked_adjustments_with_curves};
+pub use brush::{convert_mask_to_brush, BrushMask, BrushStroke, StrokePoint};
+pub use feather::{suggest_linear_mask_feather, suggest_mask_feather};
 pub use linear::LinearGradientMask;
 pub use radial::RadialGradientMask;
+pub use raster::{
+    apply_masked_adjustments_cached, apply_masked_adjustments_mixed, MaskAlpha, MaskGeometry,
+    RasterizedMask,
+};
+pub use region::{apply_masked_adjustments_region, compute_mask_dirty_rect, MaskShape, PixelRect};
+pub use selection::{
+    apply_selected_mask_stack, apply_selected_masks, MaskError, MaskSelection, StackMask,
+};
+pub use stack::{apply_ordered_masks, apply_ordered_masks_region, legacy_mask_order, MaskEntry};
+pub use strip::StripMask;
 
 /// Smootherstep interpolation function.
 ///
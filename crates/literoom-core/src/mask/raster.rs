@@ -0,0 +1,381 @@
+//! Rasterized masks for re-applying unchanged geometry
+//!
+//! Evaluating a mask's geometry at every pixel dominates the cost of
+//! [`apply_masked_adjustments`](super::apply_masked_adjustments), most of all
+//! for rotated, feathered ellipses. While only a mask's adjustments change,
+//! its strength at each pixel stays the same, so [`RasterizedMask`] stores it
+//! once as an 8-bit alpha plane, a [`GrayImage`]. The raster records the geometry and size it
+//! was made for; callers compare them with [`RasterizedMask::is_current`] and
+//! rasterize again when either changes.
+//!
+//! Quantizing the strength to 8 bits changes the output by at most 1 per
+//! channel compared with evaluating the geometry, as long as a mask's
+//! adjustments move each channel by no more than the full 0-255 range
+//! (exposure up to +1 stop, for example). Stronger pushes scale the error.
+
+use super::apply::apply_masked_blend;
+use super::selection::MaskError;
+use super::{LinearGradientMask, RadialGradientMask};
+use crate::decode::GrayImage;
+use crate::perf;
+use crate::validate::{checked_pixel_count, validate_rgb_buffer};
+use crate::BasicAdjustments;
+use serde::{Deserialize, Serialize};
+
+/// Geometry of a linear or radial mask.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskGeometry {
+    /// Linear gradient
+    Linear(LinearGradientMask),
+    /// Radial gradient
+    Radial(RadialGradientMask),
+}
+
+impl MaskGeometry {
+    /// Mask strength at normalized coordinates (0.0 to 1.0).
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        match self {
+            MaskGeometry::Linear(mask) => mask.evaluate(x, y),
+            MaskGeometry::Radial(mask) => mask.evaluate(x, y),
+        }
+    }
+}
+
+impl From<LinearGradientMask> for MaskGeometry {
+    fn from(mask: LinearGradientMask) -> Self {
+        MaskGeometry::Linear(mask)
+    }
+}
+
+impl From<RadialGradientMask> for MaskGeometry {
+    fn from(mask: RadialGradientMask) -> Self {
+        MaskGeometry::Radial(mask)
+    }
+}
+
+/// A mask's strength at every pixel of a `width x height` image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterizedMask {
+    geometry: MaskGeometry,
+    /// Strength per pixel, scaled to 0-255
+    alpha: GrayImage,
+}
+
+impl RasterizedMask {
+    /// Rasterize a mask, sampling each pixel at its center.
+    ///
+    /// Callers taking sizes from outside should check them with
+    /// [`validate_dimensions`](crate::validate::validate_dimensions) first.
+    pub fn new(geometry: MaskGeometry, width: u32, height: u32) -> Self {
+        let (w_f, h_f) = (width as f32, height as f32);
+        let mut alpha = Vec::with_capacity(checked_pixel_count(width, height).unwrap_or(0));
+        for py in 0..height {
+            let y = (py as f32 + 0.5) / h_f;
+            alpha.extend((0..width).map(|px| {
+                let x = (px as f32 + 0.5) / w_f;
+                (geometry.evaluate(x, y).clamp(0.0, 1.0) * 255.0).round() as u8
+            }));
+        }
+
+        Self {
+            geometry,
+            alpha: GrayImage::new(width, height, alpha),
+        }
+    }
+
+    /// Rasterize a linear gradient mask.
+    pub fn from_linear(mask: &LinearGradientMask, width: u32, height: u32) -> Self {
+        Self::new(MaskGeometry::Linear(mask.clone()), width, height)
+    }
+
+    /// Rasterize a radial gradient mask.
+    pub fn from_radial(mask: &RadialGradientMask, width: u32, height: u32) -> Self {
+        Self::new(MaskGeometry::Radial(mask.clone()), width, height)
+    }
+
+    /// The geometry this raster was made from.
+    pub fn geometry(&self) -> &MaskGeometry {
+        &self.geometry
+    }
+
+    /// Raster width in pixels.
+    pub fn width(&self) -> u32 {
+        self.alpha.width
+    }
+
+    /// Raster height in pixels.
+    pub fn height(&self) -> u32 {
+        self.alpha.height
+    }
+
+    /// Strength per pixel, row-major, scaled to 0-255.
+    pub fn alpha(&self) -> &[u8] {
+        &self.alpha.pixels
+    }
+
+    /// Strength per pixel as a single-channel image, e.g. to draw the mask
+    /// as an overlay.
+    pub fn image(&self) -> &GrayImage {
+        &self.alpha
+    }
+
+    /// Whether this raster is still valid for `geometry` at `width x height`.
+    ///
+    /// A `false` result means the mask must be rasterized again.
+    pub fn is_current(&self, geometry: &MaskGeometry, width: u32, height: u32) -> bool {
+        self.alpha.width == width && self.alpha.height == height && self.geometry == *geometry
+    }
+}
+
+/// Where a mask layer's strength comes from.
+#[derive(Debug, Clone, Copy)]
+pub enum MaskAlpha<'a> {
+    /// Read from a raster
+    Cached(&'a RasterizedMask),
+    /// Evaluated from the geometry at each pixel
+    Geometry(&'a MaskGeometry),
+}
+
+/// Apply rasterized masks with their adjustments.
+///
+/// Gives the same result as [`apply_masked_adjustments`](super::apply_masked_adjustments)
+/// for the masks' geometries, within the error described in the
+/// [module docs](self), without evaluating them.
+/// Masks are applied in order.
+///
+/// # Errors
+/// Returns `MaskError::InvalidImage` if the image is empty or its buffer does
+/// not match its dimensions, or `MaskError::RasterSizeMismatch` if a raster
+/// was made for a different size. The pixels are left untouched in either
+/// case.
+pub fn apply_masked_adjustments_cached(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    masks: &[(RasterizedMask, BasicAdjustments)],
+) -> Result<(), MaskError> {
+    let layers: Vec<_> = masks
+        .iter()
+        .map(|(mask, adj)| (MaskAlpha::Cached(mask), adj))
+        .collect();
+    apply_masked_adjustments_mixed(pixels, width, height, &layers)
+}
+
+/// Apply a mix of rasterized and evaluated masks, in order.
+///
+/// Returns the same errors as [`apply_masked_adjustments_cached`].
+pub fn apply_masked_adjustments_mixed(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    layers: &[(MaskAlpha, &BasicAdjustments)],
+) -> Result<(), MaskError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    for (alpha, _) in layers {
+        if let MaskAlpha::Cached(mask) = alpha {
+            if mask.width() != width || mask.height() != height {
+                return Err(MaskError::RasterSizeMismatch {
+                    mask_width: mask.width(),
+                    mask_height: mask.height(),
+                    width,
+                    height,
+                });
+            }
+        }
+    }
+
+    let _perf = perf::scope("apply_masked_adjustments_cached");
+    perf::record_pixels((pixels.len() / 3) as u64);
+
+    let (w_f, h_f) = (width as f32, height as f32);
+    for (idx, chunk) in pixels.chunks_exact_mut(3).enumerate() {
+        let mut r = chunk[0] as f32 / 255.0;
+        let mut g = chunk[1] as f32 / 255.0;
+        let mut b = chunk[2] as f32 / 255.0;
+
+        for &(alpha, adj) in layers {
+            let mask_val = match alpha {
+                MaskAlpha::Cached(mask) => mask.alpha()[idx] as f32 / 255.0,
+                MaskAlpha::Geometry(geometry) => {
+                    let px = (idx as u32) % width;
+                    let py = (idx as u32) / width;
+                    geometry.evaluate((px as f32 + 0.5) / w_f, (py as f32 + 0.5) / h_f)
+                }
+            };
+            apply_masked_blend(&mut r, &mut g, &mut b, mask_val, adj, None);
+        }
+
+        chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+        chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask::apply_masked_adjustments;
+    use crate::testing::noise;
+
+    fn exposure(value: f32) -> BasicAdjustments {
+        BasicAdjustments {
+            exposure: value,
+            ..Default::default()
+        }
+    }
+
+    fn assert_within_one(a: &[u8], b: &[u8]) {
+        assert_eq!(a.len(), b.len());
+        for (i, (x, y)) in a.iter().zip(b).enumerate() {
+            assert!(x.abs_diff(*y) <= 1, "byte {}: {} vs {}", i, x, y);
+        }
+    }
+
+    #[test]
+    fn test_cached_matches_uncached() {
+        let image = noise(64, 48, 7);
+        let linear = LinearGradientMask::new(0.1, 0.2, 0.9, 0.7, 0.8);
+        let radial = RadialGradientMask::new(0.4, 0.6, 0.3, 0.2, 0.7, 0.9, false);
+        let adj_linear = exposure(-0.7);
+        let adj_radial = BasicAdjustments {
+            saturation: 40.0,
+            contrast: 25.0,
+            ..Default::default()
+        };
+
+        let mut uncached = image.pixels.clone();
+        apply_masked_adjustments(
+            &mut uncached,
+            64,
+            48,
+            &[(linear.clone(), adj_linear.clone())],
+            &[(radial.clone(), adj_radial.clone())],
+        )
+        .unwrap();
+
+        let mut cached = image.pixels.clone();
+        apply_masked_adjustments_cached(
+            &mut cached,
+            64,
+            48,
+            &[
+                (RasterizedMask::from_linear(&linear, 64, 48), adj_linear),
+                (RasterizedMask::from_radial(&radial, 64, 48), adj_radial),
+            ],
+        )
+        .unwrap();
+
+        assert_within_one(&cached, &uncached);
+    }
+
+    #[test]
+    fn test_cached_mask_reused_across_adjustments() {
+        let image = noise(40, 30, 3);
+        let radial = RadialGradientMask::new(0.5, 0.5, 0.4, 0.3, 0.3, 0.6, true);
+        let raster = RasterizedMask::from_radial(&radial, 40, 30);
+
+        for value in [-1.0, 0.5, 1.0] {
+            let mut uncached = image.pixels.clone();
+            apply_masked_adjustments(
+                &mut uncached,
+                40,
+                30,
+                &[],
+                &[(radial.clone(), exposure(value))],
+            )
+            .unwrap();
+
+            let mut cached = image.pixels.clone();
+            apply_masked_adjustments_cached(
+                &mut cached,
+                40,
+                30,
+                &[(raster.clone(), exposure(value))],
+            )
+            .unwrap();
+
+            assert_within_one(&cached, &uncached);
+        }
+    }
+
+    #[test]
+    fn test_mixed_layers_match_uncached() {
+        let image = noise(32, 32, 11);
+        let linear = MaskGeometry::Linear(LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 1.0));
+        let radial = RadialGradientMask::circle(0.5, 0.5, 0.3, 0.5);
+        let raster = RasterizedMask::from_radial(&radial, 32, 32);
+        let (adj_linear, adj_radial) = (exposure(1.0), exposure(-1.0));
+
+        let mut mixed = image.pixels.clone();
+        apply_masked_adjustments_mixed(
+            &mut mixed,
+            32,
+            32,
+            &[
+                (MaskAlpha::Geometry(&linear), &adj_linear),
+                (MaskAlpha::Cached(&raster), &adj_radial),
+            ],
+        )
+        .unwrap();
+
+        let MaskGeometry::Linear(linear) = linear else {
+            unreachable!()
+        };
+        let mut uncached = image.pixels.clone();
+        apply_masked_adjustments(
+            &mut uncached,
+            32,
+            32,
+            &[(linear, adj_linear)],
+            &[(radial, adj_radial)],
+        )
+        .unwrap();
+
+        assert_within_one(&mixed, &uncached);
+    }
+
+    #[test]
+    fn test_is_current_tracks_geometry_and_size() {
+        let radial = RadialGradientMask::circle(0.5, 0.5, 0.3, 0.5);
+        let raster = RasterizedMask::from_radial(&radial, 20, 10);
+        let geometry = MaskGeometry::Radial(radial.clone());
+
+        assert!(raster.is_current(&geometry, 20, 10));
+        assert!(!raster.is_current(&geometry, 10, 20));
+        let moved = MaskGeometry::Radial(RadialGradientMask {
+            center_x: 0.6,
+            ..radial
+        });
+        assert!(!raster.is_current(&moved, 20, 10));
+    }
+
+    #[test]
+    fn test_image_is_the_alpha_plane() {
+        let radial = RadialGradientMask::circle(0.5, 0.5, 0.3, 0.5);
+        let raster = RasterizedMask::from_radial(&radial, 20, 10);
+        let image = raster.image();
+
+        assert_eq!((image.width, image.height), (20, 10));
+        assert_eq!(image.pixels, raster.alpha());
+        assert_eq!(image.validate(), Ok(()));
+        // Overlays are drawn from a smaller copy
+        let overlay = crate::decode::resize(image, 10, 5, crate::decode::FilterType::Bilinear);
+        assert_eq!(overlay.unwrap().pixels.len(), 50);
+    }
+
+    #[test]
+    fn test_raster_size_mismatch_is_rejected() {
+        let raster =
+            RasterizedMask::from_linear(&LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 1.0), 8, 8);
+        let mut pixels = vec![100u8; 4 * 4 * 3];
+
+        let err = apply_masked_adjustments_cached(&mut pixels, 4, 4, &[(raster, exposure(1.0))])
+            .unwrap_err();
+
+        assert_eq!(err.code(), "MASK_RASTER_SIZE_MISMATCH");
+        assert_eq!(pixels, vec![100u8; 4 * 4 * 3]);
+    }
+}
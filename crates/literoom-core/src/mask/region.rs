@@ -0,0 +1,404 @@
+//! Incremental re-rendering while a mask is dragged
+//!
+//! Moving one mask only changes the pixels where its old and new strength
+//! differ. [`compute_mask_dirty_rect`] finds the bounding box of those
+//! pixels, and [`apply_masked_adjustments_region`] re-applies the mask stack
+//! inside it alone. Restoring the unmasked pixels in the rect and
+//! re-applying there gives the same bytes as a full re-render.
+
+use super::apply::{blend_pixel, resolve_curves, MASK_EPSILON};
+use super::{LinearGradientMask, RadialGradientMask};
+use crate::curve::ToneCurveLut;
+use crate::perf;
+use crate::validate::{validate_rgb_buffer, ImageError};
+use crate::BasicAdjustments;
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of whole pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PixelRect {
+    /// Left column
+    pub x: u32,
+    /// Top row
+    pub y: u32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl PixelRect {
+    /// The whole `width x height` image.
+    pub fn full(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// The part of the rect inside a `width x height` image.
+    pub fn clamped(&self, width: u32, height: u32) -> Self {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Self {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+}
+
+/// A mask shape whose changes can be localized.
+pub trait MaskShape {
+    /// Mask strength at normalized coordinates, as in the mask's `evaluate`.
+    fn strength(&self, x: f32, y: f32) -> f32;
+
+    /// Normalized bounding box `[left, top, right, bottom]` outside which
+    /// the strength is exactly [`MaskShape::outside_strength`], or `None`
+    /// if the mask reaches across the whole image.
+    fn bounds(&self) -> Option<[f32; 4]>;
+
+    /// Strength outside [`MaskShape::bounds`].
+    fn outside_strength(&self) -> f32;
+}
+
+impl MaskShape for LinearGradientMask {
+    fn strength(&self, x: f32, y: f32) -> f32 {
+        self.evaluate(x, y)
+    }
+
+    fn bounds(&self) -> Option<[f32; 4]> {
+        // Full effect on one side of the gradient, none on the other
+        None
+    }
+
+    fn outside_strength(&self) -> f32 {
+        0.0
+    }
+}
+
+impl MaskShape for RadialGradientMask {
+    fn strength(&self, x: f32, y: f32) -> f32 {
+        self.evaluate(x, y)
+    }
+
+    fn bounds(&self) -> Option<[f32; 4]> {
+        // The feathered edge lies inside the ellipse, so the ellipse's
+        // bounding box covers it
+        let (rx, ry) = (self.radius_x.max(0.001), self.radius_y.max(0.001));
+        let (sin, cos) = self.rotation.sin_cos();
+        let half_w = ((rx * cos).powi(2) + (ry * sin).powi(2)).sqrt();
+        let half_h = ((rx * sin).powi(2) + (ry * cos).powi(2)).sqrt();
+        Some([
+            self.center_x - half_w,
+            self.center_y - half_h,
+            self.center_x + half_w,
+            self.center_y + half_h,
+        ])
+    }
+
+    fn outside_strength(&self) -> f32 {
+        if self.invert {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Bounding box of the pixels whose output changes when a mask goes from
+/// `old_mask` to `new_mask`.
+///
+/// A pixel counts as changed if the two strengths at its center differ,
+/// treating strengths too small to have an effect as zero. The rect
+/// includes the feathered edges of both masks. Radial masks are only
+/// checked within their ellipses, so a small drag costs little; linear
+/// masks reach across the image and are checked everywhere.
+///
+/// Returns `None` if no pixel changes.
+pub fn compute_mask_dirty_rect<M: MaskShape>(
+    old_mask: &M,
+    new_mask: &M,
+    width: u32,
+    height: u32,
+) -> Option<PixelRect> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let (w_f, h_f) = (width as f32, height as f32);
+
+    let candidates = match (old_mask.bounds(), new_mask.bounds()) {
+        (Some(a), Some(b)) if old_mask.outside_strength() == new_mask.outside_strength() => {
+            // Pixels whose centers may fall inside either box, plus one
+            // pixel of slack for rounding
+            let to_px = |v: f32, size: f32| (v * size - 0.5).clamp(0.0, size) as u32;
+            let x0 = to_px(a[0].min(b[0]), w_f).saturating_sub(1);
+            let y0 = to_px(a[1].min(b[1]), h_f).saturating_sub(1);
+            let x1 = (to_px(a[2].max(b[2]), w_f) + 2).min(width);
+            let y1 = (to_px(a[3].max(b[3]), h_f) + 2).min(height);
+            PixelRect {
+                x: x0,
+                y: y0,
+                width: x1.saturating_sub(x0),
+                height: y1.saturating_sub(y0),
+            }
+        }
+        _ => PixelRect::full(width, height),
+    };
+
+    let effective = |v: f32| if v < MASK_EPSILON { 0.0 } else { v };
+    let mut dirty: Option<(u32, u32, u32, u32)> = None;
+    for py in candidates.y..candidates.y + candidates.height {
+        let y = (py as f32 + 0.5) / h_f;
+        for px in candidates.x..candidates.x + candidates.width {
+            let x = (px as f32 + 0.5) / w_f;
+            if effective(old_mask.strength(x, y)) != effective(new_mask.strength(x, y)) {
+                dirty = Some(match dirty {
+                    None => (px, py, px, py),
+                    Some((x0, y0, x1, y1)) => (x0.min(px), y0.min(py), x1.max(px), y1.max(py)),
+                });
+            }
+        }
+    }
+
+    dirty.map(|(x0, y0, x1, y1)| PixelRect {
+        x: x0,
+        y: y0,
+        width: x1 - x0 + 1,
+        height: y1 - y0 + 1,
+    })
+}
+
+/// Apply masked adjustments to the pixels inside `region` only.
+///
+/// Inside the region the result is byte-identical to
+/// [`super::apply_masked_adjustments_with_curves`] on the whole image;
+/// pixels outside are not touched. To re-render after a mask moved, copy the
+/// unmasked pixels of the [`compute_mask_dirty_rect`] rect over the previous
+/// output and apply the new stack to that rect.
+///
+/// The region is clipped to the image.
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`. The pixels are left untouched in that case.
+pub fn apply_masked_adjustments_region(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    region: &PixelRect,
+    linear_masks: &[(LinearGradientMask, BasicAdjustments, Option<ToneCurveLut>)],
+    radial_masks: &[(RadialGradientMask, BasicAdjustments, Option<ToneCurveLut>)],
+) -> Result<(), ImageError> {
+    validate_rgb_buffer(pixels, width, height)?;
+    let region = region.clamped(width, height);
+
+    let _perf = perf::scope("apply_masked_adjustments");
+    perf::record_dimensions(width, height);
+    perf::record_pixels(region.width as u64 * region.height as u64);
+
+    if linear_masks.is_empty() && radial_masks.is_empty() {
+        return Ok(());
+    }
+    let linear = resolve_curves(linear_masks);
+    let radial = resolve_curves(radial_masks);
+
+    let (w_f, h_f) = (width as f32, height as f32);
+    let row_len = width as usize * 3;
+    let (start, end) = (
+        region.x as usize * 3,
+        (region.x + region.width) as usize * 3,
+    );
+    for py in region.y..region.y + region.height {
+        let row = &mut pixels[py as usize * row_len..][..row_len];
+        let y = (py as f32 + 0.5) / h_f;
+        for (i, chunk) in row[start..end].chunks_exact_mut(3).enumerate() {
+            let x = ((region.x + i as u32) as f32 + 0.5) / w_f;
+            blend_pixel(chunk, x, y, &linear, &radial);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask::apply_masked_adjustments_with_curves;
+    use crate::testing::noise;
+
+    type Layers<M> = Vec<(M, BasicAdjustments, Option<ToneCurveLut>)>;
+
+    fn exposure(value: f32) -> BasicAdjustments {
+        BasicAdjustments {
+            exposure: value,
+            ..Default::default()
+        }
+    }
+
+    fn render(
+        base: &[u8],
+        width: u32,
+        height: u32,
+        linear: &Layers<LinearGradientMask>,
+        radial: &Layers<RadialGradientMask>,
+    ) -> Vec<u8> {
+        let mut pixels = base.to_vec();
+        apply_masked_adjustments_with_curves(&mut pixels, width, height, linear, radial).unwrap();
+        pixels
+    }
+
+    /// Assert every pixel that differs between `a` and `b` lies in `rect`.
+    fn assert_contains_changes(rect: Option<PixelRect>, a: &[u8], b: &[u8], width: u32) {
+        for (idx, (pa, pb)) in a.chunks_exact(3).zip(b.chunks_exact(3)).enumerate() {
+            if pa == pb {
+                continue;
+            }
+            let (px, py) = (idx as u32 % width, idx as u32 / width);
+            let r = rect.unwrap_or_else(|| panic!("({}, {}) changed, no dirty rect", px, py));
+            assert!(
+                (r.x..r.x + r.width).contains(&px) && (r.y..r.y + r.height).contains(&py),
+                "({}, {}) changed outside {:?}",
+                px,
+                py,
+                r
+            );
+        }
+    }
+
+    /// Re-render `rect` of `previous` from `base` with the new stack.
+    fn rerender(
+        base: &[u8],
+        previous: &[u8],
+        width: u32,
+        height: u32,
+        rect: &PixelRect,
+        linear: &Layers<LinearGradientMask>,
+        radial: &Layers<RadialGradientMask>,
+    ) -> Vec<u8> {
+        let mut pixels = previous.to_vec();
+        let row_len = width as usize * 3;
+        let (start, end) = (rect.x as usize * 3, (rect.x + rect.width) as usize * 3);
+        for py in rect.y..rect.y + rect.height {
+            let row = py as usize * row_len;
+            pixels[row + start..row + end].copy_from_slice(&base[row + start..row + end]);
+        }
+        apply_masked_adjustments_region(&mut pixels, width, height, rect, linear, radial).unwrap();
+        pixels
+    }
+
+    #[test]
+    fn test_radial_drag_rerender_matches_full_render() {
+        let (width, height) = (96, 64);
+        let base = noise(width, height, 21).pixels;
+        let linear = vec![(
+            LinearGradientMask::new(0.0, 0.0, 0.0, 1.0, 0.8),
+            exposure(-0.5),
+            None,
+        )];
+        let old = RadialGradientMask::new(0.4, 0.5, 0.15, 0.2, 0.3, 0.5, false);
+        let new = RadialGradientMask {
+            center_x: 0.43,
+            center_y: 0.48,
+            ..old.clone()
+        };
+
+        let previous = render(
+            &base,
+            width,
+            height,
+            &linear,
+            &vec![(old.clone(), exposure(1.0), None)],
+        );
+        let radial = vec![(new.clone(), exposure(1.0), None)];
+        let full = render(&base, width, height, &linear, &radial);
+
+        let rect = compute_mask_dirty_rect(&old, &new, width, height).unwrap();
+        assert!(rect.width < width / 2 && rect.height < height, "{:?}", rect);
+        assert_contains_changes(Some(rect), &previous, &full, width);
+
+        let incremental = rerender(&base, &previous, width, height, &rect, &linear, &radial);
+        assert_eq!(incremental, full);
+    }
+
+    #[test]
+    fn test_inverted_and_linear_masks_are_covered() {
+        let (width, height) = (40, 30);
+        let base = noise(width, height, 4).pixels;
+
+        let old = RadialGradientMask::new(0.5, 0.5, 0.2, 0.2, 0.0, 0.4, true);
+        let new = RadialGradientMask {
+            radius_x: 0.25,
+            ..old.clone()
+        };
+        let before = render(
+            &base,
+            width,
+            height,
+            &vec![],
+            &vec![(old.clone(), exposure(1.0), None)],
+        );
+        let after = render(
+            &base,
+            width,
+            height,
+            &vec![],
+            &vec![(new.clone(), exposure(1.0), None)],
+        );
+        let rect = compute_mask_dirty_rect(&old, &new, width, height);
+        assert_contains_changes(rect, &before, &after, width);
+
+        let old = LinearGradientMask::new(0.2, 0.0, 0.4, 0.0, 0.5);
+        let new = LinearGradientMask::new(0.3, 0.0, 0.5, 0.0, 0.5);
+        let radial: Layers<RadialGradientMask> = vec![];
+        let linear = vec![(new.clone(), exposure(-1.0), None)];
+        let before = render(
+            &base,
+            width,
+            height,
+            &vec![(old.clone(), exposure(-1.0), None)],
+            &radial,
+        );
+        let after = render(&base, width, height, &linear, &radial);
+        let rect = compute_mask_dirty_rect(&old, &new, width, height).unwrap();
+        assert_contains_changes(Some(rect), &before, &after, width);
+        // A vertical band only moves columns, but on every row
+        assert_eq!((rect.y, rect.height), (0, height));
+        assert!(rect.width < width);
+        let incremental = rerender(&base, &before, width, height, &rect, &linear, &radial);
+        assert_eq!(incremental, after);
+    }
+
+    #[test]
+    fn test_unchanged_mask_has_no_dirty_rect() {
+        let mask = RadialGradientMask::circle(0.5, 0.5, 0.3, 0.5);
+        assert_eq!(compute_mask_dirty_rect(&mask, &mask, 64, 64), None);
+        assert_eq!(compute_mask_dirty_rect(&mask, &mask, 0, 64), None);
+    }
+
+    #[test]
+    fn test_region_outside_image_is_clipped() {
+        let mut pixels = noise(8, 8, 1).pixels;
+        let original = pixels.clone();
+        let region = PixelRect {
+            x: 6,
+            y: 6,
+            width: 10,
+            height: 10,
+        };
+        let radial = vec![(
+            RadialGradientMask::circle(0.5, 0.5, 1.0, 0.0),
+            exposure(1.0),
+            None,
+        )];
+        apply_masked_adjustments_region(&mut pixels, 8, 8, &region, &[], &radial).unwrap();
+        assert_eq!(pixels[..6 * 8 * 3], original[..6 * 8 * 3]);
+        assert_ne!(pixels, original);
+
+        let result = apply_masked_adjustments_region(&mut pixels, 8, 9, &region, &[], &radial);
+        assert!(matches!(result, Err(ImageError::BufferSizeMismatch { .. })));
+    }
+}
@@ -0,0 +1,328 @@
+//! Solo and bypass for masks in a stack
+//!
+//! While fine-tuning one mask among many, the editor renders the image with
+//! only that mask (solo) or with some masks switched off (bypass). Masks are
+//! referred to by id, so the selection stays valid when masks are reordered
+//! or added.
+
+use super::apply::apply_masked_adjustments_with_curves;
+use super::brush::{apply_brush_layers, BrushMask};
+use super::{LinearGradientMask, RadialGradientMask};
+use crate::curve::ToneCurveLut;
+use crate::validate::ImageError;
+use crate::BasicAdjustments;
+use thiserror::Error;
+
+/// A mask as the editor stores it, with the id used to solo or bypass it.
+#[derive(Debug, Clone)]
+pub struct StackMask<M> {
+    /// Stable identifier; masks without one cannot be soloed or bypassed
+    pub id: Option<String>,
+    /// Disabled masks are never rendered, even when soloed
+    pub enabled: bool,
+    /// Mask geometry
+    pub mask: M,
+    /// Adjustments applied inside the mask
+    pub adjustments: BasicAdjustments,
+    /// Optional tone curve applied after the adjustments
+    pub curve: Option<ToneCurveLut>,
+}
+
+/// Which masks of a stack to render.
+///
+/// The default renders every enabled mask.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaskSelection {
+    /// Render only the mask with this id
+    pub solo: Option<String>,
+    /// Skip the masks with these ids (also applies to the soloed mask)
+    pub bypass: Vec<String>,
+}
+
+/// Errors from [`apply_selected_masks`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MaskError {
+    /// The selection names a mask that is not in the stack.
+    #[error("Unknown mask id '{id}' (known ids: {})", .known.join(", "))]
+    UnknownId { id: String, known: Vec<String> },
+
+    /// A mask was picked by a position past the end of the stack.
+    #[error("Mask index {index} is out of range for a stack of {len}")]
+    IndexOutOfRange { index: usize, len: usize },
+
+    /// Two masks in an ordered stack share an id.
+    #[error("Duplicate mask id '{id}'")]
+    DuplicateId { id: String },
+
+    /// A rasterized mask was made for a different image size.
+    #[error("Mask was rasterized at {mask_width}x{mask_height}, image is {width}x{height}")]
+    RasterSizeMismatch {
+        mask_width: u32,
+        mask_height: u32,
+        width: u32,
+        height: u32,
+    },
+
+    /// A brush stroke is empty or has an out-of-range parameter.
+    #[error("Invalid brush stroke: {0}")]
+    InvalidStroke(&'static str),
+
+    /// The image is empty or its buffer does not match its dimensions.
+    #[error(transparent)]
+    InvalidImage(#[from] ImageError),
+}
+
+impl MaskError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MaskError::UnknownId { .. } => "UNKNOWN_MASK_ID",
+            MaskError::IndexOutOfRange { .. } => "MASK_INDEX_OUT_OF_RANGE",
+            MaskError::DuplicateId { .. } => "DUPLICATE_MASK_ID",
+            MaskError::RasterSizeMismatch { .. } => "MASK_RASTER_SIZE_MISMATCH",
+            MaskError::InvalidStroke(_) => "INVALID_BRUSH_STROKE",
+            MaskError::InvalidImage(e) => e.code(),
+        }
+    }
+}
+
+impl MaskSelection {
+    /// Whether a mask with `id` is rendered under this selection.
+    pub fn includes(&self, id: Option<&str>) -> bool {
+        let soloed = match &self.solo {
+            Some(solo) => id == Some(solo.as_str()),
+            None => true,
+        };
+        let bypassed = id.is_some_and(|id| self.bypass.iter().any(|b| b == id));
+        soloed && !bypassed
+    }
+
+    /// Check that every id the selection refers to is in `known`.
+    pub fn check_ids<'a>(
+        &self,
+        known: impl IntoIterator<Item = &'a str> + Clone,
+    ) -> Result<(), MaskError> {
+        for id in self.solo.iter().chain(&self.bypass) {
+            if !known.clone().into_iter().any(|k| k == id) {
+                return Err(MaskError::UnknownId {
+                    id: id.clone(),
+                    known: known.into_iter().map(str::to_string).collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Apply the enabled masks of a stack that `selection` includes.
+///
+/// Disabled masks are skipped, and so are masks the selection solos out or
+/// bypasses. The remaining masks are applied exactly as by
+/// [`apply_masked_adjustments_with_curves`], so soloing mask B gives the same
+/// pixels as a stack containing only B.
+///
+/// # Errors
+/// Returns `MaskError::UnknownId` if the selection refers to an id that no
+/// mask in the stack has (disabled masks count as known), or
+/// `MaskError::InvalidImage` if the image is empty or its buffer does not
+/// match its dimensions. The pixels are left untouched in either case.
+pub fn apply_selected_masks(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    linear_masks: &[StackMask<LinearGradientMask>],
+    radial_masks: &[StackMask<RadialGradientMask>],
+    selection: &MaskSelection,
+) -> Result<(), MaskError> {
+    apply_selected_mask_stack(
+        pixels,
+        width,
+        height,
+        linear_masks,
+        radial_masks,
+        &[],
+        selection,
+    )
+}
+
+/// Apply the enabled masks of a stack with brush masks that `selection`
+/// includes.
+///
+/// Like [`apply_selected_masks`], with brush masks applied in order after
+/// the linear and radial masks. Brush mask ids count as known.
+///
+/// # Errors
+/// Returns the same errors as [`apply_selected_masks`].
+pub fn apply_selected_mask_stack(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    linear_masks: &[StackMask<LinearGradientMask>],
+    radial_masks: &[StackMask<RadialGradientMask>],
+    brush_masks: &[StackMask<BrushMask>],
+    selection: &MaskSelection,
+) -> Result<(), MaskError> {
+    let known = linear_masks
+        .iter()
+        .filter_map(|m| m.id.as_deref())
+        .chain(radial_masks.iter().filter_map(|m| m.id.as_deref()))
+        .chain(brush_masks.iter().filter_map(|m| m.id.as_deref()));
+    selection.check_ids(known)?;
+
+    let linear = selected_layers(linear_masks, selection);
+    let radial = selected_layers(radial_masks, selection);
+    apply_masked_adjustments_with_curves(pixels, width, height, &linear, &radial)?;
+
+    // Borrowed rather than cloned, as alpha planes can be large
+    let brush: Vec<_> = brush_masks
+        .iter()
+        .filter(|m| m.enabled && selection.includes(m.id.as_deref()))
+        .map(|m| {
+            (
+                &m.mask,
+                &m.adjustments,
+                m.curve.as_ref().filter(|lut| !lut.is_identity()),
+            )
+        })
+        .collect();
+    apply_brush_layers(pixels, width, height, &brush);
+    Ok(())
+}
+
+/// Layers of the masks that are enabled and included by `selection`.
+fn selected_layers<M: Clone>(
+    masks: &[StackMask<M>],
+    selection: &MaskSelection,
+) -> Vec<(M, BasicAdjustments, Option<ToneCurveLut>)> {
+    masks
+        .iter()
+        .filter(|m| m.enabled && selection.includes(m.id.as_deref()))
+        .map(|m| (m.mask.clone(), m.adjustments.clone(), m.curve.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{gradient, GradientDirection};
+
+    fn stack_mask<M>(id: &str, mask: M, exposure: f32) -> StackMask<M> {
+        StackMask {
+            id: Some(id.to_string()),
+            enabled: true,
+            mask,
+            adjustments: BasicAdjustments {
+                exposure,
+                ..Default::default()
+            },
+            curve: None,
+        }
+    }
+
+    fn two_mask_stack() -> (
+        Vec<StackMask<LinearGradientMask>>,
+        Vec<StackMask<RadialGradientMask>>,
+    ) {
+        let a = stack_mask("a", LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 0.8), 1.0);
+        let b = stack_mask("b", RadialGradientMask::circle(0.6, 0.4, 0.3, 0.5), -0.7);
+        (vec![a], vec![b])
+    }
+
+    fn render(
+        linear: &[StackMask<LinearGradientMask>],
+        radial: &[StackMask<RadialGradientMask>],
+        selection: &MaskSelection,
+    ) -> Result<Vec<u8>, MaskError> {
+        let mut pixels = gradient(32, 24, GradientDirection::Diagonal).pixels;
+        apply_selected_masks(&mut pixels, 32, 24, linear, radial, selection)?;
+        Ok(pixels)
+    }
+
+    #[test]
+    fn test_default_selection_renders_all() {
+        let (linear, radial) = two_mask_stack();
+        let all = render(&linear, &radial, &MaskSelection::default()).unwrap();
+
+        let mut expected = gradient(32, 24, GradientDirection::Diagonal).pixels;
+        apply_masked_adjustments_with_curves(
+            &mut expected,
+            32,
+            24,
+            &[(linear[0].mask.clone(), linear[0].adjustments.clone(), None)],
+            &[(radial[0].mask.clone(), radial[0].adjustments.clone(), None)],
+        )
+        .unwrap();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn test_solo_equals_only_that_mask() {
+        let (linear, radial) = two_mask_stack();
+        let solo = MaskSelection {
+            solo: Some("b".to_string()),
+            ..Default::default()
+        };
+        let soloed = render(&linear, &radial, &solo).unwrap();
+        let only_b = render(&[], &radial, &MaskSelection::default()).unwrap();
+        assert_eq!(soloed, only_b);
+    }
+
+    #[test]
+    fn test_bypass_equals_stack_without_that_mask() {
+        let (linear, radial) = two_mask_stack();
+        let bypass = MaskSelection {
+            bypass: vec!["b".to_string()],
+            ..Default::default()
+        };
+        let bypassed = render(&linear, &radial, &bypass).unwrap();
+        let only_a = render(&linear, &[], &MaskSelection::default()).unwrap();
+        assert_eq!(bypassed, only_a);
+    }
+
+    #[test]
+    fn test_disabled_mask_stays_off_when_soloed() {
+        let (linear, mut radial) = two_mask_stack();
+        radial[0].enabled = false;
+        let solo = MaskSelection {
+            solo: Some("b".to_string()),
+            ..Default::default()
+        };
+        let soloed = render(&linear, &radial, &solo).unwrap();
+        assert_eq!(soloed, gradient(32, 24, GradientDirection::Diagonal).pixels);
+    }
+
+    #[test]
+    fn test_unknown_id_lists_known_ids() {
+        let (linear, radial) = two_mask_stack();
+        let selection = MaskSelection {
+            bypass: vec!["a".to_string(), "c".to_string()],
+            ..Default::default()
+        };
+        let err = render(&linear, &radial, &selection).unwrap_err();
+        assert_eq!(
+            err,
+            MaskError::UnknownId {
+                id: "c".to_string(),
+                known: vec!["a".to_string(), "b".to_string()],
+            }
+        );
+        assert_eq!(err.code(), "UNKNOWN_MASK_ID");
+        assert_eq!(err.to_string(), "Unknown mask id 'c' (known ids: a, b)");
+    }
+
+    #[test]
+    fn test_masks_without_id_are_always_included() {
+        let selection = MaskSelection {
+            bypass: vec!["x".to_string()],
+            ..Default::default()
+        };
+        assert!(selection.includes(None));
+        assert!(!selection.includes(Some("x")));
+
+        let solo = MaskSelection {
+            solo: Some("x".to_string()),
+            ..Default::default()
+        };
+        assert!(!solo.includes(None));
+    }
+}
@@ -0,0 +1,484 @@
+//! Masks of every kind in one user-defined order
+//!
+//! Masks apply one after another, each to the result of the ones before, so
+//! where two masks overlap their order can change the render. A dark midtone
+//! brightened past middle gray and then given contrast ends up brighter,
+//! while contrast applied first darkens it before it is brightened.
+//! [`MaskEntry`] holds a mask of any kind, so a stack
+//! can interleave kinds in the order the user arranged them, and
+//! [`apply_ordered_masks`] applies it strictly in that order.
+//!
+//! Stacks from before masks had a single order kept one list per kind and
+//! applied all linear masks, then all radial masks, then all brush masks.
+//! [`legacy_mask_order`] converts them into that same order, so they render
+//! as they always did.
+
+use std::collections::HashSet;
+
+use super::apply::apply_masked_blend;
+use super::brush::BrushMask;
+use super::region::PixelRect;
+use super::selection::{MaskError, MaskSelection, StackMask};
+use super::strip::StripMask;
+use super::{LinearGradientMask, RadialGradientMask};
+use crate::curve::ToneCurveLut;
+use crate::perf;
+use crate::validate::validate_rgb_buffer;
+use crate::BasicAdjustments;
+
+/// A mask of any kind in an ordered stack.
+#[derive(Debug, Clone)]
+pub enum MaskEntry {
+    /// Linear gradient mask
+    Linear(StackMask<LinearGradientMask>),
+    /// Radial gradient mask
+    Radial(StackMask<RadialGradientMask>),
+    /// Brush mask
+    Brush(StackMask<BrushMask>),
+    /// Strip mask
+    Strip(StackMask<StripMask>),
+}
+
+impl MaskEntry {
+    /// The mask's id, if it has one.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            MaskEntry::Linear(m) => m.id.as_deref(),
+            MaskEntry::Radial(m) => m.id.as_deref(),
+            MaskEntry::Brush(m) => m.id.as_deref(),
+            MaskEntry::Strip(m) => m.id.as_deref(),
+        }
+    }
+
+    /// Whether the mask is rendered at all.
+    pub fn enabled(&self) -> bool {
+        match self {
+            MaskEntry::Linear(m) => m.enabled,
+            MaskEntry::Radial(m) => m.enabled,
+            MaskEntry::Brush(m) => m.enabled,
+            MaskEntry::Strip(m) => m.enabled,
+        }
+    }
+
+    /// Mask strength at normalized coordinates (0.0 to 1.0).
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        match self {
+            MaskEntry::Linear(m) => m.mask.evaluate(x, y),
+            MaskEntry::Radial(m) => m.mask.evaluate(x, y),
+            MaskEntry::Brush(m) => m.mask.evaluate(x, y),
+            MaskEntry::Strip(m) => m.mask.evaluate(x, y),
+        }
+    }
+
+    /// The adjustments and curve the mask applies, with identity curves
+    /// resolved to `None`.
+    fn layer(&self) -> (&BasicAdjustments, Option<&ToneCurveLut>) {
+        let (adjustments, curve) = match self {
+            MaskEntry::Linear(m) => (&m.adjustments, &m.curve),
+            MaskEntry::Radial(m) => (&m.adjustments, &m.curve),
+            MaskEntry::Brush(m) => (&m.adjustments, &m.curve),
+            MaskEntry::Strip(m) => (&m.adjustments, &m.curve),
+        };
+        (adjustments, curve.as_ref().filter(|lut| !lut.is_identity()))
+    }
+}
+
+impl From<StackMask<LinearGradientMask>> for MaskEntry {
+    fn from(mask: StackMask<LinearGradientMask>) -> Self {
+        MaskEntry::Linear(mask)
+    }
+}
+
+impl From<StackMask<RadialGradientMask>> for MaskEntry {
+    fn from(mask: StackMask<RadialGradientMask>) -> Self {
+        MaskEntry::Radial(mask)
+    }
+}
+
+impl From<StackMask<BrushMask>> for MaskEntry {
+    fn from(mask: StackMask<BrushMask>) -> Self {
+        MaskEntry::Brush(mask)
+    }
+}
+
+impl From<StackMask<StripMask>> for MaskEntry {
+    fn from(mask: StackMask<StripMask>) -> Self {
+        MaskEntry::Strip(mask)
+    }
+}
+
+/// One ordered stack from per-kind lists, in the order such stacks have
+/// always been applied: linear masks, then radial masks, then brush masks.
+pub fn legacy_mask_order(
+    linear: Vec<StackMask<LinearGradientMask>>,
+    radial: Vec<StackMask<RadialGradientMask>>,
+    brush: Vec<StackMask<BrushMask>>,
+) -> Vec<MaskEntry> {
+    let linear = linear.into_iter().map(MaskEntry::from);
+    let radial = radial.into_iter().map(MaskEntry::from);
+    let brush = brush.into_iter().map(MaskEntry::from);
+    linear.chain(radial).chain(brush).collect()
+}
+
+/// Check that no two masks share an id.
+///
+/// # Errors
+/// Returns `MaskError::DuplicateId` naming the first repeated id.
+pub fn check_unique_ids(masks: &[MaskEntry]) -> Result<(), MaskError> {
+    let mut seen = HashSet::new();
+    for id in masks.iter().filter_map(MaskEntry::id) {
+        if !seen.insert(id) {
+            return Err(MaskError::DuplicateId { id: id.to_string() });
+        }
+    }
+    Ok(())
+}
+
+/// Apply the enabled masks of an ordered stack that `selection` includes,
+/// strictly in array order.
+///
+/// Each mask blends its adjusted (and curved) color over the result of the
+/// masks before it, as [`apply_masked_adjustments`] does for its masks. A
+/// stack from [`legacy_mask_order`] without brush masks renders exactly as
+/// [`apply_selected_masks`] renders the per-kind lists. With brush masks it
+/// can differ from [`apply_selected_mask_stack`] by one level, as that
+/// rounds to 8 bits between the gradient masks and the brush masks.
+///
+/// # Errors
+/// Returns `MaskError::DuplicateId` if two masks share an id,
+/// `MaskError::UnknownId` if the selection refers to an id that no mask in
+/// the stack has (disabled masks count as known), or
+/// `MaskError::InvalidImage` if the image is empty or its buffer does not
+/// match its dimensions. The pixels are left untouched in every case.
+///
+/// [`apply_masked_adjustments`]: super::apply_masked_adjustments
+/// [`apply_selected_masks`]: super::apply_selected_masks
+/// [`apply_selected_mask_stack`]: super::apply_selected_mask_stack
+pub fn apply_ordered_masks(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    masks: &[MaskEntry],
+    selection: &MaskSelection,
+) -> Result<(), MaskError> {
+    check_unique_ids(masks)?;
+    selection.check_ids(masks.iter().filter_map(MaskEntry::id))?;
+    validate_rgb_buffer(pixels, width, height)?;
+
+    let layers = selected_layers(masks, |m| selection.includes(m.id()));
+    let region = PixelRect::full(width, height);
+    apply_layers_in_region(pixels, width, height, &region, &layers);
+    Ok(())
+}
+
+/// Apply the enabled masks of an ordered stack inside one rect of an image.
+///
+/// The ordered counterpart of
+/// [`apply_masked_adjustments_region`](super::apply_masked_adjustments_region):
+/// pixels inside the rect come out as [`apply_ordered_masks`] would render
+/// them, and pixels outside it are untouched. The rect is clipped to the
+/// image.
+///
+/// # Errors
+/// Returns `MaskError::DuplicateId` if two masks share an id, or
+/// `MaskError::InvalidImage` if the image is empty or its buffer does not
+/// match its dimensions. The pixels are left untouched in either case.
+pub fn apply_ordered_masks_region(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    region: &PixelRect,
+    masks: &[MaskEntry],
+) -> Result<(), MaskError> {
+    check_unique_ids(masks)?;
+    validate_rgb_buffer(pixels, width, height)?;
+
+    let layers = selected_layers(masks, |_| true);
+    let region = region.clamped(width, height);
+    apply_layers_in_region(pixels, width, height, &region, &layers);
+    Ok(())
+}
+
+/// A mask with the adjustments and curve it applies.
+type EntryLayer<'a> = (
+    &'a MaskEntry,
+    &'a BasicAdjustments,
+    Option<&'a ToneCurveLut>,
+);
+
+/// Layers of the enabled masks that `include` accepts, in order.
+fn selected_layers(
+    masks: &[MaskEntry],
+    include: impl Fn(&MaskEntry) -> bool,
+) -> Vec<EntryLayer<'_>> {
+    masks
+        .iter()
+        .filter(|m| m.enabled() && include(m))
+        .map(|m| {
+            let (adjustments, curve) = m.layer();
+            (m, adjustments, curve)
+        })
+        .collect()
+}
+
+/// Blend every layer, in order, into the pixels of a valid buffer inside a
+/// rect already clipped to it.
+fn apply_layers_in_region(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    region: &PixelRect,
+    layers: &[EntryLayer],
+) {
+    let _perf = perf::scope("apply_masked_adjustments");
+    perf::record_dimensions(width, height);
+    perf::record_pixels(region.width as u64 * region.height as u64);
+
+    if layers.is_empty() {
+        return;
+    }
+    let (w_f, h_f) = (width as f32, height as f32);
+    let row_len = width as usize * 3;
+    let (start, end) = (
+        region.x as usize * 3,
+        (region.x + region.width) as usize * 3,
+    );
+    for py in region.y..region.y + region.height {
+        let row = &mut pixels[py as usize * row_len..][..row_len];
+        let y = (py as f32 + 0.5) / h_f;
+        for (i, chunk) in row[start..end].chunks_exact_mut(3).enumerate() {
+            let x = ((region.x + i as u32) as f32 + 0.5) / w_f;
+
+            let mut r = chunk[0] as f32 / 255.0;
+            let mut g = chunk[1] as f32 / 255.0;
+            let mut b = chunk[2] as f32 / 255.0;
+            for &(mask, adj, curve) in layers {
+                apply_masked_blend(&mut r, &mut g, &mut b, mask.evaluate(x, y), adj, curve);
+            }
+            chunk[0] = (r.clamp(0.0, 1.0) * 255.0) as u8;
+            chunk[1] = (g.clamp(0.0, 1.0) * 255.0) as u8;
+            chunk[2] = (b.clamp(0.0, 1.0) * 255.0) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask::apply_selected_masks;
+    use crate::testing::noise;
+
+    fn stack_mask<M>(id: &str, mask: M, adjustments: BasicAdjustments) -> StackMask<M> {
+        StackMask {
+            id: Some(id.to_string()),
+            enabled: true,
+            mask,
+            adjustments,
+            curve: None,
+        }
+    }
+
+    fn brighten() -> BasicAdjustments {
+        BasicAdjustments {
+            exposure: 0.5,
+            ..Default::default()
+        }
+    }
+
+    fn desaturate() -> BasicAdjustments {
+        BasicAdjustments {
+            saturation: -100.0,
+            ..Default::default()
+        }
+    }
+
+    fn render(masks: &[MaskEntry]) -> Vec<u8> {
+        let mut pixels = vec![100, 100, 100];
+        apply_ordered_masks(&mut pixels, 1, 1, masks, &MaskSelection::default()).unwrap();
+        pixels
+    }
+
+    #[test]
+    fn test_legacy_order_matches_per_kind_lists() {
+        let image = noise(48, 32, 11);
+        let linear = vec![
+            stack_mask(
+                "a",
+                LinearGradientMask::new(0.1, 0.2, 0.9, 0.8, 0.6),
+                brighten(),
+            ),
+            stack_mask(
+                "b",
+                LinearGradientMask::new(0.9, 0.1, 0.2, 0.5, 0.3),
+                desaturate(),
+            ),
+        ];
+        let radial = vec![stack_mask(
+            "c",
+            RadialGradientMask::new(0.4, 0.6, 0.3, 0.2, 0.5, 0.7, false),
+            BasicAdjustments {
+                contrast: 40.0,
+                temperature: -30.0,
+                ..Default::default()
+            },
+        )];
+        let selection = MaskSelection {
+            solo: None,
+            bypass: vec!["b".to_string()],
+        };
+
+        let mut expected = image.pixels.clone();
+        apply_selected_masks(&mut expected, 48, 32, &linear, &radial, &selection).unwrap();
+        let mut ordered = image.pixels.clone();
+        let masks = legacy_mask_order(linear, radial, vec![]);
+        apply_ordered_masks(&mut ordered, 48, 32, &masks, &selection).unwrap();
+        assert_eq!(ordered, expected);
+    }
+
+    #[test]
+    fn test_order_of_overlapping_masks_is_honored() {
+        // Both masks cover the whole image
+        let everywhere = || RadialGradientMask::circle(0.5, 0.5, 2.0, 0.0);
+        let bright = MaskEntry::from(stack_mask("bright", everywhere(), brighten()));
+        let contrast = MaskEntry::from(stack_mask(
+            "contrast",
+            everywhere(),
+            BasicAdjustments {
+                contrast: 60.0,
+                ..Default::default()
+            },
+        ));
+
+        // Brightened past middle gray, contrast pushes the pixel up; below
+        // middle gray, contrast pushes it down before it is brightened
+        let brighten_first = render(&[bright.clone(), contrast.clone()]);
+        let contrast_first = render(&[contrast, bright]);
+        assert!(brighten_first[0] > contrast_first[0]);
+        assert!(brighten_first[1] > contrast_first[1]);
+    }
+
+    #[test]
+    fn test_interleaved_kinds_apply_in_array_order() {
+        let image = noise(24, 16, 3);
+        let radial = stack_mask(
+            "r",
+            RadialGradientMask::circle(0.5, 0.5, 0.4, 0.5),
+            brighten(),
+        );
+        let linear = stack_mask(
+            "l",
+            LinearGradientMask::new(0.0, 0.0, 1.0, 1.0, 1.0),
+            desaturate(),
+        );
+        let brush = stack_mask(
+            "b",
+            BrushMask::rasterize(&linear.mask.clone().into(), 24, 16),
+            BasicAdjustments {
+                tint: 50.0,
+                ..Default::default()
+            },
+        );
+
+        // Radial before linear is not expressible as per-kind lists
+        let masks = [
+            MaskEntry::from(radial.clone()),
+            MaskEntry::from(brush.clone()),
+            MaskEntry::from(linear.clone()),
+        ];
+        let mut ordered = image.pixels.clone();
+        apply_ordered_masks(&mut ordered, 24, 16, &masks, &MaskSelection::default()).unwrap();
+
+        let mut legacy = image.pixels.clone();
+        let masks = legacy_mask_order(vec![linear], vec![radial], vec![brush]);
+        apply_ordered_masks(&mut legacy, 24, 16, &masks, &MaskSelection::default()).unwrap();
+        assert_ne!(ordered, legacy);
+
+        // The region render agrees with the full one inside the rect
+        let rect = PixelRect {
+            x: 4,
+            y: 2,
+            width: 10,
+            height: 9,
+        };
+        let mut region = image.pixels.clone();
+        let masks = [
+            MaskEntry::from(stack_mask(
+                "r",
+                RadialGradientMask::circle(0.5, 0.5, 0.4, 0.5),
+                brighten(),
+            )),
+            MaskEntry::from(stack_mask(
+                "l",
+                LinearGradientMask::new(0.0, 0.0, 1.0, 1.0, 1.0),
+                desaturate(),
+            )),
+        ];
+        let mut full = image.pixels.clone();
+        apply_ordered_masks(&mut full, 24, 16, &masks, &MaskSelection::default()).unwrap();
+        apply_ordered_masks_region(&mut region, 24, 16, &rect, &masks).unwrap();
+        for (idx, (got, want)) in region.chunks(3).zip(full.chunks(3)).enumerate() {
+            let (px, py) = (idx as u32 % 24, idx as u32 / 24);
+            let inside = (4..14).contains(&px) && (2..11).contains(&py);
+            let expected = if inside {
+                want
+            } else {
+                &image.pixels[idx * 3..idx * 3 + 3]
+            };
+            assert_eq!(got, expected, "pixel ({px}, {py})");
+        }
+    }
+
+    #[test]
+    fn test_strip_mask_applies_within_its_band() {
+        let image = noise(16, 20, 5);
+        let strip = StripMask::new(0.0, 0.5, 1.0, 0.5, 0.1, 0.1);
+        let masks = [MaskEntry::from(stack_mask("band", strip, brighten()))];
+        let mut pixels = image.pixels.clone();
+        apply_ordered_masks(&mut pixels, 16, 20, &masks, &MaskSelection::default()).unwrap();
+
+        let row = |pixels: &[u8], y: usize| pixels[y * 48..(y + 1) * 48].to_vec();
+        // Rows 9 and 10 straddle the center line; rows 0-5 and 15-19 are
+        // more than 0.2 of the height away from it
+        assert!(row(&pixels, 9)
+            .iter()
+            .zip(row(&image.pixels, 9))
+            .all(|(a, b)| *a >= b));
+        assert_ne!(row(&pixels, 10), row(&image.pixels, 10));
+        for y in (0..6).chain(15..20) {
+            assert_eq!(row(&pixels, y), row(&image.pixels, y), "row {y}");
+        }
+    }
+
+    #[test]
+    fn test_duplicate_ids_are_rejected() {
+        let mask = || RadialGradientMask::circle(0.5, 0.5, 0.3, 0.2);
+        let masks = [
+            MaskEntry::from(stack_mask("sky", mask(), brighten())),
+            MaskEntry::from(stack_mask("ground", mask(), brighten())),
+            MaskEntry::from(stack_mask("sky", mask(), desaturate())),
+        ];
+        let mut pixels = vec![10, 20, 30];
+        let err =
+            apply_ordered_masks(&mut pixels, 1, 1, &masks, &MaskSelection::default()).unwrap_err();
+        assert_eq!(
+            err,
+            MaskError::DuplicateId {
+                id: "sky".to_string()
+            }
+        );
+        assert_eq!(err.code(), "DUPLICATE_MASK_ID");
+        assert_eq!(pixels, vec![10, 20, 30]);
+        assert!(
+            apply_ordered_masks_region(&mut pixels, 1, 1, &PixelRect::full(1, 1), &masks).is_err()
+        );
+
+        // Masks without ids never clash
+        let mut anonymous = stack_mask("", mask(), brighten());
+        anonymous.id = None;
+        let masks = [
+            MaskEntry::from(anonymous.clone()),
+            MaskEntry::from(anonymous),
+        ];
+        assert!(check_unique_ids(&masks).is_ok());
+    }
+}
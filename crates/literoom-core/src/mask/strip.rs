@@ -0,0 +1,221 @@
+//! Strip mask implementation
+//!
+//! A strip mask is a band along a center line: full effect (1.0) within
+//! `half_width` of the line on either side, falling off to no effect (0.0)
+//! over the `feather` distance beyond that. It replaces a pair of opposing
+//! linear gradients, which only overlap into a band by accident of their
+//! feathers. An inverted mask affects everything but the band.
+
+use super::smootherstep;
+use serde::{Deserialize, Serialize};
+
+/// Strip mask for region-based adjustments.
+///
+/// The center line passes through two points in normalized coordinates
+/// (0.0 to 1.0) and extends past them in both directions. Distances are
+/// measured perpendicular to the line, in the same normalized units.
+///
+/// # Coordinate System
+/// - (0, 0) = top-left corner of the image
+/// - (1, 1) = bottom-right corner of the image
+///
+/// # Example
+/// ```
+/// use literoom_core::mask::StripMask;
+///
+/// // Horizontal band across the middle of the image
+/// let mask = StripMask::new(0.0, 0.5, 1.0, 0.5, 0.1, 0.1);
+///
+/// assert_eq!(mask.evaluate(0.3, 0.55), 1.0);
+/// assert_eq!(mask.evaluate(0.3, 0.9), 0.0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StripMask {
+    /// First point on the center line, X coordinate
+    pub start_x: f32,
+    /// First point on the center line, Y coordinate
+    pub start_y: f32,
+    /// Second point on the center line, X coordinate
+    pub end_x: f32,
+    /// Second point on the center line, Y coordinate
+    pub end_y: f32,
+    /// Distance from the center line with full effect
+    pub half_width: f32,
+    /// Distance beyond `half_width` over which the effect falls to zero
+    /// (0.0 = hard edge)
+    #[serde(default)]
+    pub feather: f32,
+    /// Whether to invert the mask (full effect outside the strip)
+    #[serde(default)]
+    pub invert: bool,
+}
+
+impl StripMask {
+    /// Create a new strip mask.
+    ///
+    /// # Arguments
+    /// * `start_x`, `start_y`, `end_x`, `end_y` - Two points on the center line
+    /// * `half_width` - Distance from the line with full effect
+    /// * `feather` - Falloff distance beyond `half_width`
+    ///
+    /// Negative distances are treated as zero.
+    pub fn new(
+        start_x: f32,
+        start_y: f32,
+        end_x: f32,
+        end_y: f32,
+        half_width: f32,
+        feather: f32,
+    ) -> Self {
+        Self {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            half_width: half_width.max(0.0),
+            feather: feather.max(0.0),
+            invert: false,
+        }
+    }
+
+    /// Set whether the mask is inverted.
+    pub fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Perpendicular distance from the center line.
+    ///
+    /// If the two points coincide there is no line, and the distance is to
+    /// that point instead, so the strip becomes a disc.
+    #[inline]
+    fn distance(&self, x: f32, y: f32) -> f32 {
+        let (px, py) = (x - self.start_x, y - self.start_y);
+        let (dx, dy) = (self.end_x - self.start_x, self.end_y - self.start_y);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len < f32::EPSILON {
+            return (px * px + py * py).sqrt();
+        }
+        (px * dy - py * dx).abs() / len
+    }
+
+    /// Evaluate the mask strength at a given normalized coordinate.
+    ///
+    /// Returns a value from 0.0 (no effect) to 1.0 (full effect).
+    ///
+    /// # Arguments
+    /// * `x` - X coordinate (0.0 to 1.0)
+    /// * `y` - Y coordinate (0.0 to 1.0)
+    ///
+    /// # Algorithm
+    /// 1. Measure the distance from the center line, perpendicular to it
+    /// 2. Full effect up to `half_width`
+    /// 3. Smootherstep falloff over the next `feather`
+    /// 4. No effect beyond `half_width + feather`
+    /// 5. Optionally invert the result
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        let half_width = self.half_width.max(0.0);
+        let feather = self.feather.max(0.0);
+        let distance = self.distance(x, y);
+
+        let mask = if distance <= half_width {
+            1.0
+        } else if distance >= half_width + feather {
+            0.0
+        } else {
+            1.0 - smootherstep((distance - half_width) / feather)
+        };
+
+        if self.invert {
+            1.0 - mask
+        } else {
+            mask
+        }
+    }
+
+    /// Get the angle of the center line in radians.
+    pub fn angle(&self) -> f32 {
+        (self.end_y - self.start_y).atan2(self.end_x - self.start_x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_center_line_has_full_effect() {
+        let mask = StripMask::new(0.2, 0.3, 0.7, 0.6, 0.05, 0.1);
+        // The points, the midpoint, and the line extended past the points
+        for t in [0.0, 0.5, 1.0, -0.8, 1.6] {
+            let x = 0.2 + 0.5 * t;
+            let y = 0.3 + 0.3 * t;
+            assert_eq!(mask.evaluate(x, y), 1.0, "t = {t}");
+        }
+    }
+
+    #[test]
+    fn test_falloff_ends_at_half_width_plus_feather() {
+        let mask = StripMask::new(0.0, 0.5, 1.0, 0.5, 0.125, 0.25);
+        assert_eq!(mask.evaluate(0.4, 0.5 + 0.125), 1.0);
+        assert_eq!(mask.evaluate(0.4, 0.5 + 0.375), 0.0);
+        assert_eq!(mask.evaluate(0.4, 0.5 - 0.375), 0.0);
+        // Halfway through the feather
+        assert!((mask.evaluate(0.4, 0.5 + 0.25) - 0.5).abs() < 1e-6);
+
+        // Without feather the edge is hard
+        let hard = StripMask::new(0.0, 0.5, 1.0, 0.5, 0.125, 0.0);
+        assert_eq!(hard.evaluate(0.4, 0.62), 1.0);
+        assert_eq!(hard.evaluate(0.4, 0.63), 0.0);
+    }
+
+    #[test]
+    fn test_symmetric_across_the_line() {
+        let mask = StripMask::new(0.1, 0.9, 0.8, 0.2, 0.05, 0.2);
+        // Unit normal of the line, and a point on it
+        let (nx, ny) = (
+            std::f32::consts::FRAC_1_SQRT_2,
+            std::f32::consts::FRAC_1_SQRT_2,
+        );
+        let (cx, cy) = (0.45, 0.55);
+        for offset in [0.0, 0.03, 0.06, 0.1, 0.2, 0.3] {
+            let above = mask.evaluate(cx + nx * offset, cy + ny * offset);
+            let below = mask.evaluate(cx - nx * offset, cy - ny * offset);
+            assert!((above - below).abs() < 1e-5, "offset {offset}");
+        }
+    }
+
+    #[test]
+    fn test_rotated_strip_at_hand_computed_points() {
+        // Center line y = x, 45 degrees, through (0.5, 0.5)
+        let mask = StripMask::new(0.25, 0.25, 0.75, 0.75, 0.1, 0.2);
+        assert!((mask.angle() - std::f32::consts::FRAC_PI_4).abs() < 1e-6);
+
+        // (0.6, 0.4) is 0.2 / sqrt(2) = 0.1414 from the line: 0.0414 into
+        // the feather, t = 0.2071, smootherstep(t) = 0.0635
+        let expected = 1.0 - 0.063_52;
+        assert!((mask.evaluate(0.6, 0.4) - expected).abs() < 1e-3);
+        // (0.3, 0.5) is 0.1414 from the line on the other side
+        assert!((mask.evaluate(0.3, 0.5) - expected).abs() < 1e-3);
+        // (0.55, 0.45) is 0.0707 from the line: inside the full band
+        assert_eq!(mask.evaluate(0.55, 0.45), 1.0);
+        // (0.9, 0.3) is 0.6 / sqrt(2) = 0.4243 from the line: beyond 0.3
+        assert_eq!(mask.evaluate(0.9, 0.3), 0.0);
+        // Far along the line past both points: still full effect
+        assert_eq!(mask.evaluate(0.0, 0.0), 1.0);
+        assert_eq!(mask.evaluate(1.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_invert_and_degenerate_line() {
+        let mask = StripMask::new(0.0, 0.5, 1.0, 0.5, 0.1, 0.1).with_invert(true);
+        assert_eq!(mask.evaluate(0.5, 0.5), 0.0);
+        assert_eq!(mask.evaluate(0.5, 0.0), 1.0);
+
+        // Coinciding points make a disc around them
+        let disc = StripMask::new(0.5, 0.5, 0.5, 0.5, 0.1, 0.1);
+        assert_eq!(disc.evaluate(0.55, 0.5), 1.0);
+        assert_eq!(disc.evaluate(0.5, 0.75), 0.0);
+        assert_eq!(disc.evaluate(0.75, 0.5), 0.0);
+    }
+}
@@ -0,0 +1,113 @@
+//! Black-and-white conversion.
+//!
+//! Monochrome is a switch in the edit state rather than a saturation of
+//! -100: the color adjustments stay as they are, so turning B&W off brings
+//! the color version back exactly. The conversion runs after the basic
+//! adjustments, so white balance and vibrance still shape the gray tones,
+//! and before the tone curve.
+
+use crate::luminance::{LUMINANCE_B, LUMINANCE_G, LUMINANCE_R};
+use serde::{Deserialize, Serialize};
+
+/// How much each channel contributes to the gray value.
+///
+/// Weights are relative: they are divided by their sum, so a neutral gray
+/// keeps its value whatever the mix. Raising `red` brightens skin and
+/// darkens a blue sky.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BwMix {
+    /// Red channel weight
+    pub red: f32,
+    /// Green channel weight
+    pub green: f32,
+    /// Blue channel weight
+    pub blue: f32,
+}
+
+impl Default for BwMix {
+    /// BT.709 luminance, matching the histogram's luminance channel.
+    fn default() -> Self {
+        Self {
+            red: LUMINANCE_R,
+            green: LUMINANCE_G,
+            blue: LUMINANCE_B,
+        }
+    }
+}
+
+impl BwMix {
+    /// Weights scaled to sum to 1, or the default mix if they cannot be.
+    fn normalized(&self) -> [f32; 3] {
+        let sum = self.red + self.green + self.blue;
+        if !sum.is_finite() || sum <= f32::EPSILON {
+            let d = Self::default();
+            return [d.red, d.green, d.blue];
+        }
+        [self.red / sum, self.green / sum, self.blue / sum]
+    }
+}
+
+/// Convert RGB pixels to gray in place with `mix`.
+pub fn apply_monochrome(pixels: &mut [u8], mix: &BwMix) {
+    let [wr, wg, wb] = mix.normalized();
+    for pixel in pixels.chunks_exact_mut(3) {
+        let gray = wr * pixel[0] as f32 + wg * pixel[1] as f32 + wb * pixel[2] as f32;
+        let gray = gray.round().clamp(0.0, 255.0) as u8;
+        pixel.fill(gray);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grays_keep_their_value_for_any_mix() {
+        let mixes = [
+            BwMix::default(),
+            BwMix {
+                red: 2.0,
+                green: 0.5,
+                blue: -0.5,
+            },
+        ];
+        for mix in mixes {
+            let mut pixels: Vec<u8> = (0..=255u8).flat_map(|v| [v, v, v]).collect();
+            let expected = pixels.clone();
+            apply_monochrome(&mut pixels, &mix);
+            assert_eq!(pixels, expected, "{:?}", mix);
+        }
+    }
+
+    #[test]
+    fn test_mix_weights_channels() {
+        let red = BwMix {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+        };
+        let mut pixels = vec![200, 40, 10];
+        apply_monochrome(&mut pixels, &red);
+        assert_eq!(pixels, vec![200, 200, 200]);
+
+        let mut pixels = vec![200, 40, 10];
+        apply_monochrome(&mut pixels, &BwMix::default());
+        // 0.2126 * 200 + 0.7152 * 40 + 0.0722 * 10
+        assert_eq!(pixels, vec![72, 72, 72]);
+    }
+
+    #[test]
+    fn test_degenerate_mix_falls_back_to_default() {
+        let zero = BwMix {
+            red: 0.0,
+            green: 0.0,
+            blue: 0.0,
+        };
+        let mut a = vec![200, 40, 10];
+        let mut b = a.clone();
+        apply_monochrome(&mut a, &zero);
+        apply_monochrome(&mut b, &BwMix::default());
+        assert_eq!(a, b);
+    }
+}
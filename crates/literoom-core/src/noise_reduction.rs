@@ -0,0 +1,220 @@
+//! Default noise reduction from capture metadata.
+//!
+//! High-ISO shots need chroma denoise to look clean, and asking the user to
+//! dial it in for every photo is tedious. [`compute_default_noise_reduction`]
+//! picks a starting strength from the EXIF ISO, scaled for cameras whose
+//! sensors are noticeably noisier or cleaner than average.
+//!
+//! The edit state only uses these defaults when `auto_nr` is on and no
+//! explicit values are set (see
+//! [`crate::edit::EditState::effective_noise_reduction`]). Because saved
+//! edits may rely on them, the table is versioned: any change to its values
+//! must bump [`NR_TABLE_VERSION`].
+
+use crate::decode::ImageMetadata;
+use serde::{Deserialize, Serialize};
+
+/// Version of [`ISO_TABLE`] and [`CAMERA_SCALES`].
+pub const NR_TABLE_VERSION: u32 = 1;
+
+/// Strongest luminance or color noise reduction.
+pub const MAX_NOISE_REDUCTION: f32 = 100.0;
+
+/// Noise reduction strengths.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseReductionParams {
+    /// Luminance noise reduction (0 to 100)
+    pub luminance: f32,
+    /// Color (chroma) noise reduction (0 to 100)
+    pub color: f32,
+}
+
+impl NoiseReductionParams {
+    /// Check if both strengths are zero.
+    pub fn is_zero(&self) -> bool {
+        self.luminance <= 0.0 && self.color <= 0.0
+    }
+}
+
+/// ISO stops with their luminance and color strengths, in ascending ISO.
+///
+/// Strengths are interpolated in log2 ISO between stops and held at the
+/// last stop above it. Below the first stop no noise reduction is applied.
+const ISO_TABLE: &[(u32, f32, f32)] = &[
+    (100, 0.0, 0.0),
+    (400, 0.0, 10.0),
+    (800, 5.0, 20.0),
+    (1600, 10.0, 30.0),
+    (3200, 20.0, 40.0),
+    (6400, 30.0, 50.0),
+    (12800, 40.0, 60.0),
+    (25600, 50.0, 70.0),
+];
+
+/// Cameras whose noise differs from the table: lowercase make and model
+/// substrings and a strength multiplier.
+///
+/// An empty model matches every model of the make. The first match wins.
+const CAMERA_SCALES: &[(&str, &str, f32)] = &[
+    // Full-frame low-resolution bodies are about a stop cleaner
+    ("sony", "ilce-7s", 0.75),
+    // Small phone sensors are noisier at the same ISO
+    ("apple", "iphone", 1.25),
+    ("google", "pixel", 1.25),
+];
+
+/// Default noise reduction for a photo from its ISO and camera.
+///
+/// Returns zero strengths when the ISO is unknown or at base ISO.
+pub fn compute_default_noise_reduction(metadata: &ImageMetadata) -> NoiseReductionParams {
+    let Some(iso) = metadata.iso.filter(|&iso| iso > 0) else {
+        return NoiseReductionParams::default();
+    };
+    let (luminance, color) = interpolate_iso(iso);
+    let scale = camera_scale(
+        metadata.camera_make.as_deref().unwrap_or(""),
+        metadata.camera_model.as_deref().unwrap_or(""),
+    );
+    NoiseReductionParams {
+        luminance: (luminance * scale).min(MAX_NOISE_REDUCTION),
+        color: (color * scale).min(MAX_NOISE_REDUCTION),
+    }
+}
+
+fn interpolate_iso(iso: u32) -> (f32, f32) {
+    let (first_iso, _, _) = ISO_TABLE[0];
+    if iso <= first_iso {
+        return (0.0, 0.0);
+    }
+    let stops = (iso as f32).log2();
+    for pair in ISO_TABLE.windows(2) {
+        let (iso0, lum0, color0) = pair[0];
+        let (iso1, lum1, color1) = pair[1];
+        if iso <= iso1 {
+            let s0 = (iso0 as f32).log2();
+            let s1 = (iso1 as f32).log2();
+            let t = (stops - s0) / (s1 - s0);
+            return (lum0 + (lum1 - lum0) * t, color0 + (color1 - color0) * t);
+        }
+    }
+    let (_, lum, color) = ISO_TABLE[ISO_TABLE.len() - 1];
+    (lum, color)
+}
+
+fn camera_scale(make: &str, model: &str) -> f32 {
+    let make = make.to_lowercase();
+    let model = model.to_lowercase();
+    CAMERA_SCALES
+        .iter()
+        .find(|(m, prefix, _)| make.contains(m) && model.contains(prefix))
+        .map_or(1.0, |&(_, _, scale)| scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(iso: Option<u32>) -> ImageMetadata {
+        ImageMetadata {
+            iso,
+            camera_make: Some("Canon".to_string()),
+            camera_model: Some("Canon EOS R5".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_base_iso_has_no_noise_reduction() {
+        for iso in [50, 64, 100] {
+            let params = compute_default_noise_reduction(&metadata(Some(iso)));
+            assert!(params.is_zero(), "ISO {}: {:?}", iso, params);
+        }
+    }
+
+    #[test]
+    fn test_unknown_iso_has_no_noise_reduction() {
+        assert!(compute_default_noise_reduction(&metadata(None)).is_zero());
+        assert!(compute_default_noise_reduction(&metadata(Some(0))).is_zero());
+        assert!(compute_default_noise_reduction(&ImageMetadata::default()).is_zero());
+    }
+
+    #[test]
+    fn test_high_iso_gets_substantial_noise_reduction() {
+        let params = compute_default_noise_reduction(&metadata(Some(6400)));
+        assert!(params.color >= 40.0, "{:?}", params);
+        assert!(params.luminance >= 20.0, "{:?}", params);
+    }
+
+    #[test]
+    fn test_strength_is_monotonic_in_iso() {
+        let mut previous = NoiseReductionParams::default();
+        for iso in (50..=102_400).step_by(50) {
+            let params = compute_default_noise_reduction(&metadata(Some(iso)));
+            assert!(params.luminance >= previous.luminance, "ISO {}", iso);
+            assert!(params.color >= previous.color, "ISO {}", iso);
+            previous = params;
+        }
+    }
+
+    #[test]
+    fn test_interpolates_between_stops_in_log_iso() {
+        // ISO 1131 is half a stop above 800
+        let params = compute_default_noise_reduction(&metadata(Some(1131)));
+        assert!((params.luminance - 7.5).abs() < 0.05, "{:?}", params);
+        assert!((params.color - 25.0).abs() < 0.05, "{:?}", params);
+    }
+
+    #[test]
+    fn test_camera_scale() {
+        let phone = ImageMetadata {
+            iso: Some(1600),
+            camera_make: Some("Apple".to_string()),
+            camera_model: Some("iPhone 15 Pro".to_string()),
+            ..Default::default()
+        };
+        let clean = ImageMetadata {
+            camera_make: Some("SONY".to_string()),
+            camera_model: Some("ILCE-7SM3".to_string()),
+            ..phone.clone()
+        };
+        let average = compute_default_noise_reduction(&metadata(Some(1600)));
+        let phone = compute_default_noise_reduction(&phone);
+        let clean = compute_default_noise_reduction(&clean);
+        assert!(phone.color > average.color);
+        assert!(clean.color < average.color);
+    }
+
+    #[test]
+    fn test_strength_is_capped() {
+        let phone = ImageMetadata {
+            iso: Some(409_600),
+            camera_make: Some("Google".to_string()),
+            camera_model: Some("Pixel 8".to_string()),
+            ..Default::default()
+        };
+        let params = compute_default_noise_reduction(&phone);
+        assert!(params.luminance <= MAX_NOISE_REDUCTION);
+        assert!(params.color <= MAX_NOISE_REDUCTION);
+    }
+
+    /// Saved edits with `auto_nr` depend on these values. If this fails,
+    /// bump `NR_TABLE_VERSION` and update the expected table.
+    #[test]
+    fn test_table_matches_version() {
+        assert_eq!(NR_TABLE_VERSION, 1);
+        let expected: Vec<(u32, f32, f32)> = vec![
+            (100, 0.0, 0.0),
+            (400, 0.0, 10.0),
+            (800, 5.0, 20.0),
+            (1600, 10.0, 30.0),
+            (3200, 20.0, 40.0),
+            (6400, 30.0, 50.0),
+            (12800, 40.0, 60.0),
+            (25600, 50.0, 70.0),
+        ];
+        assert_eq!(ISO_TABLE, expected.as_slice());
+        let scales: Vec<f32> = CAMERA_SCALES.iter().map(|&(_, _, s)| s).collect();
+        assert_eq!(scales, [0.75, 1.25, 1.25]);
+    }
+}
@@ -0,0 +1,382 @@
+//! Lightweight performance counters for image operations.
+//!
+//! Each major operation (JPEG decode/encode, resize, adjustments, masks, tone
+//! curve) reports how many pixels it processed and how many bytes it allocated
+//! for large buffers into a thread-local collector. When the outermost
+//! operation finishes, its totals become available via [`last_operation_stats`].
+//!
+//! Unlike tracing, the counters are always on and cost a few integer adds per
+//! operation, so they are safe to leave enabled in production builds.
+//!
+//! ## Nesting
+//!
+//! Operations open a [`scope`]. Only the outermost scope produces a result;
+//! scopes opened while another is active simply add to the outer totals.
+//! Pixels are recorded once, by the operation that actually touches them, so
+//! a pipeline that opens its own scope and then calls `resize` and
+//! `apply_all_adjustments` reports the sum of both without double counting.
+//!
+//! ## Timing
+//!
+//! Wall time is only measured when a timer is installed with [`set_timer`].
+//! The core has no clock of its own because `std::time::Instant` is not
+//! available on `wasm32-unknown-unknown`.
+//!
+//! ## Observing operations
+//!
+//! An observer installed with [`set_observer`] is called with the counters of
+//! every top-level operation as it finishes. This is the single place the
+//! bindings hook into to report slow operations, so new operations are
+//! covered as soon as they open a scope.
+//!
+//! ```ignore
+//! use literoom_core::perf;
+//!
+//! {
+//!     let _scope = perf::scope("export");
+//!     let resized = resize(&image, 1024, 768, FilterType::Bilinear)?;
+//!     apply_all_adjustments(&mut pixels, &adjustments);
+//! }
+//! let stats = perf::last_operation_stats().unwrap();
+//! println!("{} pixels", stats.pixels_processed);
+//! ```
+
+use std::cell::RefCell;
+
+/// Counters collected for one top-level operation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Counters {
+    /// Name of the outermost operation
+    pub operation: &'static str,
+    /// Number of pixels read or written by per-pixel work
+    pub pixels_processed: u64,
+    /// Bytes allocated for large pixel or output buffers
+    pub bytes_allocated: u64,
+    /// Wall time in milliseconds (only when a timer is installed)
+    pub elapsed_ms: Option<f64>,
+    /// Width and height of the image the operation worked on, if it reported
+    /// one with [`record_dimensions`]
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl Counters {
+    /// Throughput in pixels per second, if the operation was timed.
+    pub fn pixels_per_second(&self) -> Option<f64> {
+        match self.elapsed_ms {
+            Some(ms) if ms > 0.0 => Some(self.pixels_processed as f64 * 1000.0 / ms),
+            _ => None,
+        }
+    }
+}
+
+/// Thread-local collector state.
+#[derive(Default)]
+struct Collector {
+    /// Number of currently open scopes
+    depth: u32,
+    /// Totals for the active outermost scope
+    current: Counters,
+    /// Timer reading when the outermost scope opened
+    started_at: Option<f64>,
+    /// Totals for the most recently completed outermost scope
+    last: Option<Counters>,
+    /// Millisecond clock used for timing
+    timer: Option<fn() -> f64>,
+    /// Called with each completed outermost operation
+    observer: Option<fn(&Counters)>,
+}
+
+thread_local! {
+    static COLLECTOR: RefCell<Collector> = RefCell::new(Collector::default());
+}
+
+/// Guard returned by [`scope`]. Closes the scope when dropped.
+#[must_use = "the scope closes as soon as the guard is dropped"]
+pub struct Scope {
+    _private: (),
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let finished = COLLECTOR.with(|c| {
+            let mut c = c.borrow_mut();
+            c.depth = c.depth.saturating_sub(1);
+            if c.depth > 0 {
+                return None;
+            }
+            let mut finished = std::mem::take(&mut c.current);
+            if let (Some(timer), Some(start)) = (c.timer, c.started_at.take()) {
+                finished.elapsed_ms = Some(timer() - start);
+            }
+            c.last = Some(finished.clone());
+            c.observer.map(|observer| (observer, finished))
+        });
+        // Called after the borrow ends so the observer may use this module
+        if let Some((observer, finished)) = finished {
+            observer(&finished);
+        }
+    }
+}
+
+/// Open a counter scope for an operation.
+///
+/// If no scope is active, this starts a fresh set of counters named
+/// `operation`. Otherwise the new scope is nested and its work is added to
+/// the enclosing operation.
+pub fn scope(operation: &'static str) -> Scope {
+    COLLECTOR.with(|c| {
+        let mut c = c.borrow_mut();
+        if c.depth == 0 {
+            c.current = Counters {
+                operation,
+                ..Counters::default()
+            };
+            c.started_at = c.timer.map(|timer| timer());
+        }
+        c.depth += 1;
+    });
+    Scope { _private: () }
+}
+
+/// Record pixels processed by the active operation.
+///
+/// Ignored when no scope is open.
+pub fn record_pixels(count: u64) {
+    COLLECTOR.with(|c| {
+        let mut c = c.borrow_mut();
+        if c.depth > 0 {
+            c.current.pixels_processed += count;
+        }
+    });
+}
+
+/// Record bytes allocated by the active operation.
+///
+/// Ignored when no scope is open.
+pub fn record_allocation(bytes: u64) {
+    COLLECTOR.with(|c| {
+        let mut c = c.borrow_mut();
+        if c.depth > 0 {
+            c.current.bytes_allocated += bytes;
+        }
+    });
+}
+
+/// Record the size of the image the active operation works on.
+///
+/// Only the first size recorded in an operation is kept, so a nested
+/// operation on an intermediate image does not replace the outer one.
+/// Ignored when no scope is open.
+pub fn record_dimensions(width: u32, height: u32) {
+    COLLECTOR.with(|c| {
+        let mut c = c.borrow_mut();
+        if c.depth > 0 && c.current.dimensions.is_none() {
+            c.current.dimensions = Some((width, height));
+        }
+    });
+}
+
+/// Record a large buffer's allocation and pass it through unchanged.
+///
+/// Wrap the big output `Vec`s of an operation with this, e.g.
+/// `let pixels = perf::track_vec(img.into_raw());`.
+#[inline]
+pub fn track_vec<T>(vec: Vec<T>) -> Vec<T> {
+    record_allocation((vec.capacity() * std::mem::size_of::<T>()) as u64);
+    vec
+}
+
+/// Install (or remove) the millisecond clock used to time operations.
+///
+/// The timer applies to the current thread only.
+pub fn set_timer(timer: Option<fn() -> f64>) {
+    COLLECTOR.with(|c| c.borrow_mut().timer = timer);
+}
+
+/// Install (or remove) the observer called as each top-level operation
+/// finishes.
+///
+/// The observer applies to the current thread only. It runs after the
+/// counters are stored, so [`last_operation_stats`] already returns them.
+pub fn set_observer(observer: Option<fn(&Counters)>) {
+    COLLECTOR.with(|c| c.borrow_mut().observer = observer);
+}
+
+/// Counters for the most recently completed top-level operation.
+pub fn last_operation_stats() -> Option<Counters> {
+    COLLECTOR.with(|c| c.borrow().last.clone())
+}
+
+/// Clear the stored counters of the last operation.
+///
+/// Open scopes and the installed timer are not affected.
+pub fn reset_stats() {
+    COLLECTOR.with(|c| c.borrow_mut().last = None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_records_counters() {
+        {
+            let _scope = scope("test");
+            record_pixels(100);
+            record_allocation(300);
+        }
+
+        let stats = last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "test");
+        assert_eq!(stats.pixels_processed, 100);
+        assert_eq!(stats.bytes_allocated, 300);
+        assert_eq!(stats.elapsed_ms, None);
+    }
+
+    #[test]
+    fn test_records_outside_scope_are_ignored() {
+        reset_stats();
+        record_pixels(100);
+        record_allocation(100);
+        assert!(last_operation_stats().is_none());
+
+        {
+            let _scope = scope("empty");
+        }
+        let stats = last_operation_stats().unwrap();
+        assert_eq!(stats.pixels_processed, 0);
+        assert_eq!(stats.bytes_allocated, 0);
+    }
+
+    #[test]
+    fn test_nested_scopes_aggregate_into_outer() {
+        {
+            let _outer = scope("pipeline");
+            record_pixels(10);
+            {
+                let _inner = scope("resize");
+                record_pixels(20);
+            }
+            // The inner scope must not finalize the outer operation
+            assert!(last_operation_stats().is_none_or(|s| s.operation != "resize"));
+            record_pixels(30);
+        }
+
+        let stats = last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "pipeline");
+        assert_eq!(stats.pixels_processed, 60);
+    }
+
+    #[test]
+    fn test_new_outer_scope_starts_fresh() {
+        {
+            let _scope = scope("first");
+            record_pixels(5);
+        }
+        {
+            let _scope = scope("second");
+            record_pixels(7);
+        }
+
+        let stats = last_operation_stats().unwrap();
+        assert_eq!(stats.operation, "second");
+        assert_eq!(stats.pixels_processed, 7);
+    }
+
+    #[test]
+    fn test_track_vec_records_capacity() {
+        {
+            let _scope = scope("alloc");
+            let v: Vec<u16> = track_vec(Vec::with_capacity(50));
+            assert!(v.is_empty());
+        }
+        assert_eq!(last_operation_stats().unwrap().bytes_allocated, 100);
+    }
+
+    #[test]
+    fn test_timer_measures_elapsed() {
+        thread_local! {
+            static NOW: std::cell::Cell<f64> = const { std::cell::Cell::new(0.0) };
+        }
+        fn fake_now() -> f64 {
+            NOW.with(|n| {
+                let t = n.get();
+                n.set(t + 250.0);
+                t
+            })
+        }
+
+        set_timer(Some(fake_now));
+        {
+            let _scope = scope("timed");
+            record_pixels(1000);
+        }
+        set_timer(None);
+
+        let stats = last_operation_stats().unwrap();
+        assert_eq!(stats.elapsed_ms, Some(250.0));
+        assert_eq!(stats.pixels_per_second(), Some(4000.0));
+    }
+
+    #[test]
+    fn test_first_dimensions_are_kept() {
+        {
+            let _outer = scope("render");
+            record_dimensions(4000, 3000);
+            {
+                let _inner = scope("resize");
+                record_dimensions(400, 300);
+            }
+        }
+        assert_eq!(
+            last_operation_stats().unwrap().dimensions,
+            Some((4000, 3000))
+        );
+    }
+
+    #[test]
+    fn test_observer_sees_outermost_operations() {
+        thread_local! {
+            static SEEN: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+        }
+        fn observe(counters: &Counters) {
+            // The stats are already stored when the observer runs
+            assert_eq!(last_operation_stats().as_ref(), Some(counters));
+            SEEN.with(|s| s.borrow_mut().push(counters.operation));
+        }
+
+        set_observer(Some(observe));
+        {
+            let _outer = scope("pipeline");
+            let _inner = scope("resize");
+        }
+        {
+            let _scope = scope("encode");
+        }
+        set_observer(None);
+        {
+            let _scope = scope("unobserved");
+        }
+
+        assert_eq!(SEEN.with(|s| s.borrow().clone()), ["pipeline", "encode"]);
+    }
+
+    #[test]
+    fn test_reset_stats() {
+        {
+            let _scope = scope("op");
+        }
+        assert!(last_operation_stats().is_some());
+        reset_stats();
+        assert!(last_operation_stats().is_none());
+    }
+
+    #[test]
+    fn test_pixels_per_second_without_timer() {
+        let counters = Counters {
+            pixels_processed: 100,
+            ..Counters::default()
+        };
+        assert_eq!(counters.pixels_per_second(), None);
+    }
+}
@@ -0,0 +1,250 @@
+//! Perceptual hashes for spotting duplicate photos.
+//!
+//! Re-importing a shoot brings in copies that differ only by recompression
+//! or resizing: their bytes differ but their pictures do not.
+//! [`compute_phash`] reduces an image to 64 bits that such changes barely
+//! move, and [`hamming_distance`] compares two hashes. Hashes at most
+//! [`PHASH_DUPLICATE_THRESHOLD`] bits apart are likely duplicates.
+//!
+//! ## Algorithm
+//!
+//! The standard DCT hash: the image is averaged down to 32x32 luminance and
+//! transformed with a 2D DCT-II, and the 8x8 lowest frequencies are compared
+//! with the median of the 63 of them other than the DC term. Each bit is 1
+//! where its coefficient is above that median. The DC bit is always 0, as
+//! overall brightness says nothing about what the picture shows.
+//!
+//! Bits are stored most significant first, in row-major order of the
+//! coefficients (vertical frequency, then horizontal), so the hash value is
+//! stable across platforms and releases.
+
+use crate::decode::DecodedImage;
+use crate::luminance::calculate_luminance;
+use crate::validate::ImageError;
+
+/// Side of the luminance grid the DCT runs on.
+const GRID_SIZE: usize = 32;
+
+/// Side of the block of lowest frequencies that make up the hash.
+const HASH_SIZE: usize = 8;
+
+/// Largest [`hamming_distance`] at which two hashes are likely the same
+/// picture.
+///
+/// Recompressed and slightly resized copies land well inside it, while
+/// unrelated pictures differ in about half of the 63 bits that vary.
+pub const PHASH_DUPLICATE_THRESHOLD: u32 = 10;
+
+/// Compute the 64-bit perceptual hash of an image.
+///
+/// Thumbnails hash the same as the full image up to resampling noise, so
+/// the grid can hash the thumbnails it already has.
+///
+/// # Errors
+/// Returns `ImageError` if either dimension is zero or the buffer length is
+/// not `width * height * 3`.
+///
+/// # Example
+/// ```
+/// use literoom_core::phash::{compute_phash, hamming_distance};
+/// use literoom_core::testing::slanted_edge;
+///
+/// let hash = compute_phash(&slanted_edge(64, 64, 5.0)).unwrap();
+/// assert_eq!(hamming_distance(hash, hash), 0);
+/// ```
+pub fn compute_phash(image: &DecodedImage) -> Result<u64, ImageError> {
+    image.validate()?;
+
+    let coefficients = low_frequency_dct(&luminance_grid(image));
+    let mut ac: Vec<f64> = coefficients.iter().flatten().skip(1).copied().collect();
+    ac.sort_by(f64::total_cmp);
+    let median = ac[ac.len() / 2];
+
+    let hash = coefficients
+        .iter()
+        .flatten()
+        .enumerate()
+        .skip(1)
+        .filter(|&(_, &c)| c > median)
+        .fold(0u64, |hash, (i, _)| hash | 1 << (63 - i));
+    Ok(hash)
+}
+
+/// Number of bits in which two hashes differ.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Mean luminance (0-255) of each cell of a `GRID_SIZE` grid over the image,
+/// as `[y][x]`.
+///
+/// Each cell averages the pixels it covers; an image smaller than the grid
+/// repeats its pixels.
+fn luminance_grid(image: &DecodedImage) -> [[f64; GRID_SIZE]; GRID_SIZE] {
+    let (width, height) = (image.width as usize, image.height as usize);
+    let span = |cell: usize, len: usize| {
+        let start = cell * len / GRID_SIZE;
+        start..((cell + 1) * len / GRID_SIZE).max(start + 1)
+    };
+
+    let mut grid = [[0.0; GRID_SIZE]; GRID_SIZE];
+    for (gy, row) in grid.iter_mut().enumerate() {
+        let rows = span(gy, height);
+        for (gx, cell) in row.iter_mut().enumerate() {
+            let columns = span(gx, width);
+            let count = rows.len() * columns.len();
+            let sum: f64 = rows
+                .clone()
+                .flat_map(|y| columns.clone().map(move |x| (y * width + x) * 3))
+                .map(|i| {
+                    let [r, g, b] = [0, 1, 2].map(|c| image.pixels[i + c] as f32);
+                    calculate_luminance(r, g, b) as f64
+                })
+                .sum();
+            *cell = sum / count as f64;
+        }
+    }
+    grid
+}
+
+/// The `HASH_SIZE x HASH_SIZE` lowest-frequency DCT-II coefficients of the
+/// grid, as `[v][u]` with `v` the vertical frequency.
+///
+/// Unnormalized: `sum f(x, y) cos((2x + 1)uπ / 2N) cos((2y + 1)vπ / 2N)`.
+/// Only the order of the coefficients matters to the hash.
+fn low_frequency_dct(grid: &[[f64; GRID_SIZE]; GRID_SIZE]) -> [[f64; HASH_SIZE]; HASH_SIZE] {
+    let mut cos = [[0.0; GRID_SIZE]; HASH_SIZE];
+    for (k, row) in cos.iter_mut().enumerate() {
+        for (n, value) in row.iter_mut().enumerate() {
+            let angle = (2 * n + 1) as f64 * k as f64 * std::f64::consts::PI;
+            *value = (angle / (2 * GRID_SIZE) as f64).cos();
+        }
+    }
+
+    // Separable: horizontal frequencies of each row, then vertical ones
+    let mut rows = [[0.0; HASH_SIZE]; GRID_SIZE];
+    for (row, values) in rows.iter_mut().zip(grid) {
+        for (u, out) in row.iter_mut().enumerate() {
+            *out = values.iter().zip(&cos[u]).map(|(f, c)| f * c).sum();
+        }
+    }
+    let mut coefficients = [[0.0; HASH_SIZE]; HASH_SIZE];
+    for (v, out_row) in coefficients.iter_mut().enumerate() {
+        for (u, out) in out_row.iter_mut().enumerate() {
+            *out = rows.iter().zip(&cos[v]).map(|(row, c)| row[u] * c).sum();
+        }
+    }
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_jpeg, resize, FilterType};
+    use crate::encode::encode_jpeg;
+    use crate::testing::noise;
+
+    /// Soft shapes with a little grain, different for every seed.
+    fn scene(width: u32, height: u32, seed: u64) -> DecodedImage {
+        let grain = noise(width, height, seed);
+        let phase = seed as f32 * 1.7;
+        let pixels = grain
+            .pixels
+            .chunks_exact(3)
+            .enumerate()
+            .flat_map(|(i, grain)| {
+                let x = (i as u32 % width) as f32 / width as f32;
+                let y = (i as u32 / width) as f32 / height as f32;
+                let shape = (x * 7.0 + phase).sin() * (y * 5.0 - phase).cos();
+                let shade = 0.5 + 0.25 * shape + 0.2 * (x - y);
+                [0, 1, 2].map(|c| {
+                    let texture = (grain[c] as f32 - 128.0) * 0.1;
+                    (shade * 200.0 + 20.0 + texture + c as f32 * 10.0).clamp(0.0, 255.0) as u8
+                })
+            })
+            .collect();
+        DecodedImage::new(width, height, pixels)
+    }
+
+    #[test]
+    fn test_recompressed_copy_is_a_duplicate() {
+        let image = scene(256, 192, 1);
+        let jpeg = encode_jpeg(&image.pixels, image.width, image.height, 70).unwrap();
+        let copy = decode_jpeg(&jpeg).unwrap();
+
+        let distance = hamming_distance(
+            compute_phash(&image).unwrap(),
+            compute_phash(&copy).unwrap(),
+        );
+        assert!(distance <= 6, "distance {distance}");
+    }
+
+    #[test]
+    fn test_resized_copy_is_a_duplicate() {
+        let image = scene(256, 192, 1);
+        let copy = resize(&image, 230, 173, FilterType::Bilinear).unwrap();
+
+        let distance = hamming_distance(
+            compute_phash(&image).unwrap(),
+            compute_phash(&copy).unwrap(),
+        );
+        assert!(distance <= 6, "distance {distance}");
+    }
+
+    #[test]
+    fn test_unrelated_images_are_far_apart() {
+        let a = compute_phash(&scene(256, 192, 1)).unwrap();
+        let b = compute_phash(&scene(256, 192, 2)).unwrap();
+        let distance = hamming_distance(a, b);
+        assert!(distance > 20, "distance {distance}");
+    }
+
+    #[test]
+    fn test_dct_matches_reference_and_hash_is_pinned() {
+        let grid = luminance_grid(&scene(256, 192, 1));
+        let coefficients = low_frequency_dct(&grid);
+
+        // Direct evaluation of the DCT-II sum
+        let n = GRID_SIZE as f64;
+        let basis = |k: usize, i: usize| {
+            ((2 * i + 1) as f64 * k as f64 * std::f64::consts::PI / (2.0 * n)).cos()
+        };
+        for (v, u) in [(0, 0), (0, 1), (1, 0), (3, 5), (7, 7)] {
+            let mut reference = 0.0;
+            for (y, row) in grid.iter().enumerate() {
+                for (x, value) in row.iter().enumerate() {
+                    reference += value * basis(u, x) * basis(v, y);
+                }
+            }
+            let difference = (coefficients[v][u] - reference).abs();
+            assert!(difference < 1e-6, "({v}, {u}): {difference}");
+        }
+
+        // A change to this value changes every stored hash: bump it only
+        // with a migration that rehashes the library
+        assert_eq!(
+            compute_phash(&scene(256, 192, 1)).unwrap(),
+            0x30b5_4aca_4aca_4bcf
+        );
+    }
+
+    #[test]
+    fn test_hash_properties() {
+        assert_eq!(hamming_distance(0, u64::MAX), 64);
+        assert_eq!(hamming_distance(0b1011, 0b0110), 3);
+
+        // Scaling brightness leaves the hash alone and the DC bit stays 0
+        let image = scene(64, 48, 3);
+        let mut darker = image.clone();
+        darker.pixels.iter_mut().for_each(|v| *v /= 2);
+        let hash = compute_phash(&image).unwrap();
+        assert!(hamming_distance(hash, compute_phash(&darker).unwrap()) <= 2);
+        assert_eq!(hash >> 63, 0);
+        // Half the 63 AC bits are set
+        assert_eq!(hash.count_ones(), 31);
+
+        // Images smaller than the grid still hash
+        assert!(compute_phash(&scene(5, 3, 1)).is_ok());
+        assert!(compute_phash(&DecodedImage::new(0, 0, vec![])).is_err());
+    }
+}
@@ -0,0 +1,642 @@
+//! The per-pixel adjustment math as data, for external shader generation.
+//!
+//! [`describe_adjustment_pipeline`] turns a set of [`BasicAdjustments`] into
+//! the list of operations [`apply_adjustments_to_pixel`] performs, with
+//! every slider already converted to the factors and thresholds the math
+//! uses. A GPU backend can generate a shader from the list instead of
+//! porting the adjustment code by hand, and regenerate it when the core
+//! math changes.
+//!
+//! Stages whose slider is zero are left out, as the native pipeline skips
+//! them. [`run_stage_descriptions`] is a reference interpreter for the
+//! list; the tests hold it to the native function, so a shader that follows
+//! the documented formulas renders what the CPU renders.
+//!
+//! # Schema
+//!
+//! The serialized form is tagged by `op` and versioned by
+//! [`PIPELINE_SCHEMA_VERSION`], carried in [`PipelineDescription`]. The
+//! version changes whenever a stage is added or a formula changes meaning,
+//! so a generator can refuse descriptions it does not understand.
+//!
+//! ```ignore
+//! use literoom_core::pipeline::{run_stage_descriptions, PipelineDescription};
+//!
+//! let description = PipelineDescription::new(&adjustments);
+//! let [r, g, b] = run_stage_descriptions([0.2, 0.4, 0.6], &description.stages);
+//! ```
+//!
+//! [`apply_adjustments_to_pixel`]: crate::adjustments::apply_adjustments_to_pixel
+
+use serde::{Deserialize, Serialize};
+
+use crate::adjustments::{
+    smoothstep, SKIN_TONE_HUE_EDGES, SKIN_TONE_SATURATION_EDGES, VIBRANCE_FULL_CHROMA,
+};
+use crate::color::{scale_chroma, srgb_to_oklab, ColorModel};
+use crate::luminance::{LUMINANCE_B, LUMINANCE_G, LUMINANCE_R};
+use crate::BasicAdjustments;
+
+/// Version of the [`PipelineStageDesc`] schema.
+pub const PIPELINE_SCHEMA_VERSION: u32 = 1;
+
+/// Luminance weights used by every stage that needs luminance.
+const LUMA: [f32; 3] = [LUMINANCE_R, LUMINANCE_G, LUMINANCE_B];
+
+/// Largest channel above which the whites slider applies.
+const WHITES_THRESHOLD: f32 = 0.9;
+
+/// Smallest channel below which the blacks slider applies.
+const BLACKS_THRESHOLD: f32 = 0.1;
+
+/// A versioned pipeline description, the form sent to a shader generator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineDescription {
+    /// Always [`PIPELINE_SCHEMA_VERSION`] when produced by this crate
+    pub version: u32,
+    /// Stages to run in order on each pixel
+    pub stages: Vec<PipelineStageDesc>,
+}
+
+impl PipelineDescription {
+    /// Describe the pipeline for `adjustments` at the current schema version.
+    pub fn new(adjustments: &BasicAdjustments) -> Self {
+        Self {
+            version: PIPELINE_SCHEMA_VERSION,
+            stages: describe_adjustment_pipeline(adjustments),
+        }
+    }
+}
+
+/// One per-pixel operation on gamma-encoded RGB, unclamped.
+///
+/// `r`, `g` and `b` are the stage's input, and `luma(rgb)` is
+/// `luma[0] * r + luma[1] * g + luma[2] * b` with the stage's weights.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PipelineStageDesc {
+    /// `c *= rgb[c]` for each channel (exposure, temperature, tint)
+    Multiply {
+        /// Per-channel multipliers
+        rgb: [f32; 3],
+    },
+    /// `c = (c - pivot) * factor + pivot` for each channel (contrast)
+    ScaleAround {
+        /// Value left unchanged
+        pivot: f32,
+        /// Slope around the pivot
+        factor: f32,
+    },
+    /// Terms weighted by the luminance of the stage's input, applied in
+    /// order (highlights, then shadows). The luminance is computed once,
+    /// before the first term.
+    LumaWeighted {
+        /// Luminance weights
+        luma: [f32; 3],
+        /// Terms to apply
+        terms: Vec<LumaTerm>,
+    },
+    /// `c *= factor` for each channel when `max(r, g, b) > threshold`
+    /// (whites)
+    ScaleIfMaxAbove {
+        /// Exclusive lower bound on the largest channel
+        threshold: f32,
+        /// Multiplier
+        factor: f32,
+    },
+    /// `c *= factor` for each channel when `min(r, g, b) < threshold`
+    /// (blacks)
+    ScaleIfMinBelow {
+        /// Exclusive upper bound on the smallest channel
+        threshold: f32,
+        /// Multiplier
+        factor: f32,
+    },
+    /// Saturation, then vibrance, around the pixel's luminance.
+    ///
+    /// Both amounts are first multiplied by `1 - skin_tones.weight(rgb)`
+    /// when skin tones are protected. A nonzero amount `a` then maps each
+    /// channel to `y + (c - y) * (1 + a / 100)` with `y = luma(rgb)`.
+    /// Vibrance runs on the saturated pixel with its amount scaled by
+    /// `1 - (max - min) / max` (0 for black), and halved again when
+    /// `r > g > b` and `r - g > 0.06`.
+    Saturate {
+        /// Saturation (-100 to 100)
+        saturation: f32,
+        /// Vibrance (-100 to 100)
+        vibrance: f32,
+        /// Luminance weights
+        luma: [f32; 3],
+        /// Skin tone band to hold back, if protected
+        skin_tones: Option<SkinToneBand>,
+    },
+    /// Saturation and vibrance as one scale of Oklch chroma.
+    ///
+    /// The amounts are first scaled for skin tones as in `Saturate`. With
+    /// `s = max(1 + saturation / 100, 0)`, the chroma factor is `s` when
+    /// vibrance is zero and otherwise
+    /// `s * max(1 + vibrance / 100 * (1 - min(C * s / full_chroma, 1)), 0)`,
+    /// where `C` is the input's Oklch chroma. The pixel's chroma is then
+    /// scaled by the factor as [`scale_chroma`] does, keeping lightness and
+    /// hue and pulling colors back inside sRGB along constant hue.
+    OklchChroma {
+        /// Saturation (-100 to 100)
+        saturation: f32,
+        /// Vibrance (-100 to 100)
+        vibrance: f32,
+        /// Oklch chroma at which vibrance stops having an effect
+        full_chroma: f32,
+        /// Skin tone band to hold back, if protected
+        skin_tones: Option<SkinToneBand>,
+    },
+}
+
+/// A luminance-weighted term of [`PipelineStageDesc::LumaWeighted`].
+///
+/// The weight is `w = smoothstep(edge0, edge1, y)` for the stage's
+/// luminance `y`, with `smoothstep` clamping `(y - edge0) / (edge1 - edge0)`
+/// to 0..1 and returning `t * t * (3 - 2 * t)`. `edge0` may be above
+/// `edge1`, which weights dark pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LumaTerm {
+    /// `c *= 1 + amount * w`
+    Scale {
+        /// Luminance where the weight is 0
+        edge0: f32,
+        /// Luminance where the weight is 1
+        edge1: f32,
+        /// Strength
+        amount: f32,
+    },
+    /// `c += amount * w * gain`
+    Add {
+        /// Luminance where the weight is 0
+        edge0: f32,
+        /// Luminance where the weight is 1
+        edge1: f32,
+        /// Strength
+        amount: f32,
+        /// Scale of the strength
+        gain: f32,
+    },
+}
+
+/// The hue and saturation band treated as skin.
+///
+/// With HSV hue `h` in degrees and saturation `s`, the weight is
+/// `band(h, hue) * band(s, saturation)`, where
+/// `band(x, [e0, e1, e2, e3]) = smoothstep(e0, e1, x) * (1 - smoothstep(e2, e3, x))`.
+/// Black and gray pixels weigh 0.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SkinToneBand {
+    /// Hue edges in degrees
+    pub hue: [f32; 4],
+    /// Saturation edges
+    pub saturation: [f32; 4],
+}
+
+impl SkinToneBand {
+    /// The band used by the adjustments.
+    pub fn standard() -> Self {
+        Self {
+            hue: SKIN_TONE_HUE_EDGES,
+            saturation: SKIN_TONE_SATURATION_EDGES,
+        }
+    }
+
+    /// How much a pixel looks like skin, from 0.0 to 1.0.
+    fn weight(&self, [r, g, b]: [f32; 3]) -> f32 {
+        let max_c = r.max(g).max(b);
+        let chroma = max_c - r.min(g).min(b);
+        if max_c <= 0.0 || chroma <= 0.0 {
+            return 0.0;
+        }
+        let hue = if max_c == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max_c == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        band(hue, self.hue) * band(chroma / max_c, self.saturation)
+    }
+}
+
+fn band(x: f32, [e0, e1, e2, e3]: [f32; 4]) -> f32 {
+    smoothstep(e0, e1, x) * (1.0 - smoothstep(e2, e3, x))
+}
+
+/// Describe the per-pixel computation of
+/// [`apply_adjustments_to_pixel`](crate::adjustments::apply_adjustments_to_pixel)
+/// for `adjustments`, in the default stage order.
+pub fn describe_adjustment_pipeline(adjustments: &BasicAdjustments) -> Vec<PipelineStageDesc> {
+    let mut stages = Vec::new();
+
+    if adjustments.exposure != 0.0 {
+        let multiplier = 2.0_f32.powf(adjustments.exposure);
+        stages.push(PipelineStageDesc::Multiply {
+            rgb: [multiplier; 3],
+        });
+    }
+    if adjustments.contrast != 0.0 {
+        stages.push(PipelineStageDesc::ScaleAround {
+            pivot: 0.5,
+            factor: 1.0 + adjustments.contrast / 100.0,
+        });
+    }
+
+    let temperature = adjustments.temperature;
+    if temperature != 0.0 {
+        let shift = (temperature / 100.0 * 0.3).abs();
+        let rgb = if temperature < 0.0 {
+            [1.0 + shift, 1.0, 1.0 - shift]
+        } else {
+            [1.0 - shift, 1.0, 1.0 + shift]
+        };
+        stages.push(PipelineStageDesc::Multiply { rgb });
+    }
+    let tint = adjustments.tint;
+    if tint != 0.0 {
+        let shift = (tint / 100.0 * 0.2).abs();
+        let rgb = if tint < 0.0 {
+            [1.0, 1.0 + shift, 1.0]
+        } else {
+            [1.0 + shift, 1.0 - shift, 1.0 + shift]
+        };
+        stages.push(PipelineStageDesc::Multiply { rgb });
+    }
+
+    let terms: Vec<LumaTerm> = [
+        (adjustments.highlights, 0.5, 1.0),
+        (adjustments.shadows, 0.5, 0.0),
+    ]
+    .into_iter()
+    .filter(|&(amount, _, _)| amount != 0.0)
+    .map(|(amount, edge0, edge1)| {
+        if amount < 0.0 {
+            LumaTerm::Scale {
+                edge0,
+                edge1,
+                amount: amount / 100.0,
+            }
+        } else {
+            LumaTerm::Add {
+                edge0,
+                edge1,
+                amount: amount / 100.0,
+                gain: 0.5,
+            }
+        }
+    })
+    .collect();
+    if !terms.is_empty() {
+        stages.push(PipelineStageDesc::LumaWeighted { luma: LUMA, terms });
+    }
+
+    if adjustments.whites != 0.0 {
+        stages.push(PipelineStageDesc::ScaleIfMaxAbove {
+            threshold: WHITES_THRESHOLD,
+            factor: 1.0 + (adjustments.whites / 100.0) * 0.3,
+        });
+    }
+    if adjustments.blacks != 0.0 {
+        stages.push(PipelineStageDesc::ScaleIfMinBelow {
+            threshold: BLACKS_THRESHOLD,
+            factor: 1.0 + (adjustments.blacks / 100.0) * 0.2,
+        });
+    }
+
+    let (saturation, vibrance) = (adjustments.saturation, adjustments.vibrance);
+    if saturation != 0.0 || vibrance != 0.0 {
+        let skin_tones = adjustments.protect_skin_tones.then(SkinToneBand::standard);
+        stages.push(match adjustments.color_model {
+            ColorModel::Legacy => PipelineStageDesc::Saturate {
+                saturation,
+                vibrance,
+                luma: LUMA,
+                skin_tones,
+            },
+            ColorModel::Oklab => PipelineStageDesc::OklchChroma {
+                saturation,
+                vibrance,
+                full_chroma: VIBRANCE_FULL_CHROMA,
+                skin_tones,
+            },
+        });
+    }
+
+    stages
+}
+
+/// Run a pipeline description on one pixel.
+///
+/// This is the reference interpreter for the schema: it follows the
+/// formulas documented on [`PipelineStageDesc`] and nothing else. The
+/// result is not clamped.
+pub fn run_stage_descriptions(pixel: [f32; 3], stages: &[PipelineStageDesc]) -> [f32; 3] {
+    stages.iter().fold(pixel, run_stage)
+}
+
+fn run_stage(rgb: [f32; 3], stage: &PipelineStageDesc) -> [f32; 3] {
+    let [r, g, b] = rgb;
+    match stage {
+        PipelineStageDesc::Multiply { rgb: m } => [r * m[0], g * m[1], b * m[2]],
+        PipelineStageDesc::ScaleAround { pivot, factor } => {
+            rgb.map(|c| (c - pivot) * factor + pivot)
+        }
+        PipelineStageDesc::LumaWeighted { luma, terms } => {
+            let y = luma_of(rgb, luma);
+            terms.iter().fold(rgb, |rgb, term| match *term {
+                LumaTerm::Scale {
+                    edge0,
+                    edge1,
+                    amount,
+                } => {
+                    let factor = 1.0 + amount * smoothstep(edge0, edge1, y);
+                    rgb.map(|c| c * factor)
+                }
+                LumaTerm::Add {
+                    edge0,
+                    edge1,
+                    amount,
+                    gain,
+                } => {
+                    let boost = amount * smoothstep(edge0, edge1, y) * gain;
+                    rgb.map(|c| c + boost)
+                }
+            })
+        }
+        PipelineStageDesc::ScaleIfMaxAbove { threshold, factor } => {
+            if r.max(g).max(b) > *threshold {
+                rgb.map(|c| c * factor)
+            } else {
+                rgb
+            }
+        }
+        PipelineStageDesc::ScaleIfMinBelow { threshold, factor } => {
+            if r.min(g).min(b) < *threshold {
+                rgb.map(|c| c * factor)
+            } else {
+                rgb
+            }
+        }
+        PipelineStageDesc::Saturate {
+            saturation,
+            vibrance,
+            luma,
+            skin_tones,
+        } => {
+            let keep = skin_keep(rgb, skin_tones);
+            let rgb = saturate(rgb, saturation * keep, luma);
+            let [r, g, b] = rgb;
+            let vibrance = vibrance * keep;
+            if vibrance == 0.0 {
+                return rgb;
+            }
+            let max_c = r.max(g).max(b);
+            let current_saturation = if max_c > 0.0 {
+                (max_c - r.min(g).min(b)) / max_c
+            } else {
+                0.0
+            };
+            let skin_protection = if r > g && g > b && (r - g) > 0.06 {
+                0.5
+            } else {
+                1.0
+            };
+            saturate(
+                rgb,
+                vibrance * skin_protection * (1.0 - current_saturation),
+                luma,
+            )
+        }
+        PipelineStageDesc::OklchChroma {
+            saturation,
+            vibrance,
+            full_chroma,
+            skin_tones,
+        } => {
+            let keep = skin_keep(rgb, skin_tones);
+            let (saturation, vibrance) = (saturation * keep, vibrance * keep);
+            if saturation == 0.0 && vibrance == 0.0 {
+                return rgb;
+            }
+            let s = (1.0 + saturation / 100.0).max(0.0);
+            let factor = if vibrance == 0.0 {
+                s
+            } else {
+                let chroma = srgb_to_oklab(rgb).to_oklch().c * s;
+                let protection = 1.0 - (chroma / full_chroma).min(1.0);
+                s * (1.0 + vibrance / 100.0 * protection).max(0.0)
+            };
+            scale_chroma(rgb, factor)
+        }
+    }
+}
+
+fn luma_of([r, g, b]: [f32; 3], luma: &[f32; 3]) -> f32 {
+    luma[0] * r + luma[1] * g + luma[2] * b
+}
+
+fn skin_keep(rgb: [f32; 3], skin_tones: &Option<SkinToneBand>) -> f32 {
+    skin_tones.map_or(1.0, |band| 1.0 - band.weight(rgb))
+}
+
+fn saturate(rgb: [f32; 3], amount: f32, luma: &[f32; 3]) -> [f32; 3] {
+    if amount == 0.0 {
+        return rgb;
+    }
+    let y = luma_of(rgb, luma);
+    let factor = 1.0 + amount / 100.0;
+    rgb.map(|c| y + (c - y) * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adjustments::apply_adjustments_to_pixel;
+
+    fn every_slider(color_model: ColorModel) -> BasicAdjustments {
+        BasicAdjustments {
+            temperature: -50.0,
+            tint: 25.0,
+            exposure: 1.0,
+            contrast: 50.0,
+            highlights: -40.0,
+            shadows: 30.0,
+            whites: 20.0,
+            blacks: -10.0,
+            vibrance: 15.0,
+            saturation: 20.0,
+            protect_skin_tones: true,
+            color_model,
+        }
+    }
+
+    #[test]
+    fn test_neutral_adjustments_describe_no_stages() {
+        assert!(describe_adjustment_pipeline(&BasicAdjustments::default()).is_empty());
+        assert_eq!(
+            run_stage_descriptions([0.1, 0.5, 0.9], &[]),
+            [0.1, 0.5, 0.9]
+        );
+    }
+
+    #[test]
+    fn test_stage_per_nonzero_slider_group() {
+        let mut adjustments = BasicAdjustments::default();
+        adjustments.shadows = 30.0;
+        adjustments.saturation = -20.0;
+        let stages = describe_adjustment_pipeline(&adjustments);
+        assert_eq!(stages.len(), 2);
+        let PipelineStageDesc::LumaWeighted { terms, .. } = &stages[0] else {
+            panic!("expected luma-weighted stage, got {:?}", stages[0]);
+        };
+        assert_eq!(
+            terms,
+            &[LumaTerm::Add {
+                edge0: 0.5,
+                edge1: 0.0,
+                amount: 0.3,
+                gain: 0.5
+            }]
+        );
+        assert!(matches!(
+            stages[1],
+            PipelineStageDesc::Saturate {
+                skin_tones: None,
+                ..
+            }
+        ));
+
+        adjustments.color_model = ColorModel::Oklab;
+        let stages = describe_adjustment_pipeline(&adjustments);
+        assert!(matches!(stages[1], PipelineStageDesc::OklchChroma { .. }));
+    }
+
+    #[test]
+    fn test_serialized_form_snapshot() {
+        let description = PipelineDescription::new(&every_slider(ColorModel::Legacy));
+        let json = serde_json::to_string(&description).unwrap();
+        let expected = concat!(
+            r#"{"version":1,"stages":["#,
+            r#"{"op":"multiply","rgb":[2.0,2.0,2.0]},"#,
+            r#"{"op":"scale_around","pivot":0.5,"factor":1.5},"#,
+            r#"{"op":"multiply","rgb":[1.15,1.0,0.85]},"#,
+            r#"{"op":"multiply","rgb":[1.05,0.95,1.05]},"#,
+            r#"{"op":"luma_weighted","luma":[0.2126,0.7152,0.0722],"terms":["#,
+            r#"{"kind":"scale","edge0":0.5,"edge1":1.0,"amount":-0.4},"#,
+            r#"{"kind":"add","edge0":0.5,"edge1":0.0,"amount":0.3,"gain":0.5}]},"#,
+            r#"{"op":"scale_if_max_above","threshold":0.9,"factor":1.06},"#,
+            r#"{"op":"scale_if_min_below","threshold":0.1,"factor":0.98},"#,
+            r#"{"op":"saturate","saturation":20.0,"vibrance":15.0,"#,
+            r#""luma":[0.2126,0.7152,0.0722],"skin_tones":"#,
+            r#"{"hue":[10.0,20.0,50.0,60.0],"saturation":[0.05,0.15,0.7,0.9]}}]}"#,
+        );
+        assert_eq!(json, expected);
+
+        let parsed: PipelineDescription = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, description);
+    }
+
+    #[test]
+    fn test_oklch_stage_serialized_form() {
+        let mut adjustments = BasicAdjustments::default();
+        adjustments.vibrance = 40.0;
+        adjustments.color_model = ColorModel::Oklab;
+        let json = serde_json::to_string(&describe_adjustment_pipeline(&adjustments)).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"op":"oklch_chroma","saturation":0.0,"vibrance":40.0,"full_chroma":0.3,"skin_tones":null}]"#
+        );
+    }
+
+    #[test]
+    fn test_every_slider_matches_native() {
+        for model in [ColorModel::Legacy, ColorModel::Oklab] {
+            let adjustments = every_slider(model);
+            let stages = describe_adjustment_pipeline(&adjustments);
+            for pixel in [
+                [0.0, 0.0, 0.0],
+                [1.0, 1.0, 1.0],
+                [0.85, 0.6, 0.45],
+                [0.05, 0.2, 0.95],
+                [0.95, 0.3, 0.02],
+            ] {
+                let (r, g, b) =
+                    apply_adjustments_to_pixel(pixel[0], pixel[1], pixel[2], &adjustments);
+                let described = run_stage_descriptions(pixel, &stages);
+                for (native, described) in [r, g, b].into_iter().zip(described) {
+                    assert!((native - described).abs() <= 1e-5, "{pixel:?} {model:?}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use crate::adjustments::apply_adjustments_to_pixel;
+    use proptest::prelude::*;
+
+    /// A slider that is zero a third of the time, so stages get skipped.
+    fn slider(range: f32) -> impl Strategy<Value = f32> {
+        prop_oneof![Just(0.0f32), -range..=range, -range..=range]
+    }
+
+    fn adjustments_strategy() -> impl Strategy<Value = BasicAdjustments> {
+        (
+            (slider(5.0), slider(100.0), slider(100.0), slider(100.0)),
+            (slider(100.0), slider(100.0), slider(100.0), slider(100.0)),
+            (slider(100.0), slider(100.0), any::<bool>(), any::<bool>()),
+        )
+            .prop_map(
+                |(
+                    (exposure, contrast, temperature, tint),
+                    (highlights, shadows, whites, blacks),
+                    (saturation, vibrance, protect_skin_tones, oklab),
+                )| BasicAdjustments {
+                    temperature,
+                    tint,
+                    exposure,
+                    contrast,
+                    highlights,
+                    shadows,
+                    whites,
+                    blacks,
+                    vibrance,
+                    saturation,
+                    protect_skin_tones,
+                    color_model: if oklab {
+                        ColorModel::Oklab
+                    } else {
+                        ColorModel::Legacy
+                    },
+                },
+            )
+    }
+
+    proptest! {
+        /// Property: The described pipeline computes what the native
+        /// per-pixel function computes.
+        #[test]
+        fn prop_description_matches_native(
+            adjustments in adjustments_strategy(),
+            pixels in prop::collection::vec(prop::array::uniform3(0.0f32..=1.0), 16),
+        ) {
+            let stages = describe_adjustment_pipeline(&adjustments);
+            for pixel in pixels {
+                let (r, g, b) = apply_adjustments_to_pixel(pixel[0], pixel[1], pixel[2], &adjustments);
+                let described = run_stage_descriptions(pixel, &stages);
+                for (native, described) in [r, g, b].into_iter().zip(described) {
+                    prop_assert!(
+                        (native - described).abs() <= 1e-5,
+                        "{:?}: native {:?}, described {:?}", pixel, (r, g, b), described
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Valid ranges for adjustment values.
+//!
+//! This is the single source of truth for how far each slider can go.
+//! Clamping in the core (e.g. `BasicAdjustments::apply_relative`), input
+//! validation (`BasicAdjustments::validate`) and the limits shown by the UI
+//! all read from here.
+
+use crate::BasicAdjustments;
+use serde::Serialize;
+use thiserror::Error;
+
+/// Minimum and maximum value of one adjustment field.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AdjustmentRange {
+    /// Field name as used in `BasicAdjustments` (e.g. "exposure")
+    pub field: &'static str,
+    /// Smallest allowed value
+    pub min: f32,
+    /// Largest allowed value
+    pub max: f32,
+}
+
+impl AdjustmentRange {
+    const fn new(field: &'static str, min: f32, max: f32) -> Self {
+        Self { field, min, max }
+    }
+
+    /// Clamp a value into this range.
+    pub fn clamp(&self, value: f32) -> f32 {
+        value.clamp(self.min, self.max)
+    }
+}
+
+/// White balance temperature range.
+pub const TEMPERATURE: AdjustmentRange = AdjustmentRange::new("temperature", -100.0, 100.0);
+/// White balance tint range.
+pub const TINT: AdjustmentRange = AdjustmentRange::new("tint", -100.0, 100.0);
+/// Exposure range in stops.
+pub const EXPOSURE: AdjustmentRange = AdjustmentRange::new("exposure", -5.0, 5.0);
+/// Contrast range.
+pub const CONTRAST: AdjustmentRange = AdjustmentRange::new("contrast", -100.0, 100.0);
+/// Highlights range.
+pub const HIGHLIGHTS: AdjustmentRange = AdjustmentRange::new("highlights", -100.0, 100.0);
+/// Shadows range.
+pub const SHADOWS: AdjustmentRange = AdjustmentRange::new("shadows", -100.0, 100.0);
+/// Whites range.
+pub const WHITES: AdjustmentRange = AdjustmentRange::new("whites", -100.0, 100.0);
+/// Blacks range.
+pub const BLACKS: AdjustmentRange = AdjustmentRange::new("blacks", -100.0, 100.0);
+/// Vibrance range.
+pub const VIBRANCE: AdjustmentRange = AdjustmentRange::new("vibrance", -100.0, 100.0);
+/// Saturation range.
+pub const SATURATION: AdjustmentRange = AdjustmentRange::new("saturation", -100.0, 100.0);
+
+/// Ranges for every `BasicAdjustments` field, in declaration order.
+pub const ADJUSTMENT_RANGES: [AdjustmentRange; 10] = [
+    TEMPERATURE,
+    TINT,
+    EXPOSURE,
+    CONTRAST,
+    HIGHLIGHTS,
+    SHADOWS,
+    WHITES,
+    BLACKS,
+    VIBRANCE,
+    SATURATION,
+];
+
+/// Look up the range for a field by name.
+pub fn range_for(field: &str) -> Option<AdjustmentRange> {
+    ADJUSTMENT_RANGES.iter().find(|r| r.field == field).copied()
+}
+
+/// An adjustment field whose value is outside its range.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FieldOutOfRange {
+    /// Field name as used in `BasicAdjustments`
+    pub field: &'static str,
+    /// The rejected value
+    pub value: f32,
+    /// Smallest allowed value
+    pub min: f32,
+    /// Largest allowed value
+    pub max: f32,
+}
+
+/// Invalid adjustment values.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum AdjustmentError {
+    /// One or more fields are NaN or infinite.
+    #[error("Adjustment values are not finite: {}", .fields.join(", "))]
+    NonFinite { fields: Vec<&'static str> },
+
+    /// One or more fields are outside their range.
+    #[error("Adjustment values out of range: {}", format_out_of_range(.fields))]
+    OutOfRange { fields: Vec<FieldOutOfRange> },
+
+    /// One or more channels have a black level at or above their white level.
+    #[error("Channel levels have black at or above white: {}", .channels.join(", "))]
+    InvertedLevels { channels: Vec<&'static str> },
+}
+
+impl AdjustmentError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AdjustmentError::NonFinite { .. } => "NON_FINITE_ADJUSTMENT",
+            AdjustmentError::OutOfRange { .. } => "ADJUSTMENT_OUT_OF_RANGE",
+            AdjustmentError::InvertedLevels { .. } => "INVERTED_CHANNEL_LEVELS",
+        }
+    }
+
+    /// Names of the offending fields (channels, for inverted levels), in
+    /// declaration order.
+    pub fn fields(&self) -> Vec<&'static str> {
+        match self {
+            AdjustmentError::NonFinite { fields } => fields.clone(),
+            AdjustmentError::OutOfRange { fields } => fields.iter().map(|f| f.field).collect(),
+            AdjustmentError::InvertedLevels { channels } => channels.clone(),
+        }
+    }
+}
+
+fn format_out_of_range(fields: &[FieldOutOfRange]) -> String {
+    fields
+        .iter()
+        .map(|f| {
+            format!(
+                "{} = {} (expected {} to {})",
+                f.field, f.value, f.min, f.max
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl BasicAdjustments {
+    /// Field values in the same order as [`ADJUSTMENT_RANGES`].
+    fn values(&self) -> [f32; 10] {
+        [
+            self.temperature,
+            self.tint,
+            self.exposure,
+            self.contrast,
+            self.highlights,
+            self.shadows,
+            self.whites,
+            self.blacks,
+            self.vibrance,
+            self.saturation,
+        ]
+    }
+
+    /// Reject NaN or infinite fields.
+    fn check_finite(&self) -> Result<(), AdjustmentError> {
+        let fields: Vec<_> = ADJUSTMENT_RANGES
+            .iter()
+            .zip(self.values())
+            .filter(|(_, value)| !value.is_finite())
+            .map(|(range, _)| range.field)
+            .collect();
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(AdjustmentError::NonFinite { fields })
+        }
+    }
+
+    /// Check that every field is finite and within its range.
+    ///
+    /// The error lists every offending field, not just the first one.
+    /// NaN and infinite values are reported ahead of range violations.
+    pub fn validate(&self) -> Result<(), AdjustmentError> {
+        self.check_finite()?;
+
+        let fields: Vec<_> = ADJUSTMENT_RANGES
+            .iter()
+            .zip(self.values())
+            .filter(|(range, value)| *value < range.min || *value > range.max)
+            .map(|(range, value)| FieldOutOfRange {
+                field: range.field,
+                value,
+                min: range.min,
+                max: range.max,
+            })
+            .collect();
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(AdjustmentError::OutOfRange { fields })
+        }
+    }
+
+    /// Return a copy with every field clamped to its range.
+    ///
+    /// NaN values are left as they are; use [`Self::sanitized`] to reject them.
+    pub fn clamped(&self) -> BasicAdjustments {
+        BasicAdjustments {
+            temperature: TEMPERATURE.clamp(self.temperature),
+            tint: TINT.clamp(self.tint),
+            exposure: EXPOSURE.clamp(self.exposure),
+            contrast: CONTRAST.clamp(self.contrast),
+            highlights: HIGHLIGHTS.clamp(self.highlights),
+            shadows: SHADOWS.clamp(self.shadows),
+            whites: WHITES.clamp(self.whites),
+            blacks: BLACKS.clamp(self.blacks),
+            vibrance: VIBRANCE.clamp(self.vibrance),
+            saturation: SATURATION.clamp(self.saturation),
+            protect_skin_tones: self.protect_skin_tones,
+            color_model: self.color_model,
+        }
+    }
+
+    /// Validate, or clamp out-of-range fields when `clamp` is true.
+    ///
+    /// Interactive slider drags pass `clamp = true` so an overshoot renders
+    /// at the limit instead of failing. NaN and infinite values are rejected
+    /// either way.
+    pub fn sanitized(&self, clamp: bool) -> Result<BasicAdjustments, AdjustmentError> {
+        if clamp {
+            self.check_finite()?;
+            Ok(self.clamped())
+        } else {
+            self.validate()?;
+            Ok(self.clone())
+        }
+    }
+
+    /// Add `delta` to each field, clamping the result to the field's range.
+    ///
+    /// Used by quick develop, which nudges whatever a photo already has
+    /// (e.g. +1/3 EV) instead of setting absolute values.
+    pub fn apply_relative(&mut self, delta: &BasicAdjustments) {
+        self.temperature = TEMPERATURE.clamp(self.temperature + delta.temperature);
+        self.tint = TINT.clamp(self.tint + delta.tint);
+        self.exposure = EXPOSURE.clamp(self.exposure + delta.exposure);
+        self.contrast = CONTRAST.clamp(self.contrast + delta.contrast);
+        self.highlights = HIGHLIGHTS.clamp(self.highlights + delta.highlights);
+        self.shadows = SHADOWS.clamp(self.shadows + delta.shadows);
+        self.whites = WHITES.clamp(self.whites + delta.whites);
+        self.blacks = BLACKS.clamp(self.blacks + delta.blacks);
+        self.vibrance = VIBRANCE.clamp(self.vibrance + delta.vibrance);
+        self.saturation = SATURATION.clamp(self.saturation + delta.saturation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(adj: &BasicAdjustments, name: &str) -> f32 {
+        match name {
+            "temperature" => adj.temperature,
+            "tint" => adj.tint,
+            "exposure" => adj.exposure,
+            "contrast" => adj.contrast,
+            "highlights" => adj.highlights,
+            "shadows" => adj.shadows,
+            "whites" => adj.whites,
+            "blacks" => adj.blacks,
+            "vibrance" => adj.vibrance,
+            "saturation" => adj.saturation,
+            _ => panic!("unknown field {}", name),
+        }
+    }
+
+    fn uniform_delta(value: f32) -> BasicAdjustments {
+        BasicAdjustments {
+            temperature: value,
+            tint: value,
+            exposure: value,
+            contrast: value,
+            highlights: value,
+            shadows: value,
+            whites: value,
+            blacks: value,
+            vibrance: value,
+            saturation: value,
+            protect_skin_tones: false,
+            color_model: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_exposure_clamps_at_max() {
+        let mut adj = BasicAdjustments {
+            exposure: 4.5,
+            ..Default::default()
+        };
+        adj.apply_relative(&BasicAdjustments {
+            exposure: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(adj.exposure, 5.0);
+    }
+
+    #[test]
+    fn test_repeated_nudges_accumulate() {
+        let third = BasicAdjustments {
+            exposure: 1.0 / 3.0,
+            contrast: 10.0,
+            ..Default::default()
+        };
+        let mut adj = BasicAdjustments::default();
+        for _ in 0..3 {
+            adj.apply_relative(&third);
+        }
+        assert!((adj.exposure - 1.0).abs() < 1e-5);
+        assert_eq!(adj.contrast, 30.0);
+        assert_eq!(adj.saturation, 0.0);
+    }
+
+    #[test]
+    fn test_negative_nudge_from_existing_value() {
+        let mut adj = BasicAdjustments {
+            contrast: 25.0,
+            ..Default::default()
+        };
+        adj.apply_relative(&BasicAdjustments {
+            contrast: -10.0,
+            ..Default::default()
+        });
+        assert_eq!(adj.contrast, 15.0);
+    }
+
+    #[test]
+    fn test_ranges_match_clamping() {
+        let mut high = BasicAdjustments::default();
+        high.apply_relative(&uniform_delta(1000.0));
+        let mut low = BasicAdjustments::default();
+        low.apply_relative(&uniform_delta(-1000.0));
+
+        for range in ADJUSTMENT_RANGES {
+            assert_eq!(field(&high, range.field), range.max, "{}", range.field);
+            assert_eq!(field(&low, range.field), range.min, "{}", range.field);
+        }
+    }
+
+    #[test]
+    fn test_validate_accepts_limits() {
+        assert_eq!(BasicAdjustments::default().validate(), Ok(()));
+        assert_eq!(uniform_delta(-5.0).validate(), Ok(()));
+
+        let mut high = BasicAdjustments::default();
+        high.apply_relative(&uniform_delta(1000.0));
+        assert_eq!(high.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_lists_every_out_of_range_field() {
+        let adj = BasicAdjustments {
+            exposure: 6.0,
+            contrast: -150.0,
+            saturation: 100.0,
+            ..Default::default()
+        };
+        let err = adj.validate().unwrap_err();
+        assert_eq!(err.code(), "ADJUSTMENT_OUT_OF_RANGE");
+        assert_eq!(err.fields(), vec!["exposure", "contrast"]);
+
+        let message = err.to_string();
+        assert!(
+            message.contains("exposure = 6 (expected -5 to 5)"),
+            "{}",
+            message
+        );
+        assert!(message.contains("contrast = -150"), "{}", message);
+    }
+
+    #[test]
+    fn test_validate_rejects_non_finite() {
+        let adj = BasicAdjustments {
+            tint: f32::NAN,
+            whites: f32::INFINITY,
+            exposure: 9.0,
+            ..Default::default()
+        };
+        let err = adj.validate().unwrap_err();
+        assert_eq!(err.code(), "NON_FINITE_ADJUSTMENT");
+        assert_eq!(err.fields(), vec!["tint", "whites"]);
+    }
+
+    #[test]
+    fn test_clamped_matches_ranges() {
+        let high = uniform_delta(1000.0).clamped();
+        let low = uniform_delta(-1000.0).clamped();
+        for range in ADJUSTMENT_RANGES {
+            assert_eq!(field(&high, range.field), range.max, "{}", range.field);
+            assert_eq!(field(&low, range.field), range.min, "{}", range.field);
+        }
+        assert_eq!(high.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_sanitized() {
+        let adj = BasicAdjustments {
+            exposure: 7.5,
+            contrast: 20.0,
+            ..Default::default()
+        };
+        assert!(adj.sanitized(false).is_err());
+
+        let clamped = adj.sanitized(true).unwrap();
+        assert_eq!(clamped.exposure, 5.0);
+        assert_eq!(clamped.contrast, 20.0);
+
+        let nan = BasicAdjustments {
+            exposure: f32::NAN,
+            ..Default::default()
+        };
+        for clamp in [false, true] {
+            let err = nan.sanitized(clamp).unwrap_err();
+            assert_eq!(err.code(), "NON_FINITE_ADJUSTMENT");
+        }
+    }
+
+    #[test]
+    fn test_range_for() {
+        assert_eq!(range_for("exposure"), Some(EXPOSURE));
+        assert_eq!(range_for("contrast").unwrap().max, 100.0);
+        assert_eq!(range_for("clarity"), None);
+    }
+}
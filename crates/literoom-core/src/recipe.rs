@@ -0,0 +1,580 @@
+//! Scriptable pipelines: a list of operations run in one call.
+//!
+//! A recipe is a JSON array of steps such as
+//! `[{"op":"resize_to_fit","max_edge":2048},{"op":"adjustments","exposure":0.5},{"op":"encode_jpeg","quality":85}]`.
+//! Each step is an [`Operation`], tagged by its `op` field. Steps run in
+//! order, each on the previous step's output, and do exactly what the
+//! equivalent direct calls do.
+//!
+//! # Sequencing
+//!
+//! - `decode` turns encoded bytes into pixels. It may only be the first
+//!   step, and only when the recipe starts from bytes; a recipe that starts
+//!   from bytes without it decodes implicitly.
+//! - `encode_jpeg` turns pixels into bytes and must be the last step.
+//! - Every other step takes pixels and returns pixels.
+//!
+//! The whole recipe is checked before any step runs, and every error names
+//! the index of the offending step.
+
+use crate::adjustments::apply_all_adjustments;
+use crate::curve::{apply_tone_curve, ToneCurveLut};
+use crate::decode::{
+    decode_heif_preview, decode_jpeg, decode_raw_thumbnail, is_heif_file, is_raw_file,
+    resize as resize_image, resize_to_fit, DecodeError, DecodedImage, FilterType,
+};
+use crate::edit::{LinearMaskEdit, RadialMaskEdit};
+use crate::encode::encode_jpeg;
+use crate::mask::apply_masked_adjustments_with_curves;
+use crate::transform::{apply_crop, apply_rotation, InterpolationFilter};
+use crate::{BasicAdjustments, CurvePoint, ToneCurve};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// One step of a recipe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Decode JPEG, HEIC/HEIF or RAW bytes (RAW files use their embedded
+    /// preview).
+    Decode,
+    /// Resize to exact dimensions.
+    Resize {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        filter: FilterType,
+    },
+    /// Resize so the longer edge is at most `max_edge`.
+    ResizeToFit {
+        max_edge: u32,
+        #[serde(default)]
+        filter: FilterType,
+    },
+    /// Apply basic adjustments; the adjustment fields sit next to `op`.
+    Adjustments(BasicAdjustments),
+    /// Apply a tone curve through the given control points.
+    ToneCurve { points: Vec<CurvePoint> },
+    /// Apply the enabled masks, linear ones first.
+    Masks {
+        #[serde(default)]
+        linear_masks: Vec<LinearMaskEdit>,
+        #[serde(default)]
+        radial_masks: Vec<RadialMaskEdit>,
+    },
+    /// Crop to a normalized rectangle (0.0 to 1.0).
+    Crop {
+        left: f64,
+        top: f64,
+        width: f64,
+        height: f64,
+    },
+    /// Rotate by `angle` degrees, expanding the canvas.
+    Rotate {
+        angle: f64,
+        #[serde(default)]
+        filter: InterpolationFilter,
+    },
+    /// Encode as JPEG.
+    EncodeJpeg {
+        #[serde(default = "default_jpeg_quality")]
+        quality: u8,
+    },
+}
+
+fn default_jpeg_quality() -> u8 {
+    90
+}
+
+/// Every value the `op` field can take.
+pub const OPERATION_NAMES: &[&str] = &[
+    "decode",
+    "resize",
+    "resize_to_fit",
+    "adjustments",
+    "tone_curve",
+    "masks",
+    "crop",
+    "rotate",
+    "encode_jpeg",
+];
+
+impl Operation {
+    /// The `op` tag of this operation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Decode => "decode",
+            Operation::Resize { .. } => "resize",
+            Operation::ResizeToFit { .. } => "resize_to_fit",
+            Operation::Adjustments(_) => "adjustments",
+            Operation::ToneCurve { .. } => "tone_curve",
+            Operation::Masks { .. } => "masks",
+            Operation::Crop { .. } => "crop",
+            Operation::Rotate { .. } => "rotate",
+            Operation::EncodeJpeg { .. } => "encode_jpeg",
+        }
+    }
+}
+
+/// What a recipe starts from.
+#[derive(Debug, Clone, Copy)]
+pub enum RecipeInput<'a> {
+    /// Encoded file bytes
+    Bytes(&'a [u8]),
+    /// Decoded pixels
+    Image(&'a DecodedImage),
+}
+
+/// What a recipe produces, depending on its last step.
+#[derive(Debug, Clone)]
+pub enum RecipeOutput {
+    /// Pixels, when the recipe does not end with an encode
+    Image(DecodedImage),
+    /// Encoded JPEG bytes
+    Jpeg(Vec<u8>),
+}
+
+/// Errors from parsing, checking or running a recipe.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RecipeError {
+    /// A step's `op` is missing or names no known operation.
+    #[error("Step {step}: unknown operation '{op}' (known operations: {})", OPERATION_NAMES.join(", "))]
+    UnknownOp { step: usize, op: String },
+
+    /// A step's parameters could not be parsed.
+    #[error("Step {step}: invalid parameters: {message}")]
+    InvalidStep { step: usize, message: String },
+
+    /// `decode` appears after the first step, or the recipe starts from
+    /// pixels.
+    #[error("Step {step}: decode must be the first step and needs encoded bytes as input")]
+    MisplacedDecode { step: usize },
+
+    /// `encode_jpeg` is followed by more steps.
+    #[error("Step {step}: encode must be the last step")]
+    MisplacedEncode { step: usize },
+
+    /// A step failed while running.
+    #[error("Step {step} ({op}) failed: {message}")]
+    StepFailed {
+        step: usize,
+        op: &'static str,
+        message: String,
+    },
+}
+
+impl RecipeError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RecipeError::UnknownOp { .. } => "UNKNOWN_OP",
+            RecipeError::InvalidStep { .. } => "INVALID_STEP",
+            RecipeError::MisplacedDecode { .. } => "MISPLACED_DECODE",
+            RecipeError::MisplacedEncode { .. } => "MISPLACED_ENCODE",
+            RecipeError::StepFailed { .. } => "STEP_FAILED",
+        }
+    }
+
+    /// Index of the offending step.
+    pub fn step(&self) -> usize {
+        match self {
+            RecipeError::UnknownOp { step, .. }
+            | RecipeError::InvalidStep { step, .. }
+            | RecipeError::MisplacedDecode { step }
+            | RecipeError::MisplacedEncode { step }
+            | RecipeError::StepFailed { step, .. } => *step,
+        }
+    }
+
+    /// Classify a step that failed to deserialize.
+    ///
+    /// `op` is the step's `op` field, if it has a string one. Unknown or
+    /// missing names give [`RecipeError::UnknownOp`], anything else
+    /// [`RecipeError::InvalidStep`] with `message`.
+    pub fn from_parse(step: usize, op: Option<&str>, message: impl Into<String>) -> Self {
+        match op {
+            Some(op) if OPERATION_NAMES.contains(&op) => RecipeError::InvalidStep {
+                step,
+                message: message.into(),
+            },
+            _ => RecipeError::UnknownOp {
+                step,
+                op: op.unwrap_or_default().to_string(),
+            },
+        }
+    }
+}
+
+/// Check that the steps are in a valid order for `input`.
+pub fn validate_recipe(input: &RecipeInput, ops: &[Operation]) -> Result<(), RecipeError> {
+    let last = ops.len().saturating_sub(1);
+    for (step, op) in ops.iter().enumerate() {
+        match op {
+            Operation::Decode if step != 0 || matches!(input, RecipeInput::Image(_)) => {
+                return Err(RecipeError::MisplacedDecode { step });
+            }
+            Operation::EncodeJpeg { .. } if step != last => {
+                return Err(RecipeError::MisplacedEncode { step });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Run a recipe.
+///
+/// A recipe that starts from bytes without a `decode` step decodes first;
+/// if that fails, the error is reported for step 0 as a `decode`.
+///
+/// # Errors
+/// Returns the first ordering problem found by [`validate_recipe`] before
+/// running anything, or `RecipeError::StepFailed` for the first step that
+/// fails.
+pub fn run_recipe(input: RecipeInput, ops: &[Operation]) -> Result<RecipeOutput, RecipeError> {
+    validate_recipe(&input, ops)?;
+
+    let decode_failed = |e: DecodeError| RecipeError::StepFailed {
+        step: 0,
+        op: "decode",
+        message: e.to_string(),
+    };
+    let (mut image, start) = match (input, ops.first()) {
+        (RecipeInput::Image(image), _) => (image.clone(), 0),
+        (RecipeInput::Bytes(bytes), Some(Operation::Decode)) => {
+            (decode_any(bytes).map_err(decode_failed)?, 1)
+        }
+        (RecipeInput::Bytes(bytes), _) => (decode_any(bytes).map_err(decode_failed)?, 0),
+    };
+
+    for (step, op) in ops.iter().enumerate().skip(start) {
+        let output = run_step(image, op).map_err(|message| RecipeError::StepFailed {
+            step,
+            op: op.name(),
+            message,
+        })?;
+        match output {
+            RecipeOutput::Image(next) => image = next,
+            // Encoding is the last step
+            RecipeOutput::Jpeg(bytes) => return Ok(RecipeOutput::Jpeg(bytes)),
+        }
+    }
+
+    Ok(RecipeOutput::Image(image))
+}
+
+/// Decode JPEG, HEIC/HEIF or RAW bytes, picking the decoder by content.
+fn decode_any(bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
+    if is_raw_file(bytes) {
+        decode_raw_thumbnail(bytes)
+    } else if is_heif_file(bytes) {
+        decode_heif_preview(bytes)
+    } else {
+        decode_jpeg(bytes)
+    }
+}
+
+/// Run one pixel step, returning the error message on failure.
+fn run_step(mut image: DecodedImage, op: &Operation) -> Result<RecipeOutput, String> {
+    let image = match op {
+        Operation::Decode => unreachable!("only the first step decodes"),
+        Operation::Resize {
+            width,
+            height,
+            filter,
+        } => resize_image(&image, *width, *height, *filter).map_err(|e| e.to_string())?,
+        Operation::ResizeToFit { max_edge, filter } => {
+            resize_to_fit(&image, *max_edge, *filter).map_err(|e| e.to_string())?
+        }
+        Operation::Adjustments(adj) => {
+            image.validate().map_err(|e| e.to_string())?;
+            let adj = adj.sanitized(false).map_err(|e| e.to_string())?;
+            apply_all_adjustments(&mut image.pixels, &adj);
+            image
+        }
+        Operation::ToneCurve { points } => {
+            image.validate().map_err(|e| e.to_string())?;
+            let curve = ToneCurve {
+                points: points.clone(),
+            };
+            apply_tone_curve(&mut image.pixels, &ToneCurveLut::from_curve(&curve));
+            image
+        }
+        Operation::Masks {
+            linear_masks,
+            radial_masks,
+        } => {
+            let linear = enabled_masks(
+                linear_masks
+                    .iter()
+                    .map(|m| (m.enabled, &m.mask, &m.adjustments, &m.tone_curve)),
+            );
+            let radial = enabled_masks(
+                radial_masks
+                    .iter()
+                    .map(|m| (m.enabled, &m.mask, &m.adjustments, &m.tone_curve)),
+            );
+            let (width, height) = (image.width, image.height);
+            apply_masked_adjustments_with_curves(
+                &mut image.pixels,
+                width,
+                height,
+                &linear,
+                &radial,
+            )
+            .map_err(|e| e.to_string())?;
+            image
+        }
+        Operation::Crop {
+            left,
+            top,
+            width,
+            height,
+        } => apply_crop(&image, *left, *top, *width, *height).map_err(|e| e.to_string())?,
+        Operation::Rotate { angle, filter } => {
+            apply_rotation(&image, *angle, *filter).map_err(|e| e.to_string())?
+        }
+        Operation::EncodeJpeg { quality } => {
+            let bytes = encode_jpeg(&image.pixels, image.width, image.height, *quality)
+                .map_err(|e| e.to_string())?;
+            return Ok(RecipeOutput::Jpeg(bytes));
+        }
+    };
+    Ok(RecipeOutput::Image(image))
+}
+
+/// Mask layers for the enabled masks, with their curves as LUTs.
+fn enabled_masks<'a, M: Clone + 'a>(
+    masks: impl Iterator<Item = (bool, &'a M, &'a BasicAdjustments, &'a Option<ToneCurve>)>,
+) -> Vec<(M, BasicAdjustments, Option<ToneCurveLut>)> {
+    masks
+        .filter(|(enabled, ..)| *enabled)
+        .map(|(_, mask, adj, curve)| {
+            let lut = curve.as_ref().map(ToneCurveLut::from_curve);
+            (mask.clone(), adj.clone(), lut)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_jpeg;
+    use crate::testing::noise;
+    use crate::RadialGradientMask;
+
+    fn image_of(output: RecipeOutput) -> DecodedImage {
+        match output {
+            RecipeOutput::Image(image) => image,
+            RecipeOutput::Jpeg(_) => panic!("expected pixels"),
+        }
+    }
+
+    #[test]
+    fn test_recipe_matches_manual_chain() {
+        let source = noise(120, 80, 4);
+        let jpeg = encode_jpeg(&source.pixels, 120, 80, 95).unwrap();
+        let adj = BasicAdjustments {
+            exposure: 0.5,
+            contrast: 20.0,
+            ..Default::default()
+        };
+        let points = vec![
+            CurvePoint::new(0.0, 0.0),
+            CurvePoint::new(0.25, 0.15),
+            CurvePoint::new(0.75, 0.85),
+            CurvePoint::new(1.0, 1.0),
+        ];
+        let vignette = RadialMaskEdit {
+            id: "vignette".to_string(),
+            mask: RadialGradientMask::new(0.5, 0.5, 0.4, 0.3, 0.2, 0.5, true),
+            enabled: true,
+            adjustments: BasicAdjustments {
+                exposure: -1.0,
+                ..Default::default()
+            },
+            tone_curve: None,
+        };
+        let ops = vec![
+            Operation::Decode,
+            Operation::ResizeToFit {
+                max_edge: 60,
+                filter: FilterType::Lanczos3,
+            },
+            Operation::Rotate {
+                angle: 5.0,
+                filter: InterpolationFilter::Bilinear,
+            },
+            Operation::Crop {
+                left: 0.1,
+                top: 0.1,
+                width: 0.8,
+                height: 0.8,
+            },
+            Operation::Adjustments(adj.clone()),
+            Operation::ToneCurve {
+                points: points.clone(),
+            },
+            Operation::Masks {
+                linear_masks: vec![],
+                radial_masks: vec![vignette.clone()],
+            },
+            Operation::EncodeJpeg { quality: 85 },
+        ];
+
+        let RecipeOutput::Jpeg(bytes) = run_recipe(RecipeInput::Bytes(&jpeg), &ops).unwrap() else {
+            panic!("expected JPEG bytes");
+        };
+
+        let image = decode_jpeg(&jpeg).unwrap();
+        let image = resize_to_fit(&image, 60, FilterType::Lanczos3).unwrap();
+        let image = apply_rotation(&image, 5.0, InterpolationFilter::Bilinear).unwrap();
+        let mut image = apply_crop(&image, 0.1, 0.1, 0.8, 0.8).unwrap();
+        apply_all_adjustments(&mut image.pixels, &adj);
+        let lut = ToneCurveLut::from_curve(&ToneCurve { points });
+        apply_tone_curve(&mut image.pixels, &lut);
+        let (width, height) = (image.width, image.height);
+        apply_masked_adjustments_with_curves(
+            &mut image.pixels,
+            width,
+            height,
+            &[],
+            &[(vignette.mask, vignette.adjustments, None)],
+        )
+        .unwrap();
+        let expected = encode_jpeg(&image.pixels, image.width, image.height, 85).unwrap();
+
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_recipe_from_pixels_returns_pixels() {
+        let source = noise(40, 30, 9);
+        let ops = vec![
+            Operation::Resize {
+                width: 20,
+                height: 15,
+                filter: FilterType::Bilinear,
+            },
+            Operation::Adjustments(BasicAdjustments {
+                saturation: 30.0,
+                ..Default::default()
+            }),
+        ];
+
+        let image = image_of(run_recipe(RecipeInput::Image(&source), &ops).unwrap());
+
+        let mut expected = resize_image(&source, 20, 15, FilterType::Bilinear).unwrap();
+        apply_all_adjustments(
+            &mut expected.pixels,
+            &BasicAdjustments {
+                saturation: 30.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(image.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_bytes_without_decode_step_decode_implicitly() {
+        let source = noise(16, 16, 1);
+        let jpeg = encode_jpeg(&source.pixels, 16, 16, 90).unwrap();
+
+        let image = image_of(run_recipe(RecipeInput::Bytes(&jpeg), &[]).unwrap());
+
+        assert_eq!(image.pixels, decode_jpeg(&jpeg).unwrap().pixels);
+    }
+
+    #[test]
+    fn test_misplaced_steps_name_the_step() {
+        let source = noise(8, 8, 2);
+        let encode = Operation::EncodeJpeg { quality: 90 };
+        let adjust = Operation::Adjustments(BasicAdjustments::default());
+
+        let err = run_recipe(
+            RecipeInput::Image(&source),
+            &[adjust.clone(), encode.clone(), adjust.clone()],
+        )
+        .unwrap_err();
+        assert_eq!((err.code(), err.step()), ("MISPLACED_ENCODE", 1));
+
+        let err = run_recipe(RecipeInput::Image(&source), &[Operation::Decode]).unwrap_err();
+        assert_eq!((err.code(), err.step()), ("MISPLACED_DECODE", 0));
+
+        let err = run_recipe(
+            RecipeInput::Bytes(&[]),
+            &[adjust.clone(), Operation::Decode],
+        )
+        .unwrap_err();
+        assert_eq!((err.code(), err.step()), ("MISPLACED_DECODE", 1));
+    }
+
+    #[test]
+    fn test_failing_step_is_reported() {
+        let source = noise(8, 8, 2);
+        let ops = vec![
+            Operation::Resize {
+                width: 4,
+                height: 4,
+                filter: FilterType::Bilinear,
+            },
+            Operation::Adjustments(BasicAdjustments {
+                exposure: 50.0,
+                ..Default::default()
+            }),
+        ];
+
+        let err = run_recipe(RecipeInput::Image(&source), &ops).unwrap_err();
+
+        assert_eq!((err.code(), err.step()), ("STEP_FAILED", 1));
+        assert!(err.to_string().contains("adjustments"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_errors_are_classified() {
+        let err = RecipeError::from_parse(2, Some("sharpen"), "unknown variant");
+        assert_eq!((err.code(), err.step()), ("UNKNOWN_OP", 2));
+        assert!(err.to_string().contains("sharpen"));
+
+        let err = RecipeError::from_parse(0, None, "missing field `op`");
+        assert_eq!(err.code(), "UNKNOWN_OP");
+
+        let err = RecipeError::from_parse(1, Some("resize"), "missing field `width`");
+        assert_eq!((err.code(), err.step()), ("INVALID_STEP", 1));
+    }
+
+    #[test]
+    fn test_operation_names_match_serde_tags() {
+        let ops = [
+            Operation::Decode,
+            Operation::Resize {
+                width: 1,
+                height: 1,
+                filter: FilterType::Bilinear,
+            },
+            Operation::ResizeToFit {
+                max_edge: 1,
+                filter: FilterType::Bilinear,
+            },
+            Operation::Adjustments(BasicAdjustments::default()),
+            Operation::ToneCurve { points: vec![] },
+            Operation::Masks {
+                linear_masks: vec![],
+                radial_masks: vec![],
+            },
+            Operation::Crop {
+                left: 0.0,
+                top: 0.0,
+                width: 1.0,
+                height: 1.0,
+            },
+            Operation::Rotate {
+                angle: 0.0,
+                filter: InterpolationFilter::Bilinear,
+            },
+            Operation::EncodeJpeg { quality: 90 },
+        ];
+        let names: Vec<_> = ops.iter().map(Operation::name).collect();
+        assert_eq!(names, OPERATION_NAMES);
+    }
+}
@@ -0,0 +1,1520 @@
+//! Rendering an edit state, whole or one region at a time.
+//!
+//! [`render_edit`] runs the edit pipeline on the whole image. The zoomed-in
+//! detail view only shows a small part of a large photo, so [`render_region`]
+//! renders just the output pixels it needs: each one is mapped back through
+//! the crop and the inverse rotation to the source, and only those samples
+//! are adjusted.
+//!
+//! # Pipeline
+//!
+//! 1. Rotation (main angle + straighten)
+//! 2. Crop
+//! 3. Base curve, if enabled
+//! 4. Basic adjustments
+//! 5. Black and white conversion, if enabled
+//! 6. Tone curve
+//! 7. Enabled masks, evaluated on the cropped frame
+//!
+//! [`RenderStage`] groups these steps at the boundaries where an
+//! intermediate can be cached: a worker keeps the image entering a stage
+//! and, when an edit only touches that stage or later ones (see
+//! [`compare_edit_states_stages`](crate::edit::compare_edit_states_stages)),
+//! resumes from it with [`render_from_stage`] instead of re-running the
+//! whole pipeline.
+//!
+//! [`render_mono_split`] renders the color and black and white versions side
+//! by side in one pass, for the B&W toggle's split view.
+//!
+//! [`PreparedEdit`] holds an edit state with its curve LUTs and mask layers
+//! already built, so a batch can apply the same edit to many photos without
+//! rebuilding them per image. Every render function runs through it.
+//!
+//! [`AdjustmentToggles`] switch stages of the pipeline off without touching
+//! the edit state, so the user can judge one panel's effect by flicking it
+//! off and on.
+//!
+//! [`render_thumbnail_with_edits`] renders grid thumbnails: the source is
+//! shrunk first and the pipeline runs at thumbnail size, so an edited
+//! thumbnail costs little more than a plain one.
+//!
+//! # Seamless Tiles
+//!
+//! The rotation filter reads neighboring source pixels (one beyond the sample
+//! point for bilinear, three for Lanczos3). A region takes those straight
+//! from the source image rather than from a rotated intermediate, so tiles
+//! need no overlap: at 1:1 every output pixel is computed from the same
+//! coordinates as in [`render_edit`], and adjacent tiles stitch into the
+//! full render byte for byte, borders included. Masks are evaluated at the
+//! pixel's position in the full frame, not in the tile.
+
+use crate::adjustments::{apply_all_adjustments, apply_channel_luts};
+use crate::curve::{apply_tone_curve, ToneCurveLut};
+use crate::decode::{resize_to_fit, DecodeError, DecodedImage, FilterType};
+use crate::edit::EditState;
+use crate::mask::apply::apply_masked_adjustments_mapped;
+use crate::mask::apply_masked_adjustments_with_curves;
+use crate::monochrome::apply_monochrome;
+use crate::perf;
+use crate::transform::{
+    apply_crop, apply_rotation, crop_pixel_rect, is_negligible_angle, sample, InterpolationFilter,
+    InverseRotation, TransformError,
+};
+use crate::validate::checked_buffer_len;
+use crate::{AdjustmentError, BasicAdjustments, LinearGradientMask, RadialGradientMask, ToneCurve};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeBounds;
+
+/// Rectangle in pixels of the rendered (rotated and cropped) image.
+///
+/// Unlike a crop, the rectangle is not snapped to whole pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RenderRegion {
+    /// Left edge
+    pub x: f64,
+    /// Top edge
+    pub y: f64,
+    /// Width of the region
+    pub width: f64,
+    /// Height of the region
+    pub height: f64,
+}
+
+/// Where the rendered image sits on the rotated canvas.
+struct Frame {
+    /// Inverse rotation, or `None` when the angle is negligible
+    rotation: Option<InverseRotation>,
+    /// Crop offset on the rotated canvas
+    left: u32,
+    top: u32,
+    /// Size of the rendered image
+    width: u32,
+    height: u32,
+}
+
+impl Frame {
+    fn new(image_width: u32, image_height: u32, state: &EditState) -> Self {
+        let angle = total_angle(state);
+        let rotation = (!is_negligible_angle(angle))
+            .then(|| InverseRotation::new(image_width, image_height, angle));
+        let (canvas_w, canvas_h) =
+            rotation.map_or((image_width, image_height), |r| r.output_size());
+
+        let (left, top, width, height) = match state.crop {
+            Some(c) => crop_pixel_rect(
+                canvas_w,
+                canvas_h,
+                c.left as f64,
+                c.top as f64,
+                c.width as f64,
+                c.height as f64,
+            ),
+            None => (0, 0, canvas_w, canvas_h),
+        };
+        Self {
+            rotation,
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+}
+
+/// Main rotation plus straighten, in degrees.
+fn total_angle(state: &EditState) -> f64 {
+    (state.rotation.angle + state.rotation.straighten) as f64
+}
+
+/// Size of the image [`render_edit`] produces for a `width x height` source.
+pub fn rendered_size(width: u32, height: u32, state: &EditState) -> (u32, u32) {
+    let frame = Frame::new(width, height, state);
+    (frame.width, frame.height)
+}
+
+/// A resumable stage of the render pipeline, in the order the stages run.
+///
+/// Each stage starts from the image the previous one produced, so an
+/// intermediate cached at a stage boundary can be fed back to
+/// [`render_from_stage`]. Serializes as the lowercase name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderStage {
+    /// Rotation and crop (pipeline steps 1-2)
+    Transform,
+    /// Channel levels, base curve and basic adjustments (steps 3-4)
+    Adjustments,
+    /// Black and white conversion (step 5)
+    Effects,
+    /// Tone curve (step 6)
+    Curve,
+    /// Enabled masks (step 7)
+    Masks,
+}
+
+impl RenderStage {
+    /// Every stage, in pipeline order.
+    pub const ALL: [RenderStage; 5] = [
+        RenderStage::Transform,
+        RenderStage::Adjustments,
+        RenderStage::Effects,
+        RenderStage::Curve,
+        RenderStage::Masks,
+    ];
+
+    /// Lowercase name, as serialized.
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderStage::Transform => "transform",
+            RenderStage::Adjustments => "adjustments",
+            RenderStage::Effects => "effects",
+            RenderStage::Curve => "curve",
+            RenderStage::Masks => "masks",
+        }
+    }
+
+    /// Stage with the given [`name`](Self::name), if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|stage| stage.name() == name)
+    }
+}
+
+/// Pipeline stages to run, for switching panels off to judge their effect.
+///
+/// Toggles skip stages at render time and never change the edit state, so
+/// switching a panel back on restores the exact previous render. Every
+/// stage is on by default, and missing fields deserialize as on.
+///
+/// The tone and color sliders are skipped by rendering as if they were
+/// zero, which is how the adjustments leave a pixel alone. Curves, masks,
+/// the B&W conversion and the transform have no neutral value to render
+/// with (a curve keeps its points), so their stages are skipped outright.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdjustmentToggles {
+    /// Exposure, contrast, highlights, shadows, whites and blacks
+    pub tone: bool,
+    /// Temperature, tint, vibrance, saturation and the channel levels
+    pub color: bool,
+    /// The base curve and the tone curve
+    pub curve: bool,
+    /// The whole mask stack, whatever each mask's `enabled` flag
+    pub masks: bool,
+    /// The black and white conversion
+    pub effects: bool,
+    /// Rotation and crop; when off, the full source frame is rendered
+    pub transform: bool,
+}
+
+impl Default for AdjustmentToggles {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl AdjustmentToggles {
+    /// Every stage on.
+    pub const ALL: Self = Self {
+        tone: true,
+        color: true,
+        curve: true,
+        masks: true,
+        effects: true,
+        transform: true,
+    };
+
+    /// `adjustments` with the sliders of switched-off panels zeroed.
+    fn filter_adjustments(&self, adjustments: &BasicAdjustments) -> BasicAdjustments {
+        let mut filtered = adjustments.clone();
+        if !self.tone {
+            filtered.exposure = 0.0;
+            filtered.contrast = 0.0;
+            filtered.highlights = 0.0;
+            filtered.shadows = 0.0;
+            filtered.whites = 0.0;
+            filtered.blacks = 0.0;
+        }
+        if !self.color {
+            filtered.temperature = 0.0;
+            filtered.tint = 0.0;
+            filtered.vibrance = 0.0;
+            filtered.saturation = 0.0;
+        }
+        filtered
+    }
+}
+
+/// Render the whole image with every edit in `state` applied.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `state` - Edits to apply
+/// * `filter` - Rotation filter (Bilinear for preview, Lanczos3 for export)
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+pub fn render_edit(
+    image: &DecodedImage,
+    state: &EditState,
+    filter: InterpolationFilter,
+) -> Result<DecodedImage, TransformError> {
+    PreparedEdit::from_state(state).render(image, filter)
+}
+
+/// Render the whole image with the stages `toggles` leaves on.
+///
+/// With [`AdjustmentToggles::ALL`] this is [`render_edit`].
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+pub fn render_edit_with_toggles(
+    image: &DecodedImage,
+    state: &EditState,
+    toggles: AdjustmentToggles,
+    filter: InterpolationFilter,
+) -> Result<DecodedImage, TransformError> {
+    PreparedEdit::from_state(state)
+        .with_toggles(toggles)
+        .render(image, filter)
+}
+
+/// Render `state` from a cached intermediate, running `stage` and every
+/// stage after it.
+///
+/// `cached` is the image entering `stage`, as returned by
+/// [`render_to_stage`] for an edit whose earlier stages match `state`. The
+/// result is then identical to [`render_edit`] on the source. From
+/// [`RenderStage::Transform`], `cached` is the source itself.
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+pub fn render_from_stage(
+    cached: &DecodedImage,
+    stage: RenderStage,
+    state: &EditState,
+    filter: InterpolationFilter,
+) -> Result<DecodedImage, TransformError> {
+    PreparedEdit::from_state(state).render_from_stage(cached, stage, filter)
+}
+
+/// Run the stages before `stage`, giving the intermediate to cache for
+/// [`render_from_stage`].
+///
+/// To [`RenderStage::Transform`] this is a copy of the source.
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+pub fn render_to_stage(
+    image: &DecodedImage,
+    stage: RenderStage,
+    state: &EditState,
+    filter: InterpolationFilter,
+) -> Result<DecodedImage, TransformError> {
+    PreparedEdit::from_state(state).render_to_stage(image, stage, filter)
+}
+
+/// An edit state with everything that does not depend on the image built
+/// once: the channel level, base and tone curve LUTs and the enabled mask
+/// layers.
+///
+/// Masks are in normalized coordinates, so one prepared edit applies to
+/// images of any size. It owns a copy of the state; changing the original
+/// afterwards has no effect on it. Stages switched off with
+/// [`with_toggles`](Self::with_toggles) are skipped by every render.
+#[derive(Debug, Clone)]
+pub struct PreparedEdit {
+    state: EditState,
+    toggles: AdjustmentToggles,
+    channel_levels: Option<[[u8; 256]; 3]>,
+    base_curve: Option<ToneCurveLut>,
+    tone_curve: Option<ToneCurveLut>,
+    linear_masks: MaskLayers<LinearGradientMask>,
+    radial_masks: MaskLayers<RadialGradientMask>,
+}
+
+impl PreparedEdit {
+    /// Prepare `state` for rendering.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AdjustmentError` if the global adjustments or those of an
+    /// enabled mask are not finite or out of range, or if the channel levels
+    /// are invalid.
+    pub fn new(state: &EditState) -> Result<Self, AdjustmentError> {
+        state.channel_levels.validate()?;
+        state.adjustments.validate()?;
+        let linear = state.linear_masks.iter().filter(|m| m.enabled);
+        let radial = state.radial_masks.iter().filter(|m| m.enabled);
+        for adjustments in linear
+            .map(|m| &m.adjustments)
+            .chain(radial.map(|m| &m.adjustments))
+        {
+            adjustments.validate()?;
+        }
+        Ok(Self::from_state(state))
+    }
+
+    /// Prepare `state` without validating its adjustments. Invalid channel
+    /// levels are skipped.
+    pub(crate) fn from_state(state: &EditState) -> Self {
+        let levels = &state.channel_levels;
+        let channel_levels =
+            (!levels.is_identity() && levels.validate().is_ok()).then(|| levels.luts());
+        let base_curve = state
+            .base_curve
+            .map(|base| base.curve())
+            .filter(|curve| !curve.is_linear())
+            .map(|curve| ToneCurveLut::from_curve(&curve));
+        let tone_curve =
+            (!state.tone_curve.is_linear()).then(|| ToneCurveLut::from_curve(&state.tone_curve));
+        let (linear_masks, radial_masks) = mask_layers(state);
+        Self {
+            state: state.clone(),
+            toggles: AdjustmentToggles::ALL,
+            channel_levels,
+            base_curve,
+            tone_curve,
+            linear_masks,
+            radial_masks,
+        }
+    }
+
+    /// The edit state this was prepared from.
+    pub fn state(&self) -> &EditState {
+        &self.state
+    }
+
+    /// Skip the stages `toggles` switches off; the state is unchanged.
+    pub fn with_toggles(mut self, toggles: AdjustmentToggles) -> Self {
+        self.toggles = toggles;
+        self
+    }
+
+    /// The stages renders run.
+    pub fn toggles(&self) -> AdjustmentToggles {
+        self.toggles
+    }
+
+    /// Size of the image [`render`](Self::render) produces for a
+    /// `width x height` source.
+    pub fn rendered_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.toggles.transform {
+            rendered_size(width, height, &self.state)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Render the whole image; identical to [`render_edit`] with the
+    /// original state.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransformError::InvalidImage` if the image is empty or its
+    /// buffer does not match its dimensions.
+    pub fn render(
+        &self,
+        image: &DecodedImage,
+        filter: InterpolationFilter,
+    ) -> Result<DecodedImage, TransformError> {
+        self.run_stages(image, RenderStage::Transform..=RenderStage::Masks, filter)
+    }
+
+    /// Run `stage` and every later stage on `cached`, the image entering
+    /// `stage`; see [`render_from_stage`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransformError::InvalidImage` if the image is empty or its
+    /// buffer does not match its dimensions.
+    pub fn render_from_stage(
+        &self,
+        cached: &DecodedImage,
+        stage: RenderStage,
+        filter: InterpolationFilter,
+    ) -> Result<DecodedImage, TransformError> {
+        self.run_stages(cached, stage..=RenderStage::Masks, filter)
+    }
+
+    /// Run the stages before `stage`; see [`render_to_stage`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransformError::InvalidImage` if the image is empty or its
+    /// buffer does not match its dimensions.
+    pub fn render_to_stage(
+        &self,
+        image: &DecodedImage,
+        stage: RenderStage,
+        filter: InterpolationFilter,
+    ) -> Result<DecodedImage, TransformError> {
+        self.run_stages(image, RenderStage::Transform..stage, filter)
+    }
+
+    /// Run the stages in `stages` on a copy of `image`.
+    fn run_stages(
+        &self,
+        image: &DecodedImage,
+        stages: impl RangeBounds<RenderStage>,
+        filter: InterpolationFilter,
+    ) -> Result<DecodedImage, TransformError> {
+        let mut output = if stages.contains(&RenderStage::Transform) && self.toggles.transform {
+            rotate_and_crop(image, &self.state, filter)?
+        } else {
+            image.validate()?;
+            image.clone()
+        };
+        if stages.contains(&RenderStage::Adjustments) {
+            self.develop_color(&mut output.pixels);
+        }
+        if stages.contains(&RenderStage::Effects) {
+            self.apply_effects(&mut output.pixels);
+        }
+        if stages.contains(&RenderStage::Curve) {
+            self.apply_tone_curve(&mut output.pixels);
+        }
+        if stages.contains(&RenderStage::Masks) {
+            self.apply_masks(&mut output)?;
+        }
+        Ok(output)
+    }
+
+    /// Apply the base curve, global adjustments, B&W conversion and tone
+    /// curve.
+    pub(crate) fn develop(&self, pixels: &mut [u8]) {
+        self.develop_color(pixels);
+        self.apply_effects(pixels);
+        self.apply_tone_curve(pixels);
+    }
+
+    /// Apply the channel levels, base curve and global adjustments.
+    fn develop_color(&self, pixels: &mut [u8]) {
+        if let Some(luts) = self.channel_levels.as_ref().filter(|_| self.toggles.color) {
+            apply_channel_luts(pixels, luts);
+        }
+        if let Some(lut) = self.base_curve.as_ref().filter(|_| self.toggles.curve) {
+            apply_tone_curve(pixels, lut);
+        }
+        let adjustments = self.toggles.filter_adjustments(&self.state.adjustments);
+        apply_all_adjustments(pixels, &adjustments);
+    }
+
+    /// Apply the B&W conversion, if enabled.
+    fn apply_effects(&self, pixels: &mut [u8]) {
+        if self.state.monochrome && self.toggles.effects {
+            apply_monochrome(pixels, &self.state.bw_mix.unwrap_or_default());
+        }
+    }
+
+    /// Apply the global tone curve.
+    fn apply_tone_curve(&self, pixels: &mut [u8]) {
+        if let Some(lut) = self.tone_curve.as_ref().filter(|_| self.toggles.curve) {
+            apply_tone_curve(pixels, lut);
+        }
+    }
+
+    /// Apply the enabled masks to a rendered image.
+    fn apply_masks(&self, output: &mut DecodedImage) -> Result<(), TransformError> {
+        if !self.toggles.masks {
+            return Ok(());
+        }
+        apply_masked_adjustments_with_curves(
+            &mut output.pixels,
+            output.width,
+            output.height,
+            &self.linear_masks,
+            &self.radial_masks,
+        )?;
+        Ok(())
+    }
+}
+
+/// Render the color and black and white versions side by side.
+///
+/// Columns left of `split` (a fraction of the width, clamped to 0-1) match
+/// [`render_edit`] with `monochrome` off, and the rest match it with
+/// `monochrome` on, whatever `state.monochrome` is. Rotation, crop and the
+/// adjustments run once for both halves; only the B&W conversion is
+/// limited to the right side.
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+pub fn render_mono_split(
+    image: &DecodedImage,
+    state: &EditState,
+    split: f32,
+    filter: InterpolationFilter,
+) -> Result<DecodedImage, TransformError> {
+    let prepared = PreparedEdit::from_state(state);
+    let mut output = rotate_and_crop(image, state, filter)?;
+    prepared.develop_color(&mut output.pixels);
+
+    let split_x = (split.clamp(0.0, 1.0) * output.width as f32).round() as usize;
+    let mix = state.bw_mix.unwrap_or_default();
+    for row in output.rows_mut() {
+        apply_monochrome(&mut row[split_x * 3..], &mix);
+    }
+
+    prepared.apply_tone_curve(&mut output.pixels);
+    prepared.apply_masks(&mut output)?;
+    Ok(output)
+}
+
+/// Smallest thumbnail, in pixels on the long edge, that
+/// [`render_thumbnail_with_edits`] applies masks to.
+///
+/// Below this a gradient mask covers only a few pixels and is hard to see,
+/// so it is skipped.
+pub const THUMBNAIL_MASK_MIN_SIZE: u32 = 64;
+
+/// Render a thumbnail of the edited image, fitting within `size x size`.
+///
+/// Same as [`render_thumbnail_with_mask_threshold`] with
+/// [`THUMBNAIL_MASK_MIN_SIZE`].
+///
+/// # Errors
+///
+/// See [`render_thumbnail_with_mask_threshold`].
+pub fn render_thumbnail_with_edits(
+    image: &DecodedImage,
+    state: &EditState,
+    size: u32,
+) -> Result<DecodedImage, TransformError> {
+    render_thumbnail_with_mask_threshold(image, state, size, THUMBNAIL_MASK_MIN_SIZE)
+}
+
+/// Render a thumbnail of the edited image, applying masks only when the
+/// thumbnail is at least `mask_min_size` pixels on its long edge.
+///
+/// The source is first shrunk (bilinear, like
+/// [`generate_thumbnail`](crate::decode::generate_thumbnail)) by the factor
+/// that brings the rendered image down to `size`, then rotated, cropped and
+/// developed at that size, with masks evaluated at thumbnail resolution.
+/// Resampling before the edits rather than after makes the result differ
+/// slightly from shrinking a full [`render_edit`]: tone changes are applied
+/// to averaged pixels, and rotation samples a smaller image. The difference
+/// is a few levels on smooth images and larger only along fine detail.
+///
+/// With no edits the result is identical to `generate_thumbnail`.
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidDimensions` if `size` is zero, or
+/// `TransformError::InvalidImage` if the image is empty or its buffer does
+/// not match its dimensions.
+pub fn render_thumbnail_with_mask_threshold(
+    image: &DecodedImage,
+    state: &EditState,
+    size: u32,
+    mask_min_size: u32,
+) -> Result<DecodedImage, TransformError> {
+    if size == 0 {
+        return Err(TransformError::InvalidDimensions {
+            width: size,
+            height: size,
+        });
+    }
+    image.validate()?;
+
+    let _perf = perf::scope("render_thumbnail_with_edits");
+    perf::record_dimensions(image.width, image.height);
+
+    // Long edge of the source that makes the rendered long edge `size`
+    let (rendered_w, rendered_h) = rendered_size(image.width, image.height, state);
+    let scale = size as f64 / rendered_w.max(rendered_h) as f64;
+    let source_edge = (image.width.max(image.height) as f64 * scale)
+        .round()
+        .max(1.0) as u32;
+    let source =
+        resize_to_fit(image, source_edge, FilterType::Bilinear).map_err(thumbnail_resize_error)?;
+
+    let mut output = rotate_and_crop(&source, state, InterpolationFilter::Bilinear)?;
+    // Rounding in the crop can leave the result a pixel over
+    if output.width > size || output.height > size {
+        output =
+            resize_to_fit(&output, size, FilterType::Bilinear).map_err(thumbnail_resize_error)?;
+    }
+
+    let prepared = PreparedEdit::from_state(state);
+    prepared.develop(&mut output.pixels);
+    if size >= mask_min_size {
+        prepared.apply_masks(&mut output)?;
+    }
+    Ok(output)
+}
+
+/// Resizing only fails on an invalid image once the size is known to be
+/// non-zero.
+fn thumbnail_resize_error(error: DecodeError) -> TransformError {
+    match error {
+        DecodeError::InvalidImage(e) => TransformError::InvalidImage(e),
+        _ => TransformError::InvalidDimensions {
+            width: 0,
+            height: 0,
+        },
+    }
+}
+
+/// Rotate and crop the whole image as `state` asks.
+fn rotate_and_crop(
+    image: &DecodedImage,
+    state: &EditState,
+    filter: InterpolationFilter,
+) -> Result<DecodedImage, TransformError> {
+    let rotated = apply_rotation(image, total_angle(state), filter)?;
+    match state.crop {
+        Some(c) => apply_crop(
+            &rotated,
+            c.left as f64,
+            c.top as f64,
+            c.width as f64,
+            c.height as f64,
+        ),
+        None => Ok(rotated),
+    }
+}
+
+/// Render one region of the edited image, scaled to `out_width x out_height`.
+///
+/// Equivalent to cropping `region` out of [`render_edit`]'s result and
+/// scaling it, but only the output pixels are rendered. Each output pixel
+/// center is mapped through the crop and the inverse rotation and sampled
+/// from the source with `filter`; without rotation the nearest source pixel
+/// is taken. A 1:1 region on whole pixels therefore matches the full render
+/// exactly, and zooming in enlarges pixels without smoothing them.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `state` - Edits to apply
+/// * `region` - Part of the rendered image to draw, in its pixels (see
+///   [`rendered_size`]); may extend past its edges
+/// * `out_width` - Output width in pixels
+/// * `out_height` - Output height in pixels
+/// * `filter` - Rotation filter; use the same one as the full render
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidDimensions` if the output size is zero,
+/// `TransformError::InvalidRegion` if the region has a non-positive or
+/// non-finite size, or `TransformError::InvalidImage` if the source is empty
+/// or its buffer does not match its dimensions, or if the output exceeds the
+/// image size limits.
+pub fn render_region(
+    image: &DecodedImage,
+    state: &EditState,
+    region: &RenderRegion,
+    out_width: u32,
+    out_height: u32,
+    filter: InterpolationFilter,
+) -> Result<DecodedImage, TransformError> {
+    if out_width == 0 || out_height == 0 {
+        return Err(TransformError::InvalidDimensions {
+            width: out_width,
+            height: out_height,
+        });
+    }
+    let valid_size = |v: f64| v.is_finite() && v > 0.0;
+    if !valid_size(region.width)
+        || !valid_size(region.height)
+        || !region.x.is_finite()
+        || !region.y.is_finite()
+    {
+        return Err(TransformError::InvalidRegion);
+    }
+    image.validate()?;
+    let out_len = checked_buffer_len(out_width, out_height, 3)?;
+
+    let _perf = perf::scope("render_region");
+    perf::record_dimensions(image.width, image.height);
+    let output_pixels = out_width as u64 * out_height as u64;
+    perf::record_pixels(output_pixels);
+    perf::record_allocation(output_pixels * 3);
+
+    let frame = Frame::new(image.width, image.height, state);
+    let step_x = region.width / out_width as f64;
+    let step_y = region.height / out_height as f64;
+    // Center of output pixel `i`, in rendered-image coordinates
+    let center_x = |i: u32| region.x + (i as f64 + 0.5) * step_x;
+    let center_y = |i: u32| region.y + (i as f64 + 0.5) * step_y;
+
+    let mut pixels = vec![0u8; out_len];
+    for (oy, row) in pixels.chunks_exact_mut(out_width as usize * 3).enumerate() {
+        // Canvas pixel index, as used by the full rotation
+        let canvas_y = frame.top as f64 + (center_y(oy as u32) - 0.5);
+        for (ox, dst) in row.chunks_exact_mut(3).enumerate() {
+            let canvas_x = frame.left as f64 + (center_x(ox as u32) - 0.5);
+            let pixel = match &frame.rotation {
+                Some(rotation) => {
+                    let (src_x, src_y) = rotation.source_point(canvas_x, canvas_y);
+                    sample(image, src_x, src_y, filter)
+                }
+                None => nearest(image, canvas_x, canvas_y),
+            };
+            dst.copy_from_slice(&pixel);
+        }
+    }
+
+    let prepared = PreparedEdit::from_state(state);
+    prepared.develop(&mut pixels);
+    let (frame_w, frame_h) = (frame.width as f32, frame.height as f32);
+    apply_masked_adjustments_mapped(
+        &mut pixels,
+        out_width,
+        out_height,
+        &prepared.linear_masks,
+        &prepared.radial_masks,
+        |px, py| (center_x(px) as f32 / frame_w, center_y(py) as f32 / frame_h),
+    )?;
+
+    Ok(DecodedImage::new(out_width, out_height, pixels))
+}
+
+/// Source pixel covering canvas pixel index `(x, y)`, clamped to the image.
+#[inline]
+fn nearest(image: &DecodedImage, x: f64, y: f64) -> [u8; 3] {
+    let px = ((x + 0.5).floor().max(0.0) as u32).min(image.width - 1);
+    let py = ((y + 0.5).floor().max(0.0) as u32).min(image.height - 1);
+    let idx = (py as usize * image.width as usize + px as usize) * 3;
+    [
+        image.pixels[idx],
+        image.pixels[idx + 1],
+        image.pixels[idx + 2],
+    ]
+}
+
+/// Masks with the adjustments and optional curve each applies.
+type MaskLayers<M> = Vec<(M, BasicAdjustments, Option<ToneCurveLut>)>;
+
+/// Mask layers for the enabled masks of `state`.
+fn mask_layers(
+    state: &EditState,
+) -> (
+    MaskLayers<LinearGradientMask>,
+    MaskLayers<RadialGradientMask>,
+) {
+    let curve = |c: &Option<ToneCurve>| c.as_ref().map(ToneCurveLut::from_curve);
+    // Masks saturate in the same color model as the global adjustments
+    let adjustments = |a: &BasicAdjustments| BasicAdjustments {
+        color_model: state.adjustments.color_model,
+        ..a.clone()
+    };
+    let linear = state
+        .linear_masks
+        .iter()
+        .filter(|m| m.enabled)
+        .map(|m| {
+            (
+                m.mask.clone(),
+                adjustments(&m.adjustments),
+                curve(&m.tone_curve),
+            )
+        })
+        .collect();
+    let radial = state
+        .radial_masks
+        .iter()
+        .filter(|m| m.enabled)
+        .map(|m| {
+            (
+                m.mask.clone(),
+                adjustments(&m.adjustments),
+                curve(&m.tone_curve),
+            )
+        })
+        .collect();
+    (linear, radial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adjustments::{apply_channel_levels, ChannelLevels};
+    use crate::base_curve::BaseCurve;
+    use crate::color::ColorModel;
+    use crate::edit::{
+        compare_edit_states_stages, CropRect, LinearMaskEdit, RadialMaskEdit, Rotation,
+    };
+    use crate::monochrome::BwMix;
+    use crate::testing::{gradient, noise, GradientDirection};
+    use crate::CurvePoint;
+
+    fn edited_state() -> EditState {
+        EditState {
+            channel_levels: ChannelLevels {
+                black: [0.02, 0.0, 0.04],
+                white: [1.0, 0.97, 1.0],
+            },
+            base_curve: Some(BaseCurve::CameraStandardApprox),
+            adjustments: BasicAdjustments {
+                exposure: 0.4,
+                contrast: 25.0,
+                temperature: -20.0,
+                saturation: 15.0,
+                ..Default::default()
+            },
+            monochrome: false,
+            bw_mix: None,
+            noise_reduction: None,
+            auto_nr: false,
+            tone_curve: ToneCurve {
+                points: vec![
+                    CurvePoint::new(0.0, 0.0),
+                    CurvePoint::new(0.3, 0.25),
+                    CurvePoint::new(0.7, 0.8),
+                    CurvePoint::new(1.0, 1.0),
+                ],
+            },
+            lut: None,
+            crop: Some(CropRect {
+                left: 0.1,
+                top: 0.05,
+                width: 0.8,
+                height: 0.85,
+            }),
+            rotation: Rotation {
+                angle: 0.0,
+                straighten: 7.5,
+            },
+            linear_masks: vec![LinearMaskEdit {
+                id: "sky".to_string(),
+                mask: LinearGradientMask::new(0.5, 0.0, 0.5, 0.6, 0.7),
+                enabled: true,
+                adjustments: BasicAdjustments {
+                    exposure: -0.8,
+                    ..Default::default()
+                },
+                tone_curve: None,
+            }],
+            radial_masks: vec![RadialMaskEdit {
+                id: "face".to_string(),
+                mask: RadialGradientMask::circle(0.6, 0.55, 0.25, 0.5),
+                enabled: true,
+                adjustments: BasicAdjustments {
+                    shadows: 40.0,
+                    ..Default::default()
+                },
+                tone_curve: None,
+            }],
+        }
+    }
+
+    /// Render `state` as a 4x4 grid of 1:1 tiles and stitch them together.
+    fn render_grid(
+        image: &DecodedImage,
+        state: &EditState,
+        filter: InterpolationFilter,
+    ) -> DecodedImage {
+        let (width, height) = rendered_size(image.width, image.height, state);
+        let tile_w = width.div_ceil(4);
+        let tile_h = height.div_ceil(4);
+
+        let mut stitched = vec![0u8; (width * height * 3) as usize];
+        for ty in 0..4 {
+            for tx in 0..4 {
+                let (x0, y0) = (tx * tile_w, ty * tile_h);
+                let (w, h) = (tile_w.min(width - x0), tile_h.min(height - y0));
+                let region = RenderRegion {
+                    x: x0 as f64,
+                    y: y0 as f64,
+                    width: w as f64,
+                    height: h as f64,
+                };
+                let tile = render_region(image, state, &region, w, h, filter).unwrap();
+                for (row, src) in tile.pixels.chunks_exact(w as usize * 3).enumerate() {
+                    let start = (((y0 + row as u32) * width + x0) * 3) as usize;
+                    stitched[start..start + src.len()].copy_from_slice(src);
+                }
+            }
+        }
+        DecodedImage::new(width, height, stitched)
+    }
+
+    #[test]
+    fn test_tiles_stitch_into_full_render() {
+        let image = noise(61, 47, 3);
+        for filter in [InterpolationFilter::Bilinear, InterpolationFilter::Lanczos3] {
+            let state = edited_state();
+            let full = render_edit(&image, &state, filter).unwrap();
+            let stitched = render_grid(&image, &state, filter);
+
+            assert_eq!((stitched.width, stitched.height), (full.width, full.height));
+            assert_eq!(stitched.pixels, full.pixels, "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn test_tiles_stitch_without_rotation_or_crop() {
+        let image = noise(50, 30, 9);
+        let mut state = edited_state();
+        state.rotation = Rotation::default();
+        state.crop = None;
+
+        let full = render_edit(&image, &state, InterpolationFilter::Bilinear).unwrap();
+        let stitched = render_grid(&image, &state, InterpolationFilter::Bilinear);
+        assert_eq!(stitched.pixels, full.pixels);
+    }
+
+    #[test]
+    fn test_rendered_size_matches_full_render() {
+        let image = noise(61, 47, 3);
+        let state = edited_state();
+        let full = render_edit(&image, &state, InterpolationFilter::Bilinear).unwrap();
+        assert_eq!(
+            rendered_size(image.width, image.height, &state),
+            (full.width, full.height)
+        );
+    }
+
+    #[test]
+    fn test_zoomed_region_repeats_pixels() {
+        let image = noise(20, 20, 5);
+        let state = EditState::default();
+        let region = RenderRegion {
+            x: 4.0,
+            y: 6.0,
+            width: 5.0,
+            height: 5.0,
+        };
+        let zoomed = render_region(
+            &image,
+            &state,
+            &region,
+            10,
+            10,
+            InterpolationFilter::Bilinear,
+        )
+        .unwrap();
+
+        // At 200% each source pixel becomes a 2x2 block
+        let source = |x: u32, y: u32| &image.pixels[((y * 20 + x) * 3) as usize..][..3];
+        let output = |x: u32, y: u32| &zoomed.pixels[((y * 10 + x) * 3) as usize..][..3];
+        assert_eq!(output(0, 0), source(4, 6));
+        assert_eq!(output(1, 1), source(4, 6));
+        assert_eq!(output(9, 9), source(8, 10));
+    }
+
+    #[test]
+    fn test_base_curve_is_applied_before_adjustments() {
+        let image = noise(24, 16, 5);
+        let filter = InterpolationFilter::Bilinear;
+        let render = |base_curve| {
+            let state = EditState {
+                base_curve,
+                ..Default::default()
+            };
+            render_edit(&image, &state, filter).unwrap().pixels
+        };
+
+        let off = render(None);
+        assert_eq!(off, image.pixels);
+        assert_ne!(render(Some(BaseCurve::CameraStandardApprox)), off);
+        assert_eq!(render(Some(BaseCurve::Flat)), off);
+    }
+
+    /// Columns `[from, to)` of every row.
+    fn columns(image: &DecodedImage, from: u32, to: u32) -> Vec<u8> {
+        image
+            .pixels
+            .chunks_exact(image.width as usize * 3)
+            .flat_map(|row| &row[from as usize * 3..to as usize * 3])
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_mono_split_halves_match_separate_renders() {
+        let image = noise(61, 47, 3);
+        let filter = InterpolationFilter::Bilinear;
+        let mut state = edited_state();
+        state.bw_mix = Some(BwMix {
+            red: 0.6,
+            green: 0.3,
+            blue: 0.1,
+        });
+
+        let color = render_edit(&image, &state, filter).unwrap();
+        state.monochrome = true;
+        let mono = render_edit(&image, &state, filter).unwrap();
+        let split = render_mono_split(&image, &state, 0.4, filter).unwrap();
+
+        let (w, split_x) = (split.width, (0.4 * split.width as f32).round() as u32);
+        assert_eq!((w, split.height), (color.width, color.height));
+        assert_eq!(columns(&split, 0, split_x), columns(&color, 0, split_x));
+        assert_eq!(columns(&split, split_x, w), columns(&mono, split_x, w));
+        assert_ne!(columns(&split, 0, split_x), columns(&mono, 0, split_x));
+    }
+
+    #[test]
+    fn test_mono_split_equals_compositing_two_pipelines() {
+        let image = noise(40, 30, 11);
+        let filter = InterpolationFilter::Lanczos3;
+        let state = edited_state();
+        let mono_state = EditState {
+            monochrome: true,
+            ..state.clone()
+        };
+        let color = render_edit(&image, &state, filter).unwrap();
+        let mono = render_edit(&image, &mono_state, filter).unwrap();
+
+        for split in [0.0f32, 0.5, 1.0, 2.0] {
+            let split_x = (split.clamp(0.0, 1.0) * color.width as f32).round() as usize;
+            let row_len = color.width as usize * 3;
+            let composite: Vec<u8> = color
+                .pixels
+                .chunks_exact(row_len)
+                .zip(mono.pixels.chunks_exact(row_len))
+                .flat_map(|(c, m)| [&c[..split_x * 3], &m[split_x * 3..]].concat())
+                .collect();
+            // The state's own monochrome flag does not matter
+            for s in [&state, &mono_state] {
+                let rendered = render_mono_split(&image, s, split, filter).unwrap();
+                assert_eq!(rendered.pixels, composite, "split {}", split);
+            }
+        }
+    }
+
+    #[test]
+    fn test_monochrome_off_restores_color_render() {
+        let image = noise(24, 16, 5);
+        let filter = InterpolationFilter::Bilinear;
+        let state = edited_state();
+        let color = render_edit(&image, &state, filter).unwrap();
+
+        let mut toggled = state.clone();
+        toggled.monochrome = true;
+        toggled.bw_mix = Some(BwMix {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+        });
+        assert_ne!(
+            render_edit(&image, &toggled, filter).unwrap().pixels,
+            color.pixels
+        );
+
+        toggled.monochrome = false;
+        assert_eq!(
+            render_edit(&image, &toggled, filter).unwrap().pixels,
+            color.pixels
+        );
+    }
+
+    /// Mean absolute difference between two images of the same size
+    fn mean_difference(a: &DecodedImage, b: &DecodedImage) -> f64 {
+        assert_eq!((a.width, a.height), (b.width, b.height));
+        let total: u64 = a
+            .pixels
+            .iter()
+            .zip(&b.pixels)
+            .map(|(&x, &y)| x.abs_diff(y) as u64)
+            .sum();
+        total as f64 / a.pixels.len() as f64
+    }
+
+    #[test]
+    fn test_thumbnail_matches_downsized_full_render() {
+        let image = gradient(640, 480, GradientDirection::Diagonal);
+        let state = edited_state();
+
+        let thumbnail = render_thumbnail_with_edits(&image, &state, 128).unwrap();
+
+        let full = render_edit(&image, &state, InterpolationFilter::Bilinear).unwrap();
+        let expected = resize_to_fit(&full, 128, FilterType::Bilinear).unwrap();
+        assert!(thumbnail.width.max(thumbnail.height) <= 128);
+        assert!(
+            mean_difference(&thumbnail, &expected) < 3.0,
+            "mean difference {}",
+            mean_difference(&thumbnail, &expected)
+        );
+    }
+
+    #[test]
+    fn test_identity_thumbnail_matches_generate_thumbnail() {
+        let image = noise(300, 200, 4);
+
+        let thumbnail = render_thumbnail_with_edits(&image, &EditState::default(), 64).unwrap();
+
+        let expected = crate::decode::generate_thumbnail(&image, 64).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (64, 43));
+        assert_eq!(thumbnail.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_thumbnail_mask_threshold_is_configurable() {
+        let image = gradient(200, 200, GradientDirection::Horizontal);
+        let mut state = edited_state();
+        state.crop = None;
+        state.rotation = Rotation::default();
+        let mut unmasked = state.clone();
+        unmasked.linear_masks.clear();
+        unmasked.radial_masks.clear();
+
+        let masked = render_thumbnail_with_mask_threshold(&image, &state, 48, 32).unwrap();
+        let skipped = render_thumbnail_with_mask_threshold(&image, &state, 48, 64).unwrap();
+        let plain = render_thumbnail_with_mask_threshold(&image, &unmasked, 48, 32).unwrap();
+
+        assert_eq!(skipped.pixels, plain.pixels);
+        assert_ne!(masked.pixels, plain.pixels);
+    }
+
+    #[test]
+    fn test_thumbnail_rejects_zero_size() {
+        let image = noise(8, 8, 1);
+        let result = render_thumbnail_with_edits(&image, &EditState::default(), 0);
+        assert!(matches!(
+            result,
+            Err(TransformError::InvalidDimensions { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_region_is_rejected() {
+        let image = noise(8, 8, 1);
+        let state = EditState::default();
+        let region = RenderRegion {
+            x: 0.0,
+            y: 0.0,
+            width: 4.0,
+            height: 4.0,
+        };
+        let filter = InterpolationFilter::Bilinear;
+
+        let result = render_region(&image, &state, &region, 0, 4, filter);
+        assert!(matches!(
+            result,
+            Err(TransformError::InvalidDimensions { .. })
+        ));
+
+        let empty = RenderRegion {
+            width: 0.0,
+            ..region
+        };
+        let result = render_region(&image, &state, &empty, 4, 4, filter);
+        assert!(matches!(result, Err(TransformError::InvalidRegion)));
+
+        let mismatched = DecodedImage {
+            width: 4,
+            height: 4,
+            pixels: vec![0u8; 10],
+        };
+        let result = render_region(&mismatched, &state, &region, 4, 4, filter);
+        assert!(matches!(result, Err(TransformError::InvalidImage(_))));
+    }
+
+    /// The pipeline spelled out with the standalone operations, building
+    /// every LUT and mask layer on the spot.
+    fn render_step_by_step(image: &DecodedImage, state: &EditState) -> DecodedImage {
+        let filter = InterpolationFilter::Lanczos3;
+        let rotated = apply_rotation(image, total_angle(state), filter).unwrap();
+        let c = state.crop.unwrap();
+        let mut output = apply_crop(
+            &rotated,
+            c.left as f64,
+            c.top as f64,
+            c.width as f64,
+            c.height as f64,
+        )
+        .unwrap();
+
+        apply_channel_levels(&mut output.pixels, &state.channel_levels).unwrap();
+        let base = state.base_curve.unwrap().curve();
+        apply_tone_curve(&mut output.pixels, &ToneCurveLut::from_curve(&base));
+        apply_all_adjustments(&mut output.pixels, &state.adjustments);
+        apply_tone_curve(
+            &mut output.pixels,
+            &ToneCurveLut::from_curve(&state.tone_curve),
+        );
+        let (linear, radial) = mask_layers(state);
+        apply_masked_adjustments_with_curves(
+            &mut output.pixels,
+            output.width,
+            output.height,
+            &linear,
+            &radial,
+        )
+        .unwrap();
+        output
+    }
+
+    #[test]
+    fn test_prepared_edit_matches_step_by_step_pipeline() {
+        let image = noise(57, 43, 11);
+        let state = edited_state();
+        let prepared = PreparedEdit::new(&state).unwrap();
+        let rendered = prepared
+            .render(&image, InterpolationFilter::Lanczos3)
+            .unwrap();
+        let expected = render_step_by_step(&image, &state);
+
+        assert_eq!(
+            (rendered.width, rendered.height),
+            (expected.width, expected.height)
+        );
+        assert_eq!(rendered.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_prepared_edit_applies_to_any_size() {
+        let state = edited_state();
+        let prepared = PreparedEdit::new(&state).unwrap();
+        for (width, height) in [(40, 30), (23, 61), (96, 96)] {
+            let image = gradient(width, height, GradientDirection::Horizontal);
+            let rendered = prepared
+                .render(&image, InterpolationFilter::Bilinear)
+                .unwrap();
+            let direct = render_edit(&image, &state, InterpolationFilter::Bilinear).unwrap();
+            assert_eq!(
+                (rendered.width, rendered.height),
+                rendered_size(width, height, &state)
+            );
+            assert_eq!(rendered.pixels, direct.pixels, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn test_prepared_edit_owns_its_state() {
+        let image = noise(32, 24, 4);
+        let mut state = edited_state();
+        let prepared = PreparedEdit::new(&state).unwrap();
+        let before = prepared
+            .render(&image, InterpolationFilter::Bilinear)
+            .unwrap();
+
+        state.adjustments.exposure = -3.0;
+        state.tone_curve = ToneCurve::default();
+        state.linear_masks.clear();
+        let after = prepared
+            .render(&image, InterpolationFilter::Bilinear)
+            .unwrap();
+        assert_eq!(after.pixels, before.pixels);
+        assert_eq!(prepared.state(), &edited_state());
+    }
+
+    #[test]
+    fn test_masks_follow_global_color_model() {
+        let image = noise(32, 24, 6);
+        let mut state = edited_state();
+        state.adjustments.color_model = ColorModel::Oklab;
+        state.linear_masks[0].adjustments.saturation = 50.0;
+        let render = |state: &EditState| {
+            render_edit(&image, state, InterpolationFilter::Bilinear)
+                .unwrap()
+                .pixels
+        };
+        let global = render(&state);
+
+        state.linear_masks[0].adjustments.color_model = ColorModel::Oklab;
+        assert_eq!(render(&state), global);
+    }
+
+    #[test]
+    fn test_prepared_edit_validates_adjustments() {
+        let mut state = edited_state();
+        state.radial_masks[0].adjustments.shadows = f32::NAN;
+        assert!(matches!(
+            PreparedEdit::new(&state),
+            Err(AdjustmentError::NonFinite { .. })
+        ));
+
+        // Disabled masks are not rendered, so they are not checked
+        state.radial_masks[0].enabled = false;
+        assert!(PreparedEdit::new(&state).is_ok());
+
+        state.adjustments.exposure = 99.0;
+        assert!(matches!(
+            PreparedEdit::new(&state),
+            Err(AdjustmentError::OutOfRange { .. })
+        ));
+
+        let mut state = edited_state();
+        state.channel_levels.black[1] = 0.97;
+        assert!(matches!(
+            PreparedEdit::new(&state),
+            Err(AdjustmentError::InvertedLevels { .. })
+        ));
+    }
+
+    #[test]
+    fn test_toggled_off_stages_match_neutral_edits() {
+        let image = noise(32, 24, 8);
+        let filter = InterpolationFilter::Bilinear;
+        let state = edited_state();
+        let render_toggled = |toggles: AdjustmentToggles| {
+            render_edit_with_toggles(&image, &state, toggles, filter)
+                .unwrap()
+                .pixels
+        };
+        let render = |state: &EditState| render_edit(&image, state, filter).unwrap().pixels;
+
+        let mut no_tone = state.clone();
+        no_tone.adjustments.exposure = 0.0;
+        no_tone.adjustments.contrast = 0.0;
+        let tone_off = AdjustmentToggles {
+            tone: false,
+            ..AdjustmentToggles::ALL
+        };
+        assert_eq!(render_toggled(tone_off), render(&no_tone));
+
+        let mut no_color = state.clone();
+        no_color.adjustments.temperature = 0.0;
+        no_color.adjustments.saturation = 0.0;
+        no_color.channel_levels = ChannelLevels::default();
+        let color_off = AdjustmentToggles {
+            color: false,
+            ..AdjustmentToggles::ALL
+        };
+        assert_eq!(render_toggled(color_off), render(&no_color));
+
+        // Curves are skipped rather than flattened: an identity curve
+        // renders the same as no curve
+        let mut no_curve = state.clone();
+        no_curve.tone_curve = ToneCurve::default();
+        no_curve.base_curve = None;
+        let curve_off = AdjustmentToggles {
+            curve: false,
+            ..AdjustmentToggles::ALL
+        };
+        assert_eq!(render_toggled(curve_off), render(&no_curve));
+
+        let mut no_masks = state.clone();
+        no_masks.linear_masks.clear();
+        no_masks.radial_masks.clear();
+        let masks_off = AdjustmentToggles {
+            masks: false,
+            ..AdjustmentToggles::ALL
+        };
+        assert_eq!(render_toggled(masks_off), render(&no_masks));
+
+        let mut no_transform = state.clone();
+        no_transform.rotation = Rotation::default();
+        no_transform.crop = None;
+        let transform_off = AdjustmentToggles {
+            transform: false,
+            ..AdjustmentToggles::ALL
+        };
+        let prepared = PreparedEdit::new(&state)
+            .unwrap()
+            .with_toggles(transform_off);
+        assert_eq!(prepared.rendered_size(32, 24), (32, 24));
+        assert_eq!(render_toggled(transform_off), render(&no_transform));
+    }
+
+    #[test]
+    fn test_re_enabling_a_stage_restores_the_render() {
+        let image = noise(32, 24, 9);
+        let filter = InterpolationFilter::Bilinear;
+        let mut state = edited_state();
+        state.monochrome = true;
+        let full = render_edit(&image, &state, filter).unwrap().pixels;
+
+        let mut toggles = AdjustmentToggles::ALL;
+        for off in [
+            |t: &mut AdjustmentToggles| t.tone = false,
+            |t: &mut AdjustmentToggles| t.color = false,
+            |t: &mut AdjustmentToggles| t.curve = false,
+            |t: &mut AdjustmentToggles| t.masks = false,
+            |t: &mut AdjustmentToggles| t.effects = false,
+            |t: &mut AdjustmentToggles| t.transform = false,
+        ] {
+            off(&mut toggles);
+            let toggled = render_edit_with_toggles(&image, &state, toggles, filter).unwrap();
+            assert_ne!(toggled.pixels, full, "{:?}", toggles);
+        }
+
+        // Masks switched off as a stack keep their own flags
+        let prepared = PreparedEdit::new(&state).unwrap().with_toggles(toggles);
+        assert!(prepared.state().linear_masks[0].enabled);
+        let prepared = prepared.with_toggles(AdjustmentToggles::ALL);
+        assert_eq!(prepared.render(&image, filter).unwrap().pixels, full);
+    }
+
+    #[test]
+    fn test_render_from_cached_pre_curve_matches_full_render() {
+        let image = noise(41, 33, 12);
+        let filter = InterpolationFilter::Bilinear;
+        let mut prev = edited_state();
+        prev.monochrome = true;
+        let cached = render_to_stage(&image, RenderStage::Curve, &prev, filter).unwrap();
+
+        let mut next = prev.clone();
+        next.tone_curve.points[1] = CurvePoint::new(0.3, 0.4);
+        let stage = compare_edit_states_stages(&prev, &next).first_dirty();
+        assert_eq!(stage, Some(RenderStage::Curve));
+
+        let resumed = render_from_stage(&cached, RenderStage::Curve, &next, filter).unwrap();
+        let full = render_edit(&image, &next, filter).unwrap();
+        assert_eq!((resumed.width, resumed.height), (full.width, full.height));
+        assert_eq!(resumed.pixels, full.pixels);
+    }
+
+    #[test]
+    fn test_every_stage_boundary_resumes_exactly() {
+        let image = noise(37, 29, 13);
+        let filter = InterpolationFilter::Bilinear;
+        let mut state = edited_state();
+        state.monochrome = true;
+        let full = render_edit(&image, &state, filter).unwrap().pixels;
+
+        for stage in RenderStage::ALL {
+            let cached = render_to_stage(&image, stage, &state, filter).unwrap();
+            let resumed = render_from_stage(&cached, stage, &state, filter).unwrap();
+            assert_eq!(resumed.pixels, full, "{:?}", stage);
+        }
+        let source = render_to_stage(&image, RenderStage::Transform, &state, filter).unwrap();
+        assert_eq!(source.pixels, image.pixels);
+    }
+
+    #[test]
+    fn test_stage_order_matches_pipeline() {
+        // Same order as the module's pipeline list
+        assert_eq!(
+            RenderStage::ALL.map(RenderStage::name),
+            ["transform", "adjustments", "effects", "curve", "masks"]
+        );
+        assert!(RenderStage::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+        for stage in RenderStage::ALL {
+            assert_eq!(RenderStage::from_name(stage.name()), Some(stage));
+            let json = serde_json::to_string(&stage).unwrap();
+            assert_eq!(json, format!("\"{}\"", stage.name()));
+        }
+        assert_eq!(RenderStage::from_name("crop"), None);
+    }
+
+    #[test]
+    fn test_toggles_are_not_part_of_the_edit_state() {
+        let state = edited_state();
+        let toggles = AdjustmentToggles {
+            tone: false,
+            masks: false,
+            ..AdjustmentToggles::ALL
+        };
+        let prepared = PreparedEdit::new(&state).unwrap().with_toggles(toggles);
+        assert_eq!(prepared.toggles(), toggles);
+        assert_eq!(prepared.state(), &state);
+
+        let json = serde_json::to_string(prepared.state()).unwrap();
+        assert!(!json.contains("toggles"));
+        assert_eq!(json, serde_json::to_string(&state).unwrap());
+
+        let partial: AdjustmentToggles = serde_json::from_str(r#"{"curve":false}"#).unwrap();
+        assert_eq!(
+            partial,
+            AdjustmentToggles {
+                curve: false,
+                ..AdjustmentToggles::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Deterministic test images for fixtures and golden tests.
+//!
+//! Available to this crate's tests and, behind the `test-util` feature, to
+//! downstream crates' tests. It is not part of the default build.
+//!
+//! Every generator is a pure function of its arguments, including [`noise`],
+//! which uses its own seeded generator rather than a platform RNG. Outputs are
+//! identical across runs and targets, so [`pixel_hash`] of an operation's
+//! result can be pinned as a golden value.
+
+use crate::decode::{canonical_resize, DecodedImage};
+
+/// Direction in which a [`gradient`] ramps from black to white.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientDirection {
+    /// Black on the left edge, white on the right
+    Horizontal,
+    /// Black on the top edge, white on the bottom
+    Vertical,
+    /// Black in the top-left corner, white in the bottom-right
+    Diagonal,
+}
+
+/// Gray ramp from 0 to 255 along `direction`.
+///
+/// The first and last pixel along the ramp are exactly 0 and 255, so a
+/// 256-pixel horizontal gradient holds every value once.
+pub fn gradient(width: u32, height: u32, direction: GradientDirection) -> DecodedImage {
+    let steps = match direction {
+        GradientDirection::Horizontal => width,
+        GradientDirection::Vertical => height,
+        GradientDirection::Diagonal => (width + height).saturating_sub(1),
+    }
+    .saturating_sub(1)
+    .max(1);
+
+    from_fn(width, height, |x, y| {
+        let t = match direction {
+            GradientDirection::Horizontal => x,
+            GradientDirection::Vertical => y,
+            GradientDirection::Diagonal => x + y,
+        };
+        let v = ((t as u64 * 255 + steps as u64 / 2) / steps as u64) as u8;
+        [v, v, v]
+    })
+}
+
+/// Black and white squares of `cell` pixels, black in the top-left corner.
+///
+/// A `cell` of zero is treated as one.
+pub fn checkerboard(width: u32, height: u32, cell: u32) -> DecodedImage {
+    let cell = cell.max(1);
+    from_fn(width, height, |x, y| {
+        if (x / cell + y / cell).is_multiple_of(2) {
+            [0, 0, 0]
+        } else {
+            [255, 255, 255]
+        }
+    })
+}
+
+/// The eight full-intensity color bars, left to right.
+pub const COLOR_BARS: [[u8; 3]; 8] = [
+    [255, 255, 255], // White
+    [255, 255, 0],   // Yellow
+    [0, 255, 255],   // Cyan
+    [0, 255, 0],     // Green
+    [255, 0, 255],   // Magenta
+    [255, 0, 0],     // Red
+    [0, 0, 255],     // Blue
+    [0, 0, 0],       // Black
+];
+
+/// Vertical [`COLOR_BARS`] of equal width spanning the image.
+pub fn color_bars(width: u32, height: u32) -> DecodedImage {
+    from_fn(width, height, |x, _| {
+        COLOR_BARS[(x as u64 * 8 / width as u64) as usize]
+    })
+}
+
+/// Uniform RGB noise from a seeded SplitMix64 generator.
+///
+/// The same seed always produces the same image; different seeds produce
+/// unrelated images.
+pub fn noise(width: u32, height: u32, seed: u64) -> DecodedImage {
+    let mut state = seed;
+    from_fn(width, height, |_, _| {
+        let [r, g, b, ..] = splitmix64(&mut state).to_le_bytes();
+        [r, g, b]
+    })
+}
+
+/// A dark/light edge through the image center, for sharpness measurements.
+///
+/// `angle` is the edge's tilt from vertical in degrees (the slanted-edge
+/// method uses about 5). The left side is 64 and the right side 192. Pixels
+/// the edge crosses are 4x4 supersampled, so the edge is anti-aliased.
+pub fn slanted_edge(width: u32, height: u32, angle: f32) -> DecodedImage {
+    const SAMPLES: u32 = 4;
+    const DARK: u32 = 64;
+    const LIGHT: u32 = 192;
+
+    let (sin, cos) = (angle as f64).to_radians().sin_cos();
+    let cx = width as f64 / 2.0;
+    let cy = height as f64 / 2.0;
+    // Signed distance from the edge line; positive on the light side
+    let side = |px: f64, py: f64| (px - cx) * cos + (py - cy) * sin;
+
+    from_fn(width, height, |x, y| {
+        let mut light = 0;
+        for sy in 0..SAMPLES {
+            for sx in 0..SAMPLES {
+                let px = x as f64 + (sx as f64 + 0.5) / SAMPLES as f64;
+                let py = y as f64 + (sy as f64 + 0.5) / SAMPLES as f64;
+                if side(px, py) > 0.0 {
+                    light += 1;
+                }
+            }
+        }
+        let total = SAMPLES * SAMPLES;
+        let v = ((DARK * (total - light) + LIGHT * light + total / 2) / total) as u8;
+        [v, v, v]
+    })
+}
+
+/// Per-channel difference between two downscales of the same image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConsistencyReport {
+    /// Mean absolute difference over all channels, in 8-bit levels
+    pub mean_error: f64,
+    /// Largest absolute difference of any channel
+    pub max_error: u8,
+}
+
+/// Compare two downscales of the same source made at different sizes, such
+/// as a grid thumbnail and an editor preview.
+///
+/// The larger image is downscaled to the smaller's exact size with the
+/// [`canonical_preview`](crate::decode::canonical_preview) resampler, then
+/// compared channel by channel. Images of the same size are compared
+/// directly.
+///
+/// # Panics
+///
+/// Panics if either image is invalid.
+pub fn verify_consistency(a: &DecodedImage, b: &DecodedImage) -> ConsistencyReport {
+    let (small, large) = if a.pixels.len() <= b.pixels.len() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let large = canonical_resize(large, small.width, small.height).expect("valid images");
+    let (sum, max_error) = small
+        .pixels
+        .iter()
+        .zip(&large.pixels)
+        .map(|(&x, &y)| x.abs_diff(y))
+        .fold((0u64, 0u8), |(sum, max), d| (sum + d as u64, max.max(d)));
+    ConsistencyReport {
+        mean_error: sum as f64 / small.pixels.len() as f64,
+        max_error,
+    }
+}
+
+/// FNV-1a hash of an image's dimensions and pixels, for golden tests.
+///
+/// Stable across platforms and releases: pin the value in a test and any
+/// numeric change to an operation's output fails it.
+pub fn pixel_hash(image: &DecodedImage) -> u64 {
+    let mut hash = fnv1a(FNV_OFFSET, &image.width.to_le_bytes());
+    hash = fnv1a(hash, &image.height.to_le_bytes());
+    fnv1a(hash, &image.pixels)
+}
+
+/// FNV-1a hash of raw bytes, for golden tests of non-image outputs.
+pub fn bytes_hash(bytes: &[u8]) -> u64 {
+    fnv1a(FNV_OFFSET, bytes)
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes
+        .iter()
+        .fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+/// Build an image from a per-pixel function, row by row.
+fn from_fn(width: u32, height: u32, mut f: impl FnMut(u32, u32) -> [u8; 3]) -> DecodedImage {
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+    for y in 0..height {
+        for x in 0..width {
+            pixels.extend_from_slice(&f(x, y));
+        }
+    }
+    DecodedImage::new(width, height, pixels)
+}
+
+/// One step of the SplitMix64 generator.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(image: &DecodedImage, x: u32, y: u32) -> [u8; 3] {
+        let i = (y * image.width + x) as usize * 3;
+        [image.pixels[i], image.pixels[i + 1], image.pixels[i + 2]]
+    }
+
+    #[test]
+    fn test_gradient_endpoints() {
+        let h = gradient(256, 2, GradientDirection::Horizontal);
+        assert!((0..256).all(|x| pixel(&h, x, 1) == [x as u8; 3]));
+
+        let v = gradient(3, 10, GradientDirection::Vertical);
+        assert_eq!(pixel(&v, 2, 0), [0; 3]);
+        assert_eq!(pixel(&v, 0, 9), [255; 3]);
+
+        let d = gradient(5, 7, GradientDirection::Diagonal);
+        assert_eq!(pixel(&d, 0, 0), [0; 3]);
+        assert_eq!(pixel(&d, 4, 6), [255; 3]);
+        assert_eq!(pixel(&d, 1, 0), pixel(&d, 0, 1));
+    }
+
+    #[test]
+    fn test_single_pixel_generators() {
+        for image in [
+            gradient(1, 1, GradientDirection::Diagonal),
+            checkerboard(1, 1, 0),
+            color_bars(1, 1),
+            noise(1, 1, 0),
+            slanted_edge(1, 1, 5.0),
+        ] {
+            assert!(image.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_checkerboard_cells() {
+        let board = checkerboard(8, 8, 2);
+        assert_eq!(pixel(&board, 1, 1), [0; 3]);
+        assert_eq!(pixel(&board, 2, 1), [255; 3]);
+        assert_eq!(pixel(&board, 2, 2), [0; 3]);
+    }
+
+    #[test]
+    fn test_color_bars_cover_width() {
+        let bars = color_bars(80, 2);
+        for (i, color) in COLOR_BARS.iter().enumerate() {
+            assert_eq!(pixel(&bars, i as u32 * 10, 0), *color);
+            assert_eq!(pixel(&bars, i as u32 * 10 + 9, 1), *color);
+        }
+    }
+
+    #[test]
+    fn test_noise_is_seeded() {
+        assert_eq!(noise(16, 16, 7).pixels, noise(16, 16, 7).pixels);
+        assert_ne!(noise(16, 16, 7).pixels, noise(16, 16, 8).pixels);
+
+        // Roughly uniform: mean near 127.5
+        let pixels = noise(64, 64, 1).pixels;
+        let mean = pixels.iter().map(|&v| v as f64).sum::<f64>() / pixels.len() as f64;
+        assert!((mean - 127.5).abs() < 3.0, "mean = {}", mean);
+    }
+
+    #[test]
+    fn test_slanted_edge_sides() {
+        let edge = slanted_edge(40, 40, 5.0);
+        assert_eq!(pixel(&edge, 0, 20), [64; 3]);
+        assert_eq!(pixel(&edge, 39, 20), [192; 3]);
+
+        // The tilt moves the crossing column between top and bottom rows
+        let crossing = |y| (0..40).find(|&x| pixel(&edge, x, y)[0] > 128).unwrap();
+        assert!(crossing(0) > crossing(39));
+
+        // Pixels on the edge are blended
+        assert!(edge.pixels.iter().any(|&v| v > 64 && v < 192));
+    }
+
+    #[test]
+    fn test_pixel_hash() {
+        let a = gradient(4, 4, GradientDirection::Horizontal);
+        assert_eq!(pixel_hash(&a), pixel_hash(&a.clone()));
+
+        // Same bytes with different dimensions hash differently
+        let b = DecodedImage::new(2, 8, a.pixels.clone());
+        assert_ne!(pixel_hash(&a), pixel_hash(&b));
+    }
+}
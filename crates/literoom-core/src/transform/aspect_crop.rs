@@ -0,0 +1,345 @@
+//! Aspect-locked crop dragging.
+//!
+//! When the crop tool has an aspect ratio locked, dragging one handle
+//! changes both dimensions. [`solve_constrained_crop`] applies the drag,
+//! keeps the handle's opposite edge or corner in place, and shrinks the
+//! result towards that anchor until it lies inside the rotated photo, so
+//! no fill from the expanded canvas can end up in the crop.
+//!
+//! Coordinates are normalized to the rotated canvas of
+//! [`compute_rotated_bounds`], as for the edit state's crop and
+//! [`compute_rotation_auto_crop`](super::compute_rotation_auto_crop).
+
+use serde::{Deserialize, Serialize};
+
+use super::{compute_rotated_bounds, rotation_forward_map};
+use crate::edit::CropRect;
+
+/// Smallest crop edge in canvas pixels.
+const MIN_CROP_PIXELS: f64 = 1.0;
+
+/// The crop handle being dragged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CropHandle {
+    /// Left edge; the right edge stays, centered vertically
+    Left,
+    /// Top edge; the bottom edge stays, centered horizontally
+    Top,
+    /// Right edge; the left edge stays, centered vertically
+    Right,
+    /// Bottom edge; the top edge stays, centered horizontally
+    Bottom,
+    /// Top-left corner; the bottom-right corner stays
+    TopLeft,
+    /// Top-right corner; the bottom-left corner stays
+    TopRight,
+    /// Bottom-right corner; the top-left corner stays
+    BottomRight,
+    /// Bottom-left corner; the top-right corner stays
+    BottomLeft,
+}
+
+impl CropHandle {
+    /// Position of the anchor within the crop, as fractions of its width
+    /// and height.
+    fn anchor(self) -> (f64, f64) {
+        match self {
+            CropHandle::Left => (1.0, 0.5),
+            CropHandle::Top => (0.5, 1.0),
+            CropHandle::Right => (0.0, 0.5),
+            CropHandle::Bottom => (0.5, 0.0),
+            CropHandle::TopLeft => (1.0, 1.0),
+            CropHandle::TopRight => (0.0, 1.0),
+            CropHandle::BottomRight => (0.0, 0.0),
+            CropHandle::BottomLeft => (1.0, 0.0),
+        }
+    }
+}
+
+/// Apply a drag to an aspect-locked crop.
+///
+/// The dragged handle moves by `delta` and the other dimension follows
+/// from `aspect`, while the opposite edge (at its center) or corner stays
+/// where it is. If the result would reach past the rotated photo, it is
+/// shrunk towards the anchor to the largest crop with that aspect that
+/// fits.
+///
+/// # Arguments
+///
+/// * `current` - Crop before the drag; its anchor must lie inside the photo,
+///   as it does for any crop this function returned
+/// * `handle` - The handle being dragged
+/// * `delta` - Movement of the handle, normalized to the canvas: along x
+///   for the left and right edges and the corners, along y for the top and
+///   bottom edges (positive = right or down)
+/// * `aspect` - Locked width / height of the crop in pixels
+/// * `rotation_degrees` - Total rotation of the photo (positive =
+///   counter-clockwise)
+/// * `image_width`, `image_height` - Size of the unrotated photo
+///
+/// # Returns
+///
+/// The new crop. A zero `delta`, or an `aspect` that is not finite and
+/// positive, returns `current` unchanged.
+pub fn solve_constrained_crop(
+    current: CropRect,
+    handle: CropHandle,
+    delta: f32,
+    aspect: f32,
+    rotation_degrees: f32,
+    image_width: u32,
+    image_height: u32,
+) -> CropRect {
+    if delta.abs() < f32::EPSILON || !aspect.is_finite() || aspect <= 0.0 {
+        return current;
+    }
+    let angle = rotation_degrees as f64;
+    let (canvas_w, canvas_h) = compute_rotated_bounds(image_width, image_height, angle);
+    let (canvas_w, canvas_h) = (canvas_w as f64, canvas_h as f64);
+    let aspect = aspect as f64;
+
+    // Work in canvas pixels, where the aspect ratio is a plain ratio
+    let width = current.width as f64 * canvas_w;
+    let height = current.height as f64 * canvas_h;
+    let (fx, fy) = handle.anchor();
+    let anchor = (
+        current.left as f64 * canvas_w + fx * width,
+        current.top as f64 * canvas_h + fy * height,
+    );
+
+    // Dragging a left or top handle right or down shrinks the crop
+    let delta = delta as f64;
+    let (new_w, new_h) = match handle {
+        CropHandle::Top | CropHandle::Bottom => {
+            let grow = if handle == CropHandle::Top {
+                -delta
+            } else {
+                delta
+            };
+            let h = (height + grow * canvas_h).max(MIN_CROP_PIXELS);
+            (h * aspect, h)
+        }
+        _ => {
+            let grow = if fx > 0.5 { -delta } else { delta };
+            let w = (width + grow * canvas_w).max(MIN_CROP_PIXELS);
+            (w, w / aspect)
+        }
+    };
+
+    let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+        .map(|(cx, cy)| ((cx - fx) * new_w, (cy - fy) * new_h));
+    let scale = max_scale_inside(
+        anchor,
+        &corners,
+        &image_polygon(image_width, image_height, angle, canvas_w, canvas_h),
+    );
+    let (new_w, new_h) = (new_w * scale, new_h * scale);
+
+    CropRect {
+        left: ((anchor.0 - fx * new_w) / canvas_w) as f32,
+        top: ((anchor.1 - fy * new_h) / canvas_h) as f32,
+        width: (new_w / canvas_w) as f32,
+        height: (new_h / canvas_h) as f32,
+    }
+}
+
+/// Corners of the rotated photo on its canvas, in canvas pixels, clipped
+/// to the canvas where rounding its size cut a sliver off.
+fn image_polygon(
+    width: u32,
+    height: u32,
+    angle_degrees: f64,
+    canvas_w: f64,
+    canvas_h: f64,
+) -> [(f64, f64); 4] {
+    let (w, h) = (width as f64, height as f64);
+    [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)].map(|(x, y)| {
+        let (x, y) = rotation_forward_map(x, y, width, height, angle_degrees);
+        (x.clamp(0.0, canvas_w), y.clamp(0.0, canvas_h))
+    })
+}
+
+/// Largest scale up to 1 at which `anchor + scale * offset` lies inside the
+/// convex `polygon` for every offset.
+///
+/// The polygon is convex and contains the anchor, so a corner that is
+/// inside at some scale is inside at every smaller one; the answer is the
+/// nearest exit along any corner's ray.
+fn max_scale_inside(anchor: (f64, f64), offsets: &[(f64, f64)], polygon: &[(f64, f64); 4]) -> f64 {
+    let center = polygon
+        .iter()
+        .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / 4.0, acc.1 + p.1 / 4.0));
+    let cross = |a: (f64, f64), b: (f64, f64)| a.0 * b.1 - a.1 * b.0;
+
+    let mut scale: f64 = 1.0;
+    for (i, &p) in polygon.iter().enumerate() {
+        let q = polygon[(i + 1) % polygon.len()];
+        let edge = (q.0 - p.0, q.1 - p.1);
+        // Orient the edge so the inside of the polygon is positive
+        let side = cross(edge, (center.0 - p.0, center.1 - p.1)).signum();
+        let inside = side * cross(edge, (anchor.0 - p.0, anchor.1 - p.1));
+        for &offset in offsets {
+            let approach = side * cross(edge, offset);
+            if approach < 0.0 {
+                scale = scale.min(inside.max(0.0) / -approach);
+            }
+        }
+    }
+    scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{compute_rotation_auto_crop, rotation_inverse_map};
+
+    const HANDLES: [CropHandle; 8] = [
+        CropHandle::Left,
+        CropHandle::Top,
+        CropHandle::Right,
+        CropHandle::Bottom,
+        CropHandle::TopLeft,
+        CropHandle::TopRight,
+        CropHandle::BottomRight,
+        CropHandle::BottomLeft,
+    ];
+
+    /// Pixel aspect ratio of a crop on the canvas of a rotated image.
+    fn pixel_aspect(crop: &CropRect, width: u32, height: u32, angle: f32) -> f32 {
+        let (cw, ch) = compute_rotated_bounds(width, height, angle as f64);
+        crop.width * cw as f32 / (crop.height * ch as f32)
+    }
+
+    /// Whether every crop corner lies inside the rotated image.
+    fn inside_image(crop: &CropRect, width: u32, height: u32, angle: f32) -> bool {
+        let (cw, ch) = compute_rotated_bounds(width, height, angle as f64);
+        let (cw, ch) = (cw as f64, ch as f64);
+        [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+            .iter()
+            .all(|&(cx, cy)| {
+                let x = (crop.left + cx * crop.width) as f64 * cw;
+                let y = (crop.top + cy * crop.height) as f64 * ch;
+                // Back in the source, the corner must be inside the photo
+                let (sx, sy) = rotation_inverse_map(x, y, width, height, angle as f64);
+                let tolerance = 0.05;
+                (-tolerance..=width as f64 + tolerance).contains(&sx)
+                    && (-tolerance..=height as f64 + tolerance).contains(&sy)
+            })
+    }
+
+    /// Anchor point of `handle` on `crop`.
+    fn anchor_of(crop: &CropRect, handle: CropHandle) -> (f32, f32) {
+        let (fx, fy) = handle.anchor();
+        (
+            crop.left + fx as f32 * crop.width,
+            crop.top + fy as f32 * crop.height,
+        )
+    }
+
+    /// The auto crop of a rotated image shrunk by a fifth about its center,
+    /// a valid crop with room to grow in any direction.
+    fn inner_crop(width: u32, height: u32, angle: f32) -> CropRect {
+        let (left, top, w, h) = compute_rotation_auto_crop(width, height, angle as f64);
+        CropRect {
+            left: (left + w * 0.1) as f32,
+            top: (top + h * 0.1) as f32,
+            width: (w * 0.8) as f32,
+            height: (h * 0.8) as f32,
+        }
+    }
+
+    #[test]
+    fn test_drag_past_rotated_bounds_stays_inside() {
+        let (width, height) = (300, 200);
+        for angle in [0.0, 7.5, -12.0, 30.0] {
+            let current = inner_crop(width, height, angle);
+            let aspect = pixel_aspect(&current, width, height, angle);
+            for handle in HANDLES {
+                for delta in [-2.0, 2.0] {
+                    let crop = solve_constrained_crop(
+                        current, handle, delta, aspect, angle, width, height,
+                    );
+                    assert!(
+                        inside_image(&crop, width, height, angle),
+                        "{:?} at {} by {}: {:?}",
+                        handle,
+                        angle,
+                        delta,
+                        crop
+                    );
+                    let ratio = pixel_aspect(&crop, width, height, angle) / aspect;
+                    assert!((ratio - 1.0).abs() < 1e-4, "{:?}: {:?}", handle, crop);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_anchor_does_not_move() {
+        let (width, height) = (400, 300);
+        let angle = 10.0;
+        let current = inner_crop(width, height, angle);
+        for handle in HANDLES {
+            for delta in [-0.5, -0.05, 0.03, 0.5] {
+                let crop =
+                    solve_constrained_crop(current, handle, delta, 1.5, angle, width, height);
+                let (ax, ay) = anchor_of(&current, handle);
+                let (bx, by) = anchor_of(&crop, handle);
+                assert!(
+                    (ax - bx).abs() < 1e-5 && (ay - by).abs() < 1e-5,
+                    "{:?} by {}: {:?} moved to {:?}",
+                    handle,
+                    delta,
+                    (ax, ay),
+                    (bx, by)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_drag_within_bounds_is_exact() {
+        // Unrotated 200x100: a 100x50 crop at (50, 25) has room to grow
+        let current = CropRect {
+            left: 0.25,
+            top: 0.25,
+            width: 0.5,
+            height: 0.5,
+        };
+        let crop = solve_constrained_crop(current, CropHandle::Right, 0.1, 2.0, 0.0, 200, 100);
+        // Width grows by 20 pixels to 120, height follows to 60 about the
+        // vertical center
+        assert!((crop.left - 0.25).abs() < 1e-6);
+        assert!((crop.width - 0.6).abs() < 1e-6);
+        assert!((crop.height - 0.6).abs() < 1e-6);
+        assert!((crop.top - 0.2).abs() < 1e-6);
+
+        // Dragging the top edge down shrinks the crop towards the bottom
+        let crop = solve_constrained_crop(current, CropHandle::Top, 0.1, 2.0, 0.0, 200, 100);
+        assert!((crop.top + crop.height - 0.75).abs() < 1e-6);
+        assert!((crop.height - 0.4).abs() < 1e-6);
+        assert!((crop.width - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_zero_delta_returns_input() {
+        let current = CropRect {
+            left: 0.1,
+            top: 0.2,
+            width: 0.3,
+            height: 0.7,
+        };
+        for handle in HANDLES {
+            assert_eq!(
+                solve_constrained_crop(current, handle, 0.0, 1.5, 12.0, 300, 200),
+                current
+            );
+        }
+        // An invalid aspect leaves the crop alone too
+        assert_eq!(
+            solve_constrained_crop(current, CropHandle::Left, 0.1, f32::NAN, 0.0, 300, 200),
+            current
+        );
+    }
+}
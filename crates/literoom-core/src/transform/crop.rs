@@ -14,10 +14,12 @@
 //!
 //! ```ignore
 //! // Crop the center 50% of the image
-//! let cropped = apply_crop(&image, 0.25, 0.25, 0.5, 0.5);
+//! let cropped = apply_crop(&image, 0.25, 0.25, 0.5, 0.5)?;
 //! ```
 
+use super::TransformError;
 use crate::decode::DecodedImage;
+use crate::validate::checked_buffer_len;
 
 /// Create a test image where each pixel has a unique value based on position.
 #[cfg(test)]
@@ -62,6 +64,11 @@ fn create_test_image(width: u32, height: u32) -> DecodedImage {
 /// - Minimum output dimension is 1x1 pixels
 /// - Full crop (0, 0, 1, 1) returns a copy of the original image
 ///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+///
 /// # Example
 ///
 /// ```
@@ -73,7 +80,7 @@ fn create_test_image(width: u32, height: u32) -> DecodedImage {
 /// let image = DecodedImage::new(100, 100, pixels);
 ///
 /// // Crop the center 50x50 region
-/// let cropped = apply_crop(&image, 0.25, 0.25, 0.5, 0.5);
+/// let cropped = apply_crop(&image, 0.25, 0.25, 0.5, 0.5).unwrap();
 /// assert_eq!(cropped.width, 50);
 /// assert_eq!(cropped.height, 50);
 /// ```
@@ -83,15 +90,50 @@ pub fn apply_crop(
     top: f64,
     width: f64,
     height: f64,
-) -> DecodedImage {
+) -> Result<DecodedImage, TransformError> {
+    image.validate()?;
+
     // Fast path: full crop returns a clone
     if left <= 0.0 && top <= 0.0 && width >= 1.0 && height >= 1.0 {
-        return image.clone();
+        return Ok(image.clone());
     }
 
+    let (px_left, px_top, out_width, out_height) =
+        crop_pixel_rect(image.width, image.height, left, top, width, height);
+
+    let mut output = vec![0u8; checked_buffer_len(out_width, out_height, 3)?];
+
+    // Copy pixel data row by row using slice copy for efficiency
+    let row_bytes = (out_width * 3) as usize;
+    for y in 0..out_height {
+        let src_start = ((px_top + y) * image.width + px_left) as usize * 3;
+        let dst_start = (y * out_width) as usize * 3;
+        output[dst_start..dst_start + row_bytes]
+            .copy_from_slice(&image.pixels[src_start..src_start + row_bytes]);
+    }
+
+    Ok(DecodedImage {
+        width: out_width,
+        height: out_height,
+        pixels: output,
+    })
+}
+
+/// Pixel rectangle `(left, top, width, height)` that [`apply_crop`] copies.
+///
+/// The normalized rectangle is rounded to whole pixels and clamped to the
+/// image, keeping at least one pixel in each direction.
+pub(crate) fn crop_pixel_rect(
+    image_width: u32,
+    image_height: u32,
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+) -> (u32, u32, u32, u32) {
     // Convert normalized coordinates to pixel coordinates
-    let src_w = image.width as f64;
-    let src_h = image.height as f64;
+    let src_w = image_width as f64;
+    let src_h = image_height as f64;
 
     let px_left = (left.clamp(0.0, 1.0) * src_w).round() as u32;
     let px_top = (top.clamp(0.0, 1.0) * src_h).round() as u32;
@@ -99,41 +141,112 @@ pub fn apply_crop(
     let px_height = (height.clamp(0.0, 1.0) * src_h).round() as u32;
 
     // Clamp to image bounds
-    let px_left = px_left.min(image.width.saturating_sub(1));
-    let px_top = px_top.min(image.height.saturating_sub(1));
-    let px_right = (px_left + px_width).min(image.width);
-    let px_bottom = (px_top + px_height).min(image.height);
+    let px_left = px_left.min(image_width.saturating_sub(1));
+    let px_top = px_top.min(image_height.saturating_sub(1));
+    let px_right = (px_left + px_width).min(image_width);
+    let px_bottom = (px_top + px_height).min(image_height);
 
     // Ensure minimum dimensions
     let out_width = px_right.saturating_sub(px_left).max(1);
     let out_height = px_bottom.saturating_sub(px_top).max(1);
 
-    let mut output = vec![0u8; (out_width * out_height * 3) as usize];
+    (px_left, px_top, out_width, out_height)
+}
 
-    // Copy pixel data row by row using slice copy for efficiency
-    let row_bytes = (out_width * 3) as usize;
+/// Apply crop with a rectangle that may extend past the image bounds.
+///
+/// Unlike [`apply_crop`], the rectangle is not clamped: the output size is
+/// always the rectangle size, in-bounds pixels are copied unchanged, and
+/// every output pixel outside the source is set to `fill`. This keeps the
+/// full frame after straightening, with filled corners instead of a tighter
+/// crop ("constrain to image" off).
+///
+/// # Arguments
+///
+/// * `image` - Source image to crop
+/// * `left`, `top` - Top-left corner of the crop, normalized; may be
+///   negative or beyond 1.0
+/// * `width`, `height` - Size of the crop, normalized; may exceed 1.0
+/// * `fill` - RGB color for pixels outside the source
+///
+/// # Behavior
+///
+/// - Minimum output dimension is 1x1 pixels
+/// - A rectangle entirely outside the source produces an all-fill image
+/// - An in-bounds rectangle gives the same result as [`apply_crop`]
+///
+/// # Errors
+///
+/// - `TransformError::InvalidImage` if the image is empty or its buffer
+///   does not match its dimensions
+/// - `TransformError::InvalidDimensions` if `width` or `height` is not a
+///   finite positive number, or `left` or `top` is not finite
+/// - `TransformError::InvalidImage` if the output would exceed the image
+///   size limits
+pub fn apply_crop_with_fill(
+    image: &DecodedImage,
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+    fill: [u8; 3],
+) -> Result<DecodedImage, TransformError> {
+    image.validate()?;
+    let valid_size = |v: f64| v.is_finite() && v > 0.0;
+    if !valid_size(width) || !valid_size(height) || !left.is_finite() || !top.is_finite() {
+        return Err(TransformError::InvalidDimensions {
+            width: 0,
+            height: 0,
+        });
+    }
+
+    let src_w = image.width as f64;
+    let src_h = image.height as f64;
+
+    // Output origin in source pixels; may be negative. Far-off origins
+    // saturate, so the offsets below use saturating arithmetic.
+    let px_left = (left * src_w).round() as i64;
+    let px_top = (top * src_h).round() as i64;
+    let out_width = ((width * src_w).round() as u32).max(1);
+    let out_height = ((height * src_h).round() as u32).max(1);
+
+    let pixel_count = checked_buffer_len(out_width, out_height, 3)? / 3;
+    let mut output = fill.repeat(pixel_count);
+
+    // Output columns that fall inside the source, the same for every row
+    let col_start = px_left.saturating_neg().clamp(0, out_width as i64);
+    let col_end = (image.width as i64)
+        .saturating_sub(px_left)
+        .clamp(0, out_width as i64);
+    if col_start >= col_end {
+        return Ok(DecodedImage::new(out_width, out_height, output));
+    }
+
+    let row_bytes = (col_end - col_start) as usize * 3;
     for y in 0..out_height {
-        let src_start = ((px_top + y) * image.width + px_left) as usize * 3;
-        let dst_start = (y * out_width) as usize * 3;
+        let src_y = px_top.saturating_add(y as i64);
+        if src_y < 0 || src_y >= image.height as i64 {
+            continue;
+        }
+        let src_x = (px_left + col_start) as usize;
+        let src_start = (src_y as usize * image.width as usize + src_x) * 3;
+        let dst_start = (y as usize * out_width as usize + col_start as usize) * 3;
         output[dst_start..dst_start + row_bytes]
             .copy_from_slice(&image.pixels[src_start..src_start + row_bytes]);
     }
 
-    DecodedImage {
-        width: out_width,
-        height: out_height,
-        pixels: output,
-    }
+    Ok(DecodedImage::new(out_width, out_height, output))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::noise;
 
     #[test]
     fn test_full_crop() {
         let img = create_test_image(100, 100);
-        let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0);
+        let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0).unwrap();
 
         assert_eq!(result.width, 100);
         assert_eq!(result.height, 100);
@@ -143,7 +256,7 @@ mod tests {
     #[test]
     fn test_half_crop() {
         let img = create_test_image(100, 100);
-        let result = apply_crop(&img, 0.0, 0.0, 0.5, 0.5);
+        let result = apply_crop(&img, 0.0, 0.0, 0.5, 0.5).unwrap();
 
         assert_eq!(result.width, 50);
         assert_eq!(result.height, 50);
@@ -152,7 +265,7 @@ mod tests {
     #[test]
     fn test_center_crop() {
         let img = create_test_image(10, 10);
-        let result = apply_crop(&img, 0.2, 0.2, 0.6, 0.6);
+        let result = apply_crop(&img, 0.2, 0.2, 0.6, 0.6).unwrap();
 
         // 0.2 * 10 = 2, 0.6 * 10 = 6
         assert_eq!(result.width, 6);
@@ -168,7 +281,7 @@ mod tests {
         let img = create_test_image(10, 10);
 
         // Start at 80% and request 50% - should clamp
-        let result = apply_crop(&img, 0.8, 0.8, 0.5, 0.5);
+        let result = apply_crop(&img, 0.8, 0.8, 0.5, 0.5).unwrap();
 
         // Should only get the remaining pixels (2x2)
         assert!(result.width <= 10);
@@ -180,7 +293,7 @@ mod tests {
         let img = create_test_image(100, 100);
 
         // Negative coords should clamp to 0
-        let result = apply_crop(&img, -0.1, -0.1, 0.5, 0.5);
+        let result = apply_crop(&img, -0.1, -0.1, 0.5, 0.5).unwrap();
 
         // Should start from 0,0
         assert_eq!(result.width, 50);
@@ -192,7 +305,7 @@ mod tests {
         let img = create_test_image(100, 100);
 
         // Region larger than 1.0 should clamp
-        let result = apply_crop(&img, 0.0, 0.0, 1.5, 1.5);
+        let result = apply_crop(&img, 0.0, 0.0, 1.5, 1.5).unwrap();
 
         // Should return full image
         assert_eq!(result.width, 100);
@@ -204,7 +317,7 @@ mod tests {
         let img = create_test_image(10, 10);
 
         // Crop from (3, 3) with size (4, 4)
-        let result = apply_crop(&img, 0.3, 0.3, 0.4, 0.4);
+        let result = apply_crop(&img, 0.3, 0.3, 0.4, 0.4).unwrap();
 
         // First pixel should be from (3, 3)
         // Value = (3 * 10 + 3) % 256 = 33
@@ -218,7 +331,7 @@ mod tests {
         let img = create_test_image(200, 100);
 
         // Crop a vertical strip
-        let result = apply_crop(&img, 0.0, 0.0, 0.25, 1.0);
+        let result = apply_crop(&img, 0.0, 0.0, 0.25, 1.0).unwrap();
 
         assert_eq!(result.width, 50);
         assert_eq!(result.height, 100);
@@ -229,7 +342,7 @@ mod tests {
         let img = create_test_image(100, 100);
 
         // Very small crop region
-        let result = apply_crop(&img, 0.99, 0.99, 0.001, 0.001);
+        let result = apply_crop(&img, 0.99, 0.99, 0.001, 0.001).unwrap();
 
         // Should have minimum 1x1 dimension
         assert!(result.width >= 1);
@@ -240,7 +353,7 @@ mod tests {
     fn test_small_image_crop() {
         // Test with very small image
         let img = create_test_image(4, 4);
-        let result = apply_crop(&img, 0.25, 0.25, 0.5, 0.5);
+        let result = apply_crop(&img, 0.25, 0.25, 0.5, 0.5).unwrap();
 
         assert!(result.width >= 1);
         assert!(result.height >= 1);
@@ -249,11 +362,133 @@ mod tests {
     #[test]
     fn test_identity_crop() {
         let img = create_test_image(50, 50);
-        let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0);
+        let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0).unwrap();
 
         // Pixels should be identical
         assert_eq!(result.pixels, img.pixels);
     }
+
+    const FILL: [u8; 3] = [255, 0, 255];
+
+    #[test]
+    fn test_fill_crop_past_right_edge() {
+        let img = noise(100, 80, 11);
+        // 90% wide starting at 20%: the right 10 columns are outside
+        let result = apply_crop_with_fill(&img, 0.2, 0.25, 0.9, 0.5, FILL).unwrap();
+
+        assert_eq!((result.width, result.height), (90, 40));
+        for y in 0..40 {
+            let row = &result.pixels[y * 90 * 3..(y + 1) * 90 * 3];
+            let src_start = ((20 + y) * 100 + 20) * 3;
+            assert_eq!(row[..80 * 3], img.pixels[src_start..src_start + 80 * 3]);
+            assert!(row[80 * 3..].chunks(3).all(|p| p == FILL));
+        }
+    }
+
+    #[test]
+    fn test_fill_crop_past_top_left() {
+        let img = noise(10, 10, 12);
+        let result = apply_crop_with_fill(&img, -0.2, -0.1, 0.5, 0.5, FILL).unwrap();
+
+        assert_eq!((result.width, result.height), (5, 5));
+        // Row 0 is above the source; columns 0-1 are left of it
+        assert!(result.pixels[..5 * 3].chunks(3).all(|p| p == FILL));
+        assert_eq!(result.pixels[(5 + 1) * 3..(5 + 2) * 3], FILL);
+        assert_eq!(result.pixels[(5 + 2) * 3..(5 + 5) * 3], img.pixels[..3 * 3]);
+    }
+
+    #[test]
+    fn test_fill_crop_fully_outside_is_all_fill() {
+        let img = noise(20, 20, 13);
+        for (left, top) in [(1.5, 0.0), (-2.0, 0.5), (0.0, 1.0), (3.0, -3.0)] {
+            let result = apply_crop_with_fill(&img, left, top, 0.5, 0.5, FILL).unwrap();
+            assert_eq!((result.width, result.height), (10, 10));
+            assert!(result.pixels.chunks(3).all(|p| p == FILL));
+        }
+    }
+
+    #[test]
+    fn test_fill_crop_in_bounds_matches_apply_crop() {
+        let img = noise(37, 23, 14);
+        for (l, t, w, h) in [
+            (0.0, 0.0, 1.0, 1.0),
+            (0.1, 0.2, 0.5, 0.6),
+            (0.25, 0.5, 0.75, 0.45),
+        ] {
+            let filled = apply_crop_with_fill(&img, l, t, w, h, FILL).unwrap();
+            let clamped = apply_crop(&img, l, t, w, h).unwrap();
+            assert_eq!(filled.pixels, clamped.pixels);
+        }
+    }
+
+    #[test]
+    fn test_fill_crop_rejects_invalid_size() {
+        let img = noise(10, 10, 16);
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 0.0, -0.5] {
+            let wide = apply_crop_with_fill(&img, 0.0, 0.0, bad, 0.5, FILL);
+            assert!(
+                matches!(wide, Err(TransformError::InvalidDimensions { .. })),
+                "width {}",
+                bad
+            );
+            let tall = apply_crop_with_fill(&img, 0.0, 0.0, 0.5, bad, FILL);
+            assert!(
+                matches!(tall, Err(TransformError::InvalidDimensions { .. })),
+                "height {}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_fill_crop_rejects_non_finite_origin() {
+        let img = noise(10, 10, 17);
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let left = apply_crop_with_fill(&img, bad, 0.0, 0.5, 0.5, FILL);
+            assert!(
+                matches!(left, Err(TransformError::InvalidDimensions { .. })),
+                "left {}",
+                bad
+            );
+            let top = apply_crop_with_fill(&img, 0.0, bad, 0.5, 0.5, FILL);
+            assert!(
+                matches!(top, Err(TransformError::InvalidDimensions { .. })),
+                "top {}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_fill_crop_far_off_origin_is_all_fill() {
+        let img = noise(10, 10, 18);
+        for (left, top) in [(-1e300, 0.0), (1e300, 0.0), (0.0, -1e300), (0.0, 1e300)] {
+            let result = apply_crop_with_fill(&img, left, top, 0.5, 0.5, FILL).unwrap();
+            assert_eq!((result.width, result.height), (5, 5));
+            assert!(result.pixels.chunks(3).all(|p| p == FILL));
+        }
+    }
+
+    #[test]
+    fn test_fill_crop_larger_than_source() {
+        let img = noise(10, 10, 15);
+        let result = apply_crop_with_fill(&img, -0.5, -0.5, 2.0, 2.0, FILL).unwrap();
+
+        assert_eq!((result.width, result.height), (20, 20));
+        let filled = result.pixels.chunks(3).filter(|&p| p == FILL).count();
+        assert!(filled >= 20 * 20 - 10 * 10);
+        let first_src = (5 * 20 + 5) * 3;
+        assert_eq!(result.pixels[first_src..first_src + 30], img.pixels[..30]);
+    }
+
+    #[test]
+    fn test_fill_crop_empty_image_is_error() {
+        let empty = DecodedImage::new(0, 0, vec![]);
+        assert!(matches!(
+            apply_crop_with_fill(&empty, 0.0, 0.0, 1.0, 1.0, FILL),
+            Err(TransformError::InvalidImage(_))
+        ));
+    }
 }
 
 // ============================================================================
@@ -288,7 +523,7 @@ mod proptests {
             (left, top, crop_w, crop_h) in crop_coords_strategy(),
         ) {
             let img = create_test_image(width, height);
-            let result = apply_crop(&img, left, top, crop_w, crop_h);
+            let result = apply_crop(&img, left, top, crop_w, crop_h).unwrap();
 
             prop_assert!(result.width >= 1, "Width should be at least 1");
             prop_assert!(result.height >= 1, "Height should be at least 1");
@@ -301,7 +536,7 @@ mod proptests {
             (left, top, crop_w, crop_h) in crop_coords_strategy(),
         ) {
             let img = create_test_image(width, height);
-            let result = apply_crop(&img, left, top, crop_w, crop_h);
+            let result = apply_crop(&img, left, top, crop_w, crop_h).unwrap();
 
             prop_assert!(result.width <= width, "Output width should not exceed input");
             prop_assert!(result.height <= height, "Output height should not exceed input");
@@ -314,7 +549,7 @@ mod proptests {
             (left, top, crop_w, crop_h) in crop_coords_strategy(),
         ) {
             let img = create_test_image(width, height);
-            let result = apply_crop(&img, left, top, crop_w, crop_h);
+            let result = apply_crop(&img, left, top, crop_w, crop_h).unwrap();
 
             let expected_len = (result.width * result.height * 3) as usize;
             prop_assert_eq!(
@@ -330,7 +565,7 @@ mod proptests {
             (width, height) in dimensions_strategy(),
         ) {
             let img = create_test_image(width, height);
-            let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0);
+            let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0).unwrap();
 
             prop_assert_eq!(result.width, img.width, "Full crop width should match");
             prop_assert_eq!(result.height, img.height, "Full crop height should match");
@@ -345,8 +580,8 @@ mod proptests {
         ) {
             let img = create_test_image(width, height);
 
-            let result1 = apply_crop(&img, left, top, crop_w, crop_h);
-            let result2 = apply_crop(&img, left, top, crop_w, crop_h);
+            let result1 = apply_crop(&img, left, top, crop_w, crop_h).unwrap();
+            let result2 = apply_crop(&img, left, top, crop_w, crop_h).unwrap();
 
             prop_assert_eq!(result1.width, result2.width);
             prop_assert_eq!(result1.height, result2.height);
@@ -360,7 +595,7 @@ mod proptests {
             (left, top, crop_w, crop_h) in (0.1f64..=0.3, 0.1f64..=0.3, 0.3f64..=0.5, 0.3f64..=0.5),
         ) {
             let img = create_test_image(width, height);
-            let result = apply_crop(&img, left, top, crop_w, crop_h);
+            let result = apply_crop(&img, left, top, crop_w, crop_h).unwrap();
 
             // For each pixel in result, verify it matches some pixel in original
             for chunk in result.pixels.chunks(3) {
@@ -380,7 +615,7 @@ mod proptests {
             let img = create_test_image(width, height);
 
             // Crop with negative left/top
-            let result = apply_crop(&img, neg_amount, neg_amount, 0.5, 0.5);
+            let result = apply_crop(&img, neg_amount, neg_amount, 0.5, 0.5).unwrap();
 
             // Should still produce valid output
             prop_assert!(result.width >= 1);
@@ -399,7 +634,7 @@ mod proptests {
             let img = create_test_image(width, height);
 
             // Crop with width/height > 1.0
-            let result = apply_crop(&img, 0.0, 0.0, 2.0, 2.0);
+            let result = apply_crop(&img, 0.0, 0.0, 2.0, 2.0).unwrap();
 
             // Should return full image (clamped)
             prop_assert_eq!(result.width, width);
@@ -414,7 +649,7 @@ mod proptests {
             let img = create_test_image(width, height);
 
             // Start at 90% and request 50% - should only get ~10% of original
-            let result = apply_crop(&img, 0.9, 0.9, 0.5, 0.5);
+            let result = apply_crop(&img, 0.9, 0.9, 0.5, 0.5).unwrap();
 
             // Output should be significantly smaller than half the original
             prop_assert!(result.width < width / 2 + 5);
@@ -429,10 +664,10 @@ mod proptests {
             let img = create_test_image(width, height);
 
             // First crop: take middle 50%
-            let crop1 = apply_crop(&img, 0.25, 0.25, 0.5, 0.5);
+            let crop1 = apply_crop(&img, 0.25, 0.25, 0.5, 0.5).unwrap();
 
             // Second crop: take middle 50% of that
-            let crop2 = apply_crop(&crop1, 0.25, 0.25, 0.5, 0.5);
+            let crop2 = apply_crop(&crop1, 0.25, 0.25, 0.5, 0.5).unwrap();
 
             // Final dimensions should be approximately 25% of original
             // (with some rounding tolerance)
@@ -461,7 +696,7 @@ mod proptests {
             let img = create_test_image(width, height);
 
             // Crop to exactly half in each dimension
-            let result = apply_crop(&img, 0.0, 0.0, 0.5, 0.5);
+            let result = apply_crop(&img, 0.0, 0.0, 0.5, 0.5).unwrap();
 
             // Output should be approximately half (within rounding)
             let expected_w = (width as f64 * 0.5).round() as u32;
@@ -489,7 +724,7 @@ mod proptests {
             let img = create_test_image(width, height);
 
             // Very tiny crop region
-            let result = apply_crop(&img, 0.5, 0.5, 0.001, 0.001);
+            let result = apply_crop(&img, 0.5, 0.5, 0.001, 0.001).unwrap();
 
             // Should produce at least 1x1
             prop_assert!(result.width >= 1);
@@ -1,4 +1,4 @@
-//! Image transformation operations: rotation and cropping.
+//! Image transformation operations: rotation, cropping, and perspective.
 //!
 //! This module provides non-destructive transform operations that are applied
 //! in the editing pipeline before adjustments and tone curve processing.
@@ -16,9 +16,44 @@
 //! - Rotation angles are in degrees, positive = counter-clockwise
 //! - Crop coordinates are normalized (0.0 to 1.0) relative to image dimensions
 //! - Origin is top-left corner
+//!
+//! # Perspective
+//!
+//! `solve_guided_transform` computes perspective correction from user-drawn
+//! guide lines that should end up vertical or horizontal ("guided upright").
+//!
+//! # Auto-Straighten
+//!
+//! `detect_straighten_angle` estimates the tilt of a photo from its edges,
+//! and `compute_rotation_auto_crop` crops away the corners the correction
+//! leaves empty.
+//!
+//! # Aspect-Locked Crop
+//!
+//! `solve_constrained_crop` applies a drag of one crop handle with the
+//! aspect ratio locked, keeping the opposite edge or corner fixed and the
+//! crop inside the rotated photo.
 
+mod aspect_crop;
 mod crop;
+mod perspective;
 mod rotation;
+mod straighten;
+mod types;
+
+pub use aspect_crop::{solve_constrained_crop, CropHandle};
+pub use crop::{apply_crop, apply_crop_with_fill};
+pub use perspective::{
+    solve_guided_transform, GuideLine, GuideTarget, PerspectiveParams, SolvedAxes,
+};
+pub use rotation::{
+    apply_rotation, compute_rotated_bounds, rotation_forward_map, rotation_inverse_map,
+    InterpolationFilter,
+};
+pub use straighten::{
+    compute_rotation_auto_crop, detect_straighten_angle, StraightenEstimate, MAX_STRAIGHTEN_DEGREES,
+};
+pub use types::TransformError;
 
-pub use crop::apply_crop;
-pub use rotation::{apply_rotation, compute_rotated_bounds, InterpolationFilter};
+pub(crate) use crop::crop_pixel_rect;
+pub(crate) use rotation::{is_negligible_angle, sample, InverseRotation};
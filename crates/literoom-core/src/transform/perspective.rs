@@ -0,0 +1,650 @@
+//! Perspective correction parameters and the guided upright solver.
+//!
+//! The correction is modeled as a virtual rotation of a pinhole camera:
+//! a roll in the image plane, then a yaw (horizontal tilt) and a pitch
+//! (vertical tilt). The focal length is assumed to equal the long image edge,
+//! roughly a 35mm-equivalent lens, which is what makes the tilt angles
+//! comparable between photos.
+//!
+//! Guides are solved with damped Gauss-Newton over the parameters the guides
+//! can actually constrain, minimizing the sine of each guide's angular
+//! deviation from its target axis.
+
+use super::TransformError;
+use serde::{Deserialize, Serialize};
+
+/// Maximum Gauss-Newton iterations before giving up on convergence.
+const MAX_ITERATIONS: usize = 50;
+
+/// Step (radians) for the finite-difference Jacobian.
+const JACOBIAN_STEP: f64 = 1e-6;
+
+/// Guides shorter than this many pixels are rejected as degenerate.
+const MIN_GUIDE_LENGTH: f64 = 1.0;
+
+/// The axis a guide line should be aligned with after correction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuideTarget {
+    /// The line should become vertical.
+    Vertical,
+    /// The line should become horizontal.
+    Horizontal,
+}
+
+/// A user-drawn guide line in source pixel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GuideLine {
+    /// Start point X
+    pub x1: f64,
+    /// Start point Y
+    pub y1: f64,
+    /// End point X
+    pub x2: f64,
+    /// End point Y
+    pub y2: f64,
+    /// Axis the line should align with
+    pub target: GuideTarget,
+}
+
+/// Which correction parameters a solve was able to determine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolvedAxes {
+    /// Vertical tilt was solved (at least one vertical guide)
+    pub vertical: bool,
+    /// Horizontal tilt was solved (at least one horizontal guide)
+    pub horizontal: bool,
+    /// In-plane rotation was solved (more guides than tilt axes)
+    pub rotation: bool,
+}
+
+/// Perspective correction parameters.
+///
+/// All angles are in degrees. Zero for every angle is the identity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PerspectiveParams {
+    /// Vertical tilt (pitch). Positive corrects lines converging toward the top.
+    pub vertical: f64,
+    /// Horizontal tilt (yaw). Positive corrects lines converging toward the right.
+    pub horizontal: f64,
+    /// In-plane rotation (positive = counter-clockwise)
+    pub rotate: f64,
+    /// Parameters determined by the guides (all false when set by hand)
+    pub solved: SolvedAxes,
+    /// RMS angular error of the guides after correction, in degrees.
+    ///
+    /// Near zero when the guides are consistent; a large value means they
+    /// contradict each other and the UI should warn.
+    pub residual_degrees: f64,
+}
+
+impl PerspectiveParams {
+    /// Create parameters from tilt and rotation angles in degrees.
+    pub fn new(vertical: f64, horizontal: f64, rotate: f64) -> Self {
+        Self {
+            vertical,
+            horizontal,
+            rotate,
+            ..Self::default()
+        }
+    }
+
+    /// The 3x3 homography mapping source pixels to corrected pixels.
+    ///
+    /// Points map as `[x', y', w'] = H * [x, y, 1]`, followed by division by `w'`.
+    pub fn homography(&self, width: u32, height: u32) -> [[f64; 3]; 3] {
+        let camera = Camera::new(width, height);
+        let r = rotation_matrix(
+            self.vertical.to_radians(),
+            self.horizontal.to_radians(),
+            self.rotate.to_radians(),
+        );
+        // H = K * R * K^-1 with K = [[f, 0, cx], [0, f, cy], [0, 0, 1]]
+        let (f, cx, cy) = (camera.focal, camera.cx, camera.cy);
+        let mut h = [[0.0; 3]; 3];
+        for (i, row) in h.iter_mut().enumerate() {
+            // Row i of R * K^-1
+            let a = r[i][0] / f;
+            let b = r[i][1] / f;
+            let c = r[i][2] - (r[i][0] * cx + r[i][1] * cy) / f;
+            *row = [a, b, c];
+        }
+        // Left-multiply by K
+        let bottom = h[2];
+        for j in 0..3 {
+            h[0][j] = f * h[0][j] + cx * bottom[j];
+            h[1][j] = f * h[1][j] + cy * bottom[j];
+        }
+        h
+    }
+
+    /// Map a source pixel to its corrected position.
+    ///
+    /// Returns `None` if the point ends up behind the virtual camera.
+    pub fn map_point(&self, x: f64, y: f64, width: u32, height: u32) -> Option<(f64, f64)> {
+        let camera = Camera::new(width, height);
+        let r = rotation_matrix(
+            self.vertical.to_radians(),
+            self.horizontal.to_radians(),
+            self.rotate.to_radians(),
+        );
+        camera.map(&r, x, y)
+    }
+}
+
+/// Solve perspective correction that makes the guide lines axis-aligned.
+///
+/// The parameters that can be determined depend on the guides:
+/// - Vertical guides determine the vertical tilt
+/// - Horizontal guides determine the horizontal tilt
+/// - Any guides beyond those also determine the in-plane rotation
+///
+/// So a single guide fixes only its own axis, two vertical guides fix vertical
+/// tilt and rotation, and two vertical plus one horizontal fix everything.
+/// The solved parameters are reported in [`PerspectiveParams::solved`].
+///
+/// # Arguments
+///
+/// * `guides` - Guide lines in source pixel coordinates
+/// * `width`, `height` - Source image dimensions
+///
+/// # Returns
+///
+/// The correction parameters, with `residual_degrees` set to the RMS angular
+/// error of the guides after correction.
+///
+/// # Errors
+///
+/// - `TransformError::InvalidDimensions` if width or height is zero
+/// - `TransformError::NoGuides` if `guides` is empty
+/// - `TransformError::DegenerateGuide` if a guide has (near) zero length or
+///   non-finite coordinates
+pub fn solve_guided_transform(
+    guides: &[GuideLine],
+    width: u32,
+    height: u32,
+) -> Result<PerspectiveParams, TransformError> {
+    if width == 0 || height == 0 {
+        return Err(TransformError::InvalidDimensions { width, height });
+    }
+    if guides.is_empty() {
+        return Err(TransformError::NoGuides);
+    }
+    for (index, g) in guides.iter().enumerate() {
+        let finite = [g.x1, g.y1, g.x2, g.y2].iter().all(|v| v.is_finite());
+        if !finite || (g.x2 - g.x1).hypot(g.y2 - g.y1) < MIN_GUIDE_LENGTH {
+            return Err(TransformError::DegenerateGuide(index));
+        }
+    }
+
+    let verticals = guides
+        .iter()
+        .filter(|g| g.target == GuideTarget::Vertical)
+        .count();
+    let horizontals = guides.len() - verticals;
+    let tilt_axes = (verticals > 0) as usize + (horizontals > 0) as usize;
+    let solved = SolvedAxes {
+        vertical: verticals > 0,
+        horizontal: horizontals > 0,
+        rotation: guides.len() > tilt_axes,
+    };
+
+    let camera = Camera::new(width, height);
+    let active: Vec<usize> = [solved.vertical, solved.horizontal, solved.rotation]
+        .iter()
+        .enumerate()
+        .filter(|(_, &on)| on)
+        .map(|(i, _)| i)
+        .collect();
+
+    let angles = gauss_newton(&camera, guides, &active);
+    let residuals = guide_residuals(&camera, guides, angles);
+    let mean_square = residuals
+        .iter()
+        .map(|r| r.clamp(-1.0, 1.0).asin().to_degrees().powi(2))
+        .sum::<f64>()
+        / residuals.len() as f64;
+
+    Ok(PerspectiveParams {
+        vertical: angles[0].to_degrees(),
+        horizontal: angles[1].to_degrees(),
+        rotate: angles[2].to_degrees(),
+        solved,
+        residual_degrees: mean_square.sqrt(),
+    })
+}
+
+/// Pinhole camera centered on the image with focal length = long edge.
+struct Camera {
+    focal: f64,
+    cx: f64,
+    cy: f64,
+}
+
+impl Camera {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            focal: width.max(height) as f64,
+            cx: width as f64 / 2.0,
+            cy: height as f64 / 2.0,
+        }
+    }
+
+    fn map(&self, r: &[[f64; 3]; 3], x: f64, y: f64) -> Option<(f64, f64)> {
+        let p = [(x - self.cx) / self.focal, (y - self.cy) / self.focal, 1.0];
+        let q: Vec<f64> = r
+            .iter()
+            .map(|row| row[0] * p[0] + row[1] * p[1] + row[2] * p[2])
+            .collect();
+        if q[2] <= 1e-9 {
+            return None;
+        }
+        Some((
+            self.focal * q[0] / q[2] + self.cx,
+            self.focal * q[1] / q[2] + self.cy,
+        ))
+    }
+}
+
+/// Correction rotation `Rx(pitch) * Ry(yaw) * Rz(roll)` (angles in radians).
+fn rotation_matrix(pitch: f64, yaw: f64, roll: f64) -> [[f64; 3]; 3] {
+    let (sp, cp) = pitch.sin_cos();
+    let (sy, cy) = yaw.sin_cos();
+    let (sr, cr) = roll.sin_cos();
+    let rx = [[1.0, 0.0, 0.0], [0.0, cp, -sp], [0.0, sp, cp]];
+    let ry = [[cy, 0.0, sy], [0.0, 1.0, 0.0], [-sy, 0.0, cy]];
+    // Counter-clockwise on screen with y pointing down
+    let rz = [[cr, sr, 0.0], [-sr, cr, 0.0], [0.0, 0.0, 1.0]];
+    mat_mul(&mat_mul(&rx, &ry), &rz)
+}
+
+fn mat_mul(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+/// Sine of each guide's deviation from its target axis after correction.
+fn guide_residuals(camera: &Camera, guides: &[GuideLine], angles: [f64; 3]) -> Vec<f64> {
+    let r = rotation_matrix(angles[0], angles[1], angles[2]);
+    guides
+        .iter()
+        .map(|g| {
+            let (Some((x1, y1)), Some((x2, y2))) =
+                (camera.map(&r, g.x1, g.y1), camera.map(&r, g.x2, g.y2))
+            else {
+                // Behind the camera: treat as maximally wrong
+                return 1.0;
+            };
+            let (dx, dy) = (x2 - x1, y2 - y1);
+            let len = dx.hypot(dy).max(f64::EPSILON);
+            match g.target {
+                GuideTarget::Vertical => dx / len,
+                GuideTarget::Horizontal => dy / len,
+            }
+        })
+        .collect()
+}
+
+/// Minimize the guide residuals over the `active` angles (0=pitch, 1=yaw, 2=roll).
+fn gauss_newton(camera: &Camera, guides: &[GuideLine], active: &[usize]) -> [f64; 3] {
+    let mut angles = [0.0f64; 3];
+    let n = active.len();
+    let mut lambda = 1e-6;
+
+    for _ in 0..MAX_ITERATIONS {
+        let r = guide_residuals(camera, guides, angles);
+        let cost: f64 = r.iter().map(|v| v * v).sum();
+
+        // Finite-difference Jacobian, one column per active angle
+        let jacobian: Vec<Vec<f64>> = active
+            .iter()
+            .map(|&axis| {
+                let mut plus = angles;
+                let mut minus = angles;
+                plus[axis] += JACOBIAN_STEP;
+                minus[axis] -= JACOBIAN_STEP;
+                let rp = guide_residuals(camera, guides, plus);
+                let rm = guide_residuals(camera, guides, minus);
+                rp.iter()
+                    .zip(&rm)
+                    .map(|(a, b)| (a - b) / (2.0 * JACOBIAN_STEP))
+                    .collect()
+            })
+            .collect();
+
+        // Normal equations: (J^T J + lambda I) delta = -J^T r
+        let mut jtj = [[0.0f64; 3]; 3];
+        let mut jtr = [0.0f64; 3];
+        for a in 0..n {
+            for b in 0..n {
+                jtj[a][b] = jacobian[a]
+                    .iter()
+                    .zip(&jacobian[b])
+                    .map(|(x, y)| x * y)
+                    .sum();
+            }
+            jtr[a] = -jacobian[a].iter().zip(&r).map(|(x, y)| x * y).sum::<f64>();
+        }
+
+        let mut improved = false;
+        while lambda < 1e6 {
+            let mut damped = jtj;
+            for (a, row) in damped.iter_mut().enumerate().take(n) {
+                row[a] += lambda * (1.0 + jtj[a][a]);
+            }
+            let Some(delta) = solve_linear(&damped, &jtr, n) else {
+                lambda *= 10.0;
+                continue;
+            };
+
+            let mut candidate = angles;
+            for (a, &axis) in active.iter().enumerate() {
+                candidate[axis] += delta[a];
+            }
+            let new_cost: f64 = guide_residuals(camera, guides, candidate)
+                .iter()
+                .map(|v| v * v)
+                .sum();
+
+            if new_cost <= cost {
+                let step = delta.iter().take(n).map(|d| d.abs()).fold(0.0, f64::max);
+                angles = candidate;
+                lambda = (lambda / 10.0).max(1e-12);
+                improved = step > 1e-12;
+                break;
+            }
+            lambda *= 10.0;
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    angles
+}
+
+/// Solve the leading `n x n` block of `a * x = b` by Gaussian elimination.
+fn solve_linear(a: &[[f64; 3]; 3], b: &[f64; 3], n: usize) -> Option<[f64; 3]> {
+    let mut m = *a;
+    let mut rhs = *b;
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| m[i][col].abs().total_cmp(&m[j][col].abs()))?;
+        if m[pivot][col].abs() < 1e-15 {
+            return None;
+        }
+        m.swap(col, pivot);
+        rhs.swap(col, pivot);
+
+        let pivot_row = m[col];
+        for row in col + 1..n {
+            let factor = m[row][col] / pivot_row[col];
+            for (value, pivot_value) in m[row][col..n].iter_mut().zip(&pivot_row[col..n]) {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[col];
+        }
+    }
+
+    let mut x = [0.0; 3];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| m[row][k] * x[k]).sum();
+        x[row] = (rhs[row] - sum) / m[row][row];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: u32 = 3000;
+    const H: u32 = 2000;
+
+    /// Axis-aligned line in the corrected view: start, end, target.
+    type Segment = ((f64, f64), (f64, f64), GuideTarget);
+
+    /// Map a point from the corrected view back into the keystoned source.
+    fn distort(params: &PerspectiveParams, x: f64, y: f64) -> (f64, f64) {
+        let camera = Camera::new(W, H);
+        let r = rotation_matrix(
+            params.vertical.to_radians(),
+            params.horizontal.to_radians(),
+            params.rotate.to_radians(),
+        );
+        // Inverse of a rotation is its transpose
+        let rt = [
+            [r[0][0], r[1][0], r[2][0]],
+            [r[0][1], r[1][1], r[2][1]],
+            [r[0][2], r[1][2], r[2][2]],
+        ];
+        camera.map(&rt, x, y).unwrap()
+    }
+
+    /// Build a guide whose corrected version is the given axis-aligned line.
+    fn keystoned_guide(
+        truth: &PerspectiveParams,
+        (x1, y1): (f64, f64),
+        (x2, y2): (f64, f64),
+        target: GuideTarget,
+    ) -> GuideLine {
+        let (x1, y1) = distort(truth, x1, y1);
+        let (x2, y2) = distort(truth, x2, y2);
+        GuideLine {
+            x1,
+            y1,
+            x2,
+            y2,
+            target,
+        }
+    }
+
+    fn vertical(x: f64, y1: f64, y2: f64) -> Segment {
+        ((x, y1), (x, y2), GuideTarget::Vertical)
+    }
+
+    fn horizontal(y: f64, x1: f64, x2: f64) -> Segment {
+        ((x1, y), (x2, y), GuideTarget::Horizontal)
+    }
+
+    fn guides_for(truth: &PerspectiveParams, lines: &[Segment]) -> Vec<GuideLine> {
+        lines
+            .iter()
+            .map(|&(a, b, target)| keystoned_guide(truth, a, b, target))
+            .collect()
+    }
+
+    #[test]
+    fn test_two_vertical_guides_recover_tilt() {
+        let truth = PerspectiveParams::new(8.0, 0.0, 0.0);
+        let guides = guides_for(
+            &truth,
+            &[
+                vertical(600.0, 300.0, 1700.0),
+                vertical(2400.0, 200.0, 1800.0),
+            ],
+        );
+
+        // The keystoned guides lean toward each other at the top
+        assert!(guides[0].x1 > guides[0].x2);
+        assert!(guides[1].x1 < guides[1].x2);
+
+        let params = solve_guided_transform(&guides, W, H).unwrap();
+        assert!((params.vertical - 8.0).abs() < 0.5, "{:?}", params);
+        assert!(params.rotate.abs() < 0.5);
+        assert_eq!(params.horizontal, 0.0);
+        assert!(params.solved.vertical && params.solved.rotation);
+        assert!(!params.solved.horizontal);
+        assert!(params.residual_degrees < 0.01);
+    }
+
+    #[test]
+    fn test_recovers_tilt_and_rotation() {
+        let truth = PerspectiveParams::new(-6.0, 0.0, 2.0);
+        let guides = guides_for(
+            &truth,
+            &[
+                vertical(500.0, 400.0, 1600.0),
+                vertical(2500.0, 400.0, 1600.0),
+            ],
+        );
+
+        let params = solve_guided_transform(&guides, W, H).unwrap();
+        assert!((params.vertical + 6.0).abs() < 0.5, "{:?}", params);
+        assert!((params.rotate - 2.0).abs() < 0.5, "{:?}", params);
+    }
+
+    #[test]
+    fn test_full_solve_with_both_axes() {
+        let truth = PerspectiveParams::new(5.0, -4.0, 1.5);
+        let guides = guides_for(
+            &truth,
+            &[
+                vertical(500.0, 300.0, 1700.0),
+                vertical(2500.0, 300.0, 1700.0),
+                horizontal(1500.0, 400.0, 2600.0),
+            ],
+        );
+
+        let params = solve_guided_transform(&guides, W, H).unwrap();
+        assert!((params.vertical - 5.0).abs() < 0.5, "{:?}", params);
+        assert!((params.horizontal + 4.0).abs() < 0.5, "{:?}", params);
+        assert!((params.rotate - 1.5).abs() < 0.5, "{:?}", params);
+        assert_eq!(
+            params.solved,
+            SolvedAxes {
+                vertical: true,
+                horizontal: true,
+                rotation: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_single_guide_solves_only_its_axis() {
+        let truth = PerspectiveParams::new(0.0, 7.0, 0.0);
+        let guides = guides_for(&truth, &[horizontal(1200.0, 300.0, 2700.0)]);
+
+        let params = solve_guided_transform(&guides, W, H).unwrap();
+        assert_eq!(
+            params.solved,
+            SolvedAxes {
+                vertical: false,
+                horizontal: true,
+                rotation: false
+            }
+        );
+        assert_eq!(params.vertical, 0.0);
+        assert_eq!(params.rotate, 0.0);
+        assert!(params.residual_degrees < 0.01);
+
+        // The guide is horizontal after correction
+        let (_, y1) = params.map_point(guides[0].x1, guides[0].y1, W, H).unwrap();
+        let (_, y2) = params.map_point(guides[0].x2, guides[0].y2, W, H).unwrap();
+        assert!((y1 - y2).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_contradictory_guides_report_residual() {
+        // The same diagonal line cannot be both vertical and horizontal
+        let line = |target| GuideLine {
+            x1: 1000.0,
+            y1: 500.0,
+            x2: 2000.0,
+            y2: 1500.0,
+            target,
+        };
+        let guides = [line(GuideTarget::Vertical), line(GuideTarget::Horizontal)];
+
+        let params = solve_guided_transform(&guides, W, H).unwrap();
+        assert!(params.residual_degrees > 10.0, "{:?}", params);
+    }
+
+    #[test]
+    fn test_zero_length_guide_rejected() {
+        let guides = [
+            GuideLine {
+                x1: 100.0,
+                y1: 100.0,
+                x2: 100.0,
+                y2: 900.0,
+                target: GuideTarget::Vertical,
+            },
+            GuideLine {
+                x1: 500.0,
+                y1: 500.0,
+                x2: 500.0,
+                y2: 500.0,
+                target: GuideTarget::Vertical,
+            },
+        ];
+        assert!(matches!(
+            solve_guided_transform(&guides, W, H),
+            Err(TransformError::DegenerateGuide(1))
+        ));
+    }
+
+    #[test]
+    fn test_non_finite_guide_rejected() {
+        let guides = [GuideLine {
+            x1: f64::NAN,
+            y1: 0.0,
+            x2: 10.0,
+            y2: 10.0,
+            target: GuideTarget::Horizontal,
+        }];
+        assert!(matches!(
+            solve_guided_transform(&guides, W, H),
+            Err(TransformError::DegenerateGuide(0))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_input_errors() {
+        assert!(matches!(
+            solve_guided_transform(&[], W, H),
+            Err(TransformError::NoGuides)
+        ));
+        let guide = GuideLine {
+            x1: 0.0,
+            y1: 0.0,
+            x2: 0.0,
+            y2: 10.0,
+            target: GuideTarget::Vertical,
+        };
+        assert!(matches!(
+            solve_guided_transform(&[guide], 0, H),
+            Err(TransformError::InvalidDimensions { .. })
+        ));
+    }
+
+    #[test]
+    fn test_homography_matches_map_point() {
+        let params = PerspectiveParams::new(6.0, -3.0, 2.0);
+        let h = params.homography(W, H);
+        for (x, y) in [(0.0, 0.0), (1500.0, 1000.0), (2900.0, 150.0)] {
+            let w = h[2][0] * x + h[2][1] * y + h[2][2];
+            let hx = (h[0][0] * x + h[0][1] * y + h[0][2]) / w;
+            let hy = (h[1][0] * x + h[1][1] * y + h[1][2]) / w;
+            let (mx, my) = params.map_point(x, y, W, H).unwrap();
+            assert!((hx - mx).abs() < 1e-6 && (hy - my).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_identity_params() {
+        let h = PerspectiveParams::default().homography(W, H);
+        for (i, row) in h.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((value - expected).abs() < 1e-12);
+            }
+        }
+    }
+}
@@ -16,10 +16,13 @@
 //! src_y = (dst_x - cx) * sin(-θ) + (dst_y - cy) * cos(-θ) + src_cy
 //! ```
 
+use super::TransformError;
 use crate::decode::DecodedImage;
+use crate::validate::checked_buffer_len;
+use serde::{Deserialize, Serialize};
 
 /// Interpolation filter for rotation operations.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum InterpolationFilter {
     /// Fast bilinear interpolation - good for preview rendering.
     #[default]
@@ -111,57 +114,43 @@ pub fn compute_rotated_bounds(width: u32, height: u32, angle_degrees: f64) -> (u
 /// New `DecodedImage` with the rotated content. The dimensions may differ
 /// from the source due to canvas expansion.
 ///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions, or if the expanded canvas exceeds
+/// the image size limits.
+///
 /// # Example
 ///
 /// ```ignore
 /// use literoom_core::transform::{apply_rotation, InterpolationFilter};
 ///
-/// let rotated = apply_rotation(&image, 15.0, InterpolationFilter::Bilinear);
+/// let rotated = apply_rotation(&image, 15.0, InterpolationFilter::Bilinear)?;
 /// ```
 pub fn apply_rotation(
     image: &DecodedImage,
     angle_degrees: f64,
     filter: InterpolationFilter,
-) -> DecodedImage {
+) -> Result<DecodedImage, TransformError> {
+    image.validate()?;
+
     // Fast path: no rotation needed
-    if angle_degrees.abs() < 0.001 {
-        return image.clone();
+    if is_negligible_angle(angle_degrees) {
+        return Ok(image.clone());
     }
 
-    let (src_w, src_h) = (image.width as f64, image.height as f64);
-    let (dst_w, dst_h) = compute_rotated_bounds(image.width, image.height, angle_degrees);
-
-    // Negate angle for correct visual rotation direction
-    // (positive angle should rotate counter-clockwise visually)
-    let angle_rad = -angle_degrees.to_radians();
-    let cos = angle_rad.cos();
-    let sin = angle_rad.sin();
-
-    // Center of source and destination images
-    let src_cx = src_w / 2.0;
-    let src_cy = src_h / 2.0;
-    let dst_cx = dst_w as f64 / 2.0;
-    let dst_cy = dst_h as f64 / 2.0;
+    let inverse = InverseRotation::new(image.width, image.height, angle_degrees);
+    let (dst_w, dst_h) = inverse.output_size();
 
-    let mut output = vec![0u8; (dst_w * dst_h * 3) as usize];
+    let mut output = vec![0u8; checked_buffer_len(dst_w, dst_h, 3)?];
 
     for dst_y in 0..dst_h {
         for dst_x in 0..dst_w {
-            // Translate destination point to origin at center
-            let dx = dst_x as f64 - dst_cx;
-            let dy = dst_y as f64 - dst_cy;
-
-            // Apply inverse rotation to find source coordinates
-            let src_x = dx * cos - dy * sin + src_cx;
-            let src_y = dx * sin + dy * cos + src_cy;
-
+            let (src_x, src_y) = inverse.source_point(dst_x as f64, dst_y as f64);
             let dst_idx = ((dst_y * dst_w + dst_x) * 3) as usize;
 
             // Sample pixel using the specified interpolation
-            let pixel = match filter {
-                InterpolationFilter::Bilinear => sample_bilinear(image, src_x, src_y),
-                InterpolationFilter::Lanczos3 => sample_lanczos3(image, src_x, src_y),
-            };
+            let pixel = sample(image, src_x, src_y, filter);
 
             output[dst_idx] = pixel[0];
             output[dst_idx + 1] = pixel[1];
@@ -169,10 +158,155 @@ pub fn apply_rotation(
         }
     }
 
-    DecodedImage {
+    Ok(DecodedImage {
         width: dst_w,
         height: dst_h,
         pixels: output,
+    })
+}
+
+/// Map a point in the source image to the rotated image of [`apply_rotation`].
+///
+/// Coordinates are in pixels with integer values at pixel centers, as in
+/// the resampler: source pixel `(x, y)` ends up at the returned position on
+/// the expanded canvas. Use it to keep heal spots and masks placed on a
+/// straightened image attached to the same content when the angle changes:
+/// map them back with [`rotation_inverse_map`] at the old angle, then
+/// forward at the new one.
+///
+/// # Arguments
+///
+/// * `x`, `y` - Point in the source image
+/// * `width`, `height` - Source image size
+/// * `angle_degrees` - Rotation angle in degrees (positive = counter-clockwise)
+///
+/// # Example
+///
+/// ```
+/// use literoom_core::transform::{rotation_forward_map, rotation_inverse_map};
+///
+/// let (rx, ry) = rotation_forward_map(30.0, 10.0, 200, 100, 12.0);
+/// let (x, y) = rotation_inverse_map(rx, ry, 200, 100, 12.0);
+/// assert!((x - 30.0).abs() < 1e-9 && (y - 10.0).abs() < 1e-9);
+/// ```
+pub fn rotation_forward_map(
+    x: f64,
+    y: f64,
+    width: u32,
+    height: u32,
+    angle_degrees: f64,
+) -> (f64, f64) {
+    if is_negligible_angle(angle_degrees) {
+        return (x, y);
+    }
+    InverseRotation::new(width, height, angle_degrees).canvas_point(x, y)
+}
+
+/// Map a point in the rotated image of [`apply_rotation`] back to the
+/// source image.
+///
+/// The exact inverse of [`rotation_forward_map`], and the mapping the
+/// resampler uses to find the source of each output pixel. `width` and
+/// `height` are the size of the source image, not the rotated one.
+pub fn rotation_inverse_map(
+    x: f64,
+    y: f64,
+    width: u32,
+    height: u32,
+    angle_degrees: f64,
+) -> (f64, f64) {
+    if is_negligible_angle(angle_degrees) {
+        return (x, y);
+    }
+    InverseRotation::new(width, height, angle_degrees).source_point(x, y)
+}
+
+/// Whether [`apply_rotation`] treats `angle_degrees` as no rotation.
+#[inline]
+pub(crate) fn is_negligible_angle(angle_degrees: f64) -> bool {
+    angle_degrees.abs() < 0.001
+}
+
+/// Inverse mapping from the rotated canvas of [`apply_rotation`] to the source.
+///
+/// Shared with the region renderer, so a pixel rendered on its own lands on
+/// exactly the same source coordinates as in a full rotation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InverseRotation {
+    cos: f64,
+    sin: f64,
+    src_cx: f64,
+    src_cy: f64,
+    dst_cx: f64,
+    dst_cy: f64,
+    dst_w: u32,
+    dst_h: u32,
+}
+
+impl InverseRotation {
+    /// Mapping for rotating a `width x height` image by `angle_degrees`.
+    pub(crate) fn new(width: u32, height: u32, angle_degrees: f64) -> Self {
+        let (dst_w, dst_h) = compute_rotated_bounds(width, height, angle_degrees);
+
+        // Negate angle for correct visual rotation direction
+        // (positive angle should rotate counter-clockwise visually)
+        let angle_rad = -angle_degrees.to_radians();
+
+        // Center of source and destination images
+        Self {
+            cos: angle_rad.cos(),
+            sin: angle_rad.sin(),
+            src_cx: width as f64 / 2.0,
+            src_cy: height as f64 / 2.0,
+            dst_cx: dst_w as f64 / 2.0,
+            dst_cy: dst_h as f64 / 2.0,
+            dst_w,
+            dst_h,
+        }
+    }
+
+    /// Size of the expanded canvas.
+    pub(crate) fn output_size(&self) -> (u32, u32) {
+        (self.dst_w, self.dst_h)
+    }
+
+    /// Source coordinates of canvas pixel `(dst_x, dst_y)`.
+    #[inline]
+    pub(crate) fn source_point(&self, dst_x: f64, dst_y: f64) -> (f64, f64) {
+        // Translate destination point to origin at center
+        let dx = dst_x - self.dst_cx;
+        let dy = dst_y - self.dst_cy;
+
+        // Apply inverse rotation to find source coordinates
+        (
+            dx * self.cos - dy * self.sin + self.src_cx,
+            dx * self.sin + dy * self.cos + self.src_cy,
+        )
+    }
+
+    /// Canvas coordinates of source point `(src_x, src_y)`; the inverse of
+    /// [`source_point`](Self::source_point).
+    #[inline]
+    pub(crate) fn canvas_point(&self, src_x: f64, src_y: f64) -> (f64, f64) {
+        let dx = src_x - self.src_cx;
+        let dy = src_y - self.src_cy;
+
+        (
+            dx * self.cos + dy * self.sin + self.dst_cx,
+            -dx * self.sin + dy * self.cos + self.dst_cy,
+        )
+    }
+}
+
+/// Sample `image` at source coordinates with the given filter.
+///
+/// Reads at most one pixel beyond the sample point for bilinear and three
+/// for Lanczos3; points outside the image are black.
+#[inline]
+pub(crate) fn sample(image: &DecodedImage, x: f64, y: f64, filter: InterpolationFilter) -> [u8; 3] {
+    match filter {
+        InterpolationFilter::Bilinear => sample_bilinear(image, x, y),
+        InterpolationFilter::Lanczos3 => sample_lanczos3(image, x, y),
     }
 }
 
@@ -325,7 +459,7 @@ mod tests {
     #[test]
     fn test_no_rotation() {
         let img = test_image(100, 50);
-        let result = apply_rotation(&img, 0.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 0.0, InterpolationFilter::Bilinear).unwrap();
 
         assert_eq!(result.width, 100);
         assert_eq!(result.height, 50);
@@ -335,7 +469,7 @@ mod tests {
     #[test]
     fn test_tiny_rotation_fast_path() {
         let img = test_image(100, 50);
-        let result = apply_rotation(&img, 0.0001, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 0.0001, InterpolationFilter::Bilinear).unwrap();
 
         // Should hit fast path
         assert_eq!(result.width, 100);
@@ -376,7 +510,7 @@ mod tests {
     #[test]
     fn test_rotation_expands_canvas() {
         let img = test_image(100, 100);
-        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear).unwrap();
 
         // 45-degree rotation should expand the canvas
         assert!(result.width > img.width);
@@ -387,8 +521,8 @@ mod tests {
     fn test_bilinear_vs_lanczos() {
         let img = test_image(50, 50);
 
-        let bilinear = apply_rotation(&img, 15.0, InterpolationFilter::Bilinear);
-        let lanczos = apply_rotation(&img, 15.0, InterpolationFilter::Lanczos3);
+        let bilinear = apply_rotation(&img, 15.0, InterpolationFilter::Bilinear).unwrap();
+        let lanczos = apply_rotation(&img, 15.0, InterpolationFilter::Lanczos3).unwrap();
 
         // Both should produce same dimensions
         assert_eq!(bilinear.width, lanczos.width);
@@ -421,7 +555,7 @@ mod tests {
     fn test_small_image_rotation() {
         // Test that small images don't cause panics
         let img = test_image(4, 4);
-        let result = apply_rotation(&img, 30.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 30.0, InterpolationFilter::Bilinear).unwrap();
         assert!(result.width > 0);
         assert!(result.height > 0);
     }
@@ -430,7 +564,7 @@ mod tests {
     fn test_rectangular_image_rotation() {
         // Test non-square image
         let img = test_image(200, 100);
-        let result = apply_rotation(&img, 90.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 90.0, InterpolationFilter::Bilinear).unwrap();
 
         // After 90-degree rotation, dimensions should swap
         // (accounting for floating point in bounds calculation)
@@ -451,7 +585,7 @@ mod tests {
         let img = test_image(50, 50);
 
         // 360-degree rotation should give same dimensions
-        let result = apply_rotation(&img, 360.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 360.0, InterpolationFilter::Bilinear).unwrap();
         assert_eq!(result.width, img.width);
         assert_eq!(result.height, img.height);
     }
@@ -471,7 +605,7 @@ mod tests {
         let img = test_image(100, 100);
 
         // Negative rotation should work
-        let result = apply_rotation(&img, -45.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, -45.0, InterpolationFilter::Bilinear).unwrap();
 
         // Should expand canvas similarly to positive rotation
         assert!(result.width > img.width);
@@ -496,7 +630,7 @@ mod tests {
         let img = test_image(100, 100);
 
         // Very small rotation
-        let result = apply_rotation(&img, 1.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 1.0, InterpolationFilter::Bilinear).unwrap();
 
         // Should still expand slightly
         assert!(result.width >= img.width);
@@ -512,7 +646,7 @@ mod tests {
             pixels: vec![128, 128, 128],
         };
 
-        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear).unwrap();
         assert!(result.width >= 1);
         assert!(result.height >= 1);
     }
@@ -522,7 +656,7 @@ mod tests {
         // Very thin horizontal image
         let img = test_image(100, 1);
 
-        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear).unwrap();
 
         // Should produce reasonable dimensions
         assert!(result.width > 0);
@@ -534,7 +668,7 @@ mod tests {
         // Very narrow vertical image
         let img = test_image(1, 100);
 
-        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 45.0, InterpolationFilter::Bilinear).unwrap();
 
         assert!(result.width > 0);
         assert!(result.height > 0);
@@ -545,7 +679,7 @@ mod tests {
         let img = test_image(50, 50);
 
         // After rotation, the bounding box should contain all original pixels
-        let result = apply_rotation(&img, 30.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 30.0, InterpolationFilter::Bilinear).unwrap();
 
         // The rotated image should be larger than original
         // to contain all corners
@@ -580,7 +714,7 @@ mod tests {
         let img = test_image(8, 8);
 
         // Lanczos3 needs 6x6 neighborhood, so small images fall back
-        let result = apply_rotation(&img, 15.0, InterpolationFilter::Lanczos3);
+        let result = apply_rotation(&img, 15.0, InterpolationFilter::Lanczos3).unwrap();
 
         assert!(result.width > 0);
         assert!(result.height > 0);
@@ -591,7 +725,7 @@ mod tests {
     fn test_interpolation_produces_valid_pixels() {
         let img = test_image(50, 50);
 
-        let result = apply_rotation(&img, 37.0, InterpolationFilter::Lanczos3);
+        let result = apply_rotation(&img, 37.0, InterpolationFilter::Lanczos3).unwrap();
 
         // All pixels should be in valid range
         for pixel in &result.pixels {
@@ -625,7 +759,7 @@ mod tests {
         };
 
         // After 90 degree rotation, center region should still be center-ish
-        let result = apply_rotation(&img, 90.0, InterpolationFilter::Bilinear);
+        let result = apply_rotation(&img, 90.0, InterpolationFilter::Bilinear).unwrap();
 
         // Check a region around the center for bright values
         let center_x = result.width / 2;
@@ -664,4 +798,70 @@ mod tests {
             assert!(h > 0, "Height should be > 0 for angle {}", angle);
         }
     }
+
+    #[test]
+    fn test_forward_then_inverse_map_is_identity() {
+        for angle in [0.0, 0.0005, 7.5, -23.0, 90.0, 135.0, 180.0, -270.0] {
+            for (x, y) in [(0.0, 0.0), (12.0, 9.0), (63.5, 2.25), (99.0, 49.0)] {
+                let (rx, ry) = rotation_forward_map(x, y, 100, 50, angle);
+                let (sx, sy) = rotation_inverse_map(rx, ry, 100, 50, angle);
+                assert!(
+                    (sx - x).abs() < 1e-4 && (sy - y).abs() < 1e-4,
+                    "angle {}: ({}, {}) came back as ({}, {})",
+                    angle,
+                    x,
+                    y,
+                    sx,
+                    sy
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverse_map_matches_resampler() {
+        let inverse = InverseRotation::new(64, 40, 17.0);
+        assert_eq!(
+            rotation_inverse_map(20.0, 31.0, 64, 40, 17.0),
+            inverse.source_point(20.0, 31.0)
+        );
+    }
+
+    #[test]
+    fn test_forward_map_predicts_bright_pixel_position() {
+        let (width, height) = (41u32, 31u32);
+        let (px, py) = (12u32, 9u32);
+        let mut pixels = vec![0u8; (width * height * 3) as usize];
+        let idx = ((py * width + px) * 3) as usize;
+        pixels[idx..idx + 3].copy_from_slice(&[255, 255, 255]);
+        let img = DecodedImage {
+            width,
+            height,
+            pixels,
+        };
+
+        for angle in [20.0, -35.0, 90.0, 150.0] {
+            let rotated = apply_rotation(&img, angle, InterpolationFilter::Bilinear).unwrap();
+            let (fx, fy) = rotation_forward_map(px as f64, py as f64, width, height, angle);
+
+            // Brightness-weighted centroid of the output
+            let (mut sum, mut sum_x, mut sum_y) = (0.0, 0.0, 0.0);
+            for (i, p) in rotated.pixels.chunks_exact(3).enumerate() {
+                let v = p[0] as f64;
+                sum += v;
+                sum_x += v * (i as u32 % rotated.width) as f64;
+                sum_y += v * (i as u32 / rotated.width) as f64;
+            }
+            let (cx, cy) = (sum_x / sum, sum_y / sum);
+            assert!(
+                (cx - fx).abs() < 0.35 && (cy - fy).abs() < 0.35,
+                "angle {}: predicted ({}, {}), found ({}, {})",
+                angle,
+                fx,
+                fy,
+                cx,
+                cy
+            );
+        }
+    }
 }
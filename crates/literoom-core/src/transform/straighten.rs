@@ -0,0 +1,271 @@
+//! Automatic straightening: tilt detection and the matching crop.
+//!
+//! [`detect_straighten_angle`] finds the rotation that levels an image from
+//! the direction of its strong edges. Man-made scenes and horizons are
+//! dominated by edges that should be horizontal or vertical, so a slightly
+//! tilted photo shows a peak in edge directions a few degrees off the axes.
+//! [`compute_rotation_auto_crop`] gives the crop that removes the empty
+//! corners the rotation leaves.
+
+use super::rotation::compute_rotated_bounds;
+use super::TransformError;
+use crate::decode::DecodedImage;
+use serde::{Deserialize, Serialize};
+
+/// Largest tilt considered, in degrees. Edges further from the axes are
+/// treated as diagonal content rather than tilt.
+pub const MAX_STRAIGHTEN_DEGREES: f32 = 10.0;
+
+/// Histogram bin width for edge directions, in degrees.
+const BIN_DEGREES: f32 = 0.1;
+
+/// Edges with a gradient below this fraction of the strongest are ignored.
+const EDGE_THRESHOLD: f32 = 0.1;
+
+/// Edges within this many degrees of the peak count towards confidence.
+const PEAK_WINDOW_DEGREES: f32 = 1.0;
+
+/// Half-width of the window the peak angle is refined over, in degrees.
+const REFINE_WINDOW_DEGREES: f32 = 0.2;
+
+/// Detected tilt of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StraightenEstimate {
+    /// Rotation that levels the image, in degrees (positive =
+    /// counter-clockwise, as in [`apply_rotation`](super::apply_rotation))
+    pub angle: f32,
+    /// Share of edge strength aligned with the detected angle (0.0 to 1.0)
+    pub confidence: f32,
+}
+
+/// Estimate the rotation that levels an image.
+///
+/// Edge directions are measured with a Sobel filter on luminance. Strong
+/// edges within [`MAX_STRAIGHTEN_DEGREES`] of horizontal or vertical vote
+/// for their deviation from the axis, weighted by strength, and the peak
+/// wins. Confidence is the share of all strong edge strength within 1 degree
+/// of the peak: high for scenes with straight lines, low for foliage or
+/// noise, where edges point every way.
+///
+/// # Returns
+///
+/// `None` if the image has no strong edges (too small or flat).
+///
+/// # Errors
+///
+/// Returns `TransformError::InvalidImage` if the image is empty or its
+/// buffer does not match its dimensions.
+pub fn detect_straighten_angle(
+    image: &DecodedImage,
+) -> Result<Option<StraightenEstimate>, TransformError> {
+    image.validate()?;
+    let (width, height) = (image.width as usize, image.height as usize);
+    if width < 3 || height < 3 {
+        return Ok(None);
+    }
+
+    let luma: Vec<f32> = image
+        .pixels
+        .chunks_exact(3)
+        .map(|p| 0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32)
+        .collect();
+    let at = |x: usize, y: usize| luma[y * width + x];
+
+    let mut gradients = Vec::with_capacity((width - 2) * (height - 2));
+    let mut max_magnitude = 0.0f32;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2.0 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            let magnitude = gx.hypot(gy);
+            max_magnitude = max_magnitude.max(magnitude);
+            gradients.push((gx, gy, magnitude));
+        }
+    }
+    if max_magnitude <= 0.0 {
+        return Ok(None);
+    }
+
+    let bins = (2.0 * MAX_STRAIGHTEN_DEGREES / BIN_DEGREES).round() as usize + 1;
+    let mut histogram = vec![0.0f64; bins];
+    let mut total = 0.0f64;
+    for &(gx, gy, magnitude) in &gradients {
+        if magnitude < EDGE_THRESHOLD * max_magnitude {
+            continue;
+        }
+        total += magnitude as f64;
+        // Deviation of the edge direction from the nearest axis, measured
+        // the way `apply_rotation` turns pixel coordinates: an image rotated
+        // by `t` has its edges deviate by `t`, and rotating by `-t` levels it.
+        let edge = gy.atan2(gx).to_degrees() + 90.0;
+        let deviation = (edge + 45.0).rem_euclid(90.0) - 45.0;
+        if deviation.abs() <= MAX_STRAIGHTEN_DEGREES {
+            let bin = ((deviation + MAX_STRAIGHTEN_DEGREES) / BIN_DEGREES).round() as usize;
+            histogram[bin.min(bins - 1)] += magnitude as f64;
+        }
+    }
+    if total <= 0.0 {
+        return Ok(None);
+    }
+
+    let window_sum = |center: usize, degrees: f32| -> f64 {
+        let half = (degrees / BIN_DEGREES).round() as usize;
+        histogram[center.saturating_sub(half)..(center + half + 1).min(bins)]
+            .iter()
+            .sum()
+    };
+    let peak = (0..bins)
+        .max_by(|&a, &b| {
+            window_sum(a, REFINE_WINDOW_DEGREES).total_cmp(&window_sum(b, REFINE_WINDOW_DEGREES))
+        })
+        .unwrap_or(0);
+
+    // Refine to the weighted mean of the bins next to the peak. Staircase
+    // edges spread a shoulder towards the axis, so wider windows bias the
+    // angle low.
+    let half = (REFINE_WINDOW_DEGREES / BIN_DEGREES).round() as usize;
+    let weighted: f64 = (peak.saturating_sub(half)..(peak + half + 1).min(bins))
+        .map(|bin| bin as f64 * histogram[bin])
+        .sum();
+    let mean_bin = weighted / window_sum(peak, REFINE_WINDOW_DEGREES).max(f64::EPSILON);
+    let deviation = mean_bin as f32 * BIN_DEGREES - MAX_STRAIGHTEN_DEGREES;
+
+    Ok(Some(StraightenEstimate {
+        angle: -deviation,
+        confidence: (window_sum(peak, PEAK_WINDOW_DEGREES) / total) as f32,
+    }))
+}
+
+/// Crop that removes the empty corners left by rotating an image.
+///
+/// The crop is the largest one centered on the rotated canvas with the
+/// source's aspect ratio and no fill inside it.
+///
+/// # Arguments
+///
+/// * `width` - Source image width
+/// * `height` - Source image height
+/// * `angle_degrees` - Rotation angle in degrees (positive = counter-clockwise)
+///
+/// # Returns
+///
+/// `(left, top, width, height)` normalized (0.0 to 1.0) to the rotated
+/// canvas of [`compute_rotated_bounds`], as used for the edit state's crop.
+pub fn compute_rotation_auto_crop(
+    width: u32,
+    height: u32,
+    angle_degrees: f64,
+) -> (f64, f64, f64, f64) {
+    let (sin, cos) = angle_degrees.to_radians().sin_cos();
+    let (sin, cos) = (sin.abs(), cos.abs());
+    let (half_w, half_h) = (width as f64 / 2.0, height as f64 / 2.0);
+
+    // Scale of the source rect whose corners touch the rotated edges
+    let scale = (half_w / (half_w * cos + half_h * sin))
+        .min(half_h / (half_w * sin + half_h * cos))
+        .min(1.0);
+
+    let (canvas_w, canvas_h) = compute_rotated_bounds(width, height, angle_degrees);
+    let crop_w = (scale * width as f64 / canvas_w as f64).min(1.0);
+    let crop_h = (scale * height as f64 / canvas_h as f64).min(1.0);
+    ((1.0 - crop_w) / 2.0, (1.0 - crop_h) / 2.0, crop_w, crop_h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{checkerboard, noise};
+    use crate::transform::{apply_crop, apply_rotation, InterpolationFilter};
+
+    /// A checkerboard tilted by `angle`, cropped so no fill shows.
+    fn tilted(angle: f64) -> DecodedImage {
+        let rotated = apply_rotation(
+            &checkerboard(200, 150, 25),
+            angle,
+            InterpolationFilter::Bilinear,
+        )
+        .unwrap();
+        apply_crop(&rotated, 0.15, 0.15, 0.7, 0.7).unwrap()
+    }
+
+    #[test]
+    fn test_detects_tilt_in_both_directions() {
+        for tilt in [3.0, -2.0, 5.0] {
+            let estimate = detect_straighten_angle(&tilted(tilt)).unwrap().unwrap();
+            assert!(
+                (estimate.angle + tilt as f32).abs() < 0.3,
+                "tilt {}: got {:?}",
+                tilt,
+                estimate
+            );
+            assert!(estimate.confidence > 0.5, "{:?}", estimate);
+        }
+    }
+
+    #[test]
+    fn test_level_image_needs_no_rotation() {
+        let estimate = detect_straighten_angle(&checkerboard(120, 90, 15))
+            .unwrap()
+            .unwrap();
+        assert!(estimate.angle.abs() < 0.1, "{:?}", estimate);
+    }
+
+    #[test]
+    fn test_noise_has_low_confidence() {
+        let estimate = detect_straighten_angle(&noise(120, 90, 5))
+            .unwrap()
+            .unwrap();
+        assert!(estimate.confidence < 0.2, "{:?}", estimate);
+    }
+
+    #[test]
+    fn test_flat_image_has_no_estimate() {
+        let flat = DecodedImage::new(20, 20, vec![90; 20 * 20 * 3]);
+        assert_eq!(detect_straighten_angle(&flat).unwrap(), None);
+    }
+
+    #[test]
+    fn test_auto_crop_excludes_fill() {
+        let (width, height) = (160u32, 100u32);
+        let white = DecodedImage::new(width, height, vec![255; (width * height * 3) as usize]);
+        for angle in [0.0, 4.0, -7.5, 30.0] {
+            let rotated = apply_rotation(&white, angle, InterpolationFilter::Bilinear).unwrap();
+            let (left, top, w, h) = compute_rotation_auto_crop(width, height, angle);
+            // Keep the source aspect ratio
+            let aspect = (w * rotated.width as f64) / (h * rotated.height as f64);
+            assert!(
+                (aspect - 1.6).abs() < 0.02,
+                "angle {}: aspect {}",
+                angle,
+                aspect
+            );
+
+            // Shrink by a pixel so bilinear edge blending stays outside
+            let inset_x = 1.0 / rotated.width as f64;
+            let inset_y = 1.0 / rotated.height as f64;
+            let cropped = apply_crop(
+                &rotated,
+                left + inset_x,
+                top + inset_y,
+                w - 2.0 * inset_x,
+                h - 2.0 * inset_y,
+            )
+            .unwrap();
+            assert!(
+                cropped.pixels.iter().all(|&v| v == 255),
+                "angle {}: fill inside crop",
+                angle
+            );
+        }
+        assert_eq!(
+            compute_rotation_auto_crop(width, height, 0.0),
+            (0.0, 0.0, 1.0, 1.0)
+        );
+    }
+}
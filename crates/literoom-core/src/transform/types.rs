@@ -0,0 +1,40 @@
+//! Core types for transform operations.
+
+use thiserror::Error;
+
+/// Errors that can occur while solving or applying transforms.
+#[derive(Debug, Error)]
+pub enum TransformError {
+    /// The image has zero width or height.
+    #[error("Invalid image dimensions: {width}x{height}")]
+    InvalidDimensions { width: u32, height: u32 },
+
+    /// No guide lines were supplied.
+    #[error("At least one guide line is required")]
+    NoGuides,
+
+    /// A guide line has (near) zero length or non-finite coordinates.
+    #[error("Guide line {0} is degenerate (zero length or non-finite)")]
+    DegenerateGuide(usize),
+
+    /// A render region has a non-positive or non-finite size or position.
+    #[error("Render region must have a finite, positive size")]
+    InvalidRegion,
+
+    /// The source image is empty or its buffer does not match its dimensions.
+    #[error(transparent)]
+    InvalidImage(#[from] crate::validate::ImageError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_error_display() {
+        assert_eq!(
+            TransformError::DegenerateGuide(2).to_string(),
+            "Guide line 2 is degenerate (zero length or non-finite)"
+        );
+    }
+}
@@ -0,0 +1,665 @@
+//! Shared validation for image dimensions and pixel buffers.
+//!
+//! Every public operation that takes image dimensions checks its input with
+//! these helpers, so degenerate inputs behave the same everywhere:
+//!
+//! - **Zero width or height**: rejected with [`ImageError::EmptyImage`]
+//! - **1-pixel images** (1×1, 1×N, N×1): always valid
+//! - **Width or height above [`MAX_IMAGE_DIMENSION`]**: rejected with
+//!   [`ImageError::TooLarge`]
+//! - **More than [`MAX_PIXELS`] pixels**: rejected with
+//!   [`ImageError::DimensionOverflow`]
+//! - **Buffer length not `width * height * 3`** (or `* 4` for RGBA): rejected
+//!   with [`ImageError::BufferSizeMismatch`]. Float buffers are checked the
+//!   same way, with sizes reported in bytes.
+//!
+//! # Buffer Sizes
+//!
+//! WASM has a 32-bit `usize`, so `width * height * 3` for two in-range
+//! dimensions can still wrap. Every allocation sized from dimensions goes
+//! through [`checked_pixel_count`] or [`checked_buffer_len`], which check the
+//! dimensions and do the arithmetic with overflow checks. Within
+//! [`MAX_PIXELS`] the byte length of any supported pixel format, float RGB
+//! included, fits in a 32-bit `usize`.
+//!
+//! Operations that only take a pixel slice (adjustments, tone curve) have no
+//! dimensions to check. They process every complete RGB triple, leave any
+//! trailing bytes untouched, and treat an empty slice as a no-op.
+
+use thiserror::Error;
+
+/// Largest width or height any operation accepts, in pixels.
+///
+/// This is the JPEG format's limit, so anything the pipeline accepts can
+/// also be exported.
+pub const MAX_IMAGE_DIMENSION: u32 = 65_535;
+
+/// Largest number of pixels any operation accepts or produces (256
+/// megapixels).
+///
+/// Float RGB at this size is 3 GiB, just inside a 32-bit address space.
+pub const MAX_PIXELS: u64 = 1 << 28;
+
+/// Invalid image dimensions or pixel buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ImageError {
+    /// The image has zero width or height.
+    #[error("Image has no pixels: {width}x{height}")]
+    EmptyImage { width: u32, height: u32 },
+
+    /// The width or height is above [`MAX_IMAGE_DIMENSION`].
+    #[error("Image of {width}x{height} exceeds the maximum dimension of {max}")]
+    TooLarge { width: u32, height: u32, max: u32 },
+
+    /// The pixel count is above [`MAX_PIXELS`], so buffer sizes could overflow.
+    #[error("Image of {width}x{height} exceeds the maximum of {max_pixels} pixels")]
+    DimensionOverflow {
+        width: u32,
+        height: u32,
+        max_pixels: u64,
+    },
+
+    /// The pixel buffer length does not match the dimensions.
+    #[error("Pixel buffer has {actual} bytes, expected {expected} for {width}x{height}")]
+    BufferSizeMismatch {
+        width: u32,
+        height: u32,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl ImageError {
+    /// Stable identifier for the error kind, for callers across the WASM boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ImageError::EmptyImage { .. } => "EMPTY_IMAGE",
+            ImageError::TooLarge { .. } => "IMAGE_TOO_LARGE",
+            ImageError::DimensionOverflow { .. } => "DIMENSION_OVERFLOW",
+            ImageError::BufferSizeMismatch { .. } => "BUFFER_SIZE_MISMATCH",
+        }
+    }
+}
+
+/// Check that dimensions describe at least one pixel and are within
+/// [`MAX_IMAGE_DIMENSION`] and [`MAX_PIXELS`].
+pub fn validate_dimensions(width: u32, height: u32) -> Result<(), ImageError> {
+    if width == 0 || height == 0 {
+        return Err(ImageError::EmptyImage { width, height });
+    }
+    if width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION {
+        return Err(ImageError::TooLarge {
+            width,
+            height,
+            max: MAX_IMAGE_DIMENSION,
+        });
+    }
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(ImageError::DimensionOverflow {
+            width,
+            height,
+            max_pixels: MAX_PIXELS,
+        });
+    }
+    Ok(())
+}
+
+/// Number of pixels in a `width x height` image, after checking the
+/// dimensions with [`validate_dimensions`].
+pub fn checked_pixel_count(width: u32, height: u32) -> Result<usize, ImageError> {
+    checked_buffer_len(width, height, 1)
+}
+
+/// Length of a buffer holding `width x height` pixels of `values_per_pixel`
+/// elements each (3 for RGB, 4 for RGBA), after checking the dimensions with
+/// [`validate_dimensions`].
+///
+/// # Errors
+///
+/// Returns the [`validate_dimensions`] errors, or
+/// [`ImageError::DimensionOverflow`] if the length does not fit in `usize`.
+pub fn checked_buffer_len(
+    width: u32,
+    height: u32,
+    values_per_pixel: usize,
+) -> Result<usize, ImageError> {
+    validate_dimensions(width, height)?;
+    usize::try_from(width)
+        .ok()
+        .and_then(|w| w.checked_mul(usize::try_from(height).ok()?))
+        .and_then(|n| n.checked_mul(values_per_pixel))
+        .ok_or(ImageError::DimensionOverflow {
+            width,
+            height,
+            max_pixels: MAX_PIXELS,
+        })
+}
+
+/// Check that an RGB buffer is non-empty and matches its dimensions.
+pub fn validate_rgb_buffer(pixels: &[u8], width: u32, height: u32) -> Result<(), ImageError> {
+    validate_buffer(pixels.len(), width, height, 3)
+}
+
+/// Check that a single-channel buffer is non-empty and matches its dimensions.
+pub fn validate_gray_buffer(pixels: &[u8], width: u32, height: u32) -> Result<(), ImageError> {
+    validate_buffer(pixels.len(), width, height, 1)
+}
+
+/// Check that an RGBA buffer is non-empty and matches its dimensions.
+pub fn validate_rgba_buffer(pixels: &[u8], width: u32, height: u32) -> Result<(), ImageError> {
+    validate_buffer(pixels.len(), width, height, 4)
+}
+
+/// Check that a float RGB buffer is non-empty and matches its dimensions.
+///
+/// Sizes in the error are in bytes (4 per value), like the 8-bit checks.
+pub fn validate_rgb_f32_buffer(pixels: &[f32], width: u32, height: u32) -> Result<(), ImageError> {
+    validate_buffer(std::mem::size_of_val(pixels), width, height, 12)
+}
+
+/// Check that a buffer of `byte_len` bytes holds exactly `width x height`
+/// pixels of `bytes_per_pixel` bytes each.
+pub(crate) fn validate_buffer(
+    byte_len: usize,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: usize,
+) -> Result<(), ImageError> {
+    validate_dimensions(width, height)?;
+
+    let expected = checked_buffer_len(width, height, bytes_per_pixel)?;
+    if expected != byte_len {
+        return Err(ImageError::BufferSizeMismatch {
+            width,
+            height,
+            expected,
+            actual: byte_len,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_dimensions_rejected() {
+        for (w, h) in [(0, 0), (0, 5), (5, 0)] {
+            assert_eq!(
+                validate_dimensions(w, h),
+                Err(ImageError::EmptyImage {
+                    width: w,
+                    height: h
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_pixel_valid() {
+        assert!(validate_rgb_buffer(&[1, 2, 3], 1, 1).is_ok());
+        assert!(validate_rgb_buffer(&[0; 12], 1, 4).is_ok());
+        assert!(validate_rgb_buffer(&[0; 12], 4, 1).is_ok());
+    }
+
+    #[test]
+    fn test_buffer_mismatch() {
+        let err = validate_rgb_buffer(&[0; 11], 2, 2).unwrap_err();
+        assert_eq!(
+            err,
+            ImageError::BufferSizeMismatch {
+                width: 2,
+                height: 2,
+                expected: 12,
+                actual: 11
+            }
+        );
+        assert_eq!(err.code(), "BUFFER_SIZE_MISMATCH");
+    }
+
+    #[test]
+    fn test_rgba_buffer() {
+        assert!(validate_rgba_buffer(&[0; 16], 2, 2).is_ok());
+        assert!(matches!(
+            validate_rgba_buffer(&[0; 12], 2, 2),
+            Err(ImageError::BufferSizeMismatch { expected: 16, .. })
+        ));
+    }
+
+    #[test]
+    fn test_empty_buffer_with_dimensions() {
+        assert!(matches!(
+            validate_rgb_buffer(&[], 10, 10),
+            Err(ImageError::BufferSizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_overflowing_dimensions() {
+        assert!(validate_rgb_buffer(&[0; 3], u32::MAX, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_max_dimension_enforced() {
+        assert!(validate_dimensions(MAX_IMAGE_DIMENSION, 1).is_ok());
+        assert!(validate_dimensions(1, MAX_IMAGE_DIMENSION).is_ok());
+
+        let err = validate_dimensions(MAX_IMAGE_DIMENSION + 1, 10).unwrap_err();
+        assert_eq!(
+            err,
+            ImageError::TooLarge {
+                width: MAX_IMAGE_DIMENSION + 1,
+                height: 10,
+                max: MAX_IMAGE_DIMENSION
+            }
+        );
+        assert_eq!(err.code(), "IMAGE_TOO_LARGE");
+        assert!(matches!(
+            validate_rgb_buffer(&[], 1, MAX_IMAGE_DIMENSION + 1),
+            Err(ImageError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pixel_limit_enforced() {
+        // 16384 x 16384 is exactly MAX_PIXELS
+        assert!(validate_dimensions(16_384, 16_384).is_ok());
+
+        let err = validate_dimensions(16_384, 16_385).unwrap_err();
+        assert_eq!(
+            err,
+            ImageError::DimensionOverflow {
+                width: 16_384,
+                height: 16_385,
+                max_pixels: MAX_PIXELS
+            }
+        );
+        assert_eq!(err.code(), "DIMENSION_OVERFLOW");
+        assert!(matches!(
+            validate_dimensions(MAX_IMAGE_DIMENSION, MAX_IMAGE_DIMENSION),
+            Err(ImageError::DimensionOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn test_checked_buffer_len() {
+        assert_eq!(checked_buffer_len(4, 3, 3), Ok(36));
+        assert_eq!(checked_buffer_len(4, 3, 4), Ok(48));
+        assert_eq!(checked_pixel_count(4, 3), Ok(12));
+
+        assert!(matches!(
+            checked_buffer_len(0, 3, 3),
+            Err(ImageError::EmptyImage { .. })
+        ));
+        assert!(matches!(
+            checked_buffer_len(u32::MAX, u32::MAX, 3),
+            Err(ImageError::TooLarge { .. })
+        ));
+        assert!(matches!(
+            checked_buffer_len(16_384, 16_384, usize::MAX),
+            Err(ImageError::DimensionOverflow { .. })
+        ));
+    }
+}
+
+/// Extreme dimensions fed to every public entry point must come back as
+/// errors: no panics, no wrapped sizes and no attempt to allocate them.
+#[cfg(test)]
+mod extreme_dimensions {
+    use crate::compose::{add_border, compose_contact_sheet, pad_to_aspect, Gravity};
+    use crate::decode::{
+        extract_region_scaled, generate_thumbnail, resize, resize_to_fit, DecodedImage, FilterType,
+    };
+    use crate::edit::{CropRect, EditState};
+    use crate::encode::encode_jpeg;
+    use crate::histogram::compute_histogram;
+    use crate::render::{render_region, RenderRegion};
+    use crate::transform::{apply_crop, apply_crop_with_fill, apply_rotation, InterpolationFilter};
+
+    use super::{checked_buffer_len, MAX_IMAGE_DIMENSION};
+
+    /// Declared sizes far beyond what the buffers hold
+    const DIMENSIONS: [(u32, u32); 6] = [
+        (u32::MAX, u32::MAX),
+        (u32::MAX, 1),
+        (1, u32::MAX),
+        (MAX_IMAGE_DIMENSION, MAX_IMAGE_DIMENSION),
+        (MAX_IMAGE_DIMENSION, 4_097),
+        (1 << 16, 1 << 14),
+    ];
+
+    const WHOLE: CropRect = CropRect {
+        left: 0.0,
+        top: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    /// An image claiming `width x height` with only a few pixels behind it.
+    fn claimed(width: u32, height: u32) -> DecodedImage {
+        DecodedImage {
+            width,
+            height,
+            pixels: vec![128; 12],
+        }
+    }
+
+    fn small() -> DecodedImage {
+        DecodedImage::new(2, 2, vec![128; 12])
+    }
+
+    #[test]
+    fn test_source_dimensions() {
+        let region = RenderRegion {
+            x: 0.0,
+            y: 0.0,
+            width: 2.0,
+            height: 2.0,
+        };
+        for (w, h) in DIMENSIONS {
+            let img = claimed(w, h);
+            let label = format!("{}x{}", w, h);
+            assert!(checked_buffer_len(w, h, 3).is_err(), "{}", label);
+            assert!(img.validate().is_err(), "{}", label);
+
+            assert!(
+                resize(&img, 2, 2, FilterType::Bilinear).is_err(),
+                "{}",
+                label
+            );
+            assert!(
+                resize_to_fit(&img, 2, FilterType::Bilinear).is_err(),
+                "{}",
+                label
+            );
+            assert!(generate_thumbnail(&img, 2).is_err(), "{}", label);
+            let scaled = extract_region_scaled(&img, &WHOLE, 2, 2, FilterType::Bilinear);
+            assert!(scaled.is_err(), "{}", label);
+
+            assert!(
+                apply_rotation(&img, 30.0, InterpolationFilter::Bilinear).is_err(),
+                "{}",
+                label
+            );
+            assert!(apply_crop(&img, 0.0, 0.0, 0.5, 0.5).is_err(), "{}", label);
+            let filled = apply_crop_with_fill(&img, -0.1, -0.1, 1.2, 1.2, [0, 0, 0]);
+            assert!(filled.is_err(), "{}", label);
+            let state = EditState::default();
+            let rendered =
+                render_region(&img, &state, &region, 2, 2, InterpolationFilter::Bilinear);
+            assert!(rendered.is_err(), "{}", label);
+
+            assert!(compute_histogram(&img.pixels, w, h).is_err(), "{}", label);
+            assert!(encode_jpeg(&img.pixels, w, h, 90).is_err(), "{}", label);
+            assert!(add_border(&img, 1, (0, 0, 0)).is_err(), "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_output_dimensions() {
+        let img = small();
+        let region = RenderRegion {
+            x: 0.0,
+            y: 0.0,
+            width: 2.0,
+            height: 2.0,
+        };
+        for (w, h) in DIMENSIONS {
+            let label = format!("{}x{}", w, h);
+            assert!(
+                resize(&img, w, h, FilterType::Nearest).is_err(),
+                "{}",
+                label
+            );
+            let scaled = extract_region_scaled(&img, &WHOLE, w, h, FilterType::Nearest);
+            assert!(scaled.is_err(), "{}", label);
+            // Normalized to the 2x2 source, so the output is w x h
+            let (nw, nh) = (w as f64 / 2.0, h as f64 / 2.0);
+            let filled = apply_crop_with_fill(&img, 0.0, 0.0, nw, nh, [0, 0, 0]);
+            assert!(filled.is_err(), "{}", label);
+            let state = EditState::default();
+            let rendered =
+                render_region(&img, &state, &region, w, h, InterpolationFilter::Bilinear);
+            assert!(rendered.is_err(), "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_composed_canvas_too_large() {
+        let img = small();
+        assert!(add_border(&img, u32::MAX / 4, (0, 0, 0)).is_err());
+        assert!(add_border(&img, MAX_IMAGE_DIMENSION / 2, (0, 0, 0)).is_err());
+        assert!(pad_to_aspect(&img, 1e9, (0, 0, 0), Gravity::Center).is_err());
+        assert!(pad_to_aspect(&img, 1e-9, (0, 0, 0), Gravity::Center).is_err());
+        let images = [img];
+        assert!(compose_contact_sheet(&images, 1, u32::MAX, 0, (0, 0, 0)).is_err());
+        assert!(compose_contact_sheet(&images, u32::MAX, 1 << 12, 0, (0, 0, 0)).is_err());
+
+        let pixel = DecodedImage::new(1, 1, vec![128; 3]);
+        assert!(apply_crop_with_fill(&pixel, 0.0, 0.0, 1e9, 1.0, [0, 0, 0]).is_err());
+        assert!(apply_crop_with_fill(&pixel, 0.0, 0.0, 1e5, 1e5, [0, 0, 0]).is_err());
+    }
+}
+
+/// Every public operation against the degenerate input matrix.
+#[cfg(test)]
+mod degenerate_inputs {
+    use crate::adjustments::apply_all_adjustments;
+    use crate::curve::{apply_tone_curve, ToneCurveLut};
+    use crate::decode::{
+        generate_thumbnail, resize, resize_to_fit, DecodeError, DecodedImage, FilterType,
+    };
+    use crate::histogram::compute_histogram;
+    use crate::mask::{
+        apply_masked_adjustments, apply_masked_adjustments_with_curves, LinearGradientMask,
+        RadialGradientMask,
+    };
+    use crate::transform::{apply_crop, apply_rotation, InterpolationFilter, TransformError};
+    use crate::{BasicAdjustments, CurvePoint, ToneCurve};
+
+    use super::{ImageError, MAX_IMAGE_DIMENSION};
+
+    /// Expected outcome for an input case.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Expect {
+        Ok,
+        Empty,
+        TooLarge,
+        Mismatch,
+    }
+
+    /// (label, width, height, buffer length, expected outcome)
+    const CASES: [(&str, u32, u32, usize, Expect); 9] = [
+        ("0x0", 0, 0, 0, Expect::Empty),
+        ("0xN", 0, 7, 0, Expect::Empty),
+        ("Nx0", 7, 0, 0, Expect::Empty),
+        ("1x1", 1, 1, 3, Expect::Ok),
+        ("1xN", 1, 7, 21, Expect::Ok),
+        ("Nx1", 7, 1, 21, Expect::Ok),
+        ("short buffer", 4, 4, 45, Expect::Mismatch),
+        ("long buffer", 4, 4, 51, Expect::Mismatch),
+        (
+            "too wide",
+            MAX_IMAGE_DIMENSION + 1,
+            1,
+            (MAX_IMAGE_DIMENSION as usize + 1) * 3,
+            Expect::TooLarge,
+        ),
+    ];
+
+    fn image(width: u32, height: u32, len: usize) -> DecodedImage {
+        let pixels = (0..len).map(|i| (i * 37 % 256) as u8).collect();
+        DecodedImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn outcome<T>(result: Result<T, ImageError>) -> Expect {
+        match result {
+            Ok(_) => Expect::Ok,
+            Err(ImageError::EmptyImage { .. }) => Expect::Empty,
+            Err(ImageError::TooLarge { .. }) => Expect::TooLarge,
+            Err(ImageError::DimensionOverflow { .. }) => Expect::TooLarge,
+            Err(ImageError::BufferSizeMismatch { .. }) => Expect::Mismatch,
+        }
+    }
+
+    fn decode_outcome<T>(result: Result<T, DecodeError>) -> Expect {
+        match result {
+            Err(DecodeError::InvalidImage(e)) => outcome::<()>(Err(e)),
+            Err(e) => panic!("unexpected decode error: {}", e),
+            Ok(_) => Expect::Ok,
+        }
+    }
+
+    fn transform_outcome(result: Result<DecodedImage, TransformError>) -> Expect {
+        match result {
+            Err(TransformError::InvalidImage(e)) => outcome::<()>(Err(e)),
+            Err(e) => panic!("unexpected transform error: {}", e),
+            Ok(out) => {
+                assert!(out.validate().is_ok(), "output must be a valid image");
+                Expect::Ok
+            }
+        }
+    }
+
+    fn exposure() -> BasicAdjustments {
+        BasicAdjustments {
+            exposure: 1.0,
+            ..Default::default()
+        }
+    }
+
+    fn s_curve() -> ToneCurveLut {
+        ToneCurveLut::from_curve(&ToneCurve {
+            points: vec![
+                CurvePoint::new(0.0, 0.0),
+                CurvePoint::new(0.25, 0.15),
+                CurvePoint::new(0.75, 0.85),
+                CurvePoint::new(1.0, 1.0),
+            ],
+        })
+    }
+
+    #[test]
+    fn test_resize() {
+        for (label, w, h, len, expect) in CASES {
+            let img = image(w, h, len);
+            for filter in [
+                FilterType::Nearest,
+                FilterType::Bilinear,
+                FilterType::Lanczos3,
+            ] {
+                assert_eq!(
+                    decode_outcome(resize(&img, 3, 2, filter)),
+                    expect,
+                    "{}",
+                    label
+                );
+            }
+            let fit = resize_to_fit(&img, 2, FilterType::Bilinear);
+            assert_eq!(decode_outcome(fit), expect, "{}", label);
+            assert_eq!(
+                decode_outcome(generate_thumbnail(&img, 1)),
+                expect,
+                "{}",
+                label
+            );
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        for (label, w, h, len, expect) in CASES {
+            let img = image(w, h, len);
+            for angle in [0.0, 15.0, 90.0, -45.0] {
+                for filter in [InterpolationFilter::Bilinear, InterpolationFilter::Lanczos3] {
+                    let result = apply_rotation(&img, angle, filter);
+                    assert_eq!(transform_outcome(result), expect, "{} at {}", label, angle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_crop() {
+        let crops = [
+            (0.0, 0.0, 1.0, 1.0),
+            (0.25, 0.25, 0.5, 0.5),
+            (0.9, 0.9, 0.01, 0.01),
+            (1.0, 1.0, 0.0, 0.0),
+        ];
+        for (label, w, h, len, expect) in CASES {
+            let img = image(w, h, len);
+            for (l, t, cw, ch) in crops {
+                let result = apply_crop(&img, l, t, cw, ch);
+                assert_eq!(transform_outcome(result), expect, "{}", label);
+            }
+        }
+    }
+
+    #[test]
+    fn test_histogram() {
+        for (label, w, h, len, expect) in CASES {
+            let img = image(w, h, len);
+            let result = compute_histogram(&img.pixels, w, h);
+            if let Ok(hist) = &result {
+                let total: u32 = hist.red.iter().sum();
+                assert_eq!(total, w * h, "{}", label);
+            }
+            assert_eq!(outcome(result), expect, "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_masks() {
+        let linear = LinearGradientMask::new(0.0, 0.5, 1.0, 0.5, 0.5);
+        let radial = RadialGradientMask::circle(0.5, 0.5, 0.4, 0.5);
+        for (label, w, h, len, expect) in CASES {
+            let original = image(w, h, len).pixels;
+
+            let mut pixels = original.clone();
+            let result = apply_masked_adjustments(
+                &mut pixels,
+                w,
+                h,
+                &[(linear.clone(), exposure())],
+                &[(radial.clone(), exposure())],
+            );
+            assert_eq!(outcome(result), expect, "{}", label);
+            if expect != Expect::Ok {
+                assert_eq!(pixels, original, "{}: failed call must not write", label);
+            }
+
+            let mut pixels = original.clone();
+            let result = apply_masked_adjustments_with_curves(
+                &mut pixels,
+                w,
+                h,
+                &[],
+                &[(radial.clone(), exposure(), Some(s_curve()))],
+            );
+            assert_eq!(outcome(result), expect, "{}", label);
+        }
+    }
+
+    #[test]
+    fn test_slice_only_operations_never_panic() {
+        // Adjustments and curves have no dimensions: any buffer is processed
+        // triple by triple and trailing bytes are left alone.
+        let lut = s_curve();
+        for (_, w, h, len, _) in CASES {
+            let mut pixels = image(w, h, len).pixels;
+            let tail = pixels[len - len % 3..].to_vec();
+
+            apply_all_adjustments(&mut pixels, &exposure());
+            apply_tone_curve(&mut pixels, &lut);
+
+            assert_eq!(pixels.len(), len);
+            assert_eq!(pixels[len - len % 3..], tail[..]);
+        }
+    }
+}
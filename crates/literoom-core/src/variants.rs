@@ -0,0 +1,185 @@
+//! Rendering many edit variants of one small image.
+//!
+//! The preset panel shows the same photo with a few dozen presets applied.
+//! Downsizing is by far the most expensive step, so it happens once; each
+//! preset is then applied to its own copy of the small image.
+
+use crate::decode::{generate_thumbnail, DecodeError, DecodedImage};
+use crate::edit::EditState;
+use crate::perf;
+use crate::render::PreparedEdit;
+
+/// Downsize `image` once and render each state's develop settings onto it.
+///
+/// The source is scaled with [`generate_thumbnail`] to fit `size x size`.
+/// Each state's develop stages (channel levels, base curve, global
+/// adjustments, B&W conversion and tone curve) are then applied to a copy
+/// of the thumbnail, exactly as [`render_edit`](crate::render::render_edit)
+/// runs them. Crop, rotation and masks are ignored:
+/// presets carry develop settings, and every variant keeps the same size.
+///
+/// # Returns
+///
+/// One image per state, in the same order. All images have the same
+/// dimensions.
+///
+/// # Errors
+///
+/// Returns the same errors as [`generate_thumbnail`].
+pub fn render_variants_small(
+    image: &DecodedImage,
+    states: &[EditState],
+    size: u32,
+) -> Result<Vec<DecodedImage>, DecodeError> {
+    let thumb = generate_thumbnail(image, size)?;
+
+    let _perf = perf::scope("render_variants_small");
+    perf::record_pixels(thumb.pixel_count() as u64 * states.len() as u64);
+    perf::record_allocation(thumb.pixels.len() as u64 * states.len() as u64);
+
+    Ok(states
+        .iter()
+        .map(|state| render_variant(&thumb, state))
+        .collect())
+}
+
+/// Apply one state's develop stages to a copy of `thumb`.
+fn render_variant(thumb: &DecodedImage, state: &EditState) -> DecodedImage {
+    let mut variant = thumb.clone();
+    PreparedEdit::from_state(state).develop(&mut variant.pixels);
+    variant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adjustments::{apply_all_adjustments, ChannelLevels};
+    use crate::compose::pack_atlas;
+    use crate::curve::{apply_tone_curve, ToneCurveLut};
+    use crate::monochrome::BwMix;
+    use crate::render::render_edit;
+    use crate::{BasicAdjustments, CurvePoint, InterpolationFilter, ToneCurve};
+
+    fn gradient(width: u32, height: u32) -> DecodedImage {
+        let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push((x * 255 / width) as u8);
+                pixels.push((y * 255 / height) as u8);
+                pixels.push(((x + y) % 256) as u8);
+            }
+        }
+        DecodedImage::new(width, height, pixels)
+    }
+
+    fn presets() -> Vec<EditState> {
+        let mut bright = EditState::default();
+        bright.adjustments.exposure = 1.0;
+
+        let mut contrasty = EditState::default();
+        contrasty.adjustments = BasicAdjustments {
+            contrast: 40.0,
+            saturation: -30.0,
+            ..Default::default()
+        };
+        contrasty.tone_curve = ToneCurve {
+            points: vec![
+                CurvePoint::new(0.0, 0.0),
+                CurvePoint::new(0.25, 0.15),
+                CurvePoint::new(0.75, 0.85),
+                CurvePoint::new(1.0, 1.0),
+            ],
+        };
+
+        vec![EditState::default(), bright, contrasty]
+    }
+
+    #[test]
+    fn test_variants_match_individual_application() {
+        let source = gradient(300, 200);
+        let states = presets();
+        let variants = render_variants_small(&source, &states, 64).unwrap();
+        let thumb = generate_thumbnail(&source, 64).unwrap();
+
+        assert_eq!(variants.len(), states.len());
+        for (variant, state) in variants.iter().zip(&states) {
+            let mut expected = thumb.pixels.clone();
+            apply_all_adjustments(&mut expected, &state.adjustments);
+            apply_tone_curve(&mut expected, &ToneCurveLut::from_curve(&state.tone_curve));
+
+            assert_eq!((variant.width, variant.height), (64, 43));
+            assert_eq!(variant.pixels, expected);
+        }
+    }
+
+    #[test]
+    fn test_variants_match_render_path() {
+        let source = gradient(300, 200);
+        let mut levels = EditState::default();
+        levels.channel_levels = ChannelLevels {
+            black: [0.1, 0.0, 0.05],
+            white: [0.9, 0.8, 1.0],
+        };
+        levels.adjustments.exposure = 0.5;
+        let mut mono = presets().pop().unwrap();
+        mono.monochrome = true;
+        mono.bw_mix = Some(BwMix {
+            red: 0.6,
+            green: 0.3,
+            blue: 0.1,
+        });
+        let states = [levels, mono];
+
+        let variants = render_variants_small(&source, &states, 64).unwrap();
+        let thumb = generate_thumbnail(&source, 64).unwrap();
+        for (variant, state) in variants.iter().zip(&states) {
+            let rendered = render_edit(&thumb, state, InterpolationFilter::Bilinear).unwrap();
+            assert_eq!(variant.pixels, rendered.pixels);
+            assert_ne!(variant.pixels, thumb.pixels);
+        }
+    }
+
+    #[test]
+    fn test_default_state_is_plain_thumbnail() {
+        let source = gradient(100, 100);
+        let variants = render_variants_small(&source, &[EditState::default()], 32).unwrap();
+        assert_eq!(
+            variants[0].pixels,
+            generate_thumbnail(&source, 32).unwrap().pixels
+        );
+    }
+
+    #[test]
+    fn test_atlas_offsets_index_each_variant() {
+        let variants = render_variants_small(&gradient(120, 80), &presets(), 40).unwrap();
+        let atlas = pack_atlas(&variants, 2).unwrap();
+
+        for (cell, variant) in atlas.cells.iter().zip(&variants) {
+            for y in 0..cell.height {
+                let row = ((cell.y + y) * atlas.image.width + cell.x) as usize * 3;
+                let src = (y * variant.width) as usize * 3;
+                let len = cell.width as usize * 3;
+                assert_eq!(
+                    atlas.image.pixels[row..row + len],
+                    variant.pixels[src..src + len]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_states_is_empty() {
+        let variants = render_variants_small(&gradient(10, 10), &[], 8).unwrap();
+        assert!(variants.is_empty());
+    }
+
+    #[test]
+    fn test_empty_source_is_error() {
+        let empty = DecodedImage {
+            width: 0,
+            height: 0,
+            pixels: vec![],
+        };
+        assert!(render_variants_small(&empty, &presets(), 8).is_err());
+    }
+}
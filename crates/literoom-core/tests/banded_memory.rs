@@ -0,0 +1,92 @@
+//! Peak-memory check for the banded resize path on a panorama-sized image.
+//!
+//! Lives in its own test binary because it installs a counting global
+//! allocator, which would skew the unit tests' timings.
+
+use literoom_core::adjustments::apply_all_adjustments;
+use literoom_core::decode::{resize_to_fit_banded, DecodedImage, FilterType};
+use literoom_core::BasicAdjustments;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// System allocator that tracks live and peak heap bytes.
+struct CountingAlloc;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(live, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(live, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// Heap bytes allocated above `baseline` at the peak of `f`.
+fn peak_above<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let baseline = LIVE.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let result = f();
+    (result, PEAK.load(Ordering::SeqCst) - baseline)
+}
+
+#[test]
+fn test_panorama_resize_and_adjust_stays_within_budget() {
+    const WIDTH: u32 = 30000;
+    const HEIGHT: u32 = 4000;
+    const MAX_SCRATCH: usize = 4 * 1024 * 1024;
+    // Small allocations outside the pixel path (perf counters, test harness)
+    const SLACK: usize = 64 * 1024;
+
+    // Horizontal ramp, repeated on every row
+    let row: Vec<u8> = (0..WIDTH)
+        .flat_map(|x| [(x / 118) as u8, 64, 128])
+        .collect();
+    let mut pixels = vec![0u8; row.len() * HEIGHT as usize];
+    for dst in pixels.chunks_exact_mut(row.len()) {
+        dst.copy_from_slice(&row);
+    }
+    let source = DecodedImage::new(WIDTH, HEIGHT, pixels);
+
+    let adjustments = BasicAdjustments {
+        exposure: 0.5,
+        contrast: 20.0,
+        ..Default::default()
+    };
+    let (output, peak) = peak_above(|| {
+        let mut output =
+            resize_to_fit_banded(&source, 2048, FilterType::Bilinear, MAX_SCRATCH).unwrap();
+        apply_all_adjustments(&mut output.pixels, &adjustments);
+        output
+    });
+
+    assert_eq!((output.width, output.height), (2048, 273));
+    let output_bytes = output.pixels.capacity();
+    assert!(
+        peak <= output_bytes + MAX_SCRATCH + SLACK,
+        "peak {} bytes above source, budget {}",
+        peak,
+        output_bytes + MAX_SCRATCH
+    );
+}
@@ -0,0 +1,530 @@
+//! Golden-image regression harness.
+//!
+//! Every registered operation runs on every fixture, and each case runs one
+//! operation on its own input; every output is compared with the golden
+//! stored in `tests/goldens.txt`. Operations that
+//! are pure lookups or per-pixel math must match exactly; resamplers and the
+//! JPEG round trip may move by a per-channel tolerance, since float rounding
+//! there can shift with a refactor without any visible change.
+//!
+//! Exact goldens are stored as a [`pixel_hash`]. Goldens with a tolerance
+//! also store their pixels, since a hash cannot say how far the output
+//! moved.
+//!
+//! When an output change is intended, regenerate the file and commit it with
+//! the change, so the drift shows up as a reviewed diff:
+//!
+//! ```text
+//! REGENERATE_GOLDENS=1 cargo test -p literoom-core --test golden
+//! ```
+
+use literoom_core::adjustments::apply_all_adjustments;
+use literoom_core::color::ColorModel;
+use literoom_core::curve::{apply_tone_curve, ToneCurveLut};
+use literoom_core::decode::{decode_jpeg, resize, DecodedImage, FilterType};
+use literoom_core::encode::encode_jpeg;
+use literoom_core::histogram::compute_histogram;
+use literoom_core::mask::{apply_masked_adjustments, LinearGradientMask, RadialGradientMask};
+use literoom_core::testing::{
+    checkerboard, color_bars, gradient, noise, pixel_hash, slanted_edge, GradientDirection,
+};
+use literoom_core::transform::{apply_crop, apply_rotation, InterpolationFilter};
+use literoom_core::{BasicAdjustments, CurvePoint, ToneCurve};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+const GOLDENS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/goldens.txt");
+
+/// Output must match bit for bit.
+const EXACT: [u8; 3] = [0, 0, 0];
+
+/// Output may move by one code value per channel.
+const RESAMPLE: [u8; 3] = [1, 1, 1];
+
+/// An operation with a canonical parameter set.
+struct Operation {
+    name: &'static str,
+    tolerance: [u8; 3],
+    run: fn(&DecodedImage) -> DecodedImage,
+}
+
+/// An operation pinned on an input of its own rather than the fixtures.
+struct Case {
+    name: &'static str,
+    tolerance: [u8; 3],
+    run: fn() -> DecodedImage,
+}
+
+/// A stored golden output.
+struct Golden {
+    width: u32,
+    height: u32,
+    hash: u64,
+    tolerance: [u8; 3],
+    /// Pixels, stored only when the tolerance is not exact
+    pixels: Option<Vec<u8>>,
+}
+
+fn fixtures() -> Vec<(&'static str, DecodedImage)> {
+    vec![
+        ("noise", noise(16, 12, 0x901d)),
+        ("gradient", gradient(16, 12, GradientDirection::Diagonal)),
+        ("color_bars", color_bars(16, 12)),
+    ]
+}
+
+fn operations() -> Vec<Operation> {
+    vec![
+        Operation {
+            name: "adjust_exposure",
+            tolerance: EXACT,
+            run: |image| {
+                adjust(
+                    image,
+                    BasicAdjustments {
+                        exposure: 1.0,
+                        ..Default::default()
+                    },
+                )
+            },
+        },
+        Operation {
+            name: "adjust_tone",
+            tolerance: EXACT,
+            run: |image| {
+                adjust(
+                    image,
+                    BasicAdjustments {
+                        contrast: 40.0,
+                        highlights: -30.0,
+                        shadows: 30.0,
+                        whites: 15.0,
+                        blacks: -15.0,
+                        ..Default::default()
+                    },
+                )
+            },
+        },
+        Operation {
+            name: "adjust_color",
+            tolerance: EXACT,
+            run: |image| {
+                adjust(
+                    image,
+                    BasicAdjustments {
+                        temperature: 30.0,
+                        tint: -10.0,
+                        vibrance: 40.0,
+                        saturation: 20.0,
+                        ..Default::default()
+                    },
+                )
+            },
+        },
+        Operation {
+            name: "adjust_desaturate",
+            tolerance: EXACT,
+            run: |image| {
+                adjust(
+                    image,
+                    BasicAdjustments {
+                        saturation: -100.0,
+                        ..Default::default()
+                    },
+                )
+            },
+        },
+        Operation {
+            name: "curve_s",
+            tolerance: EXACT,
+            run: |image| curve(image, &[(0.0, 0.0), (0.25, 0.18), (0.75, 0.84), (1.0, 1.0)]),
+        },
+        Operation {
+            name: "curve_fade",
+            tolerance: EXACT,
+            run: |image| curve(image, &[(0.0, 0.12), (0.5, 0.52), (1.0, 0.94)]),
+        },
+        Operation {
+            name: "mask_linear",
+            tolerance: EXACT,
+            run: |image| {
+                let mask = LinearGradientMask::new(0.0, 0.0, 1.0, 1.0, 0.6);
+                let adj = BasicAdjustments {
+                    exposure: -1.0,
+                    ..Default::default()
+                };
+                masked(image, &[(mask, adj)], &[])
+            },
+        },
+        Operation {
+            name: "mask_radial",
+            tolerance: EXACT,
+            run: |image| {
+                let mask = RadialGradientMask::new(0.5, 0.5, 0.3, 0.4, 0.3, 0.5, false);
+                let adj = BasicAdjustments {
+                    exposure: 0.7,
+                    saturation: 30.0,
+                    ..Default::default()
+                };
+                masked(image, &[], &[(mask, adj)])
+            },
+        },
+        Operation {
+            name: "rotate_bilinear",
+            tolerance: RESAMPLE,
+            run: |image| apply_rotation(image, 7.5, InterpolationFilter::Bilinear).unwrap(),
+        },
+        Operation {
+            name: "rotate_lanczos",
+            tolerance: RESAMPLE,
+            run: |image| apply_rotation(image, -12.0, InterpolationFilter::Lanczos3).unwrap(),
+        },
+        Operation {
+            name: "resize_nearest",
+            tolerance: RESAMPLE,
+            run: |image| resize(image, 11, 7, FilterType::Nearest).unwrap(),
+        },
+        Operation {
+            name: "resize_bilinear",
+            tolerance: RESAMPLE,
+            run: |image| resize(image, 11, 7, FilterType::Bilinear).unwrap(),
+        },
+        Operation {
+            name: "resize_lanczos",
+            tolerance: RESAMPLE,
+            run: |image| resize(image, 11, 7, FilterType::Lanczos3).unwrap(),
+        },
+        Operation {
+            name: "jpeg_q90",
+            tolerance: RESAMPLE,
+            run: |image| jpeg_round_trip(image, 90),
+        },
+        Operation {
+            name: "jpeg_q60",
+            tolerance: RESAMPLE,
+            run: |image| jpeg_round_trip(image, 60),
+        },
+    ]
+}
+
+fn cases() -> Vec<Case> {
+    vec![
+        Case {
+            name: "generator/gradient",
+            tolerance: EXACT,
+            run: || gradient(64, 48, GradientDirection::Diagonal),
+        },
+        Case {
+            name: "generator/checkerboard",
+            tolerance: EXACT,
+            run: || checkerboard(64, 48, 5),
+        },
+        Case {
+            name: "generator/color_bars",
+            tolerance: EXACT,
+            run: || color_bars(64, 48),
+        },
+        Case {
+            name: "generator/noise",
+            tolerance: EXACT,
+            run: || noise(48, 32, 0x5eed),
+        },
+        Case {
+            name: "generator/slanted_edge",
+            tolerance: EXACT,
+            run: || slanted_edge(64, 48, 5.0),
+        },
+        Case {
+            name: "edge_resize/nearest",
+            tolerance: EXACT,
+            run: || resize(&slanted_edge(64, 48, 5.0), 23, 17, FilterType::Nearest).unwrap(),
+        },
+        Case {
+            name: "edge_resize/bilinear",
+            tolerance: EXACT,
+            run: || resize(&slanted_edge(64, 48, 5.0), 23, 17, FilterType::Bilinear).unwrap(),
+        },
+        Case {
+            name: "edge_resize/lanczos3",
+            tolerance: EXACT,
+            run: || resize(&slanted_edge(64, 48, 5.0), 23, 17, FilterType::Lanczos3).unwrap(),
+        },
+        Case {
+            name: "adjust_all/noise",
+            tolerance: EXACT,
+            run: || adjust(&noise(48, 32, 0x5eed), all_adjustments()),
+        },
+        Case {
+            name: "curve_s/ramp",
+            tolerance: EXACT,
+            run: || {
+                let ramp = gradient(256, 4, GradientDirection::Horizontal);
+                curve(&ramp, &[(0.0, 0.0), (0.25, 0.15), (0.75, 0.85), (1.0, 1.0)])
+            },
+        },
+        Case {
+            name: "histogram/color_bars",
+            tolerance: EXACT,
+            run: || histogram_image(&color_bars(64, 8)),
+        },
+        Case {
+            name: "rotate_bilinear/checkerboard",
+            tolerance: EXACT,
+            run: || {
+                let src = checkerboard(40, 30, 4);
+                apply_rotation(&src, 12.5, InterpolationFilter::Bilinear).unwrap()
+            },
+        },
+        Case {
+            name: "crop/noise",
+            tolerance: EXACT,
+            run: || apply_crop(&noise(48, 32, 0x5eed), 0.1, 0.2, 0.5, 0.6).unwrap(),
+        },
+        Case {
+            name: "mask_pair/vertical_gradient",
+            tolerance: EXACT,
+            run: || {
+                let linear = LinearGradientMask::new(0.0, 0.2, 0.0, 0.8, 0.5);
+                let radial = RadialGradientMask::circle(0.5, 0.5, 0.3, 0.5);
+                let darken = BasicAdjustments {
+                    exposure: -1.0,
+                    ..Default::default()
+                };
+                masked(
+                    &gradient(48, 32, GradientDirection::Vertical),
+                    &[(linear, all_adjustments())],
+                    &[(radial, darken)],
+                )
+            },
+        },
+    ]
+}
+
+/// Every basic adjustment moved off its default at once.
+fn all_adjustments() -> BasicAdjustments {
+    BasicAdjustments {
+        temperature: 20.0,
+        tint: -10.0,
+        exposure: 0.5,
+        contrast: 25.0,
+        highlights: -30.0,
+        shadows: 40.0,
+        whites: 10.0,
+        blacks: -10.0,
+        vibrance: 20.0,
+        saturation: -15.0,
+        protect_skin_tones: false,
+        color_model: ColorModel::Legacy,
+    }
+}
+
+/// The histogram bins as a 256x4 image, one row per channel and each count
+/// stored little-endian in a pixel's three bytes.
+fn histogram_image(image: &DecodedImage) -> DecodedImage {
+    let hist = compute_histogram(&image.pixels, image.width, image.height).unwrap();
+    let pixels = [hist.red, hist.green, hist.blue, hist.luminance]
+        .iter()
+        .flatten()
+        .flat_map(|&count| {
+            assert!(count < 1 << 24, "histogram count does not fit a pixel");
+            let [b0, b1, b2, _] = count.to_le_bytes();
+            [b0, b1, b2]
+        })
+        .collect();
+    DecodedImage::new(256, 4, pixels)
+}
+
+fn adjust(image: &DecodedImage, adjustments: BasicAdjustments) -> DecodedImage {
+    let mut out = image.clone();
+    apply_all_adjustments(&mut out.pixels, &adjustments);
+    out
+}
+
+fn curve(image: &DecodedImage, points: &[(f32, f32)]) -> DecodedImage {
+    let curve = ToneCurve {
+        points: points.iter().map(|&(x, y)| CurvePoint::new(x, y)).collect(),
+    };
+    let mut out = image.clone();
+    apply_tone_curve(&mut out.pixels, &ToneCurveLut::from_curve(&curve));
+    out
+}
+
+fn masked(
+    image: &DecodedImage,
+    linear: &[(LinearGradientMask, BasicAdjustments)],
+    radial: &[(RadialGradientMask, BasicAdjustments)],
+) -> DecodedImage {
+    let mut out = image.clone();
+    apply_masked_adjustments(&mut out.pixels, out.width, out.height, linear, radial).unwrap();
+    out
+}
+
+fn jpeg_round_trip(image: &DecodedImage, quality: u8) -> DecodedImage {
+    let bytes = encode_jpeg(&image.pixels, image.width, image.height, quality).unwrap();
+    decode_jpeg(&bytes).unwrap()
+}
+
+/// Run the full matrix, keyed by `operation/fixture`, and every case.
+fn run_matrix() -> BTreeMap<String, (DecodedImage, [u8; 3])> {
+    let fixtures = fixtures();
+    let mut outputs = BTreeMap::new();
+    for op in operations() {
+        for (fixture, image) in &fixtures {
+            let output = (op.run)(image);
+            outputs.insert(format!("{}/{}", op.name, fixture), (output, op.tolerance));
+        }
+    }
+    for case in cases() {
+        outputs.insert(case.name.to_string(), ((case.run)(), case.tolerance));
+    }
+    outputs
+}
+
+/// Format: `name WxH hash tolerance pixels`, with `-` for no pixels.
+fn format_goldens(outputs: &BTreeMap<String, (DecodedImage, [u8; 3])>) -> String {
+    let mut text = String::from(
+        "# Golden outputs for tests/golden.rs. Regenerate with REGENERATE_GOLDENS=1.\n",
+    );
+    for (name, (image, tolerance)) in outputs {
+        let pixels = if *tolerance == EXACT {
+            "-".to_string()
+        } else {
+            image.pixels.iter().fold(String::new(), |mut hex, b| {
+                let _ = write!(hex, "{:02x}", b);
+                hex
+            })
+        };
+        let _ = writeln!(
+            text,
+            "{} {}x{} {:016x} {},{},{} {}",
+            name,
+            image.width,
+            image.height,
+            pixel_hash(image),
+            tolerance[0],
+            tolerance[1],
+            tolerance[2],
+            pixels
+        );
+    }
+    text
+}
+
+fn parse_goldens(text: &str) -> BTreeMap<String, Golden> {
+    text.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            assert_eq!(fields.len(), 5, "malformed golden line: {}", line);
+            let (width, height) = fields[1].split_once('x').unwrap();
+            let tolerance: Vec<u8> = fields[3].split(',').map(|t| t.parse().unwrap()).collect();
+            let pixels = (fields[4] != "-").then(|| {
+                (0..fields[4].len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&fields[4][i..i + 2], 16).unwrap())
+                    .collect()
+            });
+            let golden = Golden {
+                width: width.parse().unwrap(),
+                height: height.parse().unwrap(),
+                hash: u64::from_str_radix(fields[2], 16).unwrap(),
+                tolerance: [tolerance[0], tolerance[1], tolerance[2]],
+                pixels,
+            };
+            (fields[0].to_string(), golden)
+        })
+        .collect()
+}
+
+/// Describe how `actual` differs from `golden`, or `None` if it is within
+/// the golden's tolerance.
+fn compare(actual: &DecodedImage, golden: &Golden) -> Option<String> {
+    if (actual.width, actual.height) != (golden.width, golden.height) {
+        return Some(format!(
+            "size {}x{}, golden {}x{}",
+            actual.width, actual.height, golden.width, golden.height
+        ));
+    }
+    if pixel_hash(actual) == golden.hash {
+        return None;
+    }
+    let Some(expected) = &golden.pixels else {
+        return Some("output changed (exact match required)".to_string());
+    };
+
+    let mut max_diff = [0u8; 3];
+    for (i, (&a, &e)) in actual.pixels.iter().zip(expected).enumerate() {
+        max_diff[i % 3] = max_diff[i % 3].max(a.abs_diff(e));
+    }
+    let over = (0..3).any(|c| max_diff[c] > golden.tolerance[c]);
+    over.then(|| {
+        format!(
+            "max channel difference {:?} exceeds tolerance {:?}",
+            max_diff, golden.tolerance
+        )
+    })
+}
+
+#[test]
+fn golden_matrix() {
+    let outputs = run_matrix();
+
+    if std::env::var_os("REGENERATE_GOLDENS").is_some() {
+        std::fs::write(GOLDENS_PATH, format_goldens(&outputs)).unwrap();
+        return;
+    }
+
+    let text = std::fs::read_to_string(GOLDENS_PATH).unwrap();
+    let goldens = parse_goldens(&text);
+    let mut failures = Vec::new();
+    for (name, (image, _)) in &outputs {
+        match goldens.get(name) {
+            Some(golden) => {
+                if let Some(problem) = compare(image, golden) {
+                    failures.push(format!("{}: {}", name, problem));
+                }
+            }
+            None => failures.push(format!("{}: no golden", name)),
+        }
+    }
+    for name in goldens.keys().filter(|name| !outputs.contains_key(*name)) {
+        failures.push(format!(
+            "{}: golden for an operation that no longer exists",
+            name
+        ));
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} golden mismatches (if intended, rerun with REGENERATE_GOLDENS=1):\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn golden_tolerance_catches_drift() {
+    let image = gradient(16, 12, GradientDirection::Horizontal);
+    let exact = Golden {
+        width: 16,
+        height: 12,
+        hash: pixel_hash(&image),
+        tolerance: EXACT,
+        pixels: None,
+    };
+    let loose = Golden {
+        tolerance: RESAMPLE,
+        pixels: Some(image.pixels.clone()),
+        ..exact
+    };
+
+    let mut drifted = image.clone();
+    drifted.pixels[4] = drifted.pixels[4].saturating_add(1);
+    assert!(compare(&image, &exact).is_none());
+    assert!(compare(&drifted, &exact).is_some());
+    assert!(compare(&drifted, &loose).is_none());
+
+    drifted.pixels[4] = drifted.pixels[4].saturating_add(1);
+    let problem = compare(&drifted, &loose).unwrap();
+    assert!(problem.contains("[0, 2, 0]"), "{}", problem);
+}
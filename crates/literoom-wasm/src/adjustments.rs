@@ -1,10 +1,27 @@
 //! Basic adjustments WASM bindings.
 //!
-//! This module provides JavaScript bindings for the BasicAdjustments type,
-//! allowing photo editing parameters to be manipulated from TypeScript.
+//! This module provides JavaScript bindings for the BasicAdjustments and
+//! SplitWhiteBalance types, allowing photo editing parameters to be
+//! manipulated from TypeScript, plus one-shot auto levels and auto white
+//! balance.
 
-use crate::types::JsDecodedImage;
-use literoom_core::adjustments::apply_all_adjustments;
+use crate::types::{
+    adjustment_error_to_js, color_model_from_u8, decode_error_to_js, display_color_space_from_u8,
+    image_error_to_js, stage_order_error_to_js, white_balance_method_from_u8, JsDecodedImage,
+    JsDecodedImageF32,
+};
+use literoom_core::adjustments::{
+    apply_adjustments_subsampled, apply_adjustments_with_order, apply_all_adjustments,
+    apply_all_adjustments_f32, apply_all_adjustments_with_split,
+    apply_all_adjustments_with_white_balance,
+    apply_split_white_balance as core_apply_split_white_balance, parse_stage_order, AutoLevels,
+    AutoLevelsMode, ChannelLevels, DEFAULT_STAGE_ORDER, NEUTRAL_KELVIN,
+};
+use literoom_core::color::ColorModel;
+use literoom_core::decode::{convert_for_display, DecodedImage, DecodedImageF32, ImageMetadata};
+use literoom_core::noise_reduction::{self, NoiseReductionParams, NR_TABLE_VERSION};
+use literoom_core::pipeline::PipelineDescription;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 /// Basic adjustments wrapper for JavaScript
@@ -143,6 +160,35 @@ impl BasicAdjustments {
         self.inner.blacks = value;
     }
 
+    /// Get whether saturation and vibrance spare skin tones
+    #[wasm_bindgen(getter)]
+    pub fn protect_skin_tones(&self) -> bool {
+        self.inner.protect_skin_tones
+    }
+
+    /// Set whether saturation and vibrance spare skin tones
+    #[wasm_bindgen(setter)]
+    pub fn set_protect_skin_tones(&mut self, value: bool) {
+        self.inner.protect_skin_tones = value;
+    }
+
+    /// Get the color model saturation and vibrance work in
+    /// (0 = Legacy, 1 = Oklab)
+    #[wasm_bindgen(getter)]
+    pub fn color_model(&self) -> u8 {
+        match self.inner.color_model {
+            ColorModel::Legacy => 0,
+            ColorModel::Oklab => 1,
+        }
+    }
+
+    /// Set the color model saturation and vibrance work in
+    /// (see `color_model_from_u8`)
+    #[wasm_bindgen(setter)]
+    pub fn set_color_model(&mut self, value: u8) {
+        self.inner.color_model = color_model_from_u8(value);
+    }
+
     /// Check if all adjustments are at default values
     pub fn is_default(&self) -> bool {
         self.inner.is_default()
@@ -174,6 +220,82 @@ impl BasicAdjustments {
     }
 }
 
+/// Split white balance wrapper for JavaScript
+#[wasm_bindgen]
+pub struct SplitWhiteBalance {
+    inner: literoom_core::SplitWhiteBalance,
+}
+
+#[wasm_bindgen]
+impl SplitWhiteBalance {
+    /// Create a new split white balance with default (neutral) values
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: literoom_core::SplitWhiteBalance::new(),
+        }
+    }
+
+    /// Get shadow temperature value
+    #[wasm_bindgen(getter)]
+    pub fn shadow_temperature(&self) -> f32 {
+        self.inner.shadow_temperature
+    }
+
+    /// Set shadow temperature value
+    #[wasm_bindgen(setter)]
+    pub fn set_shadow_temperature(&mut self, value: f32) {
+        self.inner.shadow_temperature = value;
+    }
+
+    /// Get shadow tint value
+    #[wasm_bindgen(getter)]
+    pub fn shadow_tint(&self) -> f32 {
+        self.inner.shadow_tint
+    }
+
+    /// Set shadow tint value
+    #[wasm_bindgen(setter)]
+    pub fn set_shadow_tint(&mut self, value: f32) {
+        self.inner.shadow_tint = value;
+    }
+
+    /// Get highlight temperature value
+    #[wasm_bindgen(getter)]
+    pub fn highlight_temperature(&self) -> f32 {
+        self.inner.highlight_temperature
+    }
+
+    /// Set highlight temperature value
+    #[wasm_bindgen(setter)]
+    pub fn set_highlight_temperature(&mut self, value: f32) {
+        self.inner.highlight_temperature = value;
+    }
+
+    /// Get highlight tint value
+    #[wasm_bindgen(getter)]
+    pub fn highlight_tint(&self) -> f32 {
+        self.inner.highlight_tint
+    }
+
+    /// Set highlight tint value
+    #[wasm_bindgen(setter)]
+    pub fn set_highlight_tint(&mut self, value: f32) {
+        self.inner.highlight_tint = value;
+    }
+
+    /// Check if all values are at their defaults
+    pub fn is_default(&self) -> bool {
+        self.inner.is_default()
+    }
+}
+
+impl Default for SplitWhiteBalance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Apply all adjustments to an image.
 ///
 /// Takes an image and adjustments, returning a new adjusted image.
@@ -182,10 +304,24 @@ impl BasicAdjustments {
 /// # Arguments
 /// * `image` - The source image to apply adjustments to
 /// * `adjustments` - The adjustment values to apply
+/// * `clamp` - Clamp out-of-range values to their limits instead of
+///   rejecting them (defaults to false). Meant for interactive slider drags.
+/// * `order` - Stage names in the order to run them (defaults to
+///   [`get_default_pipeline_order`]). For matching reference renders from
+///   other tools; every stage must be listed exactly once.
+/// * `display_color_space` - Canvas the result is drawn to: 0 = sRGB
+///   (default), 1 = Display P3. Only pass 1 when this is the last step
+///   before drawing, since the pixels are no longer sRGB afterwards.
 ///
 /// # Returns
 /// A new JsDecodedImage with the adjustments applied
 ///
+/// # Errors
+/// Throws an `Error` with `code` and `fields` properties if any value is
+/// out of range (unless `clamp` is set), or NaN or infinite (always).
+/// Throws an `Error` with `code` (`"UNKNOWN_STAGE"`, `"DUPLICATE_STAGE"` or
+/// `"MISSING_STAGE"`) and `stage` properties if `order` is invalid.
+///
 /// # Example (TypeScript)
 /// ```typescript
 /// const adj = new BasicAdjustments();
@@ -196,20 +332,594 @@ impl BasicAdjustments {
 /// const pixels = adjusted.pixels();
 /// ```
 #[wasm_bindgen]
-pub fn apply_adjustments(image: &JsDecodedImage, adjustments: &BasicAdjustments) -> JsDecodedImage {
+pub fn apply_adjustments(
+    image: &JsDecodedImage,
+    adjustments: &BasicAdjustments,
+    clamp: Option<bool>,
+    order: Option<Vec<String>>,
+    display_color_space: Option<u8>,
+) -> Result<JsDecodedImage, JsValue> {
+    let adjustments = adjustments
+        .inner()
+        .sanitized(clamp.unwrap_or(false))
+        .map_err(|e| adjustment_error_to_js(&e))?;
+    let order = match order {
+        Some(names) => parse_stage_order(&names).map_err(|e| stage_order_error_to_js(&e))?,
+        None => DEFAULT_STAGE_ORDER.to_vec(),
+    };
+
     // Clone the pixel data so we don't modify the original
-    let mut pixels = image.pixels();
+    let mut pixels = image.pooled_pixels();
 
     // Apply all adjustments
-    apply_all_adjustments(&mut pixels, adjustments.inner());
+    apply_adjustments_with_order(&mut pixels, &adjustments, &order)
+        .expect("stage order was validated");
+    convert_for_display(
+        &mut pixels,
+        display_color_space_from_u8(display_color_space.unwrap_or(0)),
+    );
 
     // Return a new image with the adjusted pixels
+    Ok(JsDecodedImage::new(image.width(), image.height(), pixels))
+}
+
+/// Get the stage names in the order `apply_adjustments` runs them by default.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// // Balance white before exposure, as some raw converters do
+/// const order = get_default_pipeline_order();
+/// order.splice(order.indexOf('white_balance'), 1);
+/// order.unshift('white_balance');
+/// const adjusted = apply_adjustments(sourceImage, adj, false, order);
+/// ```
+#[wasm_bindgen]
+pub fn get_default_pipeline_order() -> Vec<String> {
+    DEFAULT_STAGE_ORDER
+        .iter()
+        .map(|stage| stage.name().to_string())
+        .collect()
+}
+
+/// Apply basic adjustments to a float image.
+///
+/// Same adjustments as `apply_adjustments`, but values are not clamped, so
+/// +1 EV on 0.8 gives 1.6. Convert with `to_u8` to bring highlights back
+/// into range. Returns a new image; the input is not modified.
+///
+/// # Errors
+///
+/// Same as `apply_adjustments`.
+///
+/// # Example
+///
+/// ```typescript
+/// const hdr = new JsDecodedImageF32(width, height, radiance);
+/// const adjusted = apply_adjustments_f32(hdr, adjustments);
+/// const display = adjusted.to_u8(1); // Reinhard
+/// ```
+#[wasm_bindgen]
+pub fn apply_adjustments_f32(
+    image: &JsDecodedImageF32,
+    adjustments: &BasicAdjustments,
+    clamp: Option<bool>,
+) -> Result<JsDecodedImageF32, JsValue> {
+    let adjustments = adjustments
+        .inner()
+        .sanitized(clamp.unwrap_or(false))
+        .map_err(|e| adjustment_error_to_js(&e))?;
+
+    let source = image.as_decoded();
+    let mut pixels = source.pixels.clone();
+    apply_all_adjustments_f32(&mut pixels, &adjustments);
+    Ok(JsDecodedImageF32::from_decoded(DecodedImageF32::new(
+        source.width,
+        source.height,
+        pixels,
+    )))
+}
+
+/// Apply all adjustments to an image in place.
+///
+/// Same as [`apply_adjustments`] but modifies `image` instead of returning a
+/// copy, so peak memory stays at one image. Use this for very large images
+/// such as stitched panoramas.
+///
+/// # Errors
+/// Same as [`apply_adjustments`]; on error the image is unchanged.
+#[wasm_bindgen]
+pub fn apply_adjustments_in_place(
+    image: &mut JsDecodedImage,
+    adjustments: &BasicAdjustments,
+    clamp: Option<bool>,
+) -> Result<(), JsValue> {
+    let adjustments = adjustments
+        .inner()
+        .sanitized(clamp.unwrap_or(false))
+        .map_err(|e| adjustment_error_to_js(&e))?;
+    apply_all_adjustments(image.pixels_mut(), &adjustments);
+    Ok(())
+}
+
+/// Apply all adjustments to a reduced-size preview of an image.
+///
+/// For instant feedback while a slider is dragged: every `2^scale_shift`-th
+/// pixel of every `2^scale_shift`-th row is read straight from `image` and
+/// adjusted, with no resize first. Render with [`apply_adjustments`] on
+/// release for full quality. The result is exactly a nearest-neighbour
+/// downsample followed by `apply_adjustments` with the default order.
+///
+/// # Arguments
+/// * `image` - The full-size source image
+/// * `adjustments` - The adjustment values to apply
+/// * `scale_shift` - 0 for full size, 1 for half, 2 for quarter, ...
+/// * `clamp` - If true, clamp out-of-range values instead of rejecting them
+///
+/// # Returns
+/// A new `(width >> scale_shift) x (height >> scale_shift)` image, at least
+/// 1x1
+///
+/// # Errors
+/// Same as [`apply_adjustments`], and throws if the image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// slider.oninput = () => draw(apply_adjustments_preview(source, adj, 2));
+/// slider.onchange = () => draw(apply_adjustments(source, adj));
+/// ```
+#[wasm_bindgen]
+pub fn apply_adjustments_preview(
+    image: &JsDecodedImage,
+    adjustments: &BasicAdjustments,
+    scale_shift: u8,
+    clamp: Option<bool>,
+) -> Result<JsDecodedImage, JsValue> {
+    let adjustments = adjustments
+        .inner()
+        .sanitized(clamp.unwrap_or(false))
+        .map_err(|e| adjustment_error_to_js(&e))?;
+    apply_adjustments_subsampled(image.as_decoded(), &adjustments, scale_shift)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| decode_error_to_js(&e))
+}
+
+/// Apply a split (shadow/highlight) white balance to an image.
+///
+/// The shadow pair affects dark areas and the highlight pair bright areas,
+/// with a smooth crossover around middle gray.
+///
+/// # Arguments
+/// * `image` - The source image
+/// * `split` - Shadow and highlight temperature/tint
+///
+/// # Returns
+/// A new JsDecodedImage with the white balance applied
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const split = new SplitWhiteBalance();
+/// split.shadow_temperature = 30;    // Cool the window-lit shadows
+/// split.highlight_temperature = -20; // Warm the tungsten-lit subject
+///
+/// const balanced = apply_split_white_balance(sourceImage, split);
+/// ```
+#[wasm_bindgen]
+pub fn apply_split_white_balance(
+    image: &JsDecodedImage,
+    split: &SplitWhiteBalance,
+) -> JsDecodedImage {
+    let mut pixels = image.pooled_pixels();
+    core_apply_split_white_balance(&mut pixels, &split.inner);
+    JsDecodedImage::new(image.width(), image.height(), pixels)
+}
+
+/// Apply all adjustments plus a split white balance to an image.
+///
+/// The split white balance runs right after the global temperature and tint.
+///
+/// # Arguments
+/// * `image` - The source image
+/// * `adjustments` - The adjustment values to apply
+/// * `split` - Shadow and highlight temperature/tint
+///
+/// # Returns
+/// A new JsDecodedImage with the adjustments applied
+#[wasm_bindgen]
+pub fn apply_adjustments_with_split(
+    image: &JsDecodedImage,
+    adjustments: &BasicAdjustments,
+    split: &SplitWhiteBalance,
+) -> JsDecodedImage {
+    let mut pixels = image.pooled_pixels();
+    apply_all_adjustments_with_split(&mut pixels, adjustments.inner(), &split.inner);
     JsDecodedImage::new(image.width(), image.height(), pixels)
 }
 
+/// Apply all adjustments with white balance in relative or Kelvin mode.
+///
+/// The temperature and tint stored in `adjustments` are ignored in favor of
+/// `white_balance`.
+///
+/// # Arguments
+/// * `image` - The source image
+/// * `adjustments` - The adjustment values to apply
+/// * `white_balance` - `{ mode: 'relative', temperature, tint }` or
+///   `{ mode: 'absolute', kelvin, tint }`
+/// * `reference_kelvin` - Temperature the image is currently balanced for,
+///   usually the as-shot value (defaults to 6500)
+///
+/// # Returns
+/// A new JsDecodedImage with the adjustments applied
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const asShot = get_as_shot_white_balance(bytes);
+/// const result = apply_adjustments_with_white_balance(
+///   sourceImage,
+///   adjustments,
+///   { mode: 'absolute', kelvin: 5200, tint: 0 },
+///   asShot?.kelvin,
+/// );
+/// ```
+#[wasm_bindgen]
+pub fn apply_adjustments_with_white_balance(
+    image: &JsDecodedImage,
+    adjustments: &BasicAdjustments,
+    white_balance: JsValue,
+    reference_kelvin: Option<f32>,
+) -> Result<JsDecodedImage, JsValue> {
+    let white_balance: literoom_core::WhiteBalance = serde_wasm_bindgen::from_value(white_balance)
+        .map_err(|e| JsValue::from_str(&format!("Invalid white balance: {}", e)))?;
+
+    let mut pixels = image.pooled_pixels();
+    apply_all_adjustments_with_white_balance(
+        &mut pixels,
+        adjustments.inner(),
+        &white_balance,
+        reference_kelvin.unwrap_or(NEUTRAL_KELVIN),
+    );
+    Ok(JsDecodedImage::new(image.width(), image.height(), pixels))
+}
+
+/// Compute channel multipliers for a color temperature.
+///
+/// # Arguments
+/// * `kelvin` - Color temperature in Kelvin
+/// * `tint` - Green-magenta shift (-100 to 100)
+///
+/// # Returns
+/// `[r, g, b]` multipliers relative to 6500K (neutral); warm temperatures
+/// give `r > b`.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const [r, g, b] = white_balance_kelvin(3200, 0);
+/// ```
+#[wasm_bindgen]
+pub fn white_balance_kelvin(kelvin: f32, tint: f32) -> Vec<f32> {
+    literoom_core::adjustments::white_balance_kelvin(kelvin, tint).to_vec()
+}
+
+/// Estimate the temperature and tint that remove an image's color cast.
+///
+/// Nothing is applied; set the returned values on the temperature and tint
+/// sliders for a one-click auto white balance.
+///
+/// # Arguments
+/// * `image` - The image to analyze
+/// * `method` - 0 = gray world, 1 = robust gray edge (best when one color
+///   dominates the frame), 2 = brightest pixels
+///
+/// # Returns
+/// `[temperature, tint]` in slider units (-100 to 100).
+///
+/// # Errors
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const [temperature, tint] = estimate_white_balance(sourceImage, 1);
+/// adjustments.temperature = temperature;
+/// adjustments.tint = tint;
+/// ```
+#[wasm_bindgen]
+pub fn estimate_white_balance(image: &JsDecodedImage, method: u8) -> Result<Vec<f32>, JsValue> {
+    let decoded = image.as_decoded();
+    let (temperature, tint) = literoom_core::adjustments::estimate_white_balance(
+        &decoded.pixels,
+        decoded.width,
+        decoded.height,
+        white_balance_method_from_u8(method),
+    )
+    .map_err(|e| image_error_to_js(&e))?;
+    Ok(vec![temperature, tint])
+}
+
+/// Get the valid range of every basic adjustment.
+///
+/// These are the same limits the core enforces when clamping, so sliders
+/// built from them always agree with `apply_relative_adjustments`.
+///
+/// # Returns
+/// An array of `{ field, min, max }` objects in `BasicAdjustments` field order.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// for (const { field, min, max } of get_adjustment_ranges()) {
+///   sliders[field].setRange(min, max);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn get_adjustment_ranges() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&literoom_core::ranges::ADJUSTMENT_RANGES[..])
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Describe the per-pixel adjustment math as data, for generating a shader.
+///
+/// Returns `{ version, stages }`, where each stage is tagged by `op` and
+/// carries the factors and thresholds the pipeline uses for these
+/// adjustments (see `literoom_core::pipeline` for the formulas). Stages
+/// whose slider is zero are left out. `JSON.stringify` of the result is the
+/// stable serialized form; refuse versions you do not know.
+///
+/// # Arguments
+/// * `adjustments` - The adjustment values to describe
+/// * `clamp` - Clamp out-of-range values as `apply_adjustments` does
+///   (defaults to false)
+///
+/// # Errors
+/// Same as `apply_adjustments` for invalid values.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const { version, stages } = describe_adjustment_pipeline(adj);
+/// if (version !== 1) throw new Error(`unsupported pipeline version ${version}`);
+/// gl.shaderSource(shader, generateFragmentShader(stages));
+/// ```
+#[wasm_bindgen]
+pub fn describe_adjustment_pipeline(
+    adjustments: &BasicAdjustments,
+    clamp: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let adjustments = adjustments
+        .inner()
+        .sanitized(clamp.unwrap_or(false))
+        .map_err(|e| adjustment_error_to_js(&e))?;
+    // Null rather than undefined for absent fields, so the JSON keeps them
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    PipelineDescription::new(&adjustments)
+        .serialize(&serializer)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Result of [`auto_levels`]: the stretched image and the levels used.
+#[wasm_bindgen]
+pub struct JsAutoLevels {
+    image: DecodedImage,
+    levels: AutoLevels,
+}
+
+#[wasm_bindgen]
+impl JsAutoLevels {
+    /// Get the image with levels applied.
+    pub fn image(&self) -> JsDecodedImage {
+        JsDecodedImage::from_decoded(self.image.clone())
+    }
+
+    /// Get the input value mapped to 0, as `[r, g, b]`.
+    pub fn black_point(&self) -> Vec<u8> {
+        self.levels.black_point.to_vec()
+    }
+
+    /// Get the input value mapped to 255, as `[r, g, b]`.
+    pub fn white_point(&self) -> Vec<u8> {
+        self.levels.white_point.to_vec()
+    }
+
+    /// Get the multiplier applied to each channel, as `[r, g, b]`.
+    pub fn gains(&self) -> Vec<f32> {
+        self.levels.gains.to_vec()
+    }
+
+    /// Get the offset added to each channel after the gain, as `[r, g, b]`.
+    pub fn offsets(&self) -> Vec<f32> {
+        self.levels.offsets.to_vec()
+    }
+}
+
+/// Stretch an image so chosen percentiles map to black and white.
+///
+/// Unlike the adjustment sliders this is a one-shot operation. The returned
+/// levels describe what was done (`output = input * gain + offset` per
+/// channel), so the UI can display it.
+///
+/// # Arguments
+/// * `image` - The source image
+/// * `per_channel` - Stretch R, G and B independently (also removes color
+///   casts); otherwise stretch luminance and keep hues
+/// * `low_percentile` - Percentile mapped to 0 (e.g. 0.1)
+/// * `high_percentile` - Percentile mapped to 255 (e.g. 99.9)
+///
+/// # Errors
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const result = auto_levels(sourceImage, false, 0.1, 99.9);
+/// const leveled = result.image();
+/// console.log('gains', result.gains(), 'offsets', result.offsets());
+/// ```
+#[wasm_bindgen]
+pub fn auto_levels(
+    image: &JsDecodedImage,
+    per_channel: bool,
+    low_percentile: f32,
+    high_percentile: f32,
+) -> Result<JsAutoLevels, JsValue> {
+    let mode = if per_channel {
+        AutoLevelsMode::PerChannel
+    } else {
+        AutoLevelsMode::Luminance
+    };
+    let mut image = image.to_decoded();
+    let levels = literoom_core::adjustments::auto_levels(
+        &mut image.pixels,
+        image.width,
+        image.height,
+        mode,
+        low_percentile,
+        high_percentile,
+    )
+    .map_err(|e| image_error_to_js(&e))?;
+
+    Ok(JsAutoLevels { image, levels })
+}
+
+/// Remap each channel from its own black and white level.
+///
+/// A calibration step for scans: run it on the source before the rest of
+/// the pipeline to remove channel-dependent offsets such as a scanner's
+/// dark current. Levels are in 0.0-1.0 units.
+///
+/// # Arguments
+/// * `image` - The source image
+/// * `levels` - `{ black: [r, g, b], white: [r, g, b] }`, as returned by
+///   [`estimate_channel_levels`] and stored in the edit state's
+///   `channel_levels`
+///
+/// # Errors
+/// Throws if `levels` cannot be parsed, or an `Error` with `code` and
+/// `fields` properties if a level is not finite
+/// (`"NON_FINITE_ADJUSTMENT"`) or a channel's black is at or above its
+/// white (`"INVERTED_CHANNEL_LEVELS"`, naming the channels).
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const levels = estimate_channel_levels(scan, 0.5, 99.5);
+/// const calibrated = apply_channel_levels(scan, levels);
+/// ```
+#[wasm_bindgen]
+pub fn apply_channel_levels(
+    image: &JsDecodedImage,
+    levels: JsValue,
+) -> Result<JsDecodedImage, JsValue> {
+    let levels: ChannelLevels = serde_wasm_bindgen::from_value(levels)
+        .map_err(|e| JsValue::from_str(&format!("Invalid channel levels: {}", e)))?;
+
+    let mut pixels = image.pooled_pixels();
+    literoom_core::adjustments::apply_channel_levels(&mut pixels, &levels)
+        .map_err(|e| adjustment_error_to_js(&e))?;
+    Ok(JsDecodedImage::new(image.width(), image.height(), pixels))
+}
+
+/// Measure per-channel black and white levels from percentiles.
+///
+/// The value at `low_percentile` of each channel becomes its black level
+/// and the value at `high_percentile` its white level. Nothing is applied
+/// to the image.
+///
+/// # Arguments
+/// * `image` - The source image
+/// * `low_percentile` - Percentile taken as black (e.g. 0.5)
+/// * `high_percentile` - Percentile taken as white (e.g. 99.5)
+///
+/// # Returns
+/// `{ black: [r, g, b], white: [r, g, b] }` in 0.0-1.0 units; identity
+/// levels if the percentiles are invalid.
+///
+/// # Errors
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+#[wasm_bindgen]
+pub fn estimate_channel_levels(
+    image: &JsDecodedImage,
+    low_percentile: f32,
+    high_percentile: f32,
+) -> Result<JsValue, JsValue> {
+    let image = image.as_decoded();
+    let levels = literoom_core::adjustments::estimate_channel_levels(
+        &image.pixels,
+        image.width,
+        image.height,
+        low_percentile,
+        high_percentile,
+    )
+    .map_err(|e| image_error_to_js(&e))?;
+    serde_wasm_bindgen::to_value(&levels)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize channel levels: {}", e)))
+}
+
+/// The metadata fields noise reduction depends on; the rest of the
+/// metadata object is ignored, so partial objects are accepted.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NoiseMetadata {
+    iso: Option<u32>,
+    camera_make: Option<String>,
+    camera_model: Option<String>,
+}
+
+/// Result of [`compute_default_noise_reduction`].
+#[derive(Debug, Serialize)]
+struct DefaultNoiseReductionJs {
+    luminance: f32,
+    color: f32,
+    /// Version of the ISO table the values came from
+    table_version: u32,
+}
+
+fn default_noise_reduction_js(metadata: NoiseMetadata) -> DefaultNoiseReductionJs {
+    let metadata = ImageMetadata {
+        iso: metadata.iso,
+        camera_make: metadata.camera_make,
+        camera_model: metadata.camera_model,
+        ..Default::default()
+    };
+    let NoiseReductionParams { luminance, color } =
+        noise_reduction::compute_default_noise_reduction(&metadata);
+    DefaultNoiseReductionJs {
+        luminance,
+        color,
+        table_version: NR_TABLE_VERSION,
+    }
+}
+
+/// Default noise reduction for a photo from its ISO and camera.
+///
+/// This is what an edit state with `auto_nr: true` and no explicit
+/// `noise_reduction` resolves to. Strengths are zero when the ISO is
+/// unknown or at base ISO.
+///
+/// # Arguments
+/// * `metadata` - Metadata object, as returned by `JsOpenedFile.metadata()`;
+///   only `iso`, `camera_make` and `camera_model` are read
+///
+/// # Returns
+/// `{ luminance, color, table_version }`, strengths on a 0-100 scale.
+/// `table_version` changes whenever the defaults do.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const { luminance, color } = compute_default_noise_reduction(opened.metadata());
+/// nrSliders.setPlaceholder(luminance, color);
+/// ```
+#[wasm_bindgen]
+pub fn compute_default_noise_reduction(metadata: JsValue) -> Result<JsValue, JsValue> {
+    let metadata: NoiseMetadata = serde_wasm_bindgen::from_value(metadata)
+        .map_err(|e| JsValue::from_str(&format!("Invalid metadata: {}", e)))?;
+    serde_wasm_bindgen::to_value(&default_noise_reduction_js(metadata))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use literoom_core::adjustments::MAX_AUTO_LEVELS_GAIN;
+    use literoom_core::decode::DisplayColorSpace;
 
     #[test]
     fn test_basic_adjustments() {
@@ -260,13 +970,41 @@ mod tests {
         let image = JsDecodedImage::new(2, 1, pixels.clone());
         let adj = BasicAdjustments::new();
 
-        let result = apply_adjustments(&image, &adj);
+        let result = apply_adjustments(&image, &adj, None, None, None).unwrap();
 
         assert_eq!(result.width(), 2);
         assert_eq!(result.height(), 1);
         assert_eq!(result.pixels(), pixels);
     }
 
+    #[test]
+    fn test_apply_adjustments_display_p3() {
+        let image = JsDecodedImage::new(2, 1, vec![255, 0, 0, 128, 128, 128]);
+        let mut adj = BasicAdjustments::new();
+        adj.set_contrast(10.0);
+
+        let srgb = apply_adjustments(&image, &adj, None, None, None).unwrap();
+        let explicit = apply_adjustments(&image, &adj, None, None, Some(0)).unwrap();
+        assert_eq!(explicit.pixels(), srgb.pixels());
+
+        let p3 = apply_adjustments(&image, &adj, None, None, Some(1)).unwrap();
+        let mut expected = srgb.pixels();
+        convert_for_display(&mut expected, DisplayColorSpace::DisplayP3);
+        assert_eq!(p3.pixels(), expected);
+        assert!(p3.pixels()[0] < srgb.pixels()[0]);
+    }
+
+    #[test]
+    fn test_apply_adjustments_f32_exposure_is_not_clamped() {
+        let image = JsDecodedImageF32::new(1, 1, vec![0.8, 0.4, 0.1]).unwrap();
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(1.0);
+
+        let result = apply_adjustments_f32(&image, &adj, None).unwrap();
+        assert_eq!(result.pixels(), vec![1.6, 0.8, 0.2]);
+        assert_eq!(image.pixels(), vec![0.8, 0.4, 0.1]);
+    }
+
     #[test]
     fn test_apply_adjustments_exposure() {
         // Create a 1x1 gray pixel
@@ -276,7 +1014,7 @@ mod tests {
         let mut adj = BasicAdjustments::new();
         adj.set_exposure(1.0); // +1 stop = 2x brightness
 
-        let result = apply_adjustments(&image, &adj);
+        let result = apply_adjustments(&image, &adj, None, None, None).unwrap();
         let result_pixels = result.pixels();
 
         // 64 * 2 = 128
@@ -291,7 +1029,7 @@ mod tests {
         let mut adj = BasicAdjustments::new();
         adj.set_exposure(2.0);
 
-        let _result = apply_adjustments(&image, &adj);
+        let _result = apply_adjustments(&image, &adj, None, None, None).unwrap();
 
         // Original image should be unchanged
         assert_eq!(image.pixels(), pixels);
@@ -306,10 +1044,373 @@ mod tests {
         let mut adj = BasicAdjustments::new();
         adj.set_contrast(100.0); // Double contrast
 
-        let result = apply_adjustments(&image, &adj);
+        let result = apply_adjustments(&image, &adj, None, None, None).unwrap();
         let result_pixels = result.pixels();
 
         // Dark pixel should get darker with increased contrast
         assert!(result_pixels[0] < 64, "Dark pixel should get darker");
     }
+
+    #[test]
+    fn test_apply_adjustments_clamp_renders_at_limit() {
+        let image = JsDecodedImage::new(2, 1, vec![20, 20, 20, 90, 90, 90]);
+        let mut over = BasicAdjustments::new();
+        over.set_exposure(8.0);
+        let mut limit = BasicAdjustments::new();
+        limit.set_exposure(5.0);
+
+        let clamped = apply_adjustments(&image, &over, Some(true), None, None).unwrap();
+        let expected = apply_adjustments(&image, &limit, None, None, None).unwrap();
+        assert_eq!(clamped.pixels(), expected.pixels());
+    }
+
+    #[test]
+    fn test_apply_adjustments_in_place_matches_copy() {
+        let pixels = vec![20, 40, 60, 128, 128, 128, 250, 10, 90];
+        let mut image = JsDecodedImage::new(3, 1, pixels.clone());
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(0.7);
+        adj.set_saturation(30.0);
+
+        let copy = apply_adjustments(&image, &adj, None, None, None).unwrap();
+        apply_adjustments_in_place(&mut image, &adj, None).unwrap();
+        assert_eq!(image.pixels(), copy.pixels());
+        assert_ne!(image.pixels(), pixels);
+    }
+
+    #[test]
+    fn test_apply_adjustments_preview_sizes_and_full_path() {
+        let image = JsDecodedImage::from_decoded(literoom_core::testing::noise(21, 11, 3));
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(0.5);
+        adj.set_contrast(20.0);
+
+        let preview = apply_adjustments_preview(&image, &adj, 1, None).unwrap();
+        assert_eq!((preview.width(), preview.height()), (10, 5));
+
+        let full = apply_adjustments_preview(&image, &adj, 0, None).unwrap();
+        let expected = apply_adjustments(&image, &adj, None, None, None).unwrap();
+        assert_eq!(full.pixels(), expected.pixels());
+    }
+
+    #[test]
+    fn test_estimate_white_balance_cool_image_warms() {
+        // Bluish ramp: the correction moves temperature toward warm (negative)
+        let pixels: Vec<u8> = (0..16u8)
+            .flat_map(|i| [90 + i * 4, 100 + i * 4, 120 + i * 4])
+            .collect();
+        let image = JsDecodedImage::new(4, 4, pixels);
+        for method in 0..3 {
+            let estimate = estimate_white_balance(&image, method).unwrap();
+            assert_eq!(estimate.len(), 2);
+            assert!(estimate[0] < 0.0, "method {}: {:?}", method, estimate);
+        }
+    }
+
+    #[test]
+    fn test_white_balance_kelvin_neutral_at_6500() {
+        let multipliers = white_balance_kelvin(6500.0, 0.0);
+        assert_eq!(multipliers.len(), 3);
+        assert!(multipliers.iter().all(|m| (m - 1.0).abs() < 0.01));
+    }
+
+    #[test]
+    fn test_split_white_balance_accessors() {
+        let mut split = SplitWhiteBalance::new();
+        assert!(split.is_default());
+
+        split.set_shadow_temperature(-30.0);
+        split.set_shadow_tint(5.0);
+        split.set_highlight_temperature(20.0);
+        split.set_highlight_tint(-5.0);
+
+        assert_eq!(split.shadow_temperature(), -30.0);
+        assert_eq!(split.shadow_tint(), 5.0);
+        assert_eq!(split.highlight_temperature(), 20.0);
+        assert_eq!(split.highlight_tint(), -5.0);
+        assert!(!split.is_default());
+    }
+
+    #[test]
+    fn test_apply_split_white_balance_warms_shadows() {
+        let image = JsDecodedImage::new(2, 1, vec![50, 50, 50, 210, 210, 210]);
+        let mut split = SplitWhiteBalance::new();
+        split.set_shadow_temperature(-60.0);
+
+        let result = apply_split_white_balance(&image, &split).pixels();
+        assert!(result[0] > result[2]);
+        assert_eq!(&result[3..6], &[210, 210, 210]);
+    }
+
+    #[test]
+    fn test_apply_adjustments_with_default_split_matches_plain() {
+        let image = JsDecodedImage::new(2, 1, vec![50, 50, 50, 210, 210, 210]);
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(0.5);
+
+        let plain = apply_adjustments(&image, &adj, None, None, None)
+            .unwrap()
+            .pixels();
+        let split = apply_adjustments_with_split(&image, &adj, &SplitWhiteBalance::new()).pixels();
+        assert_eq!(plain, split);
+    }
+
+    #[test]
+    fn test_auto_levels_returns_image_and_levels() {
+        let pixels: Vec<u8> = (0..64u32)
+            .flat_map(|i| {
+                let v = (80 + i) as u8;
+                [v, v, v]
+            })
+            .collect();
+        let image = JsDecodedImage::new(8, 8, pixels);
+
+        let result = auto_levels(&image, false, 0.0, 100.0).unwrap();
+        assert_eq!(result.black_point(), vec![80; 3]);
+        assert_eq!(result.white_point(), vec![143; 3]);
+        assert_eq!(result.gains(), vec![MAX_AUTO_LEVELS_GAIN; 3]);
+
+        let leveled = result.image().pixels();
+        assert_eq!(&leveled[..3], &[0, 0, 0]);
+        assert_eq!(leveled.len(), 8 * 8 * 3);
+    }
+
+    #[test]
+    fn test_apply_adjustments_with_order() {
+        let image = JsDecodedImage::new(1, 1, vec![100, 100, 100]);
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(0.5);
+        adj.set_contrast(50.0);
+
+        let default = get_default_pipeline_order();
+        assert_eq!(default[0], "exposure");
+        assert_eq!(default.len(), 6);
+        let plain = apply_adjustments(&image, &adj, None, None, None).unwrap();
+        let explicit = apply_adjustments(&image, &adj, None, Some(default.clone()), None).unwrap();
+        assert_eq!(plain.pixels(), explicit.pixels());
+
+        let mut swapped = default;
+        swapped.swap(0, 1);
+        let swapped = apply_adjustments(&image, &adj, None, Some(swapped), None).unwrap();
+        assert_eq!(swapped.pixels(), vec![121, 121, 121]);
+    }
+
+    #[test]
+    fn test_protect_skin_tones_round_trips_and_applies() {
+        let image = JsDecodedImage::new(1, 1, vec![224, 172, 140]);
+        let mut adj = BasicAdjustments::new();
+        adj.set_saturation(80.0);
+        let pushed = apply_adjustments(&image, &adj, None, None, None).unwrap();
+
+        adj.set_protect_skin_tones(true);
+        assert!(adj.protect_skin_tones());
+        let spared = apply_adjustments(&image, &adj, None, None, None).unwrap();
+        assert_ne!(pushed.pixels(), image.pixels());
+        assert_eq!(spared.pixels(), image.pixels());
+    }
+
+    #[test]
+    fn test_color_model_round_trips_and_applies() {
+        let image = JsDecodedImage::new(1, 1, vec![40, 70, 170]);
+        let mut adj = BasicAdjustments::new();
+        adj.set_saturation(60.0);
+        assert_eq!(adj.color_model(), 0);
+        let legacy = apply_adjustments(&image, &adj, None, None, None).unwrap();
+
+        adj.set_color_model(1);
+        assert_eq!(adj.color_model(), 1);
+        let oklab = apply_adjustments(&image, &adj, None, None, None).unwrap();
+        assert_ne!(oklab.pixels(), legacy.pixels());
+
+        adj.set_color_model(9);
+        assert_eq!(adj.color_model(), 0);
+    }
+
+    #[test]
+    fn test_default_noise_reduction_follows_iso() {
+        let at_iso = |iso| {
+            default_noise_reduction_js(NoiseMetadata {
+                iso,
+                ..Default::default()
+            })
+        };
+        let base = at_iso(Some(100));
+        assert_eq!((base.luminance, base.color), (0.0, 0.0));
+        let unknown = at_iso(None);
+        assert_eq!((unknown.luminance, unknown.color), (0.0, 0.0));
+        let high = at_iso(Some(6400));
+        assert!(high.color > 0.0 && high.luminance > 0.0);
+        assert_eq!(high.table_version, NR_TABLE_VERSION);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use literoom_core::ranges::ADJUSTMENT_RANGES;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[derive(serde::Deserialize)]
+    struct Range {
+        field: String,
+        min: f32,
+        max: f32,
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_adjustments_with_kelvin_white_balance() {
+        let image = JsDecodedImage::new(1, 1, vec![128, 128, 128]);
+        let wb = serde_wasm_bindgen::to_value(&literoom_core::WhiteBalance::Absolute {
+            kelvin: 9000.0,
+            tint: 0.0,
+        })
+        .unwrap();
+
+        let result =
+            apply_adjustments_with_white_balance(&image, &BasicAdjustments::new(), wb, None)
+                .unwrap()
+                .pixels();
+        assert!(result[0] > result[2]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_adjustments_with_invalid_white_balance() {
+        let result = apply_adjustments_with_white_balance(
+            &JsDecodedImage::new(1, 1, vec![0, 0, 0]),
+            &BasicAdjustments::new(),
+            JsValue::from_str("warm"),
+            None,
+        );
+        assert!(result.is_err());
+    }
+
+    fn error_code_and_fields(err: &JsValue) -> (Option<String>, Vec<String>) {
+        let code = js_sys::Reflect::get(err, &"code".into()).unwrap();
+        let fields = js_sys::Reflect::get(err, &"fields".into()).unwrap();
+        let fields = js_sys::Array::from(&fields)
+            .iter()
+            .filter_map(|f| f.as_string())
+            .collect();
+        (code.as_string(), fields)
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_adjustments_out_of_range_names_fields() {
+        let image = JsDecodedImage::new(1, 1, vec![128, 128, 128]);
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(6.0);
+        adj.set_saturation(-101.0);
+
+        let err = apply_adjustments(&image, &adj, None, None, None)
+            .err()
+            .unwrap();
+        let (code, fields) = error_code_and_fields(&err);
+        assert_eq!(code.as_deref(), Some("ADJUSTMENT_OUT_OF_RANGE"));
+        assert_eq!(fields, vec!["exposure", "saturation"]);
+
+        assert!(apply_adjustments(&image, &adj, Some(true), None, None).is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_adjustments_rejects_nan_even_when_clamping() {
+        let image = JsDecodedImage::new(1, 1, vec![128, 128, 128]);
+        let mut adj = BasicAdjustments::new();
+        adj.set_contrast(f32::NAN);
+
+        let err = apply_adjustments(&image, &adj, Some(true), None, None)
+            .err()
+            .unwrap();
+        let (code, fields) = error_code_and_fields(&err);
+        assert_eq!(code.as_deref(), Some("NON_FINITE_ADJUSTMENT"));
+        assert_eq!(fields, vec!["contrast"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_channel_levels_round_trip() {
+        // Gray ramp with per-channel offsets
+        let pixels: Vec<u8> = (0..=200u8).flat_map(|v| [v + 10, v + 4, v + 16]).collect();
+        let image = JsDecodedImage::new(201, 1, pixels);
+
+        let levels = estimate_channel_levels(&image, 0.0, 100.0).unwrap();
+        let calibrated = apply_channel_levels(&image, levels).unwrap().pixels();
+        for p in calibrated.chunks(3) {
+            assert!(
+                p[0].abs_diff(p[1]) <= 2 && p[0].abs_diff(p[2]) <= 2,
+                "{:?}",
+                p
+            );
+        }
+
+        let inverted = serde_wasm_bindgen::to_value(&ChannelLevels {
+            black: [0.5; 3],
+            white: [0.5; 3],
+        })
+        .unwrap();
+        assert!(apply_channel_levels(&image, inverted).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_get_adjustment_ranges_matches_core() {
+        let ranges: Vec<Range> =
+            serde_wasm_bindgen::from_value(get_adjustment_ranges().unwrap()).unwrap();
+        assert_eq!(ranges.len(), ADJUSTMENT_RANGES.len());
+        for (js, core) in ranges.iter().zip(ADJUSTMENT_RANGES) {
+            assert_eq!(js.field, core.field);
+            assert_eq!(js.min, core.min);
+            assert_eq!(js.max, core.max);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_adjustments_rejects_invalid_order() {
+        let image = JsDecodedImage::new(1, 1, vec![128, 128, 128]);
+        let adj = BasicAdjustments::new();
+        let mut order = get_default_pipeline_order();
+        order.pop();
+
+        let err = apply_adjustments(&image, &adj, None, Some(order), None)
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        let stage = js_sys::Reflect::get(&err, &"stage".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("MISSING_STAGE"));
+        assert_eq!(stage.as_string().as_deref(), Some("saturation_vibrance"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_describe_adjustment_pipeline_is_versioned_json() {
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(1.0);
+        adj.set_vibrance(40.0);
+        adj.set_color_model(1);
+
+        let description = describe_adjustment_pipeline(&adj, None).unwrap();
+        let json = js_sys::JSON::stringify(&description).unwrap();
+        assert_eq!(
+            json.as_string().unwrap(),
+            concat!(
+                r#"{"version":1,"stages":[{"op":"multiply","rgb":[2,2,2]},"#,
+                r#"{"op":"oklch_chroma","saturation":0,"vibrance":40,"#,
+                r#""full_chroma":0.30000001192092896,"skin_tones":null}]}"#,
+            )
+        );
+        let parsed: PipelineDescription = serde_wasm_bindgen::from_value(description).unwrap();
+        assert_eq!(parsed, PipelineDescription::new(adj.inner()));
+
+        adj.set_contrast(f32::NAN);
+        assert!(describe_adjustment_pipeline(&adj, Some(true)).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_compute_default_noise_reduction_accepts_partial_metadata() {
+        let metadata = js_sys::Object::new();
+        js_sys::Reflect::set(&metadata, &"iso".into(), &6400.into()).unwrap();
+        let result = compute_default_noise_reduction(metadata.into()).unwrap();
+        let color = js_sys::Reflect::get(&result, &"color".into()).unwrap();
+        assert!(color.as_f64().unwrap() > 0.0);
+
+        assert!(compute_default_noise_reduction("iso".into()).is_err());
+    }
 }
@@ -0,0 +1,111 @@
+//! Burst grouping WASM bindings.
+//!
+//! The grid stacks photos shot in quick succession. Capture times come from
+//! the `capture_timestamp_ms` metadata field; this module clusters them.
+
+use literoom_core::burst::group_by_capture_time;
+use wasm_bindgen::prelude::*;
+
+/// Group photos into bursts by capture time.
+///
+/// # Arguments
+///
+/// * `timestamps` - Capture times in milliseconds, one per photo, as read
+///   from `metadata.capture_timestamp_ms`; any order
+/// * `max_gap_ms` - Longest gap between consecutive shots of one burst
+///   (inclusive)
+///
+/// # Returns
+///
+/// An array of groups, each an array of indexes into `timestamps` in
+/// capture order. Groups are ordered by their first capture, and a photo
+/// with no neighbour within `max_gap_ms` forms a group of its own.
+///
+/// # Errors
+///
+/// Returns an error if a timestamp is not an integer or if `max_gap_ms` is
+/// negative or not finite.
+///
+/// # Example
+///
+/// ```typescript
+/// const photos = files.filter((f) => f.metadata.capture_timestamp_ms != null);
+/// const bursts = group_burst_indices(
+///   Float64Array.from(photos, (f) => f.metadata.capture_timestamp_ms),
+///   500,
+/// );
+/// for (const burst of bursts) stackPhotos(burst.map((i) => photos[i]));
+/// ```
+#[wasm_bindgen]
+pub fn group_burst_indices(timestamps: Vec<f64>, max_gap_ms: f64) -> Result<JsValue, JsValue> {
+    let groups = group_indices(&timestamps, max_gap_ms).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&groups)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize groups: {}", e)))
+}
+
+/// Validate the JS numbers and group them.
+fn group_indices(timestamps: &[f64], max_gap_ms: f64) -> Result<Vec<Vec<usize>>, String> {
+    if !max_gap_ms.is_finite() || max_gap_ms < 0.0 {
+        return Err(format!("Invalid max_gap_ms: {}", max_gap_ms));
+    }
+    let timestamps = timestamps
+        .iter()
+        .map(|&t| {
+            if t.is_finite() && t.fract() == 0.0 {
+                Ok(t as i64)
+            } else {
+                Err(format!("Invalid timestamp: {}", t))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(group_by_capture_time(&timestamps, max_gap_ms as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_indices() {
+        let timestamps = [
+            1_717_252_205_370.0,
+            1_717_252_205_000.0,
+            1_717_252_300_000.0,
+        ];
+        assert_eq!(
+            group_indices(&timestamps, 500.0).unwrap(),
+            vec![vec![1, 0], vec![2]]
+        );
+        // Fractional gaps round down
+        assert_eq!(
+            group_indices(&[0.0, 100.0], 99.9).unwrap(),
+            vec![vec![0], vec![1]]
+        );
+    }
+
+    #[test]
+    fn test_group_indices_rejects_invalid_numbers() {
+        assert!(group_indices(&[0.0, f64::NAN], 100.0).is_err());
+        assert!(group_indices(&[0.5], 100.0).is_err());
+        assert!(group_indices(&[0.0], -1.0).is_err());
+        assert!(group_indices(&[0.0], f64::INFINITY).is_err());
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_group_burst_indices() {
+        let groups = group_burst_indices(vec![0.0, 5_000.0, 120.0], 200.0).unwrap();
+        let groups: Vec<Vec<usize>> = serde_wasm_bindgen::from_value(groups).unwrap();
+        assert_eq!(groups, vec![vec![0, 2], vec![1]]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_group_burst_indices_rejects_nan() {
+        assert!(group_burst_indices(vec![f64::NAN], 200.0).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Runtime introspection of what this build of the WASM module supports.
+//!
+//! The app toggles UI affordances (for example, offering full RAW decoding)
+//! based on [`get_capabilities`] instead of assuming every build has every
+//! feature. CPU features are compile-time target features: `simd` is set
+//! when the module is built with `-C target-feature=+simd128`, and
+//! `threads` when built with `+atomics`.
+//!
+//! # Example
+//!
+//! ```typescript
+//! import { get_capabilities, supports } from '@literoom/wasm';
+//!
+//! const caps = get_capabilities();
+//! if (caps.max_image_dimension < width) showTooLargeWarning();
+//! if (!supports('raw_full')) hideFullRawToggle();
+//! ```
+
+use literoom_core::decode::DEFAULT_MAX_SCRATCH_BYTES;
+use literoom_core::validate::MAX_IMAGE_DIMENSION;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Input formats the module can decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatSupport {
+    /// Full JPEG decode
+    pub jpeg: bool,
+    /// PNG decode (PNG files are only probed for their size today)
+    pub png: bool,
+    /// Embedded JPEG previews of RAW files
+    pub raw_thumbnail: bool,
+    /// Full RAW decode with demosaicing
+    pub raw_full: bool,
+    /// Embedded JPEG previews of HEIC/HEIF files
+    pub heif_preview: bool,
+}
+
+/// Capabilities of this build of the module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Crate version
+    pub version: String,
+    /// Built with WASM SIMD
+    pub simd: bool,
+    /// Built with shared-memory threads
+    pub threads: bool,
+    /// Supported input formats
+    pub formats: FormatSupport,
+    /// Largest width or height any operation accepts, in pixels
+    pub max_image_dimension: u32,
+    /// Scratch memory budget of banded operations such as resizing, in bytes
+    pub memory_budget: usize,
+}
+
+impl Capabilities {
+    /// Capabilities of the running build.
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            simd: cfg!(target_feature = "simd128"),
+            threads: cfg!(target_feature = "atomics"),
+            formats: FormatSupport {
+                jpeg: true,
+                png: false,
+                raw_thumbnail: true,
+                raw_full: false,
+                heif_preview: true,
+            },
+            max_image_dimension: MAX_IMAGE_DIMENSION,
+            memory_budget: DEFAULT_MAX_SCRATCH_BYTES,
+        }
+    }
+
+    /// Whether the named feature or format is supported.
+    ///
+    /// Names are the boolean fields of the capabilities object: `simd`,
+    /// `threads`, and the format names. Unknown names are unsupported.
+    pub fn supports(&self, name: &str) -> bool {
+        let formats = &self.formats;
+        match name {
+            "simd" => self.simd,
+            "threads" => self.threads,
+            "jpeg" => formats.jpeg,
+            "png" => formats.png,
+            "raw_thumbnail" => formats.raw_thumbnail,
+            "raw_full" => formats.raw_full,
+            "heif_preview" => formats.heif_preview,
+            _ => false,
+        }
+    }
+}
+
+/// Get the version and capabilities of this build.
+///
+/// # Returns
+///
+/// `{ version, simd, threads, formats: { jpeg, png, raw_thumbnail, raw_full,
+/// heif_preview }, max_image_dimension, memory_budget }`.
+///
+/// # Example
+///
+/// ```typescript
+/// const { version, formats } = get_capabilities();
+/// console.log(`Literoom WASM ${version}, RAW decode: ${formats.raw_full}`);
+/// ```
+#[wasm_bindgen]
+pub fn get_capabilities() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&Capabilities::current())
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize capabilities: {}", e)))
+}
+
+/// Check whether this build supports a feature or format by name.
+///
+/// Accepts `"simd"`, `"threads"`, `"jpeg"`, `"png"`, `"raw_thumbnail"`,
+/// `"raw_full"` and `"heif_preview"`; anything else returns `false`.
+///
+/// # Example
+///
+/// ```typescript
+/// if (supports('heif_preview')) acceptTypes.push('.heic');
+/// ```
+#[wasm_bindgen]
+pub fn supports(op_name: &str) -> bool {
+    Capabilities::current().supports(op_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use literoom_core::validate::{validate_dimensions, ImageError};
+
+    #[test]
+    fn test_cpu_features_match_build() {
+        let caps = Capabilities::current();
+        assert_eq!(caps.simd, cfg!(target_feature = "simd128"));
+        assert_eq!(caps.threads, cfg!(target_feature = "atomics"));
+        assert_eq!(supports("simd"), caps.simd);
+        assert_eq!(supports("threads"), caps.threads);
+    }
+
+    #[test]
+    fn test_max_dimension_matches_validation() {
+        let max = Capabilities::current().max_image_dimension;
+        assert!(validate_dimensions(max, 1).is_ok());
+        assert!(validate_dimensions(1, max).is_ok());
+        assert!(matches!(
+            validate_dimensions(max + 1, 1),
+            Err(ImageError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_supports_formats() {
+        assert!(supports("jpeg"));
+        assert!(supports("raw_thumbnail"));
+        assert!(supports("heif_preview"));
+        assert!(!supports("raw_full"));
+        assert!(!supports("teleport"));
+    }
+
+    #[test]
+    fn test_version_matches_crate() {
+        assert_eq!(Capabilities::current().version, crate::version());
+    }
+}
+
+/// WASM-specific tests that require JsValue.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_capabilities_round_trip() {
+        let value = get_capabilities().unwrap();
+        let caps: Capabilities = serde_wasm_bindgen::from_value(value).unwrap();
+        assert_eq!(caps, Capabilities::current());
+    }
+}
@@ -0,0 +1,150 @@
+//! Reference-image color matching WASM bindings.
+//!
+//! "Match this photo's look to that one": the target's tones (and
+//! optionally colors) are histogram-matched to a reference. The derived
+//! tables are returned alongside the image so the match can be saved with
+//! the edit and reapplied with [`apply_color_match`] later, without the
+//! reference at hand.
+
+use crate::types::{image_error_to_js, JsDecodedImage};
+use literoom_core::color_match::{self, ColorMatchLuts, ColorMatchMode};
+use literoom_core::decode::DecodedImage;
+use wasm_bindgen::prelude::*;
+
+/// Result of [`match_colors`]: the matched image and the tables used.
+#[wasm_bindgen]
+pub struct JsColorMatch {
+    image: DecodedImage,
+    luts: ColorMatchLuts,
+}
+
+#[wasm_bindgen]
+impl JsColorMatch {
+    /// Get the matched image.
+    pub fn image(&self) -> JsDecodedImage {
+        JsDecodedImage::from_decoded(self.image.clone())
+    }
+
+    /// Get the matching tables, for saving with the edit state.
+    ///
+    /// Returns `{ mode, luma, cb, cr }`, where each table is 256 values.
+    pub fn luts(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.luts)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize tables: {}", e)))
+    }
+}
+
+/// Match the tones and colors of `target` to `reference`.
+///
+/// # Arguments
+///
+/// * `target` - Image to change
+/// * `reference` - Image whose look is transferred
+/// * `strength` - Blend between the original (0.0) and the full match (1.0)
+/// * `luma_only` - Match brightness and contrast only, keeping colors;
+///   otherwise color casts are transferred too
+///
+/// # Errors
+///
+/// Throws an `Error` with a `code` property if either image is empty or
+/// its buffer does not match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const result = match_colors(photo, hero, 0.8, false);
+/// preview(result.image());
+/// editState.color_match = { luts: result.luts(), strength: 0.8 };
+/// ```
+#[wasm_bindgen]
+pub fn match_colors(
+    target: &JsDecodedImage,
+    reference: &JsDecodedImage,
+    strength: f32,
+    luma_only: bool,
+) -> Result<JsColorMatch, JsValue> {
+    let mode = if luma_only {
+        ColorMatchMode::LumaOnly
+    } else {
+        ColorMatchMode::LumaAndColor
+    };
+    let (image, luts) =
+        color_match::match_colors(target.as_decoded(), reference.as_decoded(), strength, mode)
+            .map_err(|e| image_error_to_js(&e))?;
+    Ok(JsColorMatch { image, luts })
+}
+
+/// Apply saved matching tables from [`JsColorMatch::luts`] to an image.
+///
+/// # Arguments
+///
+/// * `image` - Image to change
+/// * `luts` - Tables as returned by `luts()`
+/// * `strength` - Blend between the original (0.0) and the full match (1.0)
+///
+/// # Errors
+///
+/// Throws if `luts` cannot be parsed.
+#[wasm_bindgen]
+pub fn apply_color_match(
+    image: &JsDecodedImage,
+    luts: JsValue,
+    strength: f32,
+) -> Result<JsDecodedImage, JsValue> {
+    let luts: ColorMatchLuts = serde_wasm_bindgen::from_value(luts)
+        .map_err(|e| JsValue::from_str(&format!("Invalid color match tables: {}", e)))?;
+    let mut pixels = image.pooled_pixels();
+    luts.apply(&mut pixels, strength);
+    Ok(JsDecodedImage::new(image.width(), image.height(), pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use literoom_core::testing::noise;
+
+    #[test]
+    fn test_match_colors_matches_core() {
+        let target = noise(24, 16, 1);
+        let reference = noise(20, 20, 2);
+        for luma_only in [true, false] {
+            let result = match_colors(
+                &JsDecodedImage::from_decoded(target.clone()),
+                &JsDecodedImage::from_decoded(reference.clone()),
+                0.7,
+                luma_only,
+            )
+            .unwrap();
+            let mode = if luma_only {
+                ColorMatchMode::LumaOnly
+            } else {
+                ColorMatchMode::LumaAndColor
+            };
+            let (expected, luts) =
+                color_match::match_colors(&target, &reference, 0.7, mode).unwrap();
+            assert_eq!(result.image().pixels(), expected.pixels);
+            assert_eq!(result.luts, luts);
+        }
+    }
+}
+
+/// WASM-specific tests that require JsValue.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use literoom_core::testing::noise;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_saved_tables_reproduce_the_match() {
+        let target = JsDecodedImage::from_decoded(noise(16, 16, 3));
+        let reference = JsDecodedImage::from_decoded(noise(16, 16, 4));
+        let result = match_colors(&target, &reference, 0.5, false).unwrap();
+
+        let reapplied = apply_color_match(&target, result.luts().unwrap(), 0.5).unwrap();
+        assert_eq!(reapplied.pixels(), result.image().pixels());
+        assert!(apply_color_match(&target, JsValue::from_str("nope"), 0.5).is_err());
+    }
+}
@@ -0,0 +1,866 @@
+//! Image composition WASM bindings.
+//!
+//! This module exposes the literoom-core composition functions to JavaScript,
+//! providing aspect-ratio padding, solid borders, and contact sheets for export.
+//!
+//! # Functions
+//!
+//! - [`pad_to_aspect`] - Place an image on a solid canvas of a target aspect ratio
+//! - [`add_border`] - Surround an image with a uniform solid border
+//! - [`resize_for_export`] - Resize, pad, border, and watermark an image in one call
+//! - [`apply_watermark_image`] - Blend a logo or other image into a corner
+//! - [`apply_watermark_rgba`] - Same, keyed on the watermark's own alpha
+//! - [`apply_watermark_text`] - Blend a line of text such as a copyright notice
+//! - [`draw_text`] - Draw scaled text, optionally on a background box
+//! - [`draw_capture_info`] - Burn capture settings such as "1/250s f/2.8" into a corner
+//! - [`compose_contact_sheet`] - Lay out thumbnails in a labeled grid
+//! - [`compose_contact_sheet_jpeg`] - Contact sheet encoded straight to JPEG
+//!
+//! # Example
+//!
+//! ```typescript
+//! import { resize_for_export } from '@literoom/wasm';
+//!
+//! // Instagram-style 4:5 export with white padding and a thin gray frame
+//! const output = resize_for_export(image, {
+//!   max_edge: 1080,
+//!   filter: 2,
+//!   pad_aspect: 4 / 5,
+//!   background: [255, 255, 255],
+//!   border_px: 12,
+//!   border_color: [128, 128, 128],
+//!   watermark_text: '© 2024 Jane Doe',
+//! });
+//! ```
+
+use crate::types::{
+    filter_from_u8, gravity_from_u8, watermark_anchor_from_u8, JsDecodedImage, JsRgbaImage,
+};
+use literoom_core::compose;
+use literoom_core::decode::{self, DecodedImage};
+use literoom_core::draw::{self, CaptureInfo};
+use literoom_core::encode;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// JavaScript-compatible export composition options.
+///
+/// Every field is optional; omitted steps are skipped. Steps run in the
+/// order resize → pad → border → watermark.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JsExportOptions {
+    /// Maximum length of the longest edge (no resize when omitted)
+    #[serde(default)]
+    pub max_edge: Option<u32>,
+    /// Resize filter: 0=Nearest, 1=Bilinear (default), 2=Lanczos3
+    #[serde(default)]
+    pub filter: Option<u8>,
+    /// Target width / height ratio for padding (no padding when omitted)
+    #[serde(default)]
+    pub pad_aspect: Option<f32>,
+    /// Placement: 0=Center (default), 1=Top, 2=Bottom, 3=Left, 4=Right
+    #[serde(default)]
+    pub gravity: Option<u8>,
+    /// Padding color as [r, g, b] (default white)
+    #[serde(default)]
+    pub background: Option<[u8; 3]>,
+    /// Border width in pixels (no border when omitted or 0)
+    #[serde(default)]
+    pub border_px: Option<u32>,
+    /// Border color as [r, g, b] (default white)
+    #[serde(default)]
+    pub border_color: Option<[u8; 3]>,
+    /// Text watermark (no watermark when omitted or empty)
+    #[serde(default)]
+    pub watermark_text: Option<String>,
+    /// Watermark placement: 0=TopLeft, 1=TopRight, 2=BottomLeft,
+    /// 3=BottomRight (default), 4=Center
+    #[serde(default)]
+    pub watermark_anchor: Option<u8>,
+    /// Distance of the watermark from the image edges (default 16)
+    #[serde(default)]
+    pub watermark_margin: Option<u32>,
+    /// Watermark opacity from 0 to 1 (default 0.5)
+    #[serde(default)]
+    pub watermark_opacity: Option<f32>,
+    /// Approximate watermark text height in pixels (default 14)
+    #[serde(default)]
+    pub watermark_size: Option<u32>,
+}
+
+/// Default distance of an export watermark from the image edges.
+const DEFAULT_WATERMARK_MARGIN: u32 = 16;
+
+/// Default opacity of an export watermark.
+const DEFAULT_WATERMARK_OPACITY: f32 = 0.5;
+
+/// Default text height of an export watermark.
+const DEFAULT_WATERMARK_SIZE: u32 = 14;
+
+/// JavaScript-compatible contact sheet layout options.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JsContactSheetOptions {
+    /// Number of cells per row
+    pub columns: u32,
+    /// Edge length of each square cell in pixels
+    pub cell_size: u32,
+    /// Gap between cells and around the sheet in pixels (default 0)
+    #[serde(default)]
+    pub padding: u32,
+    /// Sheet color as [r, g, b] (default white)
+    #[serde(default)]
+    pub background: Option<[u8; 3]>,
+    /// One label per image; no labels are drawn when omitted
+    #[serde(default)]
+    pub labels: Option<Vec<String>>,
+}
+
+/// Place an image on a solid-color canvas with the target aspect ratio.
+///
+/// The source pixels are copied unchanged; only the short axis is extended.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `target_aspect` - Desired width / height ratio (e.g. 0.8 for 4:5)
+/// * `r`, `g`, `b` - Background color
+/// * `gravity` - Placement: 0=Center, 1=Top, 2=Bottom, 3=Left, 4=Right
+///
+/// # Errors
+///
+/// Returns an error if the image is empty or the aspect ratio is not positive.
+///
+/// # Example
+///
+/// ```typescript
+/// const padded = pad_to_aspect(image, 4 / 5, 255, 255, 255, 0);
+/// ```
+#[wasm_bindgen]
+pub fn pad_to_aspect(
+    image: &JsDecodedImage,
+    target_aspect: f32,
+    r: u8,
+    g: u8,
+    b: u8,
+    gravity: u8,
+) -> Result<JsDecodedImage, JsValue> {
+    compose::pad_to_aspect(
+        image.as_decoded(),
+        target_aspect,
+        (r, g, b),
+        gravity_from_u8(gravity),
+    )
+    .map(JsDecodedImage::from_decoded)
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Surround an image with a solid-color border.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `border_px` - Border width in pixels on each side
+/// * `r`, `g`, `b` - Border color
+///
+/// # Errors
+///
+/// Returns an error if the image is empty.
+///
+/// # Example
+///
+/// ```typescript
+/// const framed = add_border(image, 20, 0, 0, 0);
+/// ```
+#[wasm_bindgen]
+pub fn add_border(
+    image: &JsDecodedImage,
+    border_px: u32,
+    r: u8,
+    g: u8,
+    b: u8,
+) -> Result<JsDecodedImage, JsValue> {
+    compose::add_border(image.as_decoded(), border_px, (r, g, b))
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Resize, pad, and add a border to an image in a single call.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `options` - JavaScript object matching `JsExportOptions`
+///
+/// # Returns
+///
+/// A new `JsDecodedImage` with all requested steps applied.
+///
+/// # Example
+///
+/// ```typescript
+/// const output = resize_for_export(image, { max_edge: 2048, pad_aspect: 1 });
+/// ```
+#[wasm_bindgen]
+pub fn resize_for_export(
+    image: &JsDecodedImage,
+    options: JsValue,
+) -> Result<JsDecodedImage, JsValue> {
+    let options: JsExportOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid export options: {}", e)))?;
+
+    compose_for_export(image.to_decoded(), &options)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Blend an image watermark, such as a logo, into a copy of `image`.
+///
+/// # Arguments
+///
+/// * `image` - The image to watermark
+/// * `watermark` - The watermark pixels
+/// * `alpha` - Optional coverage per watermark pixel (0 = transparent, 255
+///   = opaque), `width * height` bytes; without it the whole rectangle is
+///   blended
+/// * `anchor` - 0=TopLeft, 1=TopRight, 2=BottomLeft, 3=BottomRight, 4=Center
+/// * `margin_px` - Distance from the anchored edges
+/// * `opacity` - Overall strength from 0 to 1
+/// * `scale` - Size relative to the watermark's own dimensions; a watermark
+///   that would not fit is shrunk with its aspect ratio kept
+///
+/// # Errors
+///
+/// Returns an error if either image is empty, `alpha` has the wrong length,
+/// or `opacity` or `scale` are not usable.
+///
+/// # Example
+///
+/// ```typescript
+/// const marked = apply_watermark_image(photo, logo, logoAlpha, 3, 24, 0.6, 0.5);
+/// ```
+#[wasm_bindgen]
+pub fn apply_watermark_image(
+    image: &JsDecodedImage,
+    watermark: &JsDecodedImage,
+    alpha: Option<Vec<u8>>,
+    anchor: u8,
+    margin_px: u32,
+    opacity: f32,
+    scale: f32,
+) -> Result<JsDecodedImage, JsValue> {
+    let mut output = image.to_decoded();
+    compose::apply_watermark_image(
+        &mut output,
+        watermark.as_decoded(),
+        alpha.as_deref(),
+        watermark_anchor_from_u8(anchor),
+        margin_px,
+        opacity,
+        scale,
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(JsDecodedImage::from_decoded(output))
+}
+
+/// Blend an RGBA watermark into a copy of `image`, keyed on its alpha.
+///
+/// Same as [`apply_watermark_image`] with the alpha plane taken from the
+/// premultiplied watermark, e.g. a PNG logo drawn to a canvas.
+///
+/// # Errors
+///
+/// Same as [`apply_watermark_image`].
+#[wasm_bindgen]
+pub fn apply_watermark_rgba(
+    image: &JsDecodedImage,
+    watermark: &JsRgbaImage,
+    anchor: u8,
+    margin_px: u32,
+    opacity: f32,
+    scale: f32,
+) -> Result<JsDecodedImage, JsValue> {
+    let rgba = watermark.pixels();
+    let alpha: Vec<u8> = rgba.chunks_exact(4).map(|px| px[3]).collect();
+    let rgb = watermark.to_rgb();
+    apply_watermark_image(image, &rgb, Some(alpha), anchor, margin_px, opacity, scale)
+}
+
+/// Blend a line of white text, such as a copyright notice, into a copy of
+/// `image`.
+///
+/// The text uses the built-in bitmap font: digits, letters (shown as
+/// capitals), common punctuation and `©`.
+///
+/// # Arguments
+///
+/// * `image` - The image to watermark
+/// * `text` - The text to draw
+/// * `anchor` - 0=TopLeft, 1=TopRight, 2=BottomLeft, 3=BottomRight, 4=Center
+/// * `margin_px` - Distance from the anchored edges
+/// * `opacity` - Overall strength from 0 to 1
+/// * `size_px` - Approximate text height; rounded to a multiple of 7
+///
+/// # Errors
+///
+/// Returns an error if the image is empty or `opacity` is not finite.
+///
+/// # Example
+///
+/// ```typescript
+/// const marked = apply_watermark_text(photo, '© 2024 Jane Doe', 3, 24, 0.5, 21);
+/// ```
+#[wasm_bindgen]
+pub fn apply_watermark_text(
+    image: &JsDecodedImage,
+    text: &str,
+    anchor: u8,
+    margin_px: u32,
+    opacity: f32,
+    size_px: u32,
+) -> Result<JsDecodedImage, JsValue> {
+    let mut output = image.to_decoded();
+    compose::apply_watermark_text(
+        &mut output,
+        text,
+        watermark_anchor_from_u8(anchor),
+        margin_px,
+        opacity,
+        size_px,
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(JsDecodedImage::from_decoded(output))
+}
+
+/// Read an `[r, g, b]` color passed from JavaScript.
+fn rgb_from_slice(name: &str, values: &[u8]) -> Result<(u8, u8, u8), JsValue> {
+    match values {
+        [r, g, b] => Ok((*r, *g, *b)),
+        _ => Err(JsValue::from_str(&format!(
+            "{} must have 3 values, got {}",
+            name,
+            values.len()
+        ))),
+    }
+}
+
+/// Draw a line of text into a copy of `image`.
+///
+/// Unlike [`apply_watermark_text`], lowercase letters are kept and every
+/// printable ASCII character is covered. Each font pixel becomes a
+/// `scale` x `scale` block, and text running off the image is clipped.
+///
+/// # Arguments
+///
+/// * `image` - The image to draw into
+/// * `text` - The text to draw
+/// * `x`, `y` - Top-left position of the text
+/// * `scale` - Whole-pixel magnification of the 5x7 font (0 is treated as 1)
+/// * `color` - Text color as `[r, g, b]`
+/// * `background` - Optional `[r, g, b]` color of a box behind exactly the
+///   text's extent
+///
+/// # Errors
+///
+/// Returns an error if a color does not have 3 values.
+///
+/// # Example
+///
+/// ```typescript
+/// const labeled = draw_text(photo, 'Proof 3 of 12', 16, 16, 2, [255, 255, 255], [0, 0, 0]);
+/// ```
+#[wasm_bindgen]
+pub fn draw_text(
+    image: &JsDecodedImage,
+    text: &str,
+    x: u32,
+    y: u32,
+    scale: u32,
+    color: Vec<u8>,
+    background: Option<Vec<u8>>,
+) -> Result<JsDecodedImage, JsValue> {
+    let color = rgb_from_slice("color", &color)?;
+    let background = background
+        .map(|values| rgb_from_slice("background", &values))
+        .transpose()?;
+    let mut output = image.to_decoded();
+    draw::draw_text(&mut output, text, x, y, scale, color, background);
+    Ok(JsDecodedImage::from_decoded(output))
+}
+
+/// Burn capture settings, such as "1/250s f/2.8 ISO400 85mm", into a
+/// corner of a copy of `image`.
+///
+/// The label is white on a dark box. Missing settings are left out, and
+/// nothing is drawn when none are present.
+///
+/// # Arguments
+///
+/// * `image` - The image to label
+/// * `metadata` - Metadata object as returned with the decoded file; only
+///   `shutter_speed`, `aperture`, `iso` and `focal_length` are read
+/// * `corner` - 0=TopLeft, 1=TopRight, 2=BottomLeft, 3=BottomRight, 4=Center
+/// * `scale` - Whole-pixel magnification of the 5x7 font (0 is treated as 1)
+///
+/// # Errors
+///
+/// Returns an error if `metadata` is not an object with those fields.
+///
+/// # Example
+///
+/// ```typescript
+/// const proof = draw_capture_info(photo, opened.metadata, 3, 3);
+/// ```
+#[wasm_bindgen]
+pub fn draw_capture_info(
+    image: &JsDecodedImage,
+    metadata: JsValue,
+    corner: u8,
+    scale: u32,
+) -> Result<JsDecodedImage, JsValue> {
+    let info: CaptureInfo = serde_wasm_bindgen::from_value(metadata)
+        .map_err(|e| JsValue::from_str(&format!("Invalid metadata: {}", e)))?;
+    let mut output = image.to_decoded();
+    draw::draw_capture_info(&mut output, &info, watermark_anchor_from_u8(corner), scale);
+    Ok(JsDecodedImage::from_decoded(output))
+}
+
+/// Lay out thumbnails of several images in a grid.
+///
+/// Each image is scaled to fit its cell and centered, letterboxed with the
+/// background color. With `labels`, a text strip is added below every cell.
+///
+/// # Arguments
+///
+/// * `images` - Source images, in display order. Ownership moves into WASM,
+///   so the passed `JsDecodedImage` handles cannot be used afterwards.
+/// * `options` - JavaScript object matching `JsContactSheetOptions`
+///
+/// # Errors
+///
+/// Returns an error if the options are invalid, `images` is empty, or any
+/// image is empty.
+///
+/// # Example
+///
+/// ```typescript
+/// const sheet = compose_contact_sheet(thumbs, {
+///   columns: 4,
+///   cell_size: 256,
+///   padding: 16,
+///   background: [32, 32, 32],
+///   labels: files.map((f) => f.name),
+/// });
+/// ```
+#[wasm_bindgen]
+pub fn compose_contact_sheet(
+    images: Vec<JsDecodedImage>,
+    options: JsValue,
+) -> Result<JsDecodedImage, JsValue> {
+    let options: JsContactSheetOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid contact sheet options: {}", e)))?;
+
+    render_contact_sheet(&images, &options)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Lay out a contact sheet and encode it to JPEG in one call.
+///
+/// # Arguments
+///
+/// * `images` - Source images (consumed, as for [`compose_contact_sheet`])
+/// * `options` - JavaScript object matching `JsContactSheetOptions`
+/// * `quality` - JPEG quality (1-100)
+///
+/// # Returns
+///
+/// A `Uint8Array` containing the encoded sheet.
+///
+/// # Example
+///
+/// ```typescript
+/// const jpeg = compose_contact_sheet_jpeg(thumbs, { columns: 5, cell_size: 200 }, 85);
+/// const blob = new Blob([jpeg], { type: 'image/jpeg' });
+/// ```
+#[wasm_bindgen]
+pub fn compose_contact_sheet_jpeg(
+    images: Vec<JsDecodedImage>,
+    options: JsValue,
+    quality: u8,
+) -> Result<Vec<u8>, JsValue> {
+    let options: JsContactSheetOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid contact sheet options: {}", e)))?;
+
+    let sheet = render_contact_sheet(&images, &options).map_err(|e| JsValue::from_str(&e))?;
+    encode::encode_jpeg(&sheet.pixels, sheet.width, sheet.height, quality)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Build a contact sheet from WASM images with the given options.
+fn render_contact_sheet(
+    images: &[JsDecodedImage],
+    options: &JsContactSheetOptions,
+) -> Result<DecodedImage, String> {
+    let images: Vec<DecodedImage> = images.iter().map(JsDecodedImage::to_decoded).collect();
+    let [r, g, b] = options.background.unwrap_or([255, 255, 255]);
+    let background = (r, g, b);
+
+    let sheet = match &options.labels {
+        Some(labels) => compose::compose_labeled_contact_sheet(
+            &images,
+            labels,
+            options.columns,
+            options.cell_size,
+            options.padding,
+            background,
+        ),
+        None => compose::compose_contact_sheet(
+            &images,
+            options.columns,
+            options.cell_size,
+            options.padding,
+            background,
+        ),
+    };
+    sheet.map_err(|e| e.to_string())
+}
+
+/// Run the resize → pad → border → watermark chain on a core image.
+pub(crate) fn compose_for_export(
+    image: DecodedImage,
+    options: &JsExportOptions,
+) -> Result<DecodedImage, String> {
+    let mut image = image;
+
+    if let Some(max_edge) = options.max_edge {
+        let filter = filter_from_u8(options.filter.unwrap_or(1));
+        image = decode::resize_to_fit(&image, max_edge, filter).map_err(|e| e.to_string())?;
+    }
+
+    if let Some(aspect) = options.pad_aspect {
+        let [r, g, b] = options.background.unwrap_or([255, 255, 255]);
+        let gravity = gravity_from_u8(options.gravity.unwrap_or(0));
+        image = compose::pad_to_aspect(&image, aspect, (r, g, b), gravity)
+            .map_err(|e| e.to_string())?;
+    }
+
+    if let Some(border_px) = options.border_px {
+        let [r, g, b] = options.border_color.unwrap_or([255, 255, 255]);
+        image = compose::add_border(&image, border_px, (r, g, b)).map_err(|e| e.to_string())?;
+    }
+
+    if let Some(text) = &options.watermark_text {
+        compose::apply_watermark_text(
+            &mut image,
+            text,
+            watermark_anchor_from_u8(options.watermark_anchor.unwrap_or(3)),
+            options.watermark_margin.unwrap_or(DEFAULT_WATERMARK_MARGIN),
+            options
+                .watermark_opacity
+                .unwrap_or(DEFAULT_WATERMARK_OPACITY),
+            options.watermark_size.unwrap_or(DEFAULT_WATERMARK_SIZE),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_gray_image(width: u32, height: u32, value: u8) -> DecodedImage {
+        DecodedImage {
+            width,
+            height,
+            pixels: vec![value; (width * height * 3) as usize],
+        }
+    }
+
+    #[test]
+    fn test_pad_to_aspect_dimensions() {
+        let image = JsDecodedImage::from_decoded(create_gray_image(300, 200, 100));
+        let result = pad_to_aspect(&image, 4.0 / 5.0, 255, 255, 255, 0).unwrap();
+
+        assert_eq!(result.width(), 300);
+        assert_eq!(result.height(), 375);
+    }
+
+    #[test]
+    fn test_add_border_dimensions() {
+        let image = JsDecodedImage::from_decoded(create_gray_image(10, 20, 100));
+        let result = add_border(&image, 5, 0, 0, 0).unwrap();
+
+        assert_eq!(result.width(), 20);
+        assert_eq!(result.height(), 30);
+        assert_eq!(&result.pixels()[0..3], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_compose_for_export_empty_options() {
+        let image = create_gray_image(40, 30, 100);
+        let result = compose_for_export(image.clone(), &JsExportOptions::default()).unwrap();
+
+        assert_eq!(result.width, 40);
+        assert_eq!(result.height, 30);
+        assert_eq!(result.pixels, image.pixels);
+    }
+
+    #[test]
+    fn test_compose_for_export_full_chain() {
+        let image = create_gray_image(600, 400, 100);
+        let options = JsExportOptions {
+            max_edge: Some(300),
+            filter: Some(0),
+            pad_aspect: Some(4.0 / 5.0),
+            gravity: Some(0),
+            background: Some([255, 255, 255]),
+            border_px: Some(10),
+            border_color: Some([0, 0, 0]),
+            ..Default::default()
+        };
+        let result = compose_for_export(image, &options).unwrap();
+
+        // 600x400 -> 300x200 -> 300x375 -> 320x395
+        assert_eq!(result.width, 320);
+        assert_eq!(result.height, 395);
+
+        // Border corner is black
+        assert_eq!(&result.pixels[0..3], &[0, 0, 0]);
+        // Inside the border, top padding row is white
+        let idx = ((10 * result.width + 10) * 3) as usize;
+        assert_eq!(&result.pixels[idx..idx + 3], &[255, 255, 255]);
+        // Center of the image keeps the source gray
+        let idx = ((197 * result.width + 160) * 3) as usize;
+        assert_eq!(&result.pixels[idx..idx + 3], &[100, 100, 100]);
+    }
+
+    #[test]
+    fn test_compose_for_export_invalid_aspect_errors() {
+        let image = create_gray_image(10, 10, 100);
+        let options = JsExportOptions {
+            pad_aspect: Some(0.0),
+            ..Default::default()
+        };
+        assert!(compose_for_export(image, &options).is_err());
+    }
+
+    #[test]
+    fn test_render_contact_sheet_dimensions() {
+        let images: Vec<_> = (0..5)
+            .map(|_| JsDecodedImage::from_decoded(create_gray_image(60, 40, 100)))
+            .collect();
+        let options = JsContactSheetOptions {
+            columns: 2,
+            cell_size: 30,
+            padding: 5,
+            ..Default::default()
+        };
+        let sheet = render_contact_sheet(&images, &options).unwrap();
+
+        assert_eq!(sheet.width, 2 * 30 + 3 * 5);
+        assert_eq!(sheet.height, 3 * 30 + 4 * 5);
+        // Default background is white
+        assert_eq!(&sheet.pixels[0..3], &[255, 255, 255]);
+    }
+
+    #[test]
+    fn test_render_contact_sheet_with_labels() {
+        let images = vec![JsDecodedImage::from_decoded(create_gray_image(20, 20, 100))];
+        let options = JsContactSheetOptions {
+            columns: 1,
+            cell_size: 40,
+            labels: Some(vec!["IMG_1".to_string()]),
+            ..Default::default()
+        };
+        let sheet = render_contact_sheet(&images, &options).unwrap();
+
+        assert_eq!(sheet.height, 40 + compose::LABEL_STRIP_HEIGHT);
+    }
+
+    #[test]
+    fn test_render_contact_sheet_empty_errors() {
+        let options = JsContactSheetOptions {
+            columns: 3,
+            cell_size: 100,
+            ..Default::default()
+        };
+        let err = render_contact_sheet(&[], &options).unwrap_err();
+        assert!(err.contains("at least one image"));
+    }
+
+    #[test]
+    fn test_compose_contact_sheet_jpeg_encodes() {
+        let images = vec![JsDecodedImage::from_decoded(create_gray_image(8, 8, 100))];
+        let options = JsContactSheetOptions {
+            columns: 1,
+            cell_size: 8,
+            ..Default::default()
+        };
+        let sheet = render_contact_sheet(&images, &options).unwrap();
+        let jpeg = encode::encode_jpeg(&sheet.pixels, sheet.width, sheet.height, 90).unwrap();
+        assert_eq!(&jpeg[0..2], &[0xFF, 0xD8]);
+    }
+
+    #[test]
+    fn test_compose_for_export_watermark_step() {
+        let image = create_gray_image(200, 100, 0);
+        let options = JsExportOptions {
+            border_px: Some(10),
+            border_color: Some([0, 0, 0]),
+            watermark_text: Some("© 2024".to_string()),
+            watermark_opacity: Some(1.0),
+            ..Default::default()
+        };
+        let result = compose_for_export(image, &options).unwrap();
+
+        // Drawn after the border, inside the default 16px margin
+        let lit: Vec<_> = result
+            .enumerate_pixels()
+            .filter(|(_, _, rgb)| rgb.0 == 255 && rgb.1 == 255)
+            .collect();
+        assert!(!lit.is_empty());
+        assert!(lit.iter().all(|(x, y, _)| *x > 110 && *y > 80));
+        assert!(lit.iter().all(|(x, y, _)| *x < 220 - 16 && *y < 120 - 16));
+    }
+
+    #[test]
+    fn test_apply_watermark_image_and_rgba() {
+        let image = JsDecodedImage::from_decoded(create_gray_image(20, 10, 100));
+        let mark = JsDecodedImage::from_decoded(create_gray_image(4, 4, 200));
+        let result = apply_watermark_image(&image, &mark, None, 0, 2, 0.5, 1.0).unwrap();
+        let out = result.as_decoded();
+        assert_eq!(out.pixel(2, 2), Some((150, 150, 150)));
+        assert_eq!(out.pixel(6, 2), Some((100, 100, 100)));
+        // The source image is not modified
+        assert_eq!(image.pixels(), vec![100; 20 * 10 * 3]);
+
+        // Half-transparent white, premultiplied: only alpha keys the blend
+        let rgba = JsRgbaImage::new(1, 1, vec![128, 128, 128, 128]).unwrap();
+        let result = apply_watermark_rgba(&image, &rgba, 4, 0, 1.0, 1.0).unwrap();
+        let (r, _, _) = result.as_decoded().pixel(9, 4).unwrap();
+        assert!(r.abs_diff(178) <= 1, "{}", r);
+    }
+
+    #[test]
+    fn test_apply_watermark_text_copies() {
+        let image = JsDecodedImage::from_decoded(create_gray_image(100, 40, 0));
+        let result = apply_watermark_text(&image, "A", 1, 4, 1.0, 7).unwrap();
+        assert!(result.pixels().contains(&255));
+        assert!(!image.pixels().contains(&255));
+    }
+
+    #[test]
+    fn test_draw_text_copies_with_background() {
+        let image = JsDecodedImage::from_decoded(create_gray_image(40, 20, 100));
+        let result = draw_text(
+            &image,
+            "f/2.8",
+            2,
+            3,
+            2,
+            vec![255, 255, 255],
+            Some(vec![0, 0, 0]),
+        )
+        .unwrap();
+        let pixels = result.pixels();
+        assert!(pixels.contains(&255));
+        assert!(pixels.contains(&0));
+        assert!(!image.pixels().contains(&255));
+    }
+
+    #[test]
+    fn test_compose_for_export_zero_max_edge_errors() {
+        let image = create_gray_image(10, 10, 100);
+        let options = JsExportOptions {
+            max_edge: Some(0),
+            ..Default::default()
+        };
+        assert!(compose_for_export(image, &options).is_err());
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_resize_for_export_from_js_object() {
+        let image = JsDecodedImage::new(30, 20, vec![100; 30 * 20 * 3]);
+        let options = serde_wasm_bindgen::to_value(&JsExportOptions {
+            pad_aspect: Some(1.0),
+            border_px: Some(2),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = resize_for_export(&image, options).unwrap();
+        assert_eq!(result.width(), 34);
+        assert_eq!(result.height(), 34);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_draw_capture_info_from_js_object() {
+        let image = JsDecodedImage::new(200, 60, vec![100; 200 * 60 * 3]);
+        let metadata = serde_wasm_bindgen::to_value(&CaptureInfo {
+            shutter_speed: Some("1/250".to_string()),
+            iso: Some(400),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = draw_capture_info(&image, metadata, 3, 1).unwrap();
+        assert!(result.pixels().contains(&255));
+        assert!(draw_capture_info(&image, JsValue::from_str("bad"), 3, 1).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_draw_text_rejects_bad_color() {
+        let image = JsDecodedImage::new(10, 10, vec![100; 10 * 10 * 3]);
+        assert!(draw_text(&image, "A", 0, 0, 1, vec![255, 255], None).is_err());
+        assert!(draw_text(&image, "A", 0, 0, 1, vec![255; 3], Some(vec![0; 4])).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resize_for_export_invalid_options() {
+        let image = JsDecodedImage::new(1, 1, vec![0; 3]);
+        let result = resize_for_export(&image, JsValue::from_str("not an object"));
+        assert!(result.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_compose_contact_sheet_from_js_object() {
+        let images = vec![
+            JsDecodedImage::new(10, 20, vec![100; 10 * 20 * 3]),
+            JsDecodedImage::new(20, 10, vec![100; 20 * 10 * 3]),
+        ];
+        let options = serde_wasm_bindgen::to_value(&JsContactSheetOptions {
+            columns: 2,
+            cell_size: 10,
+            padding: 1,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let sheet = compose_contact_sheet(images, options).unwrap();
+        assert_eq!(sheet.width(), 23);
+        assert_eq!(sheet.height(), 12);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_compose_contact_sheet_jpeg_invalid_options() {
+        let images = vec![JsDecodedImage::new(1, 1, vec![0; 3])];
+        let result = compose_contact_sheet_jpeg(images, JsValue::from_str("bad"), 90);
+        assert!(result.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pad_to_aspect_invalid_aspect_errors() {
+        let image = JsDecodedImage::new(1, 1, vec![0; 3]);
+        assert!(pad_to_aspect(&image, -1.0, 0, 0, 0, 0).is_err());
+    }
+}
@@ -1,10 +1,16 @@
 //! Tone curve WASM bindings.
 //!
 //! This module provides JavaScript bindings for tone curve processing,
-//! allowing LUT generation and curve application from the web UI.
-
-use crate::types::JsDecodedImage;
-use literoom_core::curve::{apply_tone_curve as core_apply, ToneCurveLut};
+//! allowing LUT generation and curve application from the web UI, the
+//! camera base curve lookup, and hue-vs-hue/saturation/luminance curves.
+
+use crate::types::{curve_error_to_js, decode_error_to_js, JsDecodedImage};
+use literoom_core::base_curve::{self, BaseCurve};
+use literoom_core::curve::{
+    apply_hue_curves as core_apply_hue_curves, apply_tone_curve as core_apply,
+    apply_tone_curve_subsampled, CurveError, HueCurve, HueCurveSet, LevelsAndCurve, ToneCurveLut,
+    DEFAULT_MAX_LUT_STEP,
+};
 use literoom_core::{CurvePoint, ToneCurve};
 use wasm_bindgen::prelude::*;
 
@@ -12,7 +18,9 @@ use wasm_bindgen::prelude::*;
 ///
 /// A pre-computed 256-entry lookup table for efficient curve application.
 /// Create from curve control points and pass to `apply_tone_curve` for
-/// O(1) per-pixel processing.
+/// O(1) per-pixel processing. While a curve is being edited, call `update`
+/// with the new points and keep passing the same object to
+/// `apply_tone_curve` across frames.
 ///
 /// # Example (TypeScript)
 /// ```typescript
@@ -35,13 +43,56 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 pub struct JsToneCurveLut {
     inner: ToneCurveLut,
+    /// Limit steps to [`DEFAULT_MAX_LUT_STEP`] whenever the LUT is built
+    smooth: bool,
+    /// Whether smoothing changed the last built LUT
+    smoothed: bool,
+    /// Whether the last points had coordinates clamped into 0-1
+    clamped: bool,
+}
+
+/// Helper struct for (de)serializing JS curve points via serde.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CurvePointJs {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Build a tone curve from deserialized JS curve points.
+fn curve_from_points(points: &[CurvePointJs]) -> ToneCurve {
+    ToneCurve {
+        points: points.iter().map(|p| CurvePoint::new(p.x, p.y)).collect(),
+    }
 }
 
-/// Helper struct for deserializing JS curve points via serde.
-#[derive(serde::Deserialize)]
-struct CurvePointJs {
-    x: f32,
-    y: f32,
+/// Build a LUT from deserialized JS curve points.
+pub(crate) fn lut_from_points(points: &[CurvePointJs]) -> ToneCurveLut {
+    ToneCurveLut::from_curve(&curve_from_points(points))
+}
+
+/// Build a tone curve from deserialized JS curve points, checked and
+/// cleaned up by [`ToneCurve::sanitize`].
+///
+/// Returns the curve and whether any coordinate was clamped into 0-1.
+fn checked_curve(points: &[CurvePointJs]) -> Result<(ToneCurve, bool), CurveError> {
+    let mut curve = curve_from_points(points);
+    let clamped = curve.sanitize()?;
+    Ok((curve, clamped))
+}
+
+/// Build a LUT from a tone curve and optional input black and white points
+/// (defaults 0 and 1).
+pub(crate) fn lut_from_levels(
+    curve: ToneCurve,
+    input_black: Option<f32>,
+    input_white: Option<f32>,
+) -> ToneCurveLut {
+    let levels = LevelsAndCurve {
+        curve,
+        input_black: input_black.unwrap_or(0.0),
+        input_white: input_white.unwrap_or(1.0),
+    };
+    ToneCurveLut::from_levels_and_curve(&levels)
 }
 
 #[wasm_bindgen]
@@ -49,35 +100,65 @@ impl JsToneCurveLut {
     /// Create a LUT from curve control points.
     ///
     /// # Arguments
-    /// * `points` - Array of {x: number, y: number} objects, sorted by x
+    /// * `points` - Array of {x: number, y: number} objects, sorted by x; at
+    ///   most 64. Coordinates outside 0-1 are clamped (see `was_clamped`)
+    /// * `input_black` - Optional input black point (0-1, default 0); inputs
+    ///   at or below it map to the curve's start
+    /// * `input_white` - Optional input white point (0-1, default 1); inputs
+    ///   at or above it map to the curve's end
+    /// * `smooth` - Optional; spread steep segments so adjacent entries
+    ///   differ by at most 8 levels, avoiding posterized gradients (default
+    ///   false, the exact curve). Kept for later `update` calls; check
+    ///   `was_smoothed` to see whether it changed anything.
     ///
     /// # Errors
-    /// Returns error if points cannot be deserialized
+    /// Returns error if points cannot be deserialized, or an `Error` with a
+    /// `code` property if there are too many of them
+    /// (`"TOO_MANY_CURVE_POINTS"`) or one is NaN or infinite
+    /// (`"NON_FINITE_CURVE_POINT"`)
     #[wasm_bindgen(constructor)]
-    pub fn new(points: JsValue) -> Result<JsToneCurveLut, JsValue> {
+    pub fn new(
+        points: JsValue,
+        input_black: Option<f32>,
+        input_white: Option<f32>,
+        smooth: Option<bool>,
+    ) -> Result<JsToneCurveLut, JsValue> {
         let points: Vec<CurvePointJs> = serde_wasm_bindgen::from_value(points)
             .map_err(|e| JsValue::from_str(&format!("Invalid curve points: {}", e)))?;
 
-        let core_points: Vec<CurvePoint> = points
-            .into_iter()
-            .map(|p| CurvePoint::new(p.x, p.y))
-            .collect();
-
-        let curve = ToneCurve {
-            points: core_points,
-        };
-        let inner = ToneCurveLut::from_curve(&curve);
-
-        Ok(JsToneCurveLut { inner })
+        let (curve, clamped) = checked_curve(&points).map_err(|e| curve_error_to_js(&e))?;
+        let mut lut = JsToneCurveLut::from(lut_from_levels(curve, input_black, input_white));
+        lut.smooth = smooth.unwrap_or(false);
+        lut.clamped = clamped;
+        lut.limit_steps();
+        Ok(lut)
     }
 
     /// Create an identity (no-op) LUT.
     ///
     /// This is a fast path for linear curves that produce no change.
     pub fn identity() -> JsToneCurveLut {
-        JsToneCurveLut {
-            inner: ToneCurveLut::identity(),
-        }
+        JsToneCurveLut::from(ToneCurveLut::identity())
+    }
+
+    /// Rebuild this LUT in place from new curve control points.
+    ///
+    /// Lets the UI keep one LUT object while a curve is being dragged
+    /// instead of allocating (and freeing) a new one per frame. Input black
+    /// and white points are reset to the full range.
+    ///
+    /// # Arguments
+    /// * `points` - Array of {x: number, y: number} objects, as for the
+    ///   constructor
+    ///
+    /// # Errors
+    /// Returns the same errors as the constructor; the LUT keeps its
+    /// previous values
+    pub fn update(&mut self, points: JsValue) -> Result<(), JsValue> {
+        let points: Vec<CurvePointJs> = serde_wasm_bindgen::from_value(points)
+            .map_err(|e| JsValue::from_str(&format!("Invalid curve points: {}", e)))?;
+
+        self.set_points(&points).map_err(|e| curve_error_to_js(&e))
     }
 
     /// Check if this LUT produces no change (is identity).
@@ -87,6 +168,22 @@ impl JsToneCurveLut {
         self.inner.is_identity()
     }
 
+    /// Check if smoothing modified the LUT when it was last built.
+    ///
+    /// Always false without `smooth`. The UI can use it to hint that a
+    /// steep curve segment was softened.
+    pub fn was_smoothed(&self) -> bool {
+        self.smoothed
+    }
+
+    /// Check if any coordinate of the last points given was outside 0-1
+    /// and clamped.
+    ///
+    /// A UI that never produces such points has a bug if this is true.
+    pub fn was_clamped(&self) -> bool {
+        self.clamped
+    }
+
     /// Get raw LUT data (256 bytes) for debugging/visualization.
     ///
     /// Returns a Vec<u8> where lut[i] = output value for input i.
@@ -103,6 +200,41 @@ impl JsToneCurveLut {
     }
 }
 
+impl JsToneCurveLut {
+    /// Get a reference to the inner LUT for use by other bindings
+    pub(crate) fn inner(&self) -> &ToneCurveLut {
+        &self.inner
+    }
+
+    /// Rebuild the inner LUT from deserialized JS curve points.
+    ///
+    /// # Errors
+    /// Returns the validation error, keeping the previous LUT.
+    pub(crate) fn set_points(&mut self, points: &[CurvePointJs]) -> Result<(), CurveError> {
+        let (curve, clamped) = checked_curve(points)?;
+        self.inner.rebuild_from_curve(&curve);
+        self.clamped = clamped;
+        self.limit_steps();
+        Ok(())
+    }
+
+    /// Apply smoothing to a freshly built LUT if it was requested.
+    fn limit_steps(&mut self) {
+        self.smoothed = self.smooth && self.inner.limit_steps(DEFAULT_MAX_LUT_STEP);
+    }
+}
+
+impl From<ToneCurveLut> for JsToneCurveLut {
+    fn from(inner: ToneCurveLut) -> Self {
+        Self {
+            inner,
+            smooth: false,
+            smoothed: false,
+            clamped: false,
+        }
+    }
+}
+
 /// Apply tone curve to an image.
 ///
 /// Takes a JsDecodedImage and a pre-computed LUT, returns a new image
@@ -124,20 +256,258 @@ impl JsToneCurveLut {
 /// // Get result pixels
 /// const pixels = curved.pixels();
 ///
+/// // The same LUT can be updated and reused on later frames
+/// lut.update(newPoints);
+/// const next = apply_tone_curve(sourceImage, lut);
+/// next.free();
+///
 /// // Free memory
 /// lut.free();
 /// curved.free();
 /// ```
 #[wasm_bindgen]
 pub fn apply_tone_curve(image: &JsDecodedImage, lut: &JsToneCurveLut) -> JsDecodedImage {
-    let mut pixels = image.pixels();
+    let mut pixels = image.pooled_pixels();
     core_apply(&mut pixels, &lut.inner);
     JsDecodedImage::new(image.width(), image.height(), pixels)
 }
 
+/// Apply a tone curve to a reduced-size preview of an image.
+///
+/// The curve counterpart of `apply_adjustments_preview`: every
+/// `2^scale_shift`-th pixel of every `2^scale_shift`-th row is read from
+/// `image` and passed through the LUT, with no resize first.
+///
+/// # Arguments
+/// * `image` - Full-size source image (RGB pixels)
+/// * `lut` - Pre-computed tone curve LUT
+/// * `scale_shift` - 0 for full size, 1 for half, 2 for quarter, ...
+///
+/// # Returns
+/// A new `(width >> scale_shift) x (height >> scale_shift)` image, at least
+/// 1x1
+///
+/// # Errors
+/// Throws if the image is empty or its buffer does not match its dimensions.
+#[wasm_bindgen]
+pub fn apply_tone_curve_preview(
+    image: &JsDecodedImage,
+    lut: &JsToneCurveLut,
+    scale_shift: u8,
+) -> Result<JsDecodedImage, JsValue> {
+    apply_tone_curve_subsampled(image.as_decoded(), &lut.inner, scale_shift)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| decode_error_to_js(&e))
+}
+
+/// Build a hue curve from deserialized JS curve points.
+fn hue_curve_from_points(points: &[CurvePointJs]) -> HueCurve {
+    HueCurve::new(points.iter().map(|p| CurvePoint::new(p.x, p.y)).collect())
+}
+
+/// Parse an optional hue curve: `undefined`, `null` or an array of points.
+fn hue_curve_from_js(points: JsValue) -> Result<Option<HueCurve>, JsValue> {
+    let points: Option<Vec<CurvePointJs>> = serde_wasm_bindgen::from_value(points)
+        .map_err(|e| JsValue::from_str(&format!("Invalid hue curve points: {}", e)))?;
+    Ok(points.as_deref().map(hue_curve_from_points))
+}
+
+/// Apply Capture One style hue curves to an image.
+///
+/// Each curve runs over Oklch hue: `x` is the hue in degrees (0-360, red
+/// near 30, green near 140, blue near 265) and `y` the shift at that hue
+/// (-1 to 1, 0 for no change). Curves wrap around at 360, and neutral
+/// anchors every 60 degrees keep a point's effect local.
+///
+/// # Arguments
+/// * `image` - Source image (RGB pixels)
+/// * `hue_vs_hue` - Optional `{x, y}` points; 1 rotates hue by 180 degrees
+/// * `hue_vs_sat` - Optional `{x, y}` points; -1 removes color, 1 doubles it
+/// * `hue_vs_luma` - Optional `{x, y}` points; lightness shift of up to
+///   0.25 either way
+///
+/// # Errors
+/// Throws if the image is empty or its buffer does not match its
+/// dimensions, or if a curve is not an array of points.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// // Push greens toward yellow and darken blues
+/// const result = apply_hue_curves(image, [{ x: 140, y: -0.1 }], undefined, [{ x: 265, y: -0.5 }]);
+/// ```
+#[wasm_bindgen]
+pub fn apply_hue_curves(
+    image: &JsDecodedImage,
+    hue_vs_hue: JsValue,
+    hue_vs_sat: JsValue,
+    hue_vs_luma: JsValue,
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    let curves = HueCurveSet {
+        hue_vs_hue: hue_curve_from_js(hue_vs_hue)?,
+        hue_vs_sat: hue_curve_from_js(hue_vs_sat)?,
+        hue_vs_luma: hue_curve_from_js(hue_vs_luma)?,
+    };
+    let mut pixels = image.pooled_pixels();
+    core_apply_hue_curves(&mut pixels, &curves);
+    Ok(JsDecodedImage::new(image.width(), image.height(), pixels))
+}
+
+/// A camera base curve as returned to JavaScript.
+#[derive(Debug, serde::Serialize)]
+struct BaseCurveJs {
+    /// Value for the edit state's `base_curve` field
+    name: BaseCurve,
+    /// Control points, for drawing the curve
+    points: Vec<CurvePointJs>,
+}
+
+/// Base curve and its points for a camera.
+fn base_curve_js(make: &str, model: &str) -> BaseCurveJs {
+    let name = base_curve::get_base_curve_for_camera(make, model);
+    let points = name
+        .curve()
+        .points
+        .iter()
+        .map(|p| CurvePointJs { x: p.x, y: p.y })
+        .collect();
+    BaseCurveJs { name, points }
+}
+
+/// Look up the default base curve for a camera.
+///
+/// Matching ignores case and surrounding text in the EXIF strings; unknown
+/// cameras get `"neutral"`.
+///
+/// # Returns
+///
+/// `{ name, points }`: `name` goes into the edit state's `base_curve`
+/// field (`"neutral"`, `"camera_standard_approx"` or `"flat"`), and `points`
+/// is an array of `{x, y}` control points for display.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const { name, points } = get_base_curve_for_camera('SONY', 'ILCE-6600');
+/// editState.base_curve = name;
+/// drawCurve(points);
+/// ```
+#[wasm_bindgen]
+pub fn get_base_curve_for_camera(make: &str, model: &str) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&base_curve_js(make, model))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Estimate the tone curve baked into an embedded RAW preview.
+///
+/// Matches the luminance histogram of a flat decode to that of the
+/// embedded preview of the same shot. The two may differ in size. Apply
+/// the curve to the flat decode before the user's adjustments so edits
+/// made while the preview was shown look the same on the full decode.
+///
+/// # Arguments
+/// * `preview` - The embedded preview, with the camera's look baked in
+/// * `flat` - A flat decode of the RAW data
+///
+/// # Returns
+/// An array of `{x, y}` control points mapping flat to preview luminance,
+/// ready for `new JsToneCurveLut(points)`.
+///
+/// # Errors
+/// Throws an `Error` with a `code` property if either image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const points = estimate_baked_curve(thumbnail, flatDecode);
+/// const baked = new JsToneCurveLut(points);
+/// const matched = apply_tone_curve(flatDecode, baked);
+/// ```
+#[wasm_bindgen]
+pub fn estimate_baked_curve(
+    preview: &JsDecodedImage,
+    flat: &JsDecodedImage,
+) -> Result<JsValue, JsValue> {
+    preview.validate()?;
+    flat.validate()?;
+    let points = baked_curve_points(preview, flat)?;
+    serde_wasm_bindgen::to_value(&points).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Control points of the curve baked into `preview`, for JavaScript.
+fn baked_curve_points(
+    preview: &JsDecodedImage,
+    flat: &JsDecodedImage,
+) -> Result<Vec<CurvePointJs>, String> {
+    let curve = base_curve::estimate_baked_curve(preview.as_decoded(), flat.as_decoded())
+        .map_err(|e| e.to_string())?;
+    Ok(curve
+        .points
+        .iter()
+        .map(|p| CurvePointJs { x: p.x, y: p.y })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use literoom_core::testing::GradientDirection;
+
+    #[test]
+    fn test_hue_curve_from_points_shifts_only_its_hues() {
+        let points = [CurvePointJs { x: 120.0, y: 0.1 }];
+        let curves = HueCurveSet {
+            hue_vs_hue: Some(hue_curve_from_points(&points)),
+            ..Default::default()
+        };
+        let mut pixels = vec![40, 200, 60, 200, 40, 40];
+        core_apply_hue_curves(&mut pixels, &curves);
+        assert_ne!(&pixels[0..3], &[40, 200, 60]);
+        assert_eq!(&pixels[3..6], &[200, 40, 40]);
+    }
+
+    #[test]
+    fn test_base_curve_lookup() {
+        let sony = base_curve_js("SONY", "ILCE-6600");
+        assert_eq!(sony.name, BaseCurve::CameraStandardApprox);
+        let expected = BaseCurve::CameraStandardApprox.curve();
+        assert_eq!(sony.points.len(), expected.points.len());
+
+        let unknown = base_curve_js("Leica", "M11");
+        assert_eq!(unknown.name, BaseCurve::Neutral);
+        assert_eq!((unknown.points[0].x, unknown.points[0].y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_baked_curve_points_follow_preview() {
+        let flat = literoom_core::testing::gradient(256, 8, GradientDirection::Horizontal);
+        let mut preview = flat.clone();
+        let baked = BaseCurve::Neutral.curve();
+        core_apply(&mut preview.pixels, &ToneCurveLut::from_curve(&baked));
+
+        let points = baked_curve_points(
+            &JsDecodedImage::from_decoded(preview),
+            &JsDecodedImage::from_decoded(flat),
+        )
+        .unwrap();
+        assert_eq!((points[0].x, points.last().unwrap().x), (0.0, 1.0));
+        for p in &points {
+            let expected = literoom_core::curve::evaluate_curve(&baked, p.x);
+            assert!((p.y - expected).abs() < 0.02, "{:?}", (p.x, p.y));
+        }
+    }
+
+    #[test]
+    fn test_lut_from_levels_defaults_match_points() {
+        let points = [(0.0, 0.0), (0.3, 0.2), (1.0, 1.0)].map(|(x, y)| CurvePointJs { x, y });
+        assert_eq!(
+            lut_from_levels(curve_from_points(&points), None, None).lut,
+            lut_from_points(&points).lut
+        );
+
+        let lut = lut_from_levels(curve_from_points(&points), Some(0.1), Some(0.9));
+        assert_eq!(lut.lut[20], 0);
+        assert_eq!(lut.lut[240], 255);
+    }
 
     #[test]
     fn test_identity_lut() {
@@ -178,7 +548,7 @@ mod tests {
             points: vec![CurvePoint::new(0.0, 1.0), CurvePoint::new(1.0, 0.0)],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         let image = JsDecodedImage::new(1, 1, vec![0, 128, 255]);
         let result = apply_tone_curve(&image, &lut);
@@ -189,6 +559,20 @@ mod tests {
         assert!(pixels[2] < 5, "White should map to black");
     }
 
+    #[test]
+    fn test_apply_tone_curve_preview_matches_full_at_shift_zero() {
+        let curve = ToneCurve {
+            points: vec![CurvePoint::new(0.0, 1.0), CurvePoint::new(1.0, 0.0)],
+        };
+        let lut = JsToneCurveLut::from(ToneCurveLut::from_curve(&curve));
+        let image = JsDecodedImage::from_decoded(literoom_core::testing::noise(9, 7, 1));
+
+        let full = apply_tone_curve_preview(&image, &lut, 0).unwrap();
+        assert_eq!(full.pixels(), apply_tone_curve(&image, &lut).pixels());
+        let quarter = apply_tone_curve_preview(&image, &lut, 2).unwrap();
+        assert_eq!((quarter.width(), quarter.height()), (2, 1));
+    }
+
     // ========================================================================
     // Additional Identity LUT Tests
     // ========================================================================
@@ -244,7 +628,7 @@ mod tests {
             points: vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(1.0, 1.0)],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // Should produce identity or near-identity LUT
         let data = lut.get_lut();
@@ -273,7 +657,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         let data = lut.get_lut();
         // Shadows should be darker (value at 64 should be less than 64)
@@ -304,7 +688,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         let data = lut.get_lut();
         // Black input (0) should map to approximately 0.2 * 255 = 51
@@ -335,7 +719,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         let image = JsDecodedImage::new(1, 1, vec![100, 100, 100]);
         let result = apply_tone_curve(&image, &lut);
@@ -355,7 +739,7 @@ mod tests {
             points: vec![CurvePoint::new(0.0, 1.0), CurvePoint::new(1.0, 0.0)],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // 2x2 image with 4 pixels
         let image = JsDecodedImage::new(2, 2, vec![
@@ -390,7 +774,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // Single pixel with same value in all channels
         let image = JsDecodedImage::new(1, 1, vec![128, 128, 128]);
@@ -418,7 +802,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // All black image
         let image = JsDecodedImage::new(2, 2, vec![0; 12]);
@@ -441,7 +825,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // All white image
         let image = JsDecodedImage::new(2, 2, vec![255; 12]);
@@ -461,7 +845,7 @@ mod tests {
             points: vec![CurvePoint::new(0.0, 0.0), CurvePoint::new(1.0, 1.0)],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // Gradient from black to white (8 pixels)
         let gradient: Vec<u8> = (0..8).flat_map(|i| {
@@ -503,7 +887,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // Verify LUT is generated without panics and has correct length
         let data = lut.get_lut();
@@ -525,7 +909,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         let data = lut.get_lut();
         // Verify LUT has correct length
@@ -580,7 +964,7 @@ mod tests {
 
         for (curve_idx, curve) in test_curves.iter().enumerate() {
             let inner = ToneCurveLut::from_curve(curve);
-            let lut = JsToneCurveLut { inner };
+            let lut = JsToneCurveLut::from(inner);
             let data = lut.get_lut();
 
             // Verify LUT has correct length (256 entries)
@@ -623,7 +1007,7 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // Apply to first image
         let image1 = JsDecodedImage::new(1, 1, vec![100, 100, 100]);
@@ -670,12 +1054,8 @@ mod tests {
             ],
         };
 
-        let lut1 = JsToneCurveLut {
-            inner: ToneCurveLut::from_curve(&curve1),
-        };
-        let lut2 = JsToneCurveLut {
-            inner: ToneCurveLut::from_curve(&curve2),
-        };
+        let lut1 = JsToneCurveLut::from(ToneCurveLut::from_curve(&curve1));
+        let lut2 = JsToneCurveLut::from(ToneCurveLut::from_curve(&curve2));
 
         // Apply curves in sequence
         let image = JsDecodedImage::new(1, 1, vec![128, 128, 128]);
@@ -715,7 +1095,7 @@ mod tests {
 
         // Build LUT
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         // Verify LUT properties
         assert!(!lut.is_identity(), "S-curve should not be identity");
@@ -760,13 +1140,138 @@ mod tests {
             ],
         };
         let inner = ToneCurveLut::from_curve(&curve);
-        let lut = JsToneCurveLut { inner };
+        let lut = JsToneCurveLut::from(inner);
 
         assert!(
             !lut.is_identity(),
             "LUT with modified endpoints should not be identity"
         );
     }
+
+    #[test]
+    fn test_set_points_matches_fresh_lut() {
+        let points = vec![
+            CurvePointJs { x: 0.0, y: 0.0 },
+            CurvePointJs { x: 0.25, y: 0.15 },
+            CurvePointJs { x: 0.75, y: 0.85 },
+            CurvePointJs { x: 1.0, y: 1.0 },
+        ];
+        let mut lut = JsToneCurveLut::identity();
+        lut.set_points(&points).unwrap();
+
+        assert_eq!(lut.get_lut(), lut_from_points(&points).lut.to_vec());
+    }
+
+    #[test]
+    fn test_set_points_identity_transitions() {
+        let mut lut = JsToneCurveLut::identity();
+        lut.set_points(&[
+            CurvePointJs { x: 0.0, y: 0.2 },
+            CurvePointJs { x: 1.0, y: 1.0 },
+        ])
+        .unwrap();
+        assert!(!lut.is_identity());
+
+        lut.set_points(&[
+            CurvePointJs { x: 0.0, y: 0.0 },
+            CurvePointJs { x: 1.0, y: 1.0 },
+        ])
+        .unwrap();
+        assert!(lut.is_identity());
+    }
+
+    #[test]
+    fn test_smoothing_follows_updates() {
+        let step = [
+            CurvePointJs { x: 0.0, y: 0.0 },
+            CurvePointJs { x: 0.49, y: 0.0 },
+            CurvePointJs { x: 0.51, y: 1.0 },
+            CurvePointJs { x: 1.0, y: 1.0 },
+        ];
+        let mut exact = JsToneCurveLut::identity();
+        exact.set_points(&step).unwrap();
+        assert!(!exact.was_smoothed());
+        assert_eq!(exact.get_lut(), lut_from_points(&step).lut.to_vec());
+
+        let mut smooth = JsToneCurveLut::identity();
+        smooth.smooth = true;
+        smooth.set_points(&step).unwrap();
+        assert!(smooth.was_smoothed());
+        let max_step = smooth
+            .get_lut()
+            .windows(2)
+            .map(|w| w[1].abs_diff(w[0]))
+            .max();
+        assert_eq!(max_step, Some(DEFAULT_MAX_LUT_STEP));
+
+        // A gentle curve needs no smoothing, and says so
+        smooth
+            .set_points(&[
+                CurvePointJs { x: 0.0, y: 0.1 },
+                CurvePointJs { x: 1.0, y: 0.9 },
+            ])
+            .unwrap();
+        assert!(!smooth.was_smoothed());
+    }
+
+    #[test]
+    fn test_set_points_rejects_invalid_points_and_keeps_lut() {
+        let s_curve = [(0.0, 0.0), (0.25, 0.15), (0.75, 0.85), (1.0, 1.0)]
+            .map(|(x, y)| CurvePointJs { x, y });
+        let mut lut = JsToneCurveLut::identity();
+        lut.set_points(&s_curve).unwrap();
+        let before = lut.get_lut();
+
+        // One point per mouse move, never cleared
+        let runaway: Vec<CurvePointJs> = (0..50_000)
+            .map(|i| CurvePointJs {
+                x: i as f32 / 50_000.0,
+                y: 0.5,
+            })
+            .collect();
+        let err = lut.set_points(&runaway).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Tone curve has 50000 control points; at most 64 are allowed"
+        );
+        assert_eq!(lut.get_lut(), before);
+
+        let mut with_nan = s_curve.clone();
+        with_nan[2].x = f32::NAN;
+        let err = lut.set_points(&with_nan).unwrap_err();
+        assert_eq!(err, CurveError::NonFinitePoint { index: 2 });
+        assert_eq!(lut.get_lut(), before);
+    }
+
+    #[test]
+    fn test_set_points_clamps_out_of_range_points() {
+        let mut lut = JsToneCurveLut::identity();
+        lut.set_points(&[
+            CurvePointJs { x: -0.5, y: 0.2 },
+            CurvePointJs { x: 0.5, y: 1.5 },
+            CurvePointJs { x: 1.0, y: 1.0 },
+        ])
+        .unwrap();
+        assert!(lut.was_clamped());
+        let clamped = ToneCurve {
+            points: vec![
+                CurvePoint::new(0.0, 0.2),
+                CurvePoint::new(0.5, 1.0),
+                CurvePoint::new(1.0, 1.0),
+            ],
+        };
+        assert_eq!(
+            lut.get_lut(),
+            ToneCurveLut::from_curve(&clamped).lut.to_vec()
+        );
+
+        // Valid points clear the flag and build exactly the unchecked LUT
+        let s_curve = [(0.0, 0.0), (0.25, 0.15), (0.75, 0.85), (1.0, 1.0)]
+            .map(|(x, y)| CurvePointJs { x, y });
+        lut.set_points(&s_curve).unwrap();
+        assert!(!lut.was_clamped());
+        assert_eq!(lut.get_lut(), lut_from_points(&s_curve).lut.to_vec());
+    }
 }
 
 /// WASM-specific tests that require JsValue.
@@ -787,10 +1292,83 @@ mod wasm_tests {
         y: f32,
     }
 
+    #[wasm_bindgen_test]
+    fn test_apply_hue_curves_accepts_missing_curves() {
+        let image = JsDecodedImage::new(2, 1, vec![40, 200, 60, 200, 40, 40]);
+        let unchanged = apply_hue_curves(
+            &image,
+            JsValue::UNDEFINED,
+            JsValue::NULL,
+            JsValue::UNDEFINED,
+        )
+        .unwrap();
+        assert_eq!(unchanged.pixels(), image.pixels());
+
+        let points = serde_wasm_bindgen::to_value(&[TestCurvePoint { x: 120.0, y: 0.1 }]).unwrap();
+        let shifted =
+            apply_hue_curves(&image, points, JsValue::UNDEFINED, JsValue::UNDEFINED).unwrap();
+        assert_ne!(shifted.pixels()[0..3], image.pixels()[0..3]);
+        assert_eq!(shifted.pixels()[3..6], image.pixels()[3..6]);
+
+        let invalid = JsValue::from_str("not points");
+        assert!(apply_hue_curves(&image, invalid, JsValue::NULL, JsValue::NULL).is_err());
+    }
+
     // =========================================================================
     // JsToneCurveLut constructor tests
     // =========================================================================
 
+    #[wasm_bindgen_test]
+    fn test_create_lut_rejects_too_many_points() {
+        let points: Vec<TestCurvePoint> = (0..65)
+            .map(|i| TestCurvePoint {
+                x: i as f32 / 64.0,
+                y: i as f32 / 64.0,
+            })
+            .collect();
+        let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
+
+        let err: js_sys::Error = JsToneCurveLut::new(js_points, None, None, None)
+            .err()
+            .unwrap()
+            .into();
+        assert!(String::from(err.message()).contains("65 control points"));
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("TOO_MANY_CURVE_POINTS"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_create_lut_rejects_nan_point() {
+        let points = vec![
+            TestCurvePoint { x: 0.0, y: 0.0 },
+            TestCurvePoint {
+                x: f32::NAN,
+                y: 0.5,
+            },
+            TestCurvePoint { x: 1.0, y: 1.0 },
+        ];
+        let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
+
+        let err = JsToneCurveLut::new(js_points, None, None, None)
+            .err()
+            .unwrap();
+        let index = js_sys::Reflect::get(&err, &"index".into()).unwrap();
+        assert_eq!(index.as_f64(), Some(1.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_create_lut_clamps_out_of_range_points() {
+        let points = vec![
+            TestCurvePoint { x: 0.0, y: -0.1 },
+            TestCurvePoint { x: 1.2, y: 1.0 },
+        ];
+        let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
+
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
+        assert!(lut.was_clamped());
+        assert!(lut.is_identity());
+    }
+
     #[wasm_bindgen_test]
     fn test_create_lut_from_linear_curve() {
         // Identity curve: input = output
@@ -800,7 +1378,7 @@ mod wasm_tests {
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         assert!(result.is_ok());
 
         let lut = result.unwrap();
@@ -808,6 +1386,40 @@ mod wasm_tests {
         assert!(lut.is_identity());
     }
 
+    #[wasm_bindgen_test]
+    fn test_create_lut_with_input_black_and_white() {
+        let points = vec![
+            TestCurvePoint { x: 0.0, y: 0.0 },
+            TestCurvePoint { x: 1.0, y: 1.0 },
+        ];
+        let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
+
+        let lut = JsToneCurveLut::new(js_points, Some(0.25), Some(0.75), None).unwrap();
+        let lut_data = lut.get_lut();
+        assert_eq!(lut_data[63], 0);
+        assert!(lut_data[128].abs_diff(128) <= 1);
+        assert_eq!(lut_data[192], 255);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_new_with_smoothing() {
+        let points = vec![
+            CurvePointJs { x: 0.0, y: 0.0 },
+            CurvePointJs { x: 0.49, y: 0.0 },
+            CurvePointJs { x: 0.51, y: 1.0 },
+            CurvePointJs { x: 1.0, y: 1.0 },
+        ];
+        let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
+
+        let exact = JsToneCurveLut::new(js_points.clone(), None, None, None).unwrap();
+        assert!(!exact.was_smoothed());
+        assert_eq!(exact.get_lut(), lut_from_points(&points).lut.to_vec());
+
+        let smooth = JsToneCurveLut::new(js_points, None, None, Some(true)).unwrap();
+        assert!(smooth.was_smoothed());
+        assert!(smooth.get_lut().windows(2).all(|w| w[1] - w[0] <= 8));
+    }
+
     #[wasm_bindgen_test]
     fn test_create_lut_from_s_curve() {
         // S-curve for contrast boost: darken shadows, brighten highlights
@@ -819,7 +1431,7 @@ mod wasm_tests {
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         assert!(result.is_ok());
 
         let lut = result.unwrap();
@@ -843,7 +1455,7 @@ mod wasm_tests {
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         assert!(result.is_ok());
 
         let lut = result.unwrap();
@@ -869,7 +1481,7 @@ mod wasm_tests {
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         assert!(result.is_ok());
 
         let lut = result.unwrap();
@@ -885,16 +1497,75 @@ mod wasm_tests {
         // Pass a string instead of array
         let invalid = serde_wasm_bindgen::to_value(&"not an array").unwrap();
 
-        let result = JsToneCurveLut::new(invalid);
+        let result = JsToneCurveLut::new(invalid, None, None, None);
         assert!(result.is_err());
     }
 
+    #[wasm_bindgen_test]
+    fn test_update_rebuilds_lut() {
+        let linear = vec![
+            TestCurvePoint { x: 0.0, y: 0.0 },
+            TestCurvePoint { x: 1.0, y: 1.0 },
+        ];
+        let s_curve = vec![
+            TestCurvePoint { x: 0.0, y: 0.0 },
+            TestCurvePoint { x: 0.25, y: 0.15 },
+            TestCurvePoint { x: 0.75, y: 0.85 },
+            TestCurvePoint { x: 1.0, y: 1.0 },
+        ];
+        let mut lut = JsToneCurveLut::new(
+            serde_wasm_bindgen::to_value(&linear).unwrap(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        lut.update(serde_wasm_bindgen::to_value(&s_curve).unwrap())
+            .unwrap();
+        let fresh = JsToneCurveLut::new(
+            serde_wasm_bindgen::to_value(&s_curve).unwrap(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(lut.get_lut(), fresh.get_lut());
+        assert!(!lut.is_identity());
+
+        lut.update(serde_wasm_bindgen::to_value(&linear).unwrap())
+            .unwrap();
+        assert!(lut.is_identity());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_update_invalid_points_keeps_previous_lut() {
+        let s_curve = vec![
+            TestCurvePoint { x: 0.0, y: 0.0 },
+            TestCurvePoint { x: 0.25, y: 0.15 },
+            TestCurvePoint { x: 0.75, y: 0.85 },
+            TestCurvePoint { x: 1.0, y: 1.0 },
+        ];
+        let mut lut = JsToneCurveLut::new(
+            serde_wasm_bindgen::to_value(&s_curve).unwrap(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let before = lut.get_lut();
+
+        let invalid = serde_wasm_bindgen::to_value(&"not an array").unwrap();
+        assert!(lut.update(invalid).is_err());
+        assert_eq!(lut.get_lut(), before);
+    }
+
     #[wasm_bindgen_test]
     fn test_create_lut_empty_points_array() {
         let empty: Vec<TestCurvePoint> = vec![];
         let js_points = serde_wasm_bindgen::to_value(&empty).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         // Empty array should deserialize OK but create a degenerate curve
         // The actual behavior depends on core implementation
         assert!(result.is_ok());
@@ -905,7 +1576,7 @@ mod wasm_tests {
         // Pass a number instead of object array
         let invalid = serde_wasm_bindgen::to_value(&42).unwrap();
 
-        let result = JsToneCurveLut::new(invalid);
+        let result = JsToneCurveLut::new(invalid, None, None, None);
         assert!(result.is_err());
     }
 
@@ -919,7 +1590,7 @@ mod wasm_tests {
         let points = vec![PointMissingX { y: 0.5 }];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         assert!(result.is_err());
     }
 
@@ -933,7 +1604,7 @@ mod wasm_tests {
         let points = vec![PointMissingY { x: 0.5 }];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         assert!(result.is_err());
     }
 
@@ -951,7 +1622,7 @@ mod wasm_tests {
         }];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
-        let result = JsToneCurveLut::new(js_points);
+        let result = JsToneCurveLut::new(js_points, None, None, None);
         assert!(result.is_err());
     }
 
@@ -988,7 +1659,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         assert!(lut.is_identity());
     }
@@ -1002,7 +1673,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         assert!(!lut.is_identity());
     }
@@ -1014,7 +1685,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 0.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         assert!(!lut.is_identity());
     }
@@ -1048,7 +1719,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 0.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         let data = lut.get_lut();
 
@@ -1069,7 +1740,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         let data = lut.get_lut();
         assert_eq!(data.len(), 256);
@@ -1119,7 +1790,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         // Create image with known values
         let image = JsDecodedImage::new(1, 2, vec![64, 64, 64, 192, 192, 192]);
@@ -1140,7 +1811,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 0.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         let image = JsDecodedImage::new(1, 1, vec![0, 128, 255]);
         let result = apply_tone_curve(&image, &lut);
@@ -1197,7 +1868,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         // Small image
         let small = JsDecodedImage::new(2, 2, vec![128u8; 2 * 2 * 3]);
@@ -1264,7 +1935,7 @@ mod wasm_tests {
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
 
         // Step 2: Create LUT
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
         assert!(!lut.is_identity());
         assert_eq!(lut.get_lut().len(), 256);
 
@@ -1314,7 +1985,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_contrast = serde_wasm_bindgen::to_value(&contrast_points).unwrap();
-        let contrast_lut = JsToneCurveLut::new(js_contrast).unwrap();
+        let contrast_lut = JsToneCurveLut::new(js_contrast, None, None, None).unwrap();
         let after_contrast = apply_tone_curve(&image, &contrast_lut);
 
         // 128 is midtone - with this S-curve it should still be close to 128
@@ -1336,7 +2007,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_brighten = serde_wasm_bindgen::to_value(&brighten_points).unwrap();
-        let brighten_lut = JsToneCurveLut::new(js_brighten).unwrap();
+        let brighten_lut = JsToneCurveLut::new(js_brighten, None, None, None).unwrap();
         let step1 = apply_tone_curve(&image, &brighten_lut);
 
         // Second curve: slight darken
@@ -1345,7 +2016,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 0.9 },
         ];
         let js_darken = serde_wasm_bindgen::to_value(&darken_points).unwrap();
-        let darken_lut = JsToneCurveLut::new(js_darken).unwrap();
+        let darken_lut = JsToneCurveLut::new(js_darken, None, None, None).unwrap();
         let step2 = apply_tone_curve(&step1, &darken_lut);
 
         // Verify dimensions preserved through chain
@@ -1363,7 +2034,7 @@ mod wasm_tests {
             TestCurvePoint { x: 1.0, y: 1.0 },
         ];
         let js_points = serde_wasm_bindgen::to_value(&points).unwrap();
-        let lut = JsToneCurveLut::new(js_points).unwrap();
+        let lut = JsToneCurveLut::new(js_points, None, None, None).unwrap();
 
         // Apply to multiple images
         let image1 = JsDecodedImage::new(10, 10, vec![100u8; 10 * 10 * 3]);
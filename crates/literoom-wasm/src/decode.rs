@@ -6,12 +6,36 @@
 //! # Functions
 //!
 //! - [`decode_jpeg`] - Decode a JPEG image from bytes
+//! - [`decode_jpeg_with_options`] - Decode a JPEG, optionally skipping ICC color conversion
+//! - [`decode_jpeg_tolerant`] - Decode a possibly truncated JPEG, keeping what can be recovered
+//! - [`decode_jpeg_region`] - Decode only a rectangle of a JPEG for the loupe view
 //! - [`extract_raw_thumbnail_bytes`] - Extract embedded JPEG bytes from a RAW file
 //! - [`decode_raw_thumbnail`] - Extract and decode the embedded thumbnail from a RAW file
 //! - [`is_raw_file`] - Check if bytes represent a RAW file (TIFF-based)
+//! - [`extract_heif_preview_bytes`] - Extract the embedded JPEG preview from a HEIC/HEIF file
+//! - [`decode_heif_preview`] - Extract and decode the embedded preview from a HEIC/HEIF file
+//! - [`is_heif_file`] - Check if bytes represent a HEIC/HEIF file
+//! - [`has_gain_map`] - Check if a JPEG carries an Ultra HDR gain map
+//! - [`extract_gain_map`] - Extract the gain map JPEG and its metadata
+//! - [`decode_jpeg_with_gain_map`] - Decode a JPEG with its gain map applied
+//! - [`import_canvas_pixels`] - Import canvas RGBA data (e.g. a pasted image) as sRGB
+//! - [`get_as_shot_white_balance`] - Read the camera's white balance in Kelvin
+//! - [`probe_images`] - Read type, dimensions and orientation of many files from headers
+//! - [`classify_file`] - Recognize a file's type from its first bytes, e.g. PDFs and videos
+//! - [`set_max_file_bytes`] - Set the largest file the decode functions accept
 //! - [`resize`] - Resize an image to exact dimensions
 //! - [`resize_to_fit`] - Resize an image to fit within a max edge, preserving aspect ratio
 //! - [`generate_thumbnail`] - Generate a thumbnail for grid display
+//! - [`canonical_preview`] - Downscale the same way for thumbnails and previews, so they match
+//! - [`extract_region_scaled`] - Crop and scale a region in one pass for zoom and pan
+//! - [`open_file`] - Detect, decode, preview, thumbnail, histogram and metadata in one call
+//! - [`decode_auto`] - Decode any supported format, reporting which decoder succeeded
+//!
+//! Every function that decodes file bytes classifies them first. Empty
+//! files and types no decoder reads (PDF, video, ZIP, GIF, WebP) fail at
+//! once with the `code` `UNSUPPORTED_FILE_TYPE` and a `file_type` property
+//! naming what was found, and files over the size limit fail with
+//! `FILE_TOO_LARGE`, before any decoder scans the buffer.
 //!
 //! # Example
 //!
@@ -30,14 +54,46 @@
 //! }
 //! ```
 
-use crate::types::{filter_from_u8, JsDecodedImage};
-use literoom_core::decode;
+use crate::histogram::JsHistogram;
+use crate::telemetry;
+use crate::types::{
+    canvas_color_space_from_u8, decode_error_to_js, filter_from_u8, image_error_to_js,
+    open_file_error_to_js, JsDecodedImage,
+};
+use literoom_core::decode::{
+    self, CanvasColorSpace, ColorProfile, DecodeWarnings, DecodedResult, FileClassification,
+    ImageProbe, OpenedFile,
+};
+use literoom_core::edit::CropRect;
+use literoom_core::mask::PixelRect;
+use serde::Serialize;
+use std::cell::Cell;
 use wasm_bindgen::prelude::*;
 
+thread_local! {
+    static MAX_FILE_BYTES: Cell<u64> = const { Cell::new(decode::DEFAULT_MAX_FILE_BYTES) };
+}
+
+/// Refuse empty, unsupported and oversized files before decoding them.
+fn screen_file(bytes: &[u8]) -> Result<(), decode::DecodeError> {
+    decode::classify_file(bytes, bytes.len() as u64).check(MAX_FILE_BYTES.with(Cell::get))
+}
+
+/// [`screen_file`] with the error converted for JavaScript.
+fn screen_file_js(bytes: &[u8]) -> Result<(), JsValue> {
+    screen_file(bytes).map_err(|e| decode_error_to_js(&e))
+}
+
 /// Decode a JPEG image from bytes.
 ///
 /// This function decodes JPEG data and automatically applies EXIF orientation
-/// correction to ensure the image is displayed correctly.
+/// correction to ensure the image is displayed correctly. Files tagged with an
+/// Adobe RGB or Display P3 ICC profile are converted to sRGB, and the profile
+/// name is available as `color_profile` on the result.
+///
+/// An EXIF orientation outside 1-8, as written by some broken phone
+/// firmware, is ignored: the image is decoded upright and the value is
+/// reported as `decode_warnings.invalid_orientation`.
 ///
 /// # Arguments
 ///
@@ -53,6 +109,11 @@ use wasm_bindgen::prelude::*;
 /// Returns an error if:
 /// - The bytes are not valid JPEG data
 /// - The JPEG is corrupted or truncated
+/// - The JPEG uses a variant that cannot be decoded (12-bit samples, lossless,
+///   hierarchical or arithmetic coding); the error's `code` is
+///   `UNSUPPORTED_VARIANT`
+///
+/// CMYK JPEGs are converted to RGB.
 ///
 /// # Example
 ///
@@ -63,9 +124,118 @@ use wasm_bindgen::prelude::*;
 /// ```
 #[wasm_bindgen]
 pub fn decode_jpeg(bytes: &[u8]) -> Result<JsDecodedImage, JsValue> {
-    decode::decode_jpeg(bytes)
+    decode_jpeg_with_options(bytes, true)
+}
+
+/// Decode a JPEG image from bytes, choosing whether to convert to sRGB.
+///
+/// EXIF orientation is always applied. Pass `convert_to_srgb = false` to get
+/// the raw channel values as stored in the file; `color_profile` is still
+/// reported so the caller can handle the conversion itself.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw JPEG file bytes as a `Uint8Array`
+/// * `convert_to_srgb` - Convert pixels from a recognized ICC profile to sRGB
+///
+/// # Example
+///
+/// ```typescript
+/// const image = decode_jpeg_with_options(bytes, false);
+/// console.log(`Stored in ${image.color_profile ?? 'untagged'} values`);
+/// ```
+#[wasm_bindgen]
+pub fn decode_jpeg_with_options(
+    bytes: &[u8],
+    convert_to_srgb: bool,
+) -> Result<JsDecodedImage, JsValue> {
+    screen_file_js(bytes)?;
+    let options = decode::JpegDecodeOptions {
+        convert_to_srgb,
+        ..Default::default()
+    };
+    let (image, metadata) =
+        decode::decode_jpeg_with_options(bytes, &options).map_err(|e| decode_error_to_js(&e))?;
+    let image = JsDecodedImage::from_decoded(image).with_color_profile(metadata.color_profile);
+    Ok(match metadata.invalid_orientation {
+        Some(value) => image.with_decode_warnings(DecodeWarnings {
+            rows_decoded: metadata.height,
+            total_rows: metadata.height,
+            truncated: false,
+            invalid_orientation: Some(value),
+        }),
+        None => image,
+    })
+}
+
+/// Decode a JPEG that may be truncated, e.g. from a corrupted card.
+///
+/// Rows that could be decoded are kept and the rest of the image is filled
+/// with mid-gray, so a damaged file still gets a thumbnail instead of a
+/// blank cell. Otherwise the same as `decode_jpeg`.
+///
+/// # Returns
+///
+/// The decoded image with `decode_warnings` set to
+/// `{ rows_decoded, total_rows, truncated, invalid_orientation }`. Rows are
+/// counted from the top of the image as stored, before orientation
+/// correction.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not a JPEG or end before the image
+/// data starts.
+///
+/// # Example
+///
+/// ```typescript
+/// const image = decode_jpeg_tolerant(bytes);
+/// if (image.decode_warnings.truncated) {
+///   showDamagedBadge(image.decode_warnings.rows_decoded / image.decode_warnings.total_rows);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn decode_jpeg_tolerant(bytes: &[u8]) -> Result<JsDecodedImage, JsValue> {
+    screen_file_js(bytes)?;
+    decode::decode_jpeg_tolerant(bytes)
+        .map(|(image, warnings)| JsDecodedImage::from_decoded(image).with_decode_warnings(warnings))
+        .map_err(|e| decode_error_to_js(&e))
+}
+
+/// Decode only a rectangle of a JPEG, e.g. the window of a 100% loupe.
+///
+/// Only the rows covering the region are decoded (progressive JPEGs are
+/// decoded whole), so this is much cheaper than `decode_jpeg` followed by a
+/// crop on large files. The pixels are the same as that crop.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw JPEG file bytes as a `Uint8Array`
+/// * `region` - `{ x, y, width, height }` in pixels of the image as stored
+///
+/// The region is not affected by EXIF orientation and the result is not
+/// rotated: map the displayed rect to stored pixels first, and orient the
+/// result like the rest of the view.
+///
+/// # Errors
+///
+/// Returns an error if the region cannot be parsed; otherwise an `Error`
+/// with a `code` property: `REGION_OUT_OF_BOUNDS` if the region is empty or
+/// reaches outside the image, or the codes of `decode_jpeg`.
+///
+/// # Example
+///
+/// ```typescript
+/// const loupe = decode_jpeg_region(bytes, { x: 2048, y: 1536, width: 1024, height: 1024 });
+/// ```
+#[wasm_bindgen]
+pub fn decode_jpeg_region(bytes: &[u8], region: JsValue) -> Result<JsDecodedImage, JsValue> {
+    let region: PixelRect = serde_wasm_bindgen::from_value(region)
+        .map_err(|e| JsValue::from_str(&format!("Invalid region: {}", e)))?;
+    screen_file_js(bytes)?;
+    decode::decode_jpeg_region(bytes, &region)
         .map(JsDecodedImage::from_decoded)
-        .map_err(|e| JsValue::from_str(&e.to_string()))
+        .map_err(|e| decode_error_to_js(&e))
 }
 
 /// Extract the embedded JPEG thumbnail bytes from a RAW file.
@@ -97,7 +267,8 @@ pub fn decode_jpeg(bytes: &[u8]) -> Result<JsDecodedImage, JsValue> {
 /// ```
 #[wasm_bindgen]
 pub fn extract_raw_thumbnail_bytes(bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
-    decode::extract_raw_thumbnail(bytes).map_err(|e| JsValue::from_str(&e.to_string()))
+    screen_file_js(bytes)?;
+    decode::extract_raw_thumbnail(bytes).map_err(|e| decode_error_to_js(&e))
 }
 
 /// Extract and decode the embedded thumbnail from a RAW file.
@@ -136,9 +307,10 @@ pub fn extract_raw_thumbnail_bytes(bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
 /// ```
 #[wasm_bindgen]
 pub fn decode_raw_thumbnail(bytes: &[u8]) -> Result<JsDecodedImage, JsValue> {
+    screen_file_js(bytes)?;
     decode::decode_raw_thumbnail(bytes)
         .map(JsDecodedImage::from_decoded)
-        .map_err(|e| JsValue::from_str(&e.to_string()))
+        .map_err(|e| decode_error_to_js(&e))
 }
 
 /// Check if bytes represent a RAW file (TIFF-based format).
@@ -169,6 +341,415 @@ pub fn is_raw_file(bytes: &[u8]) -> bool {
     decode::is_raw_file(bytes)
 }
 
+/// Extract the embedded JPEG preview bytes from a HEIC/HEIF file.
+///
+/// HEVC image data cannot be decoded in the browser, but HEIC files usually
+/// carry a JPEG thumbnail in their EXIF block and sometimes a JPEG-coded
+/// preview item. The best of these is returned.
+///
+/// # Arguments
+///
+/// * `bytes` - The HEIC/HEIF file bytes as a `Uint8Array`
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The file is not a valid HEIF container
+/// - The image is only available as HEVC (message starts with "Unsupported codec")
+/// - No embedded preview is found
+///
+/// # Example
+///
+/// ```typescript
+/// const jpegBytes = extract_heif_preview_bytes(heicBytes);
+/// ```
+#[wasm_bindgen]
+pub fn extract_heif_preview_bytes(bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    screen_file_js(bytes)?;
+    decode::extract_heif_preview(bytes).map_err(|e| decode_error_to_js(&e))
+}
+
+/// Extract and decode the embedded JPEG preview from a HEIC/HEIF file.
+///
+/// Combines `extract_heif_preview_bytes` and `decode_jpeg`.
+///
+/// # Arguments
+///
+/// * `bytes` - The HEIC/HEIF file bytes as a `Uint8Array`
+///
+/// # Errors
+///
+/// Same as `extract_heif_preview_bytes`, plus JPEG decode errors.
+///
+/// # Example
+///
+/// ```typescript
+/// if (is_heif_file(bytes)) {
+///   try {
+///     const preview = decode_heif_preview(bytes);
+///   } catch (e) {
+///     // "Unsupported codec: HEVC" - show a placeholder instead
+///   }
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn decode_heif_preview(bytes: &[u8]) -> Result<JsDecodedImage, JsValue> {
+    screen_file_js(bytes)?;
+    decode::decode_heif_preview(bytes)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| decode_error_to_js(&e))
+}
+
+/// Import RGBA pixels read back from a canvas, such as a pasted image.
+///
+/// Safari reports wide-gamut canvases as Display P3; those pixels are
+/// converted to sRGB so colors match the rest of the pipeline. Alpha is
+/// dropped without blending.
+///
+/// # Arguments
+///
+/// * `rgba` - Straight RGBA data from `getImageData` (4 bytes per pixel)
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `source_space` - Canvas color space: 0 = sRGB, 1 = Display P3, any
+///   other value = unknown (imported as is, with `color_unmanaged` set)
+///
+/// # Errors
+///
+/// Throws an `Error` with `code` `"EMPTY_IMAGE"` or
+/// `"BUFFER_SIZE_MISMATCH"` if the buffer does not match the dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const data = ctx.getImageData(0, 0, width, height);
+/// const space = data.colorSpace === 'display-p3' ? 1 : 0;
+/// const image = import_canvas_pixels(data.data, width, height, space);
+/// ```
+#[wasm_bindgen]
+pub fn import_canvas_pixels(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    source_space: u8,
+) -> Result<JsDecodedImage, JsValue> {
+    let space = canvas_color_space_from_u8(source_space);
+    let image = decode::DecodedImage::from_rgba_with_space(width, height, rgba, space)
+        .map_err(|e| image_error_to_js(&e))?;
+    let profile =
+        (space == CanvasColorSpace::DisplayP3).then(|| ColorProfile::DisplayP3.name().to_string());
+    Ok(JsDecodedImage::from_decoded(image)
+        .with_color_profile(profile)
+        .with_color_unmanaged(!space.is_managed()))
+}
+
+/// Check if bytes represent a HEIC/HEIF file.
+///
+/// Examines the `ftyp` box at the start of the file for a HEIF brand.
+///
+/// # Arguments
+///
+/// * `bytes` - The file bytes to check (the first 32 bytes are enough)
+///
+/// # Example
+///
+/// ```typescript
+/// if (is_heif_file(bytes)) {
+///   const preview = decode_heif_preview(bytes);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn is_heif_file(bytes: &[u8]) -> bool {
+    decode::is_heif_file(bytes)
+}
+
+/// Check whether a JPEG carries an Ultra HDR gain map.
+///
+/// Only the marker segments are read, so this is cheap enough to run on
+/// import.
+///
+/// # Example
+///
+/// ```typescript
+/// if (has_gain_map(bytes)) showHdrBadge();
+/// ```
+#[wasm_bindgen]
+pub fn has_gain_map(bytes: &[u8]) -> bool {
+    decode::has_gain_map(bytes)
+}
+
+/// A gain map extracted from an Ultra HDR JPEG.
+///
+/// Gain and capacity values are log2 of the linear boost.
+#[wasm_bindgen]
+pub struct JsGainMap {
+    inner: decode::GainMap,
+}
+
+#[wasm_bindgen]
+impl JsGainMap {
+    /// Get the encoded gain map JPEG.
+    pub fn jpeg(&self) -> Vec<u8> {
+        self.inner.jpeg.clone()
+    }
+
+    /// Log2 boost encoded by a gain map value of 0.
+    #[wasm_bindgen(getter)]
+    pub fn gain_map_min(&self) -> f32 {
+        self.inner.metadata.gain_map_min
+    }
+
+    /// Log2 boost encoded by a gain map value of 1.
+    #[wasm_bindgen(getter)]
+    pub fn gain_map_max(&self) -> f32 {
+        self.inner.metadata.gain_map_max
+    }
+
+    /// Gamma the gain map values were encoded with.
+    #[wasm_bindgen(getter)]
+    pub fn gamma(&self) -> f32 {
+        self.inner.metadata.gamma
+    }
+
+    /// Log2 display boost below which the gain map is not applied.
+    #[wasm_bindgen(getter)]
+    pub fn hdr_capacity_min(&self) -> f32 {
+        self.inner.metadata.hdr_capacity_min
+    }
+
+    /// Log2 display boost at which the gain map is fully applied.
+    #[wasm_bindgen(getter)]
+    pub fn hdr_capacity_max(&self) -> f32 {
+        self.inner.metadata.hdr_capacity_max
+    }
+
+    /// Weight to pass to `decode_jpeg_with_gain_map` for a display offering
+    /// `display_boost` times SDR white (0 = SDR, 1 = full HDR).
+    pub fn weight_for_display_boost(&self, display_boost: f32) -> f32 {
+        self.inner.metadata.weight_for_display_boost(display_boost)
+    }
+}
+
+/// Extract the gain map JPEG and its metadata from an Ultra HDR file.
+///
+/// # Errors
+///
+/// Returns an error if the file has no gain map ("No gain map found") or
+/// its Multi-Picture index points outside the file.
+///
+/// # Example
+///
+/// ```typescript
+/// const gainMap = extract_gain_map(bytes);
+/// const weight = gainMap.weight_for_display_boost(displayHeadroom);
+/// ```
+#[wasm_bindgen]
+pub fn extract_gain_map(bytes: &[u8]) -> Result<JsGainMap, JsValue> {
+    screen_file_js(bytes)?;
+    decode::extract_gain_map(bytes)
+        .map(|inner| JsGainMap { inner })
+        .map_err(|e| decode_error_to_js(&e))
+}
+
+/// Decode a JPEG and multiply its Ultra HDR gain map in with `weight`.
+///
+/// The result is still 8-bit sRGB, so highlights boosted past white clip.
+/// A weight of 0, or a file without a gain map, decodes exactly like
+/// `decode_jpeg`.
+///
+/// # Arguments
+///
+/// * `bytes` - The JPEG file bytes as a `Uint8Array`
+/// * `weight` - How much of the gain map to apply, 0.0 to 1.0
+///
+/// # Example
+///
+/// ```typescript
+/// const image = decode_jpeg_with_gain_map(bytes, 1.0);
+/// ```
+#[wasm_bindgen]
+pub fn decode_jpeg_with_gain_map(bytes: &[u8], weight: f32) -> Result<JsDecodedImage, JsValue> {
+    screen_file_js(bytes)?;
+    decode::decode_jpeg_with_gain_map(bytes, weight)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| decode_error_to_js(&e))
+}
+
+/// Read the as-shot white balance from a file's metadata.
+///
+/// Uses the DNG `AsShotWhiteXY` tag when present, otherwise the EXIF
+/// `LightSource` preset.
+///
+/// # Arguments
+///
+/// * `bytes` - The file bytes (JPEG or TIFF-based RAW)
+///
+/// # Returns
+///
+/// `{ kelvin, tint, source }`, or `null` if the camera recorded no
+/// usable white balance (e.g. auto without a light source).
+///
+/// # Example
+///
+/// ```typescript
+/// const asShot = get_as_shot_white_balance(bytes);
+/// kelvinSlider.value = asShot?.kelvin ?? 6500;
+/// ```
+#[wasm_bindgen]
+pub fn get_as_shot_white_balance(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    match decode::get_as_shot_white_balance(bytes) {
+        Some(wb) => {
+            serde_wasm_bindgen::to_value(&wb).map_err(|e| JsValue::from_str(&e.to_string()))
+        }
+        None => Ok(JsValue::NULL),
+    }
+}
+
+/// Header probe result returned to JavaScript.
+#[derive(Debug, Clone, Serialize)]
+struct JsImageProbe {
+    /// Format, stored dimensions and orientation
+    #[serde(flatten)]
+    probe: ImageProbe,
+    /// Width after orientation, if known
+    display_width: Option<u32>,
+    /// Height after orientation, if known
+    display_height: Option<u32>,
+}
+
+impl From<ImageProbe> for JsImageProbe {
+    fn from(probe: ImageProbe) -> Self {
+        let display = probe.oriented_dimensions();
+        Self {
+            probe,
+            display_width: display.map(|(w, _)| w),
+            display_height: display.map(|(_, h)| h),
+        }
+    }
+}
+
+/// Read the type, dimensions and orientation of many files from their headers.
+///
+/// Nothing is decoded, so this is fast enough to lay out the import grid
+/// with correctly shaped placeholders. Only the first 128KB of each buffer
+/// is read; passing just that slice of each file is enough.
+///
+/// # Arguments
+///
+/// * `files` - Array of `Uint8Array`s, one per file
+///
+/// # Returns
+///
+/// One object per input, in order: `{ format, width, height, orientation,
+/// display_width, display_height }`. `format` is `"jpeg"`, `"tiff_raw"`,
+/// `"png"` or `"unknown"`; dimensions are `null` when the header does not
+/// contain them. Unrecognized or truncated files never fail the batch, and
+/// entries that are not `Uint8Array`s report `"unknown"`.
+///
+/// # Errors
+///
+/// Returns an error if `files` is not an array.
+///
+/// # Example
+///
+/// ```typescript
+/// const heads = await Promise.all(files.map(async (f) =>
+///   new Uint8Array(await f.slice(0, 128 * 1024).arrayBuffer())));
+/// probe_images(heads).forEach((p, i) => {
+///   placeholders[i].aspect = p.display_width && p.display_height
+///     ? p.display_width / p.display_height
+///     : 3 / 2;
+/// });
+/// ```
+#[wasm_bindgen]
+pub fn probe_images(files: JsValue) -> Result<JsValue, JsValue> {
+    if !js_sys::Array::is_array(&files) {
+        return Err(JsValue::from_str("Invalid files: expected an array"));
+    }
+    let probes: Vec<JsImageProbe> = js_sys::Array::from(&files)
+        .iter()
+        .map(|file| {
+            let head = match file.dyn_into::<js_sys::Uint8Array>() {
+                Ok(array) => {
+                    let len = array.length().min(decode::PROBE_BYTES as u32);
+                    array.subarray(0, len).to_vec()
+                }
+                Err(_) => Vec::new(),
+            };
+            decode::probe_image(&head).into()
+        })
+        .collect();
+    serde_wasm_bindgen::to_value(&probes).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// File classification returned to JavaScript.
+#[derive(Debug, Clone, Serialize)]
+struct JsFileClassification {
+    /// Detected type and file size
+    #[serde(flatten)]
+    classification: FileClassification,
+    /// Whether a decoder reads this type
+    supported: bool,
+}
+
+/// Recognize a file's type from its first bytes, without reading the rest.
+///
+/// Lets the importer turn away PDFs, videos, archives and empty files with a
+/// clear message before reading them into memory. Only the first 64 bytes
+/// are examined.
+///
+/// # Arguments
+///
+/// * `bytes_prefix` - The start of the file, e.g. `file.slice(0, 64)`
+/// * `total_len` - Size of the whole file in bytes
+///
+/// # Returns
+///
+/// `{ file_type, size, supported }`, where `file_type` is one of `"jpeg"`,
+/// `"tiff"`, `"heif"`, `"png"`, `"gif"`, `"webp"`, `"pdf"`, `"mp4"`,
+/// `"quick_time"`, `"zip"`, `"empty"` or `"unknown"`.
+///
+/// # Example
+///
+/// ```typescript
+/// const head = new Uint8Array(await file.slice(0, 64).arrayBuffer());
+/// const { file_type, supported } = classify_file(head, file.size);
+/// if (!supported && file_type !== 'unknown') skipWithNotice(file.name, file_type);
+/// ```
+#[wasm_bindgen]
+pub fn classify_file(bytes_prefix: &[u8], total_len: f64) -> Result<JsValue, JsValue> {
+    let classification = decode::classify_file(bytes_prefix, total_len.max(0.0) as u64);
+    let result = JsFileClassification {
+        classification,
+        supported: classification.file_type.is_supported(),
+    };
+    serde_wasm_bindgen::to_value(&result)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize classification: {}", e)))
+}
+
+/// Set the largest file the decode functions accept.
+///
+/// Larger files fail with the `code` `FILE_TOO_LARGE` before they are
+/// scanned. The default is 1 GiB.
+///
+/// # Errors
+///
+/// Returns an error if `max_bytes` is not a positive number.
+///
+/// # Example
+///
+/// ```typescript
+/// set_max_file_bytes(512 * 1024 * 1024);
+/// ```
+#[wasm_bindgen]
+pub fn set_max_file_bytes(max_bytes: f64) -> Result<(), JsValue> {
+    if max_bytes.is_nan() || max_bytes < 1.0 {
+        return Err(JsValue::from_str("max_bytes must be a positive number"));
+    }
+    MAX_FILE_BYTES.with(|max| max.set(max_bytes as u64));
+    Ok(())
+}
+
 /// Resize an image to exact dimensions.
 ///
 /// This function resizes the image to the specified width and height, regardless
@@ -190,6 +771,8 @@ pub fn is_raw_file(bytes: &[u8]) -> bool {
 ///
 /// Returns an error if:
 /// - Width or height is zero
+/// - The source image is empty or its buffer does not match its dimensions
+///   (thrown as an `Error` with a `code` property)
 ///
 /// # Example
 ///
@@ -203,10 +786,12 @@ pub fn resize(
     height: u32,
     filter: u8,
 ) -> Result<JsDecodedImage, JsValue> {
-    let decoded = image.to_decoded();
+    image.validate()?;
+    let decoded = image.as_decoded();
     let filter_type = filter_from_u8(filter);
+    let _params = telemetry::describe(|| format!("{}x{} {:?}", width, height, filter_type));
 
-    decode::resize(&decoded, width, height, filter_type)
+    decode::resize(decoded, width, height, filter_type)
         .map(JsDecodedImage::from_decoded)
         .map_err(|e| JsValue::from_str(&e.to_string()))
 }
@@ -244,10 +829,12 @@ pub fn resize_to_fit(
     max_edge: u32,
     filter: u8,
 ) -> Result<JsDecodedImage, JsValue> {
-    let decoded = image.to_decoded();
+    image.validate()?;
+    let decoded = image.as_decoded();
     let filter_type = filter_from_u8(filter);
+    let _params = telemetry::describe(|| format!("max_edge={} {:?}", max_edge, filter_type));
 
-    decode::resize_to_fit(&decoded, max_edge, filter_type)
+    decode::resize_to_fit(decoded, max_edge, filter_type)
         .map(JsDecodedImage::from_decoded)
         .map_err(|e| JsValue::from_str(&e.to_string()))
 }
@@ -258,10 +845,14 @@ pub fn resize_to_fit(
 /// small thumbnail suitable for grid/filmstrip display. It preserves the
 /// aspect ratio and fits the image within a square of `size` pixels.
 ///
+/// With `detail` set, an edge-aware downscale is used instead, which keeps
+/// text and fine lines in screenshots and documents legible.
+///
 /// # Arguments
 ///
 /// * `image` - The source image
 /// * `size` - Target thumbnail size in pixels (both width and height max)
+/// * `detail` - Preserve fine detail (default: false)
 ///
 /// # Returns
 ///
@@ -273,16 +864,297 @@ pub fn resize_to_fit(
 /// ```typescript
 /// // Generate 256px thumbnails for the grid
 /// const thumb = generate_thumbnail(image, 256);
+///
+/// // Screenshots keep their text readable
+/// const screenshotThumb = generate_thumbnail(image, 256, true);
 /// ```
 #[wasm_bindgen]
-pub fn generate_thumbnail(image: &JsDecodedImage, size: u32) -> Result<JsDecodedImage, JsValue> {
-    let decoded = image.to_decoded();
+pub fn generate_thumbnail(
+    image: &JsDecodedImage,
+    size: u32,
+    detail: Option<bool>,
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    let decoded = image.as_decoded();
+
+    let thumbnail = if detail.unwrap_or(false) {
+        decode::generate_thumbnail_detail(decoded, size)
+    } else {
+        decode::generate_thumbnail(decoded, size)
+    };
+    thumbnail
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
 
-    decode::generate_thumbnail(&decoded, size)
+/// Downscale an image with the canonical resize shared by thumbnails and
+/// previews.
+///
+/// The grid thumbnail and the editor preview look different when they come
+/// from different resize chains. Making both with this function keeps them
+/// consistent: the preview downscaled to thumbnail size matches the
+/// thumbnail to within a level or two. Images that already fit are
+/// returned unchanged.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `target_edge` - Maximum size for the longest edge in pixels
+///
+/// # Errors
+///
+/// Returns an error if `target_edge` is zero, or if the image is empty or
+/// its buffer does not match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const preview = canonical_preview(image, 2560);
+/// const thumb = canonical_preview(image, 256);
+/// ```
+#[wasm_bindgen]
+pub fn canonical_preview(
+    image: &JsDecodedImage,
+    target_edge: u32,
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    let _params = telemetry::describe(|| format!("target_edge={}", target_edge));
+
+    decode::canonical_preview(image.as_decoded(), target_edge)
         .map(JsDecodedImage::from_decoded)
         .map_err(|e| JsValue::from_str(&e.to_string()))
 }
 
+/// Extract a region of an image scaled to exact output dimensions.
+///
+/// Crops and resizes in one pass, so the zoom view can fill the viewport on
+/// every pan tick without resizing the whole image. The region may start at
+/// a fractional pixel, so panning is smooth; parts of it outside the image
+/// repeat the edge pixels.
+///
+/// # Arguments
+///
+/// * `image` - The source image
+/// * `x`, `y` - Top-left corner of the region, normalized (0.0 to 1.0)
+/// * `w`, `h` - Size of the region, normalized
+/// * `out_w`, `out_h` - Output size in pixels
+/// * `filter` - Resize algorithm: 0=Nearest, 1=Bilinear (default), 2=Lanczos3
+///
+/// # Errors
+///
+/// Returns an error if the output size is zero, the region has no area, or
+/// the source image is empty or its buffer does not match its dimensions
+/// (thrown as an `Error` with a `code` property).
+///
+/// # Example
+///
+/// ```typescript
+/// // 2x zoom centered on (0.4, 0.6) into an 800x600 canvas
+/// const view = extract_region_scaled(image, 0.15, 0.35, 0.5, 0.5, 800, 600, 1);
+/// ```
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn extract_region_scaled(
+    image: &JsDecodedImage,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    out_w: u32,
+    out_h: u32,
+    filter: u8,
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    let rect = CropRect {
+        left: x,
+        top: y,
+        width: w,
+        height: h,
+    };
+
+    decode::extract_region_scaled(
+        image.as_decoded(),
+        &rect,
+        out_w,
+        out_h,
+        filter_from_u8(filter),
+    )
+    .map(JsDecodedImage::from_decoded)
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Result of [`open_file`]. Pieces that were not requested are `undefined`.
+#[wasm_bindgen]
+pub struct JsOpenedFile {
+    opened: OpenedFile,
+}
+
+#[wasm_bindgen]
+impl JsOpenedFile {
+    /// Detected format: `"jpeg"`, `"png"`, `"raw"` or `"heif"`.
+    #[wasm_bindgen(getter)]
+    pub fn format(&self) -> String {
+        self.opened.format.name().to_string()
+    }
+
+    /// Get the preview, resized to fit `preview_max_edge`.
+    pub fn preview(&self) -> Option<JsDecodedImage> {
+        self.opened
+            .preview
+            .clone()
+            .map(JsDecodedImage::from_decoded)
+    }
+
+    /// Get the thumbnail, fitted to `thumbnail_size`.
+    pub fn thumbnail(&self) -> Option<JsDecodedImage> {
+        self.opened
+            .thumbnail
+            .clone()
+            .map(JsDecodedImage::from_decoded)
+    }
+
+    /// Get the histogram of the preview (or of the full image without one).
+    pub fn histogram(&self) -> Option<JsHistogram> {
+        self.opened.histogram.clone().map(JsHistogram::from)
+    }
+
+    /// Get the metadata: `{ width, height, orientation, camera_make,
+    /// camera_model, date_taken, capture_timestamp_ms, iso, shutter_speed,
+    /// aperture, focal_length, color_profile }`, with `null` for fields the
+    /// file does not carry. `capture_timestamp_ms` includes the EXIF
+    /// subseconds and treats the camera's local time as UTC.
+    pub fn metadata(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.opened.metadata)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Open a file in one call: detect its format, decode it, and produce the
+/// requested preview, thumbnail, histogram and metadata.
+///
+/// Replaces the `is_raw_file` → decode → `resize_to_fit` →
+/// `compute_histogram` sequence with a single worker message. JPEGs are
+/// decoded directly, RAW and HEIF files through their embedded preview.
+/// Each piece equals what the individual function returns: the preview is
+/// `resize_to_fit` with Lanczos3, the thumbnail `generate_thumbnail` of the
+/// full decode. With `consistent_resize`, both are `canonical_preview`
+/// instead, so the grid thumbnail matches the editor preview.
+///
+/// # Arguments
+///
+/// * `bytes` - The file bytes as a `Uint8Array`
+/// * `options` - `{ preview_max_edge, thumbnail_size, want_histogram,
+///   want_metadata, consistent_resize }`; every field is optional and pieces
+///   are only computed when requested
+///
+/// # Errors
+///
+/// Throws an `Error` with a `stage` property naming the step that failed:
+/// `"detect"`, `"decode"`, `"preview"`, `"thumbnail"` or `"histogram"`.
+///
+/// # Example
+///
+/// ```typescript
+/// try {
+///   const opened = open_file(bytes, { preview_max_edge: 2560, want_histogram: true });
+///   editor.show(opened.preview(), opened.histogram());
+/// } catch (e) {
+///   reportImportFailure(file.name, e.stage, e.message);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn open_file(bytes: &[u8], options: JsValue) -> Result<JsOpenedFile, JsValue> {
+    let options: decode::OpenFileOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid open options: {}", e)))?;
+    screen_file(bytes).map_err(|source| {
+        open_file_error_to_js(&decode::OpenFileError {
+            stage: decode::OpenStage::Detect,
+            source,
+        })
+    })?;
+    decode::open_file(bytes, &options)
+        .map(|opened| JsOpenedFile { opened })
+        .map_err(|e| open_file_error_to_js(&e))
+}
+
+/// Result of [`decode_auto`].
+#[wasm_bindgen]
+pub struct JsDecodedResult {
+    result: DecodedResult,
+}
+
+#[wasm_bindgen]
+impl JsDecodedResult {
+    /// Get the decoded image.
+    pub fn image(&self) -> JsDecodedImage {
+        JsDecodedImage::from_decoded(self.result.image.clone())
+    }
+
+    /// Decoder that produced the image: `"jpeg"`, `"png"`, `"raw_embedded"`
+    /// or `"heif_embedded"`.
+    #[wasm_bindgen(getter)]
+    pub fn source(&self) -> String {
+        self.result.source.name().to_string()
+    }
+
+    /// Fallbacks taken on the way, in order: objects with a `kind` of
+    /// `"full_quality_unavailable"`, `"preview_from_ifd"` (with `ifd`),
+    /// `"preview_from_marker_scan"` or `"invalid_orientation"` (with
+    /// `value`). Empty when the preferred path worked.
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.result.warnings)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Decode any supported file, trying the decoders for its format in a fixed
+/// order and reporting which one succeeded.
+///
+/// Replaces "try the RAW thumbnail, else the JPEG" logic in the app. JPEGs
+/// and PNGs are decoded directly; RAW and HEIF files through their embedded
+/// preview, searched for in SubIFDs, then the IFD chain, then by a marker
+/// scan.
+///
+/// # Arguments
+///
+/// * `bytes` - The file bytes as a `Uint8Array`
+/// * `options` - `{ prefer, max_edge }`, both optional: `prefer` is
+///   `"embedded_preview"` (default) or `"full_quality"`, and `max_edge`
+///   resizes the result to fit (Lanczos3)
+///
+/// A JPEG decoded with `prefer: "full_quality"` is the same as
+/// `decode_jpeg`. RAW and HEIF files cannot be decoded at full quality yet,
+/// so their preview is used with a `"full_quality_unavailable"` warning.
+///
+/// # Errors
+///
+/// Returns an error if the options cannot be parsed; otherwise an `Error`
+/// with a `code` property: `"INVALID_FORMAT"` for unrecognized files,
+/// `"NO_DECODER_SUCCEEDED"` with an `attempts` array when a RAW or HEIF file
+/// has no usable preview, or the codes of `decode_jpeg`.
+///
+/// # Example
+///
+/// ```typescript
+/// const decoded = decode_auto(bytes, { max_edge: 2560 });
+/// if (decoded.warnings.length > 0) console.info(decoded.source, decoded.warnings);
+/// editor.show(decoded.image());
+/// ```
+#[wasm_bindgen]
+pub fn decode_auto(bytes: &[u8], options: JsValue) -> Result<JsDecodedResult, JsValue> {
+    let options: decode::AutoDecodeOptions = if options.is_undefined() || options.is_null() {
+        Default::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid decode options: {}", e)))?
+    };
+    screen_file_js(bytes)?;
+    decode::decode_auto(bytes, &options)
+        .map(|result| JsDecodedResult { result })
+        .map_err(|e| decode_error_to_js(&e))
+}
+
 /// Tests for decode bindings.
 ///
 /// These tests verify the decode bindings work correctly on native targets by
@@ -291,7 +1163,76 @@ pub fn generate_thumbnail(image: &JsDecodedImage, size: u32) -> Result<JsDecoded
 #[cfg(test)]
 mod tests {
     use super::*;
-    use literoom_core::decode::{self, DecodedImage, FilterType};
+    use literoom_core::decode::{self, DecodedImage, FilterType, Orientation, ProbeFormat};
+
+    // =========================================================================
+    // Gain map tests
+    // =========================================================================
+
+    #[test]
+    fn test_plain_jpeg_has_no_gain_map() {
+        let pixels = vec![120u8; 8 * 8 * 3];
+        let jpeg = literoom_core::encode::encode_jpeg(&pixels, 8, 8, 90).unwrap();
+        assert!(!has_gain_map(&jpeg));
+
+        let plain = decode_jpeg(&jpeg).unwrap();
+        let with_map = decode_jpeg_with_gain_map(&jpeg, 1.0).unwrap();
+        assert_eq!(with_map.pixels(), plain.pixels());
+    }
+
+    #[test]
+    fn test_js_gain_map_accessors() {
+        let gain_map = JsGainMap {
+            inner: decode::GainMap {
+                jpeg: vec![0xFF, 0xD8, 0xFF, 0xD9],
+                metadata: decode::GainMapMetadata {
+                    gain_map_max: 2.0,
+                    hdr_capacity_max: 2.0,
+                    ..Default::default()
+                },
+            },
+        };
+        assert_eq!(gain_map.jpeg(), vec![0xFF, 0xD8, 0xFF, 0xD9]);
+        assert_eq!(gain_map.gain_map_max(), 2.0);
+        assert_eq!(gain_map.weight_for_display_boost(4.0), 1.0);
+        assert_eq!(gain_map.weight_for_display_boost(1.0), 0.0);
+    }
+
+    // =========================================================================
+    // Region extraction tests
+    // =========================================================================
+
+    #[test]
+    fn test_extract_region_scaled() {
+        let image = JsDecodedImage::new(100, 80, vec![90u8; 100 * 80 * 3]);
+        let view = extract_region_scaled(&image, 0.25, 0.25, 0.5, 0.5, 30, 20, 1).unwrap();
+        assert_eq!((view.width(), view.height()), (30, 20));
+        assert!(view.pixels().iter().all(|&v| v == 90));
+    }
+
+    // =========================================================================
+    // Header probe tests
+    // =========================================================================
+
+    #[test]
+    fn test_js_image_probe_display_dimensions() {
+        let probe = ImageProbe {
+            format: ProbeFormat::Jpeg,
+            width: Some(6000),
+            height: Some(4000),
+            orientation: Orientation::Rotate90CW,
+        };
+        let js = JsImageProbe::from(probe);
+        assert_eq!(js.display_width, Some(4000));
+        assert_eq!(js.display_height, Some(6000));
+
+        let unknown = JsImageProbe::from(ImageProbe {
+            height: None,
+            ..probe
+        });
+        assert_eq!(unknown.display_width, None);
+        assert_eq!(unknown.display_height, None);
+    }
 
     // =========================================================================
     // is_raw_file tests
@@ -899,6 +1840,29 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_is_heif_file() {
+        let ftyp = [
+            0x00, 0x00, 0x00, 0x18, b'f', b't', b'y', b'p', b'h', b'e', b'i', b'c', 0, 0, 0, 0,
+            b'm', b'i', b'f', b'1', b'h', b'e', b'i', b'c',
+        ];
+        assert!(is_heif_file(&ftyp));
+        assert!(!is_heif_file(&[0xFF, 0xD8, 0xFF, 0xE0]));
+        assert!(!is_raw_file(&ftyp));
+    }
+
+    #[test]
+    fn test_decode_jpeg_untagged_has_no_color_profile() {
+        let pixels = [60u8, 200, 60].repeat(16);
+        let jpeg = literoom_core::encode::encode_jpeg(&pixels, 4, 4, 95).unwrap();
+
+        let converted = decode_jpeg_with_options(&jpeg, true).unwrap();
+        let raw = decode_jpeg_with_options(&jpeg, false).unwrap();
+
+        assert_eq!(converted.color_profile(), None);
+        assert_eq!(converted.pixels(), raw.pixels());
+    }
+
     #[test]
     fn test_decode_jpeg_png_data() {
         // PNG magic bytes
@@ -1061,6 +2025,122 @@ mod tests {
             assert_eq!(result.pixels.len(), 50 * 50 * 3);
         }
     }
+
+    #[test]
+    fn test_import_canvas_pixels_flags_color_handling() {
+        let rgba = [255, 0, 0, 255, 128, 128, 128, 255];
+
+        let p3 = import_canvas_pixels(&rgba, 2, 1, 1).unwrap();
+        assert_eq!(p3.color_profile().as_deref(), Some("Display P3"));
+        assert!(!p3.color_unmanaged());
+        assert_eq!(&p3.pixels()[3..], [128, 128, 128]);
+
+        let srgb = import_canvas_pixels(&rgba, 2, 1, 0).unwrap();
+        assert_eq!(srgb.color_profile(), None);
+        assert_eq!(srgb.pixels(), vec![255, 0, 0, 128, 128, 128]);
+
+        let unknown = import_canvas_pixels(&rgba, 2, 1, 7).unwrap();
+        assert!(unknown.color_unmanaged());
+        assert_eq!(unknown.pixels(), srgb.pixels());
+    }
+
+    // =========================================================================
+    // open_file tests
+    // =========================================================================
+
+    // =========================================================================
+    // File screening tests
+    // =========================================================================
+
+    #[test]
+    fn test_screen_file_refuses_non_images() {
+        let pdf = b"%PDF-1.7\n1 0 obj\n";
+        let err = screen_file(pdf).unwrap_err();
+        assert_eq!(err.code(), "UNSUPPORTED_FILE_TYPE");
+        assert!(matches!(
+            screen_file(&[]),
+            Err(decode::DecodeError::UnsupportedFileType {
+                file_type: decode::FileType::Empty
+            })
+        ));
+
+        // A video header on a huge buffer is refused from the header alone
+        let mut video = vec![0u8; 256 * 1024 * 1024];
+        video[..16].copy_from_slice(b"\0\0\0\x10ftypisom\0\0\x02\0");
+        let start = std::time::Instant::now();
+        let err = screen_file(&video).unwrap_err();
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+        assert!(err.to_string().contains("MP4 video"), "{}", err);
+    }
+
+    #[test]
+    fn test_screen_file_passes_images_and_honors_limit() {
+        let image = literoom_core::testing::noise(16, 8, 3);
+        let jpeg = literoom_core::encode::encode_jpeg(&image.pixels, 16, 8, 90).unwrap();
+        assert!(screen_file(&jpeg).is_ok());
+        assert_eq!(decode_jpeg(&jpeg).unwrap().width(), 16);
+
+        set_max_file_bytes((jpeg.len() - 1) as f64).unwrap();
+        assert_eq!(screen_file(&jpeg).unwrap_err().code(), "FILE_TOO_LARGE");
+        set_max_file_bytes(decode::DEFAULT_MAX_FILE_BYTES as f64).unwrap();
+        assert!(screen_file(&jpeg).is_ok());
+    }
+
+    #[test]
+    fn test_canonical_preview_matches_core() {
+        let source = literoom_core::testing::noise(300, 200, 2);
+        let image = JsDecodedImage::from_decoded(source.clone());
+        let preview = canonical_preview(&image, 60).unwrap();
+        let expected = decode::canonical_preview(&source, 60).unwrap();
+        assert_eq!((preview.width(), preview.height()), (60, 40));
+        assert_eq!(preview.pixels(), expected.pixels);
+    }
+
+    #[test]
+    fn test_opened_file_matches_individual_bindings() {
+        let source = literoom_core::testing::noise(80, 40, 5);
+        let bytes = literoom_core::encode::encode_jpeg(&source.pixels, 80, 40, 90).unwrap();
+        let options = decode::OpenFileOptions {
+            preview_max_edge: Some(40),
+            thumbnail_size: Some(20),
+            want_histogram: true,
+            want_metadata: false,
+            consistent_resize: false,
+        };
+        let opened = JsOpenedFile {
+            opened: decode::open_file(&bytes, &options).unwrap(),
+        };
+        assert_eq!(opened.format(), "jpeg");
+
+        let image = decode_jpeg(&bytes).unwrap();
+        let preview = resize_to_fit(&image, 40, 2).unwrap();
+        assert_eq!(opened.preview().unwrap().pixels(), preview.pixels());
+        let thumbnail = generate_thumbnail(&image, 20, None).unwrap();
+        assert_eq!(opened.thumbnail().unwrap().pixels(), thumbnail.pixels());
+        let histogram = crate::histogram::compute_histogram(&preview.pixels(), 40, 20).unwrap();
+        assert_eq!(
+            opened.histogram().unwrap().luminance(),
+            histogram.luminance()
+        );
+    }
+
+    #[test]
+    fn test_decoded_result_accessors() {
+        let source = literoom_core::testing::noise(48, 32, 6);
+        let bytes = literoom_core::encode::encode_jpeg(&source.pixels, 48, 32, 90).unwrap();
+        let options = decode::AutoDecodeOptions {
+            prefer: decode::DecodePreference::FullQuality,
+            max_edge: None,
+        };
+        let decoded = JsDecodedResult {
+            result: decode::decode_auto(&bytes, &options).unwrap(),
+        };
+        assert_eq!(decoded.source(), "jpeg");
+        assert_eq!(
+            decoded.image().pixels(),
+            decode_jpeg(&bytes).unwrap().pixels()
+        );
+    }
 }
 
 /// WASM-specific tests that require JsValue.
@@ -1075,6 +2155,154 @@ mod wasm_tests {
 
     wasm_bindgen_test_configure!(run_in_browser);
 
+    #[wasm_bindgen_test]
+    fn test_decode_jpeg_tolerant_reports_truncation() {
+        let source = literoom_core::testing::noise(64, 48, 3);
+        let bytes = literoom_core::encode::encode_jpeg(&source.pixels, 64, 48, 90).unwrap();
+        let image = decode_jpeg_tolerant(&bytes[..bytes.len() / 2]).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 48));
+
+        let warnings = image.decode_warnings();
+        let field = |name: &str| js_sys::Reflect::get(&warnings, &name.into()).unwrap();
+        assert_eq!(field("truncated").as_bool(), Some(true));
+        assert_eq!(field("total_rows").as_f64(), Some(48.0));
+        assert!(field("rows_decoded").as_f64().unwrap() < 48.0);
+
+        let intact = decode_jpeg(&bytes).unwrap();
+        assert!(intact.decode_warnings().is_undefined());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decode_jpeg_reports_invalid_orientation() {
+        let source = literoom_core::testing::noise(24, 16, 3);
+        let plain = literoom_core::encode::encode_jpeg(&source.pixels, 24, 16, 90).unwrap();
+        // EXIF APP1 with a single SHORT orientation of 9
+        let mut app1 = b"Exif\0\0II\x2A\x00\x08\x00\x00\x00\x01\x00".to_vec();
+        app1.extend_from_slice(&[0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 9, 0]);
+        app1.extend_from_slice(&[0; 6]);
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        bytes.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        bytes.extend(app1);
+        bytes.extend_from_slice(&plain[2..]);
+
+        let image = decode_jpeg(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (24, 16));
+        let warnings = image.decode_warnings();
+        let field = |name: &str| js_sys::Reflect::get(&warnings, &name.into()).unwrap();
+        assert_eq!(field("invalid_orientation").as_f64(), Some(9.0));
+        assert_eq!(field("truncated").as_bool(), Some(false));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decode_jpeg_region() {
+        let source = literoom_core::testing::noise(64, 48, 3);
+        let bytes = literoom_core::encode::encode_jpeg(&source.pixels, 64, 48, 90).unwrap();
+        let region = |x: u32, y: u32, width: u32, height: u32| {
+            serde_wasm_bindgen::to_value(&PixelRect {
+                x,
+                y,
+                width,
+                height,
+            })
+            .unwrap()
+        };
+
+        let image = decode_jpeg_region(&bytes, region(10, 20, 30, 17)).unwrap();
+        assert_eq!((image.width(), image.height()), (30, 17));
+        let full = decode::decode_jpeg(&bytes).unwrap();
+        // First row of the region
+        let start = (20 * 64 + 10) * 3;
+        assert_eq!(image.pixels()[..30 * 3], full.pixels[start..start + 30 * 3]);
+
+        let err = decode_jpeg_region(&bytes, region(40, 0, 30, 1))
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("REGION_OUT_OF_BOUNDS"));
+        assert!(decode_jpeg_region(&bytes, JsValue::from_str("nope")).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decode_jpeg_reports_unsupported_variant_code() {
+        let source = literoom_core::testing::noise(16, 16, 3);
+        let mut bytes = literoom_core::encode::encode_jpeg(&source.pixels, 16, 16, 90).unwrap();
+        let sof = bytes.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        bytes[sof + 1] = 0xC1;
+        bytes[sof + 4] = 12;
+
+        let err = decode_jpeg(&bytes).err().unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("UNSUPPORTED_VARIANT"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_open_file_pieces_and_stage_errors() {
+        let source = literoom_core::testing::noise(64, 48, 3);
+        let bytes = literoom_core::encode::encode_jpeg(&source.pixels, 64, 48, 90).unwrap();
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"thumbnail_size".into(), &32.into()).unwrap();
+        js_sys::Reflect::set(&options, &"want_metadata".into(), &true.into()).unwrap();
+
+        let opened = open_file(&bytes, options.clone().into()).unwrap();
+        assert_eq!(opened.format(), "jpeg");
+        assert!(opened.preview().is_none());
+        assert!(opened.histogram().is_none());
+        assert_eq!(opened.thumbnail().unwrap().width(), 32);
+        let metadata = opened.metadata().unwrap();
+        let width = js_sys::Reflect::get(&metadata, &"width".into()).unwrap();
+        assert_eq!(width.as_f64(), Some(64.0));
+
+        let stage = |bytes: &[u8]| {
+            let err = open_file(bytes, options.clone().into()).err().unwrap();
+            js_sys::Reflect::get(&err, &"stage".into())
+                .unwrap()
+                .as_string()
+        };
+        assert_eq!(stage(b"garbage").as_deref(), Some("detect"));
+        assert_eq!(stage(&bytes[..100]).as_deref(), Some("decode"));
+        assert!(open_file(&bytes, JsValue::from_str("nope")).is_err());
+    }
+
+    // =========================================================================
+    // Header probe tests
+    // =========================================================================
+
+    #[wasm_bindgen_test]
+    fn test_probe_images_batch() {
+        // PNG signature + IHDR for a 300x200 image, truncated after IHDR
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&300u32.to_be_bytes());
+        png.extend_from_slice(&200u32.to_be_bytes());
+
+        let files = js_sys::Array::new();
+        files.push(&js_sys::Uint8Array::from(&png[..]));
+        files.push(&js_sys::Uint8Array::from(&b"garbage"[..]));
+        files.push(&JsValue::from_f64(42.0));
+
+        let result = js_sys::Array::from(&probe_images(files.into()).unwrap());
+        assert_eq!(result.length(), 3);
+
+        let field =
+            |i: u32, name: &str| js_sys::Reflect::get(&result.get(i), &name.into()).unwrap();
+        assert_eq!(field(0, "format").as_string().as_deref(), Some("png"));
+        assert_eq!(field(0, "display_width").as_f64(), Some(300.0));
+        assert_eq!(field(1, "format").as_string().as_deref(), Some("unknown"));
+        assert!(field(1, "width").is_null() || field(1, "width").is_undefined());
+        assert_eq!(field(2, "format").as_string().as_deref(), Some("unknown"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_extract_region_scaled_zero_output() {
+        let image = JsDecodedImage::new(10, 10, vec![0u8; 300]);
+        assert!(extract_region_scaled(&image, 0.0, 0.0, 1.0, 1.0, 0, 10, 1).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_probe_images_requires_array() {
+        assert!(probe_images(JsValue::from_str("nope")).is_err());
+    }
+
     // =========================================================================
     // JPEG decode error tests
     // =========================================================================
@@ -1104,6 +2332,18 @@ mod wasm_tests {
         assert!(result.is_err());
     }
 
+    #[wasm_bindgen_test]
+    fn test_get_as_shot_white_balance_missing_is_null() {
+        let result = get_as_shot_white_balance(&[0xFF, 0xD8, 0xFF, 0xD9]).unwrap();
+        assert!(result.is_null());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_extract_heif_preview_not_heif() {
+        let result = extract_heif_preview_bytes(&[0xFF, 0xD8, 0xFF, 0xE0]);
+        assert!(result.is_err());
+    }
+
     // =========================================================================
     // RAW thumbnail extraction error tests
     // =========================================================================
@@ -1331,7 +2571,7 @@ mod wasm_tests {
             pixels: vec![128u8; 400 * 300 * 3],
         });
 
-        let result = generate_thumbnail(&img, 100);
+        let result = generate_thumbnail(&img, 100, None);
         assert!(result.is_ok());
 
         let thumb = result.unwrap();
@@ -1340,6 +2580,20 @@ mod wasm_tests {
         assert_eq!(thumb.height(), 75);
     }
 
+    #[wasm_bindgen_test]
+    fn test_generate_thumbnail_detail() {
+        let img = JsDecodedImage::from_decoded(DecodedImage {
+            width: 400,
+            height: 300,
+            pixels: vec![128u8; 400 * 300 * 3],
+        });
+
+        let thumb = generate_thumbnail(&img, 100, Some(true)).unwrap();
+        assert_eq!(thumb.width(), 100);
+        assert_eq!(thumb.height(), 75);
+        assert!(thumb.pixels().iter().all(|&v| v == 128));
+    }
+
     #[wasm_bindgen_test]
     fn test_generate_thumbnail_portrait() {
         let img = JsDecodedImage::from_decoded(DecodedImage {
@@ -1348,7 +2602,7 @@ mod wasm_tests {
             pixels: vec![128u8; 300 * 400 * 3],
         });
 
-        let result = generate_thumbnail(&img, 100);
+        let result = generate_thumbnail(&img, 100, None);
         assert!(result.is_ok());
 
         let thumb = result.unwrap();
@@ -1365,7 +2619,7 @@ mod wasm_tests {
             pixels: vec![128u8; 400 * 400 * 3],
         });
 
-        let result = generate_thumbnail(&img, 100);
+        let result = generate_thumbnail(&img, 100, None);
         assert!(result.is_ok());
 
         let thumb = result.unwrap();
@@ -1381,7 +2635,7 @@ mod wasm_tests {
             pixels: vec![128u8; 50 * 50 * 3],
         });
 
-        let result = generate_thumbnail(&img, 100);
+        let result = generate_thumbnail(&img, 100, None);
         assert!(result.is_ok());
 
         let thumb = result.unwrap();
@@ -1400,7 +2654,7 @@ mod wasm_tests {
 
         // Test common thumbnail sizes
         for size in [64u32, 128, 256, 512] {
-            let result = generate_thumbnail(&img, size);
+            let result = generate_thumbnail(&img, size, None);
             assert!(result.is_ok());
 
             let thumb = result.unwrap();
@@ -1512,7 +2766,7 @@ mod wasm_tests {
         assert_eq!(preview.height(), 400);
 
         // Then generate thumbnail
-        let thumb = generate_thumbnail(&preview, 100).unwrap();
+        let thumb = generate_thumbnail(&preview, 100, None).unwrap();
         assert_eq!(thumb.width(), 100);
         assert_eq!(thumb.height(), 80);
     }
@@ -1532,4 +2786,36 @@ mod wasm_tests {
         assert_eq!(step3.width(), 64);
         assert_eq!(step3.height(), 64);
     }
+
+    #[wasm_bindgen_test]
+    fn test_import_canvas_pixels_rejects_wrong_length() {
+        let err = import_canvas_pixels(&[0; 15], 2, 2, 1).err().unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().unwrap(), "BUFFER_SIZE_MISMATCH");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decode_auto_reports_source_and_attempts() {
+        let source = literoom_core::testing::noise(64, 48, 3);
+        let bytes = literoom_core::encode::encode_jpeg(&source.pixels, 64, 48, 90).unwrap();
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"max_edge".into(), &32.into()).unwrap();
+        let decoded = decode_auto(&bytes, options.into()).unwrap();
+        assert_eq!(decoded.source(), "jpeg");
+        assert_eq!(decoded.image().width(), 32);
+        assert_eq!(
+            js_sys::Array::from(&decoded.warnings().unwrap()).length(),
+            0
+        );
+
+        // A TIFF with an empty IFD0 has no preview anywhere
+        let tiff = b"II\x2A\x00\x08\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+        let err = decode_auto(tiff, JsValue::UNDEFINED).err().unwrap();
+        let field = |name: &str| js_sys::Reflect::get(&err, &name.into()).unwrap();
+        assert_eq!(
+            field("code").as_string().as_deref(),
+            Some("NO_DECODER_SUCCEEDED")
+        );
+        assert_eq!(js_sys::Array::from(&field("attempts")).length(), 1);
+    }
 }
@@ -0,0 +1,327 @@
+//! Edit state serialization WASM bindings.
+//!
+//! This module exposes the literoom-core compact binary encoding of
+//! `EditState` to JavaScript, so autosave can store a few bytes per change
+//! instead of the full JSON state.
+//!
+//! # Functions
+//!
+//! - [`serialize_edit_state_binary`] - Encode a full edit state
+//! - [`deserialize_edit_state_binary`] - Decode a full edit state
+//! - [`encode_edit_state_delta`] - Encode only the fields changed between two states
+//! - [`apply_edit_state_delta`] - Rebuild a state from its base and a delta
+//! - [`apply_relative_adjustments`] - Nudge a state's adjustments (quick develop)
+//! - [`diff_stages`] - Which render stages differ between two states, so the
+//!   worker can resume from a cached intermediate with
+//!   [`render_from_stage`](crate::render::render_from_stage)
+//!
+//! # Example
+//!
+//! ```typescript
+//! import { encode_edit_state_delta, apply_edit_state_delta } from '@literoom/wasm';
+//!
+//! const delta = encode_edit_state_delta(prevState, nextState);
+//! await db.deltas.add({ assetId, delta });
+//!
+//! // Later, replay on top of the same base
+//! const restored = apply_edit_state_delta(prevState, delta);
+//! ```
+
+use literoom_core::edit::{self, EditState, StageDiff};
+use literoom_core::render::RenderStage;
+use literoom_core::BasicAdjustments;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Convert a JavaScript edit state object into the core type.
+pub(crate) fn edit_state_from_js(value: JsValue) -> Result<EditState, JsValue> {
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|e| JsValue::from_str(&format!("Invalid edit state: {}", e)))
+}
+
+/// Convert a core edit state into a JavaScript object.
+pub(crate) fn edit_state_to_js(state: &EditState) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(state)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize edit state: {}", e)))
+}
+
+/// Encode an edit state in the compact binary format.
+///
+/// # Arguments
+///
+/// * `state` - Edit state object (`adjustments`, `tone_curve`, `crop`,
+///   `rotation`, `linear_masks`, `radial_masks`; omitted fields use defaults)
+///
+/// # Returns
+///
+/// A `Uint8Array` with the encoded state. Unedited states encode to 4 bytes.
+///
+/// # Example
+///
+/// ```typescript
+/// const bytes = serialize_edit_state_binary(state);
+/// ```
+#[wasm_bindgen]
+pub fn serialize_edit_state_binary(state: JsValue) -> Result<Vec<u8>, JsValue> {
+    Ok(edit_state_from_js(state)?.to_bytes())
+}
+
+/// Decode an edit state produced by [`serialize_edit_state_binary`].
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not a valid full edit state encoding.
+///
+/// # Example
+///
+/// ```typescript
+/// const state = deserialize_edit_state_binary(bytes);
+/// ```
+#[wasm_bindgen]
+pub fn deserialize_edit_state_binary(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    let state = EditState::from_bytes(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    edit_state_to_js(&state)
+}
+
+/// Encode only the fields that changed between two edit states.
+///
+/// # Arguments
+///
+/// * `prev` - The base state the delta will later be applied to
+/// * `next` - The new state
+///
+/// # Returns
+///
+/// A `Uint8Array` delta. A single slider change is typically 13 bytes.
+///
+/// # Example
+///
+/// ```typescript
+/// const delta = encode_edit_state_delta(prevState, nextState);
+/// ```
+#[wasm_bindgen]
+pub fn encode_edit_state_delta(prev: JsValue, next: JsValue) -> Result<Vec<u8>, JsValue> {
+    let prev = edit_state_from_js(prev)?;
+    let next = edit_state_from_js(next)?;
+    Ok(edit::delta_encode(&prev, &next))
+}
+
+/// Apply a delta from [`encode_edit_state_delta`] to its base state.
+///
+/// # Errors
+///
+/// Returns an error if `prev` is not the state the delta was built against,
+/// or if the delta is malformed.
+///
+/// # Example
+///
+/// ```typescript
+/// const next = apply_edit_state_delta(prevState, delta);
+/// ```
+#[wasm_bindgen]
+pub fn apply_edit_state_delta(prev: JsValue, delta: &[u8]) -> Result<JsValue, JsValue> {
+    let prev = edit_state_from_js(prev)?;
+    let next = edit::delta_apply(&prev, delta).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    edit_state_to_js(&next)
+}
+
+/// Add relative adjustments to an edit state, as the quick develop panel does.
+///
+/// Each field of `delta` is added to the state's global adjustments and the
+/// result clamped to the range reported by `get_adjustment_ranges`. The tone
+/// curve, crop, rotation and masks are preserved.
+///
+/// # Arguments
+///
+/// * `state` - Edit state object (omitted fields use defaults)
+/// * `delta` - Partial adjustments object; omitted fields are not changed
+///
+/// # Returns
+///
+/// The updated edit state.
+///
+/// # Example
+///
+/// ```typescript
+/// // +1/3 EV and +10 contrast on every selected photo
+/// for (const photo of selection) {
+///   photo.edit = apply_relative_adjustments(photo.edit, { exposure: 1 / 3, contrast: 10 });
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn apply_relative_adjustments(state: JsValue, delta: JsValue) -> Result<JsValue, JsValue> {
+    let mut state = edit_state_from_js(state)?;
+    let delta: BasicAdjustments = serde_wasm_bindgen::from_value(delta)
+        .map_err(|e| JsValue::from_str(&format!("Invalid adjustments: {}", e)))?;
+    state.apply_relative(&delta);
+    edit_state_to_js(&state)
+}
+
+/// Stage comparison returned by [`diff_stages`].
+#[derive(Debug, Clone, Serialize)]
+pub struct JsStageDiff {
+    /// Per-stage flags: `transform`, `adjustments`, `effects`, `curve`,
+    /// `masks`
+    #[serde(flatten)]
+    pub diff: StageDiff,
+    /// Name of the stage to resume rendering from, or `null` when nothing
+    /// changed
+    pub first_dirty: Option<RenderStage>,
+}
+
+impl From<StageDiff> for JsStageDiff {
+    fn from(diff: StageDiff) -> Self {
+        Self {
+            diff,
+            first_dirty: diff.first_dirty(),
+        }
+    }
+}
+
+/// Report which render stages differ between two edit states.
+///
+/// Each flag says whether that stage's settings changed; a changed crop or
+/// rotation sets them all. Settings that do not change the render, such as
+/// disabled masks, are ignored.
+///
+/// # Returns
+///
+/// `{ transform, adjustments, effects, curve, masks, first_dirty }`, where
+/// `first_dirty` names the stage to resume from (or is `null`).
+///
+/// # Example
+///
+/// ```typescript
+/// const { first_dirty } = diff_stages(prevState, nextState);
+/// if (first_dirty) {
+///   preview = render_from_stage(cache.get(first_dirty), first_dirty, nextState);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn diff_stages(a: JsValue, b: JsValue) -> Result<JsValue, JsValue> {
+    let a = edit_state_from_js(a)?;
+    let b = edit_state_from_js(b)?;
+    let diff = JsStageDiff::from(edit::compare_edit_states_stages(&a, &b));
+    serde_wasm_bindgen::to_value(&diff)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize stage diff: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_diff_names_first_dirty_stage() {
+        let prev = EditState::default();
+        let mut next = prev.clone();
+        next.monochrome = true;
+
+        let diff = JsStageDiff::from(edit::compare_edit_states_stages(&prev, &next));
+        assert_eq!(diff.first_dirty, Some(RenderStage::Effects));
+        assert_eq!(diff.diff.dirty_stages(), vec![RenderStage::Effects]);
+        assert_eq!(JsStageDiff::from(StageDiff::default()).first_dirty, None);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn edited_state() -> EditState {
+        let mut state = EditState::default();
+        state.adjustments.exposure = 0.5;
+        state.rotation.angle = 90.0;
+        state
+    }
+
+    #[wasm_bindgen_test]
+    fn test_binary_round_trip() {
+        let js = edit_state_to_js(&edited_state()).unwrap();
+        let bytes = serialize_edit_state_binary(js).unwrap();
+        let restored = deserialize_edit_state_binary(&bytes).unwrap();
+        let restored: EditState = serde_wasm_bindgen::from_value(restored).unwrap();
+        assert_eq!(restored, edited_state());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_partial_object_uses_defaults() {
+        let bytes = serialize_edit_state_binary(js_sys::Object::new().into()).unwrap();
+        assert_eq!(bytes.len(), 4);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_delta_round_trip() {
+        let prev = EditState::default();
+        let delta = encode_edit_state_delta(
+            edit_state_to_js(&prev).unwrap(),
+            edit_state_to_js(&edited_state()).unwrap(),
+        )
+        .unwrap();
+
+        let next = apply_edit_state_delta(edit_state_to_js(&prev).unwrap(), &delta).unwrap();
+        let next: EditState = serde_wasm_bindgen::from_value(next).unwrap();
+        assert_eq!(next, edited_state());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_delta_wrong_base_errors() {
+        let delta = encode_edit_state_delta(
+            edit_state_to_js(&EditState::default()).unwrap(),
+            edit_state_to_js(&edited_state()).unwrap(),
+        )
+        .unwrap();
+
+        let result = apply_edit_state_delta(edit_state_to_js(&edited_state()).unwrap(), &delta);
+        assert!(result.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_diff_stages_from_js_objects() {
+        let prev = edit_state_to_js(&EditState::default()).unwrap();
+        let next = edit_state_to_js(&edited_state()).unwrap();
+
+        let diff = diff_stages(prev.clone(), next).unwrap();
+        let first = js_sys::Reflect::get(&diff, &"first_dirty".into()).unwrap();
+        assert_eq!(first.as_string().as_deref(), Some("transform"));
+
+        let clean = diff_stages(prev.clone(), prev).unwrap();
+        let first = js_sys::Reflect::get(&clean, &"first_dirty".into()).unwrap();
+        assert!(first.is_null());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_deserialize_garbage_errors() {
+        assert!(deserialize_edit_state_binary(&[1, 2, 3, 4, 5]).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_relative_adjustments_clamps_and_accumulates() {
+        let mut state = edit_state_to_js(&edited_state()).unwrap();
+        let delta = serde_wasm_bindgen::to_value(&BasicAdjustments {
+            exposure: 2.0,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            state = apply_relative_adjustments(state, delta.clone()).unwrap();
+        }
+        let state: EditState = serde_wasm_bindgen::from_value(state).unwrap();
+        assert_eq!(state.adjustments.exposure, 5.0);
+        assert_eq!(state.rotation.angle, 90.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_relative_adjustments_partial_delta() {
+        let delta = js_sys::Object::new();
+        js_sys::Reflect::set(&delta, &"contrast".into(), &10.0.into()).unwrap();
+
+        let state = apply_relative_adjustments(js_sys::Object::new().into(), delta.into()).unwrap();
+        let state: EditState = serde_wasm_bindgen::from_value(state).unwrap();
+        assert_eq!(state.adjustments.contrast, 10.0);
+        assert_eq!(state.adjustments.exposure, 0.0);
+    }
+}
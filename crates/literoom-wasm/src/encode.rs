@@ -7,6 +7,13 @@
 //!
 //! - [`encode_jpeg`] - Encode RGB pixel data to JPEG bytes
 //! - [`encode_jpeg_from_image`] - Encode a JsDecodedImage to JPEG bytes
+//! - [`encode_jpeg_with_taps`] - Encode a JsDecodedImage, also returning its histogram and a
+//!   thumbnail
+//! - [`encode_jpeg_streaming`] - Encode a JsDecodedImage, delivering the JPEG in chunks
+//! - [`rotate_jpeg_lossless`] - Rotate JPEG file bytes by quarter turns without re-encoding
+//! - [`crop_jpeg_lossless`] - Crop JPEG file bytes to MCU boundaries without re-encoding
+//! - [`export_tiled`] - Encode a very large image as a grid of JPEG tiles
+//! - [`validate_export_format`] - Check an export format before starting an export
 //!
 //! # Example
 //!
@@ -20,8 +27,14 @@
 //! const jpegBytes = encode_jpeg_from_image(image, 90);
 //! ```
 
-use crate::types::JsDecodedImage;
-use literoom_core::encode;
+use crate::histogram::JsHistogram;
+use crate::telemetry;
+use crate::types::{export_format_error_to_js, JsDecodedImage};
+use literoom_core::edit::CropRect;
+use literoom_core::encode::{self, EncodeError, ExportFormat};
+use literoom_core::export::{self, ExportError, ExportTaps, TappedExport};
+use literoom_core::mask::PixelRect;
+use literoom_core::validate::checked_buffer_len;
 use wasm_bindgen::prelude::*;
 
 /// Encode RGB pixel data to JPEG bytes.
@@ -69,6 +82,7 @@ pub fn encode_jpeg(
     height: u32,
     quality: u8,
 ) -> Result<Vec<u8>, JsValue> {
+    let _params = telemetry::describe(|| format!("quality={}", quality));
     encode::encode_jpeg(pixels, width, height, quality)
         .map_err(|e| JsValue::from_str(&e.to_string()))
 }
@@ -102,8 +116,447 @@ pub fn encode_jpeg(
 /// ```
 #[wasm_bindgen]
 pub fn encode_jpeg_from_image(image: &JsDecodedImage, quality: u8) -> Result<Vec<u8>, JsValue> {
-    let pixels = image.pixels();
-    encode::encode_jpeg(&pixels, image.width(), image.height(), quality)
+    let _params = telemetry::describe(|| format!("quality={}", quality));
+    let image = image.as_decoded();
+    encode::encode_jpeg(&image.pixels, image.width, image.height, quality)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// An exported file with the results of its taps.
+///
+/// Returned by [`encode_jpeg_with_taps`] and
+/// [`export_prepared_with_taps`](crate::render::export_prepared_with_taps).
+#[wasm_bindgen]
+pub struct JsExportResult {
+    inner: TappedExport,
+}
+
+#[wasm_bindgen]
+impl JsExportResult {
+    /// Get the encoded file.
+    #[wasm_bindgen(getter)]
+    pub fn bytes(&self) -> Vec<u8> {
+        self.inner.bytes.clone()
+    }
+
+    /// Get the histogram of the exported pixels, or `undefined` if it was
+    /// not requested.
+    #[wasm_bindgen(getter)]
+    pub fn histogram(&self) -> Option<JsHistogram> {
+        self.inner.histogram.clone().map(JsHistogram::from)
+    }
+
+    /// Get the thumbnail of the exported pixels, or `undefined` if it was
+    /// not requested.
+    #[wasm_bindgen(getter)]
+    pub fn thumbnail(&self) -> Option<JsDecodedImage> {
+        self.inner
+            .thumbnail
+            .clone()
+            .map(JsDecodedImage::from_decoded)
+    }
+}
+
+impl From<TappedExport> for JsExportResult {
+    fn from(inner: TappedExport) -> Self {
+        Self { inner }
+    }
+}
+
+/// Parse export taps `{ want_histogram, thumbnail_size }`; both are
+/// optional, and `undefined` or `null` requests none.
+pub(crate) fn export_taps_from_js(taps: JsValue) -> Result<ExportTaps, JsValue> {
+    if taps.is_undefined() || taps.is_null() {
+        return Ok(ExportTaps::default());
+    }
+    serde_wasm_bindgen::from_value(taps)
+        .map_err(|e| JsValue::from_str(&format!("Invalid export taps: {}", e)))
+}
+
+/// Parse and validate an export format `{ container, color_space,
+/// bit_depth, quality, chroma_subsampling }`; every field is optional, and
+/// `undefined` or `null` is the default 8-bit sRGB JPEG.
+pub(crate) fn export_format_from_js(format: JsValue) -> Result<ExportFormat, JsValue> {
+    let format: ExportFormat = if format.is_undefined() || format.is_null() {
+        ExportFormat::default()
+    } else {
+        serde_wasm_bindgen::from_value(format)
+            .map_err(|e| JsValue::from_str(&format!("Invalid export format: {}", e)))?
+    };
+    format
+        .validate()
+        .map_err(|e| export_format_error_to_js(&e))?;
+    Ok(format)
+}
+
+/// Convert an export error to a JavaScript error.
+///
+/// An invalid export format becomes an `Error` with a `code` property, as
+/// from [`validate_export_format`].
+pub(crate) fn export_error_to_js(err: &ExportError) -> JsValue {
+    match err {
+        ExportError::Encode(EncodeError::InvalidFormat(e)) => export_format_error_to_js(e),
+        _ => JsValue::from_str(&err.to_string()),
+    }
+}
+
+/// Check an export format before starting an export.
+///
+/// The export dialog calls this as settings change, so impossible
+/// combinations are reported before any photo is rendered. Missing fields
+/// take their defaults: a JPEG container, the sRGB color space, 8 bits per
+/// channel, quality 90 for JPEG and 4:4:4 chroma.
+///
+/// # Arguments
+///
+/// * `format` - `{ container, color_space, bit_depth, quality,
+///   chroma_subsampling }`: `container` is `"jpeg"` or `"png"`,
+///   `color_space` is `"srgb"`, `"display_p3"` or `"adobe_rgb"`, and
+///   `chroma_subsampling` is `"yuv444"` or `"yuv420"`
+///
+/// # Errors
+///
+/// Returns an error if the format cannot be parsed, or an `Error` with a
+/// `code` property if its container cannot hold it:
+/// `"UNSUPPORTED_BIT_DEPTH"` (with `bit_depth`), `"QUALITY_NOT_APPLICABLE"`,
+/// `"QUALITY_OUT_OF_RANGE"` (with `quality`), `"SUBSAMPLING_NOT_APPLICABLE"`
+/// or `"UNSUPPORTED_SUBSAMPLING"`.
+///
+/// # Example
+///
+/// ```typescript
+/// try {
+///   validate_export_format({ container: 'png', color_space: 'adobe_rgb', bit_depth: 16 });
+/// } catch (e) {
+///   showFormatError(e.code);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn validate_export_format(format: JsValue) -> Result<(), JsValue> {
+    export_format_from_js(format).map(|_| ())
+}
+
+/// Encode a JsDecodedImage to JPEG, computing its histogram and a thumbnail
+/// from the same pixels.
+///
+/// The taps read the pixels that are encoded, so an export can record its
+/// histogram and show a preview without decoding the JPEG again. The image
+/// data is the same as from [`encode_jpeg_from_image`]; the file also
+/// embeds the sRGB ICC profile, like any sRGB export.
+///
+/// # Arguments
+///
+/// * `image` - The image to encode
+/// * `quality` - JPEG quality (1-100)
+/// * `taps` - `{ want_histogram, thumbnail_size }`, both optional;
+///   `thumbnail_size` is the thumbnail's long edge as in `generate_thumbnail`
+///
+/// # Errors
+///
+/// Returns an error if the taps cannot be parsed, the image is empty or its
+/// buffer does not match its dimensions, `thumbnail_size` is zero, or
+/// encoding fails.
+///
+/// # Example
+///
+/// ```typescript
+/// const result = encode_jpeg_with_taps(image, 90, { want_histogram: true, thumbnail_size: 256 });
+/// await writeFile(result.bytes);
+/// manifest.histogram = result.histogram?.luminance();
+/// ```
+#[wasm_bindgen]
+pub fn encode_jpeg_with_taps(
+    image: &JsDecodedImage,
+    quality: u8,
+    taps: JsValue,
+) -> Result<JsExportResult, JsValue> {
+    let taps = export_taps_from_js(taps)?;
+    image.validate()?;
+    let _params = telemetry::describe(|| format!("quality={} {:?}", quality, taps));
+    let format = ExportFormat::jpeg(quality.clamp(1, 100));
+    export::encode_with_taps(image.as_decoded(), &format, &taps)
+        .map(JsExportResult::from)
+        .map_err(|e| export_error_to_js(&e))
+}
+
+/// Rows encoded per band by [`encode_jpeg_streaming`].
+const STREAM_BAND_ROWS: usize = 64;
+
+/// Encode a JsDecodedImage to JPEG, handing the bytes to a callback in chunks.
+///
+/// The image is encoded in bands of rows and `on_chunk` is called with a
+/// `Uint8Array` as soon as each part of the file is ready, so the export
+/// can write to disk while encoding continues. Concatenating the chunks
+/// gives exactly the bytes `encode_jpeg_from_image` would return.
+///
+/// # Arguments
+///
+/// * `image` - The decoded image to encode
+/// * `quality` - JPEG quality (1-100, where 100 is highest quality, recommended: 90)
+/// * `on_chunk` - Called with each chunk of the file, in order. The array
+///   is only valid during the call; copy it if it must be kept.
+///
+/// # Errors
+///
+/// Returns an error if encoding fails, or the first error thrown by
+/// `on_chunk` (no further chunks are delivered after it throws).
+///
+/// # Example
+///
+/// ```typescript
+/// const writable = await fileHandle.createWritable();
+/// const chunks: Uint8Array[] = [];
+/// encode_jpeg_streaming(processed, 90, (chunk) => chunks.push(chunk.slice()));
+/// for (const chunk of chunks) await writable.write(chunk);
+/// await writable.close();
+/// ```
+#[wasm_bindgen]
+pub fn encode_jpeg_streaming(
+    image: &JsDecodedImage,
+    quality: u8,
+    on_chunk: &js_sys::Function,
+) -> Result<(), JsValue> {
+    let mut callback_error = None;
+    let result = encode_in_bands(
+        &image.pixels(),
+        image.width(),
+        image.height(),
+        quality,
+        |chunk| {
+            if callback_error.is_none() {
+                let array = js_sys::Uint8Array::from(chunk);
+                if let Err(err) = on_chunk.call1(&JsValue::NULL, &array) {
+                    callback_error = Some(err);
+                }
+            }
+        },
+    );
+    if let Some(err) = callback_error {
+        return Err(err);
+    }
+    result.map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Feed `pixels` to a streaming encoder [`STREAM_BAND_ROWS`] rows at a time.
+fn encode_in_bands(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+    sink: impl FnMut(&[u8]),
+) -> Result<(), encode::EncodeError> {
+    if width == 0 || height == 0 {
+        return Err(encode::EncodeError::InvalidDimensions { width, height });
+    }
+    let expected = checked_buffer_len(width, height, 3)
+        .map_err(|e| encode::EncodeError::EncodingFailed(e.to_string()))?;
+    if pixels.len() != expected {
+        return Err(encode::EncodeError::InvalidPixelData {
+            expected,
+            actual: pixels.len(),
+        });
+    }
+
+    let mut encoder = encode::JpegStreamEncoder::new(width, height, quality, sink)?;
+    for band in pixels.chunks(width as usize * 3 * STREAM_BAND_ROWS) {
+        encoder.write_rows(band)?;
+    }
+    encoder.finish()
+}
+
+/// Rotate a JPEG file by quarter turns without re-encoding it.
+///
+/// Only the EXIF orientation tag is rewritten (or added), so the exported
+/// file keeps the original's quality and compressed data exactly. Use it
+/// for exports whose only edit is a rotation by a multiple of 90 degrees.
+///
+/// # Arguments
+///
+/// * `bytes` - JPEG file bytes
+/// * `quarter_turns` - Clockwise quarter turns (the edit state's rotation
+///   angle is counter-clockwise, so an angle of 90 is 3 quarter turns)
+///
+/// # Returns
+///
+/// A `Uint8Array` with the rotated file.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not a well-formed JPEG, or if the
+/// pixels were already rotated while the EXIF orientation still asks for a
+/// rotation. Fall back to decoding and re-encoding in either case.
+///
+/// # Example
+///
+/// ```typescript
+/// // Rotate 90 degrees clockwise for export
+/// const rotated = rotate_jpeg_lossless(originalBytes, 1);
+/// ```
+#[wasm_bindgen]
+pub fn rotate_jpeg_lossless(bytes: &[u8], quarter_turns: u8) -> Result<Vec<u8>, JsValue> {
+    encode::rotate_jpeg_lossless(bytes, quarter_turns)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Result of [`crop_jpeg_lossless`].
+#[wasm_bindgen]
+pub struct JsLosslessCrop {
+    bytes: Vec<u8>,
+    rect: PixelRect,
+    crop: CropRect,
+}
+
+#[wasm_bindgen]
+impl JsLosslessCrop {
+    /// Get the cropped JPEG file.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Get the crop actually applied as `{ x, y, width, height }`, in pixels
+    /// of the displayed image.
+    pub fn rect(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.rect)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize rect: {}", e)))
+    }
+
+    /// Get the crop actually applied as `{ left, top, width, height }`,
+    /// normalized like the edit state's crop.
+    pub fn crop(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.crop)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize crop: {}", e)))
+    }
+}
+
+/// Crop a JPEG file without re-encoding its pixels.
+///
+/// JPEG blocks cannot be split, so the crop's left and top edges move out
+/// to the nearest 8 or 16 pixel boundary; show the user the crop from the
+/// result, which is the one actually applied. Metadata is kept.
+///
+/// # Arguments
+///
+/// * `bytes` - JPEG file bytes
+/// * `left`, `top`, `width`, `height` - Requested crop, normalized (0.0 to
+///   1.0) to the image as displayed after its EXIF orientation
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not a well-formed JPEG, or if the JPEG
+/// is progressive or otherwise cannot be cropped this way (the message
+/// starts with "Unsupported JPEG"). Fall back to decoding and re-encoding
+/// in either case.
+///
+/// # Example
+///
+/// ```typescript
+/// const result = crop_jpeg_lossless(originalBytes, 0.1, 0.1, 0.8, 0.8);
+/// cropOverlay.set(result.crop());
+/// await writeFile(result.bytes());
+/// result.free();
+/// ```
+#[wasm_bindgen]
+pub fn crop_jpeg_lossless(
+    bytes: &[u8],
+    left: f32,
+    top: f32,
+    width: f32,
+    height: f32,
+) -> Result<JsLosslessCrop, JsValue> {
+    let crop = CropRect {
+        left,
+        top,
+        width,
+        height,
+    };
+    encode::crop_jpeg_lossless(bytes, crop)
+        .map(|result| JsLosslessCrop {
+            bytes: result.bytes,
+            rect: result.rect,
+            crop: result.crop,
+        })
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Result of [`export_tiled`].
+#[wasm_bindgen]
+pub struct JsTiledExport {
+    inner: encode::TiledExport,
+}
+
+#[wasm_bindgen]
+impl JsTiledExport {
+    /// Number of tiles.
+    #[wasm_bindgen(getter)]
+    pub fn tile_count(&self) -> usize {
+        self.inner.tiles.len()
+    }
+
+    /// Get the tiles as an array of `{ x, y, bytes }`, row by row from the
+    /// top left, where `x` and `y` are the tile's top-left corner in pixels
+    /// and `bytes` is a `Uint8Array` holding the tile's JPEG file.
+    pub fn tiles(&self) -> Result<js_sys::Array, JsValue> {
+        let tiles = js_sys::Array::new();
+        for (x, y, bytes) in &self.inner.tiles {
+            let tile = js_sys::Object::new();
+            js_sys::Reflect::set(&tile, &"x".into(), &(*x).into())?;
+            js_sys::Reflect::set(&tile, &"y".into(), &(*y).into())?;
+            js_sys::Reflect::set(
+                &tile,
+                &"bytes".into(),
+                &js_sys::Uint8Array::from(bytes.as_slice()).into(),
+            )?;
+            tiles.push(&tile);
+        }
+        Ok(tiles)
+    }
+
+    /// Get the layout as `{ width, height, tile_size, columns, rows, tiles }`,
+    /// where `tiles` lists every tile's `{ x, y, width, height }` in the same
+    /// order as [`tiles`](Self::tiles). Ready for `JSON.stringify`.
+    pub fn descriptor(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.inner.descriptor())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize descriptor: {}", e)))
+    }
+}
+
+/// Encode a very large image as a grid of JPEG tiles.
+///
+/// For panoramas too big to export as one JPEG. Tiles are `tile_size`
+/// pixels square except on the right and bottom edges, where they are
+/// smaller; a viewer places each tile at its `x`, `y` to show the whole
+/// image.
+///
+/// # Arguments
+///
+/// * `image` - The image to export
+/// * `tile_size` - Side of a full tile in pixels, e.g. 512 or 1024; a size
+///   larger than the image gives a single tile
+/// * `quality` - JPEG quality (1-100)
+///
+/// # Errors
+///
+/// Returns an error if `tile_size` is 0, the image is empty, or its buffer
+/// does not match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const exported = export_tiled(panorama, 1024, 90);
+/// for (const { x, y, bytes } of exported.tiles()) {
+///   await writeFile(`tiles/${x}_${y}.jpg`, bytes);
+/// }
+/// await writeFile('tiles/descriptor.json', JSON.stringify(exported.descriptor()));
+/// exported.free();
+/// ```
+#[wasm_bindgen]
+pub fn export_tiled(
+    image: &JsDecodedImage,
+    tile_size: u32,
+    quality: u8,
+) -> Result<JsTiledExport, JsValue> {
+    encode::export_tiled(image.as_decoded(), tile_size, quality)
+        .map(|inner| JsTiledExport { inner })
         .map_err(|e| JsValue::from_str(&e.to_string()))
 }
 
@@ -132,6 +585,17 @@ mod tests {
         assert_eq!(&jpeg[0..2], &[0xFF, 0xD8]);
     }
 
+    #[test]
+    fn test_export_tiled_matches_core() {
+        let source = literoom_core::testing::noise(40, 30, 2);
+        let image = JsDecodedImage::from_decoded(source.clone());
+
+        let exported = export_tiled(&image, 16, 85).unwrap();
+        let expected = encode::export_tiled(&source, 16, 85).unwrap();
+        assert_eq!(exported.tile_count(), 6);
+        assert_eq!(exported.inner.tiles, expected.tiles);
+    }
+
     #[test]
     fn test_encode_jpeg_small_image() {
         let img = JsDecodedImage::new(1, 1, vec![255, 0, 0]); // Red pixel
@@ -401,6 +865,43 @@ mod tests {
         let result = encode::encode_jpeg(&pixels, 10, 10, 255);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_encode_in_bands_matches_one_shot_encode() {
+        let (width, height) = (40, 150);
+        let pixels: Vec<u8> = (0..width * height * 3)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect();
+
+        let mut chunks = Vec::new();
+        encode_in_bands(&pixels, width as u32, height as u32, 90, |chunk| {
+            chunks.push(chunk.to_vec())
+        })
+        .unwrap();
+
+        let one_shot = encode::encode_jpeg(&pixels, width as u32, height as u32, 90).unwrap();
+        assert!(chunks.len() > 2);
+        assert_eq!(chunks.concat(), one_shot);
+    }
+
+    #[test]
+    fn test_encode_in_bands_rejects_wrong_pixel_length() {
+        let result = encode_in_bands(&[0u8; 10], 4, 4, 90, |_| {});
+        assert!(matches!(
+            result,
+            Err(encode::EncodeError::InvalidPixelData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rotate_jpeg_lossless_swaps_decoded_dimensions() {
+        let jpeg = encode::encode_jpeg(&vec![128u8; 30 * 20 * 3], 30, 20, 90).unwrap();
+
+        let rotated = rotate_jpeg_lossless(&jpeg, 3).unwrap();
+
+        let decoded = literoom_core::decode::decode_jpeg(&rotated).unwrap();
+        assert_eq!((decoded.width, decoded.height), (20, 30));
+    }
 }
 
 /// WASM-specific tests that require JsValue.
@@ -424,6 +925,90 @@ mod wasm_tests {
         assert_eq!(&jpeg[0..2], &[0xFF, 0xD8]);
     }
 
+    #[wasm_bindgen_test]
+    fn test_encode_jpeg_with_taps() {
+        let image = JsDecodedImage::from_decoded(literoom_core::testing::noise(32, 24, 3));
+        let taps = js_sys::Object::new();
+        js_sys::Reflect::set(&taps, &"thumbnail_size".into(), &16.into()).unwrap();
+
+        let result = encode_jpeg_with_taps(&image, 90, taps.into()).unwrap();
+        let expected = encode::encode_image(image.as_decoded(), &ExportFormat::jpeg(90)).unwrap();
+        assert_eq!(result.bytes(), expected);
+        assert!(result.histogram().is_none());
+        assert_eq!(result.thumbnail().unwrap().width(), 16);
+        assert!(encode_jpeg_with_taps(&image, 90, JsValue::from_str("all")).is_err());
+    }
+
+    fn error_code(err: &JsValue) -> String {
+        js_sys::Reflect::get(err, &"code".into())
+            .unwrap()
+            .as_string()
+            .unwrap()
+    }
+
+    fn format_object(fields: &[(&str, JsValue)]) -> JsValue {
+        let format = js_sys::Object::new();
+        for (key, value) in fields {
+            js_sys::Reflect::set(&format, &(*key).into(), value).unwrap();
+        }
+        format.into()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validate_export_format() {
+        assert!(validate_export_format(JsValue::UNDEFINED).is_ok());
+        assert!(validate_export_format(format_object(&[])).is_ok());
+        assert!(validate_export_format(format_object(&[
+            ("container", "png".into()),
+            ("color_space", "adobe_rgb".into()),
+            ("bit_depth", 16.into()),
+        ]))
+        .is_ok());
+
+        let err = validate_export_format(format_object(&[("bit_depth", 16.into())]))
+            .err()
+            .unwrap();
+        assert_eq!(error_code(&err), "UNSUPPORTED_BIT_DEPTH");
+        let bit_depth = js_sys::Reflect::get(&err, &"bit_depth".into()).unwrap();
+        assert_eq!(bit_depth.as_f64(), Some(16.0));
+
+        let err = validate_export_format(format_object(&[
+            ("container", "png".into()),
+            ("quality", 90.into()),
+        ]))
+        .err()
+        .unwrap();
+        assert_eq!(error_code(&err), "QUALITY_NOT_APPLICABLE");
+
+        let err = validate_export_format(format_object(&[("chroma_subsampling", "yuv420".into())]))
+            .err()
+            .unwrap();
+        assert_eq!(error_code(&err), "UNSUPPORTED_SUBSAMPLING");
+
+        // Unknown enum values fail to parse
+        assert!(validate_export_format(format_object(&[("container", "gif".into())])).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_export_tiled_tiles_and_descriptor() {
+        let image = JsDecodedImage::from_decoded(literoom_core::testing::noise(40, 30, 2));
+        let exported = export_tiled(&image, 16, 85).unwrap();
+
+        let tiles = exported.tiles().unwrap();
+        assert_eq!(tiles.length(), 6);
+        let last = tiles.get(5);
+        let field = |name: &str| js_sys::Reflect::get(&last, &name.into()).unwrap();
+        assert_eq!(field("x").as_f64(), Some(16.0));
+        assert_eq!(field("y").as_f64(), Some(16.0));
+        assert!(field("bytes").is_instance_of::<js_sys::Uint8Array>());
+
+        let descriptor = exported.descriptor().unwrap();
+        let columns = js_sys::Reflect::get(&descriptor, &"columns".into()).unwrap();
+        assert_eq!(columns.as_f64(), Some(3.0));
+
+        assert!(export_tiled(&image, 0, 85).is_err());
+    }
+
     #[wasm_bindgen_test]
     fn test_encode_jpeg_invalid_dimensions() {
         let pixels = vec![128u8; 100];
@@ -461,4 +1046,50 @@ mod wasm_tests {
         assert_eq!(&low[0..2], &[0xFF, 0xD8]);
         assert_eq!(&high[0..2], &[0xFF, 0xD8]);
     }
+
+    #[wasm_bindgen_test]
+    fn test_encode_jpeg_streaming_delivers_whole_file() {
+        let img = JsDecodedImage::new(50, 50, vec![128u8; 50 * 50 * 3]);
+        let chunks = js_sys::Array::new();
+        let push =
+            js_sys::Function::new_with_args("chunk", "this.push(chunk.slice())").bind(&chunks);
+
+        encode_jpeg_streaming(&img, 90, &push).unwrap();
+
+        let mut streamed = Vec::new();
+        for chunk in chunks.iter() {
+            streamed.extend(js_sys::Uint8Array::from(chunk).to_vec());
+        }
+        assert_eq!(streamed, encode_jpeg_from_image(&img, 90).unwrap());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_encode_jpeg_streaming_propagates_callback_error() {
+        let img = JsDecodedImage::new(8, 8, vec![0u8; 8 * 8 * 3]);
+        let throw = js_sys::Function::new_with_args("chunk", "throw new Error('disk full')");
+
+        assert!(encode_jpeg_streaming(&img, 90, &throw).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rotate_jpeg_lossless_rejects_non_jpeg() {
+        assert!(rotate_jpeg_lossless(b"not a jpeg", 1).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_crop_jpeg_lossless_reports_snapped_crop() {
+        let pixels = vec![128u8; 32 * 32 * 3];
+        let jpeg = encode_jpeg(&pixels, 32, 32, 90).unwrap();
+
+        let result = crop_jpeg_lossless(&jpeg, 0.3, 0.0, 0.5, 1.0).unwrap();
+
+        let rect: PixelRect = serde_wasm_bindgen::from_value(result.rect().unwrap()).unwrap();
+        assert_eq!((rect.x, rect.width), (8, 18));
+        assert_eq!(&result.bytes()[0..2], &[0xFF, 0xD8]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_crop_jpeg_lossless_rejects_non_jpeg() {
+        assert!(crop_jpeg_lossless(b"not a jpeg", 0.0, 0.0, 0.5, 0.5).is_err());
+    }
 }
@@ -0,0 +1,82 @@
+//! One-click auto enhance WASM binding.
+//!
+//! Suggests a straighten angle, crop, white balance and tone curve in one
+//! call. See `literoom_core::enhance` for how each is detected and when it
+//! is skipped.
+
+use crate::edit::edit_state_to_js;
+use crate::types::JsDecodedImage;
+use literoom_core::enhance;
+use wasm_bindgen::prelude::*;
+
+/// Suggest an edit that straightens, crops, white balances and tones an image.
+///
+/// Uncertain findings are left out, so an image that needs no fixing gets
+/// back a default edit state.
+///
+/// # Arguments
+/// * `image` - Source image
+/// * `render` - Also render the image with the suggested edit
+///
+/// # Returns
+/// An object `{ edit_state, image }`. `edit_state` has the same shape as
+/// the edit state passed to the render functions; `image` is a new
+/// `JsDecodedImage` when `render` is set, otherwise it is absent.
+///
+/// # Errors
+/// Throws if the image is empty or its buffer does not match its dimensions.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const { edit_state, image } = auto_enhance(source, true);
+/// store.setEditState(edit_state);
+/// drawPreview(image);
+/// ```
+#[wasm_bindgen]
+pub fn auto_enhance(image: &JsDecodedImage, render: bool) -> Result<JsValue, JsValue> {
+    image.validate()?;
+    let (state, rendered) = enhance::auto_enhance(image.as_decoded(), render)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(&result, &"edit_state".into(), &edit_state_to_js(&state)?)?;
+    if let Some(rendered) = rendered {
+        js_sys::Reflect::set(
+            &result,
+            &"image".into(),
+            &JsDecodedImage::from_decoded(rendered).into(),
+        )?;
+    }
+    Ok(result.into())
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use crate::edit::edit_state_from_js;
+    use literoom_core::testing::checkerboard;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_auto_enhance_returns_state_and_optional_image() {
+        let board = checkerboard(64, 48, 8);
+        let image = JsDecodedImage::new(64, 48, board.pixels);
+
+        let result = auto_enhance(&image, false).unwrap();
+        let state = js_sys::Reflect::get(&result, &"edit_state".into()).unwrap();
+        assert!(edit_state_from_js(state).unwrap().is_default());
+        let rendered = js_sys::Reflect::get(&result, &"image".into()).unwrap();
+        assert!(rendered.is_undefined());
+
+        let result = auto_enhance(&image, true).unwrap();
+        let rendered = js_sys::Reflect::get(&result, &"image".into()).unwrap();
+        let width = js_sys::Reflect::get(&rendered, &"width".into()).unwrap();
+        assert_eq!(width.as_f64(), Some(64.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_auto_enhance_rejects_invalid_image() {
+        let image = JsDecodedImage::new(4, 4, vec![0; 5]);
+        assert!(auto_enhance(&image, false).is_err());
+    }
+}
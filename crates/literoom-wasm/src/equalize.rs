@@ -0,0 +1,115 @@
+//! Histogram equalization and CLAHE WASM bindings.
+//!
+//! Local-contrast tools for flat images such as fog, haze or scientific
+//! captures. Both return a new image and leave the input untouched.
+
+use crate::types::{image_error_to_js, JsDecodedImage};
+use literoom_core::equalize;
+use wasm_bindgen::prelude::*;
+
+/// Equalize the luma histogram of the whole image.
+///
+/// # Arguments
+///
+/// * `image` - Image to change
+/// * `strength` - Blend between the original (0.0) and the fully equalized
+///   image (1.0)
+///
+/// # Errors
+///
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+#[wasm_bindgen]
+pub fn equalize_histogram(
+    image: &JsDecodedImage,
+    strength: f32,
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    let mut output = image.as_decoded().clone();
+    equalize::equalize_histogram(&mut output.pixels, strength);
+    Ok(JsDecodedImage::from_decoded(output))
+}
+
+/// Apply contrast-limited adaptive histogram equalization (CLAHE).
+///
+/// # Arguments
+///
+/// * `image` - Image to change
+/// * `tiles_x`, `tiles_y` - Grid of tiles equalized separately, e.g. 8 x 8
+/// * `clip_limit` - Contrast limit as a multiple of the mean histogram bin;
+///   0 changes nothing, 2 to 4 is typical, and large values approach plain
+///   equalization
+/// * `strength` - Blend between the original (0.0) and the full effect
+///   (1.0)
+///
+/// # Errors
+///
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const clearer = apply_clahe(foggyImage, 8, 8, 3.0, 0.6);
+/// ```
+#[wasm_bindgen]
+pub fn apply_clahe(
+    image: &JsDecodedImage,
+    tiles_x: u32,
+    tiles_y: u32,
+    clip_limit: f32,
+    strength: f32,
+) -> Result<JsDecodedImage, JsValue> {
+    let mut output = image.as_decoded().clone();
+    equalize::apply_clahe(
+        &mut output.pixels,
+        output.width,
+        output.height,
+        tiles_x,
+        tiles_y,
+        clip_limit,
+        strength,
+    )
+    .map_err(|e| image_error_to_js(&e))?;
+    Ok(JsDecodedImage::from_decoded(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use literoom_core::testing::noise;
+
+    #[test]
+    fn test_bindings_match_core() {
+        let source = noise(24, 16, 5);
+        let image = JsDecodedImage::from_decoded(source.clone());
+
+        let mut expected = source.pixels.clone();
+        equalize::apply_clahe(&mut expected, 24, 16, 3, 2, 2.5, 0.8).unwrap();
+        assert_eq!(
+            apply_clahe(&image, 3, 2, 2.5, 0.8).unwrap().pixels(),
+            expected
+        );
+
+        let mut expected = source.pixels.clone();
+        equalize::equalize_histogram(&mut expected, 0.5);
+        assert_eq!(equalize_histogram(&image, 0.5).unwrap().pixels(), expected);
+        assert_eq!(image.pixels(), source.pixels);
+    }
+}
+
+/// WASM-specific tests that require JsValue.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_apply_clahe_rejects_mismatched_buffer() {
+        let image = JsDecodedImage::new(8, 8, vec![0u8; 10]);
+        assert!(apply_clahe(&image, 2, 2, 2.0, 1.0).is_err());
+        assert!(equalize_histogram(&image, 1.0).is_err());
+    }
+}
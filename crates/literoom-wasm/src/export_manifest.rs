@@ -0,0 +1,96 @@
+//! Export file naming WASM bindings.
+//!
+//! Batch export names every output file from a template such as
+//! `{basename}-{seq:3}.jpg`. The names are resolved up front, in one call,
+//! so collisions within the batch and with files already in the folder get
+//! numbered suffixes consistently.
+
+use crate::types::template_error_to_js;
+use literoom_core::export_manifest::{self, ExportNameContext};
+use wasm_bindgen::prelude::*;
+
+/// Resolve the output file names for a batch export.
+///
+/// # Arguments
+///
+/// * `items` - Array of per-file objects with `basename`, `date`, `width`,
+///   `height`, `quality` and optionally `seq`; missing fields use defaults
+///   and a missing `seq` is the 1-based position in the array
+/// * `template` - Name template; see the token list below
+/// * `existing` - Optional names already in the export folder, to avoid
+///
+/// Tokens: `{basename}`, `{seq}`, `{date}`, `{width}`, `{height}`,
+/// `{quality}`. Numeric tokens accept a zero-padded width (`{seq:3}`), and
+/// `{{` / `}}` are literal braces.
+///
+/// # Returns
+///
+/// One unique name per item, in order. Duplicates get `-1`, `-2`, ...
+/// before the extension; names are compared ignoring case.
+///
+/// # Errors
+///
+/// Returns an error if `items` is not an array of objects, or if the
+/// template is invalid. Template errors carry a `code` (`"UNKNOWN_TOKEN"`,
+/// `"INVALID_WIDTH"`, `"UNCLOSED_BRACE"`, `"UNMATCHED_BRACE"`) and the
+/// character `position` of the problem.
+///
+/// # Example
+///
+/// ```typescript
+/// const names = build_export_manifest(
+///   photos.map((p) => ({ basename: p.name, width: 2048, height: 1365, quality: 90 })),
+///   '{basename}-{seq:3}.jpg',
+/// );
+/// ```
+#[wasm_bindgen]
+pub fn build_export_manifest(
+    items: JsValue,
+    template: &str,
+    existing: Option<Vec<String>>,
+) -> Result<Vec<String>, JsValue> {
+    let items: Vec<ExportNameContext> = serde_wasm_bindgen::from_value(items)
+        .map_err(|e| JsValue::from_str(&format!("Invalid export items: {}", e)))?;
+    export_manifest::build_export_manifest(template, &items, &existing.unwrap_or_default())
+        .map_err(|e| template_error_to_js(&e))
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn items() -> JsValue {
+        let items = vec![
+            ExportNameContext {
+                basename: "beach".to_string(),
+                ..Default::default()
+            },
+            ExportNameContext {
+                basename: "beach".to_string(),
+                ..Default::default()
+            },
+        ];
+        serde_wasm_bindgen::to_value(&items).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_build_export_manifest() {
+        let names = build_export_manifest(items(), "{basename}-{seq:2}.jpg", None).unwrap();
+        assert_eq!(names, vec!["beach-01.jpg", "beach-02.jpg"]);
+
+        let names =
+            build_export_manifest(items(), "{basename}.jpg", Some(vec!["BEACH.jpg".into()]))
+                .unwrap();
+        assert_eq!(names, vec!["beach-1.jpg", "beach-2.jpg"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_template_error_has_code_and_position() {
+        let err = build_export_manifest(items(), "{basename}-{size}", None).unwrap_err();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        let position = js_sys::Reflect::get(&err, &"position".into()).unwrap();
+        assert_eq!(code.as_string().unwrap(), "UNKNOWN_TOKEN");
+        assert_eq!(position.as_f64().unwrap(), 11.0);
+    }
+}
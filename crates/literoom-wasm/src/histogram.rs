@@ -3,7 +3,16 @@
 //! This module provides JavaScript bindings for histogram computation,
 //! allowing RGB and luminance histograms to be calculated from pixel data.
 
-use literoom_core::histogram::compute_histogram as compute_histogram_core;
+use crate::curve::JsToneCurveLut;
+use crate::types::{image_error_to_js, JsDecodedImage};
+use literoom_core::decode::DecodedImage;
+use literoom_core::histogram::{
+    bin_fractions, compute_histogram as compute_histogram_core, compute_histogram_delta,
+    compute_histogram_with_lut as compute_histogram_with_lut_core,
+    sample_pixel_stats as sample_pixel_stats_core, HistogramDelta,
+};
+use literoom_core::{Histogram, ImageError};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
 /// Histogram result accessible from JavaScript.
@@ -74,6 +83,11 @@ impl JsHistogram {
 /// # Returns
 /// JsHistogram with all channel data and clipping info
 ///
+/// # Errors
+/// Throws an `Error` with a `code` property (`"EMPTY_IMAGE"` or
+/// `"BUFFER_SIZE_MISMATCH"`) if either dimension is zero or the buffer
+/// length is not `width * height * 3`.
+///
 /// # Example (TypeScript)
 /// ```typescript
 /// // Get pixel data from decoded image
@@ -91,20 +105,200 @@ impl JsHistogram {
 /// hist.free();
 /// ```
 #[wasm_bindgen]
-pub fn compute_histogram(pixels: &[u8], width: u32, height: u32) -> JsHistogram {
-    let hist = compute_histogram_core(pixels, width, height);
-
-    JsHistogram {
-        red: hist.red.to_vec(),
-        green: hist.green.to_vec(),
-        blue: hist.blue.to_vec(),
-        luminance: hist.luminance.to_vec(),
-        max_value: hist.max_value(),
-        has_highlight_clipping: hist.has_highlight_clipping(),
-        has_shadow_clipping: hist.has_shadow_clipping(),
+pub fn compute_histogram(pixels: &[u8], width: u32, height: u32) -> Result<JsHistogram, JsValue> {
+    let hist = compute_histogram_core(pixels, width, height).map_err(|e| image_error_to_js(&e))?;
+    Ok(hist.into())
+}
+
+/// Compute the histogram pixel data will have after a tone curve.
+///
+/// Equivalent to applying `lut` and then calling `compute_histogram`, but
+/// the curved pixels are never materialized. Use it to show the post-curve
+/// histogram from the pre-curve pixels.
+///
+/// # Arguments
+/// * `pixels` - RGB pixel data as Uint8Array (3 bytes per pixel, row-major)
+/// * `width` - Image width in pixels
+/// * `height` - Image height in pixels
+/// * `lut` - Pre-computed tone curve LUT
+///
+/// # Errors
+/// Throws an `Error` with a `code` property, as `compute_histogram` does.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const lut = new JsToneCurveLut(points);
+/// const hist = compute_histogram_with_lut(pixels, width, height, lut);
+/// drawHistogram(hist.luminance());
+/// hist.free();
+/// ```
+#[wasm_bindgen]
+pub fn compute_histogram_with_lut(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    lut: &JsToneCurveLut,
+) -> Result<JsHistogram, JsValue> {
+    let hist = compute_histogram_with_lut_core(pixels, width, height, lut.inner())
+        .map_err(|e| image_error_to_js(&e))?;
+    Ok(hist.into())
+}
+
+impl From<Histogram> for JsHistogram {
+    fn from(hist: Histogram) -> Self {
+        JsHistogram {
+            red: hist.red.to_vec(),
+            green: hist.green.to_vec(),
+            blue: hist.blue.to_vec(),
+            luminance: hist.luminance.to_vec(),
+            max_value: hist.max_value(),
+            has_highlight_clipping: hist.has_highlight_clipping(),
+            has_shadow_clipping: hist.has_shadow_clipping(),
+        }
+    }
+}
+
+/// Bins of one side of a before/after pair: counts, or fractions when the
+/// two images differ in pixel count.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsHistogramBins {
+    /// Red channel (256 bins)
+    pub red: Vec<f64>,
+    /// Green channel (256 bins)
+    pub green: Vec<f64>,
+    /// Blue channel (256 bins)
+    pub blue: Vec<f64>,
+    /// Luminance (256 bins)
+    pub luminance: Vec<f64>,
+}
+
+impl JsHistogramBins {
+    fn new(hist: &Histogram, normalized: bool) -> Self {
+        let bins = |channel: &[u32; 256]| -> Vec<f64> {
+            if normalized {
+                bin_fractions(channel).into_iter().map(f64::from).collect()
+            } else {
+                channel.iter().map(|&n| n as f64).collect()
+            }
+        };
+        Self {
+            red: bins(&hist.red),
+            green: bins(&hist.green),
+            blue: bins(&hist.blue),
+            luminance: bins(&hist.luminance),
+        }
     }
 }
 
+/// Before/after histograms with their luminance comparison.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsHistogramPair {
+    /// Histogram of the unedited image
+    pub original: JsHistogramBins,
+    /// Histogram of the edited image
+    pub edited: JsHistogramBins,
+    /// Whether the bins are fractions rather than counts
+    pub normalized: bool,
+    /// Luminance comparison of `edited` against `original`
+    pub delta: HistogramDelta,
+}
+
+fn histogram_pair(
+    original: &DecodedImage,
+    edited: &DecodedImage,
+) -> Result<JsHistogramPair, ImageError> {
+    let before = compute_histogram_core(&original.pixels, original.width, original.height)?;
+    let after = compute_histogram_core(&edited.pixels, edited.width, edited.height)?;
+    let normalized = original.pixel_count() != edited.pixel_count();
+
+    Ok(JsHistogramPair {
+        original: JsHistogramBins::new(&before, normalized),
+        edited: JsHistogramBins::new(&after, normalized),
+        normalized,
+        delta: compute_histogram_delta(&before, &after),
+    })
+}
+
+/// Compute the histograms of an image before and after editing, for the
+/// overlay view.
+///
+/// The images may differ in size, since edits can crop. When they do, the
+/// bins of both are fractions of each image's pixel count instead of raw
+/// counts, so the two curves stay comparable.
+///
+/// # Returns
+/// An object `{ original, edited, normalized, delta }`. `original` and
+/// `edited` each hold `red`, `green`, `blue` and `luminance` arrays of 256
+/// bins. `delta` holds the per-bin luminance difference (`edited -
+/// original`, always as fractions) in `luminance`, plus `median_before`,
+/// `median_after`, `median_shift`, `mean_before`, `mean_after` and
+/// `mean_shift`, in bins.
+///
+/// # Errors
+/// Throws an `Error` with a `code` property if either image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const pair = compute_histogram_pair(original, edited);
+/// drawOverlay(pair.original.luminance, pair.edited.luminance);
+/// console.log(`Median moved by ${pair.delta.median_shift}`);
+/// ```
+#[wasm_bindgen]
+pub fn compute_histogram_pair(
+    original: &JsDecodedImage,
+    edited: &JsDecodedImage,
+) -> Result<JsValue, JsValue> {
+    let pair = histogram_pair(original.as_decoded(), edited.as_decoded())
+        .map_err(|e| image_error_to_js(&e))?;
+    serde_wasm_bindgen::to_value(&pair)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize histograms: {}", e)))
+}
+
+/// Sample the working pixel under the cursor and the statistics of its
+/// neighborhood, for the eyedropper and info panel.
+///
+/// Reads the pipeline's own pixels, so the values are not affected by
+/// canvas scaling or color management.
+///
+/// # Arguments
+/// * `image` - The image to sample
+/// * `x`, `y` - Cursor position in normalized image coordinates (0.0 to 1.0
+///   across the width and height), independent of zoom; positions outside
+///   the image select the nearest edge pixel
+/// * `radius_px` - Radius of the neighborhood in image pixels; 0 samples the
+///   center pixel alone
+///
+/// # Returns
+/// An object `{ x, y, center, center_luminance, sample_count, red, green,
+/// blue, luminance }`. `x` and `y` are the center pixel's column and row,
+/// `center` its `[r, g, b]` value. Each channel holds `mean`, `min`, `max`
+/// and `stddev` over the pixels of the disc that lie inside the image;
+/// `sample_count` is how many there were.
+///
+/// # Errors
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const stats = sample_pixel_stats(image, cursorX / viewWidth, cursorY / viewHeight, 3);
+/// panel.show(stats.center, stats.luminance.mean, stats.luminance.stddev);
+/// ```
+#[wasm_bindgen]
+pub fn sample_pixel_stats(
+    image: &JsDecodedImage,
+    x: f32,
+    y: f32,
+    radius_px: u32,
+) -> Result<JsValue, JsValue> {
+    let image = image.as_decoded();
+    let stats = sample_pixel_stats_core(&image.pixels, image.width, image.height, x, y, radius_px)
+        .map_err(|e| image_error_to_js(&e))?;
+    serde_wasm_bindgen::to_value(&stats)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize pixel stats: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,7 +310,7 @@ mod tests {
     #[test]
     fn test_js_histogram_creation() {
         let pixels = vec![255, 0, 0, 0, 255, 0, 0, 0, 255];
-        let hist = compute_histogram(&pixels, 3, 1);
+        let hist = compute_histogram(&pixels, 3, 1).unwrap();
 
         assert_eq!(hist.red().len(), 256);
         assert_eq!(hist.green().len(), 256);
@@ -129,7 +323,7 @@ mod tests {
     #[test]
     fn test_js_histogram_max_value() {
         let pixels = vec![128, 128, 128, 128, 128, 128, 128, 128, 128, 200, 200, 200];
-        let hist = compute_histogram(&pixels, 4, 1);
+        let hist = compute_histogram(&pixels, 4, 1).unwrap();
 
         assert_eq!(hist.max_value, 3); // 3 pixels at value 128
         assert_eq!(hist.red()[128], 3);
@@ -139,27 +333,17 @@ mod tests {
     #[test]
     fn test_js_histogram_no_clipping() {
         let pixels = vec![64, 64, 64, 128, 128, 128, 192, 192, 192];
-        let hist = compute_histogram(&pixels, 3, 1);
+        let hist = compute_histogram(&pixels, 3, 1).unwrap();
 
         assert!(!hist.has_highlight_clipping);
         assert!(!hist.has_shadow_clipping);
     }
 
-    #[test]
-    fn test_js_histogram_empty() {
-        let pixels: Vec<u8> = vec![];
-        let hist = compute_histogram(&pixels, 0, 0);
-
-        assert_eq!(hist.max_value, 0);
-        assert!(!hist.has_highlight_clipping);
-        assert!(!hist.has_shadow_clipping);
-    }
-
     #[test]
     fn test_js_histogram_luminance() {
         // Pure white pixel
         let pixels = vec![255, 255, 255];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.luminance()[255], 1);
     }
@@ -173,7 +357,7 @@ mod tests {
             pixels.push(i as u8);
             pixels.push(i as u8);
         }
-        let hist = compute_histogram(&pixels, 256, 1);
+        let hist = compute_histogram(&pixels, 256, 1).unwrap();
 
         // Each bin should have exactly 1 pixel
         for i in 0..256 {
@@ -192,7 +376,7 @@ mod tests {
     fn test_js_histogram_highlight_clipping_only() {
         // Only white pixel (highlight clipping without shadow)
         let pixels = vec![255, 255, 255];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert!(hist.has_highlight_clipping());
         assert!(!hist.has_shadow_clipping());
@@ -202,7 +386,7 @@ mod tests {
     fn test_js_histogram_shadow_clipping_only() {
         // Only black pixel (shadow clipping without highlight)
         let pixels = vec![0, 0, 0];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert!(!hist.has_highlight_clipping());
         assert!(hist.has_shadow_clipping());
@@ -212,7 +396,7 @@ mod tests {
     fn test_js_histogram_both_clipping() {
         // Both extremes
         let pixels = vec![0, 0, 0, 255, 255, 255];
-        let hist = compute_histogram(&pixels, 2, 1);
+        let hist = compute_histogram(&pixels, 2, 1).unwrap();
 
         assert!(hist.has_highlight_clipping());
         assert!(hist.has_shadow_clipping());
@@ -222,7 +406,7 @@ mod tests {
     fn test_js_histogram_red_channel_only_clipping() {
         // Only red channel clipped at highlight
         let pixels = vec![255, 128, 128];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert!(hist.has_highlight_clipping());
         assert!(!hist.has_shadow_clipping());
@@ -232,7 +416,7 @@ mod tests {
     fn test_js_histogram_green_channel_only_clipping() {
         // Only green channel clipped at shadow
         let pixels = vec![128, 0, 128];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert!(!hist.has_highlight_clipping());
         assert!(hist.has_shadow_clipping());
@@ -242,7 +426,7 @@ mod tests {
     fn test_js_histogram_blue_channel_only_clipping() {
         // Only blue channel clipped
         let pixels = vec![128, 128, 255];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert!(hist.has_highlight_clipping());
         assert!(!hist.has_shadow_clipping());
@@ -255,7 +439,7 @@ mod tests {
     #[test]
     fn test_js_histogram_red_channel_accessor() {
         let pixels = vec![100, 50, 75];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         let red = hist.red();
         assert_eq!(red.len(), 256);
@@ -271,7 +455,7 @@ mod tests {
     #[test]
     fn test_js_histogram_green_channel_accessor() {
         let pixels = vec![100, 50, 75];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         let green = hist.green();
         assert_eq!(green.len(), 256);
@@ -287,7 +471,7 @@ mod tests {
     #[test]
     fn test_js_histogram_blue_channel_accessor() {
         let pixels = vec![100, 50, 75];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         let blue = hist.blue();
         assert_eq!(blue.len(), 256);
@@ -304,7 +488,7 @@ mod tests {
     fn test_js_histogram_luminance_channel_accessor() {
         // Gray pixel - luminance should equal channel values
         let pixels = vec![128, 128, 128];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         let lum = hist.luminance();
         assert_eq!(lum.len(), 256);
@@ -314,7 +498,7 @@ mod tests {
     #[test]
     fn test_js_histogram_accessors_return_clones() {
         let pixels = vec![100, 100, 100];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         // Get red twice and verify they're independent
         let red1 = hist.red();
@@ -331,7 +515,7 @@ mod tests {
     #[test]
     fn test_js_histogram_pure_red_pixel() {
         let pixels = vec![255, 0, 0];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.red()[255], 1);
         assert_eq!(hist.green()[0], 1);
@@ -345,7 +529,7 @@ mod tests {
     #[test]
     fn test_js_histogram_pure_green_pixel() {
         let pixels = vec![0, 255, 0];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.red()[0], 1);
         assert_eq!(hist.green()[255], 1);
@@ -359,7 +543,7 @@ mod tests {
     #[test]
     fn test_js_histogram_pure_blue_pixel() {
         let pixels = vec![0, 0, 255];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.red()[0], 1);
         assert_eq!(hist.green()[0], 1);
@@ -373,7 +557,7 @@ mod tests {
     #[test]
     fn test_js_histogram_white_pixel() {
         let pixels = vec![255, 255, 255];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.red()[255], 1);
         assert_eq!(hist.green()[255], 1);
@@ -384,7 +568,7 @@ mod tests {
     #[test]
     fn test_js_histogram_black_pixel() {
         let pixels = vec![0, 0, 0];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.red()[0], 1);
         assert_eq!(hist.green()[0], 1);
@@ -399,7 +583,7 @@ mod tests {
     #[test]
     fn test_js_histogram_max_value_single_pixel() {
         let pixels = vec![128, 128, 128];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.max_value(), 1);
     }
@@ -407,7 +591,7 @@ mod tests {
     #[test]
     fn test_js_histogram_max_value_multiple_same_pixels() {
         let pixels = vec![100, 100, 100, 100, 100, 100, 100, 100, 100, 100, 100, 100];
-        let hist = compute_histogram(&pixels, 4, 1);
+        let hist = compute_histogram(&pixels, 4, 1).unwrap();
 
         assert_eq!(hist.max_value(), 4);
     }
@@ -416,7 +600,7 @@ mod tests {
     fn test_js_histogram_max_value_spread_pixels() {
         // 4 different grayscale values, 1 each
         let pixels = vec![64, 64, 64, 128, 128, 128, 192, 192, 192, 255, 255, 255];
-        let hist = compute_histogram(&pixels, 4, 1);
+        let hist = compute_histogram(&pixels, 4, 1).unwrap();
 
         assert_eq!(hist.max_value(), 1);
     }
@@ -425,7 +609,7 @@ mod tests {
     fn test_js_histogram_max_value_large_image() {
         // 100x100 image = 10,000 pixels all the same
         let pixels = vec![100u8; 100 * 100 * 3];
-        let hist = compute_histogram(&pixels, 100, 100);
+        let hist = compute_histogram(&pixels, 100, 100).unwrap();
 
         assert_eq!(hist.max_value(), 10_000);
     }
@@ -437,7 +621,7 @@ mod tests {
             50, 100, 150, 50, 100, 150, 50, 100, 150, // 3 pixels same
             200, 200, 200, // 1 different pixel
         ];
-        let hist = compute_histogram(&pixels, 4, 1);
+        let hist = compute_histogram(&pixels, 4, 1).unwrap();
 
         // Red: 3 at bin 50, 1 at bin 200 -> max 3
         // Green: 3 at bin 100, 1 at bin 200 -> max 3
@@ -457,7 +641,7 @@ mod tests {
             0, 0, 255, // Blue
             128, 128, 128, // Gray
         ];
-        let hist = compute_histogram(&pixels, 2, 2);
+        let hist = compute_histogram(&pixels, 2, 2).unwrap();
 
         assert_eq!(hist.red()[255], 1);
         assert_eq!(hist.red()[0], 2);
@@ -477,7 +661,7 @@ mod tests {
             0, 0, 0, 50, 50, 50, 100, 100, 100, 128, 128, 128, 150, 150, 150, 180, 180, 180, 200,
             200, 200, 220, 220, 220, 255, 255, 255,
         ];
-        let hist = compute_histogram(&pixels, 3, 3);
+        let hist = compute_histogram(&pixels, 3, 3).unwrap();
 
         // Each grayscale value should appear once
         assert_eq!(hist.red()[0], 1);
@@ -501,7 +685,7 @@ mod tests {
             pixels.push(val);
             pixels.push(val);
         }
-        let hist = compute_histogram(&pixels, 10, 1);
+        let hist = compute_histogram(&pixels, 10, 1).unwrap();
 
         // Check we have 10 distinct values
         let red_count: u32 = hist.red().iter().sum();
@@ -518,7 +702,7 @@ mod tests {
             pixels.push(val);
             pixels.push(val);
         }
-        let hist = compute_histogram(&pixels, 1, 10);
+        let hist = compute_histogram(&pixels, 1, 10).unwrap();
 
         let red_count: u32 = hist.red().iter().sum();
         assert_eq!(red_count, 10);
@@ -531,7 +715,7 @@ mod tests {
     #[test]
     fn test_js_histogram_all_same_value() {
         let pixels = vec![77u8; 50 * 3];
-        let hist = compute_histogram(&pixels, 50, 1);
+        let hist = compute_histogram(&pixels, 50, 1).unwrap();
 
         assert_eq!(hist.red()[77], 50);
         assert_eq!(hist.green()[77], 50);
@@ -543,7 +727,7 @@ mod tests {
     fn test_js_histogram_alternating_values() {
         // Alternate between 0 and 255
         let pixels = vec![0, 0, 0, 255, 255, 255, 0, 0, 0, 255, 255, 255];
-        let hist = compute_histogram(&pixels, 4, 1);
+        let hist = compute_histogram(&pixels, 4, 1).unwrap();
 
         assert_eq!(hist.red()[0], 2);
         assert_eq!(hist.red()[255], 2);
@@ -554,7 +738,7 @@ mod tests {
     fn test_js_histogram_near_clipping_values() {
         // Values at 1 and 254 (near but not clipping)
         let pixels = vec![1, 1, 1, 254, 254, 254];
-        let hist = compute_histogram(&pixels, 2, 1);
+        let hist = compute_histogram(&pixels, 2, 1).unwrap();
 
         assert!(!hist.has_highlight_clipping());
         assert!(!hist.has_shadow_clipping());
@@ -566,7 +750,7 @@ mod tests {
     fn test_js_histogram_different_values_per_channel() {
         // R=10, G=100, B=200 for a single pixel
         let pixels = vec![10, 100, 200];
-        let hist = compute_histogram(&pixels, 1, 1);
+        let hist = compute_histogram(&pixels, 1, 1).unwrap();
 
         assert_eq!(hist.red()[10], 1);
         assert_eq!(hist.green()[100], 1);
@@ -591,7 +775,7 @@ mod tests {
         let test_values = [0u8, 32, 64, 96, 128, 160, 192, 224, 255];
         for &val in &test_values {
             let pixels = vec![val, val, val];
-            let hist = compute_histogram(&pixels, 1, 1);
+            let hist = compute_histogram(&pixels, 1, 1).unwrap();
             // Allow ±1 for rounding
             let lum = hist.luminance();
             let lum_index = lum.iter().position(|&v| v > 0).unwrap();
@@ -608,7 +792,7 @@ mod tests {
     #[test]
     fn test_js_histogram_luminance_total_count() {
         let pixels = vec![100, 150, 200, 50, 75, 100, 200, 200, 200];
-        let hist = compute_histogram(&pixels, 3, 1);
+        let hist = compute_histogram(&pixels, 3, 1).unwrap();
 
         let lum_total: u32 = hist.luminance().iter().sum();
         assert_eq!(lum_total, 3); // 3 pixels
@@ -621,8 +805,8 @@ mod tests {
     #[test]
     fn test_js_histogram_deterministic() {
         let pixels = vec![50, 100, 150, 200, 50, 100, 75, 125, 175];
-        let hist1 = compute_histogram(&pixels, 3, 1);
-        let hist2 = compute_histogram(&pixels, 3, 1);
+        let hist1 = compute_histogram(&pixels, 3, 1).unwrap();
+        let hist2 = compute_histogram(&pixels, 3, 1).unwrap();
 
         assert_eq!(hist1.red(), hist2.red());
         assert_eq!(hist1.green(), hist2.green());
@@ -639,15 +823,128 @@ mod tests {
         let pixels = vec![100, 100, 100, 200, 200, 200, 50, 50, 50, 150, 150, 150];
 
         // 2x2 layout
-        let hist_2x2 = compute_histogram(&pixels, 2, 2);
+        let hist_2x2 = compute_histogram(&pixels, 2, 2).unwrap();
         // 4x1 layout
-        let hist_4x1 = compute_histogram(&pixels, 4, 1);
+        let hist_4x1 = compute_histogram(&pixels, 4, 1).unwrap();
         // 1x4 layout
-        let hist_1x4 = compute_histogram(&pixels, 1, 4);
+        let hist_1x4 = compute_histogram(&pixels, 1, 4).unwrap();
 
         assert_eq!(hist_2x2.red(), hist_4x1.red());
         assert_eq!(hist_2x2.red(), hist_1x4.red());
         assert_eq!(hist_2x2.max_value(), hist_4x1.max_value());
         assert_eq!(hist_2x2.max_value(), hist_1x4.max_value());
     }
+
+    // =========================================================================
+    // Before/after pair tests
+    // =========================================================================
+
+    #[test]
+    fn test_histogram_pair_same_size_keeps_counts() {
+        let image = literoom_core::testing::color_bars(32, 8);
+        let pair = histogram_pair(&image, &image).unwrap();
+        assert!(!pair.normalized);
+        assert_eq!(pair.original, pair.edited);
+        assert_eq!(pair.original.luminance.iter().sum::<f64>(), 256.0);
+        assert!(pair.delta.luminance.iter().all(|&d| d == 0.0));
+    }
+
+    #[test]
+    fn test_histogram_pair_different_sizes_normalizes() {
+        let original = literoom_core::testing::noise(40, 30, 7);
+        let edited = literoom_core::testing::noise(20, 10, 7);
+        let pair = histogram_pair(&original, &edited).unwrap();
+        assert!(pair.normalized);
+        for bins in [&pair.original, &pair.edited] {
+            for channel in [&bins.red, &bins.green, &bins.blue, &bins.luminance] {
+                assert!((channel.iter().sum::<f64>() - 1.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_histogram_pair_rejects_invalid_image() {
+        let original = literoom_core::testing::noise(4, 4, 1);
+        let mut edited = original.clone();
+        edited.pixels.pop();
+        assert!(matches!(
+            histogram_pair(&original, &edited),
+            Err(ImageError::BufferSizeMismatch { .. })
+        ));
+    }
+
+    // =========================================================================
+    // Post-curve histogram tests
+    // =========================================================================
+
+    #[test]
+    fn test_histogram_with_lut_uses_curved_values() {
+        // Inverting curve: black pixels bin as white
+        let curve = literoom_core::ToneCurve {
+            points: vec![
+                literoom_core::CurvePoint::new(0.0, 1.0),
+                literoom_core::CurvePoint::new(1.0, 0.0),
+            ],
+        };
+        let lut = JsToneCurveLut::from(literoom_core::ToneCurveLut::from_curve(&curve));
+        let hist = compute_histogram_with_lut(&[0, 0, 0, 0, 0, 0], 2, 1, &lut).unwrap();
+
+        assert_eq!(hist.red()[255], 2);
+        assert_eq!(hist.luminance()[255], 2);
+        assert!(hist.has_highlight_clipping);
+        assert!(!hist.has_shadow_clipping);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn error_code(err: JsValue) -> Option<String> {
+        js_sys::Reflect::get(&err, &"code".into()).ok()?.as_string()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_empty_image_throws() {
+        let err = compute_histogram(&[], 0, 0).err().unwrap();
+        assert_eq!(error_code(err).as_deref(), Some("EMPTY_IMAGE"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_buffer_mismatch_throws() {
+        let err = compute_histogram(&[0u8; 9], 2, 2).err().unwrap();
+        assert_eq!(error_code(err).as_deref(), Some("BUFFER_SIZE_MISMATCH"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_histogram_pair_object() {
+        let image = JsDecodedImage::from_decoded(literoom_core::testing::color_bars(16, 4));
+        let pair = compute_histogram_pair(&image, &image).unwrap();
+        let normalized = js_sys::Reflect::get(&pair, &"normalized".into()).unwrap();
+        assert_eq!(normalized.as_bool(), Some(false));
+        let delta = js_sys::Reflect::get(&pair, &"delta".into()).unwrap();
+        let shift = js_sys::Reflect::get(&delta, &"median_shift".into()).unwrap();
+        assert_eq!(shift.as_f64(), Some(0.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_sample_pixel_stats_object() {
+        let image = JsDecodedImage::new(2, 1, vec![10, 20, 30, 50, 60, 70]);
+        let stats = sample_pixel_stats(&image, 0.0, 0.5, 1).unwrap();
+        let count = js_sys::Reflect::get(&stats, &"sample_count".into()).unwrap();
+        assert_eq!(count.as_f64(), Some(2.0));
+        let center = js_sys::Reflect::get(&stats, &"center".into()).unwrap();
+        assert_eq!(js_sys::Array::from(&center).get(2).as_f64(), Some(30.0));
+        let red = js_sys::Reflect::get(&stats, &"red".into()).unwrap();
+        let mean = js_sys::Reflect::get(&red, &"mean".into()).unwrap();
+        assert_eq!(mean.as_f64(), Some(30.0));
+
+        let err = sample_pixel_stats(&JsDecodedImage::new(0, 0, vec![]), 0.5, 0.5, 1)
+            .err()
+            .unwrap();
+        assert_eq!(error_code(err).as_deref(), Some("EMPTY_IMAGE"));
+    }
 }
@@ -5,10 +5,43 @@
 //!
 //! # Module Structure
 //!
-//! - `adjustments` - Basic photo adjustments (exposure, contrast, etc.)
-//! - `types` - WASM-compatible wrapper types for image data
-//! - `decode` - Image decoding bindings (JPEG, RAW thumbnail extraction, resize)
-//! - `encode` - Image encoding bindings (JPEG export)
+//! - `adjustments` - Basic photo adjustments (exposure, contrast, etc.), split white balance,
+//!   auto levels, per-channel black and white level calibration, ISO-based default noise
+//!   reduction, and the adjustment math as data for shader generation
+//! - `analysis` - Sharpness and exposure scoring for culling
+//! - `burst` - Grouping photos into bursts by capture time
+//! - `color_match` - Matching an image's tones and colors to a reference image
+//! - `capabilities` - Version and feature introspection of this build
+//! - `types` - WASM-compatible wrapper types for image data (RGB, float RGB and premultiplied
+//!   RGBA)
+//! - `decode` - Image decoding bindings (JPEG, JPEG regions for the loupe, Ultra HDR gain maps,
+//!   RAW thumbnail extraction, resize, canonical thumbnail and preview downscale, one-call
+//!   file open, format detection with fallbacks, refusing non-image files up front)
+//! - `encode` - Image encoding bindings (JPEG export, streaming JPEG export, lossless
+//!   quarter-turn rotation and crop, tiled export of very large images, export format
+//!   validation)
+//! - `export_manifest` - Output file naming for batch export
+//! - `compose` - Export composition bindings (aspect padding, borders, watermarks, contact sheets,
+//!   scaled text and burned-in capture info)
+//! - `perf` - Per-operation performance counters
+//! - `phash` - Perceptual hashes for flagging duplicate imports
+//! - `pool` - Reused scratch pixel buffers, with trimming and reuse counters
+//! - `telemetry` - Opt-in sampled reporting of slow operations
+//! - `recipe` - JSON-defined pipelines of operations run in one call
+//! - `registry` - Session-scoped image handles so chained operations keep pixels on the WASM
+//!   side
+//! - `edit` - Compact binary and delta encoding of edit states, and which render stages differ
+//!   between two states
+//! - `enhance` - One-click auto straighten, crop, white balance and tone
+//! - `equalize` - Global histogram equalization and CLAHE for local contrast
+//! - `lut3d` - Film-emulation looks from `.cube` 3D LUT files
+//! - `shared` - RGB images backed by a `SharedArrayBuffer`, for painting on the main thread
+//!   while a worker writes the next edit
+//! - `variants` - Preset strip rendering into a single atlas image
+//! - `render` - Tile rendering of the edited image for the zoomed-in view, the B&W split
+//!   preview, edited grid thumbnails, prepared edits for batch apply and export (with
+//!   histogram and thumbnail taps) with optional per-stage toggles, export size preflight, and
+//!   resuming a preview render from a cached pipeline stage
 //!
 //! # Usage
 //!
@@ -27,30 +60,116 @@
 use wasm_bindgen::prelude::*;
 
 mod adjustments;
+mod analysis;
+mod burst;
+mod capabilities;
+mod color_match;
+mod compose;
 mod curve;
 mod decode;
+mod edit;
 mod encode;
+mod enhance;
+mod equalize;
+mod export_manifest;
 mod histogram;
+mod lut3d;
 mod mask;
+mod perf;
+mod phash;
+mod pool;
+mod recipe;
+mod registry;
+mod render;
+mod shared;
+mod telemetry;
 mod transform;
 mod types;
+mod variants;
 
 // Re-export public types
-pub use adjustments::{apply_adjustments, BasicAdjustments};
-pub use curve::{apply_tone_curve, JsToneCurveLut};
+pub use adjustments::{
+    apply_adjustments, apply_adjustments_f32, apply_adjustments_in_place,
+    apply_adjustments_preview, apply_adjustments_with_split, apply_adjustments_with_white_balance,
+    apply_channel_levels, apply_split_white_balance, auto_levels, compute_default_noise_reduction,
+    describe_adjustment_pipeline, estimate_channel_levels, estimate_white_balance,
+    get_adjustment_ranges, get_default_pipeline_order, white_balance_kelvin, BasicAdjustments,
+    JsAutoLevels, SplitWhiteBalance,
+};
+pub use analysis::assess_image_quality;
+pub use burst::group_burst_indices;
+pub use capabilities::{get_capabilities, supports, Capabilities, FormatSupport};
+pub use color_match::{apply_color_match, match_colors, JsColorMatch};
+pub use compose::{
+    add_border, apply_watermark_image, apply_watermark_rgba, apply_watermark_text,
+    compose_contact_sheet, compose_contact_sheet_jpeg, draw_capture_info, draw_text, pad_to_aspect,
+    resize_for_export,
+};
+pub use curve::{
+    apply_hue_curves, apply_tone_curve, apply_tone_curve_preview, estimate_baked_curve,
+    get_base_curve_for_camera, JsToneCurveLut,
+};
 pub use decode::{
-    decode_jpeg, decode_raw_thumbnail, extract_raw_thumbnail_bytes, generate_thumbnail,
-    is_raw_file, resize, resize_to_fit,
+    canonical_preview, classify_file, decode_auto, decode_heif_preview, decode_jpeg,
+    decode_jpeg_region, decode_jpeg_tolerant, decode_jpeg_with_gain_map, decode_jpeg_with_options,
+    decode_raw_thumbnail, extract_gain_map, extract_heif_preview_bytes,
+    extract_raw_thumbnail_bytes, generate_thumbnail, get_as_shot_white_balance, has_gain_map,
+    import_canvas_pixels, is_heif_file, is_raw_file, open_file, resize, resize_to_fit,
+    set_max_file_bytes, JsDecodedResult, JsGainMap, JsOpenedFile,
+};
+pub use edit::{
+    apply_edit_state_delta, apply_relative_adjustments, deserialize_edit_state_binary, diff_stages,
+    encode_edit_state_delta, serialize_edit_state_binary, JsStageDiff,
+};
+pub use encode::{
+    crop_jpeg_lossless, encode_jpeg, encode_jpeg_from_image, encode_jpeg_streaming,
+    encode_jpeg_with_taps, export_tiled, rotate_jpeg_lossless, validate_export_format,
+    JsExportResult, JsLosslessCrop, JsTiledExport,
+};
+pub use enhance::auto_enhance;
+pub use equalize::{apply_clahe, equalize_histogram};
+pub use export_manifest::build_export_manifest;
+pub use histogram::{
+    compute_histogram, compute_histogram_pair, compute_histogram_with_lut, sample_pixel_stats,
+    JsHistogram,
 };
-pub use encode::{encode_jpeg, encode_jpeg_from_image};
-pub use histogram::{compute_histogram, JsHistogram};
-pub use mask::apply_masked_adjustments;
-pub use transform::{apply_crop, apply_rotation};
-pub use types::JsDecodedImage;
+pub use lut3d::{apply_lut3d, load_cube_lut, JsLut3d};
+pub use mask::{
+    apply_masked_adjustments, apply_masked_adjustments_cached, apply_masked_adjustments_in_place,
+    apply_masked_adjustments_preview, apply_masked_adjustments_region, compute_mask_dirty_rect,
+    compute_masked_histogram, convert_mask_to_raster, duplicate_mask, erase_from_raster_mask,
+    invert_mask, rasterize_mask, suggest_mask_feather, JsMaskLayers, JsRasterizedMask,
+};
+pub use perf::{get_last_operation_stats, reset_stats};
+pub use phash::{compute_phash, phash_distance, phash_duplicate_threshold};
+pub use pool::{pool_stats, trim_pool};
+pub use recipe::run_recipe;
+pub use registry::{
+    apply_adjustments_h, apply_tone_curve_h, encode_jpeg_h, get_image, get_image_info,
+    get_registry_stats, release_all, release_image, resize_h, store_image,
+};
+pub use render::{
+    apply_prepared_edit, export_prepared, export_prepared_jpeg, export_prepared_with_taps,
+    generate_edited_thumbnail, generate_edited_thumbnail_from_image, get_rendered_size,
+    preflight_export, preflight_export_with_proxy, prepare_edit, prepare_edit_with_toggles,
+    render_from_stage, render_mono_split, render_tile, render_to_stage, JsPreparedEdit,
+};
+pub use shared::{create_shared_image, shared_image_from_buffer, JsSharedImage};
+pub use telemetry::{configure_telemetry, JsTelemetryEvent};
+pub use transform::{
+    apply_crop, apply_rotation, rotation_forward_map, rotation_inverse_map, solve_constrained_crop,
+    solve_guided_transform,
+};
+pub use types::{JsDecodedImage, JsDecodedImageF32, JsGrayImage, JsRgbaImage};
+pub use variants::{render_preset_strip, JsPresetStrip};
 
 /// Initialize the WASM module (called automatically on load)
 #[wasm_bindgen(start)]
 pub fn init() {
+    // Time core operations with the browser clock
+    #[cfg(target_arch = "wasm32")]
+    literoom_core::perf::set_timer(Some(perf::now_ms));
+
     // Future: Set up panic hook for better error messages in browser console
     // when console_error_panic_hook feature is added
 }
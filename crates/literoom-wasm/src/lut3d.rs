@@ -0,0 +1,109 @@
+//! 3D LUT WASM bindings.
+//!
+//! Film-emulation looks ship as `.cube` files. The app reads a file's text,
+//! parses it once with [`load_cube_lut`], keeps the [`JsLut3d`] for as long
+//! as the look is in use, and applies it with [`apply_lut3d`]. Edit states
+//! refer to the look by an id (`lut: { id, strength }`) that the app
+//! resolves to the loaded LUT.
+
+use crate::types::{lut_error_to_js, JsDecodedImage};
+use literoom_core::lut3d::{self, parse_cube_lut, Lut3d};
+use wasm_bindgen::prelude::*;
+
+/// A parsed 3D LUT, ready to apply.
+#[wasm_bindgen]
+pub struct JsLut3d {
+    inner: Lut3d,
+}
+
+#[wasm_bindgen]
+impl JsLut3d {
+    /// Points per side of the lattice (e.g. 33).
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    /// Title from the file, or `undefined`.
+    #[wasm_bindgen(getter)]
+    pub fn title(&self) -> Option<String> {
+        self.inner.title.clone()
+    }
+}
+
+/// Parse the text of a `.cube` file.
+///
+/// # Errors
+///
+/// Throws an `Error` with a `code` property (`"LUT_SYNTAX"`,
+/// `"LUT_INVALID_SIZE"`, `"LUT_MISSING_SIZE"` or `"LUT_MISSING_ENTRIES"`)
+/// and, for problems on one line, its 1-based `line`.
+///
+/// # Example
+///
+/// ```typescript
+/// const lut = load_cube_lut(await file.text());
+/// const looked = apply_lut3d(image, lut, 0.8);
+/// ```
+#[wasm_bindgen]
+pub fn load_cube_lut(text: &str) -> Result<JsLut3d, JsValue> {
+    let inner = parse_cube_lut(text).map_err(|e| lut_error_to_js(&e))?;
+    Ok(JsLut3d { inner })
+}
+
+/// Apply a 3D LUT to an image.
+///
+/// # Arguments
+///
+/// * `image` - Image to change
+/// * `lut` - LUT from [`load_cube_lut`]
+/// * `strength` - Blend between the original (0.0) and the full look (1.0)
+#[wasm_bindgen]
+pub fn apply_lut3d(image: &JsDecodedImage, lut: &JsLut3d, strength: f32) -> JsDecodedImage {
+    let mut pixels = image.pooled_pixels();
+    lut3d::apply_lut3d(&mut pixels, &lut.inner, strength);
+    JsDecodedImage::new(image.width(), image.height(), pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use literoom_core::testing::noise;
+
+    const INVERT: &str = "TITLE \"Invert\"\nLUT_3D_SIZE 2\n\
+        1 1 1\n0 1 1\n1 0 1\n0 0 1\n1 1 0\n0 1 0\n1 0 0\n0 0 0\n";
+
+    #[test]
+    fn test_apply_lut3d_matches_core() {
+        let lut = load_cube_lut(INVERT).unwrap();
+        assert_eq!(lut.size(), 2);
+        assert_eq!(lut.title().as_deref(), Some("Invert"));
+
+        let source = noise(16, 12, 3);
+        let result = apply_lut3d(&JsDecodedImage::from_decoded(source.clone()), &lut, 0.6);
+        let mut expected = source.pixels.clone();
+        lut3d::apply_lut3d(&mut expected, &lut.inner, 0.6);
+        assert_eq!(result.pixels(), expected);
+        assert_ne!(result.pixels(), source.pixels);
+    }
+}
+
+/// WASM-specific tests that require JsValue.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_malformed_lut_throws_with_line() {
+        let err = load_cube_lut("LUT_3D_SIZE 2\n0 0 0\n0 zero 0\n")
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("LUT_SYNTAX"));
+        let line = js_sys::Reflect::get(&err, &"line".into()).unwrap();
+        assert_eq!(line.as_f64(), Some(3.0));
+    }
+}
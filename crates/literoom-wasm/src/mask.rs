@@ -2,28 +2,112 @@
 //!
 //! This module provides JavaScript bindings for local adjustment masks,
 //! allowing linear gradient and radial gradient masks to be applied from TypeScript.
-
-use crate::types::JsDecodedImage;
-use literoom_core::mask::{LinearGradientMask, RadialGradientMask};
+//! While a mask is dragged, [`compute_mask_dirty_rect`] and
+//! [`apply_masked_adjustments_region`] re-render only the pixels it changes.
+//! While only a mask's adjustments change, [`rasterize_mask`] caches its
+//! strength so [`apply_masked_adjustments_cached`] skips evaluating it.
+//! [`duplicate_mask`] and [`invert_mask`] edit a single mask object for the
+//! mask panel. [`convert_mask_to_raster`] turns a linear or radial mask into
+//! a brush mask, which [`erase_from_raster_mask`] erases by hand.
+//! [`suggest_mask_feather`] recommends a feather from the noise and edges
+//! under a mask's boundary. [`compute_masked_histogram`] shows the histogram
+//! of just the pixels a mask affects.
+
+use crate::curve::{lut_from_points, CurvePointJs};
+use crate::histogram::JsHistogram;
+use crate::types::{
+    adjustment_error_to_js, decode_error_to_js, image_error_to_js, mask_error_to_js,
+    JsDecodedImage, JsGrayImage,
+};
+use literoom_core::color::ColorModel;
+use literoom_core::curve::ToneCurveLut;
+use literoom_core::decode::subsample;
+use literoom_core::histogram::{
+    compute_masked_histogram as compute_masked_histogram_core, MaskTarget,
+};
+use literoom_core::mask::{
+    self as core_mask, apply_ordered_masks, apply_ordered_masks_region, stack::check_unique_ids,
+    BrushMask, BrushStroke, LinearGradientMask, MaskAlpha, MaskEntry, MaskGeometry, MaskSelection,
+    PixelRect, RadialGradientMask, RasterizedMask, StackMask, StripMask,
+};
+use literoom_core::validate::validate_dimensions;
 use literoom_core::BasicAdjustments;
 use serde::{Deserialize, Serialize};
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
 /// JavaScript-compatible mask stack data structure.
 ///
-/// Contains arrays of linear and radial masks, each with their own adjustments.
-/// This is passed from TypeScript as a JSON object via serde_wasm_bindgen.
+/// An ordered array of masks of any kind, each with a unique `id`, a `kind`
+/// (`"linear"`, `"radial"`, `"strip"` or `"brush"`) and its own adjustments. Masks apply
+/// in array order, each to the result of the ones before, so reordering
+/// overlapping masks can change the render. This is passed from TypeScript
+/// as a JSON object via serde_wasm_bindgen.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsMaskStack {
+    /// Masks in the order they are applied
+    pub masks: Vec<JsMaskEntry>,
+}
+
+/// One mask of a [`JsMaskStack`], tagged with its `kind`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JsMaskEntry {
+    /// Linear gradient mask
+    Linear(JsLinearMask),
+    /// Radial gradient mask
+    Radial(JsRadialMask),
+    /// Brush mask
+    Brush(JsBrushMask),
+    /// Strip mask
+    Strip(JsStripMask),
+}
+
+impl JsMaskEntry {
+    /// The mask's id, if it has one.
+    fn id(&self) -> Option<&str> {
+        match self {
+            JsMaskEntry::Linear(m) => m.id.as_deref(),
+            JsMaskEntry::Radial(m) => m.id.as_deref(),
+            JsMaskEntry::Brush(m) => m.id.as_deref(),
+            JsMaskEntry::Strip(m) => m.id.as_deref(),
+        }
+    }
+}
+
+/// Mask stack in the shape used before masks had a single order.
+///
+/// Contains arrays of linear, radial and brush masks, each with their own
+/// adjustments. Still accepted wherever a [`JsMaskStack`] is: the masks
+/// apply as all linear masks, then all radial masks, then all brush masks,
+/// and ids stay optional.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsLegacyMaskStack {
     /// Linear gradient masks
     pub linear_masks: Vec<JsLinearMask>,
     /// Radial gradient masks
     pub radial_masks: Vec<JsRadialMask>,
+    /// Brush masks, applied after the linear and radial masks
+    #[serde(default)]
+    pub brush_masks: Vec<JsBrushMask>,
+}
+
+impl JsLegacyMaskStack {
+    /// The masks in the order this shape has always applied them.
+    fn into_entries(self) -> Vec<JsMaskEntry> {
+        let linear = self.linear_masks.into_iter().map(JsMaskEntry::Linear);
+        let radial = self.radial_masks.into_iter().map(JsMaskEntry::Radial);
+        let brush = self.brush_masks.into_iter().map(JsMaskEntry::Brush);
+        linear.chain(radial).chain(brush).collect()
+    }
 }
 
 /// JavaScript-compatible linear gradient mask.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsLinearMask {
+    /// Stable identifier used to solo or bypass the mask (optional)
+    #[serde(default)]
+    pub id: Option<String>,
     /// Start point X coordinate (0.0 to 1.0)
     pub start_x: f32,
     /// Start point Y coordinate (0.0 to 1.0)
@@ -34,15 +118,24 @@ pub struct JsLinearMask {
     pub end_y: f32,
     /// Feather amount (0.0 = hard edge, 1.0 = full gradient)
     pub feather: f32,
+    /// Whether to invert the mask (apply effect on the end side)
+    #[serde(default)]
+    pub invert: bool,
     /// Whether the mask is enabled
     pub enabled: bool,
     /// Per-mask adjustments
     pub adjustments: JsAdjustments,
+    /// Optional per-mask tone curve control points (identity when omitted)
+    #[serde(default)]
+    pub points: Option<Vec<CurvePointJs>>,
 }
 
 /// JavaScript-compatible radial gradient mask.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsRadialMask {
+    /// Stable identifier used to solo or bypass the mask (optional)
+    #[serde(default)]
+    pub id: Option<String>,
     /// Center X coordinate (0.0 to 1.0)
     pub center_x: f32,
     /// Center Y coordinate (0.0 to 1.0)
@@ -61,6 +154,74 @@ pub struct JsRadialMask {
     pub enabled: bool,
     /// Per-mask adjustments
     pub adjustments: JsAdjustments,
+    /// Optional per-mask tone curve control points (identity when omitted)
+    #[serde(default)]
+    pub points: Option<Vec<CurvePointJs>>,
+}
+
+/// JavaScript-compatible strip mask: a feathered band along a center line.
+///
+/// Only in the ordered [`JsMaskStack`], as `kind: "strip"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsStripMask {
+    /// Stable identifier used to solo or bypass the mask (optional)
+    #[serde(default)]
+    pub id: Option<String>,
+    /// First point on the center line, X coordinate (0.0 to 1.0)
+    pub start_x: f32,
+    /// First point on the center line, Y coordinate (0.0 to 1.0)
+    pub start_y: f32,
+    /// Second point on the center line, X coordinate (0.0 to 1.0)
+    pub end_x: f32,
+    /// Second point on the center line, Y coordinate (0.0 to 1.0)
+    pub end_y: f32,
+    /// Distance from the center line with full effect
+    pub half_width: f32,
+    /// Distance beyond `half_width` over which the effect falls to zero
+    #[serde(default)]
+    pub feather: f32,
+    /// Whether to invert the mask (apply effect outside the strip)
+    #[serde(default)]
+    pub invert: bool,
+    /// Whether the mask is enabled
+    pub enabled: bool,
+    /// Per-mask adjustments
+    pub adjustments: JsAdjustments,
+    /// Optional per-mask tone curve control points (identity when omitted)
+    #[serde(default)]
+    pub points: Option<Vec<CurvePointJs>>,
+}
+
+/// JavaScript-compatible brush mask: a mask stored as an alpha plane.
+///
+/// Made from a linear or radial mask by [`convert_mask_to_raster`] and
+/// edited with [`erase_from_raster_mask`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsBrushMask {
+    /// Stable identifier used to solo or bypass the mask (optional)
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Alpha plane width in texels
+    pub width: u32,
+    /// Alpha plane height in texels
+    pub height: u32,
+    /// Strength per texel, row-major, 0 to 255
+    pub alpha: Vec<u8>,
+    /// Whether the mask is enabled
+    pub enabled: bool,
+    /// Per-mask adjustments
+    pub adjustments: JsAdjustments,
+    /// Optional per-mask tone curve control points (identity when omitted)
+    #[serde(default)]
+    pub points: Option<Vec<CurvePointJs>>,
+}
+
+impl JsBrushMask {
+    /// The core mask for this object's alpha plane.
+    fn to_core(&self) -> Result<BrushMask, JsValue> {
+        BrushMask::from_alpha(self.width, self.height, self.alpha.clone())
+            .map_err(|e| mask_error_to_js(&e))
+    }
 }
 
 /// JavaScript-compatible adjustments for masks.
@@ -89,6 +250,10 @@ pub struct JsAdjustments {
     pub saturation: f32,
     #[serde(default)]
     pub vibrance: f32,
+    #[serde(default)]
+    pub protect_skin_tones: bool,
+    #[serde(default)]
+    pub color_model: ColorModel,
 }
 
 impl From<JsAdjustments> for BasicAdjustments {
@@ -104,91 +269,1003 @@ impl From<JsAdjustments> for BasicAdjustments {
             tint: js.tint,
             saturation: js.saturation,
             vibrance: js.vibrance,
+            protect_skin_tones: js.protect_skin_tones,
+            color_model: js.color_model,
         }
     }
 }
 
+/// Build the LUT for a mask's optional curve points.
+///
+/// Returns `None` for missing or identity curves so the core skips curve work.
+fn mask_curve(points: Option<&[CurvePointJs]>) -> Option<ToneCurveLut> {
+    points.map(lut_from_points).filter(|lut| !lut.is_identity())
+}
+
 /// Apply masked adjustments to an image.
 ///
 /// Each mask in the stack applies its own set of adjustments, blended based on
-/// the mask's strength at each pixel. Masks are processed sequentially.
+/// the mask's strength at each pixel. Masks are processed sequentially, in
+/// the order of the stack's `masks` array; a stack in the older
+/// `linear_masks`/`radial_masks` shape is still accepted and renders as
+/// before.
+///
+/// A mask may also carry tone curve `points`; the curve is applied on top of
+/// the mask's adjustments, only within the mask.
 ///
 /// # Arguments
 /// * `image` - The source image to apply adjustments to
 /// * `mask_data` - JavaScript object containing the mask stack (JsMaskStack structure)
+/// * `clamp` - Clamp out-of-range mask adjustments to their limits instead of
+///   rejecting them (defaults to false)
+/// * `solo_id` - Render only the mask with this `id`
+/// * `bypass_ids` - Skip the masks with these `id`s
 ///
 /// # Returns
 /// A new JsDecodedImage with the masked adjustments applied
 ///
+/// # Errors
+/// Throws if the mask data cannot be parsed, or an `Error` with a `code`
+/// property if the image is empty or its buffer does not match its dimensions,
+/// or if an enabled mask's adjustments are out of range (unless `clamp` is
+/// set) or not finite. Referring to an id no mask has throws with `code`
+/// `"UNKNOWN_MASK_ID"` and a `known_ids` array, and two masks sharing an id
+/// throw with `code` `"DUPLICATE_MASK_ID"` and the `id`. In the `masks` shape
+/// every mask must have an id.
+///
 /// # Example (TypeScript)
 /// ```typescript
 /// const maskStack = {
-///   linear_masks: [{
+///   masks: [{
+///     kind: 'linear',
+///     id: 'sky',
 ///     start_x: 0.0, start_y: 0.5,
 ///     end_x: 1.0, end_y: 0.5,
 ///     feather: 0.5,
 ///     enabled: true,
-///     adjustments: { exposure: 1.0 }
-///   }],
-///   radial_masks: []
+///     adjustments: { exposure: 1.0 },
+///     // Optional: S-curve applied only inside the mask
+///     points: [{ x: 0, y: 0 }, { x: 0.25, y: 0.15 }, { x: 0.75, y: 0.85 }, { x: 1, y: 1 }]
+///   }, {
+///     // Applied to the result of 'sky'
+///     kind: 'radial',
+///     id: 'face',
+///     center_x: 0.5, center_y: 0.6, radius_x: 0.2, radius_y: 0.3,
+///     rotation: 0, feather: 0.5, invert: false,
+///     enabled: true,
+///     adjustments: { shadows: 30 }
+///   }]
 /// };
 ///
 /// const result = apply_masked_adjustments(sourceImage, maskStack);
+///
+/// // Solo one mask, or bypass some, by the `id` set on each mask
+/// const soloed = apply_masked_adjustments(sourceImage, maskStack, false, 'sky');
+/// const bypassed = apply_masked_adjustments(sourceImage, maskStack, false, undefined, ['sky']);
 /// ```
 #[wasm_bindgen]
 pub fn apply_masked_adjustments(
     image: &JsDecodedImage,
     mask_data: JsValue,
+    clamp: Option<bool>,
+    solo_id: Option<String>,
+    bypass_ids: Option<Vec<String>>,
 ) -> Result<JsDecodedImage, JsValue> {
-    // Parse the mask stack from JavaScript
-    let masks: JsMaskStack = serde_wasm_bindgen::from_value(mask_data)
-        .map_err(|e| JsValue::from_str(&format!("Invalid mask data: {}", e)))?;
+    let selection = MaskSelection {
+        solo: solo_id,
+        bypass: bypass_ids.unwrap_or_default(),
+    };
 
     // Clone pixel data to avoid modifying original
-    let mut pixels = image.pixels();
+    let mut pixels = image.pooled_pixels();
+    apply_mask_stack(
+        &mut pixels,
+        image.width(),
+        image.height(),
+        mask_data,
+        clamp,
+        selection,
+    )?;
 
-    // Convert and filter enabled linear masks
-    let linear: Vec<_> = masks
-        .linear_masks
-        .into_iter()
-        .filter(|m| m.enabled)
-        .map(|m| {
-            let mask = LinearGradientMask::new(m.start_x, m.start_y, m.end_x, m.end_y, m.feather);
-            let adj: BasicAdjustments = m.adjustments.into();
-            (mask, adj)
-        })
-        .collect();
+    // Return new image with adjusted pixels
+    Ok(JsDecodedImage::new(image.width(), image.height(), pixels))
+}
+
+/// Apply masked adjustments to an image in place.
+///
+/// Same as [`apply_masked_adjustments`] but modifies `image` instead of
+/// returning a copy, so peak memory stays at one image. Use this for very
+/// large images such as stitched panoramas.
+///
+/// # Errors
+/// Same as [`apply_masked_adjustments`]; on error the image is unchanged.
+#[wasm_bindgen]
+pub fn apply_masked_adjustments_in_place(
+    image: &mut JsDecodedImage,
+    mask_data: JsValue,
+    clamp: Option<bool>,
+    solo_id: Option<String>,
+    bypass_ids: Option<Vec<String>>,
+) -> Result<(), JsValue> {
+    let selection = MaskSelection {
+        solo: solo_id,
+        bypass: bypass_ids.unwrap_or_default(),
+    };
+    let (width, height) = (image.width(), image.height());
+    let pixels = image.pixels_mut();
+    apply_mask_stack(pixels, width, height, mask_data, clamp, selection)
+}
+
+/// Apply masked adjustments to a reduced-size preview of an image.
+///
+/// The mask counterpart of `apply_adjustments_preview`: every
+/// `2^scale_shift`-th pixel of every `2^scale_shift`-th row is read from
+/// `image`, with no resize, and the masks are applied to that small image.
+/// Mask geometry is normalized, so the preview matches the full render.
+///
+/// # Arguments
+/// * `scale_shift` - 0 for full size, 1 for half, 2 for quarter, ...
+///
+/// The other arguments are as for [`apply_masked_adjustments`].
+///
+/// # Returns
+/// A new `(width >> scale_shift) x (height >> scale_shift)` image, at least
+/// 1x1
+///
+/// # Errors
+/// Same as [`apply_masked_adjustments`].
+#[wasm_bindgen]
+pub fn apply_masked_adjustments_preview(
+    image: &JsDecodedImage,
+    mask_data: JsValue,
+    scale_shift: u8,
+    clamp: Option<bool>,
+    solo_id: Option<String>,
+    bypass_ids: Option<Vec<String>>,
+) -> Result<JsDecodedImage, JsValue> {
+    let selection = MaskSelection {
+        solo: solo_id,
+        bypass: bypass_ids.unwrap_or_default(),
+    };
+    let mut preview =
+        subsample(image.as_decoded(), scale_shift).map_err(|e| decode_error_to_js(&e))?;
+    apply_mask_stack(
+        &mut preview.pixels,
+        preview.width,
+        preview.height,
+        mask_data,
+        clamp,
+        selection,
+    )?;
+    Ok(JsDecodedImage::from_decoded(preview))
+}
+
+/// Compute the histogram of the pixels one mask of a stack affects.
+///
+/// Pixels count in proportion to the mask's strength over them: a pixel at
+/// 30% strength adds 0.3 to its bins, and the bins are rounded to whole
+/// counts. A hard-edged mask gives the histogram of just the pixels inside
+/// it. The mask is used whether or not it is enabled.
+///
+/// # Arguments
+/// * `image` - The image to histogram
+/// * `mask_data` - Mask stack, as for [`apply_masked_adjustments`]
+/// * `index` - Position of the mask in the stack (in the legacy shape,
+///   counting linear, then radial, then brush masks)
+///
+/// # Errors
+/// Throws if the mask data is invalid, or an `Error` with `code`
+/// `"MASK_INDEX_OUT_OF_RANGE"` if `index` is past the end of the stack.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const hist = compute_masked_histogram(image, maskStack, selectedIndex);
+/// drawHistogram(hist.luminance());
+/// hist.free();
+/// ```
+#[wasm_bindgen]
+pub fn compute_masked_histogram(
+    image: &JsDecodedImage,
+    mask_data: JsValue,
+    index: u32,
+) -> Result<JsHistogram, JsValue> {
+    let masks = parse_mask_stack(mask_data, None)?;
+    let image = image.as_decoded();
+    let hist = compute_masked_histogram_core(
+        &image.pixels,
+        image.width,
+        image.height,
+        &masks,
+        MaskTarget::Index(index as usize),
+    )
+    .map_err(|e| mask_error_to_js(&e))?;
+    Ok(hist.into())
+}
+
+/// Parse a mask stack and apply the masks `selection` includes to `pixels`.
+fn apply_mask_stack(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    mask_data: JsValue,
+    clamp: Option<bool>,
+    selection: MaskSelection,
+) -> Result<(), JsValue> {
+    let masks = parse_mask_stack(mask_data, clamp)?;
+
+    // Apply the enabled, selected masks in stack order
+    apply_ordered_masks(pixels, width, height, &masks, &selection).map_err(|e| mask_error_to_js(&e))
+}
+
+/// Parse a mask stack in either shape, checking the adjustments of enabled
+/// masks and that no two masks share an id.
+fn parse_mask_stack(mask_data: JsValue, clamp: Option<bool>) -> Result<Vec<MaskEntry>, JsValue> {
+    // Parse the mask stack from JavaScript; only the ordered shape has `masks`
+    let ordered = js_sys::Reflect::has(&mask_data, &"masks".into()).unwrap_or(false);
+    let entries = if ordered {
+        let stack: JsMaskStack = serde_wasm_bindgen::from_value(mask_data)
+            .map_err(|e| JsValue::from_str(&format!("Invalid mask data: {}", e)))?;
+        if let Some(index) = stack.masks.iter().position(|m| m.id().is_none()) {
+            return Err(JsValue::from_str(&format!(
+                "Invalid mask data: mask {} has no id",
+                index
+            )));
+        }
+        stack.masks
+    } else {
+        let stack: JsLegacyMaskStack = serde_wasm_bindgen::from_value(mask_data)
+            .map_err(|e| JsValue::from_str(&format!("Invalid mask data: {}", e)))?;
+        stack.into_entries()
+    };
+    let clamp = clamp.unwrap_or(false);
+    // Only enabled masks are rendered, so only their values are checked
+    let checked = |adj: JsAdjustments, enabled: bool| {
+        let adj = BasicAdjustments::from(adj);
+        if !enabled {
+            return Ok(adj);
+        }
+        adj.sanitized(clamp).map_err(|e| adjustment_error_to_js(&e))
+    };
 
-    // Convert and filter enabled radial masks
-    let radial: Vec<_> = masks
-        .radial_masks
+    let masks: Vec<_> = entries
         .into_iter()
-        .filter(|m| m.enabled)
-        .map(|m| {
-            let mask = RadialGradientMask::new(
-                m.center_x,
-                m.center_y,
-                m.radius_x,
-                m.radius_y,
-                m.rotation.to_radians(), // Convert degrees to radians
-                m.feather,
-                m.invert,
-            );
-            let adj: BasicAdjustments = m.adjustments.into();
-            (mask, adj)
+        .map(|entry| {
+            Ok(match entry {
+                JsMaskEntry::Linear(m) => MaskEntry::Linear(StackMask {
+                    mask: LinearGradientMask::new(
+                        m.start_x, m.start_y, m.end_x, m.end_y, m.feather,
+                    )
+                    .with_invert(m.invert),
+                    curve: mask_curve(m.points.as_deref()),
+                    adjustments: checked(m.adjustments, m.enabled)?,
+                    id: m.id,
+                    enabled: m.enabled,
+                }),
+                JsMaskEntry::Radial(m) => MaskEntry::Radial(StackMask {
+                    mask: RadialGradientMask::new(
+                        m.center_x,
+                        m.center_y,
+                        m.radius_x,
+                        m.radius_y,
+                        m.rotation.to_radians(), // Convert degrees to radians
+                        m.feather,
+                        m.invert,
+                    ),
+                    curve: mask_curve(m.points.as_deref()),
+                    adjustments: checked(m.adjustments, m.enabled)?,
+                    id: m.id,
+                    enabled: m.enabled,
+                }),
+                JsMaskEntry::Brush(m) => MaskEntry::Brush(StackMask {
+                    mask: m.to_core()?,
+                    curve: mask_curve(m.points.as_deref()),
+                    adjustments: checked(m.adjustments, m.enabled)?,
+                    id: m.id,
+                    enabled: m.enabled,
+                }),
+                JsMaskEntry::Strip(m) => MaskEntry::Strip(StackMask {
+                    mask: StripMask::new(
+                        m.start_x,
+                        m.start_y,
+                        m.end_x,
+                        m.end_y,
+                        m.half_width,
+                        m.feather,
+                    )
+                    .with_invert(m.invert),
+                    curve: mask_curve(m.points.as_deref()),
+                    adjustments: checked(m.adjustments, m.enabled)?,
+                    id: m.id,
+                    enabled: m.enabled,
+                }),
+            })
         })
-        .collect();
+        .collect::<Result<_, JsValue>>()?;
+    check_unique_ids(&masks).map_err(|e| mask_error_to_js(&e))?;
+    Ok(masks)
+}
 
-    // Apply the masked adjustments
-    literoom_core::mask::apply_masked_adjustments(
-        &mut pixels,
-        image.width(),
-        image.height(),
-        &linear,
-        &radial,
+/// Geometry of a linear or radial mask, as in [`JsLinearMask`] and
+/// [`JsRadialMask`]. Other fields of the mask objects are ignored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum JsMaskShape {
+    Radial {
+        center_x: f32,
+        center_y: f32,
+        radius_x: f32,
+        radius_y: f32,
+        #[serde(default)]
+        rotation: f32,
+        feather: f32,
+        #[serde(default)]
+        invert: bool,
+    },
+    Linear {
+        start_x: f32,
+        start_y: f32,
+        end_x: f32,
+        end_y: f32,
+        feather: f32,
+        #[serde(default)]
+        invert: bool,
+    },
+}
+
+impl JsMaskShape {
+    fn to_radial(&self) -> Option<RadialGradientMask> {
+        match *self {
+            JsMaskShape::Radial {
+                center_x,
+                center_y,
+                radius_x,
+                radius_y,
+                rotation,
+                feather,
+                invert,
+            } => Some(RadialGradientMask::new(
+                center_x,
+                center_y,
+                radius_x,
+                radius_y,
+                rotation.to_radians(),
+                feather,
+                invert,
+            )),
+            JsMaskShape::Linear { .. } => None,
+        }
+    }
+
+    fn to_linear(&self) -> Option<LinearGradientMask> {
+        match *self {
+            JsMaskShape::Linear {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                feather,
+                invert,
+            } => Some(
+                LinearGradientMask::new(start_x, start_y, end_x, end_y, feather)
+                    .with_invert(invert),
+            ),
+            JsMaskShape::Radial { .. } => None,
+        }
+    }
+
+    fn to_geometry(&self) -> MaskGeometry {
+        match (self.to_linear(), self.to_radial()) {
+            (Some(linear), _) => MaskGeometry::Linear(linear),
+            (_, Some(radial)) => MaskGeometry::Radial(radial),
+            (None, None) => unreachable!("a mask shape is linear or radial"),
+        }
+    }
+}
+
+/// A single linear or radial mask object, with all its fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JsMask {
+    Radial(JsRadialMask),
+    Linear(JsLinearMask),
+}
+
+impl JsMask {
+    /// The same mask with its position moved by `(dx, dy)`.
+    fn offset(mut self, dx: f32, dy: f32) -> Self {
+        match &mut self {
+            JsMask::Radial(m) => {
+                m.center_x += dx;
+                m.center_y += dy;
+            }
+            JsMask::Linear(m) => {
+                m.start_x += dx;
+                m.start_y += dy;
+                m.end_x += dx;
+                m.end_y += dy;
+            }
+        }
+        self
+    }
+
+    /// The same mask with its `invert` flag toggled.
+    fn inverted(mut self) -> Self {
+        match &mut self {
+            JsMask::Radial(m) => m.invert = !m.invert,
+            JsMask::Linear(m) => m.invert = !m.invert,
+        }
+        self
+    }
+}
+
+/// Parse a complete linear or radial mask object.
+fn parse_mask(mask: JsValue) -> Result<JsMask, JsValue> {
+    serde_wasm_bindgen::from_value(mask)
+        .map_err(|e| JsValue::from_str(&format!("Invalid mask: {}", e)))
+}
+
+fn mask_to_js(mask: &JsMask) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(mask)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize mask: {}", e)))
+}
+
+/// Copy a mask, moved so the copy is visible next to the original.
+///
+/// The copy keeps every other field, including `id`; give it a new one
+/// before inserting it if the stack uses ids.
+///
+/// # Arguments
+/// * `mask` - A linear or radial mask object, as in the mask stack
+/// * `offset_x` - Horizontal shift in normalized coordinates
+/// * `offset_y` - Vertical shift in normalized coordinates
+///
+/// # Returns
+/// A new mask object: a radial mask with its center moved, or a linear
+/// mask with both its points moved.
+///
+/// # Errors
+/// Throws if the mask cannot be parsed.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const copy = duplicate_mask(stack.radial_masks[i], 0.05, 0.05);
+/// stack.radial_masks.splice(i + 1, 0, { ...copy, id: crypto.randomUUID() });
+/// ```
+#[wasm_bindgen]
+pub fn duplicate_mask(mask: JsValue, offset_x: f32, offset_y: f32) -> Result<JsValue, JsValue> {
+    mask_to_js(&parse_mask(mask)?.offset(offset_x, offset_y))
+}
+
+/// Invert a mask, so its effect applies where it did not before.
+///
+/// Only the `invert` flag changes; the handles stay where they are.
+///
+/// # Arguments
+/// * `mask` - A linear or radial mask object, as in the mask stack
+///
+/// # Returns
+/// A new mask object with `invert` toggled.
+///
+/// # Errors
+/// Throws if the mask cannot be parsed.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// stack.linear_masks[i] = invert_mask(stack.linear_masks[i]);
+/// ```
+#[wasm_bindgen]
+pub fn invert_mask(mask: JsValue) -> Result<JsValue, JsValue> {
+    mask_to_js(&parse_mask(mask)?.inverted())
+}
+
+/// Convert a linear or radial mask into a brush mask that can be erased by
+/// hand.
+///
+/// The mask's strength is rasterized into a `width x height` alpha plane;
+/// `id`, `enabled`, `adjustments` and `points` are kept. At the size of the
+/// image the mask is applied to, the result matches the original mask
+/// within 1 per channel. Half that size takes a quarter of the memory and
+/// stays within 2 per channel for feathered edges at least 20 image pixels
+/// wide, but blurs hard edges.
+///
+/// # Arguments
+/// * `mask` - A linear or radial mask object, as in the mask stack
+/// * `width` - Alpha plane width in texels
+/// * `height` - Alpha plane height in texels
+///
+/// # Returns
+/// A brush mask object for the stack's `brush_masks`:
+/// `{ id, width, height, alpha, enabled, adjustments, points }`.
+///
+/// # Errors
+/// Throws if the mask cannot be parsed, or if the dimensions are zero or
+/// exceed the image size limits.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const [mask] = stack.radial_masks.splice(i, 1);
+/// stack.brush_masks.push(convert_mask_to_raster(mask, image.width, image.height));
+/// ```
+#[wasm_bindgen]
+pub fn convert_mask_to_raster(mask: JsValue, width: u32, height: u32) -> Result<JsValue, JsValue> {
+    let shape = parse_mask_shape(mask.clone())?;
+    let (id, enabled, adjustments, points) = match parse_mask(mask)? {
+        JsMask::Radial(m) => (m.id, m.enabled, m.adjustments, m.points),
+        JsMask::Linear(m) => (m.id, m.enabled, m.adjustments, m.points),
+    };
+    validate_dimensions(width, height).map_err(|e| image_error_to_js(&e))?;
+    let raster = BrushMask::rasterize(&shape.to_geometry(), width, height);
+    brush_mask_to_js(&JsBrushMask {
+        id,
+        width,
+        height,
+        alpha: raster.alpha().to_vec(),
+        enabled,
+        adjustments,
+        points,
+    })
+}
+
+/// Erase part of a brush mask with a stroke.
+///
+/// Within `radius * (1 - feather)` of the stroke's path, `flow` of the alpha
+/// is removed (all of it by default); the feathered rim fades out to the
+/// full radius. Other fields of the mask are kept.
+///
+/// # Arguments
+/// * `mask` - A brush mask object, as returned by [`convert_mask_to_raster`]
+/// * `stroke` - `{ points: [{ x, y }, ...], radius, feather?, flow? }`, with
+///   points in normalized coordinates and `radius` as a fraction of the
+///   image width
+///
+/// # Returns
+/// A new brush mask object with the stroke erased.
+///
+/// # Errors
+/// Throws if the mask or stroke cannot be parsed, an `Error` with `code`
+/// `"BUFFER_SIZE_MISMATCH"` if the alpha plane does not match the mask's
+/// size, or with `code` `"INVALID_BRUSH_STROKE"` if the stroke has no points
+/// or a parameter is out of range.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const stroke = { points: dragPath.map(toNormalized), radius: 0.02, feather: 0.5 };
+/// stack.brush_masks[i] = erase_from_raster_mask(stack.brush_masks[i], stroke);
+/// ```
+#[wasm_bindgen]
+pub fn erase_from_raster_mask(mask: JsValue, stroke: JsValue) -> Result<JsValue, JsValue> {
+    let mut mask: JsBrushMask = serde_wasm_bindgen::from_value(mask)
+        .map_err(|e| JsValue::from_str(&format!("Invalid brush mask: {}", e)))?;
+    let stroke: BrushStroke = serde_wasm_bindgen::from_value(stroke)
+        .map_err(|e| JsValue::from_str(&format!("Invalid brush stroke: {}", e)))?;
+    let mut brush = mask.to_core()?;
+    brush.erase(&stroke).map_err(|e| mask_error_to_js(&e))?;
+    mask.alpha = brush.alpha().to_vec();
+    brush_mask_to_js(&mask)
+}
+
+fn brush_mask_to_js(mask: &JsBrushMask) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(mask)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize mask: {}", e)))
+}
+
+/// Find the pixels that change when a mask is moved or reshaped.
+///
+/// Call this while a mask handle is dragged, then re-render and blit only
+/// the returned rect with [`apply_masked_adjustments_region`].
+///
+/// # Arguments
+/// * `old_mask` - The mask before the change (a linear or radial mask object)
+/// * `new_mask` - The mask after the change; must be the same kind
+/// * `width` - Preview width in pixels
+/// * `height` - Preview height in pixels
+///
+/// # Returns
+/// `{ x, y, width, height }` in pixels, covering every pixel whose mask
+/// strength changed (feathered edges included), or `null` if none did.
+///
+/// # Errors
+/// Throws if either mask cannot be parsed or the two are different kinds.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const rect = compute_mask_dirty_rect(before, after, preview.width, preview.height);
+/// if (rect) {
+///   apply_masked_adjustments_region(preview, unmasked, maskStack, rect);
+///   ctx.putImageData(toImageData(preview), 0, 0, rect.x, rect.y, rect.width, rect.height);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn compute_mask_dirty_rect(
+    old_mask: JsValue,
+    new_mask: JsValue,
+    width: u32,
+    height: u32,
+) -> Result<JsValue, JsValue> {
+    let parse = |value: JsValue| {
+        serde_wasm_bindgen::from_value::<JsMaskShape>(value)
+            .map_err(|e| JsValue::from_str(&format!("Invalid mask: {}", e)))
+    };
+    let rect = dirty_rect(&parse(old_mask)?, &parse(new_mask)?, width, height)
+        .map_err(|e| JsValue::from_str(&e))?;
+    match rect {
+        Some(rect) => {
+            serde_wasm_bindgen::to_value(&rect).map_err(|e| JsValue::from_str(&e.to_string()))
+        }
+        None => Ok(JsValue::NULL),
+    }
+}
+
+/// Dirty rect between two masks of the same kind.
+fn dirty_rect(
+    old_mask: &JsMaskShape,
+    new_mask: &JsMaskShape,
+    width: u32,
+    height: u32,
+) -> Result<Option<PixelRect>, String> {
+    if let (Some(old), Some(new)) = (old_mask.to_radial(), new_mask.to_radial()) {
+        return Ok(core_mask::compute_mask_dirty_rect(
+            &old, &new, width, height,
+        ));
+    }
+    if let (Some(old), Some(new)) = (old_mask.to_linear(), new_mask.to_linear()) {
+        return Ok(core_mask::compute_mask_dirty_rect(
+            &old, &new, width, height,
+        ));
+    }
+    Err("Old and new mask must both be linear or both be radial".to_string())
+}
+
+/// Re-render one rect of a masked image after a mask changed.
+///
+/// Copies the rect from `source` (the image before masks) into `image` (the
+/// previous masked render), then applies the enabled masks of the stack
+/// inside it, in order. The result is identical to a full
+/// [`apply_masked_adjustments`] of `source`, provided the rect came from
+/// [`compute_mask_dirty_rect`] for the only mask that changed.
+///
+/// # Arguments
+/// * `image` - Previous masked render; updated in place
+/// * `source` - The same image before masks were applied
+/// * `mask_data` - The new mask stack (JsMaskStack structure)
+/// * `region` - `{ x, y, width, height }` in pixels; clipped to the image
+/// * `clamp` - Clamp out-of-range mask adjustments instead of rejecting them
+///
+/// # Errors
+/// Throws if the mask data or region cannot be parsed, if the two images
+/// differ in size, or if the stack has enabled brush masks, which are not
+/// re-rendered by region; otherwise the same errors as
+/// [`apply_masked_adjustments`]. On error `image` is unchanged.
+#[wasm_bindgen]
+pub fn apply_masked_adjustments_region(
+    image: &mut JsDecodedImage,
+    source: &JsDecodedImage,
+    mask_data: JsValue,
+    region: JsValue,
+    clamp: Option<bool>,
+) -> Result<(), JsValue> {
+    let region: PixelRect = serde_wasm_bindgen::from_value(region)
+        .map_err(|e| JsValue::from_str(&format!("Invalid region: {}", e)))?;
+    let masks = parse_mask_stack(mask_data, clamp)?;
+    if masks
+        .iter()
+        .any(|m| matches!(m, MaskEntry::Brush(_)) && m.enabled())
+    {
+        return Err(JsValue::from_str(
+            "Brush masks cannot be re-rendered by region; use apply_masked_adjustments",
+        ));
+    }
+    image.validate()?;
+    source.validate()?;
+    if (image.width(), image.height()) != (source.width(), source.height()) {
+        return Err(JsValue::from_str(&format!(
+            "Source is {}x{} but the image is {}x{}",
+            source.width(),
+            source.height(),
+            image.width(),
+            image.height()
+        )));
+    }
+
+    let (width, height) = (image.width(), image.height());
+    rerender_region(
+        image.pixels_mut(),
+        source.as_decoded().pixels.as_slice(),
+        width,
+        height,
+        &region,
+        &masks,
     );
+    Ok(())
+}
 
-    // Return new image with adjusted pixels
+/// Restore `region` from `source` and apply the enabled masks inside it.
+///
+/// Both buffers must be valid `width x height` RGB images.
+fn rerender_region(
+    pixels: &mut [u8],
+    source: &[u8],
+    width: u32,
+    height: u32,
+    region: &PixelRect,
+    masks: &[MaskEntry],
+) {
+    let region = region.clamped(width, height);
+    let row_len = width as usize * 3;
+    let (start, end) = (
+        region.x as usize * 3,
+        (region.x + region.width) as usize * 3,
+    );
+    for y in region.y..region.y + region.height {
+        let row = y as usize * row_len;
+        pixels[row + start..row + end].copy_from_slice(&source[row + start..row + end]);
+    }
+
+    // The buffer was validated and the ids checked by the caller
+    let _ = apply_ordered_masks_region(pixels, width, height, &region, masks);
+}
+
+/// A mask's strength at every pixel, cached for re-applying it with
+/// different adjustments.
+///
+/// Create with [`rasterize_mask`] and add to a [`JsMaskLayers`]. The same
+/// object can be used across frames until the mask is moved or reshaped or
+/// the preview is resized; `is_current` tells when to rasterize again.
+#[wasm_bindgen]
+pub struct JsRasterizedMask {
+    inner: Rc<RasterizedMask>,
+}
+
+#[wasm_bindgen]
+impl JsRasterizedMask {
+    /// Raster width in pixels.
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.inner.width()
+    }
+
+    /// Raster height in pixels.
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.inner.height()
+    }
+
+    /// The mask's strength per pixel (0-255) as a single-channel image, for
+    /// drawing the mask as an overlay.
+    pub fn to_gray_image(&self) -> JsGrayImage {
+        JsGrayImage::from_gray(self.inner.image().clone())
+    }
+
+    /// Whether this raster is still valid for `mask` at `width x height`.
+    ///
+    /// # Errors
+    /// Throws if `mask` cannot be parsed.
+    pub fn is_current(&self, mask: JsValue, width: u32, height: u32) -> Result<bool, JsValue> {
+        let shape = parse_mask_shape(mask)?;
+        Ok(self.inner.is_current(&shape.to_geometry(), width, height))
+    }
+
+    /// Explicitly free WASM memory.
+    pub fn free(self) {
+        // Dropping self releases the memory
+    }
+}
+
+/// Rasterize a linear or radial mask at `width x height`.
+///
+/// # Arguments
+/// * `mask` - A linear or radial mask object; only its geometry is used
+/// * `width` - Width of the images it will be applied to
+/// * `height` - Height of the images it will be applied to
+///
+/// # Errors
+/// Throws if the mask cannot be parsed, or if the dimensions are zero or
+/// exceed the image size limits.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// let sky = rasterize_mask(skyMask, preview.width, preview.height);
+///
+/// // On every slider change
+/// if (!sky.is_current(skyMask, preview.width, preview.height)) {
+///   sky.free();
+///   sky = rasterize_mask(skyMask, preview.width, preview.height);
+/// }
+/// const layers = new JsMaskLayers();
+/// layers.add_cached(sky, skyMask.adjustments);
+/// layers.add_mask(vignette, vignette.adjustments);
+/// const result = apply_masked_adjustments_cached(preview, layers);
+/// layers.free();
+/// ```
+#[wasm_bindgen]
+pub fn rasterize_mask(mask: JsValue, width: u32, height: u32) -> Result<JsRasterizedMask, JsValue> {
+    let shape = parse_mask_shape(mask)?;
+    validate_dimensions(width, height).map_err(|e| image_error_to_js(&e))?;
+    Ok(JsRasterizedMask {
+        inner: Rc::new(RasterizedMask::new(shape.to_geometry(), width, height)),
+    })
+}
+
+/// Parse the geometry of a linear or radial mask object.
+fn parse_mask_shape(mask: JsValue) -> Result<JsMaskShape, JsValue> {
+    serde_wasm_bindgen::from_value(mask)
+        .map_err(|e| JsValue::from_str(&format!("Invalid mask: {}", e)))
+}
+
+/// Suggest a feather for a mask from the noise and edges under its boundary.
+///
+/// Backs the "auto feather" button: a hard edge through grain shimmers, so
+/// noisy or featureless boundaries get a large feather, while a boundary
+/// placed on a strong edge in the photo gets a small one.
+///
+/// # Arguments
+/// * `image` - The image the mask is drawn on (not modified)
+/// * `mask` - A linear or radial mask object, as in the mask stack
+///
+/// # Returns
+/// A feather from 0.0 to 0.8, or the mask's own feather when its boundary
+/// lies entirely outside the image.
+///
+/// # Errors
+/// Throws if the mask cannot be parsed or the image buffer is invalid.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const feather = suggest_mask_feather(preview, stack.radial_masks[i]);
+/// stack.radial_masks[i] = { ...stack.radial_masks[i], feather };
+/// ```
+#[wasm_bindgen]
+pub fn suggest_mask_feather(image: &JsDecodedImage, mask: JsValue) -> Result<f32, JsValue> {
+    let shape = parse_mask_shape(mask)?;
+    image.validate()?;
+    let image = image.as_decoded();
+    match shape.to_geometry() {
+        MaskGeometry::Linear(mask) => {
+            core_mask::suggest_linear_mask_feather(&image.pixels, image.width, image.height, &mask)
+        }
+        MaskGeometry::Radial(mask) => {
+            core_mask::suggest_mask_feather(&image.pixels, image.width, image.height, &mask)
+        }
+    }
+    .map_err(|e| image_error_to_js(&e))
+}
+
+/// Where a layer's mask strength comes from.
+enum LayerAlpha {
+    Cached(Rc<RasterizedMask>),
+    Geometry(MaskGeometry),
+}
+
+/// An ordered list of cached and uncached masks with their adjustments,
+/// for [`apply_masked_adjustments_cached`].
+///
+/// Adding a cached mask shares its raster rather than copying it, and the
+/// [`JsRasterizedMask`] stays usable afterwards.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct JsMaskLayers {
+    layers: Vec<(LayerAlpha, BasicAdjustments)>,
+}
+
+#[wasm_bindgen]
+impl JsMaskLayers {
+    /// Create an empty layer list.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsMaskLayers {
+        JsMaskLayers::default()
+    }
+
+    /// Number of layers added so far.
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    /// Add a cached mask with its adjustments.
+    ///
+    /// # Errors
+    /// Throws if the adjustments cannot be parsed, or an `Error` with a
+    /// `code` property if they are out of range (unless `clamp` is set) or
+    /// not finite.
+    pub fn add_cached(
+        &mut self,
+        mask: &JsRasterizedMask,
+        adjustments: JsValue,
+        clamp: Option<bool>,
+    ) -> Result<(), JsValue> {
+        let adj = parse_layer_adjustments(adjustments, clamp)?;
+        self.layers
+            .push((LayerAlpha::Cached(Rc::clone(&mask.inner)), adj));
+        Ok(())
+    }
+
+    /// Add a mask that is evaluated at each pixel, with its adjustments.
+    ///
+    /// # Errors
+    /// Throws if the mask cannot be parsed; otherwise the same errors as
+    /// `add_cached`.
+    pub fn add_mask(
+        &mut self,
+        mask: JsValue,
+        adjustments: JsValue,
+        clamp: Option<bool>,
+    ) -> Result<(), JsValue> {
+        let shape = parse_mask_shape(mask)?;
+        let adj = parse_layer_adjustments(adjustments, clamp)?;
+        self.layers
+            .push((LayerAlpha::Geometry(shape.to_geometry()), adj));
+        Ok(())
+    }
+
+    /// Explicitly free WASM memory.
+    pub fn free(self) {
+        // Dropping self releases the memory
+    }
+}
+
+impl JsMaskLayers {
+    /// Apply the layers to a valid `width x height` RGB buffer, in order.
+    fn apply(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), core_mask::MaskError> {
+        let layers: Vec<_> = self
+            .layers
+            .iter()
+            .map(|(alpha, adj)| {
+                let alpha = match alpha {
+                    LayerAlpha::Cached(mask) => MaskAlpha::Cached(mask),
+                    LayerAlpha::Geometry(geometry) => MaskAlpha::Geometry(geometry),
+                };
+                (alpha, adj)
+            })
+            .collect();
+        core_mask::apply_masked_adjustments_mixed(pixels, width, height, &layers)
+    }
+}
+
+/// Parse and check one layer's adjustments.
+fn parse_layer_adjustments(
+    adjustments: JsValue,
+    clamp: Option<bool>,
+) -> Result<BasicAdjustments, JsValue> {
+    let adj: JsAdjustments = serde_wasm_bindgen::from_value(adjustments)
+        .map_err(|e| JsValue::from_str(&format!("Invalid adjustments: {}", e)))?;
+    BasicAdjustments::from(adj)
+        .sanitized(clamp.unwrap_or(false))
+        .map_err(|e| adjustment_error_to_js(&e))
+}
+
+/// Apply a mix of cached and uncached masks to an image.
+///
+/// Cached masks skip evaluating their geometry, which is most of the cost of
+/// [`apply_masked_adjustments`] while only adjustments change. The result
+/// matches [`apply_masked_adjustments`] for the same masks within 1 per
+/// channel for adjustments that stay within the 0-255 range.
+///
+/// # Arguments
+/// * `image` - The source image
+/// * `layers` - Masks and adjustments, applied in the order they were added
+///
+/// # Returns
+/// A new JsDecodedImage with the masked adjustments applied
+///
+/// # Errors
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions, or with `code`
+/// `"MASK_RASTER_SIZE_MISMATCH"` if a cached mask was rasterized at a
+/// different size than the image.
+#[wasm_bindgen]
+pub fn apply_masked_adjustments_cached(
+    image: &JsDecodedImage,
+    layers: &JsMaskLayers,
+) -> Result<JsDecodedImage, JsValue> {
+    let mut pixels = image.pooled_pixels();
+    layers
+        .apply(&mut pixels, image.width(), image.height())
+        .map_err(|e| mask_error_to_js(&e))?;
     Ok(JsDecodedImage::new(image.width(), image.height(), pixels))
 }
 
@@ -223,20 +1300,24 @@ mod tests {
     #[test]
     fn test_js_mask_stack_creation() {
         // Test that mask structures can be created
-        let mask_stack = JsMaskStack {
+        let mask_stack = JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.5,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -246,7 +1327,9 @@ mod tests {
                 invert: false,
                 enabled: true,
                 adjustments: JsAdjustments::default(),
+                points: None,
             }],
+            brush_masks: vec![],
         };
 
         assert_eq!(mask_stack.linear_masks.len(), 1);
@@ -258,16 +1341,19 @@ mod tests {
     #[test]
     fn test_linear_mask_conversion() {
         let js_mask = JsLinearMask {
+            id: None,
             start_x: 0.1,
             start_y: 0.2,
             end_x: 0.8,
             end_y: 0.9,
             feather: 0.5,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments {
                 exposure: 0.5,
                 ..Default::default()
             },
+            points: None,
         };
 
         // Convert to core mask
@@ -286,9 +1372,89 @@ mod tests {
         assert_eq!(core_mask.feather, 0.5);
     }
 
+    #[test]
+    fn test_duplicated_radial_mask_differs_only_in_center() {
+        let original = JsRadialMask {
+            id: Some("vignette".to_string()),
+            center_x: 0.4,
+            center_y: 0.6,
+            radius_x: 0.3,
+            radius_y: 0.2,
+            rotation: 30.0,
+            feather: 0.5,
+            invert: true,
+            enabled: true,
+            adjustments: JsAdjustments {
+                exposure: -0.5,
+                ..Default::default()
+            },
+            points: None,
+        };
+
+        let JsMask::Radial(mut copy) = JsMask::Radial(original.clone()).offset(0.05, -0.1) else {
+            panic!("duplicating keeps the mask kind");
+        };
+        assert!((copy.center_x - 0.45).abs() < 1e-6);
+        assert!((copy.center_y - 0.5).abs() < 1e-6);
+        (copy.center_x, copy.center_y) = (original.center_x, original.center_y);
+        assert_eq!(format!("{:?}", copy), format!("{:?}", original));
+    }
+
+    #[test]
+    fn test_duplicated_linear_mask_moves_both_points() {
+        let original = JsLinearMask {
+            id: None,
+            start_x: 0.1,
+            start_y: 0.2,
+            end_x: 0.8,
+            end_y: 0.9,
+            feather: 0.5,
+            invert: false,
+            enabled: true,
+            adjustments: JsAdjustments::default(),
+            points: None,
+        };
+
+        let JsMask::Linear(copy) = JsMask::Linear(original).offset(0.1, 0.0) else {
+            panic!("duplicating keeps the mask kind");
+        };
+        assert!((copy.start_x - 0.2).abs() < 1e-6 && (copy.end_x - 0.9).abs() < 1e-6);
+        assert_eq!((copy.start_y, copy.end_y), (0.2, 0.9));
+    }
+
+    #[test]
+    fn test_inverted_linear_mask_complements_original() {
+        let original = JsLinearMask {
+            id: None,
+            start_x: 0.0,
+            start_y: 0.3,
+            end_x: 1.0,
+            end_y: 0.7,
+            feather: 0.6,
+            invert: false,
+            enabled: true,
+            adjustments: JsAdjustments::default(),
+            points: None,
+        };
+        let core = |m: &JsLinearMask| {
+            LinearGradientMask::new(m.start_x, m.start_y, m.end_x, m.end_y, m.feather)
+                .with_invert(m.invert)
+        };
+
+        let JsMask::Linear(inverted) = JsMask::Linear(original.clone()).inverted() else {
+            panic!("inverting keeps the mask kind");
+        };
+        assert!(inverted.invert);
+        for (x, y) in [(0.0, 0.0), (0.3, 0.5), (0.5, 0.5), (0.9, 0.2), (1.0, 1.0)] {
+            let expected = 1.0 - core(&original).evaluate(x, y);
+            assert!((core(&inverted).evaluate(x, y) - expected).abs() < 1e-6);
+        }
+    }
+
     #[test]
     fn test_radial_mask_conversion_degrees_to_radians() {
         let js_mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -298,6 +1464,7 @@ mod tests {
             invert: true,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         // Convert to core mask (rotation should be converted to radians)
@@ -353,6 +1520,8 @@ mod tests {
             tint: 10.0,
             vibrance: 35.0,
             saturation: -15.0,
+            protect_skin_tones: true,
+            color_model: ColorModel::Oklab,
         };
 
         let basic: BasicAdjustments = js_adj.into();
@@ -366,6 +1535,8 @@ mod tests {
         assert_eq!(basic.tint, 10.0);
         assert_eq!(basic.vibrance, 35.0);
         assert_eq!(basic.saturation, -15.0);
+        assert!(basic.protect_skin_tones);
+        assert_eq!(basic.color_model, ColorModel::Oklab);
     }
 
     #[test]
@@ -405,6 +1576,8 @@ mod tests {
             tint: -50.0,
             vibrance: -100.0,
             saturation: -100.0,
+            protect_skin_tones: false,
+            color_model: ColorModel::Legacy,
         };
 
         let basic: BasicAdjustments = js_adj.into();
@@ -427,13 +1600,16 @@ mod tests {
     #[test]
     fn test_linear_mask_boundary_values_zero() {
         let mask = JsLinearMask {
+            id: None,
             start_x: 0.0,
             start_y: 0.0,
             end_x: 0.0,
             end_y: 0.0,
             feather: 0.0,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.start_x, 0.0);
@@ -446,13 +1622,16 @@ mod tests {
     #[test]
     fn test_linear_mask_boundary_values_one() {
         let mask = JsLinearMask {
+            id: None,
             start_x: 1.0,
             start_y: 1.0,
             end_x: 1.0,
             end_y: 1.0,
             feather: 1.0,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.start_x, 1.0);
@@ -469,13 +1648,16 @@ mod tests {
 
         for feather in feather_values {
             let mask = JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments::default(),
+                points: None,
             };
 
             assert_eq!(mask.feather, feather);
@@ -496,13 +1678,16 @@ mod tests {
     #[test]
     fn test_linear_mask_enabled_flag_true() {
         let mask = JsLinearMask {
+            id: None,
             start_x: 0.0,
             start_y: 0.0,
             end_x: 1.0,
             end_y: 1.0,
             feather: 0.5,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(mask.enabled);
@@ -511,13 +1696,16 @@ mod tests {
     #[test]
     fn test_linear_mask_enabled_flag_false() {
         let mask = JsLinearMask {
+            id: None,
             start_x: 0.0,
             start_y: 0.0,
             end_x: 1.0,
             end_y: 1.0,
             feather: 0.5,
+            invert: false,
             enabled: false,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(!mask.enabled);
@@ -526,11 +1714,13 @@ mod tests {
     #[test]
     fn test_linear_mask_with_all_adjustments() {
         let mask = JsLinearMask {
+            id: None,
             start_x: 0.2,
             start_y: 0.3,
             end_x: 0.8,
             end_y: 0.7,
             feather: 0.6,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments {
                 exposure: 1.0,
@@ -543,7 +1733,10 @@ mod tests {
                 tint: -10.0,
                 vibrance: 25.0,
                 saturation: -15.0,
+                protect_skin_tones: false,
+                color_model: ColorModel::Legacy,
             },
+            points: None,
         };
 
         assert_eq!(mask.adjustments.exposure, 1.0);
@@ -565,6 +1758,7 @@ mod tests {
     #[test]
     fn test_radial_mask_rotation_0_degrees() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -574,6 +1768,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.rotation, 0.0);
@@ -586,6 +1781,7 @@ mod tests {
     #[test]
     fn test_radial_mask_rotation_45_degrees() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -595,6 +1791,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.rotation, 45.0);
@@ -607,6 +1804,7 @@ mod tests {
     #[test]
     fn test_radial_mask_rotation_90_degrees() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -616,6 +1814,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.rotation, 90.0);
@@ -628,6 +1827,7 @@ mod tests {
     #[test]
     fn test_radial_mask_rotation_180_degrees() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -637,6 +1837,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.rotation, 180.0);
@@ -649,6 +1850,7 @@ mod tests {
     #[test]
     fn test_radial_mask_rotation_360_degrees() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -658,6 +1860,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.rotation, 360.0);
@@ -670,6 +1873,7 @@ mod tests {
     #[test]
     fn test_radial_mask_invert_true() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -679,6 +1883,7 @@ mod tests {
             invert: true,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(mask.invert);
@@ -687,6 +1892,7 @@ mod tests {
     #[test]
     fn test_radial_mask_invert_false() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -696,6 +1902,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(!mask.invert);
@@ -705,6 +1912,7 @@ mod tests {
     fn test_radial_mask_symmetric_radii() {
         // Circular mask (equal radii)
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.25,
@@ -714,6 +1922,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert_eq!(mask.radius_x, mask.radius_y);
@@ -724,6 +1933,7 @@ mod tests {
     fn test_radial_mask_asymmetric_radii_horizontal() {
         // Wide ellipse (larger horizontal radius)
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.4,
@@ -733,6 +1943,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(mask.radius_x > mask.radius_y);
@@ -744,6 +1955,7 @@ mod tests {
     fn test_radial_mask_asymmetric_radii_vertical() {
         // Tall ellipse (larger vertical radius)
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.15,
@@ -753,6 +1965,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(mask.radius_y > mask.radius_x);
@@ -763,6 +1976,7 @@ mod tests {
     #[test]
     fn test_radial_mask_enabled_flag_true() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -772,6 +1986,7 @@ mod tests {
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(mask.enabled);
@@ -780,6 +1995,7 @@ mod tests {
     #[test]
     fn test_radial_mask_enabled_flag_false() {
         let mask = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -789,6 +2005,7 @@ mod tests {
             invert: false,
             enabled: false,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         assert!(!mask.enabled);
@@ -809,6 +2026,7 @@ mod tests {
 
         for (degrees, expected_radians) in test_cases {
             let mask = JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -818,6 +2036,7 @@ mod tests {
                 invert: false,
                 enabled: true,
                 adjustments: JsAdjustments::default(),
+                points: None,
             };
 
             let radians = mask.rotation.to_radians();
@@ -832,39 +2051,46 @@ mod tests {
     }
 
     // =====================================================================
-    // JsMaskStack Tests
+    // JsLegacyMaskStack Tests
     // =====================================================================
 
     #[test]
     fn test_mask_stack_only_linear_masks() {
-        let mask_stack = JsMaskStack {
+        let mask_stack = JsLegacyMaskStack {
             linear_masks: vec![
                 JsLinearMask {
+                    id: None,
                     start_x: 0.0,
                     start_y: 0.0,
                     end_x: 1.0,
                     end_y: 0.0,
                     feather: 0.5,
+                    invert: false,
                     enabled: true,
                     adjustments: JsAdjustments {
                         exposure: 0.5,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsLinearMask {
+                    id: None,
                     start_x: 0.0,
                     start_y: 1.0,
                     end_x: 1.0,
                     end_y: 1.0,
                     feather: 0.3,
+                    invert: false,
                     enabled: true,
                     adjustments: JsAdjustments {
                         contrast: 20.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
             ],
             radial_masks: vec![],
+            brush_masks: vec![],
         };
 
         assert_eq!(mask_stack.linear_masks.len(), 2);
@@ -875,10 +2101,11 @@ mod tests {
 
     #[test]
     fn test_mask_stack_only_radial_masks() {
-        let mask_stack = JsMaskStack {
+        let mask_stack = JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![
                 JsRadialMask {
+                    id: None,
                     center_x: 0.25,
                     center_y: 0.25,
                     radius_x: 0.2,
@@ -891,8 +2118,10 @@ mod tests {
                         shadows: 30.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsRadialMask {
+                    id: None,
                     center_x: 0.75,
                     center_y: 0.75,
                     radius_x: 0.15,
@@ -905,8 +2134,10 @@ mod tests {
                         highlights: -25.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
             ],
+            brush_masks: vec![],
         };
 
         assert_eq!(mask_stack.linear_masks.len(), 0);
@@ -917,20 +2148,24 @@ mod tests {
 
     #[test]
     fn test_mask_stack_both_types() {
-        let mask_stack = JsMaskStack {
+        let mask_stack = JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.5,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -943,7 +2178,9 @@ mod tests {
                     vibrance: 50.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         };
 
         assert_eq!(mask_stack.linear_masks.len(), 1);
@@ -954,9 +2191,10 @@ mod tests {
 
     #[test]
     fn test_mask_stack_empty() {
-        let mask_stack = JsMaskStack {
+        let mask_stack = JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![],
+            brush_masks: vec![],
         };
 
         assert_eq!(mask_stack.linear_masks.len(), 0);
@@ -965,47 +2203,57 @@ mod tests {
 
     #[test]
     fn test_mask_stack_multiple_masks_each_type() {
-        let mask_stack = JsMaskStack {
+        let mask_stack = JsLegacyMaskStack {
             linear_masks: vec![
                 JsLinearMask {
+                    id: None,
                     start_x: 0.0,
                     start_y: 0.0,
                     end_x: 0.5,
                     end_y: 0.5,
                     feather: 0.3,
+                    invert: false,
                     enabled: true,
                     adjustments: JsAdjustments {
                         exposure: 0.5,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsLinearMask {
+                    id: None,
                     start_x: 0.5,
                     start_y: 0.0,
                     end_x: 1.0,
                     end_y: 0.5,
                     feather: 0.6,
+                    invert: false,
                     enabled: false,
                     adjustments: JsAdjustments {
                         exposure: -0.5,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsLinearMask {
+                    id: None,
                     start_x: 0.0,
                     start_y: 1.0,
                     end_x: 1.0,
                     end_y: 0.0,
                     feather: 1.0,
+                    invert: false,
                     enabled: true,
                     adjustments: JsAdjustments {
                         contrast: 15.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
             ],
             radial_masks: vec![
                 JsRadialMask {
+                    id: None,
                     center_x: 0.25,
                     center_y: 0.25,
                     radius_x: 0.1,
@@ -1015,8 +2263,10 @@ mod tests {
                     invert: false,
                     enabled: true,
                     adjustments: JsAdjustments::default(),
+                    points: None,
                 },
                 JsRadialMask {
+                    id: None,
                     center_x: 0.75,
                     center_y: 0.25,
                     radius_x: 0.15,
@@ -1026,8 +2276,10 @@ mod tests {
                     invert: true,
                     enabled: true,
                     adjustments: JsAdjustments::default(),
+                    points: None,
                 },
                 JsRadialMask {
+                    id: None,
                     center_x: 0.5,
                     center_y: 0.75,
                     radius_x: 0.2,
@@ -1037,8 +2289,10 @@ mod tests {
                     invert: false,
                     enabled: false,
                     adjustments: JsAdjustments::default(),
+                    points: None,
                 },
             ],
+            brush_masks: vec![],
         };
 
         assert_eq!(mask_stack.linear_masks.len(), 3);
@@ -1060,11 +2314,13 @@ mod tests {
     #[test]
     fn test_linear_mask_js_to_core_conversion() {
         let js_mask = JsLinearMask {
+            id: None,
             start_x: 0.1,
             start_y: 0.2,
             end_x: 0.9,
             end_y: 0.8,
             feather: 0.7,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments {
                 exposure: 1.5,
@@ -1077,7 +2333,10 @@ mod tests {
                 tint: -5.0,
                 vibrance: 35.0,
                 saturation: -10.0,
+                protect_skin_tones: false,
+                color_model: ColorModel::Legacy,
             },
+            points: None,
         };
 
         // Convert to core mask
@@ -1113,6 +2372,7 @@ mod tests {
     #[test]
     fn test_radial_mask_js_to_core_conversion() {
         let js_mask = JsRadialMask {
+            id: None,
             center_x: 0.4,
             center_y: 0.6,
             radius_x: 0.25,
@@ -1126,6 +2386,7 @@ mod tests {
                 saturation: 20.0,
                 ..Default::default()
             },
+            points: None,
         };
 
         // Convert to core mask (including degree to radian conversion)
@@ -1157,34 +2418,41 @@ mod tests {
 
     #[test]
     fn test_mask_stack_full_conversion() {
-        let mask_stack = JsMaskStack {
+        let mask_stack = JsLegacyMaskStack {
             linear_masks: vec![
                 JsLinearMask {
+                    id: None,
                     start_x: 0.0,
                     start_y: 0.5,
                     end_x: 1.0,
                     end_y: 0.5,
                     feather: 0.5,
+                    invert: false,
                     enabled: true,
                     adjustments: JsAdjustments {
                         exposure: 1.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsLinearMask {
+                    id: None,
                     start_x: 0.5,
                     start_y: 0.0,
                     end_x: 0.5,
                     end_y: 1.0,
                     feather: 0.8,
+                    invert: false,
                     enabled: false, // Disabled
                     adjustments: JsAdjustments {
                         contrast: 30.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
             ],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -1197,7 +2465,9 @@ mod tests {
                     vibrance: 40.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         };
 
         // Convert and filter enabled linear masks (simulating what apply_masked_adjustments does)
@@ -1247,17 +2517,20 @@ mod tests {
     #[test]
     fn test_mask_clone_and_reuse() {
         let original = JsLinearMask {
+            id: None,
             start_x: 0.2,
             start_y: 0.3,
             end_x: 0.8,
             end_y: 0.7,
             feather: 0.5,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments {
                 exposure: 1.5,
                 contrast: 20.0,
                 ..Default::default()
             },
+            points: None,
         };
 
         // Clone the mask
@@ -1300,6 +2573,7 @@ mod tests {
     #[test]
     fn test_radial_mask_clone_and_reuse() {
         let original = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
             radius_x: 0.3,
@@ -1313,6 +2587,7 @@ mod tests {
                 highlights: -15.0,
                 ..Default::default()
             },
+            points: None,
         };
 
         // Clone the mask
@@ -1365,20 +2640,24 @@ mod tests {
 
     #[test]
     fn test_mask_stack_clone() {
-        let original = JsMaskStack {
+        let original = JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.5,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -1388,7 +2667,9 @@ mod tests {
                 invert: false,
                 enabled: true,
                 adjustments: JsAdjustments::default(),
+                points: None,
             }],
+            brush_masks: vec![],
         };
 
         let cloned = original.clone();
@@ -1406,13 +2687,16 @@ mod tests {
     fn test_core_mask_evaluation_after_conversion() {
         // Create a JS linear mask and convert it
         let js_linear = JsLinearMask {
+            id: None,
             start_x: 0.0,
             start_y: 0.5,
             end_x: 1.0,
             end_y: 0.5,
             feather: 1.0,
+            invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: None,
         };
 
         let core_linear = LinearGradientMask::new(
@@ -1428,27 +2712,98 @@ mod tests {
         let val_center = core_linear.evaluate(0.5, 0.5);
         let val_end = core_linear.evaluate(1.0, 0.5);
 
-        assert!(val_start > 0.99, "Start should be ~1.0");
-        assert!((val_center - 0.5).abs() < 0.01, "Center should be ~0.5");
-        assert!(val_end < 0.01, "End should be ~0.0");
+        assert!(val_start > 0.99, "Start should be ~1.0");
+        assert!((val_center - 0.5).abs() < 0.01, "Center should be ~0.5");
+        assert!(val_end < 0.01, "End should be ~0.0");
+    }
+
+    #[test]
+    fn test_core_radial_mask_evaluation_after_conversion() {
+        // Create a JS radial mask and convert it
+        let js_radial = JsRadialMask {
+            id: None,
+            center_x: 0.5,
+            center_y: 0.5,
+            radius_x: 0.3,
+            radius_y: 0.3,
+            rotation: 0.0,
+            feather: 1.0,
+            invert: false,
+            enabled: true,
+            adjustments: JsAdjustments::default(),
+            points: None,
+        };
+
+        let core_radial = RadialGradientMask::new(
+            js_radial.center_x,
+            js_radial.center_y,
+            js_radial.radius_x,
+            js_radial.radius_y,
+            js_radial.rotation.to_radians(),
+            js_radial.feather,
+            js_radial.invert,
+        );
+
+        // Test evaluation at key points
+        let val_center = core_radial.evaluate(0.5, 0.5);
+        let val_edge = core_radial.evaluate(0.5 + 0.3, 0.5);
+        let val_outside = core_radial.evaluate(0.0, 0.0);
+
+        assert!(val_center > 0.99, "Center should be ~1.0");
+        assert!(val_edge < 0.01, "Edge should be ~0.0");
+        assert!(val_outside < 0.01, "Outside should be ~0.0");
+    }
+
+    // =========================================================================
+    // Per-mask tone curve tests
+    // =========================================================================
+
+    fn inverting_points() -> Vec<CurvePointJs> {
+        vec![
+            CurvePointJs { x: 0.0, y: 1.0 },
+            CurvePointJs { x: 1.0, y: 0.0 },
+        ]
+    }
+
+    #[test]
+    fn test_mask_curve_missing_points_is_none() {
+        assert!(mask_curve(None).is_none());
+    }
+
+    #[test]
+    fn test_mask_curve_identity_points_is_none() {
+        let linear = vec![
+            CurvePointJs { x: 0.0, y: 0.0 },
+            CurvePointJs { x: 1.0, y: 1.0 },
+        ];
+        assert!(mask_curve(Some(&linear)).is_none());
+        assert!(mask_curve(Some(&[])).is_none());
     }
 
     #[test]
-    fn test_core_radial_mask_evaluation_after_conversion() {
-        // Create a JS radial mask and convert it
+    fn test_mask_curve_inverting_points() {
+        let lut = mask_curve(Some(&inverting_points())).expect("Curve should not be identity");
+        assert!(lut.lut[0] > 250);
+        assert!(lut.lut[255] < 5);
+    }
+
+    #[test]
+    fn test_mask_curve_full_coverage_inverts() {
+        // Simulate the conversion in apply_masked_adjustments for a radial mask
         let js_radial = JsRadialMask {
+            id: None,
             center_x: 0.5,
             center_y: 0.5,
-            radius_x: 0.3,
-            radius_y: 0.3,
+            radius_x: 2.0,
+            radius_y: 2.0,
             rotation: 0.0,
-            feather: 1.0,
+            feather: 0.0,
             invert: false,
             enabled: true,
             adjustments: JsAdjustments::default(),
+            points: Some(inverting_points()),
         };
-
-        let core_radial = RadialGradientMask::new(
+        let mask = RadialGradientMask::new(
             js_radial.center_x,
             js_radial.center_y,
             js_radial.radius_x,
@@ -1457,15 +2812,156 @@ mod tests {
             js_radial.feather,
             js_radial.invert,
         );
+        let curve = mask_curve(js_radial.points.as_deref());
+        let adj: BasicAdjustments = js_radial.adjustments.into();
+
+        let mut pixels = vec![40u8; 4 * 4 * 3];
+        literoom_core::mask::apply_masked_adjustments_with_curves(
+            &mut pixels,
+            4,
+            4,
+            &[],
+            &[(mask, adj, curve)],
+        )
+        .unwrap();
 
-        // Test evaluation at key points
-        let val_center = core_radial.evaluate(0.5, 0.5);
-        let val_edge = core_radial.evaluate(0.5 + 0.3, 0.5);
-        let val_outside = core_radial.evaluate(0.0, 0.0);
+        assert!(pixels.iter().all(|&v| (v as i32 - 215).abs() <= 1));
+    }
 
-        assert!(val_center > 0.99, "Center should be ~1.0");
-        assert!(val_edge < 0.01, "Edge should be ~0.0");
-        assert!(val_outside < 0.01, "Outside should be ~0.0");
+    fn radial_shape(center_x: f32) -> JsMaskShape {
+        JsMaskShape::Radial {
+            center_x,
+            center_y: 0.5,
+            radius_x: 0.2,
+            radius_y: 0.25,
+            rotation: 30.0,
+            feather: 0.5,
+            invert: false,
+        }
+    }
+
+    #[test]
+    fn test_dirty_rect_rerender_matches_full_render() {
+        let (width, height) = (80, 60);
+        let source = literoom_core::testing::noise(width, height, 8).pixels;
+        let stack = |center_x: f32| {
+            vec![StackMask {
+                id: None,
+                enabled: true,
+                mask: radial_shape(center_x).to_radial().unwrap(),
+                adjustments: BasicAdjustments {
+                    exposure: 1.0,
+                    ..Default::default()
+                },
+                curve: None,
+            }]
+        };
+        let render = |radial: &[StackMask<RadialGradientMask>]| {
+            let mut pixels = source.clone();
+            core_mask::apply_selected_masks(
+                &mut pixels,
+                width,
+                height,
+                &[],
+                radial,
+                &MaskSelection::default(),
+            )
+            .unwrap();
+            pixels
+        };
+
+        let mut previous = render(&stack(0.4));
+        let full = render(&stack(0.42));
+        let rect = dirty_rect(&radial_shape(0.4), &radial_shape(0.42), width, height)
+            .unwrap()
+            .unwrap();
+        assert!(rect.width < width);
+
+        rerender_region(
+            &mut previous,
+            &source,
+            width,
+            height,
+            &rect,
+            &core_mask::legacy_mask_order(vec![], stack(0.42), vec![]),
+        );
+        assert_eq!(previous, full);
+    }
+
+    #[test]
+    fn test_dirty_rect_needs_matching_kinds() {
+        let linear = JsMaskShape::Linear {
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 1.0,
+            end_y: 0.0,
+            feather: 0.5,
+            invert: false,
+        };
+        assert!(dirty_rect(&linear, &radial_shape(0.5), 10, 10).is_err());
+        assert_eq!(dirty_rect(&linear, &linear, 10, 10), Ok(None));
+    }
+
+    #[test]
+    fn test_cached_layers_match_uncached_render() {
+        let (width, height) = (48, 32);
+        let source = literoom_core::testing::noise(width, height, 5).pixels;
+        let shape = radial_shape(0.4);
+        let raster = Rc::new(RasterizedMask::new(shape.to_geometry(), width, height));
+        let linear = LinearGradientMask::new(0.0, 0.0, 1.0, 1.0, 0.8);
+        let exposure = |value: f32| BasicAdjustments {
+            exposure: value,
+            ..Default::default()
+        };
+
+        // The same raster with two different adjustments
+        for value in [-0.5, 1.0] {
+            let layers = JsMaskLayers {
+                layers: vec![
+                    (LayerAlpha::Cached(Rc::clone(&raster)), exposure(value)),
+                    (
+                        LayerAlpha::Geometry(MaskGeometry::Linear(linear.clone())),
+                        exposure(0.3),
+                    ),
+                ],
+            };
+            let image = JsDecodedImage::new(width, height, source.clone());
+            let cached = apply_masked_adjustments_cached(&image, &layers).unwrap();
+
+            let mut uncached = source.clone();
+            core_mask::apply_masked_adjustments_mixed(
+                &mut uncached,
+                width,
+                height,
+                &[
+                    (MaskAlpha::Geometry(&shape.to_geometry()), &exposure(value)),
+                    (
+                        MaskAlpha::Geometry(&MaskGeometry::Linear(linear.clone())),
+                        &exposure(0.3),
+                    ),
+                ],
+            )
+            .unwrap();
+
+            for (a, b) in cached.pixels().iter().zip(&uncached) {
+                assert!(a.abs_diff(*b) <= 1, "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cached_layer_size_mismatch() {
+        let raster = RasterizedMask::new(radial_shape(0.5).to_geometry(), 8, 8);
+        let layers = JsMaskLayers {
+            layers: vec![(
+                LayerAlpha::Cached(Rc::new(raster)),
+                BasicAdjustments::default(),
+            )],
+        };
+        let mut pixels = vec![0u8; 4 * 4 * 3];
+
+        let err = layers.apply(&mut pixels, 4, 4).unwrap_err();
+        assert_eq!(err.code(), "MASK_RASTER_SIZE_MISMATCH");
     }
 }
 
@@ -1477,6 +2973,7 @@ mod tests {
 mod wasm_tests {
     use super::*;
     use literoom_core::decode::DecodedImage;
+    use literoom_core::testing::{gradient, GradientDirection};
     use wasm_bindgen_test::*;
 
     wasm_bindgen_test_configure!(run_in_browser);
@@ -1524,13 +3021,14 @@ mod wasm_tests {
         let image = create_gray_image(10, 10, 128);
         let original_pixels = image.pixels();
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1539,28 +3037,64 @@ mod wasm_tests {
         assert_eq!(output.pixels(), original_pixels, "Empty masks should not modify image");
     }
 
+    #[wasm_bindgen_test]
+    fn test_apply_masked_adjustments_preview_matches_subsample_then_masks() {
+        let image = JsDecodedImage::from_decoded(literoom_core::testing::noise(21, 13, 5));
+        let stack = JsLegacyMaskStack {
+            linear_masks: vec![JsLinearMask {
+                id: None,
+                start_x: 0.0,
+                start_y: 0.5,
+                end_x: 1.0,
+                end_y: 0.5,
+                feather: 0.5,
+                invert: false,
+                enabled: true,
+                adjustments: JsAdjustments {
+                    exposure: 1.0,
+                    ..Default::default()
+                },
+                points: None,
+            }],
+            radial_masks: vec![],
+            brush_masks: vec![],
+        };
+        let mask_data = || serde_wasm_bindgen::to_value(&stack).unwrap();
+
+        let small = JsDecodedImage::from_decoded(subsample(image.as_decoded(), 1).unwrap());
+        let expected = apply_masked_adjustments(&small, mask_data(), None, None, None).unwrap();
+        let preview =
+            apply_masked_adjustments_preview(&image, mask_data(), 1, None, None, None).unwrap();
+        assert_eq!((preview.width(), preview.height()), (10, 6));
+        assert_eq!(preview.pixels(), expected.pixels());
+    }
+
     #[wasm_bindgen_test]
     fn test_apply_masked_adjustments_single_linear_mask_exposure() {
         let image = create_gray_image(20, 20, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.0, // Hard edge for predictable testing
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0, // +1 stop (doubles brightness)
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1583,9 +3117,10 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_single_radial_mask_exposure() {
         let image = create_gray_image(20, 20, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -1598,11 +3133,13 @@ mod wasm_tests {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1625,20 +3162,24 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_multiple_masks() {
         let image = create_gray_image(20, 20, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.5,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 0.5,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.4,
@@ -1651,11 +3192,13 @@ mod wasm_tests {
                     contrast: 30.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1677,20 +3220,24 @@ mod wasm_tests {
         let image = create_gray_image(10, 10, 128);
         let original_pixels = image.pixels();
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: false, // Disabled
                 adjustments: JsAdjustments {
                     exposure: 2.0, // Would make a big change if enabled
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.5,
@@ -1703,11 +3250,13 @@ mod wasm_tests {
                     exposure: 2.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1729,7 +3278,7 @@ mod wasm_tests {
         // Pass invalid JsValue (a string instead of mask object)
         let invalid_data = JsValue::from_str("not a valid mask object");
 
-        let result = apply_masked_adjustments(&image, invalid_data);
+        let result = apply_masked_adjustments(&image, invalid_data, None, None, None);
         assert!(result.is_err(), "Should return error for invalid mask data");
 
         // Check error message contains useful info
@@ -1752,7 +3301,7 @@ mod wasm_tests {
         let linear_array = js_sys::Array::new();
         js_sys::Reflect::set(&partial_mask, &"linear_masks".into(), &linear_array).unwrap();
 
-        let result = apply_masked_adjustments(&image, partial_mask.into());
+        let result = apply_masked_adjustments(&image, partial_mask.into(), None, None, None);
         assert!(
             result.is_err(),
             "Should return error when required fields are missing"
@@ -1763,7 +3312,7 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_null_mask_data() {
         let image = create_gray_image(10, 10, 128);
 
-        let result = apply_masked_adjustments(&image, JsValue::NULL);
+        let result = apply_masked_adjustments(&image, JsValue::NULL, None, None, None);
         assert!(result.is_err(), "Should return error for null mask data");
     }
 
@@ -1771,13 +3320,266 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_undefined_mask_data() {
         let image = create_gray_image(10, 10, 128);
 
-        let result = apply_masked_adjustments(&image, JsValue::UNDEFINED);
+        let result = apply_masked_adjustments(&image, JsValue::UNDEFINED, None, None, None);
         assert!(
             result.is_err(),
             "Should return error for undefined mask data"
         );
     }
 
+    #[wasm_bindgen_test]
+    fn test_apply_masked_adjustments_buffer_mismatch() {
+        let image = JsDecodedImage::new(10, 10, vec![128u8; 50]);
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
+            linear_masks: vec![],
+            radial_masks: vec![],
+            brush_masks: vec![],
+        })
+        .unwrap();
+
+        let err = apply_masked_adjustments(&image, mask_data, None, None, None)
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("BUFFER_SIZE_MISMATCH"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_masked_adjustments_out_of_range() {
+        let image = create_gray_image(10, 10, 128);
+        let stack = |adjustments: JsAdjustments| {
+            serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
+                linear_masks: vec![],
+                radial_masks: vec![JsRadialMask {
+                    id: None,
+                    center_x: 0.5,
+                    center_y: 0.5,
+                    radius_x: 0.3,
+                    radius_y: 0.3,
+                    rotation: 0.0,
+                    feather: 0.5,
+                    invert: false,
+                    enabled: true,
+                    adjustments,
+                    points: None,
+                }],
+                brush_masks: vec![],
+            })
+            .unwrap()
+        };
+        let over = JsAdjustments {
+            exposure: 7.0,
+            ..Default::default()
+        };
+        let limit = JsAdjustments {
+            exposure: 5.0,
+            ..Default::default()
+        };
+
+        let err = apply_masked_adjustments(&image, stack(over.clone()), None, None, None)
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("ADJUSTMENT_OUT_OF_RANGE"));
+
+        let clamped =
+            apply_masked_adjustments(&image, stack(over), Some(true), None, None).unwrap();
+        let expected = apply_masked_adjustments(&image, stack(limit), None, None, None).unwrap();
+        assert_eq!(clamped.pixels(), expected.pixels());
+
+        let nan = JsAdjustments {
+            tint: f32::NAN,
+            ..Default::default()
+        };
+        let err = apply_masked_adjustments(&image, stack(nan), Some(true), None, None)
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("NON_FINITE_ADJUSTMENT"));
+    }
+
+    fn two_mask_stack(linear: bool, radial: bool) -> JsValue {
+        let a = JsLinearMask {
+            id: Some("a".to_string()),
+            start_x: 0.0,
+            start_y: 0.5,
+            end_x: 1.0,
+            end_y: 0.5,
+            feather: 0.8,
+            invert: false,
+            enabled: true,
+            adjustments: JsAdjustments {
+                exposure: 1.0,
+                ..Default::default()
+            },
+            points: None,
+        };
+        let b = JsRadialMask {
+            id: Some("b".to_string()),
+            center_x: 0.6,
+            center_y: 0.4,
+            radius_x: 0.3,
+            radius_y: 0.3,
+            rotation: 0.0,
+            feather: 0.5,
+            invert: false,
+            enabled: true,
+            adjustments: JsAdjustments {
+                saturation: -60.0,
+                exposure: -0.5,
+                ..Default::default()
+            },
+            points: None,
+        };
+        serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
+            linear_masks: if linear { vec![a] } else { vec![] },
+            radial_masks: if radial { vec![b] } else { vec![] },
+            brush_masks: vec![],
+        })
+        .unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_masked_adjustments_solo_and_bypass() {
+        let image = JsDecodedImage::from_decoded(gradient(32, 24, GradientDirection::Diagonal));
+        let render = |stack, solo: Option<&str>, bypass: Option<Vec<String>>| {
+            apply_masked_adjustments(&image, stack, None, solo.map(str::to_string), bypass)
+                .unwrap()
+                .pixels()
+        };
+
+        let only_a = render(two_mask_stack(true, false), None, None);
+        let only_b = render(two_mask_stack(false, true), None, None);
+        assert_ne!(only_a, only_b);
+
+        assert_eq!(render(two_mask_stack(true, true), Some("b"), None), only_b);
+        let bypass_b = Some(vec!["b".to_string()]);
+        assert_eq!(render(two_mask_stack(true, true), None, bypass_b), only_a);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_masked_adjustments_unknown_mask_id() {
+        let image = create_gray_image(10, 10, 128);
+        let err = apply_masked_adjustments(
+            &image,
+            two_mask_stack(true, true),
+            None,
+            Some("missing".to_string()),
+            None,
+        )
+        .err()
+        .unwrap();
+
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("UNKNOWN_MASK_ID"));
+        let known = js_sys::Reflect::get(&err, &"known_ids".into()).unwrap();
+        let known: Vec<_> = js_sys::Array::from(&known)
+            .iter()
+            .filter_map(|id| id.as_string())
+            .collect();
+        assert_eq!(known, vec!["a", "b"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_ordered_stack_matches_legacy_shape() {
+        let image = JsDecodedImage::from_decoded(gradient(32, 24, GradientDirection::Diagonal));
+        let render = |stack| {
+            apply_masked_adjustments(&image, stack, None, None, None)
+                .unwrap()
+                .pixels()
+        };
+        let legacy: JsLegacyMaskStack =
+            serde_wasm_bindgen::from_value(two_mask_stack(true, true)).unwrap();
+        let mut masks = legacy.into_entries();
+        let ordered = |masks: &[JsMaskEntry]| {
+            serde_wasm_bindgen::to_value(&JsMaskStack {
+                masks: masks.to_vec(),
+            })
+            .unwrap()
+        };
+
+        // Linear then radial is the order the legacy shape always used
+        let legacy_render = render(two_mask_stack(true, true));
+        assert_eq!(render(ordered(&masks)), legacy_render);
+
+        // The masks overlap, so swapping them changes the render
+        masks.reverse();
+        assert_ne!(render(ordered(&masks)), legacy_render);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_strip_mask_in_ordered_stack() {
+        let image = create_gray_image(10, 20, 100);
+        // Feather and invert default when omitted
+        let json = r#"{"masks":[{"kind":"strip","id":"band","start_x":0,"start_y":0.5,
+            "end_x":1,"end_y":0.5,"half_width":0.1,"enabled":true,
+            "adjustments":{"exposure":1}}]}"#;
+        let stack = js_sys::JSON::parse(json).unwrap();
+        let pixels = apply_masked_adjustments(&image, stack, None, None, None)
+            .unwrap()
+            .pixels();
+
+        let row = |y: usize| pixels[y * 30];
+        assert!(row(10) > 100);
+        assert_eq!(row(0), 100);
+        assert_eq!(row(19), 100);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_ordered_stack_ids_are_required_and_unique() {
+        let image = create_gray_image(10, 10, 128);
+        let json = r#"{"masks":[
+            {"kind":"radial","id":"a","center_x":0.5,"center_y":0.5,"radius_x":0.3,
+             "radius_y":0.3,"rotation":0,"feather":0.5,"invert":false,"enabled":true,
+             "adjustments":{"exposure":1}},
+            {"kind":"linear","id":"a","start_x":0,"start_y":0.5,"end_x":1,"end_y":0.5,
+             "feather":0.5,"enabled":true,"adjustments":{"contrast":20}}]}"#;
+        let render = |json: &str| {
+            let stack = js_sys::JSON::parse(json).unwrap();
+            apply_masked_adjustments(&image, stack, None, None, None)
+        };
+        let err = render(json).err().unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("DUPLICATE_MASK_ID"));
+        let id = js_sys::Reflect::get(&err, &"id".into()).unwrap();
+        assert_eq!(id.as_string().as_deref(), Some("a"));
+
+        let json = json.replacen(r#""id":"a","#, "", 1);
+        assert!(render(&json).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_mask_without_id_deserializes() {
+        let json = r#"{"linear_masks":[{"start_x":0,"start_y":0.5,"end_x":1,"end_y":0.5,
+            "feather":0.5,"enabled":true,"adjustments":{"exposure":1}}],"radial_masks":[]}"#;
+        let value = js_sys::JSON::parse(json).unwrap();
+        let stack: JsLegacyMaskStack = serde_wasm_bindgen::from_value(value).unwrap();
+        assert_eq!(stack.linear_masks[0].id, None);
+        assert!(!stack.linear_masks[0].invert);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_duplicate_and_invert_mask_objects() {
+        let json = r#"{"id":"grad","start_x":0,"start_y":0.5,"end_x":1,"end_y":0.5,
+            "feather":0.5,"enabled":true,"adjustments":{"exposure":1}}"#;
+        let mask = js_sys::JSON::parse(json).unwrap();
+
+        let copy = duplicate_mask(mask.clone(), 0.0, 0.1).unwrap();
+        let start_y = js_sys::Reflect::get(&copy, &"start_y".into()).unwrap();
+        assert!((start_y.as_f64().unwrap() - 0.6).abs() < 1e-6);
+        let id = js_sys::Reflect::get(&copy, &"id".into()).unwrap();
+        assert_eq!(id.as_string().as_deref(), Some("grad"));
+
+        let inverted = invert_mask(mask).unwrap();
+        let invert = js_sys::Reflect::get(&inverted, &"invert".into()).unwrap();
+        assert_eq!(invert.as_bool(), Some(true));
+        let twice = invert_mask(inverted).unwrap();
+        let invert = js_sys::Reflect::get(&twice, &"invert".into()).unwrap();
+        assert_eq!(invert.as_bool(), Some(false));
+
+        assert!(invert_mask(JsValue::from_str("not a mask")).is_err());
+    }
+
     // =========================================================================
     // Edge case tests
     // =========================================================================
@@ -1786,9 +3588,10 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_inverted_radial_mask() {
         let image = create_gray_image(20, 20, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -1801,11 +3604,13 @@ mod wasm_tests {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1832,24 +3637,28 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_feathered_linear_mask() {
         let image = create_gray_image(100, 1, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 1.0, // Full feather for smooth gradient
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1892,9 +3701,10 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_feathered_radial_mask() {
         let image = create_gray_image(20, 20, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.3,
@@ -1907,11 +3717,13 @@ mod wasm_tests {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1945,13 +3757,15 @@ mod wasm_tests {
         let image = create_colored_image(10, 10, 128, 128, 128);
 
         // Test all adjustment types in a single mask
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 2.0, // Ensure full coverage
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 0.5,
@@ -1964,13 +3778,17 @@ mod wasm_tests {
                     tint: -5.0,
                     saturation: 10.0,
                     vibrance: 15.0,
+                    protect_skin_tones: false,
+                    color_model: ColorModel::Legacy,
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -1991,9 +3809,10 @@ mod wasm_tests {
 
         // Create an elliptical mask with 90 degree rotation
         // This tests that rotation is converted from degrees to radians
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.4,  // Wide
@@ -2006,11 +3825,13 @@ mod wasm_tests {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2047,24 +3868,28 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_temperature_color_shift() {
         let image = create_gray_image(10, 10, 128);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 2.0, // Full coverage
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     temperature: -50.0, // Warm (should boost red, reduce blue)
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2084,24 +3909,28 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_saturation_desaturate() {
         let image = create_colored_image(10, 10, 200, 100, 50); // Warm colored image
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 2.0,
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     saturation: -100.0, // Full desaturation
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2125,24 +3954,28 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_output_dimensions_match_input() {
         let image = create_gray_image(123, 456, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.5,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2160,17 +3993,21 @@ mod wasm_tests {
         let image = create_gray_image(10, 10, 128);
         let original_pixels = image.pixels();
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments::default(), // All zeros
+                points: None,
             }],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.5,
@@ -2180,11 +4017,13 @@ mod wasm_tests {
                 invert: false,
                 enabled: true,
                 adjustments: JsAdjustments::default(), // All zeros
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2200,24 +4039,28 @@ mod wasm_tests {
         // Test that mask correctly blends based on position
         let image = create_gray_image(100, 1, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 1.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2245,24 +4088,28 @@ mod wasm_tests {
         // Test with minimal 1x1 image
         let image = create_gray_image(1, 1, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2275,24 +4122,28 @@ mod wasm_tests {
         // Test with larger image to verify performance doesn't break
         let image = create_gray_image(500, 500, 128);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 1.0,
                 end_y: 0.5,
                 feather: 0.5,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 0.5,
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2304,24 +4155,28 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_extreme_exposure_clamps() {
         let image = create_gray_image(10, 10, 200); // Bright starting point
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 2.0, // Full coverage
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: 5.0, // Maximum exposure
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2338,24 +4193,28 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_negative_exposure_clamps() {
         let image = create_gray_image(10, 10, 50); // Dark starting point
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![JsLinearMask {
+                id: None,
                 start_x: 0.0,
                 start_y: 0.5,
                 end_x: 2.0,
                 end_y: 0.5,
                 feather: 0.0,
+                invert: false,
                 enabled: true,
                 adjustments: JsAdjustments {
                     exposure: -5.0, // Minimum exposure
                     ..Default::default()
                 },
+                points: None,
             }],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2371,9 +4230,10 @@ mod wasm_tests {
         let image = create_gray_image(40, 20, 100);
 
         // Ellipse: wide (radius_x > radius_y)
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![],
             radial_masks: vec![JsRadialMask {
+                id: None,
                 center_x: 0.5,
                 center_y: 0.5,
                 radius_x: 0.4, // Wide
@@ -2386,11 +4246,13 @@ mod wasm_tests {
                     exposure: 1.0,
                     ..Default::default()
                 },
+                points: None,
             }],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2410,35 +4272,42 @@ mod wasm_tests {
         let image = create_gray_image(50, 50, 100);
 
         // Create multiple overlapping masks
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![
                 JsLinearMask {
+                    id: None,
                     start_x: 0.0,
                     start_y: 0.5,
                     end_x: 1.0,
                     end_y: 0.5,
                     feather: 0.5,
+                    invert: false,
                     enabled: true,
                     adjustments: JsAdjustments {
                         exposure: 0.3,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsLinearMask {
+                    id: None,
                     start_x: 0.5,
                     start_y: 0.0,
                     end_x: 0.5,
                     end_y: 1.0,
                     feather: 0.5,
+                    invert: false,
                     enabled: true,
                     adjustments: JsAdjustments {
                         contrast: 20.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
             ],
             radial_masks: vec![
                 JsRadialMask {
+                    id: None,
                     center_x: 0.25,
                     center_y: 0.25,
                     radius_x: 0.2,
@@ -2451,8 +4320,10 @@ mod wasm_tests {
                         saturation: 20.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsRadialMask {
+                    id: None,
                     center_x: 0.75,
                     center_y: 0.75,
                     radius_x: 0.2,
@@ -2465,12 +4336,14 @@ mod wasm_tests {
                         vibrance: 30.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
             ],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2482,38 +4355,45 @@ mod wasm_tests {
     fn test_apply_masked_adjustments_mixed_enabled_disabled() {
         let image = create_gray_image(20, 20, 100);
 
-        let mask_data = serde_wasm_bindgen::to_value(&JsMaskStack {
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
             linear_masks: vec![
                 JsLinearMask {
+                    id: None,
                     start_x: 0.0,
                     start_y: 0.5,
                     end_x: 1.0,
                     end_y: 0.5,
                     feather: 0.0,
+                    invert: false,
                     enabled: true, // Enabled
                     adjustments: JsAdjustments {
                         exposure: 1.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
                 JsLinearMask {
+                    id: None,
                     start_x: 1.0,
                     start_y: 0.5,
                     end_x: 0.0,
                     end_y: 0.5,
                     feather: 0.0,
+                    invert: false,
                     enabled: false, // Disabled - would cancel out first if enabled
                     adjustments: JsAdjustments {
                         exposure: -1.0,
                         ..Default::default()
                     },
+                    points: None,
                 },
             ],
             radial_masks: vec![],
+            brush_masks: vec![],
         })
         .unwrap();
 
-        let result = apply_masked_adjustments(&image, mask_data);
+        let result = apply_masked_adjustments(&image, mask_data, None, None, None);
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -2526,4 +4406,259 @@ mod wasm_tests {
             "Left should be bright (only enabled mask applies)"
         );
     }
+
+    // =========================================================================
+    // Per-mask tone curve tests
+    // =========================================================================
+
+    #[wasm_bindgen_test]
+    fn test_apply_masked_adjustments_curve_inverts_half() {
+        let image = create_gray_image(20, 20, 40);
+
+        let mask_data = serde_wasm_bindgen::to_value(&JsLegacyMaskStack {
+            linear_masks: vec![JsLinearMask {
+                id: None,
+                start_x: 0.0,
+                start_y: 0.5,
+                end_x: 1.0,
+                end_y: 0.5,
+                feather: 0.0,
+                invert: false,
+                enabled: true,
+                adjustments: JsAdjustments::default(),
+                points: Some(vec![
+                    CurvePointJs { x: 0.0, y: 1.0 },
+                    CurvePointJs { x: 1.0, y: 0.0 },
+                ]),
+            }],
+            radial_masks: vec![],
+            brush_masks: vec![],
+        })
+        .unwrap();
+
+        let output = apply_masked_adjustments(&image, mask_data, None, None, None).unwrap();
+        let pixels = output.pixels();
+
+        let left = get_pixel(&pixels, 20, 0, 10);
+        assert!((left.0 as i32 - 215).abs() <= 1, "Left should be inverted");
+        let right = get_pixel(&pixels, 20, 19, 10);
+        assert_eq!(right, (40, 40, 40), "Right should be unchanged");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_masked_adjustments_without_points_field() {
+        // JSON from before per-mask curves existed has no `points` key
+        let image = create_gray_image(10, 10, 128);
+
+        let mask = js_sys::Object::new();
+        for (key, value) in [
+            ("start_x", 0.0),
+            ("start_y", 0.5),
+            ("end_x", 1.0),
+            ("end_y", 0.5),
+            ("feather", 0.0),
+        ] {
+            js_sys::Reflect::set(&mask, &key.into(), &value.into()).unwrap();
+        }
+        js_sys::Reflect::set(&mask, &"enabled".into(), &true.into()).unwrap();
+        let adjustments = js_sys::Object::new();
+        js_sys::Reflect::set(&adjustments, &"exposure".into(), &1.0.into()).unwrap();
+        js_sys::Reflect::set(&mask, &"adjustments".into(), &adjustments).unwrap();
+
+        let stack = js_sys::Object::new();
+        let linear = js_sys::Array::of1(&mask);
+        js_sys::Reflect::set(&stack, &"linear_masks".into(), &linear).unwrap();
+        js_sys::Reflect::set(&stack, &"radial_masks".into(), &js_sys::Array::new()).unwrap();
+
+        let output = apply_masked_adjustments(&image, stack.into(), None, None, None).unwrap();
+        let pixels = output.pixels();
+
+        let left = get_pixel(&pixels, 10, 0, 5);
+        assert!(left.0 > 200, "Left should be bright (exposure +1)");
+        let right = get_pixel(&pixels, 10, 9, 5);
+        assert_eq!(right, (128, 128, 128), "Right should be unchanged");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_compute_mask_dirty_rect_from_js_objects() {
+        let mask = |center_x: f64| {
+            let mask = js_sys::Object::new();
+            for (key, value) in [
+                ("center_x", center_x),
+                ("center_y", 0.5),
+                ("radius_x", 0.1),
+                ("radius_y", 0.1),
+                ("feather", 0.5),
+            ] {
+                js_sys::Reflect::set(&mask, &key.into(), &value.into()).unwrap();
+            }
+            JsValue::from(mask)
+        };
+
+        let rect = compute_mask_dirty_rect(mask(0.5), mask(0.55), 100, 100).unwrap();
+        let field = |name: &str| js_sys::Reflect::get(&rect, &name.into()).unwrap();
+        assert!(field("width").as_f64().unwrap() < 100.0);
+        assert!(field("x").as_f64().unwrap() >= 35.0);
+
+        let unchanged = compute_mask_dirty_rect(mask(0.5), mask(0.5), 100, 100).unwrap();
+        assert!(unchanged.is_null());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rasterized_mask_from_js_objects() {
+        let mask = |center_x: f64| {
+            let mask = js_sys::Object::new();
+            for (key, value) in [
+                ("center_x", center_x),
+                ("center_y", 0.5),
+                ("radius_x", 0.3),
+                ("radius_y", 0.3),
+                ("feather", 0.5),
+            ] {
+                js_sys::Reflect::set(&mask, &key.into(), &value.into()).unwrap();
+            }
+            JsValue::from(mask)
+        };
+        let adjustments = js_sys::Object::new();
+        js_sys::Reflect::set(&adjustments, &"exposure".into(), &1.0.into()).unwrap();
+
+        let raster = rasterize_mask(mask(0.5), 10, 10).unwrap();
+        assert!(raster.is_current(mask(0.5), 10, 10).unwrap());
+        assert!(!raster.is_current(mask(0.6), 10, 10).unwrap());
+
+        let mut layers = JsMaskLayers::new();
+        layers
+            .add_cached(&raster, adjustments.clone().into(), None)
+            .unwrap();
+        layers
+            .add_mask(mask(0.2), adjustments.into(), None)
+            .unwrap();
+        assert_eq!(layers.length(), 2);
+        // The raster is shared, not moved
+        assert_eq!(raster.width(), 10);
+        let overlay = raster.to_gray_image();
+        assert_eq!(overlay.byte_length(), 100);
+        assert!(overlay.pixels()[5 * 10 + 5] > overlay.pixels()[0]);
+
+        let output =
+            apply_masked_adjustments_cached(&create_gray_image(10, 10, 100), &layers).unwrap();
+        let center = get_pixel(&output.pixels(), 10, 5, 5);
+        assert!(
+            center.0 > 150,
+            "Center should be brighter, got {}",
+            center.0
+        );
+
+        let small = create_gray_image(5, 5, 100);
+        assert!(apply_masked_adjustments_cached(&small, &layers).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_suggest_mask_feather() {
+        let flat = create_gray_image(32, 32, 128);
+        let radial = js_sys::JSON::parse(
+            r#"{"center_x":0.5,"center_y":0.5,"radius_x":0.3,"radius_y":0.3,"feather":0}"#,
+        )
+        .unwrap();
+        let feather = suggest_mask_feather(&flat, radial).unwrap();
+        assert!((feather - 0.4).abs() < 0.01, "got {}", feather);
+        assert_eq!(flat.pixels(), vec![128; 32 * 32 * 3]);
+
+        let linear =
+            js_sys::JSON::parse(r#"{"start_x":0,"start_y":0,"end_x":1,"end_y":1,"feather":0.2}"#)
+                .unwrap();
+        assert!(suggest_mask_feather(&flat, linear).is_ok());
+        assert!(suggest_mask_feather(&flat, JsValue::from_str("round")).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_convert_mask_to_raster_slots_into_stack() {
+        let radial = js_sys::JSON::parse(
+            r#"{"id":"subject","center_x":0.5,"center_y":0.5,"radius_x":0.3,"radius_y":0.3,
+                "rotation":0,"feather":0.5,"invert":false,"enabled":true,
+                "adjustments":{"exposure":1.0}}"#,
+        )
+        .unwrap();
+        let brush = convert_mask_to_raster(radial.clone(), 40, 30).unwrap();
+        let parsed: JsBrushMask = serde_wasm_bindgen::from_value(brush.clone()).unwrap();
+        assert_eq!(parsed.id.as_deref(), Some("subject"));
+        assert_eq!(parsed.alpha.len(), 40 * 30);
+        assert_eq!(parsed.adjustments.exposure, 1.0);
+
+        let stack = |key: &str, mask: &JsValue| {
+            let stack = js_sys::JSON::parse(r#"{"linear_masks":[],"radial_masks":[]}"#).unwrap();
+            js_sys::Reflect::set(&stack, &key.into(), &js_sys::Array::of1(mask)).unwrap();
+            stack
+        };
+        let image = JsDecodedImage::from_decoded(gradient(40, 30, GradientDirection::Diagonal));
+        let analytic =
+            apply_masked_adjustments(&image, stack("radial_masks", &radial), None, None, None)
+                .unwrap();
+        let rasterized =
+            apply_masked_adjustments(&image, stack("brush_masks", &brush), None, None, None)
+                .unwrap();
+        let max_diff = analytic
+            .pixels()
+            .iter()
+            .zip(rasterized.pixels())
+            .map(|(a, b)| a.abs_diff(b))
+            .max()
+            .unwrap();
+        assert!(max_diff <= 1, "max diff {}", max_diff);
+
+        // Brush masks are not re-rendered by region
+        let mut previous = rasterized;
+        let region = js_sys::JSON::parse(r#"{"x":0,"y":0,"width":8,"height":8}"#).unwrap();
+        assert!(apply_masked_adjustments_region(
+            &mut previous,
+            &image,
+            stack("brush_masks", &brush),
+            region,
+            None
+        )
+        .is_err());
+
+        assert!(convert_mask_to_raster(radial, 0, 30).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_erase_from_raster_mask_clears_stroke() {
+        let radial = js_sys::JSON::parse(
+            r#"{"center_x":0.5,"center_y":0.5,"radius_x":0.4,"radius_y":0.4,"feather":0,
+                "enabled":true,"adjustments":{}}"#,
+        )
+        .unwrap();
+        let brush = convert_mask_to_raster(radial, 20, 20).unwrap();
+        let stroke =
+            js_sys::JSON::parse(r#"{"points":[{"x":0.3,"y":0.5},{"x":0.7,"y":0.5}],"radius":0.1}"#)
+                .unwrap();
+        let erased: JsBrushMask =
+            serde_wasm_bindgen::from_value(erase_from_raster_mask(brush, stroke).unwrap()).unwrap();
+
+        assert_eq!(erased.alpha[10 * 20 + 10], 0);
+        assert_eq!(erased.alpha[10 * 20 + 7], 0);
+        assert_eq!(erased.alpha[5 * 20 + 10], 255);
+        assert!(erased.enabled);
+
+        let empty = js_sys::JSON::parse(r#"{"points":[],"radius":0.1}"#).unwrap();
+        let err = erase_from_raster_mask(serde_wasm_bindgen::to_value(&erased).unwrap(), empty)
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("INVALID_BRUSH_STROKE"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_compute_masked_histogram() {
+        let image = create_gray_image(4, 4, 90);
+        let hist = compute_masked_histogram(&image, two_mask_stack(true, false), 0).unwrap();
+        let count = hist.luminance()[90];
+        assert!(count > 0 && count < 16, "{}", count);
+
+        let err = compute_masked_histogram(&image, two_mask_stack(true, false), 1)
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().as_deref(), Some("MASK_INDEX_OUT_OF_RANGE"));
+    }
 }
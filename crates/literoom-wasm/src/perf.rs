@@ -0,0 +1,163 @@
+//! Performance counter WASM bindings.
+//!
+//! This module exposes the literoom-core per-operation counters to JavaScript
+//! so the UI can surface throughput numbers while profiling.
+//!
+//! # Functions
+//!
+//! - [`get_last_operation_stats`] - Counters of the most recent top-level operation
+//! - [`reset_stats`] - Clear the stored counters
+//!
+//! # Example
+//!
+//! ```typescript
+//! import { apply_adjustments, get_last_operation_stats } from '@literoom/wasm';
+//!
+//! apply_adjustments(pixels, width, height, adjustments);
+//! const stats = get_last_operation_stats();
+//! console.log(`${stats.operation}: ${stats.pixels_per_second} px/s`);
+//! ```
+
+use literoom_core::perf::{self, Counters};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// JavaScript-compatible snapshot of an operation's counters.
+///
+/// Counts are `f64` so they arrive in JavaScript as plain numbers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsOperationStats {
+    /// Name of the top-level operation (e.g. "decode_jpeg")
+    pub operation: String,
+    /// Number of pixels processed
+    pub pixels_processed: f64,
+    /// Bytes allocated for large buffers
+    pub bytes_allocated: f64,
+    /// Wall time in milliseconds
+    pub elapsed_ms: Option<f64>,
+    /// Throughput derived from `pixels_processed` and `elapsed_ms`
+    pub pixels_per_second: Option<f64>,
+}
+
+impl From<&Counters> for JsOperationStats {
+    fn from(counters: &Counters) -> Self {
+        Self {
+            operation: counters.operation.to_string(),
+            pixels_processed: counters.pixels_processed as f64,
+            bytes_allocated: counters.bytes_allocated as f64,
+            elapsed_ms: counters.elapsed_ms,
+            pixels_per_second: counters.pixels_per_second(),
+        }
+    }
+}
+
+/// Get the counters of the most recently completed operation.
+///
+/// # Returns
+///
+/// An object `{ operation, pixels_processed, bytes_allocated, elapsed_ms,
+/// pixels_per_second }`, or `null` if no operation has run since the last
+/// [`reset_stats`]. Timing fields are `null` when no timer is available.
+///
+/// # Example
+///
+/// ```typescript
+/// const stats = get_last_operation_stats();
+/// if (stats) {
+///   console.log(`${stats.operation} processed ${stats.pixels_processed} pixels`);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn get_last_operation_stats() -> Result<JsValue, JsValue> {
+    match perf::last_operation_stats() {
+        Some(counters) => serde_wasm_bindgen::to_value(&JsOperationStats::from(&counters))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize stats: {}", e))),
+        None => Ok(JsValue::NULL),
+    }
+}
+
+/// Clear the counters of the last operation.
+///
+/// # Example
+///
+/// ```typescript
+/// reset_stats();
+/// get_last_operation_stats(); // null
+/// ```
+#[wasm_bindgen]
+pub fn reset_stats() {
+    perf::reset_stats();
+}
+
+/// Millisecond clock used to time operations in the browser.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_from_counters() {
+        let counters = Counters {
+            operation: "resize",
+            pixels_processed: 2000,
+            bytes_allocated: 6000,
+            elapsed_ms: Some(500.0),
+            dimensions: None,
+        };
+        let stats = JsOperationStats::from(&counters);
+
+        assert_eq!(stats.operation, "resize");
+        assert_eq!(stats.pixels_processed, 2000.0);
+        assert_eq!(stats.bytes_allocated, 6000.0);
+        assert_eq!(stats.elapsed_ms, Some(500.0));
+        assert_eq!(stats.pixels_per_second, Some(4000.0));
+    }
+
+    #[test]
+    fn test_stats_without_timer() {
+        let stats = JsOperationStats::from(&Counters::default());
+        assert_eq!(stats.elapsed_ms, None);
+        assert_eq!(stats.pixels_per_second, None);
+    }
+
+    #[test]
+    fn test_reset_stats_clears_core_counters() {
+        let mut pixels = vec![128u8; 4 * 4 * 3];
+        let lut = literoom_core::ToneCurveLut::identity();
+        literoom_core::curve::apply_tone_curve(&mut pixels, &lut);
+        assert!(perf::last_operation_stats().is_some());
+
+        reset_stats();
+        assert!(perf::last_operation_stats().is_none());
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_get_last_operation_stats_null_after_reset() {
+        reset_stats();
+        assert!(get_last_operation_stats().unwrap().is_null());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_get_last_operation_stats_reports_pixels() {
+        let mut pixels = vec![128u8; 8 * 5 * 3];
+        let lut = literoom_core::ToneCurveLut::identity();
+        literoom_core::curve::apply_tone_curve(&mut pixels, &lut);
+
+        let value = get_last_operation_stats().unwrap();
+        let stats: JsOperationStats = serde_wasm_bindgen::from_value(value).unwrap();
+        assert_eq!(stats.operation, "apply_tone_curve");
+        assert_eq!(stats.pixels_processed, 40.0);
+    }
+}
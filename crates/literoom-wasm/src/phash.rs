@@ -0,0 +1,97 @@
+//! Perceptual hash WASM bindings.
+//!
+//! The grid import flow hashes thumbnails with [`compute_phash`] and flags
+//! photos whose hashes are close as likely duplicates of ones already in
+//! the library. Hashes cross the boundary as 16-digit hex strings, since a
+//! 64-bit integer does not fit in a JavaScript number.
+
+use crate::types::{image_error_to_js, JsDecodedImage};
+use literoom_core::phash::{self, hamming_distance, PHASH_DUPLICATE_THRESHOLD};
+use wasm_bindgen::prelude::*;
+
+/// Compute the perceptual hash of an image.
+///
+/// Recompressed or resized copies of a picture hash to nearly the same
+/// value, so hashing a thumbnail finds duplicates of the full image.
+///
+/// # Returns
+///
+/// The hash as 16 lowercase hex digits; store it with the photo.
+///
+/// # Errors
+///
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const hash = compute_phash(thumbnail);
+/// const threshold = phash_duplicate_threshold();
+/// const duplicate = library.find((p) => phash_distance(p.hash, hash) <= threshold);
+/// if (duplicate) flagDuplicate(file, duplicate);
+/// ```
+#[wasm_bindgen]
+pub fn compute_phash(image: &JsDecodedImage) -> Result<String, JsValue> {
+    let hash = phash::compute_phash(image.as_decoded()).map_err(|e| image_error_to_js(&e))?;
+    Ok(format!("{:016x}", hash))
+}
+
+/// Number of bits in which two hashes from [`compute_phash`] differ.
+///
+/// At most [`phash_duplicate_threshold`] means the photos are likely the
+/// same picture.
+///
+/// # Errors
+///
+/// Returns an error if either hash is not a 64-bit hex number.
+#[wasm_bindgen]
+pub fn phash_distance(a: &str, b: &str) -> Result<u32, JsValue> {
+    let a = parse_hash(a).map_err(|e| JsValue::from_str(&e))?;
+    let b = parse_hash(b).map_err(|e| JsValue::from_str(&e))?;
+    Ok(hamming_distance(a, b))
+}
+
+/// Largest [`phash_distance`] at which two photos are likely duplicates.
+#[wasm_bindgen]
+pub fn phash_duplicate_threshold() -> u32 {
+    PHASH_DUPLICATE_THRESHOLD
+}
+
+/// Parse a hash written by [`compute_phash`].
+fn parse_hash(hash: &str) -> Result<u64, String> {
+    if hash.is_empty() || hash.len() > 16 {
+        return Err(format!("Invalid perceptual hash: '{}'", hash));
+    }
+    u64::from_str_radix(hash, 16).map_err(|_| format!("Invalid perceptual hash: '{}'", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use literoom_core::testing::{noise, slanted_edge};
+
+    #[test]
+    fn test_hash_round_trips_through_hex() {
+        let image = JsDecodedImage::from_decoded(slanted_edge(64, 48, 5.0));
+        let hash = compute_phash(&image).unwrap();
+        assert_eq!(hash.len(), 16);
+        assert_eq!(phash_distance(&hash, &hash).unwrap(), 0);
+
+        let core_hash = phash::compute_phash(image.as_decoded()).unwrap();
+        assert_eq!(parse_hash(&hash), Ok(core_hash));
+
+        let other = compute_phash(&JsDecodedImage::from_decoded(noise(64, 48, 1))).unwrap();
+        assert!(phash_distance(&hash, &other).unwrap() > phash_duplicate_threshold());
+    }
+
+    #[test]
+    fn test_parse_hash_rejects_invalid_input() {
+        assert_eq!(parse_hash("00000000000000ff"), Ok(255));
+        assert_eq!(parse_hash("ff"), Ok(255));
+        assert!(parse_hash("").is_err());
+        assert!(parse_hash("0123456789abcdef0").is_err());
+        assert!(parse_hash("-1").is_err());
+        assert!(parse_hash("not a hash").is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! Scratch buffer pool for the binding layer.
+//!
+//! Interactive edits run the same operation many times a second, and each
+//! call used to allocate a pixel buffer the size of the image and free it
+//! again when JavaScript dropped the result. In WASM every such buffer goes
+//! through the allocator, which grows and shrinks the heap on each call.
+//! The bindings instead take their intermediate buffers from this pool, and
+//! images return their pixel buffers to it when freed, so repeated
+//! operations on one image keep reusing the same few buffers.
+//!
+//! Buffers are kept by size class, the power of two at or below their
+//! capacity, up to [`MAX_RETAINED_BYTES`] in total. Core functions are
+//! unaffected: only the glue decides where its buffers come from.
+//!
+//! # Functions
+//!
+//! - [`trim_pool`] - Free retained buffers, e.g. when the app goes idle
+//! - [`pool_stats`] - Retained memory and reuse counters
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+/// Buffers smaller than this are left to the allocator, which handles them
+/// cheaply without growing the heap.
+const MIN_POOLED_BYTES: usize = 64 * 1024;
+
+/// Most memory the pool keeps; buffers released beyond it are freed.
+const MAX_RETAINED_BYTES: usize = 128 * 1024 * 1024;
+
+thread_local! {
+    static POOL: RefCell<Pool> = RefCell::new(Pool::default());
+}
+
+/// Snapshot of the pool's retained memory and counters.
+///
+/// Counters cover buffers of at least 64 KiB; smaller ones bypass the pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct JsPoolStats {
+    /// Buffers currently retained
+    pub buffers: u32,
+    /// Capacity in bytes of the retained buffers
+    pub bytes: f64,
+    /// Requests served by a newly allocated buffer
+    pub allocations: u32,
+    /// Requests served by a retained buffer
+    pub reuses: u32,
+}
+
+/// Retained buffers, indexed by size class (log2 of the capacity, rounded
+/// down).
+#[derive(Default)]
+struct Pool {
+    classes: Vec<Vec<Vec<u8>>>,
+    bytes: usize,
+    allocations: u32,
+    reuses: u32,
+}
+
+impl Pool {
+    fn take(&mut self, len: usize) -> Option<Vec<u8>> {
+        // A buffer in the requested class may still be too small; any
+        // buffer in the class above is large enough
+        let class = size_class(len);
+        let fits = self
+            .classes
+            .get(class)
+            .and_then(|buffers| buffers.iter().position(|b| b.capacity() >= len));
+        let buffer = match fits {
+            Some(i) => self.classes[class].swap_remove(i),
+            None => self.classes.get_mut(class + 1)?.pop()?,
+        };
+        self.bytes -= buffer.capacity();
+        Some(buffer)
+    }
+
+    fn put(&mut self, mut buffer: Vec<u8>) {
+        let capacity = buffer.capacity();
+        if capacity < MIN_POOLED_BYTES || self.bytes + capacity > MAX_RETAINED_BYTES {
+            return;
+        }
+        buffer.clear();
+        let class = size_class(capacity);
+        if self.classes.len() <= class {
+            self.classes.resize_with(class + 1, Vec::new);
+        }
+        self.classes[class].push(buffer);
+        self.bytes += capacity;
+    }
+
+    /// Free buffers, largest classes first, until at most `max_bytes` are
+    /// retained.
+    fn trim(&mut self, max_bytes: usize) {
+        for buffers in self.classes.iter_mut().rev() {
+            while self.bytes > max_bytes {
+                let Some(buffer) = buffers.pop() else {
+                    break;
+                };
+                self.bytes -= buffer.capacity();
+            }
+        }
+    }
+
+    fn stats(&self) -> JsPoolStats {
+        JsPoolStats {
+            buffers: self.classes.iter().map(Vec::len).sum::<usize>() as u32,
+            bytes: self.bytes as f64,
+            allocations: self.allocations,
+            reuses: self.reuses,
+        }
+    }
+}
+
+fn size_class(bytes: usize) -> usize {
+    bytes.max(1).ilog2() as usize
+}
+
+/// Get an empty buffer with room for at least `len` bytes.
+///
+/// Return it with [`release`] once done, or let the image it ends up in
+/// return it when freed.
+pub(crate) fn acquire(len: usize) -> Vec<u8> {
+    if len < MIN_POOLED_BYTES {
+        return Vec::with_capacity(len);
+    }
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        match pool.take(len) {
+            Some(buffer) => {
+                pool.reuses += 1;
+                buffer
+            }
+            None => {
+                pool.allocations += 1;
+                Vec::with_capacity(len)
+            }
+        }
+    })
+}
+
+/// Copy `data` into a buffer from the pool.
+pub(crate) fn acquire_copy(data: &[u8]) -> Vec<u8> {
+    let mut buffer = acquire(data.len());
+    buffer.extend_from_slice(data);
+    buffer
+}
+
+/// Hand a buffer back to the pool for reuse.
+pub(crate) fn release(buffer: Vec<u8>) {
+    // The pool may already be gone while the thread shuts down
+    let _ = POOL.try_with(|pool| pool.borrow_mut().put(buffer));
+}
+
+/// Free retained buffers until the pool holds at most `max_bytes`.
+///
+/// Call with `0` to free everything, e.g. when the editor closes or the app
+/// has been idle for a while. Buffers in use are unaffected.
+///
+/// # Returns
+///
+/// The bytes still retained.
+///
+/// # Example
+///
+/// ```typescript
+/// // Keep up to 32 MB for the next edit
+/// trim_pool(32 * 1024 * 1024);
+/// ```
+#[wasm_bindgen]
+pub fn trim_pool(max_bytes: usize) -> usize {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        pool.trim(max_bytes);
+        pool.bytes
+    })
+}
+
+/// Get the pool's retained memory and reuse counters.
+///
+/// # Returns
+///
+/// An object `{ buffers, bytes, allocations, reuses }`. While an image is
+/// edited interactively, `reuses` should climb while `allocations` stays
+/// put.
+#[wasm_bindgen]
+pub fn pool_stats() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&stats())
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize stats: {}", e)))
+}
+
+fn stats() -> JsPoolStats {
+    POOL.with(|pool| pool.borrow().stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{apply_tone_curve, JsToneCurveLut};
+    use crate::types::JsDecodedImage;
+    use literoom_core::curve::{apply_tone_curve as core_apply, ToneCurveLut};
+    use literoom_core::testing::noise;
+    use literoom_core::{CurvePoint, ToneCurve};
+
+    fn contrast_lut() -> JsToneCurveLut {
+        let curve = ToneCurve {
+            points: vec![
+                CurvePoint::new(0.0, 0.0),
+                CurvePoint::new(0.25, 0.15),
+                CurvePoint::new(0.75, 0.85),
+                CurvePoint::new(1.0, 1.0),
+            ],
+        };
+        JsToneCurveLut::from(ToneCurveLut::from_curve(&curve))
+    }
+
+    #[test]
+    fn test_repeated_operations_reuse_buffers() {
+        let image = JsDecodedImage::from_decoded(noise(256, 192, 1));
+        let lut = contrast_lut();
+        let before = stats();
+
+        for _ in 0..10 {
+            let result = apply_tone_curve(&image, &lut);
+            drop(result);
+        }
+        let after = stats();
+        assert!(after.allocations - before.allocations <= 1);
+        assert!(after.reuses - before.reuses >= 9);
+        assert!(after.bytes <= before.bytes + (256 * 192 * 3) as f64);
+    }
+
+    #[test]
+    fn test_pooled_results_match_unpooled() {
+        let source = noise(256, 192, 2);
+        let image = JsDecodedImage::from_decoded(source.clone());
+        let lut = contrast_lut();
+
+        let mut expected = source.pixels.clone();
+        core_apply(&mut expected, lut.inner());
+        // Later results come from reused buffers holding earlier output
+        for _ in 0..3 {
+            assert_eq!(apply_tone_curve(&image, &lut).pixels(), expected);
+        }
+    }
+
+    #[test]
+    fn test_trim_reduces_retained_bytes() {
+        for len in [100_000, 300_000, 1_000_000, 5_000_000] {
+            release(vec![0; len]);
+        }
+        assert!(stats().bytes >= 6_400_000.0);
+
+        let cap = 1_000_000;
+        let retained = trim_pool(cap);
+        assert!(retained <= cap);
+        assert_eq!(stats().bytes, retained as f64);
+        assert_eq!(trim_pool(0), 0);
+        assert_eq!(stats().buffers, 0);
+    }
+
+    #[test]
+    fn test_never_hands_out_a_smaller_buffer() {
+        trim_pool(0);
+        for len in [70_000, 100_000, 130_000, 200_000, 262_144, 400_000] {
+            release(Vec::with_capacity(len));
+        }
+        for len in [65_536, 99_999, 100_001, 131_072, 262_145, 300_000, 1 << 20] {
+            let buffer = acquire(len);
+            assert!(buffer.capacity() >= len, "{} < {}", buffer.capacity(), len);
+            assert!(buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_retained_bytes_are_capped() {
+        trim_pool(0);
+        for _ in 0..4 {
+            release(Vec::with_capacity(MAX_RETAINED_BYTES / 3));
+        }
+        assert!(stats().bytes <= MAX_RETAINED_BYTES as f64);
+        assert_eq!(stats().buffers, 3);
+        trim_pool(0);
+    }
+}
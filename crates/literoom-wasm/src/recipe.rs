@@ -0,0 +1,229 @@
+//! Recipe WASM bindings.
+//!
+//! Runs a JSON-defined list of operations in one call, for automated tests
+//! and scripted processing. See `literoom_core::recipe` for the operations
+//! and the rules for ordering them.
+
+use crate::types::{recipe_error_to_js, JsDecodedImage};
+use literoom_core::decode::DecodedImage;
+use literoom_core::recipe::{self, Operation, RecipeError, RecipeInput, RecipeOutput};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Run a recipe of operations on file bytes or an image.
+///
+/// # Arguments
+/// * `image_or_bytes` - A `Uint8Array` of JPEG, HEIC/HEIF or RAW file bytes,
+///   or an image: a `JsDecodedImage` or any object with `width`, `height`
+///   and RGB `pixels`
+/// * `recipe` - Array of steps, each an object with an `op` field:
+///   `decode`, `resize` (`width`, `height`), `resize_to_fit` (`max_edge`),
+///   `adjustments` (adjustment fields), `tone_curve` (`points`), `masks`
+///   (`linear_masks`, `radial_masks` as in the edit state), `crop` (`left`,
+///   `top`, `width`, `height`), `rotate` (`angle`) or `encode_jpeg`
+///   (`quality`, default 90). Resizes take an optional `filter`
+///   (`"Nearest"`, `"Bilinear"` or `"Lanczos3"`), rotation an optional
+///   `filter` (`"Bilinear"` or `"Lanczos3"`).
+///
+/// `decode` may only be the first step, and bytes are decoded implicitly
+/// without it; `encode_jpeg` must be the last step.
+///
+/// # Returns
+/// A `Uint8Array` of JPEG bytes if the recipe ends with `encode_jpeg`,
+/// otherwise a new `JsDecodedImage`. The input is not modified.
+///
+/// # Errors
+/// Throws if the input is neither bytes nor an image, or the recipe is not
+/// an array. Problems with a step throw an `Error` with a `code`
+/// (`"UNKNOWN_OP"`, `"INVALID_STEP"`, `"MISPLACED_DECODE"`,
+/// `"MISPLACED_ENCODE"` or `"STEP_FAILED"`) and the `step` index. Ordering
+/// is checked before any step runs.
+///
+/// # Example (TypeScript)
+/// ```typescript
+/// const jpeg = run_recipe(fileBytes, [
+///   { op: 'resize_to_fit', max_edge: 2048 },
+///   { op: 'adjustments', exposure: 0.5 },
+///   { op: 'tone_curve', points: [{ x: 0, y: 0 }, { x: 0.5, y: 0.6 }, { x: 1, y: 1 }] },
+///   { op: 'encode_jpeg', quality: 85 },
+/// ]);
+/// ```
+#[wasm_bindgen]
+pub fn run_recipe(image_or_bytes: JsValue, recipe: JsValue) -> Result<JsValue, JsValue> {
+    let ops = parse_recipe(&recipe)?;
+
+    let output = match image_or_bytes.dyn_ref::<js_sys::Uint8Array>() {
+        Some(bytes) => recipe::run_recipe(RecipeInput::Bytes(&bytes.to_vec()), &ops),
+        None => recipe::run_recipe(RecipeInput::Image(&image_from_js(&image_or_bytes)?), &ops),
+    }
+    .map_err(|e| recipe_error_to_js(&e))?;
+
+    Ok(match output {
+        RecipeOutput::Image(image) => JsDecodedImage::from_decoded(image).into(),
+        RecipeOutput::Jpeg(bytes) => js_sys::Uint8Array::from(bytes.as_slice()).into(),
+    })
+}
+
+/// Parse each step of a recipe array, naming the step that fails.
+fn parse_recipe(recipe: &JsValue) -> Result<Vec<Operation>, JsValue> {
+    let steps = recipe
+        .dyn_ref::<js_sys::Array>()
+        .ok_or_else(|| JsValue::from_str("Recipe must be an array of steps"))?;
+
+    steps
+        .iter()
+        .enumerate()
+        .map(|(step, value)| {
+            let op = js_sys::Reflect::get(&value, &"op".into())
+                .ok()
+                .and_then(|op| op.as_string());
+            serde_wasm_bindgen::from_value(value).map_err(|e| {
+                recipe_error_to_js(&RecipeError::from_parse(step, op.as_deref(), e.to_string()))
+            })
+        })
+        .collect()
+}
+
+/// Read an image object: `width`, `height` and `pixels`, where `pixels` is
+/// a `Uint8Array` or a method returning one (as on `JsDecodedImage`).
+fn image_from_js(value: &JsValue) -> Result<DecodedImage, JsValue> {
+    let invalid = || {
+        JsValue::from_str(
+            "Input must be a Uint8Array of file bytes or an image with width, height and pixels",
+        )
+    };
+    let get = |key: &str| js_sys::Reflect::get(value, &key.into()).map_err(|_| invalid());
+
+    let width = get("width")?.as_f64().ok_or_else(invalid)? as u32;
+    let height = get("height")?.as_f64().ok_or_else(invalid)? as u32;
+    let mut pixels = get("pixels")?;
+    if let Some(method) = pixels.dyn_ref::<js_sys::Function>() {
+        pixels = method.call0(value)?;
+    }
+    let pixels = pixels
+        .dyn_ref::<js_sys::Uint8Array>()
+        .ok_or_else(invalid)?
+        .to_vec();
+
+    Ok(DecodedImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use literoom_core::curve::ToneCurveLut;
+    use literoom_core::decode::{decode_jpeg, resize_to_fit, FilterType};
+    use literoom_core::encode::encode_jpeg;
+    use literoom_core::testing::noise;
+    use literoom_core::{BasicAdjustments, CurvePoint, ToneCurve};
+    use wasm_bindgen_test::*;
+
+    fn step(json: &[(&str, JsValue)]) -> JsValue {
+        let step = js_sys::Object::new();
+        for (key, value) in json {
+            js_sys::Reflect::set(&step, &(*key).into(), value).unwrap();
+        }
+        step.into()
+    }
+
+    fn error_code_and_step(err: &JsValue) -> (String, f64) {
+        let code = js_sys::Reflect::get(err, &"code".into()).unwrap();
+        let step = js_sys::Reflect::get(err, &"step".into()).unwrap();
+        (code.as_string().unwrap(), step.as_f64().unwrap())
+    }
+
+    #[wasm_bindgen_test]
+    fn test_recipe_matches_manual_chain() {
+        let source = noise(80, 60, 3);
+        let jpeg = encode_jpeg(&source.pixels, 80, 60, 95).unwrap();
+        let points = vec![
+            CurvePoint::new(0.0, 0.0),
+            CurvePoint::new(0.5, 0.6),
+            CurvePoint::new(1.0, 1.0),
+        ];
+        let recipe = js_sys::Array::of4(
+            &step(&[("op", "resize_to_fit".into()), ("max_edge", 40.into())]),
+            &step(&[("op", "adjustments".into()), ("exposure", 0.5.into())]),
+            &step(&[
+                ("op", "tone_curve".into()),
+                ("points", serde_wasm_bindgen::to_value(&points).unwrap()),
+            ]),
+            &step(&[("op", "encode_jpeg".into()), ("quality", 85.into())]),
+        );
+
+        let output = run_recipe(
+            js_sys::Uint8Array::from(jpeg.as_slice()).into(),
+            recipe.into(),
+        )
+        .unwrap();
+        let bytes = output.dyn_into::<js_sys::Uint8Array>().unwrap().to_vec();
+
+        let mut image =
+            resize_to_fit(&decode_jpeg(&jpeg).unwrap(), 40, FilterType::Bilinear).unwrap();
+        literoom_core::adjustments::apply_all_adjustments(
+            &mut image.pixels,
+            &BasicAdjustments {
+                exposure: 0.5,
+                ..Default::default()
+            },
+        );
+        literoom_core::apply_tone_curve(
+            &mut image.pixels,
+            &ToneCurveLut::from_curve(&ToneCurve { points }),
+        );
+        let expected = encode_jpeg(&image.pixels, image.width, image.height, 85).unwrap();
+        assert_eq!(bytes, expected);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_recipe_on_image_returns_image() {
+        let image = JsDecodedImage::new(4, 2, vec![100; 4 * 2 * 3]);
+        let recipe = js_sys::Array::of1(&step(&[
+            ("op", "adjustments".into()),
+            ("exposure", 1.0.into()),
+        ]));
+
+        let output = run_recipe(image.into(), recipe.into()).unwrap();
+
+        let width = js_sys::Reflect::get(&output, &"width".into()).unwrap();
+        assert_eq!(width.as_f64(), Some(4.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_malformed_recipes_name_the_step() {
+        let image = || {
+            step(&[
+                ("width", 2.into()),
+                ("height", 2.into()),
+                ("pixels", js_sys::Uint8Array::from(&[0u8; 12][..]).into()),
+            ])
+        };
+        let adjust = step(&[("op", "adjustments".into())]);
+
+        let recipe = js_sys::Array::of2(&adjust, &step(&[("op", "sharpen".into())]));
+        let err = run_recipe(image(), recipe.into()).unwrap_err();
+        assert_eq!(error_code_and_step(&err), ("UNKNOWN_OP".to_string(), 1.0));
+
+        let recipe = js_sys::Array::of1(&step(&[("op", "resize".into()), ("width", 2.into())]));
+        let err = run_recipe(image(), recipe.into()).unwrap_err();
+        assert_eq!(error_code_and_step(&err), ("INVALID_STEP".to_string(), 0.0));
+
+        let recipe = js_sys::Array::of3(&adjust, &step(&[("op", "encode_jpeg".into())]), &adjust);
+        let err = run_recipe(image(), recipe.into()).unwrap_err();
+        assert_eq!(
+            error_code_and_step(&err),
+            ("MISPLACED_ENCODE".to_string(), 1.0)
+        );
+
+        let recipe = js_sys::Array::of2(&adjust, &step(&[("op", "decode".into())]));
+        let err = run_recipe(image(), recipe.into()).unwrap_err();
+        assert_eq!(
+            error_code_and_step(&err),
+            ("MISPLACED_DECODE".to_string(), 1.0)
+        );
+    }
+}
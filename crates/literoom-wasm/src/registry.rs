@@ -0,0 +1,505 @@
+//! Session-scoped image registry WASM bindings.
+//!
+//! The object API hands every intermediate image to JavaScript, which must
+//! `.free()` each one. The registry keeps images on the WASM side instead and
+//! gives JavaScript `u32` handles, so a chain such as resize → adjust → curve
+//! → encode never copies pixels across the boundary. Operations can write
+//! into an existing output handle, reusing its buffer when the dimensions
+//! match.
+//!
+//! # Functions
+//!
+//! - [`store_image`] / [`get_image`] - Move images in and out of the registry
+//! - [`get_image_info`] - Dimensions of a stored image
+//! - [`release_image`] / [`release_all`] - Free stored images
+//! - [`apply_adjustments_h`], [`apply_tone_curve_h`], [`resize_h`],
+//!   [`encode_jpeg_h`] - Handle-based variants of the object API
+//! - [`get_registry_stats`] - Live images and buffer reuse counters
+//!
+//! # Example
+//!
+//! ```typescript
+//! import { store_image, resize_h, apply_adjustments_h, encode_jpeg_h, release_all } from '@literoom/wasm';
+//!
+//! const source = store_image(decoded);
+//! decoded.free();
+//!
+//! const preview = resize_h(source, 1024, 683, 2);
+//! apply_adjustments_h(preview, adjustments, false, preview); // in place
+//! const jpeg = encode_jpeg_h(preview, 90);
+//!
+//! release_all();
+//! ```
+
+use crate::adjustments::BasicAdjustments;
+use crate::curve::JsToneCurveLut;
+use crate::types::{adjustment_error_to_js, filter_from_u8, JsDecodedImage};
+use literoom_core::adjustments::apply_all_adjustments;
+use literoom_core::curve::apply_tone_curve as core_apply_curve;
+use literoom_core::decode::{self, DecodedImage};
+use literoom_core::encode;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+/// Snapshot of the registry's contents and buffer counters.
+///
+/// Counters run from the last [`release_all`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct JsRegistryStats {
+    /// Images currently stored
+    pub images: u32,
+    /// Bytes held by stored pixel buffers
+    pub bytes: f64,
+    /// Operations that allocated a new output buffer
+    pub allocations: u32,
+    /// Operations that wrote into an existing buffer
+    pub reuses: u32,
+}
+
+/// Stored images keyed by handle. Handle 0 is never issued.
+#[derive(Default)]
+struct Registry {
+    images: HashMap<u32, DecodedImage>,
+    last_handle: u32,
+    allocations: u32,
+    reuses: u32,
+}
+
+impl Registry {
+    fn insert(&mut self, image: DecodedImage) -> u32 {
+        loop {
+            self.last_handle = self.last_handle.wrapping_add(1);
+            if self.last_handle != 0 && !self.images.contains_key(&self.last_handle) {
+                break;
+            }
+        }
+        self.images.insert(self.last_handle, image);
+        self.last_handle
+    }
+
+    fn get(&self, handle: u32) -> Result<&DecodedImage, String> {
+        self.images
+            .get(&handle)
+            .ok_or_else(|| unknown_handle(handle))
+    }
+
+    fn release(&mut self, handle: u32) -> Result<(), String> {
+        self.images
+            .remove(&handle)
+            .map(drop)
+            .ok_or_else(|| unknown_handle(handle))
+    }
+
+    /// Copy `src` into an output image and run `op` on its pixels.
+    ///
+    /// With no `dst` a new handle is created. Otherwise the output replaces
+    /// `dst`, reusing its buffer if it has the source's dimensions; `dst`
+    /// equal to `src` works in place.
+    fn map_into(
+        &mut self,
+        src: u32,
+        dst: Option<u32>,
+        op: impl FnOnce(&mut [u8]),
+    ) -> Result<u32, String> {
+        let source = self.get(src)?;
+        let Some(dst) = dst else {
+            let mut output = source.clone();
+            op(&mut output.pixels);
+            self.allocations += 1;
+            return Ok(self.insert(output));
+        };
+
+        if dst == src {
+            self.reuses += 1;
+            op(&mut self
+                .images
+                .get_mut(&src)
+                .expect("source checked above")
+                .pixels);
+            return Ok(src);
+        }
+
+        let mut output = self
+            .images
+            .remove(&dst)
+            .ok_or_else(|| unknown_handle(dst))?;
+        let source = &self.images[&src];
+        if output.width == source.width && output.height == source.height {
+            output.pixels.copy_from_slice(&source.pixels);
+            self.reuses += 1;
+        } else {
+            output = source.clone();
+            self.allocations += 1;
+        }
+        op(&mut output.pixels);
+        self.images.insert(dst, output);
+        Ok(dst)
+    }
+
+    /// Store a newly produced image under `dst`, or a new handle.
+    fn put(&mut self, dst: Option<u32>, image: DecodedImage) -> Result<u32, String> {
+        self.allocations += 1;
+        match dst {
+            Some(dst) => {
+                let slot = self
+                    .images
+                    .get_mut(&dst)
+                    .ok_or_else(|| unknown_handle(dst))?;
+                *slot = image;
+                Ok(dst)
+            }
+            None => Ok(self.insert(image)),
+        }
+    }
+
+    fn stats(&self) -> JsRegistryStats {
+        JsRegistryStats {
+            images: self.images.len() as u32,
+            bytes: self.images.values().map(|i| i.pixels.len() as f64).sum(),
+            allocations: self.allocations,
+            reuses: self.reuses,
+        }
+    }
+}
+
+fn unknown_handle(handle: u32) -> String {
+    format!("Unknown image handle {}", handle)
+}
+
+/// Run `f` on the registry, converting its error for JavaScript.
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> Result<T, String>) -> Result<T, JsValue> {
+    REGISTRY
+        .with(|registry| f(&mut registry.borrow_mut()))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Copy an image into the registry and return its handle.
+///
+/// The color profile and decode warnings stay on the object; only the
+/// pixels are stored. The object can be freed afterwards.
+///
+/// # Errors
+///
+/// Returns an error if the image's buffer does not match its dimensions.
+#[wasm_bindgen]
+pub fn store_image(image: &JsDecodedImage) -> Result<u32, JsValue> {
+    image.validate()?;
+    with_registry(|r| Ok(r.insert(image.to_decoded())))
+}
+
+/// Copy a stored image out to a new `JsDecodedImage`.
+///
+/// # Errors
+///
+/// Returns an error if `handle` is not in the registry.
+#[wasm_bindgen]
+pub fn get_image(handle: u32) -> Result<JsDecodedImage, JsValue> {
+    with_registry(|r| r.get(handle).cloned().map(JsDecodedImage::from_decoded))
+}
+
+/// Dimensions of a stored image.
+///
+/// # Returns
+///
+/// An object `{ width, height, byte_length }`.
+///
+/// # Errors
+///
+/// Returns an error if `handle` is not in the registry.
+#[wasm_bindgen]
+pub fn get_image_info(handle: u32) -> Result<JsValue, JsValue> {
+    let info = with_registry(|r| r.get(handle).map(ImageInfo::from))?;
+    serde_wasm_bindgen::to_value(&info)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize image info: {}", e)))
+}
+
+/// Free a stored image.
+///
+/// # Errors
+///
+/// Returns an error if `handle` is not in the registry, including when it
+/// was already released.
+#[wasm_bindgen]
+pub fn release_image(handle: u32) -> Result<(), JsValue> {
+    with_registry(|r| r.release(handle))
+}
+
+/// Free every stored image and reset the buffer counters.
+///
+/// Outstanding handles become invalid; new handles never reuse them.
+#[wasm_bindgen]
+pub fn release_all() {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        *registry = Registry {
+            last_handle: registry.last_handle,
+            ..Registry::default()
+        };
+    });
+}
+
+/// Get the registry's contents and buffer counters.
+///
+/// # Returns
+///
+/// An object `{ images, bytes, allocations, reuses }`. `reuses` counts
+/// operations that wrote into an existing output buffer instead of
+/// allocating one.
+#[wasm_bindgen]
+pub fn get_registry_stats() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&registry_stats())
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize stats: {}", e)))
+}
+
+fn registry_stats() -> JsRegistryStats {
+    REGISTRY.with(|registry| registry.borrow().stats())
+}
+
+/// Handle-based [`apply_adjustments`](crate::apply_adjustments).
+///
+/// Writes into `dst` when given (pass `src` to adjust in place), otherwise
+/// into a new handle.
+///
+/// # Returns
+///
+/// The handle holding the result.
+///
+/// # Errors
+///
+/// Returns an error for unknown handles, or for adjustments that
+/// `apply_adjustments` rejects.
+#[wasm_bindgen]
+pub fn apply_adjustments_h(
+    src: u32,
+    adjustments: &BasicAdjustments,
+    clamp: Option<bool>,
+    dst: Option<u32>,
+) -> Result<u32, JsValue> {
+    let adjustments = adjustments
+        .inner()
+        .sanitized(clamp.unwrap_or(false))
+        .map_err(|e| adjustment_error_to_js(&e))?;
+    with_registry(|r| {
+        r.map_into(src, dst, |pixels| {
+            apply_all_adjustments(pixels, &adjustments)
+        })
+    })
+}
+
+/// Handle-based [`apply_tone_curve`](crate::apply_tone_curve).
+///
+/// `dst` works as in [`apply_adjustments_h`].
+///
+/// # Errors
+///
+/// Returns an error for unknown handles.
+#[wasm_bindgen]
+pub fn apply_tone_curve_h(
+    src: u32,
+    lut: &JsToneCurveLut,
+    dst: Option<u32>,
+) -> Result<u32, JsValue> {
+    with_registry(|r| r.map_into(src, dst, |pixels| core_apply_curve(pixels, lut.inner())))
+}
+
+/// Handle-based [`resize`](crate::resize).
+///
+/// The resized image replaces `dst` when given, otherwise it gets a new
+/// handle. `filter` is as for `resize`.
+///
+/// # Errors
+///
+/// Returns an error for unknown handles or if resizing fails.
+#[wasm_bindgen]
+pub fn resize_h(
+    src: u32,
+    width: u32,
+    height: u32,
+    filter: u8,
+    dst: Option<u32>,
+) -> Result<u32, JsValue> {
+    with_registry(|r| {
+        let resized = decode::resize(r.get(src)?, width, height, filter_from_u8(filter))
+            .map_err(|e| e.to_string())?;
+        r.put(dst, resized)
+    })
+}
+
+/// Handle-based [`encode_jpeg_from_image`](crate::encode_jpeg_from_image).
+///
+/// # Errors
+///
+/// Returns an error for unknown handles or if encoding fails.
+#[wasm_bindgen]
+pub fn encode_jpeg_h(handle: u32, quality: u8) -> Result<Vec<u8>, JsValue> {
+    with_registry(|r| {
+        let image = r.get(handle)?;
+        encode::encode_jpeg(&image.pixels, image.width, image.height, quality)
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Dimensions returned by [`get_image_info`].
+#[derive(Debug, Serialize)]
+struct ImageInfo {
+    width: u32,
+    height: u32,
+    byte_length: usize,
+}
+
+impl From<&DecodedImage> for ImageInfo {
+    fn from(image: &DecodedImage) -> Self {
+        Self {
+            width: image.width,
+            height: image.height,
+            byte_length: image.pixels.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{lut_from_points, CurvePointJs};
+
+    fn test_image(width: u32, height: u32) -> JsDecodedImage {
+        let pixels = (0..width * height * 3)
+            .map(|i| (i * 7 % 256) as u8)
+            .collect();
+        JsDecodedImage::new(width, height, pixels)
+    }
+
+    #[test]
+    fn test_handle_lifecycle() {
+        release_all();
+        let handle = store_image(&test_image(4, 3)).unwrap();
+        assert_ne!(handle, 0);
+        assert_eq!(
+            get_image(handle).unwrap().pixels(),
+            test_image(4, 3).pixels()
+        );
+        assert_eq!(registry_stats().images, 1);
+        assert_eq!(registry_stats().bytes, 36.0);
+
+        release_image(handle).unwrap();
+        assert_eq!(registry_stats().images, 0);
+
+        let other = store_image(&test_image(2, 2)).unwrap();
+        assert_ne!(other, handle, "handles are not reused within a session");
+        release_all();
+        assert_eq!(registry_stats(), JsRegistryStats::default());
+    }
+
+    #[test]
+    fn test_double_release_is_an_error() {
+        let mut registry = Registry::default();
+        let handle = registry.insert(DecodedImage::new(1, 1, vec![0; 3]));
+        assert_eq!(registry.release(handle), Ok(()));
+        assert_eq!(registry.release(handle), Err(unknown_handle(handle)));
+        assert!(registry.get(handle).is_err());
+        assert!(registry.map_into(handle, None, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_handle_operations_match_object_api() {
+        release_all();
+        let image = test_image(12, 8);
+        let mut adjustments = BasicAdjustments::new();
+        adjustments.set_exposure(0.7);
+        adjustments.set_contrast(25.0);
+        let points = [(0.0, 0.0), (0.5, 0.6), (1.0, 1.0)].map(|(x, y)| CurvePointJs { x, y });
+        let lut = JsToneCurveLut::from(lut_from_points(&points));
+
+        let adjusted = crate::apply_adjustments(&image, &adjustments, None, None, None).unwrap();
+        let curved = crate::apply_tone_curve(&adjusted, &lut);
+        let resized = crate::resize(&curved, 6, 4, 1).unwrap();
+
+        let src = store_image(&image).unwrap();
+        let adjusted_h = apply_adjustments_h(src, &adjustments, None, None).unwrap();
+        let curved_h = apply_tone_curve_h(adjusted_h, &lut, None).unwrap();
+        let resized_h = resize_h(curved_h, 6, 4, 1, None).unwrap();
+
+        assert_eq!(get_image(adjusted_h).unwrap().pixels(), adjusted.pixels());
+        assert_eq!(get_image(curved_h).unwrap().pixels(), curved.pixels());
+        assert_eq!(get_image(resized_h).unwrap().pixels(), resized.pixels());
+        assert_eq!(
+            encode_jpeg_h(resized_h, 90).unwrap(),
+            crate::encode_jpeg_from_image(&resized, 90).unwrap()
+        );
+        // The source is untouched
+        assert_eq!(get_image(src).unwrap().pixels(), image.pixels());
+        release_all();
+    }
+
+    #[test]
+    fn test_output_buffers_are_reused_when_dimensions_match() {
+        release_all();
+        let image = test_image(8, 8);
+        let adjustments = BasicAdjustments::new();
+        let src = store_image(&image).unwrap();
+
+        let out = apply_adjustments_h(src, &adjustments, None, None).unwrap();
+        assert_eq!(registry_stats().allocations, 1);
+
+        // Same dimensions: written into the existing buffer
+        assert_eq!(
+            apply_adjustments_h(src, &adjustments, None, Some(out)).unwrap(),
+            out
+        );
+        // In place
+        assert_eq!(
+            apply_adjustments_h(out, &adjustments, None, Some(out)).unwrap(),
+            out
+        );
+        let stats = registry_stats();
+        assert_eq!((stats.allocations, stats.reuses), (1, 2));
+
+        // Different dimensions: the output is replaced by a new buffer
+        let small = resize_h(src, 4, 4, 0, None).unwrap();
+        apply_adjustments_h(src, &adjustments, None, Some(small)).unwrap();
+        let stats = registry_stats();
+        assert_eq!((stats.allocations, stats.reuses), (3, 2));
+        assert_eq!(get_image(small).unwrap().width(), 8);
+        assert_eq!(stats.images, 3);
+        release_all();
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_release_unknown_handle_errors() {
+        release_all();
+        let handle = store_image(&JsDecodedImage::new(2, 2, vec![0; 12])).unwrap();
+        assert!(release_image(handle).is_ok());
+        let err = release_image(handle).unwrap_err();
+        assert!(err.as_string().unwrap().contains("Unknown image handle"));
+        assert!(get_image(handle).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_get_image_info_and_stats() {
+        release_all();
+        let handle = store_image(&JsDecodedImage::new(3, 2, vec![0; 18])).unwrap();
+        let info = get_image_info(handle).unwrap();
+        let width = js_sys::Reflect::get(&info, &"width".into()).unwrap();
+        assert_eq!(width.as_f64(), Some(3.0));
+
+        let stats: JsRegistryStats =
+            serde_wasm_bindgen::from_value(get_registry_stats().unwrap()).unwrap();
+        assert_eq!(stats.images, 1);
+        assert_eq!(stats.bytes, 18.0);
+        release_all();
+    }
+}
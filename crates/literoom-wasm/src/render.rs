@@ -0,0 +1,1247 @@
+//! Tile rendering WASM bindings for the zoomed-in detail view.
+//!
+//! At 100% zoom only a small part of a large photo is on screen. Instead of
+//! rendering the whole edited image and letting the browser crop it, the
+//! detail view asks for the tiles it shows and draws each one straight to
+//! the canvas. Tiles stitch seamlessly, so they can be rendered in any order
+//! and cached independently.
+//!
+//! Grid thumbnails of edited photos are rendered here too, decoding and
+//! editing in one call so the thumbnail worker queue stays simple.
+//!
+//! Batch operations such as "sync settings then export" prepare the edit
+//! state once with [`prepare_edit`] and reuse it for every photo, so the
+//! state is parsed and its curve LUTs and masks are built only once.
+//!
+//! On-screen renders take an optional display color space so tiles and
+//! previews drawn to a Display P3 canvas are converted as a last step.
+//!
+//! While a slider is dragged, the worker keeps the image entering each
+//! pipeline stage from [`render_to_stage`] and re-renders only from the
+//! first stage [`diff_stages`](crate::edit::diff_stages) reports as changed
+//! with [`render_from_stage`].
+
+use crate::edit::edit_state_from_js;
+use crate::encode::{
+    export_error_to_js, export_format_from_js, export_taps_from_js, JsExportResult,
+};
+use crate::types::{
+    adjustment_error_to_js, display_color_space_from_u8, image_error_to_js, JsDecodedImage,
+};
+use literoom_core::decode::{self, convert_for_display, DecodedImage};
+use literoom_core::edit::EditState;
+use literoom_core::encode::{self, ExportContainer, ExportFormat};
+use literoom_core::export::{
+    preflight_export as core_preflight_export, process_and_encode, ExportPreflightOptions,
+    ExportTaps,
+};
+use literoom_core::render::{
+    render_from_stage as core_render_from_stage, render_mono_split as core_mono_split,
+    render_region, render_thumbnail_with_mask_threshold, render_to_stage as core_render_to_stage,
+    rendered_size, AdjustmentToggles, PreparedEdit, RenderRegion, RenderStage,
+    THUMBNAIL_MASK_MIN_SIZE,
+};
+use literoom_core::transform::InterpolationFilter;
+use literoom_core::validate::validate_dimensions;
+use wasm_bindgen::prelude::*;
+
+/// Get the size of the fully edited image (after rotation and crop).
+///
+/// Multiply by the zoom level to get the size of the tile grid in screen
+/// pixels.
+///
+/// # Returns
+///
+/// `[width, height]` in pixels.
+///
+/// # Example
+///
+/// ```typescript
+/// const [width, height] = get_rendered_size(source, editState);
+/// const columns = Math.ceil(Math.round(width * zoom) / 256);
+/// ```
+#[wasm_bindgen]
+pub fn get_rendered_size(image: &JsDecodedImage, edit_state: JsValue) -> Result<Vec<u32>, JsValue> {
+    let state = edit_state_from_js(edit_state)?;
+    let (width, height) = rendered_size(image.width(), image.height(), &state);
+    Ok(vec![width, height])
+}
+
+/// Render one tile of the edited image at a zoom level.
+///
+/// The edited image is scaled by `zoom` and split into a grid of
+/// `tile_size` squares; this renders square `(tile_x, tile_y)`. Only the
+/// source pixels under the tile are processed. Tiles on the right and bottom
+/// edges are smaller when the scaled image does not divide evenly.
+///
+/// At zoom 1 the tiles are byte-identical to the matching part of a full
+/// render; above 1 pixels are enlarged without smoothing.
+///
+/// # Arguments
+///
+/// * `image` - Source image (full resolution)
+/// * `edit_state` - Edit state object; missing fields use defaults
+/// * `tile_x` - Tile column
+/// * `tile_y` - Tile row
+/// * `tile_size` - Tile edge in screen pixels
+/// * `zoom` - Screen pixels per edited-image pixel (1.0 = 100%)
+/// * `display_color_space` - Canvas the tile is drawn to: 0 = sRGB
+///   (default), 1 = Display P3
+///
+/// # Errors
+///
+/// Returns an error if the edit state cannot be parsed, `tile_size` is zero,
+/// `zoom` is not a positive number, the tile lies outside the image, or the
+/// image is empty or its buffer does not match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const tile = render_tile(source, editState, col, row, 256, 1.0);
+/// const rgba = rgbToRgba(tile.pixels());
+/// ctx.putImageData(new ImageData(rgba, tile.width, tile.height), col * 256 - scrollX, row * 256 - scrollY);
+/// ```
+#[wasm_bindgen]
+pub fn render_tile(
+    image: &JsDecodedImage,
+    edit_state: JsValue,
+    tile_x: u32,
+    tile_y: u32,
+    tile_size: u32,
+    zoom: f32,
+    display_color_space: Option<u8>,
+) -> Result<JsDecodedImage, JsValue> {
+    let state = edit_state_from_js(edit_state)?;
+    image.validate()?;
+    render_tile_decoded(image.as_decoded(), &state, tile_x, tile_y, tile_size, zoom)
+        .map(|tile| JsDecodedImage::from_decoded(for_display(tile, display_color_space)))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Convert rendered sRGB pixels for the canvas they are drawn to.
+fn for_display(mut image: DecodedImage, display_color_space: Option<u8>) -> DecodedImage {
+    let target = display_color_space_from_u8(display_color_space.unwrap_or(0));
+    convert_for_display(&mut image.pixels, target);
+    image
+}
+
+/// Render tile `(tile_x, tile_y)` of the edited image scaled by `zoom`.
+fn render_tile_decoded(
+    image: &DecodedImage,
+    state: &EditState,
+    tile_x: u32,
+    tile_y: u32,
+    tile_size: u32,
+    zoom: f32,
+) -> Result<DecodedImage, String> {
+    if tile_size == 0 {
+        return Err("Tile size must be positive".to_string());
+    }
+    if !(zoom.is_finite() && zoom > 0.0) {
+        return Err(format!("Zoom must be a positive number, got {}", zoom));
+    }
+    let zoom = zoom as f64;
+
+    let (width, height) = rendered_size(image.width, image.height, state);
+    let display_w = ((width as f64 * zoom).round() as u64).max(1);
+    let display_h = ((height as f64 * zoom).round() as u64).max(1);
+    let (x0, y0) = (
+        tile_x as u64 * tile_size as u64,
+        tile_y as u64 * tile_size as u64,
+    );
+    if x0 >= display_w || y0 >= display_h {
+        return Err(format!(
+            "Tile ({}, {}) is outside the {}x{} image",
+            tile_x, tile_y, display_w, display_h
+        ));
+    }
+    let out_w = (tile_size as u64).min(display_w - x0) as u32;
+    let out_h = (tile_size as u64).min(display_h - y0) as u32;
+
+    let region = RenderRegion {
+        x: x0 as f64 / zoom,
+        y: y0 as f64 / zoom,
+        width: out_w as f64 / zoom,
+        height: out_h as f64 / zoom,
+    };
+    render_region(
+        image,
+        state,
+        &region,
+        out_w,
+        out_h,
+        InterpolationFilter::Bilinear,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Render the edited image with color left of `split` and B&W right of it.
+///
+/// Both halves use the full edit state; the right half is converted to
+/// black and white with the state's `bw_mix` (BT.709 luminance if unset),
+/// whether or not `monochrome` is on. Rotation, crop and adjustments are
+/// computed once for both.
+///
+/// # Arguments
+///
+/// * `image` - Source image
+/// * `edit_state` - Edit state object; missing fields use defaults
+/// * `split` - Position of the split line as a fraction of the width (0-1)
+///
+/// # Errors
+///
+/// Returns an error if the edit state cannot be parsed, `split` is not a
+/// number between 0 and 1, or the image is empty or its buffer does not
+/// match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const preview = render_mono_split(source, editState, dragX / canvas.width);
+/// ```
+#[wasm_bindgen]
+pub fn render_mono_split(
+    image: &JsDecodedImage,
+    edit_state: JsValue,
+    split: f32,
+) -> Result<JsDecodedImage, JsValue> {
+    let state = edit_state_from_js(edit_state)?;
+    image.validate()?;
+    render_mono_split_decoded(image.as_decoded(), &state, split)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Render the color/B&W split preview at `split`.
+fn render_mono_split_decoded(
+    image: &DecodedImage,
+    state: &EditState,
+    split: f32,
+) -> Result<DecodedImage, String> {
+    if !(0.0..=1.0).contains(&split) {
+        return Err(format!("Split must be between 0 and 1, got {}", split));
+    }
+    core_mono_split(image, state, split, InterpolationFilter::Bilinear).map_err(|e| e.to_string())
+}
+
+/// Parse a pipeline stage name.
+fn render_stage_from_name(name: &str) -> Result<RenderStage, String> {
+    RenderStage::from_name(name).ok_or_else(|| {
+        format!(
+            "Unknown render stage '{}', expected one of: transform, adjustments, effects, curve, \
+             masks",
+            name
+        )
+    })
+}
+
+/// Render the edited preview from a cached intermediate, running only
+/// `stage_name` and the stages after it.
+///
+/// `image` is the image entering that stage, from [`render_to_stage`] with
+/// an edit state whose earlier stages match `edit_state`. The result is
+/// byte-identical to a full preview render of the source.
+///
+/// # Arguments
+///
+/// * `image` - Cached intermediate (the source itself for `"transform"`)
+/// * `stage_name` - `"transform"`, `"adjustments"`, `"effects"`, `"curve"`
+///   or `"masks"`
+/// * `edit_state` - Edit state object; missing fields use defaults
+/// * `display_color_space` - Canvas the result is drawn to: 0 = sRGB
+///   (default), 1 = Display P3
+///
+/// # Errors
+///
+/// Returns an error if the stage name is unknown, the edit state cannot be
+/// parsed, or the image is empty or its buffer does not match its
+/// dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const preview = render_from_stage(cache.get('curve'), 'curve', nextState);
+/// ```
+#[wasm_bindgen]
+pub fn render_from_stage(
+    image: &JsDecodedImage,
+    stage_name: &str,
+    edit_state: JsValue,
+    display_color_space: Option<u8>,
+) -> Result<JsDecodedImage, JsValue> {
+    let stage = render_stage_from_name(stage_name).map_err(|e| JsValue::from_str(&e))?;
+    let state = edit_state_from_js(edit_state)?;
+    image.validate()?;
+    core_render_from_stage(
+        image.as_decoded(),
+        stage,
+        &state,
+        InterpolationFilter::Bilinear,
+    )
+    .map(|rendered| JsDecodedImage::from_decoded(for_display(rendered, display_color_space)))
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Run the preview pipeline up to, but not including, `stage_name`, giving
+/// the intermediate to cache for [`render_from_stage`].
+///
+/// The result is in sRGB whatever the canvas, since later stages expect
+/// it.
+///
+/// # Errors
+///
+/// Returns an error if the stage name is unknown, the edit state cannot be
+/// parsed, or the image is empty or its buffer does not match its
+/// dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// cache.set('curve', render_to_stage(source, 'curve', editState));
+/// ```
+#[wasm_bindgen]
+pub fn render_to_stage(
+    image: &JsDecodedImage,
+    stage_name: &str,
+    edit_state: JsValue,
+) -> Result<JsDecodedImage, JsValue> {
+    let stage = render_stage_from_name(stage_name).map_err(|e| JsValue::from_str(&e))?;
+    let state = edit_state_from_js(edit_state)?;
+    image.validate()?;
+    core_render_to_stage(
+        image.as_decoded(),
+        stage,
+        &state,
+        InterpolationFilter::Bilinear,
+    )
+    .map(JsDecodedImage::from_decoded)
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Decode a photo and render a thumbnail with its edits applied.
+///
+/// RAW files use their embedded preview and HEIF files their embedded JPEG;
+/// anything else is decoded as JPEG. The edits are rendered at thumbnail
+/// size, so this costs little more than `generate_thumbnail`, and with an
+/// empty edit state the result is the same.
+///
+/// # Arguments
+///
+/// * `bytes` - The file bytes
+/// * `edit_state` - Edit state object; missing fields use defaults
+/// * `size` - Maximum thumbnail dimension in pixels
+/// * `mask_min_size` - Skip masks on thumbnails smaller than this
+///   (default: 64)
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be decoded, the edit state cannot be
+/// parsed, or `size` is zero.
+///
+/// # Example
+///
+/// ```typescript
+/// const thumb = generate_edited_thumbnail(bytes, editState, 256);
+/// ```
+#[wasm_bindgen]
+pub fn generate_edited_thumbnail(
+    bytes: &[u8],
+    edit_state: JsValue,
+    size: u32,
+    mask_min_size: Option<u32>,
+) -> Result<JsDecodedImage, JsValue> {
+    let state = edit_state_from_js(edit_state)?;
+    decode_thumbnail_source(bytes)
+        .and_then(|image| edited_thumbnail(&image, &state, size, mask_min_size))
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Render a thumbnail of an already decoded image with its edits applied.
+///
+/// Same as [`generate_edited_thumbnail`] without the decode.
+///
+/// # Errors
+///
+/// Returns an error if the edit state cannot be parsed, `size` is zero, or
+/// the image is empty or its buffer does not match its dimensions.
+///
+/// # Example
+///
+/// ```typescript
+/// const thumb = generate_edited_thumbnail_from_image(preview, editState, 256);
+/// ```
+#[wasm_bindgen]
+pub fn generate_edited_thumbnail_from_image(
+    image: &JsDecodedImage,
+    edit_state: JsValue,
+    size: u32,
+    mask_min_size: Option<u32>,
+) -> Result<JsDecodedImage, JsValue> {
+    let state = edit_state_from_js(edit_state)?;
+    image.validate()?;
+    edited_thumbnail(image.as_decoded(), &state, size, mask_min_size)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Decode the image a grid thumbnail is made from.
+fn decode_thumbnail_source(bytes: &[u8]) -> Result<DecodedImage, String> {
+    let decoded = if decode::is_raw_file(bytes) {
+        decode::decode_raw_thumbnail(bytes)
+    } else if decode::is_heif_file(bytes) {
+        decode::decode_heif_preview(bytes)
+    } else {
+        decode::decode_jpeg(bytes)
+    };
+    decoded.map_err(|e| e.to_string())
+}
+
+/// Render the edited thumbnail, skipping masks below `mask_min_size`.
+fn edited_thumbnail(
+    image: &DecodedImage,
+    state: &EditState,
+    size: u32,
+    mask_min_size: Option<u32>,
+) -> Result<DecodedImage, String> {
+    let mask_min_size = mask_min_size.unwrap_or(THUMBNAIL_MASK_MIN_SIZE);
+    render_thumbnail_with_mask_threshold(image, state, size, mask_min_size)
+        .map_err(|e| e.to_string())
+}
+
+/// An edit state parsed and prepared once for applying to many images.
+///
+/// Holds its own copy of the state, so later changes to the JavaScript
+/// object do not affect it.
+#[wasm_bindgen]
+pub struct JsPreparedEdit {
+    inner: PreparedEdit,
+}
+
+/// Parse and prepare an edit state for batch rendering.
+///
+/// Validates the adjustments and builds the curve lookup tables and mask
+/// layers once. Masks are in normalized coordinates, so the prepared edit
+/// applies to images of any size.
+///
+/// # Errors
+///
+/// Returns an error if the edit state cannot be parsed, or an error with
+/// `code` and `fields` properties if the global or an enabled mask's
+/// adjustments are not finite or out of range.
+///
+/// # Example
+///
+/// ```typescript
+/// const prepared = prepare_edit(sourceEditState);
+/// for (const bytes of selectedFiles) {
+///   const jpeg = export_prepared(decode_jpeg(bytes), prepared, { quality: 90 });
+///   await writeFile(jpeg);
+/// }
+/// prepared.free();
+/// ```
+#[wasm_bindgen]
+pub fn prepare_edit(edit_state: JsValue) -> Result<JsPreparedEdit, JsValue> {
+    let state = edit_state_from_js(edit_state)?;
+    PreparedEdit::new(&state)
+        .map(|inner| JsPreparedEdit { inner })
+        .map_err(|e| adjustment_error_to_js(&e))
+}
+
+/// Prepare an edit state with some pipeline stages switched off.
+///
+/// For comparing an edit with and without one panel: renders and exports
+/// with the result skip the stages that are off, while the edit state
+/// itself is left as it is. Switching a stage back on is a matter of
+/// preparing again with it on.
+///
+/// # Arguments
+///
+/// * `edit_state` - Edit state, as for [`prepare_edit`]
+/// * `toggles` - `{ tone, color, curve, masks, effects, transform }`, all
+///   optional booleans defaulting to `true`
+///
+/// # Errors
+///
+/// Returns an error if the edit state or toggles cannot be parsed, or an
+/// error as from [`prepare_edit`] for invalid adjustments.
+///
+/// # Example
+///
+/// ```typescript
+/// // Preview the photo as it looks without the color panel
+/// const before = prepare_edit_with_toggles(editState, { color: false });
+/// const preview = apply_prepared_edit(source, before);
+/// ```
+#[wasm_bindgen]
+pub fn prepare_edit_with_toggles(
+    edit_state: JsValue,
+    toggles: JsValue,
+) -> Result<JsPreparedEdit, JsValue> {
+    let toggles: AdjustmentToggles = serde_wasm_bindgen::from_value(toggles)
+        .map_err(|e| JsValue::from_str(&format!("Invalid toggles: {}", e)))?;
+    let prepared = prepare_edit(edit_state)?;
+    Ok(JsPreparedEdit {
+        inner: prepared.inner.with_toggles(toggles),
+    })
+}
+
+/// Render an image with a prepared edit, using the Lanczos3 filter for
+/// rotation.
+///
+/// # Arguments
+///
+/// * `image` - Source image (full resolution)
+/// * `prepared` - Edit from [`prepare_edit`]
+/// * `display_color_space` - Canvas the result is drawn to: 0 = sRGB
+///   (default), 1 = Display P3. Leave unset for pixels that are exported.
+///
+/// # Errors
+///
+/// Returns an error if the image is empty or its buffer does not match its
+/// dimensions.
+#[wasm_bindgen]
+pub fn apply_prepared_edit(
+    image: &JsDecodedImage,
+    prepared: &JsPreparedEdit,
+    display_color_space: Option<u8>,
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    render_prepared(image.as_decoded(), &prepared.inner)
+        .map(|rendered| JsDecodedImage::from_decoded(for_display(rendered, display_color_space)))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Render an image with a prepared edit and encode it in an export format.
+///
+/// # Arguments
+///
+/// * `image` - Source image (full resolution)
+/// * `prepared` - Edit from [`prepare_edit`]
+/// * `format` - Export format as in
+///   [`validate_export_format`](crate::encode::validate_export_format);
+///   `undefined` is an 8-bit sRGB JPEG at quality 90
+///
+/// # Errors
+///
+/// Returns an error if the format cannot be parsed, an `Error` with a
+/// `code` property if it is invalid, or an error if the image is empty or
+/// its buffer does not match its dimensions, or if encoding fails.
+#[wasm_bindgen]
+pub fn export_prepared(
+    image: &JsDecodedImage,
+    prepared: &JsPreparedEdit,
+    format: JsValue,
+) -> Result<Vec<u8>, JsValue> {
+    let format = export_format_from_js(format)?;
+    image.validate()?;
+    export_prepared_decoded(image.as_decoded(), &prepared.inner, &format)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Render an image with a prepared edit and encode it in an export format,
+/// computing its histogram and a thumbnail from the rendered pixels.
+///
+/// The bytes are the same as from [`export_prepared`]; the taps read the
+/// pixels right before they are encoded, so the export manifest's histogram
+/// and the exported preview need no second decode.
+///
+/// # Arguments
+///
+/// * `image` - Source image (full resolution)
+/// * `prepared` - Edit from [`prepare_edit`]
+/// * `format` - Export format, as in [`export_prepared`]
+/// * `taps` - `{ want_histogram, thumbnail_size }`, both optional
+///
+/// # Errors
+///
+/// Returns an error if the format or taps cannot be parsed, an `Error` with
+/// a `code` property if the format is invalid, or an error if the image is
+/// empty or its buffer does not match its dimensions, `thumbnail_size` is
+/// zero, or encoding fails.
+///
+/// # Example
+///
+/// ```typescript
+/// const format = { container: 'png', color_space: 'display_p3', bit_depth: 16 };
+/// const result = export_prepared_with_taps(image, prepared, format, { want_histogram: true, thumbnail_size: 256 });
+/// await writeFile(result.bytes);
+/// previews.set(name, result.thumbnail);
+/// ```
+#[wasm_bindgen]
+pub fn export_prepared_with_taps(
+    image: &JsDecodedImage,
+    prepared: &JsPreparedEdit,
+    format: JsValue,
+    taps: JsValue,
+) -> Result<JsExportResult, JsValue> {
+    let format = export_format_from_js(format)?;
+    let taps = export_taps_from_js(taps)?;
+    image.validate()?;
+    process_and_encode(
+        image.as_decoded(),
+        &prepared.inner,
+        InterpolationFilter::Lanczos3,
+        &format,
+        &taps,
+    )
+    .map(JsExportResult::from)
+    .map_err(|e| export_error_to_js(&e))
+}
+
+/// Export a JPEG file with a prepared edit, losslessly when possible.
+///
+/// With `lossless_when_possible` set and a crop as the only edit, the file
+/// is cropped with [`crop_jpeg_lossless`](crate::encode::crop_jpeg_lossless)
+/// instead of being re-encoded, keeping its quality and metadata. The crop's
+/// left and top edges then snap out by up to 15 pixels. This needs the
+/// format to be a JPEG in the file's own color space (sRGB if it has no
+/// profile). Any other edit or format, or a JPEG that cannot be cropped
+/// that way (such as a progressive one), is decoded, rendered and encoded
+/// as in [`export_prepared`].
+///
+/// # Arguments
+///
+/// * `bytes` - Original JPEG file bytes
+/// * `prepared` - Edit from [`prepare_edit`]
+/// * `format` - Export format when re-encoding, as in [`export_prepared`]
+/// * `lossless_when_possible` - Prefer the lossless crop (default false)
+///
+/// # Errors
+///
+/// Returns an error if the format cannot be parsed, an `Error` with a
+/// `code` property if it is invalid, or an error if the bytes cannot be
+/// decoded or encoding fails.
+///
+/// # Example
+///
+/// ```typescript
+/// const jpeg = export_prepared_jpeg(originalBytes, prepared, { quality: 90 }, settings.losslessWhenPossible);
+/// ```
+#[wasm_bindgen]
+pub fn export_prepared_jpeg(
+    bytes: &[u8],
+    prepared: &JsPreparedEdit,
+    format: JsValue,
+    lossless_when_possible: Option<bool>,
+) -> Result<Vec<u8>, JsValue> {
+    let format = export_format_from_js(format)?;
+    export_jpeg_bytes(
+        bytes,
+        &prepared.inner,
+        &format,
+        lossless_when_possible.unwrap_or(false),
+    )
+    .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Report an export's output size and estimated file size before running
+/// it.
+///
+/// The dimensions come from the same math as the export itself (rotation,
+/// crop, then `resize_for_export`'s resize, padding and border), so they
+/// match the exported file exactly. The file size is a typical figure for
+/// the format and quality; [`preflight_export_with_proxy`] measures it on a
+/// small decode instead. See `literoom_core::export::preflight_export` for
+/// how close each estimate is.
+///
+/// # Arguments
+///
+/// * `source_width`, `source_height` - Full-resolution source size
+/// * `edit_state` - Edit state, as for [`prepare_edit`]
+/// * `export_options` - `{ max_edge, pad_aspect, border_px,
+///   lossless_when_possible, format }`, all optional; `format` is as in
+///   [`export_prepared`]
+///
+/// # Returns
+///
+/// `{ output_width, output_height, estimated_bytes, will_resize,
+/// will_reencode }`. `will_reencode` is false when
+/// [`export_prepared_jpeg`] would crop losslessly; its left and top edges
+/// may then snap out by up to 15 pixels.
+///
+/// # Errors
+///
+/// The errors the export would throw for the same input: an `Error` with a
+/// `code` property for an invalid format, empty or oversized source
+/// dimensions, or invalid adjustments, and an error for an unparsable edit
+/// state or options, a `max_edge` of zero, or an invalid pad aspect.
+///
+/// # Example
+///
+/// ```typescript
+/// const { output_width, output_height, estimated_bytes } = preflight_export(
+///   photo.width, photo.height, editState, { max_edge: 2048, format: { quality: 85 } });
+/// dialog.showSummary(`${output_width}×${output_height}, about ${formatBytes(estimated_bytes)}`);
+/// ```
+#[wasm_bindgen]
+pub fn preflight_export(
+    source_width: u32,
+    source_height: u32,
+    edit_state: JsValue,
+    export_options: JsValue,
+) -> Result<JsValue, JsValue> {
+    preflight_js(
+        source_width,
+        source_height,
+        edit_state,
+        export_options,
+        None,
+    )
+}
+
+/// [`preflight_export`] with the file size measured on a proxy.
+///
+/// `proxy` is a small decode of the source, such as its embedded preview;
+/// it is edited and encoded, and its bytes per pixel scaled to the output
+/// size.
+///
+/// # Errors
+///
+/// Same as [`preflight_export`], and an error if the proxy is empty or its
+/// buffer does not match its dimensions.
+#[wasm_bindgen]
+pub fn preflight_export_with_proxy(
+    source_width: u32,
+    source_height: u32,
+    edit_state: JsValue,
+    export_options: JsValue,
+    proxy: &JsDecodedImage,
+) -> Result<JsValue, JsValue> {
+    preflight_js(
+        source_width,
+        source_height,
+        edit_state,
+        export_options,
+        Some(proxy.as_decoded()),
+    )
+}
+
+fn preflight_js(
+    source_width: u32,
+    source_height: u32,
+    edit_state: JsValue,
+    export_options: JsValue,
+    proxy: Option<&DecodedImage>,
+) -> Result<JsValue, JsValue> {
+    let state = edit_state_from_js(edit_state)?;
+    let prepared = PreparedEdit::new(&state).map_err(|e| adjustment_error_to_js(&e))?;
+    let (options, format) = if export_options.is_undefined() || export_options.is_null() {
+        (ExportPreflightOptions::default(), ExportFormat::default())
+    } else {
+        let format = js_sys::Reflect::get(&export_options, &"format".into())?;
+        let options: ExportPreflightOptions = serde_wasm_bindgen::from_value(export_options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid export options: {}", e)))?;
+        (options, export_format_from_js(format)?)
+    };
+    validate_dimensions(source_width, source_height).map_err(|e| image_error_to_js(&e))?;
+
+    let preflight = core_preflight_export(
+        source_width,
+        source_height,
+        &prepared,
+        &options,
+        &format,
+        proxy,
+    )
+    .map_err(|e| export_error_to_js(&e))?;
+    serde_wasm_bindgen::to_value(&preflight)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize preflight: {}", e)))
+}
+
+/// Crop losslessly if allowed and possible, otherwise decode and export.
+fn export_jpeg_bytes(
+    bytes: &[u8],
+    prepared: &PreparedEdit,
+    format: &ExportFormat,
+    lossless: bool,
+) -> Result<Vec<u8>, String> {
+    let keeps_format = format.container == ExportContainer::Jpeg
+        && decode::extract_icc_profile(bytes).map_or(Some(decode::ColorProfile::Srgb), |icc| {
+            decode::detect_color_profile(&icc)
+        }) == Some(format.color_space.profile());
+    if let Some(crop) = prepared
+        .state()
+        .crop_only()
+        .filter(|_| lossless && keeps_format && prepared.toggles().transform)
+    {
+        match encode::crop_jpeg_lossless(bytes, crop) {
+            Ok(result) => return Ok(result.bytes),
+            Err(encode::EncodeError::UnsupportedJpeg(_)) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    let image = decode::decode_jpeg(bytes).map_err(|e| e.to_string())?;
+    export_prepared_decoded(&image, prepared, format)
+}
+
+/// Render with the export-quality rotation filter.
+fn render_prepared(image: &DecodedImage, prepared: &PreparedEdit) -> Result<DecodedImage, String> {
+    prepared
+        .render(image, InterpolationFilter::Lanczos3)
+        .map_err(|e| e.to_string())
+}
+
+/// Render and encode in `format`.
+fn export_prepared_decoded(
+    image: &DecodedImage,
+    prepared: &PreparedEdit,
+    format: &ExportFormat,
+) -> Result<Vec<u8>, String> {
+    let taps = ExportTaps::default();
+    process_and_encode(
+        image,
+        prepared,
+        InterpolationFilter::Lanczos3,
+        format,
+        &taps,
+    )
+    .map(|export| export.bytes)
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use literoom_core::edit::{CropRect, Rotation};
+    use literoom_core::render::render_edit;
+    use literoom_core::testing::noise;
+
+    fn edited_state() -> EditState {
+        let mut state = EditState::default();
+        state.adjustments.exposure = 0.3;
+        state.adjustments.vibrance = 20.0;
+        state.rotation = Rotation {
+            angle: 0.0,
+            straighten: -4.0,
+        };
+        state.crop = Some(CropRect {
+            left: 0.05,
+            top: 0.1,
+            width: 0.9,
+            height: 0.8,
+        });
+        state
+    }
+
+    #[test]
+    fn test_render_stage_names() {
+        for stage in RenderStage::ALL {
+            assert_eq!(render_stage_from_name(stage.name()), Ok(stage));
+        }
+        let err = render_stage_from_name("tone").unwrap_err();
+        assert!(err.contains("'tone'"));
+    }
+
+    #[test]
+    fn test_tile_grid_matches_full_render() {
+        let image = noise(70, 50, 11);
+        let state = edited_state();
+        let full = render_edit(&image, &state, InterpolationFilter::Bilinear).unwrap();
+        let tile_size = full.width.div_ceil(4).max(full.height.div_ceil(4));
+
+        let mut stitched = vec![0u8; full.pixels.len()];
+        for ty in 0..full.height.div_ceil(tile_size) {
+            for tx in 0..full.width.div_ceil(tile_size) {
+                let tile = render_tile_decoded(&image, &state, tx, ty, tile_size, 1.0).unwrap();
+                for (row, src) in tile
+                    .pixels
+                    .chunks_exact(tile.width as usize * 3)
+                    .enumerate()
+                {
+                    let y = ty * tile_size + row as u32;
+                    let start = ((y * full.width + tx * tile_size) * 3) as usize;
+                    stitched[start..start + src.len()].copy_from_slice(src);
+                }
+            }
+        }
+        assert_eq!(stitched, full.pixels);
+    }
+
+    #[test]
+    fn test_edge_tiles_are_trimmed() {
+        let image = noise(50, 30, 2);
+        let state = EditState::default();
+
+        // 200% of 50x30 is 100x60: the last column is 4 wide, the last row 28 tall
+        let tile = render_tile_decoded(&image, &state, 1, 1, 32, 2.0).unwrap();
+        assert_eq!((tile.width, tile.height), (32, 28));
+        let tile = render_tile_decoded(&image, &state, 3, 0, 32, 2.0).unwrap();
+        assert_eq!((tile.width, tile.height), (4, 32));
+    }
+
+    #[test]
+    fn test_invalid_tiles_are_rejected() {
+        let image = noise(16, 16, 4);
+        let state = EditState::default();
+        assert!(render_tile_decoded(&image, &state, 0, 0, 0, 1.0).is_err());
+        assert!(render_tile_decoded(&image, &state, 0, 0, 8, 0.0).is_err());
+        assert!(render_tile_decoded(&image, &state, 0, 0, 8, f32::NAN).is_err());
+        assert!(render_tile_decoded(&image, &state, 2, 0, 8, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_mono_split_preview() {
+        let image = noise(40, 30, 4);
+        let state = edited_state();
+        let color = render_edit(&image, &state, InterpolationFilter::Bilinear).unwrap();
+
+        // Color is left of the split line
+        let all_color = render_mono_split_decoded(&image, &state, 1.0).unwrap();
+        let all_mono = render_mono_split_decoded(&image, &state, 0.0).unwrap();
+        assert_eq!(all_color.pixels, color.pixels);
+        assert!(all_mono
+            .pixels
+            .chunks_exact(3)
+            .all(|p| p[0] == p[1] && p[1] == p[2]));
+
+        assert!(render_mono_split_decoded(&image, &state, 1.5).is_err());
+        assert!(render_mono_split_decoded(&image, &state, f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_edited_thumbnail_from_jpeg_bytes() {
+        let image = noise(120, 80, 6);
+        let jpeg = literoom_core::encode::encode_jpeg(&image.pixels, 120, 80, 90).unwrap();
+        let state = edited_state();
+
+        let source = decode_thumbnail_source(&jpeg).unwrap();
+        let thumbnail = edited_thumbnail(&source, &state, 32, None).unwrap();
+
+        assert!(thumbnail.width.max(thumbnail.height) <= 32);
+        let plain = decode::generate_thumbnail(&source, 32).unwrap();
+        let unedited = edited_thumbnail(&source, &EditState::default(), 32, None).unwrap();
+        assert_eq!(unedited.pixels, plain.pixels);
+        assert_ne!(thumbnail.pixels, plain.pixels);
+    }
+
+    #[test]
+    fn test_edited_thumbnail_rejects_bad_input() {
+        assert!(decode_thumbnail_source(b"not an image").is_err());
+        let image = noise(16, 16, 1);
+        assert!(edited_thumbnail(&image, &EditState::default(), 0, None).is_err());
+    }
+
+    #[test]
+    fn test_prepared_edit_matches_unprepared_render() {
+        let state = edited_state();
+        let prepared = PreparedEdit::new(&state).unwrap();
+        for (width, height) in [(64, 48), (31, 77), (100, 100)] {
+            let image = noise(width, height, 2);
+            let rendered = render_prepared(&image, &prepared).unwrap();
+            let direct = render_edit(&image, &state, InterpolationFilter::Lanczos3).unwrap();
+            assert_eq!(rendered.pixels, direct.pixels, "{}x{}", width, height);
+
+            let format = ExportFormat::jpeg(90);
+            let jpeg = export_prepared_decoded(&image, &prepared, &format).unwrap();
+            let expected = encode::encode_image(&direct, &format).unwrap();
+            assert_eq!(jpeg, expected);
+        }
+    }
+
+    #[test]
+    fn test_export_prepared_rejects_invalid_image() {
+        let prepared = PreparedEdit::new(&edited_state()).unwrap();
+        let mismatched = DecodedImage {
+            width: 8,
+            height: 8,
+            pixels: vec![0; 10],
+        };
+        assert!(export_prepared_decoded(&mismatched, &prepared, &ExportFormat::jpeg(90)).is_err());
+    }
+
+    #[test]
+    fn test_export_jpeg_bytes_crops_losslessly_when_allowed() {
+        let image = noise(64, 48, 4);
+        let bytes = encode::encode_jpeg(&image.pixels, 64, 48, 90).unwrap();
+        let mut state = EditState::default();
+        state.crop = Some(CropRect {
+            left: 0.25,
+            top: 0.25,
+            width: 0.5,
+            height: 0.5,
+        });
+        let prepared = PreparedEdit::new(&state).unwrap();
+
+        let lossless = export_jpeg_bytes(&bytes, &prepared, &ExportFormat::jpeg(90), true).unwrap();
+        let expected = encode::crop_jpeg_lossless(&bytes, state.crop.unwrap()).unwrap();
+        assert_eq!(lossless, expected.bytes);
+
+        let reencoded =
+            export_jpeg_bytes(&bytes, &prepared, &ExportFormat::jpeg(90), false).unwrap();
+        let decoded = decode::decode_jpeg(&bytes).unwrap();
+        assert_eq!(
+            reencoded,
+            export_prepared_decoded(&decoded, &prepared, &ExportFormat::jpeg(90)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_export_jpeg_bytes_without_transform_keeps_full_frame() {
+        let image = noise(64, 48, 4);
+        let bytes = encode::encode_jpeg(&image.pixels, 64, 48, 90).unwrap();
+        let mut state = EditState::default();
+        state.crop = Some(CropRect {
+            left: 0.25,
+            top: 0.25,
+            width: 0.5,
+            height: 0.5,
+        });
+        let toggles = AdjustmentToggles {
+            transform: false,
+            ..AdjustmentToggles::ALL
+        };
+        let prepared = PreparedEdit::new(&state).unwrap().with_toggles(toggles);
+
+        let exported = export_jpeg_bytes(&bytes, &prepared, &ExportFormat::jpeg(90), true).unwrap();
+        let decoded = decode::decode_jpeg(&exported).unwrap();
+        assert_eq!((decoded.width, decoded.height), (64, 48));
+    }
+
+    #[test]
+    fn test_export_jpeg_bytes_keeps_color_space_when_lossless() {
+        let image = noise(64, 48, 5);
+        let srgb = encode::encode_jpeg(&image.pixels, 64, 48, 90).unwrap();
+        let mut state = EditState::default();
+        state.crop = edited_state().crop;
+        let prepared = PreparedEdit::new(&state).unwrap();
+        let adobe = ExportFormat {
+            color_space: encode::ExportColorSpace::AdobeRgb,
+            ..ExportFormat::jpeg(90)
+        };
+
+        // An untagged file is sRGB, so an Adobe RGB export re-encodes it
+        let exported = export_jpeg_bytes(&srgb, &prepared, &adobe, true).unwrap();
+        let icc = decode::extract_icc_profile(&exported).unwrap();
+        assert_eq!(
+            decode::detect_color_profile(&icc),
+            Some(decode::ColorProfile::AdobeRgb)
+        );
+
+        // A file already in Adobe RGB is cropped losslessly
+        let tagged = encode::encode_image(&image, &adobe).unwrap();
+        let lossless = export_jpeg_bytes(&tagged, &prepared, &adobe, true).unwrap();
+        let expected = encode::crop_jpeg_lossless(&tagged, state.crop.unwrap()).unwrap();
+        assert_eq!(lossless, expected.bytes);
+
+        // PNG is always re-encoded
+        let png = export_jpeg_bytes(&srgb, &prepared, &ExportFormat::png(), true).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+
+    #[test]
+    fn test_export_jpeg_bytes_falls_back() {
+        let image = noise(32, 32, 6);
+        let bytes = encode::encode_jpeg(&image.pixels, 32, 32, 90).unwrap();
+        let decoded = decode::decode_jpeg(&bytes).unwrap();
+
+        // More than a crop
+        let prepared = PreparedEdit::new(&edited_state()).unwrap();
+        assert_eq!(
+            export_jpeg_bytes(&bytes, &prepared, &ExportFormat::jpeg(90), true).unwrap(),
+            export_prepared_decoded(&decoded, &prepared, &ExportFormat::jpeg(90)).unwrap()
+        );
+
+        // Progressive files are re-encoded; patching the frame marker is
+        // enough for the lossless path to refuse them
+        let mut state = EditState::default();
+        state.crop = edited_state().crop;
+        let prepared = PreparedEdit::new(&state).unwrap();
+        let mut progressive = bytes.clone();
+        let sof = progressive
+            .windows(2)
+            .position(|w| w == [0xFF, 0xC0])
+            .unwrap();
+        progressive[sof + 1] = 0xC2;
+        let fallback = match decode::decode_jpeg(&progressive) {
+            Ok(decoded) => export_prepared_decoded(&decoded, &prepared, &ExportFormat::jpeg(90)),
+            Err(e) => Err(e.to_string()),
+        };
+        assert_eq!(
+            export_jpeg_bytes(&progressive, &prepared, &ExportFormat::jpeg(90), true),
+            fallback
+        );
+    }
+
+    #[test]
+    fn test_for_display() {
+        let image = noise(6, 4, 7);
+        assert_eq!(for_display(image.clone(), None).pixels, image.pixels);
+        assert_eq!(for_display(image.clone(), Some(0)).pixels, image.pixels);
+
+        let mut expected = image.pixels.clone();
+        decode::convert_for_display(&mut expected, decode::DisplayColorSpace::DisplayP3);
+        assert_eq!(for_display(image, Some(1)).pixels, expected);
+    }
+
+    #[test]
+    fn test_preflight_matches_resize_for_export() {
+        use crate::compose::{compose_for_export, JsExportOptions};
+
+        let image = noise(90, 70, 5);
+        let state = edited_state();
+        let prepared = PreparedEdit::new(&state).unwrap();
+        let rendered = render_prepared(&image, &prepared).unwrap();
+        for (max_edge, pad_aspect, border_px) in [
+            (None, None, None),
+            (Some(32), None, None),
+            (Some(48), Some(1.0), Some(4)),
+            (None, Some(16.0 / 9.0), None),
+        ] {
+            let options = JsExportOptions {
+                max_edge,
+                pad_aspect,
+                border_px,
+                watermark_text: Some("© Literoom".to_string()),
+                ..Default::default()
+            };
+            let output = compose_for_export(rendered.clone(), &options).unwrap();
+            let preflight = core_preflight_export(
+                90,
+                70,
+                &prepared,
+                &ExportPreflightOptions {
+                    max_edge,
+                    pad_aspect,
+                    border_px,
+                    lossless_when_possible: false,
+                },
+                &ExportFormat::default(),
+                Some(&image),
+            )
+            .unwrap();
+            assert_eq!(
+                (preflight.output_width, preflight.output_height),
+                (output.width, output.height)
+            );
+        }
+    }
+}
+
+/// WASM-specific tests that require JsValue.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use literoom_core::testing::noise;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_prepared_edit_ignores_later_changes() {
+        let adjustments = js_sys::Object::new();
+        js_sys::Reflect::set(&adjustments, &"exposure".into(), &0.5.into()).unwrap();
+        let state = js_sys::Object::new();
+        js_sys::Reflect::set(&state, &"adjustments".into(), &adjustments).unwrap();
+
+        let prepared = prepare_edit(state.clone().into()).unwrap();
+        let image = JsDecodedImage::new(8, 8, vec![100; 8 * 8 * 3]);
+        let before = apply_prepared_edit(&image, &prepared, None).unwrap();
+
+        js_sys::Reflect::set(&adjustments, &"exposure".into(), &(-2.0).into()).unwrap();
+        let after = apply_prepared_edit(&image, &prepared, None).unwrap();
+        assert_eq!(after.pixels(), before.pixels());
+        assert!(before.pixels()[0] > 100);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_from_cached_stage_matches_full_render() {
+        let adjustments = js_sys::Object::new();
+        js_sys::Reflect::set(&adjustments, &"exposure".into(), &0.5.into()).unwrap();
+        let state = js_sys::Object::new();
+        js_sys::Reflect::set(&state, &"adjustments".into(), &adjustments).unwrap();
+        js_sys::Reflect::set(&state, &"monochrome".into(), &true.into()).unwrap();
+
+        let image = JsDecodedImage::from_decoded(noise(16, 12, 3));
+        let cached = render_to_stage(&image, "curve", state.clone().into()).unwrap();
+        let resumed = render_from_stage(&cached, "curve", state.clone().into(), None).unwrap();
+        let full = render_from_stage(&image, "transform", state.clone().into(), None).unwrap();
+        assert_eq!(resumed.pixels(), full.pixels());
+        assert!(render_from_stage(&image, "tone", state.into(), None).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_prepare_edit_rejects_out_of_range_adjustments() {
+        let adjustments = js_sys::Object::new();
+        js_sys::Reflect::set(&adjustments, &"exposure".into(), &99.0.into()).unwrap();
+        let state = js_sys::Object::new();
+        js_sys::Reflect::set(&state, &"adjustments".into(), &adjustments).unwrap();
+        assert!(prepare_edit(state.into()).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_prepared_edit_with_toggles_skips_stages() {
+        let adjustments = js_sys::Object::new();
+        js_sys::Reflect::set(&adjustments, &"exposure".into(), &0.5.into()).unwrap();
+        let state = js_sys::Object::new();
+        js_sys::Reflect::set(&state, &"adjustments".into(), &adjustments).unwrap();
+        let toggles = js_sys::Object::new();
+        js_sys::Reflect::set(&toggles, &"tone".into(), &false.into()).unwrap();
+
+        let image = JsDecodedImage::new(8, 8, vec![100; 8 * 8 * 3]);
+        let prepared = prepare_edit_with_toggles(state.clone().into(), toggles.into()).unwrap();
+        let off = apply_prepared_edit(&image, &prepared, None).unwrap();
+        assert_eq!(off.pixels(), image.pixels());
+
+        let prepared =
+            prepare_edit_with_toggles(state.into(), js_sys::Object::new().into()).unwrap();
+        let on = apply_prepared_edit(&image, &prepared, None).unwrap();
+        assert!(on.pixels()[0] > 100);
+
+        let invalid = js_sys::Object::new();
+        js_sys::Reflect::set(&invalid, &"masks".into(), &"no".into()).unwrap();
+        assert!(prepare_edit_with_toggles(js_sys::Object::new().into(), invalid.into()).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_export_prepared_with_taps() {
+        let prepared = prepare_edit(js_sys::Object::new().into()).unwrap();
+        let image = JsDecodedImage::from_decoded(literoom_core::testing::noise(40, 30, 4));
+        let taps = js_sys::Object::new();
+        js_sys::Reflect::set(&taps, &"want_histogram".into(), &true.into()).unwrap();
+        js_sys::Reflect::set(&taps, &"thumbnail_size".into(), &20.into()).unwrap();
+
+        let result =
+            export_prepared_with_taps(&image, &prepared, JsValue::UNDEFINED, taps.into()).unwrap();
+        assert_eq!(
+            result.bytes(),
+            export_prepared(&image, &prepared, JsValue::UNDEFINED).unwrap()
+        );
+        let thumbnail = result.thumbnail().unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (20, 15));
+        let total: u32 = result.histogram().unwrap().red().iter().sum();
+        assert_eq!(total, 40 * 30);
+
+        let plain =
+            export_prepared_with_taps(&image, &prepared, JsValue::UNDEFINED, JsValue::UNDEFINED)
+                .unwrap();
+        assert!(plain.histogram().is_none() && plain.thumbnail().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_preflight_export() {
+        let state: JsValue = js_sys::Object::new().into();
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"max_edge".into(), &50.into()).unwrap();
+        let result = preflight_export(200, 100, state.clone(), options.clone().into()).unwrap();
+        let get = |key: &str| js_sys::Reflect::get(&result, &key.into()).unwrap();
+        assert_eq!(get("output_width").as_f64(), Some(50.0));
+        assert_eq!(get("output_height").as_f64(), Some(25.0));
+        assert_eq!(get("will_resize").as_bool(), Some(true));
+
+        let format = js_sys::Object::new();
+        js_sys::Reflect::set(&format, &"bit_depth".into(), &16.into()).unwrap();
+        js_sys::Reflect::set(&options, &"format".into(), &format).unwrap();
+        let err = preflight_export(200, 100, state.clone(), options.into())
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().unwrap(), "UNSUPPORTED_BIT_DEPTH");
+
+        let err = preflight_export(0, 100, state, JsValue::UNDEFINED)
+            .err()
+            .unwrap();
+        let code = js_sys::Reflect::get(&err, &"code".into()).unwrap();
+        assert_eq!(code.as_string().unwrap(), "EMPTY_IMAGE");
+    }
+}
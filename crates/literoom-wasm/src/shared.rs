@@ -0,0 +1,402 @@
+//! RGB images backed by a `SharedArrayBuffer`.
+//!
+//! Transferring a decoded preview between workers with `postMessage` copies
+//! its pixels every time. A [`JsSharedImage`] keeps them in a
+//! `SharedArrayBuffer` instead, so a worker can compute the next edit into
+//! it while the main thread paints from the same memory.
+//!
+//! # Layout
+//!
+//! The buffer starts with a 4-byte header holding the generation counter
+//! (an `Int32Array` element at index 0), followed by the RGB pixels
+//! (3 bytes per pixel, row-major) at byte offset [`HEADER_BYTES`].
+//!
+//! # Safety model
+//!
+//! Rust never holds a reference into the shared memory. WASM only touches
+//! the buffer inside a call: an operation copies the pixels into its own
+//! memory, runs the same code as the matching in-place entry point, and
+//! copies the result back. There is no Rust-side aliasing to protect, but
+//! JavaScript readers can still observe a frame half-written, so writes
+//! are bracketed by the generation counter like a seqlock:
+//!
+//! - the counter is odd while a write is in progress and even otherwise,
+//!   and advances by 2 per operation;
+//! - a reader loads the counter, copies the pixels, and loads it again,
+//!   retrying if it was odd or has changed.
+//!
+//! Only one thread may run operations on a given buffer at a time; any
+//! number may read.
+//!
+//! # Example
+//!
+//! ```typescript
+//! // Main thread
+//! const { image, buffer } = create_shared_image(width, height);
+//! worker.postMessage({ buffer, width, height });
+//!
+//! // Worker
+//! const image = shared_image_from_buffer(buffer, width, height);
+//! image.apply_adjustments(adj);
+//!
+//! // Main thread, every frame
+//! const gen = new Int32Array(buffer, 0, 1);
+//! const g = Atomics.load(gen, 0);
+//! if (g !== lastPainted && g % 2 === 0) {
+//!   const frame = image.snapshot();
+//!   paint(frame);
+//!   lastPainted = g;
+//! }
+//! ```
+
+use crate::adjustments::{apply_adjustments_in_place, BasicAdjustments};
+use crate::mask::apply_masked_adjustments_in_place;
+use crate::types::{image_error_to_js, JsDecodedImage};
+use js_sys::{Atomics, Int32Array, SharedArrayBuffer, Uint8Array};
+use literoom_core::validate::{checked_buffer_len, ImageError, MAX_PIXELS};
+use wasm_bindgen::prelude::*;
+
+/// Bytes before the pixels: the generation counter.
+pub const HEADER_BYTES: u32 = 4;
+
+/// Reads attempted by [`JsSharedImage::snapshot`] before giving up.
+const SNAPSHOT_ATTEMPTS: u32 = 64;
+
+/// Total buffer length for a `width x height` shared image.
+fn shared_byte_length(width: u32, height: u32) -> Result<u32, ImageError> {
+    let pixel_len = checked_buffer_len(width, height, 3)?;
+    u32::try_from(pixel_len)
+        .ok()
+        .and_then(|len| len.checked_add(HEADER_BYTES))
+        .ok_or(ImageError::DimensionOverflow {
+            width,
+            height,
+            max_pixels: MAX_PIXELS,
+        })
+}
+
+/// Check that a buffer of `byte_len` bytes holds a `width x height` image.
+///
+/// Sizes in the error are pixel bytes, excluding the header, so they match
+/// the errors of `JsDecodedImage`.
+fn validate_shared_length(byte_len: u32, width: u32, height: u32) -> Result<(), ImageError> {
+    let expected = shared_byte_length(width, height)?;
+    if byte_len != expected {
+        return Err(ImageError::BufferSizeMismatch {
+            width,
+            height,
+            expected: (expected - HEADER_BYTES) as usize,
+            actual: byte_len.saturating_sub(HEADER_BYTES) as usize,
+        });
+    }
+    Ok(())
+}
+
+/// An RGB image whose pixels live in a `SharedArrayBuffer`.
+///
+/// See the [module documentation](self) for the layout and safety model.
+#[wasm_bindgen]
+pub struct JsSharedImage {
+    width: u32,
+    height: u32,
+    buffer: SharedArrayBuffer,
+    generation: Int32Array,
+    pixels: Uint8Array,
+}
+
+#[wasm_bindgen]
+impl JsSharedImage {
+    /// Get the image width in pixels
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the image height in pixels
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the number of pixel bytes (width * height * 3), excluding the
+    /// header
+    #[wasm_bindgen(getter)]
+    pub fn byte_length(&self) -> usize {
+        self.pixels.length() as usize
+    }
+
+    /// Get the byte offset of the pixels in the buffer
+    #[wasm_bindgen(getter)]
+    pub fn pixel_offset(&self) -> u32 {
+        HEADER_BYTES
+    }
+
+    /// Get the backing `SharedArrayBuffer`, to post to another worker
+    #[wasm_bindgen(getter)]
+    pub fn buffer(&self) -> SharedArrayBuffer {
+        self.buffer.clone()
+    }
+
+    /// Get the generation counter: odd while a write is in progress, and
+    /// advanced by 2 by every operation.
+    #[wasm_bindgen(getter)]
+    pub fn generation(&self) -> i32 {
+        Atomics::load(&self.generation, 0).unwrap_or(0)
+    }
+
+    /// Copy out a consistent frame.
+    ///
+    /// Retries while a write is in progress or lands during the copy.
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with `code` `"SHARED_IMAGE_BUSY"` if no untorn
+    /// frame could be read after repeated attempts.
+    pub fn snapshot(&self) -> Result<JsDecodedImage, JsValue> {
+        let mut pixels = vec![0u8; self.byte_length()];
+        for _ in 0..SNAPSHOT_ATTEMPTS {
+            let before = self.generation();
+            if before % 2 != 0 {
+                continue;
+            }
+            self.pixels.copy_to(&mut pixels);
+            if self.generation() == before {
+                return Ok(JsDecodedImage::new(self.width, self.height, pixels));
+            }
+        }
+        let err = js_sys::Error::new("Shared image is being written");
+        let _ = js_sys::Reflect::set(&err, &"code".into(), &"SHARED_IMAGE_BUSY".into());
+        Err(err.into())
+    }
+
+    /// Replace the pixels with those of `image`.
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with `code` `"BUFFER_SIZE_MISMATCH"` if `image`
+    /// has different dimensions, or the validation errors of `image`.
+    pub fn write_image(&self, image: &JsDecodedImage) -> Result<(), JsValue> {
+        image.validate()?;
+        if image.width() != self.width || image.height() != self.height {
+            return Err(image_error_to_js(&ImageError::BufferSizeMismatch {
+                width: self.width,
+                height: self.height,
+                expected: self.byte_length(),
+                actual: image.byte_length(),
+            }));
+        }
+        self.write(image.as_decoded().pixels.as_slice());
+        Ok(())
+    }
+
+    /// Apply all adjustments in place, as `apply_adjustments_in_place`.
+    ///
+    /// # Errors
+    /// Same as `apply_adjustments_in_place`; on error the pixels and the
+    /// generation are unchanged.
+    pub fn apply_adjustments(
+        &self,
+        adjustments: &BasicAdjustments,
+        clamp: Option<bool>,
+    ) -> Result<(), JsValue> {
+        self.update(|image| apply_adjustments_in_place(image, adjustments, clamp))
+    }
+
+    /// Apply masked adjustments in place, as
+    /// `apply_masked_adjustments_in_place`.
+    ///
+    /// # Errors
+    /// Same as `apply_masked_adjustments_in_place`; on error the pixels and
+    /// the generation are unchanged.
+    pub fn apply_masked_adjustments(
+        &self,
+        mask_data: JsValue,
+        clamp: Option<bool>,
+        solo_id: Option<String>,
+        bypass_ids: Option<Vec<String>>,
+    ) -> Result<(), JsValue> {
+        self.update(|image| {
+            apply_masked_adjustments_in_place(image, mask_data, clamp, solo_id, bypass_ids)
+        })
+    }
+}
+
+impl JsSharedImage {
+    fn from_buffer_unchecked(buffer: SharedArrayBuffer, width: u32, height: u32) -> Self {
+        let pixel_len = buffer.byte_length() - HEADER_BYTES;
+        Self {
+            width,
+            height,
+            generation: Int32Array::new_with_byte_offset_and_length(&buffer, 0, 1),
+            pixels: Uint8Array::new_with_byte_offset_and_length(&buffer, HEADER_BYTES, pixel_len),
+            buffer,
+        }
+    }
+
+    /// Run an in-place operation on a copy of the pixels and publish the
+    /// result if it succeeds.
+    fn update(
+        &self,
+        op: impl FnOnce(&mut JsDecodedImage) -> Result<(), JsValue>,
+    ) -> Result<(), JsValue> {
+        let mut image = JsDecodedImage::new(self.width, self.height, self.pixels.to_vec());
+        op(&mut image)?;
+        self.write(image.as_decoded().pixels.as_slice());
+        Ok(())
+    }
+
+    /// Copy `pixels` into the buffer between two generation increments.
+    fn write(&self, pixels: &[u8]) {
+        // Atomics on an Int32Array view cannot fail
+        let _ = Atomics::add(&self.generation, 0, 1);
+        self.pixels.copy_from(pixels);
+        let _ = Atomics::add(&self.generation, 0, 1);
+    }
+}
+
+/// Create a black shared image.
+///
+/// # Returns
+/// `{ image, buffer }`: the `JsSharedImage` and its `SharedArrayBuffer`
+/// (header plus `width * height * 3` pixel bytes).
+///
+/// # Errors
+///
+/// Throws an `Error` with `code` `"EMPTY_IMAGE"`, `"TOO_LARGE"` or
+/// `"DIMENSION_OVERFLOW"` for invalid dimensions, like `JsDecodedImage`.
+#[wasm_bindgen]
+pub fn create_shared_image(width: u32, height: u32) -> Result<JsValue, JsValue> {
+    let byte_len = shared_byte_length(width, height).map_err(|e| image_error_to_js(&e))?;
+    let buffer = SharedArrayBuffer::new(byte_len);
+    let image = JsSharedImage::from_buffer_unchecked(buffer.clone(), width, height);
+
+    let result = js_sys::Object::new();
+    // Setting properties on a fresh object cannot fail
+    let _ = js_sys::Reflect::set(&result, &"image".into(), &image.into());
+    let _ = js_sys::Reflect::set(&result, &"buffer".into(), &buffer);
+    Ok(result.into())
+}
+
+/// Wrap a buffer from [`create_shared_image`], e.g. one posted to a worker.
+///
+/// # Errors
+///
+/// Throws an `Error` with `code` `"BUFFER_SIZE_MISMATCH"` if the buffer
+/// length does not match the dimensions, or the dimension errors of
+/// [`create_shared_image`].
+#[wasm_bindgen]
+pub fn shared_image_from_buffer(
+    buffer: SharedArrayBuffer,
+    width: u32,
+    height: u32,
+) -> Result<JsSharedImage, JsValue> {
+    validate_shared_length(buffer.byte_length(), width, height)
+        .map_err(|e| image_error_to_js(&e))?;
+    Ok(JsSharedImage::from_buffer_unchecked(buffer, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_length_includes_header() {
+        assert_eq!(shared_byte_length(4, 3).unwrap(), HEADER_BYTES + 36);
+    }
+
+    #[test]
+    fn test_dimension_errors_match_normal_image() {
+        assert!(matches!(
+            shared_byte_length(0, 3),
+            Err(ImageError::EmptyImage { .. })
+        ));
+        assert!(matches!(
+            shared_byte_length(u32::MAX, 1),
+            Err(ImageError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_length_mismatch_reports_pixel_bytes() {
+        assert!(validate_shared_length(HEADER_BYTES + 36, 4, 3).is_ok());
+        match validate_shared_length(HEADER_BYTES + 30, 4, 3) {
+            Err(ImageError::BufferSizeMismatch {
+                expected, actual, ..
+            }) => assert_eq!((expected, actual), (36, 30)),
+            other => panic!("expected a size mismatch, got {:?}", other),
+        }
+        assert!(validate_shared_length(0, 4, 3).is_err());
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn created(width: u32, height: u32) -> (JsSharedImage, SharedArrayBuffer) {
+        let result = create_shared_image(width, height).unwrap();
+        let buffer: SharedArrayBuffer = js_sys::Reflect::get(&result, &"buffer".into())
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+        let image = shared_image_from_buffer(buffer.clone(), width, height).unwrap();
+        (image, buffer)
+    }
+
+    #[wasm_bindgen_test]
+    fn test_writes_are_visible_through_the_buffer() {
+        let (image, buffer) = created(2, 1);
+        let source = JsDecodedImage::new(2, 1, vec![10, 20, 30, 40, 50, 60]);
+        image.write_image(&source).unwrap();
+
+        let view = Uint8Array::new_with_byte_offset(&buffer, HEADER_BYTES);
+        assert_eq!(view.to_vec(), vec![10, 20, 30, 40, 50, 60]);
+        assert_eq!(image.snapshot().unwrap().pixels(), view.to_vec());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_generation_advances_per_operation() {
+        let (image, buffer) = created(2, 2);
+        let counter = Int32Array::new_with_byte_offset_and_length(&buffer, 0, 1);
+        assert_eq!(Atomics::load(&counter, 0).unwrap(), 0);
+
+        let mut adj = BasicAdjustments::new();
+        adj.set_exposure(1.0);
+        image.apply_adjustments(&adj, None).unwrap();
+        assert_eq!(Atomics::load(&counter, 0).unwrap(), 2);
+        image.apply_adjustments(&adj, None).unwrap();
+        assert_eq!(image.generation(), 4);
+
+        // A failed operation does not publish anything
+        adj.set_exposure(f32::NAN);
+        assert!(image.apply_adjustments(&adj, None).is_err());
+        assert_eq!(image.generation(), 4);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validation_matches_normal_image() {
+        let code = |err: JsValue| {
+            js_sys::Reflect::get(&err, &"code".into())
+                .unwrap()
+                .as_string()
+                .unwrap()
+        };
+        assert_eq!(
+            code(create_shared_image(0, 4).err().unwrap()),
+            "EMPTY_IMAGE"
+        );
+
+        let buffer = SharedArrayBuffer::new(HEADER_BYTES + 10);
+        let err = shared_image_from_buffer(buffer, 2, 2).err().unwrap();
+        assert_eq!(code(err), "BUFFER_SIZE_MISMATCH");
+
+        let (image, _) = created(2, 2);
+        let wrong = JsDecodedImage::new(1, 1, vec![0, 0, 0]);
+        let err = image.write_image(&wrong).err().unwrap();
+        assert_eq!(code(err), "BUFFER_SIZE_MISMATCH");
+    }
+}
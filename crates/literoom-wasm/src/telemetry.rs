@@ -0,0 +1,309 @@
+//! Opt-in reporting of slow operations.
+//!
+//! Once configured, every top-level core operation is timed with the browser
+//! clock, and those slower than a threshold are passed to a JavaScript
+//! callback, for a sampled fraction of them. Nothing is measured or kept
+//! until [`configure_telemetry`] is called.
+//!
+//! Operations are picked up through the core performance counters'
+//! observer, so every binding that runs a core operation is covered without
+//! wrapping it here. Bindings can add a short description of their
+//! parameters with [`describe`].
+//!
+//! # Functions
+//!
+//! - [`configure_telemetry`] - Start (or stop) reporting slow operations
+//!
+//! # Example
+//!
+//! ```typescript
+//! import { configure_telemetry } from '@literoom/wasm';
+//!
+//! configure_telemetry({
+//!   sample_rate: 0.1,
+//!   slow_threshold_ms: 250,
+//!   callback: (event) => report(`${event.op} took ${event.ms}ms`, event),
+//! });
+//! ```
+
+use literoom_core::perf::{self, Counters};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    static TELEMETRY: RefCell<Option<Telemetry>> = const { RefCell::new(None) };
+}
+
+/// A slow operation, as passed to the telemetry callback.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsTelemetryEvent {
+    /// Name of the top-level operation (e.g. "resize")
+    pub op: String,
+    /// Wall time in milliseconds
+    pub ms: f64,
+    /// Width of the image the operation worked on, if it reported one
+    pub width: Option<u32>,
+    /// Height of the image the operation worked on, if it reported one
+    pub height: Option<u32>,
+    /// Parameters of the call, or an empty string if the binding gives none
+    pub params_summary: String,
+}
+
+/// Picks which slow operations are reported.
+///
+/// Sampling is deterministic: each slow operation adds `rate` to a credit,
+/// and one is reported whenever the credit reaches 1. A rate of 0.25 reports
+/// every fourth slow operation, 1.0 all of them and 0.0 none.
+#[derive(Debug, Clone, PartialEq)]
+struct Sampler {
+    rate: f64,
+    slow_threshold_ms: f64,
+    credit: f64,
+}
+
+impl Sampler {
+    fn new(rate: f64, slow_threshold_ms: f64) -> Self {
+        Self {
+            rate,
+            slow_threshold_ms,
+            credit: 0.0,
+        }
+    }
+
+    /// Whether an operation that took `ms` should be reported.
+    fn should_report(&mut self, ms: f64) -> bool {
+        if ms < self.slow_threshold_ms {
+            return false;
+        }
+        self.credit += self.rate;
+        if self.credit >= 1.0 {
+            self.credit -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Configured telemetry state.
+struct Telemetry {
+    sampler: Sampler,
+    callback: js_sys::Function,
+    /// Parameters of the binding currently running, from [`describe`]
+    params_summary: Option<String>,
+}
+
+/// Start or stop reporting slow operations.
+///
+/// # Arguments
+///
+/// * `options` - `{ sample_rate, slow_threshold_ms, callback }`, or `null`
+///   to stop reporting. `sample_rate` is the fraction of slow operations to
+///   report (0.0 to 1.0); `slow_threshold_ms` is the wall time from which an
+///   operation counts as slow.
+///
+/// The callback receives `{ op, ms, width, height, params_summary }` right
+/// after the operation finishes. `width` and `height` are those of the image
+/// the operation worked on (the source image for a resize) and are
+/// `undefined` for operations that do not report one. Errors thrown by the
+/// callback are ignored.
+///
+/// # Errors
+///
+/// Returns an error if `sample_rate` is not between 0.0 and 1.0,
+/// `slow_threshold_ms` is negative or not a number, or `callback` is not a
+/// function. The previous configuration is kept in that case.
+///
+/// # Example
+///
+/// ```typescript
+/// configure_telemetry({ sample_rate: 1.0, slow_threshold_ms: 500, callback: console.warn });
+/// // ...
+/// configure_telemetry(null);
+/// ```
+#[wasm_bindgen]
+pub fn configure_telemetry(options: JsValue) -> Result<(), JsValue> {
+    if options.is_null() || options.is_undefined() {
+        perf::set_observer(None);
+        TELEMETRY.with(|t| *t.borrow_mut() = None);
+        return Ok(());
+    }
+
+    let field = |name: &str| js_sys::Reflect::get(&options, &name.into());
+    let sample_rate = field("sample_rate")?
+        .as_f64()
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .ok_or_else(|| JsValue::from_str("Invalid telemetry sample_rate: expected 0.0 to 1.0"))?;
+    let slow_threshold_ms = field("slow_threshold_ms")?
+        .as_f64()
+        .filter(|ms| *ms >= 0.0)
+        .ok_or_else(|| {
+            JsValue::from_str("Invalid telemetry slow_threshold_ms: expected a non-negative number")
+        })?;
+    let callback: js_sys::Function = field("callback")?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("Invalid telemetry callback: expected a function"))?;
+
+    TELEMETRY.with(|t| {
+        *t.borrow_mut() = Some(Telemetry {
+            sampler: Sampler::new(sample_rate, slow_threshold_ms),
+            callback,
+            params_summary: None,
+        })
+    });
+    #[cfg(target_arch = "wasm32")]
+    perf::set_timer(Some(crate::perf::now_ms));
+    perf::set_observer(Some(observe));
+    Ok(())
+}
+
+/// Guard returned by [`describe`]. Clears the description when dropped.
+#[must_use = "the description is cleared as soon as the guard is dropped"]
+pub(crate) struct Described {
+    _private: (),
+}
+
+impl Drop for Described {
+    fn drop(&mut self) {
+        TELEMETRY.with(|t| {
+            if let Some(telemetry) = t.borrow_mut().as_mut() {
+                telemetry.params_summary = None;
+            }
+        });
+    }
+}
+
+/// Describe the parameters of the binding about to run a core operation.
+///
+/// The description becomes the `params_summary` of the operations that
+/// finish while the guard is alive. `summary` is only called when telemetry
+/// is configured.
+pub(crate) fn describe(summary: impl FnOnce() -> String) -> Described {
+    TELEMETRY.with(|t| {
+        if let Some(telemetry) = t.borrow_mut().as_mut() {
+            telemetry.params_summary = Some(summary());
+        }
+    });
+    Described { _private: () }
+}
+
+/// Core observer: reports the finished operation if it is slow and sampled.
+fn observe(counters: &Counters) {
+    let Some(ms) = counters.elapsed_ms else {
+        return;
+    };
+    let report = TELEMETRY.with(|t| {
+        let mut t = t.borrow_mut();
+        let telemetry = t.as_mut()?;
+        if !telemetry.sampler.should_report(ms) {
+            return None;
+        }
+        let event = JsTelemetryEvent {
+            op: counters.operation.to_string(),
+            ms,
+            width: counters.dimensions.map(|(w, _)| w),
+            height: counters.dimensions.map(|(_, h)| h),
+            params_summary: telemetry.params_summary.clone().unwrap_or_default(),
+        };
+        Some((telemetry.callback.clone(), event))
+    });
+    // Called after the borrow ends so the callback may run other operations
+    if let Some((callback, event)) = report {
+        if let Ok(value) = serde_wasm_bindgen::to_value(&event) {
+            let _ = callback.call1(&JsValue::NULL, &value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::JsDecodedImage;
+
+    #[test]
+    fn test_full_rate_reports_every_slow_operation() {
+        let mut sampler = Sampler::new(1.0, 0.0);
+        assert!((0..10).all(|_| sampler.should_report(0.0)));
+    }
+
+    #[test]
+    fn test_zero_rate_never_reports() {
+        let mut sampler = Sampler::new(0.0, 0.0);
+        assert!((0..10).all(|_| !sampler.should_report(1000.0)));
+    }
+
+    #[test]
+    fn test_fast_operations_are_not_sampled() {
+        let mut sampler = Sampler::new(0.5, 100.0);
+        let reported: Vec<bool> = [50.0, 150.0, 20.0, 150.0, 150.0, 150.0]
+            .iter()
+            .map(|&ms| sampler.should_report(ms))
+            .collect();
+        assert_eq!(reported, [false, false, false, true, false, true]);
+    }
+
+    #[test]
+    fn test_unconfigured_operations_are_unchanged() {
+        let _params = describe(|| unreachable!("summary built without telemetry"));
+        let image = JsDecodedImage::new(4, 4, vec![128; 4 * 4 * 3]);
+        let resized = crate::decode::resize(&image, 2, 2, 1).unwrap();
+        assert_eq!((resized.width(), resized.height()), (2, 2));
+        assert!(TELEMETRY.with(|t| t.borrow().is_none()));
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use crate::types::JsDecodedImage;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Configure telemetry with a callback that collects events into an array.
+    fn collect_events(sample_rate: f64) -> js_sys::Array {
+        let events = js_sys::Array::new();
+        let callback = js_sys::Function::new_with_args("event", "this.push(event)").bind(&events);
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"sample_rate".into(), &sample_rate.into()).unwrap();
+        js_sys::Reflect::set(&options, &"slow_threshold_ms".into(), &0.0.into()).unwrap();
+        js_sys::Reflect::set(&options, &"callback".into(), &callback).unwrap();
+        configure_telemetry(options.into()).unwrap();
+        events
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resize_reports_source_dimensions() {
+        let events = collect_events(1.0);
+        let image = JsDecodedImage::new(8, 6, vec![128; 8 * 6 * 3]);
+        crate::decode::resize(&image, 4, 3, 1).unwrap();
+        configure_telemetry(JsValue::NULL).unwrap();
+
+        assert_eq!(events.length(), 1);
+        let event: JsTelemetryEvent = serde_wasm_bindgen::from_value(events.get(0)).unwrap();
+        assert_eq!(event.op, "resize");
+        assert_eq!((event.width, event.height), (Some(8), Some(6)));
+        assert!(event.ms >= 0.0);
+        assert!(event.params_summary.contains("4x3"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_zero_rate_never_calls_back() {
+        let events = collect_events(0.0);
+        let image = JsDecodedImage::new(8, 6, vec![128; 8 * 6 * 3]);
+        for _ in 0..5 {
+            crate::decode::resize(&image, 4, 3, 1).unwrap();
+        }
+        configure_telemetry(JsValue::NULL).unwrap();
+        assert_eq!(events.length(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_invalid_options_are_rejected() {
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"sample_rate".into(), &2.0.into()).unwrap();
+        assert!(configure_telemetry(options.into()).is_err());
+        assert!(TELEMETRY.with(|t| t.borrow().is_none()));
+    }
+}
@@ -1,14 +1,45 @@
 //! WASM bindings for image transformation operations.
 //!
 //! This module provides JavaScript bindings for rotation and crop operations,
-//! enabling the preview and export pipelines to apply transforms.
+//! enabling the preview and export pipelines to apply transforms, plus the
+//! guided upright solver for perspective correction, the coordinate
+//! mappings between a photo and its rotated version, and the aspect-locked
+//! crop drag solver.
 
 use crate::types::JsDecodedImage;
+use literoom_core::edit::CropRect;
 use literoom_core::transform::{
-    apply_crop as core_crop, apply_rotation as core_rotate, InterpolationFilter,
+    apply_crop as core_crop, apply_crop_with_fill as core_crop_with_fill,
+    apply_rotation as core_rotate, rotation_forward_map as core_forward_map,
+    rotation_inverse_map as core_inverse_map, solve_constrained_crop as core_constrained_crop,
+    solve_guided_transform as core_solve, CropHandle, GuideLine, InterpolationFilter,
+    PerspectiveParams,
 };
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+/// Guided upright result returned to JavaScript.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsGuidedTransform {
+    /// Solved angles, solved axes, and residual
+    #[serde(flatten)]
+    pub params: PerspectiveParams,
+    /// Row-major 3x3 homography from source to corrected pixels
+    pub homography: Vec<f64>,
+}
+
+impl JsGuidedTransform {
+    fn new(params: PerspectiveParams, width: u32, height: u32) -> Self {
+        let homography = params
+            .homography(width, height)
+            .iter()
+            .flatten()
+            .copied()
+            .collect();
+        Self { params, homography }
+    }
+}
+
 /// Apply rotation to an image.
 ///
 /// The image is rotated around its center. The output canvas is expanded
@@ -25,6 +56,11 @@ use wasm_bindgen::prelude::*;
 /// New `JsDecodedImage` with the rotated content. The dimensions may differ
 /// from the source due to canvas expansion.
 ///
+/// # Errors
+///
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions.
+///
 /// # Example (TypeScript)
 ///
 /// ```typescript
@@ -39,16 +75,80 @@ pub fn apply_rotation(
     image: &JsDecodedImage,
     angle_degrees: f64,
     use_lanczos: bool,
-) -> JsDecodedImage {
-    let src = image.to_decoded();
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    let src = image.as_decoded();
     let filter = if use_lanczos {
         InterpolationFilter::Lanczos3
     } else {
         InterpolationFilter::Bilinear
     };
 
-    let result = core_rotate(&src, angle_degrees, filter);
-    JsDecodedImage::new(result.width, result.height, result.pixels)
+    core_rotate(src, angle_degrees, filter)
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Map a point in the source image to the rotated image of `apply_rotation`.
+///
+/// Coordinates are in pixels, with integer values at pixel centers. The
+/// result is exactly where the resampler puts that source point on the
+/// expanded canvas.
+///
+/// # Arguments
+///
+/// * `x`, `y` - Point in the source image
+/// * `width`, `height` - Source image size
+/// * `angle_degrees` - Rotation angle in degrees (positive = counter-clockwise)
+///
+/// # Returns
+///
+/// `[x, y]` on the rotated canvas.
+///
+/// # Example (TypeScript)
+///
+/// ```typescript
+/// // Keep a heal spot on the same content when the straighten angle changes
+/// const [sx, sy] = rotation_inverse_map(spot.x, spot.y, width, height, oldAngle);
+/// const [nx, ny] = rotation_forward_map(sx, sy, width, height, newAngle);
+/// ```
+#[wasm_bindgen]
+pub fn rotation_forward_map(
+    x: f64,
+    y: f64,
+    width: u32,
+    height: u32,
+    angle_degrees: f64,
+) -> Vec<f64> {
+    let (x, y) = core_forward_map(x, y, width, height, angle_degrees);
+    vec![x, y]
+}
+
+/// Map a point in the rotated image of `apply_rotation` back to the source.
+///
+/// The inverse of [`rotation_forward_map`]. `width` and `height` are the
+/// size of the source image, not the rotated one.
+///
+/// # Returns
+///
+/// `[x, y]` in the source image.
+///
+/// # Example (TypeScript)
+///
+/// ```typescript
+/// // Where did the user click in the unrotated photo?
+/// const [sx, sy] = rotation_inverse_map(clickX, clickY, width, height, angle);
+/// ```
+#[wasm_bindgen]
+pub fn rotation_inverse_map(
+    x: f64,
+    y: f64,
+    width: u32,
+    height: u32,
+    angle_degrees: f64,
+) -> Vec<f64> {
+    let (x, y) = core_inverse_map(x, y, width, height, angle_degrees);
+    vec![x, y]
 }
 
 /// Apply crop to an image using normalized coordinates.
@@ -63,16 +163,28 @@ pub fn apply_rotation(
 /// * `top` - Top edge position (0.0 to 1.0)
 /// * `width` - Crop region width (0.0 to 1.0)
 /// * `height` - Crop region height (0.0 to 1.0)
+/// * `fill` - Optional `[r, g, b]` background. When given, the region may
+///   extend past the image: the output keeps the full region size and
+///   pixels outside the source are set to this color. When omitted, the
+///   region is clamped to the image bounds.
 ///
 /// # Returns
 ///
 /// New `JsDecodedImage` containing only the cropped region.
 ///
+/// # Errors
+///
+/// Throws an `Error` with a `code` property if the image is empty or its
+/// buffer does not match its dimensions. Throws if `fill` is not 3 bytes.
+///
 /// # Example (TypeScript)
 ///
 /// ```typescript
 /// // Crop the center 50% of the image
 /// const cropped = apply_crop(sourceImage, 0.25, 0.25, 0.5, 0.5);
+///
+/// // Keep the full straightened frame with white corners
+/// const framed = apply_crop(rotated, -0.05, -0.05, 1.1, 1.1, new Uint8Array([255, 255, 255]));
 /// ```
 #[wasm_bindgen]
 pub fn apply_crop(
@@ -81,10 +193,137 @@ pub fn apply_crop(
     top: f64,
     width: f64,
     height: f64,
-) -> JsDecodedImage {
-    let src = image.to_decoded();
-    let result = core_crop(&src, left, top, width, height);
-    JsDecodedImage::new(result.width, result.height, result.pixels)
+    fill: Option<Vec<u8>>,
+) -> Result<JsDecodedImage, JsValue> {
+    image.validate()?;
+    let src = image.as_decoded();
+    let result = match fill {
+        None => core_crop(src, left, top, width, height),
+        Some(fill) => {
+            let fill: [u8; 3] = fill.as_slice().try_into().map_err(|_| {
+                JsValue::from_str(&format!("Fill must be [r, g, b], got {} bytes", fill.len()))
+            })?;
+            core_crop_with_fill(src, left, top, width, height, fill)
+        }
+    };
+    result
+        .map(JsDecodedImage::from_decoded)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Solve perspective correction from user-drawn guide lines.
+///
+/// Each guide is a line in source pixels that should become vertical or
+/// horizontal. With a single guide only that axis is solved; the `solved`
+/// field reports which parameters were determined.
+///
+/// # Arguments
+///
+/// * `guides` - Array of `{ x1, y1, x2, y2, target: "vertical" | "horizontal" }`
+/// * `width` - Source image width in pixels
+/// * `height` - Source image height in pixels
+///
+/// # Returns
+///
+/// An object `{ vertical, horizontal, rotate, solved: { vertical, horizontal,
+/// rotation }, residual_degrees, homography }`. Angles are in degrees. A
+/// `residual_degrees` above a degree or so means the guides contradict each
+/// other.
+///
+/// # Errors
+///
+/// Returns an error if the guides cannot be parsed, the list is empty, or a
+/// guide has zero length.
+///
+/// # Example (TypeScript)
+///
+/// ```typescript
+/// const result = solve_guided_transform(
+///   [
+///     { x1: 410, y1: 220, x2: 380, y2: 1800, target: 'vertical' },
+///     { x1: 2630, y1: 230, x2: 2660, y2: 1790, target: 'vertical' },
+///   ],
+///   3000,
+///   2000,
+/// );
+/// if (result.residual_degrees > 1) showGuideWarning();
+/// ```
+#[wasm_bindgen]
+pub fn solve_guided_transform(
+    guides: JsValue,
+    width: u32,
+    height: u32,
+) -> Result<JsValue, JsValue> {
+    let guides: Vec<GuideLine> = serde_wasm_bindgen::from_value(guides)
+        .map_err(|e| JsValue::from_str(&format!("Invalid guides: {}", e)))?;
+
+    let params =
+        core_solve(&guides, width, height).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    serde_wasm_bindgen::to_value(&JsGuidedTransform::new(params, width, height))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
+}
+
+/// Apply a drag of one crop handle with the aspect ratio locked.
+///
+/// The dragged handle moves, the other dimension follows the aspect ratio,
+/// and the opposite edge (at its center) or corner stays in place. A drag
+/// past the rotated photo stops at the largest crop that still fits, so no
+/// empty canvas corners end up in the crop.
+///
+/// # Arguments
+///
+/// * `current` - Crop before the drag, `{ left, top, width, height }`
+///   normalized to the rotated canvas
+/// * `handle` - `"left"`, `"top"`, `"right"`, `"bottom"`, `"top_left"`,
+///   `"top_right"`, `"bottom_right"` or `"bottom_left"`
+/// * `delta` - Movement of the handle, normalized to the canvas: along x
+///   for the left and right edges and the corners, along y for the top and
+///   bottom edges (positive = right or down)
+/// * `aspect` - Locked width / height of the crop in pixels
+/// * `rotation_degrees` - Total rotation (angle + straighten)
+/// * `image_width`, `image_height` - Size of the unrotated photo
+///
+/// # Returns
+///
+/// The new crop, `{ left, top, width, height }`.
+///
+/// # Errors
+///
+/// Returns an error if the crop or handle cannot be parsed.
+///
+/// # Example (TypeScript)
+///
+/// ```typescript
+/// const dx = (event.clientX - dragStartX) / canvasWidth;
+/// crop = solve_constrained_crop(startCrop, 'top_right', dx, 3 / 2, angle + straighten, width, height);
+/// ```
+#[wasm_bindgen]
+pub fn solve_constrained_crop(
+    current: JsValue,
+    handle: JsValue,
+    delta: f32,
+    aspect: f32,
+    rotation_degrees: f32,
+    image_width: u32,
+    image_height: u32,
+) -> Result<JsValue, JsValue> {
+    let current: CropRect = serde_wasm_bindgen::from_value(current)
+        .map_err(|e| JsValue::from_str(&format!("Invalid crop: {}", e)))?;
+    let handle: CropHandle = serde_wasm_bindgen::from_value(handle)
+        .map_err(|e| JsValue::from_str(&format!("Invalid crop handle: {}", e)))?;
+
+    let crop = core_constrained_crop(
+        current,
+        handle,
+        delta,
+        aspect,
+        rotation_degrees,
+        image_width,
+        image_height,
+    );
+    serde_wasm_bindgen::to_value(&crop)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize result: {}", e)))
 }
 
 #[cfg(test)]
@@ -102,7 +341,7 @@ mod tests {
     #[test]
     fn test_rotation_no_change() {
         let img = test_image(100, 100);
-        let result = apply_rotation(&img, 0.0, false);
+        let result = apply_rotation(&img, 0.0, false).unwrap();
         assert_eq!(result.width(), 100);
         assert_eq!(result.height(), 100);
     }
@@ -110,7 +349,7 @@ mod tests {
     #[test]
     fn test_rotation_90_degrees() {
         let img = test_image(100, 50);
-        let result = apply_rotation(&img, 90.0, false);
+        let result = apply_rotation(&img, 90.0, false).unwrap();
         // 90-degree rotation swaps dimensions
         assert_eq!(result.width(), 50);
         assert_eq!(result.height(), 100);
@@ -119,7 +358,7 @@ mod tests {
     #[test]
     fn test_rotation_45_degrees_expands() {
         let img = test_image(100, 100);
-        let result = apply_rotation(&img, 45.0, false);
+        let result = apply_rotation(&img, 45.0, false).unwrap();
         // Diagonal rotation expands the canvas
         assert!(result.width() > 100);
         assert!(result.height() > 100);
@@ -128,18 +367,26 @@ mod tests {
     #[test]
     fn test_rotation_bilinear_vs_lanczos() {
         let img = test_image(50, 50);
-        let bilinear = apply_rotation(&img, 15.0, false);
-        let lanczos = apply_rotation(&img, 15.0, true);
+        let bilinear = apply_rotation(&img, 15.0, false).unwrap();
+        let lanczos = apply_rotation(&img, 15.0, true).unwrap();
 
         // Same dimensions regardless of filter
         assert_eq!(bilinear.width(), lanczos.width());
         assert_eq!(bilinear.height(), lanczos.height());
     }
 
+    #[test]
+    fn test_rotation_maps_round_trip() {
+        let forward = rotation_forward_map(25.0, 40.0, 120, 80, -12.0);
+        let back = rotation_inverse_map(forward[0], forward[1], 120, 80, -12.0);
+        assert!((back[0] - 25.0).abs() < 1e-4);
+        assert!((back[1] - 40.0).abs() < 1e-4);
+    }
+
     #[test]
     fn test_crop_full_image() {
         let img = test_image(100, 100);
-        let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0);
+        let result = apply_crop(&img, 0.0, 0.0, 1.0, 1.0, None).unwrap();
         assert_eq!(result.width(), 100);
         assert_eq!(result.height(), 100);
     }
@@ -147,7 +394,7 @@ mod tests {
     #[test]
     fn test_crop_half() {
         let img = test_image(100, 100);
-        let result = apply_crop(&img, 0.0, 0.0, 0.5, 0.5);
+        let result = apply_crop(&img, 0.0, 0.0, 0.5, 0.5, None).unwrap();
         assert_eq!(result.width(), 50);
         assert_eq!(result.height(), 50);
     }
@@ -155,7 +402,7 @@ mod tests {
     #[test]
     fn test_crop_center() {
         let img = test_image(100, 100);
-        let result = apply_crop(&img, 0.25, 0.25, 0.5, 0.5);
+        let result = apply_crop(&img, 0.25, 0.25, 0.5, 0.5, None).unwrap();
         assert_eq!(result.width(), 50);
         assert_eq!(result.height(), 50);
     }
@@ -163,8 +410,141 @@ mod tests {
     #[test]
     fn test_crop_non_square() {
         let img = test_image(200, 100);
-        let result = apply_crop(&img, 0.0, 0.0, 0.5, 1.0);
+        let result = apply_crop(&img, 0.0, 0.0, 0.5, 1.0, None).unwrap();
         assert_eq!(result.width(), 100);
         assert_eq!(result.height(), 100);
     }
+
+    #[test]
+    fn test_crop_with_fill_extends_past_edge() {
+        let img = test_image(100, 100);
+        let result = apply_crop(&img, 0.5, 0.0, 0.6, 1.0, Some(vec![1, 2, 3])).unwrap();
+        assert_eq!(result.width(), 60);
+        assert_eq!(result.height(), 100);
+        // Last column is outside the source
+        let pixels = result.pixels();
+        assert_eq!(pixels[59 * 3..60 * 3], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_guided_transform_homography_is_row_major() {
+        let params = PerspectiveParams::new(5.0, 0.0, 0.0);
+        let result = JsGuidedTransform::new(params, 300, 200);
+        let h = params.homography(300, 200);
+
+        assert_eq!(result.homography.len(), 9);
+        assert_eq!(result.homography[1], h[0][1]);
+        assert_eq!(result.homography[5], h[1][2]);
+        assert_eq!(result.homography[7], h[2][1]);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use literoom_core::transform::GuideTarget;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn guide(x1: f64, y1: f64, x2: f64, y2: f64, target: GuideTarget) -> GuideLine {
+        GuideLine {
+            x1,
+            y1,
+            x2,
+            y2,
+            target,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_solve_guided_transform_from_js() {
+        let guides = vec![guide(100.0, 500.0, 2900.0, 520.0, GuideTarget::Horizontal)];
+        let value =
+            solve_guided_transform(serde_wasm_bindgen::to_value(&guides).unwrap(), 3000, 2000)
+                .unwrap();
+
+        let result: JsGuidedTransform = serde_wasm_bindgen::from_value(value).unwrap();
+        assert!(result.params.solved.horizontal);
+        assert!(!result.params.solved.vertical);
+        assert_eq!(result.homography.len(), 9);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_solve_guided_transform_rejects_zero_length() {
+        let guides = vec![guide(10.0, 10.0, 10.0, 10.0, GuideTarget::Vertical)];
+        let result =
+            solve_guided_transform(serde_wasm_bindgen::to_value(&guides).unwrap(), 100, 100);
+        assert!(result.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_solve_guided_transform_invalid_input() {
+        assert!(solve_guided_transform(JsValue::from_str("nope"), 100, 100).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_degenerate_images_throw_with_code() {
+        let code = |err: JsValue| js_sys::Reflect::get(&err, &"code".into()).unwrap();
+
+        let empty = JsDecodedImage::new(0, 10, vec![]);
+        let err = apply_rotation(&empty, 15.0, false).err().unwrap();
+        assert_eq!(code(err).as_string().as_deref(), Some("EMPTY_IMAGE"));
+
+        let mismatched = JsDecodedImage::new(4, 4, vec![0u8; 10]);
+        let err = apply_crop(&mismatched, 0.25, 0.25, 0.5, 0.5, None)
+            .err()
+            .unwrap();
+        assert_eq!(
+            code(err).as_string().as_deref(),
+            Some("BUFFER_SIZE_MISMATCH")
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_single_pixel_succeeds() {
+        let pixel = JsDecodedImage::new(1, 1, vec![10, 20, 30]);
+        assert!(apply_rotation(&pixel, 45.0, true).is_ok());
+        assert!(apply_crop(&pixel, 0.5, 0.5, 0.5, 0.5, None).is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_solve_constrained_crop_from_js() {
+        let current = CropRect {
+            left: 0.25,
+            top: 0.25,
+            width: 0.5,
+            height: 0.5,
+        };
+        let value = solve_constrained_crop(
+            serde_wasm_bindgen::to_value(&current).unwrap(),
+            JsValue::from_str("right"),
+            0.1,
+            2.0,
+            0.0,
+            200,
+            100,
+        )
+        .unwrap();
+        let crop: CropRect = serde_wasm_bindgen::from_value(value).unwrap();
+        assert!((crop.width - 0.6).abs() < 1e-6);
+        assert!((crop.left - 0.25).abs() < 1e-6);
+
+        let invalid = solve_constrained_crop(
+            serde_wasm_bindgen::to_value(&current).unwrap(),
+            JsValue::from_str("middle"),
+            0.1,
+            2.0,
+            0.0,
+            200,
+            100,
+        );
+        assert!(invalid.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_crop_fill_must_be_rgb() {
+        let img = JsDecodedImage::new(2, 2, vec![0u8; 12]);
+        assert!(apply_crop(&img, 0.0, 0.0, 2.0, 2.0, Some(vec![0, 0])).is_err());
+    }
 }
@@ -3,7 +3,26 @@
 //! This module provides JavaScript-friendly types that wrap the core Literoom types,
 //! handling the conversion between Rust and JavaScript data representations.
 
-use literoom_core::decode::{DecodedImage, FilterType};
+use crate::pool;
+use literoom_core::adjustments::{StageOrderError, WhiteBalanceMethod};
+use literoom_core::color::ColorModel;
+use literoom_core::compose::{Gravity, WatermarkAnchor};
+use literoom_core::curve::CurveError;
+use literoom_core::decode::{
+    self, CanvasColorSpace, DecodeError, DecodeWarnings, DecodedImage, DecodedImageF32,
+    DisplayColorSpace, FilterType, GrayImage, OpenFileError, QuantizeMethod, ToneMap,
+};
+use literoom_core::encode::ExportFormatError;
+use literoom_core::export_manifest::TemplateError;
+use literoom_core::luminance::LUMA_WEIGHTS;
+use literoom_core::lut3d::LutError;
+use literoom_core::mask::MaskError;
+use literoom_core::recipe::RecipeError;
+use literoom_core::validate::{
+    validate_gray_buffer, validate_rgb_buffer, validate_rgb_f32_buffer, validate_rgba_buffer,
+    ImageError,
+};
+use literoom_core::AdjustmentError;
 use wasm_bindgen::prelude::*;
 
 /// A decoded image wrapper for JavaScript.
@@ -21,9 +40,10 @@ use wasm_bindgen::prelude::*;
 /// optional as wasm-bindgen's finalizer will handle cleanup automatically.
 #[wasm_bindgen]
 pub struct JsDecodedImage {
-    width: u32,
-    height: u32,
-    pixels: Vec<u8>,
+    image: DecodedImage,
+    color_profile: Option<String>,
+    decode_warnings: Option<DecodeWarnings>,
+    color_unmanaged: bool,
 }
 
 #[wasm_bindgen]
@@ -37,28 +57,65 @@ impl JsDecodedImage {
     #[wasm_bindgen(constructor)]
     pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> JsDecodedImage {
         JsDecodedImage {
-            width,
-            height,
-            pixels,
+            // Fields are set directly: an invalid buffer is reported by the
+            // bindings' `validate()` rather than panicking here
+            image: DecodedImage {
+                width,
+                height,
+                pixels,
+            },
+            color_profile: None,
+            decode_warnings: None,
+            color_unmanaged: false,
         }
     }
 
     /// Get the image width in pixels
     #[wasm_bindgen(getter)]
     pub fn width(&self) -> u32 {
-        self.width
+        self.image.width
     }
 
     /// Get the image height in pixels
     #[wasm_bindgen(getter)]
     pub fn height(&self) -> u32 {
-        self.height
+        self.image.height
     }
 
     /// Get the number of bytes in the pixel buffer (width * height * 3 for RGB)
     #[wasm_bindgen(getter)]
     pub fn byte_length(&self) -> usize {
-        self.pixels.len()
+        self.image.pixels.len()
+    }
+
+    /// Get the name of the source's embedded ICC color profile, if any.
+    ///
+    /// Set by `decode_jpeg` for tagged files (e.g. "Adobe RGB (1998)");
+    /// `undefined` for untagged images and derived images.
+    #[wasm_bindgen(getter)]
+    pub fn color_profile(&self) -> Option<String> {
+        self.color_profile.clone()
+    }
+
+    /// Get what the JPEG decoder had to recover from.
+    ///
+    /// `{ rows_decoded, total_rows, truncated, invalid_orientation }` from
+    /// `decode_jpeg_tolerant`, and from `decode_jpeg` when the EXIF
+    /// orientation was outside 1-8; `undefined` otherwise.
+    #[wasm_bindgen(getter)]
+    pub fn decode_warnings(&self) -> JsValue {
+        self.decode_warnings
+            .and_then(|w| serde_wasm_bindgen::to_value(&w).ok())
+            .unwrap_or(JsValue::UNDEFINED)
+    }
+
+    /// Whether the pixels were imported without color management.
+    ///
+    /// Set by `import_canvas_pixels` when the canvas color space was unknown;
+    /// colors may then be off and the UI can say so.
+    #[wasm_bindgen(getter)]
+    pub fn color_unmanaged(&self) -> bool {
+        self.color_unmanaged
     }
 
     /// Returns RGB pixel data as Uint8Array.
@@ -66,7 +123,52 @@ impl JsDecodedImage {
     /// Note: This creates a copy of the pixel data. For large images, this can
     /// take 10-50ms but is necessary for safe memory management.
     pub fn pixels(&self) -> Vec<u8> {
-        self.pixels.clone()
+        self.image.pixels.clone()
+    }
+
+    /// Convert to premultiplied RGBA for direct WebGL texture upload.
+    ///
+    /// Every pixel gets the same `alpha`; color channels are scaled by it in
+    /// the same pass. Use `255` for an opaque texture.
+    ///
+    /// # Example
+    ///
+    /// ```typescript
+    /// const rgba = image.to_rgba_premultiplied(255);
+    /// gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, rgba.width, rgba.height, 0,
+    ///               gl.RGBA, gl.UNSIGNED_BYTE, rgba.pixels());
+    /// ```
+    pub fn to_rgba_premultiplied(&self, alpha: u8) -> JsRgbaImage {
+        JsRgbaImage {
+            width: self.image.width,
+            height: self.image.height,
+            pixels: self.image.to_rgba_premultiplied(alpha),
+        }
+    }
+
+    /// Convert to a single-channel luminance image (BT.709 weights, as the
+    /// histogram and tonal adjustments use).
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with `code` `"EMPTY_IMAGE"` or
+    /// `"BUFFER_SIZE_MISMATCH"` if the buffer does not match the dimensions.
+    pub fn to_gray(&self) -> Result<JsGrayImage, JsValue> {
+        self.validate()?;
+        Ok(JsGrayImage::from_gray(self.image.to_gray(LUMA_WEIGHTS)))
+    }
+
+    /// Convert to a float image where 1.0 is 8-bit white.
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with `code` `"EMPTY_IMAGE"` or
+    /// `"BUFFER_SIZE_MISMATCH"` if the buffer does not match the dimensions.
+    pub fn to_f32(&self) -> Result<JsDecodedImageF32, JsValue> {
+        self.validate()?;
+        Ok(JsDecodedImageF32 {
+            image: self.image.to_f32(),
+        })
     }
 
     /// Explicitly free WASM memory.
@@ -85,24 +187,504 @@ impl JsDecodedImage {
     #[allow(dead_code)] // Used in decode module (Phase 3)
     pub(crate) fn from_decoded(img: DecodedImage) -> Self {
         Self {
-            width: img.width,
-            height: img.height,
-            pixels: img.pixels,
+            image: img,
+            color_profile: None,
+            decode_warnings: None,
+            color_unmanaged: false,
         }
     }
 
+    /// Attach the source color profile name reported by the decoder.
+    pub(crate) fn with_color_profile(mut self, color_profile: Option<String>) -> Self {
+        self.color_profile = color_profile;
+        self
+    }
+
+    /// Attach the warnings reported by the decoder.
+    pub(crate) fn with_decode_warnings(mut self, warnings: DecodeWarnings) -> Self {
+        self.decode_warnings = Some(warnings);
+        self
+    }
+
+    /// Mark the pixels as imported without color management.
+    pub(crate) fn with_color_unmanaged(mut self, color_unmanaged: bool) -> Self {
+        self.color_unmanaged = color_unmanaged;
+        self
+    }
+
+    /// Check that the image is non-empty and its buffer matches its dimensions.
+    ///
+    /// Bindings call this before handing the image to a core operation, so an
+    /// invalid image surfaces as a structured error (see [`image_error_to_js`]).
+    pub(crate) fn validate(&self) -> Result<(), JsValue> {
+        validate_rgb_buffer(&self.image.pixels, self.image.width, self.image.height)
+            .map_err(|e| image_error_to_js(&e))
+    }
+
+    /// Borrow the underlying core DecodedImage.
+    ///
+    /// Prefer this over [`Self::to_decoded`] when passing the image to a core
+    /// function that only reads it, so large images are not held twice.
+    pub(crate) fn as_decoded(&self) -> &DecodedImage {
+        &self.image
+    }
+
+    /// Copy the RGB pixel data into a buffer from the scratch pool.
+    ///
+    /// Use this instead of [`Self::pixels`] for a copy that an operation
+    /// modifies and wraps in a new image; the buffer returns to the pool
+    /// when that image is freed.
+    pub(crate) fn pooled_pixels(&self) -> Vec<u8> {
+        pool::acquire_copy(&self.image.pixels)
+    }
+
+    /// Mutable access to the RGB pixel data, for in-place operations.
+    pub(crate) fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.image.pixels
+    }
+
     /// Convert back to a core DecodedImage.
     ///
-    /// This is used when passing an image to core functions like resize.
-    /// Note: This clones the pixel data.
+    /// Note: This clones the pixel data. Use [`Self::as_decoded`] unless the
+    /// core function needs an owned image.
     #[allow(dead_code)] // Used in decode module (Phase 3)
     pub(crate) fn to_decoded(&self) -> DecodedImage {
-        DecodedImage {
-            width: self.width,
-            height: self.height,
-            pixels: self.pixels.clone(),
+        self.image.clone()
+    }
+}
+
+impl Drop for JsDecodedImage {
+    fn drop(&mut self) {
+        pool::release(std::mem::take(&mut self.image.pixels));
+    }
+}
+
+/// A premultiplied RGBA image for JavaScript.
+///
+/// This is the layout WebGL uploads directly (`gl.RGBA`, 4 bytes per pixel,
+/// color already multiplied by alpha), and the layout `gl.readPixels` returns
+/// from a premultiplied framebuffer.
+#[wasm_bindgen]
+pub struct JsRgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl JsRgbaImage {
+    /// Create a new JsRgbaImage from dimensions and premultiplied pixel data.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `pixels` - Premultiplied RGBA data (4 bytes per pixel, row-major order)
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with `code` `"EMPTY_IMAGE"` or
+    /// `"BUFFER_SIZE_MISMATCH"` if the buffer does not match the dimensions.
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<JsRgbaImage, JsValue> {
+        validate_rgba_buffer(&pixels, width, height).map_err(|e| image_error_to_js(&e))?;
+        Ok(JsRgbaImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Get the image width in pixels
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the image height in pixels
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the number of bytes in the pixel buffer (width * height * 4)
+    #[wasm_bindgen(getter)]
+    pub fn byte_length(&self) -> usize {
+        self.pixels.len()
+    }
+
+    /// Returns premultiplied RGBA pixel data as Uint8Array.
+    ///
+    /// Note: This creates a copy of the pixel data.
+    pub fn pixels(&self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+
+    /// Un-premultiply and drop alpha, e.g. after reading back a render.
+    ///
+    /// Fully transparent pixels become black.
+    pub fn to_rgb(&self) -> JsDecodedImage {
+        // The constructor validated the buffer, so conversion cannot fail
+        let decoded = DecodedImage::from_rgba_premultiplied(self.width, self.height, &self.pixels)
+            .expect("JsRgbaImage buffer is validated on construction");
+        JsDecodedImage::from_decoded(decoded)
+    }
+}
+
+/// A single-channel 8-bit image for JavaScript.
+///
+/// Masks and luminance maps in a third of the memory of an RGB image, laid
+/// out for upload as a single-channel texture (`gl.LUMINANCE` or `gl.R8`,
+/// 1 byte per pixel).
+#[wasm_bindgen]
+pub struct JsGrayImage {
+    image: GrayImage,
+}
+
+#[wasm_bindgen]
+impl JsGrayImage {
+    /// Create a new JsGrayImage from dimensions and pixel data.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `pixels` - One byte per pixel, row-major order
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with `code` `"EMPTY_IMAGE"` or
+    /// `"BUFFER_SIZE_MISMATCH"` if the buffer does not match the dimensions.
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<JsGrayImage, JsValue> {
+        validate_gray_buffer(&pixels, width, height).map_err(|e| image_error_to_js(&e))?;
+        Ok(JsGrayImage {
+            image: GrayImage::new(width, height, pixels),
+        })
+    }
+
+    /// Get the image width in pixels
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.image.width
+    }
+
+    /// Get the image height in pixels
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.image.height
+    }
+
+    /// Get the number of bytes in the pixel buffer (width * height)
+    #[wasm_bindgen(getter)]
+    pub fn byte_length(&self) -> usize {
+        self.image.pixels.len()
+    }
+
+    /// Returns the pixel data as Uint8Array.
+    ///
+    /// Note: This creates a copy of the pixel data.
+    pub fn pixels(&self) -> Vec<u8> {
+        self.image.pixels.clone()
+    }
+
+    /// Convert to RGB with the value in all three channels.
+    pub fn to_rgb(&self) -> JsDecodedImage {
+        JsDecodedImage::from_decoded(self.image.to_rgb())
+    }
+
+    /// Resize to exact dimensions, e.g. to match an overlay to the canvas.
+    ///
+    /// `filter` is as for `resize`: 0 = nearest, 1 = bilinear (the default
+    /// for unknown values), 2 = Lanczos3.
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with a `code` property if a target dimension is
+    /// zero or exceeds the image size limits.
+    pub fn resize(&self, width: u32, height: u32, filter: u8) -> Result<JsGrayImage, JsValue> {
+        decode::resize(&self.image, width, height, filter_from_u8(filter))
+            .map(JsGrayImage::from_gray)
+            .map_err(|e| decode_error_to_js(&e))
+    }
+}
+
+impl JsGrayImage {
+    /// Wrap a core GrayImage whose buffer matches its dimensions.
+    pub(crate) fn from_gray(image: GrayImage) -> Self {
+        Self { image }
+    }
+}
+
+/// A float RGB image for JavaScript, for HDR and scientific data.
+///
+/// Holds 3 `f32` values per pixel with 1.0 as 8-bit white. Values above 1.0
+/// (and below 0.0) are kept through adjustments until `to_u8` tone maps them.
+#[wasm_bindgen]
+pub struct JsDecodedImageF32 {
+    image: DecodedImageF32,
+}
+
+#[wasm_bindgen]
+impl JsDecodedImageF32 {
+    /// Create a new JsDecodedImageF32 from dimensions and float pixel data.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `pixels` - RGB float data (3 values per pixel, row-major order)
+    ///
+    /// # Errors
+    ///
+    /// Throws an `Error` with `code` `"EMPTY_IMAGE"` or
+    /// `"BUFFER_SIZE_MISMATCH"` if the buffer does not match the dimensions.
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u32, height: u32, pixels: Vec<f32>) -> Result<JsDecodedImageF32, JsValue> {
+        validate_rgb_f32_buffer(&pixels, width, height).map_err(|e| image_error_to_js(&e))?;
+        Ok(JsDecodedImageF32 {
+            image: DecodedImageF32::new(width, height, pixels),
+        })
+    }
+
+    /// Get the image width in pixels
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.image.width
+    }
+
+    /// Get the image height in pixels
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.image.height
+    }
+
+    /// Returns RGB float data as Float32Array.
+    ///
+    /// Note: This creates a copy of the pixel data.
+    pub fn pixels(&self) -> Vec<f32> {
+        self.image.pixels.clone()
+    }
+
+    /// Convert to an 8-bit image.
+    ///
+    /// `tone_map` selects how values above 1.0 are brought into range:
+    /// 0 = clamp, 1 = Reinhard (see `tone_map_from_u8`). `quantize` selects
+    /// how they are brought down to 8 bits: 0 = truncate (the default),
+    /// 1 = error diffusion, which hides banding on soft gradients in
+    /// previews (see `quantize_method_from_u8`).
+    pub fn to_u8(&self, tone_map: u8, quantize: Option<u8>) -> JsDecodedImage {
+        let method = quantize_method_from_u8(quantize.unwrap_or(0));
+        JsDecodedImage::from_decoded(self.image.to_u8_with(tone_map_from_u8(tone_map), method))
+    }
+}
+
+impl JsDecodedImageF32 {
+    /// Create a JsDecodedImageF32 from a core image with a validated buffer.
+    pub(crate) fn from_decoded(image: DecodedImageF32) -> Self {
+        Self { image }
+    }
+
+    /// Borrow the underlying core image.
+    pub(crate) fn as_decoded(&self) -> &DecodedImageF32 {
+        &self.image
+    }
+}
+
+/// Convert an image validation error to a JavaScript `Error`.
+///
+/// The error carries a `code` property (`"EMPTY_IMAGE"` or
+/// `"BUFFER_SIZE_MISMATCH"`) so callers can branch without parsing the message:
+///
+/// ```typescript
+/// try {
+///   apply_rotation(image, 15, false);
+/// } catch (e) {
+///   if (e.code === 'EMPTY_IMAGE') showPlaceholder();
+/// }
+/// ```
+pub(crate) fn image_error_to_js(err: &ImageError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting a property on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    js_err.into()
+}
+
+/// Convert an adjustment validation error to a JavaScript `Error`.
+///
+/// Like [`image_error_to_js`], the error carries a `code` property
+/// (`"ADJUSTMENT_OUT_OF_RANGE"` or `"NON_FINITE_ADJUSTMENT"`), plus a
+/// `fields` array naming every offending field:
+///
+/// ```typescript
+/// try {
+///   apply_adjustments(image, adj);
+/// } catch (e) {
+///   if (e.code === 'ADJUSTMENT_OUT_OF_RANGE') highlightSliders(e.fields);
+/// }
+/// ```
+pub(crate) fn adjustment_error_to_js(err: &AdjustmentError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    let fields: js_sys::Array = err.fields().into_iter().map(JsValue::from_str).collect();
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    let _ = js_sys::Reflect::set(&js_err, &"fields".into(), &fields);
+    js_err.into()
+}
+
+/// Convert a tone curve validation error to a JavaScript `Error`.
+///
+/// The `code` is `"TOO_MANY_CURVE_POINTS"`, with the point `count`, or
+/// `"NON_FINITE_CURVE_POINT"`, with the `index` of the offending point.
+pub(crate) fn curve_error_to_js(err: &CurveError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    match *err {
+        CurveError::TooManyPoints { count, .. } => {
+            let _ = js_sys::Reflect::set(&js_err, &"count".into(), &(count as u32).into());
+        }
+        CurveError::NonFinitePoint { index } => {
+            let _ = js_sys::Reflect::set(&js_err, &"index".into(), &(index as u32).into());
+        }
+    }
+    js_err.into()
+}
+
+/// Convert an export format error to a JavaScript `Error`.
+///
+/// The `code` is that of [`ExportFormatError::code`]. Bit depth errors
+/// carry the rejected `bit_depth`, and out-of-range qualities the `quality`.
+pub(crate) fn export_format_error_to_js(err: &ExportFormatError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    match *err {
+        ExportFormatError::JpegBitDepth { bit_depth }
+        | ExportFormatError::PngBitDepth { bit_depth } => {
+            let _ = js_sys::Reflect::set(&js_err, &"bit_depth".into(), &bit_depth.into());
+        }
+        ExportFormatError::QualityOutOfRange { quality } => {
+            let _ = js_sys::Reflect::set(&js_err, &"quality".into(), &quality.into());
+        }
+        _ => {}
+    }
+    js_err.into()
+}
+
+/// Convert a mask stack error to a JavaScript `Error` with a `code` property.
+///
+/// Unknown mask ids (`"UNKNOWN_MASK_ID"`) also carry a `known_ids` array,
+/// and duplicate ones (`"DUPLICATE_MASK_ID"`) the repeated `id`; image errors use the same codes as [`image_error_to_js`].
+pub(crate) fn mask_error_to_js(err: &MaskError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    if let MaskError::UnknownId { known, .. } = err {
+        let known: js_sys::Array = known.iter().map(|id| JsValue::from_str(id)).collect();
+        let _ = js_sys::Reflect::set(&js_err, &"known_ids".into(), &known);
+    }
+    if let MaskError::DuplicateId { id } = err {
+        let _ = js_sys::Reflect::set(&js_err, &"id".into(), &id.into());
+    }
+    js_err.into()
+}
+
+/// Convert a decode error to a JavaScript `Error` with a `code` property.
+///
+/// Codes name the kind of failure, so the UI can tell a broken file
+/// (`"CORRUPTED_FILE"`) from a valid one it cannot open, such as a 12-bit
+/// JPEG (`"UNSUPPORTED_VARIANT"`). Image errors use the same codes as
+/// [`image_error_to_js`]. For `"NO_DECODER_SUCCEEDED"`, an `attempts` array
+/// lists what was tried; for `"UNSUPPORTED_FILE_TYPE"`, `file_type` names
+/// the type found (e.g. `"pdf"`).
+///
+/// ```typescript
+/// try {
+///   decode_jpeg(bytes);
+/// } catch (e) {
+///   if (e.code === 'UNSUPPORTED_VARIANT') showNotice(e.message);
+/// }
+/// ```
+pub(crate) fn decode_error_to_js(err: &DecodeError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    match err {
+        DecodeError::NoDecoderSucceeded { attempts } => {
+            let attempts: js_sys::Array = attempts.iter().map(|a| JsValue::from_str(a)).collect();
+            let _ = js_sys::Reflect::set(&js_err, &"attempts".into(), &attempts);
+        }
+        DecodeError::UnsupportedFileType { file_type } => {
+            let _ = js_sys::Reflect::set(&js_err, &"file_type".into(), &file_type.name().into());
         }
+        _ => {}
     }
+    js_err.into()
+}
+
+/// Convert an [`open_file`](crate::decode::open_file) error to a JavaScript
+/// `Error`.
+///
+/// Sets `stage` to the step that failed (e.g. `"decode"`) and the same
+/// `code` as [`decode_error_to_js`].
+pub(crate) fn open_file_error_to_js(err: &OpenFileError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"stage".into(), &err.stage.name().into());
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.source.code().into());
+    js_err.into()
+}
+
+/// Convert an export name template error to a JavaScript `Error`.
+///
+/// Sets `code` (e.g. `"UNKNOWN_TOKEN"`) and `position`, the character index
+/// in the template where the problem starts, so the UI can mark it.
+pub(crate) fn template_error_to_js(err: &TemplateError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    let _ = js_sys::Reflect::set(&js_err, &"position".into(), &(err.position() as u32).into());
+    js_err.into()
+}
+
+/// Convert a `.cube` LUT parse error to a JavaScript `Error`.
+///
+/// Sets `code` (e.g. `"LUT_SYNTAX"`) and, when the problem is on one line of
+/// the file, `line` (1-based), so the UI can point at it.
+pub(crate) fn lut_error_to_js(err: &LutError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    if let Some(line) = err.line() {
+        let _ = js_sys::Reflect::set(&js_err, &"line".into(), &(line as u32).into());
+    }
+    js_err.into()
+}
+
+/// Convert an adjustment stage order error to a JavaScript `Error`.
+///
+/// Sets `code` (`"UNKNOWN_STAGE"`, `"DUPLICATE_STAGE"` or `"MISSING_STAGE"`)
+/// and `stage`, the offending stage name.
+pub(crate) fn stage_order_error_to_js(err: &StageOrderError) -> JsValue {
+    let stage = match err {
+        StageOrderError::Unknown { name } => name.as_str(),
+        StageOrderError::Duplicate { stage } | StageOrderError::Missing { stage } => stage.name(),
+    };
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    let _ = js_sys::Reflect::set(&js_err, &"stage".into(), &stage.into());
+    js_err.into()
+}
+
+/// Convert a recipe error to a JavaScript `Error`.
+///
+/// Sets `code` (e.g. `"UNKNOWN_OP"`) and `step`, the index of the offending
+/// step in the recipe.
+pub(crate) fn recipe_error_to_js(err: &RecipeError) -> JsValue {
+    let js_err = js_sys::Error::new(&err.to_string());
+    // Setting properties on a fresh Error object cannot fail
+    let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+    let _ = js_sys::Reflect::set(&js_err, &"step".into(), &(err.step() as u32).into());
+    js_err.into()
 }
 
 /// Convert a u8 filter type value to the core FilterType enum.
@@ -122,17 +704,140 @@ pub(crate) fn filter_from_u8(value: u8) -> FilterType {
     }
 }
 
+/// Convert a u8 gravity value to the core Gravity enum.
+///
+/// Values:
+/// - 0 = Center
+/// - 1 = Top
+/// - 2 = Bottom
+/// - 3 = Left
+/// - 4 = Right
+///
+/// Any other value defaults to Center.
+pub(crate) fn gravity_from_u8(value: u8) -> Gravity {
+    match value {
+        1 => Gravity::Top,
+        2 => Gravity::Bottom,
+        3 => Gravity::Left,
+        4 => Gravity::Right,
+        _ => Gravity::Center, // Default
+    }
+}
+
+/// Convert a u8 watermark anchor value to the core WatermarkAnchor enum.
+///
+/// Values:
+/// - 0 = TopLeft
+/// - 1 = TopRight
+/// - 2 = BottomLeft
+/// - 3 = BottomRight
+/// - 4 = Center
+///
+/// Any other value defaults to BottomRight.
+pub(crate) fn watermark_anchor_from_u8(value: u8) -> WatermarkAnchor {
+    match value {
+        0 => WatermarkAnchor::TopLeft,
+        1 => WatermarkAnchor::TopRight,
+        2 => WatermarkAnchor::BottomLeft,
+        4 => WatermarkAnchor::Center,
+        _ => WatermarkAnchor::BottomRight, // Default
+    }
+}
+
+/// Convert a u8 white balance method value to the core enum.
+///
+/// Values:
+/// - 0 = GrayWorld (channel means)
+/// - 1 = RobustGrayEdge (means weighted by local contrast)
+/// - 2 = BrightestPixels (mean of the brightest 2%)
+///
+/// Any other value defaults to GrayWorld.
+pub(crate) fn white_balance_method_from_u8(value: u8) -> WhiteBalanceMethod {
+    match value {
+        1 => WhiteBalanceMethod::RobustGrayEdge,
+        2 => WhiteBalanceMethod::BrightestPixels,
+        _ => WhiteBalanceMethod::GrayWorld, // Default
+    }
+}
+
+/// Convert a u8 color model value to the core enum.
+///
+/// Values:
+/// - 0 = Legacy (scale RGB distance from luminance)
+/// - 1 = Oklab (scale Oklch chroma, keeping hue)
+///
+/// Any other value defaults to Legacy.
+pub(crate) fn color_model_from_u8(value: u8) -> ColorModel {
+    match value {
+        1 => ColorModel::Oklab,
+        _ => ColorModel::Legacy, // Default
+    }
+}
+
+/// Convert a u8 tone map value to the core enum.
+///
+/// Values:
+/// - 0 = Clamp (cut off values outside 0.0-1.0)
+/// - 1 = Reinhard (compress highlights smoothly)
+///
+/// Any other value defaults to Clamp.
+pub(crate) fn tone_map_from_u8(value: u8) -> ToneMap {
+    match value {
+        1 => ToneMap::Reinhard,
+        _ => ToneMap::Clamp, // Default
+    }
+}
+
+/// Convert a u8 quantize method value to the core enum.
+///
+/// Values:
+/// - 0 = Truncate (round each value on its own)
+/// - 1 = ErrorDiffusion (dither the luminance error)
+///
+/// Any other value defaults to Truncate.
+pub(crate) fn quantize_method_from_u8(value: u8) -> QuantizeMethod {
+    match value {
+        1 => QuantizeMethod::ErrorDiffusion,
+        _ => QuantizeMethod::Truncate, // Default
+    }
+}
+
+/// Convert a u8 canvas color space value to the core enum.
+///
+/// Values:
+/// - 0 = sRGB
+/// - 1 = Display P3
+///
+/// Any other value is Unknown: pixels are imported without conversion.
+pub(crate) fn canvas_color_space_from_u8(value: u8) -> CanvasColorSpace {
+    match value {
+        0 => CanvasColorSpace::Srgb,
+        1 => CanvasColorSpace::DisplayP3,
+        _ => CanvasColorSpace::Unknown,
+    }
+}
+
+/// Convert a u8 display color space value to the core enum.
+///
+/// Values:
+/// - 0 = sRGB
+/// - 1 = Display P3
+///
+/// Any other value defaults to sRGB, leaving pixels unchanged.
+pub(crate) fn display_color_space_from_u8(value: u8) -> DisplayColorSpace {
+    match value {
+        1 => DisplayColorSpace::DisplayP3,
+        _ => DisplayColorSpace::Srgb,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_js_decoded_image_creation() {
-        let img = JsDecodedImage {
-            width: 100,
-            height: 50,
-            pixels: vec![0u8; 100 * 50 * 3],
-        };
+        let img = JsDecodedImage::new(100, 50, vec![0u8; 100 * 50 * 3]);
         assert_eq!(img.width(), 100);
         assert_eq!(img.height(), 50);
         assert_eq!(img.byte_length(), 15000);
@@ -141,11 +846,7 @@ mod tests {
     #[test]
     fn test_js_decoded_image_pixels() {
         let pixels = vec![255u8, 128, 64, 32, 16, 8]; // 2 RGB pixels
-        let img = JsDecodedImage {
-            width: 2,
-            height: 1,
-            pixels: pixels.clone(),
-        };
+        let img = JsDecodedImage::new(2, 1, pixels.clone());
         assert_eq!(img.pixels(), pixels);
     }
 
@@ -164,11 +865,7 @@ mod tests {
 
     #[test]
     fn test_to_decoded() {
-        let js_img = JsDecodedImage {
-            width: 50,
-            height: 25,
-            pixels: vec![128u8; 50 * 25 * 3],
-        };
+        let js_img = JsDecodedImage::new(50, 25, vec![128u8; 50 * 25 * 3]);
         let decoded = js_img.to_decoded();
         assert_eq!(decoded.width, 50);
         assert_eq!(decoded.height, 25);
@@ -184,4 +881,145 @@ mod tests {
         assert!(matches!(filter_from_u8(3), FilterType::Bilinear));
         assert!(matches!(filter_from_u8(255), FilterType::Bilinear));
     }
+
+    #[test]
+    fn test_gravity_from_u8() {
+        assert_eq!(gravity_from_u8(0), Gravity::Center);
+        assert_eq!(gravity_from_u8(1), Gravity::Top);
+        assert_eq!(gravity_from_u8(2), Gravity::Bottom);
+        assert_eq!(gravity_from_u8(3), Gravity::Left);
+        assert_eq!(gravity_from_u8(4), Gravity::Right);
+        // Unknown values default to Center
+        assert_eq!(gravity_from_u8(5), Gravity::Center);
+    }
+
+    #[test]
+    fn test_rgba_premultiplied_round_trip() {
+        let img = JsDecodedImage::new(2, 1, vec![255, 128, 0, 10, 20, 30]);
+
+        let rgba = img.to_rgba_premultiplied(255);
+        assert_eq!((rgba.width(), rgba.height()), (2, 1));
+        assert_eq!(rgba.byte_length(), 8);
+        assert_eq!(rgba.pixels(), vec![255, 128, 0, 255, 10, 20, 30, 255]);
+        assert_eq!(rgba.to_rgb().pixels(), img.pixels());
+
+        let rgba = img.to_rgba_premultiplied(128);
+        assert_eq!(rgba.pixels()[3], 128);
+        for (a, b) in img.pixels().iter().zip(rgba.to_rgb().pixels()) {
+            assert!(a.abs_diff(b) <= 1);
+        }
+    }
+
+    #[test]
+    fn test_rgba_image_valid_buffer() {
+        let rgba = JsRgbaImage::new(2, 2, vec![0u8; 16]).ok().unwrap();
+        assert_eq!(rgba.byte_length(), 16);
+    }
+
+    #[test]
+    fn test_gray_image_accessors_and_conversions() {
+        let gray = JsGrayImage::new(3, 2, vec![0, 50, 100, 150, 200, 250])
+            .ok()
+            .unwrap();
+        assert_eq!((gray.width(), gray.height()), (3, 2));
+        assert_eq!(gray.byte_length(), 6);
+        assert_eq!(gray.pixels(), vec![0, 50, 100, 150, 200, 250]);
+
+        let rgb = gray.to_rgb();
+        assert_eq!(rgb.byte_length(), 18);
+        assert_eq!(&rgb.pixels()[3..6], &[50, 50, 50]);
+        assert_eq!(rgb.to_gray().ok().unwrap().pixels(), gray.pixels());
+
+        let resized = gray.resize(6, 4, 0).ok().unwrap();
+        assert_eq!((resized.width(), resized.height()), (6, 4));
+        assert_eq!(resized.pixels()[..6], [0, 0, 50, 50, 100, 100]);
+    }
+
+    #[test]
+    fn test_f32_round_trip() {
+        let pixels: Vec<u8> = (0..=255).flat_map(|v| [v, 255 - v, v / 2]).collect();
+        let img = JsDecodedImage::new(16, 16, pixels.clone());
+        let float = img.to_f32().unwrap();
+        assert_eq!((float.width(), float.height()), (16, 16));
+        assert_eq!(float.pixels()[3], 1.0 / 255.0);
+        assert_eq!(float.to_u8(0, None).pixels(), pixels);
+    }
+
+    #[test]
+    fn test_f32_tone_map_values() {
+        let float = JsDecodedImageF32::new(1, 1, vec![0.5, 1.0, 3.0]).unwrap();
+        assert_eq!(float.to_u8(0, None).pixels(), vec![128, 255, 255]);
+        // Reinhard compresses 3.0 to 0.75 instead of clipping it
+        assert_eq!(float.to_u8(1, None).pixels(), vec![85, 128, 191]);
+    }
+
+    #[test]
+    fn test_f32_error_diffusion_quantize() {
+        // A soft gray ramp a quarter of a level wide per pixel
+        let pixels: Vec<f32> = (0..64 * 4)
+            .flat_map(|i| [(100.0 + (i % 64) as f32 * 0.25) / 255.0; 3])
+            .collect();
+        let float = JsDecodedImageF32::new(64, 4, pixels).unwrap();
+        let truncated = float.to_u8(0, None).pixels();
+        assert_eq!(float.to_u8(0, Some(0)).pixels(), truncated);
+        let dithered = float.to_u8(0, Some(1)).pixels();
+        assert_ne!(dithered, truncated);
+        assert_eq!(float.to_u8(0, Some(1)).pixels(), dithered);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn error_code(err: &JsValue) -> String {
+        js_sys::Reflect::get(err, &"code".into())
+            .unwrap()
+            .as_string()
+            .unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validate_empty_image() {
+        let img = JsDecodedImage::new(0, 0, vec![]);
+        let err = img.validate().unwrap_err();
+        assert!(err.is_instance_of::<js_sys::Error>());
+        assert_eq!(error_code(&err), "EMPTY_IMAGE");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validate_buffer_mismatch() {
+        let img = JsDecodedImage::new(2, 2, vec![0u8; 6]);
+        assert_eq!(
+            error_code(&img.validate().unwrap_err()),
+            "BUFFER_SIZE_MISMATCH"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validate_single_pixel() {
+        assert!(JsDecodedImage::new(1, 1, vec![1, 2, 3]).validate().is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rgba_image_rejects_rgb_length() {
+        let err = JsRgbaImage::new(2, 2, vec![0u8; 12]).err().unwrap();
+        assert_eq!(error_code(&err), "BUFFER_SIZE_MISMATCH");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_gray_image_rejects_wrong_length() {
+        let err = JsGrayImage::new(2, 2, vec![0u8; 12]).err().unwrap();
+        assert_eq!(error_code(&err), "BUFFER_SIZE_MISMATCH");
+        let err = JsGrayImage::new(0, 2, vec![]).err().unwrap();
+        assert_eq!(error_code(&err), "EMPTY_IMAGE");
+        let err = JsDecodedImage::new(2, 2, vec![0u8; 4])
+            .to_gray()
+            .err()
+            .unwrap();
+        assert_eq!(error_code(&err), "BUFFER_SIZE_MISMATCH");
+    }
 }
@@ -0,0 +1,210 @@
+//! Preset strip WASM bindings.
+//!
+//! Renders every preset in the preset panel from one call: the source is
+//! passed and downsized once, and the results come back packed into a single
+//! atlas image for one texture upload.
+
+use crate::types::JsDecodedImage;
+use literoom_core::compose::{pack_atlas, AtlasCell};
+use literoom_core::decode::DecodedImage;
+use literoom_core::edit::EditState;
+use literoom_core::variants::render_variants_small;
+use wasm_bindgen::prelude::*;
+
+/// Preset previews packed into one atlas image.
+#[wasm_bindgen]
+pub struct JsPresetStrip {
+    atlas: DecodedImage,
+    cells: Vec<AtlasCell>,
+}
+
+#[wasm_bindgen]
+impl JsPresetStrip {
+    /// Get the packed atlas image.
+    ///
+    /// Note: This creates a copy of the atlas pixels.
+    pub fn atlas(&self) -> JsDecodedImage {
+        JsDecodedImage::from_decoded(self.atlas.clone())
+    }
+
+    /// Get the number of cells (one per preset).
+    #[wasm_bindgen(getter)]
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Get the width of every cell in pixels.
+    #[wasm_bindgen(getter)]
+    pub fn cell_width(&self) -> u32 {
+        self.cells[0].width
+    }
+
+    /// Get the height of every cell in pixels.
+    #[wasm_bindgen(getter)]
+    pub fn cell_height(&self) -> u32 {
+        self.cells[0].height
+    }
+
+    /// Get cell rectangles as a flat `Uint32Array`.
+    ///
+    /// Four values per preset, in input order: `[x, y, width, height, ...]`.
+    pub fn offsets(&self) -> Vec<u32> {
+        self.cells
+            .iter()
+            .flat_map(|c| [c.x, c.y, c.width, c.height])
+            .collect()
+    }
+}
+
+/// Render preview thumbnails of one image with each preset applied.
+///
+/// The source is downsized once to fit `thumb_size x thumb_size`, then each
+/// preset's develop settings are applied to the small image as the render
+/// pipeline applies them. The
+/// previews are packed on a near-square grid (`ceil(sqrt(n))` columns) to
+/// keep the atlas within texture size limits.
+///
+/// # Arguments
+///
+/// * `image` - Source image (typically the current preview)
+/// * `presets` - Array of edit state objects; missing fields use defaults
+/// * `thumb_size` - Maximum edge of each preview in pixels
+///
+/// # Errors
+///
+/// Returns an error if the presets cannot be parsed, the array is empty,
+/// `thumb_size` is zero, or the image is empty.
+///
+/// # Example
+///
+/// ```typescript
+/// const strip = render_preset_strip(preview, presets.map((p) => p.state), 96);
+/// const atlas = strip.atlas();
+/// gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGB, atlas.width, atlas.height, 0,
+///               gl.RGB, gl.UNSIGNED_BYTE, atlas.pixels());
+///
+/// const offsets = strip.offsets();
+/// presets.forEach((p, i) => {
+///   const [x, y, w, h] = offsets.subarray(i * 4, i * 4 + 4);
+///   drawCell(p, x, y, w, h);
+/// });
+/// ```
+#[wasm_bindgen]
+pub fn render_preset_strip(
+    image: &JsDecodedImage,
+    presets: JsValue,
+    thumb_size: u32,
+) -> Result<JsPresetStrip, JsValue> {
+    let presets: Vec<EditState> = serde_wasm_bindgen::from_value(presets)
+        .map_err(|e| JsValue::from_str(&format!("Invalid presets: {}", e)))?;
+
+    build_preset_strip(image.as_decoded(), &presets, thumb_size).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Render and pack the preset strip.
+fn build_preset_strip(
+    image: &DecodedImage,
+    presets: &[EditState],
+    thumb_size: u32,
+) -> Result<JsPresetStrip, String> {
+    let variants = render_variants_small(image, presets, thumb_size).map_err(|e| e.to_string())?;
+    let columns = (variants.len() as f64).sqrt().ceil() as u32;
+    let atlas = pack_atlas(&variants, columns).map_err(|e| e.to_string())?;
+
+    Ok(JsPresetStrip {
+        atlas: atlas.image,
+        cells: atlas.cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use literoom_core::decode::generate_thumbnail;
+    use literoom_core::testing::{checkerboard, color_bars};
+    use literoom_core::BasicAdjustments;
+
+    fn exposure_preset(exposure: f32) -> EditState {
+        EditState {
+            adjustments: BasicAdjustments {
+                exposure,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_strip_cells_match_individual_presets() {
+        let source = color_bars(200, 100);
+        let presets: Vec<_> = [-1.0, 0.0, 0.5, 1.0, 2.0]
+            .into_iter()
+            .map(exposure_preset)
+            .collect();
+        let strip = build_preset_strip(&source, &presets, 50).unwrap();
+        let thumb = generate_thumbnail(&source, 50).unwrap();
+
+        // 5 presets -> 3 columns, 2 rows of 50x25 cells
+        assert_eq!(strip.cell_count(), 5);
+        assert_eq!((strip.cell_width(), strip.cell_height()), (50, 25));
+        assert_eq!((strip.atlas.width, strip.atlas.height), (150, 50));
+
+        let offsets = strip.offsets();
+        for (i, preset) in presets.iter().enumerate() {
+            let mut expected = thumb.pixels.clone();
+            literoom_core::adjustments::apply_all_adjustments(&mut expected, &preset.adjustments);
+
+            let [x, y, w, h] = [0, 1, 2, 3].map(|k| offsets[i * 4 + k]);
+            for row in 0..h {
+                let start = ((y + row) * strip.atlas.width + x) as usize * 3;
+                let src = (row * w) as usize * 3;
+                let len = w as usize * 3;
+                assert_eq!(
+                    strip.atlas.pixels[start..start + len],
+                    expected[src..src + len],
+                    "preset {} row {}",
+                    i,
+                    row
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_preset_is_one_cell() {
+        let strip =
+            build_preset_strip(&checkerboard(40, 40, 4), &[EditState::default()], 20).unwrap();
+        assert_eq!(strip.offsets(), vec![0, 0, 20, 20]);
+    }
+
+    #[test]
+    fn test_no_presets_is_error() {
+        assert!(build_preset_strip(&checkerboard(40, 40, 4), &[], 20).is_err());
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_render_preset_strip_from_js() {
+        let image = JsDecodedImage::new(20, 20, vec![100u8; 20 * 20 * 3]);
+        let presets = js_sys::Array::new();
+        presets.push(&js_sys::Object::new());
+        presets.push(&js_sys::Object::new());
+
+        let strip = render_preset_strip(&image, presets.into(), 10).unwrap();
+        assert_eq!(strip.cell_count(), 2);
+        assert_eq!(strip.offsets(), vec![0, 0, 10, 10, 10, 0, 10, 10]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_preset_strip_invalid_presets() {
+        let image = JsDecodedImage::new(20, 20, vec![100u8; 20 * 20 * 3]);
+        assert!(render_preset_strip(&image, JsValue::from_str("nope"), 10).is_err());
+    }
+}
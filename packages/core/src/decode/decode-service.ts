@@ -90,6 +90,8 @@ export interface IDecodeService {
     height: number,
     quality?: number
   ): Promise<Uint8Array>
+  /** Rotate JPEG file bytes by clockwise quarter turns without re-encoding */
+  rotateJpegLossless(bytes: Uint8Array, quarterTurns: number): Promise<Uint8Array>
   /** Apply masked adjustments (local adjustments) to image pixel data */
   applyMaskedAdjustments(
     pixels: Uint8Array,
@@ -357,6 +359,10 @@ export class DecodeService implements IDecodeService {
     return this.routeRequest({ type: 'encode-jpeg', pixels, width, height, quality })
   }
 
+  rotateJpegLossless(bytes: Uint8Array, quarterTurns: number): Promise<Uint8Array> {
+    return this.routeRequest({ type: 'rotate-jpeg-lossless', bytes, quarterTurns })
+  }
+
   applyMaskedAdjustments(
     pixels: Uint8Array,
     width: number,
@@ -319,6 +319,10 @@ export class DecodeWorkerPool implements IDecodeService {
     return this.routeRequest({ type: 'encode-jpeg', pixels, width, height, quality })
   }
 
+  rotateJpegLossless(bytes: Uint8Array, quarterTurns: number): Promise<Uint8Array> {
+    return this.routeRequest({ type: 'rotate-jpeg-lossless', bytes, quarterTurns })
+  }
+
   applyMaskedAdjustments(
     pixels: Uint8Array,
     width: number,
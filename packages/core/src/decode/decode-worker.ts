@@ -24,6 +24,7 @@ import init, {
   apply_crop,
   compute_histogram,
   encode_jpeg,
+  rotate_jpeg_lossless,
   apply_masked_adjustments,
   BasicAdjustments,
   JsDecodedImage,
@@ -410,6 +411,22 @@ self.onmessage = async (event: MessageEvent<DecodeRequest>) => {
         break
       }
 
+      case 'rotate-jpeg-lossless': {
+        const { bytes, quarterTurns } = request
+
+        // Rewrite the EXIF orientation; the compressed data is copied as is
+        const jpegBytes = rotate_jpeg_lossless(bytes, quarterTurns)
+
+        const response: EncodeJpegResponse = {
+          id,
+          type: 'encode-jpeg-result',
+          bytes: jpegBytes
+        }
+
+        self.postMessage(response, [jpegBytes.buffer])
+        break
+      }
+
       case 'apply-masked-adjustments': {
         const { pixels, width, height, maskStack } = request
         const inputImage = new JsDecodedImage(width, height, pixels)
@@ -36,6 +36,7 @@ export {
   type ComputeHistogramRequest,
   type ApplyToneCurveRequest,
   type EncodeJpegRequest,
+  type RotateJpegLosslessRequest,
   type ApplyMaskedAdjustmentsRequest,
   type GenerateEditedThumbnailRequest,
   type MaskStackData,
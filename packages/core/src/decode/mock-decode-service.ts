@@ -533,6 +533,14 @@ export class MockDecodeService implements IDecodeService {
     return mockJpeg
   }
 
+  async rotateJpegLossless(bytes: Uint8Array, _quarterTurns: number): Promise<Uint8Array> {
+    await this.simulateOperation()
+
+    // For mock, return the file unchanged; the real worker only rewrites
+    // the EXIF orientation tag
+    return bytes.slice()
+  }
+
   async applyMaskedAdjustments(
     pixels: Uint8Array,
     width: number,
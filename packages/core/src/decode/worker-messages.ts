@@ -51,6 +51,7 @@ export type DecodeRequest =
   | ApplyRotationRequest
   | ApplyCropRequest
   | EncodeJpegRequest
+  | RotateJpegLosslessRequest
   | ApplyMaskedAdjustmentsRequest
   | GenerateEditedThumbnailRequest
 
@@ -207,6 +208,20 @@ export interface EncodeJpegRequest {
   quality: number
 }
 
+/**
+ * Rotate JPEG file bytes by quarter turns without re-encoding.
+ * Only the EXIF orientation tag changes; the response is an
+ * EncodeJpegResponse with the rotated file.
+ */
+export interface RotateJpegLosslessRequest {
+  id: string
+  type: 'rotate-jpeg-lossless'
+  /** JPEG file bytes */
+  bytes: Uint8Array
+  /** Clockwise quarter turns */
+  quarterTurns: number
+}
+
 /**
  * Apply masked adjustments to image pixels.
  * Applies local adjustments (linear gradient, radial gradient masks)
@@ -3,7 +3,12 @@
  */
 
 import { describe, it, expect, vi, beforeEach } from 'vitest'
-import { ExportService, filterAssetsForExport, createExportService } from './export-service'
+import {
+  ExportService,
+  filterAssetsForExport,
+  createExportService,
+  getLosslessQuarterTurns,
+} from './export-service'
 import type { ExportServiceDependencies, ExportOptions, ExportProgress } from './types'
 import type { Asset } from '../catalog/types'
 
@@ -277,6 +282,76 @@ describe('ExportService', () => {
       expect(mockDeps.applyRotation).not.toHaveBeenCalled()
     })
 
+    it('rotates JPEGs losslessly when the only edit is a quarter turn', async () => {
+      const asset = createMockAsset({ extension: 'jpg' })
+      const rotated = new Uint8Array([0xff, 0xd8, 0xff, 0xe1])
+      mockDeps.rotateJpegLossless = vi.fn().mockResolvedValue(rotated)
+      mockDeps.getEditState = vi.fn().mockResolvedValue({
+        rotation: { angle: 90, straighten: 0 },
+      })
+
+      const options: ExportOptions = {
+        destinationHandle: createMockDirectoryHandle(),
+        filenameTemplate: '{orig}',
+        quality: 90,
+        resizeLongEdge: 0,
+        scope: 'all',
+      }
+
+      const result = await service.exportAssets([asset], options)
+
+      expect(result.successCount).toBe(1)
+      expect(mockDeps.rotateJpegLossless).toHaveBeenCalledWith(expect.any(Uint8Array), 3)
+      expect(mockDeps.decodeImage).not.toHaveBeenCalled()
+      expect(mockDeps.encodeJpeg).not.toHaveBeenCalled()
+    })
+
+    it('re-encodes when lossless rotation fails', async () => {
+      const asset = createMockAsset({ extension: 'jpg' })
+      mockDeps.rotateJpegLossless = vi.fn().mockRejectedValue(new Error('conflicting orientation'))
+      mockDeps.getEditState = vi.fn().mockResolvedValue({
+        rotation: { angle: -90, straighten: 0 },
+      })
+      vi.spyOn(console, 'warn').mockImplementation(() => {})
+
+      const options: ExportOptions = {
+        destinationHandle: createMockDirectoryHandle(),
+        filenameTemplate: '{orig}',
+        quality: 90,
+        resizeLongEdge: 0,
+        scope: 'all',
+      }
+
+      const result = await service.exportAssets([asset], options)
+
+      expect(result.successCount).toBe(1)
+      expect(mockDeps.applyRotation).toHaveBeenCalled()
+      expect(mockDeps.encodeJpeg).toHaveBeenCalled()
+    })
+
+    it('re-encodes quarter turns of RAW files and resized exports', async () => {
+      mockDeps.rotateJpegLossless = vi.fn()
+      mockDeps.getEditState = vi.fn().mockResolvedValue({
+        rotation: { angle: 180, straighten: 0 },
+      })
+
+      const options: ExportOptions = {
+        destinationHandle: createMockDirectoryHandle(),
+        filenameTemplate: '{orig}_{seq:2}',
+        quality: 90,
+        resizeLongEdge: 0,
+        scope: 'all',
+      }
+      await service.exportAssets([createMockAsset({ extension: 'arw' })], options)
+      await service.exportAssets(
+        [createMockAsset({ extension: 'jpg' })],
+        { ...options, resizeLongEdge: 2048 }
+      )
+
+      expect(mockDeps.rotateJpegLossless).not.toHaveBeenCalled()
+      expect(mockDeps.encodeJpeg).toHaveBeenCalledTimes(2)
+    })
+
     it('resizes when resizeLongEdge is specified', async () => {
       const asset = createMockAsset()
       mockDeps.decodeImage = vi.fn().mockResolvedValue({
@@ -404,6 +479,60 @@ describe('ExportService', () => {
   })
 })
 
+describe('getLosslessQuarterTurns', () => {
+  it('converts counter-clockwise angles to clockwise quarter turns', () => {
+    const turns = (angle: number) =>
+      getLosslessQuarterTurns({ rotation: { angle, straighten: 0 } })
+
+    expect(turns(90)).toBe(3)
+    expect(turns(-90)).toBe(1)
+    expect(turns(180)).toBe(2)
+    expect(turns(-180)).toBe(2)
+  })
+
+  it('returns null without a quarter turn', () => {
+    expect(getLosslessQuarterTurns(null)).toBeNull()
+    expect(getLosslessQuarterTurns({})).toBeNull()
+    expect(getLosslessQuarterTurns({ rotation: { angle: 0, straighten: 0 } })).toBeNull()
+    expect(getLosslessQuarterTurns({ rotation: { angle: 45, straighten: 0 } })).toBeNull()
+    expect(getLosslessQuarterTurns({ rotation: { angle: 90, straighten: 1 } })).toBeNull()
+  })
+
+  it('returns null when anything else is edited', () => {
+    const rotation = { angle: 90, straighten: 0 }
+
+    expect(
+      getLosslessQuarterTurns({ rotation, crop: { left: 0, top: 0, width: 0.5, height: 0.5 } })
+    ).toBeNull()
+    expect(
+      getLosslessQuarterTurns({
+        rotation,
+        toneCurve: { points: [{ x: 0, y: 0.1 }, { x: 1, y: 1 }] },
+      })
+    ).toBeNull()
+    expect(
+      getLosslessQuarterTurns({
+        rotation,
+        masks: {
+          linearMasks: [],
+          radialMasks: [{ enabled: true } as never],
+        },
+      })
+    ).toBeNull()
+  })
+
+  it('ignores default adjustments, curves and disabled masks', () => {
+    expect(
+      getLosslessQuarterTurns({
+        rotation: { angle: 90, straighten: 0 },
+        crop: null,
+        toneCurve: { points: [{ x: 0, y: 0 }, { x: 1, y: 1 }] },
+        masks: { linearMasks: [{ enabled: false } as never], radialMasks: [] },
+      })
+    ).toBe(3)
+  })
+})
+
 describe('filterAssetsForExport', () => {
   const assets: Asset[] = [
     createMockAsset({ id: '1', flag: 'pick' }),
@@ -12,10 +12,15 @@
  * 8. Encode to JPEG
  * 9. Write to destination folder
  *
+ * A JPEG whose only edit is a rotation by a multiple of 90 degrees skips
+ * steps 2-8 when `rotateJpegLossless` is available: only its EXIF
+ * orientation is rewritten, so the original compressed data is kept.
+ *
  * The service is designed to be dependency-injected for testability.
  */
 
 import type { Asset, CropRectangle, RotationParameters, Adjustments } from '../catalog/types'
+import { hasModifiedAdjustments, isModifiedToneCurve } from '../catalog/types'
 import type { ToneCurve } from '../decode/types'
 import type {
   ExportOptions,
@@ -175,21 +180,37 @@ export class ExportService {
     // 1. Load original image bytes
     const imageBytes = await this.deps.loadImageBytes(asset)
 
-    // 2. Decode image
+    // 2. Get edit state
+    const editState = await this.deps.getEditState(asset.id)
+
+    // 3. Quarter-turn rotations of JPEGs need no re-encode
+    const quarterTurns = getLosslessQuarterTurns(editState)
+    if (
+      quarterTurns !== null
+      && resizeLongEdge <= 0
+      && this.deps.rotateJpegLossless
+      && isJpegExtension(asset.extension)
+    ) {
+      try {
+        return await this.deps.rotateJpegLossless(imageBytes, quarterTurns)
+      } catch (error) {
+        // e.g. pixels already rotated with a stale EXIF tag: re-encode instead
+        console.warn(`[ExportService] Lossless rotation failed for "${asset.filename}":`, error)
+      }
+    }
+
+    // 4. Decode image
     let decoded = await this.deps.decodeImage(
       imageBytes,
       `${asset.filename}.${asset.extension}`
     )
 
-    // 3. Get edit state
-    const editState = await this.deps.getEditState(asset.id)
-
-    // 4. Apply edits if present
+    // 5. Apply edits if present
     if (editState) {
       decoded = await this.applyEdits(decoded, editState)
     }
 
-    // 5. Resize if requested
+    // 6. Resize if requested
     if (resizeLongEdge > 0) {
       const longEdge = Math.max(decoded.width, decoded.height)
       if (longEdge > resizeLongEdge) {
@@ -206,7 +227,7 @@ export class ExportService {
       }
     }
 
-    // 6. Encode to JPEG
+    // 7. Encode to JPEG
     return this.deps.encodeJpeg(
       decoded.data,
       decoded.width,
@@ -369,6 +390,45 @@ export function createExportService(
 // Helper Functions
 // ============================================================================
 
+/**
+ * Clockwise quarter turns for an edit that is only a rotation by a
+ * multiple of 90 degrees, or null if anything else is edited (or nothing
+ * is).
+ *
+ * Rotation angles are counter-clockwise, so an angle of 90 is 3 turns.
+ */
+export function getLosslessQuarterTurns(editState: ExportEditState | null): number | null {
+  if (!editState?.rotation) return null
+  const { angle, straighten } = editState.rotation
+  if (straighten !== 0 || angle % 90 !== 0) return null
+  const quarterTurns = (((-angle / 90) % 4) + 4) % 4
+  if (quarterTurns === 0) return null
+
+  if (editState.crop) return null
+  if (editState.adjustments && hasModifiedAdjustments(editState.adjustments)) return null
+  if (
+    editState.toneCurve
+    && editState.toneCurve.points.length > 0
+    && isModifiedToneCurve(editState.toneCurve)
+  ) {
+    return null
+  }
+  const hasEnabledMasks =
+    editState.masks?.linearMasks?.some((m) => m.enabled)
+    || editState.masks?.radialMasks?.some((m) => m.enabled)
+  if (hasEnabledMasks) return null
+
+  return quarterTurns
+}
+
+/**
+ * Whether a file extension is a JPEG.
+ */
+function isJpegExtension(extension: string): boolean {
+  const ext = extension.toLowerCase()
+  return ext === 'jpg' || ext === 'jpeg'
+}
+
 /**
  * Filter assets based on export options.
  *
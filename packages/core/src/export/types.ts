@@ -234,6 +234,13 @@ export interface ExportServiceDependencies {
     height: number
   }>
 
+  /**
+   * Rotate JPEG file bytes by clockwise quarter turns without re-encoding.
+   * Optional: when missing, quarter-turn rotations are re-encoded like any
+   * other edit.
+   */
+  rotateJpegLossless?: (bytes: Uint8Array, quarterTurns: number) => Promise<Uint8Array>
+
   /**
    * Get edit state for an asset.
    */